@@ -193,10 +193,10 @@ impl FungibleTokenContract {
                 source,
             };
             self.runtime
-                .prepare_message(message)
+                .message(target_account.chain_id)
                 .with_authentication()
                 .with_tracking()
-                .send_to(target_account.chain_id);
+                .send(message);
         }
     }
 }
@@ -4,6 +4,7 @@
 #![cfg_attr(target_arch = "wasm32", no_main)]
 
 use linera_sdk::{
+    contract::IncomingMessage,
     linera_base_types::{ApplicationId, StreamName, WithContractAbi},
     Contract, ContractRuntime, Resources,
 };
@@ -80,14 +81,13 @@ impl Contract for MetaCounterContract {
     }
 
     async fn execute_message(&mut self, message: Message) {
-        let is_bouncing = self
-            .runtime
-            .message_is_bouncing()
-            .expect("Message delivery status has to be available when executing a message");
-        if is_bouncing {
-            log::trace!("receiving a bouncing message {message:?}");
-            return;
-        }
+        let message = match self.runtime.incoming_message(message) {
+            IncomingMessage::Received(message) => message,
+            IncomingMessage::Bounced(message) => {
+                log::trace!("receiving a bouncing message {message:?}");
+                return;
+            }
+        };
         match message {
             Message::Fail => {
                 panic!("Message failed intentionally");
@@ -67,6 +67,16 @@ pub trait ServiceAbi {
 }
 // ANCHOR_END: service_abi
 
+/// A trait that includes the types of the read-only view functions that an application contract
+/// exposes to other applications.
+pub trait ViewAbi {
+    /// The type of a call to one of the view functions.
+    type ViewCall: Serialize + DeserializeOwned + Send + Sync + Debug + 'static;
+
+    /// The response type of the view functions.
+    type ViewResponse: Serialize + DeserializeOwned + Send + Sync + Debug + 'static;
+}
+
 /// Marker trait to help importing contract types.
 pub trait WithContractAbi {
     /// The contract types to import.
@@ -81,6 +91,15 @@ where
     type Response = <<A as WithContractAbi>::Abi as ContractAbi>::Response;
 }
 
+impl<A> ViewAbi for A
+where
+    A: WithContractAbi,
+    <A as WithContractAbi>::Abi: ViewAbi,
+{
+    type ViewCall = <<A as WithContractAbi>::Abi as ViewAbi>::ViewCall;
+    type ViewResponse = <<A as WithContractAbi>::Abi as ViewAbi>::ViewResponse;
+}
+
 /// Marker trait to help importing service types.
 pub trait WithServiceAbi {
     /// The service types to import.
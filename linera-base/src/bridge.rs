@@ -0,0 +1,22 @@
+// Copyright (c) Zefchain Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! Types used when bridging data from external blockchains.
+
+use custom_debug_derive::Debug;
+use serde::{Deserialize, Serialize};
+
+use crate::hex_debug;
+
+/// A storage slot of an Ethereum contract, as of a given Ethereum block.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash, Serialize, Deserialize)]
+pub struct EthereumStorageKey {
+    /// The number of the Ethereum block.
+    pub block_number: u64,
+    /// The address of the contract.
+    #[debug(with = "hex_debug")]
+    pub address: [u8; 20],
+    /// The storage slot.
+    #[debug(with = "hex_debug")]
+    pub slot: [u8; 32],
+}
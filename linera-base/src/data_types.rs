@@ -7,6 +7,7 @@
 #[cfg(with_testing)]
 use std::ops;
 use std::{
+    collections::BTreeMap,
     fmt::{self, Display},
     fs,
     hash::Hash,
@@ -27,6 +28,7 @@ use thiserror::Error;
 #[cfg(with_metrics)]
 use crate::prometheus_util::MeasureLatency as _;
 use crate::{
+    bridge::EthereumStorageKey,
     crypto::{BcsHashable, CryptoError, CryptoHash},
     doc_scalar, hex_debug, http,
     identifiers::{
@@ -301,6 +303,31 @@ pub struct Resources {
     // TODO(#1533): Allow declaring calls to other applications instead of having to count them here.
 }
 
+/// A human-readable description of an operation scheduled by a service, for wallets to
+/// show when asking for confirmation.
+#[derive(
+    Clone,
+    Debug,
+    Default,
+    Deserialize,
+    Eq,
+    Hash,
+    PartialEq,
+    Serialize,
+    SimpleObject,
+    WitLoad,
+    WitStore,
+    WitType,
+)]
+pub struct OperationMetadata {
+    /// A short summary of the operation.
+    pub title: String,
+    /// A longer explanation of what the operation does.
+    pub description: String,
+    /// The effects the user should expect, e.g. the tokens leaving their account.
+    pub expected_effects: Vec<String>,
+}
+
 /// A request to send a message.
 #[derive(Clone, Debug, Deserialize, Serialize, WitLoad, WitType)]
 #[cfg_attr(with_testing, derive(Eq, PartialEq, WitStore))]
@@ -724,6 +751,13 @@ impl Amount {
     pub fn is_zero(&self) -> bool {
         *self == Amount::ZERO
     }
+
+    /// Adds up the given amounts, or returns an error if the sum overflows.
+    pub fn try_sum(amounts: impl IntoIterator<Item = Amount>) -> Result<Amount, ArithmeticError> {
+        amounts
+            .into_iter()
+            .try_fold(Amount::ZERO, |sum, amount| sum.try_add(amount))
+    }
 }
 
 /// What created a chain.
@@ -751,7 +785,9 @@ impl ChainOrigin {
 }
 
 /// A number identifying the configuration of the chain (aka the committee).
-#[derive(Eq, PartialEq, Ord, PartialOrd, Copy, Clone, Hash, Default, Debug)]
+#[derive(
+    Eq, PartialEq, Ord, PartialOrd, Copy, Clone, Hash, Default, Debug, WitType, WitLoad, WitStore,
+)]
 pub struct Epoch(pub u32);
 
 impl Epoch {
@@ -1023,6 +1059,24 @@ impl ApplicationPermissions {
     }
 }
 
+/// Whether an application accepts calls while one of its own calls is still running, i.e.
+/// while it is already in the call stack.
+///
+/// The instance of a contract that is running can't be entered again, so reentrant calls
+/// are always view calls, executed by a separate instance. That instance sees the state
+/// that the application last stored, not the changes made by the calls that are running.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, WitType, WitLoad, WitStore)]
+pub enum ReentrancyPolicy {
+    /// Reentrant calls are rejected.
+    #[default]
+    Deny,
+    /// Reentrant view calls are accepted.
+    Allow,
+    /// Reentrant view calls are accepted, except while executing a message from another
+    /// chain.
+    AllowSameChainOnly,
+}
+
 /// A record of a single oracle response.
 #[derive(Debug, PartialEq, Eq, Hash, Clone, Serialize, Deserialize)]
 pub enum OracleResponse {
@@ -1044,6 +1098,18 @@ pub enum OracleResponse {
     Event(EventId, Vec<u8>),
     /// An event exists.
     EventExists(EventId),
+    /// The value of an Ethereum storage slot, verified against the Ethereum block header.
+    EthereumStorage(EthereumStorageKey, #[debug(with = "hex_debug")] [u8; 32]),
+    /// The value under a key of an application's state on another chain, as read from the
+    /// storage of the executing validator.
+    ApplicationValue(
+        ChainId,
+        ApplicationId,
+        #[debug(with = "hex_debug")]
+        #[serde(with = "serde_bytes")]
+        Vec<u8>,
+        Option<Vec<u8>>,
+    ),
 }
 
 impl BcsHashable<'_> for OracleResponse {}
@@ -1094,6 +1160,132 @@ impl ApplicationDescription {
     pub fn service_bytecode_blob_id(&self) -> BlobId {
         self.module_id.service_bytecode_blob_id()
     }
+
+    /// Returns the fee schedule declared in the `operation_fees` field of the JSON
+    /// parameters, if any.
+    pub fn operation_fees(&self) -> Option<OperationFees> {
+        #[derive(Deserialize)]
+        struct DeclaredFees {
+            operation_fees: Option<OperationFees>,
+        }
+
+        serde_json::from_slice::<DeclaredFees>(&self.parameters)
+            .ok()?
+            .operation_fees
+    }
+}
+
+/// The fees that an application charges for its operations.
+///
+/// An application declares its fees by including an `operation_fees` field of this type in
+/// its parameters. Before each of the application's operations is executed, the fee is
+/// transferred from the account of the authenticated signer to the application's account
+/// on the same chain.
+#[derive(Clone, Debug, Default, Eq, PartialEq, Serialize, Deserialize)]
+pub struct OperationFees {
+    /// The fee of the operations that have no specific fee.
+    #[serde(default)]
+    pub default: Amount,
+    /// Specific fees, by the index of the variant in the application's operation enum,
+    /// i.e. the first value of the operation's BCS serialization.
+    #[serde(default)]
+    pub by_variant: BTreeMap<u32, Amount>,
+}
+
+impl OperationFees {
+    /// Returns the fee of the operation with the given serialized bytes.
+    pub fn fee(&self, operation: &[u8]) -> Amount {
+        Self::variant_index(operation)
+            .and_then(|index| self.by_variant.get(&index))
+            .copied()
+            .unwrap_or(self.default)
+    }
+
+    /// Decodes the ULEB128-encoded variant index at the start of a BCS-serialized enum.
+    fn variant_index(bytes: &[u8]) -> Option<u32> {
+        let mut value = 0u64;
+        for (i, byte) in bytes.iter().take(5).enumerate() {
+            value |= u64::from(byte & 0x7f) << (7 * i);
+            if byte & 0x80 == 0 {
+                return u32::try_from(value).ok();
+            }
+        }
+        None
+    }
+}
+
+/// The fees charged for the execution of a block, or part of it, by kind of resource.
+#[derive(
+    Clone, Copy, Debug, Default, Deserialize, Eq, Hash, PartialEq, Serialize, SimpleObject,
+)]
+pub struct FeeBreakdown {
+    /// The fees for the fuel consumed by applications.
+    pub fuel: Amount,
+    /// The fees for reading from storage, including blobs.
+    pub storage_reads: Amount,
+    /// The fees for writing to storage.
+    pub storage_writes: Amount,
+    /// The fees for the outgoing messages.
+    pub messages: Amount,
+    /// The fees for the bytes of the published blobs.
+    pub blob_bytes: Amount,
+    /// The fees for the operations.
+    pub operations: Amount,
+    /// The fees for everything else, e.g. HTTP requests, services queried as oracles and
+    /// runtime calls.
+    pub other: Amount,
+}
+
+impl FeeBreakdown {
+    /// Returns the sum of all the fees.
+    pub fn total(&self) -> Result<Amount, ArithmeticError> {
+        Amount::try_sum([
+            self.fuel,
+            self.storage_reads,
+            self.storage_writes,
+            self.messages,
+            self.blob_bytes,
+            self.operations,
+            self.other,
+        ])
+    }
+
+    /// Adds the fees of `other` to these, kind by kind.
+    pub fn try_add_assign(&mut self, other: &FeeBreakdown) -> Result<(), ArithmeticError> {
+        let mut sum = *self;
+        sum.fuel.try_add_assign(other.fuel)?;
+        sum.storage_reads.try_add_assign(other.storage_reads)?;
+        sum.storage_writes.try_add_assign(other.storage_writes)?;
+        sum.messages.try_add_assign(other.messages)?;
+        sum.blob_bytes.try_add_assign(other.blob_bytes)?;
+        sum.operations.try_add_assign(other.operations)?;
+        sum.other.try_add_assign(other.other)?;
+        *self = sum;
+        Ok(())
+    }
+}
+
+impl BcsHashable<'_> for FeeBreakdown {}
+
+impl Display for FeeBreakdown {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let items = [
+            ("fuel", self.fuel),
+            ("storage reads", self.storage_reads),
+            ("storage writes", self.storage_writes),
+            ("messages", self.messages),
+            ("blob bytes", self.blob_bytes),
+            ("operations", self.operations),
+            ("other", self.other),
+        ];
+        for (name, amount) in items {
+            writeln!(f, "{name:<16}{amount:>30}")?;
+        }
+        match self.total() {
+            Ok(total) => write!(f, "{:<16}{total:>30}", "total"),
+            Err(_) => write!(f, "{:<16}{:>30}", "total", "overflow"),
+        }
+    }
 }
 
 /// A WebAssembly module's bytecode.
@@ -1579,9 +1771,9 @@ mod metrics {
 
 #[cfg(test)]
 mod tests {
-    use std::str::FromStr;
+    use std::{collections::BTreeMap, str::FromStr};
 
-    use super::Amount;
+    use super::{Amount, ArithmeticError, FeeBreakdown, OperationFees};
 
     #[test]
     fn display_amount() {
@@ -1608,4 +1800,57 @@ mod tests {
             format!("{:~^+9.1}", Amount::from_str("12.34").unwrap())
         );
     }
+
+    #[test]
+    fn operation_fees_by_variant() {
+        let fees = OperationFees {
+            default: Amount::ONE,
+            by_variant: BTreeMap::from([(1, Amount::ZERO), (200, Amount::from_tokens(3))]),
+        };
+        assert_eq!(fees.fee(&[0, 42]), Amount::ONE);
+        assert_eq!(fees.fee(&[1]), Amount::ZERO);
+        assert_eq!(fees.fee(&[0xc8, 0x01, 7]), Amount::from_tokens(3));
+        assert_eq!(fees.fee(&[]), Amount::ONE);
+        assert_eq!(fees.fee(&[0xff; 6]), Amount::ONE);
+    }
+
+    #[test]
+    fn fee_breakdown_aggregation() {
+        let mut fees = FeeBreakdown {
+            fuel: Amount::from_tokens(2),
+            messages: Amount::ONE,
+            ..FeeBreakdown::default()
+        };
+        let more_fees = FeeBreakdown {
+            fuel: Amount::ONE,
+            blob_bytes: Amount::from_millis(500),
+            ..FeeBreakdown::default()
+        };
+        fees.try_add_assign(&more_fees).unwrap();
+        assert_eq!(fees.fuel, Amount::from_tokens(3));
+        assert_eq!(fees.blob_bytes, Amount::from_millis(500));
+        assert_eq!(fees.total().unwrap(), Amount::from_millis(4_500));
+
+        let max_fees = FeeBreakdown {
+            fuel: Amount::MAX,
+            ..FeeBreakdown::default()
+        };
+        assert!(matches!(
+            fees.try_add_assign(&max_fees),
+            Err(ArithmeticError::Overflow)
+        ));
+        assert!(matches!(
+            FeeBreakdown {
+                other: Amount::ONE,
+                ..max_fees
+            }
+            .total(),
+            Err(ArithmeticError::Overflow)
+        ));
+        let display = fees.to_string();
+        assert_eq!(
+            display.lines().last().unwrap(),
+            format!("{:<16}{:>30}", "total", "4.5")
+        );
+    }
 }
@@ -40,6 +40,9 @@ pub enum AccountOwner {
     /// 20-byte account EVM-compatible address.
     #[debug(with = "hex_debug")]
     Address20([u8; 20]),
+    /// The account that a chain owns on other chains. Only the owners of that chain can
+    /// withdraw from it.
+    Chain(ChainId),
 }
 
 impl AccountOwner {
@@ -57,6 +60,7 @@ impl AccountOwner {
             AccountOwner::Reserved(_) => 1,
             AccountOwner::Address32(_) => 32,
             AccountOwner::Address20(_) => 20,
+            AccountOwner::Chain(_) => 32,
         }
     }
 
@@ -402,6 +406,12 @@ impl<A> From<ApplicationId<A>> for AccountOwner {
     }
 }
 
+impl From<ChainId> for AccountOwner {
+    fn from(chain_id: ChainId) -> Self {
+        AccountOwner::Chain(chain_id)
+    }
+}
+
 impl From<AccountPublicKey> for AccountOwner {
     fn from(public_key: AccountPublicKey) -> Self {
         match public_key {
@@ -496,6 +506,58 @@ impl std::str::FromStr for StreamName {
     }
 }
 
+/// The name of a channel that an application publishes messages to.
+#[derive(
+    Clone,
+    Debug,
+    Eq,
+    Hash,
+    Ord,
+    PartialEq,
+    PartialOrd,
+    Serialize,
+    Deserialize,
+    WitLoad,
+    WitStore,
+    WitType,
+)]
+pub struct ChannelName(
+    #[serde(with = "serde_bytes")]
+    #[debug(with = "hex_debug")]
+    pub Vec<u8>,
+);
+
+impl<T> From<T> for ChannelName
+where
+    T: Into<Vec<u8>>,
+{
+    fn from(name: T) -> Self {
+        ChannelName(name.into())
+    }
+}
+
+impl fmt::Display for ChannelName {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        Display::fmt(&hex::encode(&self.0), f)
+    }
+}
+
+impl std::str::FromStr for ChannelName {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let vec = hex::decode(s)?;
+        Ok(ChannelName(vec))
+    }
+}
+
+impl ChannelName {
+    /// Turns the channel name into bytes.
+    pub fn into_bytes(self) -> Vec<u8> {
+        self.0
+    }
+}
+
 /// An event stream ID.
 #[derive(
     Clone,
@@ -554,6 +616,15 @@ pub struct IndexAndEvent {
     pub event: Vec<u8>,
 }
 
+/// A stream that events were published on, with the index of its latest event.
+#[derive(Debug, Eq, PartialEq, Clone, Hash, Serialize, Deserialize, SimpleObject)]
+pub struct EventStreamInfo {
+    /// The stream.
+    pub stream_id: StreamId,
+    /// The index of the latest event published on the stream.
+    pub max_index: u32,
+}
+
 impl fmt::Display for StreamId {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         Display::fmt(&self.application_id, f)?;
@@ -975,6 +1046,7 @@ enum SerializableAccountOwner {
     Reserved(u8),
     Address32(CryptoHash),
     Address20([u8; 20]),
+    Chain(ChainId),
 }
 
 impl Serialize for AccountOwner {
@@ -986,6 +1058,7 @@ impl Serialize for AccountOwner {
                 AccountOwner::Reserved(value) => SerializableAccountOwner::Reserved(*value),
                 AccountOwner::Address32(value) => SerializableAccountOwner::Address32(*value),
                 AccountOwner::Address20(value) => SerializableAccountOwner::Address20(*value),
+                AccountOwner::Chain(value) => SerializableAccountOwner::Chain(*value),
             }
             .serialize(serializer)
         }
@@ -1004,6 +1077,7 @@ impl<'de> Deserialize<'de> for AccountOwner {
                 SerializableAccountOwner::Reserved(value) => Ok(AccountOwner::Reserved(value)),
                 SerializableAccountOwner::Address32(value) => Ok(AccountOwner::Address32(value)),
                 SerializableAccountOwner::Address20(value) => Ok(AccountOwner::Address20(value)),
+                SerializableAccountOwner::Chain(value) => Ok(AccountOwner::Chain(value)),
             }
         }
     }
@@ -1017,6 +1091,7 @@ impl fmt::Display for AccountOwner {
             }
             AccountOwner::Address32(value) => write!(f, "0x{}", value)?,
             AccountOwner::Address20(value) => write!(f, "0x{}", hex::encode(&value[..]))?,
+            AccountOwner::Chain(chain_id) => write!(f, "chain-{}", chain_id)?,
         };
 
         Ok(())
@@ -1027,6 +1102,9 @@ impl std::str::FromStr for AccountOwner {
     type Err = anyhow::Error;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if let Some(chain_id) = s.strip_prefix("chain-") {
+            return Ok(AccountOwner::Chain(chain_id.parse()?));
+        }
         if let Some(s) = s.strip_prefix("0x") {
             if s.len() == 64 {
                 if let Ok(hash) = CryptoHash::from_str(s) {
@@ -1106,6 +1184,7 @@ doc_scalar!(
     ChainDescription."
 );
 doc_scalar!(StreamName, "The name of an event stream");
+doc_scalar!(ChannelName, "The name of a channel of an application");
 bcs_scalar!(MessageId, "The index of a message in a chain");
 doc_scalar!(
     AccountOwner,
@@ -1187,7 +1266,18 @@ mod tests {
             "0x6e0ab7f37b667b7228d3a03116ca21be83213823"
         );
 
+        let address = AccountOwner::from_str(
+            "chain-5487b70625ce71f7ee29154ad32aefa1c526cb483bdb783dea2e1d17bc497844",
+        )
+        .unwrap();
+        assert_matches!(address, AccountOwner::Chain(_));
+        assert_eq!(
+            address.to_string(),
+            "chain-5487b70625ce71f7ee29154ad32aefa1c526cb483bdb783dea2e1d17bc497844"
+        );
+
         assert!(AccountOwner::from_str("0x5487b7").is_err());
+        assert!(AccountOwner::from_str("chain-5487b7").is_err());
         assert!(AccountOwner::from_str("0").is_err());
         assert!(AccountOwner::from_str(
             "5487b70625ce71f7ee29154ad32aefa1c526cb483bdb783dea2e1d17bc497844"
@@ -18,6 +18,7 @@ use tokio::signal::unix;
 #[cfg(not(target_arch = "wasm32"))]
 use {::tracing::debug, tokio_util::sync::CancellationToken};
 pub mod abi;
+pub mod bridge;
 #[cfg(not(target_arch = "wasm32"))]
 pub mod command;
 pub mod crypto;
@@ -28,6 +29,7 @@ pub mod hashed;
 pub mod http;
 pub mod identifiers;
 mod limited_writer;
+pub mod merkle;
 pub mod ownership;
 #[cfg(not(target_arch = "wasm32"))]
 pub mod port;
@@ -0,0 +1,325 @@
+// Copyright (c) Zefchain Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! Merkle trees over the entries of an application's storage, and proofs that a value is part
+//! of the execution state of a chain.
+//!
+//! If the committee's policy enables Merkle state hashes, the storage of each application is
+//! hashed as a sparse binary Merkle tree whose leaves are its key-value pairs, each at the
+//! position given by the hash of its key (see [`key_path`]). An empty subtree hashes to
+//! [`EMPTY_SUBTREE`], and a subtree with a single leaf is that leaf, so that the tree is only
+//! as deep as needed to tell the keys apart, and changing an entry only changes the nodes on
+//! its path. The roots of these trees are themselves the leaves of a Merkle tree of the
+//! applications, in the order of their IDs, and the state hash of a block commits to its root
+//! in a [`MerkleStateHash`]. A [`StateInclusionProof`] is the path from one entry to that root,
+//! so that a light client can check a single value against a block's state hash with a
+//! number of hashes logarithmic in the size of the state.
+
+use serde::{Deserialize, Serialize};
+use sha3::{Digest as _, Sha3_256};
+
+use crate::{
+    crypto::{BcsHashable, CryptoHash},
+    identifiers::ApplicationId,
+};
+
+/// A hash in a Merkle tree.
+pub type MerkleHash = [u8; 32];
+
+/// The prefix of the hashes of leaves, so that they cannot be mistaken for inner nodes.
+const LEAF_TAG: u8 = 0;
+/// The prefix of the hashes of inner nodes.
+const NODE_TAG: u8 = 1;
+
+/// The hash of an empty subtree of the Merkle tree of an application's storage.
+pub const EMPTY_SUBTREE: MerkleHash = [0; 32];
+
+/// Returns the path from the root to the leaf for the entry with the given `key`, in the
+/// Merkle tree of an application's storage: the bits of the hash of the key, from the most
+/// significant one.
+pub fn key_path(key: &[u8]) -> MerkleHash {
+    Sha3_256::digest(key).into()
+}
+
+/// Returns whether `path` goes to the right child of the node at the given `depth`.
+pub fn goes_right(path: &MerkleHash, depth: usize) -> bool {
+    path[depth / 8] & (0x80 >> (depth % 8)) != 0
+}
+
+/// Returns the hash of the leaf for the entry with the given `key` and `value`.
+pub fn leaf_hash(key: &[u8], value: &[u8]) -> MerkleHash {
+    let mut hasher = Sha3_256::new();
+    hasher.update([LEAF_TAG]);
+    hasher.update((key.len() as u64).to_le_bytes());
+    hasher.update(key);
+    hasher.update(value);
+    hasher.finalize().into()
+}
+
+/// Returns the hash of the leaf for the storage of an application in the tree of
+/// applications.
+pub fn application_leaf_hash(
+    application_id: &ApplicationId,
+    storage_root: &MerkleHash,
+) -> MerkleHash {
+    let key = bcs::to_bytes(application_id).expect("application IDs should serialize");
+    leaf_hash(&key, storage_root)
+}
+
+/// Returns the hash of the inner node with the given children.
+pub fn node_hash(left: &MerkleHash, right: &MerkleHash) -> MerkleHash {
+    let mut hasher = Sha3_256::new();
+    hasher.update([NODE_TAG]);
+    hasher.update(left);
+    hasher.update(right);
+    hasher.finalize().into()
+}
+
+/// A sibling on the path from a leaf to the root of a Merkle tree.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum MerkleSibling {
+    /// The sibling is the left child, and the node on the path the right one.
+    Left(MerkleHash),
+    /// The sibling is the right child, and the node on the path the left one.
+    Right(MerkleHash),
+}
+
+/// The siblings on the path from a leaf to the root of a Merkle tree, starting at the leaf.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MerkleProof {
+    pub siblings: Vec<MerkleSibling>,
+}
+
+impl MerkleProof {
+    /// Returns the root of the tree that this proof places `leaf` in.
+    pub fn root(&self, leaf: MerkleHash) -> MerkleHash {
+        self.siblings
+            .iter()
+            .fold(leaf, |node, sibling| match sibling {
+                MerkleSibling::Left(left) => node_hash(left, &node),
+                MerkleSibling::Right(right) => node_hash(&node, right),
+            })
+    }
+}
+
+/// A complete subtree of a Merkle tree being built.
+struct Subtree {
+    /// The number of levels below the root of the subtree.
+    height: u32,
+    /// The root of the subtree.
+    hash: MerkleHash,
+    /// The path to the root of the subtree, if it contains the leaf being proven.
+    siblings: Option<Vec<MerkleSibling>>,
+}
+
+impl Subtree {
+    fn merge(left: Subtree, right: Subtree) -> Subtree {
+        let siblings = match (left.siblings, right.siblings) {
+            (Some(mut siblings), None) => {
+                siblings.push(MerkleSibling::Right(right.hash));
+                Some(siblings)
+            }
+            (None, Some(mut siblings)) => {
+                siblings.push(MerkleSibling::Left(left.hash));
+                Some(siblings)
+            }
+            (None, None) => None,
+            (Some(_), Some(_)) => unreachable!("only one leaf is proven"),
+        };
+        Subtree {
+            height: left.height.max(right.height) + 1,
+            hash: node_hash(&left.hash, &right.hash),
+            siblings,
+        }
+    }
+}
+
+/// Computes the root of a Merkle tree from its leaves, one at a time, and optionally the
+/// proof of one of them, keeping only a number of hashes logarithmic in the number of leaves.
+///
+/// Pairs of subtrees of the same height are merged from the left, and the remaining subtrees
+/// are merged from the right once all the leaves are pushed.
+#[derive(Default)]
+pub struct MerkleTreeBuilder {
+    /// The roots of the complete subtrees built so far, from the left, by decreasing height.
+    stack: Vec<Subtree>,
+}
+
+impl MerkleTreeBuilder {
+    /// Appends a leaf to the tree.
+    pub fn push(&mut self, leaf: MerkleHash) {
+        self.push_subtree(leaf, None);
+    }
+
+    /// Appends the leaf whose proof is returned by [`MerkleTreeBuilder::finish`]. This must be
+    /// called at most once.
+    pub fn push_proven(&mut self, leaf: MerkleHash) {
+        self.push_subtree(leaf, Some(Vec::new()));
+    }
+
+    fn push_subtree(&mut self, hash: MerkleHash, siblings: Option<Vec<MerkleSibling>>) {
+        let mut node = Subtree {
+            height: 0,
+            hash,
+            siblings,
+        };
+        while self
+            .stack
+            .last()
+            .is_some_and(|last| last.height == node.height)
+        {
+            let left = self.stack.pop().expect("the stack is not empty");
+            node = Subtree::merge(left, node);
+        }
+        self.stack.push(node);
+    }
+
+    /// Returns the root of the tree, and the proof of the leaf pushed with
+    /// [`MerkleTreeBuilder::push_proven`], if any. The root of an empty tree is the hash of
+    /// the empty string.
+    pub fn finish(mut self) -> (MerkleHash, Option<MerkleProof>) {
+        let Some(mut node) = self.stack.pop() else {
+            return (Sha3_256::digest(b"").into(), None);
+        };
+        while let Some(left) = self.stack.pop() {
+            node = Subtree::merge(left, node);
+        }
+        let proof = node.siblings.map(|siblings| MerkleProof { siblings });
+        (node.hash, proof)
+    }
+}
+
+/// Returns the root of the Merkle tree with the given leaves.
+pub fn merkle_root(leaves: impl IntoIterator<Item = MerkleHash>) -> MerkleHash {
+    let mut builder = MerkleTreeBuilder::default();
+    for leaf in leaves {
+        builder.push(leaf);
+    }
+    builder.finish().0
+}
+
+/// The parts of an execution state that its Merkle state hash commits to.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MerkleStateHash {
+    /// The hash of the system state.
+    pub system_hash: MerkleHash,
+    /// The root of the Merkle tree of the applications' storage.
+    pub applications_root: MerkleHash,
+    /// The hash of the event counts of the streams the chain writes to.
+    pub stream_event_counts_hash: MerkleHash,
+}
+
+impl BcsHashable<'_> for MerkleStateHash {}
+
+impl MerkleStateHash {
+    /// Returns the state hash that blocks contain.
+    pub fn state_hash(&self) -> CryptoHash {
+        CryptoHash::new(self)
+    }
+}
+
+/// A proof that the storage of an application holds a value under a key, in an execution
+/// state with a Merkle state hash.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StateInclusionProof {
+    /// The hash of the system state.
+    pub system_hash: MerkleHash,
+    /// The hash of the event counts of the streams the chain writes to.
+    pub stream_event_counts_hash: MerkleHash,
+    /// The path from the application's storage to the root of the tree of applications.
+    pub application_proof: MerkleProof,
+    /// The path from the entry to the root of the application's storage.
+    pub storage_proof: MerkleProof,
+}
+
+impl StateInclusionProof {
+    /// Returns the hash of the execution state in which, according to this proof, the storage
+    /// of `application_id` holds `value` under `key`.
+    pub fn state_hash(
+        &self,
+        application_id: ApplicationId,
+        key: &[u8],
+        value: &[u8],
+    ) -> CryptoHash {
+        let storage_root = self.storage_proof.root(leaf_hash(key, value));
+        let applications_root = self
+            .application_proof
+            .root(application_leaf_hash(&application_id, &storage_root));
+        MerkleStateHash {
+            system_hash: self.system_hash,
+            applications_root,
+            stream_event_counts_hash: self.stream_event_counts_hash,
+        }
+        .state_hash()
+    }
+}
+
+/// Checks that the storage of `application_id` holds `value` under `key` in the execution
+/// state with the hash `state_hash`, e.g. the state hash of a block.
+pub fn verify_state_inclusion(
+    state_hash: CryptoHash,
+    application_id: ApplicationId,
+    key: &[u8],
+    value: &[u8],
+    proof: &StateInclusionProof,
+) -> bool {
+    proof.state_hash(application_id, key, value) == state_hash
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn leaf(i: u8) -> MerkleHash {
+        leaf_hash(&[i], &[i, i])
+    }
+
+    #[test]
+    fn test_merkle_proofs_lead_to_the_root() {
+        for count in 1..=9 {
+            let root = merkle_root((0..count).map(leaf));
+            for index in 0..count {
+                let mut builder = MerkleTreeBuilder::default();
+                for i in 0..count {
+                    if i == index {
+                        builder.push_proven(leaf(i));
+                    } else {
+                        builder.push(leaf(i));
+                    }
+                }
+                let (builder_root, proof) = builder.finish();
+                assert_eq!(builder_root, root);
+                let proof = proof.unwrap();
+                assert_eq!(proof.root(leaf(index)), root);
+                assert_ne!(proof.root(leaf_hash(&[index], &[index])), root);
+            }
+        }
+    }
+
+    #[test]
+    fn test_proofs_are_logarithmic() {
+        let mut builder = MerkleTreeBuilder::default();
+        builder.push_proven(leaf(0));
+        for i in 1..=255 {
+            builder.push(leaf(i));
+        }
+        let (_, proof) = builder.finish();
+        assert_eq!(proof.unwrap().siblings.len(), 8);
+    }
+
+    #[test]
+    fn test_paths_go_right_on_set_bits() {
+        let mut path = EMPTY_SUBTREE;
+        path[1] = 0b0100_0000;
+        assert!(!goes_right(&path, 8));
+        assert!(goes_right(&path, 9));
+        assert!(!goes_right(&path, 10));
+    }
+
+    #[test]
+    fn test_leaves_cannot_pass_for_inner_nodes() {
+        let root = merkle_root([leaf(0), leaf(1)]);
+        assert_ne!(root, leaf_hash(&leaf(0), &leaf(1)));
+        assert_ne!(merkle_root([]), leaf_hash(&[], &[]));
+    }
+}
@@ -4,10 +4,12 @@
 //! This module provides unified handling for tracing subscribers within Linera binaries.
 
 use std::{
+    collections::VecDeque,
     env,
     fs::{File, OpenOptions},
+    io,
     path::Path,
-    sync::Arc,
+    sync::{Arc, Mutex, OnceLock},
 };
 
 use is_terminal::IsTerminal as _;
@@ -33,6 +35,9 @@ use tracing_subscriber::{
 /// The `LINERA_LOG_DIR` environment variable can be used to configure a directory to
 /// store log files. If it is set, a file named `log_name` with the `log` extension is
 /// created in the directory.
+///
+/// The most recent log output is also kept in memory, so that it can be retrieved with
+/// [`recent_logs`], e.g. for debug bundles.
 pub fn init(log_name: &str) {
     let env_filter = tracing_subscriber::EnvFilter::builder()
         .with_default_directive(tracing_subscriber::filter::LevelFilter::INFO.into())
@@ -66,13 +71,65 @@ pub fn init(log_name: &str) {
         )
     });
 
+    let recent_logs_layer = prepare_formatted_layer(
+        format.as_deref(),
+        fmt::layer()
+            .with_writer(|| RecentLogsWriter)
+            .with_ansi(false),
+    );
+
     tracing_subscriber::registry()
         .with(env_filter)
         .with(maybe_log_file_layer)
         .with(stderr_layer)
+        .with(recent_logs_layer)
         .init();
 }
 
+/// The maximum number of bytes of log output kept in memory.
+const RECENT_LOGS_CAPACITY: usize = 1 << 20;
+
+/// The most recent log output, if [`init`] was called.
+static RECENT_LOGS: OnceLock<Mutex<VecDeque<u8>>> = OnceLock::new();
+
+/// A writer appending to [`RECENT_LOGS`], and dropping the oldest output beyond
+/// [`RECENT_LOGS_CAPACITY`].
+struct RecentLogsWriter;
+
+impl io::Write for RecentLogsWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let mut logs = RECENT_LOGS
+            .get_or_init(Default::default)
+            .lock()
+            .expect("the recent logs should not be poisoned");
+        let kept = &buf[buf.len().saturating_sub(RECENT_LOGS_CAPACITY)..];
+        let excess = (logs.len() + kept.len()).saturating_sub(RECENT_LOGS_CAPACITY);
+        logs.drain(..excess);
+        logs.extend(kept);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Returns the most recent log output, starting at a full line, or [`None`] if tracing was
+/// not initialized with [`init`].
+pub fn recent_logs() -> Option<String> {
+    let logs = RECENT_LOGS.get()?.lock().ok()?;
+    let bytes = logs.iter().copied().collect::<Vec<_>>();
+    let start = if logs.len() == RECENT_LOGS_CAPACITY {
+        bytes
+            .iter()
+            .position(|byte| *byte == b'\n')
+            .map_or(0, |i| i + 1)
+    } else {
+        0
+    };
+    Some(String::from_utf8_lossy(&bytes[start..]).into_owned())
+}
+
 /// Opens a log file for writing.
 ///
 /// The location of the file is determined by the `LINERA_LOG_DIR` environment variable,
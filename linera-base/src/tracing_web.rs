@@ -24,3 +24,8 @@ pub fn init() {
         )
         .init();
 }
+
+/// Returns the most recent log output. Logs are not kept in memory in the browser.
+pub fn recent_logs() -> Option<String> {
+    None
+}
@@ -57,6 +57,58 @@ scalar!(VmRuntime);
 #[error("{0:?} is not a valid virtual machine runtime")]
 pub struct InvalidVmRuntime(String);
 
+/// The version of the interface between the Wasm runtime and the applications, as described
+/// by the WIT files of `linera-sdk`.
+///
+/// Minor versions only add to the interface, so the runtime can support applications built
+/// against an older minor version of the same major version.
+#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq, PartialOrd, Ord)]
+pub struct WasmApiVersion {
+    /// The major version, changed when the interface changes incompatibly.
+    pub major: u16,
+    /// The minor version, changed when the interface is extended.
+    pub minor: u16,
+}
+
+impl WasmApiVersion {
+    /// The version of the interface implemented by this code.
+    pub const CURRENT: Self = WasmApiVersion::new(1, 10);
+
+    /// The version of the applications built before the interface was versioned.
+    pub const UNVERSIONED: Self = WasmApiVersion::new(1, 0);
+
+    /// The name of the custom section of a Wasm module declaring the version it was built
+    /// against.
+    pub const CUSTOM_SECTION: &'static str = "linera:api-version";
+
+    /// Creates a [`WasmApiVersion`] from its components.
+    pub const fn new(major: u16, minor: u16) -> Self {
+        WasmApiVersion { major, minor }
+    }
+
+    /// Returns the contents of the custom section declaring this version.
+    pub const fn to_custom_section(self) -> [u8; 4] {
+        let [major0, major1] = self.major.to_le_bytes();
+        let [minor0, minor1] = self.minor.to_le_bytes();
+        [major0, major1, minor0, minor1]
+    }
+
+    /// Parses the contents of a custom section declaring a version.
+    pub fn from_custom_section(bytes: &[u8]) -> Option<Self> {
+        let [major0, major1, minor0, minor1] = bytes.try_into().ok()?;
+        Some(WasmApiVersion::new(
+            u16::from_le_bytes([major0, major1]),
+            u16::from_le_bytes([minor0, minor1]),
+        ))
+    }
+}
+
+impl std::fmt::Display for WasmApiVersion {
+    fn fmt(&self, formatter: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(formatter, "{}.{}", self.major, self.minor)
+    }
+}
+
 /// The possible types of queries for an EVM contract
 #[derive(Clone, Debug, Deserialize, Serialize)]
 pub enum EvmQuery {
@@ -11,7 +11,7 @@ use std::{
 use async_graphql::SimpleObject;
 use linera_base::{
     crypto::{BcsHashable, CryptoHash},
-    data_types::{Blob, BlockHeight, Epoch, Event, OracleResponse, Timestamp},
+    data_types::{Blob, BlockHeight, Epoch, Event, FeeBreakdown, OracleResponse, Timestamp},
     hashed::Hashed,
     identifiers::{AccountOwner, BlobId, BlobType, ChainId, StreamId},
 };
@@ -271,6 +271,7 @@ impl<'de> Deserialize<'de> for Block {
         let events_hash = hashing::hash_vec_vec(&inner.body.events);
         let blobs_hash = hashing::hash_vec_vec(&inner.body.blobs);
         let operation_results_hash = hashing::hash_vec(&inner.body.operation_results);
+        let fees_hash = CryptoHash::new(&inner.body.fees);
 
         let header = BlockHeader {
             chain_id: inner.header.chain_id,
@@ -289,6 +290,7 @@ impl<'de> Deserialize<'de> for Block {
             events_hash,
             blobs_hash,
             operation_results_hash,
+            fees_hash,
         };
 
         Ok(Self {
@@ -342,6 +344,8 @@ pub struct BlockHeader {
     pub blobs_hash: CryptoHash,
     /// A cryptographic hash of the execution results of all operations in a block.
     pub operation_results_hash: CryptoHash,
+    /// Cryptographic hash of the fees charged for the block.
+    pub fees_hash: CryptoHash,
 }
 
 /// The body of a block containing all the data included in the block.
@@ -366,6 +370,8 @@ pub struct BlockBody {
     pub blobs: Vec<Vec<Blob>>,
     /// The execution result for each operation.
     pub operation_results: Vec<OperationResult>,
+    /// The fees charged for the block, by kind of resource.
+    pub fees: FeeBreakdown,
 }
 
 impl Block {
@@ -383,6 +389,7 @@ impl Block {
         let events_hash = hashing::hash_vec_vec(&outcome.events);
         let blobs_hash = hashing::hash_vec_vec(&outcome.blobs);
         let operation_results_hash = hashing::hash_vec(&outcome.operation_results);
+        let fees_hash = CryptoHash::new(&outcome.fees);
 
         let header = BlockHeader {
             chain_id: block.chain_id,
@@ -401,6 +408,7 @@ impl Block {
             events_hash,
             blobs_hash,
             operation_results_hash,
+            fees_hash,
         };
 
         let body = BlockBody {
@@ -413,6 +421,7 @@ impl Block {
             events: outcome.events,
             blobs: outcome.blobs,
             operation_results: outcome.operation_results,
+            fees: outcome.fees,
         };
 
         Self { header, body }
@@ -587,6 +596,7 @@ impl Block {
             events: self.body.events,
             blobs: self.body.blobs,
             operation_results: self.body.operation_results,
+            fees: self.body.fees,
         };
         (proposed_block, outcome)
     }
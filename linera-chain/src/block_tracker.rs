@@ -12,9 +12,9 @@ use linera_base::{
     identifiers::{AccountOwner, BlobId, ChainId, MessageId, StreamId},
 };
 use linera_execution::{
-    ExecutionRuntimeContext, ExecutionStateView, MessageContext, OperationContext, OutgoingMessage,
-    ResourceController, ResourceTracker, SystemExecutionStateView, TransactionOutcome,
-    TransactionTracker,
+    system::RESOURCE_USAGE_STREAM_NAME, BlockResourceUsage, ExecutionRuntimeContext,
+    ExecutionStateView, MessageContext, OperationContext, OutgoingMessage, ResourceController,
+    ResourceTracker, SystemExecutionStateView, TransactionOutcome, TransactionTracker,
 };
 use linera_views::context::Context;
 
@@ -115,6 +115,7 @@ impl<'resources, 'blobs> BlockExecutionTracker<'resources, 'blobs> {
 
         match transaction {
             Transaction::ReceiveMessages(incoming_bundle) => {
+                self.check_incoming_bundle_age(incoming_bundle)?;
                 self.resource_controller_mut()
                     .track_block_size_of(&incoming_bundle)
                     .with_execution_context(chain_execution_context)?;
@@ -170,6 +171,31 @@ impl<'resources, 'blobs> BlockExecutionTracker<'resources, 'blobs> {
         Ok(())
     }
 
+    /// Checks that the bundle is not too old to be accepted. Old bundles can only be rejected,
+    /// unless they are protected, in which case they can't be rejected at all.
+    fn check_incoming_bundle_age(
+        &self,
+        incoming_bundle: &IncomingBundle,
+    ) -> Result<(), ChainError> {
+        let bundle = &incoming_bundle.bundle;
+        ensure!(
+            incoming_bundle.action == MessageAction::Reject
+                || bundle.is_protected()
+                || !self
+                    .resource_controller
+                    .policy()
+                    .is_incoming_bundle_too_old(bundle.timestamp, self.timestamp),
+            ChainError::IncomingBundleTooOld {
+                chain_id: self.chain_id,
+                origin: incoming_bundle.origin,
+                height: bundle.height,
+                bundle_timestamp: bundle.timestamp,
+                block_timestamp: self.timestamp,
+            }
+        );
+        Ok(())
+    }
+
     /// Returns a new TransactionTracker for the current transaction.
     fn new_transaction_tracker(&mut self) -> Result<TransactionTracker, ChainError> {
         Ok(TransactionTracker::new(
@@ -275,6 +301,10 @@ impl<'resources, 'blobs> BlockExecutionTracker<'resources, 'blobs> {
     /// Updates block tracker with indexes for the next messages, applications, etc.
     /// so that the execution of the next transaction doesn't overwrite the previous ones.
     ///
+    /// The messages published to channels are sent to the chains subscribed to them once the
+    /// transaction is complete, after the other messages of the transaction. Like these, they
+    /// are then added to the outbox of each subscriber when the block is confirmed.
+    ///
     /// Tracks the resources used by the transaction - size of the incoming and outgoing messages, blobs, etc.
     pub async fn process_txn_outcome<C>(
         &mut self,
@@ -291,7 +321,20 @@ impl<'resources, 'blobs> BlockExecutionTracker<'resources, 'blobs> {
             .push(txn_outcome.oracle_responses.clone());
         self.events.push(txn_outcome.events.clone());
         self.blobs.push(txn_outcome.blobs.clone());
-        self.messages.push(txn_outcome.outgoing_messages.clone());
+        let mut outgoing_messages = txn_outcome.outgoing_messages.clone();
+        for published in &txn_outcome.published_messages {
+            let key = (published.application_id, published.channel.clone());
+            let Some(subscribers) = view.channel_subscribers.try_load_entry(&key).await? else {
+                continue;
+            };
+            outgoing_messages.extend(
+                subscribers
+                    .indices()
+                    .await?
+                    .into_iter()
+                    .map(|subscriber| published.to_subscriber(subscriber)),
+            );
+        }
         if matches!(context, ChainExecutionContext::Operation(_)) {
             self.operation_results
                 .push(OperationResult(txn_outcome.operation_result.clone()));
@@ -299,7 +342,7 @@ impl<'resources, 'blobs> BlockExecutionTracker<'resources, 'blobs> {
 
         let mut resource_controller = self.resource_controller.with_state(view).await?;
 
-        for message_out in &txn_outcome.outgoing_messages {
+        for message_out in &outgoing_messages {
             resource_controller
                 .track_message(&message_out.message)
                 .with_execution_context(context)?;
@@ -308,7 +351,7 @@ impl<'resources, 'blobs> BlockExecutionTracker<'resources, 'blobs> {
         resource_controller
             .track_block_size_of(&(
                 &txn_outcome.oracle_responses,
-                &txn_outcome.outgoing_messages,
+                &outgoing_messages,
                 &txn_outcome.events,
                 &txn_outcome.blobs,
             ))
@@ -338,10 +381,39 @@ impl<'resources, 'blobs> BlockExecutionTracker<'resources, 'blobs> {
             .track_block_size_of(&(&txn_outcome.operation_result))
             .with_execution_context(context)?;
 
+        self.messages.push(outgoing_messages);
         self.transaction_index += 1;
         Ok(())
     }
 
+    /// Publishes a summary of the resources consumed by the block, if the policy requires it.
+    ///
+    /// The summary is an event of the last transaction, whose index is the height of the block.
+    /// It must be published after all transactions were executed.
+    pub fn publish_resource_usage(&mut self) -> Result<(), ChainError> {
+        if !self.resource_controller.policy().publish_resource_usage {
+            return Ok(());
+        }
+        // Heights that don't fit in an event index are too far away to care about.
+        let Ok(index) = u32::try_from(self.block_height.0) else {
+            return Ok(());
+        };
+        let usage = BlockResourceUsage::from(&self.resource_controller.tracker);
+        let event = Event {
+            stream_id: StreamId::system(RESOURCE_USAGE_STREAM_NAME),
+            index,
+            value: bcs::to_bytes(&usage)?,
+        };
+        self.resource_controller
+            .track_block_size_of(&event)
+            .with_execution_context(ChainExecutionContext::Block)?;
+        self.events
+            .last_mut()
+            .ok_or_else(|| ChainError::InternalError("block without transactions".into()))?
+            .push(event);
+        Ok(())
+    }
+
     /// Returns recipient chain IDs for outgoing messages in the block.
     pub fn recipients(&self) -> BTreeSet<ChainId> {
         self.messages
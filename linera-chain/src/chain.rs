@@ -11,28 +11,35 @@ use futures::stream::{self, StreamExt, TryStreamExt};
 use linera_base::{
     crypto::{CryptoHash, ValidatorPublicKey},
     data_types::{
-        ApplicationDescription, ApplicationPermissions, ArithmeticError, Blob, BlockHeight,
+        Amount, ApplicationDescription, ApplicationPermissions, ArithmeticError, Blob, BlockHeight,
         BlockHeightRangeBounds as _, Epoch, OracleResponse, Timestamp,
     },
     ensure,
-    identifiers::{AccountOwner, ApplicationId, BlobType, ChainId, StreamId},
+    identifiers::{
+        AccountOwner, ApplicationId, BlobType, ChainId, EventId, GenericApplicationId, StreamId,
+    },
     ownership::ChainOwnership,
 };
 use linera_execution::{
-    committee::Committee, ExecutionRuntimeContext, ExecutionStateView, Message, Operation,
-    OutgoingMessage, Query, QueryContext, QueryOutcome, ResourceController, ResourceTracker,
-    ServiceRuntimeEndpoint, TransactionTracker,
+    committee::Committee,
+    system::{InboxQuotas, OperationPermissions},
+    ExecutionRuntimeContext, ExecutionStateView, Message, MessageContext, Operation,
+    OperationContext, OutgoingMessage, Query, QueryContext, QueryOutcome, ResourceController,
+    ResourceTracker, ServiceRuntimeEndpoint, SystemOperation, TransactionOutcome,
+    TransactionTracker,
 };
 use linera_views::{
+    batch::Batch,
     bucket_queue_view::BucketQueueView,
     context::Context,
     log_view::LogView,
     map_view::MapView,
+    queue_view::QueueView,
     reentrant_collection_view::{ReadGuardedView, ReentrantCollectionView},
     register_view::RegisterView,
     set_view::SetView,
     store::ReadableKeyValueStore as _,
-    views::{ClonableView, CryptoHashView, RootView, View},
+    views::{ClonableView, RootView, View},
 };
 use serde::{Deserialize, Serialize};
 
@@ -40,10 +47,11 @@ use crate::{
     block::{Block, ConfirmedBlock},
     block_tracker::BlockExecutionTracker,
     data_types::{
-        BlockExecutionOutcome, ChainAndHeight, IncomingBundle, MessageBundle, ProposedBlock,
+        BlockExecutionOutcome, ChainAndHeight, IncomingBundle, MessageAction, MessageBundle,
+        ProposedBlock, Transaction,
     },
     inbox::{Cursor, InboxError, InboxStateView},
-    manager::ChainManager,
+    manager::{ChainManager, VoteRecord},
     outbox::OutboxStateView,
     pending_blobs::PendingBlobsView,
     ChainError, ChainExecutionContext, ExecutionError, ExecutionResultExt,
@@ -228,6 +236,10 @@ impl BundleInInbox {
 // of 100 seems reasonable for the storing of the data.
 const TIMESTAMPBUNDLE_BUCKET_SIZE: usize = 100;
 
+/// The maximal number of entries in the vote audit log of a chain. The oldest ones are
+/// dropped first.
+pub const VOTE_AUDIT_LOG_CAPACITY: usize = 1000;
+
 /// A view accessing the state of a chain.
 #[cfg_attr(
     with_graphql,
@@ -284,6 +296,50 @@ where
 
     /// Blocks that have been verified but not executed yet, and that may not be contiguous.
     pub preprocessed_blocks: MapView<C, BlockHeight, CryptoHash>,
+    /// If set, the chain is only synchronized partially: only the transactions of these
+    /// applications are executed, and only the block headers and the events of these
+    /// applications are stored. The state of the system and of the other applications stays as
+    /// it was when the filter was set.
+    pub application_filter: RegisterView<C, Option<BTreeSet<ApplicationId>>>,
+    /// The latest proposals and votes seen by this node, including the ones of other
+    /// validators in certificates, up to [`VOTE_AUDIT_LOG_CAPACITY`] of them.
+    pub vote_audit_log: QueueView<C, VoteRecord>,
+    /// The events of confirmed blocks that the chain worker buffered instead of writing them to
+    /// storage. They are saved in the same batch as their blocks, and written to storage
+    /// together later.
+    #[cfg_attr(with_graphql, graphql(skip))]
+    pub buffered_events: QueueView<C, (EventId, Vec<u8>)>,
+    /// The index of the latest event published on each stream, so that the streams of an
+    /// application can be listed without scanning the events.
+    pub event_streams: MapView<C, StreamId, u32>,
+    /// A newer epoch than the one in the execution state, that the validator has moved the
+    /// chain to without a block. It only tells clients that the chain's next blocks are
+    /// expected to switch to that epoch: certificates are always checked against the committee
+    /// of their own epoch. It is cleared once a block brings the execution state to that epoch.
+    pub migrated_epoch: RegisterView<C, Option<Epoch>>,
+    /// Inboxes with bundles waiting to be added or removed. This allows us to avoid loading all
+    /// inboxes. It is `None` on chains that existed before it was tracked, until an inbox
+    /// changes and it is computed from all of them.
+    pub nonempty_inboxes: RegisterView<C, Option<BTreeSet<ChainId>>>,
+    /// The writes reverting a persisted execution state delta that was written ahead of the
+    /// rest of its block. It is cleared when the chain is saved with the block, and the writes
+    /// are applied if the chain is loaded before that.
+    #[cfg_attr(with_graphql, graphql(skip))]
+    pub unsaved_execution_state_undo: RegisterView<C, Option<Batch>>,
+    /// On a chain that is only synchronized partially, the height of the first block whose
+    /// transactions could not be executed for the tracked applications. The state of these
+    /// applications is not maintained from that block on.
+    pub tracked_state_lost_at: RegisterView<C, Option<BlockHeight>>,
+}
+
+/// Returns whether `application_id` is one of the given user `applications`.
+fn is_tracked_application(
+    application_id: GenericApplicationId,
+    applications: &BTreeSet<ApplicationId>,
+) -> bool {
+    application_id
+        .user_application_id()
+        .is_some_and(|application_id| applications.contains(application_id))
 }
 
 /// Block-chaining state.
@@ -385,6 +441,26 @@ where
             next_block_height: self.tip_state.get().next_block_height,
             local_time,
         };
+        if let Some(applications) = self.application_filter.get() {
+            // Only the tracked applications' state is maintained, and their services can't
+            // use the state of the system or of other applications.
+            let Query::User {
+                application_id,
+                bytes,
+            } = query
+            else {
+                let error = ExecutionError::UntrackedState("querying the system".to_string());
+                return Err(ChainError::ExecutionError(
+                    Box::new(error),
+                    ChainExecutionContext::Query,
+                ));
+            };
+            return self
+                .execution_state
+                .query_tracked_application(context, application_id, bytes, applications)
+                .await
+                .with_execution_context(ChainExecutionContext::Query);
+        }
         self.execution_state
             .query_application(context, query, service_runtime_endpoint)
             .await
@@ -474,7 +550,7 @@ where
             return Ok(());
         }
         // Recompute the state hash.
-        let hash = self.execution_state.crypto_hash().await?;
+        let hash = self.execution_state.state_hash_mut().await?;
         self.execution_state_hash.set(Some(hash));
         let maybe_committee = self.execution_state.system.current_committee().into_iter();
         // Last, reset the consensus state based on the current ownership.
@@ -491,7 +567,7 @@ where
     /// have been properly received by now.
     pub async fn validate_incoming_bundles(&self) -> Result<(), ChainError> {
         let chain_id = self.chain_id();
-        let pairs = self.inboxes.try_load_all_entries().await?;
+        let pairs = self.load_nonempty_inboxes().await?;
         let max_stream_queries = self.context().store().max_stream_queries();
         let stream = stream::iter(pairs)
             .map(|(origin, inbox)| async move {
@@ -534,6 +610,36 @@ where
         }
     }
 
+    /// Returns whether the bundles from `origin` waiting in the inbox have reached the
+    /// [`InboxQuotas`] of this chain, so that no more bundles from `origin` should be accepted
+    /// for now.
+    pub async fn is_inbox_full(&self, origin: &ChainId) -> Result<bool, ChainError> {
+        let quotas = *self.execution_state.system.inbox_quotas.get();
+        if quotas == InboxQuotas::default()
+            || *self.execution_state.system.admin_id.get() == Some(*origin)
+        {
+            return Ok(false);
+        }
+        let Some(inbox) = self.inboxes.try_load_entry(origin).await? else {
+            return Ok(false);
+        };
+        let pending_bundles = inbox.added_bundles.count();
+        if quotas
+            .max_pending_bundles
+            .is_some_and(|max| pending_bundles >= max as usize)
+        {
+            return Ok(true);
+        }
+        let Some(max_pending_bytes) = quotas.max_pending_bytes else {
+            return Ok(false);
+        };
+        let mut pending_bytes = 0;
+        for bundle in inbox.added_bundles.elements().await? {
+            pending_bytes += bcs::serialized_size(&bundle)? as u64;
+        }
+        Ok(pending_bytes >= max_pending_bytes)
+    }
+
     /// Attempts to process a new `bundle` of messages from the given `origin`. Returns an
     /// internal error if the bundle doesn't appear to be new, based on the sender's
     /// height. The value `local_time` is specific to each validator and only used for
@@ -548,6 +654,7 @@ where
         add_to_received_log: bool,
     ) -> Result<(), ChainError> {
         assert!(!bundle.messages.is_empty());
+        self.backfill_nonempty_inboxes().await?;
         let chain_id = self.chain_id();
         tracing::trace!(
             "Processing new messages to {chain_id:.8} from {origin} at height {}",
@@ -589,6 +696,7 @@ where
                     "while processing messages in certified block: {error}"
                 )),
             })?;
+        Self::update_nonempty_inbox(&mut self.nonempty_inboxes, origin, &inbox);
         if newly_added && !skippable {
             let seen = local_time;
             self.unskippable_bundles
@@ -639,6 +747,7 @@ where
         timestamp: Timestamp,
         incoming_bundles: &[IncomingBundle],
     ) -> Result<(), ChainError> {
+        self.backfill_nonempty_inboxes().await?;
         let chain_id = self.chain_id();
         let mut bundles_by_origin: BTreeMap<_, Vec<&MessageBundle>> = Default::default();
         for IncomingBundle { bundle, origin, .. } in incoming_bundles {
@@ -674,6 +783,7 @@ where
                     removed_unskippable.insert(BundleInInbox::new(*origin, bundle));
                 }
             }
+            Self::update_nonempty_inbox(&mut self.nonempty_inboxes, origin, &inbox);
         }
         if !removed_unskippable.is_empty() {
             // Delete all removed bundles from the front of the unskippable queue.
@@ -705,6 +815,89 @@ where
         Ok(())
     }
 
+    /// Records whether the inbox for `origin` still has bundles waiting to be added or removed.
+    fn update_nonempty_inbox(
+        nonempty_inboxes: &mut RegisterView<C, Option<BTreeSet<ChainId>>>,
+        origin: &ChainId,
+        inbox: &InboxStateView<C>,
+    ) {
+        let Some(origins) = nonempty_inboxes.get() else {
+            return;
+        };
+        let is_recorded = origins.contains(origin);
+        if inbox.is_empty() != is_recorded {
+            return;
+        }
+        if let Some(origins) = nonempty_inboxes.get_mut() {
+            if is_recorded {
+                origins.remove(origin);
+            } else {
+                origins.insert(*origin);
+            }
+        }
+    }
+
+    /// Computes the set of non-empty inboxes from all of them, if it was never recorded.
+    async fn backfill_nonempty_inboxes(&mut self) -> Result<(), ChainError> {
+        if self.nonempty_inboxes.get().is_some() {
+            return Ok(());
+        }
+        let origins = self.nonempty_inbox_origins().await?;
+        self.nonempty_inboxes.set(Some(origins));
+        Ok(())
+    }
+
+    /// Returns the origins of the inboxes that have bundles waiting to be added or removed.
+    pub async fn nonempty_inbox_origins(&self) -> Result<BTreeSet<ChainId>, ChainError> {
+        if let Some(origins) = self.nonempty_inboxes.get() {
+            return Ok(origins.clone());
+        }
+        Ok(self
+            .inboxes
+            .try_load_all_entries()
+            .await?
+            .into_iter()
+            .filter(|(_, inbox)| !inbox.is_empty())
+            .map(|(origin, _)| origin)
+            .collect())
+    }
+
+    /// Loads the inboxes for the given origins with a single batch of reads, and keeps them in
+    /// memory until the chain state is saved or rolled back. Later accesses to these inboxes
+    /// don't read from storage again.
+    pub async fn prefetch_inboxes(
+        &self,
+        origins: impl IntoIterator<Item = &ChainId>,
+    ) -> Result<(), ChainError> {
+        let origins = origins.into_iter().collect::<BTreeSet<_>>();
+        self.inboxes.try_load_entries(origins).await?;
+        Ok(())
+    }
+
+    /// Returns the inboxes that have bundles waiting to be added or removed, without loading
+    /// the other ones.
+    pub async fn load_nonempty_inboxes(
+        &self,
+    ) -> Result<Vec<(ChainId, ReadGuardedView<InboxStateView<C>>)>, ChainError> {
+        let Some(origins) = self.nonempty_inboxes.get() else {
+            let pairs = self.inboxes.try_load_all_entries().await?;
+            return Ok(pairs
+                .into_iter()
+                .filter(|(_, inbox)| !inbox.is_empty())
+                .collect());
+        };
+        let inboxes = self.inboxes.try_load_entries(origins).await?;
+        origins
+            .iter()
+            .zip(inboxes)
+            .map(|(origin, inbox)| {
+                let inbox =
+                    inbox.ok_or_else(|| ChainError::InternalError("Missing inboxes".into()))?;
+                Ok((*origin, inbox))
+            })
+            .collect()
+    }
+
     /// Returns the chain IDs of all recipients for which a message is waiting in the outbox.
     pub fn nonempty_outbox_chain_ids(&self) -> Vec<ChainId> {
         self.nonempty_outboxes.get().iter().copied().collect()
@@ -720,6 +913,21 @@ where
         optional_vec.ok_or_else(|| ChainError::InternalError("Missing outboxes".into()))
     }
 
+    /// Adds proposals and votes to the vote audit log, dropping the oldest entries beyond
+    /// [`VOTE_AUDIT_LOG_CAPACITY`].
+    pub fn record_votes(&mut self, records: impl IntoIterator<Item = VoteRecord>) {
+        for record in records {
+            self.vote_audit_log.push_back(record);
+        }
+        let excess = self
+            .vote_audit_log
+            .count()
+            .saturating_sub(VOTE_AUDIT_LOG_CAPACITY);
+        for _ in 0..excess {
+            self.vote_audit_log.delete_front();
+        }
+    }
+
     /// Executes a block: first the incoming messages, then the main operation.
     /// Does not update chain state other than the execution state.
     #[expect(clippy::too_many_arguments)]
@@ -749,7 +957,7 @@ where
         let mut resource_controller = ResourceController::new(
             Arc::new(policy),
             ResourceTracker::default(),
-            block.authenticated_signer,
+            block.fee_payer(),
         );
 
         for blob in published_blobs {
@@ -779,6 +987,7 @@ where
                 .execute_transaction(transaction, round, chain)
                 .await?;
         }
+        block_execution_tracker.publish_resource_usage()?;
 
         let recipients = block_execution_tracker.recipients();
         let mut previous_message_blocks = BTreeMap::new();
@@ -811,7 +1020,7 @@ where
         let state_hash = {
             #[cfg(with_metrics)]
             let _hash_latency = metrics::STATE_HASH_COMPUTATION_LATENCY.measure_latency();
-            chain.crypto_hash().await?
+            chain.state_hash_mut().await?
         };
 
         let (messages, oracle_responses, events, blobs, operation_results) =
@@ -826,6 +1035,7 @@ where
             events,
             blobs,
             operation_results,
+            fees: resource_controller.tracker.fees,
         })
     }
 
@@ -875,8 +1085,12 @@ where
             );
         }
 
+        self.execution_state
+            .system
+            .activate_staged_application_permissions(block.height, block.timestamp);
         Self::check_app_permissions(
             self.execution_state.system.application_permissions.get(),
+            self.execution_state.system.operation_permissions.get(),
             block,
         )?;
 
@@ -904,8 +1118,307 @@ where
         let hash = block.inner().hash();
         let block = block.inner().inner();
         self.execution_state_hash.set(Some(block.header.state_hash));
-        let recipients = self.process_outgoing_messages(block).await?;
+        self.update_sender_state(block).await?;
+        // Last, reset the consensus state based on the current ownership.
+        self.reset_chain_manager(block.header.height.try_add_one()?, local_time)?;
+        self.advance_tip(hash, block)
+    }
+
+    /// Applies a confirmed block to a chain that is only synchronized partially, executing
+    /// only the transactions of the given tracked `applications`. Apart from their state, only
+    /// the inboxes, the outboxes and the tip are updated.
+    pub async fn apply_unexecuted_block(
+        &mut self,
+        block: &ConfirmedBlock,
+        applications: &BTreeSet<ApplicationId>,
+    ) -> Result<(), ChainError> {
+        let hash = block.inner().hash();
+        let block = block.inner().inner();
+        self.execute_tracked_transactions(block, applications)
+            .await?;
+        self.remove_bundles_from_inboxes(block.header.timestamp, &block.body.incoming_bundles)
+            .await?;
+        self.update_sender_state(block).await?;
+        self.advance_tip(hash, block)
+    }
+
+    /// Executes the transactions of a confirmed block that belong to the tracked
+    /// `applications`, i.e. their operations and the incoming bundles that only contain their
+    /// messages.
+    ///
+    /// The transactions replay the oracle responses recorded in the block, and may only use
+    /// the state of these applications. If one of them fails or has another outcome than in
+    /// the block, or if another transaction affects these applications, e.g. by calling them,
+    /// their state is marked as lost from this block on.
+    async fn execute_tracked_transactions(
+        &mut self,
+        block: &Block,
+        applications: &BTreeSet<ApplicationId>,
+    ) -> Result<(), ChainError> {
+        if self.tracked_state_lost_at.get().is_some() {
+            return Ok(());
+        }
+        for blob_id in block.published_blob_ids() {
+            self.execution_state.system.used_blobs.insert(&blob_id)?;
+        }
+        let bundles = block.body.incoming_bundles.iter();
+        let operations = block.body.operations.iter();
+        let transactions = bundles
+            .map(Transaction::ReceiveMessages)
+            .chain(operations.map(Transaction::ExecuteOperation));
+        for (index, transaction) in transactions.enumerate() {
+            let is_consistent = if Self::is_tracked_transaction(&transaction, applications) {
+                self.execute_tracked_transaction(block, index, transaction, applications)
+                    .await?
+            } else {
+                self.record_used_blobs(block, index)?;
+                !Self::affects_applications(block, index, &transaction, applications)
+            };
+            if !is_consistent {
+                self.tracked_state_lost_at.set(Some(block.header.height));
+                return Ok(());
+            }
+        }
+        Ok(())
+    }
+
+    /// Records the blobs that the transaction with the given index in a confirmed block used
+    /// for the first time, as if it had been executed, so that the tracked applications' later
+    /// transactions read the same oracle responses.
+    fn record_used_blobs(&mut self, block: &Block, index: usize) -> Result<(), ChainError> {
+        let responses = block.body.oracle_responses.get(index).into_iter().flatten();
+        for response in responses {
+            if let OracleResponse::Blob(blob_id) = response {
+                self.execution_state.system.used_blobs.insert(blob_id)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Executes the transaction with the given index in a confirmed block, using only the
+    /// state of the tracked `applications`. Returns whether it had the same outcome as in the
+    /// block.
+    async fn execute_tracked_transaction(
+        &mut self,
+        block: &Block,
+        index: usize,
+        transaction: Transaction<'_>,
+        applications: &BTreeSet<ApplicationId>,
+    ) -> Result<bool, ChainError> {
+        let chain_id = block.header.chain_id;
+        let height = block.header.height;
+        let timestamp = block.header.timestamp;
+        self.execution_state.system.timestamp.set(timestamp);
+        let oracle_responses = block
+            .body
+            .oracle_responses
+            .get(index)
+            .cloned()
+            .ok_or(ChainError::MissingOracleResponseList)?;
+        // Transactions that create applications or chains use the system's state, so the
+        // indices of the next ones are never needed.
+        let mut txn_tracker = TransactionTracker::new(
+            timestamp,
+            u32::try_from(index).map_err(|_| ArithmeticError::Overflow)?,
+            0,
+            0,
+            Some(oracle_responses),
+        )
+        .with_application_filter(applications.clone());
+        let mut resource_controller = ResourceController::default();
+        let result = match transaction {
+            Transaction::ReceiveMessages(bundle) => {
+                let mut result = Ok(());
+                for (message_id, posted_message) in bundle.messages_and_ids() {
+                    let context = MessageContext {
+                        chain_id,
+                        is_bouncing: posted_message.is_bouncing(),
+                        height,
+                        round: None,
+                        message_id,
+                        authenticated_signer: posted_message.authenticated_signer,
+                        refund_grant_to: posted_message.refund_grant_to,
+                        timestamp,
+                    };
+                    let mut grant = posted_message.grant;
+                    result = Box::pin(self.execution_state.execute_message(
+                        context,
+                        posted_message.message.clone(),
+                        (grant > Amount::ZERO).then_some(&mut grant),
+                        &mut txn_tracker,
+                        &mut resource_controller,
+                    ))
+                    .await;
+                    if result.is_ok() {
+                        result = self
+                            .execution_state
+                            .send_refund(context, grant, &mut txn_tracker)
+                            .await;
+                    }
+                    if result.is_err() {
+                        break;
+                    }
+                }
+                result
+            }
+            Transaction::ExecuteOperation(operation) => {
+                let context = OperationContext {
+                    chain_id,
+                    height,
+                    round: None,
+                    authenticated_signer: block.header.authenticated_signer,
+                    authenticated_caller_id: None,
+                    timestamp,
+                };
+                Box::pin(self.execution_state.execute_operation(
+                    context,
+                    operation.clone(),
+                    &mut txn_tracker,
+                    &mut resource_controller,
+                ))
+                .await
+            }
+        };
+        let outcome = match result.and_then(|()| txn_tracker.into_outcome()) {
+            Ok(outcome) => outcome,
+            Err(error) if error.is_retriable() => {
+                return Err(ChainError::ExecutionError(
+                    Box::new(error),
+                    ChainExecutionContext::Block,
+                ));
+            }
+            Err(_) => return Ok(false),
+        };
+        // The block's last transaction may also contain the system's resource usage event.
+        let events = block.body.events.get(index).into_iter().flatten();
+        let application_events = events
+            .filter(|event| event.stream_id.application_id != GenericApplicationId::System)
+            .cloned()
+            .collect::<Vec<_>>();
+        let operation_index = index.checked_sub(block.body.incoming_bundles.len());
+        let operation_result = operation_index
+            .and_then(|operation_index| block.body.operation_results.get(operation_index));
+        Ok(outcome.events == application_events
+            && block
+                .body
+                .messages
+                .get(index)
+                .is_some_and(|messages| Self::has_outgoing_messages(messages, &outcome))
+            && operation_result.is_none_or(|result| result.0 == outcome.operation_result))
+    }
 
+    /// Returns whether `messages` are the ones a transaction with the given outcome sent,
+    /// without knowing the subscribers of the channels it published to: its other messages,
+    /// followed by each published message, sent to chains in increasing order.
+    fn has_outgoing_messages(messages: &[OutgoingMessage], outcome: &TransactionOutcome) -> bool {
+        let Some(mut remaining) = messages.strip_prefix(outcome.outgoing_messages.as_slice())
+        else {
+            return false;
+        };
+        for published in &outcome.published_messages {
+            let mut previous = None;
+            while let Some((message, rest)) = remaining.split_first() {
+                let subscriber = message.destination;
+                if previous.is_some_and(|previous| previous >= subscriber)
+                    || *message != published.to_subscriber(subscriber)
+                {
+                    break;
+                }
+                previous = Some(subscriber);
+                remaining = rest;
+            }
+        }
+        remaining.is_empty()
+    }
+
+    /// Returns whether a transaction is one of the tracked `applications`': an operation of
+    /// theirs, or an accepted bundle of their messages only.
+    fn is_tracked_transaction(
+        transaction: &Transaction<'_>,
+        applications: &BTreeSet<ApplicationId>,
+    ) -> bool {
+        match transaction {
+            Transaction::ReceiveMessages(bundle) => {
+                let messages = &bundle.bundle.messages;
+                bundle.action == MessageAction::Accept
+                    && !messages.is_empty()
+                    && messages.iter().all(|posted| {
+                        let application_id = posted.message.application_id();
+                        is_tracked_application(application_id, applications)
+                    })
+            }
+            Transaction::ExecuteOperation(Operation::User { application_id, .. }) => {
+                applications.contains(application_id)
+            }
+            Transaction::ExecuteOperation(Operation::System(_)) => false,
+        }
+    }
+
+    /// Returns whether a transaction with the given index in a confirmed block that is not
+    /// one of the tracked `applications`' has an effect on them: if it accepts some of their
+    /// messages, upgrades one of them, creates one of them, or has events or messages of
+    /// theirs.
+    fn affects_applications(
+        block: &Block,
+        index: usize,
+        transaction: &Transaction<'_>,
+        applications: &BTreeSet<ApplicationId>,
+    ) -> bool {
+        let is_tracked = |application_id| is_tracked_application(application_id, applications);
+        match transaction {
+            // Rejected messages are only bounced, without running any application.
+            Transaction::ReceiveMessages(bundle) if bundle.action == MessageAction::Reject => {
+                return false;
+            }
+            Transaction::ReceiveMessages(bundle) => {
+                let messages = &bundle.bundle.messages;
+                if messages
+                    .iter()
+                    .any(|posted| is_tracked(posted.message.application_id()))
+                {
+                    return true;
+                }
+            }
+            Transaction::ExecuteOperation(operation) => {
+                if let Some(SystemOperation::UpgradeApplication { application_id, .. }) =
+                    operation.as_system_operation()
+                {
+                    if applications.contains(application_id) {
+                        return true;
+                    }
+                }
+            }
+        }
+        let has_events = block
+            .body
+            .events
+            .get(index)
+            .into_iter()
+            .flatten()
+            .any(|event| is_tracked(event.stream_id.application_id));
+        let has_messages = block
+            .body
+            .messages
+            .get(index)
+            .into_iter()
+            .flatten()
+            .any(|message| is_tracked(message.message.application_id()));
+        let has_descriptions = block
+            .body
+            .blobs
+            .get(index)
+            .into_iter()
+            .flatten()
+            .filter(|blob| blob.id().blob_type == BlobType::ApplicationDescription)
+            .any(|blob| applications.contains(&ApplicationId::new(blob.id().hash)));
+        has_events || has_messages || has_descriptions
+    }
+
+    /// Schedules the block's outgoing messages, and records it as the latest block sending
+    /// messages to its recipients and publishing events to its streams. Also records the
+    /// index of the latest event of each stream.
+    async fn update_sender_state(&mut self, block: &Block) -> Result<(), ChainError> {
+        let recipients = self.process_outgoing_messages(block).await?;
         for recipient in recipients {
             self.previous_message_blocks
                 .insert(&recipient, block.header.height)?;
@@ -913,11 +1426,13 @@ where
         for event in block.body.events.iter().flatten() {
             self.previous_event_blocks
                 .insert(&event.stream_id, block.header.height)?;
+            self.event_streams.insert(&event.stream_id, event.index)?;
         }
-        // Last, reset the consensus state based on the current ownership.
-        self.reset_chain_manager(block.header.height.try_add_one()?, local_time)?;
+        Ok(())
+    }
 
-        // Advance to next block height.
+    /// Advances the tip to the block with the given hash.
+    fn advance_tip(&mut self, hash: CryptoHash, block: &Block) -> Result<(), ChainError> {
         let tip = self.tip_state.get_mut();
         tip.block_hash = Some(hash);
         tip.next_block_height.try_add_assign_one()?;
@@ -953,9 +1468,11 @@ where
         description.is_child()
     }
 
-    /// Verifies that the block is valid according to the chain's application permission settings.
+    /// Verifies that the block is valid according to the chain's application permission settings,
+    /// and that its signer may execute its operations.
     fn check_app_permissions(
         app_permissions: &ApplicationPermissions,
+        operation_permissions: &Option<OperationPermissions>,
         block: &ProposedBlock,
     ) -> Result<(), ChainError> {
         let mut mandatory = HashSet::<ApplicationId>::from_iter(
@@ -966,12 +1483,23 @@ where
                 mandatory.clear();
                 continue;
             }
+            let application_id = operation.application_id();
             ensure!(
-                app_permissions.can_execute_operations(&operation.application_id()),
+                app_permissions.can_execute_operations(&application_id),
                 ChainError::AuthorizedApplications(
                     app_permissions.execute_operations.clone().unwrap()
                 )
             );
+            if let Some(operation_permissions) = operation_permissions {
+                let owner = block.authenticated_signer.as_ref();
+                ensure!(
+                    operation_permissions.can_execute_operation(owner, &application_id),
+                    ChainError::UnauthorizedOperation {
+                        owner: block.authenticated_signer,
+                        application_id,
+                    }
+                );
+            }
             if let Operation::User { application_id, .. } = operation {
                 mandatory.remove(application_id);
             }
@@ -2,7 +2,10 @@
 // Copyright (c) Zefchain Labs, Inc.
 // SPDX-License-Identifier: Apache-2.0
 
-use std::collections::{BTreeMap, BTreeSet, HashSet};
+use std::{
+    borrow::Cow,
+    collections::{BTreeMap, BTreeSet, HashSet},
+};
 
 use async_graphql::SimpleObject;
 use custom_debug_derive::Debug;
@@ -12,11 +15,15 @@ use linera_base::{
         AccountSignature, BcsHashable, BcsSignable, CryptoError, CryptoHash, Signer,
         ValidatorPublicKey, ValidatorSecretKey, ValidatorSignature,
     },
-    data_types::{Amount, Blob, BlockHeight, Epoch, Event, OracleResponse, Round, Timestamp},
+    data_types::{
+        Amount, Blob, BlockHeight, Epoch, Event, FeeBreakdown, OracleResponse, Round, Timestamp,
+    },
     doc_scalar, ensure, hex_debug,
     identifiers::{Account, AccountOwner, BlobId, ChainId, MessageId, StreamId},
 };
-use linera_execution::{committee::Committee, Message, MessageKind, Operation, OutgoingMessage};
+use linera_execution::{
+    committee::Committee, system::SystemOperation, Message, MessageKind, Operation, OutgoingMessage,
+};
 use serde::{Deserialize, Serialize};
 
 use crate::{
@@ -58,9 +65,10 @@ pub struct ProposedBlock {
     /// in this block, but no later than the current time.
     pub timestamp: Timestamp,
     /// The user signing for the operations in the block and paying for their execution
-    /// fees. If set, this must be the `owner` in the block proposal. `None` means that
-    /// the default account of the chain is used. This value is also used as recipient of
-    /// potential refunds for the message grants created by the operations.
+    /// fees, unless the block has a fee sponsor (see [`ProposedBlock::fee_sponsor`]). If
+    /// set, this must be the `owner` in the block proposal. `None` means that the default
+    /// account of the chain is used. This value is also used as recipient of potential
+    /// refunds for the message grants created by the operations.
     #[debug(skip_if = Option::is_none)]
     pub authenticated_signer: Option<AccountOwner>,
     /// Certified hash (see `Certificate` below) of the previous block in the
@@ -114,6 +122,30 @@ impl ProposedBlock {
         bundles.chain(operations)
     }
 
+    /// Returns the owner paying for the fees of this block instead of the authenticated
+    /// signer, if the first operation is [`SystemOperation::SponsorFees`].
+    pub fn fee_sponsor(&self) -> Option<AccountOwner> {
+        match self.operations.first()?.as_system_operation()? {
+            SystemOperation::SponsorFees { sponsor } => Some(*sponsor),
+            _ => None,
+        }
+    }
+
+    /// Returns the payload for the chain's authorization application, if the first operation
+    /// is [`SystemOperation::AuthorizeProposal`].
+    pub fn authorization_payload(&self) -> Option<&[u8]> {
+        match self.operations.first()?.as_system_operation()? {
+            SystemOperation::AuthorizeProposal { payload } => Some(payload),
+            _ => None,
+        }
+    }
+
+    /// Returns the account paying for the fees of this block: the fee sponsor if there is
+    /// one, otherwise the authenticated signer.
+    pub fn fee_payer(&self) -> Option<AccountOwner> {
+        self.fee_sponsor().or(self.authenticated_signer)
+    }
+
     pub fn check_proposal_size(&self, maximum_block_proposal_size: u64) -> Result<(), ChainError> {
         let size = bcs::serialized_size(self)?;
         ensure!(
@@ -179,6 +211,8 @@ impl IncomingBundle {
 
 impl BcsHashable<'_> for IncomingBundle {}
 
+impl BcsHashable<'_> for ProposedBlock {}
+
 /// What to do with a message picked from the inbox.
 #[derive(Copy, Debug, PartialEq, Eq, Hash, Clone, Serialize, Deserialize)]
 pub enum MessageAction {
@@ -225,6 +259,74 @@ pub struct BlockProposal {
     pub signature: AccountSignature,
     #[debug(skip_if = Option::is_none)]
     pub original_proposal: Option<OriginalProposal>,
+    /// A key chosen by the client to identify this submission. Validators that already
+    /// handled a proposal with the same key return the same outcome again. This is not
+    /// signed, and not part of the chain state.
+    #[debug(skip_if = Option::is_none)]
+    pub idempotency_key: Option<CryptoHash>,
+    /// The fee sponsor's approval, if the block's fees are paid by a sponsor. This is
+    /// required if and only if the block starts with [`SystemOperation::SponsorFees`].
+    #[debug(skip_if = Option::is_none)]
+    pub sponsor_approval: Option<SponsorApproval>,
+}
+
+/// A fee sponsor's agreement to pay for the fees of a proposed block, up to a limit.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[cfg_attr(with_testing, derive(Eq, PartialEq))]
+pub struct SponsorApproval {
+    /// The maximum amount by which executing the block may reduce the sponsor's balance on
+    /// the chain.
+    pub max_fees: Amount,
+    /// The sponsor's signature of the block and the maximum fees.
+    pub signature: AccountSignature,
+}
+
+/// The value signed by a fee sponsor.
+#[derive(Debug, Serialize, Deserialize)]
+struct SponsoredBlock<'a> {
+    block: Cow<'a, ProposedBlock>,
+    max_fees: Amount,
+}
+
+impl<'de> BcsSignable<'de> for SponsoredBlock<'de> {}
+
+impl SponsorApproval {
+    /// Signs the given block as its fee sponsor, agreeing to pay at most `max_fees`.
+    pub async fn new<S: Signer + ?Sized>(
+        block: &ProposedBlock,
+        max_fees: Amount,
+        sponsor: AccountOwner,
+        signer: &S,
+    ) -> Result<Self, S::Error> {
+        let value = SponsoredBlock {
+            block: Cow::Borrowed(block),
+            max_fees,
+        };
+        let signature = signer.sign(&sponsor, &CryptoHash::new(&value)).await?;
+        Ok(Self {
+            max_fees,
+            signature,
+        })
+    }
+
+    /// Returns the sponsor who signed this approval.
+    pub fn sponsor(&self) -> AccountOwner {
+        self.signature.owner()
+    }
+
+    /// Checks that this is a valid approval of the given block by its fee sponsor.
+    pub fn check(&self, block: &ProposedBlock) -> Result<(), ChainError> {
+        ensure!(
+            block.fee_sponsor() == Some(self.sponsor()),
+            ChainError::InvalidSponsorApproval
+        );
+        let value = SponsoredBlock {
+            block: Cow::Borrowed(block),
+            max_fees: self.max_fees,
+        };
+        self.signature.verify(&value)?;
+        Ok(())
+    }
 }
 
 /// A message together with kind, authentication and grant information.
@@ -309,6 +411,8 @@ pub struct BlockExecutionOutcome {
     pub blobs: Vec<Vec<Blob>>,
     /// The execution result for each operation.
     pub operation_results: Vec<OperationResult>,
+    /// The fees charged for the block, by kind of resource.
+    pub fees: FeeBreakdown,
 }
 
 /// The hash and chain ID of a `CertificateValue`.
@@ -502,18 +606,9 @@ impl BlockProposal {
         block: ProposedBlock,
         signer: &S,
     ) -> Result<Self, S::Error> {
-        let content = ProposalContent {
-            round,
-            block,
-            outcome: None,
-        };
-        let signature = signer.sign(&owner, &CryptoHash::new(&content)).await?;
-
-        Ok(Self {
-            content,
-            signature,
-            original_proposal: None,
-        })
+        ProposalSigningRequest::new_initial(owner, round, block)
+            .sign_into_proposal(signer)
+            .await
     }
 
     pub async fn new_retry_fast<S: Signer + ?Sized>(
@@ -522,18 +617,9 @@ impl BlockProposal {
         old_proposal: BlockProposal,
         signer: &S,
     ) -> Result<Self, S::Error> {
-        let content = ProposalContent {
-            round,
-            block: old_proposal.content.block,
-            outcome: None,
-        };
-        let signature = signer.sign(&owner, &CryptoHash::new(&content)).await?;
-
-        Ok(Self {
-            content,
-            signature,
-            original_proposal: Some(OriginalProposal::Fast(old_proposal.signature)),
-        })
+        ProposalSigningRequest::new_retry_fast(owner, round, old_proposal)
+            .sign_into_proposal(signer)
+            .await
     }
 
     pub async fn new_retry_regular<S: Signer>(
@@ -542,21 +628,22 @@ impl BlockProposal {
         validated_block_certificate: ValidatedBlockCertificate,
         signer: &S,
     ) -> Result<Self, S::Error> {
-        let certificate = validated_block_certificate.lite_certificate().cloned();
-        let block = validated_block_certificate.into_inner().into_inner();
-        let (block, outcome) = block.into_proposal();
-        let content = ProposalContent {
-            block,
-            round,
-            outcome: Some(outcome),
-        };
-        let signature = signer.sign(&owner, &CryptoHash::new(&content)).await?;
+        ProposalSigningRequest::new_retry_regular(owner, round, validated_block_certificate)
+            .sign_into_proposal(signer)
+            .await
+    }
 
-        Ok(Self {
-            content,
-            signature,
-            original_proposal: Some(OriginalProposal::Regular { certificate }),
-        })
+    /// Returns this proposal with the given idempotency key, so that resubmitting it to a
+    /// validator returns the outcome of the first submission.
+    pub fn with_idempotency_key(mut self, key: CryptoHash) -> Self {
+        self.idempotency_key = Some(key);
+        self
+    }
+
+    /// Returns this proposal with the approval of the block's fee sponsor.
+    pub fn with_sponsor_approval(mut self, approval: SponsorApproval) -> Self {
+        self.sponsor_approval = Some(approval);
+        self
     }
 
     /// Returns the `AccountOwner` that proposed the block.
@@ -623,6 +710,149 @@ impl BlockProposal {
     }
 }
 
+/// A block proposal to be signed by its owner on another machine, e.g. an air-gapped one.
+///
+/// This contains everything in a [`BlockProposal`] except the owner's signature. Only the
+/// `content` is signed; its [`signing_hash`](Self::signing_hash) should be compared on both
+/// machines before signing.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[cfg_attr(with_testing, derive(Eq, PartialEq))]
+pub struct ProposalSigningRequest {
+    /// The version of this format. Requests of other versions are rejected.
+    pub version: u32,
+    /// The owner who has to sign the proposal.
+    pub owner: AccountOwner,
+    /// The content to be signed.
+    pub content: ProposalContent,
+    /// The earlier proposal being retried, if any. This contains the certificate of the
+    /// validated block that the new proposal re-proposes.
+    #[debug(skip_if = Option::is_none)]
+    pub original_proposal: Option<OriginalProposal>,
+    /// The fee sponsor's approval, if the block's fees are paid by a sponsor.
+    #[debug(skip_if = Option::is_none)]
+    pub sponsor_approval: Option<SponsorApproval>,
+}
+
+impl ProposalSigningRequest {
+    /// The version of the format of the requests created by this code.
+    pub const VERSION: u32 = 1;
+
+    /// Creates a request to sign a proposal of a new block.
+    pub fn new_initial(owner: AccountOwner, round: Round, block: ProposedBlock) -> Self {
+        let content = ProposalContent {
+            round,
+            block,
+            outcome: None,
+        };
+        Self::new(owner, content, None, None)
+    }
+
+    /// Creates a request to sign a proposal retrying a block from the fast round.
+    pub fn new_retry_fast(owner: AccountOwner, round: Round, old_proposal: BlockProposal) -> Self {
+        let content = ProposalContent {
+            round,
+            block: old_proposal.content.block,
+            outcome: None,
+        };
+        let original_proposal = Some(OriginalProposal::Fast(old_proposal.signature));
+        Self::new(
+            owner,
+            content,
+            original_proposal,
+            old_proposal.sponsor_approval,
+        )
+    }
+
+    /// Creates a request to sign a proposal retrying a validated block from an earlier round.
+    pub fn new_retry_regular(
+        owner: AccountOwner,
+        round: Round,
+        validated_block_certificate: ValidatedBlockCertificate,
+    ) -> Self {
+        let certificate = validated_block_certificate.lite_certificate().cloned();
+        let block = validated_block_certificate.into_inner().into_inner();
+        let (block, outcome) = block.into_proposal();
+        let content = ProposalContent {
+            block,
+            round,
+            outcome: Some(outcome),
+        };
+        let original_proposal = Some(OriginalProposal::Regular { certificate });
+        Self::new(owner, content, original_proposal, None)
+    }
+
+    fn new(
+        owner: AccountOwner,
+        content: ProposalContent,
+        original_proposal: Option<OriginalProposal>,
+        sponsor_approval: Option<SponsorApproval>,
+    ) -> Self {
+        Self {
+            version: Self::VERSION,
+            owner,
+            content,
+            original_proposal,
+            sponsor_approval,
+        }
+    }
+
+    /// Returns this request with the approval of the block's fee sponsor.
+    pub fn with_sponsor_approval(mut self, approval: SponsorApproval) -> Self {
+        self.sponsor_approval = Some(approval);
+        self
+    }
+
+    /// Returns an error if this request was created with an unsupported version of the format.
+    pub fn check_version(&self) -> Result<(), ChainError> {
+        ensure!(
+            self.version == Self::VERSION,
+            ChainError::UnsupportedSigningRequestVersion {
+                found: self.version,
+                supported: Self::VERSION,
+            }
+        );
+        Ok(())
+    }
+
+    /// Returns the hash that the owner signs.
+    pub fn signing_hash(&self) -> CryptoHash {
+        CryptoHash::new(&self.content)
+    }
+
+    /// Signs the request with the owner's key.
+    pub async fn sign<S: Signer + ?Sized>(&self, signer: &S) -> Result<AccountSignature, S::Error> {
+        signer.sign(&self.owner, &self.signing_hash()).await
+    }
+
+    /// Returns the block proposal with the given signature, after checking that it is the
+    /// owner's signature of this request.
+    pub fn into_proposal(self, signature: AccountSignature) -> Result<BlockProposal, ChainError> {
+        self.check_version()?;
+        ensure!(signature.owner() == self.owner, ChainError::InvalidSigner);
+        signature.verify(&self.content)?;
+        Ok(self.with_signature(signature))
+    }
+
+    /// Signs the request with the owner's key and returns the block proposal.
+    async fn sign_into_proposal<S: Signer + ?Sized>(
+        self,
+        signer: &S,
+    ) -> Result<BlockProposal, S::Error> {
+        let signature = self.sign(signer).await?;
+        Ok(self.with_signature(signature))
+    }
+
+    fn with_signature(self, signature: AccountSignature) -> BlockProposal {
+        BlockProposal {
+            content: self.content,
+            signature,
+            original_proposal: self.original_proposal,
+            idempotency_key: None,
+            sponsor_approval: self.sponsor_approval,
+        }
+    }
+}
+
 impl LiteVote {
     /// Uses the signing key to create a signed object.
     pub fn new(value: LiteValue, round: Round, secret_key: &ValidatorSecretKey) -> Self {
@@ -806,6 +1036,8 @@ mod signing {
             content: proposal,
             signature,
             original_proposal: None,
+            idempotency_key: None,
+            sponsor_approval: None,
         };
         assert_eq!(block_proposal.owner(), public_key.into(),);
     }
@@ -133,6 +133,11 @@ impl From<&MessageBundle> for Cursor {
 }
 
 impl Cursor {
+    /// Returns the height of the sender's block with the bundle.
+    pub fn height(&self) -> BlockHeight {
+        self.height
+    }
+
     fn try_add_one(self) -> Result<Self, ArithmeticError> {
         let value = Self {
             height: self.height,
@@ -191,6 +196,11 @@ where
         }
     }
 
+    /// Returns `true` if no bundles are waiting to be added or removed.
+    pub fn is_empty(&self) -> bool {
+        self.added_bundles.count() == 0 && self.removed_bundles.count() == 0
+    }
+
     /// Consumes a bundle from the inbox.
     ///
     /// Returns `true` if the bundle was already known, i.e. it was present in `added_bundles`.
@@ -28,7 +28,7 @@ use linera_base::{
     bcs,
     crypto::{CryptoError, CryptoHash},
     data_types::{ArithmeticError, BlockHeight, Round, Timestamp},
-    identifiers::{ApplicationId, ChainId},
+    identifiers::{AccountOwner, ApplicationId, ChainId, GenericApplicationId},
 };
 use linera_execution::ExecutionError;
 use linera_views::ViewError;
@@ -106,8 +106,24 @@ pub enum ChainError {
         bundle_timestamp: Timestamp,
         block_timestamp: Timestamp,
     },
+    #[error(
+        "Incoming message bundle from {origin:?} at height {height} in block proposed to \
+        {chain_id:?} has timestamp {bundle_timestamp:}, which is too old to be accepted at the \
+        block timestamp {block_timestamp:}."
+    )]
+    IncomingBundleTooOld {
+        chain_id: ChainId,
+        origin: ChainId,
+        height: BlockHeight,
+        bundle_timestamp: Timestamp,
+        block_timestamp: Timestamp,
+    },
     #[error("The signature was not created by a valid entity")]
     InvalidSigner,
+    #[error("The block's fee sponsor and the sponsor approval in the proposal do not match")]
+    InvalidSponsorApproval,
+    #[error("Unsupported version {found} of the signing request; expected version {supported}")]
+    UnsupportedSigningRequestVersion { found: u32, supported: u32 },
     #[error(
         "Was expecting block height {expected_block_height} but found {found_block_height} instead"
     )]
@@ -161,6 +177,11 @@ pub enum ChainError {
     AuthorizedApplications(Vec<ApplicationId>),
     #[error("Missing operations or messages from mandatory applications: {0:?}")]
     MissingMandatoryApplications(Vec<ApplicationId>),
+    #[error("{owner:?} may not execute the operations of {application_id} on this chain")]
+    UnauthorizedOperation {
+        owner: Option<AccountOwner>,
+        application_id: GenericApplicationId,
+    },
     #[error("Can't use grant across different broadcast messages")]
     GrantUseOnBroadcast,
     #[error("Executed block contains fewer oracle responses than requests")]
@@ -172,6 +193,79 @@ pub enum ChainError {
     },
 }
 
+impl ChainError {
+    /// Returns a stable code identifying the kind of error, for programmatic clients: the
+    /// code of the underlying [`ExecutionError`], if any.
+    ///
+    /// Codes start out as the names of the variants, and must be kept if a variant is renamed.
+    pub fn code(&self) -> &'static str {
+        match self {
+            ChainError::CryptoError(..) => "CryptoError",
+            ChainError::ArithmeticError(..) => "ArithmeticError",
+            ChainError::ViewError(..) => "ViewError",
+            ChainError::ExecutionError(error, _) => error.code(),
+            ChainError::InactiveChain(..) => "InactiveChain",
+            ChainError::MissingCrossChainUpdate { .. } => "MissingCrossChainUpdate",
+            ChainError::UnexpectedMessage { .. } => "UnexpectedMessage",
+            ChainError::IncorrectMessageOrder { .. } => "IncorrectMessageOrder",
+            ChainError::CannotRejectMessage { .. } => "CannotRejectMessage",
+            ChainError::CannotSkipMessage { .. } => "CannotSkipMessage",
+            ChainError::IncorrectBundleTimestamp { .. } => "IncorrectBundleTimestamp",
+            ChainError::IncomingBundleTooOld { .. } => "IncomingBundleTooOld",
+            ChainError::InvalidSigner => "InvalidSigner",
+            ChainError::InvalidSponsorApproval => "InvalidSponsorApproval",
+            ChainError::UnsupportedSigningRequestVersion { .. } => {
+                "UnsupportedSigningRequestVersion"
+            }
+            ChainError::UnexpectedBlockHeight { .. } => "UnexpectedBlockHeight",
+            ChainError::UnexpectedPreviousBlockHash => "UnexpectedPreviousBlockHash",
+            ChainError::BlockHeightOverflow => "BlockHeightOverflow",
+            ChainError::InvalidBlockTimestamp { .. } => "InvalidBlockTimestamp",
+            ChainError::PreviousBlockMustBeConfirmedFirst => "PreviousBlockMustBeConfirmedFirst",
+            ChainError::InsufficientRound(..) => "InsufficientRound",
+            ChainError::InsufficientRoundStrict(..) => "InsufficientRoundStrict",
+            ChainError::WrongRound(..) => "WrongRound",
+            ChainError::HasIncompatibleConfirmedVote(..) => "HasIncompatibleConfirmedVote",
+            ChainError::MustBeNewerThanLockingBlock(..) => "MustBeNewerThanLockingBlock",
+            ChainError::MissingEarlierBlocks { .. } => "MissingEarlierBlocks",
+            ChainError::CertificateValidatorReuse => "CertificateValidatorReuse",
+            ChainError::CertificateRequiresQuorum => "CertificateRequiresQuorum",
+            ChainError::CertificateSignatureVerificationFailed { .. } => {
+                "CertificateSignatureVerificationFailed"
+            }
+            ChainError::InternalError(..) => "InternalError",
+            ChainError::BlockProposalTooLarge(..) => "BlockProposalTooLarge",
+            ChainError::BcsError(..) => "BcsError",
+            ChainError::InsufficientBalance => "InsufficientBalance",
+            ChainError::OwnerWeightError(..) => "OwnerWeightError",
+            ChainError::ClosedChain => "ClosedChain",
+            ChainError::EmptyBlock => "EmptyBlock",
+            ChainError::AuthorizedApplications(..) => "AuthorizedApplications",
+            ChainError::MissingMandatoryApplications(..) => "MissingMandatoryApplications",
+            ChainError::UnauthorizedOperation { .. } => "UnauthorizedOperation",
+            ChainError::GrantUseOnBroadcast => "GrantUseOnBroadcast",
+            ChainError::MissingOracleResponseList => "MissingOracleResponseList",
+            ChainError::CertificateValueHashMismatch { .. } => "CertificateValueHashMismatch",
+        }
+    }
+
+    /// Returns whether the same request may succeed if it is retried later, e.g. after
+    /// synchronizing the chain or in a later round.
+    pub fn is_retriable(&self) -> bool {
+        match self {
+            ChainError::ExecutionError(error, _) => error.is_retriable(),
+            ChainError::ViewError(_)
+            | ChainError::InactiveChain(_)
+            | ChainError::MissingCrossChainUpdate { .. }
+            | ChainError::UnexpectedBlockHeight { .. }
+            | ChainError::PreviousBlockMustBeConfirmedFirst
+            | ChainError::WrongRound(_)
+            | ChainError::MissingEarlierBlocks { .. } => true,
+            _ => false,
+        }
+    }
+}
+
 #[derive(Copy, Clone, Debug)]
 #[cfg_attr(with_testing, derive(Eq, PartialEq))]
 pub enum ChainExecutionContext {
@@ -68,14 +68,14 @@
 //! certificate known to any honest validator and include that in their block proposal, just like
 //! in the cooperative case.
 
-use std::collections::BTreeMap;
+use std::{collections::BTreeMap, num::NonZeroU32};
 
 use custom_debug_derive::Debug;
-use futures::future::Either;
+use futures::future::{BoxFuture, Either};
 use linera_base::{
-    crypto::{AccountPublicKey, CryptoError, ValidatorSecretKey},
-    data_types::{Blob, BlockHeight, Epoch, Round, Timestamp},
-    ensure,
+    crypto::{AccountPublicKey, CryptoError, CryptoHash, ValidatorPublicKey, ValidatorSecretKey},
+    data_types::{Blob, BlockHeight, Epoch, Round, TimeDelta, Timestamp},
+    doc_scalar, ensure,
     identifiers::{AccountOwner, BlobId, ChainId},
     ownership::ChainOwnership,
 };
@@ -94,7 +94,10 @@ use serde::{Deserialize, Serialize};
 use crate::{
     block::{Block, ConfirmedBlock, Timeout, ValidatedBlock},
     data_types::{BlockProposal, LiteVote, OriginalProposal, ProposedBlock, Vote},
-    types::{TimeoutCertificate, ValidatedBlockCertificate},
+    types::{
+        CertificateKind, CertificateValue, GenericCertificate, TimeoutCertificate,
+        ValidatedBlockCertificate,
+    },
     ChainError,
 };
 
@@ -137,6 +140,130 @@ impl LockingBlock {
     }
 }
 
+/// An item of the consensus state held by the chain manager in some round.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[cfg_attr(with_testing, derive(Eq, PartialEq))]
+pub enum ManagerEntry {
+    /// The latest block proposal we received.
+    Proposal {
+        /// The owner who signed the proposal.
+        owner: AccountOwner,
+        /// The timestamp of the proposed block.
+        timestamp: Timestamp,
+    },
+    /// The latest block that validators may have voted to confirm.
+    LockingBlock,
+    /// The latest leader timeout certificate we received.
+    TimeoutCertificate,
+    /// The vote we cast to confirm a block.
+    ConfirmedVote(LiteVote),
+    /// The vote we cast to validate a block.
+    ValidatedVote(LiteVote),
+    /// The vote we cast for a leader timeout.
+    TimeoutVote(LiteVote),
+    /// The vote we cast to switch to fallback mode.
+    FallbackVote(LiteVote),
+}
+
+/// What a recorded proposal or vote was for.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(with_graphql, derive(async_graphql::Enum))]
+pub enum VoteKind {
+    /// A block proposal by a chain owner.
+    Proposal,
+    /// A vote to validate a block.
+    Validated,
+    /// A vote to confirm a block.
+    Confirmed,
+    /// A vote for a leader timeout, or to switch to fallback mode.
+    Timeout,
+}
+
+impl From<CertificateKind> for VoteKind {
+    fn from(kind: CertificateKind) -> Self {
+        match kind {
+            CertificateKind::Timeout => VoteKind::Timeout,
+            CertificateKind::Validated => VoteKind::Validated,
+            CertificateKind::Confirmed => VoteKind::Confirmed,
+        }
+    }
+}
+
+/// Who signed a recorded proposal or vote.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub enum Voter {
+    /// The chain owner who signed a block proposal.
+    Owner(AccountOwner),
+    /// The validator who signed a vote.
+    Validator(ValidatorPublicKey),
+}
+
+doc_scalar!(Voter, "Who signed a recorded proposal or vote");
+
+/// A proposal or vote seen by this node, kept in the vote audit log of the chain to find out
+/// after the fact who voted for what.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[cfg_attr(with_graphql, derive(async_graphql::SimpleObject))]
+#[cfg_attr(with_testing, derive(Eq, PartialEq))]
+pub struct VoteRecord {
+    /// The height of the block the proposal or vote is for.
+    pub height: BlockHeight,
+    pub round: Round,
+    pub kind: VoteKind,
+    pub voter: Voter,
+    /// The hash of the block or timeout value.
+    pub value_hash: CryptoHash,
+    /// The local time when the proposal or vote was recorded.
+    pub timestamp: Timestamp,
+}
+
+impl VoteRecord {
+    /// Records the block proposal signed by `owner` that we cast `vote` for.
+    pub fn from_proposal<T: CertificateValue>(
+        owner: AccountOwner,
+        vote: &Vote<T>,
+        timestamp: Timestamp,
+    ) -> Self {
+        VoteRecord {
+            kind: VoteKind::Proposal,
+            voter: Voter::Owner(owner),
+            ..Self::from_vote(vote, timestamp)
+        }
+    }
+
+    /// Records a vote.
+    pub fn from_vote<T: CertificateValue>(vote: &Vote<T>, timestamp: Timestamp) -> Self {
+        VoteRecord {
+            height: vote.value.height(),
+            round: vote.round,
+            kind: T::KIND.into(),
+            voter: Voter::Validator(vote.public_key),
+            value_hash: vote.value.hash(),
+            timestamp,
+        }
+    }
+
+    /// Records the votes of all validators who signed a certificate.
+    pub fn from_certificate<T: CertificateValue>(
+        certificate: &GenericCertificate<T>,
+        timestamp: Timestamp,
+    ) -> impl Iterator<Item = Self> + '_ {
+        let height = certificate.inner().height();
+        let value_hash = certificate.hash();
+        certificate
+            .signatures()
+            .iter()
+            .map(move |(public_key, _)| VoteRecord {
+                height,
+                round: certificate.round,
+                kind: T::KIND.into(),
+                voter: Voter::Validator(*public_key),
+                value_hash,
+                timestamp,
+            })
+    }
+}
+
 /// The state of the certification process for a chain's next block.
 #[cfg_attr(with_graphql, derive(async_graphql::SimpleObject), graphql(complex))]
 #[derive(Debug, View, ClonableView)]
@@ -193,8 +320,46 @@ where
     pub current_round: RegisterView<C, Round>,
     /// The owners that take over in fallback mode.
     pub fallback_owners: RegisterView<C, BTreeMap<AccountOwner, u64>>,
+    /// The leaders of single-leader rounds assigned by a [`Pacemaker`], by round number, instead
+    /// of the ones drawn from the owners.
+    #[cfg_attr(with_graphql, graphql(skip))]
+    pub leader_overrides: RegisterView<C, BTreeMap<u32, AccountOwner>>,
+}
+
+/// The deadline and leader of a consensus round, as supplied by a [`Pacemaker`]. The default
+/// keeps the chain manager's own schedule: the deadline derived from the chain's timeout
+/// configuration, and the leader drawn from the owners by weight.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct RoundSchedule {
+    /// The time after which validators may vote to time out the round, if it differs from the
+    /// chain manager's own deadline.
+    pub deadline: Option<Timestamp>,
+    /// The owner allowed to propose in a single-leader round, if it differs from the one drawn
+    /// by the chain manager. Accounts that don't own the chain are ignored.
+    pub leader: Option<AccountOwner>,
 }
 
+/// Supplies the deadlines and leaders of the consensus rounds of chains from outside the chain
+/// manager, e.g. to pace the rounds in a coordinated way across the network.
+///
+/// Leaders must be assigned the same way by all validators, or they will disagree on who may
+/// propose. So the schedule of a round is fetched before checking a proposal or voting to time
+/// out in that round, and a validator that fails to fetch it doesn't vote.
+pub trait Pacemaker: Send + Sync {
+    /// Returns the schedule of `round` of the block at `height` on the chain.
+    fn round_schedule(
+        &self,
+        chain_id: ChainId,
+        height: BlockHeight,
+        round: Round,
+    ) -> BoxFuture<'_, Result<RoundSchedule, PacemakerError>>;
+}
+
+/// The error returned when a [`Pacemaker`] fails to supply a round schedule.
+#[derive(Debug, thiserror::Error)]
+#[error("Failed to get the round schedule from the pacemaker: {0}")]
+pub struct PacemakerError(pub String);
+
 #[cfg(with_graphql)]
 #[async_graphql::ComplexObject]
 impl<C> ChainManager<C>
@@ -550,6 +715,104 @@ where
         self.locking_blobs.get(blob_id).await
     }
 
+    /// Returns the proposal, votes and certificates we hold, by round.
+    pub fn entries_by_round(&self) -> BTreeMap<Round, Vec<ManagerEntry>> {
+        let mut entries = BTreeMap::<Round, Vec<ManagerEntry>>::new();
+        if let Some(proposal) = self.proposed.get() {
+            entries
+                .entry(proposal.content.round)
+                .or_default()
+                .push(ManagerEntry::Proposal {
+                    owner: proposal.owner(),
+                    timestamp: proposal.content.block.timestamp,
+                });
+        }
+        if let Some(locking_block) = self.locking_block.get() {
+            entries
+                .entry(locking_block.round())
+                .or_default()
+                .push(ManagerEntry::LockingBlock);
+        }
+        if let Some(certificate) = self.timeout.get() {
+            entries
+                .entry(certificate.round)
+                .or_default()
+                .push(ManagerEntry::TimeoutCertificate);
+        }
+        let votes = [
+            self.confirmed_vote()
+                .map(|vote| (vote.round, ManagerEntry::ConfirmedVote(vote.lite()))),
+            self.validated_vote()
+                .map(|vote| (vote.round, ManagerEntry::ValidatedVote(vote.lite()))),
+            self.timeout_vote()
+                .map(|vote| (vote.round, ManagerEntry::TimeoutVote(vote.lite()))),
+            self.fallback_vote()
+                .map(|vote| (vote.round, ManagerEntry::FallbackVote(vote.lite()))),
+        ];
+        for (round, entry) in votes.into_iter().flatten() {
+            entries.entry(round).or_default().push(entry);
+        }
+        entries
+    }
+
+    /// Removes the proposal and the timeout votes from rounds below the current one, and the
+    /// proposal if its block is older than `max_proposal_age`. Returns the number of entries
+    /// that were removed.
+    ///
+    /// None of these can affect the outcome of the current round anymore. The votes to validate
+    /// or confirm a block, the locking block and the timeout certificate are always kept: they
+    /// restrict which votes we may cast later, or let other nodes catch up with the current
+    /// round.
+    pub fn prune_stale_entries(
+        &mut self,
+        local_time: Timestamp,
+        max_proposal_age: Option<TimeDelta>,
+    ) -> usize {
+        let current_round = self.current_round();
+        let mut pruned = 0;
+        if let Some(proposal) = self.proposed.get() {
+            let is_too_old = max_proposal_age.is_some_and(|max_age| {
+                proposal.content.block.timestamp.saturating_add(max_age) < local_time
+            });
+            if proposal.content.round < current_round || is_too_old {
+                self.proposed.set(None);
+                self.proposed_blobs.clear();
+                pruned += 1;
+            }
+        }
+        for vote in [&mut self.timeout_vote, &mut self.fallback_vote] {
+            if vote
+                .get()
+                .as_ref()
+                .is_some_and(|vote| vote.round < current_round)
+            {
+                vote.set(None);
+                pruned += 1;
+            }
+        }
+        pruned
+    }
+
+    /// Discards the proposal if the current round is at least `max_round_age` rounds after
+    /// the proposal's round, and returns it.
+    ///
+    /// Unlike the proposals pruned by [`ChainManager::prune_stale_entries`], expired ones are
+    /// meant to be reported to the clients that may still be waiting for them.
+    pub fn expire_proposal(&mut self, max_round_age: NonZeroU32) -> Option<BlockProposal> {
+        let proposal = self.proposed.get().as_ref()?;
+        let current_round = self.current_round();
+        let mut round = proposal.content.round;
+        for _ in 0..max_round_age.get() {
+            round = self.ownership.get().next_round(round)?;
+            if round > current_round {
+                return None;
+            }
+        }
+        let proposal = self.proposed.get_mut().take();
+        self.proposed_blobs.clear();
+        proposal
+    }
+
     /// Updates `current_round` and `round_timeout` if necessary.
     ///
     /// This must be after every change to `timeout`, `locking` or `proposed`.
@@ -583,6 +846,27 @@ where
         self.current_round.set(current_round);
     }
 
+    /// Applies the `schedule` of `round` supplied by a [`Pacemaker`]: its deadline if it is the
+    /// current round, and its leader if it is a single-leader round.
+    pub fn apply_round_schedule(&mut self, round: Round, schedule: RoundSchedule) {
+        if let Some(deadline) = schedule.deadline {
+            if round == self.current_round() && *self.round_timeout.get() != Some(deadline) {
+                self.round_timeout.set(Some(deadline));
+            }
+        }
+        let Round::SingleLeader(round_number) = round else {
+            return;
+        };
+        let Some(leader) = schedule.leader else {
+            return;
+        };
+        if self.ownership.get().owners.contains_key(&leader)
+            && self.leader_overrides.get().get(&round_number) != Some(&leader)
+        {
+            self.leader_overrides.get_mut().insert(round_number, leader);
+        }
+    }
+
     /// Updates the round number and timer if the timeout certificate is from a higher round than
     /// any known certificate.
     pub fn handle_timeout_certificate(
@@ -621,6 +905,9 @@ where
                 ownership.open_multi_leader_rounds || ownership.owners.contains_key(proposal_owner)
             }
             Round::SingleLeader(r) => {
+                if let Some(leader) = self.leader_overrides.get().get(&r) {
+                    return Ok(leader == proposal_owner);
+                }
                 let Some(index) = self.round_leader_index(r) else {
                     return Ok(false);
                 };
@@ -640,6 +927,9 @@ where
     fn round_leader(&self, round: Round) -> Option<&AccountOwner> {
         match round {
             Round::SingleLeader(r) => {
+                if let Some(leader) = self.leader_overrides.get().get(&r) {
+                    return Some(leader);
+                }
                 let index = self.round_leader_index(r)?;
                 self.ownership.get().owners.keys().nth(index)
             }
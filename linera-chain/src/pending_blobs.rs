@@ -4,7 +4,7 @@
 use std::collections::BTreeMap;
 
 use linera_base::{
-    data_types::{Blob, Round},
+    data_types::{Blob, Epoch, Round},
     ensure,
     identifiers::BlobId,
 };
@@ -35,6 +35,10 @@ where
     pub validated: RegisterView<C, bool>,
     /// The map of blobs needed to process the block.
     pub pending_blobs: MapView<C, BlobId, Option<Blob>>,
+    /// The epoch of the block, whose policy limits the blobs it can publish.
+    ///
+    /// This is `None` for entries that were stored before the epoch was recorded.
+    pub epoch: RegisterView<C, Option<Epoch>>,
 }
 
 impl<C> PendingBlobsView<C>
@@ -61,6 +65,7 @@ where
         &mut self,
         round: Round,
         validated: bool,
+        epoch: Epoch,
         maybe_blobs: BTreeMap<BlobId, Option<Blob>>,
     ) -> Result<(), ChainError> {
         let existing_round = *self.round.get();
@@ -73,6 +78,7 @@ where
             self.round.set(round);
             self.validated.set(validated);
         }
+        self.epoch.set(Some(epoch));
         for (blob_id, maybe_blob) in maybe_blobs {
             self.pending_blobs.insert(&blob_id, maybe_blob)?;
         }
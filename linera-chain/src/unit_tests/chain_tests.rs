@@ -12,34 +12,43 @@ use std::{
 use assert_matches::assert_matches;
 use axum::{routing::get, Router};
 use linera_base::{
-    crypto::{AccountPublicKey, ValidatorPublicKey},
+    crypto::{AccountPublicKey, CryptoHash, ValidatorPublicKey},
     data_types::{
         Amount, ApplicationDescription, ApplicationPermissions, Blob, BlockHeight, Bytecode,
-        ChainDescription, ChainOrigin, Epoch, InitialChainConfig, Timestamp,
+        ChainDescription, ChainOrigin, Epoch, Event, InitialChainConfig, Timestamp,
     },
     http,
-    identifiers::{AccountOwner, ApplicationId, ChainId, ModuleId},
+    identifiers::{
+        AccountOwner, ApplicationId, ChainId, ChannelName, GenericApplicationId, ModuleId, StreamId,
+    },
     ownership::ChainOwnership,
     vm::VmRuntime,
 };
 use linera_execution::{
     committee::{Committee, ValidatorState},
-    system::Recipient,
+    system::{
+        InboxQuotas, OperationPermissions, PermissionsActivation, Recipient,
+        StagedApplicationPermissions, APPLICATION_PERMISSIONS_STREAM_NAME,
+        RESOURCE_USAGE_STREAM_NAME,
+    },
     test_utils::{ExpectedCall, MockApplication},
-    BaseRuntime, ContractRuntime, ExecutionError, ExecutionRuntimeConfig, ExecutionRuntimeContext,
-    Operation, ResourceControlPolicy, ServiceRuntime, SystemOperation, TestExecutionRuntimeContext,
+    BaseRuntime, BlockResourceUsage, ContractRuntime, ExecutionError, ExecutionRuntimeConfig,
+    ExecutionRuntimeContext, Message, MessageKind, Operation, Query, QueryResponse,
+    ResourceControlPolicy, ServiceRuntime, SystemOperation, SystemQuery,
+    TestExecutionRuntimeContext,
 };
 use linera_views::{
+    batch::Batch,
     context::{Context as _, MemoryContext, ViewContext},
     memory::MemoryStore,
-    views::View,
+    views::{RootView as _, View},
 };
 use test_case::test_case;
 
 use crate::{
     block::{Block, ConfirmedBlock},
-    data_types::{BlockExecutionOutcome, ProposedBlock},
-    test::{make_child_block, make_first_block, BlockTestExt, HttpServer},
+    data_types::{BlockExecutionOutcome, MessageBundle, OperationResult, ProposedBlock},
+    test::{make_child_block, make_first_block, BlockTestExt, HttpServer, MessageTestExt as _},
     ChainError, ChainExecutionContext, ChainStateView,
 };
 
@@ -239,6 +248,63 @@ async fn test_block_size_limit() -> anyhow::Result<()> {
     Ok(())
 }
 
+/// Checks that blocks publish the resources they consumed if the policy requires it.
+#[tokio::test]
+async fn test_publish_resource_usage() -> anyhow::Result<()> {
+    let mut env = TestEnvironment::new();
+    let time = Timestamp::from(0);
+    let config = env.make_open_chain_config();
+    let chain_desc = env.make_child_chain_description_with_config(3, config);
+    let chain_id = chain_desc.id();
+    let owner = chain_desc
+        .config()
+        .ownership
+        .all_owners()
+        .next()
+        .copied()
+        .unwrap();
+
+    let mut chain = ChainStateView::new(chain_id).await;
+    let policy = ResourceControlPolicy {
+        publish_resource_usage: true,
+        ..ResourceControlPolicy::default()
+    };
+    chain
+        .context()
+        .extra()
+        .add_blobs([committee_blob(policy)])
+        .await?;
+    chain
+        .context()
+        .extra()
+        .add_blobs(env.description_blobs())
+        .await?;
+    chain.ensure_is_active(time).await?;
+
+    let block = make_first_block(chain_id)
+        .with_authenticated_signer(Some(owner))
+        .with_operation(SystemOperation::Transfer {
+            owner: AccountOwner::CHAIN,
+            recipient: Recipient::chain(env.admin_id()),
+            amount: Amount::ONE,
+        });
+    let outcome = chain.execute_block(&block, time, None, &[], None).await?;
+
+    let [event] = outcome.events[0].as_slice() else {
+        panic!("expected a single event, got {:?}", outcome.events);
+    };
+    assert_eq!(
+        event.stream_id,
+        StreamId::system(RESOURCE_USAGE_STREAM_NAME)
+    );
+    assert_eq!(event.index, 0);
+    let usage = bcs::from_bytes::<BlockResourceUsage>(&event.value)?;
+    assert_eq!(usage.operations, 1);
+    assert_eq!(usage.messages, 1);
+    assert!(usage.block_size > 0);
+    Ok(())
+}
+
 #[tokio::test]
 async fn test_application_permissions() -> anyhow::Result<()> {
     let mut env = TestEnvironment::new();
@@ -367,6 +433,129 @@ async fn test_application_permissions() -> anyhow::Result<()> {
     Ok(())
 }
 
+#[tokio::test]
+async fn test_operation_permissions() -> anyhow::Result<()> {
+    let mut env = TestEnvironment::new();
+    let time = Timestamp::from(0);
+    let config = env.make_open_chain_config();
+    let chain_desc = env.make_child_chain_description_with_config(3, config);
+    let chain_id = chain_desc.id();
+    let owner = chain_desc
+        .config()
+        .ownership
+        .all_owners()
+        .next()
+        .copied()
+        .unwrap();
+    let other_owner = AccountOwner::from(AccountPublicKey::test_key(42));
+
+    let mut chain = ChainStateView::new(chain_id).await;
+    let extra = chain.context().extra();
+    extra
+        .add_blobs([committee_blob(Default::default())])
+        .await?;
+    extra.add_blobs(env.description_blobs()).await?;
+    chain.ensure_is_active(time).await?;
+
+    // Only the owner may execute system operations from now on.
+    let operation_permissions = OperationPermissions {
+        allowed_applications: BTreeMap::from([(
+            owner,
+            BTreeSet::from([GenericApplicationId::System]),
+        )]),
+    };
+    let block = make_first_block(chain_id)
+        .with_authenticated_signer(Some(owner))
+        .with_operation(SystemOperation::ChangeOperationPermissions(Some(
+            operation_permissions,
+        )));
+    let outcome = chain.execute_block(&block, time, None, &[], None).await?;
+    let value = ConfirmedBlock::new(outcome.with(block));
+    chain.apply_confirmed_block(&value, time).await?;
+
+    // Operations from other signers, or without any signer, are rejected.
+    let lift_restriction = SystemOperation::ChangeOperationPermissions(None);
+    for signer in [None, Some(other_owner)] {
+        let block = make_child_block(&value)
+            .with_authenticated_signer(signer)
+            .with_operation(lift_restriction.clone());
+        let result = chain.execute_block(&block, time, None, &[], None).await;
+        assert_matches!(
+            result,
+            Err(ChainError::UnauthorizedOperation { owner, application_id })
+                if owner == signer && application_id == GenericApplicationId::System
+        );
+    }
+
+    // The owner can still lift the restriction.
+    let block = make_child_block(&value)
+        .with_authenticated_signer(Some(owner))
+        .with_operation(lift_restriction);
+    chain.execute_block(&block, time, None, &[], None).await?;
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_staged_application_permissions() -> anyhow::Result<()> {
+    let mut env = TestEnvironment::new();
+    let time = Timestamp::from(0);
+    let config = env.make_open_chain_config();
+    let chain_desc = env.make_child_chain_description_with_config(3, config);
+    let chain_id = chain_desc.id();
+    let (app_description, _, _) = env.make_app_description();
+    let application_id = ApplicationId::from(&app_description);
+
+    let mut chain = ChainStateView::new(chain_id).await;
+    let extra = chain.context().extra();
+    extra
+        .add_blobs([committee_blob(Default::default())])
+        .await?;
+    extra.add_blobs(env.description_blobs()).await?;
+    chain.ensure_is_active(time).await?;
+
+    // Only the application's operations will be allowed from height 2 on.
+    let staged = StagedApplicationPermissions {
+        application_permissions: ApplicationPermissions {
+            execute_operations: Some(vec![application_id]),
+            ..ApplicationPermissions::default()
+        },
+        activation: PermissionsActivation::Height(BlockHeight(2)),
+    };
+    let block = make_first_block(chain_id).with_operation(
+        SystemOperation::StageApplicationPermissions(Some(staged.clone())),
+    );
+    let outcome = chain.execute_block(&block, time, None, &[], None).await?;
+    let event = Event {
+        stream_id: StreamId::system(APPLICATION_PERMISSIONS_STREAM_NAME),
+        index: 0,
+        value: bcs::to_bytes(&staged)?,
+    };
+    assert_eq!(outcome.events, vec![vec![event]]);
+    let value = ConfirmedBlock::new(outcome.with(block));
+    chain.apply_confirmed_block(&value, time).await?;
+
+    // System operations are still allowed in the next block.
+    let system_operation = SystemOperation::ChangeInboxQuotas(InboxQuotas::default());
+    let block = make_child_block(&value).with_operation(system_operation.clone());
+    let outcome = chain.execute_block(&block, time, None, &[], None).await?;
+    let value = ConfirmedBlock::new(outcome.with(block));
+    chain.apply_confirmed_block(&value, time).await?;
+    assert!(chain
+        .execution_state
+        .system
+        .staged_application_permissions
+        .get()
+        .is_some());
+
+    // From height 2 on, the new permissions apply.
+    let block = make_child_block(&value).with_operation(system_operation);
+    let result = chain.execute_block(&block, time, None, &[], None).await;
+    assert_matches!(result, Err(ChainError::AuthorizedApplications(app_ids))
+        if app_ids == vec![application_id]
+    );
+    Ok(())
+}
+
 /// Tests if services can execute as oracles if the total execution time is less than the limit.
 #[test_case(&[100]; "single service as oracle call")]
 #[test_case(&[50, 50]; "two service as oracle calls")]
@@ -746,3 +935,267 @@ async fn prepare_test_with_dummy_mock_application(
 
     Ok((application, application_id, chain, block, time))
 }
+
+#[tokio::test]
+async fn test_untracked_nonempty_inboxes() -> anyhow::Result<()> {
+    let env = TestEnvironment::new();
+    let mut chain = ChainStateView::new(env.admin_id()).await;
+    let origin = ChainId(CryptoHash::test_hash("origin"));
+    let message = Message::User {
+        application_id: ApplicationId::default(),
+        bytes: vec![0],
+    };
+    let bundle = MessageBundle {
+        certificate_hash: CryptoHash::test_hash("certificate"),
+        height: BlockHeight(0),
+        timestamp: Timestamp::default(),
+        transaction_index: 0,
+        messages: vec![message.to_posted(0, MessageKind::Simple)],
+    };
+    // The inbox was written before the non-empty inboxes were recorded.
+    chain
+        .inboxes
+        .load_entry_mut(&origin)
+        .await?
+        .add_bundle(bundle)
+        .await?;
+    assert_eq!(*chain.nonempty_inboxes.get(), None);
+    assert_eq!(
+        chain.nonempty_inbox_origins().await?,
+        BTreeSet::from([origin])
+    );
+    let origins = chain
+        .load_nonempty_inboxes()
+        .await?
+        .into_iter()
+        .map(|(origin, _)| origin)
+        .collect::<Vec<_>>();
+    assert_eq!(origins, vec![origin]);
+
+    chain.backfill_nonempty_inboxes().await?;
+    assert_eq!(
+        *chain.nonempty_inboxes.get(),
+        Some(BTreeSet::from([origin]))
+    );
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_execute_tracked_applications() -> anyhow::Result<()> {
+    let mut env = TestEnvironment::new();
+    let time = Timestamp::from(0);
+    let (tracked_app, tracked_contract, tracked_service) = env.make_app_description();
+    let tracked_id = ApplicationId::from(&tracked_app);
+    let (other_app, other_contract, other_service) = env.make_app_from_bytecodes(
+        Bytecode::new(b"contractB".into()),
+        Bytecode::new(b"serviceB".into()),
+    );
+    let other_id = ApplicationId::from(&other_app);
+    let config = env.make_open_chain_config();
+    let chain_id = env.make_child_chain_description_with_config(3, config).id();
+    let mut chain = ChainStateView::new(chain_id).await;
+
+    let application = MockApplication::default();
+    let other_application = MockApplication::default();
+    let extra = &chain.context().extra();
+    extra
+        .user_contracts()
+        .insert(tracked_id, application.clone().into());
+    extra
+        .user_services()
+        .insert(tracked_id, application.clone().into());
+    extra
+        .user_contracts()
+        .insert(other_id, other_application.clone().into());
+    extra
+        .add_blobs([committee_blob(Default::default())])
+        .await?;
+    extra.add_blobs(env.description_blobs()).await?;
+    extra
+        .add_blobs([
+            tracked_contract,
+            tracked_service,
+            Blob::new_application_description(&tracked_app),
+            other_contract,
+            other_service,
+            Blob::new_application_description(&other_app),
+        ])
+        .await?;
+    chain.ensure_is_active(time).await?;
+    chain.save().await?;
+
+    let write_value = || {
+        ExpectedCall::execute_operation(|runtime, _| {
+            let mut batch = Batch::new();
+            batch.put_key_value_bytes(b"key".to_vec(), b"value".to_vec());
+            runtime.write_batch(batch)?;
+            Ok(vec![])
+        })
+    };
+    let operation = |application_id| Operation::User {
+        application_id,
+        bytes: vec![],
+    };
+
+    // Execute a block with an operation of each application, then revert it.
+    application.expect_call(write_value());
+    application.expect_call(ExpectedCall::default_finalize());
+    other_application.expect_call(ExpectedCall::execute_operation(|_, _| Ok(vec![])));
+    other_application.expect_call(ExpectedCall::default_finalize());
+    let block = make_first_block(chain_id)
+        .with_operation(operation(tracked_id))
+        .with_operation(operation(other_id));
+    let outcome = chain.execute_block(&block, time, None, &[], None).await?;
+    let value = ConfirmedBlock::new(outcome.with(block));
+    chain.rollback();
+
+    // Synchronizing the chain partially executes only the tracked application's operation.
+    let applications = BTreeSet::from([tracked_id]);
+    chain.application_filter.set(Some(applications.clone()));
+    application.expect_call(write_value());
+    application.expect_call(ExpectedCall::default_finalize());
+    chain.apply_unexecuted_block(&value, &applications).await?;
+    assert_eq!(*chain.tracked_state_lost_at.get(), None);
+    assert_eq!(chain.tip_state.get().next_block_height, BlockHeight(1));
+
+    // Its state can be queried, but not the system's.
+    application.expect_call(ExpectedCall::handle_query(|runtime, _| {
+        Ok(runtime
+            .read_value_bytes(b"key".to_vec())?
+            .unwrap_or_default())
+    }));
+    let query = Query::User {
+        application_id: tracked_id,
+        bytes: vec![],
+    };
+    let outcome = chain.query_application(time, query, None).await?;
+    assert_eq!(outcome.response, QueryResponse::User(b"value".to_vec()));
+    let result = chain
+        .query_application(time, Query::System(SystemQuery), None)
+        .await;
+    assert_matches!(
+        result,
+        Err(ChainError::ExecutionError(error, _))
+            if matches!(*error, ExecutionError::UntrackedState(_))
+    );
+
+    // If a transaction of the tracked application uses the system's state, which is not
+    // maintained, the application's state is lost.
+    application.expect_call(ExpectedCall::execute_operation(|runtime, _| {
+        runtime.read_chain_balance()?;
+        Ok(vec![])
+    }));
+    let block = make_child_block(&value).with_operation(operation(tracked_id));
+    let outcome = BlockExecutionOutcome {
+        messages: vec![Vec::new()],
+        oracle_responses: vec![Vec::new()],
+        events: vec![Vec::new()],
+        blobs: vec![Vec::new()],
+        operation_results: vec![OperationResult::default()],
+        ..BlockExecutionOutcome::default()
+    };
+    let value = ConfirmedBlock::new(outcome.with(block));
+    chain.apply_unexecuted_block(&value, &applications).await?;
+    assert_eq!(*chain.tracked_state_lost_at.get(), Some(BlockHeight(1)));
+    assert_eq!(chain.tip_state.get().next_block_height, BlockHeight(2));
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_published_messages_are_sent_to_subscribers() -> anyhow::Result<()> {
+    let mut env = TestEnvironment::new();
+    let time = Timestamp::from(0);
+    let (app_description, contract_blob, service_blob) = env.make_app_description();
+    let application_id = ApplicationId::from(&app_description);
+    let config = env.make_open_chain_config();
+    let chain_id = env.make_child_chain_description_with_config(3, config).id();
+    let mut chain = ChainStateView::new(chain_id).await;
+
+    let application = MockApplication::default();
+    let extra = &chain.context().extra();
+    extra
+        .user_contracts()
+        .insert(application_id, application.clone().into());
+    extra
+        .add_blobs([committee_blob(Default::default())])
+        .await?;
+    extra.add_blobs(env.description_blobs()).await?;
+    extra
+        .add_blobs([
+            contract_blob,
+            service_blob,
+            Blob::new_application_description(&app_description),
+        ])
+        .await?;
+    chain.ensure_is_active(time).await?;
+
+    let channel = ChannelName::from(b"news".to_vec());
+    let subscriber_1 = ChainId(CryptoHash::test_hash("subscriber 1"));
+    let subscriber_2 = ChainId(CryptoHash::test_hash("subscriber 2"));
+    let subscribers = BTreeSet::from([subscriber_1, subscriber_2]);
+    let channel_subscribers = chain
+        .execution_state
+        .system
+        .channel_subscribers
+        .load_entry_mut(&(application_id, channel.clone()))
+        .await?;
+    for subscriber in &subscribers {
+        channel_subscribers.insert(subscriber)?;
+    }
+    chain.save().await?;
+
+    let publish = || {
+        let channel = channel.clone();
+        ExpectedCall::execute_operation(move |runtime, _| {
+            runtime.publish_message(channel, b"hello".to_vec())?;
+            Ok(vec![])
+        })
+    };
+    let operation = Operation::User {
+        application_id,
+        bytes: vec![],
+    };
+
+    // The published message is sent to each subscriber, in the order of their chain IDs.
+    application.expect_call(publish());
+    application.expect_call(ExpectedCall::default_finalize());
+    let block = make_first_block(chain_id).with_operation(operation);
+    let outcome = chain.execute_block(&block, time, None, &[], None).await?;
+    let destinations = outcome.messages[0]
+        .iter()
+        .map(|message| message.destination)
+        .collect::<Vec<_>>();
+    assert_eq!(
+        destinations,
+        subscribers.iter().copied().collect::<Vec<_>>()
+    );
+    for message in &outcome.messages[0] {
+        assert_eq!(
+            message.message,
+            Message::User {
+                application_id,
+                bytes: b"hello".to_vec(),
+            }
+        );
+    }
+    let value = ConfirmedBlock::new(outcome.with(block));
+    chain.rollback();
+
+    // A partially synchronized chain accepts the messages without knowing the subscribers,
+    // and schedules the block in the outbox of each of them.
+    let applications = BTreeSet::from([application_id]);
+    chain.application_filter.set(Some(applications.clone()));
+    application.expect_call(publish());
+    application.expect_call(ExpectedCall::default_finalize());
+    chain.apply_unexecuted_block(&value, &applications).await?;
+    assert_eq!(*chain.tracked_state_lost_at.get(), None);
+    for subscriber in subscribers {
+        let outbox = chain
+            .outboxes
+            .try_load_entry(&subscriber)
+            .await?
+            .expect("the subscriber should have an outbox");
+        assert_eq!(outbox.queue.elements().await?, [BlockHeight(0)]);
+    }
+    Ok(())
+}
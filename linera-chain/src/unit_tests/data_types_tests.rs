@@ -4,7 +4,7 @@
 
 use linera_base::{
     crypto::{AccountSecretKey, Ed25519SecretKey, Secp256k1SecretKey, ValidatorKeypair},
-    data_types::Amount,
+    data_types::{Amount, FeeBreakdown},
 };
 
 use super::*;
@@ -31,6 +31,7 @@ fn test_signed_values() {
         events: vec![Vec::new()],
         blobs: vec![Vec::new()],
         operation_results: vec![OperationResult::default()],
+        fees: FeeBreakdown::default(),
     }
     .with(make_first_block(dummy_chain_id(1)).with_simple_transfer(dummy_chain_id(2), Amount::ONE));
     let confirmed_value = ConfirmedBlock::new(block.clone());
@@ -102,6 +103,7 @@ fn test_certificates() {
         events: vec![Vec::new()],
         blobs: vec![Vec::new()],
         operation_results: vec![OperationResult::default()],
+        fees: FeeBreakdown::default(),
     }
     .with(make_first_block(dummy_chain_id(1)).with_simple_transfer(dummy_chain_id(1), Amount::ONE));
     let value = ConfirmedBlock::new(block);
@@ -152,3 +154,37 @@ fn round_ordering() {
     assert!(Round::SingleLeader(2) < Round::Validator(0));
     assert!(Round::Validator(1) < Round::Validator(2))
 }
+
+#[test]
+fn test_block_header_commits_to_fees() {
+    let outcome = BlockExecutionOutcome {
+        messages: vec![Vec::new()],
+        previous_message_blocks: BTreeMap::new(),
+        previous_event_blocks: BTreeMap::new(),
+        state_hash: CryptoHash::test_hash("state"),
+        oracle_responses: vec![Vec::new()],
+        events: vec![Vec::new()],
+        blobs: vec![Vec::new()],
+        operation_results: vec![OperationResult::default()],
+        fees: FeeBreakdown {
+            fuel: Amount::ONE,
+            ..FeeBreakdown::default()
+        },
+    };
+    let proposal =
+        make_first_block(dummy_chain_id(1)).with_simple_transfer(dummy_chain_id(2), Amount::ONE);
+    let block = outcome.clone().with(proposal.clone());
+    assert_eq!(block.header.fees_hash, CryptoHash::new(&block.body.fees));
+
+    // The header is recomputed from the body when deserializing a block.
+    let mut tampered = block.clone();
+    tampered.body.fees = FeeBreakdown::default();
+    let deserialized = bcs::from_bytes::<Block>(&bcs::to_bytes(&tampered).unwrap()).unwrap();
+    assert_ne!(deserialized.header.fees_hash, block.header.fees_hash);
+    let other_block = BlockExecutionOutcome {
+        fees: FeeBreakdown::default(),
+        ..outcome
+    }
+    .with(proposal);
+    assert_eq!(deserialized, other_block);
+}
@@ -0,0 +1,520 @@
+// Copyright (c) Zefchain Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! Encrypted backups of the wallet and the keystore, with the `backup` feature.
+//!
+//! A backup is a snapshot of the wallet, including the chains, their owners and any pending
+//! proposals, together with the keystore holding the owners' secret keys. It is encrypted with
+//! a key derived from a passphrase, so that it can be stored by an untrusted [`BackupTarget`]:
+//! a local directory, or, with the `remote-backup` feature, an S3 bucket or a WebDAV server.
+//!
+//! The encryption is authenticated: restoring a backup with the wrong passphrase, or one that
+//! was modified or truncated by the target, fails instead of producing a corrupt wallet.
+
+use std::{
+    io,
+    num::NonZeroU32,
+    path::{Path, PathBuf},
+};
+
+use linera_base::{crypto::InMemorySigner, data_types::Timestamp, identifiers::ChainId};
+use ring::{
+    aead::{self, Aad, LessSafeKey, Nonce, UnboundKey},
+    pbkdf2,
+    rand::{SecureRandom, SystemRandom},
+};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::wallet::Wallet;
+
+/// The bytes that every backup starts with.
+const BACKUP_MAGIC: [u8; 4] = *b"LWB\x01";
+
+/// The default number of PBKDF2 iterations used to derive the encryption key.
+pub const DEFAULT_KEY_DERIVATION_ITERATIONS: NonZeroU32 = NonZeroU32::new(600_000).unwrap();
+
+/// The length of the salt used to derive the encryption key.
+const SALT_LEN: usize = 16;
+
+/// An error when creating or restoring a backup.
+#[derive(Debug, Error)]
+pub enum BackupError {
+    /// The data is not a wallet backup, or was written by an unsupported version.
+    #[error("the data is not a wallet backup")]
+    NotABackup,
+    /// The backup failed to decrypt.
+    #[error("failed to decrypt the backup: the passphrase is wrong or the backup is corrupted")]
+    Integrity,
+    /// The backup read back from the target differs from the one that was written.
+    #[error("the backup {0:?} stored by the target differs from the one that was written")]
+    VerificationFailed(String),
+    /// The name of a backup can't be used by every target.
+    #[error("invalid backup name {0:?}: only ASCII letters, digits, '-', '_' and '.' are allowed")]
+    InvalidName(String),
+    /// The target has no backup with that name.
+    #[error("backup {0:?} not found")]
+    NotFound(String),
+    /// The system failed to provide random bytes.
+    #[error("failed to generate random bytes")]
+    Random,
+    #[error("I/O error: {0}")]
+    Io(#[from] io::Error),
+    #[error("BCS error: {0}")]
+    Bcs(#[from] bcs::Error),
+    #[error("JSON error: {0}")]
+    Json(#[from] serde_json::Error),
+    #[cfg(feature = "remote-backup")]
+    #[error("invalid URL {0:?}")]
+    InvalidUrl(String),
+    /// The prefix of the keys in an S3 bucket has an empty, `.` or `..` segment, or a
+    /// control character.
+    #[cfg(feature = "remote-backup")]
+    #[error("invalid key prefix {0:?}")]
+    InvalidPrefix(String),
+    #[cfg(feature = "remote-backup")]
+    #[error("HTTP error: {0}")]
+    Http(#[from] reqwest::Error),
+    #[cfg(feature = "remote-backup")]
+    #[error("the backup target responded with status {0}")]
+    UnexpectedStatus(reqwest::StatusCode),
+}
+
+/// A restored backup.
+#[derive(Serialize, Deserialize)]
+pub struct WalletSnapshot {
+    /// When the backup was created.
+    pub created_at: Timestamp,
+    /// The wallet, with its chains and their pending proposals.
+    pub wallet: Wallet,
+    /// The secret keys of the chain owners.
+    pub keystore: InMemorySigner,
+}
+
+/// The borrowed form of [`WalletSnapshot`], serialized when creating a backup.
+#[derive(Serialize)]
+struct WalletSnapshotRef<'a> {
+    created_at: Timestamp,
+    wallet: &'a Wallet,
+    keystore: &'a InMemorySigner,
+}
+
+impl WalletSnapshot {
+    /// Returns the chains that have an owner whose secret key is not in the keystore.
+    pub fn chains_without_keys(&self) -> Vec<ChainId> {
+        let owners = self
+            .keystore
+            .keys()
+            .into_iter()
+            .map(|(owner, _)| owner)
+            .collect::<Vec<_>>();
+        self.wallet
+            .chains
+            .values()
+            .filter(|chain| chain.owner.is_some_and(|owner| !owners.contains(&owner)))
+            .map(|chain| chain.chain_id)
+            .collect()
+    }
+}
+
+/// The header of a backup. It is not encrypted, but it is authenticated along with the
+/// snapshot.
+#[derive(Serialize, Deserialize)]
+struct BackupHeader {
+    /// The salt used to derive the encryption key from the passphrase.
+    salt: [u8; SALT_LEN],
+    /// The number of PBKDF2 iterations used to derive the encryption key.
+    iterations: NonZeroU32,
+    /// The nonce used to encrypt the snapshot.
+    nonce: [u8; aead::NONCE_LEN],
+}
+
+impl BackupHeader {
+    /// Returns the key to encrypt and decrypt the snapshot with.
+    fn key(&self, passphrase: &str) -> LessSafeKey {
+        let mut key = [0; 32];
+        pbkdf2::derive(
+            pbkdf2::PBKDF2_HMAC_SHA256,
+            self.iterations,
+            &self.salt,
+            passphrase.as_bytes(),
+            &mut key,
+        );
+        let key = UnboundKey::new(&aead::CHACHA20_POLY1305, &key)
+            .expect("the key should have the length of a ChaCha20-Poly1305 key");
+        LessSafeKey::new(key)
+    }
+}
+
+/// Encrypts a snapshot of `wallet` and `keystore` with `passphrase`.
+pub fn encrypt_snapshot(
+    wallet: &Wallet,
+    keystore: &InMemorySigner,
+    passphrase: &str,
+    iterations: NonZeroU32,
+) -> Result<Vec<u8>, BackupError> {
+    let random = SystemRandom::new();
+    let mut header = BackupHeader {
+        salt: [0; SALT_LEN],
+        iterations,
+        nonce: [0; aead::NONCE_LEN],
+    };
+    random
+        .fill(&mut header.salt)
+        .map_err(|_| BackupError::Random)?;
+    random
+        .fill(&mut header.nonce)
+        .map_err(|_| BackupError::Random)?;
+    let header_bytes = bcs::to_bytes(&header)?;
+    let mut ciphertext = serde_json::to_vec(&WalletSnapshotRef {
+        created_at: Timestamp::now(),
+        wallet,
+        keystore,
+    })?;
+    header
+        .key(passphrase)
+        .seal_in_place_append_tag(
+            Nonce::assume_unique_for_key(header.nonce),
+            Aad::from(&header_bytes),
+            &mut ciphertext,
+        )
+        .map_err(|_| BackupError::Integrity)?;
+    let mut bytes = BACKUP_MAGIC.to_vec();
+    bcs::serialize_into(&mut bytes, &(header_bytes, ciphertext))?;
+    Ok(bytes)
+}
+
+/// Decrypts a backup created by [`encrypt_snapshot`] with the same `passphrase`.
+pub fn decrypt_snapshot(bytes: &[u8], passphrase: &str) -> Result<WalletSnapshot, BackupError> {
+    let contents = bytes
+        .strip_prefix(&BACKUP_MAGIC)
+        .ok_or(BackupError::NotABackup)?;
+    let (header_bytes, mut ciphertext) =
+        bcs::from_bytes::<(Vec<u8>, Vec<u8>)>(contents).map_err(|_| BackupError::NotABackup)?;
+    let header =
+        bcs::from_bytes::<BackupHeader>(&header_bytes).map_err(|_| BackupError::NotABackup)?;
+    let plaintext = header
+        .key(passphrase)
+        .open_in_place(
+            Nonce::assume_unique_for_key(header.nonce),
+            Aad::from(&header_bytes),
+            &mut ciphertext,
+        )
+        .map_err(|_| BackupError::Integrity)?;
+    Ok(serde_json::from_slice(plaintext)?)
+}
+
+/// Checks that `name` can be used as the name of a backup by every target, i.e. as a file
+/// name and in a URL without escaping.
+fn check_name(name: &str) -> Result<(), BackupError> {
+    let is_valid = !name.is_empty()
+        && !name.starts_with('.')
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'));
+    if is_valid {
+        Ok(())
+    } else {
+        Err(BackupError::InvalidName(name.to_owned()))
+    }
+}
+
+/// A place where backups are stored.
+#[trait_variant::make(Send)]
+pub trait BackupTarget {
+    /// Stores `bytes` as the backup called `name`, replacing any previous one.
+    async fn write(&self, name: &str, bytes: Vec<u8>) -> Result<(), BackupError>;
+
+    /// Returns the backup called `name`.
+    async fn read(&self, name: &str) -> Result<Vec<u8>, BackupError>;
+}
+
+/// Creates an encrypted backup of `wallet` and `keystore` called `name` in `target`.
+///
+/// The backup is read back after it is written, to check that the target stored it intact.
+pub async fn backup_wallet<T: BackupTarget>(
+    target: &T,
+    name: &str,
+    wallet: &Wallet,
+    keystore: &InMemorySigner,
+    passphrase: &str,
+    iterations: NonZeroU32,
+) -> Result<(), BackupError> {
+    check_name(name)?;
+    let bytes = encrypt_snapshot(wallet, keystore, passphrase, iterations)?;
+    target.write(name, bytes.clone()).await?;
+    if target.read(name).await? != bytes {
+        return Err(BackupError::VerificationFailed(name.to_owned()));
+    }
+    tracing::info!(name, "Created an encrypted backup of the wallet");
+    Ok(())
+}
+
+/// Restores the backup called `name` from `target`.
+pub async fn restore_wallet<T: BackupTarget>(
+    target: &T,
+    name: &str,
+    passphrase: &str,
+) -> Result<WalletSnapshot, BackupError> {
+    check_name(name)?;
+    let bytes = target.read(name).await?;
+    let snapshot = decrypt_snapshot(&bytes, passphrase)?;
+    let chains_without_keys = snapshot.chains_without_keys();
+    if !chains_without_keys.is_empty() {
+        tracing::warn!(
+            ?chains_without_keys,
+            "The restored keystore lacks the keys of some owned chains"
+        );
+    }
+    Ok(snapshot)
+}
+
+/// Stores backups as files in a local directory.
+#[derive(Clone, Debug)]
+pub struct FileBackupTarget {
+    directory: PathBuf,
+}
+
+impl FileBackupTarget {
+    /// Stores backups in `directory`, which is created if needed.
+    pub fn new(directory: impl Into<PathBuf>) -> Self {
+        FileBackupTarget {
+            directory: directory.into(),
+        }
+    }
+
+    /// Returns the directory where the backups are stored.
+    pub fn directory(&self) -> &Path {
+        &self.directory
+    }
+}
+
+impl BackupTarget for FileBackupTarget {
+    async fn write(&self, name: &str, bytes: Vec<u8>) -> Result<(), BackupError> {
+        check_name(name)?;
+        tokio::fs::create_dir_all(&self.directory).await?;
+        // Write to a temporary file first, so that an interrupted backup doesn't replace a
+        // previous one with a truncated file.
+        let temporary_path = self.directory.join(format!(".{name}.tmp"));
+        tokio::fs::write(&temporary_path, bytes).await?;
+        tokio::fs::rename(&temporary_path, self.directory.join(name)).await?;
+        Ok(())
+    }
+
+    async fn read(&self, name: &str) -> Result<Vec<u8>, BackupError> {
+        check_name(name)?;
+        match tokio::fs::read(self.directory.join(name)).await {
+            Ok(bytes) => Ok(bytes),
+            Err(error) if error.kind() == io::ErrorKind::NotFound => {
+                Err(BackupError::NotFound(name.to_owned()))
+            }
+            Err(error) => Err(error.into()),
+        }
+    }
+}
+
+#[cfg(feature = "remote-backup")]
+pub use remote::{S3BackupTarget, WebDavBackupTarget};
+
+#[cfg(feature = "remote-backup")]
+pub(crate) mod remote {
+    use chrono::{DateTime, Utc};
+    use linera_base::data_types::Timestamp;
+    use linera_storage::object_store::{payload_hash, uri_encode, SigV4Signer};
+    use reqwest::{Client, Method, RequestBuilder, Response, StatusCode, Url};
+
+    use super::{check_name, BackupError, BackupTarget};
+
+    /// Returns the bytes of a successful response, or the error corresponding to its status.
+    async fn response_bytes(name: &str, response: Response) -> Result<Vec<u8>, BackupError> {
+        match response.status() {
+            status if status.is_success() => Ok(response.bytes().await?.to_vec()),
+            StatusCode::NOT_FOUND => Err(BackupError::NotFound(name.to_owned())),
+            status => Err(BackupError::UnexpectedStatus(status)),
+        }
+    }
+
+    /// Returns the URL of the backup called `name` under `base_url`.
+    fn backup_url(base_url: &Url, name: &str) -> Result<Url, BackupError> {
+        check_name(name)?;
+        let url = format!("{}/{name}", base_url.as_str().trim_end_matches('/'));
+        Url::parse(&url).map_err(|_| BackupError::InvalidUrl(url))
+    }
+
+    /// Stores backups on a WebDAV server.
+    #[derive(Clone)]
+    pub struct WebDavBackupTarget {
+        client: Client,
+        base_url: Url,
+        credentials: Option<(String, String)>,
+    }
+
+    impl WebDavBackupTarget {
+        /// Stores backups in the collection at `base_url`.
+        pub fn new(base_url: &str) -> Result<Self, BackupError> {
+            let base_url =
+                Url::parse(base_url).map_err(|_| BackupError::InvalidUrl(base_url.to_owned()))?;
+            Ok(WebDavBackupTarget {
+                client: Client::new(),
+                base_url,
+                credentials: None,
+            })
+        }
+
+        /// Authenticates to the server with HTTP basic authentication.
+        pub fn with_credentials(mut self, username: String, password: String) -> Self {
+            self.credentials = Some((username, password));
+            self
+        }
+
+        fn request(&self, method: Method, name: &str) -> Result<RequestBuilder, BackupError> {
+            let request = self
+                .client
+                .request(method, backup_url(&self.base_url, name)?);
+            Ok(match &self.credentials {
+                Some((username, password)) => request.basic_auth(username, Some(password)),
+                None => request,
+            })
+        }
+    }
+
+    impl BackupTarget for WebDavBackupTarget {
+        async fn write(&self, name: &str, bytes: Vec<u8>) -> Result<(), BackupError> {
+            let response = self.request(Method::PUT, name)?.body(bytes).send().await?;
+            response_bytes(name, response).await?;
+            Ok(())
+        }
+
+        async fn read(&self, name: &str) -> Result<Vec<u8>, BackupError> {
+            let response = self.request(Method::GET, name)?.send().await?;
+            response_bytes(name, response).await
+        }
+    }
+
+    /// Checks that `prefix` can start the keys of objects in a bucket, and returns it
+    /// percent-encoded.
+    ///
+    /// The prefix is a sequence of segments separated by `/`. Only the last one may be empty,
+    /// i.e. the prefix may end with a `/`. Segments that URLs normalize away, i.e. `.` and
+    /// `..`, are rejected, because the signed path would then differ from the requested one.
+    fn encode_prefix(prefix: &str) -> Result<String, BackupError> {
+        let invalid = || BackupError::InvalidPrefix(prefix.to_owned());
+        if prefix.is_empty() {
+            return Ok(String::new());
+        }
+        if prefix.chars().any(char::is_control) {
+            return Err(invalid());
+        }
+        let segments = prefix.split('/').collect::<Vec<_>>();
+        let (last, directories) = segments.split_last().expect("split returns a segment");
+        if directories
+            .iter()
+            .any(|segment| matches!(*segment, "" | "." | ".."))
+            || matches!(*last, "." | "..")
+        {
+            return Err(invalid());
+        }
+        Ok(segments
+            .iter()
+            .map(|segment| uri_encode(segment))
+            .collect::<Vec<_>>()
+            .join("/"))
+    }
+
+    /// Stores backups in an S3 bucket, or with any storage service compatible with its API.
+    ///
+    /// Requests are signed with AWS Signature Version 4, and use path-style URLs, i.e.
+    /// `<endpoint>/<bucket>/<prefix><name>`.
+    #[derive(Clone)]
+    pub struct S3BackupTarget {
+        client: Client,
+        endpoint: Url,
+        bucket: String,
+        /// The percent-encoded prefix of the keys of the backups.
+        prefix: String,
+        signer: SigV4Signer,
+    }
+
+    impl S3BackupTarget {
+        /// Stores backups in `bucket`, using the service at `endpoint`, e.g.
+        /// `https://s3.eu-west-1.amazonaws.com`.
+        pub fn new(
+            endpoint: &str,
+            region: String,
+            bucket: String,
+            access_key_id: String,
+            secret_access_key: String,
+        ) -> Result<Self, BackupError> {
+            let endpoint =
+                Url::parse(endpoint).map_err(|_| BackupError::InvalidUrl(endpoint.to_owned()))?;
+            check_name(&bucket)?;
+            Ok(S3BackupTarget {
+                client: Client::new(),
+                endpoint,
+                bucket,
+                prefix: String::new(),
+                signer: SigV4Signer::new(region, access_key_id, secret_access_key),
+            })
+        }
+
+        /// Stores the backups under keys starting with `prefix`, e.g. `"wallets/"`.
+        pub fn with_prefix(mut self, prefix: &str) -> Result<Self, BackupError> {
+            self.prefix = encode_prefix(prefix)?;
+            Ok(self)
+        }
+
+        /// Returns the URL of the backup called `name`.
+        pub(crate) fn url(&self, name: &str) -> Result<Url, BackupError> {
+            check_name(name)?;
+            let mut url = self.endpoint.clone();
+            url.set_path(&format!("{}/{}{name}", self.bucket, self.prefix));
+            Ok(url)
+        }
+
+        /// Returns a request for the backup called `name`, signed with AWS Signature
+        /// Version 4.
+        fn signed_request(
+            &self,
+            method: Method,
+            name: &str,
+            payload: &[u8],
+        ) -> Result<RequestBuilder, BackupError> {
+            let url = self.url(name)?;
+            let time = DateTime::from_timestamp_micros(Timestamp::now().micros() as i64)
+                .expect("the current time should be a valid date");
+            let headers = self.signature_headers(&method, &url, payload_hash(payload), time)?;
+            Ok(headers.into_iter().fold(
+                self.client.request(method, url),
+                |request, (name, value)| request.header(name, value),
+            ))
+        }
+
+        /// Returns the headers that authenticate a request to `url` at `time`, with a payload
+        /// with the given SHA-256 hash, with AWS Signature Version 4.
+        pub(crate) fn signature_headers(
+            &self,
+            method: &Method,
+            url: &Url,
+            payload_hash: String,
+            time: DateTime<Utc>,
+        ) -> Result<[(&'static str, String); 3], BackupError> {
+            self.signer
+                .signature_headers(method, url, payload_hash, time)
+                .ok_or_else(|| BackupError::InvalidUrl(url.to_string()))
+        }
+    }
+
+    impl BackupTarget for S3BackupTarget {
+        async fn write(&self, name: &str, bytes: Vec<u8>) -> Result<(), BackupError> {
+            let request = self.signed_request(Method::PUT, name, &bytes)?;
+            let response = request.body(bytes).send().await?;
+            response_bytes(name, response).await?;
+            Ok(())
+        }
+
+        async fn read(&self, name: &str) -> Result<Vec<u8>, BackupError> {
+            let response = self.signed_request(Method::GET, name, &[])?.send().await?;
+            response_bytes(name, response).await
+        }
+    }
+}
@@ -231,6 +231,10 @@ impl<C: ClientContext> ChainListener<C> {
                 self.maybe_process_inbox(notification.chain_id).await?;
             }
             Reason::NewRound { .. } => self.update_validators(&notification).await?,
+            // Don't keep waiting for a round timeout if our proposal was discarded.
+            Reason::ProposalDiscarded { .. } => {
+                self.maybe_process_inbox(notification.chain_id).await?;
+            }
             Reason::NewBlock { hash, .. } => {
                 self.update_wallet(notification.chain_id).await?;
                 self.add_new_chains(*hash).await?;
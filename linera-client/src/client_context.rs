@@ -1,29 +1,33 @@
 // Copyright (c) Zefchain Labs, Inc.
 // SPDX-License-Identifier: Apache-2.0
 
-use std::sync::Arc;
+use std::{collections::BTreeMap, sync::Arc};
 
-use futures::Future;
+use futures::{future, Future};
 use linera_base::{
     crypto::{CryptoHash, ValidatorPublicKey},
     data_types::{BlockHeight, Timestamp},
-    identifiers::{Account, AccountOwner, ChainId},
+    ensure,
+    identifiers::{Account, AccountOwner, ChainId, EventId},
     ownership::ChainOwnership,
     time::{Duration, Instant},
 };
 use linera_chain::types::ConfirmedBlockCertificate;
 use linera_core::{
-    client::{ChainClient, Client},
+    client::{ChainClient, ChainClientError, Client},
     data_types::{ChainInfoQuery, ClientOutcome},
     join_set_ext::JoinSet,
-    node::ValidatorNode,
+    node::{ValidatorNode, ValidatorNodeProvider as _},
+    recording::RecordingNodeProvider,
+    remote_node::RemoteNode,
     Environment, JoinSetExt as _,
 };
 use linera_persistent::{Persist, PersistExt as _};
 use linera_rpc::node_provider::{NodeOptions, NodeProvider};
+use linera_storage::Storage as _;
 use linera_version::VersionInfo;
 use thiserror_context::Context;
-use tracing::{debug, info};
+use tracing::{debug, info, warn};
 #[cfg(feature = "benchmark")]
 use {
     crate::benchmark::{Benchmark, BenchmarkError},
@@ -33,7 +37,6 @@ use {
         data_types::Amount,
         identifiers::{ApplicationId, BlobType},
     },
-    linera_core::client::ChainClientError,
     linera_execution::{
         system::{OpenChainConfig, SystemOperation},
         Operation,
@@ -42,7 +45,10 @@ use {
     tokio::task,
 };
 #[cfg(not(web))]
-use {crate::client_metrics::ClientMetrics, tokio::sync::mpsc};
+use {
+    crate::client_metrics::ClientMetrics, linera_core::recording::ConversationRecorder,
+    tokio::sync::mpsc, tracing::error,
+};
 #[cfg(feature = "fs")]
 use {
     linera_base::{
@@ -57,7 +63,9 @@ use {
 use crate::{
     chain_listener::{self, ClientContext as _},
     client_options::{ChainOwnershipConfig, ClientContextOptions},
-    error, util,
+    error,
+    recovery::{RecoveryAction, RecoveryPlan},
+    util,
     wallet::{UserChain, Wallet},
     Error,
 };
@@ -69,6 +77,8 @@ pub struct ClientContext<Env: Environment, W> {
     pub recv_timeout: Duration,
     pub retry_delay: Duration,
     pub max_retries: u32,
+    /// Whether to recover from missing blobs and events when processing the inbox.
+    pub auto_recover: bool,
     pub chain_listeners: JoinSet,
     #[cfg(not(web))]
     pub client_metrics: Option<ClientMetrics>,
@@ -116,7 +126,8 @@ where
     }
 }
 
-impl<S, Si, W> ClientContext<linera_core::environment::Impl<S, NodeProvider, Si>, W>
+impl<S, Si, W>
+    ClientContext<linera_core::environment::Impl<S, RecordingNodeProvider<NodeProvider>, Si>, W>
 where
     S: linera_core::environment::Storage,
     Si: linera_core::environment::Signer,
@@ -131,6 +142,17 @@ where
             retry_delay: options.retry_delay,
             max_retries: options.max_retries,
         });
+        #[cfg(not(web))]
+        let recorder = options.record_conversations.as_deref().and_then(|path| {
+            ConversationRecorder::create(path)
+                .inspect_err(|error| {
+                    error!("Cannot record conversations to {}: {error}", path.display())
+                })
+                .ok()
+        });
+        #[cfg(web)]
+        let recorder = None;
+        let node_provider = RecordingNodeProvider::new(node_provider, recorder);
         let chain_ids = wallet.chain_ids();
         let name = match chain_ids.len() {
             0 => "Client node".to_string(),
@@ -149,7 +171,8 @@ where
             name,
             Duration::from_secs(30),
             options.to_chain_client_options(),
-        );
+        )
+        .with_trusted_checkpoint(wallet.trusted_checkpoint.clone());
 
         #[cfg(not(web))]
         let client_metrics = if timing_config.enabled {
@@ -165,6 +188,7 @@ where
             recv_timeout: options.recv_timeout,
             retry_delay: options.retry_delay,
             max_retries: options.max_retries,
+            auto_recover: options.auto_recover,
             chain_listeners: JoinSet::default(),
             #[cfg(not(web))]
             client_metrics,
@@ -194,7 +218,7 @@ where
         let client = Client::new(
             linera_core::environment::Impl {
                 storage,
-                network: NodeProvider::new(node_options),
+                network: RecordingNodeProvider::new(NodeProvider::new(node_options), None),
                 signer,
             },
             wallet.genesis_admin_chain(),
@@ -215,6 +239,7 @@ where
             recv_timeout: send_recv_timeout,
             retry_delay,
             max_retries,
+            auto_recover: false,
             chain_listeners: JoinSet::default(),
             client_metrics: None,
         }
@@ -331,7 +356,9 @@ impl<Env: Environment, W: Persist<Target = Wallet>> ClientContext<Env, W> {
         // Try processing the inbox optimistically without waiting for validator notifications.
         let (new_certificates, maybe_timeout) = {
             chain_client.synchronize_from_validators().await?;
-            let result = chain_client.process_inbox_without_prepare().await;
+            let result = self
+                .with_recovery(|| chain_client.process_inbox_without_prepare())
+                .await;
             self.update_wallet_from_client(chain_client).await?;
             if result.is_err() {
                 self.save_wallet().await?;
@@ -350,7 +377,7 @@ impl<Env: Environment, W: Persist<Target = Wallet>> ClientContext<Env, W> {
 
         loop {
             let (new_certificates, maybe_timeout) = {
-                let result = chain_client.process_inbox().await;
+                let result = self.with_recovery(|| chain_client.process_inbox()).await;
                 self.update_wallet_from_client(chain_client).await?;
                 if result.is_err() {
                     self.save_wallet().await?;
@@ -367,6 +394,133 @@ impl<Env: Environment, W: Persist<Target = Wallet>> ClientContext<Env, W> {
         }
     }
 
+    /// Runs `f`, and if it fails because blobs or events are missing and automatic recovery
+    /// is enabled, carries out the recovery plan and runs `f` once more.
+    async fn with_recovery<T, F, Fut>(&self, mut f: F) -> Result<T, ChainClientError>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<T, ChainClientError>>,
+    {
+        let error = match f().await {
+            Ok(value) => return Ok(value),
+            Err(error) if self.auto_recover => error,
+            Err(error) => return Err(error),
+        };
+        let plan = match self.plan_recovery(&error).await {
+            Ok(Some(plan)) => plan,
+            Ok(None) => return Err(error),
+            Err(recovery_error) => {
+                warn!("Failed to plan the recovery from \"{error}\": {recovery_error}");
+                return Err(error);
+            }
+        };
+        info!("Recovering from \"{error}\": {plan}");
+        if let Err(recovery_error) = self.recover(&plan).await {
+            warn!("{recovery_error}");
+            return Err(error);
+        }
+        f().await
+    }
+
+    /// Returns the steps to obtain the blobs and events that `error` reports as missing, or
+    /// `None` if it isn't about missing data.
+    pub async fn plan_recovery(
+        &self,
+        error: &ChainClientError,
+    ) -> Result<Option<RecoveryPlan>, Error> {
+        let Some(missing) = error.missing_data() else {
+            return Ok(None);
+        };
+        let storage = self.client.storage_client();
+        let mut local_blobs = Vec::new();
+        let mut publishers = BTreeMap::new();
+        for blob_id in &missing.blobs {
+            if storage.contains_blob(*blob_id).await? {
+                local_blobs.push(*blob_id);
+                continue;
+            }
+            let publisher = self
+                .client
+                .locate_blob(*blob_id)
+                .await?
+                .into_iter()
+                .find_map(|(_, provenance)| provenance.state);
+            if let Some(state) = publisher {
+                publishers.insert(*blob_id, (state.chain_id, state.block_height));
+            }
+        }
+        let plan = RecoveryPlan::new(&missing, &local_blobs, &publishers);
+        Ok((!plan.is_empty()).then_some(plan))
+    }
+
+    /// Carries out the steps of a recovery plan, in order.
+    pub async fn recover(&self, plan: &RecoveryPlan) -> Result<(), Error> {
+        let storage = self.client.storage_client();
+        for action in &plan.actions {
+            debug!("Recovery: {action}");
+            let failed = || error::Inner::RecoveryFailed(action.clone());
+            match action {
+                RecoveryAction::UploadBlob(blob_id) => {
+                    let blob = storage.read_blob(*blob_id).await?.ok_or_else(failed)?;
+                    let (_, committee) = self.client.admin_committee().await?;
+                    let nodes = self
+                        .client
+                        .validator_node_provider()
+                        .make_nodes(&committee)?
+                        .collect::<Vec<_>>();
+                    let results = future::join_all(
+                        nodes
+                            .iter()
+                            .map(|(_, node)| node.upload_blob(blob.content().clone())),
+                    )
+                    .await;
+                    for ((public_key, _), result) in nodes.iter().zip(results) {
+                        if let Err(error) = result {
+                            warn!("Failed to upload blob {blob_id} to {public_key}: {error}");
+                        }
+                    }
+                }
+                RecoveryAction::DownloadBlob(blob_id) => {
+                    let (_, committee) = self.client.admin_committee().await?;
+                    let validators = self
+                        .client
+                        .validator_node_provider()
+                        .make_nodes(&committee)?
+                        .map(|(public_key, node)| RemoteNode { public_key, node })
+                        .collect::<Vec<_>>();
+                    let timeout = self.client.options().blob_download_timeout;
+                    let blob = RemoteNode::download_blob(&validators, *blob_id, timeout)
+                        .await
+                        .ok_or_else(failed)?;
+                    storage.write_blob(&blob).await?;
+                }
+                RecoveryAction::SynchronizeChain { chain_id, height } => {
+                    let info = self
+                        .make_chain_client(*chain_id)
+                        .synchronize_chain_state(*chain_id)
+                        .await?;
+                    ensure!(info.next_block_height > *height, failed());
+                }
+                RecoveryAction::FetchEventStream {
+                    chain_id,
+                    stream_id,
+                    from_index,
+                } => {
+                    self.make_chain_client(*chain_id)
+                        .synchronize_chain_state(*chain_id)
+                        .await?;
+                    let event_id = EventId {
+                        chain_id: *chain_id,
+                        stream_id: stream_id.clone(),
+                        index: *from_index,
+                    };
+                    ensure!(storage.contains_event(event_id).await?, failed());
+                }
+            }
+        }
+        Ok(())
+    }
+
     pub async fn assign_new_chain_to_key(
         &mut self,
         chain_id: ChainId,
@@ -10,9 +10,11 @@ use linera_base::{
     time::Duration,
 };
 use linera_core::{
-    client::{BlanketMessagePolicy, ChainClientOptions, MessagePolicy},
+    client::{
+        reproposal::ReproposalConfig, BlanketMessagePolicy, ChainClientOptions, MessagePolicy,
+    },
     node::CrossChainMessageDelivery,
-    DEFAULT_GRACE_PERIOD,
+    HedgingConfig, DEFAULT_GRACE_PERIOD,
 };
 use linera_execution::ResourceControlPolicy;
 
@@ -118,6 +120,12 @@ pub struct ClientContextOptions {
     #[arg(long, default_value = "5")]
     pub timing_interval: u64,
 
+    /// Record all requests to validators and their responses to this file, e.g. to replay
+    /// them later with `linera replay-conversation`.
+    #[cfg(not(web))]
+    #[arg(long)]
+    pub record_conversations: Option<PathBuf>,
+
     /// An additional delay, after reaching a quorum, to wait for additional validator signatures,
     /// as a fraction of time taken to reach quorum.
     #[arg(long, default_value_t = DEFAULT_GRACE_PERIOD)]
@@ -130,6 +138,62 @@ pub struct ClientContextOptions {
         value_parser = util::parse_millis
     )]
     pub blob_download_timeout: Duration,
+
+    /// Hedge the requests to validators: first contact only the validators expected to be
+    /// the fastest ones making up a quorum, and the others after this delay in milliseconds,
+    /// or earlier if the first ones fail. By default, all validators are contacted at once.
+    #[arg(long = "hedging-delay-ms", value_parser = util::parse_millis)]
+    pub hedging_delay: Option<Duration>,
+
+    /// When hedging, the number of validators contacted right away in addition to the
+    /// fastest ones making up a quorum.
+    #[arg(long, default_value = "0")]
+    pub hedging_extra_validators: usize,
+
+    /// When hedging, wait at least this multiple of the expected latency of the slowest
+    /// validator contacted first before contacting the others, if that is longer than the
+    /// hedging delay.
+    #[arg(long, default_value = "2.0", value_parser = util::parse_non_negative_factor)]
+    pub hedging_latency_factor: f64,
+
+    /// When processing the inbox fails because blobs or events are missing, upload, download
+    /// or synchronize what is missing, and try again.
+    #[arg(long)]
+    pub auto_recover: bool,
+
+    /// The number of blocks before the latest one whose execution states can be queried, on
+    /// each chain executed locally. Zero disables historical queries.
+    #[arg(long, default_value = "0")]
+    pub execution_state_history: u64,
+
+    /// The maximum number of validators to download the certificates of a long chain from in
+    /// parallel.
+    #[arg(long, default_value = "4")]
+    pub max_parallel_certificate_downloads: usize,
+
+    /// When a block proposal is preempted by another block, e.g. of a faster round leader,
+    /// synchronize the chain and propose the operations again, up to this many times, unless
+    /// they were committed in the meantime. By default, the error is returned right away.
+    #[arg(long)]
+    pub max_reproposals: Option<u32>,
+
+    /// The delay before proposing operations again after a preemption, in milliseconds. It
+    /// doubles after each re-proposal.
+    #[arg(
+        long = "reproposal-delay-ms",
+        default_value = "100",
+        value_parser = util::parse_millis
+    )]
+    pub reproposal_delay: Duration,
+
+    /// The maximum delay before proposing operations again after a preemption, in
+    /// milliseconds.
+    #[arg(
+        long = "reproposal-max-delay-ms",
+        default_value = "5000",
+        value_parser = util::parse_millis
+    )]
+    pub reproposal_max_delay: Duration,
 }
 
 impl ClientContextOptions {
@@ -147,6 +211,18 @@ impl ClientContextOptions {
             cross_chain_message_delivery,
             grace_period: self.grace_period,
             blob_download_timeout: self.blob_download_timeout,
+            hedging: self.hedging_delay.map(|min_delay| HedgingConfig {
+                extra_validators: self.hedging_extra_validators,
+                min_delay,
+                latency_factor: self.hedging_latency_factor,
+            }),
+            execution_state_history: self.execution_state_history,
+            max_parallel_certificate_downloads: self.max_parallel_certificate_downloads,
+            reproposal: self.max_reproposals.map(|max_attempts| ReproposalConfig {
+                max_attempts,
+                initial_delay: self.reproposal_delay,
+                max_delay: self.reproposal_max_delay,
+            }),
         }
     }
 
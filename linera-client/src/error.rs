@@ -11,7 +11,7 @@ use thiserror_context::Context;
 
 #[cfg(feature = "benchmark")]
 use crate::benchmark::BenchmarkError;
-use crate::util;
+use crate::{recovery::RecoveryAction, util};
 
 #[derive(Debug, thiserror::Error)]
 #[non_exhaustive]
@@ -71,6 +71,8 @@ pub(crate) enum Inner {
         chain_id: ChainId,
         error: Box<NodeError>,
     },
+    #[error("failed to recover missing data: could not {0}")]
+    RecoveryFailed(RecoveryAction),
 }
 
 thiserror_context::impl_context!(Error(Inner));
@@ -7,6 +7,8 @@
 #![deny(clippy::large_futures)]
 #![allow(async_fn_in_trait)]
 
+#[cfg(all(feature = "backup", not(web)))]
+pub mod backup;
 pub mod chain_listener;
 pub mod client_context;
 #[cfg(not(web))]
@@ -14,6 +16,7 @@ pub mod client_metrics;
 pub mod client_options;
 pub mod config;
 mod error;
+pub mod recovery;
 pub mod util;
 pub mod wallet;
 
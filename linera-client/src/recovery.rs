@@ -0,0 +1,133 @@
+// Copyright (c) Zefchain Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! Planning how to recover from blobs and events that could not be found.
+//!
+//! An operation fails with `BlobsNotFound` or `EventsNotFound` when the local node or the
+//! validators lack some data it depends on. What to do about it depends on who has the data:
+//! a blob that the local node has must be uploaded to the validators, a blob that was
+//! published in a block is obtained by synchronizing the chain of that block, and events are
+//! obtained by synchronizing the chain that emitted them. A [`RecoveryPlan`] lists these
+//! steps, so that they can be reported to the user or carried out before retrying.
+
+use std::{collections::BTreeMap, fmt};
+
+use linera_base::{
+    data_types::BlockHeight,
+    identifiers::{BlobId, ChainId, StreamId},
+};
+use linera_core::client::MissingData;
+
+/// A step to obtain some data that an operation could not find.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum RecoveryAction {
+    /// Upload a blob that the local node has to the validators.
+    UploadBlob(BlobId),
+    /// Download a blob that was not published in any known block from the validators.
+    DownloadBlob(BlobId),
+    /// Synchronize a chain from the validators, at least up to the block at `height`.
+    SynchronizeChain {
+        chain_id: ChainId,
+        height: BlockHeight,
+    },
+    /// Synchronize the chain that emits a stream, until it has the events from `from_index`.
+    FetchEventStream {
+        chain_id: ChainId,
+        stream_id: StreamId,
+        from_index: u32,
+    },
+}
+
+impl fmt::Display for RecoveryAction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RecoveryAction::UploadBlob(blob_id) => write!(f, "upload blob {blob_id}"),
+            RecoveryAction::DownloadBlob(blob_id) => write!(f, "download blob {blob_id}"),
+            RecoveryAction::SynchronizeChain { chain_id, height } => {
+                write!(f, "synchronize chain {chain_id} up to height {height}")
+            }
+            RecoveryAction::FetchEventStream {
+                chain_id,
+                stream_id,
+                from_index,
+            } => write!(
+                f,
+                "fetch the events of stream {stream_id} on chain {chain_id} from index \
+                 {from_index}"
+            ),
+        }
+    }
+}
+
+/// The steps to obtain the data that an operation could not find, in the order in which they
+/// should be carried out.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct RecoveryPlan {
+    pub actions: Vec<RecoveryAction>,
+}
+
+impl RecoveryPlan {
+    /// Plans how to obtain the `missing` data.
+    ///
+    /// `local_blobs` are the missing blobs that the local node has nevertheless, i.e. the ones
+    /// that only the validators are missing. `publishers` contains the chain and height of the
+    /// latest block known to use each of the other blobs.
+    pub fn new(
+        missing: &MissingData,
+        local_blobs: &[BlobId],
+        publishers: &BTreeMap<BlobId, (ChainId, BlockHeight)>,
+    ) -> Self {
+        let mut chain_heights = BTreeMap::<ChainId, BlockHeight>::new();
+        let mut downloads = Vec::new();
+        let mut uploads = Vec::new();
+        for blob_id in &missing.blobs {
+            if local_blobs.contains(blob_id) {
+                uploads.push(RecoveryAction::UploadBlob(*blob_id));
+            } else if let Some((chain_id, height)) = publishers.get(blob_id) {
+                let known_height = chain_heights.entry(*chain_id).or_default();
+                *known_height = (*known_height).max(*height);
+            } else {
+                downloads.push(RecoveryAction::DownloadBlob(*blob_id));
+            }
+        }
+        let mut streams = BTreeMap::<(ChainId, StreamId), u32>::new();
+        for event_id in &missing.events {
+            let from_index = streams
+                .entry((event_id.chain_id, event_id.stream_id.clone()))
+                .or_insert(event_id.index);
+            *from_index = (*from_index).min(event_id.index);
+        }
+        let mut actions = chain_heights
+            .into_iter()
+            .map(|(chain_id, height)| RecoveryAction::SynchronizeChain { chain_id, height })
+            .collect::<Vec<_>>();
+        for ((chain_id, stream_id), from_index) in streams {
+            actions.push(RecoveryAction::FetchEventStream {
+                chain_id,
+                stream_id,
+                from_index,
+            });
+        }
+        actions.extend(downloads);
+        // Uploads come last: the other steps may be needed for the validators to accept them.
+        actions.extend(uploads);
+        RecoveryPlan { actions }
+    }
+
+    /// Returns whether there is nothing to do.
+    pub fn is_empty(&self) -> bool {
+        self.actions.is_empty()
+    }
+}
+
+impl fmt::Display for RecoveryPlan {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, action) in self.actions.iter().enumerate() {
+            if i > 0 {
+                write!(f, ", then ")?;
+            }
+            write!(f, "{action}")?;
+        }
+        Ok(())
+    }
+}
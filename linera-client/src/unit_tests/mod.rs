@@ -1,7 +1,10 @@
 // Copyright (c) Zefchain Labs, Inc.
 // SPDX-License-Identifier: Apache-2.0
 
+#[cfg(all(feature = "backup", not(web)))]
+mod backup;
 mod chain_listener;
+mod recovery;
 mod util;
 #[cfg(feature = "fs")]
 mod wallet;
@@ -0,0 +1,83 @@
+// Copyright (c) Zefchain Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+use std::collections::BTreeMap;
+
+use linera_base::{
+    crypto::CryptoHash,
+    data_types::BlockHeight,
+    identifiers::{BlobId, BlobType, ChainId, EventId, StreamId},
+};
+use linera_core::{
+    client::{ChainClientError, MissingData},
+    node::NodeError,
+};
+
+use crate::recovery::{RecoveryAction, RecoveryPlan};
+
+fn blob_id(name: &str) -> BlobId {
+    BlobId::new(CryptoHash::test_hash(name), BlobType::Data)
+}
+
+fn chain_id(name: &str) -> ChainId {
+    ChainId(CryptoHash::test_hash(name))
+}
+
+/// Checks that the missing data of an error is mapped to the steps that obtain it.
+#[test]
+fn test_recovery_plan() {
+    let stream_id = StreamId::system("events");
+    let event_id = |index| EventId {
+        chain_id: chain_id("publisher"),
+        stream_id: stream_id.clone(),
+        index,
+    };
+    let error = ChainClientError::RemoteNodeError(NodeError::BlobsNotFound(vec![
+        blob_id("local"),
+        blob_id("published1"),
+        blob_id("published2"),
+        blob_id("unknown"),
+    ]));
+    let missing = error.missing_data().unwrap();
+    let publishers = BTreeMap::from([
+        (
+            blob_id("published1"),
+            (chain_id("publisher"), BlockHeight(3)),
+        ),
+        (
+            blob_id("published2"),
+            (chain_id("publisher"), BlockHeight(5)),
+        ),
+    ]);
+    let plan = RecoveryPlan::new(&missing, &[blob_id("local")], &publishers);
+    assert_eq!(
+        plan.actions,
+        vec![
+            RecoveryAction::SynchronizeChain {
+                chain_id: chain_id("publisher"),
+                height: BlockHeight(5),
+            },
+            RecoveryAction::DownloadBlob(blob_id("unknown")),
+            RecoveryAction::UploadBlob(blob_id("local")),
+        ]
+    );
+
+    let error = ChainClientError::RemoteNodeError(NodeError::EventsNotFound(vec![
+        event_id(4),
+        event_id(2),
+    ]));
+    let missing = error.missing_data().unwrap();
+    let plan = RecoveryPlan::new(&missing, &[], &BTreeMap::new());
+    assert_eq!(
+        plan.actions,
+        vec![RecoveryAction::FetchEventStream {
+            chain_id: chain_id("publisher"),
+            stream_id,
+            from_index: 2,
+        }]
+    );
+
+    let error = ChainClientError::RemoteNodeError(NodeError::InactiveChain(chain_id("other")));
+    assert_eq!(error.missing_data(), None);
+    assert!(RecoveryPlan::new(&MissingData::default(), &[], &BTreeMap::new()).is_empty());
+}
@@ -25,6 +25,18 @@ pub fn parse_millis_delta(s: &str) -> Result<TimeDelta, ParseIntError> {
     Ok(TimeDelta::from_millis(s.parse()?))
 }
 
+/// Parses a finite, non-negative factor.
+pub fn parse_non_negative_factor(s: &str) -> Result<f64, String> {
+    let factor = s.parse::<f64>().map_err(|error| error.to_string())?;
+    if factor.is_finite() && factor >= 0.0 {
+        Ok(factor)
+    } else {
+        Err(format!(
+            "expected a finite, non-negative number, got {factor}"
+        ))
+    }
+}
+
 pub fn parse_chain_set(s: &str) -> Result<HashSet<ChainId>, CryptoError> {
     match s.trim() {
         "" => Ok(HashSet::new()),
@@ -40,11 +52,13 @@ pub fn parse_ascii_alphanumeric_string(s: &str) -> Result<String, &'static str>
     }
 }
 
-/// Returns after the specified time or if we receive a notification that a new round has started.
+/// Returns after the specified time or if we receive a notification that a new round has started
+/// or that a proposal we may be waiting for was discarded.
 pub async fn wait_for_next_round(stream: &mut NotificationStream, timeout: RoundTimeout) {
     let mut stream = stream.filter(|notification| match &notification.reason {
         Reason::NewBlock { height, .. } => *height >= timeout.next_block_height,
         Reason::NewRound { round, .. } => *round > timeout.current_round,
+        Reason::ProposalDiscarded { height, .. } => *height >= timeout.next_block_height,
         Reason::NewIncomingBundle { .. } => false,
     });
     future::select(
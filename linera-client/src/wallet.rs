@@ -9,7 +9,10 @@ use linera_base::{
     ensure,
     identifiers::{AccountOwner, ChainId},
 };
-use linera_core::{client::PendingProposal, data_types::ChainInfo};
+use linera_core::{
+    client::{checkpoint::TrustedCheckpoint, PendingProposal},
+    data_types::ChainInfo,
+};
 use serde::{Deserialize, Serialize};
 
 use crate::{config::GenesisConfig, error, Error};
@@ -19,6 +22,9 @@ pub struct Wallet {
     pub chains: BTreeMap<ChainId, UserChain>,
     pub default: Option<ChainId>,
     pub genesis_config: GenesisConfig,
+    /// The admin chain checkpoint used as the root of trust instead of genesis, if any.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub trusted_checkpoint: Option<TrustedCheckpoint>,
 }
 
 impl Extend<UserChain> for Wallet {
@@ -37,6 +43,7 @@ impl Wallet {
             chains: BTreeMap::new(),
             default: None,
             genesis_config,
+            trusted_checkpoint: None,
         }
     }
 
@@ -0,0 +1,496 @@
+// Copyright (c) Zefchain Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! An append-only, tamper-evident log of the state transitions of the chain workers.
+//!
+//! When an [`AuditLog`] is configured, the chain workers record every block proposal they
+//! accept, every vote they sign, every certificate they process and every cross-chain update
+//! they receive. Each entry contains the hash of the previous one, so removing, reordering or
+//! altering entries breaks the chain of hashes, which [`verify_audit_log`] detects.
+//!
+//! Entries are appended before the chain state they describe is saved, and a failure to
+//! append one aborts the request. The log may therefore contain transitions that failed to be
+//! saved afterwards, but a saved transition is never missing from it.
+//!
+//! If the log is opened with the validator's key, every record is signed, so that the log
+//! cannot be rewritten by someone who doesn't have that key, not even from the start.
+
+#[cfg(test)]
+#[path = "unit_tests/audit_log_tests.rs"]
+mod unit_tests;
+
+use std::sync::Mutex;
+
+use futures::future::{self, BoxFuture, FutureExt as _};
+use linera_base::{
+    crypto::{BcsSignable, CryptoHash, ValidatorPublicKey, ValidatorSecretKey, ValidatorSignature},
+    data_types::{BlockHeight, Round, Timestamp},
+    identifiers::{AccountOwner, ChainId},
+};
+use linera_chain::{
+    manager::{VoteKind, VoteRecord, Voter},
+    types::{CertificateKind, CertificateValue, GenericCertificate},
+};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+/// A state transition of a chain worker.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub enum AuditEvent {
+    /// A block proposal by `owner` was accepted and voted for.
+    ProposalAccepted {
+        owner: AccountOwner,
+        height: BlockHeight,
+        round: Round,
+        block_hash: CryptoHash,
+    },
+    /// The validator signed a vote.
+    Vote {
+        validator: ValidatorPublicKey,
+        kind: VoteKind,
+        height: BlockHeight,
+        round: Round,
+        value_hash: CryptoHash,
+    },
+    /// A certificate was processed.
+    Certificate {
+        kind: CertificateKind,
+        height: BlockHeight,
+        round: Round,
+        value_hash: CryptoHash,
+    },
+    /// The message bundles of the blocks at the given heights of the `origin` chain were
+    /// received.
+    CrossChainUpdate {
+        origin: ChainId,
+        heights: Vec<BlockHeight>,
+    },
+}
+
+impl AuditEvent {
+    /// Returns the event of processing the given certificate.
+    pub fn certificate<T: CertificateValue>(certificate: &GenericCertificate<T>) -> Self {
+        AuditEvent::Certificate {
+            kind: T::KIND,
+            height: certificate.inner().height(),
+            round: certificate.round,
+            value_hash: certificate.hash(),
+        }
+    }
+}
+
+impl From<&VoteRecord> for AuditEvent {
+    fn from(record: &VoteRecord) -> Self {
+        match record.voter {
+            Voter::Owner(owner) => AuditEvent::ProposalAccepted {
+                owner,
+                height: record.height,
+                round: record.round,
+                block_hash: record.value_hash,
+            },
+            Voter::Validator(validator) => AuditEvent::Vote {
+                validator,
+                kind: record.kind,
+                height: record.height,
+                round: record.round,
+                value_hash: record.value_hash,
+            },
+        }
+    }
+}
+
+/// A state transition, as it is hashed in the audit log.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct AuditRecord {
+    /// The position of the record in the log, starting at zero.
+    pub sequence: u64,
+    /// The hash of the previous record, or `None` for the first one.
+    pub previous_hash: Option<CryptoHash>,
+    /// The local time when the transition was recorded.
+    pub timestamp: Timestamp,
+    /// The chain whose state changed.
+    pub chain_id: ChainId,
+    pub event: AuditEvent,
+}
+
+impl BcsSignable<'_> for AuditRecord {}
+
+/// A record of the audit log, with its hash.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct AuditEntry {
+    pub record: AuditRecord,
+    pub hash: CryptoHash,
+    /// The validator's signature of the record, if the log is signed.
+    pub signature: Option<ValidatorSignature>,
+}
+
+impl AuditEntry {
+    fn new(record: AuditRecord, signer: Option<&ValidatorSecretKey>) -> Self {
+        let hash = CryptoHash::new(&record);
+        let signature = signer.map(|secret| ValidatorSignature::new(&record, secret));
+        AuditEntry {
+            record,
+            hash,
+            signature,
+        }
+    }
+
+    /// Checks that the hash matches the record and, if `signer` is given, that the record is
+    /// signed by it.
+    pub fn check(&self, signer: Option<ValidatorPublicKey>) -> Result<(), AuditError> {
+        let sequence = self.record.sequence;
+        if CryptoHash::new(&self.record) != self.hash {
+            return Err(AuditError::InvalidHash { sequence });
+        }
+        if let Some(public_key) = signer {
+            let signature = self
+                .signature
+                .ok_or(AuditError::InvalidSignature { sequence })?;
+            signature
+                .check(&self.record, public_key)
+                .map_err(|_| AuditError::InvalidSignature { sequence })?;
+        }
+        Ok(())
+    }
+}
+
+/// An error while writing or verifying the audit log.
+#[derive(Debug, Error)]
+pub enum AuditError {
+    #[error("I/O error in the audit log: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("malformed audit log entry: {0}")]
+    Json(#[from] serde_json::Error),
+    #[error("the hash of audit log entry {sequence} does not match its record")]
+    InvalidHash { sequence: u64 },
+    #[error("audit log entry {sequence} is not signed by the validator")]
+    InvalidSignature { sequence: u64 },
+    #[error("audit log entry {sequence} does not follow the previous one")]
+    Discontinuity { sequence: u64 },
+    #[error("the audit log {0} is already open in another process")]
+    InUse(std::path::PathBuf),
+    #[error("the audit log writer has stopped")]
+    WriterStopped,
+}
+
+/// Where the entries of an [`AuditLog`] are kept. Entries are only ever appended.
+pub trait AuditSink: Send + Sync {
+    /// Queues entries to be appended after all the ones queued before. The returned future
+    /// completes once they are persisted.
+    fn append(&self, entries: Vec<AuditEntry>) -> BoxFuture<'static, Result<(), AuditError>>;
+
+    /// Reads the last entry, if any.
+    fn read_last(&self) -> Result<Option<AuditEntry>, AuditError>;
+
+    /// Reads all the entries, in the order in which they were appended.
+    fn read_all(&self) -> Result<Vec<AuditEntry>, AuditError>;
+}
+
+/// An [`AuditSink`] keeping the entries in memory.
+#[derive(Debug, Default)]
+pub struct MemoryAuditSink(Mutex<Vec<AuditEntry>>);
+
+impl AuditSink for MemoryAuditSink {
+    fn append(&self, entries: Vec<AuditEntry>) -> BoxFuture<'static, Result<(), AuditError>> {
+        self.0
+            .lock()
+            .expect("Panics should not happen while holding a lock to the audit log")
+            .extend(entries);
+        future::ready(Ok(())).boxed()
+    }
+
+    fn read_last(&self) -> Result<Option<AuditEntry>, AuditError> {
+        Ok(self
+            .0
+            .lock()
+            .expect("Panics should not happen while holding a lock to the audit log")
+            .last()
+            .cloned())
+    }
+
+    fn read_all(&self) -> Result<Vec<AuditEntry>, AuditError> {
+        Ok(self
+            .0
+            .lock()
+            .expect("Panics should not happen while holding a lock to the audit log")
+            .clone())
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub use file::FileAuditSink;
+
+#[cfg(not(target_arch = "wasm32"))]
+mod file {
+    use std::{
+        fs::File,
+        io::{Read as _, Seek as _, SeekFrom, Write as _},
+        path::PathBuf,
+        sync::mpsc,
+        thread::JoinHandle,
+    };
+
+    use fs4::FileExt as _;
+    use futures::{
+        channel::oneshot,
+        future::{BoxFuture, FutureExt as _},
+    };
+
+    use super::{AuditEntry, AuditError, AuditSink};
+
+    /// The number of bytes read at a time when looking for the last entry from the end of the
+    /// file.
+    const TAIL_CHUNK_SIZE: u64 = 4096;
+
+    /// Lines to append to the file, and where to report once they are persisted.
+    struct WriteRequest {
+        lines: Vec<u8>,
+        done: oneshot::Sender<Result<(), std::io::Error>>,
+    }
+
+    /// An [`AuditSink`] appending the entries to a file, one JSON object per line.
+    ///
+    /// The file is written by a dedicated thread, so that the chain workers don't block on
+    /// the disk. The thread appends all the entries queued while it was busy at once, and
+    /// syncs them with a single `fsync`. An exclusive lock on the file makes sure that no
+    /// other process writes to it.
+    pub struct FileAuditSink {
+        path: PathBuf,
+        sender: Option<mpsc::Sender<WriteRequest>>,
+        writer: Option<JoinHandle<()>>,
+    }
+
+    impl FileAuditSink {
+        /// Creates a sink appending to the file at `path`, which is created if it doesn't
+        /// exist. Fails if the file is open in another process.
+        pub fn new(path: impl Into<PathBuf>) -> Result<Self, AuditError> {
+            let path = path.into();
+            let file = std::fs::OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(&path)?;
+            if file.try_lock_exclusive().is_err() {
+                return Err(AuditError::InUse(path));
+            }
+            let (sender, receiver) = mpsc::channel();
+            let writer = std::thread::Builder::new()
+                .name("audit-log-writer".to_string())
+                .spawn(move || write_requests(file, receiver))?;
+            Ok(FileAuditSink {
+                path,
+                sender: Some(sender),
+                writer: Some(writer),
+            })
+        }
+    }
+
+    /// Appends the lines of the requests to `file` until the sink is dropped. After a failed
+    /// write, the following entries would not be chained to a persisted one, so all the
+    /// remaining requests fail.
+    fn write_requests(mut file: File, receiver: mpsc::Receiver<WriteRequest>) {
+        let mut failure: Option<std::io::Error> = None;
+        while let Ok(request) = receiver.recv() {
+            let mut batch = vec![request];
+            batch.extend(receiver.try_iter());
+            if failure.is_none() {
+                let lines = batch
+                    .iter()
+                    .flat_map(|request| &request.lines)
+                    .copied()
+                    .collect::<Vec<_>>();
+                if let Err(error) = file.write_all(&lines).and_then(|()| file.sync_data()) {
+                    tracing::error!("Failed to append to the audit log: {error}");
+                    failure = Some(error);
+                }
+            }
+            for request in batch {
+                let result = match &failure {
+                    None => Ok(()),
+                    Some(error) => Err(std::io::Error::new(error.kind(), error.to_string())),
+                };
+                // The chain worker may have given up waiting.
+                let _ = request.done.send(result);
+            }
+        }
+    }
+
+    impl Drop for FileAuditSink {
+        fn drop(&mut self) {
+            // Closing the channel stops the writer once it has written the queued entries.
+            self.sender.take();
+            if let Some(writer) = self.writer.take() {
+                if writer.join().is_err() {
+                    tracing::error!("The audit log writer panicked");
+                }
+            }
+        }
+    }
+
+    impl AuditSink for FileAuditSink {
+        fn append(&self, entries: Vec<AuditEntry>) -> BoxFuture<'static, Result<(), AuditError>> {
+            let mut lines = Vec::new();
+            for entry in &entries {
+                if let Err(error) = serde_json::to_writer(&mut lines, entry) {
+                    return futures::future::ready(Err(error.into())).boxed();
+                }
+                lines.push(b'\n');
+            }
+            let (done, persisted) = oneshot::channel();
+            let sender = self
+                .sender
+                .as_ref()
+                .expect("the sender is only taken on drop");
+            let queued = sender.send(WriteRequest { lines, done }).is_ok();
+            async move {
+                if !queued {
+                    return Err(AuditError::WriterStopped);
+                }
+                persisted.await.map_err(|_| AuditError::WriterStopped)??;
+                Ok(())
+            }
+            .boxed()
+        }
+
+        fn read_last(&self) -> Result<Option<AuditEntry>, AuditError> {
+            let mut file = match File::open(&self.path) {
+                Ok(file) => file,
+                Err(error) if error.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+                Err(error) => return Err(error.into()),
+            };
+            let mut position = file.metadata()?.len();
+            let mut tail = Vec::new();
+            loop {
+                let chunk_size = TAIL_CHUNK_SIZE.min(position);
+                position -= chunk_size;
+                let mut chunk = vec![0; chunk_size as usize];
+                file.seek(SeekFrom::Start(position))?;
+                file.read_exact(&mut chunk)?;
+                chunk.extend_from_slice(&tail);
+                tail = chunk;
+                let line = tail.strip_suffix(b"\n").unwrap_or(&tail);
+                if let Some(start) = line.iter().rposition(|byte| *byte == b'\n') {
+                    return Ok(Some(serde_json::from_slice(&line[start + 1..])?));
+                }
+                if position == 0 {
+                    if line.is_empty() {
+                        return Ok(None);
+                    }
+                    return Ok(Some(serde_json::from_slice(line)?));
+                }
+            }
+        }
+
+        fn read_all(&self) -> Result<Vec<AuditEntry>, AuditError> {
+            let contents = match std::fs::read_to_string(&self.path) {
+                Ok(contents) => contents,
+                Err(error) if error.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+                Err(error) => return Err(error.into()),
+            };
+            contents
+                .lines()
+                .map(|line| Ok(serde_json::from_str(line)?))
+                .collect()
+        }
+    }
+}
+
+/// The audit log shared by the chain workers.
+pub struct AuditLog {
+    sink: Box<dyn AuditSink>,
+    /// The key signing the records, if any.
+    signer: Option<ValidatorSecretKey>,
+    /// The sequence number and the hash of the last entry, if any.
+    last: Mutex<Option<(u64, CryptoHash)>>,
+}
+
+impl AuditLog {
+    /// Opens the audit log kept in `sink`. New entries are chained to the last one, which is
+    /// checked, and signed with `signer` if it is given. Use [`AuditLog::verify`] to check
+    /// the whole log.
+    pub fn new(
+        sink: impl AuditSink + 'static,
+        signer: Option<ValidatorSecretKey>,
+    ) -> Result<Self, AuditError> {
+        let last = sink.read_last()?;
+        if let Some(entry) = &last {
+            entry.check(signer.as_ref().map(ValidatorSecretKey::public))?;
+        }
+        let last = last.map(|entry| (entry.record.sequence, entry.hash));
+        Ok(AuditLog {
+            sink: Box::new(sink),
+            signer,
+            last: Mutex::new(last),
+        })
+    }
+
+    /// Appends state transitions of the chain `chain_id` to the log, and waits until they
+    /// are persisted.
+    pub async fn record(
+        &self,
+        chain_id: ChainId,
+        timestamp: Timestamp,
+        events: impl IntoIterator<Item = AuditEvent>,
+    ) -> Result<(), AuditError> {
+        let persisted = {
+            // Entries are queued in the sink while holding the lock, so that they are
+            // appended in the order in which they are chained.
+            let mut last = self
+                .last
+                .lock()
+                .expect("Panics should not happen while holding a lock to the audit log");
+            let entries = events
+                .into_iter()
+                .map(|event| {
+                    let entry = AuditEntry::new(
+                        AuditRecord {
+                            sequence: last.map_or(0, |(sequence, _)| sequence + 1),
+                            previous_hash: last.map(|(_, hash)| hash),
+                            timestamp,
+                            chain_id,
+                            event,
+                        },
+                        self.signer.as_ref(),
+                    );
+                    *last = Some((entry.record.sequence, entry.hash));
+                    entry
+                })
+                .collect::<Vec<_>>();
+            if entries.is_empty() {
+                return Ok(());
+            }
+            self.sink.append(entries)
+        };
+        persisted.await
+    }
+
+    /// Reads the whole log and checks its continuity and signatures.
+    pub fn verify(&self) -> Result<Vec<AuditEntry>, AuditError> {
+        let entries = self.sink.read_all()?;
+        verify_audit_log(
+            &entries,
+            self.signer.as_ref().map(ValidatorSecretKey::public),
+        )?;
+        Ok(entries)
+    }
+}
+
+/// Checks that each entry's hash matches its record, that each record follows the
+/// previous one, from the start of the log, and that each record is signed by `signer`, if
+/// it is given.
+pub fn verify_audit_log(
+    entries: &[AuditEntry],
+    signer: Option<ValidatorPublicKey>,
+) -> Result<(), AuditError> {
+    let mut previous = None;
+    for entry in entries {
+        entry.check(signer)?;
+        let sequence = entry.record.sequence;
+        let expected_sequence = previous.map_or(0, |(sequence, _)| sequence + 1);
+        let expected_hash = previous.map(|(_, hash)| hash);
+        if sequence != expected_sequence || entry.record.previous_hash != expected_hash {
+            return Err(AuditError::Discontinuity { sequence });
+        }
+        previous = Some((sequence, entry.hash));
+    }
+    Ok(())
+}
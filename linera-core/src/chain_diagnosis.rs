@@ -0,0 +1,228 @@
+// Copyright (c) Zefchain Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! A report on what a chain is waiting for, to find out why it isn't advancing.
+
+use linera_base::{
+    data_types::{BlockHeight, Round, TimeDelta, Timestamp},
+    identifiers::{AccountOwner, BlobId, ChainId},
+};
+use linera_chain::{
+    manager::{ChainManagerInfo, ManagerEntry},
+    ChainError, ChainStateView,
+};
+use linera_execution::ExecutionRuntimeContext;
+use linera_views::context::Context;
+use serde::{Deserialize, Serialize};
+
+/// The entries held by a chain manager in one round.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct RoundEntries {
+    pub round: Round,
+    pub entries: Vec<ManagerEntry>,
+}
+
+/// An unskippable bundle in the inbox of a chain, which the next block has to include.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct PendingUnskippableBundle {
+    /// The chain that sent the bundle.
+    pub origin: ChainId,
+    /// The height of the sender's block with the bundle.
+    pub height: BlockHeight,
+    /// How long ago the bundle was added to the inbox.
+    pub age: TimeDelta,
+}
+
+/// The oldest block of a chain with messages that a recipient hasn't acknowledged yet.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct UndeliveredOutbox {
+    /// The recipient of the messages.
+    pub recipient: ChainId,
+    /// The height of the oldest block with unacknowledged messages to the recipient.
+    pub oldest_height: BlockHeight,
+    /// The number of blocks with unacknowledged messages to the recipient.
+    pub num_heights: usize,
+}
+
+/// What the next block of a chain is waiting for.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[cfg_attr(with_testing, derive(Eq, PartialEq))]
+pub enum BlockingCondition {
+    /// The chain is not active on this validator yet.
+    Inactive,
+    /// A block can't be handled before these blobs are uploaded.
+    MissingBlobs(Vec<BlobId>),
+    /// A validated block is waiting for a quorum of votes to confirm it.
+    AwaitingConfirmation { round: Round },
+    /// A proposal is waiting for a quorum of votes to validate it.
+    AwaitingValidation { round: Round, owner: AccountOwner },
+    /// The round is over, and a quorum of timeout votes is needed to start the next one.
+    AwaitingTimeoutCertificate { round: Round, deadline: Timestamp },
+    /// An unskippable bundle has been waiting longer than the fallback duration, and a quorum
+    /// of validators has to vote for the fallback mode.
+    AwaitingFallback { origin: ChainId, age: TimeDelta },
+    /// There is work for the chain, but nobody has proposed a block in the current round yet.
+    AwaitingProposal {
+        round: Round,
+        /// The owner allowed to propose, if the round has a single leader.
+        leader: Option<AccountOwner>,
+        deadline: Option<Timestamp>,
+    },
+    /// There are no proposals and no incoming messages: nothing needs to be done.
+    Idle,
+}
+
+/// The state of a chain relevant to its progress, as seen by one validator.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ChainDiagnosis {
+    pub chain_id: ChainId,
+    pub next_block_height: BlockHeight,
+    /// The validator's local time when the diagnosis was made.
+    pub local_time: Timestamp,
+    pub current_round: Round,
+    /// The owner allowed to propose in the current round, if it has a single leader.
+    pub leader: Option<AccountOwner>,
+    /// The time after which the current round may time out.
+    pub round_timeout: Option<Timestamp>,
+    /// The proposals, votes and certificates held by the chain manager, by round.
+    pub manager_entries: Vec<RoundEntries>,
+    /// The blobs missing for the pending validated block or the pending proposals.
+    pub missing_blobs: Vec<BlobId>,
+    /// The unskippable bundles in the inboxes, oldest first.
+    pub unskippable_bundles: Vec<PendingUnskippableBundle>,
+    /// The outboxes with messages not yet acknowledged by their recipient.
+    pub undelivered_outboxes: Vec<UndeliveredOutbox>,
+    /// The chains that sent bundles not yet included in a block.
+    pub nonempty_inboxes: Vec<ChainId>,
+    /// What the next block is waiting for.
+    pub blocking_condition: BlockingCondition,
+}
+
+impl ChainDiagnosis {
+    /// Inspects the chain state at the given local time.
+    pub async fn new<C>(
+        chain: &ChainStateView<C>,
+        local_time: Timestamp,
+    ) -> Result<Self, ChainError>
+    where
+        C: Context + Clone + Send + Sync + 'static,
+        C::Extra: ExecutionRuntimeContext,
+    {
+        let manager = &chain.manager;
+        let manager_info = ChainManagerInfo::from(manager);
+        let manager_entries = manager
+            .entries_by_round()
+            .into_iter()
+            .map(|(round, entries)| RoundEntries { round, entries })
+            .collect();
+
+        let mut missing_blobs = Vec::new();
+        let mut pending_blobs = vec![&chain.pending_validated_blobs];
+        let proposed_blobs = chain.pending_proposed_blobs.try_load_all_entries().await?;
+        pending_blobs.extend(proposed_blobs.iter().map(|(_, blobs)| &**blobs));
+        for blobs in pending_blobs {
+            for (blob_id, blob) in blobs.pending_blobs.index_values().await? {
+                if blob.is_none() && !missing_blobs.contains(&blob_id) {
+                    missing_blobs.push(blob_id);
+                }
+            }
+        }
+
+        let mut unskippable_bundles = Vec::new();
+        for bundle in chain.unskippable_bundles.elements().await? {
+            if chain
+                .removed_unskippable_bundles
+                .contains(&bundle.entry)
+                .await?
+            {
+                continue;
+            }
+            unskippable_bundles.push(PendingUnskippableBundle {
+                origin: bundle.entry.origin,
+                height: bundle.entry.cursor.height(),
+                age: local_time.delta_since(bundle.seen),
+            });
+        }
+
+        let recipients = chain.nonempty_outbox_chain_ids();
+        let outboxes = chain.load_outboxes(&recipients).await?;
+        let undelivered_outboxes = recipients
+            .into_iter()
+            .zip(outboxes)
+            .filter_map(|(recipient, outbox)| {
+                Some(UndeliveredOutbox {
+                    recipient,
+                    oldest_height: *outbox.queue.front()?,
+                    num_heights: outbox.queue.count(),
+                })
+            })
+            .collect();
+
+        let mut diagnosis = ChainDiagnosis {
+            chain_id: chain.chain_id(),
+            next_block_height: chain.tip_state.get().next_block_height,
+            local_time,
+            current_round: manager_info.current_round,
+            leader: manager_info.leader,
+            round_timeout: manager_info.round_timeout,
+            manager_entries,
+            missing_blobs,
+            unskippable_bundles,
+            undelivered_outboxes,
+            nonempty_inboxes: chain.nonempty_inbox_origins().await?.into_iter().collect(),
+            blocking_condition: BlockingCondition::Idle,
+        };
+        diagnosis.blocking_condition = diagnosis.blocking_condition(chain);
+        Ok(diagnosis)
+    }
+
+    /// Returns the first condition, in the order they have to be resolved, that keeps the
+    /// chain from advancing.
+    fn blocking_condition<C>(&self, chain: &ChainStateView<C>) -> BlockingCondition
+    where
+        C: Context + Clone + Send + Sync + 'static,
+        C::Extra: ExecutionRuntimeContext,
+    {
+        if !chain.is_active() {
+            return BlockingCondition::Inactive;
+        }
+        if !self.missing_blobs.is_empty() {
+            return BlockingCondition::MissingBlobs(self.missing_blobs.clone());
+        }
+        let round = self.current_round;
+        let locking_block = chain.manager.locking_block.get().as_ref();
+        if locking_block.is_some_and(|block| block.round() == round) {
+            return BlockingCondition::AwaitingConfirmation { round };
+        }
+        if let Some(proposal) = chain.manager.proposed.get() {
+            if proposal.content.round == round {
+                return BlockingCondition::AwaitingValidation {
+                    round,
+                    owner: proposal.owner(),
+                };
+            }
+        }
+        if let Some(deadline) = self.round_timeout {
+            if deadline <= self.local_time {
+                return BlockingCondition::AwaitingTimeoutCertificate { round, deadline };
+            }
+        }
+        let fallback_duration = chain.ownership().timeout_config.fallback_duration;
+        if let Some(bundle) = self.unskippable_bundles.first() {
+            if !matches!(round, Round::Validator(_)) && bundle.age >= fallback_duration {
+                return BlockingCondition::AwaitingFallback {
+                    origin: bundle.origin,
+                    age: bundle.age,
+                };
+            }
+        }
+        if locking_block.is_some() || !self.nonempty_inboxes.is_empty() {
+            return BlockingCondition::AwaitingProposal {
+                round,
+                leader: self.leader,
+                deadline: self.round_timeout,
+            };
+        }
+        BlockingCondition::Idle
+    }
+}
@@ -4,7 +4,7 @@
 //! An actor that runs a chain worker.
 
 use std::{
-    collections::{BTreeMap, HashSet},
+    collections::{BTreeMap, BTreeSet, HashSet},
     fmt,
     sync::{self, Arc, RwLock},
 };
@@ -32,7 +32,9 @@ use tracing::{debug, instrument, trace, warn, Instrument as _};
 
 use super::{config::ChainWorkerConfig, state::ChainWorkerState, DeliveryNotifier};
 use crate::{
+    committee_cache::CommitteeCache,
     data_types::{ChainInfoQuery, ChainInfoResponse},
+    service_runtime_pool::QueryPermit,
     value_cache::ValueCache,
     worker::{NetworkActions, WorkerError},
 };
@@ -61,6 +63,8 @@ where
     /// Query an application's state.
     QueryApplication {
         query: Query,
+        /// The admission of the query by the service runtime pool, if there is one.
+        permit: Option<QueryPermit>,
         #[debug(skip)]
         callback: oneshot::Sender<Result<QueryOutcome, WorkerError>>,
     },
@@ -81,6 +85,14 @@ where
         callback: oneshot::Sender<Result<(Block, ChainInfoResponse), WorkerError>>,
     },
 
+    /// Execute a block on a fork of the chain state, without touching the chain itself.
+    SimulateBlockExecution {
+        block: ProposedBlock,
+        published_blobs: Vec<Blob>,
+        #[debug(skip)]
+        callback: oneshot::Sender<Result<(Block, ChainInfoResponse), WorkerError>>,
+    },
+
     /// Process a leader timeout issued for this multi-owner chain.
     ProcessTimeout {
         certificate: TimeoutCertificate,
@@ -119,10 +131,10 @@ where
         callback: oneshot::Sender<Result<Option<BlockHeight>, WorkerError>>,
     },
 
-    /// Handle cross-chain request to confirm that the recipient was updated.
-    ConfirmUpdatedRecipient {
-        recipient: ChainId,
-        latest_height: BlockHeight,
+    /// Handle cross-chain requests to confirm that the recipients were updated up to the
+    /// given heights.
+    ConfirmUpdatedRecipients {
+        confirmations: BTreeMap<ChainId, BlockHeight>,
         #[debug(skip)]
         callback: oneshot::Sender<Result<(), WorkerError>>,
     },
@@ -153,6 +165,26 @@ where
         new_trackers: BTreeMap<ValidatorPublicKey, u64>,
         callback: oneshot::Sender<Result<(), WorkerError>>,
     },
+
+    /// Prune the stale proposals and votes of the chain manager.
+    PruneStaleManagerEntries {
+        #[debug(skip)]
+        callback: oneshot::Sender<Result<usize, WorkerError>>,
+    },
+
+    /// Synchronize the chain only partially, keeping the events of the given applications.
+    SetApplicationFilter {
+        applications: BTreeSet<ApplicationId>,
+        #[debug(skip)]
+        callback: oneshot::Sender<Result<(), WorkerError>>,
+    },
+
+    /// Move the chain to a newer epoch without producing a block.
+    MigrateToEpoch {
+        epoch: Epoch,
+        #[debug(skip)]
+        callback: oneshot::Sender<Result<bool, WorkerError>>,
+    },
 }
 
 /// The actor worker type.
@@ -165,6 +197,8 @@ where
     storage: StorageClient,
     block_values: Arc<ValueCache<CryptoHash, Hashed<Block>>>,
     execution_state_cache: Arc<ValueCache<CryptoHash, ExecutionStateView<StorageClient::Context>>>,
+    read_sets: Arc<ValueCache<CryptoHash, Vec<Vec<u8>>>>,
+    committees: Arc<CommitteeCache>,
     tracked_chains: Option<Arc<sync::RwLock<HashSet<ChainId>>>>,
     delivery_notifier: DeliveryNotifier,
 }
@@ -186,6 +220,8 @@ where
         execution_state_cache: Arc<
             ValueCache<CryptoHash, ExecutionStateView<StorageClient::Context>>,
         >,
+        read_sets: Arc<ValueCache<CryptoHash, Vec<Vec<u8>>>>,
+        committees: Arc<CommitteeCache>,
         tracked_chains: Option<Arc<RwLock<HashSet<ChainId>>>>,
         delivery_notifier: DeliveryNotifier,
         chain_id: ChainId,
@@ -200,6 +236,8 @@ where
                 storage.clone(),
                 block_cache.clone(),
                 execution_state_cache.clone(),
+                read_sets.clone(),
+                committees.clone(),
                 tracked_chains.clone(),
                 delivery_notifier.clone(),
                 chain_id,
@@ -223,6 +261,7 @@ where
 
     /// Creates a [`ChainWorkerActor`], loading it with the chain state for the requested
     /// [`ChainId`].
+    #[expect(clippy::too_many_arguments)]
     pub async fn load(
         config: ChainWorkerConfig,
         storage: StorageClient,
@@ -230,6 +269,8 @@ where
         execution_state_cache: Arc<
             ValueCache<CryptoHash, ExecutionStateView<StorageClient::Context>>,
         >,
+        read_sets: Arc<ValueCache<CryptoHash, Vec<Vec<u8>>>>,
+        committees: Arc<CommitteeCache>,
         tracked_chains: Option<Arc<RwLock<HashSet<ChainId>>>>,
         delivery_notifier: DeliveryNotifier,
         chain_id: ChainId,
@@ -239,6 +280,8 @@ where
             storage,
             block_values,
             execution_state_cache,
+            read_sets,
+            committees,
             tracked_chains,
             delivery_notifier,
             chain_id,
@@ -297,8 +340,17 @@ where
         trace!("Starting `ChainWorkerActor`");
 
         while let Some((request, span)) = incoming_requests.recv().await {
+            let mut long_lived_services = self.config.long_lived_services;
+            let mut memory_reservation = None;
+            if let Some(pool) = self.config.service_runtime_pool.as_ref() {
+                if long_lived_services {
+                    memory_reservation = pool.try_reserve_long_lived_runtime();
+                    // Without enough memory for a long-lived runtime, each query spawns its own.
+                    long_lived_services = memory_reservation.is_some();
+                }
+            }
             let (service_runtime_thread, service_runtime_endpoint) = {
-                if self.config.long_lived_services {
+                if long_lived_services {
                     let (thread, endpoint) = Self::spawn_service_runtime_actor(self.chain_id).await;
                     (Some(thread), Some(endpoint))
                 } else {
@@ -311,6 +363,8 @@ where
                 self.storage.clone(),
                 self.block_values.clone(),
                 self.execution_state_cache.clone(),
+                self.read_sets.clone(),
+                self.committees.clone(),
                 self.tracked_chains.clone(),
                 self.delivery_notifier.clone(),
                 self.chain_id,
@@ -321,6 +375,11 @@ where
             Box::pin(worker.handle_request(request).instrument(span)).await;
 
             loop {
+                // The confirmed blocks of a synchronization arrive together: their events
+                // are buffered until there are no more of them.
+                if incoming_requests.is_empty() {
+                    worker.flush_events().await;
+                }
                 futures::select! {
                     () = self.sleep_until_timeout().fuse() => break,
                     maybe_request = incoming_requests.recv().fuse() => {
@@ -337,6 +396,7 @@ where
             if let Some(thread) = service_runtime_thread {
                 thread.join().await
             }
+            drop(memory_reservation);
         }
 
         trace!("`ChainWorkerActor` finished");
@@ -367,6 +427,9 @@ where
             ChainWorkerRequest::StageBlockExecution { callback, .. } => {
                 callback.send(Err(error)).is_ok()
             }
+            ChainWorkerRequest::SimulateBlockExecution { callback, .. } => {
+                callback.send(Err(error)).is_ok()
+            }
             ChainWorkerRequest::ProcessTimeout { callback, .. } => {
                 callback.send(Err(error)).is_ok()
             }
@@ -382,7 +445,7 @@ where
             ChainWorkerRequest::ProcessCrossChainUpdate { callback, .. } => {
                 callback.send(Err(error)).is_ok()
             }
-            ChainWorkerRequest::ConfirmUpdatedRecipient { callback, .. } => {
+            ChainWorkerRequest::ConfirmUpdatedRecipients { callback, .. } => {
                 callback.send(Err(error)).is_ok()
             }
             ChainWorkerRequest::HandleChainInfoQuery { callback, .. } => {
@@ -397,6 +460,15 @@ where
             ChainWorkerRequest::UpdateReceivedCertificateTrackers { callback, .. } => {
                 callback.send(Err(error)).is_ok()
             }
+            ChainWorkerRequest::PruneStaleManagerEntries { callback } => {
+                callback.send(Err(error)).is_ok()
+            }
+            ChainWorkerRequest::SetApplicationFilter { callback, .. } => {
+                callback.send(Err(error)).is_ok()
+            }
+            ChainWorkerRequest::MigrateToEpoch { callback, .. } => {
+                callback.send(Err(error)).is_ok()
+            }
         };
 
         if !responded {
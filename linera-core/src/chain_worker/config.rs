@@ -3,9 +3,14 @@
 
 //! Configuration parameters for the chain worker.
 
-use std::sync::Arc;
+use std::{num::NonZeroU32, sync::Arc};
 
-use linera_base::{crypto::ValidatorSecretKey, time::Duration};
+use linera_base::{crypto::ValidatorSecretKey, data_types::TimeDelta, time::Duration};
+use linera_chain::manager::Pacemaker;
+
+use crate::{
+    audit_log::AuditLog, service_runtime_pool::ServiceRuntimePool, signing_fence::SigningFence,
+};
 
 /// Configuration parameters for the [`ChainWorkerState`][`super::state::ChainWorkerState`].
 #[derive(Clone, Default)]
@@ -24,6 +29,40 @@ pub struct ChainWorkerConfig {
     pub grace_period: Duration,
     /// Idle chain workers free their memory after that duration without requests.
     pub ttl: Duration,
+    /// Whether the worker only verifies proposals and certificates and tracks the chain
+    /// states, without ever signing votes, e.g. in a public RPC gateway.
+    pub validation_only: bool,
+    /// Whether stale proposals and votes are pruned from the chain manager whenever a chain
+    /// is saved.
+    pub prune_manager_on_save: bool,
+    /// Proposals whose block is older than this are pruned from the chain manager, even in
+    /// the current round.
+    pub max_proposal_age: Option<Duration>,
+    /// Proposals are discarded from the chain manager once the current round is that many
+    /// rounds after theirs, and a notification is sent about them.
+    pub proposal_expiry_rounds: Option<NonZeroU32>,
+    /// The maximal number of execution state deltas of validated blocks persisted in storage,
+    /// to apply them instead of executing the blocks again when they are confirmed. Zero
+    /// disables this.
+    pub max_execution_state_deltas: usize,
+    /// The number of blocks before the latest one whose execution states can be queried.
+    /// The changes needed to revert each block are persisted, and removed once they are
+    /// that old. Zero disables this.
+    pub execution_state_history: u64,
+    /// The maximal number of events of confirmed blocks that are buffered and written to
+    /// storage together while the chain catches up. Zero writes the events of each block
+    /// right away.
+    pub max_buffered_events: usize,
+    /// Supplies round deadlines and leaders to the chain managers instead of their own
+    /// schedule, if set.
+    pub pacemaker: Option<Arc<dyn Pacemaker>>,
+    /// If set, the worker is one instance of an active/passive pair, and only signs votes
+    /// and saves chains while this fence says it is the primary.
+    pub signing_fence: Option<SigningFence>,
+    /// Limits the queries and long-lived service runtimes of all chain workers, if set.
+    pub service_runtime_pool: Option<Arc<ServiceRuntimePool>>,
+    /// Records the state transitions of all chain workers, if set.
+    pub audit_log: Option<Arc<AuditLog>>,
 }
 
 impl ChainWorkerConfig {
@@ -40,8 +79,22 @@ impl ChainWorkerConfig {
         self
     }
 
-    /// Gets a reference to the [`ValidatorSecretKey`], if available.
+    /// Gets a reference to the [`ValidatorSecretKey`], if available and if the worker is
+    /// allowed to vote.
     pub fn key_pair(&self) -> Option<&ValidatorSecretKey> {
+        if self.validation_only
+            || self
+                .signing_fence
+                .as_ref()
+                .is_some_and(|fence| !fence.is_primary())
+        {
+            return None;
+        }
         self.key_pair.as_ref().map(Arc::as_ref)
     }
+
+    /// Gets the maximum age of the proposals kept by the chain manager, if any.
+    pub fn max_proposal_age(&self) -> Option<TimeDelta> {
+        self.max_proposal_age.map(TimeDelta::from_duration)
+    }
 }
@@ -10,7 +10,7 @@ mod state;
 
 pub(super) use self::delivery_notifier::DeliveryNotifier;
 #[cfg(test)]
-pub(crate) use self::state::CrossChainUpdateHelper;
+pub(crate) use self::state::{check_certificate_with_weight_updates, CrossChainUpdateHelper};
 pub(crate) use self::{
     actor::{ChainWorkerActor, ChainWorkerRequest},
     config::ChainWorkerConfig,
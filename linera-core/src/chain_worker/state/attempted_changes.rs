@@ -3,34 +3,47 @@
 
 //! Operations that persist changes to the chain state when they are successful.
 
-use std::{borrow::Cow, collections::BTreeMap};
+use std::{
+    borrow::Cow,
+    collections::{BTreeMap, BTreeSet},
+    sync::Arc,
+};
 
 use futures::future::Either;
 use linera_base::{
-    crypto::ValidatorPublicKey,
-    data_types::{Blob, BlockHeight, Epoch, Timestamp},
+    crypto::{CryptoHash, ValidatorPublicKey},
+    data_types::{Blob, BlockHeight, Epoch, Event, Timestamp},
     ensure,
-    identifiers::{ChainId, EventId, StreamId},
+    identifiers::{ApplicationId, ChainId, EventId, GenericApplicationId, StreamId},
 };
 use linera_chain::{
     data_types::{
         BlockExecutionOutcome, BlockProposal, MessageBundle, OriginalProposal, ProposalContent,
     },
-    manager,
+    manager::{self, VoteRecord},
     types::{ConfirmedBlockCertificate, TimeoutCertificate, ValidatedBlockCertificate},
     ChainExecutionContext, ChainStateView, ExecutionResultExt as _,
 };
-use linera_execution::{committee::Committee, system::EPOCH_STREAM_NAME};
-use linera_storage::{Clock as _, Storage};
+use linera_execution::{
+    committee::Committee,
+    system::{
+        ValidatorWeightUpdate, EPOCH_STREAM_NAME, REMOVED_EPOCH_STREAM_NAME,
+        VALIDATOR_WEIGHTS_STREAM_NAME,
+    },
+    ExecutionStateView,
+};
+use linera_storage::{Clock as _, EpochCommittee, Storage};
 use linera_views::{
-    context::Context,
-    views::{RootView, View},
+    batch::Batch, context::Context, store::WritableKeyValueStore as _, views::View, ViewError,
 };
 use tokio::sync::oneshot;
 use tracing::{debug, instrument, trace, warn};
 
-use super::{check_block_epoch, ChainWorkerConfig, ChainWorkerState};
+use super::{
+    check_block_epoch, check_certificate_with_weight_updates, ChainWorkerConfig, ChainWorkerState,
+};
 use crate::{
+    audit_log::AuditEvent,
     data_types::ChainInfoResponse,
     worker::{NetworkActions, Notification, Reason, WorkerError},
 };
@@ -57,10 +70,24 @@ where
             "`ChainStateView` has unexpected leftover changes"
         );
 
-        ChainWorkerStateWithAttemptedChanges {
+        let mut this = ChainWorkerStateWithAttemptedChanges {
             state,
             succeeded: false,
+        };
+        if this
+            .state
+            .chain
+            .unsaved_execution_state_undo
+            .get()
+            .is_some()
+        {
+            // A block failed after its persisted execution state delta was written.
+            if let Err(error) = this.revert_unsaved_execution_state_delta().await {
+                warn!(%error, "Failed to revert an unsaved execution state delta");
+            }
+            this.succeeded = false;
         }
+        this
     }
 
     /// Processes a leader timeout issued for this multi-owner chain.
@@ -97,10 +124,15 @@ where
         let old_round = self.state.chain.manager.current_round();
         let timeout_chain_id = certificate.inner().chain_id();
         let timeout_height = certificate.inner().height();
+        let local_time = self.state.storage.clock().current_time();
+        self.audit([AuditEvent::certificate(&certificate)]).await?;
+        self.state
+            .chain
+            .record_votes(VoteRecord::from_certificate(&certificate, local_time));
         self.state
             .chain
             .manager
-            .handle_timeout_certificate(certificate, self.state.storage.clock().current_time());
+            .handle_timeout_certificate(certificate, local_time);
         let round = self.state.chain.manager.current_round();
         if round > old_round {
             actions.notifications.push(Notification {
@@ -109,7 +141,8 @@ where
                     height: timeout_height,
                     round,
                 },
-            })
+            });
+            self.expire_proposal(&mut actions.notifications);
         }
         let info = ChainInfoResponse::new(&self.state.chain, self.state.config.key_pair());
         self.save().await?;
@@ -134,6 +167,8 @@ where
                 },
             original_proposal,
             signature: _,
+            idempotency_key: _,
+            sponsor_approval: _,
         } = proposal;
 
         let mut maybe_blobs = self
@@ -143,7 +178,9 @@ where
         let missing_blob_ids = super::missing_blob_ids(&maybe_blobs);
         if !missing_blob_ids.is_empty() {
             let chain = &mut self.state.chain;
-            if chain.ownership().open_multi_leader_rounds {
+            let authorization_application =
+                chain.execution_state.system.authorization_application.get();
+            if chain.ownership().open_multi_leader_rounds || authorization_application.is_some() {
                 // TODO(#3203): Allow multiple pending proposals on permissionless chains.
                 chain.pending_proposed_blobs.clear();
             }
@@ -152,7 +189,7 @@ where
                 .pending_proposed_blobs
                 .try_load_entry_mut(&owner)
                 .await?
-                .update(*round, validated, maybe_blobs)
+                .update(*round, validated, block.epoch, maybe_blobs)
                 .await?;
             self.save().await?;
             return Err(WorkerError::BlobsNotFound(missing_blob_ids));
@@ -180,21 +217,32 @@ where
             .get_required_blobs(proposal.expected_blob_ids(), &created_blobs)
             .await?;
         let key_pair = self.state.config.key_pair();
+        let owner = proposal.owner();
         let manager = &mut self.state.chain.manager;
-        match manager.create_vote(proposal, block, key_pair, local_time, blobs)? {
+        let records = match manager.create_vote(proposal, block, key_pair, local_time, blobs)? {
             // Cache the value we voted on, so the client doesn't have to send it again.
             Some(Either::Left(vote)) => {
                 self.state
                     .block_values
                     .insert(Cow::Borrowed(vote.value.inner()));
+                vec![
+                    VoteRecord::from_proposal(owner, vote, local_time),
+                    VoteRecord::from_vote(vote, local_time),
+                ]
             }
             Some(Either::Right(vote)) => {
                 self.state
                     .block_values
                     .insert(Cow::Borrowed(vote.value.inner()));
+                vec![
+                    VoteRecord::from_proposal(owner, vote, local_time),
+                    VoteRecord::from_vote(vote, local_time),
+                ]
             }
-            None => (),
-        }
+            None => Vec::new(),
+        };
+        self.audit(records.iter().map(AuditEvent::from)).await?;
+        self.state.chain.record_votes(records);
         self.save().await?;
         Ok(())
     }
@@ -213,7 +261,22 @@ where
         self.state.ensure_is_active().await?;
         let (epoch, committee) = self.state.chain.current_committee()?;
         check_block_epoch(epoch, header.chain_id, header.epoch)?;
-        certificate.check(committee)?;
+        let local_time = self.state.storage.clock().current_time();
+        let superseded_committee = self
+            .state
+            .chain
+            .execution_state
+            .system
+            .superseded_committee(epoch, local_time);
+        check_certificate_with_weight_updates(
+            &self.state.storage,
+            &self.state.committees,
+            &certificate,
+            local_time,
+            committee,
+            superseded_committee,
+        )
+        .await?;
         let mut actions = NetworkActions::default();
         let already_committed_block = self
             .state
@@ -250,7 +313,7 @@ where
             self.state
                 .chain
                 .pending_validated_blobs
-                .update(certificate.round, true, maybe_blobs)
+                .update(certificate.round, true, block.header.epoch, maybe_blobs)
                 .await?;
             self.save().await?;
             return Err(WorkerError::BlobsNotFound(missing_blob_ids));
@@ -260,21 +323,35 @@ where
             .filter_map(|(blob_id, maybe_blob)| Some((blob_id, maybe_blob?)))
             .collect();
         let old_round = self.state.chain.manager.current_round();
+        let mut records =
+            VoteRecord::from_certificate(&certificate, local_time).collect::<Vec<_>>();
+        let mut events = vec![AuditEvent::certificate(&certificate)];
+        let (round, value_hash) = (certificate.round, certificate.hash());
         self.state.chain.manager.create_final_vote(
             certificate,
             self.state.config.key_pair(),
-            self.state.storage.clock().current_time(),
+            local_time,
             blobs,
         )?;
-        let info = ChainInfoResponse::new(&self.state.chain, self.state.config.key_pair());
-        self.save().await?;
+        if let Some(vote) = self.state.chain.manager.confirmed_vote() {
+            if vote.round == round && vote.value.hash() == value_hash {
+                let record = VoteRecord::from_vote(vote, local_time);
+                events.push(AuditEvent::from(&record));
+                records.push(record);
+            }
+        }
+        self.audit(events).await?;
+        self.state.chain.record_votes(records);
         let round = self.state.chain.manager.current_round();
         if round > old_round {
             actions.notifications.push(Notification {
                 chain_id: self.state.chain_id(),
                 reason: Reason::NewRound { height, round },
-            })
+            });
+            self.expire_proposal(&mut actions.notifications);
         }
+        let info = ChainInfoResponse::new(&self.state.chain, self.state.config.key_pair());
+        self.save().await?;
         Ok((info, actions, false))
     }
 
@@ -302,33 +379,43 @@ where
         // We haven't processed the block - verify the certificate first
         let epoch = block.header.epoch;
         // Get the committee for the block's epoch from storage.
-        if let Some(committee) = self
-            .state
-            .chain
-            .execution_state
-            .system
-            .committees
-            .get()
-            .get(&epoch)
-        {
-            certificate.check(committee)?;
+        let system = &self.state.chain.execution_state.system;
+        let local_time = self.state.storage.clock().current_time();
+        if let Some(committee) = system.committees.get().get(&epoch) {
+            let superseded_committee = system.superseded_committee(epoch, local_time);
+            check_certificate_with_weight_updates(
+                &self.state.storage,
+                &self.state.committees,
+                &certificate,
+                local_time,
+                committee,
+                superseded_committee,
+            )
+            .await?;
         } else {
-            let committees = self.state.storage.committees_for(epoch..=epoch).await?;
-            let Some(committee) = committees.get(&epoch) else {
-                let net_description = self
-                    .state
-                    .storage
-                    .read_network_description()
-                    .await?
-                    .ok_or_else(|| WorkerError::MissingNetworkDescription)?;
-                return Err(WorkerError::EventsNotFound(vec![EventId {
-                    chain_id: net_description.admin_chain_id,
-                    stream_id: StreamId::system(EPOCH_STREAM_NAME),
-                    index: epoch.0,
-                }]));
-            };
-            // This line is duplicated, but this avoids cloning and a lifetimes error.
-            certificate.check(committee)?;
+            let epoch_committee = self.load_committee(epoch).await?;
+            check_certificate_with_weight_updates(
+                &self.state.storage,
+                &self.state.committees,
+                &certificate,
+                local_time,
+                &epoch_committee.committee,
+                epoch_committee.superseded_committee(local_time),
+            )
+            .await?;
+        }
+        self.state
+            .chain
+            .record_votes(VoteRecord::from_certificate(&certificate, local_time));
+
+        if let Some(applications) = self.state.chain.application_filter.get().clone() {
+            return self
+                .process_unexecuted_block(
+                    certificate,
+                    &applications,
+                    notify_when_messages_are_delivered,
+                )
+                .await;
         }
 
         // Certificate check passed - which means the blobs the block requires are legitimate and
@@ -347,13 +434,33 @@ where
                 .storage
                 .write_blobs_and_certificate(blobs, &certificate)
                 .await?;
+            let epoch_streams = [
+                StreamId::system(EPOCH_STREAM_NAME),
+                StreamId::system(REMOVED_EPOCH_STREAM_NAME),
+            ];
             let events = block
                 .body
                 .events
                 .iter()
                 .flatten()
                 .map(|event| (event.id(chain_id), event.value.clone()));
-            self.state.storage.write_events(events).await?;
+            if tip.next_block_height == height && self.state.config.max_buffered_events > 0 {
+                // All chains read the committees from storage: write them right away.
+                let (epoch_events, events) = events.partition::<Vec<_>, _>(|(event_id, _)| {
+                    epoch_streams.contains(&event_id.stream_id)
+                });
+                self.state.storage.write_events(epoch_events).await?;
+                self.buffer_events(events).await?;
+            } else {
+                self.state.storage.write_events(events).await?;
+            }
+            // The admin chain created or removed committees: drop them from the cache, so that
+            // they are read from the events again.
+            for event in block.body.events.iter().flatten() {
+                if epoch_streams.contains(&event.stream_id) {
+                    self.state.committees.invalidate(Epoch(event.index));
+                }
+            }
         }
 
         // Update the blob state with last used certificate hash.
@@ -377,6 +484,7 @@ where
                 .chain
                 .preprocess_block(certificate.value())
                 .await?;
+            self.audit([AuditEvent::certificate(&certificate)]).await?;
             // Persist chain.
             self.save().await?;
             let actions = self.state.create_network_actions().await?;
@@ -419,19 +527,30 @@ where
 
         // Execute the block and update inboxes.
         let local_time = self.state.storage.clock().current_time();
-        let chain = &mut self.state.chain;
-        chain
+        self.state
+            .chain
             .remove_bundles_from_inboxes(block.header.timestamp, &block.body.incoming_bundles)
             .await?;
         let oracle_responses = Some(block.body.oracle_responses.clone());
         let (proposed_block, outcome) = block.clone().into_proposal();
+        // Reverts the changes to the execution state that are written before saving the chain.
+        let mut written_undo = Batch::new();
         let verified_outcome = if let Some(execution_state) =
             self.state.execution_state_cache.remove(&outcome.state_hash)
         {
-            chain.execution_state = execution_state;
+            self.state.chain.execution_state = execution_state;
+            outcome.clone()
+        } else if self
+            .apply_execution_state_delta(outcome.state_hash, &mut written_undo)
+            .await?
+        {
             outcome.clone()
         } else {
-            chain
+            let block_hash = CryptoHash::new(&proposed_block);
+            self.state.prefetch_read_set(block_hash).await;
+            let verified_outcome = self
+                .state
+                .chain
                 .execute_block(
                     &proposed_block,
                     local_time,
@@ -439,7 +558,9 @@ where
                     &published_blobs,
                     oracle_responses,
                 )
-                .await?
+                .await?;
+            self.state.record_read_set(block_hash);
+            verified_outcome
         };
         // We should always agree on the messages and state hash.
         ensure!(
@@ -450,7 +571,8 @@ where
             }
         );
         // Update the rest of the chain state.
-        chain
+        self.state
+            .chain
             .apply_confirmed_block(certificate.value(), local_time)
             .await?;
         self.state
@@ -475,8 +597,19 @@ where
                 event_streams,
             },
         });
-        // Persist chain.
-        self.save().await?;
+        self.audit([AuditEvent::certificate(&certificate)]).await?;
+        // Persist chain, and what is needed to revert the block's changes to the execution state.
+        let execution_state_undo =
+            (self.state.config.execution_state_history > 0).then_some((height, written_undo));
+        self.save_with_execution_state_undo(execution_state_undo)
+            .await?;
+        if self.state.config.max_execution_state_deltas > 0 {
+            self.state
+                .storage
+                .remove_execution_state_delta(outcome.state_hash)
+                .await?;
+        }
+        self.record_epoch_committees(&outcome.events).await?;
 
         self.state
             .block_values
@@ -489,6 +622,250 @@ where
         Ok((info, actions))
     }
 
+    /// Processes a verified confirmed block of a chain that is only synchronized partially:
+    /// only the transactions of the given `applications` are executed, and only the block's
+    /// header and these applications' events are stored.
+    async fn process_unexecuted_block(
+        &mut self,
+        certificate: ConfirmedBlockCertificate,
+        applications: &BTreeSet<ApplicationId>,
+        notify_when_messages_are_delivered: Option<oneshot::Sender<()>>,
+    ) -> Result<(ChainInfoResponse, NetworkActions), WorkerError> {
+        let block = certificate.block();
+        let height = block.header.height;
+        let chain_id = block.header.chain_id;
+        self.state
+            .storage
+            .write_certificate_header(&certificate)
+            .await?;
+        let events = block
+            .body
+            .events
+            .iter()
+            .flatten()
+            .filter(|event| match event.stream_id.application_id {
+                GenericApplicationId::System => true,
+                GenericApplicationId::User(application_id) => {
+                    applications.contains(&application_id)
+                }
+            })
+            .map(|event| (event.id(chain_id), event.value.clone()));
+        self.state.storage.write_events(events).await?;
+
+        let tip = self.state.chain.tip_state.get();
+        if tip.next_block_height < height {
+            self.state
+                .chain
+                .preprocess_block(certificate.value())
+                .await?;
+            self.audit([AuditEvent::certificate(&certificate)]).await?;
+            self.save().await?;
+            let actions = self.state.create_network_actions().await?;
+            self.register_delivery_notifier(height, &actions, notify_when_messages_are_delivered)
+                .await;
+            let info = ChainInfoResponse::new(&self.state.chain, self.state.config.key_pair());
+            return Ok((info, actions));
+        }
+        ensure!(
+            tip.block_hash == block.header.previous_block_hash,
+            WorkerError::InvalidBlockChaining
+        );
+        self.state
+            .chain
+            .apply_unexecuted_block(certificate.value(), applications)
+            .await?;
+        let mut actions = self.state.create_network_actions().await?;
+        trace!("Processed unexecuted block {height} on partially synchronized chain {chain_id:.8}");
+        let event_streams = block
+            .body
+            .events
+            .iter()
+            .flatten()
+            .map(|event| event.stream_id.clone())
+            .collect();
+        actions.notifications.push(Notification {
+            chain_id,
+            reason: Reason::NewBlock {
+                height,
+                hash: certificate.hash(),
+                event_streams,
+            },
+        });
+        self.audit([AuditEvent::certificate(&certificate)]).await?;
+        self.save().await?;
+        self.register_delivery_notifier(height, &actions, notify_when_messages_are_delivered)
+            .await;
+        let info = ChainInfoResponse::new(&self.state.chain, self.state.config.key_pair());
+        Ok((info, actions))
+    }
+
+    /// Adds the `events` of a confirmed block to the chain's buffered events, and writes them
+    /// to storage if there are enough of them.
+    ///
+    /// When a chain catches up, the worker receives many confirmed blocks in a row, and writing
+    /// the events of each of them separately costs one more storage batch per block. Buffered
+    /// events are saved in the same batch as the chain instead, and written to storage once
+    /// there are enough of them, the request queue is empty, or another kind of request needs
+    /// to see them.
+    async fn buffer_events(&mut self, events: Vec<(EventId, Vec<u8>)>) -> Result<(), WorkerError> {
+        let buffered_events = &mut self.state.chain.buffered_events;
+        for event in events {
+            buffered_events.push_back(event);
+        }
+        if buffered_events.count() >= self.state.config.max_buffered_events {
+            self.write_buffered_events().await?;
+        }
+        Ok(())
+    }
+
+    /// Writes the buffered events to storage. The chain has to be saved afterwards.
+    async fn write_buffered_events(&mut self) -> Result<(), WorkerError> {
+        let buffered_events = &mut self.state.chain.buffered_events;
+        if buffered_events.count() == 0 {
+            return Ok(());
+        }
+        let events = buffered_events.elements().await?;
+        self.state.storage.write_events(events).await?;
+        buffered_events.clear();
+        Ok(())
+    }
+
+    /// Writes the buffered events to storage and saves the chain.
+    pub(super) async fn flush_events(&mut self) -> Result<(), WorkerError> {
+        if self.state.chain.buffered_events.count() == 0 {
+            return Ok(());
+        }
+        self.write_buffered_events().await?;
+        self.save().await
+    }
+
+    /// Sets the applications that are executed and whose events are kept when synchronizing
+    /// the chain partially. From then on, only their transactions are executed.
+    pub(super) async fn set_application_filter(
+        &mut self,
+        applications: BTreeSet<ApplicationId>,
+    ) -> Result<(), WorkerError> {
+        let chain = &mut self.state.chain;
+        if let Some(tracked) = chain.application_filter.get() {
+            // The state of the newly tracked applications was not maintained so far.
+            if !applications.is_subset(tracked) && chain.tracked_state_lost_at.get().is_none() {
+                let height = chain.tip_state.get().next_block_height;
+                chain.tracked_state_lost_at.set(Some(height));
+            }
+        }
+        chain.application_filter.set(Some(applications));
+        self.save().await
+    }
+
+    /// Records that the chain's next block is expected in `epoch`, if the chain is active and
+    /// still on an older epoch. The execution state is unchanged: the chain only switches to the
+    /// new committee when it processes the admin chain's event in a block. Returns whether the
+    /// chain was migrated.
+    pub(super) async fn migrate_to_epoch(&mut self, epoch: Epoch) -> Result<bool, WorkerError> {
+        let chain = &self.state.chain;
+        if !chain.is_active()
+            || *chain.execution_state.system.epoch.get() >= epoch
+            || chain
+                .migrated_epoch
+                .get()
+                .is_some_and(|migrated| migrated >= epoch)
+        {
+            return Ok(false);
+        }
+        // Make sure the committee is known, and cached for the certificates of the new epoch.
+        let committee = self
+            .state
+            .committees
+            .get_or_load(&self.state.storage, epoch)
+            .await?;
+        if committee.is_none() {
+            return Ok(false);
+        }
+        self.state.chain.migrated_epoch.set(Some(epoch));
+        self.save().await?;
+        Ok(true)
+    }
+
+    /// Applies the persisted delta leading to the execution state with the given hash, if
+    /// there is one that applies to the current execution state. Returns whether it was
+    /// applied.
+    ///
+    /// The delta is written ahead of the rest of the block, together with the writes reverting
+    /// it in the chain's `unsaved_execution_state_undo`. Saving the chain clears them in the
+    /// same batch as the block's other changes; otherwise they are applied before the chain
+    /// is changed again, so the execution state never gets ahead of the chain.
+    async fn apply_execution_state_delta(
+        &mut self,
+        state_hash: CryptoHash,
+        undo: &mut Batch,
+    ) -> Result<bool, WorkerError> {
+        if self.state.config.max_execution_state_deltas == 0 {
+            return Ok(false);
+        }
+        let Some(delta) = self
+            .state
+            .storage
+            .read_execution_state_delta(state_hash)
+            .await?
+        else {
+            return Ok(false);
+        };
+        let chain = &mut self.state.chain;
+        if !delta.is_intact()
+            || delta.state_hash != state_hash
+            || *chain.execution_state_hash.get() != Some(delta.base_state_hash)
+        {
+            warn!("Ignoring the persisted execution state delta leading to {state_hash}");
+            return Ok(false);
+        }
+        let context = chain.execution_state.context().clone();
+        let delta_undo = delta
+            .batch
+            .undo(context.store())
+            .await
+            .map_err(ViewError::from)?;
+        let mut batch = delta.batch;
+        chain
+            .unsaved_execution_state_undo
+            .set(Some(delta_undo.clone()));
+        chain.unsaved_execution_state_undo.flush(&mut batch)?;
+        chain.unsaved_execution_state_undo.set(None);
+        context
+            .store()
+            .write_batch(batch)
+            .await
+            .map_err(ViewError::from)?;
+        if self.state.config.execution_state_history > 0 {
+            *undo = delta_undo;
+        }
+        chain.execution_state = ExecutionStateView::load(context).await?;
+        ensure!(
+            chain.execution_state.state_hash_mut().await? == state_hash,
+            WorkerError::InvalidExecutionStateDelta(state_hash)
+        );
+        Ok(true)
+    }
+
+    /// Reverts the changes of a persisted execution state delta that were written without the
+    /// rest of their block, because the worker failed or stopped before saving the chain.
+    pub(super) async fn revert_unsaved_execution_state_delta(&mut self) -> Result<(), WorkerError> {
+        let chain = &mut self.state.chain;
+        let Some(undo) = chain.unsaved_execution_state_undo.get().clone() else {
+            return Ok(());
+        };
+        let chain_id = chain.chain_id();
+        debug!("Reverting an unsaved execution state delta on chain {chain_id:.8}");
+        let context = chain.execution_state.context().clone();
+        context
+            .store()
+            .write_batch(undo)
+            .await
+            .map_err(ViewError::from)?;
+        chain.execution_state = ExecutionStateView::load(context).await?;
+        chain.unsaved_execution_state_undo.set(None);
+        self.save().await
+    }
+
     /// Schedules a notification for when cross-chain messages are delivered up to the given
     /// `height`.
     #[instrument(level = "trace", skip(self, notify_when_messages_are_delivered))]
@@ -522,6 +899,8 @@ where
         origin: ChainId,
         bundles: Vec<(Epoch, MessageBundle)>,
     ) -> Result<Option<BlockHeight>, WorkerError> {
+        // The inbox is read several times below: load it only once.
+        self.state.chain.prefetch_inboxes([&origin]).await?;
         // Only process certificates with relevant heights and epochs.
         let next_height_to_receive = self
             .state
@@ -542,21 +921,34 @@ where
             last_anticipated_block_height,
             bundles,
         )?;
-        let Some(last_updated_height) = bundles.last().map(|bundle| bundle.height) else {
-            return Ok(None);
-        };
         // Process the received messages in certificates.
         let local_time = self.state.storage.clock().current_time();
         let mut previous_height = None;
+        let mut heights = Vec::new();
         for bundle in bundles {
             let add_to_received_log = previous_height != Some(bundle.height);
+            // Only whole blocks are confirmed to the sender: check the quotas between them.
+            if add_to_received_log && self.state.chain.is_inbox_full(&origin).await? {
+                warn!(
+                    "Refusing to deliver messages to {recipient:?} from {origin:?} \
+                    at height {} because the inbox quotas are reached",
+                    bundle.height,
+                );
+                break;
+            }
             previous_height = Some(bundle.height);
+            if add_to_received_log {
+                heights.push(bundle.height);
+            }
             // Update the staged chain state with the received block.
             self.state
                 .chain
                 .receive_message_bundle(&origin, bundle, local_time, add_to_received_log)
                 .await?;
         }
+        let Some(last_updated_height) = previous_height else {
+            return Ok(None);
+        };
         if !self.state.config.allow_inactive_chains && !self.state.chain.is_active() {
             // Refuse to create a chain state if the chain is still inactive by
             // now. Accordingly, do not send a confirmation, so that the
@@ -567,31 +959,48 @@ where
             );
             return Ok(None);
         }
+        self.audit([AuditEvent::CrossChainUpdate { origin, heights }])
+            .await?;
         // Save the chain.
         self.save().await?;
         Ok(Some(last_updated_height))
     }
 
-    /// Handles the cross-chain request confirming that the recipient was updated.
-    pub(super) async fn confirm_updated_recipient(
+    /// Handles the cross-chain requests confirming that the recipients were updated up to
+    /// the given heights. All the confirmations are persisted at once.
+    pub(super) async fn confirm_updated_recipients(
         &mut self,
-        recipient: ChainId,
-        latest_height: BlockHeight,
+        confirmations: BTreeMap<ChainId, BlockHeight>,
     ) -> Result<(), WorkerError> {
-        let fully_delivered = self
-            .state
-            .chain
-            .mark_messages_as_received(&recipient, latest_height)
-            .await?
-            && self
+        let mut updated_heights = BTreeSet::new();
+        for (recipient, latest_height) in confirmations {
+            if self
                 .state
-                .all_messages_to_tracked_chains_delivered_up_to(latest_height)
-                .await?;
+                .chain
+                .mark_messages_as_received(&recipient, latest_height)
+                .await?
+            {
+                updated_heights.insert(latest_height);
+            }
+        }
+
+        // Notifying about the highest delivered height covers all the lower ones.
+        let mut delivered_height = None;
+        for height in updated_heights.into_iter().rev() {
+            if self
+                .state
+                .all_messages_to_tracked_chains_delivered_up_to(height)
+                .await?
+            {
+                delivered_height = Some(height);
+                break;
+            }
+        }
 
         self.save().await?;
 
-        if fully_delivered {
-            self.state.delivery_notifier.notify(latest_height);
+        if let Some(height) = delivered_height {
+            self.state.delivery_notifier.notify(height);
         }
 
         Ok(())
@@ -608,8 +1017,51 @@ where
         Ok(())
     }
 
+    /// Prunes the stale proposals and votes of the chain manager, and saves the chain.
+    /// Returns the number of entries that were removed.
+    pub async fn prune_stale_manager_entries(&mut self) -> Result<usize, WorkerError> {
+        let pruned = self.prune_chain_manager();
+        self.save().await?;
+        Ok(pruned)
+    }
+
+    /// Prunes the stale proposals and votes of the chain manager.
+    fn prune_chain_manager(&mut self) -> usize {
+        let local_time = self.state.storage.clock().current_time();
+        let max_proposal_age = self.state.config.max_proposal_age();
+        self.state
+            .chain
+            .manager
+            .prune_stale_entries(local_time, max_proposal_age)
+    }
+
+    /// Discards the proposal of the chain manager if the round advanced too far past it, and
+    /// notifies the clients that may be waiting for it.
+    fn expire_proposal(&mut self, notifications: &mut Vec<Notification>) {
+        let Some(max_round_age) = self.state.config.proposal_expiry_rounds else {
+            return;
+        };
+        let chain_id = self.state.chain_id();
+        if let Some(proposal) = self.state.chain.manager.expire_proposal(max_round_age) {
+            notifications.push(Notification {
+                chain_id,
+                reason: Reason::ProposalDiscarded {
+                    height: proposal.content.block.height,
+                    round: proposal.content.round,
+                },
+            });
+        }
+    }
+
     /// Attempts to vote for a leader timeout, if possible.
     pub(super) async fn vote_for_leader_timeout(&mut self) -> Result<(), WorkerError> {
+        let round = self.state.chain.manager.current_round();
+        if let Some(schedule) = self.state.round_schedule(round).await? {
+            self.state
+                .chain
+                .manager
+                .apply_round_schedule(round, schedule);
+        }
         let chain = &mut self.state.chain;
         let epoch = chain.execution_state.system.epoch.get();
         let chain_id = chain.chain_id();
@@ -620,6 +1072,12 @@ where
             .manager
             .vote_timeout(chain_id, height, *epoch, key_pair, local_time)
         {
+            let record = chain
+                .manager
+                .timeout_vote()
+                .map(|vote| VoteRecord::from_vote(vote, local_time));
+            self.audit(record.iter().map(AuditEvent::from)).await?;
+            self.state.chain.record_votes(record);
             self.save().await?;
         }
         Ok(())
@@ -632,12 +1090,8 @@ where
             chain.execution_state.system.epoch.get(),
             chain.unskippable_bundles.front(),
         ) {
-            let elapsed = self
-                .state
-                .storage
-                .clock()
-                .current_time()
-                .delta_since(entry.seen);
+            let local_time = self.state.storage.clock().current_time();
+            let elapsed = local_time.delta_since(entry.seen);
             if elapsed >= chain.ownership().timeout_config.fallback_duration {
                 let chain_id = chain.chain_id();
                 let height = chain.tip_state.get().next_block_height;
@@ -646,6 +1100,12 @@ where
                     .manager
                     .vote_fallback(chain_id, height, *epoch, key_pair)
                 {
+                    let record = chain
+                        .manager
+                        .fallback_vote()
+                        .map(|vote| VoteRecord::from_vote(vote, local_time));
+                    self.audit(record.iter().map(AuditEvent::from)).await?;
+                    self.state.chain.record_votes(record);
                     self.save().await?;
                 }
             }
@@ -657,6 +1117,42 @@ where
         &mut self,
         blob: Blob,
     ) -> Result<ChainInfoResponse, WorkerError> {
+        // New proposals can only publish the blobs allowed by the policy of their epoch, which
+        // is not necessarily the current one anymore. Proposals from epochs that are no longer
+        // active can't be voted on, so their pending blobs are dropped.
+        let system = &self.state.chain.execution_state.system;
+        let current_epoch = *system.epoch.get();
+        let active_epochs = system
+            .committees
+            .get()
+            .keys()
+            .copied()
+            .collect::<BTreeSet<_>>();
+        let mut committees = BTreeMap::new();
+        let mut stale_owners = Vec::new();
+        for (owner, pending_blobs) in self
+            .state
+            .chain
+            .pending_proposed_blobs
+            .try_load_all_entries()
+            .await?
+        {
+            if *pending_blobs.validated.get() {
+                continue;
+            }
+            let epoch = pending_blobs.epoch.get().unwrap_or(current_epoch);
+            if !active_epochs.contains(&epoch) {
+                stale_owners.push(owner);
+            } else if !committees.contains_key(&epoch) {
+                committees.insert(epoch, self.committee_for_epoch(epoch).await?);
+            }
+        }
+        for owner in &stale_owners {
+            self.state
+                .chain
+                .pending_proposed_blobs
+                .remove_entry(owner)?;
+        }
         let mut was_expected = self
             .state
             .chain
@@ -671,8 +1167,8 @@ where
             .await?
         {
             if !pending_blobs.validated.get() {
-                let (_, committee) = self.state.chain.current_committee()?;
-                let policy = committee.policy();
+                let epoch = pending_blobs.epoch.get().unwrap_or(current_epoch);
+                let policy = committees[&epoch].policy();
                 policy
                     .check_blob_size(blob.content())
                     .with_execution_context(ChainExecutionContext::Block)?;
@@ -692,12 +1188,159 @@ where
         ))
     }
 
+    /// Returns the committee of `epoch`, whose policy applies to the blocks of that epoch.
+    async fn committee_for_epoch(&self, epoch: Epoch) -> Result<Arc<Committee>, WorkerError> {
+        let system = &self.state.chain.execution_state.system;
+        let committee = match system.committees.get().get(&epoch) {
+            Some(committee) => committee.clone(),
+            None => self.load_committee(epoch).await?.committee.clone(),
+        };
+        Ok(Arc::new(committee))
+    }
+
+    /// Records the committees of the epochs whose voting rights were changed by the given
+    /// events of the admin chain, as they are after executing the block, and drops them from
+    /// the cache. Other chains check their certificates against these.
+    async fn record_epoch_committees(&self, events: &[Vec<Event>]) -> Result<(), WorkerError> {
+        let weights_stream_id = StreamId::system(VALIDATOR_WEIGHTS_STREAM_NAME);
+        let epochs = events
+            .iter()
+            .flatten()
+            .filter(|event| event.stream_id == weights_stream_id)
+            .map(|event| {
+                let update = bcs::from_bytes::<ValidatorWeightUpdate>(&event.value)?;
+                Ok(update.epoch)
+            })
+            .collect::<Result<BTreeSet<_>, ViewError>>()?;
+        let system = &self.state.chain.execution_state.system;
+        for epoch in epochs {
+            let Some(committee) = system.committees.get().get(&epoch) else {
+                continue;
+            };
+            let epoch_committee = EpochCommittee {
+                committee: committee.clone(),
+                superseded: system.superseded_committees.get().get(&epoch).cloned(),
+            };
+            self.state
+                .storage
+                .write_epoch_committee(epoch, &epoch_committee)
+                .await?;
+            self.state.committees.invalidate(epoch);
+        }
+        Ok(())
+    }
+
+    /// Reads the committee of `epoch` from the cache or the storage. Fails with the missing
+    /// event of the admin chain if the epoch is not known yet.
+    async fn load_committee(&self, epoch: Epoch) -> Result<Arc<EpochCommittee>, WorkerError> {
+        let committee = self
+            .state
+            .committees
+            .get_or_load(&self.state.storage, epoch)
+            .await?;
+        if let Some(committee) = committee {
+            return Ok(committee);
+        }
+        let net_description = self
+            .state
+            .storage
+            .read_network_description()
+            .await?
+            .ok_or_else(|| WorkerError::MissingNetworkDescription)?;
+        Err(WorkerError::EventsNotFound(vec![EventId {
+            chain_id: net_description.admin_chain_id,
+            stream_id: StreamId::system(EPOCH_STREAM_NAME),
+            index: epoch.0,
+        }]))
+    }
+
+    /// Appends state transitions of this chain to the audit log, if there is one.
+    async fn audit(&self, events: impl IntoIterator<Item = AuditEvent>) -> Result<(), WorkerError> {
+        let Some(audit_log) = &self.state.config.audit_log else {
+            return Ok(());
+        };
+        let chain_id = self.state.chain_id();
+        let timestamp = self.state.storage.clock().current_time();
+        audit_log.record(chain_id, timestamp, events).await?;
+        Ok(())
+    }
+
     /// Stores the chain state in persistent storage.
     ///
     /// Waits until the [`ChainStateView`] is no longer shared before persisting the changes.
     async fn save(&mut self) -> Result<(), WorkerError> {
+        self.save_with_execution_state_undo(None).await
+    }
+
+    /// Stores the chain state in persistent storage. If `execution_state_undo` contains the
+    /// height of the block being saved and the batch reverting the changes to the execution
+    /// state that were already written, the batch reverting all the block's changes to the
+    /// execution state is persisted first: it is derived from the chain's own batch, before
+    /// that is written.
+    async fn save_with_execution_state_undo(
+        &mut self,
+        execution_state_undo: Option<(BlockHeight, Batch)>,
+    ) -> Result<(), WorkerError> {
+        if let Some(fence) = &self.state.config.signing_fence {
+            fence
+                .ensure_is_primary(&self.state.storage, self.state.loaded_in_term)
+                .await?;
+        }
         self.state.clear_shared_chain_view().await;
-        self.state.chain.save().await?;
+        if self.state.config.prune_manager_on_save {
+            self.prune_chain_manager();
+        }
+        // Forget the migration once a block has moved the execution state to that epoch.
+        let chain = &mut self.state.chain;
+        let epoch = *chain.execution_state.system.epoch.get();
+        if chain
+            .migrated_epoch
+            .get()
+            .is_some_and(|migrated| migrated <= epoch)
+        {
+            chain.migrated_epoch.set(None);
+        }
+        // Keep the current round's schedule in the stored chain info. Where it matters, the
+        // schedule is fetched again before it is used, so a failure here is not fatal.
+        let round = self.state.chain.manager.current_round();
+        match self.state.round_schedule(round).await {
+            Ok(Some(schedule)) => self
+                .state
+                .chain
+                .manager
+                .apply_round_schedule(round, schedule),
+            Ok(None) => {}
+            Err(error) => warn!(%error, %round, "Failed to get the round schedule"),
+        }
+        let chain = &mut self.state.chain;
+        let mut batch = Batch::new();
+        chain.flush(&mut batch)?;
+        if let Some((height, written_undo)) = execution_state_undo {
+            let mut undo = batch
+                .restrict_to_prefix(&chain.execution_state.context().base_key().bytes)
+                .undo(chain.context().store())
+                .await
+                .map_err(ViewError::from)?;
+            undo.operations.extend(written_undo.operations);
+            self.state
+                .storage
+                .write_execution_state_undo(
+                    chain.chain_id(),
+                    height,
+                    &undo,
+                    self.state.config.execution_state_history,
+                )
+                .await?;
+        }
+        if !batch.is_empty() {
+            linera_views::change_log::write_batch(&*chain, batch).await?;
+        }
+        if let Some(fence) = &self.state.config.signing_fence {
+            // Another instance may have been promoted while the chain was being written.
+            fence
+                .ensure_is_primary(&self.state.storage, self.state.loaded_in_term)
+                .await?;
+        }
         self.succeeded = true;
         Ok(())
     }
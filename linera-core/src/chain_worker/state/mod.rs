@@ -14,22 +14,31 @@ use std::{
 
 use linera_base::{
     crypto::{CryptoHash, ValidatorPublicKey},
-    data_types::{ApplicationDescription, Blob, BlockHeight, Epoch},
+    data_types::{ApplicationDescription, Blob, BlockHeight, Epoch, Round, Timestamp},
     ensure,
     hashed::Hashed,
     identifiers::{ApplicationId, BlobId, BlobType, ChainId},
 };
 use linera_chain::{
     data_types::{BlockExecutionOutcome, BlockProposal, MessageBundle, ProposedBlock},
-    manager,
-    types::{Block, ConfirmedBlockCertificate, TimeoutCertificate, ValidatedBlockCertificate},
+    manager::{self, RoundSchedule},
+    types::{
+        Block, CertificateValue, ConfirmedBlockCertificate, GenericCertificate, TimeoutCertificate,
+        ValidatedBlockCertificate,
+    },
     ChainError, ChainStateView,
 };
-use linera_execution::{ExecutionStateView, Query, QueryOutcome, ServiceRuntimeEndpoint};
+use linera_execution::{
+    committee::Committee, ExecutionStateView, Query, QueryOutcome, ServiceRuntimeEndpoint,
+};
 use linera_storage::{Clock as _, ResultReadCertificates, Storage};
-use linera_views::views::{ClonableView, RootView};
+use linera_views::{
+    context::Context as _,
+    store::ReadableKeyValueStore as _,
+    views::{ClonableView, RootView, View as _},
+};
 use tokio::sync::{oneshot, OwnedRwLockReadGuard, RwLock, RwLockWriteGuard};
-use tracing::{instrument, warn};
+use tracing::{debug, instrument, warn};
 
 #[cfg(test)]
 pub(crate) use self::attempted_changes::CrossChainUpdateHelper;
@@ -39,7 +48,10 @@ use self::{
 };
 use super::{ChainWorkerConfig, ChainWorkerRequest, DeliveryNotifier};
 use crate::{
+    committee_cache::CommitteeCache,
     data_types::{ChainInfoQuery, ChainInfoResponse, CrossChainRequest},
+    service_runtime_pool::QueryPermit,
+    signing_fence::SigningFence,
     value_cache::ValueCache,
     worker::{NetworkActions, WorkerError},
 };
@@ -56,9 +68,14 @@ where
     service_runtime_endpoint: Option<ServiceRuntimeEndpoint>,
     block_values: Arc<ValueCache<CryptoHash, Hashed<Block>>>,
     execution_state_cache: Arc<ValueCache<CryptoHash, ExecutionStateView<StorageClient::Context>>>,
+    read_sets: Arc<ValueCache<CryptoHash, Vec<Vec<u8>>>>,
+    committees: Arc<CommitteeCache>,
     tracked_chains: Option<Arc<sync::RwLock<HashSet<ChainId>>>>,
     delivery_notifier: DeliveryNotifier,
     knows_chain_is_active: bool,
+    /// The fencing term of this instance when the chain was loaded, if it was the primary.
+    /// A chain loaded in another term may be outdated and is never saved.
+    loaded_in_term: Option<u64>,
 }
 
 impl<StorageClient> ChainWorkerState<StorageClient>
@@ -74,14 +91,17 @@ where
         execution_state_cache: Arc<
             ValueCache<CryptoHash, ExecutionStateView<StorageClient::Context>>,
         >,
+        read_sets: Arc<ValueCache<CryptoHash, Vec<Vec<u8>>>>,
+        committees: Arc<CommitteeCache>,
         tracked_chains: Option<Arc<sync::RwLock<HashSet<ChainId>>>>,
         delivery_notifier: DeliveryNotifier,
         chain_id: ChainId,
         service_runtime_endpoint: Option<ServiceRuntimeEndpoint>,
     ) -> Result<Self, WorkerError> {
+        let loaded_in_term = config.signing_fence.as_ref().and_then(SigningFence::term);
         let chain = storage.load_chain(chain_id).await?;
 
-        Ok(ChainWorkerState {
+        let mut state = ChainWorkerState {
             config,
             storage,
             chain,
@@ -89,10 +109,20 @@ where
             service_runtime_endpoint,
             block_values,
             execution_state_cache,
+            read_sets,
+            committees,
             tracked_chains,
             delivery_notifier,
             knows_chain_is_active: false,
-        })
+            loaded_in_term,
+        };
+        if state.chain.unsaved_execution_state_undo.get().is_some() {
+            // Creating the attempted changes reverts the delta, or retries the next time.
+            ChainWorkerStateWithAttemptedChanges::new(&mut state).await;
+        }
+        // A worker may have stopped before writing the buffered events.
+        state.flush_events().await;
+        Ok(state)
     }
 
     /// Returns the [`ChainId`] of the chain handled by this worker.
@@ -100,10 +130,35 @@ where
         self.chain.chain_id()
     }
 
+    /// Loads the storage keys that the block read when it was last executed into the cache of
+    /// the chain state, and starts recording the keys it reads this time.
+    async fn prefetch_read_set(&self, block_hash: CryptoHash) {
+        let store = self.chain.context().store();
+        if let Some(keys) = self.read_sets.get(&block_hash) {
+            if let Err(error) = store.prefetch(keys).await {
+                debug!(%error, "Failed to prefetch the storage keys read by the block");
+            }
+        }
+        store.start_recording_reads();
+    }
+
+    /// Remembers the storage keys read since [`Self::prefetch_read_set`] was called, for the
+    /// next execution of the same block.
+    fn record_read_set(&self, block_hash: CryptoHash) {
+        let keys = self.chain.context().store().take_recorded_reads();
+        if !keys.is_empty() {
+            self.read_sets.insert_owned(&block_hash, keys);
+        }
+    }
+
     /// Handles a request and applies it to the chain state.
     #[instrument(skip_all)]
     pub async fn handle_request(&mut self, request: ChainWorkerRequest<StorageClient::Context>) {
         tracing::trace!("Handling chain worker request: {request:?}");
+        // Only confirmed blocks may be processed while events are buffered.
+        if !matches!(request, ChainWorkerRequest::ProcessConfirmedBlock { .. }) {
+            self.flush_events().await;
+        }
         // TODO(#2237): Spawn concurrent tasks for read-only operations
         let responded = match request {
             #[cfg(with_testing)]
@@ -113,9 +168,13 @@ where
             ChainWorkerRequest::GetChainStateView { callback } => {
                 callback.send(self.chain_state_view().await).is_ok()
             }
-            ChainWorkerRequest::QueryApplication { query, callback } => {
-                callback.send(self.query_application(query).await).is_ok()
-            }
+            ChainWorkerRequest::QueryApplication {
+                query,
+                permit,
+                callback,
+            } => callback
+                .send(self.query_application(query, permit).await)
+                .is_ok(),
             ChainWorkerRequest::DescribeApplication {
                 application_id,
                 callback,
@@ -133,6 +192,13 @@ where
                         .await,
                 )
                 .is_ok(),
+            ChainWorkerRequest::SimulateBlockExecution {
+                block,
+                published_blobs,
+                callback,
+            } => callback
+                .send(self.simulate_block_execution(block, &published_blobs).await)
+                .is_ok(),
             ChainWorkerRequest::ProcessTimeout {
                 certificate,
                 callback,
@@ -165,15 +231,11 @@ where
             } => callback
                 .send(self.process_cross_chain_update(origin, bundles).await)
                 .is_ok(),
-            ChainWorkerRequest::ConfirmUpdatedRecipient {
-                recipient,
-                latest_height,
+            ChainWorkerRequest::ConfirmUpdatedRecipients {
+                confirmations,
                 callback,
             } => callback
-                .send(
-                    self.confirm_updated_recipient(recipient, latest_height)
-                        .await,
-                )
+                .send(self.confirm_updated_recipients(confirmations).await)
                 .is_ok(),
             ChainWorkerRequest::HandleChainInfoQuery { query, callback } => callback
                 .send(self.handle_chain_info_query(query).await)
@@ -193,6 +255,18 @@ where
                         .await,
                 )
                 .is_ok(),
+            ChainWorkerRequest::PruneStaleManagerEntries { callback } => callback
+                .send(self.prune_stale_manager_entries().await)
+                .is_ok(),
+            ChainWorkerRequest::SetApplicationFilter {
+                applications,
+                callback,
+            } => callback
+                .send(self.set_application_filter(applications).await)
+                .is_ok(),
+            ChainWorkerRequest::MigrateToEpoch { epoch, callback } => {
+                callback.send(self.migrate_to_epoch(epoch).await).is_ok()
+            }
         };
 
         if !responded {
@@ -232,11 +306,19 @@ where
             .await
     }
 
-    /// Queries an application's state on the chain.
+    /// Queries an application's state on the chain, with the `permit` of the service runtime
+    /// pool, if there is one.
     pub(super) async fn query_application(
         &mut self,
         query: Query,
+        mut permit: Option<QueryPermit>,
     ) -> Result<QueryOutcome, WorkerError> {
+        if self.service_runtime_endpoint.is_none() {
+            // The query spawns its own runtime.
+            if let Some(permit) = &mut permit {
+                permit.ensure_memory_is_reserved()?;
+            }
+        }
         ChainWorkerStateWithTemporaryChanges::new(self)
             .await
             .query_application(query)
@@ -268,6 +350,29 @@ where
         Ok((block, response))
     }
 
+    /// Executes a block on a copy-on-write fork of the chain state. Unlike
+    /// [`Self::stage_block_execution`], the chain view is never modified, and nothing is
+    /// cached for later.
+    pub(super) async fn simulate_block_execution(
+        &mut self,
+        block: ProposedBlock,
+        published_blobs: &[Blob],
+    ) -> Result<(Block, ChainInfoResponse), WorkerError> {
+        self.ensure_is_active().await?;
+        let mut chain = ChainStateView::load(self.chain.context().fork()).await?;
+        let local_time = self.storage.clock().current_time();
+        let (_, committee) = chain.current_committee()?;
+        block.check_proposal_size(committee.policy().maximum_block_proposal_size)?;
+        let outcome =
+            Box::pin(chain.execute_block(&block, local_time, None, published_blobs, None)).await?;
+        let mut response = ChainInfoResponse::new(&chain, None);
+        if let Some(signer) = block.authenticated_signer {
+            response.info.requested_owner_balance =
+                chain.execution_state.system.balances.get(&signer).await?;
+        }
+        Ok((outcome.with(block), response))
+    }
+
     /// Processes a leader timeout issued for this multi-owner chain.
     pub(super) async fn process_timeout(
         &mut self,
@@ -286,9 +391,10 @@ where
         proposal: BlockProposal,
     ) -> Result<(ChainInfoResponse, NetworkActions), WorkerError> {
         self.ensure_is_active().await?;
+        let schedule = self.round_schedule(proposal.content.round).await?;
         if ChainWorkerStateWithTemporaryChanges::new(&mut *self)
             .await
-            .check_proposed_block(&proposal)
+            .check_proposed_block(&proposal, schedule)
             .await?
             == manager::Outcome::Skip
         {
@@ -302,9 +408,18 @@ where
             .await?;
         let validation_outcome = ChainWorkerStateWithTemporaryChanges::new(self)
             .await
-            .validate_proposal_content(&proposal.content, &published_blobs)
+            .validate_proposal_content(
+                &proposal.content,
+                proposal.sponsor_approval.as_ref(),
+                &published_blobs,
+            )
             .await?;
 
+        if self.config.validation_only {
+            // The proposal is valid, but there is no vote to record.
+            let info = ChainInfoResponse::new(&self.chain, None);
+            return Ok((info, NetworkActions::default()));
+        }
         let actions = if let Some((outcome, local_time)) = validation_outcome {
             ChainWorkerStateWithAttemptedChanges::new(&mut *self)
                 .await
@@ -321,6 +436,21 @@ where
         Ok((info, actions))
     }
 
+    /// Fetches the schedule of `round` of the next block from the pacemaker, if there is one.
+    pub(super) async fn round_schedule(
+        &self,
+        round: Round,
+    ) -> Result<Option<RoundSchedule>, WorkerError> {
+        let Some(pacemaker) = &self.config.pacemaker else {
+            return Ok(None);
+        };
+        let chain_id = self.chain.chain_id();
+        let height = self.chain.tip_state.get().next_block_height;
+        Ok(Some(
+            pacemaker.round_schedule(chain_id, height, round).await?,
+        ))
+    }
+
     /// Clears the shared chain view, and acquires and drops its write lock.
     ///
     /// This is the only place a write lock is acquired, and read locks are acquired in
@@ -334,6 +464,21 @@ where
         }
     }
 
+    /// Writes the buffered events of confirmed blocks to storage, if there are any.
+    pub(super) async fn flush_events(&mut self) {
+        if self.chain.buffered_events.count() == 0 {
+            return;
+        }
+        let result = ChainWorkerStateWithAttemptedChanges::new(self)
+            .await
+            .flush_events()
+            .await;
+        if let Err(error) = result {
+            // The events stay in the chain state, and are written later.
+            warn!(%error, "Failed to write the buffered events");
+        }
+    }
+
     /// Processes a validated block issued for this multi-owner chain.
     #[tracing::instrument(level = "debug", skip(self))]
     pub(super) async fn process_validated_block(
@@ -372,15 +517,14 @@ where
             .await
     }
 
-    /// Handles the cross-chain request confirming that the recipient was updated.
-    pub(super) async fn confirm_updated_recipient(
+    /// Handles the cross-chain requests confirming that the recipients were updated.
+    pub(super) async fn confirm_updated_recipients(
         &mut self,
-        recipient: ChainId,
-        latest_height: BlockHeight,
+        confirmations: BTreeMap<ChainId, BlockHeight>,
     ) -> Result<(), WorkerError> {
         ChainWorkerStateWithAttemptedChanges::new(self)
             .await
-            .confirm_updated_recipient(recipient, latest_height)
+            .confirm_updated_recipients(confirmations)
             .await
     }
 
@@ -390,6 +534,11 @@ where
         &mut self,
         query: ChainInfoQuery,
     ) -> Result<(ChainInfoResponse, NetworkActions), WorkerError> {
+        ensure!(
+            !self.config.validation_only
+                || !(query.request_leader_timeout || query.request_fallback),
+            WorkerError::VotingDisabled
+        );
         if query.request_leader_timeout {
             ChainWorkerStateWithAttemptedChanges::new(&mut *self)
                 .await
@@ -657,6 +806,64 @@ where
             .update_received_certificate_trackers(new_trackers)
             .await
     }
+
+    /// Prunes the stale proposals and votes of the chain manager.
+    pub async fn prune_stale_manager_entries(&mut self) -> Result<usize, WorkerError> {
+        ChainWorkerStateWithAttemptedChanges::new(self)
+            .await
+            .prune_stale_manager_entries()
+            .await
+    }
+
+    /// Synchronizes the chain only partially from now on, keeping the events of the given
+    /// applications.
+    pub async fn set_application_filter(
+        &mut self,
+        applications: BTreeSet<ApplicationId>,
+    ) -> Result<(), WorkerError> {
+        ChainWorkerStateWithAttemptedChanges::new(self)
+            .await
+            .set_application_filter(applications)
+            .await
+    }
+
+    /// Moves the chain to the given newer epoch without producing a block. Returns whether the
+    /// chain was migrated.
+    pub async fn migrate_to_epoch(&mut self, epoch: Epoch) -> Result<bool, WorkerError> {
+        ChainWorkerStateWithAttemptedChanges::new(self)
+            .await
+            .migrate_to_epoch(epoch)
+            .await
+    }
+
+    /// Returns an error if the chain's execution state is not kept up to date, because the
+    /// chain is only synchronized partially.
+    fn ensure_execution_state_is_available(&self) -> Result<(), WorkerError> {
+        ensure!(
+            self.chain.application_filter.get().is_none(),
+            WorkerError::PartiallySynchronizedChain(self.chain_id())
+        );
+        Ok(())
+    }
+
+    /// Returns an error if the state that the query needs is not kept up to date. On a chain
+    /// that is only synchronized partially, only the tracked applications can be queried, as
+    /// long as all their transactions could be executed.
+    fn ensure_query_is_available(&self, query: &Query) -> Result<(), WorkerError> {
+        let Some(applications) = self.chain.application_filter.get() else {
+            return Ok(());
+        };
+        let chain_id = self.chain_id();
+        match query {
+            Query::User { application_id, .. } if applications.contains(application_id) => {
+                if let Some(height) = *self.chain.tracked_state_lost_at.get() {
+                    return Err(WorkerError::TrackedStateLost { chain_id, height });
+                }
+                Ok(())
+            }
+            _ => Err(WorkerError::PartiallySynchronizedChain(chain_id)),
+        }
+    }
 }
 
 /// Returns the keys whose value is `None`.
@@ -668,6 +875,53 @@ fn missing_blob_ids(maybe_blobs: &BTreeMap<BlobId, Option<Blob>>) -> Vec<BlobId>
         .collect()
 }
 
+/// Verifies a certificate at the validator's `local_time`, taking the validator weight updates
+/// within its epoch into account in both directions.
+///
+/// The certificate is checked against `committee`, i.e. the chain's or the cached committee of
+/// its epoch, and then against the committee the chain had before its last weight update, if
+/// that update's grace window hasn't ended yet. The grace windows are measured with the
+/// validator's clock, not the block's timestamp, which the proposer chooses. If both fail, the
+/// certificate may be signed according to weights that `committee` doesn't reflect yet, or
+/// according to weights that the chain doesn't have anymore, e.g. after moving to a new epoch:
+/// the versions of the committee recorded when the admin chain processed the updates are then
+/// tried. These are read from the `committees` cache, so that invalid certificates don't cause
+/// storage reads.
+pub(crate) async fn check_certificate_with_weight_updates<S, T>(
+    storage: &S,
+    committees: &CommitteeCache,
+    certificate: &GenericCertificate<T>,
+    local_time: Timestamp,
+    committee: &Committee,
+    superseded_committee: Option<&Committee>,
+) -> Result<(), WorkerError>
+where
+    S: Storage,
+    T: CertificateValue,
+{
+    let Err(error) = certificate.check(committee) else {
+        return Ok(());
+    };
+    if superseded_committee.is_some_and(|committee| certificate.check(committee).is_ok()) {
+        return Ok(());
+    }
+    let epoch = certificate.inner().epoch();
+    if let Some(epoch_committee) = committees.get_or_load(storage, epoch).await? {
+        let is_valid = [
+            Some(&epoch_committee.committee),
+            epoch_committee.superseded_committee(local_time),
+        ]
+        .into_iter()
+        .flatten()
+        .filter(|version| *version != committee && Some(*version) != superseded_committee)
+        .any(|version| certificate.check(version).is_ok());
+        if is_valid {
+            return Ok(());
+        }
+    }
+    Err(error.into())
+}
+
 /// Returns an error if the block is not at the expected epoch.
 fn check_block_epoch(
     chain_epoch: Epoch,
@@ -4,27 +4,34 @@
 //! Operations that don't persist any changes to the chain state.
 
 use linera_base::{
-    data_types::{ApplicationDescription, ArithmeticError, Blob, Round, Timestamp},
+    crypto::CryptoHash,
+    data_types::{
+        Amount, ApplicationDescription, ArithmeticError, Blob, BlockHeight, Round, Timestamp,
+    },
     ensure,
-    identifiers::{AccountOwner, ApplicationId},
+    identifiers::{AccountOwner, ApplicationId, EventStreamInfo},
 };
+#[cfg(with_testing)]
+use linera_chain::types::ConfirmedBlockCertificate;
 use linera_chain::{
     data_types::{
         BlockExecutionOutcome, BlockProposal, IncomingBundle, MessageAction, OriginalProposal,
-        ProposalContent, ProposedBlock,
+        ProposalContent, ProposedBlock, SponsorApproval,
     },
-    manager,
+    manager::{self, RoundSchedule},
     types::Block,
+    ChainError,
 };
 use linera_execution::{Query, QueryOutcome};
-use linera_storage::{Clock as _, Storage};
-use linera_views::views::{ClonableView, View};
-#[cfg(with_testing)]
-use {linera_base::data_types::BlockHeight, linera_chain::types::ConfirmedBlockCertificate};
+use linera_storage::{Clock as _, ExecutionStateDelta, Storage};
+use linera_views::{
+    batch::Batch,
+    views::{ClonableView, View},
+};
 
 use super::ChainWorkerState;
 use crate::{
-    data_types::{ChainInfo, ChainInfoQuery, ChainInfoResponse},
+    data_types::{ChainInfo, ChainInfoQuery, ChainInfoResponse, LogRange},
     worker::WorkerError,
 };
 
@@ -76,6 +83,7 @@ where
         query: Query,
     ) -> Result<QueryOutcome, WorkerError> {
         self.0.ensure_is_active().await?;
+        self.0.ensure_query_is_available(&query)?;
         let local_time = self.0.storage.clock().current_time();
         let outcome = self
             .0
@@ -91,6 +99,7 @@ where
         application_id: ApplicationId,
     ) -> Result<ApplicationDescription, WorkerError> {
         self.0.ensure_is_active().await?;
+        self.0.ensure_execution_state_is_available()?;
         let response = self.0.chain.describe_application(application_id).await?;
         Ok(response)
     }
@@ -103,14 +112,18 @@ where
         published_blobs: &[Blob],
     ) -> Result<(Block, ChainInfoResponse), WorkerError> {
         self.0.ensure_is_active().await?;
+        self.0.ensure_execution_state_is_available()?;
         let local_time = self.0.storage.clock().current_time();
         let signer = block.authenticated_signer;
         let (_, committee) = self.0.chain.current_committee()?;
         block.check_proposal_size(committee.policy().maximum_block_proposal_size)?;
 
+        let block_hash = CryptoHash::new(&block);
+        self.0.prefetch_read_set(block_hash).await;
         let outcome = self
             .execute_block(&block, local_time, round, published_blobs)
             .await?;
+        self.0.record_read_set(block_hash);
 
         let mut response = ChainInfoResponse::new(&self.0.chain, None);
         if let Some(signer) = signer {
@@ -128,11 +141,19 @@ where
     }
 
     /// Validates a proposal's signatures; returns `manager::Outcome::Skip` if we already voted
-    /// for it.
+    /// for it. The `schedule` of the proposal's round from the pacemaker, if any, determines
+    /// its leader.
     pub(super) async fn check_proposed_block(
-        &self,
+        &mut self,
         proposal: &BlockProposal,
+        schedule: Option<RoundSchedule>,
     ) -> Result<manager::Outcome, WorkerError> {
+        if let Some(schedule) = schedule {
+            self.0
+                .chain
+                .manager
+                .apply_round_schedule(proposal.content.round, schedule);
+        }
         proposal
             .check_invariants()
             .map_err(|msg| WorkerError::InvalidBlockProposal(msg.to_string()))?;
@@ -142,6 +163,8 @@ where
             content,
             original_proposal,
             signature: _,
+            idempotency_key: _,
+            sponsor_approval,
         } = proposal;
         let block = &content.block;
         let chain = &self.0.chain;
@@ -150,9 +173,21 @@ where
         super::check_block_epoch(epoch, block.chain_id, block.epoch)?;
         let policy = committee.policy().clone();
         block.check_proposal_size(policy.maximum_block_proposal_size)?;
-        // Check the authentication of the block.
+        // Check the authentication of the block. In multi-leader rounds, a non-owner may also
+        // propose if the chain's authorization application accepts the signer when the block is
+        // executed.
+        let is_authorized_by_application = proposal.content.round.is_multi_leader()
+            && block.authorization_payload().is_some()
+            && block.authenticated_signer == Some(owner)
+            && chain
+                .execution_state
+                .system
+                .authorization_application
+                .get()
+                .is_some();
         ensure!(
-            chain.manager.verify_owner(&owner, proposal.content.round)?,
+            is_authorized_by_application
+                || chain.manager.verify_owner(&owner, proposal.content.round)?,
             WorkerError::InvalidOwner
         );
         match original_proposal {
@@ -175,6 +210,8 @@ where
                     },
                     signature: *signature,
                     original_proposal: None,
+                    idempotency_key: None,
+                    sponsor_approval: None,
                 };
                 let super_owner = original_proposal.owner();
                 ensure!(
@@ -193,6 +230,16 @@ where
                 original_proposal.check_signature()?;
             }
         }
+        if !matches!(original_proposal, Some(OriginalProposal::Regular { .. })) {
+            // Unless a quorum already validated the block, its fee sponsor must approve it.
+            match sponsor_approval {
+                Some(approval) => approval.check(block)?,
+                None => ensure!(
+                    block.fee_sponsor().is_none(),
+                    ChainError::InvalidSponsorApproval
+                ),
+            }
+        }
         // Check if the chain is ready for this new block proposal.
         chain.tip_state.get().verify_block_chaining(block)?;
         Ok(chain.manager.check_proposed_block(proposal)?)
@@ -202,6 +249,7 @@ where
     pub(super) async fn validate_proposal_content(
         &mut self,
         content: &ProposalContent,
+        sponsor_approval: Option<&SponsorApproval>,
         published_blobs: &[Blob],
     ) -> Result<Option<(BlockExecutionOutcome, Timestamp)>, WorkerError> {
         let ProposalContent {
@@ -215,9 +263,25 @@ where
             block.timestamp.duration_since(local_time) <= self.0.config.grace_period,
             WorkerError::InvalidTimestamp
         );
-        self.0.storage.clock().sleep_until(block.timestamp).await;
+        let block_hash = CryptoHash::new(block);
+        let sleep = self.0.storage.clock().sleep_until(block.timestamp);
+        if outcome.is_none() {
+            // Warm up the cache of the chain state while waiting for the block's timestamp.
+            futures::join!(sleep, self.0.prefetch_read_set(block_hash));
+        } else {
+            sleep.await;
+        }
         let local_time = self.0.storage.clock().current_time();
 
+        // Only the inboxes of the block's origins and those with pending bundles are needed.
+        let chain = &self.0.chain;
+        let nonempty_origins = chain.nonempty_inbox_origins().await?;
+        let origins = block
+            .incoming_bundles
+            .iter()
+            .map(|bundle| &bundle.origin)
+            .chain(&nonempty_origins);
+        chain.prefetch_inboxes(origins).await?;
         self.0
             .chain
             .remove_bundles_from_inboxes(block.timestamp, &block.incoming_bundles)
@@ -225,8 +289,25 @@ where
         let outcome = if let Some(outcome) = outcome {
             outcome.clone()
         } else {
-            self.execute_block(block, local_time, round.multi_leader(), published_blobs)
-                .await?
+            let sponsor_balance = match sponsor_approval {
+                Some(approval) => Some(self.owner_balance(approval.sponsor()).await?),
+                None => None,
+            };
+            let outcome = self
+                .execute_block(block, local_time, round.multi_leader(), published_blobs)
+                .await?;
+            self.0.record_read_set(block_hash);
+            if let (Some(approval), Some(balance)) = (sponsor_approval, sponsor_balance) {
+                let fees = balance.saturating_sub(self.owner_balance(approval.sponsor()).await?);
+                ensure!(
+                    fees <= approval.max_fees,
+                    WorkerError::SponsorFeeLimitExceeded {
+                        fees,
+                        max_fees: approval.max_fees,
+                    }
+                );
+            }
+            outcome
         };
 
         ensure!(
@@ -245,6 +326,12 @@ where
         Ok(Some((outcome, local_time)))
     }
 
+    /// Returns the balance of the given owner's account on this chain.
+    async fn owner_balance(&self, owner: AccountOwner) -> Result<Amount, WorkerError> {
+        let balances = &self.0.chain.execution_state.system.balances;
+        Ok(balances.get(&owner).await?.unwrap_or_default())
+    }
+
     /// Prepares a [`ChainInfoResponse`] for a [`ChainInfoQuery`].
     pub(super) async fn prepare_chain_info_response(
         &mut self,
@@ -277,7 +364,7 @@ where
         }
         if query.request_pending_message_bundles {
             let mut messages = Vec::new();
-            let pairs = chain.inboxes.try_load_all_entries().await?;
+            let pairs = chain.load_nonempty_inboxes().await?;
             let action = if *chain.execution_state.system.closed.get() {
                 MessageAction::Reject
             } else {
@@ -297,23 +384,56 @@ where
         }
         if let Some(range) = query.request_sent_certificate_hashes_in_range {
             let start: usize = range.start.try_into()?;
-            let end = match range.limit {
-                None => chain.confirmed_log.count(),
-                Some(limit) => start
-                    .checked_add(usize::try_from(limit).map_err(|_| ArithmeticError::Overflow)?)
-                    .ok_or(ArithmeticError::Overflow)?
-                    .min(chain.confirmed_log.count()),
-            };
+            let end = log_range_end(start, range.limit, chain.confirmed_log.count())?;
             let keys = chain.confirmed_log.read(start..end).await?;
             info.requested_sent_certificate_hashes = keys;
+            info.next_sent_certificate_hashes_cursor =
+                Some(BlockHeight::from(end.max(start) as u64));
         }
-        if let Some(start) = query.request_received_log_excluding_first_n {
-            let start = usize::try_from(start).map_err(|_| ArithmeticError::Overflow)?;
-            info.requested_received_log = chain.received_log.read(start..).await?;
+        let received_log_range = query.request_received_log_in_range.or(query
+            .request_received_log_excluding_first_n
+            .map(|start| LogRange { start, limit: None }));
+        if let Some(range) = received_log_range {
+            let start = usize::try_from(range.start).map_err(|_| ArithmeticError::Overflow)?;
+            let end = log_range_end(start, range.limit, chain.received_log.count())?;
+            info.requested_received_log = chain.received_log.read(start..end).await?;
+            info.next_received_log_cursor = Some(end.max(start) as u64);
         }
         if query.request_manager_values {
             info.manager.add_values(&chain.manager);
         }
+        if let Some(count) = query.request_vote_audit_log {
+            let count = usize::try_from(count).map_err(|_| ArithmeticError::Overflow)?;
+            info.requested_vote_audit_log = chain.vote_audit_log.read_back(count).await?;
+        }
+        if query.request_event_streams {
+            info.requested_event_streams = chain
+                .event_streams
+                .index_values()
+                .await?
+                .into_iter()
+                .map(|(stream_id, max_index)| EventStreamInfo {
+                    stream_id,
+                    max_index,
+                })
+                .collect();
+        }
+        if let Some((application_id, key)) = &query.request_state_inclusion_proof {
+            let execution_state = &chain.execution_state;
+            ensure!(
+                execution_state.uses_merkle_state_hash(),
+                WorkerError::NoMerkleStateHash(query.chain_id)
+            );
+            info.requested_state_inclusion_proof = execution_state
+                .inclusion_proof(*application_id, key)
+                .await?;
+        }
+        if let Some(fields) = &query.request_fields {
+            info.retain_fields(fields);
+        }
+        if let Some(max_bytes) = query.max_response_bytes {
+            info.truncate_to(max_bytes);
+        }
         Ok(ChainInfoResponse::new(info, self.0.config.key_pair()))
     }
 
@@ -332,6 +452,23 @@ where
                     .execute_block(block, local_time, round, published_blobs, None),
             )
             .await?;
+        let max_deltas = self.0.config.max_execution_state_deltas;
+        let base_state_hash = *self.0.chain.execution_state_hash.get();
+        if let Some(base_state_hash) = base_state_hash.filter(|_| max_deltas > 0) {
+            // Persist the changes to the execution state, for a restarted worker to apply them
+            // once the block is confirmed.
+            let mut batch = Batch::new();
+            self.0
+                .chain
+                .execution_state
+                .clone_unchecked()?
+                .flush(&mut batch)?;
+            let delta = ExecutionStateDelta::new(base_state_hash, outcome.state_hash, batch)?;
+            self.0
+                .storage
+                .write_execution_state_delta(&delta, max_deltas)
+                .await?;
+        }
         self.0.execution_state_cache.insert_owned(
             &outcome.state_hash,
             self.0.chain.execution_state.clone_unchecked()?,
@@ -348,3 +485,15 @@ where
         self.0.chain.rollback();
     }
 }
+
+/// Returns the end of the range of up to `limit` entries starting at `start`, in a log with
+/// `count` entries.
+fn log_range_end(start: usize, limit: Option<u64>, count: usize) -> Result<usize, ArithmeticError> {
+    match limit {
+        None => Ok(count),
+        Some(limit) => Ok(start
+            .checked_add(usize::try_from(limit).map_err(|_| ArithmeticError::Overflow)?)
+            .ok_or(ArithmeticError::Overflow)?
+            .min(count)),
+    }
+}
@@ -0,0 +1,148 @@
+// Copyright (c) Zefchain Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! Batching of several operations in one block, keeping track of the outcome of each.
+//!
+//! Each operation added to a [`BlockBuilder`] is labeled. Once the block is confirmed, the
+//! result, messages, events and blobs of each operation are taken from the block and
+//! reported under the operation's label, in the order in which the operations were added.
+
+use std::collections::BTreeSet;
+
+use linera_base::data_types::{Blob, Event};
+use linera_chain::types::ConfirmedBlockCertificate;
+use linera_execution::{Operation, OutgoingMessage};
+use serde::de::DeserializeOwned;
+
+use super::{ChainClient, ChainClientError};
+use crate::{data_types::ClientOutcome, environment::Environment};
+
+/// Builds a block out of labeled operations.
+pub struct BlockBuilder<'a, Env: Environment> {
+    chain_client: &'a ChainClient<Env>,
+    labels: Vec<String>,
+    operations: Vec<Operation>,
+    blobs: Vec<Blob>,
+}
+
+impl<'a, Env: Environment> BlockBuilder<'a, Env> {
+    pub(super) fn new(chain_client: &'a ChainClient<Env>) -> Self {
+        BlockBuilder {
+            chain_client,
+            labels: Vec::new(),
+            operations: Vec::new(),
+            blobs: Vec::new(),
+        }
+    }
+
+    /// Adds an operation to the block. Its outcome is reported under the given label, which
+    /// must be unique within the block.
+    pub fn with_operation(mut self, label: impl Into<String>, operation: Operation) -> Self {
+        self.labels.push(label.into());
+        self.operations.push(operation);
+        self
+    }
+
+    /// Adds a blob to be published by the block.
+    pub fn with_blob(mut self, blob: Blob) -> Self {
+        self.blobs.push(blob);
+        self
+    }
+
+    /// Executes the operations in a single block, and reports the outcome of each of them
+    /// once the block is confirmed.
+    pub async fn execute(self) -> Result<ClientOutcome<BlockReport>, ChainClientError> {
+        let mut labels = BTreeSet::new();
+        for label in &self.labels {
+            if !labels.insert(label) {
+                return Err(ChainClientError::DuplicateOperationLabel(label.clone()));
+            }
+        }
+        let outcome = self
+            .chain_client
+            .execute_operations(self.operations, self.blobs)
+            .await?;
+        Ok(outcome.map(|certificate| BlockReport::new(certificate, self.labels)))
+    }
+}
+
+/// The outcome of the labeled operations of a confirmed block.
+#[derive(Clone, Debug)]
+pub struct BlockReport {
+    /// The certificate of the confirmed block.
+    pub certificate: ConfirmedBlockCertificate,
+    /// The outcome of each operation, in the order in which they were added.
+    pub operations: Vec<OperationReport>,
+}
+
+/// The outcome of one labeled operation.
+#[derive(Clone, Debug)]
+pub struct OperationReport {
+    /// The label of the operation.
+    pub label: String,
+    /// The index of the operation among the operations of the block.
+    pub index: usize,
+    /// The serialized result returned by the operation.
+    pub result: Vec<u8>,
+    /// The messages sent by the operation.
+    pub messages: Vec<OutgoingMessage>,
+    /// The events emitted by the operation.
+    pub events: Vec<Event>,
+    /// The blobs created by the operation.
+    pub blobs: Vec<Blob>,
+}
+
+impl BlockReport {
+    fn new(certificate: ConfirmedBlockCertificate, labels: Vec<String>) -> Self {
+        let body = &certificate.block().body;
+        // The incoming bundles are executed before the operations.
+        let offset = body.incoming_bundles.len();
+        let operations = labels
+            .into_iter()
+            .enumerate()
+            .map(|(index, label)| {
+                let transaction_index = offset + index;
+                OperationReport {
+                    label,
+                    index,
+                    result: body
+                        .operation_results
+                        .get(index)
+                        .map(|result| result.0.clone())
+                        .unwrap_or_default(),
+                    messages: body
+                        .messages
+                        .get(transaction_index)
+                        .cloned()
+                        .unwrap_or_default(),
+                    events: body
+                        .events
+                        .get(transaction_index)
+                        .cloned()
+                        .unwrap_or_default(),
+                    blobs: body
+                        .blobs
+                        .get(transaction_index)
+                        .cloned()
+                        .unwrap_or_default(),
+                }
+            })
+            .collect();
+        BlockReport {
+            certificate,
+            operations,
+        }
+    }
+
+    /// Returns the outcome of the operation with the given label.
+    pub fn operation(&self, label: &str) -> Option<&OperationReport> {
+        self.operations.iter().find(|report| report.label == label)
+    }
+}
+
+impl OperationReport {
+    /// Deserializes the result of the operation, e.g. the response of a user application.
+    pub fn deserialize_result<T: DeserializeOwned>(&self) -> Result<T, bcs::Error> {
+        bcs::from_bytes(&self.result)
+    }
+}
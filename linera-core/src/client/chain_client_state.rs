@@ -5,7 +5,7 @@
 use std::{collections::BTreeSet, sync::Arc};
 
 use linera_base::data_types::Blob;
-use linera_chain::data_types::ProposedBlock;
+use linera_chain::data_types::{ProposedBlock, SponsorApproval};
 use tokio::sync::Mutex;
 
 use super::PendingProposal;
@@ -53,7 +53,18 @@ impl ChainClientState {
             block.published_blob_ids(),
             BTreeSet::from_iter(blobs.iter().map(Blob::id))
         );
-        self.pending_proposal = Some(PendingProposal { block, blobs });
+        self.pending_proposal = Some(PendingProposal {
+            block,
+            blobs,
+            sponsor_approval: None,
+        });
+    }
+
+    /// Attaches the fee sponsor's approval to the pending proposal, if there is one.
+    pub(super) fn set_sponsor_approval(&mut self, approval: SponsorApproval) {
+        if let Some(pending) = &mut self.pending_proposal {
+            pending.sponsor_approval = Some(approval);
+        }
     }
 
     pub(super) fn update_from_info(&mut self, info: &ChainInfo) {
@@ -0,0 +1,318 @@
+// Copyright (c) Zefchain Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! Trusted checkpoints of the admin chain.
+//!
+//! A new client learns the committees of all epochs by downloading and executing the admin
+//! chain from its genesis block, which gets slower as the network ages. Instead, a client can
+//! be configured with a [`TrustedCheckpoint`]: the committee of an epoch, together with the
+//! hash of an admin chain block at that point. The checkpoint replaces the genesis committee
+//! as the root of trust. From there, the client only follows the admin chain's certificates
+//! forward, checking that each of them extends the previous one and is signed by the
+//! committee of its epoch, and records the committees of the epochs created on the way. The
+//! admin chain itself is not executed.
+
+use std::sync::RwLock;
+
+use futures::future;
+use linera_base::{
+    crypto::CryptoHash,
+    data_types::{Blob, BlockHeight, Epoch},
+    ensure,
+    identifiers::{BlobId, BlobType, EventId, StreamId},
+};
+use linera_chain::types::ConfirmedBlockCertificate;
+use linera_execution::{
+    committee::Committee,
+    system::{EPOCH_STREAM_NAME, REMOVED_EPOCH_STREAM_NAME},
+};
+use linera_storage::Storage as _;
+use rand::prelude::SliceRandom as _;
+use serde::{Deserialize, Serialize};
+use tracing::{info, warn};
+
+use super::{ChainClientError, Client};
+use crate::{environment::Environment, node::NodeError, remote_node::RemoteNode};
+
+/// The number of admin chain certificates requested from a validator at once.
+const CERTIFICATE_BATCH_SIZE: u64 = 100;
+
+/// A state of the admin chain that a client trusts without verifying its history.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TrustedCheckpoint {
+    /// The latest epoch created on the admin chain up to the checkpoint.
+    pub epoch: Epoch,
+    /// The committee of that epoch.
+    pub committee: Committee,
+    /// The height of the admin chain block at the checkpoint.
+    pub admin_height: BlockHeight,
+    /// The hash of the certificate of that block.
+    pub admin_block_hash: CryptoHash,
+}
+
+impl TrustedCheckpoint {
+    /// Returns the blob that the admin chain published for the checkpoint's committee.
+    fn committee_blob(&self) -> Result<Blob, ChainClientError> {
+        Ok(Blob::new_committee(bcs::to_bytes(&self.committee)?))
+    }
+}
+
+impl<Env: Environment> Client<Env> {
+    /// Makes the client use `checkpoint` as its root of trust. Its committee is recorded the
+    /// first time the client synchronizes a chain.
+    pub fn with_trusted_checkpoint(mut self, checkpoint: Option<TrustedCheckpoint>) -> Self {
+        self.trusted_checkpoint = RwLock::new(checkpoint);
+        self
+    }
+
+    /// Returns the admin chain checkpoint that the client trusts, if any.
+    pub fn trusted_checkpoint(&self) -> Option<TrustedCheckpoint> {
+        self.trusted_checkpoint
+            .read()
+            .expect("Panics should not happen while holding a lock to `trusted_checkpoint`")
+            .clone()
+    }
+
+    /// Replaces the admin chain checkpoint that the client trusts, and records its committee.
+    pub async fn set_trusted_checkpoint(
+        &self,
+        checkpoint: TrustedCheckpoint,
+    ) -> Result<(), ChainClientError> {
+        self.record_checkpoint_committee(&checkpoint).await?;
+        self.replace_trusted_checkpoint(checkpoint);
+        Ok(())
+    }
+
+    fn replace_trusted_checkpoint(&self, checkpoint: TrustedCheckpoint) {
+        *self
+            .trusted_checkpoint
+            .write()
+            .expect("Panics should not happen while holding a lock to `trusted_checkpoint`") =
+            Some(checkpoint);
+    }
+
+    /// Records the committee of the trusted checkpoint, if any, in the local storage.
+    pub(super) async fn install_trusted_checkpoint(&self) -> Result<(), ChainClientError> {
+        if let Some(checkpoint) = self.trusted_checkpoint() {
+            self.record_checkpoint_committee(&checkpoint).await?;
+        }
+        Ok(())
+    }
+
+    /// Writes the committee of `checkpoint` to storage, as if the admin chain had been executed
+    /// up to the checkpoint, so that the local node accepts certificates of its epoch.
+    async fn record_checkpoint_committee(
+        &self,
+        checkpoint: &TrustedCheckpoint,
+    ) -> Result<(), ChainClientError> {
+        if checkpoint.epoch == Epoch::ZERO {
+            // The genesis committee is part of the network description.
+            return Ok(());
+        }
+        let storage = self.storage_client();
+        let blob = checkpoint.committee_blob()?;
+        let event_id = EventId {
+            chain_id: self.admin_id,
+            stream_id: StreamId::system(EPOCH_STREAM_NAME),
+            index: checkpoint.epoch.0,
+        };
+        let value = bcs::to_bytes(&blob.id().hash)?;
+        match storage.read_event(event_id.clone()).await? {
+            Some(known_value) => ensure!(
+                known_value == value,
+                ChainClientError::ConflictingTrustedCheckpoint(checkpoint.epoch)
+            ),
+            None => {
+                storage.write_blob(&blob).await?;
+                storage.write_events([(event_id, value)]).await?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Brings the committees up to date: follows the admin chain forward from the trusted
+    /// checkpoint if there is one, and synchronizes the whole admin chain otherwise.
+    pub(super) async fn synchronize_admin_committees(&self) -> Result<(), ChainClientError> {
+        if self.trusted_checkpoint().is_some() {
+            self.update_trusted_checkpoint().await?;
+        } else {
+            self.synchronize_chain_state(self.admin_id).await?;
+        }
+        Ok(())
+    }
+
+    /// Follows the admin chain forward from the trusted checkpoint, and moves the checkpoint
+    /// to the latest block that the validators return. Returns the new checkpoint.
+    ///
+    /// Faulty validators can only withhold blocks: every certificate must extend the previous
+    /// one and be signed by the committee of its epoch.
+    pub async fn update_trusted_checkpoint(&self) -> Result<TrustedCheckpoint, ChainClientError> {
+        let mut checkpoint = self
+            .trusted_checkpoint()
+            .ok_or(ChainClientError::NoTrustedCheckpoint)?;
+        self.record_checkpoint_committee(&checkpoint).await?;
+        loop {
+            let epoch = checkpoint.epoch;
+            let mut validators = self.make_nodes(&checkpoint.committee)?;
+            validators.shuffle(&mut rand::thread_rng());
+            for remote_node in &validators {
+                if let Err(error) = self
+                    .advance_checkpoint_from(remote_node, &validators, &mut checkpoint)
+                    .await
+                {
+                    warn!(
+                        "Failed to follow the admin chain from validator {}: {error}",
+                        remote_node.public_key
+                    );
+                }
+            }
+            // The validators of a new committee may know about even more recent blocks.
+            if checkpoint.epoch == epoch {
+                break;
+            }
+        }
+        info!(
+            epoch = %checkpoint.epoch,
+            height = %checkpoint.admin_height,
+            "Updated the trusted checkpoint"
+        );
+        self.replace_trusted_checkpoint(checkpoint.clone());
+        Ok(checkpoint)
+    }
+
+    /// Moves `checkpoint` forward along the admin chain certificates that `remote_node` has.
+    async fn advance_checkpoint_from(
+        &self,
+        remote_node: &RemoteNode<Env::ValidatorNode>,
+        validators: &[RemoteNode<Env::ValidatorNode>],
+        checkpoint: &mut TrustedCheckpoint,
+    ) -> Result<(), ChainClientError> {
+        loop {
+            let start = checkpoint.admin_height.try_add_one()?;
+            let certificates = remote_node
+                .query_certificates_from(self.admin_id, start, CERTIFICATE_BATCH_SIZE)
+                .await?;
+            if certificates.is_empty() {
+                return Ok(());
+            }
+            for certificate in &certificates {
+                self.extend_checkpoint(checkpoint, certificate, validators)
+                    .await?;
+            }
+        }
+    }
+
+    /// Checks that `certificate` is the admin chain block following `checkpoint`, records the
+    /// committees it creates or removes, and moves the checkpoint to it.
+    async fn extend_checkpoint(
+        &self,
+        checkpoint: &mut TrustedCheckpoint,
+        certificate: &ConfirmedBlockCertificate,
+        validators: &[RemoteNode<Env::ValidatorNode>],
+    ) -> Result<(), ChainClientError> {
+        let block = certificate.block();
+        let height = checkpoint.admin_height.try_add_one()?;
+        ensure!(
+            block.header.chain_id == self.admin_id
+                && block.header.height == height
+                && block.header.previous_block_hash == Some(checkpoint.admin_block_hash),
+            ChainClientError::InvalidCheckpointExtension(height)
+        );
+        let storage = self.storage_client();
+        let epoch = block.header.epoch;
+        if epoch == checkpoint.epoch {
+            certificate.check(&checkpoint.committee)?;
+        } else {
+            let committee = storage
+                .committees_for(epoch..=epoch)
+                .await?
+                .remove(&epoch)
+                .ok_or(ChainClientError::CommitteeSynchronizationError)?;
+            certificate.check(&committee)?;
+        }
+
+        let epoch_stream_id = StreamId::system(EPOCH_STREAM_NAME);
+        let removed_epoch_stream_id = StreamId::system(REMOVED_EPOCH_STREAM_NAME);
+        let mut events = Vec::new();
+        let mut new_committees = Vec::new();
+        for event in block.body.events.iter().flatten() {
+            if event.stream_id == epoch_stream_id {
+                let blob_hash = bcs::from_bytes::<CryptoHash>(&event.value)?;
+                new_committees.push((Epoch(event.index), blob_hash));
+            } else if event.stream_id != removed_epoch_stream_id {
+                continue;
+            }
+            let event_id = EventId {
+                chain_id: self.admin_id,
+                stream_id: event.stream_id.clone(),
+                index: event.index,
+            };
+            events.push((event_id, event.value.clone()));
+        }
+        for (new_epoch, blob_hash) in new_committees {
+            let blob_id = BlobId::new(blob_hash, BlobType::Committee);
+            let blob =
+                RemoteNode::download_blob(validators, blob_id, self.options.blob_download_timeout)
+                    .await
+                    .ok_or_else(|| NodeError::BlobsNotFound(vec![blob_id]))?;
+            let committee = bcs::from_bytes::<Committee>(blob.bytes())?;
+            storage.write_blob(&blob).await?;
+            if new_epoch > checkpoint.epoch {
+                checkpoint.epoch = new_epoch;
+                checkpoint.committee = committee;
+            }
+        }
+        // The events are written last: they make the new committees visible to the local node.
+        storage.write_events(events).await?;
+        checkpoint.admin_height = height;
+        checkpoint.admin_block_hash = certificate.hash();
+        Ok(())
+    }
+
+    /// Asks the validators of the checkpoint's committee for the admin chain block at the
+    /// checkpoint's height. Fails unless validators with a quorum of the votes return the
+    /// block that the checkpoint refers to.
+    pub async fn audit_trusted_checkpoint(&self) -> Result<(), ChainClientError> {
+        let checkpoint = self
+            .trusted_checkpoint()
+            .ok_or(ChainClientError::NoTrustedCheckpoint)?;
+        let validators = self.make_nodes(&checkpoint.committee)?;
+        let confirmations = future::join_all(validators.iter().map(|remote_node| async {
+            let certificates = remote_node
+                .query_certificates_from(self.admin_id, checkpoint.admin_height, 1)
+                .await;
+            match certificates.as_deref() {
+                Ok([certificate, ..]) if certificate.hash() == checkpoint.admin_block_hash => true,
+                Ok([certificate, ..]) => {
+                    warn!(
+                        "Validator {} has a different admin chain block {} at height {}",
+                        remote_node.public_key,
+                        certificate.hash(),
+                        checkpoint.admin_height
+                    );
+                    false
+                }
+                Ok([]) => false,
+                Err(error) => {
+                    warn!(
+                        "Failed to audit the trusted checkpoint with validator {}: {error}",
+                        remote_node.public_key
+                    );
+                    false
+                }
+            }
+        }))
+        .await;
+        let confirmed_weight = validators
+            .iter()
+            .zip(confirmations)
+            .filter(|(_, is_confirmed)| *is_confirmed)
+            .map(|(remote_node, _)| checkpoint.committee.weight(&remote_node.public_key))
+            .sum::<u64>();
+        ensure!(
+            confirmed_weight >= checkpoint.committee.quorum_threshold(),
+            ChainClientError::TrustedCheckpointNotConfirmed(checkpoint.admin_height)
+        );
+        Ok(())
+    }
+}
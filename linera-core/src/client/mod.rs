@@ -3,6 +3,7 @@
 // SPDX-License-Identifier: Apache-2.0
 
 use std::{
+    cmp::Reverse,
     collections::{hash_map, BTreeMap, BTreeSet, HashMap, HashSet},
     convert::Infallible,
     iter,
@@ -11,6 +12,7 @@ use std::{
 };
 
 use chain_client_state::ChainClientState;
+use checkpoint::TrustedCheckpoint;
 use custom_debug_derive::Debug;
 use dashmap::{
     mapref::one::{MappedRef as DashMapMappedRef, Ref as DashMapRef, RefMut as DashMapRefMut},
@@ -24,15 +26,15 @@ use futures::{
 use linera_base::prometheus_util::MeasureLatency as _;
 use linera_base::{
     abi::Abi,
-    crypto::{signer, AccountPublicKey, CryptoHash, Signer, ValidatorPublicKey},
+    crypto::{signer, AccountPublicKey, AccountSignature, CryptoHash, Signer, ValidatorPublicKey},
     data_types::{
         Amount, ApplicationPermissions, ArithmeticError, Blob, BlobContent, BlockHeight,
-        ChainDescription, Epoch, Round, Timestamp,
+        ChainDescription, Epoch, FeeBreakdown, Round, TimeDelta, Timestamp,
     },
     ensure,
     identifiers::{
-        Account, AccountOwner, ApplicationId, BlobId, BlobType, ChainId, EventId, IndexAndEvent,
-        ModuleId, StreamId,
+        Account, AccountOwner, ApplicationId, BlobId, BlobType, ChainId, EventId, EventStreamInfo,
+        GenericApplicationId, IndexAndEvent, ModuleId, StreamId,
     },
     ownership::{ChainOwnership, TimeoutConfig},
     time::{Duration, Instant},
@@ -41,7 +43,8 @@ use linera_base::{
 use linera_base::{data_types::Bytecode, vm::VmRuntime};
 use linera_chain::{
     data_types::{
-        BlockProposal, ChainAndHeight, IncomingBundle, LiteVote, MessageAction, ProposedBlock,
+        BlockProposal, ChainAndHeight, IncomingBundle, LiteVote, MessageAction,
+        ProposalSigningRequest, ProposedBlock, SponsorApproval,
     },
     manager::LockingBlock,
     types::{
@@ -53,10 +56,12 @@ use linera_chain::{
 use linera_execution::{
     committee::Committee,
     system::{
-        AdminOperation, OpenChainConfig, Recipient, SystemOperation, EPOCH_STREAM_NAME,
-        REMOVED_EPOCH_STREAM_NAME,
+        AdminOperation, InboxQuotas, OpenChainConfig, OperationPermissions, Recipient,
+        SignedValidatorAddress, StagedApplicationPermissions, SystemOperation, EPOCH_STREAM_NAME,
+        REMOVED_EPOCH_STREAM_NAME, VALIDATOR_WEIGHTS_STREAM_NAME,
     },
-    ExecutionError, Operation, Query, QueryOutcome, QueryResponse, SystemQuery, SystemResponse,
+    BlobProvenance, ExecutionError, Operation, Query, QueryOutcome, QueryResponse, SystemQuery,
+    SystemResponse,
 };
 use linera_storage::{Clock as _, ResultReadCertificates, Storage as _};
 use linera_views::ViewError;
@@ -69,24 +74,37 @@ use tracing::{debug, error, info, instrument, warn, Instrument as _};
 
 use crate::{
     data_types::{
-        BlockHeightRange, ChainInfo, ChainInfoQuery, ChainInfoResponse, ClientOutcome, RoundTimeout,
+        BlockHeightRange, ChainDebugBundle, ChainInfo, ChainInfoQuery, ChainInfoResponse,
+        ClientOutcome, RoundTimeout,
     },
     environment::Environment,
     local_node::{LocalChainInfoExt as _, LocalNodeClient, LocalNodeError},
     node::{
-        CrossChainMessageDelivery, NodeError, NotificationStream, ValidatorNode,
-        ValidatorNodeProvider as _,
+        CrossChainMessageDelivery, NodeError, NotificationStream, StreamedCertificate,
+        ValidatorNode, ValidatorNodeProvider as _,
     },
     notifier::ChannelNotifier,
     remote_node::RemoteNode,
-    updater::{communicate_with_quorum, CommunicateAction, CommunicationError, ValidatorUpdater},
+    updater::{
+        communicate_with_quorum, CommunicateAction, CommunicationError, DownloadThroughputs,
+        HedgingConfig, ValidatorLatencies, ValidatorUpdater,
+    },
     worker::{Notification, ProcessableCertificate, Reason, WorkerError, WorkerState},
 };
 
+pub mod block_builder;
 mod chain_client_state;
+pub mod checkpoint;
 #[cfg(test)]
 #[path = "../unit_tests/client_tests.rs"]
 mod client_tests;
+pub mod monitoring;
+pub mod rebase;
+pub mod reproposal;
+
+/// The number of certificates requested from a validator at a time when downloading a chain.
+// TODO(#2045): Analyze network errors instead of guessing the batch size.
+const CERTIFICATE_BATCH_SIZE: u64 = 1000;
 
 #[cfg(with_metrics)]
 mod metrics {
@@ -159,6 +177,16 @@ pub struct Client<Env: Environment> {
     chains: DashMap<ChainId, ChainClientState>,
     /// Configuration options.
     options: ChainClientOptions,
+    /// The latencies of the validators, to decide which ones to contact first.
+    validator_latencies: ValidatorLatencies,
+    /// The download throughputs of the validators, to decide which ones to download
+    /// certificates from.
+    download_throughputs: DownloadThroughputs,
+    /// The admin chain checkpoint used as the root of trust instead of genesis, if any.
+    trusted_checkpoint: RwLock<Option<TrustedCheckpoint>>,
+    /// The network addresses announced by the validators on the admin chain, as of the last
+    /// time the validators were looked up.
+    announced_addresses: RwLock<BTreeMap<ValidatorPublicKey, String>>,
 }
 
 impl<Env: Environment> Client<Env> {
@@ -182,7 +210,8 @@ impl<Env: Environment> Client<Env> {
         .with_long_lived_services(long_lived_services)
         .with_allow_inactive_chains(true)
         .with_allow_messages_from_deprecated_epochs(true)
-        .with_chain_worker_ttl(chain_worker_ttl);
+        .with_chain_worker_ttl(chain_worker_ttl)
+        .with_execution_state_history(options.execution_state_history);
         let local_node = LocalNodeClient::new(state);
 
         Self {
@@ -193,6 +222,10 @@ impl<Env: Environment> Client<Env> {
             tracked_chains,
             notifier: Arc::new(ChannelNotifier::default()),
             options,
+            validator_latencies: ValidatorLatencies::default(),
+            download_throughputs: DownloadThroughputs::default(),
+            trusted_checkpoint: RwLock::default(),
+            announced_addresses: RwLock::default(),
         }
     }
 
@@ -205,6 +238,11 @@ impl<Env: Environment> Client<Env> {
         self.environment.network()
     }
 
+    /// Returns the options that new chain clients are created with.
+    pub fn options(&self) -> &ChainClientOptions {
+        &self.options
+    }
+
     /// Returns a reference to the [`LocalNodeClient`] of the client.
     #[instrument(level = "trace", skip(self))]
     pub fn local_node(&self) -> &LocalNodeClient<Env::Storage> {
@@ -226,6 +264,26 @@ impl<Env: Environment> Client<Env> {
             .insert(chain_id);
     }
 
+    /// Adds a chain to the set of chains tracked by the local node, but only downloads the
+    /// block headers and the events of the given applications.
+    ///
+    /// Only the transactions of the given applications are executed, so only these
+    /// applications can be queried, and proposing blocks on the chain fails. If another
+    /// application calls them, or if their transactions use the state of the system or of
+    /// other applications, e.g. balances, their state is not maintained anymore.
+    #[instrument(level = "trace", skip(self))]
+    pub async fn track_chain_partially(
+        &self,
+        chain_id: ChainId,
+        applications: BTreeSet<ApplicationId>,
+    ) -> Result<(), ChainClientError> {
+        self.local_node
+            .set_application_filter(chain_id, applications)
+            .await?;
+        self.track_chain(chain_id);
+        Ok(())
+    }
+
     /// Creates a new `ChainClient`.
     #[instrument(level = "trace", skip_all, fields(chain_id, next_block_height))]
     pub fn create_chain_client(
@@ -260,11 +318,13 @@ impl<Env: Environment> Client<Env> {
         chain_id: ChainId,
         validators: &[RemoteNode<Env::ValidatorNode>],
     ) -> Result<Box<ChainInfo>, ChainClientError> {
+        // The local node can't check certificates of the checkpoint's epoch without it.
+        self.install_trusted_checkpoint().await?;
         match self.local_node.chain_info(chain_id).await {
             Ok(info) => Ok(info),
             Err(LocalNodeError::BlobsNotFound(blob_ids)) => {
-                // Make sure the admin chain is up to date.
-                self.synchronize_chain_state(self.admin_id).await?;
+                // Make sure the committees are up to date.
+                self.synchronize_admin_committees().await?;
                 // If the chain is missing then the error is a WorkerError
                 // and so a BlobsNotFound
                 self.update_local_node_with_blobs_from(blob_ids, validators)
@@ -283,22 +343,56 @@ impl<Env: Environment> Client<Env> {
         target_next_block_height: BlockHeight,
     ) -> Result<Box<ChainInfo>, ChainClientError> {
         let mut validators = self.validator_nodes().await?;
-        // Sequentially try each validator in random order.
+        // Validators with the same expected throughput are tried in random order.
         validators.shuffle(&mut rand::thread_rng());
+        self.download_throughputs.sort(&mut validators);
         let mut info = self.fetch_chain_info(chain_id, &validators).await?;
+        let missing =
+            u64::from(target_next_block_height).saturating_sub(u64::from(info.next_block_height));
+        if missing > CERTIFICATE_BATCH_SIZE
+            && validators.len() > 1
+            && self.options.max_parallel_certificate_downloads > 1
+        {
+            match self
+                .download_certificate_ranges(&validators, chain_id, target_next_block_height)
+                .await
+            {
+                Err(err) => {
+                    warn!("Failed to download certificates of chain {chain_id} in parallel: {err}")
+                }
+                Ok(Some(new_info)) => info = new_info,
+                Ok(None) => {}
+            }
+        }
+        // Sequentially try each validator, fastest first.
         for remote_node in validators {
             if target_next_block_height <= info.next_block_height {
                 return Ok(info);
             }
+            let start_height = info.next_block_height;
+            let start_time = Instant::now();
             match self
                 .download_certificates_from(&remote_node, chain_id, target_next_block_height)
                 .await
             {
-                Err(err) => warn!(
-                    "Failed to download certificates from validator {:?}: {err}",
-                    remote_node.public_key
-                ),
-                Ok(Some(new_info)) => info = new_info,
+                Err(err) => {
+                    self.download_throughputs
+                        .record_failure(remote_node.public_key);
+                    warn!(
+                        "Failed to download certificates from validator {:?}: {err}",
+                        remote_node.public_key
+                    )
+                }
+                Ok(Some(new_info)) => {
+                    let downloaded = u64::from(new_info.next_block_height)
+                        .saturating_sub(u64::from(start_height));
+                    self.download_throughputs.record(
+                        remote_node.public_key,
+                        downloaded,
+                        start_time.elapsed(),
+                    );
+                    info = new_info;
+                }
                 Ok(None) => {}
             }
         }
@@ -321,8 +415,62 @@ impl<Env: Environment> Client<Env> {
         chain_id: ChainId,
         stop: BlockHeight,
     ) -> Result<Option<Box<ChainInfo>>, ChainClientError> {
-        let mut last_info = None;
         // First load any blocks from local storage, if available.
+        let (mut next_height, mut last_info) = self
+            .process_preprocessed_certificates(chain_id, stop)
+            .await?;
+        // Now download the rest from the remote node, preferably over a single stream.
+        if next_height < stop {
+            match self
+                .stream_certificates_from(
+                    remote_node,
+                    chain_id,
+                    &mut next_height,
+                    stop,
+                    &mut last_info,
+                )
+                .await
+            {
+                Ok(()) => {}
+                Err(ChainClientError::RemoteNodeError(
+                    NodeError::CertificateStreamingNotSupported { .. },
+                )) => {}
+                Err(error) => warn!(
+                    "Failed to stream certificates of chain {chain_id} from validator {}: {error}; \
+                     downloading them in batches",
+                    remote_node.public_key
+                ),
+            }
+        }
+        // Download anything the stream did not provide in batches.
+        while next_height < stop {
+            let limit = u64::from(stop)
+                .checked_sub(u64::from(next_height))
+                .ok_or(ArithmeticError::Overflow)?
+                .min(CERTIFICATE_BATCH_SIZE);
+            let certificates = remote_node
+                .query_certificates_from(chain_id, next_height, limit)
+                .await?;
+            let Some(info) = self.process_certificates(remote_node, certificates).await? else {
+                break;
+            };
+            assert!(info.next_block_height > next_height);
+            next_height = info.next_block_height;
+            last_info = Some(info);
+        }
+        Ok(last_info)
+    }
+
+    /// Processes the certificates of the chain from its tip up to (excluding) `stop` that
+    /// are already in local storage because their blocks were preprocessed. Returns the
+    /// height of the first missing certificate and the chain info after the last processed
+    /// one, if any.
+    async fn process_preprocessed_certificates(
+        &self,
+        chain_id: ChainId,
+        stop: BlockHeight,
+    ) -> Result<(BlockHeight, Option<Box<ChainInfo>>), ChainClientError> {
+        let mut last_info = None;
         let mut hashes = Vec::new();
         let mut next_height = BlockHeight::ZERO;
         {
@@ -349,26 +497,164 @@ impl<Env: Environment> Client<Env> {
         for certificate in certificates {
             last_info = Some(self.handle_certificate(Box::new(certificate)).await?.info);
         }
-        // Now download the rest in batches from the remote node.
-        while next_height < stop {
-            // TODO(#2045): Analyze network errors instead of guessing the batch size.
-            let limit = u64::from(stop)
-                .checked_sub(u64::from(next_height))
-                .ok_or(ArithmeticError::Overflow)?
-                .min(1000);
-            let certificates = remote_node
-                .query_certificates_from(chain_id, next_height, limit)
-                .await?;
+        Ok((next_height, last_info))
+    }
+
+    /// Downloads and processes the certificates up to (excluding) `stop`, in batches that are
+    /// requested from several validators in parallel, and processed in order.
+    ///
+    /// Each batch is requested from the validators in turn, starting with a different one of
+    /// the first `max_parallel_certificate_downloads` for consecutive batches. This stops at
+    /// the first batch that no validator returned completely, leaving the rest to the caller.
+    #[instrument(level = "trace", skip_all)]
+    async fn download_certificate_ranges(
+        &self,
+        validators: &[RemoteNode<Env::ValidatorNode>],
+        chain_id: ChainId,
+        stop: BlockHeight,
+    ) -> Result<Option<Box<ChainInfo>>, ChainClientError> {
+        let (next_height, mut last_info) = self
+            .process_preprocessed_certificates(chain_id, stop)
+            .await?;
+        let sources = self
+            .options
+            .max_parallel_certificate_downloads
+            .clamp(1, validators.len().max(1));
+        let ranges = (u64::from(next_height)..u64::from(stop))
+            .step_by(CERTIFICATE_BATCH_SIZE as usize)
+            .map(|start| {
+                let limit = (u64::from(stop) - start).min(CERTIFICATE_BATCH_SIZE);
+                (BlockHeight(start), limit)
+            });
+        let mut batches = stream::iter(ranges.enumerate())
+            .map(|(index, (start, limit))| {
+                self.download_certificate_range(validators, index % sources, chain_id, start, limit)
+            })
+            .buffered(sources);
+        while let Some(batch) = batches.next().await {
+            let Some((remote_node, certificates)) = batch else {
+                break;
+            };
             let Some(info) = self.process_certificates(remote_node, certificates).await? else {
                 break;
             };
-            assert!(info.next_block_height > next_height);
-            next_height = info.next_block_height;
             last_info = Some(info);
         }
         Ok(last_info)
     }
 
+    /// Downloads the `limit` certificates of the chain from height `start` on, asking the
+    /// validators in turn, beginning with `validators[first]`, until one returns all of them.
+    async fn download_certificate_range<'a>(
+        &self,
+        validators: &'a [RemoteNode<Env::ValidatorNode>],
+        first: usize,
+        chain_id: ChainId,
+        start: BlockHeight,
+        limit: u64,
+    ) -> Option<(
+        &'a RemoteNode<Env::ValidatorNode>,
+        Vec<ConfirmedBlockCertificate>,
+    )> {
+        for remote_node in validators.iter().cycle().skip(first).take(validators.len()) {
+            let start_time = Instant::now();
+            match remote_node
+                .query_certificates_from(chain_id, start, limit)
+                .await
+            {
+                Ok(certificates)
+                    if certificates.len() as u64 == limit
+                        && certificates.iter().zip(u64::from(start)..).all(
+                            |(certificate, height)| {
+                                certificate.block().header.height == BlockHeight(height)
+                            },
+                        ) =>
+                {
+                    self.download_throughputs.record(
+                        remote_node.public_key,
+                        limit,
+                        start_time.elapsed(),
+                    );
+                    return Some((remote_node, certificates));
+                }
+                Ok(certificates) => {
+                    self.download_throughputs
+                        .record_failure(remote_node.public_key);
+                    debug!(
+                        "Validator {} returned {} of the {limit} certificates of chain {chain_id} \
+                         from height {start}",
+                        remote_node.public_key,
+                        certificates.len(),
+                    );
+                }
+                Err(error) => {
+                    self.download_throughputs
+                        .record_failure(remote_node.public_key);
+                    warn!(
+                        "Failed to download certificates of chain {chain_id} from height {start} \
+                         from validator {}: {error}",
+                        remote_node.public_key
+                    );
+                }
+            }
+        }
+        None
+    }
+
+    /// Downloads and processes the certificates from `next_height` up to (excluding) `stop`
+    /// over a single stream from the given validator, using the blobs sent along with them.
+    /// `next_height` and `last_info` are updated after each processed certificate, so the
+    /// caller can resume from there if the stream fails.
+    #[instrument(level = "trace", skip_all)]
+    async fn stream_certificates_from(
+        &self,
+        remote_node: &RemoteNode<Env::ValidatorNode>,
+        chain_id: ChainId,
+        next_height: &mut BlockHeight,
+        stop: BlockHeight,
+        last_info: &mut Option<Box<ChainInfo>>,
+    ) -> Result<(), ChainClientError> {
+        let mut stream = remote_node
+            .stream_certificates_from(chain_id, *next_height)
+            .await?;
+        while *next_height < stop {
+            let Some(streamed) = stream.next().await else {
+                break;
+            };
+            let StreamedCertificate { certificate, blobs } = streamed?;
+            ensure!(
+                certificate.block().header.height == *next_height,
+                NodeError::UnexpectedCertificateValue
+            );
+            let certificate = Box::new(certificate);
+            let mut result = self.handle_certificate(certificate.clone()).await;
+            if let Err(LocalNodeError::BlobsNotFound(_)) = &result {
+                if !blobs.is_empty() {
+                    // The certificate was checked, so the local node now accepts its blobs.
+                    self.local_node.store_blobs(&blobs).await?;
+                    result = self.handle_certificate(certificate.clone()).await;
+                }
+            }
+            let info = match result {
+                Ok(response) => response.info,
+                // Some blobs were not sent along; download them as usual.
+                Err(LocalNodeError::BlobsNotFound(_)) => {
+                    let Some(info) = self
+                        .process_certificates(remote_node, vec![*certificate])
+                        .await?
+                    else {
+                        break;
+                    };
+                    info
+                }
+                Err(error) => return Err(error.into()),
+            };
+            *next_height = info.next_block_height;
+            *last_info = Some(info);
+        }
+        Ok(())
+    }
+
     /// Tries to process all the certificates, requesting any missing blobs from the given node.
     /// Returns the chain info of the last successfully processed certificate.
     #[instrument(level = "trace", skip_all)]
@@ -377,6 +663,18 @@ impl<Env: Environment> Client<Env> {
         remote_node: &RemoteNode<impl ValidatorNode>,
         certificates: Vec<ConfirmedBlockCertificate>,
     ) -> Result<Option<Box<ChainInfo>>, ChainClientError> {
+        if certificates.len() > 1 {
+            // Each certificate needs the committee of its epoch: read them all at once.
+            let epochs = certificates
+                .iter()
+                .map(|certificate| certificate.block().header.epoch)
+                .collect::<BTreeSet<_>>();
+            if let (Some(first), Some(last)) = (epochs.first(), epochs.last()) {
+                if let Err(error) = self.local_node.prefetch_committees(*first..=*last).await {
+                    debug!(%error, "Failed to prefetch the committees");
+                }
+            }
+        }
         let mut info = None;
         for certificate in certificates {
             let certificate = Box::new(certificate);
@@ -429,13 +727,27 @@ impl<Env: Environment> Client<Env> {
         &self,
     ) -> Result<(Epoch, BTreeMap<Epoch, Committee>), LocalNodeError> {
         let info = self.chain_info_with_committees(self.admin_id).await?;
-        Ok((info.epoch, info.into_committees()?))
+        let epoch = info.epoch;
+        let mut committees = info.into_committees()?;
+        match self.trusted_checkpoint() {
+            Some(checkpoint) if checkpoint.epoch > epoch => {
+                committees.insert(checkpoint.epoch, checkpoint.committee);
+                Ok((checkpoint.epoch, committees))
+            }
+            _ => Ok((epoch, committees)),
+        }
     }
 
-    /// Obtains the committee for the latest epoch on the admin chain.
+    /// Obtains the committee for the latest epoch on the admin chain, or of the trusted
+    /// checkpoint if that is newer.
     pub async fn admin_committee(&self) -> Result<(Epoch, Committee), LocalNodeError> {
         let info = self.chain_info_with_committees(self.admin_id).await?;
-        Ok((info.epoch, info.into_current_committee()?))
+        match self.trusted_checkpoint() {
+            Some(checkpoint) if checkpoint.epoch > info.epoch => {
+                Ok((checkpoint.epoch, checkpoint.committee))
+            }
+            _ => Ok((info.epoch, info.into_current_committee()?)),
+        }
     }
 
     /// Obtains the validators for the latest epoch.
@@ -443,21 +755,115 @@ impl<Env: Environment> Client<Env> {
         &self,
     ) -> Result<Vec<RemoteNode<Env::ValidatorNode>>, ChainClientError> {
         let (_, committee) = self.admin_committee().await?;
+        self.update_announced_addresses().await?;
         Ok(self.make_nodes(&committee)?)
     }
 
+    /// Reloads the network addresses that the validators announced on the admin chain, as
+    /// far as the admin chain is known locally.
+    async fn update_announced_addresses(&self) -> Result<(), LocalNodeError> {
+        let admin_chain = self.local_node.chain_state_view(self.admin_id).await?;
+        let mut addresses = BTreeMap::new();
+        admin_chain
+            .execution_state
+            .system
+            .validator_addresses
+            .for_each_index_value(|public_key, announcement| {
+                addresses.insert(public_key, announcement.address.network_address.clone());
+                Ok(())
+            })
+            .await?;
+        *self
+            .announced_addresses
+            .write()
+            .expect("Panics should not happen while holding a lock to the announced addresses") =
+            addresses;
+        Ok(())
+    }
+
+    /// Returns the network addresses that the validators announced on the admin chain, as of
+    /// the last time the validators were looked up. These take precedence over the addresses
+    /// in the committee.
+    pub fn announced_addresses(&self) -> BTreeMap<ValidatorPublicKey, String> {
+        self.announced_addresses
+            .read()
+            .expect("Panics should not happen while holding a lock to the announced addresses")
+            .clone()
+    }
+
     /// Creates a [`RemoteNode`] for each validator in the committee.
+    ///
+    /// Validators are contacted at the addresses they announced on the admin chain, if any.
+    /// If one of these is not understood by the node provider, the committee's addresses are
+    /// used instead.
     fn make_nodes(
         &self,
         committee: &Committee,
     ) -> Result<Vec<RemoteNode<Env::ValidatorNode>>, NodeError> {
-        Ok(self
-            .validator_node_provider()
-            .make_nodes(committee)?
+        let announced_addresses = self.announced_addresses();
+        let provider = self.validator_node_provider();
+        let addresses = committee
+            .validator_addresses()
+            .map(|(public_key, address)| {
+                let address = announced_addresses
+                    .get(&public_key)
+                    .map_or(address, String::as_str);
+                (public_key, address)
+            })
+            .collect::<Vec<_>>();
+        let nodes = match provider.make_nodes_from_list(addresses) {
+            Ok(nodes) => nodes.collect::<Vec<_>>(),
+            Err(error) => {
+                warn!(
+                    "Using the committee's validator addresses instead of the announced ones: \
+                    {error}"
+                );
+                provider.make_nodes(committee)?.collect()
+            }
+        };
+        Ok(nodes
+            .into_iter()
             .map(|(public_key, node)| RemoteNode { public_key, node })
             .collect())
     }
 
+    /// Asks the validators of the current committee what they know about a blob. Returns
+    /// their answers, starting with the validators that are most likely to serve the blob's
+    /// content: the ones that have it, and among those, the ones that saw it used in the
+    /// latest epoch. Validators that fail to answer are left out.
+    #[instrument(level = "trace", skip(self))]
+    pub async fn locate_blob(
+        &self,
+        blob_id: BlobId,
+    ) -> Result<Vec<(ValidatorPublicKey, BlobProvenance)>, ChainClientError> {
+        let validators = self.validator_nodes().await?;
+        let results = future::join_all(
+            validators
+                .iter()
+                .map(|remote_node| remote_node.node.blob_provenance(blob_id)),
+        )
+        .await;
+        let mut located = validators
+            .iter()
+            .zip(results)
+            .filter_map(|(remote_node, result)| match result {
+                Ok(provenance) => Some((remote_node.public_key, provenance)),
+                Err(error) => {
+                    warn!(
+                        "Failed to query validator {} about blob {blob_id}: {error}",
+                        remote_node.public_key
+                    );
+                    None
+                }
+            })
+            .collect::<Vec<_>>();
+        located.sort_by_key(|(_, provenance)| {
+            let epoch = provenance.state.as_ref().and_then(|state| state.epoch);
+            Reverse((provenance.has_content, epoch))
+        });
+        Ok(located)
+    }
+
     /// Ensures that the client has the `ChainDescription` blob corresponding to this
     /// client's `ChainId`.
     pub async fn get_chain_description(
@@ -475,7 +881,7 @@ impl<Env: Environment> Client<Env> {
             return Ok(bcs::from_bytes(blob.bytes())?);
         };
         // Recover history from the current validators, according to the admin chain.
-        self.synchronize_chain_state(self.admin_id).await?;
+        self.synchronize_admin_committees().await?;
         let nodes = self.validator_nodes().await?;
         let blob = self
             .update_local_node_with_blobs_from(vec![chain_desc_id], &nodes)
@@ -579,6 +985,8 @@ impl<Env: Environment> Client<Env> {
                 })
             },
             self.options.grace_period,
+            &self.validator_latencies,
+            self.options.hedging.as_ref(),
         )
         .await?;
         Ok(())
@@ -611,6 +1019,8 @@ impl<Env: Environment> Client<Env> {
                 Box::pin(async move { updater.send_chain_update(action).await })
             },
             self.options.grace_period,
+            &self.validator_latencies,
+            self.options.hedging.as_ref(),
         )
         .await?;
         ensure!(
@@ -996,6 +1406,8 @@ impl<Env: Environment> Client<Env> {
                     .await
             },
             self.options.grace_period,
+            &self.validator_latencies,
+            self.options.hedging.as_ref(),
         )
         .await?;
 
@@ -1235,7 +1647,7 @@ impl<Env: Environment> Client<Env> {
 
     /// Attempts to execute the block locally. If any incoming message execution fails, that
     /// message is rejected and execution is retried, until the block accepts only messages
-    /// that succeed.
+    /// that succeed. Messages that are too old to be accepted are rejected as well.
     // TODO(#2806): Measure how failing messages affect the execution times.
     #[tracing::instrument(level = "trace", skip(self, block))]
     async fn stage_block_execution_and_discard_failing_messages(
@@ -1275,6 +1687,21 @@ impl<Env: Environment> Client<Env> {
                         continue;
                     }
                 }
+                if let ChainError::IncomingBundleTooOld { origin, height, .. } = &**chain_error {
+                    if let Some(message) = block.incoming_bundles.iter_mut().find(|message| {
+                        message.origin == *origin
+                            && message.bundle.height == *height
+                            && message.action == MessageAction::Accept
+                    }) {
+                        // Expired messages can't be accepted anymore: reject them instead.
+                        info!(
+                            origin = ?message.origin,
+                            "Message is too old to be accepted and will be rejected."
+                        );
+                        message.action = MessageAction::Reject;
+                        continue;
+                    }
+                }
             }
             return result;
         }
@@ -1393,6 +1820,17 @@ pub struct ChainClientOptions {
     pub grace_period: f64,
     /// The delay when downloading a blob, after which we try a second validator.
     pub blob_download_timeout: Duration,
+    /// How to hedge requests to a quorum of validators, if at all.
+    pub hedging: Option<HedgingConfig>,
+    /// The number of blocks before the latest one whose execution states the local node can
+    /// query.
+    pub execution_state_history: u64,
+    /// The maximum number of validators to download the certificates of a long chain from in
+    /// parallel.
+    pub max_parallel_certificate_downloads: usize,
+    /// Whether and how operations are proposed again after their block was preempted by
+    /// another one. If `None`, the error is returned instead.
+    pub reproposal: Option<reproposal::ReproposalConfig>,
 }
 
 #[cfg(with_testing)]
@@ -1406,6 +1844,10 @@ impl ChainClientOptions {
             cross_chain_message_delivery: CrossChainMessageDelivery::NonBlocking,
             grace_period: DEFAULT_GRACE_PERIOD,
             blob_download_timeout: Duration::from_secs(1),
+            hedging: None,
+            execution_state_history: 0,
+            max_parallel_certificate_downloads: 1,
+            reproposal: None,
         }
     }
 }
@@ -1544,6 +1986,24 @@ pub enum ChainClientError {
 
     #[error("Epoch is already revoked")]
     EpochAlreadyRevoked,
+
+    #[error("Operation label {0:?} is used more than once in the same block")]
+    DuplicateOperationLabel(String),
+
+    #[error("Application {0} is not tracked on this partially synchronized chain")]
+    UntrackedApplication(ApplicationId),
+
+    #[error("No trusted checkpoint is configured")]
+    NoTrustedCheckpoint,
+
+    #[error("The admin chain block at height {0} does not extend the trusted checkpoint")]
+    InvalidCheckpointExtension(BlockHeight),
+
+    #[error("The trusted checkpoint conflicts with the committee of epoch {0} known locally")]
+    ConflictingTrustedCheckpoint(Epoch),
+
+    #[error("The trusted checkpoint at height {0} is not confirmed by a quorum of validators")]
+    TrustedCheckpointNotConfirmed(BlockHeight),
 }
 
 impl From<Infallible> for ChainClientError {
@@ -1556,6 +2016,154 @@ impl ChainClientError {
     pub fn signer_failure(err: impl signer::Error + 'static) -> Self {
         Self::Signer(Box::new(err))
     }
+
+    /// Returns a stable code identifying the kind of error, for programmatic clients: the
+    /// code of the underlying worker, chain or validator error, if any.
+    pub fn code(&self) -> &str {
+        match self {
+            ChainClientError::LocalNodeError(LocalNodeError::WorkerError(error)) => error.code(),
+            ChainClientError::RemoteNodeError(error)
+            | ChainClientError::CommunicationError(CommunicationError::Trusted(error)) => {
+                error.code()
+            }
+            ChainClientError::ChainError(error) => error.code(),
+            ChainClientError::LocalNodeError(LocalNodeError::ArithmeticError(_))
+            | ChainClientError::ArithmeticError(_) => "ArithmeticError",
+            ChainClientError::LocalNodeError(LocalNodeError::ViewError(_))
+            | ChainClientError::ViewError(_) => "ViewError",
+            ChainClientError::LocalNodeError(LocalNodeError::InactiveChain(_)) => "InactiveChain",
+            ChainClientError::LocalNodeError(LocalNodeError::InvalidChainInfoResponse) => {
+                "InvalidChainInfoResponse"
+            }
+            ChainClientError::LocalNodeError(LocalNodeError::BlobsNotFound(_)) => "BlobsNotFound",
+            ChainClientError::CommunicationError(CommunicationError::NoConsensus(..)) => {
+                "NoConsensus"
+            }
+            ChainClientError::CommunicationError(CommunicationError::Sample(_)) => {
+                "CommunicationError"
+            }
+            ChainClientError::ReadCertificatesError(..) => "ReadCertificatesError",
+            ChainClientError::MissingConfirmedBlock(..) => "MissingConfirmedBlock",
+            ChainClientError::JsonError(..) => "JsonError",
+            ChainClientError::InternalError(..) => "InternalError",
+            ChainClientError::CommitteeSynchronizationError => "CommitteeSynchronizationError",
+            ChainClientError::WalletSynchronizationError => "WalletSynchronizationError",
+            ChainClientError::BlockProposalError(..) => "BlockProposalError",
+            ChainClientError::CommitteeDeprecationError => "CommitteeDeprecationError",
+            ChainClientError::ProtocolError(..) => "ProtocolError",
+            ChainClientError::CannotFindKeyForChain(..) => "CannotFindKeyForChain",
+            ChainClientError::NoAccountKeyConfigured(..) => "NoAccountKeyConfigured",
+            ChainClientError::NotAnOwner(..) => "NotAnOwner",
+            ChainClientError::CannotDownloadCertificates { .. } => "CannotDownloadCertificates",
+            ChainClientError::BcsError(..) => "BcsError",
+            ChainClientError::UnexpectedQuorum { .. } => "UnexpectedQuorum",
+            ChainClientError::Signer(..) => "Signer",
+            ChainClientError::CannotRevokeCurrentEpoch(..) => "CannotRevokeCurrentEpoch",
+            ChainClientError::EpochAlreadyRevoked => "EpochAlreadyRevoked",
+            ChainClientError::DuplicateOperationLabel(..) => "DuplicateOperationLabel",
+            ChainClientError::UntrackedApplication(..) => "UntrackedApplication",
+            ChainClientError::NoTrustedCheckpoint => "NoTrustedCheckpoint",
+            ChainClientError::InvalidCheckpointExtension(..) => "InvalidCheckpointExtension",
+            ChainClientError::ConflictingTrustedCheckpoint(..) => "ConflictingTrustedCheckpoint",
+            ChainClientError::TrustedCheckpointNotConfirmed(..) => "TrustedCheckpointNotConfirmed",
+        }
+    }
+
+    /// Returns whether the same operation may succeed if it is retried later, e.g. after
+    /// synchronizing with the validators.
+    pub fn is_retriable(&self) -> bool {
+        match self {
+            ChainClientError::LocalNodeError(LocalNodeError::WorkerError(error)) => {
+                error.is_retriable()
+            }
+            ChainClientError::RemoteNodeError(error)
+            | ChainClientError::CommunicationError(CommunicationError::Trusted(error)) => {
+                error.is_retriable()
+            }
+            ChainClientError::ChainError(error) => error.is_retriable(),
+            ChainClientError::LocalNodeError(
+                LocalNodeError::ViewError(_)
+                | LocalNodeError::InactiveChain(_)
+                | LocalNodeError::BlobsNotFound(_),
+            )
+            | ChainClientError::CommunicationError(
+                CommunicationError::NoConsensus(..) | CommunicationError::Sample(_),
+            )
+            | ChainClientError::ViewError(_)
+            | ChainClientError::CommitteeSynchronizationError
+            | ChainClientError::WalletSynchronizationError
+            | ChainClientError::CannotDownloadCertificates { .. }
+            | ChainClientError::TrustedCheckpointNotConfirmed(..)
+            | ChainClientError::UnexpectedQuorum { .. } => true,
+            _ => false,
+        }
+    }
+
+    /// Returns the blobs and events that the local node or the validators could not find, if
+    /// that is why the operation failed.
+    pub fn missing_data(&self) -> Option<MissingData> {
+        let mut missing = MissingData::default();
+        match self {
+            ChainClientError::LocalNodeError(LocalNodeError::BlobsNotFound(blob_ids)) => {
+                missing.add_blobs(blob_ids);
+            }
+            ChainClientError::LocalNodeError(LocalNodeError::WorkerError(
+                WorkerError::EventsNotFound(event_ids),
+            )) => missing.add_events(event_ids),
+            ChainClientError::RemoteNodeError(error)
+            | ChainClientError::CommunicationError(CommunicationError::Trusted(error)) => {
+                missing.add_node_error(error);
+            }
+            ChainClientError::CommunicationError(CommunicationError::Sample(errors)) => {
+                for (error, _) in errors {
+                    missing.add_node_error(error);
+                }
+            }
+            _ => {}
+        }
+        (!missing.is_empty()).then_some(missing)
+    }
+}
+
+/// The blobs and events that an operation failed to find, on the local node or on the
+/// validators.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct MissingData {
+    /// The missing blobs.
+    pub blobs: Vec<BlobId>,
+    /// The missing events.
+    pub events: Vec<EventId>,
+}
+
+impl MissingData {
+    /// Returns whether nothing is missing.
+    pub fn is_empty(&self) -> bool {
+        self.blobs.is_empty() && self.events.is_empty()
+    }
+
+    fn add_node_error(&mut self, error: &NodeError) {
+        match error {
+            NodeError::BlobsNotFound(blob_ids) => self.add_blobs(blob_ids),
+            NodeError::EventsNotFound(event_ids) => self.add_events(event_ids),
+            _ => {}
+        }
+    }
+
+    fn add_blobs(&mut self, blob_ids: &[BlobId]) {
+        for blob_id in blob_ids {
+            if !self.blobs.contains(blob_id) {
+                self.blobs.push(*blob_id);
+            }
+        }
+    }
+
+    fn add_events(&mut self, event_ids: &[EventId]) {
+        for event_id in event_ids {
+            if !self.events.contains(event_id) {
+                self.events.push(event_id.clone());
+            }
+        }
+    }
 }
 
 // We never want to pass the DashMap references over an `await` point, for fear of
@@ -1752,6 +2360,51 @@ impl<Env: Environment> ChainClient<Env> {
         Ok(response.info)
     }
 
+    /// Collects the local state of the chain and its confirmed certificates in the given
+    /// range of heights into a [`ChainDebugBundle`], e.g. to attach it to an issue report.
+    #[instrument(level = "trace")]
+    pub async fn debug_bundle(
+        &self,
+        range: BlockHeightRange,
+    ) -> Result<ChainDebugBundle, ChainClientError> {
+        let query = ChainInfoQuery::new(self.chain_id)
+            .with_manager_values()
+            .with_pending_message_bundles()
+            .with_committees()
+            .with_sent_certificate_hashes_in_range(range);
+        let info = self
+            .client
+            .local_node
+            .handle_chain_info_query(query)
+            .await?
+            .info;
+        let hashes = info.requested_sent_certificate_hashes.clone();
+        let certificates = self
+            .storage_client()
+            .read_certificates(hashes.clone())
+            .await?;
+        let certificates = match ResultReadCertificates::new(certificates, hashes) {
+            ResultReadCertificates::Certificates(certificates) => certificates,
+            ResultReadCertificates::InvalidHashes(hashes) => {
+                return Err(ChainClientError::ReadCertificatesError(hashes))
+            }
+        };
+        #[cfg(with_metrics)]
+        let metrics = prometheus::TextEncoder::new()
+            .encode_to_string(&prometheus::gather())
+            .ok();
+        #[cfg(not(with_metrics))]
+        let metrics = None;
+        Ok(ChainDebugBundle {
+            version: linera_version::VersionInfo::default(),
+            created_at: self.storage_client().clock().current_time(),
+            info,
+            certificates,
+            metrics,
+            logs: linera_base::tracing::recent_logs(),
+        })
+    }
+
     /// Returns the chain's description. Fetches it from the validators if necessary.
     pub async fn get_chain_description(&self) -> Result<ChainDescription, ChainClientError> {
         self.client.get_chain_description(self.chain_id).await
@@ -1874,7 +2527,28 @@ impl<Env: Environment> ChainClient<Env> {
     /// Returns an error if we don't have the private key for the identity.
     #[instrument(level = "trace")]
     pub async fn identity(&self) -> Result<AccountOwner, ChainClientError> {
-        let Some(preferred_owner) = self.preferred_owner else {
+        let preferred_owner = self.identity_without_key().await?;
+        let has_signer = self
+            .signer()
+            .contains_key(&preferred_owner)
+            .await
+            .map_err(ChainClientError::signer_failure)?;
+
+        if !has_signer {
+            warn!(%self.chain_id, ?preferred_owner,
+                "Chain is one of the owners but its Signer instance doesn't contain the key",
+            );
+            return Err(ChainClientError::CannotFindKeyForChain(self.chain_id));
+        }
+
+        Ok(preferred_owner)
+    }
+
+    /// Obtains the identity of the current owner of the chain, without checking that we have
+    /// its private key, e.g. because it is kept on another machine.
+    #[instrument(level = "trace")]
+    pub async fn identity_without_key(&self) -> Result<AccountOwner, ChainClientError> {
+        let Some(preferred_owner) = self.preferred_owner else {
             return Err(ChainClientError::NoAccountKeyConfigured(self.chain_id));
         };
         let manager = self.chain_info().await?.manager;
@@ -1901,19 +2575,6 @@ impl<Env: Environment> ChainClient<Env> {
             return Err(ChainClientError::NotAnOwner(self.chain_id));
         }
 
-        let has_signer = self
-            .signer()
-            .contains_key(&preferred_owner)
-            .await
-            .map_err(ChainClientError::signer_failure)?;
-
-        if !has_signer {
-            warn!(%self.chain_id, ?preferred_owner,
-                "Chain is one of the owners but its Signer instance doesn't contain the key",
-            );
-            return Err(ChainClientError::CannotFindKeyForChain(self.chain_id));
-        }
-
         Ok(preferred_owner)
     }
 
@@ -1934,9 +2595,7 @@ impl<Env: Environment> ChainClient<Env> {
         }
 
         if info.epoch > self.client.admin_committees().await?.0 {
-            self.client
-                .synchronize_chain_state(self.client.admin_id)
-                .await?;
+            self.client.synchronize_admin_committees().await?;
         }
 
         let result = self
@@ -2141,8 +2800,11 @@ impl<Env: Environment> ChainClient<Env> {
     }
 
     /// Synchronizes all chains that any application on this chain subscribes to.
-    /// We always consider the admin chain a relevant publishing chain, for new epochs.
+    /// We always consider the admin chain a relevant publishing chain, for new epochs. With a
+    /// trusted checkpoint, only its new committees are downloaded.
     async fn synchronize_publisher_chains(&self) -> Result<(), ChainClientError> {
+        let admin_id = self.client.admin_id;
+        let has_checkpoint = self.client.trusted_checkpoint().is_some();
         let chain_ids = self
             .chain_state_view()
             .await?
@@ -2153,7 +2815,7 @@ impl<Env: Environment> ChainClient<Env> {
             .await?
             .iter()
             .map(|(chain_id, _)| *chain_id)
-            .chain(iter::once(self.client.admin_id))
+            .chain((!has_checkpoint).then_some(admin_id))
             .filter(|chain_id| *chain_id != self.chain_id)
             .collect::<BTreeSet<_>>();
         future::try_join_all(
@@ -2162,6 +2824,9 @@ impl<Env: Environment> ChainClient<Env> {
                 .map(|chain_id| self.client.synchronize_chain_state(chain_id)),
         )
         .await?;
+        if has_checkpoint && self.chain_id != admin_id {
+            self.client.update_trusted_checkpoint().await?;
+        }
         Ok(())
     }
 
@@ -2196,6 +2861,8 @@ impl<Env: Environment> ChainClient<Env> {
                 })
             },
             self.options.grace_period,
+            &self.client.validator_latencies,
+            self.options.hedging.as_ref(),
         )
         .await;
         let received_certificate_batches = match result {
@@ -2264,6 +2931,18 @@ impl<Env: Environment> ChainClient<Env> {
         .await
     }
 
+    /// Withdraws money from the account that this chain owns in a remote chain.
+    #[instrument(level = "trace")]
+    pub async fn withdraw_from_chain_account(
+        &self,
+        target_id: ChainId,
+        recipient: Recipient,
+        amount: Amount,
+    ) -> Result<ClientOutcome<ConfirmedBlockCertificate>, ChainClientError> {
+        let owner = AccountOwner::Chain(self.chain_id);
+        self.claim(owner, target_id, recipient, amount).await
+    }
+
     /// Requests a leader timeout vote from all validators. If a quorum signs it, creates a
     /// certificate and sends it to all validators, to make them enter the next round.
     #[instrument(level = "trace")]
@@ -2302,7 +2981,16 @@ impl<Env: Environment> ChainClient<Env> {
         self.client.synchronize_chain_state(chain_id).await
     }
 
+    /// Returns a builder for a block of labeled operations, whose outcomes are reported
+    /// separately.
+    pub fn block_builder(&self) -> block_builder::BlockBuilder<'_, Env> {
+        block_builder::BlockBuilder::new(self)
+    }
+
     /// Executes a list of operations.
+    ///
+    /// If the block is preempted by another one and [`ChainClientOptions::reproposal`] is
+    /// set, the operations are proposed again unless they were committed in the meantime.
     #[instrument(level = "trace", skip(operations, blobs))]
     pub async fn execute_operations(
         &self,
@@ -2310,11 +2998,20 @@ impl<Env: Environment> ChainClient<Env> {
         blobs: Vec<Blob>,
     ) -> Result<ClientOutcome<ConfirmedBlockCertificate>, ChainClientError> {
         let timing_start = std::time::Instant::now();
+        let mut reproposals = reproposal::Reproposals::new(self).await?;
 
         let result = loop {
             let execute_block_start = std::time::Instant::now();
             // TODO(#2066): Remove boxing once the call-stack is shallower
-            match Box::pin(self.execute_block(operations.clone(), blobs.clone())).await? {
+            let outcome =
+                match Box::pin(self.execute_block(operations.clone(), blobs.clone())).await {
+                    Ok(outcome) => outcome,
+                    Err(error) => match reproposals.handle_error(self, error).await? {
+                        Some(certificate) => break Ok(ClientOutcome::Committed(certificate)),
+                        None => continue,
+                    },
+                };
+            match outcome {
                 ExecuteBlockOutcome::Executed(certificate) => {
                     if let Some(sender) = &self.timing_sender {
                         let _ = sender.send((
@@ -2327,6 +3024,12 @@ impl<Env: Environment> ChainClient<Env> {
                 ExecuteBlockOutcome::WaitForTimeout(timeout) => {
                     break Ok(ClientOutcome::WaitForTimeout(timeout));
                 }
+                // Our preempted block may have been committed after all, in a later round.
+                ExecuteBlockOutcome::Conflict(certificate)
+                    if reproposals.is_fulfilled_by(&certificate) =>
+                {
+                    break Ok(ClientOutcome::Committed(certificate));
+                }
                 ExecuteBlockOutcome::Conflict(certificate) => {
                     info!(
                         height = %certificate.block().header.height,
@@ -2346,6 +3049,22 @@ impl<Env: Environment> ChainClient<Env> {
         result
     }
 
+    /// Executes a list of operations like [`ChainClient::execute_operations`], but whenever
+    /// another block is committed first, e.g. by another owner of a multi-owner chain, the
+    /// operations are rebased onto it before they are proposed again.
+    ///
+    /// Rebasing lets `hook` adjust or drop each operation, then drops the operations that
+    /// fail on top of the new chain state. See [`rebase::RebaseHook`].
+    #[instrument(level = "trace", skip(operations, blobs, hook))]
+    pub async fn execute_operations_with_rebase(
+        &self,
+        operations: Vec<Operation>,
+        blobs: Vec<Blob>,
+        hook: &mut impl rebase::RebaseHook,
+    ) -> Result<ClientOutcome<rebase::RebasedBlock>, ChainClientError> {
+        rebase::execute_with_rebase(self, operations, blobs, hook).await
+    }
+
     /// Executes an operation.
     pub async fn execute_operation(
         &self,
@@ -2355,6 +3074,194 @@ impl<Env: Environment> ChainClient<Env> {
             .await
     }
 
+    /// Executes a block with the given operations and the pending incoming messages in the
+    /// local node, without proposing it, and returns the fees it would cost by kind of
+    /// resource.
+    #[instrument(level = "trace", skip(operations, blobs))]
+    pub async fn estimate_fees(
+        &self,
+        operations: Vec<Operation>,
+        blobs: Vec<Blob>,
+    ) -> Result<FeeBreakdown, ChainClientError> {
+        let block = self.next_proposed_block(operations).await?;
+        loop {
+            let result = self
+                .client
+                .local_node
+                .simulate_block_execution(block.clone(), blobs.clone())
+                .await;
+            if let Err(LocalNodeError::BlobsNotFound(blob_ids)) = &result {
+                self.client
+                    .receive_certificates_for_blobs(blob_ids.clone())
+                    .await?;
+                continue; // We found the missing blob: retry.
+            }
+            let (block, _) = result?;
+            return Ok(block.body.fees);
+        }
+    }
+
+    /// Returns a block with the given operations and the pending incoming messages, as it
+    /// would be proposed next by this client.
+    async fn next_proposed_block(
+        &self,
+        operations: Vec<Operation>,
+    ) -> Result<ProposedBlock, ChainClientError> {
+        let incoming_bundles = self.pending_message_bundles().await?;
+        let identity = self.identity().await?;
+        let info = self.chain_info().await?;
+        let timestamp = self.next_timestamp(&incoming_bundles, info.timestamp);
+        Ok(ProposedBlock {
+            epoch: info.epoch,
+            chain_id: self.chain_id,
+            incoming_bundles,
+            operations,
+            previous_block_hash: info.block_hash,
+            height: info.next_block_height,
+            authenticated_signer: Some(identity),
+            timestamp,
+        })
+    }
+
+    /// Creates a new pending block whose fees are paid by `sponsor` instead of this client's
+    /// identity, and returns it so that it can be sent to the sponsor for approval.
+    ///
+    /// The block is proposed by [`ChainClient::process_pending_block`] once the sponsor's
+    /// approval was added with [`ChainClient::add_sponsor_approval`].
+    #[instrument(level = "trace", skip(operations, blobs))]
+    pub async fn prepare_sponsored_block(
+        &self,
+        operations: Vec<Operation>,
+        blobs: Vec<Blob>,
+        sponsor: AccountOwner,
+    ) -> Result<ProposedBlock, ChainClientError> {
+        let mutex = self.state().client_mutex();
+        let _guard = mutex.lock_owned().await;
+        let incoming_bundles = self.pending_message_bundles().await?;
+        let identity = self.identity().await?;
+        let operations = iter::once(Operation::system(SystemOperation::SponsorFees { sponsor }))
+            .chain(operations)
+            .collect();
+        let confirmed_value = self
+            .new_pending_block(incoming_bundles, operations, blobs, identity)
+            .await?;
+        let (block, _) = confirmed_value.block().clone().into_proposal();
+        Ok(block)
+    }
+
+    /// Agrees to pay for the fees of a block prepared by another client, as long as they don't
+    /// exceed `max_fees`. The block's fee sponsor must be this client's preferred owner.
+    #[instrument(level = "trace", skip(block))]
+    pub async fn approve_sponsored_block(
+        &self,
+        block: &ProposedBlock,
+        max_fees: Amount,
+    ) -> Result<SponsorApproval, ChainClientError> {
+        let sponsor = self
+            .preferred_owner
+            .ok_or(ChainClientError::NoAccountKeyConfigured(self.chain_id))?;
+        ensure!(
+            block.fee_sponsor() == Some(sponsor),
+            ChainClientError::BlockProposalError("The block is not sponsored by this client")
+        );
+        SponsorApproval::new(block, max_fees, sponsor, self.signer())
+            .await
+            .map_err(ChainClientError::signer_failure)
+    }
+
+    /// Adds the fee sponsor's approval to the pending block.
+    #[instrument(level = "trace", skip(approval))]
+    pub fn add_sponsor_approval(&self, approval: SponsorApproval) -> Result<(), ChainClientError> {
+        let mut state = self.state_mut();
+        let Some(pending_proposal) = state.pending_proposal() else {
+            return Err(ChainClientError::BlockProposalError(
+                "There is no pending block to add the sponsor approval to",
+            ));
+        };
+        approval.check(&pending_proposal.block)?;
+        state.set_sponsor_approval(approval);
+        Ok(())
+    }
+
+    /// Returns the request to sign the next block proposal, for an owner whose key is kept on
+    /// another machine, e.g. an air-gapped one.
+    ///
+    /// If `operations` is not empty, a new pending block with these operations is created
+    /// first. Otherwise the request is for the pending block or a validated block that must be
+    /// retried. Once signed, the proposal is submitted with
+    /// [`ChainClient::submit_signed_proposal`].
+    #[instrument(level = "trace", skip(operations, blobs))]
+    pub async fn export_proposal(
+        &self,
+        operations: Vec<Operation>,
+        blobs: Vec<Blob>,
+    ) -> Result<ClientOutcome<Option<ProposalSigningRequest>>, ChainClientError> {
+        self.prepare_chain().await?;
+        let mutex = self.state().client_mutex();
+        let _guard = mutex.lock_owned().await;
+        let owner = self.identity_without_key().await?;
+        let mut info = self.request_leader_timeout_if_needed().await?;
+        // Finalizing a validated block doesn't require the owner's signature.
+        if info.manager.has_locking_block_in_current_round()
+            && !info.manager.current_round.is_fast()
+        {
+            if let ClientOutcome::WaitForTimeout(timeout) =
+                self.finalize_locking_block(info).await?
+            {
+                return Ok(ClientOutcome::WaitForTimeout(timeout));
+            }
+            info = self.chain_info_with_manager_values().await?;
+        }
+        if !operations.is_empty() {
+            ensure!(
+                info.manager.requested_locking.is_none(),
+                ChainClientError::BlockProposalError(
+                    "A block from an earlier round must be retried first; \
+                        export its proposal without operations"
+                )
+            );
+            let incoming_bundles = self.pending_message_bundles().await?;
+            self.new_pending_block(incoming_bundles, operations, blobs, owner)
+                .await?;
+        }
+        Ok(self
+            .prepare_proposal(info, owner)
+            .await?
+            .map(|prepared| prepared.map(|prepared| prepared.request)))
+    }
+
+    /// Submits a proposal exported with [`ChainClient::export_proposal`], with the owner's
+    /// signature produced on another machine.
+    ///
+    /// Fails if the chain has changed since, so that the proposal would be different.
+    #[instrument(level = "trace", skip(request, signature))]
+    pub async fn submit_signed_proposal(
+        &self,
+        request: ProposalSigningRequest,
+        signature: AccountSignature,
+    ) -> Result<ClientOutcome<ConfirmedBlockCertificate>, ChainClientError> {
+        request.check_version()?;
+        self.synchronize_from_validators().await?;
+        let mutex = self.state().client_mutex();
+        let _guard = mutex.lock_owned().await;
+        let info = self.request_leader_timeout_if_needed().await?;
+        let prepared = match self.prepare_proposal(info, request.owner).await? {
+            ClientOutcome::Committed(prepared) => prepared,
+            ClientOutcome::WaitForTimeout(timeout) => {
+                return Ok(ClientOutcome::WaitForTimeout(timeout))
+            }
+        };
+        let Some(prepared) =
+            prepared.filter(|prepared| prepared.request.signing_hash() == request.signing_hash())
+        else {
+            return Err(ChainClientError::BlockProposalError(
+                "The signed proposal is outdated; export and sign it again",
+            ));
+        };
+        let certificate = self.submit_prepared_proposal(prepared, signature).await?;
+        Ok(ClientOutcome::Committed(certificate))
+    }
+
     /// Executes a new block.
     ///
     /// This must be preceded by a call to `prepare_chain()`.
@@ -2497,6 +3404,32 @@ impl<Env: Environment> ChainClient<Env> {
         }
     }
 
+    /// Queries an application as it was right after the block at the given height.
+    ///
+    /// This only succeeds if the local node keeps the history of the execution state back
+    /// to that height.
+    #[instrument(level = "trace", skip(query))]
+    pub async fn query_application_at(
+        &self,
+        query: Query,
+        height: BlockHeight,
+    ) -> Result<QueryOutcome, ChainClientError> {
+        loop {
+            let result = self
+                .client
+                .local_node
+                .query_application_at(self.chain_id, query.clone(), height)
+                .await;
+            if let Err(LocalNodeError::BlobsNotFound(blob_ids)) = &result {
+                self.client
+                    .receive_certificates_for_blobs(blob_ids.clone())
+                    .await?;
+                continue; // We found the missing blob: retry.
+            }
+            return Ok(result?);
+        }
+    }
+
     /// Queries a system application.
     #[instrument(level = "trace", skip(query))]
     pub async fn query_system_application(
@@ -2506,11 +3439,13 @@ impl<Env: Environment> ChainClient<Env> {
         let QueryOutcome {
             response,
             operations,
+            operation_metadata,
         } = self.query_application(Query::System(query)).await?;
         match response {
             QueryResponse::System(response) => Ok(QueryOutcome {
                 response,
                 operations,
+                operation_metadata,
             }),
             _ => Err(ChainClientError::InternalError(
                 "Unexpected response for system query",
@@ -2529,6 +3464,7 @@ impl<Env: Environment> ChainClient<Env> {
         let QueryOutcome {
             response,
             operations,
+            operation_metadata,
         } = self.query_application(query).await?;
         match response {
             QueryResponse::User(response_bytes) => {
@@ -2536,6 +3472,7 @@ impl<Env: Environment> ChainClient<Env> {
                 Ok(QueryOutcome {
                     response,
                     operations,
+                    operation_metadata,
                 })
             }
             _ => Err(ChainClientError::InternalError(
@@ -2753,10 +3690,35 @@ impl<Env: Environment> ChainClient<Env> {
             return self.finalize_locking_block(info).await;
         }
         let owner = self.identity().await?;
+        let prepared = match self.prepare_proposal(info, owner).await? {
+            ClientOutcome::Committed(Some(prepared)) => prepared,
+            ClientOutcome::Committed(None) => return Ok(ClientOutcome::Committed(None)),
+            ClientOutcome::WaitForTimeout(timeout) => {
+                return Ok(ClientOutcome::WaitForTimeout(timeout))
+            }
+        };
+        let signature = prepared
+            .request
+            .sign(self.signer())
+            .await
+            .map_err(ChainClientError::signer_failure)?;
+        let certificate = self.submit_prepared_proposal(prepared, signature).await?;
+        Ok(ClientOutcome::Committed(Some(certificate)))
+    }
 
+    /// Prepares the proposal of the highest validated block, or otherwise of the pending
+    /// block, by `owner`. The proposal still needs to be signed.
+    async fn prepare_proposal(
+        &self,
+        info: Box<ChainInfo>,
+        owner: AccountOwner,
+    ) -> Result<ClientOutcome<Option<PreparedProposal>>, ChainClientError> {
         let local_node = &self.client.local_node;
-        // Otherwise we have to re-propose the highest validated block, if there is one.
+        // We have to re-propose the highest validated block, if there is one.
         let pending_proposal = self.state().pending_proposal().clone();
+        let sponsor_approval = pending_proposal
+            .as_ref()
+            .and_then(|pending_proposal| pending_proposal.sponsor_approval.clone());
         let (block, blobs) = if let Some(locking) = &info.manager.requested_locking {
             match &**locking {
                 LockingBlock::Regular(certificate) => {
@@ -2818,27 +3780,46 @@ impl<Env: Environment> ChainClient<Env> {
         let already_handled_locally = info
             .manager
             .already_handled_proposal(round, &proposed_block);
-        // Create the final block proposal.
-        let proposal = if let Some(locking) = info.manager.requested_locking {
-            Box::new(match *locking {
+        let request = if let Some(locking) = info.manager.requested_locking {
+            match *locking {
                 LockingBlock::Regular(cert) => {
-                    BlockProposal::new_retry_regular(owner, round, cert, self.signer())
-                        .await
-                        .map_err(ChainClientError::signer_failure)?
+                    ProposalSigningRequest::new_retry_regular(owner, round, cert)
                 }
                 LockingBlock::Fast(proposal) => {
-                    BlockProposal::new_retry_fast(owner, round, proposal, self.signer())
-                        .await
-                        .map_err(ChainClientError::signer_failure)?
+                    ProposalSigningRequest::new_retry_fast(owner, round, proposal)
                 }
-            })
+            }
         } else {
-            Box::new(
-                BlockProposal::new_initial(owner, round, proposed_block.clone(), self.signer())
-                    .await
-                    .map_err(ChainClientError::signer_failure)?,
-            )
+            let request = ProposalSigningRequest::new_initial(owner, round, proposed_block.clone());
+            match sponsor_approval {
+                Some(approval) => request.with_sponsor_approval(approval),
+                None => request,
+            }
         };
+        Ok(ClientOutcome::Committed(Some(PreparedProposal {
+            request,
+            block: Block::new(proposed_block, outcome),
+            blobs,
+            already_handled_locally,
+        })))
+    }
+
+    /// Submits a prepared proposal with the owner's signature to the validators, and returns
+    /// the certificate of the confirmed block.
+    async fn submit_prepared_proposal(
+        &self,
+        prepared: PreparedProposal,
+        signature: AccountSignature,
+    ) -> Result<ConfirmedBlockCertificate, ChainClientError> {
+        let PreparedProposal {
+            request,
+            block,
+            blobs,
+            already_handled_locally,
+        } = prepared;
+        let round = request.content.round;
+        let proposal = Box::new(request.into_proposal(signature)?);
+        let local_node = &self.client.local_node;
         if !already_handled_locally {
             // Check the final block proposal. This will be cheaper after #1401.
             if let Err(err) = local_node.handle_block_proposal(*proposal.clone()).await {
@@ -2853,8 +3834,10 @@ impl<Env: Environment> ChainClient<Env> {
                 }
             }
         }
+        // Let validators recognize retries of this submission, e.g. after a timeout.
+        let key = CryptoHash::from(rand::random::<[u8; 32]>());
+        let proposal = Box::new(proposal.with_idempotency_key(key));
         let committee = self.local_committee().await?;
-        let block = Block::new(proposed_block, outcome);
         // Send the query to validators.
         let submit_block_proposal_start = std::time::Instant::now();
         let certificate = if round.is_fast() {
@@ -2889,7 +3872,7 @@ impl<Env: Environment> ChainClient<Env> {
                 TimingType::UpdateValidators,
             ));
         }
-        Ok(ClientOutcome::Committed(Some(certificate)))
+        Ok(certificate)
     }
 
     /// Requests a leader timeout certificate if the current round has timed out. Returns the
@@ -3107,6 +4090,82 @@ impl<Env: Environment> ChainClient<Env> {
         .await
     }
 
+    /// Changes the application permissions of this chain from a later block on, or cancels
+    /// the change staged before if `staged` is `None`.
+    #[instrument(level = "trace", skip(staged))]
+    pub async fn stage_application_permissions(
+        &self,
+        staged: Option<StagedApplicationPermissions>,
+    ) -> Result<ClientOutcome<ConfirmedBlockCertificate>, ChainClientError> {
+        self.execute_operation(SystemOperation::StageApplicationPermissions(staged))
+            .await
+    }
+
+    /// Changes the limits on the pending bundles from each origin in the inboxes of this
+    /// chain.
+    #[instrument(level = "trace")]
+    pub async fn change_inbox_quotas(
+        &self,
+        inbox_quotas: InboxQuotas,
+    ) -> Result<ClientOutcome<ConfirmedBlockCertificate>, ChainClientError> {
+        self.execute_operation(SystemOperation::ChangeInboxQuotas(inbox_quotas))
+            .await
+    }
+
+    /// Sets or removes the application that can authorize block proposals from accounts that
+    /// are not owners of this chain.
+    #[instrument(level = "trace")]
+    pub async fn change_authorization_application(
+        &self,
+        application_id: Option<ApplicationId>,
+    ) -> Result<ClientOutcome<ConfirmedBlockCertificate>, ChainClientError> {
+        self.execute_operation(SystemOperation::ChangeAuthorizationApplication { application_id })
+            .await
+    }
+
+    /// Restricts which owners may execute the operations of which applications on this chain,
+    /// or lifts the restriction if `operation_permissions` is `None`.
+    #[instrument(level = "trace")]
+    pub async fn change_operation_permissions(
+        &self,
+        operation_permissions: Option<OperationPermissions>,
+    ) -> Result<ClientOutcome<ConfirmedBlockCertificate>, ChainClientError> {
+        self.execute_operation(SystemOperation::ChangeOperationPermissions(
+            operation_permissions,
+        ))
+        .await
+    }
+
+    /// Sets the configuration of an application on this chain, or removes it if `config` is
+    /// `None`.
+    #[instrument(level = "trace")]
+    pub async fn set_application_config(
+        &self,
+        application_id: ApplicationId,
+        config: Option<Vec<u8>>,
+    ) -> Result<ClientOutcome<ConfirmedBlockCertificate>, ChainClientError> {
+        self.execute_operation(SystemOperation::SetApplicationConfig {
+            application_id,
+            config,
+        })
+        .await
+    }
+
+    /// Makes an application use the code of `module_id` on this chain, and lets it migrate
+    /// its state by calling its `on_upgrade` entrypoint.
+    #[instrument(level = "trace")]
+    pub async fn upgrade_application(
+        &self,
+        application_id: ApplicationId,
+        module_id: ModuleId,
+    ) -> Result<ClientOutcome<ConfirmedBlockCertificate>, ChainClientError> {
+        self.execute_operation(SystemOperation::UpgradeApplication {
+            application_id,
+            module_id,
+        })
+        .await
+    }
+
     /// Opens a new chain with a derived UID.
     #[instrument(level = "trace", skip(self))]
     pub async fn open_chain(
@@ -3379,7 +4438,7 @@ impl<Env: Environment> ChainClient<Env> {
     }
 
     /// Returns operations to process all pending epoch changes: first the new epochs, in order,
-    /// then the removed epochs, in order.
+    /// then the validator weight updates, in order, then the removed epochs, in order.
     async fn collect_epoch_changes(&self) -> Result<Vec<Operation>, ChainClientError> {
         let (mut min_epoch, mut next_epoch) = {
             let (epoch, committees) = self.epoch_and_committees().await?;
@@ -3396,6 +4455,24 @@ impl<Env: Environment> ChainClient<Env> {
             )));
             next_epoch.try_add_assign_one()?;
         }
+        let mut next_weight_update = *self
+            .chain_state_view()
+            .await?
+            .execution_state
+            .system
+            .validator_weight_updates
+            .get();
+        while self
+            .has_admin_event(VALIDATOR_WEIGHTS_STREAM_NAME, next_weight_update)
+            .await?
+        {
+            epoch_change_ops.push(Operation::system(SystemOperation::ProcessValidatorWeights(
+                next_weight_update,
+            )));
+            next_weight_update = next_weight_update
+                .checked_add(1)
+                .ok_or(ArithmeticError::Overflow)?;
+        }
         while self
             .has_admin_event(REMOVED_EPOCH_STREAM_NAME, min_epoch.0)
             .await?
@@ -3434,6 +4511,15 @@ impl<Env: Environment> ChainClient<Env> {
         stream_id: StreamId,
         start_index: u32,
     ) -> Result<Vec<IndexAndEvent>, ChainClientError> {
+        if let GenericApplicationId::User(application_id) = stream_id.application_id {
+            let chain = self.chain_state_view().await?;
+            if let Some(applications) = chain.application_filter.get() {
+                ensure!(
+                    applications.contains(&application_id),
+                    ChainClientError::UntrackedApplication(application_id)
+                );
+            }
+        }
         Ok(self
             .client
             .storage_client()
@@ -3441,6 +4527,26 @@ impl<Env: Environment> ChainClient<Env> {
             .await?)
     }
 
+    /// Returns the streams that events were published on in this chain, with the index of
+    /// their latest event, optionally only the ones of the given application.
+    pub async fn event_streams(
+        &self,
+        application_id: Option<GenericApplicationId>,
+    ) -> Result<Vec<EventStreamInfo>, ChainClientError> {
+        let query = ChainInfoQuery::new(self.chain_id).with_event_streams();
+        let info = self
+            .client
+            .local_node
+            .handle_chain_info_query(query)
+            .await?
+            .info;
+        Ok(info
+            .requested_event_streams
+            .into_iter()
+            .filter(|stream| application_id.is_none_or(|id| stream.stream_id.application_id == id))
+            .collect())
+    }
+
     /// Deprecates all the configurations of voting rights up to the given one (admin chains
     /// only). Currently, each individual chain is still entitled to wait before accepting
     /// this command. However, it is expected that deprecated validators stop functioning
@@ -3475,6 +4581,38 @@ impl<Env: Environment> ChainClient<Env> {
         self.execute_operations(operations, vec![]).await
     }
 
+    /// Changes the voting rights of some validators in the current epoch (admin chains only).
+    /// Validators keep accepting certificates signed according to the previous weights for
+    /// `grace_period` after this block.
+    #[instrument(level = "trace")]
+    pub async fn update_validator_weights(
+        &self,
+        votes: BTreeMap<ValidatorPublicKey, u64>,
+        grace_period: TimeDelta,
+    ) -> Result<ClientOutcome<ConfirmedBlockCertificate>, ChainClientError> {
+        self.prepare_chain().await?;
+        let (epoch, _) = self.epoch_and_committees().await?;
+        let operation = SystemOperation::Admin(AdminOperation::UpdateValidatorWeights {
+            epoch,
+            votes,
+            grace_period,
+        });
+        self.execute_operation(operation).await
+    }
+
+    /// Publishes a new network address of a validator, signed by that validator (admin
+    /// chains only). Clients will contact the validator at that address from now on.
+    #[instrument(level = "trace")]
+    pub async fn announce_validator_address(
+        &self,
+        announcement: SignedValidatorAddress,
+    ) -> Result<ClientOutcome<ConfirmedBlockCertificate>, ChainClientError> {
+        self.prepare_chain().await?;
+        let operation =
+            SystemOperation::Admin(AdminOperation::AnnounceValidatorAddress(announcement));
+        self.execute_operation(operation).await
+    }
+
     /// Sends money to a chain.
     /// Do not check balance. (This may block the client)
     /// Do not confirm the transaction.
@@ -3631,6 +4769,16 @@ impl<Env: Environment> ChainClient<Env> {
                     );
                 }
             }
+            Reason::ProposalDiscarded { height, round } => {
+                debug!(
+                    chain_id = %self.chain_id,
+                    "Validator {} discarded the proposal at height {height} in round {round}",
+                    remote_node.public_key
+                );
+                self.client
+                    .synchronize_chain_state_from(&remote_node, notification.chain_id)
+                    .await?;
+            }
         }
         Ok(())
     }
@@ -3940,6 +5088,18 @@ enum ExecuteBlockOutcome {
     WaitForTimeout(RoundTimeout),
 }
 
+/// A block proposal that only needs the owner's signature to be submitted.
+struct PreparedProposal {
+    /// The proposal without the signature.
+    request: ProposalSigningRequest,
+    /// The proposed block, with its execution outcome.
+    block: Block,
+    /// The blobs published by the block.
+    blobs: Vec<Blob>,
+    /// Whether the local node already handled this proposal.
+    already_handled_locally: bool,
+}
+
 /// Wrapper for `AbortHandle` that aborts when its dropped.
 #[must_use]
 pub struct AbortOnDrop(pub AbortHandle);
@@ -3970,6 +5130,9 @@ struct ReceivedCertificatesFromValidator {
 pub struct PendingProposal {
     pub block: ProposedBlock,
     pub blobs: Vec<Blob>,
+    /// The fee sponsor's approval, if the block has a fee sponsor.
+    #[serde(default)]
+    pub sponsor_approval: Option<SponsorApproval>,
 }
 
 enum ReceiveCertificateMode {
@@ -0,0 +1,223 @@
+// Copyright (c) Zefchain Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! Monitoring of the validators of the current committee.
+//!
+//! The validators are probed for their version and for their view of the chains tracked by
+//! the client. The results are aggregated in a [`NetworkHealth`] snapshot, which shows which
+//! validators are reachable and how far behind each of them is on each chain.
+
+use std::{
+    collections::BTreeSet,
+    sync::{Arc, RwLock},
+};
+
+use futures::future;
+use linera_base::{
+    crypto::ValidatorPublicKey,
+    data_types::{BlockHeight, Epoch, Timestamp},
+    doc_scalar,
+    identifiers::ChainId,
+    time::{timer, Duration, Instant},
+};
+use linera_storage::{Clock as _, Storage as _};
+use serde::{Deserialize, Serialize};
+use tracing::{debug, instrument, warn};
+
+use super::{ChainClientError, Client};
+use crate::{
+    data_types::ChainInfoQuery, environment::Environment, node::ValidatorNode,
+    remote_node::RemoteNode,
+};
+
+/// A snapshot of the health of the validators of the current committee.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct NetworkHealth {
+    /// The local time when the validators were probed.
+    pub probed_at: Timestamp,
+    /// The epoch of the committee.
+    pub epoch: Epoch,
+    /// The next block heights of the tracked chains, according to the local node.
+    pub local_heights: Vec<(ChainId, BlockHeight)>,
+    /// The state of each validator of the committee.
+    pub validators: Vec<ValidatorHealth>,
+}
+
+doc_scalar!(
+    NetworkHealth,
+    "The committee's validators, their reachability and their view of the tracked chains"
+);
+
+/// The state of a validator, as seen by a client.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ValidatorHealth {
+    /// The public key of the validator.
+    pub public_key: ValidatorPublicKey,
+    /// The network address of the validator.
+    pub network_address: String,
+    /// The voting power of the validator.
+    pub votes: u64,
+    /// Whether the validator answered the probe.
+    pub reachable: bool,
+    /// How long the validator took to return its version, in milliseconds.
+    pub latency_ms: Option<u64>,
+    /// The version of the validator's software.
+    pub version: Option<String>,
+    /// The error returned by the validator, if it was not reachable.
+    pub error: Option<String>,
+    /// The validator's view of each tracked chain.
+    pub chains: Vec<ChainHealth>,
+}
+
+/// A validator's view of a chain.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ChainHealth {
+    /// The chain.
+    pub chain_id: ChainId,
+    /// The next block height of the chain according to the validator, if it answered.
+    pub next_block_height: Option<BlockHeight>,
+    /// How many blocks the validator is behind the highest height known to the client.
+    pub lag: Option<u64>,
+}
+
+impl<Env: Environment> Client<Env> {
+    /// Probes every validator of the current committee, and returns their reachability and
+    /// their view of the chains tracked by this client.
+    #[instrument(level = "trace", skip(self))]
+    pub async fn probe_network_health(&self) -> Result<NetworkHealth, ChainClientError> {
+        let (epoch, committee) = self.admin_committee().await?;
+        let nodes = self.make_nodes(&committee)?;
+        let chain_ids = self
+            .tracked_chains
+            .read()
+            .expect("Panics should not happen while holding a lock to `tracked_chains`")
+            .iter()
+            .copied()
+            .collect::<BTreeSet<_>>();
+
+        let mut local_heights = Vec::new();
+        for chain_id in &chain_ids {
+            let info = self.local_node.chain_info(*chain_id).await?;
+            local_heights.push((*chain_id, info.next_block_height));
+        }
+
+        let mut validators = future::join_all(
+            nodes
+                .iter()
+                .map(|node| probe_validator(node, chain_ids.iter().copied())),
+        )
+        .await;
+
+        for (index, (chain_id, local_height)) in local_heights.iter().enumerate() {
+            let highest_height = validators
+                .iter()
+                .filter_map(|validator| validator.chains[index].next_block_height)
+                .fold(*local_height, BlockHeight::max);
+            for validator in &mut validators {
+                let chain = &mut validator.chains[index];
+                debug_assert_eq!(chain.chain_id, *chain_id);
+                chain.lag = chain
+                    .next_block_height
+                    .map(|height| highest_height.0.saturating_sub(height.0));
+            }
+        }
+        for validator in &mut validators {
+            if let Some(state) = committee.validators().get(&validator.public_key) {
+                validator.network_address.clone_from(&state.network_address);
+                validator.votes = state.votes;
+            }
+        }
+
+        Ok(NetworkHealth {
+            probed_at: self.storage_client().clock().current_time(),
+            epoch,
+            local_heights,
+            validators,
+        })
+    }
+}
+
+/// Probes a validator for its version and for its view of the given chains.
+async fn probe_validator<N: ValidatorNode>(
+    node: &RemoteNode<N>,
+    chain_ids: impl IntoIterator<Item = ChainId>,
+) -> ValidatorHealth {
+    let start = Instant::now();
+    let version = node.node.get_version_info().await;
+    let latency_ms = start.elapsed().as_millis() as u64;
+    let mut health = ValidatorHealth {
+        public_key: node.public_key,
+        network_address: String::new(),
+        votes: 0,
+        reachable: version.is_ok(),
+        latency_ms: None,
+        version: None,
+        error: None,
+        chains: Vec::new(),
+    };
+    match version {
+        Ok(version) => {
+            health.latency_ms = Some(latency_ms);
+            health.version = Some(version.crate_version.to_string());
+        }
+        Err(error) => {
+            debug!(validator = %node.public_key, %error, "Validator is not reachable");
+            health.error = Some(error.to_string());
+        }
+    }
+
+    for chain_id in chain_ids {
+        let next_block_height = if health.reachable {
+            node.handle_chain_info_query(ChainInfoQuery::new(chain_id))
+                .await
+                .inspect_err(|error| {
+                    let validator = node.public_key;
+                    debug!(%validator, %chain_id, %error, "Failed to query chain");
+                })
+                .ok()
+                .map(|info| info.next_block_height)
+        } else {
+            None
+        };
+        health.chains.push(ChainHealth {
+            chain_id,
+            next_block_height,
+            lag: None,
+        });
+    }
+    health
+}
+
+/// Probes the validators periodically and keeps the latest [`NetworkHealth`] snapshot.
+#[derive(Clone, Default)]
+pub struct NetworkMonitor {
+    latest: Arc<RwLock<Option<NetworkHealth>>>,
+}
+
+impl NetworkMonitor {
+    /// The default time between two probes of the validators.
+    pub const DEFAULT_INTERVAL: Duration = Duration::from_secs(30);
+
+    /// Returns the latest snapshot, if the validators were probed already.
+    pub fn latest(&self) -> Option<NetworkHealth> {
+        self.latest
+            .read()
+            .expect("Panics should not happen while holding a lock to the network health")
+            .clone()
+    }
+
+    /// Probes the validators with the given client every `interval`. This never returns.
+    pub async fn run<Env: Environment>(&self, client: Arc<Client<Env>>, interval: Duration) {
+        loop {
+            match client.probe_network_health().await {
+                Ok(health) => {
+                    *self.latest.write().expect(
+                        "Panics should not happen while holding a lock to the network health",
+                    ) = Some(health);
+                }
+                Err(error) => warn!(%error, "Failed to probe the validators"),
+            }
+            timer::sleep(interval).await;
+        }
+    }
+}
@@ -0,0 +1,232 @@
+// Copyright (c) Zefchain Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! Rebasing of the operations of a block proposal that lost to another block.
+//!
+//! When several owners of a chain propose concurrently, only one of their blocks is committed
+//! at each height. Instead of failing, the other clients can rebase their operations onto the
+//! committed block: a [`RebaseHook`] decides whether to keep each operation, possibly
+//! adjusted to the new chain state, and the operations that would now fail are dropped.
+//! The remaining operations are then proposed again at the next height.
+
+use linera_base::data_types::Blob;
+use linera_chain::{types::ConfirmedBlockCertificate, ChainError, ChainExecutionContext};
+use linera_execution::{ExecutionError, Operation};
+use tracing::info;
+
+use super::{ChainClient, ChainClientError, ExecuteBlockOutcome};
+use crate::{
+    data_types::ClientOutcome, environment::Environment, local_node::LocalNodeError,
+    worker::WorkerError,
+};
+
+/// What to do with an operation that is rebased onto a block committed in the meantime.
+#[derive(Clone, Debug)]
+pub enum RebaseDecision {
+    /// Propose the given operation, i.e. the original one or an adjusted one, again.
+    Keep(Operation),
+    /// Drop the operation.
+    Drop,
+}
+
+/// Decides what happens to each operation when it is rebased.
+pub trait RebaseHook: Send {
+    /// Returns whether to keep `operation`, now that the block of `certificate` was committed
+    /// instead of the one that contained it.
+    fn rebase(
+        &mut self,
+        certificate: &ConfirmedBlockCertificate,
+        operation: Operation,
+    ) -> RebaseDecision;
+}
+
+impl<F> RebaseHook for F
+where
+    F: FnMut(&ConfirmedBlockCertificate, Operation) -> RebaseDecision + Send,
+{
+    fn rebase(
+        &mut self,
+        certificate: &ConfirmedBlockCertificate,
+        operation: Operation,
+    ) -> RebaseDecision {
+        self(certificate, operation)
+    }
+}
+
+/// A [`RebaseHook`] that keeps all operations unchanged, as long as they still succeed.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct KeepAll;
+
+impl RebaseHook for KeepAll {
+    fn rebase(&mut self, _: &ConfirmedBlockCertificate, operation: Operation) -> RebaseDecision {
+        RebaseDecision::Keep(operation)
+    }
+}
+
+/// The outcome of executing operations with rebasing.
+#[derive(Debug)]
+pub struct RebasedBlock {
+    /// The certificate of the block with the remaining operations, or `None` if all of them
+    /// were dropped, so that no block was proposed.
+    pub certificate: Option<ConfirmedBlockCertificate>,
+    /// The indices in the original list of the operations that the block contains.
+    pub indices: Vec<usize>,
+    /// The operations that were dropped while rebasing.
+    pub dropped: Vec<DroppedOperation>,
+    /// The number of times the operations were rebased onto another block.
+    pub rebases: usize,
+}
+
+/// An operation that was dropped while rebasing.
+#[derive(Debug)]
+pub struct DroppedOperation {
+    /// The index of the operation in the original list.
+    pub index: usize,
+    /// The operation, as it was before it was dropped.
+    pub operation: Operation,
+    /// Why the operation was dropped.
+    pub reason: DropReason,
+}
+
+/// Why an operation was dropped while rebasing.
+#[derive(Debug)]
+pub enum DropReason {
+    /// The [`RebaseHook`] dropped it.
+    Vetoed,
+    /// It failed when executed on top of the new chain state.
+    Failed(Box<ExecutionError>),
+}
+
+pub(super) async fn execute_with_rebase<Env: Environment>(
+    chain_client: &ChainClient<Env>,
+    operations: Vec<Operation>,
+    blobs: Vec<Blob>,
+    hook: &mut impl RebaseHook,
+) -> Result<ClientOutcome<RebasedBlock>, ChainClientError> {
+    let mut operations = operations.into_iter().enumerate().collect::<Vec<_>>();
+    let mut blobs = blobs;
+    let mut dropped = Vec::new();
+    let mut rebases = 0;
+    loop {
+        let current = operations
+            .iter()
+            .map(|(_, operation)| operation.clone())
+            .collect();
+        // TODO(#2066): Remove boxing once the call-stack is shallower
+        match Box::pin(chain_client.execute_block(current, blobs.clone())).await? {
+            ExecuteBlockOutcome::Executed(certificate) => {
+                return Ok(ClientOutcome::Committed(RebasedBlock {
+                    certificate: Some(certificate),
+                    indices: operations.into_iter().map(|(index, _)| index).collect(),
+                    dropped,
+                    rebases,
+                }));
+            }
+            ExecuteBlockOutcome::WaitForTimeout(timeout) => {
+                return Ok(ClientOutcome::WaitForTimeout(timeout));
+            }
+            ExecuteBlockOutcome::Conflict(certificate) => {
+                info!(
+                    height = %certificate.block().header.height,
+                    "Another block was committed; rebasing the operations onto it."
+                );
+                rebases += 1;
+                let was_empty = operations.is_empty();
+                operations = rebase_operations(
+                    chain_client,
+                    &certificate,
+                    operations,
+                    &mut blobs,
+                    hook,
+                    &mut dropped,
+                )
+                .await?;
+                if operations.is_empty() && !was_empty {
+                    return Ok(ClientOutcome::Committed(RebasedBlock {
+                        certificate: None,
+                        indices: Vec::new(),
+                        dropped,
+                        rebases,
+                    }));
+                }
+            }
+        }
+    }
+}
+
+/// Rebases the operations onto the block of `certificate`, which must be the last block of
+/// the chain, and returns the ones to propose again.
+async fn rebase_operations<Env: Environment>(
+    chain_client: &ChainClient<Env>,
+    certificate: &ConfirmedBlockCertificate,
+    operations: Vec<(usize, Operation)>,
+    blobs: &mut Vec<Blob>,
+    hook: &mut impl RebaseHook,
+    dropped: &mut Vec<DroppedOperation>,
+) -> Result<Vec<(usize, Operation)>, ChainClientError> {
+    let mut rebased = Vec::new();
+    for (index, operation) in operations {
+        match hook.rebase(certificate, operation.clone()) {
+            RebaseDecision::Keep(operation) => rebased.push((index, operation)),
+            RebaseDecision::Drop => dropped.push(DroppedOperation {
+                index,
+                operation,
+                reason: DropReason::Vetoed,
+            }),
+        }
+    }
+    // Drop the operations that fail on top of the new chain state, one at a time.
+    while !rebased.is_empty() {
+        retain_published_blobs(&rebased, blobs);
+        let current = rebased
+            .iter()
+            .map(|(_, operation)| operation.clone())
+            .collect();
+        let block = chain_client.next_proposed_block(current).await?;
+        let num_bundles = block.incoming_bundles.len();
+        let result = chain_client
+            .client
+            .stage_block_execution_and_discard_failing_messages(block, None, blobs.clone())
+            .await;
+        let chain_error = match result {
+            Ok(_) => break,
+            Err(ChainClientError::LocalNodeError(LocalNodeError::WorkerError(
+                WorkerError::ChainError(chain_error),
+            ))) => chain_error,
+            Err(error) => return Err(error),
+        };
+        let (error, index) = match *chain_error {
+            ChainError::ExecutionError(error, ChainExecutionContext::Operation(index)) => {
+                (error, index)
+            }
+            chain_error => {
+                let error = WorkerError::ChainError(Box::new(chain_error));
+                return Err(LocalNodeError::WorkerError(error).into());
+            }
+        };
+        let position = (index as usize)
+            .checked_sub(num_bundles)
+            .filter(|position| *position < rebased.len())
+            .ok_or(ChainClientError::InternalError(
+                "The failing operation is not in the block",
+            ))?;
+        let (index, operation) = rebased.remove(position);
+        info!(%error, index, "Operation failed after rebasing and will be dropped.");
+        dropped.push(DroppedOperation {
+            index,
+            operation,
+            reason: DropReason::Failed(error),
+        });
+    }
+    retain_published_blobs(&rebased, blobs);
+    Ok(rebased)
+}
+
+/// Removes the blobs that none of the operations publish anymore.
+fn retain_published_blobs(operations: &[(usize, Operation)], blobs: &mut Vec<Blob>) {
+    blobs.retain(|blob| {
+        operations
+            .iter()
+            .any(|(_, operation)| operation.published_blob_ids().contains(&blob.id()))
+    });
+}
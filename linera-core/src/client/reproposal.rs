@@ -0,0 +1,248 @@
+// Copyright (c) Zefchain Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! Proposing operations again after their block lost its round to another block.
+//!
+//! On a chain with several owners, a faster owner or round leader can get a conflicting block
+//! validated or confirmed while ours is still being voted on. The validators then reject our
+//! proposal because it is for an outdated round or height. With a [`ReproposalConfig`], the
+//! client waits, synchronizes the chain and checks whether one of the blocks it proposed was
+//! committed after all, e.g. by another owner retrying our locked block. Only if none was, it
+//! proposes the operations again, waiting twice as long after each preemption.
+
+use std::collections::BTreeSet;
+
+use linera_base::{
+    crypto::CryptoHash,
+    data_types::BlockHeight,
+    time::{timer, Duration},
+};
+use linera_chain::{block::Block, types::ConfirmedBlockCertificate, ChainError};
+use linera_storage::{ResultReadCertificates, Storage as _};
+use tracing::info;
+
+use super::{ChainClient, ChainClientError};
+use crate::{
+    environment::Environment, local_node::LocalNodeError, node::NodeError,
+    updater::CommunicationError, worker::WorkerError,
+};
+
+/// How often and when operations are proposed again after their block was preempted.
+#[derive(Clone, Copy, Debug)]
+pub struct ReproposalConfig {
+    /// The maximum number of times the operations are proposed again.
+    pub max_attempts: u32,
+    /// The delay before the first re-proposal.
+    pub initial_delay: Duration,
+    /// The maximum delay before a re-proposal. The delay doubles after each re-proposal, up
+    /// to this value.
+    pub max_delay: Duration,
+}
+
+/// Returns whether a chain rejected a proposal because the chain moved on to a later round or
+/// height.
+fn is_preempting_chain_error(error: &ChainError) -> bool {
+    matches!(
+        error,
+        ChainError::InsufficientRound(_)
+            | ChainError::InsufficientRoundStrict(_)
+            | ChainError::WrongRound(_)
+            | ChainError::HasIncompatibleConfirmedVote(..)
+            | ChainError::UnexpectedBlockHeight { .. }
+    )
+}
+
+/// Returns whether a validator rejected a proposal because the chain moved on to a later round
+/// or height.
+fn is_preempting_node_error(error: &NodeError) -> bool {
+    matches!(
+        error,
+        NodeError::InsufficientRound(_)
+            | NodeError::InsufficientRoundStrict(_)
+            | NodeError::WrongRound(_)
+            | NodeError::HasIncompatibleConfirmedVote(..)
+            | NodeError::UnexpectedBlockHeight { .. }
+    )
+}
+
+/// Returns whether the proposal failed because another block was validated or confirmed in a
+/// later round or at its height.
+fn is_preemption(error: &ChainClientError) -> bool {
+    match error {
+        ChainClientError::ChainError(error) => is_preempting_chain_error(error),
+        ChainClientError::LocalNodeError(LocalNodeError::WorkerError(WorkerError::ChainError(
+            error,
+        ))) => is_preempting_chain_error(error),
+        ChainClientError::RemoteNodeError(error)
+        | ChainClientError::CommunicationError(CommunicationError::Trusted(error)) => {
+            is_preempting_node_error(error)
+        }
+        ChainClientError::CommunicationError(CommunicationError::Sample(errors)) => errors
+            .iter()
+            .any(|(error, _)| is_preempting_node_error(error)),
+        _ => false,
+    }
+}
+
+/// Returns the idempotency key of a proposed block: two blocks with the same key are the same
+/// proposal, even if one was committed by another owner retrying the other in a later round.
+fn proposal_key(block: &Block) -> CryptoHash {
+    CryptoHash::new(&block.clone().into_proposal().0)
+}
+
+/// The re-proposals of the operations of one call to [`ChainClient::execute_operations`].
+pub(super) struct Reproposals {
+    config: Option<ReproposalConfig>,
+    /// The idempotency keys of the blocks with the operations that were proposed and
+    /// preempted. If one of them is committed, the operations must not be proposed again.
+    keys: BTreeSet<CryptoHash>,
+    /// The next block height of the chain before the operations were proposed.
+    start_height: BlockHeight,
+    attempts: u32,
+    delay: Duration,
+}
+
+impl Reproposals {
+    pub(super) async fn new<Env: Environment>(
+        chain_client: &ChainClient<Env>,
+    ) -> Result<Self, ChainClientError> {
+        let config = chain_client.options.reproposal;
+        let start_height = match config {
+            Some(_) => chain_client.chain_info().await?.next_block_height,
+            None => BlockHeight::ZERO,
+        };
+        Ok(Self {
+            config,
+            keys: BTreeSet::new(),
+            start_height,
+            attempts: 0,
+            delay: config.map_or(Duration::ZERO, |config| config.initial_delay),
+        })
+    }
+
+    /// Handles an error while executing the operations. If their block was preempted and
+    /// the limits allow it, waits and synchronizes the chain, then returns the certificate
+    /// of the block that committed the operations in the meantime, if any, or `None` to
+    /// propose them again. Otherwise, returns the error.
+    pub(super) async fn handle_error<Env: Environment>(
+        &mut self,
+        chain_client: &ChainClient<Env>,
+        error: ChainClientError,
+    ) -> Result<Option<ConfirmedBlockCertificate>, ChainClientError> {
+        let Some(config) = self.config else {
+            return Err(error);
+        };
+        if self.attempts >= config.max_attempts || !is_preemption(&error) {
+            return Err(error);
+        }
+        self.attempts += 1;
+        // The block that was preempted is still pending: remember it, in case it is committed.
+        let key = chain_client
+            .pending_proposal()
+            .as_ref()
+            .map(|pending| CryptoHash::new(&pending.block));
+        self.keys.extend(key);
+        info!(
+            %error,
+            attempt = self.attempts,
+            delay_ms = self.delay.as_millis(),
+            "The block proposal was preempted; synchronizing before proposing it again."
+        );
+        timer::sleep(self.delay).await;
+        self.delay = (self.delay * 2).min(config.max_delay);
+        chain_client.synchronize_from_validators().await?;
+        self.find_fulfilling_certificate(chain_client).await
+    }
+
+    /// Returns whether `certificate` commits one of the preempted blocks with the operations.
+    pub(super) fn is_fulfilled_by(&self, certificate: &ConfirmedBlockCertificate) -> bool {
+        self.keys.contains(&proposal_key(certificate.block()))
+    }
+
+    /// Returns the certificate of a block committed since the operations were first proposed
+    /// that is one of the preempted blocks with the operations, if any.
+    async fn find_fulfilling_certificate<Env: Environment>(
+        &self,
+        chain_client: &ChainClient<Env>,
+    ) -> Result<Option<ConfirmedBlockCertificate>, ChainClientError> {
+        if self.keys.is_empty() {
+            return Ok(None);
+        }
+        let next_height = chain_client.chain_info().await?.next_block_height;
+        let start = usize::try_from(self.start_height.0).expect("`usize` should be at least `u64`");
+        let end = usize::try_from(next_height.0).expect("`usize` should be at least `u64`");
+        if start >= end {
+            return Ok(None);
+        }
+        let hashes = chain_client
+            .chain_state_view()
+            .await?
+            .confirmed_log
+            .read(start..end)
+            .await?;
+        let certificates = chain_client
+            .client
+            .storage_client()
+            .read_certificates(hashes.clone())
+            .await?;
+        let certificates = match ResultReadCertificates::new(certificates, hashes) {
+            ResultReadCertificates::Certificates(certificates) => certificates,
+            ResultReadCertificates::InvalidHashes(hashes) => {
+                return Err(ChainClientError::ReadCertificatesError(hashes))
+            }
+        };
+        Ok(certificates
+            .into_iter()
+            .find(|certificate| self.is_fulfilled_by(certificate)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use linera_base::data_types::{ArithmeticError, BlockHeight, Round};
+    use linera_chain::ChainError;
+
+    use super::is_preemption;
+    use crate::{
+        client::ChainClientError, local_node::LocalNodeError, node::NodeError,
+        updater::CommunicationError, worker::WorkerError,
+    };
+
+    #[test]
+    fn test_preemption_errors() {
+        let wrong_round = NodeError::WrongRound(Round::SingleLeader(2));
+        let insufficient_round = NodeError::from(ChainError::InsufficientRound(Round::Fast));
+        assert_eq!(
+            insufficient_round,
+            NodeError::InsufficientRound(Round::Fast)
+        );
+        let arithmetic = NodeError::ArithmeticError {
+            error: ArithmeticError::Overflow.to_string(),
+        };
+        assert!(is_preemption(&ChainClientError::CommunicationError(
+            CommunicationError::Trusted(wrong_round)
+        )));
+        assert!(is_preemption(&ChainClientError::CommunicationError(
+            CommunicationError::Sample(vec![(arithmetic.clone(), 1), (insufficient_round, 2)])
+        )));
+        assert!(is_preemption(&ChainClientError::ChainError(
+            ChainError::WrongRound(Round::Fast)
+        )));
+        assert!(is_preemption(&ChainClientError::LocalNodeError(
+            LocalNodeError::WorkerError(WorkerError::ChainError(Box::new(
+                ChainError::HasIncompatibleConfirmedVote(BlockHeight(1), Round::Fast)
+            )))
+        )));
+        assert!(!is_preemption(&ChainClientError::CommunicationError(
+            CommunicationError::Trusted(arithmetic)
+        )));
+        // Only the typed errors count, not other errors that happen to have the same code.
+        assert!(!is_preemption(&ChainClientError::CommunicationError(
+            CommunicationError::Trusted(NodeError::ChainErrorWithCode {
+                error: "Round number should be Fast".to_string(),
+                code: "WrongRound".to_string(),
+                retriable: true,
+            })
+        )));
+    }
+}
@@ -0,0 +1,126 @@
+// Copyright (c) Zefchain Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! A cache of the committees of past epochs, shared by the chain workers.
+//!
+//! A chain that is behind the current epoch needs the committee of the block's epoch to check
+//! each confirmed certificate, and reading it requires several storage round-trips: the
+//! network description, the admin chain's epoch events and the committee blob. The cached
+//! committees include the validator weight updates within their epochs, so that certificates
+//! signed according to weights a chain doesn't know about are checked without reading the
+//! storage again.
+
+#[cfg(test)]
+#[path = "unit_tests/committee_cache_tests.rs"]
+mod unit_tests;
+
+use std::{
+    collections::BTreeMap,
+    future::Future,
+    num::NonZeroUsize,
+    ops::RangeInclusive,
+    sync::{Arc, Mutex},
+};
+
+use linera_base::data_types::Epoch;
+use linera_storage::{EpochCommittee, Storage};
+use linera_views::ViewError;
+use lru::LruCache;
+
+/// The maximum number of committees kept in the cache.
+const COMMITTEE_CACHE_SIZE: usize = 100;
+
+/// A least-recently used cache of committees, indexed by epoch.
+pub struct CommitteeCache {
+    committees: Mutex<LruCache<Epoch, Arc<EpochCommittee>>>,
+}
+
+impl CommitteeCache {
+    /// Returns the committee of the given epoch, reading it from storage if it is not cached.
+    ///
+    /// Returns `None` if the epoch was not created on the admin chain yet.
+    pub async fn get_or_load<S: Storage>(
+        &self,
+        storage: &S,
+        epoch: Epoch,
+    ) -> Result<Option<Arc<EpochCommittee>>, ViewError> {
+        self.get_or_load_with(epoch, |epochs| storage.epoch_committees_for(epochs))
+            .await
+    }
+
+    /// Reads the committees of all the given epochs with a single query, unless they are all
+    /// cached already.
+    pub async fn prefetch<S: Storage>(
+        &self,
+        storage: &S,
+        epochs: RangeInclusive<Epoch>,
+    ) -> Result<(), ViewError> {
+        self.prefetch_with(epochs, |epochs| storage.epoch_committees_for(epochs))
+            .await
+    }
+
+    /// Removes the committee of the given epoch, so that it is read from storage again, e.g.
+    /// after a validator weight update.
+    pub fn invalidate(&self, epoch: Epoch) {
+        self.committees.lock().unwrap().pop(&epoch);
+    }
+
+    /// Returns the committee of the given epoch, reading it with `load` if it is not cached.
+    async fn get_or_load_with<F, Fut>(
+        &self,
+        epoch: Epoch,
+        load: F,
+    ) -> Result<Option<Arc<EpochCommittee>>, ViewError>
+    where
+        F: FnOnce(RangeInclusive<Epoch>) -> Fut,
+        Fut: Future<Output = Result<BTreeMap<Epoch, EpochCommittee>, ViewError>>,
+    {
+        if let Some(committee) = self.committees.lock().unwrap().get(&epoch) {
+            return Ok(Some(committee.clone()));
+        }
+        let committee = load(epoch..=epoch).await?.remove(&epoch).map(Arc::new);
+        if let Some(committee) = &committee {
+            self.committees
+                .lock()
+                .unwrap()
+                .put(epoch, committee.clone());
+        }
+        Ok(committee)
+    }
+
+    /// Reads the committees of all the given epochs with `load`, unless they are all cached
+    /// already.
+    async fn prefetch_with<F, Fut>(
+        &self,
+        epochs: RangeInclusive<Epoch>,
+        load: F,
+    ) -> Result<(), ViewError>
+    where
+        F: FnOnce(RangeInclusive<Epoch>) -> Fut,
+        Fut: Future<Output = Result<BTreeMap<Epoch, EpochCommittee>, ViewError>>,
+    {
+        let is_cached = {
+            let committees = self.committees.lock().unwrap();
+            (epochs.start().0..=epochs.end().0).all(|epoch| committees.contains(&Epoch(epoch)))
+        };
+        if is_cached {
+            return Ok(());
+        }
+        let loaded = load(epochs).await?;
+        let mut committees = self.committees.lock().unwrap();
+        for (epoch, committee) in loaded {
+            committees.put(epoch, Arc::new(committee));
+        }
+        Ok(())
+    }
+}
+
+impl Default for CommitteeCache {
+    fn default() -> Self {
+        let size =
+            NonZeroUsize::try_from(COMMITTEE_CACHE_SIZE).expect("Cache size is larger than zero");
+        CommitteeCache {
+            committees: Mutex::new(LruCache::new(size)),
+        }
+    }
+}
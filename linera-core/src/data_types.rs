@@ -2,7 +2,10 @@
 // Copyright (c) Zefchain Labs, Inc.
 // SPDX-License-Identifier: Apache-2.0
 
-use std::{collections::BTreeMap, ops::Not};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    ops::Not,
+};
 
 use custom_debug_derive::Debug;
 use linera_base::{
@@ -11,11 +14,13 @@ use linera_base::{
         ValidatorSignature,
     },
     data_types::{Amount, BlockHeight, ChainDescription, Epoch, Round, Timestamp},
-    identifiers::{AccountOwner, ChainId},
+    identifiers::{AccountOwner, ApplicationId, ChainId, EventStreamInfo},
+    merkle::StateInclusionProof,
 };
 use linera_chain::{
     data_types::{ChainAndHeight, IncomingBundle, MessageBundle},
-    manager::ChainManagerInfo,
+    manager::{ChainManagerInfo, VoteRecord},
+    types::ConfirmedBlockCertificate,
     ChainStateView,
 };
 use linera_execution::{committee::Committee, ExecutionRuntimeContext};
@@ -58,6 +63,58 @@ impl BlockHeightRange {
     }
 }
 
+/// A range of entries of a chain's log, by index, as used in `ChainInfoQuery`.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[cfg_attr(with_testing, derive(test_strategy::Arbitrary, Eq, PartialEq))]
+pub struct LogRange {
+    /// The index of the first entry.
+    pub start: u64,
+    /// Optional limit on the number of entries.
+    #[debug(skip_if = Option::is_none)]
+    pub limit: Option<u64>,
+}
+
+/// A part of a [`ChainInfo`] that a client can leave out of the response, or that a
+/// validator cut short to respect the client's byte budget.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Ord, PartialOrd, Hash, Serialize, Deserialize)]
+#[cfg_attr(with_testing, derive(test_strategy::Arbitrary))]
+pub enum ChainInfoField {
+    /// The chain description.
+    Description,
+    /// The chain manager's state. Without it, `manager` is left at its default value.
+    Manager,
+    /// The requested committees.
+    Committees,
+    /// The requested pending message bundles.
+    PendingMessageBundles,
+    /// The requested sent certificate hashes.
+    SentCertificateHashes,
+    /// The requested part of the received log.
+    ReceivedLog,
+    /// The requested entries of the vote audit log.
+    VoteAuditLog,
+    /// The requested event streams.
+    EventStreams,
+    /// The requested state inclusion proof.
+    StateInclusionProof,
+}
+
+impl ChainInfoField {
+    /// All the fields, in the order in which they are cut short when a response exceeds its
+    /// byte budget: the ones that are easiest to fetch again go first.
+    pub const ALL: [ChainInfoField; 9] = [
+        ChainInfoField::StateInclusionProof,
+        ChainInfoField::EventStreams,
+        ChainInfoField::VoteAuditLog,
+        ChainInfoField::PendingMessageBundles,
+        ChainInfoField::ReceivedLog,
+        ChainInfoField::SentCertificateHashes,
+        ChainInfoField::Committees,
+        ChainInfoField::Manager,
+        ChainInfoField::Description,
+    ];
+}
+
 /// Request information about a chain.
 #[derive(Clone, Debug, Serialize, Deserialize)]
 #[cfg_attr(with_testing, derive(test_strategy::Arbitrary, Eq, PartialEq))]
@@ -81,6 +138,10 @@ pub struct ChainInfoQuery {
     /// Query new certificate sender chain IDs and block heights received from the chain.
     #[debug(skip_if = Option::is_none)]
     pub request_received_log_excluding_first_n: Option<u64>,
+    /// Query a range of the received log. Takes precedence over
+    /// `request_received_log_excluding_first_n`.
+    #[debug(skip_if = Option::is_none)]
+    pub request_received_log_in_range: Option<LogRange>,
     /// Query values from the chain manager, not just votes.
     #[debug(skip_if = Not::not)]
     pub request_manager_values: bool,
@@ -90,6 +151,23 @@ pub struct ChainInfoQuery {
     /// Include a vote to switch to fallback mode, if appropriate.
     #[debug(skip_if = Not::not)]
     pub request_fallback: bool,
+    /// Query the latest entries of the vote audit log, up to this number.
+    #[debug(skip_if = Option::is_none)]
+    pub request_vote_audit_log: Option<u64>,
+    /// Query the streams that events were published on, with their latest indices.
+    #[debug(skip_if = Not::not)]
+    pub request_event_streams: bool,
+    /// If set, only these fields are included in the response.
+    #[debug(skip_if = Option::is_none)]
+    pub request_fields: Option<BTreeSet<ChainInfoField>>,
+    /// If set, fields are cut short or left out until the serialized `ChainInfo` is at most
+    /// this many bytes long. The affected fields are listed in `truncated_fields`.
+    #[debug(skip_if = Option::is_none)]
+    pub max_response_bytes: Option<u64>,
+    /// Query the value that an application stores under a key, with a proof that it is part
+    /// of the chain's execution state.
+    #[debug(skip_if = Option::is_none)]
+    pub request_state_inclusion_proof: Option<(ApplicationId, Vec<u8>)>,
 }
 
 impl ChainInfoQuery {
@@ -102,9 +180,15 @@ impl ChainInfoQuery {
             request_pending_message_bundles: false,
             request_sent_certificate_hashes_in_range: None,
             request_received_log_excluding_first_n: None,
+            request_received_log_in_range: None,
             request_manager_values: false,
             request_leader_timeout: false,
             request_fallback: false,
+            request_vote_audit_log: None,
+            request_event_streams: false,
+            request_fields: None,
+            max_response_bytes: None,
+            request_state_inclusion_proof: None,
         }
     }
 
@@ -138,6 +222,11 @@ impl ChainInfoQuery {
         self
     }
 
+    pub fn with_received_log_in_range(mut self, range: LogRange) -> Self {
+        self.request_received_log_in_range = Some(range);
+        self
+    }
+
     pub fn with_manager_values(mut self) -> Self {
         self.request_manager_values = true;
         self
@@ -152,6 +241,35 @@ impl ChainInfoQuery {
         self.request_fallback = true;
         self
     }
+
+    pub fn with_vote_audit_log(mut self, count: u64) -> Self {
+        self.request_vote_audit_log = Some(count);
+        self
+    }
+
+    pub fn with_event_streams(mut self) -> Self {
+        self.request_event_streams = true;
+        self
+    }
+
+    pub fn with_fields(mut self, fields: impl IntoIterator<Item = ChainInfoField>) -> Self {
+        self.request_fields = Some(fields.into_iter().collect());
+        self
+    }
+
+    pub fn with_max_response_bytes(mut self, max_bytes: u64) -> Self {
+        self.max_response_bytes = Some(max_bytes);
+        self
+    }
+
+    pub fn with_state_inclusion_proof(
+        mut self,
+        application_id: ApplicationId,
+        key: Vec<u8>,
+    ) -> Self {
+        self.request_state_inclusion_proof = Some((application_id, key));
+        self
+    }
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -190,11 +308,38 @@ pub struct ChainInfo {
     /// The response to `request_sent_certificate_hashes_in_range`
     #[debug(skip_if = Vec::is_empty)]
     pub requested_sent_certificate_hashes: Vec<CryptoHash>,
+    /// The height to start the next page of sent certificate hashes at, if they were
+    /// requested. There are more of them if it is below `next_block_height`.
+    #[debug(skip_if = Option::is_none)]
+    pub next_sent_certificate_hashes_cursor: Option<BlockHeight>,
     /// The current number of received certificates (useful for `request_received_log_excluding_first_n`)
     pub count_received_log: usize,
-    /// The response to `request_received_certificates_excluding_first_n`
+    /// The response to `request_received_log_excluding_first_n` or
+    /// `request_received_log_in_range`
     #[debug(skip_if = Vec::is_empty)]
     pub requested_received_log: Vec<ChainAndHeight>,
+    /// The index to start the next page of the received log at, if it was requested.
+    /// The log is append-only, so this remains valid; there are more entries if it is below
+    /// `count_received_log`.
+    #[debug(skip_if = Option::is_none)]
+    pub next_received_log_cursor: Option<u64>,
+    /// The response to `request_vote_audit_log`, oldest first.
+    #[debug(skip_if = Vec::is_empty)]
+    pub requested_vote_audit_log: Vec<VoteRecord>,
+    /// The response to `request_event_streams`.
+    #[debug(skip_if = Vec::is_empty)]
+    pub requested_event_streams: Vec<EventStreamInfo>,
+    /// The fields that were cut short or left out to respect `max_response_bytes`.
+    #[debug(skip_if = BTreeSet::is_empty)]
+    pub truncated_fields: BTreeSet<ChainInfoField>,
+    /// The response to `request_state_inclusion_proof`: the value and its proof against
+    /// `state_hash`, if the value exists.
+    #[debug(skip_if = Option::is_none)]
+    pub requested_state_inclusion_proof: Option<(Vec<u8>, StateInclusionProof)>,
+    /// The newer epoch that the validator has moved the chain to, if its next block is
+    /// expected to switch to it.
+    #[debug(skip_if = Option::is_none)]
+    pub migrated_epoch: Option<Epoch>,
 }
 
 impl ChainInfo {
@@ -208,6 +353,118 @@ impl ChainInfo {
             next_block_height: self.next_block_height,
         })
     }
+
+    /// Leaves out the fields that are not in `fields`.
+    pub fn retain_fields(&mut self, fields: &BTreeSet<ChainInfoField>) {
+        for field in ChainInfoField::ALL {
+            if !fields.contains(&field) {
+                self.leave_out(field);
+            }
+        }
+    }
+
+    /// Cuts fields short, in the order of [`ChainInfoField::ALL`], until the serialized info
+    /// is at most `max_bytes` long, or until only the fields that can't be left out remain.
+    ///
+    /// Lists lose their last entries first, and their cursors are moved back accordingly, so
+    /// that the rest can be fetched with another query. Only the vote audit log loses its
+    /// oldest entries instead.
+    pub fn truncate_to(&mut self, max_bytes: u64) {
+        for field in ChainInfoField::ALL {
+            let size = serialized_size(self);
+            if size <= max_bytes {
+                return;
+            }
+            // Recording the truncated field takes one more byte.
+            let excess = size + 1 - max_bytes;
+            if self.cut_short(field, excess) {
+                self.truncated_fields.insert(field);
+            }
+        }
+    }
+
+    fn leave_out(&mut self, field: ChainInfoField) {
+        match field {
+            ChainInfoField::Description => self.description = None,
+            ChainInfoField::Manager => self.manager = Box::default(),
+            ChainInfoField::Committees => self.requested_committees = None,
+            ChainInfoField::PendingMessageBundles => self.requested_pending_message_bundles.clear(),
+            ChainInfoField::SentCertificateHashes => {
+                self.requested_sent_certificate_hashes.clear();
+                self.next_sent_certificate_hashes_cursor = None;
+            }
+            ChainInfoField::ReceivedLog => {
+                self.requested_received_log.clear();
+                self.next_received_log_cursor = None;
+            }
+            ChainInfoField::VoteAuditLog => self.requested_vote_audit_log.clear(),
+            ChainInfoField::EventStreams => self.requested_event_streams.clear(),
+            ChainInfoField::StateInclusionProof => self.requested_state_inclusion_proof = None,
+        }
+    }
+
+    /// Removes at least `excess` bytes from `field` if possible, and all of it otherwise.
+    /// Returns whether anything was removed.
+    fn cut_short(&mut self, field: ChainInfoField, excess: u64) -> bool {
+        match field {
+            ChainInfoField::Description => self.description.take().is_some(),
+            ChainInfoField::Manager => {
+                let size = serialized_size(&self.manager);
+                self.leave_out(field);
+                serialized_size(&self.manager) < size
+            }
+            ChainInfoField::Committees => self.requested_committees.take().is_some(),
+            ChainInfoField::PendingMessageBundles => {
+                drop_last_entries(&mut self.requested_pending_message_bundles, excess) > 0
+            }
+            ChainInfoField::SentCertificateHashes => {
+                let dropped =
+                    drop_last_entries(&mut self.requested_sent_certificate_hashes, excess);
+                if let Some(cursor) = &mut self.next_sent_certificate_hashes_cursor {
+                    cursor.0 -= dropped;
+                }
+                dropped > 0
+            }
+            ChainInfoField::ReceivedLog => {
+                let dropped = drop_last_entries(&mut self.requested_received_log, excess);
+                if let Some(cursor) = &mut self.next_received_log_cursor {
+                    *cursor -= dropped;
+                }
+                dropped > 0
+            }
+            ChainInfoField::VoteAuditLog => {
+                self.requested_vote_audit_log.reverse();
+                let dropped = drop_last_entries(&mut self.requested_vote_audit_log, excess);
+                self.requested_vote_audit_log.reverse();
+                dropped > 0
+            }
+            ChainInfoField::EventStreams => {
+                drop_last_entries(&mut self.requested_event_streams, excess) > 0
+            }
+            ChainInfoField::StateInclusionProof => {
+                self.requested_state_inclusion_proof.take().is_some()
+            }
+        }
+    }
+}
+
+/// Removes entries from the end of `entries` until they account for at least `excess` bytes,
+/// and returns how many were removed.
+fn drop_last_entries<T: Serialize>(entries: &mut Vec<T>, excess: u64) -> u64 {
+    let mut freed = 0;
+    let mut dropped = 0;
+    while freed < excess {
+        let Some(entry) = entries.pop() else {
+            break;
+        };
+        freed += serialized_size(&entry);
+        dropped += 1;
+    }
+    dropped
+}
+
+fn serialized_size<T: Serialize>(value: &T) -> u64 {
+    bcs::serialized_size(value).expect("ChainInfo serialization should not fail") as u64
 }
 
 /// The response to an `ChainInfoQuery`
@@ -259,6 +516,40 @@ impl CrossChainRequest {
     }
 }
 
+impl BcsSignable<'_> for CrossChainRequest {}
+
+/// A [`CrossChainRequest`] sent over the network between the shards of a validator, signed
+/// with the validator's key so that the receiving shard can reject requests from anyone else.
+///
+/// Replaying a signed request is harmless: the recipient ignores the blocks it already has.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[cfg_attr(with_testing, derive(Eq, PartialEq))]
+pub struct SignedCrossChainRequest {
+    pub request: CrossChainRequest,
+    pub signature: Option<ValidatorSignature>,
+}
+
+impl SignedCrossChainRequest {
+    /// Signs `request` with `key_pair`, if any.
+    pub fn new(request: CrossChainRequest, key_pair: Option<&ValidatorSecretKey>) -> Self {
+        let signature = key_pair.map(|kp| ValidatorSignature::new(&request, kp));
+        Self { request, signature }
+    }
+
+    /// Where to send the cross-chain request.
+    pub fn target_chain_id(&self) -> ChainId {
+        self.request.target_chain_id()
+    }
+
+    /// Checks that the request was signed by the validator with the given `public_key`.
+    pub fn check(&self, public_key: ValidatorPublicKey) -> Result<(), CryptoError> {
+        match self.signature.as_ref() {
+            Some(sig) => sig.check(&self.request, public_key),
+            None => Err(CryptoError::MissingValidatorSignature),
+        }
+    }
+}
+
 impl<C, S> From<&ChainStateView<C>> for ChainInfo
 where
     C: Context<Extra = ChainRuntimeContext<S>> + Clone + Send + Sync + 'static,
@@ -281,8 +572,15 @@ where
             requested_owner_balance: None,
             requested_pending_message_bundles: Vec::new(),
             requested_sent_certificate_hashes: Vec::new(),
+            next_sent_certificate_hashes_cursor: None,
             count_received_log: view.received_log.count(),
             requested_received_log: Vec::new(),
+            next_received_log_cursor: None,
+            requested_vote_audit_log: Vec::new(),
+            requested_event_streams: Vec::new(),
+            truncated_fields: BTreeSet::new(),
+            requested_state_inclusion_proof: None,
+            migrated_epoch: *view.migrated_epoch.get(),
         }
     }
 }
@@ -310,6 +608,61 @@ impl ChainInfoResponse {
 
 impl BcsSignable<'_> for ChainInfo {}
 
+/// A self-contained snapshot of what a client knows about a chain, to help reproduce issues.
+///
+/// A bundle only contains data that is shared with the validators anyway, so it never
+/// includes private keys or other secrets from the wallet.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ChainDebugBundle {
+    /// The version of the software that created the bundle.
+    pub version: linera_version::VersionInfo,
+    /// The local time when the bundle was created.
+    pub created_at: Timestamp,
+    /// The chain state, including the chain manager values and the pending message bundles.
+    pub info: Box<ChainInfo>,
+    /// The confirmed certificates in the requested range of block heights.
+    pub certificates: Vec<ConfirmedBlockCertificate>,
+    /// The current metrics in Prometheus' text format, if metrics are enabled.
+    #[debug(skip_if = Option::is_none)]
+    pub metrics: Option<String>,
+    /// The recent log output of the process, if it is kept in memory.
+    #[debug(skip_if = Option::is_none)]
+    pub logs: Option<String>,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl ChainDebugBundle {
+    /// Packs the bundle into a tar archive containing:
+    /// * `version.json`, the version of the software that created the bundle;
+    /// * `chain_info.json`, the chain state;
+    /// * `certificates/<height>.json`, one file per confirmed certificate;
+    /// * `metrics.txt`, the metrics in Prometheus' text format, if metrics are enabled;
+    /// * `logs.txt`, the recent log output, if it is kept in memory.
+    pub fn to_archive(&self) -> std::io::Result<Vec<u8>> {
+        let mut builder = tar::Builder::new(Vec::new());
+        let mut append = |path: &str, contents: &[u8]| {
+            let mut header = tar::Header::new_ustar();
+            header.set_size(contents.len() as u64);
+            header.set_mode(0o644);
+            header.set_mtime(self.created_at.micros() / 1_000_000);
+            builder.append_data(&mut header, path, contents)
+        };
+        append("version.json", &serde_json::to_vec_pretty(&self.version)?)?;
+        append("chain_info.json", &serde_json::to_vec_pretty(&self.info)?)?;
+        for certificate in &self.certificates {
+            let path = format!("certificates/{}.json", certificate.block().header.height);
+            append(&path, &serde_json::to_vec_pretty(certificate)?)?;
+        }
+        if let Some(metrics) = &self.metrics {
+            append("metrics.txt", metrics.as_bytes())?;
+        }
+        if let Some(logs) = &self.logs {
+            append("logs.txt", logs.as_bytes())?;
+        }
+        builder.into_inner()
+    }
+}
+
 /// The outcome of trying to commit a list of operations to the chain.
 #[derive(Debug)]
 pub enum ClientOutcome<T> {
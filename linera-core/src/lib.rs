@@ -7,6 +7,8 @@
 #![recursion_limit = "256"]
 #![deny(clippy::large_futures)]
 
+pub mod audit_log;
+pub mod chain_diagnosis;
 pub mod chain_worker;
 pub mod client;
 pub mod data_types;
@@ -14,16 +16,22 @@ pub mod join_set_ext;
 pub mod local_node;
 pub mod node;
 pub mod notifier;
+pub mod recording;
 pub mod remote_node;
+pub mod service_runtime_pool;
+pub mod signing_fence;
 #[cfg(with_testing)]
 #[path = "unit_tests/test_utils.rs"]
 pub mod test_utils;
+pub mod verifier;
 pub mod worker;
 
+mod committee_cache;
+mod proposal_outcome_cache;
 pub(crate) mod updater;
 mod value_cache;
 
-pub use updater::DEFAULT_GRACE_PERIOD;
+pub use updater::{HedgingConfig, DEFAULT_GRACE_PERIOD};
 
 pub use crate::join_set_ext::{JoinSetExt, TaskHandle};
 
@@ -3,7 +3,8 @@
 // SPDX-License-Identifier: Apache-2.0
 
 use std::{
-    collections::{BTreeMap, VecDeque},
+    collections::{BTreeMap, BTreeSet, VecDeque},
+    ops::RangeInclusive,
     sync::Arc,
 };
 
@@ -158,6 +159,19 @@ where
             .await?)
     }
 
+    #[instrument(level = "trace", skip_all)]
+    pub async fn simulate_block_execution(
+        &self,
+        block: ProposedBlock,
+        published_blobs: Vec<Blob>,
+    ) -> Result<(Block, ChainInfoResponse), LocalNodeError> {
+        Ok(self
+            .node
+            .state
+            .simulate_block_execution(block, published_blobs)
+            .await?)
+    }
+
     /// Reads blobs from storage.
     pub async fn read_blobs_from_storage(
         &self,
@@ -210,6 +224,16 @@ where
         Ok(Some(blobs))
     }
 
+    /// Reads the committees of the given epochs with a single query, before processing many
+    /// certificates from these epochs.
+    pub async fn prefetch_committees(
+        &self,
+        epochs: RangeInclusive<Epoch>,
+    ) -> Result<(), LocalNodeError> {
+        self.node.state.prefetch_committees(epochs).await?;
+        Ok(())
+    }
+
     /// Writes the given blobs to storage if there is an appropriate blob state.
     pub async fn store_blobs(&self, blobs: &[Blob]) -> Result<(), LocalNodeError> {
         let storage = self.storage_client();
@@ -260,6 +284,21 @@ where
         Ok(outcome)
     }
 
+    #[instrument(level = "trace", skip(self, query))]
+    pub async fn query_application_at(
+        &self,
+        chain_id: ChainId,
+        query: Query,
+        height: BlockHeight,
+    ) -> Result<QueryOutcome, LocalNodeError> {
+        let outcome = self
+            .node
+            .state
+            .query_application_at(chain_id, query, height)
+            .await?;
+        Ok(outcome)
+    }
+
     #[instrument(level = "trace", skip(self))]
     pub async fn describe_application(
         &self,
@@ -274,6 +313,21 @@ where
         Ok(response)
     }
 
+    /// Stops executing the chain's blocks, and keeps only the events of the given
+    /// applications.
+    #[instrument(level = "trace", skip(self))]
+    pub async fn set_application_filter(
+        &self,
+        chain_id: ChainId,
+        applications: BTreeSet<ApplicationId>,
+    ) -> Result<(), LocalNodeError> {
+        self.node
+            .state
+            .set_application_filter(chain_id, applications)
+            .await?;
+        Ok(())
+    }
+
     /// Handles any pending local cross-chain requests.
     #[instrument(level = "trace", skip(self))]
     pub async fn retry_pending_cross_chain_requests(
@@ -20,7 +20,7 @@ use linera_chain::{
     },
     ChainError,
 };
-use linera_execution::{committee::Committee, ExecutionError};
+use linera_execution::{committee::Committee, BlobProvenance, ExecutionError};
 use linera_version::VersionInfo;
 use linera_views::ViewError;
 use serde::{Deserialize, Serialize};
@@ -34,6 +34,19 @@ use crate::{
 /// A pinned [`Stream`] of Notifications.
 pub type NotificationStream = BoxStream<'static, Notification>;
 
+/// A pinned [`Stream`] of the certificates of a chain, in increasing order of height.
+pub type CertificateStream = BoxStream<'static, Result<StreamedCertificate, NodeError>>;
+
+/// A confirmed certificate sent by a validator as part of a [`CertificateStream`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct StreamedCertificate {
+    /// The certificate.
+    pub certificate: ConfirmedBlockCertificate,
+    /// The blobs required by the certificate's block, if they were requested. Blobs that
+    /// the validator could not send are omitted.
+    pub blobs: Vec<Blob>,
+}
+
 /// Whether to wait for the delivery of outgoing cross-chain messages.
 #[derive(Debug, Default, Clone, Copy)]
 pub enum CrossChainMessageDelivery {
@@ -148,8 +161,22 @@ pub trait ValidatorNode {
     /// Returns the hash of the `Certificate` that last used a blob.
     async fn blob_last_used_by(&self, blob_id: BlobId) -> Result<CryptoHash, NodeError>;
 
+    /// Returns the state of a blob, i.e. the certificate that last used it, and whether the
+    /// validator has its content.
+    async fn blob_provenance(&self, blob_id: BlobId) -> Result<BlobProvenance, NodeError>;
+
     /// Returns the missing `Blob`s by their IDs.
     async fn missing_blob_ids(&self, blob_ids: Vec<BlobId>) -> Result<Vec<BlobId>, NodeError>;
+
+    /// Streams the confirmed certificates of a chain in order, starting at height `start`
+    /// and ending with the validator's latest block. If `include_blobs` is set, the blobs
+    /// required by each block are sent along with its certificate.
+    async fn stream_certificates(
+        &self,
+        chain_id: ChainId,
+        start: BlockHeight,
+        include_blobs: bool,
+    ) -> Result<CertificateStream, NodeError>;
 }
 
 /// Turn an address into a validator node.
@@ -288,16 +315,60 @@ pub enum NodeError {
     EmptyBlobsNotFound,
     #[error("Local error handling validator response: {error}")]
     ResponseHandlingError { error: String },
+    #[error("Streaming certificates is not supported by the {transport} transport")]
+    CertificateStreamingNotSupported { transport: String },
+
+    // Added after the variants above, to keep their serialized indices.
+    #[error("Chain error: {error}")]
+    ChainErrorWithCode {
+        error: String,
+        code: String,
+        retriable: bool,
+    },
+    #[error("Worker error: {error}")]
+    WorkerErrorWithCode {
+        error: String,
+        code: String,
+        retriable: bool,
+    },
+
+    // Added after the variants above, to keep their serialized indices.
+    #[error("Round number should be at least {0:?}")]
+    InsufficientRound(Round),
+    #[error("Round number should be greater than {0:?}")]
+    InsufficientRoundStrict(Round),
+    #[error("Already voted to confirm a different block for height {0:?} at round number {1:?}")]
+    HasIncompatibleConfirmedVote(BlockHeight, Round),
 }
 
 impl From<tonic::Status> for NodeError {
     fn from(status: tonic::Status) -> Self {
+        if let Ok(error) = bincode::deserialize(status.details()) {
+            return error;
+        }
         Self::GrpcError {
             error: status.to_string(),
         }
     }
 }
 
+impl From<NodeError> for tonic::Status {
+    /// Returns a status carrying the error in its details, so that clients can recover its
+    /// code and whether it is retriable.
+    fn from(error: NodeError) -> Self {
+        let code = if error.is_retriable() {
+            tonic::Code::Unavailable
+        } else {
+            tonic::Code::FailedPrecondition
+        };
+        // Like the errors in shard responses, the details are encoded with `bincode`.
+        match bincode::serialize(&error) {
+            Ok(details) => tonic::Status::with_details(code, error.to_string(), details.into()),
+            Err(_) => tonic::Status::new(code, error.to_string()),
+        }
+    }
+}
+
 impl CrossChainMessageDelivery {
     pub fn new(wait_for_outgoing_messages: bool) -> Self {
         if wait_for_outgoing_messages {
@@ -339,6 +410,83 @@ impl From<CryptoError> for NodeError {
     }
 }
 
+impl NodeError {
+    /// Returns a stable code identifying the kind of error, for programmatic clients. For
+    /// errors of the validator's chain or worker, this is the code of the original error.
+    pub fn code(&self) -> &str {
+        match self {
+            NodeError::CryptoError { .. } => "CryptoError",
+            NodeError::ArithmeticError { .. } => "ArithmeticError",
+            NodeError::ViewError { .. } => "ViewError",
+            NodeError::ChainError { .. } => "ChainError",
+            NodeError::WorkerError { .. } => "WorkerError",
+            NodeError::InactiveChain(..) => "InactiveChain",
+            NodeError::WrongRound(..) => "WrongRound",
+            NodeError::UnexpectedBlockHeight { .. } => "UnexpectedBlockHeight",
+            NodeError::MissingCrossChainUpdate { .. } => "MissingCrossChainUpdate",
+            NodeError::BlobsNotFound(..) => "BlobsNotFound",
+            NodeError::EventsNotFound(..) => "EventsNotFound",
+            NodeError::MissingCertificateValue => "MissingCertificateValue",
+            NodeError::MissingCertificates(..) => "MissingCertificates",
+            NodeError::MissingVoteInValidatorResponse => "MissingVoteInValidatorResponse",
+            NodeError::InvalidChainInfoResponse => "InvalidChainInfoResponse",
+            NodeError::UnexpectedCertificateValue => "UnexpectedCertificateValue",
+            NodeError::InvalidDecoding => "InvalidDecoding",
+            NodeError::UnexpectedMessage => "UnexpectedMessage",
+            NodeError::GrpcError { .. } => "GrpcError",
+            NodeError::ClientIoError { .. } => "ClientIoError",
+            NodeError::CannotResolveValidatorAddress { .. } => "CannotResolveValidatorAddress",
+            NodeError::SubscriptionError { .. } => "SubscriptionError",
+            NodeError::SubscriptionFailed { .. } => "SubscriptionFailed",
+            NodeError::InvalidCertificateForBlob(..) => "InvalidCertificateForBlob",
+            NodeError::DuplicatesInBlobsNotFound => "DuplicatesInBlobsNotFound",
+            NodeError::UnexpectedEntriesInBlobsNotFound => "UnexpectedEntriesInBlobsNotFound",
+            NodeError::UnexpectedCertificates { .. } => "UnexpectedCertificates",
+            NodeError::EmptyBlobsNotFound => "EmptyBlobsNotFound",
+            NodeError::ResponseHandlingError { .. } => "ResponseHandlingError",
+            NodeError::CertificateStreamingNotSupported { .. } => {
+                "CertificateStreamingNotSupported"
+            }
+            NodeError::ChainErrorWithCode { code, .. }
+            | NodeError::WorkerErrorWithCode { code, .. } => code,
+            NodeError::InsufficientRound(..) => "InsufficientRound",
+            NodeError::InsufficientRoundStrict(..) => "InsufficientRoundStrict",
+            NodeError::HasIncompatibleConfirmedVote(..) => "HasIncompatibleConfirmedVote",
+        }
+    }
+
+    /// Returns whether the same request may succeed if it is retried later, e.g. after
+    /// synchronizing the validator or once it is reachable again.
+    pub fn is_retriable(&self) -> bool {
+        match self {
+            NodeError::ChainErrorWithCode { retriable, .. }
+            | NodeError::WorkerErrorWithCode { retriable, .. } => *retriable,
+            NodeError::ViewError { .. }
+            | NodeError::InactiveChain(_)
+            | NodeError::WrongRound(_)
+            | NodeError::UnexpectedBlockHeight { .. }
+            | NodeError::MissingCrossChainUpdate { .. }
+            | NodeError::BlobsNotFound(_)
+            | NodeError::EventsNotFound(_)
+            | NodeError::MissingCertificateValue
+            | NodeError::GrpcError { .. }
+            | NodeError::ClientIoError { .. }
+            | NodeError::CannotResolveValidatorAddress { .. }
+            | NodeError::SubscriptionFailed { .. } => true,
+            _ => false,
+        }
+    }
+
+    /// Converts a [`ChainError`] that has no specific [`NodeError`] variant.
+    fn chain_error(error: ChainError) -> Self {
+        NodeError::ChainErrorWithCode {
+            error: error.to_string(),
+            code: error.code().to_owned(),
+            retriable: error.is_retriable(),
+        }
+    }
+}
+
 impl From<ChainError> for NodeError {
     fn from(error: ChainError) -> Self {
         match error {
@@ -355,9 +503,7 @@ impl From<ChainError> for NodeError {
             ChainError::ExecutionError(execution_error, context) => match *execution_error {
                 ExecutionError::BlobsNotFound(blob_ids) => Self::BlobsNotFound(blob_ids),
                 ExecutionError::EventsNotFound(event_ids) => Self::EventsNotFound(event_ids),
-                _ => Self::ChainError {
-                    error: ChainError::ExecutionError(execution_error, context).to_string(),
-                },
+                _ => Self::chain_error(ChainError::ExecutionError(execution_error, context)),
             },
             ChainError::UnexpectedBlockHeight {
                 expected_block_height,
@@ -367,9 +513,12 @@ impl From<ChainError> for NodeError {
                 found_block_height,
             },
             ChainError::WrongRound(round) => Self::WrongRound(round),
-            error => Self::ChainError {
-                error: error.to_string(),
-            },
+            ChainError::InsufficientRound(round) => Self::InsufficientRound(round),
+            ChainError::InsufficientRoundStrict(round) => Self::InsufficientRoundStrict(round),
+            ChainError::HasIncompatibleConfirmedVote(height, round) => {
+                Self::HasIncompatibleConfirmedVote(height, round)
+            }
+            error => Self::chain_error(error),
         }
     }
 }
@@ -381,8 +530,11 @@ impl From<WorkerError> for NodeError {
             WorkerError::MissingCertificateValue => Self::MissingCertificateValue,
             WorkerError::BlobsNotFound(blob_ids) => Self::BlobsNotFound(blob_ids),
             WorkerError::EventsNotFound(event_ids) => Self::EventsNotFound(event_ids),
-            error => Self::WorkerError {
+            WorkerError::RecordedProposalError(error) => error,
+            error => Self::WorkerErrorWithCode {
                 error: error.to_string(),
+                code: error.code().to_owned(),
+                retriable: error.is_retriable(),
             },
         }
     }
@@ -0,0 +1,106 @@
+// Copyright (c) Zefchain Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! A cache of the outcomes of block proposals that were submitted with an idempotency key.
+
+use std::{num::NonZeroUsize, sync::Mutex, time::Duration};
+
+use linera_base::{
+    crypto::{AccountSignature, CryptoHash},
+    identifiers::ChainId,
+    time::Instant,
+};
+use linera_chain::data_types::BlockProposal;
+use lru::LruCache;
+
+use crate::{data_types::ChainInfoResponse, node::NodeError};
+
+/// The maximum number of outcomes kept in the cache.
+const PROPOSAL_OUTCOME_CACHE_SIZE: usize = 10_000;
+
+/// The default duration for which an outcome is kept.
+const DEFAULT_PROPOSAL_OUTCOME_TTL: Duration = Duration::from_secs(60);
+
+/// The outcome of a block proposal, as returned to the client.
+pub(crate) type ProposalOutcome = Result<ChainInfoResponse, NodeError>;
+
+/// What identifies a signed proposal, independently of its idempotency key.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub(crate) struct ProposalFingerprint {
+    content_hash: CryptoHash,
+    signature: AccountSignature,
+}
+
+impl ProposalFingerprint {
+    pub(crate) fn new(proposal: &BlockProposal) -> Self {
+        ProposalFingerprint {
+            content_hash: CryptoHash::new(&proposal.content),
+            signature: proposal.signature,
+        }
+    }
+}
+
+struct CachedOutcome {
+    fingerprint: ProposalFingerprint,
+    outcome: ProposalOutcome,
+    expires_at: Instant,
+}
+
+/// A least-recently used cache of proposal outcomes, indexed by chain and idempotency key.
+pub(crate) struct ProposalOutcomeCache {
+    ttl: Duration,
+    outcomes: Mutex<LruCache<(ChainId, CryptoHash), CachedOutcome>>,
+}
+
+impl ProposalOutcomeCache {
+    /// Creates a cache keeping outcomes for the given duration.
+    pub(crate) fn new(ttl: Duration) -> Self {
+        let size = NonZeroUsize::try_from(PROPOSAL_OUTCOME_CACHE_SIZE)
+            .expect("Cache size is larger than zero");
+        ProposalOutcomeCache {
+            ttl,
+            outcomes: Mutex::new(LruCache::new(size)),
+        }
+    }
+
+    /// Returns the outcome of the proposal submitted with the given key, if it is still in
+    /// the cache.
+    ///
+    /// Nothing is returned if the key was used for a different proposal.
+    pub(crate) fn get(
+        &self,
+        chain_id: ChainId,
+        key: CryptoHash,
+        fingerprint: &ProposalFingerprint,
+    ) -> Option<ProposalOutcome> {
+        let mut outcomes = self.outcomes.lock().unwrap();
+        let cached = outcomes.get(&(chain_id, key))?;
+        if cached.expires_at > Instant::now() {
+            return (cached.fingerprint == *fingerprint).then(|| cached.outcome.clone());
+        }
+        outcomes.pop(&(chain_id, key));
+        None
+    }
+
+    /// Records the outcome of the proposal submitted with the given key.
+    pub(crate) fn insert(
+        &self,
+        chain_id: ChainId,
+        key: CryptoHash,
+        fingerprint: ProposalFingerprint,
+        outcome: ProposalOutcome,
+    ) {
+        let cached = CachedOutcome {
+            fingerprint,
+            outcome,
+            expires_at: Instant::now() + self.ttl,
+        };
+        self.outcomes.lock().unwrap().put((chain_id, key), cached);
+    }
+}
+
+impl Default for ProposalOutcomeCache {
+    fn default() -> Self {
+        Self::new(DEFAULT_PROPOSAL_OUTCOME_TTL)
+    }
+}
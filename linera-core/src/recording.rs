@@ -0,0 +1,657 @@
+// Copyright (c) Zefchain Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! Recording of the conversations between a client and the validators, and replaying of
+//! recorded conversations against a local worker for debugging.
+//!
+//! A recording is a sequence of [`RecordedExchange`]s, each stored as a little-endian
+//! `u32` length followed by its BCS serialization.
+
+use std::{
+    collections::BTreeMap,
+    fs::File,
+    io::{self, BufReader, BufWriter, Read, Write},
+    path::Path,
+    sync::{Arc, Mutex},
+};
+
+use futures::Future;
+use linera_base::{
+    crypto::{CryptoHash, ValidatorPublicKey},
+    data_types::{Blob, BlobContent, BlockHeight, NetworkDescription, Timestamp},
+    identifiers::{BlobId, ChainId},
+};
+use linera_chain::{
+    data_types::BlockProposal,
+    types::{
+        ConfirmedBlockCertificate, LiteCertificate, TimeoutCertificate, ValidatedBlockCertificate,
+    },
+};
+use linera_execution::BlobProvenance;
+use linera_storage::Storage;
+use linera_version::VersionInfo;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use tracing::warn;
+
+use crate::{
+    data_types::{ChainInfoQuery, ChainInfoResponse},
+    local_node::{LocalNodeClient, LocalNodeError},
+    node::{
+        CertificateStream, CrossChainMessageDelivery, NodeError, ValidatorNode,
+        ValidatorNodeProvider,
+    },
+};
+
+/// A request sent to a validator.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub enum RecordedRequest {
+    BlockProposal(Box<BlockProposal>),
+    LiteCertificate {
+        certificate: LiteCertificate<'static>,
+        blocking: bool,
+    },
+    ConfirmedCertificate {
+        certificate: Box<ConfirmedBlockCertificate>,
+        blocking: bool,
+    },
+    ValidatedCertificate(Box<ValidatedBlockCertificate>),
+    TimeoutCertificate(Box<TimeoutCertificate>),
+    ChainInfoQuery(Box<ChainInfoQuery>),
+    VersionInfo,
+    NetworkDescription,
+    Subscribe(Vec<ChainId>),
+    UploadBlob(BlobContent),
+    DownloadBlob(BlobId),
+    DownloadPendingBlob {
+        chain_id: ChainId,
+        blob_id: BlobId,
+    },
+    HandlePendingBlob {
+        chain_id: ChainId,
+        blob: BlobContent,
+    },
+    DownloadCertificate(CryptoHash),
+    DownloadCertificates(Vec<CryptoHash>),
+    BlobLastUsedBy(BlobId),
+    BlobProvenance(BlobId),
+    MissingBlobIds(Vec<BlobId>),
+    StreamCertificates {
+        chain_id: ChainId,
+        start: BlockHeight,
+        include_blobs: bool,
+    },
+}
+
+impl RecordedRequest {
+    /// Returns a short name for the kind of request.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            RecordedRequest::BlockProposal(_) => "block proposal",
+            RecordedRequest::LiteCertificate { .. } => "lite certificate",
+            RecordedRequest::ConfirmedCertificate { .. } => "confirmed certificate",
+            RecordedRequest::ValidatedCertificate(_) => "validated certificate",
+            RecordedRequest::TimeoutCertificate(_) => "timeout certificate",
+            RecordedRequest::ChainInfoQuery(_) => "chain info query",
+            RecordedRequest::VersionInfo => "version info",
+            RecordedRequest::NetworkDescription => "network description",
+            RecordedRequest::Subscribe(_) => "subscribe",
+            RecordedRequest::UploadBlob(_) => "upload blob",
+            RecordedRequest::DownloadBlob(_) => "download blob",
+            RecordedRequest::DownloadPendingBlob { .. } => "download pending blob",
+            RecordedRequest::HandlePendingBlob { .. } => "handle pending blob",
+            RecordedRequest::DownloadCertificate(_) => "download certificate",
+            RecordedRequest::DownloadCertificates(_) => "download certificates",
+            RecordedRequest::BlobLastUsedBy(_) => "blob last used by",
+            RecordedRequest::BlobProvenance(_) => "blob provenance",
+            RecordedRequest::MissingBlobIds(_) => "missing blob IDs",
+            RecordedRequest::StreamCertificates { .. } => "stream certificates",
+        }
+    }
+}
+
+/// A successful response received from a validator.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub enum RecordedResponse {
+    ChainInfo(Box<ChainInfoResponse>),
+    VersionInfo(VersionInfo),
+    NetworkDescription(NetworkDescription),
+    /// The subscription succeeded. Notifications are not recorded.
+    Subscribed,
+    BlobId(BlobId),
+    BlobIds(Vec<BlobId>),
+    Blob(BlobContent),
+    Certificate(Box<ConfirmedBlockCertificate>),
+    Certificates(Vec<ConfirmedBlockCertificate>),
+    CertificateHash(CryptoHash),
+    BlobProvenance(BlobProvenance),
+    /// The certificate stream was opened. The streamed certificates are not recorded.
+    CertificateStreamOpened,
+}
+
+/// A request to a validator together with the validator's response.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct RecordedExchange {
+    /// The public key of the validator, if the node was created from a committee.
+    pub validator: Option<ValidatorPublicKey>,
+    /// The network address of the validator.
+    pub address: String,
+    /// When the request was sent.
+    pub sent_at: Timestamp,
+    /// When the response was received.
+    pub received_at: Timestamp,
+    /// The request.
+    pub request: RecordedRequest,
+    /// The response, or the error returned by the node.
+    pub response: Result<RecordedResponse, NodeError>,
+}
+
+/// An error while writing or reading a recorded conversation.
+#[derive(Debug, Error)]
+pub enum RecordingError {
+    #[error("I/O error in conversation recording: {0}")]
+    Io(#[from] io::Error),
+
+    #[error("Failed to serialize or deserialize a recorded exchange: {0}")]
+    Bcs(#[from] bcs::Error),
+}
+
+/// A shared sink for the exchanges of all the [`RecordingNode`]s of a client.
+#[derive(Clone)]
+pub struct ConversationRecorder {
+    writer: Arc<Mutex<Box<dyn Write + Send>>>,
+}
+
+impl ConversationRecorder {
+    /// Creates a recorder writing to the given writer.
+    pub fn new(writer: impl Write + Send + 'static) -> Self {
+        Self {
+            writer: Arc::new(Mutex::new(Box::new(writer))),
+        }
+    }
+
+    /// Creates a recorder writing to a new file at the given path, truncating it if it
+    /// exists.
+    pub fn create(path: &Path) -> Result<Self, RecordingError> {
+        Ok(Self::new(BufWriter::new(File::create(path)?)))
+    }
+
+    /// Appends an exchange to the recording.
+    pub fn record(&self, exchange: &RecordedExchange) -> Result<(), RecordingError> {
+        let bytes = bcs::to_bytes(exchange)?;
+        let length = u32::try_from(bytes.len())
+            .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "exchange too large"))?;
+        let mut writer = self.writer.lock().unwrap();
+        writer.write_all(&length.to_le_bytes())?;
+        writer.write_all(&bytes)?;
+        // Flush every exchange so that the recording is usable even if the client crashes.
+        writer.flush()?;
+        Ok(())
+    }
+
+    /// Reads all the exchanges of a recording.
+    pub fn read(mut reader: impl Read) -> Result<Vec<RecordedExchange>, RecordingError> {
+        let mut exchanges = Vec::new();
+        loop {
+            let mut length = [0u8; 4];
+            match reader.read_exact(&mut length) {
+                Ok(()) => {}
+                Err(error) if error.kind() == io::ErrorKind::UnexpectedEof => break,
+                Err(error) => return Err(error.into()),
+            }
+            let mut bytes = vec![0u8; u32::from_le_bytes(length) as usize];
+            reader.read_exact(&mut bytes)?;
+            exchanges.push(bcs::from_bytes(&bytes)?);
+        }
+        Ok(exchanges)
+    }
+
+    /// Reads all the exchanges of the recording at the given path.
+    pub fn read_file(path: &Path) -> Result<Vec<RecordedExchange>, RecordingError> {
+        Self::read(BufReader::new(File::open(path)?))
+    }
+}
+
+/// A [`ValidatorNode`] that records its requests and responses.
+#[derive(Clone)]
+pub struct RecordingNode<N> {
+    node: N,
+    address: String,
+    validator: Option<ValidatorPublicKey>,
+    recorder: Option<ConversationRecorder>,
+}
+
+impl<N> RecordingNode<N> {
+    /// Wraps a node, recording its exchanges if `recorder` is set.
+    pub fn new(node: N, address: String, recorder: Option<ConversationRecorder>) -> Self {
+        Self {
+            node,
+            address,
+            validator: None,
+            recorder,
+        }
+    }
+
+    /// Returns the underlying node.
+    pub fn inner(&self) -> &N {
+        &self.node
+    }
+
+    /// Returns the given request if exchanges are recorded.
+    fn request(&self, request: impl FnOnce() -> RecordedRequest) -> Option<RecordedRequest> {
+        self.recorder.as_ref().map(|_| request())
+    }
+
+    /// Awaits the response to a request, and records the exchange.
+    async fn exchange<T>(
+        &self,
+        request: Option<RecordedRequest>,
+        response: impl Future<Output = Result<T, NodeError>>,
+        to_recorded: impl FnOnce(&T) -> RecordedResponse,
+    ) -> Result<T, NodeError> {
+        let (Some(recorder), Some(request)) = (&self.recorder, request) else {
+            return response.await;
+        };
+        let sent_at = Timestamp::now();
+        let result = response.await;
+        let exchange = RecordedExchange {
+            validator: self.validator,
+            address: self.address.clone(),
+            sent_at,
+            received_at: Timestamp::now(),
+            request,
+            response: result.as_ref().map(to_recorded).map_err(Clone::clone),
+        };
+        if let Err(error) = recorder.record(&exchange) {
+            warn!(
+                "Failed to record an exchange with {}: {error}",
+                self.address
+            );
+        }
+        result
+    }
+}
+
+fn is_blocking(delivery: CrossChainMessageDelivery) -> bool {
+    matches!(delivery, CrossChainMessageDelivery::Blocking)
+}
+
+fn chain_info(response: &ChainInfoResponse) -> RecordedResponse {
+    RecordedResponse::ChainInfo(Box::new(response.clone()))
+}
+
+/// The nodes that can be wrapped in a [`RecordingNode`]: outside of the web, they must be
+/// shareable between threads since the node is borrowed across `.await` points.
+#[cfg(not(web))]
+pub trait RecordableNode: ValidatorNode + Sync {}
+
+#[cfg(not(web))]
+impl<N: ValidatorNode + Sync> RecordableNode for N {}
+
+/// The nodes that can be wrapped in a [`RecordingNode`].
+#[cfg(web)]
+pub trait RecordableNode: ValidatorNode {}
+
+#[cfg(web)]
+impl<N: ValidatorNode> RecordableNode for N {}
+
+impl<N: RecordableNode> ValidatorNode for RecordingNode<N> {
+    type NotificationStream = N::NotificationStream;
+
+    async fn handle_block_proposal(
+        &self,
+        proposal: BlockProposal,
+    ) -> Result<ChainInfoResponse, NodeError> {
+        let request = self.request(|| RecordedRequest::BlockProposal(Box::new(proposal.clone())));
+        let response = self.node.handle_block_proposal(proposal);
+        self.exchange(request, response, chain_info).await
+    }
+
+    async fn handle_lite_certificate(
+        &self,
+        certificate: LiteCertificate<'_>,
+        delivery: CrossChainMessageDelivery,
+    ) -> Result<ChainInfoResponse, NodeError> {
+        let request = self.request(|| RecordedRequest::LiteCertificate {
+            certificate: certificate.cloned(),
+            blocking: is_blocking(delivery),
+        });
+        let response = self.node.handle_lite_certificate(certificate, delivery);
+        self.exchange(request, response, chain_info).await
+    }
+
+    async fn handle_confirmed_certificate(
+        &self,
+        certificate: ConfirmedBlockCertificate,
+        delivery: CrossChainMessageDelivery,
+    ) -> Result<ChainInfoResponse, NodeError> {
+        let request = self.request(|| RecordedRequest::ConfirmedCertificate {
+            certificate: Box::new(certificate.clone()),
+            blocking: is_blocking(delivery),
+        });
+        let response = self
+            .node
+            .handle_confirmed_certificate(certificate, delivery);
+        self.exchange(request, response, chain_info).await
+    }
+
+    async fn handle_validated_certificate(
+        &self,
+        certificate: ValidatedBlockCertificate,
+    ) -> Result<ChainInfoResponse, NodeError> {
+        let request =
+            self.request(|| RecordedRequest::ValidatedCertificate(Box::new(certificate.clone())));
+        let response = self.node.handle_validated_certificate(certificate);
+        self.exchange(request, response, chain_info).await
+    }
+
+    async fn handle_timeout_certificate(
+        &self,
+        certificate: TimeoutCertificate,
+    ) -> Result<ChainInfoResponse, NodeError> {
+        let request =
+            self.request(|| RecordedRequest::TimeoutCertificate(Box::new(certificate.clone())));
+        let response = self.node.handle_timeout_certificate(certificate);
+        self.exchange(request, response, chain_info).await
+    }
+
+    async fn handle_chain_info_query(
+        &self,
+        query: ChainInfoQuery,
+    ) -> Result<ChainInfoResponse, NodeError> {
+        let request = self.request(|| RecordedRequest::ChainInfoQuery(Box::new(query.clone())));
+        let response = self.node.handle_chain_info_query(query);
+        self.exchange(request, response, chain_info).await
+    }
+
+    async fn get_version_info(&self) -> Result<VersionInfo, NodeError> {
+        let request = self.request(|| RecordedRequest::VersionInfo);
+        let response = self.node.get_version_info();
+        self.exchange(request, response, |info| {
+            RecordedResponse::VersionInfo(info.clone())
+        })
+        .await
+    }
+
+    async fn get_network_description(&self) -> Result<NetworkDescription, NodeError> {
+        let request = self.request(|| RecordedRequest::NetworkDescription);
+        let response = self.node.get_network_description();
+        self.exchange(request, response, |description| {
+            RecordedResponse::NetworkDescription(description.clone())
+        })
+        .await
+    }
+
+    async fn subscribe(&self, chains: Vec<ChainId>) -> Result<Self::NotificationStream, NodeError> {
+        let request = self.request(|| RecordedRequest::Subscribe(chains.clone()));
+        let response = self.node.subscribe(chains);
+        self.exchange(request, response, |_| RecordedResponse::Subscribed)
+            .await
+    }
+
+    async fn upload_blob(&self, content: BlobContent) -> Result<BlobId, NodeError> {
+        let request = self.request(|| RecordedRequest::UploadBlob(content.clone()));
+        let response = self.node.upload_blob(content);
+        self.exchange(request, response, |blob_id| {
+            RecordedResponse::BlobId(*blob_id)
+        })
+        .await
+    }
+
+    async fn download_blob(&self, blob_id: BlobId) -> Result<BlobContent, NodeError> {
+        let request = self.request(|| RecordedRequest::DownloadBlob(blob_id));
+        let response = self.node.download_blob(blob_id);
+        self.exchange(request, response, |content| {
+            RecordedResponse::Blob(content.clone())
+        })
+        .await
+    }
+
+    async fn download_pending_blob(
+        &self,
+        chain_id: ChainId,
+        blob_id: BlobId,
+    ) -> Result<BlobContent, NodeError> {
+        let request = self.request(|| RecordedRequest::DownloadPendingBlob { chain_id, blob_id });
+        let response = self.node.download_pending_blob(chain_id, blob_id);
+        self.exchange(request, response, |content| {
+            RecordedResponse::Blob(content.clone())
+        })
+        .await
+    }
+
+    async fn handle_pending_blob(
+        &self,
+        chain_id: ChainId,
+        blob: BlobContent,
+    ) -> Result<ChainInfoResponse, NodeError> {
+        let request = self.request(|| RecordedRequest::HandlePendingBlob {
+            chain_id,
+            blob: blob.clone(),
+        });
+        let response = self.node.handle_pending_blob(chain_id, blob);
+        self.exchange(request, response, chain_info).await
+    }
+
+    async fn download_certificate(
+        &self,
+        hash: CryptoHash,
+    ) -> Result<ConfirmedBlockCertificate, NodeError> {
+        let request = self.request(|| RecordedRequest::DownloadCertificate(hash));
+        let response = self.node.download_certificate(hash);
+        self.exchange(request, response, |certificate| {
+            RecordedResponse::Certificate(Box::new(certificate.clone()))
+        })
+        .await
+    }
+
+    async fn download_certificates(
+        &self,
+        hashes: Vec<CryptoHash>,
+    ) -> Result<Vec<ConfirmedBlockCertificate>, NodeError> {
+        let request = self.request(|| RecordedRequest::DownloadCertificates(hashes.clone()));
+        let response = self.node.download_certificates(hashes);
+        self.exchange(request, response, |certificates| {
+            RecordedResponse::Certificates(certificates.clone())
+        })
+        .await
+    }
+
+    async fn blob_last_used_by(&self, blob_id: BlobId) -> Result<CryptoHash, NodeError> {
+        let request = self.request(|| RecordedRequest::BlobLastUsedBy(blob_id));
+        let response = self.node.blob_last_used_by(blob_id);
+        self.exchange(request, response, |hash| {
+            RecordedResponse::CertificateHash(*hash)
+        })
+        .await
+    }
+
+    async fn blob_provenance(&self, blob_id: BlobId) -> Result<BlobProvenance, NodeError> {
+        let request = self.request(|| RecordedRequest::BlobProvenance(blob_id));
+        let response = self.node.blob_provenance(blob_id);
+        self.exchange(request, response, |provenance| {
+            RecordedResponse::BlobProvenance(provenance.clone())
+        })
+        .await
+    }
+
+    async fn missing_blob_ids(&self, blob_ids: Vec<BlobId>) -> Result<Vec<BlobId>, NodeError> {
+        let request = self.request(|| RecordedRequest::MissingBlobIds(blob_ids.clone()));
+        let response = self.node.missing_blob_ids(blob_ids);
+        self.exchange(request, response, |blob_ids| {
+            RecordedResponse::BlobIds(blob_ids.clone())
+        })
+        .await
+    }
+
+    async fn stream_certificates(
+        &self,
+        chain_id: ChainId,
+        start: BlockHeight,
+        include_blobs: bool,
+    ) -> Result<CertificateStream, NodeError> {
+        let request = self.request(|| RecordedRequest::StreamCertificates {
+            chain_id,
+            start,
+            include_blobs,
+        });
+        let response = self
+            .node
+            .stream_certificates(chain_id, start, include_blobs);
+        self.exchange(request, response, |_| {
+            RecordedResponse::CertificateStreamOpened
+        })
+        .await
+    }
+}
+
+/// A [`ValidatorNodeProvider`] whose nodes record their conversations if a recorder is set.
+#[derive(Clone)]
+pub struct RecordingNodeProvider<P> {
+    provider: P,
+    recorder: Option<ConversationRecorder>,
+}
+
+impl<P> RecordingNodeProvider<P> {
+    /// Wraps a node provider, recording the exchanges of its nodes if `recorder` is set.
+    pub fn new(provider: P, recorder: Option<ConversationRecorder>) -> Self {
+        Self { provider, recorder }
+    }
+
+    /// Returns the underlying node provider.
+    pub fn inner(&self) -> &P {
+        &self.provider
+    }
+}
+
+impl<P: ValidatorNodeProvider> ValidatorNodeProvider for RecordingNodeProvider<P> {
+    type Node = RecordingNode<P::Node>;
+
+    fn make_node(&self, address: &str) -> Result<Self::Node, NodeError> {
+        let node = self.provider.make_node(address)?;
+        Ok(RecordingNode::new(
+            node,
+            address.to_owned(),
+            self.recorder.clone(),
+        ))
+    }
+
+    fn make_nodes_from_list<A>(
+        &self,
+        validators: impl IntoIterator<Item = (ValidatorPublicKey, A)>,
+    ) -> Result<impl Iterator<Item = (ValidatorPublicKey, Self::Node)>, NodeError>
+    where
+        A: AsRef<str>,
+    {
+        let validators = validators
+            .into_iter()
+            .map(|(public_key, address)| (public_key, address.as_ref().to_owned()))
+            .collect::<Vec<_>>();
+        let addresses = validators.iter().cloned().collect::<BTreeMap<_, _>>();
+        Ok(self
+            .provider
+            .make_nodes_from_list(validators)?
+            .map(|(public_key, node)| {
+                let address = addresses.get(&public_key).cloned().unwrap_or_default();
+                let mut node = RecordingNode::new(node, address, self.recorder.clone());
+                node.validator = Some(public_key);
+                (public_key, node)
+            })
+            .collect::<Vec<_>>()
+            .into_iter())
+    }
+}
+
+/// The result of re-driving a recorded request against a local worker.
+#[derive(Debug)]
+pub struct ReplayOutcome {
+    /// The recorded exchange.
+    pub exchange: RecordedExchange,
+    /// The response of the local worker, or `None` if the request is not handled by workers,
+    /// e.g. downloads.
+    pub replayed: Option<Result<ChainInfoResponse, LocalNodeError>>,
+}
+
+impl ReplayOutcome {
+    /// Returns whether the local worker behaved differently from the validator: either only
+    /// one of them failed, or they disagree on the chain's height or latest block.
+    pub fn diverges(&self) -> bool {
+        match (&self.exchange.response, &self.replayed) {
+            (_, None) => false,
+            (Ok(RecordedResponse::ChainInfo(recorded)), Some(Ok(replayed))) => {
+                recorded.info.next_block_height != replayed.info.next_block_height
+                    || recorded.info.block_hash != replayed.info.block_hash
+            }
+            (Ok(_), Some(Ok(_))) | (Err(_), Some(Err(_))) => false,
+            (Ok(_), Some(Err(_))) | (Err(_), Some(Ok(_))) => true,
+        }
+    }
+}
+
+/// Re-drives the requests of a recorded conversation, in order, against a local worker.
+///
+/// Only the requests that a worker handles directly (proposals, certificates, chain info
+/// queries and blobs) are replayed; the others are reported with no replayed response.
+pub async fn replay_conversation<S>(
+    exchanges: impl IntoIterator<Item = RecordedExchange>,
+    node: &LocalNodeClient<S>,
+) -> Vec<ReplayOutcome>
+where
+    S: Storage + Clone + Send + Sync + 'static,
+{
+    let mut outcomes = Vec::new();
+    for exchange in exchanges {
+        let replayed = replay_request(exchange.request.clone(), node).await;
+        outcomes.push(ReplayOutcome { exchange, replayed });
+    }
+    outcomes
+}
+
+async fn replay_request<S>(
+    request: RecordedRequest,
+    node: &LocalNodeClient<S>,
+) -> Option<Result<ChainInfoResponse, LocalNodeError>>
+where
+    S: Storage + Clone + Send + Sync + 'static,
+{
+    Some(match request {
+        RecordedRequest::BlockProposal(proposal) => node.handle_block_proposal(*proposal).await,
+        RecordedRequest::LiteCertificate { certificate, .. } => {
+            node.handle_lite_certificate(certificate, &()).await
+        }
+        RecordedRequest::ConfirmedCertificate { certificate, .. } => {
+            node.handle_certificate(*certificate, &()).await
+        }
+        RecordedRequest::ValidatedCertificate(certificate) => {
+            node.handle_certificate(*certificate, &()).await
+        }
+        RecordedRequest::TimeoutCertificate(certificate) => {
+            node.handle_certificate(*certificate, &()).await
+        }
+        RecordedRequest::ChainInfoQuery(query) => node.handle_chain_info_query(*query).await,
+        RecordedRequest::HandlePendingBlob { chain_id, blob } => {
+            match node
+                .handle_pending_blobs(chain_id, vec![Blob::new(blob)])
+                .await
+            {
+                Ok(()) => {
+                    node.handle_chain_info_query(ChainInfoQuery::new(chain_id))
+                        .await
+                }
+                Err(error) => Err(error),
+            }
+        }
+        RecordedRequest::VersionInfo
+        | RecordedRequest::NetworkDescription
+        | RecordedRequest::Subscribe(_)
+        | RecordedRequest::UploadBlob(_)
+        | RecordedRequest::DownloadBlob(_)
+        | RecordedRequest::DownloadPendingBlob { .. }
+        | RecordedRequest::DownloadCertificate(_)
+        | RecordedRequest::DownloadCertificates(_)
+        | RecordedRequest::BlobLastUsedBy(_)
+        | RecordedRequest::BlobProvenance(_)
+        | RecordedRequest::MissingBlobIds(_)
+        | RecordedRequest::StreamCertificates { .. } => return None,
+    })
+}
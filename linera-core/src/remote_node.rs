@@ -4,7 +4,11 @@
 use std::{collections::HashSet, time::Duration};
 
 use custom_debug_derive::Debug;
-use futures::{future::try_join_all, stream::FuturesUnordered, StreamExt};
+use futures::{
+    future::try_join_all,
+    stream::{FuturesUnordered, Stream},
+    StreamExt,
+};
 use linera_base::{
     crypto::{CryptoHash, ValidatorPublicKey},
     data_types::{Blob, BlockHeight},
@@ -23,7 +27,7 @@ use tracing::{instrument, warn};
 
 use crate::{
     data_types::{BlockHeightRange, ChainInfo, ChainInfoQuery, ChainInfoResponse},
-    node::{CrossChainMessageDelivery, NodeError, ValidatorNode},
+    node::{CrossChainMessageDelivery, NodeError, StreamedCertificate, ValidatorNode},
 };
 
 /// A validator node together with the validator's name.
@@ -190,6 +194,30 @@ impl<N: ValidatorNode> RemoteNode<N> {
             .collect()
     }
 
+    /// Streams the certificates of the chain from height `start` on, together with the blobs
+    /// their blocks require. Certificates of other chains are rejected, and blobs that are
+    /// not required are dropped.
+    pub(crate) async fn stream_certificates_from(
+        &self,
+        chain_id: ChainId,
+        start: BlockHeight,
+    ) -> Result<impl Stream<Item = Result<StreamedCertificate, NodeError>> + Unpin, NodeError> {
+        tracing::debug!(name = ?self.public_key, ?chain_id, ?start, "Streaming certificates");
+        let stream = self.node.stream_certificates(chain_id, start, true).await?;
+        Ok(stream.map(move |result| {
+            let mut streamed = result?;
+            ensure!(
+                streamed.certificate.inner().chain_id() == chain_id,
+                NodeError::UnexpectedCertificateValue
+            );
+            let required_blob_ids = streamed.certificate.block().required_blob_ids();
+            streamed
+                .blobs
+                .retain(|blob| required_blob_ids.contains(&blob.id()));
+            Ok(streamed)
+        }))
+    }
+
     #[instrument(level = "trace")]
     pub(crate) async fn download_certificate_for_blob(
         &self,
@@ -0,0 +1,264 @@
+// Copyright (c) Zefchain Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! Admission control for the service runtimes that answer application queries.
+//!
+//! Each query to a user application runs in a Wasm or EVM service runtime, either spawned
+//! for the query or kept alive by the chain worker. Without limits, heavy GraphQL traffic
+//! across many chains spawns as many runtimes as there are concurrent queries. The
+//! [`ServiceRuntimePool`] bounds the number of queries running at the same time, in total
+//! and per application, and the memory reserved by the runtimes. Queries that can't start
+//! within the configured delay are rejected, so that clients can retry elsewhere.
+//!
+//! Queries wait for their [`QueryPermit`] before they are sent to the chain worker, so that
+//! waiting doesn't hold up the other requests to the chain.
+
+#[cfg(test)]
+#[path = "unit_tests/service_runtime_pool_tests.rs"]
+mod unit_tests;
+
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+
+#[cfg(with_metrics)]
+use linera_base::time::Instant;
+use linera_base::{
+    identifiers::ApplicationId,
+    time::{timer::timeout, Duration},
+};
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+use crate::worker::WorkerError;
+
+#[cfg(with_metrics)]
+mod metrics {
+    use std::sync::LazyLock;
+
+    use linera_base::prometheus_util::{
+        exponential_bucket_interval, register_histogram, register_int_counter_vec,
+    };
+    use prometheus::{Histogram, IntCounterVec};
+
+    pub static SERVICE_RUNTIME_QUEUE_LATENCY: LazyLock<Histogram> = LazyLock::new(|| {
+        register_histogram(
+            "service_runtime_queue_latency",
+            "Time queries waited for a service runtime (ms)",
+            exponential_bucket_interval(0.1, 10_000.0),
+        )
+    });
+
+    pub static SERVICE_RUNTIME_REJECTED_QUERIES: LazyLock<IntCounterVec> = LazyLock::new(|| {
+        register_int_counter_vec(
+            "service_runtime_rejected_queries",
+            "Number of queries rejected because no service runtime was available in time",
+            &[],
+        )
+    });
+
+    pub static LONG_LIVED_SERVICE_RUNTIMES_DENIED: LazyLock<IntCounterVec> = LazyLock::new(|| {
+        register_int_counter_vec(
+            "long_lived_service_runtimes_denied",
+            "Number of long-lived service runtimes not started for lack of memory",
+            &[],
+        )
+    });
+}
+
+/// The limits of a [`ServiceRuntimePool`].
+#[derive(Clone, Debug)]
+pub struct ServiceRuntimePoolConfig {
+    /// The maximum number of queries running at the same time, across all applications.
+    pub max_concurrent_queries: usize,
+    /// The maximum number of queries to the same application running at the same time.
+    pub max_concurrent_queries_per_application: usize,
+    /// How long a query may wait for a runtime before it is rejected.
+    pub max_queue_delay: Duration,
+    /// The memory that all service runtimes together may reserve, in MiB.
+    pub max_memory_mb: u32,
+    /// The memory reserved by each service runtime, in MiB.
+    pub memory_per_runtime_mb: u32,
+}
+
+impl Default for ServiceRuntimePoolConfig {
+    fn default() -> Self {
+        Self {
+            max_concurrent_queries: 64,
+            max_concurrent_queries_per_application: 16,
+            max_queue_delay: Duration::from_secs(2),
+            max_memory_mb: 4096,
+            memory_per_runtime_mb: 64,
+        }
+    }
+}
+
+/// Limits the service runtimes that run at the same time.
+#[derive(Debug)]
+pub struct ServiceRuntimePool {
+    config: ServiceRuntimePoolConfig,
+    /// One permit per query allowed to run.
+    queries: Arc<Semaphore>,
+    /// One permit per MiB of memory that runtimes may reserve.
+    memory: Arc<Semaphore>,
+    /// The permits of the applications with queries running or waiting.
+    applications: Mutex<HashMap<ApplicationId, Arc<Semaphore>>>,
+}
+
+/// Allows a query to run until it is dropped.
+#[derive(Debug)]
+pub struct QueryPermit {
+    pool: Arc<ServiceRuntimePool>,
+    application_id: ApplicationId,
+    application_permit: Option<OwnedSemaphorePermit>,
+    _query_permit: OwnedSemaphorePermit,
+    memory: Option<MemoryReservation>,
+}
+
+/// Memory reserved for a service runtime, released when dropped.
+#[derive(Debug)]
+pub struct MemoryReservation {
+    _permit: OwnedSemaphorePermit,
+}
+
+impl ServiceRuntimePool {
+    /// Creates a pool with the given limits.
+    pub fn new(config: ServiceRuntimePoolConfig) -> Self {
+        Self {
+            queries: Arc::new(Semaphore::new(config.max_concurrent_queries)),
+            memory: Arc::new(Semaphore::new(config.max_memory_mb as usize)),
+            applications: Mutex::default(),
+            config,
+        }
+    }
+
+    /// Waits until a query to `application_id` may run, or returns an error if that takes
+    /// longer than the configured delay. Memory is only reserved if `reserve_memory` is
+    /// true, i.e. if the query doesn't run in a long-lived runtime that already reserved it.
+    pub async fn acquire_query(
+        self: &Arc<Self>,
+        application_id: ApplicationId,
+        reserve_memory: bool,
+    ) -> Result<QueryPermit, WorkerError> {
+        #[cfg(with_metrics)]
+        let start = Instant::now();
+        let application = self
+            .applications
+            .lock()
+            .unwrap()
+            .entry(application_id)
+            .or_insert_with(|| {
+                Arc::new(Semaphore::new(
+                    self.config.max_concurrent_queries_per_application,
+                ))
+            })
+            .clone();
+        let memory_per_runtime = self.config.memory_per_runtime_mb;
+        let acquire = async {
+            let application_permit = application.acquire_owned().await;
+            let query_permit = self.queries.clone().acquire_owned().await;
+            let memory = if reserve_memory {
+                let permit = self
+                    .memory
+                    .clone()
+                    .acquire_many_owned(memory_per_runtime)
+                    .await;
+                Some(MemoryReservation {
+                    _permit: permit.expect("the semaphore is never closed"),
+                })
+            } else {
+                None
+            };
+            (
+                application_permit.expect("the semaphore is never closed"),
+                query_permit.expect("the semaphore is never closed"),
+                memory,
+            )
+        };
+        let result = timeout(self.config.max_queue_delay, acquire).await;
+        drop(application);
+        let Ok((application_permit, query_permit, memory)) = result else {
+            self.remove_idle_application(application_id);
+            #[cfg(with_metrics)]
+            metrics::SERVICE_RUNTIME_REJECTED_QUERIES
+                .with_label_values(&[])
+                .inc();
+            return Err(WorkerError::ServiceRuntimesBusy(application_id));
+        };
+        #[cfg(with_metrics)]
+        metrics::SERVICE_RUNTIME_QUEUE_LATENCY.observe(start.elapsed().as_secs_f64() * 1000.0);
+        Ok(QueryPermit {
+            pool: self.clone(),
+            application_id,
+            application_permit: Some(application_permit),
+            _query_permit: query_permit,
+            memory,
+        })
+    }
+
+    /// Reserves the memory of a long-lived service runtime, if enough is available now.
+    pub fn try_reserve_long_lived_runtime(&self) -> Option<MemoryReservation> {
+        let reservation = self.try_reserve_memory();
+        #[cfg(with_metrics)]
+        if reservation.is_none() {
+            metrics::LONG_LIVED_SERVICE_RUNTIMES_DENIED
+                .with_label_values(&[])
+                .inc();
+        }
+        reservation
+    }
+
+    /// Reserves the memory of a service runtime, if enough is available now.
+    fn try_reserve_memory(&self) -> Option<MemoryReservation> {
+        self.memory
+            .clone()
+            .try_acquire_many_owned(self.config.memory_per_runtime_mb)
+            .ok()
+            .map(|permit| MemoryReservation { _permit: permit })
+    }
+
+    /// Returns the number of queries that may start immediately.
+    pub fn available_queries(&self) -> usize {
+        self.queries.available_permits()
+    }
+
+    /// Returns the memory that is not reserved by any runtime, in MiB.
+    pub fn available_memory_mb(&self) -> usize {
+        self.memory.available_permits()
+    }
+
+    /// Forgets the semaphore of `application_id` if no query is running or waiting for it.
+    fn remove_idle_application(&self, application_id: ApplicationId) {
+        let mut applications = self.applications.lock().unwrap();
+        if applications
+            .get(&application_id)
+            .is_some_and(|semaphore| Arc::strong_count(semaphore) == 1)
+        {
+            applications.remove(&application_id);
+        }
+    }
+}
+
+impl QueryPermit {
+    /// Makes sure that the permit reserves the memory of a runtime for the query, without
+    /// waiting. This is needed if the query was admitted for a long-lived runtime that the
+    /// chain worker couldn't start.
+    pub fn ensure_memory_is_reserved(&mut self) -> Result<(), WorkerError> {
+        if self.memory.is_none() {
+            let memory = self
+                .pool
+                .try_reserve_memory()
+                .ok_or(WorkerError::ServiceRuntimesBusy(self.application_id))?;
+            self.memory = Some(memory);
+        }
+        Ok(())
+    }
+}
+
+impl Drop for QueryPermit {
+    fn drop(&mut self) {
+        // Release the permit first, so that it doesn't keep the semaphore alive.
+        drop(self.application_permit.take());
+        self.pool.remove_idle_application(self.application_id);
+    }
+}
@@ -0,0 +1,134 @@
+// Copyright (c) Zefchain Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! Fencing of the validator instances of an active/passive pair.
+//!
+//! Both instances of a shard share its storage, which holds the fencing term of the
+//! instance allowed to sign and write. The upper half of a term counts the promotions, and
+//! its lower half identifies the promoted instance, so that no two instances ever hold the
+//! same term. The stored term alone decides which instance is the primary: an instance
+//! checks that the stored term is still its own both before saving a chain and after, and
+//! only returns a vote if both checks pass. An old primary that missed its demotion
+//! therefore stops as soon as another instance is promoted, instead of signing conflicting
+//! votes, and a vote saved while another instance was being promoted is never returned.
+//!
+//! The storage offers no compare-and-swap, so the checks cannot make the writes of a chain
+//! conditional on the term: a save of the old primary that passed its check just before a
+//! promotion still lands afterwards. A promoted instance therefore writes its new term, waits
+//! for a handover delay and only then becomes the primary, if its term is still the stored
+//! one. The delay must be at least the longest time between the check before a save and the
+//! end of that save, so that the saves of the old primary land before the new primary loads
+//! any chain, and are not overwritten by it. If two instances are promoted concurrently, the
+//! last write of the term wins and the other instance remains a standby. Chains loaded before
+//! a promotion are never saved, since the previous primary may have changed them since.
+
+use std::sync::{
+    atomic::{AtomicU64, Ordering},
+    Arc,
+};
+
+use linera_base::{ensure, time::Duration};
+use linera_storage::Storage;
+use rand::Rng as _;
+use tracing::{info, warn};
+
+use crate::worker::WorkerError;
+
+/// The term of a validator instance that is not allowed to sign.
+const STANDBY_TERM: u64 = 0;
+
+/// Decides whether a validator instance may sign votes and write chain states, for a
+/// validator run as an active/passive pair.
+#[derive(Clone, Debug)]
+pub struct SigningFence {
+    /// The shard whose fencing term this instance checks.
+    shard_id: u32,
+    /// The fencing term this instance was promoted with, or [`STANDBY_TERM`].
+    term: Arc<AtomicU64>,
+    /// The lower half of the terms of this instance, telling it apart from the other one.
+    instance_tag: u32,
+    /// How long a promoted instance waits before it starts signing.
+    handover_delay: Duration,
+}
+
+impl SigningFence {
+    /// Creates the fence of a standby instance of the given shard, which only signs once
+    /// promoted.
+    pub fn standby(shard_id: u32, handover_delay: Duration) -> Self {
+        Self {
+            shard_id,
+            term: Arc::new(AtomicU64::new(STANDBY_TERM)),
+            instance_tag: rand::thread_rng().gen(),
+            handover_delay,
+        }
+    }
+
+    /// Returns the fencing term of this instance, if it is the primary.
+    pub fn term(&self) -> Option<u64> {
+        let term = self.term.load(Ordering::SeqCst);
+        (term != STANDBY_TERM).then_some(term)
+    }
+
+    /// Returns whether this instance is currently allowed to sign, as far as it knows.
+    pub fn is_primary(&self) -> bool {
+        self.term().is_some()
+    }
+
+    /// Makes this instance the primary, fencing off the previous one, and returns the new
+    /// term. Returns once the handover delay has elapsed, or fails with
+    /// [`WorkerError::NotPrimary`] if another instance was promoted in the meantime.
+    pub async fn promote<S: Storage>(&self, storage: &S) -> Result<u64, WorkerError> {
+        if let Some(term) = self.term() {
+            return Ok(term);
+        }
+        let stored_term = storage.read_signing_term(self.shard_id).await?;
+        let term = (((stored_term >> 32) + 1) << 32) | u64::from(self.instance_tag);
+        storage.write_signing_term(self.shard_id, term).await?;
+        info!(
+            shard_id = self.shard_id,
+            term, "Claimed the primary term; waiting for the handover delay before signing"
+        );
+        linera_base::time::timer::sleep(self.handover_delay).await;
+        let stored_term = storage.read_signing_term(self.shard_id).await?;
+        if stored_term != term {
+            warn!(
+                shard_id = self.shard_id,
+                term, stored_term, "Another instance was promoted concurrently; staying standby"
+            );
+            return Err(WorkerError::NotPrimary);
+        }
+        self.term.store(term, Ordering::SeqCst);
+        info!(shard_id = self.shard_id, term, "Promoted to primary");
+        Ok(term)
+    }
+
+    /// Stops signing until this instance is promoted again.
+    pub fn demote(&self) {
+        self.term.store(STANDBY_TERM, Ordering::SeqCst);
+    }
+
+    /// Checks that this instance is still the primary in the term a chain was loaded in,
+    /// demoting it if another instance was promoted in the meantime.
+    pub async fn ensure_is_primary<S: Storage>(
+        &self,
+        storage: &S,
+        loaded_in_term: Option<u64>,
+    ) -> Result<(), WorkerError> {
+        let Some(term) = self.term() else {
+            return Err(WorkerError::NotPrimary);
+        };
+        ensure!(loaded_in_term == Some(term), WorkerError::NotPrimary);
+        let stored_term = storage.read_signing_term(self.shard_id).await?;
+        if stored_term != term {
+            warn!(
+                shard_id = self.shard_id,
+                term, stored_term, "Another instance was promoted; demoting this one"
+            );
+            let _ =
+                self.term
+                    .compare_exchange(term, STANDBY_TERM, Ordering::SeqCst, Ordering::SeqCst);
+            return Err(WorkerError::NotPrimary);
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,170 @@
+// Copyright (c) Zefchain Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+use assert_matches::assert_matches;
+use linera_base::crypto::ValidatorKeypair;
+
+use super::*;
+
+fn chain_id(name: &str) -> ChainId {
+    ChainId(CryptoHash::test_hash(name))
+}
+
+fn certificate_event(height: u64) -> AuditEvent {
+    AuditEvent::Certificate {
+        kind: CertificateKind::Confirmed,
+        height: BlockHeight(height),
+        round: Round::Fast,
+        value_hash: CryptoHash::test_hash(format!("block {height}")),
+    }
+}
+
+/// Tests that the entries are chained to each other, and that altering, removing or
+/// reordering them is detected.
+#[test_log::test(tokio::test)]
+async fn test_audit_log_is_tamper_evident() {
+    let log = AuditLog::new(MemoryAuditSink::default(), None).unwrap();
+    for height in 0..3 {
+        log.record(
+            chain_id("chain"),
+            Timestamp::from(height),
+            [certificate_event(height)],
+        )
+        .await
+        .unwrap();
+    }
+    log.record(
+        chain_id("chain"),
+        Timestamp::from(3),
+        [AuditEvent::CrossChainUpdate {
+            origin: chain_id("origin"),
+            heights: vec![BlockHeight(4), BlockHeight(5)],
+        }],
+    )
+    .await
+    .unwrap();
+    let entries = log.verify().unwrap();
+    assert_eq!(entries.len(), 4);
+    assert_eq!(entries[0].record.previous_hash, None);
+    assert_eq!(entries[3].record.previous_hash, Some(entries[2].hash));
+
+    let mut altered = entries.clone();
+    altered[1].record.event = certificate_event(7);
+    assert_matches!(
+        verify_audit_log(&altered, None),
+        Err(AuditError::InvalidHash { sequence: 1 })
+    );
+
+    let mut removed = entries.clone();
+    removed.remove(1);
+    assert_matches!(
+        verify_audit_log(&removed, None),
+        Err(AuditError::Discontinuity { sequence: 2 })
+    );
+
+    let mut reordered = entries;
+    reordered.swap(1, 2);
+    assert_matches!(
+        verify_audit_log(&reordered, None),
+        Err(AuditError::Discontinuity { sequence: 2 })
+    );
+}
+
+/// Tests that a signed log cannot be rewritten, even with consistent hashes, without the
+/// validator's key.
+#[test_log::test(tokio::test)]
+async fn test_signed_audit_log_cannot_be_rewritten() {
+    let keypair = ValidatorKeypair::generate();
+    let log = AuditLog::new(MemoryAuditSink::default(), Some(keypair.secret_key.copy())).unwrap();
+    let events = (0..3).map(certificate_event).collect::<Vec<_>>();
+    log.record(chain_id("chain"), Timestamp::from(0), events.clone())
+        .await
+        .unwrap();
+    let entries = log.verify().unwrap();
+    verify_audit_log(&entries, Some(keypair.public_key)).unwrap();
+
+    let forged = AuditLog::new(MemoryAuditSink::default(), None).unwrap();
+    forged
+        .record(chain_id("chain"), Timestamp::from(0), events)
+        .await
+        .unwrap();
+    let forged_entries = forged.verify().unwrap();
+    assert_matches!(
+        verify_audit_log(&forged_entries, Some(keypair.public_key)),
+        Err(AuditError::InvalidSignature { sequence: 0 })
+    );
+
+    let other = ValidatorKeypair::generate();
+    assert_matches!(
+        verify_audit_log(&entries, Some(other.public_key)),
+        Err(AuditError::InvalidSignature { sequence: 0 })
+    );
+}
+
+/// Tests that a reopened file log continues the chain of hashes from its last entry, even
+/// if that entry doesn't fit in the chunk read from the end of the file.
+#[test_log::test(tokio::test)]
+async fn test_file_audit_log_continues_after_reopening() {
+    let directory = tempfile::tempdir().unwrap();
+    let path = directory.path().join("audit.log");
+    let log = AuditLog::new(FileAuditSink::new(&path).unwrap(), None).unwrap();
+    log.record(
+        chain_id("chain"),
+        Timestamp::from(0),
+        [certificate_event(0)],
+    )
+    .await
+    .unwrap();
+    let heights = (0..1000).map(BlockHeight).collect();
+    let origin = chain_id("origin");
+    log.record(
+        chain_id("chain"),
+        Timestamp::from(1),
+        [AuditEvent::CrossChainUpdate { origin, heights }],
+    )
+    .await
+    .unwrap();
+    drop(log);
+
+    let log = AuditLog::new(FileAuditSink::new(&path).unwrap(), None).unwrap();
+    log.record(
+        chain_id("chain"),
+        Timestamp::from(2),
+        [certificate_event(1)],
+    )
+    .await
+    .unwrap();
+    let entries = log.verify().unwrap();
+    assert_eq!(entries.len(), 3);
+    assert_eq!(entries[2].record.sequence, 2);
+    assert_eq!(entries[2].record.previous_hash, Some(entries[1].hash));
+}
+
+/// Tests that entries recorded concurrently are all persisted, in the order of the chain.
+#[test_log::test(tokio::test)]
+async fn test_file_audit_log_batches_concurrent_entries() {
+    let directory = tempfile::tempdir().unwrap();
+    let path = directory.path().join("audit.log");
+    let log = AuditLog::new(FileAuditSink::new(&path).unwrap(), None).unwrap();
+    futures::future::try_join_all((0..50).map(|height| {
+        log.record(
+            chain_id("chain"),
+            Timestamp::from(height),
+            [certificate_event(height)],
+        )
+    }))
+    .await
+    .unwrap();
+    assert_eq!(log.verify().unwrap().len(), 50);
+}
+
+/// Tests that two processes, e.g. two shards, cannot write to the same file.
+#[test]
+fn test_file_audit_log_is_exclusive() {
+    let directory = tempfile::tempdir().unwrap();
+    let path = directory.path().join("audit.log");
+    let sink = FileAuditSink::new(&path).unwrap();
+    assert_matches!(FileAuditSink::new(&path), Err(AuditError::InUse(_)));
+    drop(sink);
+    FileAuditSink::new(&path).unwrap();
+}
@@ -6,25 +6,31 @@ mod test_helpers;
 #[path = "./wasm_client_tests.rs"]
 mod wasm;
 
-use std::collections::{BTreeMap, BTreeSet};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    sync::Arc,
+};
 
 use assert_matches::assert_matches;
-use futures::StreamExt;
+use futures::{StreamExt, TryStreamExt as _};
 use linera_base::{
-    crypto::{AccountSecretKey, CryptoHash, InMemorySigner},
+    crypto::{AccountSecretKey, CryptoError, CryptoHash, InMemorySigner, Signer as _},
     data_types::*,
-    identifiers::{Account, AccountOwner, ApplicationId},
+    identifiers::{
+        Account, AccountOwner, ApplicationId, EventStreamInfo, GenericApplicationId, StreamId,
+    },
     ownership::{ChainOwnership, TimeoutConfig},
+    time::Duration,
 };
 use linera_chain::{
     data_types::{IncomingBundle, MessageBundle, PostedMessage},
     manager::LockingBlock,
-    types::Timeout,
+    types::{ConfirmedBlock, ConfirmedBlockCertificate, LiteCertificate, Timeout},
     ChainError, ChainExecutionContext,
 };
 use linera_execution::{
     committee::Committee,
-    system::{Recipient, SystemOperation},
+    system::{Recipient, SystemOperation, EPOCH_STREAM_NAME},
     ExecutionError, Message, MessageKind, Operation, QueryOutcome, ResourceControlPolicy,
     SystemMessage, SystemQuery, SystemResponse,
 };
@@ -46,18 +52,26 @@ use crate::test_utils::ScyllaDbStorageBuilder;
 use crate::test_utils::ServiceStorageBuilder;
 use crate::{
     client::{
-        BlanketMessagePolicy, ChainClient, ChainClientError, ClientOutcome, MessageAction,
-        MessagePolicy,
+        checkpoint::TrustedCheckpoint,
+        rebase::{DropReason, DroppedOperation, RebaseDecision},
+        reproposal::{ReproposalConfig, Reproposals},
+        BlanketMessagePolicy, ChainClient, ChainClientError, ChainClientOptions, ClientOutcome,
+        MessageAction, MessagePolicy,
     },
+    data_types::{BlockHeightRange, ChainInfoQuery},
     local_node::LocalNodeError,
     node::{
         NodeError::{self, ClientIoError},
-        ValidatorNode,
+        ValidatorNode, ValidatorNodeProvider as _,
+    },
+    recording::{
+        replay_conversation, ConversationRecorder, RecordedRequest, RecordingNodeProvider,
     },
     test_utils::{
-        ClientOutcomeResultExt as _, FaultType, MemoryStorageBuilder, StorageBuilder, TestBuilder,
+        with_wrong_state_hash, ClientOutcomeResultExt as _, FaultType, MemoryStorageBuilder,
+        StorageBuilder, TestBuilder,
     },
-    updater::CommunicationError,
+    updater::{CommunicationError, HedgingConfig},
     worker::{Notification, Reason, WorkerError},
     Environment,
 };
@@ -141,6 +155,371 @@ where
     Ok(())
 }
 
+#[test_case(MemoryStorageBuilder::default(); "memory")]
+#[test_log::test(tokio::test)]
+async fn test_debug_bundle<B>(storage_builder: B) -> anyhow::Result<()>
+where
+    B: StorageBuilder,
+{
+    let signer = InMemorySigner::new(None);
+    let mut builder = TestBuilder::new(storage_builder, 4, 1, signer).await?;
+    let sender = builder.add_root_chain(1, Amount::from_tokens(4)).await?;
+    let receiver = builder.add_root_chain(2, Amount::ZERO).await?;
+    for _ in 0..3 {
+        sender
+            .transfer_to_account(
+                AccountOwner::CHAIN,
+                Amount::ONE,
+                Account::chain(receiver.chain_id()),
+            )
+            .await
+            .unwrap_ok_committed();
+    }
+
+    let bundle = sender
+        .debug_bundle(BlockHeightRange::multi(BlockHeight::from(1), 5))
+        .await?;
+    assert_eq!(bundle.info.chain_id, sender.chain_id());
+    assert_eq!(bundle.info.next_block_height, BlockHeight::from(3));
+    let heights = bundle
+        .certificates
+        .iter()
+        .map(|certificate| certificate.block().header.height)
+        .collect::<Vec<_>>();
+    assert_eq!(heights, vec![BlockHeight::from(1), BlockHeight::from(2)]);
+
+    let archive = bundle.to_archive()?;
+    let mut paths = Vec::new();
+    for entry in tar::Archive::new(&archive[..]).entries()? {
+        let mut entry = entry?;
+        let path = entry.path()?.to_string_lossy().into_owned();
+        if path.ends_with(".json") {
+            serde_json::from_reader::<_, serde_json::Value>(&mut entry)?;
+        }
+        paths.push(path);
+    }
+    assert_eq!(
+        paths[..4],
+        [
+            "version.json",
+            "chain_info.json",
+            "certificates/1.json",
+            "certificates/2.json"
+        ]
+    );
+    Ok(())
+}
+
+#[test_case(MemoryStorageBuilder::default(); "memory")]
+#[test_log::test(tokio::test)]
+async fn test_probe_network_health<B>(storage_builder: B) -> anyhow::Result<()>
+where
+    B: StorageBuilder,
+{
+    let signer = InMemorySigner::new(None);
+    let mut builder = TestBuilder::new(storage_builder, 4, 1, signer).await?;
+    let sender = builder.add_root_chain(1, Amount::from_tokens(4)).await?;
+    let receiver = builder.add_root_chain(2, Amount::ZERO).await?;
+    builder.set_fault_type([3], FaultType::Offline).await;
+    sender
+        .transfer_to_account(
+            AccountOwner::CHAIN,
+            Amount::ONE,
+            Account::chain(receiver.chain_id()),
+        )
+        .await
+        .unwrap_ok_committed();
+
+    let health = sender.client.probe_network_health().await?;
+    assert_eq!(health.validators.len(), 4);
+    assert!(health
+        .local_heights
+        .contains(&(sender.chain_id(), BlockHeight::from(1))));
+    let sender_heights = health
+        .validators
+        .iter()
+        .map(|validator| {
+            assert!(validator.reachable);
+            let chain = validator
+                .chains
+                .iter()
+                .find(|chain| chain.chain_id == sender.chain_id())
+                .unwrap();
+            (chain.next_block_height, chain.lag)
+        })
+        .collect::<Vec<_>>();
+    let up_to_date = (Some(BlockHeight::from(1)), Some(0));
+    assert_eq!(
+        sender_heights.iter().filter(|h| **h == up_to_date).count(),
+        3
+    );
+    assert_eq!(
+        sender_heights
+            .iter()
+            .filter(|h| **h == (None, None))
+            .count(),
+        1
+    );
+    Ok(())
+}
+
+#[test_case(MemoryStorageBuilder::default(); "memory")]
+#[test_log::test(tokio::test)]
+async fn test_hedged_requests<B>(storage_builder: B) -> anyhow::Result<()>
+where
+    B: StorageBuilder,
+{
+    let signer = InMemorySigner::new(None);
+    // The hedging delay is so long that the test only completes if the other validators
+    // are contacted as soon as one of the first ones fails.
+    let options = ChainClientOptions {
+        hedging: Some(HedgingConfig {
+            extra_validators: 0,
+            min_delay: Duration::from_secs(3600),
+            latency_factor: 2.0,
+        }),
+        ..ChainClientOptions::test_default()
+    };
+    let mut builder = TestBuilder::new(storage_builder, 4, 1, signer)
+        .await?
+        .with_chain_client_options(options);
+    let sender = builder.add_root_chain(1, Amount::from_tokens(4)).await?;
+    let receiver = builder.add_root_chain(2, Amount::ZERO).await?;
+    builder.set_fault_type([3], FaultType::Offline).await;
+    for height in 0..2 {
+        let certificate = sender
+            .transfer_to_account(
+                AccountOwner::CHAIN,
+                Amount::ONE,
+                Account::chain(receiver.chain_id()),
+            )
+            .await
+            .unwrap_ok_committed();
+        assert_eq!(certificate.block().header.height, BlockHeight(height));
+    }
+
+    // Validators that were never contacted are contacted first, so all of them were.
+    let latencies = &sender.client.validator_latencies;
+    assert!(builder
+        .initial_committee
+        .validators()
+        .keys()
+        .all(|public_key| latencies.expected(public_key).is_some()));
+    Ok(())
+}
+
+#[test_case(MemoryStorageBuilder::default(); "memory")]
+#[test_log::test(tokio::test)]
+async fn test_hedged_requests_with_extreme_latency_factors<B>(
+    storage_builder: B,
+) -> anyhow::Result<()>
+where
+    B: StorageBuilder,
+{
+    let signer = InMemorySigner::new(None);
+    let mut builder = TestBuilder::new(storage_builder, 4, 1, signer).await?;
+    let receiver = builder.add_root_chain(2, Amount::ZERO).await?;
+    for (index, latency_factor) in [f64::INFINITY, f64::MAX, f64::NAN, -1.0]
+        .into_iter()
+        .enumerate()
+    {
+        builder = builder.with_chain_client_options(ChainClientOptions {
+            hedging: Some(HedgingConfig {
+                extra_validators: 0,
+                min_delay: Duration::from_millis(10),
+                latency_factor,
+            }),
+            ..ChainClientOptions::test_default()
+        });
+        let sender = builder
+            .add_root_chain(3 + index as u32, Amount::from_tokens(4))
+            .await?;
+        // Once the latencies are known, the factor is applied to them without panicking.
+        for height in 0..2 {
+            let certificate = sender
+                .transfer_to_account(
+                    AccountOwner::CHAIN,
+                    Amount::ONE,
+                    Account::chain(receiver.chain_id()),
+                )
+                .await
+                .unwrap_ok_committed();
+            assert_eq!(certificate.block().header.height, BlockHeight(height));
+        }
+    }
+    Ok(())
+}
+
+#[test_case(MemoryStorageBuilder::default(); "memory")]
+#[test_log::test(tokio::test)]
+async fn test_record_and_replay_conversation<B>(storage_builder: B) -> anyhow::Result<()>
+where
+    B: StorageBuilder,
+{
+    /// A writer whose contents remain readable after it is handed to the recorder.
+    #[derive(Clone, Default)]
+    struct SharedBuffer(Arc<std::sync::Mutex<Vec<u8>>>);
+
+    impl std::io::Write for SharedBuffer {
+        fn write(&mut self, bytes: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().extend_from_slice(bytes);
+            Ok(bytes.len())
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    let signer = InMemorySigner::new(None);
+    let mut builder = TestBuilder::new(storage_builder, 4, 0, signer).await?;
+    let sender = builder.add_root_chain(1, Amount::from_tokens(4)).await?;
+    sender
+        .transfer_to_account(
+            AccountOwner::CHAIN,
+            Amount::ONE,
+            Account::chain(builder.admin_id()),
+        )
+        .await
+        .unwrap_ok_committed();
+
+    let buffer = SharedBuffer::default();
+    let provider = RecordingNodeProvider::new(
+        builder.make_node_provider(),
+        Some(ConversationRecorder::new(buffer.clone())),
+    );
+    let (public_key, node) = provider
+        .make_nodes(&builder.initial_committee)?
+        .next()
+        .unwrap();
+    let query = ChainInfoQuery::new(sender.chain_id());
+    let response = node.handle_chain_info_query(query).await?;
+    assert_eq!(response.info.next_block_height, BlockHeight::from(1));
+
+    let exchanges = ConversationRecorder::read(buffer.0.lock().unwrap().as_slice())?;
+    assert_eq!(exchanges.len(), 1);
+    assert_eq!(exchanges[0].validator, Some(public_key));
+    assert!(exchanges[0].sent_at <= exchanges[0].received_at);
+    assert_matches!(
+        &exchanges[0].request,
+        RecordedRequest::ChainInfoQuery(query) if query.chain_id == sender.chain_id()
+    );
+
+    let outcomes = replay_conversation(exchanges, &sender.client.local_node).await;
+    assert_eq!(outcomes.len(), 1);
+    assert_matches!(&outcomes[0].replayed, Some(Ok(_)));
+    assert!(!outcomes[0].diverges());
+    Ok(())
+}
+
+#[test_case(MemoryStorageBuilder::default(); "memory")]
+#[test_log::test(tokio::test)]
+async fn test_stream_certificates<B>(storage_builder: B) -> anyhow::Result<()>
+where
+    B: StorageBuilder,
+{
+    let signer = InMemorySigner::new(None);
+    let mut builder = TestBuilder::new(storage_builder, 4, 0, signer).await?;
+    let sender = builder.add_root_chain(1, Amount::from_tokens(4)).await?;
+    let blob_bytes = b"streamed".to_vec();
+    let blob_id = Blob::new_data(blob_bytes.clone()).id();
+    let certificate = sender
+        .publish_data_blob(blob_bytes)
+        .await
+        .unwrap_ok_committed();
+    sender
+        .burn(AccountOwner::CHAIN, Amount::ONE)
+        .await
+        .unwrap_ok_committed();
+    let sender_info = sender.chain_info().await?;
+
+    // A validator sends the certificates in order, with the blobs they require.
+    let (_, node) = builder
+        .make_node_provider()
+        .make_nodes(&builder.initial_committee)?
+        .next()
+        .unwrap();
+    let streamed = node
+        .stream_certificates(sender.chain_id(), BlockHeight::ZERO, true)
+        .await?
+        .try_collect::<Vec<_>>()
+        .await?;
+    assert_eq!(streamed.len(), 2);
+    assert_eq!(streamed[0].certificate, certificate);
+    assert!(streamed[0].blobs.iter().any(|blob| blob.id() == blob_id));
+    assert_eq!(
+        streamed[1].certificate.block().header.height,
+        BlockHeight::from(1)
+    );
+    let streamed = node
+        .stream_certificates(sender.chain_id(), BlockHeight::from(1), false)
+        .await?
+        .try_collect::<Vec<_>>()
+        .await?;
+    assert_eq!(streamed.len(), 1);
+    assert!(streamed[0].blobs.is_empty());
+
+    // A new client catches up with the chain, including the published blob.
+    let client = builder
+        .make_client(
+            sender.chain_id(),
+            sender_info.block_hash,
+            BlockHeight::from(2),
+        )
+        .await?;
+    client.synchronize_from_validators().await?;
+    assert_eq!(client.local_balance().await?, Amount::from_tokens(3));
+    assert!(client.storage_client().read_blob(blob_id).await?.is_some());
+    Ok(())
+}
+
+#[test_case(MemoryStorageBuilder::default(); "memory")]
+#[test_log::test(tokio::test)]
+async fn test_download_from_fastest_validator<B>(storage_builder: B) -> anyhow::Result<()>
+where
+    B: StorageBuilder,
+{
+    let signer = InMemorySigner::new(None);
+    let mut builder = TestBuilder::new(storage_builder, 4, 0, signer).await?;
+    let sender = builder.add_root_chain(1, Amount::from_tokens(4)).await?;
+    for _ in 0..2 {
+        sender
+            .burn(AccountOwner::CHAIN, Amount::ONE)
+            .await
+            .unwrap_ok_committed();
+    }
+    let sender_info = sender.chain_info().await?;
+    let client = builder
+        .make_client(
+            sender.chain_id(),
+            sender_info.block_hash,
+            BlockHeight::from(2),
+        )
+        .await?;
+
+    // The validator expected to be the fastest is offline, so the next one is used instead.
+    builder.set_fault_type([3], FaultType::Offline).await;
+    let offline = builder.node(3).name();
+    let throughputs = &client.client.download_throughputs;
+    for (index, public_key) in builder.initial_committee.validators().keys().enumerate() {
+        let certificates = if *public_key == offline {
+            10
+        } else {
+            6 + index as u64
+        };
+        throughputs.record(*public_key, certificates, Duration::from_secs(1));
+    }
+    client.synchronize_from_validators().await?;
+    assert_eq!(client.local_balance().await?, Amount::from_tokens(2));
+
+    // Its expected throughput was halved, so it is now tried last.
+    assert_eq!(throughputs.expected(&offline), Some(5.0));
+    let mut validators = client.client.validator_nodes().await?;
+    throughputs.sort(&mut validators);
+    assert_eq!(validators.last().unwrap().public_key, offline);
+    Ok(())
+}
+
 #[test_case(MemoryStorageBuilder::default(); "memory")]
 #[cfg_attr(feature = "storage-service", test_case(ServiceStorageBuilder::new().await; "storage_service"))]
 #[cfg_attr(feature = "rocksdb", test_case(RocksDbStorageBuilder::new().await; "rocks_db"))]
@@ -258,6 +637,81 @@ where
     Ok(())
 }
 
+#[test_case(MemoryStorageBuilder::default(); "memory")]
+#[cfg_attr(feature = "storage-service", test_case(ServiceStorageBuilder::new().await; "storage_service"))]
+#[cfg_attr(feature = "rocksdb", test_case(RocksDbStorageBuilder::new().await; "rocks_db"))]
+#[cfg_attr(feature = "dynamodb", test_case(DynamoDbStorageBuilder::default(); "dynamo_db"))]
+#[cfg_attr(feature = "scylladb", test_case(ScyllaDbStorageBuilder::default(); "scylla_db"))]
+#[test_log::test(tokio::test)]
+async fn test_withdraw_from_chain_account<B>(storage_builder: B) -> anyhow::Result<()>
+where
+    B: StorageBuilder,
+{
+    let signer = InMemorySigner::new(None);
+    let mut builder = TestBuilder::new(storage_builder, 4, 1, signer)
+        .await?
+        .with_policy(ResourceControlPolicy::only_fuel());
+    let sender = builder.add_root_chain(1, Amount::from_tokens(4)).await?;
+    let sender_id = sender.chain_id();
+    let chain_account_owner = AccountOwner::Chain(sender_id);
+    let receiver = builder.add_root_chain(2, Amount::ZERO).await?;
+    let receiver_id = receiver.chain_id();
+
+    // The sender chain funds its own account on the receiver chain.
+    let cert = sender
+        .transfer_to_account(
+            AccountOwner::CHAIN,
+            Amount::from_tokens(3),
+            Account::new(receiver_id, chain_account_owner),
+        )
+        .await
+        .unwrap_ok_committed();
+    receiver
+        .receive_certificate_and_update_validators(cert)
+        .await?;
+    receiver.process_inbox().await?;
+    assert_eq!(
+        receiver.local_owner_balance(chain_account_owner).await?,
+        Amount::from_tokens(3)
+    );
+
+    // The owners of the receiver chain cannot claim the sender chain's account.
+    assert!(receiver
+        .claim(
+            chain_account_owner,
+            receiver_id,
+            Recipient::chain(receiver_id),
+            Amount::ONE,
+        )
+        .await
+        .is_err());
+
+    // The sender chain withdraws from its account.
+    let cert = sender
+        .withdraw_from_chain_account(
+            receiver_id,
+            Recipient::chain(sender_id),
+            Amount::from_tokens(2),
+        )
+        .await
+        .unwrap_ok_committed();
+    receiver
+        .receive_certificate_and_update_validators(cert)
+        .await?;
+    let cert = receiver.process_inbox().await?.0.pop().unwrap();
+    assert_eq!(
+        receiver.local_owner_balance(chain_account_owner).await?,
+        Amount::ONE
+    );
+    sender
+        .receive_certificate_and_update_validators(cert)
+        .await?;
+    sender.process_inbox().await?;
+    assert_eq!(sender.local_balance().await?, Amount::from_tokens(3));
+
+    Ok(())
+}
+
 #[test_case(MemoryStorageBuilder::default(); "memory")]
 #[cfg_attr(feature = "storage-service", test_case(ServiceStorageBuilder::new().await; "storage_service"))]
 #[cfg_attr(feature = "rocksdb", test_case(RocksDbStorageBuilder::new().await; "rocks_db"))]
@@ -844,16 +1298,212 @@ where
 #[cfg_attr(feature = "dynamodb", test_case(DynamoDbStorageBuilder::default(); "dynamo_db"))]
 #[cfg_attr(feature = "scylladb", test_case(ScyllaDbStorageBuilder::default(); "scylla_db"))]
 #[test_log::test(tokio::test)]
-async fn test_bidirectional_transfer<B>(storage_builder: B) -> anyhow::Result<()>
+async fn test_byzantine_validator_votes<B>(storage_builder: B) -> anyhow::Result<()>
 where
     B: StorageBuilder,
 {
     let signer = InMemorySigner::new(None);
-    let mut builder = TestBuilder::new(storage_builder, 4, 1, signer).await?;
-    let client1 = builder.add_root_chain(1, Amount::from_tokens(3)).await?;
-    let client2 = builder.add_root_chain(2, Amount::ZERO).await?;
-    assert_eq!(
-        client1.local_balance().await.unwrap(),
+    let mut builder = TestBuilder::new(storage_builder, 4, 0, signer).await?;
+    let chain_1 = builder.add_root_chain(1, Amount::from_tokens(4)).await?;
+    let chain_2 = builder.add_root_chain(2, Amount::ZERO).await?;
+    let fault_types = [
+        FaultType::WrongStateHash,
+        FaultType::InvalidVoteSignature,
+        FaultType::WithholdVote,
+    ];
+    for (height, fault_type) in (0..).map(BlockHeight).zip(fault_types) {
+        // A single Byzantine validator can't prevent the others from forming a quorum.
+        builder.set_fault_type([3], fault_type).await;
+        let certificate = chain_1
+            .transfer_to_account(
+                AccountOwner::CHAIN,
+                Amount::ONE,
+                Account::chain(chain_2.chain_id()),
+            )
+            .await
+            .unwrap_ok_committed();
+        assert_eq!(certificate.block().header.height, height);
+        assert_eq!(
+            builder
+                .check_that_validators_have_certificate(chain_1.chain_id(), height, 3)
+                .await
+                .unwrap(),
+            certificate
+        );
+    }
+    assert_eq!(chain_1.local_balance().await?, Amount::ONE);
+    Ok(())
+}
+
+#[test_case(MemoryStorageBuilder::default(); "memory")]
+#[cfg_attr(feature = "storage-service", test_case(ServiceStorageBuilder::new().await; "storage_service"))]
+#[cfg_attr(feature = "rocksdb", test_case(RocksDbStorageBuilder::new().await; "rocks_db"))]
+#[cfg_attr(feature = "dynamodb", test_case(DynamoDbStorageBuilder::default(); "dynamo_db"))]
+#[cfg_attr(feature = "scylladb", test_case(ScyllaDbStorageBuilder::default(); "scylla_db"))]
+#[test_log::test(tokio::test)]
+async fn test_byzantine_quorum_with_wrong_state_hash<B>(storage_builder: B) -> anyhow::Result<()>
+where
+    B: StorageBuilder,
+{
+    let signer = InMemorySigner::new(None);
+    let mut builder = TestBuilder::new(storage_builder, 4, 0, signer).await?;
+    let chain_1 = builder.add_root_chain(1, Amount::from_tokens(4)).await?;
+    let chain_2 = builder.add_root_chain(2, Amount::ZERO).await?;
+    builder
+        .set_fault_type([0, 1, 2], FaultType::WrongStateHash)
+        .await;
+    let result = chain_1
+        .transfer_to_account(
+            AccountOwner::CHAIN,
+            Amount::ONE,
+            Account::chain(chain_2.chain_id()),
+        )
+        .await;
+    // The client doesn't accept a quorum for a different block than the one it proposed.
+    assert_matches!(result, Err(ChainClientError::UnexpectedQuorum { .. }));
+    assert_eq!(
+        chain_1.chain_info().await?.next_block_height,
+        BlockHeight::ZERO
+    );
+
+    // A certificate assembled from the Byzantine votes is rejected by the honest validator.
+    let pending = chain_1
+        .pending_proposal()
+        .as_ref()
+        .cloned()
+        .expect("the proposal should still be pending");
+    let (block, _) = chain_1
+        .client
+        .stage_block_execution(pending.block, None, pending.blobs)
+        .await?;
+    let value = ConfirmedBlock::new(with_wrong_state_hash(block));
+    let round = chain_1
+        .chain_info_with_manager_values()
+        .await?
+        .manager
+        .current_round;
+    let mut votes = Vec::new();
+    for index in [0, 1, 2] {
+        votes.push(builder.node(index).make_vote(&value, round).await);
+    }
+    let certificate = LiteCertificate::try_from_votes(votes)
+        .unwrap()
+        .with_value(value)
+        .unwrap();
+    assert_matches!(
+        builder
+            .node(3)
+            .handle_certificate_in_worker(certificate)
+            .await,
+        Err(WorkerError::IncorrectOutcome { .. })
+    );
+
+    // Once the validators are honest again, the client commits its proposal.
+    builder.set_fault_type([0, 1, 2], FaultType::Honest).await;
+    let certificate = chain_1
+        .process_pending_block()
+        .await
+        .unwrap_ok_committed()
+        .expect("the pending proposal should be committed");
+    assert_eq!(certificate.block().header.height, BlockHeight::ZERO);
+    assert_eq!(chain_1.local_balance().await?, Amount::from_tokens(3));
+    Ok(())
+}
+
+#[test_case(MemoryStorageBuilder::default(); "memory")]
+#[cfg_attr(feature = "storage-service", test_case(ServiceStorageBuilder::new().await; "storage_service"))]
+#[cfg_attr(feature = "rocksdb", test_case(RocksDbStorageBuilder::new().await; "rocks_db"))]
+#[cfg_attr(feature = "dynamodb", test_case(DynamoDbStorageBuilder::default(); "dynamo_db"))]
+#[cfg_attr(feature = "scylladb", test_case(ScyllaDbStorageBuilder::default(); "scylla_db"))]
+#[test_log::test(tokio::test)]
+async fn test_byzantine_votes_with_invalid_signatures<B>(storage_builder: B) -> anyhow::Result<()>
+where
+    B: StorageBuilder,
+{
+    let signer = InMemorySigner::new(None);
+    let mut builder = TestBuilder::new(storage_builder, 4, 0, signer).await?;
+    let chain_1 = builder.add_root_chain(1, Amount::from_tokens(4)).await?;
+    let chain_2 = builder.add_root_chain(2, Amount::ZERO).await?;
+    builder
+        .set_fault_type([0, 1], FaultType::InvalidVoteSignature)
+        .await;
+    let result = chain_1
+        .transfer_to_account(
+            AccountOwner::CHAIN,
+            Amount::ONE,
+            Account::chain(chain_2.chain_id()),
+        )
+        .await;
+    // Only two of the four votes are valid, which is not enough for a quorum.
+    assert!(result.is_err());
+    assert_eq!(
+        chain_1.chain_info().await?.next_block_height,
+        BlockHeight::ZERO
+    );
+
+    // A certificate including the invalid signatures is rejected by the honest validator.
+    let pending = chain_1
+        .pending_proposal()
+        .as_ref()
+        .cloned()
+        .expect("the proposal should still be pending");
+    let (block, _) = chain_1
+        .client
+        .stage_block_execution(pending.block, None, pending.blobs)
+        .await?;
+    let value = ConfirmedBlock::new(block);
+    let round = chain_1
+        .chain_info_with_manager_values()
+        .await?
+        .manager
+        .current_round;
+    let mut votes = Vec::new();
+    for index in [0, 1, 2] {
+        votes.push(builder.node(index).make_vote(&value, round).await);
+    }
+    let certificate = LiteCertificate::try_from_votes(votes)
+        .unwrap()
+        .with_value(value)
+        .unwrap();
+    assert_matches!(
+        builder.node(3).handle_certificate_in_worker(certificate).await,
+        Err(WorkerError::ChainError(error))
+            if matches!(*error, ChainError::CryptoError(CryptoError::InvalidSignature { .. }))
+    );
+
+    // Once the validators are honest again, the client commits its proposal.
+    builder.set_fault_type([0, 1], FaultType::Honest).await;
+    let certificate = chain_1
+        .process_pending_block()
+        .await
+        .unwrap_ok_committed()
+        .expect("the pending proposal should be committed");
+    assert_eq!(
+        builder
+            .check_that_validators_have_certificate(chain_1.chain_id(), BlockHeight::ZERO, 3)
+            .await
+            .unwrap(),
+        certificate
+    );
+    Ok(())
+}
+
+#[test_case(MemoryStorageBuilder::default(); "memory")]
+#[cfg_attr(feature = "storage-service", test_case(ServiceStorageBuilder::new().await; "storage_service"))]
+#[cfg_attr(feature = "rocksdb", test_case(RocksDbStorageBuilder::new().await; "rocks_db"))]
+#[cfg_attr(feature = "dynamodb", test_case(DynamoDbStorageBuilder::default(); "dynamo_db"))]
+#[cfg_attr(feature = "scylladb", test_case(ScyllaDbStorageBuilder::default(); "scylla_db"))]
+#[test_log::test(tokio::test)]
+async fn test_bidirectional_transfer<B>(storage_builder: B) -> anyhow::Result<()>
+where
+    B: StorageBuilder,
+{
+    let signer = InMemorySigner::new(None);
+    let mut builder = TestBuilder::new(storage_builder, 4, 1, signer).await?;
+    let client1 = builder.add_root_chain(1, Amount::from_tokens(3)).await?;
+    let client2 = builder.add_root_chain(2, Amount::ZERO).await?;
+    assert_eq!(
+        client1.local_balance().await.unwrap(),
         Amount::from_tokens(3)
     );
     assert_eq!(
@@ -864,6 +1514,7 @@ where
                 balance: Amount::from_tokens(3),
             },
             operations: vec![],
+            operation_metadata: vec![],
         }
     );
     let certificate = client1
@@ -889,6 +1540,7 @@ where
                 balance: Amount::ZERO,
             },
             operations: vec![],
+            operation_metadata: vec![],
         },
     );
 
@@ -912,6 +1564,7 @@ where
                 balance: Amount::from_tokens(0),
             },
             operations: vec![],
+            operation_metadata: vec![],
         },
     );
 
@@ -949,6 +1602,7 @@ where
                 balance: Amount::from_tokens(2),
             },
             operations: vec![],
+            operation_metadata: vec![],
         },
     );
     Ok(())
@@ -1239,6 +1893,168 @@ where
     Ok(())
 }
 
+#[test_case(MemoryStorageBuilder::default(); "memory")]
+#[cfg_attr(feature = "storage-service", test_case(ServiceStorageBuilder::new().await; "storage_service"))]
+#[test_log::test(tokio::test)]
+async fn test_bootstrap_from_trusted_checkpoint<B>(storage_builder: B) -> anyhow::Result<()>
+where
+    B: StorageBuilder,
+{
+    let signer = InMemorySigner::new(None);
+    let mut builder = TestBuilder::new(storage_builder, 4, 1, signer).await?;
+    let admin = builder.add_root_chain(0, Amount::from_tokens(3)).await?;
+    let user = builder.add_root_chain(1, Amount::from_tokens(3)).await?;
+    let validators = builder.initial_committee.validators().clone();
+
+    // Take a checkpoint right after the first new epoch.
+    let committee = Committee::new(validators.clone(), ResourceControlPolicy::only_fuel());
+    admin.stage_new_committee(committee.clone()).await?;
+    let info = admin.chain_info().await?;
+    let checkpoint = TrustedCheckpoint {
+        epoch: Epoch::from(1),
+        committee,
+        admin_height: BlockHeight(info.next_block_height.0 - 1),
+        admin_block_hash: info.block_hash.unwrap(),
+    };
+
+    // The network moves on to another epoch, and the user chain follows.
+    let committee = Committee::new(validators, ResourceControlPolicy::default());
+    admin.stage_new_committee(committee.clone()).await?;
+    user.synchronize_from_validators().await?;
+    user.process_inbox().await?;
+    user.transfer_to_account(
+        AccountOwner::CHAIN,
+        Amount::ONE,
+        Account::chain(admin.chain_id()),
+    )
+    .await
+    .unwrap_ok_committed();
+    assert_eq!(user.chain_info().await?.epoch, Epoch::from(2));
+
+    // A new client starts from the checkpoint and only follows the admin chain from there.
+    let client = builder
+        .make_client(user.chain_id(), None, BlockHeight::ZERO)
+        .await?;
+    client
+        .client
+        .set_trusted_checkpoint(checkpoint.clone())
+        .await?;
+    client.client.audit_trusted_checkpoint().await?;
+    let updated = client.client.update_trusted_checkpoint().await?;
+    let admin_info = admin.chain_info().await?;
+    assert_eq!(updated.epoch, Epoch::from(2));
+    assert_eq!(updated.committee, committee);
+    assert_eq!(updated.admin_height.0 + 1, admin_info.next_block_height.0);
+    assert_eq!(Some(updated.admin_block_hash), admin_info.block_hash);
+    assert_eq!(
+        client.client.admin_committee().await?,
+        (Epoch::from(2), committee)
+    );
+
+    client.synchronize_from_validators().await?;
+    let info = client.chain_info().await?;
+    assert_eq!(info.epoch, Epoch::from(2));
+    assert_eq!(info.block_hash, user.chain_info().await?.block_hash);
+    assert!(client
+        .client
+        .local_node
+        .chain_info(admin.chain_id())
+        .await
+        .map_or(true, |info| info.next_block_height == BlockHeight::ZERO));
+
+    // A checkpoint that the validators don't know is rejected by the audit.
+    let client = builder
+        .make_client(user.chain_id(), None, BlockHeight::ZERO)
+        .await?;
+    let forged = TrustedCheckpoint {
+        admin_block_hash: CryptoHash::test_hash("forged"),
+        ..checkpoint
+    };
+    client.client.set_trusted_checkpoint(forged).await?;
+    assert_matches!(
+        client.client.audit_trusted_checkpoint().await,
+        Err(ChainClientError::TrustedCheckpointNotConfirmed(_))
+    );
+
+    Ok(())
+}
+
+#[test_case(MemoryStorageBuilder::default(); "memory")]
+#[cfg_attr(feature = "storage-service", test_case(ServiceStorageBuilder::new().await; "storage_service"))]
+#[test_log::test(tokio::test)]
+async fn test_event_streams<B>(storage_builder: B) -> anyhow::Result<()>
+where
+    B: StorageBuilder,
+{
+    let signer = InMemorySigner::new(None);
+    let mut builder = TestBuilder::new(storage_builder, 4, 1, signer).await?;
+    let admin = builder.add_root_chain(0, Amount::from_tokens(3)).await?;
+    assert!(admin.event_streams(None).await?.is_empty());
+
+    // Each new committee is announced as an event on the admin chain.
+    let validators = builder.initial_committee.validators().clone();
+    for _ in 0..2 {
+        let committee = Committee::new(validators.clone(), ResourceControlPolicy::default());
+        admin.stage_new_committee(committee).await?;
+    }
+    let epoch_stream = EventStreamInfo {
+        stream_id: StreamId::system(EPOCH_STREAM_NAME),
+        max_index: 2,
+    };
+    assert_eq!(admin.event_streams(None).await?, vec![epoch_stream.clone()]);
+    assert_eq!(
+        admin
+            .event_streams(Some(GenericApplicationId::System))
+            .await?,
+        vec![epoch_stream]
+    );
+    let application_id = ApplicationId::new(CryptoHash::test_hash("application"));
+    assert!(admin
+        .event_streams(Some(GenericApplicationId::User(application_id)))
+        .await?
+        .is_empty());
+
+    Ok(())
+}
+
+#[test_case(MemoryStorageBuilder::default(); "memory")]
+#[cfg_attr(feature = "storage-service", test_case(ServiceStorageBuilder::new().await; "storage_service"))]
+#[test_log::test(tokio::test)]
+async fn test_locate_blob<B>(storage_builder: B) -> anyhow::Result<()>
+where
+    B: StorageBuilder,
+{
+    let signer = InMemorySigner::new(None);
+    let mut builder = TestBuilder::new(storage_builder, 4, 1, signer).await?;
+    let client = builder.add_root_chain(1, Amount::from_tokens(3)).await?;
+
+    // Validator 3 is down while the blob is published, so it never sees it.
+    builder.set_fault_type([3], FaultType::Offline).await;
+    let blob_bytes = b"blob".to_vec();
+    let blob_id = Blob::new_data(blob_bytes.clone()).id();
+    let certificate = client
+        .publish_data_blob(blob_bytes)
+        .await
+        .unwrap_ok_committed();
+    builder.set_fault_type([3], FaultType::Honest).await;
+
+    let located = client.client.locate_blob(blob_id).await?;
+    assert_eq!(located.len(), 4);
+    // The validators that have the content come first.
+    for (_, provenance) in &located[..3] {
+        assert!(provenance.has_content);
+        let state = provenance.state.as_ref().unwrap();
+        assert_eq!(state.last_used_by, Some(certificate.hash()));
+        assert_eq!(state.chain_id, client.chain_id());
+        assert_eq!(state.epoch, Some(Epoch::ZERO));
+    }
+    let (_, provenance) = &located[3];
+    assert!(!provenance.has_content);
+    assert!(provenance.state.is_none());
+
+    Ok(())
+}
+
 #[test_case(MemoryStorageBuilder::default(); "memory")]
 #[cfg_attr(feature = "storage-service", test_case(ServiceStorageBuilder::new().await; "storage_service"))]
 #[test_log::test(tokio::test)]
@@ -1268,6 +2084,487 @@ where
     Ok(())
 }
 
+#[test_case(MemoryStorageBuilder::default(); "memory")]
+#[cfg_attr(feature = "storage-service", test_case(ServiceStorageBuilder::new().await; "storage_service"))]
+#[test_log::test(tokio::test)]
+async fn test_sponsored_block<B>(storage_builder: B) -> anyhow::Result<()>
+where
+    B: StorageBuilder,
+{
+    let signer = InMemorySigner::new(None);
+    let policy = ResourceControlPolicy {
+        operation: Amount::from_micros(1),
+        ..ResourceControlPolicy::default()
+    };
+    let mut builder = TestBuilder::new(storage_builder, 4, 1, signer)
+        .await?
+        .with_policy(policy);
+    let user = builder.add_root_chain(1, Amount::ZERO).await?;
+    let sponsor_client = builder.add_root_chain(2, Amount::from_tokens(1)).await?;
+    let sponsor = sponsor_client.identity().await?;
+
+    // The sponsor funds an account on the user's chain.
+    let sponsor_account = Account {
+        chain_id: user.chain_id(),
+        owner: sponsor,
+    };
+    sponsor_client
+        .transfer_to_account(AccountOwner::CHAIN, Amount::from_millis(1), sponsor_account)
+        .await
+        .unwrap_ok_committed();
+    user.synchronize_from_validators().await?;
+    user.process_inbox().await?;
+
+    let operation = Operation::system(SystemOperation::ChangeApplicationPermissions(
+        ApplicationPermissions::default(),
+    ));
+    let block = user
+        .prepare_sponsored_block(vec![operation], vec![], sponsor)
+        .await?;
+    assert_eq!(block.fee_sponsor(), Some(sponsor));
+
+    // Without the sponsor's approval, the block is rejected.
+    let result = user.process_pending_block().await;
+    assert_matches!(
+        result,
+        Err(ChainClientError::LocalNodeError(
+            LocalNodeError::WorkerError(WorkerError::ChainError(error))
+        )) if matches!(*error, ChainError::InvalidSponsorApproval)
+    );
+
+    // The two operations cost more than the sponsor agreed to pay.
+    let approval = sponsor_client
+        .approve_sponsored_block(&block, Amount::from_micros(1))
+        .await?;
+    user.add_sponsor_approval(approval)?;
+    let result = user.process_pending_block().await;
+    assert_matches!(
+        result,
+        Err(ChainClientError::LocalNodeError(
+            LocalNodeError::WorkerError(WorkerError::SponsorFeeLimitExceeded { .. })
+        ))
+    );
+
+    let approval = sponsor_client
+        .approve_sponsored_block(&block, Amount::from_micros(2))
+        .await?;
+    user.add_sponsor_approval(approval)?;
+    user.process_pending_block().await.unwrap_ok_committed();
+    assert_eq!(
+        user.local_owner_balance(sponsor).await?,
+        Amount::from_micros(998)
+    );
+    assert_eq!(user.local_balance().await?, Amount::ZERO);
+    Ok(())
+}
+
+#[test_case(MemoryStorageBuilder::default(); "memory")]
+#[cfg_attr(feature = "storage-service", test_case(ServiceStorageBuilder::new().await; "storage_service"))]
+#[test_log::test(tokio::test)]
+async fn test_offline_signing<B>(storage_builder: B) -> anyhow::Result<()>
+where
+    B: StorageBuilder,
+{
+    let signer = InMemorySigner::new(None);
+    let mut builder = TestBuilder::new(storage_builder, 4, 1, signer.clone()).await?;
+    let sender = builder.add_root_chain(1, Amount::from_tokens(3)).await?;
+    let receiver = builder.add_root_chain(2, Amount::ZERO).await?;
+
+    let operation = Operation::system(SystemOperation::Transfer {
+        owner: AccountOwner::CHAIN,
+        recipient: Recipient::chain(receiver.chain_id()),
+        amount: Amount::ONE,
+    });
+    let request = sender
+        .export_proposal(vec![operation], vec![])
+        .await?
+        .unwrap()
+        .expect("the new block should need to be signed");
+    assert_eq!(request.owner, sender.identity().await?);
+    assert_eq!(request.content.block.operations.len(), 1);
+
+    // A signature with a different key is rejected.
+    let mut other_signer = InMemorySigner::new(None);
+    let other_owner = AccountOwner::from(other_signer.generate_new());
+    let wrong_signature = other_signer
+        .sign(&other_owner, &request.signing_hash())
+        .await?;
+    let result = sender
+        .submit_signed_proposal(request.clone(), wrong_signature)
+        .await;
+    assert_matches!(
+        result,
+        Err(ChainClientError::ChainError(ChainError::InvalidSigner))
+    );
+
+    // So is a request of another version.
+    let mut future_request = request.clone();
+    future_request.version += 1;
+    let signature = future_request.sign(&signer).await?;
+    let result = sender
+        .submit_signed_proposal(future_request, signature)
+        .await;
+    assert_matches!(
+        result,
+        Err(ChainClientError::ChainError(
+            ChainError::UnsupportedSigningRequestVersion { .. }
+        ))
+    );
+
+    let signature = request.sign(&signer).await?;
+    let certificate = sender
+        .submit_signed_proposal(request, signature)
+        .await?
+        .unwrap();
+    assert_eq!(certificate.block().header.height, BlockHeight::ZERO);
+    assert_eq!(sender.local_balance().await?, Amount::from_tokens(2));
+
+    // Nothing is left to sign.
+    assert!(sender
+        .export_proposal(vec![], vec![])
+        .await?
+        .unwrap()
+        .is_none());
+    Ok(())
+}
+
+#[test_case(MemoryStorageBuilder::default(); "memory")]
+#[cfg_attr(feature = "storage-service", test_case(ServiceStorageBuilder::new().await; "storage_service"))]
+#[test_log::test(tokio::test)]
+async fn test_estimate_fees<B>(storage_builder: B) -> anyhow::Result<()>
+where
+    B: StorageBuilder,
+{
+    let signer = InMemorySigner::new(None);
+    let policy = ResourceControlPolicy {
+        operation: Amount::from_micros(1),
+        message: Amount::from_micros(2),
+        ..ResourceControlPolicy::default()
+    };
+    let mut builder = TestBuilder::new(storage_builder, 4, 1, signer)
+        .await?
+        .with_policy(policy);
+    let sender = builder.add_root_chain(1, Amount::from_tokens(1)).await?;
+    let receiver = builder.add_root_chain(2, Amount::ZERO).await?;
+
+    let operation = Operation::system(SystemOperation::Transfer {
+        owner: AccountOwner::CHAIN,
+        recipient: Recipient::chain(receiver.chain_id()),
+        amount: Amount::from_millis(1),
+    });
+    let fees = sender
+        .estimate_fees(vec![operation.clone()], vec![])
+        .await?;
+    assert_eq!(
+        fees,
+        FeeBreakdown {
+            operations: Amount::from_micros(1),
+            messages: Amount::from_micros(2),
+            ..FeeBreakdown::default()
+        }
+    );
+    assert_eq!(fees.total()?, Amount::from_micros(3));
+
+    // The estimate didn't change the chain.
+    assert_eq!(sender.local_balance().await?, Amount::from_tokens(1));
+    assert_eq!(
+        sender.chain_info().await?.next_block_height,
+        BlockHeight::ZERO
+    );
+
+    // The fees are stored in the executed block.
+    let certificate = sender.execute_operation(operation).await?.unwrap();
+    assert_eq!(certificate.block().body.fees, fees);
+    Ok(())
+}
+
+#[test_case(MemoryStorageBuilder::default(); "memory")]
+#[cfg_attr(feature = "storage-service", test_case(ServiceStorageBuilder::new().await; "storage_service"))]
+#[test_log::test(tokio::test)]
+async fn test_block_builder_reports_each_operation<B>(storage_builder: B) -> anyhow::Result<()>
+where
+    B: StorageBuilder,
+{
+    let signer = InMemorySigner::new(None);
+    let mut builder = TestBuilder::new(storage_builder, 4, 1, signer).await?;
+    let sender = builder.add_root_chain(1, Amount::from_tokens(4)).await?;
+    let receiver1 = builder.add_root_chain(2, Amount::ZERO).await?;
+    let receiver2 = builder.add_root_chain(3, Amount::ZERO).await?;
+    let transfer = |receiver: &ChainClient<_>, amount| {
+        Operation::system(SystemOperation::Transfer {
+            owner: AccountOwner::CHAIN,
+            recipient: Recipient::chain(receiver.chain_id()),
+            amount,
+        })
+    };
+
+    // Labels must be unique within a block.
+    let result = sender
+        .block_builder()
+        .with_operation("first", transfer(&receiver1, Amount::ONE))
+        .with_operation("first", transfer(&receiver2, Amount::ONE))
+        .execute()
+        .await;
+    assert_matches!(
+        result,
+        Err(ChainClientError::DuplicateOperationLabel(label)) if label == "first"
+    );
+
+    let report = sender
+        .block_builder()
+        .with_operation("to receiver 1", transfer(&receiver1, Amount::ONE))
+        .with_operation(
+            "to receiver 2",
+            transfer(&receiver2, Amount::from_tokens(2)),
+        )
+        .execute()
+        .await
+        .unwrap_ok_committed();
+    assert_eq!(report.certificate.block().body.operations.len(), 2);
+    let labels = report
+        .operations
+        .iter()
+        .map(|operation| operation.label.as_str())
+        .collect::<Vec<_>>();
+    assert_eq!(labels, ["to receiver 1", "to receiver 2"]);
+    for (label, receiver) in [("to receiver 1", &receiver1), ("to receiver 2", &receiver2)] {
+        let operation = report.operation(label).unwrap();
+        assert_eq!(operation.messages.len(), 1);
+        assert_eq!(operation.messages[0].destination, receiver.chain_id());
+    }
+    assert!(report.operation("unknown").is_none());
+    Ok(())
+}
+
+#[test_case(MemoryStorageBuilder::default(); "memory")]
+#[cfg_attr(feature = "storage-service", test_case(ServiceStorageBuilder::new().await; "storage_service"))]
+#[test_log::test(tokio::test)]
+async fn test_rebase_operations_onto_conflicting_block<B>(storage_builder: B) -> anyhow::Result<()>
+where
+    B: StorageBuilder,
+{
+    let signer = InMemorySigner::new(None);
+    let mut builder = TestBuilder::new(storage_builder, 4, 0, signer).await?;
+    let client0 = builder.add_root_chain(1, Amount::from_tokens(10)).await?;
+    let chain_id = client0.chain_id();
+    let owner0 = client0.identity().await?;
+    let owner1 = builder.signer.generate_new().into();
+    let ownership =
+        ChainOwnership::multiple([(owner0, 100), (owner1, 100)], 10, TimeoutConfig::default());
+    client0.change_ownership(ownership).await?;
+    let mut client1 = builder
+        .make_client(
+            chain_id,
+            client0.chain_info().await?.block_hash,
+            BlockHeight::from(1),
+        )
+        .await?;
+    client1.set_preferred_owner(owner1);
+
+    // Client 0's block burning 3 tokens gets validated, but not confirmed.
+    builder
+        .set_fault_type([1, 2], FaultType::DontProcessValidated)
+        .await;
+    builder.set_fault_type([3], FaultType::Offline).await;
+    assert!(client0
+        .burn(AccountOwner::CHAIN, Amount::from_tokens(3))
+        .await
+        .is_err());
+    let manager = client0.chain_info_with_manager_values().await?.manager;
+    let LockingBlock::Regular(certificate) = *manager.requested_locking.unwrap() else {
+        panic!("Unexpected locking fast block.");
+    };
+    builder
+        .node(0)
+        .handle_validated_certificate(certificate)
+        .await?;
+
+    // Client 1 wants to burn 2, 6 and 1 tokens. It has to commit client 0's block first, and
+    // then rebases its operations: the hook drops the last one, and the second one fails.
+    builder.set_fault_type([0, 1, 2], FaultType::Honest).await;
+    client1.synchronize_from_validators().await?;
+    let burn = |tokens| {
+        Operation::system(SystemOperation::Transfer {
+            owner: AccountOwner::CHAIN,
+            recipient: Recipient::Burn,
+            amount: Amount::from_tokens(tokens),
+        })
+    };
+    let mut rebased_onto = Vec::new();
+    let mut hook = |certificate: &ConfirmedBlockCertificate, operation: Operation| {
+        rebased_onto.push(certificate.block().header.height);
+        if operation == burn(1) {
+            RebaseDecision::Drop
+        } else {
+            RebaseDecision::Keep(operation)
+        }
+    };
+    let rebased = client1
+        .execute_operations_with_rebase(vec![burn(2), burn(6), burn(1)], vec![], &mut hook)
+        .await
+        .unwrap_ok_committed();
+    assert_eq!(rebased_onto, vec![BlockHeight::from(1); 3]);
+    assert_eq!(rebased.rebases, 1);
+    assert_eq!(rebased.indices, vec![0]);
+    let certificate = rebased.certificate.unwrap();
+    assert_eq!(certificate.block().header.height, BlockHeight::from(2));
+    assert_eq!(certificate.block().body.operations, vec![burn(2)]);
+    assert_matches!(
+        &rebased.dropped[..],
+        [
+            DroppedOperation {
+                index: 2,
+                reason: DropReason::Vetoed,
+                ..
+            },
+            DroppedOperation {
+                index: 1,
+                reason: DropReason::Failed(_),
+                ..
+            },
+        ]
+    );
+    assert_eq!(client1.local_balance().await?, Amount::from_tokens(5));
+    Ok(())
+}
+
+#[test_case(MemoryStorageBuilder::default(); "memory")]
+#[cfg_attr(feature = "storage-service", test_case(ServiceStorageBuilder::new().await; "storage_service"))]
+#[test_log::test(tokio::test)]
+async fn test_repropose_operations_after_preemption<B>(storage_builder: B) -> anyhow::Result<()>
+where
+    B: StorageBuilder,
+{
+    let signer = InMemorySigner::new(None);
+    let mut builder = TestBuilder::new(storage_builder, 4, 0, signer).await?;
+    let mut client0 = builder.add_root_chain(1, Amount::from_tokens(10)).await?;
+    let chain_id = client0.chain_id();
+    let owner0 = client0.identity().await?;
+    let owner1 = builder.signer.generate_new().into();
+    let ownership =
+        ChainOwnership::multiple([(owner0, 100), (owner1, 100)], 10, TimeoutConfig::default());
+    client0.change_ownership(ownership).await?;
+    client0.options_mut().reproposal = Some(ReproposalConfig {
+        max_attempts: 2,
+        initial_delay: Duration::ZERO,
+        max_delay: Duration::ZERO,
+    });
+    let mut client1 = builder
+        .make_client(
+            chain_id,
+            client0.chain_info().await?.block_hash,
+            BlockHeight::from(1),
+        )
+        .await?;
+    client1.set_preferred_owner(owner1);
+    let burn = |tokens| {
+        Operation::system(SystemOperation::Transfer {
+            owner: AccountOwner::CHAIN,
+            recipient: Recipient::Burn,
+            amount: Amount::from_tokens(tokens),
+        })
+    };
+
+    // Client 1 commits a block with the same operation at height 1 first. Client 0's proposal
+    // at that height is rejected, and it proposes its operation again at height 2.
+    client1
+        .execute_operations(vec![burn(3)], vec![])
+        .await
+        .unwrap_ok_committed();
+    let certificate = client0
+        .execute_operations(vec![burn(3)], vec![])
+        .await
+        .unwrap_ok_committed();
+    assert_eq!(certificate.block().header.height, BlockHeight::from(2));
+    assert_eq!(
+        certificate.block().header.authenticated_signer,
+        Some(owner0)
+    );
+    assert_eq!(client0.local_balance().await?, Amount::from_tokens(4));
+
+    // Client 0's next block gets validated, but not confirmed.
+    builder
+        .set_fault_type([1, 2], FaultType::DontProcessValidated)
+        .await;
+    builder.set_fault_type([3], FaultType::Offline).await;
+    let mut reproposals = Reproposals::new(&client0).await?;
+    assert!(client0
+        .execute_operations(vec![burn(1)], vec![])
+        .await
+        .is_err());
+    let manager = client0.chain_info_with_manager_values().await?.manager;
+    let LockingBlock::Regular(validated) = *manager.requested_locking.unwrap() else {
+        panic!("Unexpected locking fast block.");
+    };
+    builder
+        .node(0)
+        .handle_validated_certificate(validated)
+        .await?;
+
+    // Client 1 commits client 0's locked block. When client 0 then learns that its proposal
+    // was preempted, it finds its block committed and doesn't propose it again.
+    builder.set_fault_type([0, 1, 2], FaultType::Honest).await;
+    let committed = client1.process_pending_block().await.unwrap_ok_committed();
+    let committed = committed.expect("client 1 should commit the locked block");
+    assert_eq!(committed.block().header.height, BlockHeight::from(3));
+    assert_eq!(committed.block().body.operations, vec![burn(1)]);
+    let error = ChainClientError::CommunicationError(CommunicationError::Trusted(
+        NodeError::WrongRound(Round::MultiLeader(1)),
+    ));
+    let fulfilling = reproposals.handle_error(&client0, error).await?;
+    assert_eq!(
+        fulfilling.map(|certificate| certificate.hash()),
+        Some(committed.hash())
+    );
+    assert!(client0.pending_proposal().is_none());
+    assert_eq!(client0.local_balance().await?, Amount::from_tokens(3));
+    Ok(())
+}
+
+#[test_case(MemoryStorageBuilder::default(); "memory")]
+#[cfg_attr(feature = "storage-service", test_case(ServiceStorageBuilder::new().await; "storage_service"))]
+#[test_log::test(tokio::test)]
+async fn test_reject_too_old_incoming_bundles<B>(storage_builder: B) -> anyhow::Result<()>
+where
+    B: StorageBuilder,
+{
+    let clock = storage_builder.clock().clone();
+    let signer = InMemorySigner::new(None);
+    let mut builder = TestBuilder::new(storage_builder, 4, 1, signer)
+        .await?
+        .with_policy(ResourceControlPolicy {
+            maximum_incoming_bundle_age_ms: 1_000,
+            ..ResourceControlPolicy::default()
+        });
+    let sender = builder.add_root_chain(1, Amount::from_tokens(3)).await?;
+    let receiver = builder.add_root_chain(2, Amount::ZERO).await?;
+    let receiver_id = receiver.chain_id();
+
+    sender
+        .transfer_to_account(
+            AccountOwner::CHAIN,
+            Amount::ONE,
+            Account::chain(receiver_id),
+        )
+        .await
+        .unwrap_ok_committed();
+
+    // The transfer is now too old to be accepted, so the receiver's client rejects it.
+    clock.add(TimeDelta::from_secs(2));
+    receiver.synchronize_from_validators().await?;
+    let cert = receiver.process_inbox().await?.0.pop().unwrap();
+    let bundles = &cert.block().body.incoming_bundles;
+    assert_eq!(bundles.len(), 1);
+    assert_eq!(bundles[0].action, MessageAction::Reject);
+    assert_eq!(receiver.local_balance().await?, Amount::ZERO);
+
+    // The rejected transfer bounces back to the sender, whose bundle is recent enough.
+    sender.synchronize_from_validators().await?;
+    sender.process_inbox().await?;
+    assert_eq!(sender.local_balance().await?, Amount::from_tokens(3));
+    Ok(())
+}
+
 #[test_case(MemoryStorageBuilder::default(); "memory")]
 #[cfg_attr(feature = "storage-service", test_case(ServiceStorageBuilder::new().await; "storage_service"))]
 #[test_log::test(tokio::test)]
@@ -1481,8 +2778,9 @@ where
         let node3 = builder.node(3);
         let content1 = blob1.into_content();
         assert_matches!(node3.handle_pending_blob(chain_2, content1.clone()).await,
-            Err(NodeError::WorkerError { error })
+            Err(NodeError::WorkerErrorWithCode { error, code, retriable: false })
             if error.contains("Blob was not required by any pending block")
+                && code == "UnexpectedBlob"
         );
         let result = node3.handle_validated_certificate(validated.clone()).await;
         assert_matches!(result, Err(NodeError::BlobsNotFound(_)));
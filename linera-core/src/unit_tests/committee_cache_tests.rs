@@ -0,0 +1,145 @@
+// Copyright (c) Zefchain Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+use std::{
+    collections::BTreeMap,
+    ops::RangeInclusive,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+};
+
+use linera_base::data_types::Epoch;
+use linera_execution::committee::Committee;
+use linera_storage::EpochCommittee;
+use linera_views::ViewError;
+
+use super::{CommitteeCache, COMMITTEE_CACHE_SIZE};
+
+/// Tests that a committee is only read once, and then returned from the cache.
+#[tokio::test]
+async fn test_cache_hit() -> Result<(), ViewError> {
+    let cache = CommitteeCache::default();
+    let storage = FakeStorage::new(Epoch(3));
+
+    assert!(storage.get(&cache, Epoch(1)).await?.is_some());
+    assert_eq!(storage.loads(), 1);
+    assert!(storage.get(&cache, Epoch(1)).await?.is_some());
+    assert_eq!(storage.loads(), 1);
+
+    assert!(storage.get(&cache, Epoch(2)).await?.is_some());
+    assert_eq!(storage.loads(), 2);
+    Ok(())
+}
+
+/// Tests that epochs that don't exist yet are not cached, so that they are read again.
+#[tokio::test]
+async fn test_cache_miss_for_unknown_epoch() -> Result<(), ViewError> {
+    let cache = CommitteeCache::default();
+    let storage = FakeStorage::new(Epoch(1));
+
+    assert!(storage.get(&cache, Epoch(2)).await?.is_none());
+    assert!(storage.get(&cache, Epoch(2)).await?.is_none());
+    assert_eq!(storage.loads(), 2);
+    Ok(())
+}
+
+/// Tests that an invalidated committee is read from storage again.
+#[tokio::test]
+async fn test_invalidate() -> Result<(), ViewError> {
+    let cache = CommitteeCache::default();
+    let storage = FakeStorage::new(Epoch(1));
+
+    assert!(storage.get(&cache, Epoch(1)).await?.is_some());
+    cache.invalidate(Epoch(1));
+    assert!(storage.get(&cache, Epoch(1)).await?.is_some());
+    assert_eq!(storage.loads(), 2);
+    Ok(())
+}
+
+/// Tests that the least recently used committee is evicted once the cache is full.
+#[tokio::test]
+async fn test_eviction() -> Result<(), ViewError> {
+    let cache = CommitteeCache::default();
+    let last_epoch = Epoch(COMMITTEE_CACHE_SIZE as u32);
+    let storage = FakeStorage::new(last_epoch);
+
+    for epoch in 0..=last_epoch.0 {
+        assert!(storage.get(&cache, Epoch(epoch)).await?.is_some());
+    }
+    assert_eq!(storage.loads(), COMMITTEE_CACHE_SIZE + 1);
+
+    // The first epoch was evicted to make room for the last one.
+    assert!(storage.get(&cache, Epoch(0)).await?.is_some());
+    assert_eq!(storage.loads(), COMMITTEE_CACHE_SIZE + 2);
+    assert!(storage.get(&cache, last_epoch).await?.is_some());
+    assert_eq!(storage.loads(), COMMITTEE_CACHE_SIZE + 2);
+    Ok(())
+}
+
+/// Tests that prefetching reads all the committees with a single query.
+#[tokio::test]
+async fn test_prefetch() -> Result<(), ViewError> {
+    let cache = CommitteeCache::default();
+    let storage = FakeStorage::new(Epoch(4));
+
+    storage.prefetch(&cache, Epoch(0)..=Epoch(4)).await?;
+    assert_eq!(storage.loads(), 1);
+    for epoch in 0..=4 {
+        assert!(storage.get(&cache, Epoch(epoch)).await?.is_some());
+    }
+    storage.prefetch(&cache, Epoch(1)..=Epoch(3)).await?;
+    assert_eq!(storage.loads(), 1);
+    Ok(())
+}
+
+/// A stand-in for the storage, with a committee for every epoch up to `latest_epoch`, that
+/// counts how many times committees are read.
+struct FakeStorage {
+    latest_epoch: Epoch,
+    loads: AtomicUsize,
+}
+
+impl FakeStorage {
+    fn new(latest_epoch: Epoch) -> Self {
+        FakeStorage {
+            latest_epoch,
+            loads: AtomicUsize::new(0),
+        }
+    }
+
+    fn loads(&self) -> usize {
+        self.loads.load(Ordering::SeqCst)
+    }
+
+    async fn committees_for(
+        &self,
+        epochs: RangeInclusive<Epoch>,
+    ) -> Result<BTreeMap<Epoch, EpochCommittee>, ViewError> {
+        self.loads.fetch_add(1, Ordering::SeqCst);
+        Ok((epochs.start().0..=epochs.end().0.min(self.latest_epoch.0))
+            .map(|epoch| (Epoch(epoch), EpochCommittee::from(Committee::default())))
+            .collect())
+    }
+
+    async fn get(
+        &self,
+        cache: &CommitteeCache,
+        epoch: Epoch,
+    ) -> Result<Option<Arc<EpochCommittee>>, ViewError> {
+        cache
+            .get_or_load_with(epoch, |epochs| self.committees_for(epochs))
+            .await
+    }
+
+    async fn prefetch(
+        &self,
+        cache: &CommitteeCache,
+        epochs: RangeInclusive<Epoch>,
+    ) -> Result<(), ViewError> {
+        cache
+            .prefetch_with(epochs, |epochs| self.committees_for(epochs))
+            .await
+    }
+}
@@ -0,0 +1,107 @@
+// Copyright (c) Zefchain Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+use assert_matches::assert_matches;
+use linera_base::crypto::CryptoHash;
+
+use super::*;
+
+fn application_id(name: &str) -> ApplicationId {
+    ApplicationId::new(CryptoHash::test_hash(name))
+}
+
+fn pool(max_concurrent_queries_per_application: usize) -> Arc<ServiceRuntimePool> {
+    Arc::new(ServiceRuntimePool::new(ServiceRuntimePoolConfig {
+        max_concurrent_queries: 3,
+        max_concurrent_queries_per_application,
+        max_queue_delay: Duration::from_millis(50),
+        max_memory_mb: 100,
+        memory_per_runtime_mb: 40,
+    }))
+}
+
+/// Tests that an application can't run more queries than its limit, while other
+/// applications can.
+#[tokio::test]
+async fn test_queries_per_application_are_limited() {
+    let pool = pool(1);
+    let app_a = application_id("a");
+    let app_b = application_id("b");
+
+    let permit = pool.acquire_query(app_a, false).await.unwrap();
+    assert_matches!(
+        pool.acquire_query(app_a, false).await,
+        Err(WorkerError::ServiceRuntimesBusy(id)) if id == app_a
+    );
+    let other_permit = pool.acquire_query(app_b, false).await.unwrap();
+    assert_eq!(pool.available_queries(), 1);
+
+    drop(permit);
+    pool.acquire_query(app_a, false).await.unwrap();
+    drop(other_permit);
+    assert_eq!(pool.available_queries(), 3);
+    assert!(pool.applications.lock().unwrap().is_empty());
+}
+
+/// Tests that a waiting query starts as soon as a runtime is released.
+#[tokio::test]
+async fn test_queued_query_starts_when_a_runtime_is_released() {
+    let pool = pool(1);
+    let app = application_id("a");
+
+    let permit = pool.acquire_query(app, false).await.unwrap();
+    let release = async {
+        linera_base::time::timer::sleep(Duration::from_millis(10)).await;
+        drop(permit);
+    };
+    let (result, ()) = futures::join!(pool.acquire_query(app, false), release);
+    assert!(result.is_ok());
+}
+
+/// Tests that long-lived runtimes and queries share the memory budget.
+#[tokio::test]
+async fn test_memory_is_reserved_by_runtimes() {
+    let pool = pool(3);
+    let app = application_id("a");
+
+    let first = pool.try_reserve_long_lived_runtime().unwrap();
+    let _second = pool.try_reserve_long_lived_runtime().unwrap();
+    assert_eq!(pool.available_memory_mb(), 20);
+    assert!(pool.try_reserve_long_lived_runtime().is_none());
+
+    // Queries to long-lived runtimes don't need more memory, but the others do.
+    let permit = pool.acquire_query(app, false).await.unwrap();
+    assert_matches!(
+        pool.acquire_query(app, true).await,
+        Err(WorkerError::ServiceRuntimesBusy(_))
+    );
+
+    drop(first);
+    let other_permit = pool.acquire_query(app, true).await.unwrap();
+    assert_eq!(pool.available_memory_mb(), 20);
+    drop((permit, other_permit));
+    assert_eq!(pool.available_memory_mb(), 60);
+}
+
+/// Tests that a query admitted for a long-lived runtime can reserve memory afterwards, if the
+/// chain worker has no such runtime, but without waiting for it.
+#[tokio::test]
+async fn test_memory_is_reserved_for_queries_without_long_lived_runtimes() {
+    let pool = pool(3);
+    let app = application_id("a");
+
+    let mut permit = pool.acquire_query(app, false).await.unwrap();
+    permit.ensure_memory_is_reserved().unwrap();
+    assert_eq!(pool.available_memory_mb(), 60);
+    permit.ensure_memory_is_reserved().unwrap();
+    assert_eq!(pool.available_memory_mb(), 60);
+
+    let _runtime = pool.try_reserve_long_lived_runtime().unwrap();
+    let mut other_permit = pool.acquire_query(app, false).await.unwrap();
+    assert_matches!(
+        other_permit.ensure_memory_is_reserved(),
+        Err(WorkerError::ServiceRuntimesBusy(_))
+    );
+    drop(permit);
+    assert_eq!(pool.available_memory_mb(), 60);
+}
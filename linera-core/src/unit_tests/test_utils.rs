@@ -12,22 +12,25 @@ use async_trait::async_trait;
 use futures::{
     future::Either,
     lock::{Mutex, MutexGuard},
-    Future,
+    stream, Future,
 };
 use linera_base::{
-    crypto::{AccountPublicKey, CryptoHash, InMemorySigner, ValidatorKeypair, ValidatorPublicKey},
+    crypto::{
+        AccountPublicKey, CryptoHash, InMemorySigner, ValidatorKeypair, ValidatorPublicKey,
+        ValidatorSecretKey,
+    },
     data_types::*,
     identifiers::{AccountOwner, BlobId, ChainId},
     ownership::ChainOwnership,
 };
 use linera_chain::{
-    data_types::BlockProposal,
+    data_types::{BlockProposal, LiteValue, LiteVote, ProposedBlock, SignatureAggregator},
     types::{
-        CertificateKind, ConfirmedBlock, ConfirmedBlockCertificate, GenericCertificate,
-        LiteCertificate, Timeout, ValidatedBlock,
+        Block, CertificateKind, CertificateValue, ConfirmedBlock, ConfirmedBlockCertificate,
+        GenericCertificate, LiteCertificate, Timeout, ValidatedBlock,
     },
 };
-use linera_execution::{committee::Committee, ResourceControlPolicy, WasmRuntime};
+use linera_execution::{committee::Committee, BlobProvenance, ResourceControlPolicy, WasmRuntime};
 use linera_storage::{DbStorage, ResultReadCertificates, Storage, TestClock};
 #[cfg(all(not(target_arch = "wasm32"), feature = "storage-service"))]
 use linera_storage_service::client::StorageServiceDatabase;
@@ -37,7 +40,10 @@ use linera_views::dynamo_db::DynamoDbDatabase;
 #[cfg(feature = "scylladb")]
 use linera_views::scylla_db::ScyllaDbDatabase;
 use linera_views::{
-    memory::MemoryDatabase, random::generate_test_namespace, store::TestKeyValueDatabase as _,
+    memory::MemoryDatabase,
+    random::generate_test_namespace,
+    store::TestKeyValueDatabase as _,
+    views::{CryptoHashView as _, RootView as _},
 };
 use tokio::sync::oneshot;
 use tokio_stream::wrappers::UnboundedReceiverStream;
@@ -51,11 +57,11 @@ use crate::{
     client::{ChainClientOptions, Client},
     data_types::*,
     node::{
-        CrossChainMessageDelivery, NodeError, NotificationStream, ValidatorNode,
-        ValidatorNodeProvider,
+        CertificateStream, CrossChainMessageDelivery, NodeError, NotificationStream,
+        StreamedCertificate, ValidatorNode, ValidatorNodeProvider,
     },
     notifier::ChannelNotifier,
-    worker::{NetworkActions, Notification, ProcessableCertificate, WorkerState},
+    worker::{NetworkActions, Notification, ProcessableCertificate, WorkerError, WorkerState},
 };
 
 #[derive(Debug, PartialEq, Clone, Copy)]
@@ -67,10 +73,36 @@ pub enum FaultType {
     DontSendConfirmVote,
     DontProcessValidated,
     DontSendValidateVote,
+    /// Votes for the block with a wrong state hash, conflicting with the honest votes.
+    WrongStateHash,
+    /// Returns votes whose signatures don't match their values.
+    InvalidVoteSignature,
+    /// Processes proposals and validated blocks, but doesn't return its votes.
+    WithholdVote,
+}
+
+/// Returns the block with a state hash that doesn't match its execution, as voted for by
+/// validators with [`FaultType::WrongStateHash`].
+pub fn with_wrong_state_hash(mut block: Block) -> Block {
+    block.header.state_hash = CryptoHash::test_hash("wrong state hash");
+    block
+}
+
+/// Returns the vote with a signature that was made for a different value.
+fn with_invalid_signature(vote: LiteVote, secret_key: &ValidatorSecretKey) -> LiteVote {
+    let other_value = LiteValue {
+        value_hash: CryptoHash::test_hash("other value"),
+        ..vote.value
+    };
+    LiteVote {
+        signature: LiteVote::new(other_value, vote.round, secret_key).signature,
+        ..vote
+    }
 }
 
 /// A validator used for testing. "Faulty" validators ignore block proposals (but not
 /// certificates or info queries) and have the wrong initial balance for all chains.
+/// Byzantine validators process everything honestly, but tamper with the votes they send.
 ///
 /// All methods are executed in spawned Tokio tasks, so that canceling a client task doesn't cause
 /// the validator's tasks to be canceled: In a real network, a validator also wouldn't cancel
@@ -80,10 +112,75 @@ where
     S: Storage,
 {
     state: WorkerState<S>,
+    secret_key: ValidatorSecretKey,
     fault_type: FaultType,
     notifier: Arc<ChannelNotifier<Notification>>,
 }
 
+impl<S> LocalValidator<S>
+where
+    S: Storage + Clone + Send + Sync + 'static,
+{
+    /// Replaces the vote in the response as a validator with our fault type would, and
+    /// re-signs the response.
+    async fn tamper_with_vote(
+        &self,
+        mut response: ChainInfoResponse,
+    ) -> Result<ChainInfoResponse, NodeError> {
+        let Some(vote) = response.info.manager.pending.clone() else {
+            return Ok(response);
+        };
+        let tampered_vote = match self.fault_type {
+            FaultType::WrongStateHash => Some(self.vote_with_wrong_state_hash(vote).await?),
+            FaultType::InvalidVoteSignature => Some(with_invalid_signature(vote, &self.secret_key)),
+            FaultType::WithholdVote => None,
+            FaultType::Honest
+            | FaultType::Offline
+            | FaultType::OfflineWithInfo
+            | FaultType::Malicious
+            | FaultType::DontSendConfirmVote
+            | FaultType::DontProcessValidated
+            | FaultType::DontSendValidateVote => return Ok(response),
+        };
+        response.info.manager.pending = tampered_vote;
+        response.sign(&self.secret_key);
+        Ok(response)
+    }
+
+    /// Returns a vote for the same block as the given vote, but with a wrong state hash.
+    async fn vote_with_wrong_state_hash(&self, vote: LiteVote) -> Result<LiteVote, NodeError> {
+        let chain = self.state.chain_state_view(vote.value.chain_id).await?;
+        let value_hash = match vote.kind() {
+            CertificateKind::Validated => {
+                let block = chain
+                    .manager
+                    .validated_vote()
+                    .expect("the vote should be in the chain manager")
+                    .value()
+                    .block()
+                    .clone();
+                ValidatedBlock::new(with_wrong_state_hash(block)).hash()
+            }
+            CertificateKind::Confirmed => {
+                let block = chain
+                    .manager
+                    .confirmed_vote()
+                    .expect("the vote should be in the chain manager")
+                    .value()
+                    .block()
+                    .clone();
+                ConfirmedBlock::new(with_wrong_state_hash(block)).hash()
+            }
+            CertificateKind::Timeout => return Ok(vote),
+        };
+        let value = LiteValue {
+            value_hash,
+            ..vote.value
+        };
+        Ok(LiteVote::new(value, vote.round, &self.secret_key))
+    }
+}
+
 #[derive(Clone)]
 pub struct LocalValidatorClient<S>
 where
@@ -245,22 +342,43 @@ where
         .await
     }
 
+    async fn blob_provenance(&self, blob_id: BlobId) -> Result<BlobProvenance, NodeError> {
+        self.spawn_and_receive(move |validator, sender| {
+            validator.do_blob_provenance(blob_id, sender)
+        })
+        .await
+    }
+
     async fn missing_blob_ids(&self, blob_ids: Vec<BlobId>) -> Result<Vec<BlobId>, NodeError> {
         self.spawn_and_receive(move |validator, sender| {
             validator.do_missing_blob_ids(blob_ids, sender)
         })
         .await
     }
+
+    async fn stream_certificates(
+        &self,
+        chain_id: ChainId,
+        start: BlockHeight,
+        include_blobs: bool,
+    ) -> Result<CertificateStream, NodeError> {
+        self.spawn_and_receive(move |validator, sender| {
+            validator.do_stream_certificates(chain_id, start, include_blobs, sender)
+        })
+        .await
+    }
 }
 
 impl<S> LocalValidatorClient<S>
 where
     S: Storage + Clone + Send + Sync + 'static,
 {
-    fn new(public_key: ValidatorPublicKey, state: WorkerState<S>) -> Self {
+    fn new(secret_key: ValidatorSecretKey, state: WorkerState<S>) -> Self {
+        let public_key = secret_key.public();
         let client = LocalValidator {
             fault_type: FaultType::Honest,
             state,
+            secret_key,
             notifier: Arc::new(ChannelNotifier::default()),
         };
         Self {
@@ -291,6 +409,31 @@ where
         Ok(response.info)
     }
 
+    /// Signs a vote for the given value, with an invalid signature if this validator has
+    /// [`FaultType::InvalidVoteSignature`]. This allows assembling certificates the way
+    /// a Byzantine quorum could.
+    pub async fn make_vote<T: CertificateValue>(&self, value: &T, round: Round) -> LiteVote {
+        let validator = self.client.lock().await;
+        let vote = LiteVote::new(LiteValue::new(value), round, &validator.secret_key);
+        match validator.fault_type {
+            FaultType::InvalidVoteSignature => with_invalid_signature(vote, &validator.secret_key),
+            _ => vote,
+        }
+    }
+
+    /// Processes a certificate regardless of the fault type, and returns the worker's error
+    /// as is instead of converting it into a [`NodeError`].
+    pub async fn handle_certificate_in_worker<T: ProcessableCertificate>(
+        &self,
+        certificate: GenericCertificate<T>,
+    ) -> Result<ChainInfoResponse, WorkerError> {
+        let validator = self.client.lock().await;
+        validator
+            .state
+            .fully_handle_certificate_with_notifications(certificate, &validator.notifier)
+            .await
+    }
+
     /// Executes the future produced by `f` in a new thread in a new Tokio runtime.
     /// Returns the value that the future puts into the sender.
     async fn spawn_and_receive<F, R, T>(&self, f: F) -> T
@@ -331,12 +474,19 @@ where
                 }),
                 FaultType::Honest
                 | FaultType::DontSendConfirmVote
-                | FaultType::DontProcessValidated => handle_block_proposal_result
+                | FaultType::DontProcessValidated
+                | FaultType::WrongStateHash
+                | FaultType::InvalidVoteSignature
+                | FaultType::WithholdVote => handle_block_proposal_result
                     .expect("handle_block_proposal_result should be Some"),
             },
         };
         // In a local node cross-chain messages can't get lost, so we can ignore the actions here.
-        sender.send(result.map(|(info, _actions)| info))
+        let result = match result {
+            Ok((response, _actions)) => validator.tamper_with_vote(response).await,
+            Err(error) => Err(error),
+        };
+        sender.send(result)
     }
 
     async fn handle_block_proposal(
@@ -348,7 +498,10 @@ where
             FaultType::Honest
             | FaultType::DontSendConfirmVote
             | FaultType::DontProcessValidated
-            | FaultType::DontSendValidateVote => Some(
+            | FaultType::DontSendValidateVote
+            | FaultType::WrongStateHash
+            | FaultType::InvalidVoteSignature
+            | FaultType::WithholdVote => Some(
                 validator
                     .state
                     .handle_block_proposal(proposal)
@@ -368,7 +521,10 @@ where
             | FaultType::DontSendConfirmVote
             | FaultType::Malicious
             | FaultType::DontProcessValidated
-            | FaultType::DontSendValidateVote => Some(
+            | FaultType::DontSendValidateVote
+            | FaultType::WrongStateHash
+            | FaultType::InvalidVoteSignature
+            | FaultType::WithholdVote => Some(
                 validator
                     .state
                     .fully_handle_certificate_with_notifications(certificate, &validator.notifier)
@@ -420,11 +576,23 @@ where
                         error: "refusing to confirm".to_string(),
                     })
                 }
+                FaultType::WrongStateHash
+                | FaultType::InvalidVoteSignature
+                | FaultType::WithholdVote
+                    if T::KIND == CertificateKind::Validated =>
+                {
+                    let response = handle_certificate_result
+                        .expect("handle_certificate_result should be Some")?;
+                    validator.tamper_with_vote(response).await
+                }
                 FaultType::Honest
                 | FaultType::DontSendConfirmVote
                 | FaultType::DontProcessValidated
                 | FaultType::Malicious
-                | FaultType::DontSendValidateVote => {
+                | FaultType::DontSendValidateVote
+                | FaultType::WrongStateHash
+                | FaultType::InvalidVoteSignature
+                | FaultType::WithholdVote => {
                     handle_certificate_result.expect("handle_certificate_result should be Some")
                 }
                 FaultType::Offline | FaultType::OfflineWithInfo => Err(NodeError::ClientIoError {
@@ -621,6 +789,21 @@ where
         sender.send(certificate_hash)
     }
 
+    async fn do_blob_provenance(
+        self,
+        blob_id: BlobId,
+        sender: oneshot::Sender<Result<BlobProvenance, NodeError>>,
+    ) -> Result<(), Result<BlobProvenance, NodeError>> {
+        let validator = self.client.lock().await;
+        let provenance = validator
+            .state
+            .storage_client()
+            .blob_provenance(blob_id)
+            .await
+            .map_err(Into::into);
+        sender.send(provenance)
+    }
+
     async fn do_missing_blob_ids(
         self,
         blob_ids: Vec<BlobId>,
@@ -635,6 +818,68 @@ where
             .map_err(Into::into);
         sender.send(missing_blob_ids)
     }
+
+    async fn do_stream_certificates(
+        self,
+        chain_id: ChainId,
+        start: BlockHeight,
+        include_blobs: bool,
+        sender: oneshot::Sender<Result<CertificateStream, NodeError>>,
+    ) -> Result<(), Result<CertificateStream, NodeError>> {
+        let validator = self.client.lock().await;
+        let result = if validator.fault_type == FaultType::Offline {
+            Err(NodeError::ClientIoError {
+                error: "offline".to_string(),
+            })
+        } else {
+            Self::read_streamed_certificates(&validator.state, chain_id, start, include_blobs).await
+        };
+        sender.send(result.map(|certificates| -> CertificateStream {
+            Box::pin(stream::iter(certificates.into_iter().map(Ok)))
+        }))
+    }
+
+    /// Reads all certificates of the chain from height `start` on, with their blobs if
+    /// `include_blobs` is set.
+    async fn read_streamed_certificates(
+        state: &WorkerState<S>,
+        chain_id: ChainId,
+        start: BlockHeight,
+        include_blobs: bool,
+    ) -> Result<Vec<StreamedCertificate>, NodeError> {
+        let range = BlockHeightRange { start, limit: None };
+        let query = ChainInfoQuery::new(chain_id).with_sent_certificate_hashes_in_range(range);
+        let (response, _actions) = state.handle_chain_info_query(query).await?;
+        let hashes = response.info.requested_sent_certificate_hashes;
+        let storage = state.storage_client();
+        let certificates = storage.read_certificates(hashes.clone()).await?;
+        let certificates = match ResultReadCertificates::new(certificates, hashes) {
+            ResultReadCertificates::Certificates(certificates) => certificates,
+            ResultReadCertificates::InvalidHashes(hashes) => {
+                return Err(NodeError::MissingCertificates(hashes))
+            }
+        };
+        let mut streamed = Vec::with_capacity(certificates.len());
+        for certificate in certificates {
+            let blobs = if include_blobs {
+                let blob_ids = certificate
+                    .block()
+                    .required_blob_ids()
+                    .into_iter()
+                    .collect::<Vec<_>>();
+                storage
+                    .read_blobs(&blob_ids)
+                    .await?
+                    .into_iter()
+                    .flatten()
+                    .collect()
+            } else {
+                Vec::new()
+            };
+            streamed.push(StreamedCertificate { certificate, blobs });
+        }
+        Ok(streamed)
+    }
 }
 
 #[derive(Clone)]
@@ -707,6 +952,7 @@ pub struct TestBuilder<B: StorageBuilder> {
     chain_client_storages: Vec<B::Storage>,
     pub chain_owners: BTreeMap<ChainId, AccountOwner>,
     pub signer: InMemorySigner,
+    chain_client_options: ChainClientOptions,
 }
 
 #[async_trait]
@@ -801,6 +1047,7 @@ where
         for (i, (validator_keypair, _account_public_key)) in validators.into_iter().enumerate() {
             let validator_public_key = validator_keypair.public_key;
             let storage = storage_builder.build().await?;
+            let secret_key = validator_keypair.secret_key.copy();
             let state = WorkerState::new(
                 format!("Node {}", i),
                 Some(validator_keypair.secret_key),
@@ -808,7 +1055,7 @@ where
             )
             .with_allow_inactive_chains(false)
             .with_allow_messages_from_deprecated_epochs(false);
-            let validator = LocalValidatorClient::new(validator_public_key, state);
+            let validator = LocalValidatorClient::new(secret_key, state);
             if i < with_faulty_validators {
                 faulty_validators.insert(validator_public_key);
                 validator.set_fault_type(FaultType::Malicious).await;
@@ -831,6 +1078,7 @@ where
             chain_client_storages: Vec::new(),
             chain_owners: BTreeMap::new(),
             signer,
+            chain_client_options: ChainClientOptions::test_default(),
         })
     }
 
@@ -840,6 +1088,11 @@ where
         self
     }
 
+    pub fn with_chain_client_options(mut self, options: ChainClientOptions) -> Self {
+        self.chain_client_options = options;
+        self
+    }
+
     pub async fn set_fault_type(&mut self, indexes: impl AsRef<[usize]>, fault_type: FaultType) {
         let mut faulty_validators = vec![];
         for index in indexes.as_ref() {
@@ -1001,7 +1254,7 @@ where
             [chain_id],
             format!("Client node for {:.8}", chain_id),
             Duration::from_secs(30),
-            ChainClientOptions::test_default(),
+            self.chain_client_options.clone(),
         ));
         Ok(client.create_chain_client(
             chain_id,
@@ -1133,6 +1386,229 @@ impl MemoryStorageBuilder {
     }
 }
 
+/// A single [`WorkerState`] over a fresh in-memory storage, for testing the worker's logic
+/// narrowly: there is no network, and the fixture holds the keys of all validators, so tests
+/// can certify blocks themselves.
+pub struct DryRunWorker {
+    pub worker: WorkerState<DbStorage<MemoryDatabase, TestClock>>,
+    pub clock: TestClock,
+    pub committee: Committee,
+    pub admin_description: ChainDescription,
+    /// The chains added with [`DryRunWorkerBuilder::with_chain`], in order.
+    pub chains: Vec<ChainDescription>,
+    validator_secrets: Vec<ValidatorSecretKey>,
+}
+
+/// Builds a [`DryRunWorker`] with a committee, chains and funded accounts.
+pub struct DryRunWorkerBuilder {
+    validator_count: usize,
+    policy: ResourceControlPolicy,
+    admin_ownership: ChainOwnership,
+    chains: Vec<DryRunChain>,
+}
+
+struct DryRunChain {
+    ownership: ChainOwnership,
+    balance: Amount,
+    accounts: BTreeMap<AccountOwner, Amount>,
+}
+
+impl Default for DryRunWorkerBuilder {
+    fn default() -> Self {
+        DryRunWorkerBuilder {
+            validator_count: 1,
+            policy: ResourceControlPolicy::default(),
+            admin_ownership: ChainOwnership::single(AccountPublicKey::test_key(0).into()),
+            chains: Vec::new(),
+        }
+    }
+}
+
+impl DryRunWorkerBuilder {
+    /// Sets the number of validators in the committee. The worker is run by the first one.
+    pub fn with_validators(mut self, count: usize) -> Self {
+        assert!(count > 0, "The committee needs at least one validator");
+        self.validator_count = count;
+        self
+    }
+
+    pub fn with_policy(mut self, policy: ResourceControlPolicy) -> Self {
+        self.policy = policy;
+        self
+    }
+
+    pub fn with_admin_ownership(mut self, ownership: ChainOwnership) -> Self {
+        self.admin_ownership = ownership;
+        self
+    }
+
+    /// Adds a root chain with the given ownership and chain balance.
+    pub fn with_chain(mut self, ownership: ChainOwnership, balance: Amount) -> Self {
+        self.chains.push(DryRunChain {
+            ownership,
+            balance,
+            accounts: BTreeMap::new(),
+        });
+        self
+    }
+
+    /// Funds an account on the chain that was added last.
+    pub fn with_account(mut self, owner: AccountOwner, amount: Amount) -> Self {
+        let chain = self
+            .chains
+            .last_mut()
+            .expect("`with_chain` must be called before `with_account`");
+        chain.accounts.insert(owner, amount);
+        self
+    }
+
+    pub async fn build(self) -> anyhow::Result<DryRunWorker> {
+        let mut storage_builder = MemoryStorageBuilder::default();
+        let storage = storage_builder.build().await?;
+        let keypairs = (0..self.validator_count)
+            .map(|_| ValidatorKeypair::generate())
+            .collect::<Vec<_>>();
+        let validators = keypairs
+            .iter()
+            .enumerate()
+            .map(|(index, keypair)| (keypair.public_key, AccountPublicKey::test_key(index as u8)))
+            .collect();
+        let validators = Committee::make_simple(validators).validators().clone();
+        let committee = Committee::new(validators, self.policy);
+        let committee_blob = Blob::new_committee(bcs::to_bytes(&committee)?);
+        storage.write_blob(&committee_blob).await?;
+
+        let admin_description = root_chain_description(0, self.admin_ownership, Amount::ZERO);
+        storage
+            .write_network_description(&NetworkDescription {
+                admin_chain_id: admin_description.id(),
+                genesis_config_hash: CryptoHash::test_hash("genesis config"),
+                genesis_timestamp: Timestamp::from(0),
+                genesis_committee_blob_hash: committee_blob.id().hash,
+                name: "dry run".to_string(),
+            })
+            .await?;
+        storage.create_chain(admin_description.clone()).await?;
+
+        let mut chains = Vec::new();
+        for (index, chain) in (1..).zip(self.chains) {
+            let description = root_chain_description(index, chain.ownership, chain.balance);
+            storage.create_chain(description.clone()).await?;
+            if !chain.accounts.is_empty() {
+                let mut view = storage.load_chain(description.id()).await?;
+                for (owner, amount) in chain.accounts {
+                    view.execution_state
+                        .system
+                        .balances
+                        .insert(&owner, amount)?;
+                }
+                let hash = view.execution_state.crypto_hash().await?;
+                view.execution_state_hash.set(Some(hash));
+                view.save().await?;
+            }
+            chains.push(description);
+        }
+
+        let validator_secrets = keypairs
+            .into_iter()
+            .map(|keypair| keypair.secret_key)
+            .collect::<Vec<_>>();
+        let worker = WorkerState::new(
+            "Dry-run worker".to_string(),
+            Some(validator_secrets[0].copy()),
+            storage,
+        );
+        Ok(DryRunWorker {
+            worker,
+            clock: storage_builder.clock().clone(),
+            committee,
+            admin_description,
+            chains,
+            validator_secrets,
+        })
+    }
+}
+
+fn root_chain_description(
+    index: u32,
+    ownership: ChainOwnership,
+    balance: Amount,
+) -> ChainDescription {
+    let config = InitialChainConfig {
+        ownership,
+        epoch: Epoch::ZERO,
+        min_active_epoch: Epoch::ZERO,
+        max_active_epoch: Epoch::ZERO,
+        balance,
+        application_permissions: ApplicationPermissions::default(),
+    };
+    ChainDescription::new(ChainOrigin::Root(index), config, Timestamp::from(0))
+}
+
+impl DryRunWorker {
+    pub fn builder() -> DryRunWorkerBuilder {
+        DryRunWorkerBuilder::default()
+    }
+
+    pub fn admin_id(&self) -> ChainId {
+        self.admin_description.id()
+    }
+
+    /// Returns a certificate for `value`, signed by all validators.
+    pub fn make_certificate<T: CertificateValue>(
+        &self,
+        value: T,
+        round: Round,
+    ) -> GenericCertificate<T> {
+        let lite_value = LiteValue::new(&value);
+        let mut aggregator = SignatureAggregator::new(value, round, &self.committee);
+        for secret_key in &self.validator_secrets {
+            let vote = LiteVote::new(lite_value.clone(), round, secret_key);
+            let certificate = aggregator
+                .append(vote.public_key, vote.signature)
+                .expect("validators of the committee sign valid votes");
+            if let Some(certificate) = certificate {
+                return certificate;
+            }
+        }
+        unreachable!("all validators together always reach a quorum")
+    }
+
+    /// Returns a certificate for `value`, signed only by the given validators, whether or not
+    /// they have a quorum.
+    pub fn make_certificate_signed_by<T: CertificateValue>(
+        &self,
+        value: T,
+        round: Round,
+        signers: &[ValidatorPublicKey],
+    ) -> GenericCertificate<T> {
+        let lite_value = LiteValue::new(&value);
+        let signatures = self
+            .validator_secrets
+            .iter()
+            .filter(|secret_key| signers.contains(&secret_key.public()))
+            .map(|secret_key| {
+                let vote = LiteVote::new(lite_value.clone(), round, secret_key);
+                (vote.public_key, vote.signature)
+            })
+            .collect();
+        GenericCertificate::new(value, round, signatures)
+    }
+
+    /// Executes `block` on the worker without committing it, and returns a certificate for
+    /// the resulting confirmed block.
+    pub async fn make_confirmed_certificate(
+        &self,
+        block: ProposedBlock,
+    ) -> Result<ConfirmedBlockCertificate, WorkerError> {
+        let (block, _) = self
+            .worker
+            .stage_block_execution(block, None, Vec::new())
+            .await?;
+        Ok(self.make_certificate(ConfirmedBlock::new(block), Round::MultiLeader(0)))
+    }
+}
+
 #[cfg(feature = "rocksdb")]
 pub struct RocksDbStorageBuilder {
     namespace: String,
@@ -157,6 +157,7 @@ where
             async_graphql::Value::from_json(json!({"value": 15})).unwrap(),
         ),
         operations: vec![],
+        operation_metadata: vec![],
     };
 
     assert_eq!(outcome, expected);
@@ -391,6 +392,7 @@ where
             async_graphql::Value::from_json(json!({"value": 5})).unwrap(),
         ),
         operations: vec![],
+        operation_metadata: vec![],
     };
 
     assert_eq!(outcome, expected);
@@ -803,6 +805,7 @@ where
             .unwrap(),
         ),
         operations: vec![],
+        operation_metadata: vec![],
     };
     assert_eq!(outcome, expected);
 
@@ -857,6 +860,7 @@ where
             .unwrap(),
         ),
         operations: vec![],
+        operation_metadata: vec![],
     };
     assert_eq!(outcome, expected);
 
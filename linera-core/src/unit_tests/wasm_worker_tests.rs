@@ -16,7 +16,8 @@ use assert_matches::assert_matches;
 use linera_base::{
     crypto::AccountSecretKey,
     data_types::{
-        Amount, ApplicationDescription, Blob, BlockHeight, Bytecode, OracleResponse, Timestamp,
+        Amount, ApplicationDescription, Blob, BlockHeight, Bytecode, FeeBreakdown, OracleResponse,
+        Timestamp,
     },
     identifiers::ModuleId,
     vm::VmRuntime,
@@ -143,6 +144,7 @@ where
             state_hash: publisher_state_hash,
             oracle_responses: vec![vec![]],
             operation_results: vec![OperationResult::default()],
+            fees: FeeBreakdown::default(),
         }
         .with(publish_block),
     );
@@ -224,6 +226,7 @@ where
             ]],
             blobs: vec![vec![application_description_blob.clone()]],
             operation_results: vec![OperationResult::default()],
+            fees: FeeBreakdown::default(),
         }
         .with(create_block),
     );
@@ -300,6 +303,7 @@ where
             state_hash: creator_state.crypto_hash().await?,
             oracle_responses: vec![vec![]],
             operation_results: vec![OperationResult(bcs::to_bytes(&15u64)?)],
+            fees: FeeBreakdown::default(),
         }
         .with(run_block),
     );
@@ -10,18 +10,29 @@ mod wasm;
 use std::{
     collections::{BTreeMap, BTreeSet},
     iter,
-    sync::{Arc, Mutex},
+    num::NonZeroU32,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
     time::Duration,
 };
 
 use assert_matches::assert_matches;
+use futures::{
+    future::{self, BoxFuture},
+    FutureExt as _,
+};
 use linera_base::{
     crypto::{
         AccountPublicKey, AccountSecretKey, AccountSignature, CryptoHash, InMemorySigner,
         ValidatorKeypair,
     },
     data_types::*,
-    identifiers::{Account, AccountOwner, ChainId, EventId, StreamId},
+    identifiers::{
+        Account, AccountOwner, ApplicationId, ChainId, EventId, GenericApplicationId, StreamId,
+    },
+    merkle::verify_state_inclusion,
     ownership::{ChainOwnership, TimeoutConfig},
 };
 use linera_chain::{
@@ -30,7 +41,9 @@ use linera_chain::{
         MessageAction, MessageBundle, OperationResult, PostedMessage, ProposedBlock,
         SignatureAggregator,
     },
-    manager::LockingBlock,
+    manager::{
+        LockingBlock, ManagerEntry, Pacemaker, PacemakerError, RoundSchedule, VoteKind, Voter,
+    },
     test::{make_child_block, make_first_block, BlockTestExt, MessageTestExt, VoteTestExt},
     types::{
         CertificateValue, ConfirmedBlock, ConfirmedBlockCertificate, GenericCertificate, Timeout,
@@ -41,7 +54,7 @@ use linera_chain::{
 use linera_execution::{
     committee::Committee,
     system::{
-        AdminOperation, OpenChainConfig, Recipient, SystemMessage, SystemOperation,
+        AdminOperation, InboxQuotas, OpenChainConfig, Recipient, SystemMessage, SystemOperation,
         EPOCH_STREAM_NAME as NEW_EPOCH_STREAM_NAME, REMOVED_EPOCH_STREAM_NAME,
     },
     test_utils::{
@@ -50,8 +63,9 @@ use linera_execution::{
     ExecutionError, Message, MessageKind, OutgoingMessage, Query, QueryContext, QueryOutcome,
     QueryResponse, SystemQuery, SystemResponse,
 };
-use linera_storage::{DbStorage, Storage, TestClock};
+use linera_storage::{DbStorage, EpochCommittee, Storage, TestClock};
 use linera_views::{
+    change_log::{ChangeLog, ChannelSink},
     memory::MemoryDatabase,
     random::generate_test_namespace,
     store::TestKeyValueDatabase as _,
@@ -59,6 +73,7 @@ use linera_views::{
 };
 use test_case::test_case;
 use test_log::test;
+use tokio::sync::oneshot;
 
 #[cfg(feature = "dynamodb")]
 use crate::test_utils::DynamoDbStorageBuilder;
@@ -67,13 +82,17 @@ use crate::test_utils::RocksDbStorageBuilder;
 #[cfg(feature = "scylladb")]
 use crate::test_utils::ScyllaDbStorageBuilder;
 use crate::{
-    chain_worker::CrossChainUpdateHelper,
+    chain_diagnosis::BlockingCondition,
+    chain_worker::{check_certificate_with_weight_updates, CrossChainUpdateHelper},
+    committee_cache::CommitteeCache,
     data_types::*,
-    test_utils::{MemoryStorageBuilder, StorageBuilder},
+    signing_fence::SigningFence,
+    test_utils::{DryRunWorker, MemoryStorageBuilder, StorageBuilder},
+    verifier::verify_chain,
     worker::{
         Notification,
         Reason::{self, NewBlock, NewIncomingBundle},
-        WorkerError, WorkerState,
+        ShutdownStatus, WorkerError, WorkerState,
     },
 };
 
@@ -448,6 +467,7 @@ where
                 state_hash,
                 oracle_responses,
                 operation_results,
+                fees: FeeBreakdown::default(),
             }
             .with(block),
         );
@@ -588,6 +608,53 @@ where
     Ok(())
 }
 
+/// The changes of the chain state saved when handling a block proposal are emitted to the
+/// change log of the storage, with the root key of that chain.
+#[test_log::test(tokio::test)]
+async fn test_handle_block_proposal_emits_change_log() -> anyhow::Result<()> {
+    let (sink, mut receiver) = ChannelSink::new();
+    let storage = MemoryStorageBuilder::default()
+        .build()
+        .await?
+        .with_change_log(ChangeLog::default().with_sink(Arc::new(sink)));
+    let mut signer = InMemorySigner::new(None);
+    let sender_owner = signer.generate_new().into();
+    let mut env = TestEnvironment::new(storage, false, false).await;
+    let chain_1 = env
+        .add_root_chain(1, sender_owner, Amount::from_tokens(5))
+        .await
+        .id();
+    let chain_2 = env
+        .add_root_chain(2, AccountPublicKey::test_key(2).into(), Amount::ZERO)
+        .await
+        .id();
+    while receiver.try_recv().is_ok() {}
+
+    let block_proposal = make_first_block(chain_1)
+        .with_simple_transfer(chain_2, Amount::from_tokens(5))
+        .with_authenticated_signer(Some(sender_owner))
+        .into_first_proposal(sender_owner, &signer)
+        .await
+        .unwrap();
+    env.worker().handle_block_proposal(block_proposal).await?;
+
+    let mut entries = Vec::new();
+    while let Ok(entry) = receiver.try_recv() {
+        entries.push(entry);
+    }
+    let chain_1_key = bcs::to_bytes(&chain_1)?;
+    let chain_2_key = bcs::to_bytes(&chain_2)?;
+    assert!(entries.iter().any(|entry| {
+        entry.root_key.ends_with(&chain_1_key)
+            && entry.view_name.ends_with("ChainStateView")
+            && !entry.changes.is_empty()
+    }));
+    assert!(entries
+        .iter()
+        .all(|entry| !entry.root_key.ends_with(&chain_2_key)));
+    Ok(())
+}
+
 #[test_case(MemoryStorageBuilder::default(); "memory")]
 #[cfg_attr(feature = "rocksdb", test_case(RocksDbStorageBuilder::new().await; "rocks_db"))]
 #[cfg_attr(feature = "dynamodb", test_case(DynamoDbStorageBuilder::default(); "dynamo_db"))]
@@ -1035,6 +1102,7 @@ where
             .await,
             oracle_responses: vec![Vec::new(); 2],
             operation_results: vec![OperationResult::default(); 2],
+            fees: FeeBreakdown::default(),
         }
         .with(
             make_first_block(chain_1)
@@ -1062,6 +1130,7 @@ where
             .await,
             oracle_responses: vec![Vec::new()],
             operation_results: vec![OperationResult::default()],
+            fees: FeeBreakdown::default(),
         }
         .with(
             make_child_block(&certificate0.clone().into_value())
@@ -1307,6 +1376,7 @@ where
                     .await,
                 oracle_responses: vec![Vec::new(); 2],
                 operation_results: vec![OperationResult::default()],
+                fees: FeeBreakdown::default(),
             }
             .with(block_proposal.content.block),
         ));
@@ -1492,36 +1562,54 @@ where
 #[cfg_attr(feature = "dynamodb", test_case(DynamoDbStorageBuilder::default(); "dynamo_db"))]
 #[cfg_attr(feature = "scylladb", test_case(ScyllaDbStorageBuilder::default(); "scylla_db"))]
 #[test_log::test(tokio::test)]
-async fn test_handle_certificate_unknown_sender<B>(mut storage_builder: B) -> anyhow::Result<()>
+async fn test_handle_block_proposal_validation_only<B>(mut storage_builder: B) -> anyhow::Result<()>
 where
     B: StorageBuilder,
 {
     let mut signer = InMemorySigner::new(None);
-    let sender_pubkey = signer.generate_new();
-    let test_pubkey = signer.generate_new();
+    let sender_owner = signer.generate_new().into();
     let mut env = TestEnvironment::new(storage_builder.build().await?, false, false).await;
+    let chain_1_desc = env
+        .add_root_chain(1, sender_owner, Amount::from_tokens(5))
+        .await;
     let chain_2_desc = env
-        .add_root_chain(2, test_pubkey.into(), Amount::ZERO)
+        .add_root_chain(2, sender_owner, Amount::from_tokens(5))
         .await;
+    let chain_1 = chain_1_desc.id();
     let chain_2 = chain_2_desc.id();
-    let chain_1_desc = dummy_chain_description(1);
-    let certificate = env
-        .make_simple_transfer_certificate(
-            chain_1_desc.clone(),
-            sender_pubkey,
-            chain_2,
-            Amount::from_tokens(5),
-            Vec::new(),
-            Amount::from_tokens(5),
-            vec![],
-        )
-        .await;
+    let worker = env.worker().clone().with_validation_only(true);
+
+    // An invalid proposal is still rejected.
+    let invalid_proposal = make_first_block(chain_1)
+        .with_simple_transfer(chain_2, Amount::from_tokens(6))
+        .with_authenticated_signer(Some(sender_owner))
+        .into_first_proposal(sender_owner, &signer)
+        .await
+        .unwrap();
     assert_matches!(
-        env.worker()
-            .fully_handle_certificate_with_notifications(certificate, &())
-            .await,
-        Err(WorkerError::BlobsNotFound(error))
-            if error == vec![Blob::new_chain_description(&chain_1_desc).id()]
+        worker.handle_block_proposal(invalid_proposal).await,
+        Err(WorkerError::ChainError(error)) if matches!(&*error, ChainError::ExecutionError(..))
+    );
+
+    // A valid proposal is accepted, but neither the response nor the proposal is signed.
+    let block_proposal = make_first_block(chain_1)
+        .with_simple_transfer(chain_2, Amount::from_tokens(5))
+        .with_authenticated_signer(Some(sender_owner))
+        .into_first_proposal(sender_owner, &signer)
+        .await
+        .unwrap();
+    let (response, _actions) = worker.handle_block_proposal(block_proposal).await?;
+    assert!(response.signature.is_none());
+    let chain = worker.chain_state_view(chain_1).await?;
+    assert!(chain.manager.validated_vote().is_none());
+    assert!(chain.manager.confirmed_vote().is_none());
+    drop(chain);
+
+    // Queries requesting a vote are rejected.
+    let query = ChainInfoQuery::new(chain_1).with_timeout();
+    assert_matches!(
+        worker.handle_chain_info_query(query).await,
+        Err(WorkerError::VotingDisabled)
     );
     Ok(())
 }
@@ -1531,48 +1619,71 @@ where
 #[cfg_attr(feature = "dynamodb", test_case(DynamoDbStorageBuilder::default(); "dynamo_db"))]
 #[cfg_attr(feature = "scylladb", test_case(ScyllaDbStorageBuilder::default(); "scylla_db"))]
 #[test_log::test(tokio::test)]
-async fn test_handle_certificate_with_open_chain<B>(mut storage_builder: B) -> anyhow::Result<()>
+async fn test_execution_state_delta_across_restart<B>(mut storage_builder: B) -> anyhow::Result<()>
 where
     B: StorageBuilder,
 {
-    let sender_key_pair = AccountSecretKey::generate();
+    let mut signer = InMemorySigner::new(None);
+    let sender_owner = signer.generate_new().into();
     let mut env = TestEnvironment::new(storage_builder.build().await?, false, false).await;
-    let chain_2_desc = env
-        .add_root_chain(2, AccountPublicKey::test_key(2).into(), Amount::ZERO)
+    let chain_1_desc = env
+        .add_root_chain(1, sender_owner, Amount::from_tokens(5))
         .await;
-    let balance = Amount::from_tokens(42);
-    let small_transfer = Amount::from_tokens(1);
-    let description = env
-        .add_child_chain(chain_2_desc.id(), sender_key_pair.public().into(), balance)
+    let chain_2_desc = env
+        .add_root_chain(2, sender_owner, Amount::from_tokens(5))
         .await;
-    let chain_id = description.id();
-    let mut state = env.system_execution_state(&description.id());
-    // Account for burnt tokens.
-    state.balance = balance - small_transfer;
-    let block = make_first_block(chain_id)
-        .with_burn(small_transfer)
-        .with_authenticated_signer(Some(sender_key_pair.public().into()));
-
-    let value = ConfirmedBlock::new(
-        BlockExecutionOutcome {
-            messages: vec![vec![]],
-            previous_message_blocks: BTreeMap::new(),
-            previous_event_blocks: BTreeMap::new(),
-            events: vec![vec![]],
-            blobs: vec![vec![]],
-            state_hash: state.into_hash().await,
-            oracle_responses: vec![vec![]],
-            operation_results: vec![OperationResult::default()],
-        }
-        .with(block),
+    let chain_1 = chain_1_desc.id();
+    let chain_2 = chain_2_desc.id();
+    let worker = env.worker().clone().with_max_execution_state_deltas(10);
+    let block_proposal = make_first_block(chain_1)
+        .with_simple_transfer(chain_2, Amount::from_tokens(3))
+        .with_authenticated_signer(Some(sender_owner))
+        .into_first_proposal(sender_owner, &signer)
+        .await
+        .unwrap();
+    worker.handle_block_proposal(block_proposal).await?;
+    let chain = worker.chain_state_view(chain_1).await?;
+    let block = chain
+        .manager
+        .validated_vote()
+        .unwrap()
+        .value()
+        .block()
+        .clone();
+    drop(chain);
+    let state_hash = block.header.state_hash;
+    assert!(worker
+        .storage
+        .read_execution_state_delta(state_hash)
+        .await?
+        .is_some());
+
+    // A restarted worker applies the persisted changes instead of the cached state.
+    let restarted = WorkerState {
+        block_cache: Arc::default(),
+        execution_state_cache: Arc::default(),
+        chain_worker_tasks: Arc::default(),
+        chain_workers: Arc::default(),
+        ..worker.clone()
+    };
+    let certificate = env.make_certificate(ConfirmedBlock::new(block));
+    restarted
+        .handle_confirmed_certificate(certificate, None)
+        .await?;
+    let chain = restarted.chain_state_view(chain_1).await?;
+    assert_eq!(chain.tip_state.get().next_block_height, BlockHeight(1));
+    assert_eq!(*chain.execution_state_hash.get(), Some(state_hash));
+    assert_eq!(chain.execution_state.crypto_hash().await?, state_hash);
+    assert_eq!(
+        *chain.execution_state.system.balance.get(),
+        Amount::from_tokens(2)
     );
-    let certificate = env.make_certificate(value);
-    let info = env
-        .worker()
-        .fully_handle_certificate_with_notifications(certificate, &())
+    drop(chain);
+    assert!(worker
+        .storage
+        .read_execution_state_delta(state_hash)
         .await?
-        .info;
-    assert_eq!(info.next_block_height, BlockHeight::from(1));
+        .is_none());
     Ok(())
 }
 
@@ -1581,79 +1692,90 @@ where
 #[cfg_attr(feature = "dynamodb", test_case(DynamoDbStorageBuilder::default(); "dynamo_db"))]
 #[cfg_attr(feature = "scylladb", test_case(ScyllaDbStorageBuilder::default(); "scylla_db"))]
 #[test_log::test(tokio::test)]
-async fn test_handle_certificate_wrong_owner<B>(mut storage_builder: B) -> anyhow::Result<()>
+async fn test_query_application_at_past_height<B>(mut storage_builder: B) -> anyhow::Result<()>
 where
     B: StorageBuilder,
 {
-    let sender_key_pair = AccountSecretKey::generate();
-    let chain_key_pair = AccountSecretKey::generate();
+    let mut signer = InMemorySigner::new(None);
+    let sender_public_key = signer.generate_new();
+    let sender_owner = sender_public_key.into();
     let mut env = TestEnvironment::new(storage_builder.build().await?, false, false).await;
-    let chain_2_desc = env
-        .add_root_chain(2, chain_key_pair.public().into(), Amount::from_tokens(5))
+    let chain_desc = env
+        .add_root_chain(1, sender_owner, Amount::from_tokens(5))
         .await;
-    let chain_2 = chain_2_desc.id();
-    let certificate = env
-        .make_transfer_certificate_for_epoch(
-            chain_2_desc.clone(),
-            sender_key_pair.public(),
-            chain_key_pair.public().into(),
-            AccountOwner::CHAIN,
-            Recipient::chain(chain_2),
-            Amount::from_tokens(5),
+    let chain_1 = chain_desc.id();
+    let chain_2 = env.add_root_chain(2, sender_owner, Amount::ZERO).await.id();
+    // Only the changes made by the latest block are kept.
+    let worker = env.worker().clone().with_execution_state_history(1);
+
+    let certificate0 = env
+        .make_simple_transfer_certificate(
+            chain_desc.clone(),
+            sender_public_key,
+            chain_2,
+            Amount::ONE,
             Vec::new(),
-            Epoch::ZERO,
-            Amount::ZERO,
-            BTreeMap::new(),
+            Amount::from_tokens(4),
             vec![],
         )
         .await;
-    // This fails because `make_simple_transfer_certificate` uses `sender_key_pair.public()` to
-    // compute the hash of the execution state.
-    assert_matches!(
-        env.worker()
-            .fully_handle_certificate_with_notifications(certificate, &())
-            .await,
-        Err(WorkerError::IncorrectOutcome { .. })
-    );
-    Ok(())
-}
-
-#[test_case(MemoryStorageBuilder::default(); "memory")]
-#[cfg_attr(feature = "rocksdb", test_case(RocksDbStorageBuilder::new().await; "rocks_db"))]
-#[cfg_attr(feature = "dynamodb", test_case(DynamoDbStorageBuilder::default(); "dynamo_db"))]
-#[cfg_attr(feature = "scylladb", test_case(ScyllaDbStorageBuilder::default(); "scylla_db"))]
-#[test_log::test(tokio::test)]
-async fn test_handle_certificate_bad_block_height<B>(mut storage_builder: B) -> anyhow::Result<()>
-where
-    B: StorageBuilder,
-{
-    let sender_key_pair = AccountSecretKey::generate();
-    let mut env = TestEnvironment::new(storage_builder.build().await?, false, false).await;
-    let chain_1_desc = env
-        .add_root_chain(1, sender_key_pair.public().into(), Amount::from_tokens(5))
-        .await;
-    let chain_2_desc = env
-        .add_root_chain(2, AccountPublicKey::test_key(2).into(), Amount::ZERO)
+    let certificate1 = env
+        .make_simple_transfer_certificate(
+            chain_desc.clone(),
+            sender_public_key,
+            chain_1,
+            Amount::ONE,
+            Vec::new(),
+            Amount::from_tokens(3),
+            vec![&certificate0],
+        )
         .await;
-    let chain_2 = chain_2_desc.id();
-    let certificate = env
+    let certificate2 = env
         .make_simple_transfer_certificate(
-            chain_1_desc.clone(),
-            sender_key_pair.public(),
+            chain_desc.clone(),
+            sender_public_key,
             chain_2,
-            Amount::from_tokens(5),
+            Amount::ONE,
             Vec::new(),
-            Amount::ZERO,
-            vec![],
+            Amount::from_tokens(2),
+            vec![&certificate1, &certificate0],
         )
         .await;
-    // Replays are ignored.
-    env.worker()
-        .fully_handle_certificate_with_notifications(certificate.clone(), &())
-        .await?;
-    env.worker()
-        .fully_handle_certificate_with_notifications(certificate, &())
-        .await?;
+    for certificate in [certificate0, certificate1, certificate2] {
+        worker
+            .handle_confirmed_certificate(certificate, None)
+            .await?;
+    }
+
+    for (height, balance) in [(2, 2), (1, 3)] {
+        let outcome = worker
+            .query_application_at(chain_1, Query::System(SystemQuery), BlockHeight(height))
+            .await?;
+        assert_eq!(
+            outcome.response,
+            QueryResponse::System(SystemResponse {
+                chain_id: chain_1,
+                balance: Amount::from_tokens(balance),
+            })
+        );
+    }
+    // The state is unchanged by the historical query.
+    let chain = worker.chain_state_view(chain_1).await?;
+    assert_eq!(
+        *chain.execution_state.system.balance.get(),
+        Amount::from_tokens(2)
+    );
+    drop(chain);
+
+    for height in [0, 3] {
+        assert_matches!(
+            worker
+                .query_application_at(chain_1, Query::System(SystemQuery), BlockHeight(height))
+                .await,
+            Err(WorkerError::HistoryUnavailable { chain_id, height: unavailable_height })
+                if chain_id == chain_1 && unavailable_height == BlockHeight(height)
+        );
+    }
     Ok(())
 }
 
@@ -1662,95 +1784,66 @@ where
 #[cfg_attr(feature = "dynamodb", test_case(DynamoDbStorageBuilder::default(); "dynamo_db"))]
 #[cfg_attr(feature = "scylladb", test_case(ScyllaDbStorageBuilder::default(); "scylla_db"))]
 #[test_log::test(tokio::test)]
-async fn test_handle_certificate_with_anticipated_incoming_bundle<B>(
+async fn test_handle_block_proposal_with_idempotency_key<B>(
     mut storage_builder: B,
 ) -> anyhow::Result<()>
 where
     B: StorageBuilder,
 {
-    let key_pair = AccountSecretKey::generate();
+    let mut signer = InMemorySigner::new(None);
+    let sender_owner = signer.generate_new().into();
     let mut env = TestEnvironment::new(storage_builder.build().await?, false, false).await;
     let chain_1_desc = env
-        .add_root_chain(1, key_pair.public().into(), Amount::from_tokens(5))
+        .add_root_chain(1, sender_owner, Amount::from_tokens(5))
         .await;
     let chain_2_desc = env
         .add_root_chain(2, AccountPublicKey::test_key(2).into(), Amount::ZERO)
         .await;
-    let chain_3_desc = env
-        .add_root_chain(3, AccountPublicKey::test_key(3).into(), Amount::ZERO)
-        .await;
     let chain_1 = chain_1_desc.id();
     let chain_2 = chain_2_desc.id();
-    let chain_3 = chain_3_desc.id();
+    let key = CryptoHash::test_hash("idempotency key");
+    let invalid_proposal = make_first_block(chain_1)
+        .with_simple_transfer(chain_2, Amount::from_tokens(1000))
+        .with_authenticated_signer(Some(sender_owner))
+        .into_first_proposal(sender_owner, &signer)
+        .await
+        .unwrap()
+        .with_idempotency_key(key);
 
-    let certificate = env
-        .make_simple_transfer_certificate(
-            chain_1_desc.clone(),
-            key_pair.public(),
-            chain_2,
-            Amount::from_tokens(1000),
-            vec![IncomingBundle {
-                origin: chain_3,
-                bundle: MessageBundle {
-                    certificate_hash: CryptoHash::test_hash("certificate"),
-                    height: BlockHeight::ZERO,
-                    timestamp: Timestamp::from(0),
-                    transaction_index: 0,
-                    messages: vec![system_credit_message(Amount::from_tokens(995))
-                        .to_posted(0, MessageKind::Tracked)],
-                },
-                action: MessageAction::Accept,
-            }],
-            Amount::ZERO,
-            vec![],
-        )
-        .await;
-    env.worker()
-        .fully_handle_certificate_with_notifications(certificate.clone(), &())
+    // The rejection is recorded and returned again for retries.
+    let Err(WorkerError::RecordedProposalError(error)) = env
+        .worker()
+        .handle_block_proposal(invalid_proposal.clone())
+        .await
+    else {
+        panic!("Expected the proposal to be rejected");
+    };
+    assert_matches!(
+        env.worker().handle_block_proposal(invalid_proposal).await,
+        Err(WorkerError::RecordedProposalError(replayed_error)) if replayed_error == error
+    );
+
+    // A different proposal reusing the key is handled normally.
+    let valid_proposal = make_first_block(chain_1)
+        .with_simple_transfer(chain_2, Amount::from_tokens(5))
+        .with_authenticated_signer(Some(sender_owner))
+        .into_first_proposal(sender_owner, &signer)
+        .await
+        .unwrap()
+        .with_idempotency_key(key);
+    let (response, _actions) = env
+        .worker()
+        .handle_block_proposal(valid_proposal.clone())
         .await?;
-    let chain = env.worker().chain_state_view(chain_1).await?;
-    assert!(chain.is_active());
-    assert_eq!(Amount::ZERO, *chain.execution_state.system.balance.get());
+    response.check(env.worker().public_key())?;
+
+    let (replay_response, actions) = env.worker().handle_block_proposal(valid_proposal).await?;
     assert_eq!(
-        BlockHeight::from(1),
-        chain.tip_state.get().next_block_height
-    );
-    let inbox = chain
-        .inboxes
-        .try_load_entry(&chain_3)
-        .await?
-        .expect("Missing inbox for `chain_3` in `ChainId::root(1)`");
-    assert_eq!(BlockHeight::ZERO, inbox.next_block_height_to_receive()?);
-    assert_eq!(inbox.added_bundles.count(), 0);
-    assert_matches!(
-        inbox
-            .removed_bundles
-            .front()
-            .await?
-            .unwrap(),
-        MessageBundle {
-            certificate_hash,
-            height,
-            timestamp,
-            transaction_index: 0,
-            messages,
-        } if certificate_hash == CryptoHash::test_hash("certificate")
-            && height == BlockHeight::ZERO
-            && timestamp == Timestamp::from(0)
-            && matches!(messages[..], [PostedMessage {
-                authenticated_signer: None,
-                grant: Amount::ZERO,
-                refund_grant_to: None,
-                kind: MessageKind::Tracked,
-                index: 0,
-                message: Message::System(SystemMessage::Credit { amount, .. }),
-            }] if amount == Amount::from_tokens(995)),
-        "Unexpected bundle",
+        CryptoHash::new(&*response.info),
+        CryptoHash::new(&*replay_response.info)
     );
-    assert_eq!(chain.confirmed_log.count(), 1);
-    assert_eq!(Some(certificate.hash()), chain.tip_state.get().block_hash);
-    let chain = env.worker().chain_state_view(chain_2).await?;
-    assert!(chain.is_active());
+    assert!(actions.cross_chain_requests.is_empty());
+    assert!(actions.notifications.is_empty());
     Ok(())
 }
 
@@ -1759,129 +1852,115 @@ where
 #[cfg_attr(feature = "dynamodb", test_case(DynamoDbStorageBuilder::default(); "dynamo_db"))]
 #[cfg_attr(feature = "scylladb", test_case(ScyllaDbStorageBuilder::default(); "scylla_db"))]
 #[test_log::test(tokio::test)]
-async fn test_handle_certificate_receiver_balance_overflow<B>(
-    mut storage_builder: B,
-) -> anyhow::Result<()>
+async fn test_handle_certificate_unknown_sender<B>(mut storage_builder: B) -> anyhow::Result<()>
 where
     B: StorageBuilder,
 {
-    let sender_key_pair = AccountSecretKey::generate();
+    let mut signer = InMemorySigner::new(None);
+    let sender_pubkey = signer.generate_new();
+    let test_pubkey = signer.generate_new();
     let mut env = TestEnvironment::new(storage_builder.build().await?, false, false).await;
-    let chain_1_desc = env
-        .add_root_chain(1, sender_key_pair.public().into(), Amount::ONE)
-        .await;
     let chain_2_desc = env
-        .add_root_chain(2, AccountPublicKey::test_key(2).into(), Amount::MAX)
+        .add_root_chain(2, test_pubkey.into(), Amount::ZERO)
         .await;
-    let chain_1 = chain_1_desc.id();
     let chain_2 = chain_2_desc.id();
-
+    let chain_1_desc = dummy_chain_description(1);
     let certificate = env
         .make_simple_transfer_certificate(
             chain_1_desc.clone(),
-            sender_key_pair.public(),
+            sender_pubkey,
             chain_2,
-            Amount::ONE,
+            Amount::from_tokens(5),
             Vec::new(),
-            Amount::ZERO,
+            Amount::from_tokens(5),
             vec![],
         )
         .await;
-    env.worker()
-        .fully_handle_certificate_with_notifications(certificate.clone(), &())
-        .await?;
-    let new_sender_chain = env.worker().chain_state_view(chain_1).await?;
-    assert!(new_sender_chain.is_active());
-    assert_eq!(
-        Amount::ZERO,
-        *new_sender_chain.execution_state.system.balance.get()
-    );
-    assert_eq!(
-        BlockHeight::from(1),
-        new_sender_chain.tip_state.get().next_block_height
-    );
-    assert_eq!(new_sender_chain.confirmed_log.count(), 1);
-    assert_eq!(
-        Some(certificate.hash()),
-        new_sender_chain.tip_state.get().block_hash
-    );
-    let new_recipient_chain = env.worker().chain_state_view(chain_2).await?;
-    assert!(new_recipient_chain.is_active());
-    assert_eq!(
-        Amount::MAX,
-        *new_recipient_chain.execution_state.system.balance.get()
+    assert_matches!(
+        env.worker()
+            .fully_handle_certificate_with_notifications(certificate, &())
+            .await,
+        Err(WorkerError::BlobsNotFound(error))
+            if error == vec![Blob::new_chain_description(&chain_1_desc).id()]
     );
     Ok(())
 }
 
+/// Tests that the pending blobs of proposals from epochs that are no longer active are dropped,
+/// and that entries without a recorded epoch use the current one.
 #[test_case(MemoryStorageBuilder::default(); "memory")]
 #[cfg_attr(feature = "rocksdb", test_case(RocksDbStorageBuilder::new().await; "rocks_db"))]
 #[cfg_attr(feature = "dynamodb", test_case(DynamoDbStorageBuilder::default(); "dynamo_db"))]
 #[cfg_attr(feature = "scylladb", test_case(ScyllaDbStorageBuilder::default(); "scylla_db"))]
 #[test_log::test(tokio::test)]
-async fn test_handle_certificate_receiver_equal_sender<B>(
-    mut storage_builder: B,
-) -> anyhow::Result<()>
+async fn test_stale_epoch_pending_blobs<B>(mut storage_builder: B) -> anyhow::Result<()>
 where
     B: StorageBuilder,
 {
-    let storage = storage_builder.build().await?;
-    let key_pair = AccountSecretKey::generate();
-    let owner = key_pair.public().into();
-    let mut env = TestEnvironment::new(storage, false, false).await;
-    let chain_1_desc = env.add_root_chain(1, owner, Amount::ONE).await;
-    let chain_1 = chain_1_desc.id();
-
-    let certificate = env
-        .make_simple_transfer_certificate(
-            chain_1_desc.clone(),
-            key_pair.public(),
-            chain_1,
-            Amount::ONE,
-            Vec::new(),
-            Amount::ZERO,
-            vec![],
-        )
+    let mut env = TestEnvironment::new(storage_builder.build().await?, false, false).await;
+    let chain_description = env
+        .add_root_chain(1, AccountPublicKey::test_key(1).into(), Amount::ONE)
         .await;
+    let chain_id = chain_description.id();
+    let stale_owner = AccountOwner::from(AccountPublicKey::test_key(2));
+    let legacy_owner = AccountOwner::from(AccountPublicKey::test_key(3));
+    let stale_blob = Blob::new_data(b"stale".to_vec());
+    let legacy_blob = Blob::new_data(b"legacy".to_vec());
+    {
+        let mut chain = env.worker().storage.load_chain(chain_id).await?;
+        let mut pending_blobs = chain
+            .pending_proposed_blobs
+            .try_load_entry_mut(&stale_owner)
+            .await?;
+        pending_blobs
+            .update(
+                Round::Fast,
+                false,
+                Epoch::from(7),
+                BTreeMap::from([(stale_blob.id(), None)]),
+            )
+            .await?;
+        drop(pending_blobs);
+        let mut pending_blobs = chain
+            .pending_proposed_blobs
+            .try_load_entry_mut(&legacy_owner)
+            .await?;
+        pending_blobs
+            .update(
+                Round::Fast,
+                false,
+                Epoch::ZERO,
+                BTreeMap::from([(legacy_blob.id(), None)]),
+            )
+            .await?;
+        // Entries stored before the epoch was recorded don't have one.
+        pending_blobs.epoch.set(None);
+        drop(pending_blobs);
+        chain.save().await?;
+    }
+
+    // The stale proposal can't be voted on anymore, so its blob is not expected.
+    assert_matches!(
+        env.worker().handle_pending_blob(chain_id, stale_blob).await,
+        Err(WorkerError::UnexpectedBlob)
+    );
+
+    // The legacy entry is checked against the current epoch, and the stale one is dropped.
     env.worker()
-        .fully_handle_certificate_with_notifications(certificate.clone(), &())
+        .handle_pending_blob(chain_id, legacy_blob.clone())
         .await?;
-    let chain = env.worker().chain_state_view(chain_1).await?;
-    assert!(chain.is_active());
-    assert_eq!(Amount::ZERO, *chain.execution_state.system.balance.get());
-    let inbox = chain
-        .inboxes
-        .try_load_entry(&chain_1)
+    let chain = env.worker().storage.load_chain(chain_id).await?;
+    let owners = chain.pending_proposed_blobs.indices().await?;
+    assert_eq!(owners, vec![legacy_owner]);
+    let pending_blobs = chain
+        .pending_proposed_blobs
+        .try_load_entry(&legacy_owner)
         .await?
-        .expect("Missing inbox for `ChainId::root(1)` in `ChainId::root(1)`");
-    assert_eq!(BlockHeight::from(1), inbox.next_block_height_to_receive()?);
-    assert_matches!(
-        inbox.added_bundles.front().await?.unwrap(),
-        MessageBundle {
-            certificate_hash,
-            height,
-            timestamp,
-            transaction_index: 0,
-            messages,
-        } if certificate_hash == certificate.hash()
-        && height == BlockHeight::ZERO
-        && timestamp == Timestamp::from(0)
-        && matches!(messages[..], [PostedMessage {
-            authenticated_signer: None,
-            grant: Amount::ZERO,
-            refund_grant_to: None,
-            kind: MessageKind::Tracked,
-            index: 0,
-            message: Message::System(SystemMessage::Credit { amount, .. })
-        }] if amount == Amount::ONE),
-        "Unexpected bundle",
-    );
+        .expect("the legacy entry should still exist");
     assert_eq!(
-        BlockHeight::from(1),
-        chain.tip_state.get().next_block_height
+        pending_blobs.get(&legacy_blob.id()).await?,
+        Some(legacy_blob)
     );
-    assert_eq!(chain.confirmed_log.count(), 1);
-    assert_eq!(Some(certificate.hash()), chain.tip_state.get().block_hash);
     Ok(())
 }
 
@@ -1890,70 +1969,91 @@ where
 #[cfg_attr(feature = "dynamodb", test_case(DynamoDbStorageBuilder::default(); "dynamo_db"))]
 #[cfg_attr(feature = "scylladb", test_case(ScyllaDbStorageBuilder::default(); "scylla_db"))]
 #[test_log::test(tokio::test)]
-async fn test_handle_cross_chain_request<B>(mut storage_builder: B) -> anyhow::Result<()>
+async fn test_handle_certificate_with_open_chain<B>(mut storage_builder: B) -> anyhow::Result<()>
 where
     B: StorageBuilder,
 {
     let sender_key_pair = AccountSecretKey::generate();
     let mut env = TestEnvironment::new(storage_builder.build().await?, false, false).await;
     let chain_2_desc = env
-        .add_root_chain(2, AccountPublicKey::test_key(2).into(), Amount::ONE)
+        .add_root_chain(2, AccountPublicKey::test_key(2).into(), Amount::ZERO)
+        .await;
+    let balance = Amount::from_tokens(42);
+    let small_transfer = Amount::from_tokens(1);
+    let description = env
+        .add_child_chain(chain_2_desc.id(), sender_key_pair.public().into(), balance)
+        .await;
+    let chain_id = description.id();
+    let mut state = env.system_execution_state(&description.id());
+    // Account for burnt tokens.
+    state.balance = balance - small_transfer;
+    let block = make_first_block(chain_id)
+        .with_burn(small_transfer)
+        .with_authenticated_signer(Some(sender_key_pair.public().into()));
+
+    let value = ConfirmedBlock::new(
+        BlockExecutionOutcome {
+            messages: vec![vec![]],
+            previous_message_blocks: BTreeMap::new(),
+            previous_event_blocks: BTreeMap::new(),
+            events: vec![vec![]],
+            blobs: vec![vec![]],
+            state_hash: state.into_hash().await,
+            oracle_responses: vec![vec![]],
+            operation_results: vec![OperationResult::default()],
+            fees: FeeBreakdown::default(),
+        }
+        .with(block),
+    );
+    let certificate = env.make_certificate(value);
+    let info = env
+        .worker()
+        .fully_handle_certificate_with_notifications(certificate, &())
+        .await?
+        .info;
+    assert_eq!(info.next_block_height, BlockHeight::from(1));
+    Ok(())
+}
+
+#[test_case(MemoryStorageBuilder::default(); "memory")]
+#[cfg_attr(feature = "rocksdb", test_case(RocksDbStorageBuilder::new().await; "rocks_db"))]
+#[cfg_attr(feature = "dynamodb", test_case(DynamoDbStorageBuilder::default(); "dynamo_db"))]
+#[cfg_attr(feature = "scylladb", test_case(ScyllaDbStorageBuilder::default(); "scylla_db"))]
+#[test_log::test(tokio::test)]
+async fn test_handle_certificate_wrong_owner<B>(mut storage_builder: B) -> anyhow::Result<()>
+where
+    B: StorageBuilder,
+{
+    let sender_key_pair = AccountSecretKey::generate();
+    let chain_key_pair = AccountSecretKey::generate();
+    let mut env = TestEnvironment::new(storage_builder.build().await?, false, false).await;
+    let chain_2_desc = env
+        .add_root_chain(2, chain_key_pair.public().into(), Amount::from_tokens(5))
         .await;
     let chain_2 = chain_2_desc.id();
-    let chain_1_desc = dummy_chain_description(1);
-    let chain_1 = chain_1_desc.id();
     let certificate = env
-        .make_simple_transfer_certificate(
-            chain_1_desc,
+        .make_transfer_certificate_for_epoch(
+            chain_2_desc.clone(),
             sender_key_pair.public(),
-            chain_2,
-            Amount::from_tokens(10),
+            chain_key_pair.public().into(),
+            AccountOwner::CHAIN,
+            Recipient::chain(chain_2),
+            Amount::from_tokens(5),
             Vec::new(),
+            Epoch::ZERO,
             Amount::ZERO,
+            BTreeMap::new(),
             vec![],
         )
         .await;
-    env.worker()
-        .handle_cross_chain_request(update_recipient_direct(chain_2, &certificate.clone()))
-        .await?;
-    let chain = env.worker().chain_state_view(chain_2).await?;
-    assert!(chain.is_active());
-    assert_eq!(Amount::ONE, *chain.execution_state.system.balance.get());
-    assert_eq!(BlockHeight::ZERO, chain.tip_state.get().next_block_height);
-    let inbox = chain
-        .inboxes
-        .try_load_entry(&chain_1)
-        .await?
-        .expect("Missing inbox for `ChainId::root(1)` in `chain_2`");
-    assert_eq!(BlockHeight::from(1), inbox.next_block_height_to_receive()?);
+    // This fails because `make_simple_transfer_certificate` uses `sender_key_pair.public()` to
+    // compute the hash of the execution state.
     assert_matches!(
-        inbox
-            .added_bundles
-            .front()
-            .await?
-            .unwrap(),
-        MessageBundle {
-            certificate_hash,
-            height,
-            timestamp,
-            transaction_index: 0,
-            messages,
-        } if certificate_hash == certificate.hash()
-        && height == BlockHeight::ZERO
-        && timestamp == Timestamp::from(0)
-        && matches!(messages[..], [PostedMessage {
-            authenticated_signer: None,
-            grant: Amount::ZERO,
-            refund_grant_to: None,
-            kind: MessageKind::Tracked,
-            index: 0,
-            message: Message::System(SystemMessage::Credit { amount, .. })
-        }] if amount == Amount::from_tokens(10)),
-        "Unexpected bundle",
+        env.worker()
+            .fully_handle_certificate_with_notifications(certificate, &())
+            .await,
+        Err(WorkerError::IncorrectOutcome { .. })
     );
-    assert_eq!(chain.confirmed_log.count(), 0);
-    assert_eq!(None, chain.tip_state.get().block_hash);
-    assert_eq!(chain.received_log.count(), 1);
     Ok(())
 }
 
@@ -1962,39 +2062,37 @@ where
 #[cfg_attr(feature = "dynamodb", test_case(DynamoDbStorageBuilder::default(); "dynamo_db"))]
 #[cfg_attr(feature = "scylladb", test_case(ScyllaDbStorageBuilder::default(); "scylla_db"))]
 #[test_log::test(tokio::test)]
-async fn test_handle_cross_chain_request_no_recipient_chain<B>(
-    mut storage_builder: B,
-) -> anyhow::Result<()>
+async fn test_handle_certificate_bad_block_height<B>(mut storage_builder: B) -> anyhow::Result<()>
 where
     B: StorageBuilder,
 {
-    let storage = storage_builder.build().await?;
     let sender_key_pair = AccountSecretKey::generate();
-    let mut env = TestEnvironment::new(storage, false, false).await;
+    let mut env = TestEnvironment::new(storage_builder.build().await?, false, false).await;
     let chain_1_desc = env
-        .add_root_chain(1, sender_key_pair.public().into(), Amount::from_tokens(10))
+        .add_root_chain(1, sender_key_pair.public().into(), Amount::from_tokens(5))
         .await;
-    let chain_2 = dummy_chain_description(2).id();
+    let chain_2_desc = env
+        .add_root_chain(2, AccountPublicKey::test_key(2).into(), Amount::ZERO)
+        .await;
+    let chain_2 = chain_2_desc.id();
     let certificate = env
         .make_simple_transfer_certificate(
-            chain_1_desc,
+            chain_1_desc.clone(),
             sender_key_pair.public(),
             chain_2,
-            Amount::from_tokens(10),
+            Amount::from_tokens(5),
             Vec::new(),
             Amount::ZERO,
             vec![],
         )
         .await;
-    assert!(env
-        .worker()
-        .handle_cross_chain_request(update_recipient_direct(chain_2, &certificate))
-        .await?
-        .cross_chain_requests
-        .is_empty());
-    let chain = env.worker().chain_state_view(chain_2).await?;
-    // The target chain did not receive the message
-    assert!(chain.inboxes.indices().await?.is_empty());
+    // Replays are ignored.
+    env.worker()
+        .fully_handle_certificate_with_notifications(certificate.clone(), &())
+        .await?;
+    env.worker()
+        .fully_handle_certificate_with_notifications(certificate, &())
+        .await?;
     Ok(())
 }
 
@@ -2003,52 +2101,95 @@ where
 #[cfg_attr(feature = "dynamodb", test_case(DynamoDbStorageBuilder::default(); "dynamo_db"))]
 #[cfg_attr(feature = "scylladb", test_case(ScyllaDbStorageBuilder::default(); "scylla_db"))]
 #[test_log::test(tokio::test)]
-async fn test_handle_cross_chain_request_no_recipient_chain_on_client<B>(
+async fn test_handle_certificate_with_anticipated_incoming_bundle<B>(
     mut storage_builder: B,
 ) -> anyhow::Result<()>
 where
     B: StorageBuilder,
 {
-    let storage = storage_builder.build().await?;
-    let sender_key_pair = AccountSecretKey::generate();
-    let mut env = TestEnvironment::new(storage, true, false).await;
+    let key_pair = AccountSecretKey::generate();
+    let mut env = TestEnvironment::new(storage_builder.build().await?, false, false).await;
     let chain_1_desc = env
-        .add_root_chain(1, sender_key_pair.public().into(), Amount::from_tokens(10))
+        .add_root_chain(1, key_pair.public().into(), Amount::from_tokens(5))
+        .await;
+    let chain_2_desc = env
+        .add_root_chain(2, AccountPublicKey::test_key(2).into(), Amount::ZERO)
+        .await;
+    let chain_3_desc = env
+        .add_root_chain(3, AccountPublicKey::test_key(3).into(), Amount::ZERO)
         .await;
     let chain_1 = chain_1_desc.id();
-    let chain_2 = dummy_chain_description(2).id();
+    let chain_2 = chain_2_desc.id();
+    let chain_3 = chain_3_desc.id();
+
     let certificate = env
         .make_simple_transfer_certificate(
-            chain_1_desc,
-            sender_key_pair.public(),
+            chain_1_desc.clone(),
+            key_pair.public(),
             chain_2,
-            Amount::from_tokens(10),
-            Vec::new(),
+            Amount::from_tokens(1000),
+            vec![IncomingBundle {
+                origin: chain_3,
+                bundle: MessageBundle {
+                    certificate_hash: CryptoHash::test_hash("certificate"),
+                    height: BlockHeight::ZERO,
+                    timestamp: Timestamp::from(0),
+                    transaction_index: 0,
+                    messages: vec![system_credit_message(Amount::from_tokens(995))
+                        .to_posted(0, MessageKind::Tracked)],
+                },
+                action: MessageAction::Accept,
+            }],
             Amount::ZERO,
             vec![],
         )
         .await;
-    // An inactive target chain is created and it acknowledges the message.
-    let actions = env
-        .worker()
-        .handle_cross_chain_request(update_recipient_direct(chain_2, &certificate))
+    env.worker()
+        .fully_handle_certificate_with_notifications(certificate.clone(), &())
         .await?;
-    assert_matches!(
-        actions.cross_chain_requests.as_slice(),
-        &[CrossChainRequest::ConfirmUpdatedRecipient { .. }]
-    );
+    let chain = env.worker().chain_state_view(chain_1).await?;
+    assert!(chain.is_active());
+    assert_eq!(Amount::ZERO, *chain.execution_state.system.balance.get());
     assert_eq!(
-        actions.notifications,
-        vec![Notification {
-            chain_id: chain_2,
-            reason: Reason::NewIncomingBundle {
-                origin: chain_1,
-                height: BlockHeight::ZERO,
-            }
-        }]
+        BlockHeight::from(1),
+        chain.tip_state.get().next_block_height
+    );
+    let inbox = chain
+        .inboxes
+        .try_load_entry(&chain_3)
+        .await?
+        .expect("Missing inbox for `chain_3` in `ChainId::root(1)`");
+    assert_eq!(BlockHeight::ZERO, inbox.next_block_height_to_receive()?);
+    assert_eq!(inbox.added_bundles.count(), 0);
+    assert_matches!(
+        inbox
+            .removed_bundles
+            .front()
+            .await?
+            .unwrap(),
+        MessageBundle {
+            certificate_hash,
+            height,
+            timestamp,
+            transaction_index: 0,
+            messages,
+        } if certificate_hash == CryptoHash::test_hash("certificate")
+            && height == BlockHeight::ZERO
+            && timestamp == Timestamp::from(0)
+            && matches!(messages[..], [PostedMessage {
+                authenticated_signer: None,
+                grant: Amount::ZERO,
+                refund_grant_to: None,
+                kind: MessageKind::Tracked,
+                index: 0,
+                message: Message::System(SystemMessage::Credit { amount, .. }),
+            }] if amount == Amount::from_tokens(995)),
+        "Unexpected bundle",
     );
+    assert_eq!(chain.confirmed_log.count(), 1);
+    assert_eq!(Some(certificate.hash()), chain.tip_state.get().block_hash);
     let chain = env.worker().chain_state_view(chain_2).await?;
-    assert!(!chain.inboxes.indices().await?.is_empty());
+    assert!(chain.is_active());
     Ok(())
 }
 
@@ -2057,158 +2198,57 @@ where
 #[cfg_attr(feature = "dynamodb", test_case(DynamoDbStorageBuilder::default(); "dynamo_db"))]
 #[cfg_attr(feature = "scylladb", test_case(ScyllaDbStorageBuilder::default(); "scylla_db"))]
 #[test_log::test(tokio::test)]
-async fn test_handle_certificate_to_active_recipient<B>(
+async fn test_handle_certificate_receiver_balance_overflow<B>(
     mut storage_builder: B,
 ) -> anyhow::Result<()>
 where
     B: StorageBuilder,
 {
     let sender_key_pair = AccountSecretKey::generate();
-    let recipient_key_pair = AccountSecretKey::generate();
     let mut env = TestEnvironment::new(storage_builder.build().await?, false, false).await;
     let chain_1_desc = env
-        .add_root_chain(1, sender_key_pair.public().into(), Amount::from_tokens(5))
+        .add_root_chain(1, sender_key_pair.public().into(), Amount::ONE)
         .await;
     let chain_2_desc = env
-        .add_root_chain(2, recipient_key_pair.public().into(), Amount::ZERO)
-        .await;
-    let chain_3_desc = env
-        .add_root_chain(3, recipient_key_pair.public().into(), Amount::ZERO)
+        .add_root_chain(2, AccountPublicKey::test_key(2).into(), Amount::MAX)
         .await;
     let chain_1 = chain_1_desc.id();
     let chain_2 = chain_2_desc.id();
-    let chain_3 = chain_3_desc.id();
-    assert_eq!(
-        env.worker()
-            .query_application(chain_1, Query::System(SystemQuery))
-            .await?,
-        QueryOutcome {
-            response: QueryResponse::System(SystemResponse {
-                chain_id: chain_1,
-                balance: Amount::from_tokens(5),
-            }),
-            operations: vec![],
-        }
-    );
-    assert_eq!(
-        env.worker()
-            .query_application(chain_2, Query::System(SystemQuery))
-            .await?,
-        QueryOutcome {
-            response: QueryResponse::System(SystemResponse {
-                chain_id: chain_2,
-                balance: Amount::ZERO,
-            }),
-            operations: vec![],
-        }
-    );
 
     let certificate = env
         .make_simple_transfer_certificate(
             chain_1_desc.clone(),
             sender_key_pair.public(),
             chain_2,
-            Amount::from_tokens(5),
+            Amount::ONE,
             Vec::new(),
             Amount::ZERO,
             vec![],
         )
         .await;
-
-    let info = env
-        .worker()
+    env.worker()
         .fully_handle_certificate_with_notifications(certificate.clone(), &())
-        .await?
-        .info;
-    assert_eq!(chain_1, info.chain_id);
-    assert_eq!(Amount::ZERO, info.chain_balance);
-    assert_eq!(BlockHeight::from(1), info.next_block_height);
-    assert_eq!(Some(certificate.hash()), info.block_hash);
-    assert!(info.manager.pending.is_none());
+        .await?;
+    let new_sender_chain = env.worker().chain_state_view(chain_1).await?;
+    assert!(new_sender_chain.is_active());
     assert_eq!(
-        env.worker()
-            .query_application(chain_1, Query::System(SystemQuery))
-            .await?,
-        QueryOutcome {
-            response: QueryResponse::System(SystemResponse {
-                chain_id: chain_1,
-                balance: Amount::ZERO,
-            }),
-            operations: vec![],
-        }
+        Amount::ZERO,
+        *new_sender_chain.execution_state.system.balance.get()
     );
-
-    // Try to use the money. This requires selecting the incoming message in a next block.
-    let certificate = env
-        .make_simple_transfer_certificate(
-            chain_2_desc.clone(),
-            recipient_key_pair.public(),
-            chain_3,
-            Amount::ONE,
-            vec![IncomingBundle {
-                origin: chain_1,
-                bundle: MessageBundle {
-                    certificate_hash: certificate.hash(),
-                    height: BlockHeight::ZERO,
-                    timestamp: Timestamp::from(0),
-                    transaction_index: 0,
-                    messages: vec![system_credit_message(Amount::from_tokens(5))
-                        .to_posted(0, MessageKind::Tracked)],
-                },
-                action: MessageAction::Accept,
-            }],
-            Amount::from_tokens(4),
-            vec![],
-        )
-        .await;
-    env.worker()
-        .fully_handle_certificate_with_notifications(certificate.clone(), &())
-        .await?;
-
     assert_eq!(
-        env.worker()
-            .query_application(chain_2, Query::System(SystemQuery))
-            .await?,
-        QueryOutcome {
-            response: QueryResponse::System(SystemResponse {
-                chain_id: chain_2,
-                balance: Amount::from_tokens(4),
-            }),
-            operations: vec![],
-        }
+        BlockHeight::from(1),
+        new_sender_chain.tip_state.get().next_block_height
     );
-
-    {
-        let recipient_chain = env.worker().chain_state_view(chain_2).await?;
-        assert!(recipient_chain.is_active());
-        assert_eq!(
-            *recipient_chain.execution_state.system.balance.get(),
-            Amount::from_tokens(4)
-        );
-        let ownership = &recipient_chain.manager.ownership.get();
-        assert!(
-            ownership
-                .owners
-                .contains_key(&recipient_key_pair.public().into())
-                && ownership.super_owners.is_empty()
-                && ownership.owners.len() == 1
-        );
-        assert_eq!(recipient_chain.confirmed_log.count(), 1);
-        assert_eq!(
-            recipient_chain.tip_state.get().block_hash,
-            Some(certificate.hash())
-        );
-        assert_eq!(recipient_chain.received_log.count(), 1);
-    }
-    let query = ChainInfoQuery::new(chain_2).with_received_log_excluding_first_n(0);
-    let (response, _actions) = env.worker().handle_chain_info_query(query).await?;
-    assert_eq!(response.info.requested_received_log.len(), 1);
+    assert_eq!(new_sender_chain.confirmed_log.count(), 1);
     assert_eq!(
-        response.info.requested_received_log[0],
-        ChainAndHeight {
-            chain_id: chain_1,
-            height: BlockHeight::ZERO
-        }
+        Some(certificate.hash()),
+        new_sender_chain.tip_state.get().block_hash
+    );
+    let new_recipient_chain = env.worker().chain_state_view(chain_2).await?;
+    assert!(new_recipient_chain.is_active());
+    assert_eq!(
+        Amount::MAX,
+        *new_recipient_chain.execution_state.system.balance.get()
     );
     Ok(())
 }
@@ -2218,41 +2258,69 @@ where
 #[cfg_attr(feature = "dynamodb", test_case(DynamoDbStorageBuilder::default(); "dynamo_db"))]
 #[cfg_attr(feature = "scylladb", test_case(ScyllaDbStorageBuilder::default(); "scylla_db"))]
 #[test_log::test(tokio::test)]
-async fn test_handle_certificate_to_inactive_recipient<B>(
+async fn test_handle_certificate_receiver_equal_sender<B>(
     mut storage_builder: B,
 ) -> anyhow::Result<()>
 where
     B: StorageBuilder,
 {
-    let sender_key_pair = AccountSecretKey::generate();
-    let mut env = TestEnvironment::new(storage_builder.build().await?, false, false).await;
-    let chain_1_desc = env
-        .add_root_chain(1, sender_key_pair.public().into(), Amount::from_tokens(5))
-        .await;
+    let storage = storage_builder.build().await?;
+    let key_pair = AccountSecretKey::generate();
+    let owner = key_pair.public().into();
+    let mut env = TestEnvironment::new(storage, false, false).await;
+    let chain_1_desc = env.add_root_chain(1, owner, Amount::ONE).await;
     let chain_1 = chain_1_desc.id();
-    let chain_2 = dummy_chain_description(2).id();
+
     let certificate = env
         .make_simple_transfer_certificate(
             chain_1_desc.clone(),
-            sender_key_pair.public(),
-            chain_2, // the recipient chain does not exist
-            Amount::from_tokens(5),
+            key_pair.public(),
+            chain_1,
+            Amount::ONE,
             Vec::new(),
             Amount::ZERO,
             vec![],
         )
         .await;
-
-    let info = env
-        .worker()
+    env.worker()
         .fully_handle_certificate_with_notifications(certificate.clone(), &())
+        .await?;
+    let chain = env.worker().chain_state_view(chain_1).await?;
+    assert!(chain.is_active());
+    assert_eq!(Amount::ZERO, *chain.execution_state.system.balance.get());
+    let inbox = chain
+        .inboxes
+        .try_load_entry(&chain_1)
         .await?
-        .info;
-    assert_eq!(chain_1, info.chain_id);
-    assert_eq!(Amount::ZERO, info.chain_balance);
-    assert_eq!(BlockHeight::from(1), info.next_block_height);
-    assert_eq!(Some(certificate.hash()), info.block_hash);
-    assert!(info.manager.pending.is_none());
+        .expect("Missing inbox for `ChainId::root(1)` in `ChainId::root(1)`");
+    assert_eq!(BlockHeight::from(1), inbox.next_block_height_to_receive()?);
+    assert_matches!(
+        inbox.added_bundles.front().await?.unwrap(),
+        MessageBundle {
+            certificate_hash,
+            height,
+            timestamp,
+            transaction_index: 0,
+            messages,
+        } if certificate_hash == certificate.hash()
+        && height == BlockHeight::ZERO
+        && timestamp == Timestamp::from(0)
+        && matches!(messages[..], [PostedMessage {
+            authenticated_signer: None,
+            grant: Amount::ZERO,
+            refund_grant_to: None,
+            kind: MessageKind::Tracked,
+            index: 0,
+            message: Message::System(SystemMessage::Credit { amount, .. })
+        }] if amount == Amount::ONE),
+        "Unexpected bundle",
+    );
+    assert_eq!(
+        BlockHeight::from(1),
+        chain.tip_state.get().next_block_height
+    );
+    assert_eq!(chain.confirmed_log.count(), 1);
+    assert_eq!(Some(certificate.hash()), chain.tip_state.get().block_hash);
     Ok(())
 }
 
@@ -2261,238 +2329,306 @@ where
 #[cfg_attr(feature = "dynamodb", test_case(DynamoDbStorageBuilder::default(); "dynamo_db"))]
 #[cfg_attr(feature = "scylladb", test_case(ScyllaDbStorageBuilder::default(); "scylla_db"))]
 #[test_log::test(tokio::test)]
-async fn test_handle_certificate_with_rejected_transfer<B>(
-    mut storage_builder: B,
-) -> anyhow::Result<()>
+async fn test_handle_cross_chain_request<B>(mut storage_builder: B) -> anyhow::Result<()>
 where
     B: StorageBuilder,
 {
     let sender_key_pair = AccountSecretKey::generate();
-    let sender_pubkey = sender_key_pair.public();
-    let sender = AccountOwner::from(sender_pubkey);
-
-    let recipient_key_pair = AccountSecretKey::generate();
-    let recipient_pubkey = recipient_key_pair.public();
-    let recipient = AccountOwner::from(recipient_pubkey);
-
     let mut env = TestEnvironment::new(storage_builder.build().await?, false, false).await;
-    let chain_1_desc = env
-        .add_root_chain(1, sender_key_pair.public().into(), Amount::from_tokens(6))
-        .await;
     let chain_2_desc = env
-        .add_root_chain(2, recipient_key_pair.public().into(), Amount::ZERO)
+        .add_root_chain(2, AccountPublicKey::test_key(2).into(), Amount::ONE)
         .await;
-    let chain_1 = chain_1_desc.id();
     let chain_2 = chain_2_desc.id();
-
-    let sender_account = Account {
-        chain_id: chain_1,
-        owner: sender,
-    };
-    let recipient_account = Account {
-        chain_id: chain_2,
-        owner: recipient,
-    };
-
-    // First move the money from the public balance to the sender's account.
-    // This takes two certificates (sending, receiving) sadly.
-    let certificate00 = env
-        .make_transfer_certificate(
-            chain_1_desc.clone(),
-            sender_pubkey,
-            sender,
-            AccountOwner::CHAIN,
-            Recipient::Account(sender_account),
-            Amount::from_tokens(5),
+    let chain_1_desc = dummy_chain_description(1);
+    let chain_1 = chain_1_desc.id();
+    let certificate = env
+        .make_simple_transfer_certificate(
+            chain_1_desc,
+            sender_key_pair.public(),
+            chain_2,
+            Amount::from_tokens(10),
             Vec::new(),
-            Amount::ONE,
-            BTreeMap::new(),
+            Amount::ZERO,
             vec![],
         )
         .await;
-
     env.worker()
-        .fully_handle_certificate_with_notifications(certificate00.clone(), &())
+        .handle_cross_chain_request(update_recipient_direct(chain_2, &certificate.clone()))
         .await?;
+    let chain = env.worker().chain_state_view(chain_2).await?;
+    assert!(chain.is_active());
+    assert_eq!(Amount::ONE, *chain.execution_state.system.balance.get());
+    assert_eq!(BlockHeight::ZERO, chain.tip_state.get().next_block_height);
+    let inbox = chain
+        .inboxes
+        .try_load_entry(&chain_1)
+        .await?
+        .expect("Missing inbox for `ChainId::root(1)` in `chain_2`");
+    assert_eq!(BlockHeight::from(1), inbox.next_block_height_to_receive()?);
+    assert_matches!(
+        inbox
+            .added_bundles
+            .front()
+            .await?
+            .unwrap(),
+        MessageBundle {
+            certificate_hash,
+            height,
+            timestamp,
+            transaction_index: 0,
+            messages,
+        } if certificate_hash == certificate.hash()
+        && height == BlockHeight::ZERO
+        && timestamp == Timestamp::from(0)
+        && matches!(messages[..], [PostedMessage {
+            authenticated_signer: None,
+            grant: Amount::ZERO,
+            refund_grant_to: None,
+            kind: MessageKind::Tracked,
+            index: 0,
+            message: Message::System(SystemMessage::Credit { amount, .. })
+        }] if amount == Amount::from_tokens(10)),
+        "Unexpected bundle",
+    );
+    assert_eq!(chain.confirmed_log.count(), 0);
+    assert_eq!(None, chain.tip_state.get().block_hash);
+    assert_eq!(chain.received_log.count(), 1);
+    Ok(())
+}
 
-    let certificate01 = env
-        .make_transfer_certificate(
-            chain_1_desc.clone(),
-            sender_pubkey,
-            sender,
-            AccountOwner::CHAIN,
-            Recipient::Burn,
-            Amount::ONE,
-            vec![IncomingBundle {
-                origin: chain_1,
-                bundle: MessageBundle {
-                    certificate_hash: certificate00.hash(),
-                    height: BlockHeight::from(0),
-                    timestamp: Timestamp::from(0),
-                    transaction_index: 0,
-                    messages: vec![Message::System(SystemMessage::Credit {
-                        source: AccountOwner::CHAIN,
-                        target: sender,
-                        amount: Amount::from_tokens(5),
-                    })
-                    .to_posted(0, MessageKind::Tracked)],
-                },
-                action: MessageAction::Accept,
-            }],
-            Amount::ZERO,
-            BTreeMap::from_iter([(sender, Amount::from_tokens(5))]),
-            vec![&certificate00],
-        )
-        .await;
-
-    env.worker()
-        .fully_handle_certificate_with_notifications(certificate01.clone(), &())
-        .await?;
+#[test_case(MemoryStorageBuilder::default(); "memory")]
+#[cfg_attr(feature = "rocksdb", test_case(RocksDbStorageBuilder::new().await; "rocks_db"))]
+#[cfg_attr(feature = "dynamodb", test_case(DynamoDbStorageBuilder::default(); "dynamo_db"))]
+#[cfg_attr(feature = "scylladb", test_case(ScyllaDbStorageBuilder::default(); "scylla_db"))]
+#[test_log::test(tokio::test)]
+async fn test_received_log_pagination<B>(mut storage_builder: B) -> anyhow::Result<()>
+where
+    B: StorageBuilder,
+{
+    let sender_key_pair = AccountSecretKey::generate();
+    let mut env = TestEnvironment::new(storage_builder.build().await?, false, false).await;
+    let chain_2 = env
+        .add_root_chain(2, AccountPublicKey::test_key(2).into(), Amount::ONE)
+        .await
+        .id();
+    let mut expected_log = Vec::new();
+    for index in [1, 3, 4] {
+        let sender_desc = dummy_chain_description(index);
+        expected_log.push(ChainAndHeight {
+            chain_id: sender_desc.id(),
+            height: BlockHeight::ZERO,
+        });
+        let certificate = env
+            .make_simple_transfer_certificate(
+                sender_desc,
+                sender_key_pair.public(),
+                chain_2,
+                Amount::ONE,
+                Vec::new(),
+                Amount::ZERO,
+                vec![],
+            )
+            .await;
+        env.worker()
+            .handle_cross_chain_request(update_recipient_direct(chain_2, &certificate))
+            .await?;
+    }
 
-    {
-        let chain = env.worker.chain_state_view(chain_1).await?;
-        assert!(chain.is_active());
-        chain.validate_incoming_bundles().await?;
+    let mut log = Vec::new();
+    let mut cursor = 0;
+    loop {
+        let query = ChainInfoQuery::new(chain_2).with_received_log_in_range(LogRange {
+            start: cursor,
+            limit: Some(2),
+        });
+        let (response, _) = env.worker().handle_chain_info_query(query).await?;
+        assert_eq!(response.info.count_received_log, 3);
+        assert!(response.info.requested_received_log.len() <= 2);
+        log.extend(response.info.requested_received_log);
+        cursor = response.info.next_received_log_cursor.unwrap();
+        if cursor == response.info.count_received_log as u64 {
+            break;
+        }
     }
+    assert_eq!(log, expected_log);
 
-    // Then, make two transfers to the recipient.
-    let certificate1 = env
-        .make_transfer_certificate(
-            chain_1_desc.clone(),
-            sender_pubkey,
-            sender,
-            sender,
-            Recipient::Account(recipient_account),
-            Amount::from_tokens(3),
-            Vec::new(),
-            Amount::ZERO,
-            BTreeMap::from_iter([(sender, Amount::from_tokens(2))]),
-            vec![&certificate01, &certificate00],
-        )
-        .await;
+    // The unbounded query also returns a cursor.
+    let query = ChainInfoQuery::new(chain_2).with_received_log_excluding_first_n(1);
+    let (response, _) = env.worker().handle_chain_info_query(query).await?;
+    assert_eq!(response.info.requested_received_log, expected_log[1..]);
+    assert_eq!(response.info.next_received_log_cursor, Some(3));
 
-    env.worker()
-        .fully_handle_certificate_with_notifications(certificate1.clone(), &())
-        .await?;
+    // Starting past the end returns nothing and the same cursor.
+    let query = ChainInfoQuery::new(chain_2).with_received_log_in_range(LogRange {
+        start: 5,
+        limit: Some(2),
+    });
+    let (response, _) = env.worker().handle_chain_info_query(query).await?;
+    assert!(response.info.requested_received_log.is_empty());
+    assert_eq!(response.info.next_received_log_cursor, Some(5));
+    Ok(())
+}
 
-    let certificate2 = env
-        .make_transfer_certificate(
-            chain_1_desc.clone(),
-            sender_pubkey,
-            sender,
-            sender,
-            Recipient::Account(recipient_account),
-            Amount::from_tokens(2),
-            Vec::new(),
-            Amount::ZERO,
-            BTreeMap::new(),
-            vec![&certificate1, &certificate01, &certificate00],
-        )
-        .await;
+#[test_case(MemoryStorageBuilder::default(); "memory")]
+#[cfg_attr(feature = "rocksdb", test_case(RocksDbStorageBuilder::new().await; "rocks_db"))]
+#[cfg_attr(feature = "dynamodb", test_case(DynamoDbStorageBuilder::default(); "dynamo_db"))]
+#[cfg_attr(feature = "scylladb", test_case(ScyllaDbStorageBuilder::default(); "scylla_db"))]
+#[test_log::test(tokio::test)]
+async fn test_chain_info_fields_and_byte_budget<B>(mut storage_builder: B) -> anyhow::Result<()>
+where
+    B: StorageBuilder,
+{
+    let sender_key_pair = AccountSecretKey::generate();
+    let mut env = TestEnvironment::new(storage_builder.build().await?, false, false).await;
+    let chain_2 = env
+        .add_root_chain(2, AccountPublicKey::test_key(2).into(), Amount::ONE)
+        .await
+        .id();
+    for index in [1, 3, 4] {
+        let certificate = env
+            .make_simple_transfer_certificate(
+                dummy_chain_description(index),
+                sender_key_pair.public(),
+                chain_2,
+                Amount::ONE,
+                Vec::new(),
+                Amount::ZERO,
+                vec![],
+            )
+            .await;
+        env.worker()
+            .handle_cross_chain_request(update_recipient_direct(chain_2, &certificate))
+            .await?;
+    }
+    let query = ChainInfoQuery::new(chain_2).with_received_log_excluding_first_n(0);
+    let (response, _) = env.worker().handle_chain_info_query(query.clone()).await?;
+    let full_info = response.info;
+    assert!(full_info.description.is_some());
+    assert_eq!(full_info.requested_received_log.len(), 3);
+    assert!(full_info.truncated_fields.is_empty());
 
-    env.worker()
-        .fully_handle_certificate_with_notifications(certificate2.clone(), &())
+    // Fields that were not selected are left out, without being reported as truncated.
+    let (response, _) = env
+        .worker()
+        .handle_chain_info_query(query.clone().with_fields([ChainInfoField::ReceivedLog]))
         .await?;
+    assert!(response.info.description.is_none());
+    assert_eq!(
+        response.info.requested_received_log,
+        full_info.requested_received_log
+    );
+    assert!(response.info.truncated_fields.is_empty());
 
-    // Reject the first transfer and try to use the money of the second one.
-    let certificate = env
-        .make_transfer_certificate(
-            chain_2_desc.clone(),
-            recipient_pubkey,
-            recipient,
-            recipient,
-            Recipient::Burn,
-            Amount::ONE,
-            vec![
-                IncomingBundle {
-                    origin: chain_1,
-                    bundle: MessageBundle {
-                        certificate_hash: certificate1.hash(),
-                        height: BlockHeight::from(2),
-                        timestamp: Timestamp::from(0),
-                        transaction_index: 0,
-                        messages: vec![Message::System(SystemMessage::Credit {
-                            source: sender,
-                            target: recipient,
-                            amount: Amount::from_tokens(3),
-                        })
-                        .to_posted(0, MessageKind::Tracked)],
-                    },
-                    action: MessageAction::Reject,
-                },
-                IncomingBundle {
-                    origin: chain_1,
-                    bundle: MessageBundle {
-                        certificate_hash: certificate2.hash(),
-                        height: BlockHeight::from(3),
-                        timestamp: Timestamp::from(0),
-                        transaction_index: 0,
-                        messages: vec![Message::System(SystemMessage::Credit {
-                            source: sender,
-                            target: recipient,
-                            amount: Amount::from_tokens(2),
-                        })
-                        .to_posted(0, MessageKind::Tracked)],
-                    },
-                    action: MessageAction::Accept,
-                },
-            ],
-            Amount::ZERO,
-            BTreeMap::from_iter([(recipient, Amount::from_tokens(1))]),
-            vec![],
-        )
-        .await;
+    // A budget just below the full size cuts the last entry of the received log, and the
+    // cursor points at it.
+    let full_size = bcs::serialized_size(&*full_info)? as u64;
+    let (response, _) = env
+        .worker()
+        .handle_chain_info_query(query.clone().with_max_response_bytes(full_size - 1))
+        .await?;
+    assert!(bcs::serialized_size(&*response.info)? as u64 <= full_size - 1);
+    assert_eq!(
+        response.info.requested_received_log,
+        full_info.requested_received_log[..2]
+    );
+    assert_eq!(response.info.next_received_log_cursor, Some(2));
+    assert_eq!(
+        response.info.truncated_fields,
+        BTreeSet::from([ChainInfoField::ReceivedLog])
+    );
+    assert!(response.info.description.is_some());
 
-    env.worker()
-        .fully_handle_certificate_with_notifications(certificate.clone(), &())
+    // A budget that is too small leaves out everything that can be left out.
+    let (response, _) = env
+        .worker()
+        .handle_chain_info_query(query.with_max_response_bytes(0))
         .await?;
+    assert!(response.info.requested_received_log.is_empty());
+    assert_eq!(response.info.next_received_log_cursor, Some(0));
+    assert!(response.info.description.is_none());
+    assert!(response.info.truncated_fields.is_superset(&BTreeSet::from([
+        ChainInfoField::ReceivedLog,
+        ChainInfoField::Description
+    ])));
+    Ok(())
+}
+
+#[test_case(MemoryStorageBuilder::default(); "memory")]
+#[cfg_attr(feature = "rocksdb", test_case(RocksDbStorageBuilder::new().await; "rocks_db"))]
+#[cfg_attr(feature = "dynamodb", test_case(DynamoDbStorageBuilder::default(); "dynamo_db"))]
+#[cfg_attr(feature = "scylladb", test_case(ScyllaDbStorageBuilder::default(); "scylla_db"))]
+#[test_log::test(tokio::test)]
+async fn test_state_inclusion_proof_query<B>(mut storage_builder: B) -> anyhow::Result<()>
+where
+    B: StorageBuilder,
+{
+    let storage = storage_builder.build().await?;
+    let mut env = TestEnvironment::new(storage.clone(), false, false).await;
+    let chain_id = env
+        .add_root_chain(1, AccountPublicKey::test_key(1).into(), Amount::ONE)
+        .await
+        .id();
+    let other_chain_id = env
+        .add_root_chain(2, AccountPublicKey::test_key(2).into(), Amount::ONE)
+        .await
+        .id();
+    let application_id = ApplicationId::new(CryptoHash::test_hash("application"));
+
+    // Without Merkle state hashes, values can't be proven.
+    let query =
+        ChainInfoQuery::new(other_chain_id).with_state_inclusion_proof(application_id, vec![1]);
+    assert_matches!(
+        env.worker().handle_chain_info_query(query).await,
+        Err(WorkerError::NoMerkleStateHash(id)) if id == other_chain_id
+    );
 
+    // Enable Merkle state hashes on the first chain, and store some values.
     {
-        let chain = env.worker().chain_state_view(chain_2).await?;
-        assert!(chain.is_active());
-        chain.validate_incoming_bundles().await?;
+        let mut chain = storage.load_chain(chain_id).await?;
+        let execution_state = &mut chain.execution_state;
+        let mut committees = execution_state.system.committees.get().clone();
+        for committee in committees.values_mut() {
+            committee.policy_mut().merkle_state_hash = true;
+        }
+        execution_state.system.committees.set(committees);
+        let mut application_state = execution_state
+            .users
+            .try_load_entry_mut(&application_id)
+            .await?;
+        for key in 0..10u8 {
+            application_state.insert(vec![key], vec![key; 3]).await?;
+        }
+        drop(application_state);
+        let state_hash = execution_state.state_hash().await?;
+        chain.execution_state_hash.set(Some(state_hash));
+        chain.save().await?;
     }
 
-    // Process the bounced message and try to use the refund.
-    let certificate3 = env
-        .make_transfer_certificate(
-            chain_1_desc.clone(),
-            sender_pubkey,
-            sender,
-            sender,
-            Recipient::Burn,
-            Amount::from_tokens(3),
-            vec![IncomingBundle {
-                origin: chain_2,
-                bundle: MessageBundle {
-                    certificate_hash: certificate.hash(),
-                    height: BlockHeight::from(0),
-                    timestamp: Timestamp::from(0),
-                    transaction_index: 0,
-                    messages: vec![Message::System(SystemMessage::Credit {
-                        source: sender,
-                        target: recipient,
-                        amount: Amount::from_tokens(3),
-                    })
-                    .to_posted(0, MessageKind::Bouncing)],
-                },
-                action: MessageAction::Accept,
-            }],
-            Amount::ZERO,
-            BTreeMap::new(),
-            vec![&certificate2, &certificate1, &certificate01, &certificate00],
-        )
-        .await;
+    let query = ChainInfoQuery::new(chain_id).with_state_inclusion_proof(application_id, vec![1]);
+    let (response, _) = env.worker().handle_chain_info_query(query).await?;
+    let state_hash = response.info.state_hash.unwrap();
+    let (value, proof) = response.info.requested_state_inclusion_proof.unwrap();
+    assert_eq!(value, vec![1; 3]);
+    assert!(verify_state_inclusion(
+        state_hash,
+        application_id,
+        &[1],
+        &value,
+        &proof
+    ));
+    assert!(!verify_state_inclusion(
+        state_hash,
+        application_id,
+        &[2],
+        &value,
+        &proof
+    ));
 
-    env.worker()
-        .fully_handle_certificate_with_notifications(certificate3.clone(), &())
+    let proof = env
+        .worker()
+        .state_inclusion_proof(chain_id, application_id, vec![10])
         .await?;
-
-    {
-        let chain = env.worker.chain_state_view(chain_1).await?;
-        assert!(chain.is_active());
-        chain.validate_incoming_bundles().await?;
-    }
+    assert!(proof.is_none());
     Ok(())
 }
 
@@ -2501,218 +2637,1922 @@ where
 #[cfg_attr(feature = "dynamodb", test_case(DynamoDbStorageBuilder::default(); "dynamo_db"))]
 #[cfg_attr(feature = "scylladb", test_case(ScyllaDbStorageBuilder::default(); "scylla_db"))]
 #[test_log::test(tokio::test)]
-async fn run_test_chain_creation_with_committee_creation<B>(
+async fn test_handle_cross_chain_request_with_inbox_quotas<B>(
     mut storage_builder: B,
 ) -> anyhow::Result<()>
 where
     B: StorageBuilder,
 {
-    let storage = storage_builder.build().await?;
-    let mut env =
-        TestEnvironment::new_with_amount(storage.clone(), false, false, Amount::from_tokens(2))
-            .await;
-    let mut committees = BTreeMap::new();
-    let committee = env.committee().clone();
-    committees.insert(Epoch::ZERO, committee.clone());
-    let admin_id = env.admin_id();
-    // Have the admin chain create a user chain.
-    let user_description = env
-        .add_child_chain(admin_id, env.admin_public_key().into(), Amount::ZERO)
-        .await;
-    let user_id = user_description.id();
-    let certificate0 = env.make_certificate(ConfirmedBlock::new(
-        BlockExecutionOutcome {
-            messages: vec![vec![]],
-            previous_message_blocks: BTreeMap::new(),
-            previous_event_blocks: BTreeMap::new(),
-            events: vec![Vec::new()],
-            blobs: vec![vec![Blob::new_chain_description(&user_description)]],
-            state_hash: env.system_execution_state(&admin_id).into_hash().await,
-            oracle_responses: vec![Vec::new()],
-            operation_results: vec![OperationResult::default()],
-        }
-        .with(
-            make_first_block(admin_id)
-                .with_operation(SystemOperation::OpenChain(OpenChainConfig {
-                    ownership: ChainOwnership::single(env.admin_public_key().into()),
-                    balance: Amount::ZERO,
-                    application_permissions: Default::default(),
-                }))
-                .with_authenticated_signer(Some(env.admin_public_key().into())),
-        ),
-    ));
-    env.worker()
-        .fully_handle_certificate_with_notifications(certificate0.clone(), &())
-        .await?;
+    let sender_key_pair = AccountSecretKey::generate();
+    let mut env = TestEnvironment::new(storage_builder.build().await?, false, false).await;
+    let chain_2 = env
+        .add_root_chain(2, AccountPublicKey::test_key(2).into(), Amount::ONE)
+        .await
+        .id();
     {
-        let admin_chain = env.worker().chain_state_view(admin_id).await?;
-        assert!(admin_chain.is_active());
-        admin_chain.validate_incoming_bundles().await?;
-        assert_eq!(
-            BlockHeight::from(1),
-            admin_chain.tip_state.get().next_block_height
-        );
-        assert!(admin_chain.outboxes.indices().await?.is_empty());
-        assert_eq!(
-            *admin_chain.execution_state.system.admin_id.get(),
-            Some(admin_id)
-        );
+        let mut chain = env.worker().storage.load_chain(chain_2).await?;
+        chain.execution_state.system.inbox_quotas.set(InboxQuotas {
+            max_pending_bundles: Some(1),
+            max_pending_bytes: None,
+        });
+        chain.save().await?;
     }
-
-    // Create a new committee and transfer money before accepting the subscription.
-    let committees2 = BTreeMap::from_iter([
-        (Epoch::ZERO, committee.clone()),
-        (Epoch::from(1), committee.clone()),
-    ]);
-    let event_id = EventId {
-        chain_id: admin_id,
-        stream_id: StreamId::system(NEW_EPOCH_STREAM_NAME),
-        index: 1,
+    let chain_1_desc = dummy_chain_description(1);
+    let chain_1 = chain_1_desc.id();
+    let certificate0 = env
+        .make_simple_transfer_certificate(
+            chain_1_desc.clone(),
+            sender_key_pair.public(),
+            chain_2,
+            Amount::ONE,
+            Vec::new(),
+            Amount::ZERO,
+            vec![],
+        )
+        .await;
+    let certificate1 = env
+        .make_simple_transfer_certificate(
+            chain_1_desc,
+            sender_key_pair.public(),
+            chain_2,
+            Amount::ONE,
+            Vec::new(),
+            Amount::ZERO,
+            vec![&certificate0],
+        )
+        .await;
+    let request = CrossChainRequest::UpdateRecipient {
+        sender: chain_1,
+        recipient: chain_2,
+        bundles: certificate0
+            .message_bundles_for(chain_2)
+            .chain(certificate1.message_bundles_for(chain_2))
+            .collect(),
     };
-    let committee_blob = Blob::new(BlobContent::new_committee(bcs::to_bytes(&committee)?));
-    // `PublishCommitteeBlob` is tested e.g. in `client_tests::test_change_voting_rights`, so we
-    // just write it directly to storage here for simplicity.
-    storage.write_blob(&committee_blob).await?;
-    let blob_hash = committee_blob.id().hash;
-    let certificate1 = env.make_certificate(ConfirmedBlock::new(
-        BlockExecutionOutcome {
-            messages: vec![
-                vec![],
-                vec![direct_credit_message(user_id, Amount::from_tokens(2))],
-            ],
-            previous_message_blocks: BTreeMap::new(),
-            previous_event_blocks: BTreeMap::new(),
-            events: vec![
-                vec![Event {
-                    stream_id: event_id.stream_id.clone(),
-                    index: event_id.index,
-                    value: bcs::to_bytes(&blob_hash).unwrap(),
-                }],
-                Vec::new(),
-            ],
-            blobs: vec![Vec::new(); 2],
-            state_hash: SystemExecutionState {
-                // The root chain knows both committees at the end.
-                committees: committees2.clone(),
-                used_blobs: BTreeSet::from([committee_blob.id()]),
-                epoch: Epoch::from(1),
-                balance: Amount::ZERO,
-                ..env.system_execution_state(&admin_id)
-            }
-            .into_hash()
-            .await,
-            oracle_responses: vec![vec![OracleResponse::Blob(committee_blob.id())], vec![]],
-            operation_results: vec![OperationResult::default(); 2],
-        }
-        .with(
-            make_child_block(&certificate0.clone().into_value())
-                .with_operation(SystemOperation::Admin(AdminOperation::CreateCommittee {
-                    epoch: Epoch::from(1),
-                    blob_hash,
-                }))
-                .with_simple_transfer(user_id, Amount::from_tokens(2)),
-        ),
-    ));
+
+    // Only the first block fits in the inbox, so only its messages are confirmed.
+    let actions = env
+        .worker()
+        .handle_cross_chain_request(request.clone())
+        .await?;
+    assert_matches!(
+        actions.cross_chain_requests[..],
+        [CrossChainRequest::ConfirmUpdatedRecipient { latest_height, .. }]
+            if latest_height == BlockHeight::ZERO
+    );
+    let chain = env.worker().chain_state_view(chain_2).await?;
+    let inbox = chain.inboxes.try_load_entry(&chain_1).await?.unwrap();
+    assert_eq!(inbox.added_bundles.count(), 1);
+    assert_eq!(BlockHeight::from(1), inbox.next_block_height_to_receive()?);
+    drop(inbox);
+    drop(chain);
+
+    // The sender retries later, but the inbox is still full.
+    let actions = env.worker().handle_cross_chain_request(request).await?;
+    assert!(actions.cross_chain_requests.is_empty());
+    Ok(())
+}
+
+#[test_case(MemoryStorageBuilder::default(); "memory")]
+#[cfg_attr(feature = "rocksdb", test_case(RocksDbStorageBuilder::new().await; "rocks_db"))]
+#[cfg_attr(feature = "dynamodb", test_case(DynamoDbStorageBuilder::default(); "dynamo_db"))]
+#[cfg_attr(feature = "scylladb", test_case(ScyllaDbStorageBuilder::default(); "scylla_db"))]
+#[test_log::test(tokio::test)]
+async fn test_confirm_updated_recipients_in_batch<B>(mut storage_builder: B) -> anyhow::Result<()>
+where
+    B: StorageBuilder,
+{
+    let sender_key_pair = AccountSecretKey::generate();
+    let mut env = TestEnvironment::new(storage_builder.build().await?, false, false).await;
+    let chain_1_desc = env
+        .add_root_chain(1, sender_key_pair.public().into(), Amount::from_tokens(5))
+        .await;
+    let chain_1 = chain_1_desc.id();
+    let chain_2 = env
+        .add_root_chain(2, AccountPublicKey::test_key(2).into(), Amount::ZERO)
+        .await
+        .id();
+    let chain_3 = env
+        .add_root_chain(3, AccountPublicKey::test_key(3).into(), Amount::ZERO)
+        .await
+        .id();
+
+    let certificate0 = env
+        .make_simple_transfer_certificate(
+            chain_1_desc.clone(),
+            sender_key_pair.public(),
+            chain_2,
+            Amount::ONE,
+            Vec::new(),
+            Amount::from_tokens(4),
+            vec![],
+        )
+        .await;
+    let certificate1 = env
+        .make_simple_transfer_certificate(
+            chain_1_desc,
+            sender_key_pair.public(),
+            chain_3,
+            Amount::ONE,
+            Vec::new(),
+            Amount::from_tokens(3),
+            vec![&certificate0],
+        )
+        .await;
     env.worker()
-        .fully_handle_certificate_with_notifications(certificate1.clone(), &())
+        .handle_confirmed_certificate(certificate0, None)
         .await?;
-    {
-        // The child is active and has not migrated yet.
-        let user_chain = env.worker().chain_state_view(user_id).await?;
-        assert!(user_chain.is_active());
-        assert_eq!(
-            BlockHeight::ZERO,
-            user_chain.tip_state.get().next_block_height
-        );
-        assert_eq!(
-            *user_chain.execution_state.system.admin_id.get(),
-            Some(admin_id)
-        );
-        user_chain.validate_incoming_bundles().await?;
-        matches!(
-            &user_chain
-                .inboxes
-                .try_load_entry(&admin_id)
-                .await?
-                .expect("Missing inbox for admin chain in user chain")
-                .added_bundles
-                .read_front(10)
-                .await?[..],
-            [bundle1]
-            if matches!(bundle1.messages[..], [PostedMessage {
-                message: Message::System(SystemMessage::Credit { .. }), ..
-            }])
-        );
-        assert_eq!(user_chain.execution_state.system.committees.get().len(), 1);
-    }
-    // Make the child receive the pending messages.
-    let certificate3 = env.make_certificate(ConfirmedBlock::new(
-        BlockExecutionOutcome {
-            messages: vec![Vec::new(); 2],
-            previous_message_blocks: BTreeMap::new(),
-            previous_event_blocks: BTreeMap::new(),
-            events: vec![Vec::new(); 2],
-            blobs: vec![Vec::new(); 2],
-            state_hash: SystemExecutionState {
-                // Finally the child knows about both committees and has the money.
-                committees: committees2.clone(),
-                balance: Amount::from_tokens(2),
-                used_blobs: BTreeSet::from([committee_blob.id()]),
-                epoch: Epoch::from(1),
-                ..env.system_execution_state(&user_description.id())
-            }
-            .into_hash()
-            .await,
-            oracle_responses: vec![
-                vec![],
-                vec![
-                    OracleResponse::Event(
-                        EventId {
-                            chain_id: admin_id,
-                            stream_id: StreamId::system(NEW_EPOCH_STREAM_NAME),
-                            index: 1,
-                        },
-                        bcs::to_bytes(&blob_hash).unwrap(),
-                    ),
-                    OracleResponse::Blob(committee_blob.id()),
-                ],
-            ],
-            operation_results: vec![OperationResult::default()],
-        }
-        .with(
-            make_first_block(user_id)
-                .with_incoming_bundle(IncomingBundle {
-                    origin: admin_id,
-                    bundle: MessageBundle {
-                        certificate_hash: certificate1.hash(),
-                        height: BlockHeight::from(1),
-                        timestamp: Timestamp::from(0),
-                        transaction_index: 1,
-                        messages: vec![system_credit_message(Amount::from_tokens(2))
-                            .to_posted(0, MessageKind::Tracked)],
-                    },
-                    action: MessageAction::Accept,
-                })
-                .with_operation(SystemOperation::ProcessNewEpoch(Epoch::from(1))),
-        ),
-    ));
+    let (sender, mut delivered) = oneshot::channel();
+    env.worker()
+        .handle_confirmed_certificate(certificate1, Some(sender))
+        .await?;
+    let chain = env.worker().chain_state_view(chain_1).await?;
+    assert_eq!(chain.nonempty_outbox_chain_ids().len(), 2);
+    drop(chain);
+    assert!(delivered.try_recv().is_err());
+
+    env.worker()
+        .confirm_updated_recipients(
+            chain_1,
+            BTreeMap::from([
+                (chain_2, BlockHeight::ZERO),
+                (chain_3, BlockHeight::from(1)),
+            ]),
+        )
+        .await?;
+    let chain = env.worker().chain_state_view(chain_1).await?;
+    assert!(chain.nonempty_outbox_chain_ids().is_empty());
+    assert_eq!(delivered.try_recv(), Ok(()));
+    Ok(())
+}
+
+#[test_case(MemoryStorageBuilder::default(); "memory")]
+#[cfg_attr(feature = "rocksdb", test_case(RocksDbStorageBuilder::new().await; "rocks_db"))]
+#[cfg_attr(feature = "dynamodb", test_case(DynamoDbStorageBuilder::default(); "dynamo_db"))]
+#[cfg_attr(feature = "scylladb", test_case(ScyllaDbStorageBuilder::default(); "scylla_db"))]
+#[test_log::test(tokio::test)]
+async fn test_handle_cross_chain_request_no_recipient_chain<B>(
+    mut storage_builder: B,
+) -> anyhow::Result<()>
+where
+    B: StorageBuilder,
+{
+    let storage = storage_builder.build().await?;
+    let sender_key_pair = AccountSecretKey::generate();
+    let mut env = TestEnvironment::new(storage, false, false).await;
+    let chain_1_desc = env
+        .add_root_chain(1, sender_key_pair.public().into(), Amount::from_tokens(10))
+        .await;
+    let chain_2 = dummy_chain_description(2).id();
+    let certificate = env
+        .make_simple_transfer_certificate(
+            chain_1_desc,
+            sender_key_pair.public(),
+            chain_2,
+            Amount::from_tokens(10),
+            Vec::new(),
+            Amount::ZERO,
+            vec![],
+        )
+        .await;
+    assert!(env
+        .worker()
+        .handle_cross_chain_request(update_recipient_direct(chain_2, &certificate))
+        .await?
+        .cross_chain_requests
+        .is_empty());
+    let chain = env.worker().chain_state_view(chain_2).await?;
+    // The target chain did not receive the message
+    assert!(chain.inboxes.indices().await?.is_empty());
+    Ok(())
+}
+
+#[test_case(MemoryStorageBuilder::default(); "memory")]
+#[cfg_attr(feature = "rocksdb", test_case(RocksDbStorageBuilder::new().await; "rocks_db"))]
+#[cfg_attr(feature = "dynamodb", test_case(DynamoDbStorageBuilder::default(); "dynamo_db"))]
+#[cfg_attr(feature = "scylladb", test_case(ScyllaDbStorageBuilder::default(); "scylla_db"))]
+#[test_log::test(tokio::test)]
+async fn test_handle_cross_chain_request_no_recipient_chain_on_client<B>(
+    mut storage_builder: B,
+) -> anyhow::Result<()>
+where
+    B: StorageBuilder,
+{
+    let storage = storage_builder.build().await?;
+    let sender_key_pair = AccountSecretKey::generate();
+    let mut env = TestEnvironment::new(storage, true, false).await;
+    let chain_1_desc = env
+        .add_root_chain(1, sender_key_pair.public().into(), Amount::from_tokens(10))
+        .await;
+    let chain_1 = chain_1_desc.id();
+    let chain_2 = dummy_chain_description(2).id();
+    let certificate = env
+        .make_simple_transfer_certificate(
+            chain_1_desc,
+            sender_key_pair.public(),
+            chain_2,
+            Amount::from_tokens(10),
+            Vec::new(),
+            Amount::ZERO,
+            vec![],
+        )
+        .await;
+    // An inactive target chain is created and it acknowledges the message.
+    let actions = env
+        .worker()
+        .handle_cross_chain_request(update_recipient_direct(chain_2, &certificate))
+        .await?;
+    assert_matches!(
+        actions.cross_chain_requests.as_slice(),
+        &[CrossChainRequest::ConfirmUpdatedRecipient { .. }]
+    );
+    assert_eq!(
+        actions.notifications,
+        vec![Notification {
+            chain_id: chain_2,
+            reason: Reason::NewIncomingBundle {
+                origin: chain_1,
+                height: BlockHeight::ZERO,
+            }
+        }]
+    );
+    let chain = env.worker().chain_state_view(chain_2).await?;
+    assert!(!chain.inboxes.indices().await?.is_empty());
+    Ok(())
+}
+
+#[test_case(MemoryStorageBuilder::default(); "memory")]
+#[cfg_attr(feature = "rocksdb", test_case(RocksDbStorageBuilder::new().await; "rocks_db"))]
+#[cfg_attr(feature = "dynamodb", test_case(DynamoDbStorageBuilder::default(); "dynamo_db"))]
+#[cfg_attr(feature = "scylladb", test_case(ScyllaDbStorageBuilder::default(); "scylla_db"))]
+#[test_log::test(tokio::test)]
+async fn test_handle_certificate_to_active_recipient<B>(
+    mut storage_builder: B,
+) -> anyhow::Result<()>
+where
+    B: StorageBuilder,
+{
+    let sender_key_pair = AccountSecretKey::generate();
+    let recipient_key_pair = AccountSecretKey::generate();
+    let mut env = TestEnvironment::new(storage_builder.build().await?, false, false).await;
+    let chain_1_desc = env
+        .add_root_chain(1, sender_key_pair.public().into(), Amount::from_tokens(5))
+        .await;
+    let chain_2_desc = env
+        .add_root_chain(2, recipient_key_pair.public().into(), Amount::ZERO)
+        .await;
+    let chain_3_desc = env
+        .add_root_chain(3, recipient_key_pair.public().into(), Amount::ZERO)
+        .await;
+    let chain_1 = chain_1_desc.id();
+    let chain_2 = chain_2_desc.id();
+    let chain_3 = chain_3_desc.id();
+    assert_eq!(
+        env.worker()
+            .query_application(chain_1, Query::System(SystemQuery))
+            .await?,
+        QueryOutcome {
+            response: QueryResponse::System(SystemResponse {
+                chain_id: chain_1,
+                balance: Amount::from_tokens(5),
+            }),
+            operations: vec![],
+            operation_metadata: vec![],
+        }
+    );
+    assert_eq!(
+        env.worker()
+            .query_application(chain_2, Query::System(SystemQuery))
+            .await?,
+        QueryOutcome {
+            response: QueryResponse::System(SystemResponse {
+                chain_id: chain_2,
+                balance: Amount::ZERO,
+            }),
+            operations: vec![],
+            operation_metadata: vec![],
+        }
+    );
+
+    let certificate = env
+        .make_simple_transfer_certificate(
+            chain_1_desc.clone(),
+            sender_key_pair.public(),
+            chain_2,
+            Amount::from_tokens(5),
+            Vec::new(),
+            Amount::ZERO,
+            vec![],
+        )
+        .await;
+
+    let info = env
+        .worker()
+        .fully_handle_certificate_with_notifications(certificate.clone(), &())
+        .await?
+        .info;
+    assert_eq!(chain_1, info.chain_id);
+    assert_eq!(Amount::ZERO, info.chain_balance);
+    assert_eq!(BlockHeight::from(1), info.next_block_height);
+    assert_eq!(Some(certificate.hash()), info.block_hash);
+    assert!(info.manager.pending.is_none());
+    assert_eq!(
+        env.worker()
+            .query_application(chain_1, Query::System(SystemQuery))
+            .await?,
+        QueryOutcome {
+            response: QueryResponse::System(SystemResponse {
+                chain_id: chain_1,
+                balance: Amount::ZERO,
+            }),
+            operations: vec![],
+            operation_metadata: vec![],
+        }
+    );
+
+    // Try to use the money. This requires selecting the incoming message in a next block.
+    let certificate = env
+        .make_simple_transfer_certificate(
+            chain_2_desc.clone(),
+            recipient_key_pair.public(),
+            chain_3,
+            Amount::ONE,
+            vec![IncomingBundle {
+                origin: chain_1,
+                bundle: MessageBundle {
+                    certificate_hash: certificate.hash(),
+                    height: BlockHeight::ZERO,
+                    timestamp: Timestamp::from(0),
+                    transaction_index: 0,
+                    messages: vec![system_credit_message(Amount::from_tokens(5))
+                        .to_posted(0, MessageKind::Tracked)],
+                },
+                action: MessageAction::Accept,
+            }],
+            Amount::from_tokens(4),
+            vec![],
+        )
+        .await;
+    env.worker()
+        .fully_handle_certificate_with_notifications(certificate.clone(), &())
+        .await?;
+
+    assert_eq!(
+        env.worker()
+            .query_application(chain_2, Query::System(SystemQuery))
+            .await?,
+        QueryOutcome {
+            response: QueryResponse::System(SystemResponse {
+                chain_id: chain_2,
+                balance: Amount::from_tokens(4),
+            }),
+            operations: vec![],
+            operation_metadata: vec![],
+        }
+    );
+
+    {
+        let recipient_chain = env.worker().chain_state_view(chain_2).await?;
+        assert!(recipient_chain.is_active());
+        assert_eq!(
+            *recipient_chain.execution_state.system.balance.get(),
+            Amount::from_tokens(4)
+        );
+        let ownership = &recipient_chain.manager.ownership.get();
+        assert!(
+            ownership
+                .owners
+                .contains_key(&recipient_key_pair.public().into())
+                && ownership.super_owners.is_empty()
+                && ownership.owners.len() == 1
+        );
+        assert_eq!(recipient_chain.confirmed_log.count(), 1);
+        assert_eq!(
+            recipient_chain.tip_state.get().block_hash,
+            Some(certificate.hash())
+        );
+        assert_eq!(recipient_chain.received_log.count(), 1);
+    }
+    let query = ChainInfoQuery::new(chain_2).with_received_log_excluding_first_n(0);
+    let (response, _actions) = env.worker().handle_chain_info_query(query).await?;
+    assert_eq!(response.info.requested_received_log.len(), 1);
+    assert_eq!(
+        response.info.requested_received_log[0],
+        ChainAndHeight {
+            chain_id: chain_1,
+            height: BlockHeight::ZERO
+        }
+    );
+    Ok(())
+}
+
+#[test_case(MemoryStorageBuilder::default(); "memory")]
+#[cfg_attr(feature = "rocksdb", test_case(RocksDbStorageBuilder::new().await; "rocks_db"))]
+#[cfg_attr(feature = "dynamodb", test_case(DynamoDbStorageBuilder::default(); "dynamo_db"))]
+#[cfg_attr(feature = "scylladb", test_case(ScyllaDbStorageBuilder::default(); "scylla_db"))]
+#[test_log::test(tokio::test)]
+async fn test_handle_certificate_to_inactive_recipient<B>(
+    mut storage_builder: B,
+) -> anyhow::Result<()>
+where
+    B: StorageBuilder,
+{
+    let sender_key_pair = AccountSecretKey::generate();
+    let mut env = TestEnvironment::new(storage_builder.build().await?, false, false).await;
+    let chain_1_desc = env
+        .add_root_chain(1, sender_key_pair.public().into(), Amount::from_tokens(5))
+        .await;
+    let chain_1 = chain_1_desc.id();
+    let chain_2 = dummy_chain_description(2).id();
+    let certificate = env
+        .make_simple_transfer_certificate(
+            chain_1_desc.clone(),
+            sender_key_pair.public(),
+            chain_2, // the recipient chain does not exist
+            Amount::from_tokens(5),
+            Vec::new(),
+            Amount::ZERO,
+            vec![],
+        )
+        .await;
+
+    let info = env
+        .worker()
+        .fully_handle_certificate_with_notifications(certificate.clone(), &())
+        .await?
+        .info;
+    assert_eq!(chain_1, info.chain_id);
+    assert_eq!(Amount::ZERO, info.chain_balance);
+    assert_eq!(BlockHeight::from(1), info.next_block_height);
+    assert_eq!(Some(certificate.hash()), info.block_hash);
+    assert!(info.manager.pending.is_none());
+    Ok(())
+}
+
+#[test_case(MemoryStorageBuilder::default(); "memory")]
+#[cfg_attr(feature = "rocksdb", test_case(RocksDbStorageBuilder::new().await; "rocks_db"))]
+#[cfg_attr(feature = "dynamodb", test_case(DynamoDbStorageBuilder::default(); "dynamo_db"))]
+#[cfg_attr(feature = "scylladb", test_case(ScyllaDbStorageBuilder::default(); "scylla_db"))]
+#[test_log::test(tokio::test)]
+async fn test_handle_certificate_with_rejected_transfer<B>(
+    mut storage_builder: B,
+) -> anyhow::Result<()>
+where
+    B: StorageBuilder,
+{
+    let sender_key_pair = AccountSecretKey::generate();
+    let sender_pubkey = sender_key_pair.public();
+    let sender = AccountOwner::from(sender_pubkey);
+
+    let recipient_key_pair = AccountSecretKey::generate();
+    let recipient_pubkey = recipient_key_pair.public();
+    let recipient = AccountOwner::from(recipient_pubkey);
+
+    let mut env = TestEnvironment::new(storage_builder.build().await?, false, false).await;
+    let chain_1_desc = env
+        .add_root_chain(1, sender_key_pair.public().into(), Amount::from_tokens(6))
+        .await;
+    let chain_2_desc = env
+        .add_root_chain(2, recipient_key_pair.public().into(), Amount::ZERO)
+        .await;
+    let chain_1 = chain_1_desc.id();
+    let chain_2 = chain_2_desc.id();
+
+    let sender_account = Account {
+        chain_id: chain_1,
+        owner: sender,
+    };
+    let recipient_account = Account {
+        chain_id: chain_2,
+        owner: recipient,
+    };
+
+    // First move the money from the public balance to the sender's account.
+    // This takes two certificates (sending, receiving) sadly.
+    let certificate00 = env
+        .make_transfer_certificate(
+            chain_1_desc.clone(),
+            sender_pubkey,
+            sender,
+            AccountOwner::CHAIN,
+            Recipient::Account(sender_account),
+            Amount::from_tokens(5),
+            Vec::new(),
+            Amount::ONE,
+            BTreeMap::new(),
+            vec![],
+        )
+        .await;
+
+    env.worker()
+        .fully_handle_certificate_with_notifications(certificate00.clone(), &())
+        .await?;
+
+    let certificate01 = env
+        .make_transfer_certificate(
+            chain_1_desc.clone(),
+            sender_pubkey,
+            sender,
+            AccountOwner::CHAIN,
+            Recipient::Burn,
+            Amount::ONE,
+            vec![IncomingBundle {
+                origin: chain_1,
+                bundle: MessageBundle {
+                    certificate_hash: certificate00.hash(),
+                    height: BlockHeight::from(0),
+                    timestamp: Timestamp::from(0),
+                    transaction_index: 0,
+                    messages: vec![Message::System(SystemMessage::Credit {
+                        source: AccountOwner::CHAIN,
+                        target: sender,
+                        amount: Amount::from_tokens(5),
+                    })
+                    .to_posted(0, MessageKind::Tracked)],
+                },
+                action: MessageAction::Accept,
+            }],
+            Amount::ZERO,
+            BTreeMap::from_iter([(sender, Amount::from_tokens(5))]),
+            vec![&certificate00],
+        )
+        .await;
+
+    env.worker()
+        .fully_handle_certificate_with_notifications(certificate01.clone(), &())
+        .await?;
+
+    {
+        let chain = env.worker.chain_state_view(chain_1).await?;
+        assert!(chain.is_active());
+        chain.validate_incoming_bundles().await?;
+    }
+
+    // Then, make two transfers to the recipient.
+    let certificate1 = env
+        .make_transfer_certificate(
+            chain_1_desc.clone(),
+            sender_pubkey,
+            sender,
+            sender,
+            Recipient::Account(recipient_account),
+            Amount::from_tokens(3),
+            Vec::new(),
+            Amount::ZERO,
+            BTreeMap::from_iter([(sender, Amount::from_tokens(2))]),
+            vec![&certificate01, &certificate00],
+        )
+        .await;
+
+    env.worker()
+        .fully_handle_certificate_with_notifications(certificate1.clone(), &())
+        .await?;
+
+    let certificate2 = env
+        .make_transfer_certificate(
+            chain_1_desc.clone(),
+            sender_pubkey,
+            sender,
+            sender,
+            Recipient::Account(recipient_account),
+            Amount::from_tokens(2),
+            Vec::new(),
+            Amount::ZERO,
+            BTreeMap::new(),
+            vec![&certificate1, &certificate01, &certificate00],
+        )
+        .await;
+
+    env.worker()
+        .fully_handle_certificate_with_notifications(certificate2.clone(), &())
+        .await?;
+
+    // Reject the first transfer and try to use the money of the second one.
+    let certificate = env
+        .make_transfer_certificate(
+            chain_2_desc.clone(),
+            recipient_pubkey,
+            recipient,
+            recipient,
+            Recipient::Burn,
+            Amount::ONE,
+            vec![
+                IncomingBundle {
+                    origin: chain_1,
+                    bundle: MessageBundle {
+                        certificate_hash: certificate1.hash(),
+                        height: BlockHeight::from(2),
+                        timestamp: Timestamp::from(0),
+                        transaction_index: 0,
+                        messages: vec![Message::System(SystemMessage::Credit {
+                            source: sender,
+                            target: recipient,
+                            amount: Amount::from_tokens(3),
+                        })
+                        .to_posted(0, MessageKind::Tracked)],
+                    },
+                    action: MessageAction::Reject,
+                },
+                IncomingBundle {
+                    origin: chain_1,
+                    bundle: MessageBundle {
+                        certificate_hash: certificate2.hash(),
+                        height: BlockHeight::from(3),
+                        timestamp: Timestamp::from(0),
+                        transaction_index: 0,
+                        messages: vec![Message::System(SystemMessage::Credit {
+                            source: sender,
+                            target: recipient,
+                            amount: Amount::from_tokens(2),
+                        })
+                        .to_posted(0, MessageKind::Tracked)],
+                    },
+                    action: MessageAction::Accept,
+                },
+            ],
+            Amount::ZERO,
+            BTreeMap::from_iter([(recipient, Amount::from_tokens(1))]),
+            vec![],
+        )
+        .await;
+
+    env.worker()
+        .fully_handle_certificate_with_notifications(certificate.clone(), &())
+        .await?;
+
+    {
+        let chain = env.worker().chain_state_view(chain_2).await?;
+        assert!(chain.is_active());
+        chain.validate_incoming_bundles().await?;
+    }
+
+    // Process the bounced message and try to use the refund.
+    let certificate3 = env
+        .make_transfer_certificate(
+            chain_1_desc.clone(),
+            sender_pubkey,
+            sender,
+            sender,
+            Recipient::Burn,
+            Amount::from_tokens(3),
+            vec![IncomingBundle {
+                origin: chain_2,
+                bundle: MessageBundle {
+                    certificate_hash: certificate.hash(),
+                    height: BlockHeight::from(0),
+                    timestamp: Timestamp::from(0),
+                    transaction_index: 0,
+                    messages: vec![Message::System(SystemMessage::Credit {
+                        source: sender,
+                        target: recipient,
+                        amount: Amount::from_tokens(3),
+                    })
+                    .to_posted(0, MessageKind::Bouncing)],
+                },
+                action: MessageAction::Accept,
+            }],
+            Amount::ZERO,
+            BTreeMap::new(),
+            vec![&certificate2, &certificate1, &certificate01, &certificate00],
+        )
+        .await;
+
+    env.worker()
+        .fully_handle_certificate_with_notifications(certificate3.clone(), &())
+        .await?;
+
+    {
+        let chain = env.worker.chain_state_view(chain_1).await?;
+        assert!(chain.is_active());
+        chain.validate_incoming_bundles().await?;
+    }
+    Ok(())
+}
+
+#[test_case(MemoryStorageBuilder::default(); "memory")]
+#[cfg_attr(feature = "rocksdb", test_case(RocksDbStorageBuilder::new().await; "rocks_db"))]
+#[cfg_attr(feature = "dynamodb", test_case(DynamoDbStorageBuilder::default(); "dynamo_db"))]
+#[cfg_attr(feature = "scylladb", test_case(ScyllaDbStorageBuilder::default(); "scylla_db"))]
+#[test_log::test(tokio::test)]
+async fn run_test_chain_creation_with_committee_creation<B>(
+    mut storage_builder: B,
+) -> anyhow::Result<()>
+where
+    B: StorageBuilder,
+{
+    let storage = storage_builder.build().await?;
+    let mut env =
+        TestEnvironment::new_with_amount(storage.clone(), false, false, Amount::from_tokens(2))
+            .await;
+    let mut committees = BTreeMap::new();
+    let committee = env.committee().clone();
+    committees.insert(Epoch::ZERO, committee.clone());
+    let admin_id = env.admin_id();
+    // Have the admin chain create a user chain.
+    let user_description = env
+        .add_child_chain(admin_id, env.admin_public_key().into(), Amount::ZERO)
+        .await;
+    let user_id = user_description.id();
+    let certificate0 = env.make_certificate(ConfirmedBlock::new(
+        BlockExecutionOutcome {
+            messages: vec![vec![]],
+            previous_message_blocks: BTreeMap::new(),
+            previous_event_blocks: BTreeMap::new(),
+            events: vec![Vec::new()],
+            blobs: vec![vec![Blob::new_chain_description(&user_description)]],
+            state_hash: env.system_execution_state(&admin_id).into_hash().await,
+            oracle_responses: vec![Vec::new()],
+            operation_results: vec![OperationResult::default()],
+            fees: FeeBreakdown::default(),
+        }
+        .with(
+            make_first_block(admin_id)
+                .with_operation(SystemOperation::OpenChain(OpenChainConfig {
+                    ownership: ChainOwnership::single(env.admin_public_key().into()),
+                    balance: Amount::ZERO,
+                    application_permissions: Default::default(),
+                }))
+                .with_authenticated_signer(Some(env.admin_public_key().into())),
+        ),
+    ));
+    env.worker()
+        .fully_handle_certificate_with_notifications(certificate0.clone(), &())
+        .await?;
+    {
+        let admin_chain = env.worker().chain_state_view(admin_id).await?;
+        assert!(admin_chain.is_active());
+        admin_chain.validate_incoming_bundles().await?;
+        assert_eq!(
+            BlockHeight::from(1),
+            admin_chain.tip_state.get().next_block_height
+        );
+        assert!(admin_chain.outboxes.indices().await?.is_empty());
+        assert_eq!(
+            *admin_chain.execution_state.system.admin_id.get(),
+            Some(admin_id)
+        );
+    }
+
+    // Create a new committee and transfer money before accepting the subscription.
+    let committees2 = BTreeMap::from_iter([
+        (Epoch::ZERO, committee.clone()),
+        (Epoch::from(1), committee.clone()),
+    ]);
+    let event_id = EventId {
+        chain_id: admin_id,
+        stream_id: StreamId::system(NEW_EPOCH_STREAM_NAME),
+        index: 1,
+    };
+    let committee_blob = Blob::new(BlobContent::new_committee(bcs::to_bytes(&committee)?));
+    // `PublishCommitteeBlob` is tested e.g. in `client_tests::test_change_voting_rights`, so we
+    // just write it directly to storage here for simplicity.
+    storage.write_blob(&committee_blob).await?;
+    let blob_hash = committee_blob.id().hash;
+    let certificate1 = env.make_certificate(ConfirmedBlock::new(
+        BlockExecutionOutcome {
+            messages: vec![
+                vec![],
+                vec![direct_credit_message(user_id, Amount::from_tokens(2))],
+            ],
+            previous_message_blocks: BTreeMap::new(),
+            previous_event_blocks: BTreeMap::new(),
+            events: vec![
+                vec![Event {
+                    stream_id: event_id.stream_id.clone(),
+                    index: event_id.index,
+                    value: bcs::to_bytes(&blob_hash).unwrap(),
+                }],
+                Vec::new(),
+            ],
+            blobs: vec![Vec::new(); 2],
+            state_hash: SystemExecutionState {
+                // The root chain knows both committees at the end.
+                committees: committees2.clone(),
+                used_blobs: BTreeSet::from([committee_blob.id()]),
+                epoch: Epoch::from(1),
+                balance: Amount::ZERO,
+                ..env.system_execution_state(&admin_id)
+            }
+            .into_hash()
+            .await,
+            oracle_responses: vec![vec![OracleResponse::Blob(committee_blob.id())], vec![]],
+            operation_results: vec![OperationResult::default(); 2],
+            fees: FeeBreakdown::default(),
+        }
+        .with(
+            make_child_block(&certificate0.clone().into_value())
+                .with_operation(SystemOperation::Admin(AdminOperation::CreateCommittee {
+                    epoch: Epoch::from(1),
+                    blob_hash,
+                }))
+                .with_simple_transfer(user_id, Amount::from_tokens(2)),
+        ),
+    ));
+    env.worker()
+        .fully_handle_certificate_with_notifications(certificate1.clone(), &())
+        .await?;
+    {
+        // The child is active and has not migrated yet.
+        let user_chain = env.worker().chain_state_view(user_id).await?;
+        assert!(user_chain.is_active());
+        assert_eq!(
+            BlockHeight::ZERO,
+            user_chain.tip_state.get().next_block_height
+        );
+        assert_eq!(
+            *user_chain.execution_state.system.admin_id.get(),
+            Some(admin_id)
+        );
+        user_chain.validate_incoming_bundles().await?;
+        matches!(
+            &user_chain
+                .inboxes
+                .try_load_entry(&admin_id)
+                .await?
+                .expect("Missing inbox for admin chain in user chain")
+                .added_bundles
+                .read_front(10)
+                .await?[..],
+            [bundle1]
+            if matches!(bundle1.messages[..], [PostedMessage {
+                message: Message::System(SystemMessage::Credit { .. }), ..
+            }])
+        );
+        assert_eq!(user_chain.execution_state.system.committees.get().len(), 1);
+    }
+    // Make the child receive the pending messages.
+    let certificate3 = env.make_certificate(ConfirmedBlock::new(
+        BlockExecutionOutcome {
+            messages: vec![Vec::new(); 2],
+            previous_message_blocks: BTreeMap::new(),
+            previous_event_blocks: BTreeMap::new(),
+            events: vec![Vec::new(); 2],
+            blobs: vec![Vec::new(); 2],
+            state_hash: SystemExecutionState {
+                // Finally the child knows about both committees and has the money.
+                committees: committees2.clone(),
+                balance: Amount::from_tokens(2),
+                used_blobs: BTreeSet::from([committee_blob.id()]),
+                epoch: Epoch::from(1),
+                ..env.system_execution_state(&user_description.id())
+            }
+            .into_hash()
+            .await,
+            oracle_responses: vec![
+                vec![],
+                vec![
+                    OracleResponse::Event(
+                        EventId {
+                            chain_id: admin_id,
+                            stream_id: StreamId::system(NEW_EPOCH_STREAM_NAME),
+                            index: 1,
+                        },
+                        bcs::to_bytes(&blob_hash).unwrap(),
+                    ),
+                    OracleResponse::Blob(committee_blob.id()),
+                ],
+            ],
+            operation_results: vec![OperationResult::default()],
+            fees: FeeBreakdown::default(),
+        }
+        .with(
+            make_first_block(user_id)
+                .with_incoming_bundle(IncomingBundle {
+                    origin: admin_id,
+                    bundle: MessageBundle {
+                        certificate_hash: certificate1.hash(),
+                        height: BlockHeight::from(1),
+                        timestamp: Timestamp::from(0),
+                        transaction_index: 1,
+                        messages: vec![system_credit_message(Amount::from_tokens(2))
+                            .to_posted(0, MessageKind::Tracked)],
+                    },
+                    action: MessageAction::Accept,
+                })
+                .with_operation(SystemOperation::ProcessNewEpoch(Epoch::from(1))),
+        ),
+    ));
+    env.worker()
+        .fully_handle_certificate_with_notifications(certificate3, &())
+        .await?;
+    {
+        let user_chain = env.worker().chain_state_view(user_id).await?;
+        assert!(user_chain.is_active());
+        assert_eq!(
+            BlockHeight::from(1),
+            user_chain.tip_state.get().next_block_height
+        );
+        assert_eq!(
+            *user_chain.execution_state.system.admin_id.get(),
+            Some(admin_id)
+        );
+        assert_eq!(user_chain.execution_state.system.committees.get().len(), 2);
+        user_chain.validate_incoming_bundles().await?;
+        Ok(())
+    }
+}
+
+#[test_case(MemoryStorageBuilder::default(); "memory")]
+#[cfg_attr(feature = "rocksdb", test_case(RocksDbStorageBuilder::new().await; "rocks_db"))]
+#[cfg_attr(feature = "dynamodb", test_case(DynamoDbStorageBuilder::default(); "dynamo_db"))]
+#[cfg_attr(feature = "scylladb", test_case(ScyllaDbStorageBuilder::default(); "scylla_db"))]
+#[test_log::test(tokio::test)]
+async fn test_transfers_and_committee_creation<B>(mut storage_builder: B) -> anyhow::Result<()>
+where
+    B: StorageBuilder,
+{
+    let owner1 = AccountSecretKey::generate().public().into();
+    let storage = storage_builder.build().await?;
+    let mut env = TestEnvironment::new(storage.clone(), false, false).await;
+    let chain_1_desc = env.add_root_chain(1, owner1, Amount::from_tokens(3)).await;
+    let mut committees = BTreeMap::new();
+    let committee = env.committee().clone();
+    committees.insert(Epoch::ZERO, committee.clone());
+    let admin_id = env.admin_id();
+    let user_id = chain_1_desc.id();
+
+    // Have the user chain start a transfer to the admin chain.
+    let certificate0 = env.make_certificate(ConfirmedBlock::new(
+        BlockExecutionOutcome {
+            messages: vec![vec![direct_credit_message(admin_id, Amount::ONE)]],
+            previous_message_blocks: BTreeMap::new(),
+            previous_event_blocks: BTreeMap::new(),
+            events: vec![Vec::new()],
+            blobs: vec![Vec::new()],
+            state_hash: SystemExecutionState {
+                balance: Amount::from_tokens(2),
+                ..env.system_execution_state(&chain_1_desc.id())
+            }
+            .into_hash()
+            .await,
+            oracle_responses: vec![Vec::new()],
+            operation_results: vec![OperationResult::default()],
+            fees: FeeBreakdown::default(),
+        }
+        .with(
+            make_first_block(user_id)
+                .with_simple_transfer(admin_id, Amount::ONE)
+                .with_authenticated_signer(Some(owner1)),
+        ),
+    ));
+    // Have the admin chain create a new epoch without retiring the old one.
+    let committees2 = BTreeMap::from_iter([
+        (Epoch::ZERO, committee.clone()),
+        (Epoch::from(1), committee.clone()),
+    ]);
+    let committee_blob = Blob::new(BlobContent::new_committee(bcs::to_bytes(&committee)?));
+    let blob_hash = committee_blob.id().hash;
+    storage.write_blob(&committee_blob).await?;
+    let certificate1 = env.make_certificate(ConfirmedBlock::new(
+        BlockExecutionOutcome {
+            messages: vec![vec![]],
+            previous_message_blocks: BTreeMap::new(),
+            previous_event_blocks: BTreeMap::new(),
+            events: vec![vec![Event {
+                stream_id: StreamId::system(NEW_EPOCH_STREAM_NAME),
+                index: 1,
+                value: bcs::to_bytes(&committee_blob.id().hash).unwrap(),
+            }]],
+            blobs: vec![Vec::new()],
+            state_hash: SystemExecutionState {
+                committees: committees2.clone(),
+                used_blobs: BTreeSet::from([committee_blob.id()]),
+                epoch: Epoch::from(1),
+                ..env.system_execution_state(&admin_id)
+            }
+            .into_hash()
+            .await,
+            oracle_responses: vec![vec![OracleResponse::Blob(committee_blob.id())]],
+            operation_results: vec![OperationResult::default()],
+            fees: FeeBreakdown::default(),
+        }
+        .with(
+            make_first_block(admin_id).with_operation(SystemOperation::Admin(
+                AdminOperation::CreateCommittee {
+                    epoch: Epoch::from(1),
+                    blob_hash,
+                },
+            )),
+        ),
+    ));
+    env.worker()
+        .fully_handle_certificate_with_notifications(certificate1.clone(), &())
+        .await?;
+
+    // Try to execute the transfer.
+    env.worker()
+        .fully_handle_certificate_with_notifications(certificate0.clone(), &())
+        .await?;
+
+    // The transfer was started..
+    let user_chain = env.worker().chain_state_view(user_id).await?;
+    assert!(user_chain.is_active());
+    assert_eq!(
+        BlockHeight::from(1),
+        user_chain.tip_state.get().next_block_height
+    );
+    assert_eq!(
+        *user_chain.execution_state.system.balance.get(),
+        Amount::from_tokens(2)
+    );
+    assert_eq!(*user_chain.execution_state.system.epoch.get(), Epoch::ZERO);
+
+    // .. and the message has gone through.
+    let admin_chain = env.worker().chain_state_view(admin_id).await?;
+    assert!(admin_chain.is_active());
+    assert_eq!(admin_chain.inboxes.indices().await?.len(), 1);
+    matches!(
+        &admin_chain
+            .inboxes
+            .try_load_entry(&user_id)
+            .await?
+            .expect("Missing inbox for user chain in admin chain")
+            .added_bundles
+            .read_front(10)
+            .await?[..],
+        [bundle] if matches!(bundle.messages[..], [PostedMessage {
+            message: Message::System(SystemMessage::Credit { .. }),
+            ..
+        }])
+    );
+    Ok(())
+}
+
+#[test_case(MemoryStorageBuilder::default(); "memory")]
+#[cfg_attr(feature = "rocksdb", test_case(RocksDbStorageBuilder::new().await; "rocks_db"))]
+#[cfg_attr(feature = "dynamodb", test_case(DynamoDbStorageBuilder::default(); "dynamo_db"))]
+#[cfg_attr(feature = "scylladb", test_case(ScyllaDbStorageBuilder::default(); "scylla_db"))]
+#[test_log::test(tokio::test)]
+async fn test_buffered_events<B>(mut storage_builder: B) -> anyhow::Result<()>
+where
+    B: StorageBuilder,
+{
+    let storage = storage_builder.build().await?;
+    let mut env = TestEnvironment::new(storage.clone(), false, false).await;
+    let worker = env.worker().clone().with_max_buffered_events(100);
+    let committee = env.committee().clone();
+    let admin_id = env.admin_id();
+
+    // Have the admin chain create a new epoch.
+    let committees = BTreeMap::from_iter([
+        (Epoch::ZERO, committee.clone()),
+        (Epoch::from(1), committee.clone()),
+    ]);
+    let committee_blob = Blob::new(BlobContent::new_committee(bcs::to_bytes(&committee)?));
+    let blob_hash = committee_blob.id().hash;
+    storage.write_blob(&committee_blob).await?;
+    let event = Event {
+        stream_id: StreamId::system(NEW_EPOCH_STREAM_NAME),
+        index: 1,
+        value: bcs::to_bytes(&blob_hash)?,
+    };
+    let certificate = env.make_certificate(ConfirmedBlock::new(
+        BlockExecutionOutcome {
+            messages: vec![vec![]],
+            previous_message_blocks: BTreeMap::new(),
+            previous_event_blocks: BTreeMap::new(),
+            events: vec![vec![event.clone()]],
+            blobs: vec![Vec::new()],
+            state_hash: SystemExecutionState {
+                committees,
+                used_blobs: BTreeSet::from([committee_blob.id()]),
+                epoch: Epoch::from(1),
+                ..env.system_execution_state(&admin_id)
+            }
+            .into_hash()
+            .await,
+            oracle_responses: vec![vec![OracleResponse::Blob(committee_blob.id())]],
+            operation_results: vec![OperationResult::default()],
+            fees: FeeBreakdown::default(),
+        }
+        .with(
+            make_first_block(admin_id).with_operation(SystemOperation::Admin(
+                AdminOperation::CreateCommittee {
+                    epoch: Epoch::from(1),
+                    blob_hash,
+                },
+            )),
+        ),
+    ));
+    worker
+        .fully_handle_certificate_with_notifications(certificate, &())
+        .await?;
+    let event_id = event.id(admin_id);
+    assert_eq!(
+        storage.read_event(event_id.clone()).await?,
+        Some(event.value)
+    );
+    let chain = worker.chain_state_view(admin_id).await?;
+    assert_eq!(chain.buffered_events.count(), 0);
+    drop(chain);
+
+    // If a worker stopped before writing its buffered events, they were saved with the chain,
+    // and are written when the chain is loaded again.
+    let buffered_event_id = EventId {
+        chain_id: admin_id,
+        stream_id: StreamId::system("buffered"),
+        index: 0,
+    };
+    let mut chain = storage.load_chain(admin_id).await?;
+    chain
+        .buffered_events
+        .push_back((buffered_event_id.clone(), vec![1, 2, 3]));
+    chain.save().await?;
+    assert!(!storage.contains_event(buffered_event_id.clone()).await?);
+    let restarted = WorkerState {
+        chain_worker_tasks: Arc::default(),
+        chain_workers: Arc::default(),
+        ..worker.clone()
+    };
+    let chain = restarted.chain_state_view(admin_id).await?;
+    assert_eq!(chain.buffered_events.count(), 0);
+    assert_eq!(
+        storage.read_event(buffered_event_id).await?,
+        Some(vec![1, 2, 3])
+    );
+    assert!(storage.contains_event(event_id).await?);
+    Ok(())
+}
+
+#[test_case(MemoryStorageBuilder::default(); "memory")]
+#[cfg_attr(feature = "rocksdb", test_case(RocksDbStorageBuilder::new().await; "rocks_db"))]
+#[cfg_attr(feature = "dynamodb", test_case(DynamoDbStorageBuilder::default(); "dynamo_db"))]
+#[cfg_attr(feature = "scylladb", test_case(ScyllaDbStorageBuilder::default(); "scylla_db"))]
+#[test_log::test(tokio::test)]
+async fn test_transfers_and_committee_removal<B>(mut storage_builder: B) -> anyhow::Result<()>
+where
+    B: StorageBuilder,
+{
+    let storage = storage_builder.build().await?;
+    let mut env =
+        TestEnvironment::new_with_amount(storage.clone(), false, false, Amount::ZERO).await;
+    let owner1 = AccountSecretKey::generate().public().into();
+    let chain_1_desc = env.add_root_chain(1, owner1, Amount::from_tokens(3)).await;
+    let mut committees = BTreeMap::new();
+    let committee = env.committee().clone();
+    committees.insert(Epoch::ZERO, committee.clone());
+    let admin_id = env.admin_id();
+    let user_id = chain_1_desc.id();
+
+    // Have the user chain start a transfer to the admin chain.
+    let certificate0 = env.make_certificate(ConfirmedBlock::new(
+        BlockExecutionOutcome {
+            messages: vec![vec![direct_credit_message(admin_id, Amount::ONE)]],
+            previous_message_blocks: BTreeMap::new(),
+            previous_event_blocks: BTreeMap::new(),
+            events: vec![Vec::new()],
+            blobs: vec![Vec::new()],
+            state_hash: SystemExecutionState {
+                balance: Amount::from_tokens(2),
+                ..env.system_execution_state(&chain_1_desc.id())
+            }
+            .into_hash()
+            .await,
+            oracle_responses: vec![Vec::new()],
+            operation_results: vec![OperationResult::default()],
+            fees: FeeBreakdown::default(),
+        }
+        .with(
+            make_first_block(user_id)
+                .with_simple_transfer(admin_id, Amount::ONE)
+                .with_authenticated_signer(Some(owner1)),
+        ),
+    ));
+    // Have the admin chain create a new epoch and retire the old one immediately.
+    let committees1 = BTreeMap::from_iter([(Epoch::from(1), committee.clone())]);
+    let committee_blob = Blob::new(BlobContent::new_committee(bcs::to_bytes(&committee)?));
+    let blob_hash = committee_blob.id().hash;
+    storage.write_blob(&committee_blob).await?;
+
+    let certificate1 = env.make_certificate(ConfirmedBlock::new(
+        BlockExecutionOutcome {
+            messages: vec![vec![]; 2],
+            previous_message_blocks: BTreeMap::new(),
+            previous_event_blocks: BTreeMap::new(),
+            events: vec![
+                vec![Event {
+                    stream_id: StreamId::system(NEW_EPOCH_STREAM_NAME),
+                    index: 1,
+                    value: bcs::to_bytes(&committee_blob.id().hash).unwrap(),
+                }],
+                vec![Event {
+                    stream_id: StreamId::system(REMOVED_EPOCH_STREAM_NAME),
+                    index: 0,
+                    value: Vec::new(),
+                }],
+            ],
+            blobs: vec![Vec::new(); 2],
+            state_hash: SystemExecutionState {
+                committees: committees1.clone(),
+                used_blobs: BTreeSet::from([committee_blob.id()]),
+                epoch: Epoch::from(1),
+                ..env.system_execution_state(&admin_id)
+            }
+            .into_hash()
+            .await,
+            oracle_responses: vec![vec![OracleResponse::Blob(committee_blob.id())], vec![]],
+            operation_results: vec![OperationResult::default(); 2],
+            fees: FeeBreakdown::default(),
+        }
+        .with(
+            make_first_block(admin_id)
+                .with_operation(SystemOperation::Admin(AdminOperation::CreateCommittee {
+                    epoch: Epoch::from(1),
+                    blob_hash,
+                }))
+                .with_operation(SystemOperation::Admin(AdminOperation::RemoveCommittee {
+                    epoch: Epoch::ZERO,
+                })),
+        ),
+    ));
+
+    env.worker()
+        .fully_handle_certificate_with_notifications(certificate1.clone(), &())
+        .await?;
+
+    // Try to execute the transfer from the user chain to the admin chain.
+    env.worker()
+        .fully_handle_certificate_with_notifications(certificate0.clone(), &())
+        .await?;
+
+    {
+        // The transfer was started..
+        let user_chain = env.worker().chain_state_view(user_id).await?;
+        assert!(user_chain.is_active());
+        assert_eq!(
+            BlockHeight::from(1),
+            user_chain.tip_state.get().next_block_height
+        );
+        assert_eq!(
+            *user_chain.execution_state.system.balance.get(),
+            Amount::from_tokens(2)
+        );
+        assert_eq!(*user_chain.execution_state.system.epoch.get(), Epoch::ZERO);
+
+        // .. but the message hasn't gone through.
+        let admin_chain = env.worker().chain_state_view(admin_id).await?;
+        assert!(admin_chain.is_active());
+        assert!(admin_chain.inboxes.indices().await?.is_empty());
+    }
+
+    // Force the admin chain to receive the money nonetheless by anticipation.
+    let certificate2 = env.make_certificate(ConfirmedBlock::new(
+        BlockExecutionOutcome {
+            messages: vec![Vec::new()],
+            previous_message_blocks: BTreeMap::new(),
+            previous_event_blocks: BTreeMap::new(),
+            events: vec![Vec::new()],
+            blobs: vec![Vec::new()],
+            state_hash: SystemExecutionState {
+                committees: committees1.clone(),
+                balance: Amount::ONE,
+                used_blobs: BTreeSet::from([committee_blob.id()]),
+                epoch: Epoch::from(1),
+                ..env.system_execution_state(&admin_id)
+            }
+            .into_hash()
+            .await,
+            oracle_responses: vec![Vec::new()],
+            operation_results: vec![],
+            fees: FeeBreakdown::default(),
+        }
+        .with(
+            make_child_block(&certificate1.clone().into_value())
+                .with_epoch(1)
+                .with_incoming_bundle(IncomingBundle {
+                    origin: user_id,
+                    bundle: MessageBundle {
+                        certificate_hash: certificate0.hash(),
+                        height: BlockHeight::ZERO,
+                        timestamp: Timestamp::from(0),
+                        transaction_index: 0,
+                        messages: vec![
+                            system_credit_message(Amount::ONE).to_posted(0, MessageKind::Tracked)
+                        ],
+                    },
+                    action: MessageAction::Accept,
+                }),
+        ),
+    ));
+    env.worker()
+        .fully_handle_certificate_with_notifications(certificate2.clone(), &())
+        .await?;
+
+    {
+        // The admin chain has an anticipated message.
+        let admin_chain = env.worker().chain_state_view(admin_id).await?;
+        assert!(admin_chain.is_active());
+        assert_matches!(
+            admin_chain.validate_incoming_bundles().await,
+            Err(ChainError::MissingCrossChainUpdate { .. })
+        );
+        assert_eq!(
+            *admin_chain.nonempty_inboxes.get(),
+            Some(BTreeSet::from([user_id]))
+        );
+    }
+
+    // Try again to execute the transfer from the user chain to the admin chain.
+    // This time, the epoch verification should be overruled.
+    env.worker()
+        .fully_handle_certificate_with_notifications(certificate0.clone(), &())
+        .await?;
+
+    {
+        // The admin chain has no more anticipated messages.
+        let admin_chain = env.worker().chain_state_view(admin_id).await?;
+        assert!(admin_chain.is_active());
+        admin_chain.validate_incoming_bundles().await?;
+        assert_eq!(*admin_chain.nonempty_inboxes.get(), Some(BTreeSet::new()));
+    }
+
+    // Let's make a certificate for a block creating another epoch.
+    // This one should contain previous_event_blocks.
+    let committees3 = BTreeMap::from_iter([
+        (Epoch::from(1), committee.clone()),
+        (Epoch::from(2), committee.clone()),
+    ]);
+    let certificate3 = env.make_certificate(ConfirmedBlock::new(
+        BlockExecutionOutcome {
+            messages: vec![Vec::new()],
+            previous_message_blocks: BTreeMap::new(),
+            previous_event_blocks: BTreeMap::from([(
+                StreamId::system(NEW_EPOCH_STREAM_NAME),
+                (certificate1.hash(), BlockHeight(0)),
+            )]),
+            events: vec![vec![Event {
+                stream_id: StreamId::system(NEW_EPOCH_STREAM_NAME),
+                index: 2,
+                value: bcs::to_bytes(&committee_blob.id().hash).unwrap(),
+            }]],
+            blobs: vec![Vec::new()],
+            state_hash: SystemExecutionState {
+                committees: committees3.clone(),
+                balance: Amount::ONE,
+                used_blobs: BTreeSet::from([committee_blob.id()]),
+                epoch: Epoch::from(2),
+                ..env.system_execution_state(&admin_id)
+            }
+            .into_hash()
+            .await,
+            oracle_responses: vec![Vec::new()],
+            operation_results: vec![OperationResult::default()],
+            fees: FeeBreakdown::default(),
+        }
+        .with(
+            make_child_block(&certificate2.into_value())
+                .with_epoch(1)
+                .with_operation(SystemOperation::Admin(AdminOperation::CreateCommittee {
+                    epoch: Epoch::from(2),
+                    blob_hash,
+                })),
+        ),
+    ));
+
+    env.worker()
+        .fully_handle_certificate_with_notifications(certificate3, &())
+        .await?;
+
+    Ok(())
+}
+
+#[test(tokio::test)]
+async fn test_cross_chain_helper() -> anyhow::Result<()> {
+    let store_config = MemoryDatabase::new_test_config().await?;
+    let namespace = generate_test_namespace();
+    let store = DbStorage::<MemoryDatabase, _>::new_for_testing(
+        store_config,
+        &namespace,
+        None,
+        TestClock::new(),
+    )
+    .await?;
+    let env = TestEnvironment::new(store, true, false).await;
+    let committees = BTreeMap::from([(Epoch::from(1), env.committee().clone())]);
+
+    let chain_0 = env.admin_description.clone();
+    let chain_1 = dummy_chain_description(1);
+
+    let key_pair0 = AccountSecretKey::generate();
+    let id0 = chain_0.id();
+    let id1 = chain_1.id();
+
+    let certificate0 = env
+        .make_transfer_certificate_for_epoch(
+            chain_0.clone(),
+            key_pair0.public(),
+            key_pair0.public().into(),
+            AccountOwner::CHAIN,
+            Recipient::chain(id1),
+            Amount::ONE,
+            Vec::new(),
+            Epoch::ZERO,
+            Amount::ONE,
+            BTreeMap::new(),
+            vec![],
+        )
+        .await;
+    let certificate1 = env
+        .make_transfer_certificate_for_epoch(
+            chain_0.clone(),
+            key_pair0.public(),
+            key_pair0.public().into(),
+            AccountOwner::CHAIN,
+            Recipient::chain(id1),
+            Amount::ONE,
+            Vec::new(),
+            Epoch::ZERO,
+            Amount::ONE,
+            BTreeMap::new(),
+            vec![&certificate0],
+        )
+        .await;
+    let certificate2 = env
+        .make_transfer_certificate_for_epoch(
+            chain_0.clone(),
+            key_pair0.public(),
+            key_pair0.public().into(),
+            AccountOwner::CHAIN,
+            Recipient::chain(id1),
+            Amount::ONE,
+            Vec::new(),
+            Epoch::from(1),
+            Amount::ONE,
+            BTreeMap::new(),
+            vec![&certificate1, &certificate0],
+        )
+        .await;
+    // Weird case: epoch going backward.
+    let certificate3 = env
+        .make_transfer_certificate_for_epoch(
+            chain_0.clone(),
+            key_pair0.public(),
+            key_pair0.public().into(),
+            AccountOwner::CHAIN,
+            Recipient::chain(id1),
+            Amount::ONE,
+            Vec::new(),
+            Epoch::ZERO,
+            Amount::ONE,
+            BTreeMap::new(),
+            vec![&certificate2, &certificate1, &certificate0],
+        )
+        .await;
+    let bundles0 = certificate0.message_bundles_for(id1).collect::<Vec<_>>();
+    let bundles1 = certificate1.message_bundles_for(id1).collect::<Vec<_>>();
+    let bundles2 = certificate2.message_bundles_for(id1).collect::<Vec<_>>();
+    let bundles3 = certificate3.message_bundles_for(id1).collect::<Vec<_>>();
+    let bundles01 = Vec::from_iter(bundles0.iter().cloned().chain(bundles1.iter().cloned()));
+    let bundles012 = Vec::from_iter(bundles01.iter().cloned().chain(bundles2.iter().cloned()));
+    let bundles0123 = Vec::from_iter(bundles012.iter().cloned().chain(bundles3.iter().cloned()));
+
+    fn without_epochs<'a>(
+        bundles: impl IntoIterator<Item = &'a (Epoch, MessageBundle)>,
+    ) -> Vec<MessageBundle> {
+        bundles
+            .into_iter()
+            .map(|(_, bundle)| bundle.clone())
+            .collect()
+    }
+
+    let helper = CrossChainUpdateHelper {
+        allow_messages_from_deprecated_epochs: true,
+        current_epoch: Epoch::from(1),
+        committees: &committees,
+    };
+    // Epoch is not tested when `allow_messages_from_deprecated_epochs` is true.
+    assert_eq!(
+        helper.select_message_bundles(&id0, id1, BlockHeight::ZERO, None, bundles01.clone())?,
+        without_epochs(&bundles01)
+    );
+    // Received heights is removing prefixes.
+    assert_eq!(
+        helper.select_message_bundles(&id0, id1, BlockHeight::from(1), None, bundles01.clone())?,
+        without_epochs(&bundles1)
+    );
+    assert_eq!(
+        helper.select_message_bundles(&id0, id1, BlockHeight::from(2), None, bundles01.clone())?,
+        vec![]
+    );
+    // Order of certificates is checked.
+    assert_matches!(
+        helper.select_message_bundles(
+            &id0,
+            id1,
+            BlockHeight::ZERO,
+            None,
+            Vec::from_iter(bundles1.iter().cloned().chain(bundles0.iter().cloned()))
+        ),
+        Err(WorkerError::InvalidCrossChainRequest)
+    );
+
+    let helper = CrossChainUpdateHelper {
+        allow_messages_from_deprecated_epochs: false,
+        current_epoch: Epoch::from(1),
+        committees: &committees,
+    };
+    // Epoch is tested when `allow_messages_from_deprecated_epochs` is false.
+    assert_eq!(
+        helper.select_message_bundles(&id0, id1, BlockHeight::ZERO, None, bundles01.clone())?,
+        vec![]
+    );
+    // A certificate with a recent epoch certifies all the previous blocks.
+    assert_eq!(
+        helper.select_message_bundles(&id0, id1, BlockHeight::ZERO, None, bundles0123.clone())?,
+        without_epochs(&bundles012)
+    );
+    // Received heights is still removing prefixes.
+    assert_eq!(
+        helper.select_message_bundles(&id0, id1, BlockHeight::from(1), None, bundles012.clone())?,
+        without_epochs(bundles1.iter().chain(&bundles2))
+    );
+    // Anticipated messages re-certify blocks up to the given height.
+    assert_eq!(
+        helper.select_message_bundles(
+            &id0,
+            id1,
+            BlockHeight::from(1),
+            Some(BlockHeight::from(1)),
+            bundles01.clone()
+        )?,
+        without_epochs(&bundles1)
+    );
+    assert_eq!(
+        helper.select_message_bundles(
+            &id0,
+            id1,
+            BlockHeight::ZERO,
+            Some(BlockHeight::from(1)),
+            bundles01.clone()
+        )?,
+        without_epochs(&bundles01)
+    );
+    Ok(())
+}
+
+#[test_case(MemoryStorageBuilder::default(); "memory")]
+#[cfg_attr(feature = "rocksdb", test_case(RocksDbStorageBuilder::new().await; "rocks_db"))]
+#[cfg_attr(feature = "dynamodb", test_case(DynamoDbStorageBuilder::default(); "dynamo_db"))]
+#[cfg_attr(feature = "scylladb", test_case(ScyllaDbStorageBuilder::default(); "scylla_db"))]
+#[test_log::test(tokio::test)]
+async fn test_simulate_block_execution<B>(mut storage_builder: B) -> anyhow::Result<()>
+where
+    B: StorageBuilder,
+{
+    let storage = storage_builder.build().await?;
+    let mut signer = InMemorySigner::new(None);
+    let owner = AccountOwner::from(generate_key_pairs(&mut signer, 1)[0]);
+    let mut env = TestEnvironment::new(storage, false, false).await;
+    let chain_1 = env
+        .add_root_chain(1, owner, Amount::from_tokens(2))
+        .await
+        .id();
+    let chain_2 = env.add_root_chain(2, owner, Amount::ZERO).await.id();
+
+    let proposed_block = make_first_block(chain_1)
+        .with_simple_transfer(chain_2, Amount::ONE)
+        .with_authenticated_signer(Some(owner));
+    let (simulated_block, response) = env
+        .worker()
+        .simulate_block_execution(proposed_block.clone(), vec![])
+        .await?;
+    assert_eq!(response.info.chain_balance, Amount::ONE);
+
+    // The simulation has the same outcome as staging the block.
+    let (staged_block, _) = env
+        .worker()
+        .stage_block_execution(proposed_block, None, vec![])
+        .await?;
+    assert_eq!(simulated_block, staged_block);
+
+    // Nothing was written to the chain state.
+    let chain = env.worker().chain_state_view(chain_1).await?;
+    assert_eq!(
+        *chain.execution_state.system.balance.get(),
+        Amount::from_tokens(2)
+    );
+    assert_eq!(chain.tip_state.get().next_block_height, BlockHeight::ZERO);
+    Ok(())
+}
+
+#[test_case(MemoryStorageBuilder::default(); "memory")]
+#[cfg_attr(feature = "rocksdb", test_case(RocksDbStorageBuilder::new().await; "rocks_db"))]
+#[cfg_attr(feature = "dynamodb", test_case(DynamoDbStorageBuilder::default(); "dynamo_db"))]
+#[cfg_attr(feature = "scylladb", test_case(ScyllaDbStorageBuilder::default(); "scylla_db"))]
+#[test_log::test(tokio::test)]
+async fn test_verify_chain<B>(mut storage_builder: B) -> anyhow::Result<()>
+where
+    B: StorageBuilder,
+{
+    let storage = storage_builder.build().await?;
+    let mut signer = InMemorySigner::new(None);
+    let owner = AccountOwner::from(generate_key_pairs(&mut signer, 1)[0]);
+    let mut env = TestEnvironment::new(storage, false, false).await;
+    let chain_1 = env
+        .add_root_chain(1, owner, Amount::from_tokens(2))
+        .await
+        .id();
+    let chain_2 = env.add_root_chain(2, owner, Amount::ZERO).await.id();
+
+    let mut proposed_block = make_first_block(chain_1)
+        .with_simple_transfer(chain_2, Amount::ONE)
+        .with_authenticated_signer(Some(owner));
+    for _ in 0..2 {
+        let (block, _) = env
+            .worker()
+            .stage_block_execution(proposed_block, None, vec![])
+            .await?;
+        let certificate = env.make_certificate(ConfirmedBlock::new(block));
+        env.worker()
+            .fully_handle_certificate_with_notifications(certificate.clone(), &())
+            .await?;
+        proposed_block = make_child_block(certificate.value())
+            .with_simple_transfer(chain_2, Amount::ONE)
+            .with_authenticated_signer(Some(owner));
+    }
+
+    let storage = env.worker().storage_client();
+    assert!(verify_chain(storage, chain_1, ..).await?.is_none());
+    assert!(
+        verify_chain(storage, chain_1, BlockHeight(1)..BlockHeight(2))
+            .await?
+            .is_none()
+    );
+
+    // The verification did not modify the chain.
+    let chain = env.worker().chain_state_view(chain_1).await?;
+    assert_eq!(chain.tip_state.get().next_block_height, BlockHeight(2));
+    assert_eq!(*chain.execution_state.system.balance.get(), Amount::ZERO);
+    Ok(())
+}
+
+#[test_case(MemoryStorageBuilder::default(); "memory")]
+#[cfg_attr(feature = "rocksdb", test_case(RocksDbStorageBuilder::new().await; "rocks_db"))]
+#[cfg_attr(feature = "dynamodb", test_case(DynamoDbStorageBuilder::default(); "dynamo_db"))]
+#[cfg_attr(feature = "scylladb", test_case(ScyllaDbStorageBuilder::default(); "scylla_db"))]
+#[test_log::test(tokio::test)]
+async fn test_timeouts<B>(mut storage_builder: B) -> anyhow::Result<()>
+where
+    B: StorageBuilder,
+{
+    let storage = storage_builder.build().await?;
+    let mut signer = InMemorySigner::new(None);
+    let clock = storage_builder.clock();
+    let key_pairs = generate_key_pairs(&mut signer, 2);
+    let owner0 = AccountOwner::from(key_pairs[0]);
+    let owner1 = AccountOwner::from(key_pairs[1]);
+    let mut env = TestEnvironment::new(storage, false, false).await;
+    let chain_1_desc = env.add_root_chain(1, owner0, Amount::from_tokens(2)).await;
+    let small_transfer = Amount::from_micros(1);
+    let chain_1 = chain_1_desc.id();
+
+    // Add another owner and use the leader-based protocol in all rounds.
+    let proposed_block0 = make_first_block(chain_1)
+        .with_operation(SystemOperation::ChangeOwnership {
+            super_owners: Vec::new(),
+            owners: vec![(owner0, 100), (owner1, 100)],
+            multi_leader_rounds: 0,
+            open_multi_leader_rounds: false,
+            timeout_config: TimeoutConfig::default(),
+        })
+        .with_authenticated_signer(Some(owner0));
+    let (block0, _) = env
+        .worker()
+        .stage_block_execution(proposed_block0, None, vec![])
+        .await?;
+    let value0 = ConfirmedBlock::new(block0);
+    let certificate0 = env.make_certificate(value0.clone());
+    let response = env
+        .worker()
+        .fully_handle_certificate_with_notifications(certificate0, &())
+        .await?;
+
+    // The leader sequence is pseudorandom but deterministic. The first leader is owner 1.
+    assert_eq!(response.info.manager.leader, Some(owner1));
+
+    // So owner 0 cannot propose a block in this round. And the next round hasn't started yet.
+    let proposal = make_child_block(&value0.clone())
+        .with_simple_transfer(chain_1, small_transfer)
+        .into_proposal_with_round(owner0, &signer, Round::SingleLeader(0))
+        .await
+        .unwrap();
+    let result = env.worker().handle_block_proposal(proposal).await;
+    assert_matches!(result, Err(WorkerError::InvalidOwner));
+    let proposal = make_child_block(&value0.clone())
+        .with_simple_transfer(chain_1, small_transfer)
+        .into_proposal_with_round(owner0, &signer, Round::SingleLeader(1))
+        .await
+        .unwrap();
+    let result = env.worker().handle_block_proposal(proposal).await;
+
+    assert_matches!(result, Err(WorkerError::ChainError(ref error))
+        if matches!(**error, ChainError::WrongRound(Round::SingleLeader(0)))
+    );
+
+    // The round hasn't timed out yet, so the validator won't sign a leader timeout vote yet.
+    let query = ChainInfoQuery::new(chain_1).with_timeout();
+    let (response, _) = env.worker().handle_chain_info_query(query).await?;
+    assert!(response.info.manager.timeout_vote.is_none());
+
+    // Set the clock to the end of the round.
+    clock.set(response.info.manager.round_timeout.unwrap());
+
+    // Now the validator will sign a leader timeout vote.
+    let query = ChainInfoQuery::new(chain_1).with_timeout();
+    let (response, _) = env.worker().handle_chain_info_query(query).await?;
+    let vote = response.info.manager.timeout_vote.clone().unwrap();
+    let value_timeout = Timeout::new(chain_1, BlockHeight::from(1), Epoch::from(0));
+
+    // Once we provide the validator with a timeout certificate, the next round starts, where owner
+    // 0 happens to be the leader.
+    let certificate_timeout = vote
+        .with_value(value_timeout.clone())
+        .unwrap()
+        .into_certificate();
+    let (response, _) = env
+        .worker()
+        .handle_timeout_certificate(certificate_timeout)
+        .await?;
+    assert_eq!(response.info.manager.leader, Some(owner0));
+
+    // Now owner 0 can propose a block, but owner 1 can't.
+    let proposed_block1 =
+        make_child_block(&value0.clone()).with_simple_transfer(chain_1, small_transfer);
+    let (block1, _) = env
+        .worker()
+        .stage_block_execution(proposed_block1.clone(), None, vec![])
+        .await?;
+    let proposal1_wrong_owner = proposed_block1
+        .clone()
+        .with_authenticated_signer(Some(owner1))
+        .into_proposal_with_round(owner1, &signer, Round::SingleLeader(1))
+        .await
+        .unwrap();
+    let result = env
+        .worker()
+        .handle_block_proposal(proposal1_wrong_owner)
+        .await;
+    assert_matches!(result, Err(WorkerError::InvalidOwner));
+    let proposal1 = proposed_block1
+        .clone()
+        .into_proposal_with_round(owner0, &signer, Round::SingleLeader(1))
+        .await
+        .unwrap();
+    let (response, _) = env.worker().handle_block_proposal(proposal1).await?;
+    let value1 = ValidatedBlock::new(block1.clone());
+
+    // If we send the validated block certificate to the worker, it votes to confirm.
+    let vote = response.info.manager.pending.clone().unwrap();
+    let certificate1 = vote.with_value(value1.clone()).unwrap().into_certificate();
+    let (response, _) = env
+        .worker()
+        .handle_validated_certificate(certificate1.clone())
+        .await?;
+    let vote = response.info.manager.pending.as_ref().unwrap();
+    let value = ConfirmedBlock::new(block1.clone());
+    assert_eq!(vote.value, LiteValue::new(&value));
+
+    // Instead of submitting the confirmed block certificate, let rounds 2 to 4 time out, too.
+    let certificate_timeout =
+        env.make_certificate_with_round(value_timeout.clone(), Round::SingleLeader(4));
+    let (response, _) = env
+        .worker()
+        .handle_timeout_certificate(certificate_timeout)
+        .await?;
+    assert_eq!(response.info.manager.leader, Some(owner1));
+    assert_eq!(response.info.manager.current_round, Round::SingleLeader(5));
+
+    // Create block2, also at height 1, but different from block 1.
+    let amount = Amount::from_tokens(1);
+    let proposed_block2 = make_child_block(&value0.clone()).with_simple_transfer(chain_1, amount);
+    let (block2, _) = env
+        .worker()
+        .stage_block_execution(proposed_block2.clone(), None, vec![])
+        .await?;
+
+    // Since round 3 is already over, the validator won't vote for a validated block from round 3.
+    let value2 = ValidatedBlock::new(block2.clone());
+    let certificate = env.make_certificate_with_round(value2.clone(), Round::SingleLeader(2));
     env.worker()
-        .fully_handle_certificate_with_notifications(certificate3, &())
+        .handle_validated_certificate(certificate)
         .await?;
-    {
-        let user_chain = env.worker().chain_state_view(user_id).await?;
-        assert!(user_chain.is_active());
-        assert_eq!(
-            BlockHeight::from(1),
-            user_chain.tip_state.get().next_block_height
-        );
-        assert_eq!(
-            *user_chain.execution_state.system.admin_id.get(),
-            Some(admin_id)
-        );
-        assert_eq!(user_chain.execution_state.system.committees.get().len(), 2);
-        user_chain.validate_incoming_bundles().await?;
-        Ok(())
+    let query_values = ChainInfoQuery::new(chain_1).with_manager_values();
+    let (response, _) = env
+        .worker()
+        .handle_chain_info_query(query_values.clone())
+        .await?;
+    let manager = response.info.manager;
+    assert_eq!(
+        manager.requested_confirmed.unwrap().block(),
+        certificate1.block()
+    );
+
+    // Proposing block2 now would fail.
+    let proposal = proposed_block2
+        .clone()
+        .with_authenticated_signer(Some(owner1))
+        .into_proposal_with_round(owner1, &signer, Round::SingleLeader(5))
+        .await
+        .unwrap();
+    let result = env.worker().handle_block_proposal(proposal.clone()).await;
+    assert_matches!(result, Err(WorkerError::ChainError(error))
+         if matches!(*error, ChainError::HasIncompatibleConfirmedVote(_, _))
+    );
+
+    // But with the validated block certificate for block2, it is allowed.
+    let certificate2 = env.make_certificate_with_round(value2.clone(), Round::SingleLeader(4));
+
+    let proposal = BlockProposal::new_retry_regular(
+        owner1,
+        Round::SingleLeader(5),
+        certificate2.clone(),
+        &signer,
+    )
+    .await
+    .unwrap();
+    let lite_value2 = LiteValue::new(&value2);
+    let (_, _) = env.worker().handle_block_proposal(proposal).await?;
+    let (response, _) = env
+        .worker()
+        .handle_chain_info_query(query_values.clone())
+        .await?;
+    assert_eq!(
+        response.info.manager.requested_locking,
+        Some(Box::new(LockingBlock::Regular(certificate2)))
+    );
+    let vote = response.info.manager.pending.as_ref().unwrap();
+    assert_eq!(vote.value, lite_value2);
+    assert_eq!(vote.round, Round::SingleLeader(5));
+
+    // Let round 5 time out, too.
+    let certificate_timeout =
+        env.make_certificate_with_round(value_timeout.clone(), Round::SingleLeader(5));
+    let (response, _) = env
+        .worker()
+        .handle_timeout_certificate(certificate_timeout)
+        .await?;
+    assert_eq!(response.info.manager.leader, Some(owner0));
+    assert_eq!(response.info.manager.current_round, Round::SingleLeader(6));
+
+    // Since the validator now voted for block2, it can't vote for block1 anymore.
+    let proposal = proposed_block1
+        .into_proposal_with_round(owner0, &signer, Round::SingleLeader(6))
+        .await
+        .unwrap();
+    let result = env.worker().handle_block_proposal(proposal.clone()).await;
+    assert_matches!(result, Err(WorkerError::ChainError(error))
+         if matches!(*error, ChainError::HasIncompatibleConfirmedVote(_, _))
+    );
+
+    // Let rounds 6 and 7 time out.
+    let certificate_timeout =
+        env.make_certificate_with_round(value_timeout, Round::SingleLeader(7));
+    let (response, _) = env
+        .worker()
+        .handle_timeout_certificate(certificate_timeout)
+        .await?;
+    assert_eq!(response.info.manager.current_round, Round::SingleLeader(8));
+
+    // The worker updates its locking block even if it's from a past round.
+    let certificate = env.make_certificate_with_round(value1, Round::SingleLeader(7));
+    let worker = env.worker().clone().with_key_pair(None).await; // Forget validator keys.
+    worker
+        .handle_validated_certificate(certificate.clone())
+        .await?;
+    let (response, _) = worker.handle_chain_info_query(query_values).await?;
+    assert_eq!(
+        response.info.manager.requested_locking,
+        Some(Box::new(LockingBlock::Regular(certificate)))
+    );
+    Ok(())
+}
+
+/// A pacemaker that assigns a fixed leader and deadline to the first single-leader round of
+/// the block at height 1, and fails while it is not available.
+struct TestPacemaker {
+    leader: AccountOwner,
+    deadline: Timestamp,
+    available: AtomicBool,
+}
+
+impl Pacemaker for TestPacemaker {
+    fn round_schedule(
+        &self,
+        _: ChainId,
+        height: BlockHeight,
+        round: Round,
+    ) -> BoxFuture<'_, Result<RoundSchedule, PacemakerError>> {
+        let result = if !self.available.load(Ordering::SeqCst) {
+            Err(PacemakerError("not available".to_string()))
+        } else if height == BlockHeight(1) && round == Round::SingleLeader(0) {
+            Ok(RoundSchedule {
+                deadline: Some(self.deadline),
+                leader: Some(self.leader),
+            })
+        } else {
+            Ok(RoundSchedule::default())
+        };
+        future::ready(result).boxed()
     }
 }
 
@@ -2721,121 +4561,182 @@ where
 #[cfg_attr(feature = "dynamodb", test_case(DynamoDbStorageBuilder::default(); "dynamo_db"))]
 #[cfg_attr(feature = "scylladb", test_case(ScyllaDbStorageBuilder::default(); "scylla_db"))]
 #[test_log::test(tokio::test)]
-async fn test_transfers_and_committee_creation<B>(mut storage_builder: B) -> anyhow::Result<()>
+async fn test_pacemaker<B>(mut storage_builder: B) -> anyhow::Result<()>
 where
     B: StorageBuilder,
 {
-    let owner1 = AccountSecretKey::generate().public().into();
     let storage = storage_builder.build().await?;
-    let mut env = TestEnvironment::new(storage.clone(), false, false).await;
-    let chain_1_desc = env.add_root_chain(1, owner1, Amount::from_tokens(3)).await;
-    let mut committees = BTreeMap::new();
-    let committee = env.committee().clone();
-    committees.insert(Epoch::ZERO, committee.clone());
-    let admin_id = env.admin_id();
-    let user_id = chain_1_desc.id();
+    let clock = storage_builder.clock();
+    let mut signer = InMemorySigner::new(None);
+    let key_pairs = generate_key_pairs(&mut signer, 2);
+    let owner0 = AccountOwner::from(key_pairs[0]);
+    let owner1 = AccountOwner::from(key_pairs[1]);
+    let mut env = TestEnvironment::new(storage, false, false).await;
+    let chain_1_desc = env.add_root_chain(1, owner0, Amount::from_tokens(2)).await;
+    let chain_1 = chain_1_desc.id();
+    let deadline = Timestamp::from(1_000_000_000);
+    let pacemaker = Arc::new(TestPacemaker {
+        leader: owner0,
+        deadline,
+        available: AtomicBool::new(true),
+    });
+    let worker = env.worker().clone().with_pacemaker(Some(pacemaker.clone()));
 
-    // Have the user chain start a transfer to the admin chain.
-    let certificate0 = env.make_certificate(ConfirmedBlock::new(
-        BlockExecutionOutcome {
-            messages: vec![vec![direct_credit_message(admin_id, Amount::ONE)]],
-            previous_message_blocks: BTreeMap::new(),
-            previous_event_blocks: BTreeMap::new(),
-            events: vec![Vec::new()],
-            blobs: vec![Vec::new()],
-            state_hash: SystemExecutionState {
-                balance: Amount::from_tokens(2),
-                ..env.system_execution_state(&chain_1_desc.id())
-            }
-            .into_hash()
-            .await,
-            oracle_responses: vec![Vec::new()],
-            operation_results: vec![OperationResult::default()],
-        }
-        .with(
-            make_first_block(user_id)
-                .with_simple_transfer(admin_id, Amount::ONE)
-                .with_authenticated_signer(Some(owner1)),
-        ),
-    ));
-    // Have the admin chain create a new epoch without retiring the old one.
-    let committees2 = BTreeMap::from_iter([
-        (Epoch::ZERO, committee.clone()),
-        (Epoch::from(1), committee.clone()),
-    ]);
-    let committee_blob = Blob::new(BlobContent::new_committee(bcs::to_bytes(&committee)?));
-    let blob_hash = committee_blob.id().hash;
-    storage.write_blob(&committee_blob).await?;
-    let certificate1 = env.make_certificate(ConfirmedBlock::new(
-        BlockExecutionOutcome {
-            messages: vec![vec![]],
-            previous_message_blocks: BTreeMap::new(),
-            previous_event_blocks: BTreeMap::new(),
-            events: vec![vec![Event {
-                stream_id: StreamId::system(NEW_EPOCH_STREAM_NAME),
-                index: 1,
-                value: bcs::to_bytes(&committee_blob.id().hash).unwrap(),
-            }]],
-            blobs: vec![Vec::new()],
-            state_hash: SystemExecutionState {
-                committees: committees2.clone(),
-                used_blobs: BTreeSet::from([committee_blob.id()]),
-                epoch: Epoch::from(1),
-                ..env.system_execution_state(&admin_id)
-            }
-            .into_hash()
-            .await,
-            oracle_responses: vec![vec![OracleResponse::Blob(committee_blob.id())]],
-            operation_results: vec![OperationResult::default()],
-        }
-        .with(
-            make_first_block(admin_id).with_operation(SystemOperation::Admin(
-                AdminOperation::CreateCommittee {
-                    epoch: Epoch::from(1),
-                    blob_hash,
-                },
-            )),
-        ),
-    ));
-    env.worker()
-        .fully_handle_certificate_with_notifications(certificate1.clone(), &())
+    let proposed_block0 = make_first_block(chain_1)
+        .with_operation(SystemOperation::ChangeOwnership {
+            super_owners: Vec::new(),
+            owners: vec![(owner0, 100), (owner1, 100)],
+            multi_leader_rounds: 0,
+            open_multi_leader_rounds: false,
+            timeout_config: TimeoutConfig::default(),
+        })
+        .with_authenticated_signer(Some(owner0));
+    let (block0, _) = worker
+        .stage_block_execution(proposed_block0, None, vec![])
+        .await?;
+    let value0 = ConfirmedBlock::new(block0);
+    let certificate0 = env.make_certificate(value0.clone());
+    let response = worker
+        .fully_handle_certificate_with_notifications(certificate0, &())
+        .await?;
+
+    // Without the pacemaker, owner 1 would be the first leader (see `test_timeouts`).
+    assert_eq!(response.info.manager.leader, Some(owner0));
+    assert_eq!(response.info.manager.round_timeout, Some(deadline));
+
+    // Owner 0 can propose in the first round, but owner 1 can't.
+    let proposal = make_child_block(&value0)
+        .with_simple_transfer(chain_1, Amount::from_micros(1))
+        .into_proposal_with_round(owner1, &signer, Round::SingleLeader(0))
+        .await
+        .unwrap();
+    let result = worker.handle_block_proposal(proposal).await;
+    assert_matches!(result, Err(WorkerError::InvalidOwner));
+    let proposal = make_child_block(&value0)
+        .with_simple_transfer(chain_1, Amount::from_micros(1))
+        .into_proposal_with_round(owner0, &signer, Round::SingleLeader(0))
+        .await
+        .unwrap();
+
+    // Without the round's schedule, the validator neither votes for proposals nor for timeouts.
+    pacemaker.available.store(false, Ordering::SeqCst);
+    let result = worker.handle_block_proposal(proposal.clone()).await;
+    assert_matches!(result, Err(WorkerError::Pacemaker(_)));
+    clock.set(deadline);
+    let query = ChainInfoQuery::new(chain_1).with_timeout();
+    let result = worker.handle_chain_info_query(query.clone()).await;
+    assert_matches!(result, Err(WorkerError::Pacemaker(_)));
+
+    // Once the pacemaker is back, the round times out at the deadline it supplied.
+    pacemaker.available.store(true, Ordering::SeqCst);
+    clock.set(Timestamp::from(deadline.micros() - 1));
+    let (response, _) = worker.handle_chain_info_query(query.clone()).await?;
+    assert!(response.info.manager.timeout_vote.is_none());
+    clock.set(deadline);
+    let (response, _) = worker.handle_chain_info_query(query).await?;
+    assert!(response.info.manager.timeout_vote.is_some());
+
+    worker.handle_block_proposal(proposal).await?;
+    Ok(())
+}
+
+#[test_case(MemoryStorageBuilder::default(); "memory")]
+#[cfg_attr(feature = "rocksdb", test_case(RocksDbStorageBuilder::new().await; "rocks_db"))]
+#[cfg_attr(feature = "dynamodb", test_case(DynamoDbStorageBuilder::default(); "dynamo_db"))]
+#[cfg_attr(feature = "scylladb", test_case(ScyllaDbStorageBuilder::default(); "scylla_db"))]
+#[test_log::test(tokio::test)]
+async fn test_prune_chain_manager<B>(mut storage_builder: B) -> anyhow::Result<()>
+where
+    B: StorageBuilder,
+{
+    let storage = storage_builder.build().await?;
+    let mut signer = InMemorySigner::new(None);
+    let clock = storage_builder.clock();
+    let key_pairs = generate_key_pairs(&mut signer, 2);
+    let owner0 = AccountOwner::from(key_pairs[0]);
+    let owner1 = AccountOwner::from(key_pairs[1]);
+    let mut env = TestEnvironment::new(storage, false, false).await;
+    let chain_1_desc = env.add_root_chain(1, owner0, Amount::from_tokens(2)).await;
+    let chain_1 = chain_1_desc.id();
+
+    // Use the leader-based protocol in all rounds. The first leader is owner 1.
+    let proposed_block0 = make_first_block(chain_1)
+        .with_operation(SystemOperation::ChangeOwnership {
+            super_owners: Vec::new(),
+            owners: vec![(owner0, 100), (owner1, 100)],
+            multi_leader_rounds: 0,
+            open_multi_leader_rounds: false,
+            timeout_config: TimeoutConfig::default(),
+        })
+        .with_authenticated_signer(Some(owner0));
+    let (block0, _) = env
+        .worker()
+        .stage_block_execution(proposed_block0, None, vec![])
+        .await?;
+    let value0 = ConfirmedBlock::new(block0);
+    let certificate0 = env.make_certificate(value0.clone());
+    let response = env
+        .worker()
+        .fully_handle_certificate_with_notifications(certificate0, &())
         .await?;
 
-    // Try to execute the transfer.
+    // Let round 0 time out, so that owner 0 becomes the leader of round 1 and proposes a block.
+    clock.set(response.info.manager.round_timeout.unwrap());
+    let query = ChainInfoQuery::new(chain_1).with_timeout();
+    let (response, _) = env.worker().handle_chain_info_query(query).await?;
+    let vote = response.info.manager.timeout_vote.clone().unwrap();
+    let value_timeout = Timeout::new(chain_1, BlockHeight::from(1), Epoch::from(0));
+    let certificate_timeout = vote
+        .with_value(value_timeout.clone())
+        .unwrap()
+        .into_certificate();
     env.worker()
-        .fully_handle_certificate_with_notifications(certificate0.clone(), &())
+        .handle_timeout_certificate(certificate_timeout)
         .await?;
+    let proposal1 = make_child_block(&value0)
+        .with_simple_transfer(chain_1, Amount::from_micros(1))
+        .into_proposal_with_round(owner0, &signer, Round::SingleLeader(1))
+        .await
+        .unwrap();
+    env.worker().handle_block_proposal(proposal1).await?;
 
-    // The transfer was started..
-    let user_chain = env.worker().chain_state_view(user_id).await?;
-    assert!(user_chain.is_active());
-    assert_eq!(
-        BlockHeight::from(1),
-        user_chain.tip_state.get().next_block_height
+    let entries = env.worker().chain_manager_entries(chain_1).await?;
+    assert_matches!(
+        &entries[&Round::SingleLeader(0)][..],
+        [
+            ManagerEntry::TimeoutCertificate,
+            ManagerEntry::TimeoutVote(_)
+        ]
     );
-    assert_eq!(
-        *user_chain.execution_state.system.balance.get(),
-        Amount::from_tokens(2)
+    assert_matches!(
+        &entries[&Round::SingleLeader(1)][..],
+        [ManagerEntry::Proposal { owner, .. }, ManagerEntry::ValidatedVote(_)] if *owner == owner0
     );
-    assert_eq!(*user_chain.execution_state.system.epoch.get(), Epoch::ZERO);
 
-    // .. and the message has gone through.
-    let admin_chain = env.worker().chain_state_view(admin_id).await?;
-    assert!(admin_chain.is_active());
-    assert_eq!(admin_chain.inboxes.indices().await?.len(), 1);
-    matches!(
-        &admin_chain
-            .inboxes
-            .try_load_entry(&user_id)
-            .await?
-            .expect("Missing inbox for user chain in admin chain")
-            .added_bundles
-            .read_front(10)
-            .await?[..],
-        [bundle] if matches!(bundle.messages[..], [PostedMessage {
-            message: Message::System(SystemMessage::Credit { .. }),
-            ..
-        }])
+    // Only the timeout vote from round 0 is stale.
+    assert_eq!(env.worker().prune_chain_manager(chain_1).await?, 1);
+    let entries = env.worker().chain_manager_entries(chain_1).await?;
+    assert_matches!(
+        &entries[&Round::SingleLeader(0)][..],
+        [ManagerEntry::TimeoutCertificate]
+    );
+    assert_eq!(entries[&Round::SingleLeader(1)].len(), 2);
+
+    // Once round 1 times out, too, its proposal is stale, but the vote to validate it is kept.
+    let certificate_timeout =
+        env.make_certificate_with_round(value_timeout, Round::SingleLeader(1));
+    env.worker()
+        .handle_timeout_certificate(certificate_timeout)
+        .await?;
+    assert_eq!(env.worker().prune_chain_manager(chain_1).await?, 1);
+    let entries = env.worker().chain_manager_entries(chain_1).await?;
+    assert_eq!(entries.len(), 1);
+    assert_matches!(
+        &entries[&Round::SingleLeader(1)][..],
+        [
+            ManagerEntry::TimeoutCertificate,
+            ManagerEntry::ValidatedVote(_)
+        ]
     );
     Ok(())
 }
@@ -2845,405 +4746,469 @@ where
 #[cfg_attr(feature = "dynamodb", test_case(DynamoDbStorageBuilder::default(); "dynamo_db"))]
 #[cfg_attr(feature = "scylladb", test_case(ScyllaDbStorageBuilder::default(); "scylla_db"))]
 #[test_log::test(tokio::test)]
-async fn test_transfers_and_committee_removal<B>(mut storage_builder: B) -> anyhow::Result<()>
+async fn test_expired_proposal_is_discarded<B>(mut storage_builder: B) -> anyhow::Result<()>
 where
     B: StorageBuilder,
 {
     let storage = storage_builder.build().await?;
-    let mut env =
-        TestEnvironment::new_with_amount(storage.clone(), false, false, Amount::ZERO).await;
-    let owner1 = AccountSecretKey::generate().public().into();
-    let chain_1_desc = env.add_root_chain(1, owner1, Amount::from_tokens(3)).await;
-    let mut committees = BTreeMap::new();
-    let committee = env.committee().clone();
-    committees.insert(Epoch::ZERO, committee.clone());
-    let admin_id = env.admin_id();
-    let user_id = chain_1_desc.id();
-
-    // Have the user chain start a transfer to the admin chain.
-    let certificate0 = env.make_certificate(ConfirmedBlock::new(
-        BlockExecutionOutcome {
-            messages: vec![vec![direct_credit_message(admin_id, Amount::ONE)]],
-            previous_message_blocks: BTreeMap::new(),
-            previous_event_blocks: BTreeMap::new(),
-            events: vec![Vec::new()],
-            blobs: vec![Vec::new()],
-            state_hash: SystemExecutionState {
-                balance: Amount::from_tokens(2),
-                ..env.system_execution_state(&chain_1_desc.id())
-            }
-            .into_hash()
-            .await,
-            oracle_responses: vec![Vec::new()],
-            operation_results: vec![OperationResult::default()],
-        }
-        .with(
-            make_first_block(user_id)
-                .with_simple_transfer(admin_id, Amount::ONE)
-                .with_authenticated_signer(Some(owner1)),
-        ),
-    ));
-    // Have the admin chain create a new epoch and retire the old one immediately.
-    let committees1 = BTreeMap::from_iter([(Epoch::from(1), committee.clone())]);
-    let committee_blob = Blob::new(BlobContent::new_committee(bcs::to_bytes(&committee)?));
-    let blob_hash = committee_blob.id().hash;
-    storage.write_blob(&committee_blob).await?;
-
-    let certificate1 = env.make_certificate(ConfirmedBlock::new(
-        BlockExecutionOutcome {
-            messages: vec![vec![]; 2],
-            previous_message_blocks: BTreeMap::new(),
-            previous_event_blocks: BTreeMap::new(),
-            events: vec![
-                vec![Event {
-                    stream_id: StreamId::system(NEW_EPOCH_STREAM_NAME),
-                    index: 1,
-                    value: bcs::to_bytes(&committee_blob.id().hash).unwrap(),
-                }],
-                vec![Event {
-                    stream_id: StreamId::system(REMOVED_EPOCH_STREAM_NAME),
-                    index: 0,
-                    value: Vec::new(),
-                }],
-            ],
-            blobs: vec![Vec::new(); 2],
-            state_hash: SystemExecutionState {
-                committees: committees1.clone(),
-                used_blobs: BTreeSet::from([committee_blob.id()]),
-                epoch: Epoch::from(1),
-                ..env.system_execution_state(&admin_id)
-            }
-            .into_hash()
-            .await,
-            oracle_responses: vec![vec![OracleResponse::Blob(committee_blob.id())], vec![]],
-            operation_results: vec![OperationResult::default(); 2],
-        }
-        .with(
-            make_first_block(admin_id)
-                .with_operation(SystemOperation::Admin(AdminOperation::CreateCommittee {
-                    epoch: Epoch::from(1),
-                    blob_hash,
-                }))
-                .with_operation(SystemOperation::Admin(AdminOperation::RemoveCommittee {
-                    epoch: Epoch::ZERO,
-                })),
-        ),
-    ));
+    let mut signer = InMemorySigner::new(None);
+    let clock = storage_builder.clock();
+    let key_pairs = generate_key_pairs(&mut signer, 2);
+    let owner0 = AccountOwner::from(key_pairs[0]);
+    let owner1 = AccountOwner::from(key_pairs[1]);
+    let mut env = TestEnvironment::new(storage, false, false).await;
+    let chain_1_desc = env.add_root_chain(1, owner0, Amount::from_tokens(2)).await;
+    let chain_1 = chain_1_desc.id();
+    // Proposals expire two rounds after their own.
+    let worker = env
+        .worker()
+        .clone()
+        .with_proposal_expiry_rounds(NonZeroU32::new(2));
 
-    env.worker()
-        .fully_handle_certificate_with_notifications(certificate1.clone(), &())
+    // Use the leader-based protocol in all rounds. The first leader is owner 1.
+    let proposed_block0 = make_first_block(chain_1)
+        .with_operation(SystemOperation::ChangeOwnership {
+            super_owners: Vec::new(),
+            owners: vec![(owner0, 100), (owner1, 100)],
+            multi_leader_rounds: 0,
+            open_multi_leader_rounds: false,
+            timeout_config: TimeoutConfig::default(),
+        })
+        .with_authenticated_signer(Some(owner0));
+    let (block0, _) = worker
+        .stage_block_execution(proposed_block0, None, vec![])
         .await?;
-
-    // Try to execute the transfer from the user chain to the admin chain.
-    env.worker()
-        .fully_handle_certificate_with_notifications(certificate0.clone(), &())
+    let value0 = ConfirmedBlock::new(block0);
+    let certificate0 = env.make_certificate(value0.clone());
+    let response = worker
+        .fully_handle_certificate_with_notifications(certificate0, &())
         .await?;
 
-    {
-        // The transfer was started..
-        let user_chain = env.worker().chain_state_view(user_id).await?;
-        assert!(user_chain.is_active());
-        assert_eq!(
-            BlockHeight::from(1),
-            user_chain.tip_state.get().next_block_height
-        );
-        assert_eq!(
-            *user_chain.execution_state.system.balance.get(),
-            Amount::from_tokens(2)
-        );
-        assert_eq!(*user_chain.execution_state.system.epoch.get(), Epoch::ZERO);
-
-        // .. but the message hasn't gone through.
-        let admin_chain = env.worker().chain_state_view(admin_id).await?;
-        assert!(admin_chain.is_active());
-        assert!(admin_chain.inboxes.indices().await?.is_empty());
-    }
-
-    // Force the admin chain to receive the money nonetheless by anticipation.
-    let certificate2 = env.make_certificate(ConfirmedBlock::new(
-        BlockExecutionOutcome {
-            messages: vec![Vec::new()],
-            previous_message_blocks: BTreeMap::new(),
-            previous_event_blocks: BTreeMap::new(),
-            events: vec![Vec::new()],
-            blobs: vec![Vec::new()],
-            state_hash: SystemExecutionState {
-                committees: committees1.clone(),
-                balance: Amount::ONE,
-                used_blobs: BTreeSet::from([committee_blob.id()]),
-                epoch: Epoch::from(1),
-                ..env.system_execution_state(&admin_id)
-            }
-            .into_hash()
-            .await,
-            oracle_responses: vec![Vec::new()],
-            operation_results: vec![],
-        }
-        .with(
-            make_child_block(&certificate1.clone().into_value())
-                .with_epoch(1)
-                .with_incoming_bundle(IncomingBundle {
-                    origin: user_id,
-                    bundle: MessageBundle {
-                        certificate_hash: certificate0.hash(),
-                        height: BlockHeight::ZERO,
-                        timestamp: Timestamp::from(0),
-                        transaction_index: 0,
-                        messages: vec![
-                            system_credit_message(Amount::ONE).to_posted(0, MessageKind::Tracked)
-                        ],
-                    },
-                    action: MessageAction::Accept,
-                }),
-        ),
-    ));
-    env.worker()
-        .fully_handle_certificate_with_notifications(certificate2.clone(), &())
+    // Let round 0 time out, so that owner 0 becomes the leader of round 1 and proposes a block.
+    clock.set(response.info.manager.round_timeout.unwrap());
+    let query = ChainInfoQuery::new(chain_1).with_timeout();
+    let (response, _) = worker.handle_chain_info_query(query).await?;
+    let vote = response.info.manager.timeout_vote.clone().unwrap();
+    let value_timeout = Timeout::new(chain_1, BlockHeight::from(1), Epoch::from(0));
+    let certificate_timeout = vote
+        .with_value(value_timeout.clone())
+        .unwrap()
+        .into_certificate();
+    worker
+        .handle_timeout_certificate(certificate_timeout)
         .await?;
+    let proposal1 = make_child_block(&value0)
+        .with_simple_transfer(chain_1, Amount::from_micros(1))
+        .into_proposal_with_round(owner0, &signer, Round::SingleLeader(1))
+        .await
+        .unwrap();
+    worker.handle_block_proposal(proposal1).await?;
 
-    {
-        // The admin chain has an anticipated message.
-        let admin_chain = env.worker().chain_state_view(admin_id).await?;
-        assert!(admin_chain.is_active());
-        assert_matches!(
-            admin_chain.validate_incoming_bundles().await,
-            Err(ChainError::MissingCrossChainUpdate { .. })
-        );
-    }
+    // In round 2, the proposal from round 1 is kept.
+    let certificate_timeout =
+        env.make_certificate_with_round(value_timeout.clone(), Round::SingleLeader(1));
+    let (_, actions) = worker
+        .handle_timeout_certificate(certificate_timeout)
+        .await?;
+    assert!(!actions
+        .notifications
+        .iter()
+        .any(|notification| matches!(notification.reason, Reason::ProposalDiscarded { .. })));
+    let entries = worker.chain_manager_entries(chain_1).await?;
+    assert_matches!(
+        &entries[&Round::SingleLeader(1)][..],
+        [ManagerEntry::Proposal { .. }, ..]
+    );
 
-    // Try again to execute the transfer from the user chain to the admin chain.
-    // This time, the epoch verification should be overruled.
-    env.worker()
-        .fully_handle_certificate_with_notifications(certificate0.clone(), &())
+    // In round 3, it expired: it is discarded, and the clients are notified.
+    let certificate_timeout =
+        env.make_certificate_with_round(value_timeout, Round::SingleLeader(2));
+    let (_, actions) = worker
+        .handle_timeout_certificate(certificate_timeout)
         .await?;
+    assert!(actions.notifications.contains(&Notification {
+        chain_id: chain_1,
+        reason: Reason::ProposalDiscarded {
+            height: BlockHeight::from(1),
+            round: Round::SingleLeader(1),
+        },
+    }));
+    let entries = worker.chain_manager_entries(chain_1).await?;
+    assert!(entries
+        .values()
+        .flatten()
+        .all(|entry| !matches!(entry, ManagerEntry::Proposal { .. })));
+    Ok(())
+}
 
-    {
-        // The admin chain has no more anticipated messages.
-        let admin_chain = env.worker().chain_state_view(admin_id).await?;
-        assert!(admin_chain.is_active());
-        admin_chain.validate_incoming_bundles().await?;
-    }
+#[test_case(MemoryStorageBuilder::default(); "memory")]
+#[cfg_attr(feature = "rocksdb", test_case(RocksDbStorageBuilder::new().await; "rocks_db"))]
+#[cfg_attr(feature = "dynamodb", test_case(DynamoDbStorageBuilder::default(); "dynamo_db"))]
+#[cfg_attr(feature = "scylladb", test_case(ScyllaDbStorageBuilder::default(); "scylla_db"))]
+#[test_log::test(tokio::test)]
+async fn test_diagnose_chain<B>(mut storage_builder: B) -> anyhow::Result<()>
+where
+    B: StorageBuilder,
+{
+    let storage = storage_builder.build().await?;
+    let mut signer = InMemorySigner::new(None);
+    let owner = AccountOwner::from(signer.generate_new());
+    let mut env = TestEnvironment::new(storage, false, false).await;
+    let chain_1 = env
+        .add_root_chain(1, owner, Amount::from_tokens(2))
+        .await
+        .id();
+
+    // A chain without proposals or incoming messages isn't waiting for anything.
+    let diagnosis = env.worker().diagnose_chain(chain_1).await?;
+    assert_eq!(diagnosis.next_block_height, BlockHeight::ZERO);
+    assert!(diagnosis.manager_entries.is_empty());
+    assert!(diagnosis.missing_blobs.is_empty());
+    assert!(diagnosis.unskippable_bundles.is_empty());
+    assert!(diagnosis.undelivered_outboxes.is_empty());
+    assert_eq!(diagnosis.blocking_condition, BlockingCondition::Idle);
+
+    // Once a block is proposed, it needs a quorum to validate it.
+    let proposal = make_first_block(chain_1)
+        .with_simple_transfer(chain_1, Amount::from_micros(1))
+        .with_authenticated_signer(Some(owner))
+        .into_first_proposal(owner, &signer)
+        .await
+        .unwrap();
+    let round = proposal.content.round;
+    env.worker().handle_block_proposal(proposal).await?;
+    let diagnosis = env.worker().diagnose_chain(chain_1).await?;
+    assert_eq!(diagnosis.current_round, round);
+    assert_eq!(diagnosis.manager_entries.len(), 1);
+    assert_eq!(
+        diagnosis.blocking_condition,
+        BlockingCondition::AwaitingValidation { round, owner }
+    );
+    Ok(())
+}
 
-    // Let's make a certificate for a block creating another epoch.
-    // This one should contain previous_event_blocks.
-    let committees3 = BTreeMap::from_iter([
-        (Epoch::from(1), committee.clone()),
-        (Epoch::from(2), committee.clone()),
-    ]);
-    let certificate3 = env.make_certificate(ConfirmedBlock::new(
-        BlockExecutionOutcome {
-            messages: vec![Vec::new()],
-            previous_message_blocks: BTreeMap::new(),
-            previous_event_blocks: BTreeMap::from([(
-                StreamId::system(NEW_EPOCH_STREAM_NAME),
-                (certificate1.hash(), BlockHeight(0)),
-            )]),
-            events: vec![vec![Event {
-                stream_id: StreamId::system(NEW_EPOCH_STREAM_NAME),
-                index: 2,
-                value: bcs::to_bytes(&committee_blob.id().hash).unwrap(),
-            }]],
-            blobs: vec![Vec::new()],
-            state_hash: SystemExecutionState {
-                committees: committees3.clone(),
-                balance: Amount::ONE,
-                used_blobs: BTreeSet::from([committee_blob.id()]),
-                epoch: Epoch::from(2),
-                ..env.system_execution_state(&admin_id)
-            }
-            .into_hash()
-            .await,
-            oracle_responses: vec![Vec::new()],
-            operation_results: vec![OperationResult::default()],
-        }
-        .with(
-            make_child_block(&certificate2.into_value())
-                .with_epoch(1)
-                .with_operation(SystemOperation::Admin(AdminOperation::CreateCommittee {
-                    epoch: Epoch::from(2),
-                    blob_hash,
-                })),
-        ),
-    ));
+#[test_case(MemoryStorageBuilder::default(); "memory")]
+#[cfg_attr(feature = "rocksdb", test_case(RocksDbStorageBuilder::new().await; "rocks_db"))]
+#[cfg_attr(feature = "dynamodb", test_case(DynamoDbStorageBuilder::default(); "dynamo_db"))]
+#[cfg_attr(feature = "scylladb", test_case(ScyllaDbStorageBuilder::default(); "scylla_db"))]
+#[test_log::test(tokio::test)]
+async fn test_epoch_migration<B>(mut storage_builder: B) -> anyhow::Result<()>
+where
+    B: StorageBuilder,
+{
+    let storage = storage_builder.build().await?;
+    let mut signer = InMemorySigner::new(None);
+    let owner = AccountOwner::from(signer.generate_new());
+    let mut env = TestEnvironment::new(storage.clone(), false, false).await;
+    let chain_1 = env
+        .add_root_chain(1, owner, Amount::from_tokens(2))
+        .await
+        .id();
+    let worker = env.worker().clone();
+    let admin_id = env.admin_id();
 
-    env.worker()
-        .fully_handle_certificate_with_notifications(certificate3, &())
+    // Epoch 1 doesn't exist yet, so the chain can't be migrated to it.
+    let (response, _) = worker
+        .handle_chain_info_query(ChainInfoQuery::new(chain_1))
+        .await?;
+    assert_eq!(response.info.migrated_epoch, None);
+    assert_eq!(worker.latest_epoch(Epoch::ZERO).await?, Epoch::ZERO);
+    assert_eq!(worker.migrate_chains_to_epoch(Epoch::from(1)).await, 0);
+
+    // Once the admin chain has created it, the loaded chains are moved to it.
+    let committee_blob = Blob::new(BlobContent::new_committee(bcs::to_bytes(env.committee())?));
+    storage.write_blob(&committee_blob).await?;
+    let event_id = EventId {
+        chain_id: admin_id,
+        stream_id: StreamId::system(NEW_EPOCH_STREAM_NAME),
+        index: 1,
+    };
+    storage
+        .write_events([(event_id, bcs::to_bytes(&committee_blob.id().hash)?)])
+        .await?;
+    assert_eq!(worker.latest_epoch(Epoch::ZERO).await?, Epoch::from(1));
+    assert!(worker.migrate_chains_to_epoch(Epoch::from(1)).await > 0);
+    let (response, _) = worker
+        .handle_chain_info_query(ChainInfoQuery::new(chain_1))
         .await?;
+    assert_eq!(response.info.epoch, Epoch::ZERO);
+    assert_eq!(response.info.migrated_epoch, Some(Epoch::from(1)));
+    let chain = worker.chain_state_view(chain_1).await?;
+    assert_eq!(*chain.execution_state.system.epoch.get(), Epoch::ZERO);
+    drop(chain);
+    assert_eq!(worker.migrate_chains_to_epoch(Epoch::from(1)).await, 0);
+
+    // The background task saves the last epoch it migrated the chains to, and resumes from it.
+    let checkpoint_name = "epoch_migration_test";
+    let task = tokio::spawn(
+        worker
+            .clone()
+            .run_epoch_migration(Duration::from_millis(10), checkpoint_name.to_string()),
+    );
+    while storage
+        .read_task_checkpoint(checkpoint_name)
+        .await?
+        .is_none()
+    {
+        linera_base::time::timer::sleep(Duration::from_millis(10)).await;
+    }
+    task.abort();
+    assert_eq!(
+        storage.read_task_checkpoint(checkpoint_name).await?,
+        Some(bcs::to_bytes(&Epoch::from(1))?)
+    );
 
+    // A removed epoch isn't the latest one anymore.
+    let removal_id = EventId {
+        chain_id: admin_id,
+        stream_id: StreamId::system(REMOVED_EPOCH_STREAM_NAME),
+        index: 1,
+    };
+    storage.write_events([(removal_id, Vec::new())]).await?;
+    assert_eq!(worker.latest_epoch(Epoch::ZERO).await?, Epoch::ZERO);
     Ok(())
 }
 
-#[test(tokio::test)]
-async fn test_cross_chain_helper() -> anyhow::Result<()> {
-    let store_config = MemoryDatabase::new_test_config().await?;
-    let namespace = generate_test_namespace();
-    let store = DbStorage::<MemoryDatabase, _>::new_for_testing(
-        store_config,
-        &namespace,
-        None,
-        TestClock::new(),
-    )
-    .await?;
-    let env = TestEnvironment::new(store, true, false).await;
-    let committees = BTreeMap::from([(Epoch::from(1), env.committee().clone())]);
+/// Tests that certificates signed according to the weights before or after a validator weight
+/// update are accepted whether or not the chain has processed the update, and that the previous
+/// weights are only accepted during the grace window, also for epochs the chain doesn't have.
+#[test_case(MemoryStorageBuilder::default(); "memory")]
+#[cfg_attr(feature = "rocksdb", test_case(RocksDbStorageBuilder::new().await; "rocks_db"))]
+#[cfg_attr(feature = "dynamodb", test_case(DynamoDbStorageBuilder::default(); "dynamo_db"))]
+#[cfg_attr(feature = "scylladb", test_case(ScyllaDbStorageBuilder::default(); "scylla_db"))]
+#[test_log::test(tokio::test)]
+async fn test_certificates_across_validator_weight_update<B>(
+    mut storage_builder: B,
+) -> anyhow::Result<()>
+where
+    B: StorageBuilder,
+{
+    let storage = storage_builder.build().await?;
+    let keypairs = [ValidatorKeypair::generate(), ValidatorKeypair::generate()];
+    let [key0, key1] = keypairs.each_ref().map(|keypair| keypair.public_key);
+    // The first validator has a quorum before the update, and the second one after it.
+    let initial = Committee::make_simple(vec![
+        (key0, AccountPublicKey::test_key(0)),
+        (key1, AccountPublicKey::test_key(1)),
+    ])
+    .with_votes(&BTreeMap::from([(key0, 300)]))
+    .unwrap();
+    let votes = BTreeMap::from([(key0, 100), (key1, 300)]);
+    let updated = initial.with_votes(&votes).unwrap();
+    let committee_blob = Blob::new_committee(bcs::to_bytes(&initial)?);
+    storage.write_blob(&committee_blob).await?;
+    let admin_id = dummy_chain_description(0).id();
+    storage
+        .write_network_description(&NetworkDescription {
+            admin_chain_id: admin_id,
+            genesis_config_hash: CryptoHash::test_hash("genesis config"),
+            genesis_timestamp: Timestamp::from(0),
+            genesis_committee_blob_hash: committee_blob.id().hash,
+            name: "test network".to_string(),
+        })
+        .await?;
 
-    let chain_0 = env.admin_description.clone();
-    let chain_1 = dummy_chain_description(1);
+    let value = Timeout::new(dummy_chain_description(1).id(), BlockHeight(1), Epoch::ZERO);
+    let round = Round::MultiLeader(0);
+    let [old_certificate, new_certificate] = keypairs.each_ref().map(|keypair| {
+        let vote = LiteVote::new(LiteValue::new(&value), round, &keypair.secret_key);
+        GenericCertificate::new(
+            value.clone(),
+            round,
+            vec![(vote.public_key, vote.signature)],
+        )
+    });
+    let (in_grace, after_grace) = (Timestamp::from(1_000), Timestamp::from(1_001));
 
-    let key_pair0 = AccountSecretKey::generate();
-    let id0 = chain_0.id();
-    let id1 = chain_1.id();
+    let committees = CommitteeCache::default();
 
-    let certificate0 = env
-        .make_transfer_certificate_for_epoch(
-            chain_0.clone(),
-            key_pair0.public(),
-            key_pair0.public().into(),
-            AccountOwner::CHAIN,
-            Recipient::chain(id1),
-            Amount::ONE,
-            Vec::new(),
-            Epoch::ZERO,
-            Amount::ONE,
-            BTreeMap::new(),
-            vec![],
-        )
-        .await;
-    let certificate1 = env
-        .make_transfer_certificate_for_epoch(
-            chain_0.clone(),
-            key_pair0.public(),
-            key_pair0.public().into(),
-            AccountOwner::CHAIN,
-            Recipient::chain(id1),
-            Amount::ONE,
-            Vec::new(),
-            Epoch::ZERO,
-            Amount::ONE,
-            BTreeMap::new(),
-            vec![&certificate0],
-        )
-        .await;
-    let certificate2 = env
-        .make_transfer_certificate_for_epoch(
-            chain_0.clone(),
-            key_pair0.public(),
-            key_pair0.public().into(),
-            AccountOwner::CHAIN,
-            Recipient::chain(id1),
-            Amount::ONE,
-            Vec::new(),
-            Epoch::from(1),
-            Amount::ONE,
-            BTreeMap::new(),
-            vec![&certificate1, &certificate0],
-        )
-        .await;
-    // Weird case: epoch going backward.
-    let certificate3 = env
-        .make_transfer_certificate_for_epoch(
-            chain_0.clone(),
-            key_pair0.public(),
-            key_pair0.public().into(),
-            AccountOwner::CHAIN,
-            Recipient::chain(id1),
-            Amount::ONE,
-            Vec::new(),
-            Epoch::ZERO,
-            Amount::ONE,
-            BTreeMap::new(),
-            vec![&certificate2, &certificate1, &certificate0],
+    // The update isn't processed by the admin chain yet.
+    check_certificate_with_weight_updates(
+        &storage,
+        &committees,
+        &old_certificate,
+        in_grace,
+        &initial,
+        None,
+    )
+    .await?;
+    assert_matches!(
+        check_certificate_with_weight_updates(
+            &storage,
+            &committees,
+            &new_certificate,
+            in_grace,
+            &initial,
+            None
         )
-        .await;
-    let bundles0 = certificate0.message_bundles_for(id1).collect::<Vec<_>>();
-    let bundles1 = certificate1.message_bundles_for(id1).collect::<Vec<_>>();
-    let bundles2 = certificate2.message_bundles_for(id1).collect::<Vec<_>>();
-    let bundles3 = certificate3.message_bundles_for(id1).collect::<Vec<_>>();
-    let bundles01 = Vec::from_iter(bundles0.iter().cloned().chain(bundles1.iter().cloned()));
-    let bundles012 = Vec::from_iter(bundles01.iter().cloned().chain(bundles2.iter().cloned()));
-    let bundles0123 = Vec::from_iter(bundles012.iter().cloned().chain(bundles3.iter().cloned()));
-
-    fn without_epochs<'a>(
-        bundles: impl IntoIterator<Item = &'a (Epoch, MessageBundle)>,
-    ) -> Vec<MessageBundle> {
-        bundles
-            .into_iter()
-            .map(|(_, bundle)| bundle.clone())
-            .collect()
-    }
+        .await,
+        Err(WorkerError::ChainError(_))
+    );
 
-    let helper = CrossChainUpdateHelper {
-        allow_messages_from_deprecated_epochs: true,
-        current_epoch: Epoch::from(1),
-        committees: &committees,
+    let epoch_committee = EpochCommittee {
+        committee: updated.clone(),
+        superseded: Some((initial.clone(), in_grace)),
     };
-    // Epoch is not tested when `allow_messages_from_deprecated_epochs` is true.
+    storage
+        .write_epoch_committee(Epoch::ZERO, &epoch_committee)
+        .await?;
+    committees.invalidate(Epoch::ZERO);
+    let epochs = Epoch::ZERO..=Epoch::ZERO;
     assert_eq!(
-        helper.select_message_bundles(&id0, id1, BlockHeight::ZERO, None, bundles01.clone())?,
-        without_epochs(&bundles01)
+        storage.epoch_committees_for(epochs.clone()).await?[&Epoch::ZERO],
+        epoch_committee
     );
-    // Received heights is removing prefixes.
     assert_eq!(
-        helper.select_message_bundles(&id0, id1, BlockHeight::from(1), None, bundles01.clone())?,
-        without_epochs(&bundles1)
+        storage.committees_for(epochs.clone()).await?[&Epoch::ZERO],
+        updated
     );
     assert_eq!(
-        helper.select_message_bundles(&id0, id1, BlockHeight::from(2), None, bundles01.clone())?,
-        vec![]
+        storage.initial_committees_for(epochs).await?[&Epoch::ZERO],
+        initial
     );
-    // Order of certificates is checked.
+
+    // A chain that hasn't processed the update yet accepts the new weights.
+    check_certificate_with_weight_updates(
+        &storage,
+        &committees,
+        &new_certificate,
+        after_grace,
+        &initial,
+        None,
+    )
+    .await?;
+
+    // A chain that has processed it accepts the previous weights during the grace window.
+    check_certificate_with_weight_updates(
+        &storage,
+        &committees,
+        &old_certificate,
+        in_grace,
+        &updated,
+        Some(&initial),
+    )
+    .await?;
     assert_matches!(
-        helper.select_message_bundles(
-            &id0,
-            id1,
-            BlockHeight::ZERO,
-            None,
-            Vec::from_iter(bundles1.iter().cloned().chain(bundles0.iter().cloned()))
-        ),
-        Err(WorkerError::InvalidCrossChainRequest)
+        check_certificate_with_weight_updates(
+            &storage,
+            &committees,
+            &old_certificate,
+            after_grace,
+            &updated,
+            None
+        )
+        .await,
+        Err(WorkerError::ChainError(_))
     );
 
-    let helper = CrossChainUpdateHelper {
-        allow_messages_from_deprecated_epochs: false,
-        current_epoch: Epoch::from(1),
-        committees: &committees,
-    };
-    // Epoch is tested when `allow_messages_from_deprecated_epochs` is false.
-    assert_eq!(
-        helper.select_message_bundles(&id0, id1, BlockHeight::ZERO, None, bundles01.clone())?,
-        vec![]
-    );
-    // A certificate with a recent epoch certifies all the previous blocks.
-    assert_eq!(
-        helper.select_message_bundles(&id0, id1, BlockHeight::ZERO, None, bundles0123.clone())?,
-        without_epochs(&bundles012)
+    // So does a chain that doesn't have the epoch anymore, and uses the stored committee.
+    let stored = committees
+        .get_or_load(&storage, Epoch::ZERO)
+        .await?
+        .expect("the epoch committee was written");
+    for (certificate, local_time) in [
+        (&old_certificate, in_grace),
+        (&new_certificate, after_grace),
+    ] {
+        check_certificate_with_weight_updates(
+            &storage,
+            &committees,
+            certificate,
+            local_time,
+            &stored.committee,
+            stored.superseded_committee(local_time),
+        )
+        .await?;
+    }
+    assert_matches!(
+        check_certificate_with_weight_updates(
+            &storage,
+            &committees,
+            &old_certificate,
+            after_grace,
+            &stored.committee,
+            stored.superseded_committee(after_grace)
+        )
+        .await,
+        Err(WorkerError::ChainError(_))
     );
-    // Received heights is still removing prefixes.
+    Ok(())
+}
+
+/// Tests that a block can't extend the grace window of a validator weight update by being
+/// backdated: the window is measured with the validator's clock, not the block's timestamp.
+#[test_log::test(tokio::test)]
+async fn test_backdated_block_after_validator_weight_update() -> anyhow::Result<()> {
+    let dry_run = DryRunWorker::builder().with_validators(4).build().await?;
+    let worker = &dry_run.worker;
+    let validators = dry_run
+        .committee
+        .validators()
+        .keys()
+        .copied()
+        .collect::<Vec<_>>();
+    // Three of the four validators have a quorum before the update, but not after it.
+    let (light, heavy) = validators.split_at(3);
+    dry_run.clock.set(Timestamp::from(1_000));
+    let block = make_first_block(dry_run.admin_id())
+        .with_timestamp(1_000)
+        .with_operation(SystemOperation::Admin(
+            AdminOperation::UpdateValidatorWeights {
+                epoch: Epoch::ZERO,
+                votes: BTreeMap::from([(heavy[0], 100)]),
+                grace_period: TimeDelta::from_micros(1_000),
+            },
+        ));
+    let certificate = dry_run.make_confirmed_certificate(block).await?;
+    worker
+        .fully_handle_certificate_with_notifications(certificate.clone(), &())
+        .await?;
+
+    // The admin chain recorded the updated committee for other chains, with the superseded one.
+    let epoch_committees = worker
+        .storage_client()
+        .epoch_committees_for(Epoch::ZERO..=Epoch::ZERO)
+        .await?;
+    let updated = dry_run
+        .committee
+        .with_votes(&BTreeMap::from([(heavy[0], 100)]))
+        .unwrap();
     assert_eq!(
-        helper.select_message_bundles(&id0, id1, BlockHeight::from(1), None, bundles012.clone())?,
-        without_epochs(bundles1.iter().chain(&bundles2))
+        epoch_committees[&Epoch::ZERO],
+        EpochCommittee {
+            committee: updated,
+            superseded: Some((dry_run.committee.clone(), Timestamp::from(2_000))),
+        }
     );
-    // Anticipated messages re-certify blocks up to the given height.
-    assert_eq!(
-        helper.select_message_bundles(
-            &id0,
-            id1,
-            BlockHeight::from(1),
-            Some(BlockHeight::from(1)),
-            bundles01.clone()
-        )?,
-        without_epochs(&bundles1)
+
+    // The next block claims to be from within the grace window, and is only signed by the
+    // validators that lost their quorum.
+    dry_run.clock.set(Timestamp::from(1_500));
+    let block = make_child_block(certificate.value()).with_timestamp(1_500);
+    let (block, _) = worker
+        .stage_block_execution(block, None, Vec::new())
+        .await?;
+    let backdated = dry_run.make_certificate_signed_by(
+        ConfirmedBlock::new(block),
+        Round::MultiLeader(0),
+        light,
     );
-    assert_eq!(
-        helper.select_message_bundles(
-            &id0,
-            id1,
-            BlockHeight::ZERO,
-            Some(BlockHeight::from(1)),
-            bundles01.clone()
-        )?,
-        without_epochs(&bundles01)
+    assert!(backdated.check(&dry_run.committee).is_ok());
+
+    // Once the grace window has ended on the validator's clock, the certificate is rejected.
+    dry_run.clock.set(Timestamp::from(2_500));
+    assert_matches!(
+        worker
+            .fully_handle_certificate_with_notifications(backdated.clone(), &())
+            .await,
+        Err(WorkerError::ChainError(_))
     );
+
+    // Before that, it is accepted.
+    dry_run.clock.set(Timestamp::from(2_000));
+    worker
+        .fully_handle_certificate_with_notifications(backdated, &())
+        .await?;
     Ok(())
 }
 
@@ -3252,239 +5217,403 @@ async fn test_cross_chain_helper() -> anyhow::Result<()> {
 #[cfg_attr(feature = "dynamodb", test_case(DynamoDbStorageBuilder::default(); "dynamo_db"))]
 #[cfg_attr(feature = "scylladb", test_case(ScyllaDbStorageBuilder::default(); "scylla_db"))]
 #[test_log::test(tokio::test)]
-async fn test_timeouts<B>(mut storage_builder: B) -> anyhow::Result<()>
+async fn test_signing_fence<B>(mut storage_builder: B) -> anyhow::Result<()>
 where
     B: StorageBuilder,
 {
     let storage = storage_builder.build().await?;
     let mut signer = InMemorySigner::new(None);
-    let clock = storage_builder.clock();
-    let key_pairs = generate_key_pairs(&mut signer, 2);
-    let owner0 = AccountOwner::from(key_pairs[0]);
-    let owner1 = AccountOwner::from(key_pairs[1]);
-    let mut env = TestEnvironment::new(storage, false, false).await;
-    let chain_1_desc = env.add_root_chain(1, owner0, Amount::from_tokens(2)).await;
-    let small_transfer = Amount::from_micros(1);
-    let chain_1 = chain_1_desc.id();
-
-    // Add another owner and use the leader-based protocol in all rounds.
-    let proposed_block0 = make_first_block(chain_1)
-        .with_operation(SystemOperation::ChangeOwnership {
-            super_owners: Vec::new(),
-            owners: vec![(owner0, 100), (owner1, 100)],
-            multi_leader_rounds: 0,
-            open_multi_leader_rounds: false,
-            timeout_config: TimeoutConfig::default(),
-        })
-        .with_authenticated_signer(Some(owner0));
-    let (block0, _) = env
-        .worker()
-        .stage_block_execution(proposed_block0, None, vec![])
-        .await?;
-    let value0 = ConfirmedBlock::new(block0);
-    let certificate0 = env.make_certificate(value0.clone());
-    let response = env
+    let owner = AccountOwner::from(signer.generate_new());
+    let mut env = TestEnvironment::new(storage.clone(), false, false).await;
+    let chain_1 = env
+        .add_root_chain(1, owner, Amount::from_tokens(2))
+        .await
+        .id();
+    let chain_2 = env
+        .add_root_chain(2, owner, Amount::from_tokens(2))
+        .await
+        .id();
+    let secret_key = env
         .worker()
-        .fully_handle_certificate_with_notifications(certificate0, &())
-        .await?;
+        .chain_worker_config
+        .key_pair
+        .as_ref()
+        .unwrap()
+        .copy();
+    let make_instance = |name: &str| {
+        WorkerState::new(name.to_string(), Some(secret_key.copy()), storage.clone())
+            .with_signing_fence(Some(SigningFence::standby(0, Duration::ZERO)))
+    };
+    let instance_a = make_instance("Instance A");
+    let instance_b = make_instance("Instance B");
+    let make_proposal = |chain_id| {
+        make_first_block(chain_id)
+            .with_simple_transfer(chain_id, Amount::from_micros(1))
+            .with_authenticated_signer(Some(owner))
+            .into_first_proposal(owner, &signer)
+    };
 
-    // The leader sequence is pseudorandom but deterministic. The first leader is owner 1.
-    assert_eq!(response.info.manager.leader, Some(owner1));
+    // A standby neither votes nor saves the chain.
+    assert_eq!(instance_a.signing_term()?, None);
+    assert_matches!(
+        instance_a
+            .handle_block_proposal(make_proposal(chain_1).await?)
+            .await,
+        Err(WorkerError::NotPrimary)
+    );
+    let chain = instance_a.chain_state_view(chain_1).await?;
+    assert!(chain.manager.validated_vote().is_none());
+    drop(chain);
 
-    // So owner 0 cannot propose a block in this round. And the next round hasn't started yet.
-    let proposal = make_child_block(&value0.clone())
-        .with_simple_transfer(chain_1, small_transfer)
-        .into_proposal_with_round(owner0, &signer, Round::SingleLeader(0))
-        .await
-        .unwrap();
-    let result = env.worker().handle_block_proposal(proposal).await;
-    assert_matches!(result, Err(WorkerError::InvalidOwner));
-    let proposal = make_child_block(&value0.clone())
-        .with_simple_transfer(chain_1, small_transfer)
-        .into_proposal_with_round(owner0, &signer, Round::SingleLeader(1))
-        .await
-        .unwrap();
-    let result = env.worker().handle_block_proposal(proposal).await;
+    // Once promoted, it votes.
+    let term_a = instance_a.promote_to_primary().await?;
+    assert_eq!(instance_a.signing_term()?, Some(term_a));
+    let (response, _actions) = instance_a
+        .handle_block_proposal(make_proposal(chain_1).await?)
+        .await?;
+    assert!(response.signature.is_some());
 
-    assert_matches!(result, Err(WorkerError::ChainError(ref error))
-        if matches!(**error, ChainError::WrongRound(Round::SingleLeader(0)))
+    // Promoting the other instance fences off the first one, which demotes itself.
+    let term_b = instance_b.promote_to_primary().await?;
+    assert!(term_b > term_a);
+    assert_matches!(
+        instance_a
+            .handle_block_proposal(make_proposal(chain_2).await?)
+            .await,
+        Err(WorkerError::NotPrimary)
     );
+    assert_eq!(instance_a.signing_term()?, None);
+    let (response, _actions) = instance_b
+        .handle_block_proposal(make_proposal(chain_2).await?)
+        .await?;
+    assert!(response.signature.is_some());
 
-    // The round hasn't timed out yet, so the validator won't sign a leader timeout vote yet.
-    let query = ChainInfoQuery::new(chain_1).with_timeout();
-    let (response, _) = env.worker().handle_chain_info_query(query).await?;
-    assert!(response.info.manager.timeout_vote.is_none());
+    // Without a fence, there is nothing to promote.
+    assert_matches!(
+        env.worker().promote_to_primary().await,
+        Err(WorkerError::NoSigningFence)
+    );
+    Ok(())
+}
 
-    // Set the clock to the end of the round.
-    clock.set(response.info.manager.round_timeout.unwrap());
+#[test_case(MemoryStorageBuilder::default(); "memory")]
+#[cfg_attr(feature = "rocksdb", test_case(RocksDbStorageBuilder::new().await; "rocks_db"))]
+#[cfg_attr(feature = "dynamodb", test_case(DynamoDbStorageBuilder::default(); "dynamo_db"))]
+#[cfg_attr(feature = "scylladb", test_case(ScyllaDbStorageBuilder::default(); "scylla_db"))]
+#[test_log::test(tokio::test)]
+async fn test_concurrent_promotions<B>(mut storage_builder: B) -> anyhow::Result<()>
+where
+    B: StorageBuilder,
+{
+    let storage = storage_builder.build().await?;
+    let fence_a = SigningFence::standby(0, Duration::from_millis(50));
+    let fence_b = SigningFence::standby(0, Duration::from_millis(50));
+
+    // Both standbys read the same term, but only one of them becomes the primary.
+    let (result_a, result_b) = futures::join!(fence_a.promote(&storage), fence_b.promote(&storage));
+    let term = match (result_a, result_b) {
+        (Ok(term), Err(WorkerError::NotPrimary)) => {
+            assert!(fence_a.is_primary() && !fence_b.is_primary());
+            term
+        }
+        (Err(WorkerError::NotPrimary), Ok(term)) => {
+            assert!(fence_b.is_primary() && !fence_a.is_primary());
+            term
+        }
+        results => panic!("Expected exactly one promotion to succeed, got {results:?}"),
+    };
+    assert_eq!(storage.read_signing_term(0).await?, term);
+    Ok(())
+}
 
-    // Now the validator will sign a leader timeout vote.
-    let query = ChainInfoQuery::new(chain_1).with_timeout();
-    let (response, _) = env.worker().handle_chain_info_query(query).await?;
-    let vote = response.info.manager.timeout_vote.clone().unwrap();
-    let value_timeout = Timeout::new(chain_1, BlockHeight::from(1), Epoch::from(0));
+#[test_case(MemoryStorageBuilder::default(); "memory")]
+#[cfg_attr(feature = "rocksdb", test_case(RocksDbStorageBuilder::new().await; "rocks_db"))]
+#[cfg_attr(feature = "dynamodb", test_case(DynamoDbStorageBuilder::default(); "dynamo_db"))]
+#[cfg_attr(feature = "scylladb", test_case(ScyllaDbStorageBuilder::default(); "scylla_db"))]
+#[test_log::test(tokio::test)]
+async fn test_save_interleaved_with_promotion<B>(mut storage_builder: B) -> anyhow::Result<()>
+where
+    B: StorageBuilder,
+{
+    let storage = storage_builder.build().await?;
+    let mut signer = InMemorySigner::new(None);
+    let owner = AccountOwner::from(signer.generate_new());
+    let mut env = TestEnvironment::new(storage.clone(), false, false).await;
+    let chain_id = env
+        .add_root_chain(1, owner, Amount::from_tokens(2))
+        .await
+        .id();
+    let fence_a = SigningFence::standby(0, Duration::ZERO);
+    let fence_b = SigningFence::standby(0, Duration::from_millis(200));
+    let term_a = fence_a.promote(&storage).await?;
+
+    // The old primary loads the chain and passes the check before saving it.
+    let mut chain = storage.load_chain(chain_id).await?;
+    fence_a.ensure_is_primary(&storage, Some(term_a)).await?;
+    chain.execution_state.system.balance.set(Amount::ONE);
+
+    // The other instance is promoted while the old primary is still saving the chain.
+    let promotion = async {
+        let term_b = fence_b.promote(&storage).await?;
+        // Once promoted, the new primary loads the chain the old primary saved.
+        let chain = storage.load_chain(chain_id).await?;
+        assert_eq!(*chain.execution_state.system.balance.get(), Amount::ONE);
+        anyhow::Ok(term_b)
+    };
+    let save = async {
+        linera_base::time::timer::sleep(Duration::from_millis(20)).await;
+        assert!(!fence_b.is_primary());
+        chain.save().await?;
+        // The old primary then withholds its vote.
+        assert_matches!(
+            fence_a.ensure_is_primary(&storage, Some(term_a)).await,
+            Err(WorkerError::NotPrimary)
+        );
+        anyhow::Ok(())
+    };
+    let (term_b, ()) = futures::try_join!(promotion, save)?;
+    assert!(term_b > term_a);
+    assert!(fence_b.is_primary() && !fence_a.is_primary());
+    Ok(())
+}
 
-    // Once we provide the validator with a timeout certificate, the next round starts, where owner
-    // 0 happens to be the leader.
-    let certificate_timeout = vote
-        .with_value(value_timeout.clone())
-        .unwrap()
-        .into_certificate();
-    let (response, _) = env
+#[test_case(MemoryStorageBuilder::default(); "memory")]
+#[cfg_attr(feature = "rocksdb", test_case(RocksDbStorageBuilder::new().await; "rocks_db"))]
+#[cfg_attr(feature = "dynamodb", test_case(DynamoDbStorageBuilder::default(); "dynamo_db"))]
+#[cfg_attr(feature = "scylladb", test_case(ScyllaDbStorageBuilder::default(); "scylla_db"))]
+#[test_log::test(tokio::test)]
+async fn test_vote_audit_log<B>(mut storage_builder: B) -> anyhow::Result<()>
+where
+    B: StorageBuilder,
+{
+    let storage = storage_builder.build().await?;
+    let mut signer = InMemorySigner::new(None);
+    let owner = AccountOwner::from(signer.generate_new());
+    let mut env = TestEnvironment::new(storage, false, false).await;
+    let chain_1 = env
+        .add_root_chain(1, owner, Amount::from_tokens(2))
+        .await
+        .id();
+    let validator = env
         .worker()
-        .handle_timeout_certificate(certificate_timeout)
-        .await?;
-    assert_eq!(response.info.manager.leader, Some(owner0));
+        .chain_worker_config
+        .key_pair()
+        .unwrap()
+        .public();
 
-    // Now owner 0 can propose a block, but owner 1 can't.
-    let proposed_block1 =
-        make_child_block(&value0.clone()).with_simple_transfer(chain_1, small_transfer);
-    let (block1, _) = env
-        .worker()
-        .stage_block_execution(proposed_block1.clone(), None, vec![])
-        .await?;
-    let proposal1_wrong_owner = proposed_block1
-        .clone()
-        .with_authenticated_signer(Some(owner1))
-        .into_proposal_with_round(owner1, &signer, Round::SingleLeader(1))
+    let proposal = make_first_block(chain_1)
+        .with_simple_transfer(chain_1, Amount::from_micros(1))
+        .with_authenticated_signer(Some(owner))
+        .into_first_proposal(owner, &signer)
         .await
         .unwrap();
-    let result = env
-        .worker()
-        .handle_block_proposal(proposal1_wrong_owner)
+    let round = proposal.content.round;
+    let (response, _) = env.worker().handle_block_proposal(proposal).await?;
+    let vote = response.info.manager.pending.unwrap();
+
+    // Both the proposal and our vote for it are recorded.
+    let query = ChainInfoQuery::new(chain_1).with_vote_audit_log(10);
+    let (response, _) = env.worker().handle_chain_info_query(query).await?;
+    let log = response.info.requested_vote_audit_log;
+    assert_eq!(log.len(), 2);
+    assert_eq!(log[0].kind, VoteKind::Proposal);
+    assert_eq!(log[0].voter, Voter::Owner(owner));
+    assert_eq!(log[1].kind, VoteKind::from(vote.value.kind));
+    assert_eq!(log[1].voter, Voter::Validator(validator));
+    for record in &log {
+        assert_eq!(record.round, round);
+        assert_eq!(record.height, BlockHeight::ZERO);
+        assert_eq!(record.value_hash, vote.value.value_hash);
+    }
+
+    // Only the latest entries are returned if fewer are requested.
+    let query = ChainInfoQuery::new(chain_1).with_vote_audit_log(1);
+    let (response, _) = env.worker().handle_chain_info_query(query).await?;
+    assert_eq!(response.info.requested_vote_audit_log, log[1..]);
+    Ok(())
+}
+
+#[test_case(MemoryStorageBuilder::default(); "memory")]
+#[cfg_attr(feature = "rocksdb", test_case(RocksDbStorageBuilder::new().await; "rocks_db"))]
+#[cfg_attr(feature = "dynamodb", test_case(DynamoDbStorageBuilder::default(); "dynamo_db"))]
+#[cfg_attr(feature = "scylladb", test_case(ScyllaDbStorageBuilder::default(); "scylla_db"))]
+#[test_log::test(tokio::test)]
+async fn test_partially_synchronized_chain<B>(mut storage_builder: B) -> anyhow::Result<()>
+where
+    B: StorageBuilder,
+{
+    let mut signer = InMemorySigner::new(None);
+    let sender_pubkey = signer.generate_new();
+    let mut env = TestEnvironment::new(storage_builder.build().await?, false, false).await;
+    let chain_1_desc = env
+        .add_root_chain(1, sender_pubkey.into(), Amount::from_tokens(5))
         .await;
-    assert_matches!(result, Err(WorkerError::InvalidOwner));
-    let proposal1 = proposed_block1
-        .clone()
-        .into_proposal_with_round(owner0, &signer, Round::SingleLeader(1))
+    let chain_1 = chain_1_desc.id();
+    let chain_2 = env
+        .add_root_chain(2, sender_pubkey.into(), Amount::ZERO)
         .await
-        .unwrap();
-    let (response, _) = env.worker().handle_block_proposal(proposal1).await?;
-    let value1 = ValidatedBlock::new(block1.clone());
-
-    // If we send the validated block certificate to the worker, it votes to confirm.
-    let vote = response.info.manager.pending.clone().unwrap();
-    let certificate1 = vote.with_value(value1.clone()).unwrap().into_certificate();
-    let (response, _) = env
-        .worker()
-        .handle_validated_certificate(certificate1.clone())
+        .id();
+    env.worker()
+        .set_application_filter(chain_1, BTreeSet::new())
         .await?;
-    let vote = response.info.manager.pending.as_ref().unwrap();
-    let value = ConfirmedBlock::new(block1.clone());
-    assert_eq!(vote.value, LiteValue::new(&value));
 
-    // Instead of submitting the confirmed block certificate, let rounds 2 to 4 time out, too.
-    let certificate_timeout =
-        env.make_certificate_with_round(value_timeout.clone(), Round::SingleLeader(4));
-    let (response, _) = env
-        .worker()
-        .handle_timeout_certificate(certificate_timeout)
+    let certificate = env
+        .make_simple_transfer_certificate(
+            chain_1_desc,
+            sender_pubkey,
+            chain_2,
+            Amount::ONE,
+            Vec::new(),
+            Amount::from_tokens(4),
+            vec![],
+        )
+        .await;
+    env.worker()
+        .fully_handle_certificate_with_notifications(certificate.clone(), &())
         .await?;
-    assert_eq!(response.info.manager.leader, Some(owner1));
-    assert_eq!(response.info.manager.current_round, Round::SingleLeader(5));
 
-    // Create block2, also at height 1, but different from block 1.
-    let amount = Amount::from_tokens(1);
-    let proposed_block2 = make_child_block(&value0.clone()).with_simple_transfer(chain_1, amount);
-    let (block2, _) = env
+    // The block is applied without being executed: only the header is stored.
+    let chain = env.worker().chain_state_view(chain_1).await?;
+    assert_eq!(chain.tip_state.get().next_block_height, BlockHeight(1));
+    assert_eq!(chain.tip_state.get().block_hash, Some(certificate.hash()));
+    assert_eq!(
+        *chain.execution_state.system.balance.get(),
+        Amount::from_tokens(5)
+    );
+    drop(chain);
+    let header = env
         .worker()
-        .stage_block_execution(proposed_block2.clone(), None, vec![])
-        .await?;
-
-    // Since round 3 is already over, the validator won't vote for a validated block from round 3.
-    let value2 = ValidatedBlock::new(block2.clone());
-    let certificate = env.make_certificate_with_round(value2.clone(), Round::SingleLeader(2));
-    env.worker()
-        .handle_validated_certificate(certificate)
+        .storage
+        .read_block_header(certificate.hash())
         .await?;
-    let query_values = ChainInfoQuery::new(chain_1).with_manager_values();
-    let (response, _) = env
+    assert_eq!(header.as_ref(), Some(&certificate.block().header));
+    let hash = env
         .worker()
-        .handle_chain_info_query(query_values.clone())
+        .storage
+        .read_block_hash(chain_1, BlockHeight(0))
         .await?;
-    let manager = response.info.manager;
-    assert_eq!(
-        manager.requested_confirmed.unwrap().block(),
-        certificate1.block()
-    );
+    assert_eq!(hash, Some(certificate.hash()));
 
-    // Proposing block2 now would fail.
-    let proposal = proposed_block2
-        .clone()
-        .with_authenticated_signer(Some(owner1))
-        .into_proposal_with_round(owner1, &signer, Round::SingleLeader(5))
-        .await
-        .unwrap();
-    let result = env.worker().handle_block_proposal(proposal.clone()).await;
-    assert_matches!(result, Err(WorkerError::ChainError(error))
-         if matches!(*error, ChainError::HasIncompatibleConfirmedVote(_, _))
+    // The execution state can't be queried anymore.
+    assert_matches!(
+        env.worker()
+            .query_application(chain_1, Query::System(SystemQuery))
+            .await,
+        Err(WorkerError::PartiallySynchronizedChain(chain_id)) if chain_id == chain_1
     );
+    Ok(())
+}
 
-    // But with the validated block certificate for block2, it is allowed.
-    let certificate2 = env.make_certificate_with_round(value2.clone(), Round::SingleLeader(4));
-
-    let proposal = BlockProposal::new_retry_regular(
-        owner1,
-        Round::SingleLeader(5),
-        certificate2.clone(),
-        &signer,
-    )
-    .await
-    .unwrap();
-    let lite_value2 = LiteValue::new(&value2);
-    let (_, _) = env.worker().handle_block_proposal(proposal).await?;
-    let (response, _) = env
+#[test_case(MemoryStorageBuilder::default(); "memory")]
+#[cfg_attr(feature = "rocksdb", test_case(RocksDbStorageBuilder::new().await; "rocks_db"))]
+#[cfg_attr(feature = "dynamodb", test_case(DynamoDbStorageBuilder::default(); "dynamo_db"))]
+#[cfg_attr(feature = "scylladb", test_case(ScyllaDbStorageBuilder::default(); "scylla_db"))]
+#[test_log::test(tokio::test)]
+async fn test_partially_synchronized_chain_application_state<B>(
+    mut storage_builder: B,
+) -> anyhow::Result<()>
+where
+    B: StorageBuilder,
+{
+    let mut signer = InMemorySigner::new(None);
+    let sender_pubkey = signer.generate_new();
+    let mut env = TestEnvironment::new(storage_builder.build().await?, false, false).await;
+    let chain_1_desc = env
+        .add_root_chain(1, sender_pubkey.into(), Amount::from_tokens(5))
+        .await;
+    let chain_1 = chain_1_desc.id();
+    let chain_2 = env
+        .add_root_chain(2, sender_pubkey.into(), Amount::ZERO)
+        .await
+        .id();
+    let tracked_id = ApplicationId::new(CryptoHash::test_hash("tracked"));
+    let untracked_id = ApplicationId::new(CryptoHash::test_hash("untracked"));
+    env.worker()
+        .set_application_filter(chain_1, BTreeSet::from([tracked_id]))
+        .await?;
+    let state_hash_before = env
         .worker()
-        .handle_chain_info_query(query_values.clone())
+        .chain_state_view(chain_1)
+        .await?
+        .execution_state
+        .crypto_hash()
         .await?;
-    assert_eq!(
-        response.info.manager.requested_locking,
-        Some(Box::new(LockingBlock::Regular(certificate2)))
-    );
-    let vote = response.info.manager.pending.as_ref().unwrap();
-    assert_eq!(vote.value, lite_value2);
-    assert_eq!(vote.round, Round::SingleLeader(5));
 
-    // Let round 5 time out, too.
-    let certificate_timeout =
-        env.make_certificate_with_round(value_timeout.clone(), Round::SingleLeader(5));
-    let (response, _) = env
-        .worker()
-        .handle_timeout_certificate(certificate_timeout)
+    let event = |application_id| Event {
+        stream_id: StreamId {
+            application_id: GenericApplicationId::User(application_id),
+            stream_name: b"updates".into(),
+        },
+        index: 0,
+        value: b"new state".to_vec(),
+    };
+    let certificate = env.make_certificate(ConfirmedBlock::new(
+        BlockExecutionOutcome {
+            messages: vec![vec![direct_credit_message(chain_2, Amount::ONE)]],
+            previous_message_blocks: BTreeMap::new(),
+            previous_event_blocks: BTreeMap::new(),
+            events: vec![vec![event(tracked_id), event(untracked_id)]],
+            blobs: vec![Vec::new()],
+            state_hash: CryptoHash::test_hash("not checked"),
+            oracle_responses: vec![Vec::new()],
+            operation_results: vec![OperationResult::default()],
+            fees: FeeBreakdown::default(),
+        }
+        .with(
+            make_first_block(chain_1)
+                .with_simple_transfer(chain_2, Amount::ONE)
+                .with_authenticated_signer(Some(sender_pubkey.into())),
+        ),
+    ));
+    env.worker()
+        .fully_handle_certificate_with_notifications(certificate.clone(), &())
         .await?;
-    assert_eq!(response.info.manager.leader, Some(owner0));
-    assert_eq!(response.info.manager.current_round, Round::SingleLeader(6));
 
-    // Since the validator now voted for block2, it can't vote for block1 anymore.
-    let proposal = proposed_block1
-        .into_proposal_with_round(owner0, &signer, Round::SingleLeader(6))
-        .await
-        .unwrap();
-    let result = env.worker().handle_block_proposal(proposal.clone()).await;
-    assert_matches!(result, Err(WorkerError::ChainError(error))
-         if matches!(*error, ChainError::HasIncompatibleConfirmedVote(_, _))
+    // Only the events of the tracked application are stored.
+    let storage = &env.worker().storage;
+    let tracked_event_id = event(tracked_id).id(chain_1);
+    assert_eq!(
+        storage.read_event(tracked_event_id).await?,
+        Some(b"new state".to_vec())
+    );
+    assert!(
+        !storage
+            .contains_event(event(untracked_id).id(chain_1))
+            .await?
     );
 
-    // Let rounds 6 and 7 time out.
-    let certificate_timeout =
-        env.make_certificate_with_round(value_timeout, Round::SingleLeader(7));
-    let (response, _) = env
-        .worker()
-        .handle_timeout_certificate(certificate_timeout)
-        .await?;
-    assert_eq!(response.info.manager.current_round, Round::SingleLeader(8));
-
-    // The worker updates its locking block even if it's from a past round.
-    let certificate = env.make_certificate_with_round(value1, Round::SingleLeader(7));
-    let worker = env.worker().clone().with_key_pair(None).await; // Forget validator keys.
-    worker
-        .handle_validated_certificate(certificate.clone())
-        .await?;
-    let (response, _) = worker.handle_chain_info_query(query_values).await?;
+    // The transfer is not executed, so the execution state is the one from before the filter
+    // was set. But it published an event of the tracked application without being one of its
+    // transactions, so the tracked application's state is not maintained anymore.
+    let chain = env.worker().chain_state_view(chain_1).await?;
+    assert_eq!(chain.tip_state.get().block_hash, Some(certificate.hash()));
     assert_eq!(
-        response.info.manager.requested_locking,
-        Some(Box::new(LockingBlock::Regular(certificate)))
+        chain.execution_state.crypto_hash().await?,
+        state_hash_before
+    );
+    assert_eq!(*chain.tracked_state_lost_at.get(), Some(BlockHeight(0)));
+    drop(chain);
+    let query = |application_id| Query::User {
+        application_id,
+        bytes: vec![],
+    };
+    assert_matches!(
+        env.worker().query_application(chain_1, query(tracked_id)).await,
+        Err(WorkerError::TrackedStateLost { chain_id, height })
+            if chain_id == chain_1 && height == BlockHeight(0)
+    );
+    assert_matches!(
+        env.worker().query_application(chain_1, query(untracked_id)).await,
+        Err(WorkerError::PartiallySynchronizedChain(chain_id)) if chain_id == chain_1
     );
+    for application_id in [tracked_id, untracked_id] {
+        assert_matches!(
+            env.worker()
+                .describe_application(chain_1, application_id)
+                .await,
+            Err(WorkerError::PartiallySynchronizedChain(chain_id)) if chain_id == chain_1
+        );
+    }
     Ok(())
 }
 
@@ -3966,6 +6095,7 @@ where
             QueryOutcome {
                 response: QueryResponse::User(vec![]),
                 operations: vec![],
+                operation_metadata: vec![],
             }
         );
     }
@@ -4063,6 +6193,7 @@ where
             QueryOutcome {
                 response: QueryResponse::User(vec![]),
                 operations: vec![],
+                operation_metadata: vec![],
             }
         );
     }
@@ -4090,6 +6221,7 @@ where
             QueryOutcome {
                 response: QueryResponse::User(vec![]),
                 operations: vec![],
+                operation_metadata: vec![],
             }
         );
     }
@@ -4113,6 +6245,7 @@ where
             state_hash: state.crypto_hash_mut().await?,
             oracle_responses: vec![vec![]],
             operation_results: vec![],
+            fees: FeeBreakdown::default(),
         }
         .with(block),
     );
@@ -4138,6 +6271,7 @@ where
             QueryOutcome {
                 response: QueryResponse::User(vec![]),
                 operations: vec![],
+                operation_metadata: vec![],
             }
         );
     }
@@ -4149,3 +6283,147 @@ where
 
     Ok(())
 }
+
+#[test_log::test(tokio::test)]
+async fn test_dry_run_worker_transfer_from_funded_account() -> anyhow::Result<()> {
+    let owner = AccountOwner::from(AccountPublicKey::test_key(1));
+    let dry_run = DryRunWorker::builder()
+        .with_validators(4)
+        .with_chain(ChainOwnership::single(owner), Amount::ONE)
+        .with_account(owner, Amount::from_tokens(5))
+        .with_chain(ChainOwnership::single(owner), Amount::ZERO)
+        .build()
+        .await?;
+    let [sender, recipient] = [dry_run.chains[0].id(), dry_run.chains[1].id()];
+
+    let block = make_first_block(sender)
+        .with_authenticated_signer(Some(owner))
+        .with_transfer(owner, Recipient::chain(recipient), Amount::from_tokens(2));
+    let certificate = dry_run.make_confirmed_certificate(block).await?;
+    assert!(certificate.check(&dry_run.committee).is_ok());
+    dry_run
+        .worker
+        .fully_handle_certificate_with_notifications(certificate, &())
+        .await?;
+
+    let chain = dry_run.worker.chain_state_view(sender).await?;
+    let system = &chain.execution_state.system;
+    assert_eq!(*system.balance.get(), Amount::ONE);
+    assert_eq!(
+        system.balances.get(&owner).await?,
+        Some(Amount::from_tokens(3))
+    );
+    assert_eq!(
+        chain.tip_state.get().next_block_height,
+        BlockHeight::from(1)
+    );
+    Ok(())
+}
+
+#[test_log::test(tokio::test)]
+async fn test_drain_saves_chains_and_rejects_new_requests() -> anyhow::Result<()> {
+    let owner = AccountOwner::from(AccountPublicKey::test_key(1));
+    let dry_run = DryRunWorker::builder()
+        .with_chain(ChainOwnership::single(owner), Amount::from_tokens(5))
+        .with_chain(ChainOwnership::single(owner), Amount::ZERO)
+        .build()
+        .await?;
+    let [sender, recipient] = [dry_run.chains[0].id(), dry_run.chains[1].id()];
+    let worker = &dry_run.worker;
+    assert_eq!(worker.shutdown_status(), ShutdownStatus::Running);
+
+    let block = make_first_block(sender)
+        .with_authenticated_signer(Some(owner))
+        .with_simple_transfer(recipient, Amount::ONE);
+    let certificate = dry_run.make_confirmed_certificate(block).await?;
+    worker
+        .fully_handle_certificate_with_notifications(certificate, &())
+        .await?;
+
+    let status = worker.drain(Duration::from_secs(10)).await;
+    assert_eq!(status, ShutdownStatus::Drained);
+    assert!(status.is_ready_to_terminate());
+    assert_matches!(
+        worker
+            .handle_chain_info_query(ChainInfoQuery::new(sender))
+            .await,
+        Err(WorkerError::ShuttingDown)
+    );
+
+    let chain = worker.storage_client().load_chain(sender).await?;
+    assert_eq!(
+        chain.tip_state.get().next_block_height,
+        BlockHeight::from(1)
+    );
+    Ok(())
+}
+
+#[test_log::test(tokio::test)]
+async fn test_cross_chain_requests_are_authenticated() -> anyhow::Result<()> {
+    let dry_run = DryRunWorker::builder()
+        .with_chain(ChainOwnership::default(), Amount::ZERO)
+        .with_chain(ChainOwnership::default(), Amount::ZERO)
+        .build()
+        .await?;
+    let worker = &dry_run.worker;
+    let request = CrossChainRequest::ConfirmUpdatedRecipient {
+        sender: dry_run.chains[0].id(),
+        recipient: dry_run.chains[1].id(),
+        latest_height: BlockHeight::ZERO,
+    };
+
+    let signed = worker.sign_cross_chain_request(request.clone());
+    assert_eq!(
+        worker.authenticate_cross_chain_request(signed.clone(), false)?,
+        request
+    );
+    assert_eq!(
+        worker.authenticate_cross_chain_request(signed, true)?,
+        request
+    );
+
+    // Unsigned requests are accepted until signatures are required.
+    let unsigned = SignedCrossChainRequest::new(request.clone(), None);
+    assert_eq!(
+        worker.authenticate_cross_chain_request(unsigned.clone(), false)?,
+        request
+    );
+    assert_matches!(
+        worker.authenticate_cross_chain_request(unsigned, true),
+        Err(WorkerError::UnauthenticatedCrossChainRequest)
+    );
+
+    // Invalid signatures are always rejected.
+    let other_validator = ValidatorKeypair::generate();
+    let forged = SignedCrossChainRequest::new(request.clone(), Some(&other_validator.secret_key));
+    let mut tampered = worker.sign_cross_chain_request(request.clone());
+    if let CrossChainRequest::ConfirmUpdatedRecipient { latest_height, .. } = &mut tampered.request
+    {
+        *latest_height = BlockHeight(10);
+    }
+    for signed in [forged, tampered] {
+        for require_signature in [false, true] {
+            assert_matches!(
+                worker.authenticate_cross_chain_request(signed.clone(), require_signature),
+                Err(WorkerError::UnauthenticatedCrossChainRequest)
+            );
+        }
+    }
+
+    // Workers that cannot check signatures reject all requests once they are required.
+    let signed = worker.sign_cross_chain_request(request.clone());
+    let keyless_worker = WorkerState::new(
+        "Worker without key pair".to_string(),
+        None,
+        worker.storage_client().clone(),
+    );
+    assert_eq!(
+        keyless_worker.authenticate_cross_chain_request(signed.clone(), false)?,
+        request
+    );
+    assert_matches!(
+        keyless_worker.authenticate_cross_chain_request(signed, true),
+        Err(WorkerError::UnauthenticatedCrossChainRequest)
+    );
+    Ok(())
+}
@@ -3,10 +3,12 @@
 // SPDX-License-Identifier: Apache-2.0
 
 use std::{
+    cmp::Ordering,
     collections::{BTreeMap, BTreeSet, HashMap},
     fmt,
     hash::Hash,
     mem,
+    sync::Mutex,
 };
 
 use futures::{
@@ -14,6 +16,7 @@ use futures::{
     Future, StreamExt,
 };
 use linera_base::{
+    crypto::ValidatorPublicKey,
     data_types::{BlockHeight, Round},
     ensure,
     identifiers::{BlobId, ChainId, GenericApplicationId},
@@ -97,11 +100,126 @@ pub enum CommunicationError<E: fmt::Debug> {
     Sample(Vec<(E, u64)>),
 }
 
+/// The weight of a new latency sample in the moving average of a validator's latency.
+const LATENCY_EWMA_WEIGHT: f64 = 0.2;
+
+/// How requests to a quorum of validators are hedged: instead of contacting all validators
+/// at once, the fastest ones are contacted first, and the others only if those don't reach
+/// a quorum in time.
+#[derive(Clone, Debug)]
+pub struct HedgingConfig {
+    /// The number of validators contacted right away, in addition to the fastest ones
+    /// making up a quorum.
+    pub extra_validators: usize,
+    /// The minimum delay after which the other validators are contacted too.
+    pub min_delay: Duration,
+    /// The delay after which the other validators are contacted too, as a multiple of the
+    /// expected latency of the slowest validator contacted first.
+    pub latency_factor: f64,
+}
+
+/// The exponentially weighted moving averages of the latencies of the validators.
+#[derive(Debug, Default)]
+pub struct ValidatorLatencies {
+    latencies: Mutex<HashMap<ValidatorPublicKey, Duration>>,
+}
+
+impl ValidatorLatencies {
+    /// Returns the expected latency of the validator, if it answered any request yet.
+    pub fn expected(&self, public_key: &ValidatorPublicKey) -> Option<Duration> {
+        self.latencies.lock().unwrap().get(public_key).copied()
+    }
+
+    /// Records how long the validator took to answer a request.
+    pub fn record(&self, public_key: ValidatorPublicKey, latency: Duration) {
+        self.latencies
+            .lock()
+            .unwrap()
+            .entry(public_key)
+            .and_modify(|average| {
+                *average = average.mul_f64(1.0 - LATENCY_EWMA_WEIGHT)
+                    + latency.mul_f64(LATENCY_EWMA_WEIGHT)
+            })
+            .or_insert(latency);
+    }
+
+    /// Records that the validator didn't answer a request within `elapsed`, which only
+    /// affects its expected latency if that was lower.
+    pub fn record_unanswered(&self, public_key: ValidatorPublicKey, elapsed: Duration) {
+        if self
+            .expected(&public_key)
+            .is_none_or(|expected| expected < elapsed)
+        {
+            self.record(public_key, elapsed);
+        }
+    }
+}
+
+/// The exponentially weighted moving averages of the number of certificates per second the
+/// validators delivered when downloading chains.
+#[derive(Debug, Default)]
+pub struct DownloadThroughputs {
+    throughputs: Mutex<HashMap<ValidatorPublicKey, f64>>,
+}
+
+impl DownloadThroughputs {
+    /// Returns the expected throughput of the validator, if anything was downloaded from it yet.
+    pub fn expected(&self, public_key: &ValidatorPublicKey) -> Option<f64> {
+        self.throughputs.lock().unwrap().get(public_key).copied()
+    }
+
+    /// Records that the validator delivered `certificates` certificates within `elapsed`.
+    pub fn record(&self, public_key: ValidatorPublicKey, certificates: u64, elapsed: Duration) {
+        let throughput = certificates as f64 / elapsed.as_secs_f64().max(f64::EPSILON);
+        self.throughputs
+            .lock()
+            .unwrap()
+            .entry(public_key)
+            .and_modify(|average| {
+                *average = *average * (1.0 - LATENCY_EWMA_WEIGHT) + throughput * LATENCY_EWMA_WEIGHT
+            })
+            .or_insert(throughput);
+    }
+
+    /// Records that a download from the validator failed, which halves its expected
+    /// throughput.
+    pub fn record_failure(&self, public_key: ValidatorPublicKey) {
+        self.throughputs
+            .lock()
+            .unwrap()
+            .entry(public_key)
+            .and_modify(|average| *average /= 2.0)
+            .or_insert(0.0);
+    }
+
+    /// Sorts the validators by decreasing expected throughput. Validators that nothing was
+    /// downloaded from yet come first, so that their throughput is learned. The sort is
+    /// stable, so validators that cannot be told apart keep their order.
+    pub fn sort<A>(&self, validators: &mut [RemoteNode<A>]) {
+        let throughputs = self.throughputs.lock().unwrap().clone();
+        validators.sort_by(|a, b| {
+            match (
+                throughputs.get(&a.public_key),
+                throughputs.get(&b.public_key),
+            ) {
+                (None, None) => Ordering::Equal,
+                (None, Some(_)) => Ordering::Less,
+                (Some(_), None) => Ordering::Greater,
+                (Some(a), Some(b)) => b.total_cmp(a),
+            }
+        });
+    }
+}
+
 /// Executes a sequence of actions in parallel for all validators.
 ///
 /// Tries to stop early when a quorum is reached. If `grace_period` is specified, other validators
 /// are given additional time to contribute to the result. The grace period is calculated as a fraction
 /// (defaulting to `DEFAULT_GRACE_PERIOD`) of the time taken to reach quorum.
+///
+/// The latencies of the validators are recorded in `latencies`. With `hedging`, only the
+/// validators expected to be the fastest are contacted at first, and the others once the
+/// hedging delay has elapsed, or as soon as the first ones cannot reach a quorum anymore.
 pub async fn communicate_with_quorum<'a, A, V, K, F, R, G>(
     validator_clients: &'a [RemoteNode<A>],
     committee: &Committee,
@@ -109,6 +227,8 @@ pub async fn communicate_with_quorum<'a, A, V, K, F, R, G>(
     execute: F,
     // Grace period as a fraction of time taken to reach quorum
     grace_period: f64,
+    latencies: &ValidatorLatencies,
+    hedging: Option<&HedgingConfig>,
 ) -> Result<(K, Vec<V>), CommunicationError<NodeError>>
 where
     A: ValidatorNode + Clone + 'static,
@@ -118,33 +238,95 @@ where
     K: Hash + PartialEq + Eq + Clone + 'static,
     V: 'static,
 {
-    let mut responses: futures::stream::FuturesUnordered<_> = validator_clients
+    // Certificates are not allowed to include votes with weight 0, so don't ask for those.
+    let mut pending = validator_clients
         .iter()
-        .filter_map(|remote_node| {
-            if committee.weight(&remote_node.public_key) == 0 {
-                // This should not happen but better prevent it because certificates
-                // are not allowed to include votes with weight 0.
-                return None;
+        .filter(|remote_node| committee.weight(&remote_node.public_key) > 0)
+        .cloned()
+        .collect::<Vec<_>>();
+    let start_time = Instant::now();
+    let mut hedge_time = None;
+    let first_nodes = match hedging {
+        None => mem::take(&mut pending),
+        Some(config) => {
+            // Validators that never answered come first, to learn their latency.
+            pending.sort_by_key(|remote_node| latencies.expected(&remote_node.public_key));
+            let mut weight = 0;
+            let mut count = 0;
+            for remote_node in &pending {
+                if weight >= committee.quorum_threshold() {
+                    break;
+                }
+                weight += committee.weight(&remote_node.public_key);
+                count += 1;
             }
-            let execute = execute.clone();
-            let remote_node = remote_node.clone();
-            Some(async move { (remote_node.public_key, execute(remote_node).await) })
-        })
-        .collect();
+            let count = (count + config.extra_validators).min(pending.len());
+            let first_nodes = pending.drain(..count).collect::<Vec<_>>();
+            let slowest_latency = first_nodes
+                .iter()
+                .filter_map(|remote_node| latencies.expected(&remote_node.public_key))
+                .max()
+                .unwrap_or_default();
+            // Negative or NaN factors are treated as zero, and overflows as the maximum timeout.
+            let scaled_secs = (slowest_latency.as_secs_f64() * config.latency_factor).max(0.0);
+            let delay = Duration::try_from_secs_f64(scaled_secs)
+                .unwrap_or(MAX_TIMEOUT)
+                .max(config.min_delay)
+                .min(MAX_TIMEOUT);
+            hedge_time = (!pending.is_empty()).then(|| start_time + delay);
+            first_nodes
+        }
+    };
+
+    let start = |remote_node: RemoteNode<A>| {
+        let execute = execute.clone();
+        let public_key = remote_node.public_key;
+        async move { (public_key, execute(remote_node).await) }
+    };
+    let mut started_at = first_nodes
+        .iter()
+        .map(|remote_node| (remote_node.public_key, start_time))
+        .collect::<HashMap<_, _>>();
+    let mut responses = first_nodes
+        .into_iter()
+        .map(start)
+        .collect::<FuturesUnordered<_>>();
 
-    let start_time = Instant::now();
     let mut end_time: Option<Instant> = None;
     let mut remaining_votes = committee.total_votes();
     let mut highest_key_score = 0;
     let mut value_scores = HashMap::new();
     let mut error_scores = HashMap::new();
 
-    'vote_wait: while let Ok(Some((name, result))) = timeout(
-        end_time.map_or(MAX_TIMEOUT, |t| t.saturating_duration_since(Instant::now())),
-        responses.next(),
-    )
-    .await
-    {
+    loop {
+        // Once a quorum is reached, there is no need to hedge anymore.
+        let deadline = end_time.or(hedge_time);
+        let response = timeout(
+            deadline.map_or(MAX_TIMEOUT, |t| t.saturating_duration_since(Instant::now())),
+            responses.next(),
+        )
+        .await;
+        let (name, result) = match response {
+            Ok(Some(response)) => response,
+            Ok(None) | Err(_) if end_time.is_none() && hedge_time.is_some() => {
+                let now = Instant::now();
+                started_at.extend(
+                    pending
+                        .iter()
+                        .map(|remote_node| (remote_node.public_key, now)),
+                );
+                responses.extend(pending.drain(..).map(start));
+                hedge_time = None;
+                continue;
+            }
+            Ok(None) | Err(_) => break,
+        };
+        // Validators returning errors are treated like those that don't answer in time.
+        if result.is_ok() {
+            if let Some(started) = started_at.remove(&name) {
+                latencies.record(name, started.elapsed());
+            }
+        }
         remaining_votes -= committee.weight(&name);
         match result {
             Ok(value) => {
@@ -173,7 +355,7 @@ where
         }
         // If it becomes clear that no key can reach a quorum, break early.
         if highest_key_score + remaining_votes < committee.quorum_threshold() {
-            break 'vote_wait;
+            break;
         }
 
         // If a key reaches a quorum, wait for the grace period to collect more values
@@ -181,6 +363,22 @@ where
         if end_time.is_none() && highest_key_score >= committee.quorum_threshold() {
             end_time = Some(Instant::now() + start_time.elapsed().mul_f64(grace_period));
         }
+
+        // If the validators contacted so far cannot reach a quorum anymore, hedge right away.
+        let pending_votes = pending
+            .iter()
+            .map(|remote_node| committee.weight(&remote_node.public_key))
+            .sum::<u64>();
+        if hedge_time.is_some()
+            && highest_key_score + remaining_votes - pending_votes < committee.quorum_threshold()
+        {
+            hedge_time = Some(Instant::now());
+        }
+    }
+
+    // The validators that didn't answer successfully in time are at least that slow.
+    for (public_key, started) in started_at {
+        latencies.record_unanswered(public_key, started.elapsed());
     }
 
     let scores = value_scores
@@ -299,16 +497,14 @@ where
                 Err(NodeError::UnexpectedBlockHeight {
                     expected_block_height,
                     found_block_height,
-                }) => {
-                    if expected_block_height < found_block_height {
-                        // The proposal is for a a later block height, so we need to update the validator.
-                        self.send_chain_information(
-                            chain_id,
-                            found_block_height,
-                            CrossChainMessageDelivery::NonBlocking,
-                        )
-                        .await?;
-                    }
+                }) if expected_block_height < found_block_height => {
+                    // The proposal is for a a later block height, so we need to update the validator.
+                    self.send_chain_information(
+                        chain_id,
+                        found_block_height,
+                        CrossChainMessageDelivery::NonBlocking,
+                    )
+                    .await?;
                 }
                 Err(NodeError::MissingCrossChainUpdate { .. }) if !sent_cross_chain_updates => {
                     sent_cross_chain_updates = true;
@@ -56,12 +56,7 @@ where
     K: Hash + Eq + PartialEq + Copy,
 {
     fn default() -> Self {
-        let size = NonZeroUsize::try_from(DEFAULT_VALUE_CACHE_SIZE)
-            .expect("Default cache size is larger than zero");
-
-        ValueCache {
-            cache: Mutex::new(LruCache::new(size)),
-        }
+        Self::new(DEFAULT_VALUE_CACHE_SIZE)
     }
 }
 
@@ -69,6 +64,14 @@ impl<K, V> ValueCache<K, V>
 where
     K: Hash + Eq + PartialEq + Copy,
 {
+    /// Creates a cache holding at most `size` values.
+    pub fn new(size: usize) -> Self {
+        let size = NonZeroUsize::try_from(size).expect("Cache size should be larger than zero");
+        ValueCache {
+            cache: Mutex::new(LruCache::new(size)),
+        }
+    }
+
     /// Inserts a `V` into the cache, if it's not already present.
     pub fn insert_owned(&self, key: &K, value: V) -> bool {
         let mut cache = self.cache.lock().unwrap();
@@ -0,0 +1,164 @@
+// Copyright (c) Zefchain Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! Offline verification of the blocks of a chain.
+//!
+//! The confirmed blocks are re-executed from the certificates and blobs in storage, the
+//! same way a validator executes them, and the outcomes are compared with the certified
+//! ones. This does not require a running worker, and leaves the storage untouched.
+
+use std::ops::{Bound, RangeBounds};
+
+use linera_base::{
+    crypto::CryptoHash,
+    data_types::{ArithmeticError, BlockHeight},
+    identifiers::{BlobId, ChainId},
+};
+use linera_chain::{data_types::BlockExecutionOutcome, ChainError, ChainStateView};
+use linera_storage::Storage;
+use linera_views::{
+    context::Context as _,
+    views::{RootView as _, View as _},
+    ViewError,
+};
+use thiserror::Error;
+use tracing::{debug, instrument};
+
+/// A confirmed block that re-executes differently.
+#[derive(Debug)]
+pub struct Divergence {
+    /// The height of the block.
+    pub height: BlockHeight,
+    /// The hash of the block's certificate.
+    pub certificate_hash: CryptoHash,
+    /// What went differently.
+    pub reason: DivergenceReason,
+}
+
+/// How the re-execution of a confirmed block diverged.
+#[derive(Debug)]
+pub enum DivergenceReason {
+    /// The block failed to execute.
+    ExecutionFailed(Box<ChainError>),
+    /// The block executed with a different outcome than the certified one.
+    IncorrectOutcome {
+        certified: Box<BlockExecutionOutcome>,
+        computed: Box<BlockExecutionOutcome>,
+    },
+}
+
+/// An error preventing the verification of a chain.
+#[derive(Debug, Error)]
+pub enum VerificationError {
+    #[error(transparent)]
+    ViewError(#[from] ViewError),
+
+    #[error(transparent)]
+    ArithmeticError(#[from] ArithmeticError),
+
+    #[error(transparent)]
+    ChainError(#[from] Box<ChainError>),
+
+    #[error("Certificate {0} of the confirmed log is missing from storage")]
+    MissingCertificate(CryptoHash),
+
+    #[error("Blobs not found: {0:?}")]
+    BlobsNotFound(Vec<BlobId>),
+}
+
+impl From<ChainError> for VerificationError {
+    fn from(error: ChainError) -> Self {
+        Box::new(error).into()
+    }
+}
+
+/// Re-executes the confirmed blocks of a chain and returns the first one whose outcome
+/// differs from the certified one, if any.
+///
+/// Only the current state of a chain is kept in storage, so the execution always starts
+/// from the chain's creation. Blocks before the given range are re-executed to rebuild the
+/// state, but their outcomes are not compared. Blocks that were not executed by this node
+/// yet are ignored.
+#[instrument(skip(storage, heights))]
+pub async fn verify_chain<S>(
+    storage: &S,
+    chain_id: ChainId,
+    heights: impl RangeBounds<BlockHeight>,
+) -> Result<Option<Divergence>, VerificationError>
+where
+    S: Storage + Clone + Send + Sync + 'static,
+{
+    let chain = storage.load_chain_for_read(chain_id).await?;
+    let next_height = chain.tip_state.get().next_block_height;
+    let end = match heights.end_bound() {
+        Bound::Included(height) => height.try_add_one()?.min(next_height),
+        Bound::Excluded(height) => (*height).min(next_height),
+        Bound::Unbounded => next_height,
+    };
+    let end = usize::try_from(end.0).map_err(|_| ArithmeticError::Overflow)?;
+    let hashes = chain.confirmed_log.read(0..end).await?;
+
+    // Execute on a cleared fork of the chain state, to start over from the chain's
+    // creation without writing anything to storage.
+    let mut chain = ChainStateView::load(chain.context().fork()).await?;
+    chain.clear();
+
+    for (height, hash) in (0..).map(BlockHeight).zip(hashes) {
+        let certificate = storage
+            .read_certificate(hash)
+            .await?
+            .ok_or(VerificationError::MissingCertificate(hash))?;
+        let block = certificate.block();
+        let blob_ids = block.published_blob_ids().into_iter().collect::<Vec<_>>();
+        let maybe_blobs = storage.read_blobs(&blob_ids).await?;
+        let missing_blob_ids = blob_ids
+            .iter()
+            .zip(&maybe_blobs)
+            .filter_map(|(blob_id, maybe_blob)| maybe_blob.is_none().then_some(*blob_id))
+            .collect::<Vec<_>>();
+        if !missing_blob_ids.is_empty() {
+            return Err(VerificationError::BlobsNotFound(missing_blob_ids));
+        }
+        let published_blobs = maybe_blobs.into_iter().flatten().collect::<Vec<_>>();
+
+        let local_time = block.header.timestamp;
+        let oracle_responses = Some(block.body.oracle_responses.clone());
+        let (proposed_block, certified) = block.clone().into_proposal();
+        let computed = match Box::pin(chain.execute_block(
+            &proposed_block,
+            local_time,
+            None,
+            &published_blobs,
+            oracle_responses,
+        ))
+        .await
+        {
+            Ok(computed) => computed,
+            Err(error) => {
+                return Ok(Some(Divergence {
+                    height,
+                    certificate_hash: hash,
+                    reason: DivergenceReason::ExecutionFailed(Box::new(error)),
+                }))
+            }
+        };
+        if heights.contains(&height) {
+            if computed != certified {
+                return Ok(Some(Divergence {
+                    height,
+                    certificate_hash: hash,
+                    reason: DivergenceReason::IncorrectOutcome {
+                        certified: Box::new(certified),
+                        computed: Box::new(computed),
+                    },
+                }));
+            }
+            debug!("Verified block {height} of chain {chain_id:.8}");
+        }
+        chain
+            .apply_confirmed_block(certificate.value(), local_time)
+            .await?;
+        chain.save().await?;
+    }
+    Ok(None)
+}
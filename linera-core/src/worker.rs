@@ -4,6 +4,8 @@
 
 use std::{
     collections::{BTreeMap, BTreeSet, HashMap, HashSet, VecDeque},
+    num::NonZeroU32,
+    ops::RangeInclusive,
     sync::{Arc, Mutex, RwLock},
     time::Duration,
 };
@@ -11,35 +13,47 @@ use std::{
 use futures::future::Either;
 use linera_base::{
     crypto::{CryptoError, CryptoHash, ValidatorPublicKey, ValidatorSecretKey},
-    data_types::{ApplicationDescription, ArithmeticError, Blob, BlockHeight, Epoch, Round},
+    data_types::{
+        Amount, ApplicationDescription, ArithmeticError, Blob, BlockHeight, Epoch, Round,
+    },
     doc_scalar,
     hashed::Hashed,
     identifiers::{AccountOwner, ApplicationId, BlobId, ChainId, EventId, StreamId},
+    merkle::StateInclusionProof,
     time::timer::{sleep, timeout},
 };
-#[cfg(with_testing)]
-use linera_chain::ChainExecutionContext;
 use linera_chain::{
     data_types::{BlockExecutionOutcome, BlockProposal, MessageBundle, ProposedBlock},
+    manager::{ManagerEntry, Pacemaker, PacemakerError},
     types::{
         Block, CertificateValue, ConfirmedBlock, ConfirmedBlockCertificate, GenericCertificate,
         LiteCertificate, Timeout, TimeoutCertificate, ValidatedBlock, ValidatedBlockCertificate,
     },
-    ChainError, ChainStateView,
+    ChainError, ChainExecutionContext, ChainStateView, ExecutionResultExt as _,
 };
-use linera_execution::{ExecutionError, ExecutionStateView, Query, QueryOutcome};
-use linera_storage::Storage;
+use linera_execution::{
+    system::{EPOCH_STREAM_NAME, REMOVED_EPOCH_STREAM_NAME},
+    ExecutionError, ExecutionStateView, Query, QueryContext, QueryOutcome,
+};
+use linera_storage::{Clock as _, Storage};
 use linera_views::ViewError;
 use serde::{Deserialize, Serialize};
 use thiserror::Error;
 use tokio::sync::{mpsc, oneshot, OwnedRwLockReadGuard};
-use tracing::{error, instrument, trace, warn};
+use tracing::{error, info, instrument, trace, warn};
 
 use crate::{
+    audit_log::{AuditError, AuditLog},
+    chain_diagnosis::ChainDiagnosis,
     chain_worker::{ChainWorkerActor, ChainWorkerConfig, ChainWorkerRequest, DeliveryNotifier},
-    data_types::{ChainInfoQuery, ChainInfoResponse, CrossChainRequest},
+    committee_cache::CommitteeCache,
+    data_types::{ChainInfoQuery, ChainInfoResponse, CrossChainRequest, SignedCrossChainRequest},
     join_set_ext::{JoinSet, JoinSetExt},
+    node::NodeError,
     notifier::Notifier,
+    proposal_outcome_cache::{ProposalFingerprint, ProposalOutcomeCache},
+    service_runtime_pool::{QueryPermit, ServiceRuntimePool},
+    signing_fence::SigningFence,
     value_cache::ValueCache,
 };
 
@@ -142,6 +156,11 @@ pub enum Reason {
         height: BlockHeight,
         round: Round,
     },
+    /// The proposal for the block at `height` in `round` expired and was discarded.
+    ProposalDiscarded {
+        height: BlockHeight,
+        round: Round,
+    },
 }
 
 /// Error type for worker operations.
@@ -209,6 +228,8 @@ pub enum WorkerError {
     InvalidLiteCertificate,
     #[error("Fast blocks cannot query oracles")]
     FastBlockUsingOracles,
+    #[error("The block costs its fee sponsor {fees}, but the sponsor only approved {max_fees}")]
+    SponsorFeeLimitExceeded { fees: Amount, max_fees: Amount },
     #[error("Blobs not found: {0:?}")]
     BlobsNotFound(Vec<BlobId>),
     #[error("confirmed_log entry at height {height} for chain {chain_id:8} not found")]
@@ -233,6 +254,46 @@ pub enum WorkerError {
     TooManyPublishedBlobs(u64),
     #[error("Missing network description")]
     MissingNetworkDescription,
+    #[error(transparent)]
+    RecordedProposalError(NodeError),
+    #[error("This worker only validates blocks and does not vote")]
+    VotingDisabled,
+    #[error("This worker is a standby instance and does not sign or save chains")]
+    NotPrimary,
+    #[error("This worker is not part of an active/passive pair")]
+    NoSigningFence,
+    #[error("The persisted execution state delta did not lead to the execution state {0}")]
+    InvalidExecutionStateDelta(CryptoHash),
+    #[error(
+        "Chain {0} is only synchronized for some applications: its execution state is not \
+        available"
+    )]
+    PartiallySynchronizedChain(ChainId),
+    #[error(
+        "The state of the tracked applications of chain {chain_id} is not maintained since \
+        block {height}"
+    )]
+    TrackedStateLost {
+        chain_id: ChainId,
+        height: BlockHeight,
+    },
+    #[error("Too many queries are running: application {0} could not be queried in time")]
+    ServiceRuntimesBusy(ApplicationId),
+    #[error("The worker is shutting down and does not accept new requests")]
+    ShuttingDown,
+    #[error("The cross-chain request is not signed by this validator")]
+    UnauthenticatedCrossChainRequest,
+    #[error("The execution state of chain {chain_id} at height {height} is not available")]
+    HistoryUnavailable {
+        chain_id: ChainId,
+        height: BlockHeight,
+    },
+    #[error("Failed to write to the audit log: {0}")]
+    AuditLog(#[from] AuditError),
+    #[error("The state hash of chain {0} is not a Merkle state hash, so values can't be proven")]
+    NoMerkleStateHash(ChainId),
+    #[error(transparent)]
+    Pacemaker(#[from] PacemakerError),
 }
 
 impl From<ChainError> for WorkerError {
@@ -254,6 +315,114 @@ impl From<ChainError> for WorkerError {
     }
 }
 
+impl WorkerError {
+    /// Returns a stable code identifying the kind of error, for programmatic clients: the
+    /// code of the underlying [`ChainError`] or [`NodeError`], if any.
+    ///
+    /// Codes start out as the names of the variants, and must be kept if a variant is renamed.
+    pub fn code(&self) -> &str {
+        match self {
+            WorkerError::CryptoError(..) => "CryptoError",
+            WorkerError::ArithmeticError(..) => "ArithmeticError",
+            WorkerError::ViewError(..) => "ViewError",
+            WorkerError::ReadCertificatesError(..) => "ReadCertificatesError",
+            WorkerError::ChainError(error) => error.code(),
+            WorkerError::InvalidOwner => "InvalidOwner",
+            WorkerError::InvalidSigner(..) => "InvalidSigner",
+            WorkerError::UnexpectedBlockHeight { .. } => "UnexpectedBlockHeight",
+            WorkerError::InvalidEpoch { .. } => "InvalidEpoch",
+            WorkerError::EventsNotFound(..) => "EventsNotFound",
+            WorkerError::InvalidCrossChainRequest => "InvalidCrossChainRequest",
+            WorkerError::InvalidBlockChaining => "InvalidBlockChaining",
+            WorkerError::IncorrectOutcome { .. } => "IncorrectOutcome",
+            WorkerError::InvalidTimestamp => "InvalidTimestamp",
+            WorkerError::MissingCertificateValue => "MissingCertificateValue",
+            WorkerError::InvalidLiteCertificate => "InvalidLiteCertificate",
+            WorkerError::FastBlockUsingOracles => "FastBlockUsingOracles",
+            WorkerError::SponsorFeeLimitExceeded { .. } => "SponsorFeeLimitExceeded",
+            WorkerError::BlobsNotFound(..) => "BlobsNotFound",
+            WorkerError::ConfirmedLogEntryNotFound { .. } => "ConfirmedLogEntryNotFound",
+            WorkerError::PreprocessedBlocksEntryNotFound { .. } => {
+                "PreprocessedBlocksEntryNotFound"
+            }
+            WorkerError::InvalidBlockProposal(..) => "InvalidBlockProposal",
+            WorkerError::FullChainWorkerCache => "FullChainWorkerCache",
+            WorkerError::JoinError => "JoinError",
+            WorkerError::UnexpectedBlob => "UnexpectedBlob",
+            WorkerError::TooManyPublishedBlobs(..) => "TooManyPublishedBlobs",
+            WorkerError::MissingNetworkDescription => "MissingNetworkDescription",
+            WorkerError::RecordedProposalError(error) => error.code(),
+            WorkerError::VotingDisabled => "VotingDisabled",
+            WorkerError::NotPrimary => "NotPrimary",
+            WorkerError::NoSigningFence => "NoSigningFence",
+            WorkerError::InvalidExecutionStateDelta(..) => "InvalidExecutionStateDelta",
+            WorkerError::PartiallySynchronizedChain(..) => "PartiallySynchronizedChain",
+            WorkerError::TrackedStateLost { .. } => "TrackedStateLost",
+            WorkerError::ServiceRuntimesBusy(..) => "ServiceRuntimesBusy",
+            WorkerError::ShuttingDown => "ShuttingDown",
+            WorkerError::UnauthenticatedCrossChainRequest => "UnauthenticatedCrossChainRequest",
+            WorkerError::HistoryUnavailable { .. } => "HistoryUnavailable",
+            WorkerError::AuditLog(_) => "AuditLog",
+            WorkerError::NoMerkleStateHash(_) => "NoMerkleStateHash",
+            WorkerError::Pacemaker(_) => "Pacemaker",
+        }
+    }
+
+    /// Returns whether the same request may succeed if it is retried later, e.g. after
+    /// synchronizing the chain or once the worker is less busy.
+    pub fn is_retriable(&self) -> bool {
+        match self {
+            WorkerError::ChainError(error) => error.is_retriable(),
+            WorkerError::RecordedProposalError(error) => error.is_retriable(),
+            WorkerError::ViewError(_)
+            | WorkerError::UnexpectedBlockHeight { .. }
+            | WorkerError::EventsNotFound(_)
+            | WorkerError::MissingCertificateValue
+            | WorkerError::BlobsNotFound(_)
+            | WorkerError::FullChainWorkerCache
+            | WorkerError::JoinError
+            | WorkerError::ServiceRuntimesBusy(_)
+            | WorkerError::ShuttingDown
+            | WorkerError::Pacemaker(_) => true,
+            _ => false,
+        }
+    }
+
+    /// Returns whether handling the same block proposal again would fail in the same way,
+    /// i.e. the client can neither fix this by updating the validator nor by waiting.
+    fn is_final_for_proposal(&self) -> bool {
+        match self {
+            WorkerError::CryptoError(_)
+            | WorkerError::ArithmeticError(_)
+            | WorkerError::InvalidOwner
+            | WorkerError::InvalidSigner(_)
+            | WorkerError::InvalidBlockChaining
+            | WorkerError::IncorrectOutcome { .. }
+            | WorkerError::FastBlockUsingOracles
+            | WorkerError::SponsorFeeLimitExceeded { .. }
+            | WorkerError::InvalidBlockProposal(_)
+            | WorkerError::TooManyPublishedBlobs(_) => true,
+            WorkerError::ChainError(error) => match &**error {
+                ChainError::ViewError(_)
+                | ChainError::InactiveChain(_)
+                | ChainError::MissingCrossChainUpdate { .. }
+                | ChainError::UnexpectedBlockHeight { .. }
+                | ChainError::WrongRound(_)
+                | ChainError::MissingEarlierBlocks { .. }
+                | ChainError::InternalError(_) => false,
+                ChainError::ExecutionError(error, _) => !matches!(
+                    **error,
+                    ExecutionError::ViewError(_)
+                        | ExecutionError::BlobsNotFound(_)
+                        | ExecutionError::EventsNotFound(_)
+                ),
+                _ => true,
+            },
+            _ => false,
+        }
+    }
+}
+
 #[cfg(with_testing)]
 impl WorkerError {
     /// Returns the inner [`ExecutionError`] in this error.
@@ -276,6 +445,9 @@ impl WorkerError {
     }
 }
 
+/// The number of recently executed blocks whose read sets are kept for prefetching.
+const READ_SET_CACHE_SIZE: usize = 1_000;
+
 /// State of a worker in a validator or a local node.
 pub struct WorkerState<StorageClient>
 where
@@ -289,6 +461,8 @@ where
     chain_worker_config: ChainWorkerConfig,
     block_cache: Arc<ValueCache<CryptoHash, Hashed<Block>>>,
     execution_state_cache: Arc<ValueCache<CryptoHash, ExecutionStateView<StorageClient::Context>>>,
+    /// The storage keys read by recently executed blocks, by hash of the proposed block.
+    read_sets: Arc<ValueCache<CryptoHash, Vec<Vec<u8>>>>,
     /// Chain IDs that should be tracked by a worker.
     tracked_chains: Option<Arc<RwLock<HashSet<ChainId>>>>,
     /// One-shot channels to notify callers when messages of a particular chain have been
@@ -298,6 +472,12 @@ where
     chain_worker_tasks: Arc<Mutex<JoinSet>>,
     /// The cache of running [`ChainWorkerActor`]s.
     chain_workers: Arc<Mutex<BTreeMap<ChainId, ChainActorEndpoint<StorageClient>>>>,
+    /// The outcomes of the block proposals submitted with an idempotency key.
+    proposal_outcomes: Arc<ProposalOutcomeCache>,
+    /// The committees of past epochs.
+    committees: Arc<CommitteeCache>,
+    /// Whether the worker still accepts requests, or how far it has gone in shutting down.
+    shutdown_status: Arc<Mutex<ShutdownStatus>>,
 }
 
 impl<StorageClient> Clone for WorkerState<StorageClient>
@@ -311,10 +491,14 @@ where
             chain_worker_config: self.chain_worker_config.clone(),
             block_cache: self.block_cache.clone(),
             execution_state_cache: self.execution_state_cache.clone(),
+            read_sets: self.read_sets.clone(),
             tracked_chains: self.tracked_chains.clone(),
             delivery_notifiers: self.delivery_notifiers.clone(),
             chain_worker_tasks: self.chain_worker_tasks.clone(),
             chain_workers: self.chain_workers.clone(),
+            proposal_outcomes: self.proposal_outcomes.clone(),
+            committees: self.committees.clone(),
+            shutdown_status: self.shutdown_status.clone(),
         }
     }
 }
@@ -327,6 +511,31 @@ type ChainActorEndpoint<StorageClient> = mpsc::UnboundedSender<(
 
 pub(crate) type DeliveryNotifiers = HashMap<ChainId, DeliveryNotifier>;
 
+/// How far a worker has gone in shutting down.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ShutdownStatus {
+    /// The worker accepts new requests.
+    #[default]
+    Running,
+    /// The worker rejects new requests, and waits for its chain workers to handle the queued
+    /// ones.
+    Draining,
+    /// All chain workers have handled their queued requests and saved their chains.
+    Drained,
+    /// The deadline passed first, and the given number of chain workers were aborted.
+    DeadlineExceeded { aborted: usize },
+}
+
+impl ShutdownStatus {
+    /// Returns whether the worker is done shutting down, so that the process may exit.
+    pub fn is_ready_to_terminate(&self) -> bool {
+        matches!(
+            self,
+            ShutdownStatus::Drained | ShutdownStatus::DeadlineExceeded { .. }
+        )
+    }
+}
+
 impl<StorageClient> WorkerState<StorageClient>
 where
     StorageClient: Storage,
@@ -343,10 +552,14 @@ where
             chain_worker_config: ChainWorkerConfig::default().with_key_pair(key_pair),
             block_cache: Arc::new(ValueCache::default()),
             execution_state_cache: Arc::new(ValueCache::default()),
+            read_sets: Arc::new(ValueCache::new(READ_SET_CACHE_SIZE)),
             tracked_chains: None,
             delivery_notifiers: Arc::default(),
             chain_worker_tasks: Arc::default(),
             chain_workers: Arc::new(Mutex::new(BTreeMap::new())),
+            proposal_outcomes: Arc::default(),
+            committees: Arc::default(),
+            shutdown_status: Arc::default(),
         }
     }
 
@@ -362,10 +575,14 @@ where
             chain_worker_config: ChainWorkerConfig::default(),
             block_cache: Arc::new(ValueCache::default()),
             execution_state_cache: Arc::new(ValueCache::default()),
+            read_sets: Arc::new(ValueCache::new(READ_SET_CACHE_SIZE)),
             tracked_chains: Some(tracked_chains),
             delivery_notifiers: Arc::default(),
             chain_worker_tasks: Arc::default(),
             chain_workers: Arc::new(Mutex::new(BTreeMap::new())),
+            proposal_outcomes: Arc::default(),
+            committees: Arc::default(),
+            shutdown_status: Arc::default(),
         }
     }
 
@@ -407,6 +624,109 @@ where
         self
     }
 
+    /// Returns an instance that only validates blocks and never votes, if `value` is true.
+    ///
+    /// This is meant for nodes that serve clients without being part of the committee.
+    #[instrument(level = "trace", skip(self, value))]
+    pub fn with_validation_only(mut self, value: bool) -> Self {
+        self.chain_worker_config.validation_only = value;
+        self
+    }
+
+    /// Returns an instance that prunes the stale proposals and votes of the chain managers
+    /// whenever a chain is saved, if `value` is true.
+    #[instrument(level = "trace", skip(self, value))]
+    pub fn with_prune_manager_on_save(mut self, value: bool) -> Self {
+        self.chain_worker_config.prune_manager_on_save = value;
+        self
+    }
+
+    /// Returns an instance that prunes the proposals whose block is older than
+    /// `max_proposal_age` from the chain managers, even in the current round.
+    #[instrument(level = "trace", skip(self))]
+    pub fn with_max_proposal_age(mut self, max_proposal_age: Option<Duration>) -> Self {
+        self.chain_worker_config.max_proposal_age = max_proposal_age;
+        self
+    }
+
+    /// Returns an instance that discards the proposals from the chain managers once the
+    /// current round is `rounds` rounds after theirs, and notifies about them.
+    #[instrument(level = "trace", skip(self))]
+    pub fn with_proposal_expiry_rounds(mut self, rounds: Option<NonZeroU32>) -> Self {
+        self.chain_worker_config.proposal_expiry_rounds = rounds;
+        self
+    }
+
+    /// Returns an instance that persists the changes made by up to `max_entries` validated
+    /// blocks to the execution states, so that confirming them doesn't require executing them
+    /// again, even after a restart. This is disabled if `max_entries` is zero.
+    #[instrument(level = "trace", skip(self))]
+    pub fn with_max_execution_state_deltas(mut self, max_entries: usize) -> Self {
+        self.chain_worker_config.max_execution_state_deltas = max_entries;
+        self
+    }
+
+    /// Returns an instance that keeps what is needed to query the execution states of the
+    /// last `history` blocks of each chain, in addition to the current one. This is disabled
+    /// if `history` is zero.
+    #[instrument(level = "trace", skip(self))]
+    pub fn with_execution_state_history(mut self, history: u64) -> Self {
+        self.chain_worker_config.execution_state_history = history;
+        self
+    }
+
+    /// Returns an instance that records the accepted proposals, the votes, the certificates
+    /// and the cross-chain updates of its chains in the `audit_log`, if any.
+    #[instrument(level = "trace", skip(self, audit_log))]
+    pub fn with_audit_log(mut self, audit_log: Option<Arc<AuditLog>>) -> Self {
+        self.chain_worker_config.audit_log = audit_log;
+        self
+    }
+
+    /// Returns an instance that buffers up to `max_events` events of consecutive confirmed
+    /// blocks and writes them to storage together, e.g. while synchronizing a chain. Events
+    /// are written with each block if `max_events` is zero.
+    #[instrument(level = "trace", skip(self))]
+    pub fn with_max_buffered_events(mut self, max_events: usize) -> Self {
+        self.chain_worker_config.max_buffered_events = max_events;
+        self
+    }
+
+    /// Returns an instance whose chain managers take their round deadlines and leaders from
+    /// the `pacemaker`.
+    #[instrument(level = "trace", skip(self, pacemaker))]
+    pub fn with_pacemaker(mut self, pacemaker: Option<Arc<dyn Pacemaker>>) -> Self {
+        self.chain_worker_config.pacemaker = pacemaker;
+        self
+    }
+
+    /// Returns an instance that remembers the outcomes of block proposals submitted with
+    /// an idempotency key for the specified duration.
+    #[instrument(level = "trace", skip(self))]
+    pub fn with_proposal_outcome_ttl(mut self, proposal_outcome_ttl: Duration) -> Self {
+        self.proposal_outcomes = Arc::new(ProposalOutcomeCache::new(proposal_outcome_ttl));
+        self
+    }
+
+    /// Returns an instance that only signs and writes chain states while it is the primary
+    /// of its active/passive pair, as decided by the `signing_fence`.
+    #[instrument(level = "trace", skip(self, signing_fence))]
+    pub fn with_signing_fence(mut self, signing_fence: Option<SigningFence>) -> Self {
+        self.chain_worker_config.signing_fence = signing_fence;
+        self
+    }
+
+    /// Returns an instance whose application queries and long-lived service runtimes are
+    /// limited by the `service_runtime_pool`, shared by all its chain workers.
+    #[instrument(level = "trace", skip(self, service_runtime_pool))]
+    pub fn with_service_runtime_pool(
+        mut self,
+        service_runtime_pool: Option<Arc<ServiceRuntimePool>>,
+    ) -> Self {
+        self.chain_worker_config.service_runtime_pool = service_runtime_pool;
+        self
+    }
+
     #[instrument(level = "trace", skip(self))]
     pub fn nickname(&self) -> &str {
         &self.nickname
@@ -554,6 +874,23 @@ where
         .await
     }
 
+    /// Executes a block proposal on a fork of the chain state, leaving the chain untouched.
+    #[instrument(level = "trace", skip(self, block))]
+    pub async fn simulate_block_execution(
+        &self,
+        block: ProposedBlock,
+        published_blobs: Vec<Blob>,
+    ) -> Result<(Block, ChainInfoResponse), WorkerError> {
+        self.query_chain_worker(block.chain_id, move |callback| {
+            ChainWorkerRequest::SimulateBlockExecution {
+                block,
+                published_blobs,
+                callback,
+            }
+        })
+        .await
+    }
+
     /// Executes a [`Query`] for an application's state on a specific chain.
     #[instrument(level = "trace", skip(self, chain_id, query))]
     pub async fn query_application(
@@ -561,12 +898,92 @@ where
         chain_id: ChainId,
         query: Query,
     ) -> Result<QueryOutcome, WorkerError> {
+        let permit = self.acquire_query_permit(&query).await?;
         self.query_chain_worker(chain_id, move |callback| {
-            ChainWorkerRequest::QueryApplication { query, callback }
+            ChainWorkerRequest::QueryApplication {
+                query,
+                permit,
+                callback,
+            }
         })
         .await
     }
 
+    /// Executes a [`Query`] for an application's state on a specific chain, as it was right
+    /// after the block at the given height.
+    #[instrument(level = "trace", skip(self, chain_id, query))]
+    pub async fn query_application_at(
+        &self,
+        chain_id: ChainId,
+        query: Query,
+        height: BlockHeight,
+    ) -> Result<QueryOutcome, WorkerError> {
+        // The state is reconstructed from storage, without holding up the chain worker.
+        let chain = self.storage.load_chain_shared(chain_id).await?;
+        ensure!(
+            chain.application_filter.get().is_none(),
+            WorkerError::PartiallySynchronizedChain(chain_id)
+        );
+        let mut execution_state = self
+            .storage
+            .load_execution_state_at(&chain, height)
+            .await?
+            .ok_or(WorkerError::HistoryUnavailable { chain_id, height })?;
+        drop(chain);
+        let mut permit = self.acquire_query_permit(&query).await?;
+        if let Some(permit) = &mut permit {
+            // The query spawns its own runtime.
+            permit.ensure_memory_is_reserved()?;
+        }
+        let context = QueryContext {
+            chain_id,
+            next_block_height: height.try_add_one()?,
+            local_time: self.storage.clock().current_time(),
+        };
+        let outcome = Box::pin(execution_state.query_application(context, query, None))
+            .await
+            .with_execution_context(ChainExecutionContext::Query)?;
+        Ok(outcome)
+    }
+
+    /// Waits until the service runtime pool, if there is one, admits a query to a user
+    /// application. This happens before the query is sent to the chain worker, so that the
+    /// worker keeps handling other requests in the meantime.
+    async fn acquire_query_permit(
+        &self,
+        query: &Query,
+    ) -> Result<Option<QueryPermit>, WorkerError> {
+        let (Some(pool), Query::User { application_id, .. }) =
+            (&self.chain_worker_config.service_runtime_pool, query)
+        else {
+            return Ok(None);
+        };
+        // Queries to long-lived runtimes don't reserve memory, unless the chain worker turns
+        // out not to have one.
+        let reserve_memory = !self.chain_worker_config.long_lived_services;
+        Ok(Some(
+            pool.acquire_query(*application_id, reserve_memory).await?,
+        ))
+    }
+
+    /// Returns the value that an application stores under `key` on a chain, with a proof
+    /// that it is part of the chain's execution state, i.e. against the state hash of the
+    /// chain's latest block. Returns `None` if there is no such value.
+    ///
+    /// This is the same as a [`ChainInfoQuery`] with
+    /// [`ChainInfoQuery::with_state_inclusion_proof`].
+    #[instrument(level = "trace", skip(self, key))]
+    pub async fn state_inclusion_proof(
+        &self,
+        chain_id: ChainId,
+        application_id: ApplicationId,
+        key: Vec<u8>,
+    ) -> Result<Option<(Vec<u8>, StateInclusionProof)>, WorkerError> {
+        let query = ChainInfoQuery::new(chain_id).with_state_inclusion_proof(application_id, key);
+        let (response, _) = self.handle_chain_info_query(query).await?;
+        Ok(response.info.requested_state_inclusion_proof)
+    }
+
     #[instrument(level = "trace", skip(self, chain_id, application_id))]
     pub async fn describe_application(
         &self,
@@ -721,13 +1138,14 @@ where
         let (sender, new_receiver) = timeout(Duration::from_secs(3), async move {
             loop {
                 match self.try_get_chain_worker_endpoint(chain_id) {
-                    Some(endpoint) => break endpoint,
-                    None => sleep(Duration::from_millis(250)).await,
+                    Ok(Some(endpoint)) => break Ok(endpoint),
+                    Ok(None) => sleep(Duration::from_millis(250)).await,
+                    Err(error) => break Err(error),
                 }
             }
         })
         .await
-        .map_err(|_| WorkerError::FullChainWorkerCache)?;
+        .map_err(|_| WorkerError::FullChainWorkerCache)??;
 
         if let Some(receiver) = new_receiver {
             let delivery_notifier = self
@@ -743,6 +1161,8 @@ where
                 self.storage.clone(),
                 self.block_cache.clone(),
                 self.execution_state_cache.clone(),
+                self.read_sets.clone(),
+                self.committees.clone(),
                 self.tracked_chains.clone(),
                 delivery_notifier,
                 chain_id,
@@ -761,29 +1181,43 @@ where
     /// Retrieves an endpoint to a [`ChainWorkerActor`] from the cache, attempting to create one
     /// and add it to the cache if needed.
     ///
-    /// Returns [`None`] if the cache is full and no candidate for eviction was found.
+    /// Returns [`None`] if the cache is full and no candidate for eviction was found, and an
+    /// error if the worker is shutting down.
     #[instrument(level = "trace", skip(self))]
     #[expect(clippy::type_complexity)]
     fn try_get_chain_worker_endpoint(
         &self,
         chain_id: ChainId,
-    ) -> Option<(
-        ChainActorEndpoint<StorageClient>,
-        Option<
-            mpsc::UnboundedReceiver<(ChainWorkerRequest<StorageClient::Context>, tracing::Span)>,
-        >,
-    )> {
+    ) -> Result<
+        Option<(
+            ChainActorEndpoint<StorageClient>,
+            Option<
+                mpsc::UnboundedReceiver<(
+                    ChainWorkerRequest<StorageClient::Context>,
+                    tracing::Span,
+                )>,
+            >,
+        )>,
+        WorkerError,
+    > {
         let mut chain_workers = self.chain_workers.lock().unwrap();
 
+        if *self.shutdown_status.lock().unwrap() != ShutdownStatus::Running {
+            return Err(WorkerError::ShuttingDown);
+        }
         if let Some(endpoint) = chain_workers.get(&chain_id) {
-            Some((endpoint.clone(), None))
+            Ok(Some((endpoint.clone(), None)))
         } else {
             let (sender, receiver) = mpsc::unbounded_channel();
             chain_workers.insert(chain_id, sender.clone());
-            Some((sender, Some(receiver)))
+            Ok(Some((sender, Some(receiver))))
         }
     }
 
+    /// Handles a block proposal and returns the resulting vote, if any.
+    ///
+    /// If the proposal has an idempotency key, the outcome is recorded, and returned again
+    /// without validating the block if the same proposal is resubmitted with that key.
     #[instrument(skip_all, fields(
         nick = self.nickname,
         chain_id = format!("{:.8}", proposal.content.block.chain_id),
@@ -791,9 +1225,41 @@ where
     ))]
     pub async fn handle_block_proposal(
         &self,
-        proposal: BlockProposal,
+        mut proposal: BlockProposal,
     ) -> Result<(ChainInfoResponse, NetworkActions), WorkerError> {
         trace!("{} <-- {:?}", self.nickname, proposal);
+        let Some(key) = proposal.idempotency_key.take() else {
+            return self.process_block_proposal(proposal).await;
+        };
+        let chain_id = proposal.content.block.chain_id;
+        let fingerprint = ProposalFingerprint::new(&proposal);
+        if let Some(outcome) = self.proposal_outcomes.get(chain_id, key, &fingerprint) {
+            trace!("Returning the recorded outcome for idempotency key {key}");
+            return match outcome {
+                Ok(response) => Ok((response, NetworkActions::default())),
+                Err(error) => Err(WorkerError::RecordedProposalError(error)),
+            };
+        }
+        match self.process_block_proposal(proposal).await {
+            Ok((response, actions)) => {
+                self.proposal_outcomes
+                    .insert(chain_id, key, fingerprint, Ok(response.clone()));
+                Ok((response, actions))
+            }
+            Err(error) if error.is_final_for_proposal() => {
+                let error = NodeError::from(error);
+                self.proposal_outcomes
+                    .insert(chain_id, key, fingerprint, Err(error.clone()));
+                Err(WorkerError::RecordedProposalError(error))
+            }
+            Err(error) => Err(error),
+        }
+    }
+
+    async fn process_block_proposal(
+        &self,
+        proposal: BlockProposal,
+    ) -> Result<(ChainInfoResponse, NetworkActions), WorkerError> {
         #[cfg(with_metrics)]
         let round = proposal.content.round;
         let response = self
@@ -990,6 +1456,50 @@ where
         result
     }
 
+    /// Reads the committees of the given epochs into the cache of the chain workers with a
+    /// single query. This saves storage round-trips before processing many blocks from past
+    /// epochs, e.g. when synchronizing a chain.
+    #[instrument(level = "trace", skip(self))]
+    pub async fn prefetch_committees(
+        &self,
+        epochs: RangeInclusive<Epoch>,
+    ) -> Result<(), WorkerError> {
+        self.committees.prefetch(&self.storage, epochs).await?;
+        Ok(())
+    }
+
+    /// Signs a cross-chain request to be sent to another shard of this validator.
+    pub fn sign_cross_chain_request(&self, request: CrossChainRequest) -> SignedCrossChainRequest {
+        SignedCrossChainRequest::new(request, self.chain_worker_config.key_pair())
+    }
+
+    /// Checks that a cross-chain request received from the network was signed by this
+    /// validator, and returns the request. Unsigned requests are only rejected if
+    /// `require_signature` is set. Workers without a key pair cannot check signatures, so
+    /// they reject all requests if `require_signature` is set.
+    pub fn authenticate_cross_chain_request(
+        &self,
+        request: SignedCrossChainRequest,
+        require_signature: bool,
+    ) -> Result<CrossChainRequest, WorkerError> {
+        if request.signature.is_none() && !require_signature {
+            return Ok(request.request);
+        }
+        match self.chain_worker_config.key_pair() {
+            Some(key_pair) => request.check(key_pair.public()).map_err(|error| {
+                warn!(%error, "Rejecting a cross-chain request with an invalid signature");
+                WorkerError::UnauthenticatedCrossChainRequest
+            })?,
+            None => {
+                if require_signature {
+                    warn!("Rejecting a cross-chain request: no key pair to check its signature");
+                    return Err(WorkerError::UnauthenticatedCrossChainRequest);
+                }
+            }
+        }
+        Ok(request.request)
+    }
+
     #[instrument(skip_all, fields(
         nick = self.nickname,
         chain_id = format!("{:.8}", request.target_chain_id())
@@ -1031,19 +1541,38 @@ where
                 recipient,
                 latest_height,
             } => {
-                self.query_chain_worker(sender, move |callback| {
-                    ChainWorkerRequest::ConfirmUpdatedRecipient {
-                        recipient,
-                        latest_height,
-                        callback,
-                    }
-                })
+                self.confirm_updated_recipients(
+                    sender,
+                    BTreeMap::from([(recipient, latest_height)]),
+                )
                 .await?;
                 Ok(NetworkActions::default())
             }
         }
     }
 
+    /// Handles a batch of confirmations that the given recipients received the messages of
+    /// the `sender` chain up to the given heights. The sender's chain state is saved once
+    /// for the whole batch.
+    #[instrument(skip_all, fields(
+        nick = self.nickname,
+        chain_id = format!("{:.8}", sender),
+        num_confirmations = confirmations.len(),
+    ))]
+    pub async fn confirm_updated_recipients(
+        &self,
+        sender: ChainId,
+        confirmations: BTreeMap<ChainId, BlockHeight>,
+    ) -> Result<(), WorkerError> {
+        self.query_chain_worker(sender, move |callback| {
+            ChainWorkerRequest::ConfirmUpdatedRecipients {
+                confirmations,
+                callback,
+            }
+        })
+        .await
+    }
+
     /// Updates the received certificate trackers to at least the given values.
     pub async fn update_received_certificate_trackers(
         &self,
@@ -1058,6 +1587,244 @@ where
         })
         .await
     }
+
+    /// Returns the proposals, votes and certificates held by the chain manager, by round.
+    #[instrument(level = "trace", skip(self))]
+    pub async fn chain_manager_entries(
+        &self,
+        chain_id: ChainId,
+    ) -> Result<BTreeMap<Round, Vec<ManagerEntry>>, WorkerError> {
+        let chain = self.chain_state_view(chain_id).await?;
+        Ok(chain.manager.entries_by_round())
+    }
+
+    /// Prunes the stale proposals and votes held by the chain manager. Returns the number of
+    /// entries that were removed.
+    #[instrument(level = "trace", skip(self))]
+    pub async fn prune_chain_manager(&self, chain_id: ChainId) -> Result<usize, WorkerError> {
+        self.query_chain_worker(chain_id, |callback| {
+            ChainWorkerRequest::PruneStaleManagerEntries { callback }
+        })
+        .await
+    }
+
+    /// Synchronizes the chain only partially from now on: its blocks are not executed anymore,
+    /// and only their headers and the events of the given applications are stored.
+    #[instrument(level = "trace", skip(self))]
+    pub async fn set_application_filter(
+        &self,
+        chain_id: ChainId,
+        applications: BTreeSet<ApplicationId>,
+    ) -> Result<(), WorkerError> {
+        self.query_chain_worker(chain_id, |callback| {
+            ChainWorkerRequest::SetApplicationFilter {
+                applications,
+                callback,
+            }
+        })
+        .await
+    }
+
+    /// Reports what the chain is waiting for, to find out why it isn't advancing.
+    #[instrument(level = "trace", skip(self))]
+    pub async fn diagnose_chain(&self, chain_id: ChainId) -> Result<ChainDiagnosis, WorkerError> {
+        let chain = self.chain_state_view(chain_id).await?;
+        let local_time = self.storage.clock().current_time();
+        Ok(ChainDiagnosis::new(&chain, local_time).await?)
+    }
+
+    /// Returns the latest epoch created on the admin chain and not removed since, or
+    /// `known_epoch` if there is no newer one in storage.
+    async fn latest_epoch(&self, known_epoch: Epoch) -> Result<Epoch, WorkerError> {
+        let admin_chain_id = self
+            .storage
+            .read_network_description()
+            .await?
+            .ok_or(WorkerError::MissingNetworkDescription)?
+            .admin_chain_id;
+        let new_epochs = self
+            .storage
+            .read_events_from_index(
+                &admin_chain_id,
+                &StreamId::system(EPOCH_STREAM_NAME),
+                known_epoch.0.saturating_add(1),
+            )
+            .await?;
+        let mut latest_epoch = known_epoch;
+        for index_and_event in new_epochs {
+            let removal_id = EventId {
+                chain_id: admin_chain_id,
+                stream_id: StreamId::system(REMOVED_EPOCH_STREAM_NAME),
+                index: index_and_event.index,
+            };
+            if !self.storage.contains_event(removal_id).await? {
+                latest_epoch = latest_epoch.max(Epoch(index_and_event.index));
+            }
+        }
+        Ok(latest_epoch)
+    }
+
+    /// Moves the chains of this worker that are still on an older epoch to `epoch`, without
+    /// producing blocks, and returns how many of them were migrated.
+    ///
+    /// These are the tracked chains if the worker has a list of them, and the chains whose
+    /// workers are currently loaded otherwise: a chain that is loaded later is checked against
+    /// the committee of each certificate's epoch anyway.
+    #[instrument(level = "trace", skip(self))]
+    pub async fn migrate_chains_to_epoch(&self, epoch: Epoch) -> usize {
+        let chain_ids: Vec<ChainId> = match &self.tracked_chains {
+            Some(tracked_chains) => tracked_chains.read().unwrap().iter().copied().collect(),
+            None => self.chain_workers.lock().unwrap().keys().copied().collect(),
+        };
+        let mut migrated = 0;
+        for chain_id in chain_ids {
+            let result = self
+                .query_chain_worker(chain_id, |callback| ChainWorkerRequest::MigrateToEpoch {
+                    epoch,
+                    callback,
+                })
+                .await;
+            match result {
+                Ok(true) => migrated += 1,
+                Ok(false) => {}
+                Err(error) => warn!("Failed to migrate chain {chain_id} to {epoch}: {error}"),
+            }
+        }
+        migrated
+    }
+
+    /// Checks the admin chain's events every `interval` for new epochs, and migrates the chains
+    /// of this worker to them, until the worker shuts down.
+    ///
+    /// The last epoch the chains were migrated to is saved in the task checkpoint named
+    /// `checkpoint_name`, so that a restarted worker resumes from there.
+    pub async fn run_epoch_migration(self, interval: Duration, checkpoint_name: String) {
+        let mut epoch = match self.storage.read_task_checkpoint(&checkpoint_name).await {
+            Ok(checkpoint) => checkpoint
+                .and_then(|bytes| bcs::from_bytes(&bytes).ok())
+                .unwrap_or(Epoch::ZERO),
+            Err(error) => {
+                warn!(
+                    "{}: failed to read the last migrated epoch: {error}",
+                    self.nickname
+                );
+                Epoch::ZERO
+            }
+        };
+        while self.shutdown_status() == ShutdownStatus::Running {
+            match self.latest_epoch(epoch).await {
+                Ok(latest_epoch) if latest_epoch > epoch => {
+                    epoch = latest_epoch;
+                    let migrated = self.migrate_chains_to_epoch(epoch).await;
+                    info!("{}: migrated {migrated} chains to {epoch}", self.nickname);
+                    let checkpoint = bcs::to_bytes(&epoch).expect("epochs should serialize");
+                    if let Err(error) = self
+                        .storage
+                        .write_task_checkpoint(&checkpoint_name, checkpoint)
+                        .await
+                    {
+                        warn!(
+                            "{}: failed to save the last migrated epoch: {error}",
+                            self.nickname
+                        );
+                    }
+                }
+                Ok(_) => {}
+                Err(error) => warn!(
+                    "{}: failed to read the latest epoch: {error}",
+                    self.nickname
+                ),
+            }
+            sleep(interval).await;
+        }
+    }
+
+    /// Returns the fencing term of this worker if it is the primary of its active/passive
+    /// pair, or `None` if it is the standby.
+    #[instrument(level = "trace", skip(self))]
+    pub fn signing_term(&self) -> Result<Option<u64>, WorkerError> {
+        let fence = self.signing_fence()?;
+        Ok(fence.term())
+    }
+
+    /// Returns whether the worker still accepts requests, or how far it has gone in shutting
+    /// down.
+    pub fn shutdown_status(&self) -> ShutdownStatus {
+        *self.shutdown_status.lock().unwrap()
+    }
+
+    /// Stops accepting new requests. The chain workers exit once they have handled the requests
+    /// that are already queued, saving their chains and flushing their buffered events.
+    #[instrument(level = "trace", skip(self))]
+    pub fn stop_accepting_requests(&self) {
+        // The lock on the chain workers makes sure no new endpoint is handed out afterwards.
+        let mut chain_workers = self.chain_workers.lock().unwrap();
+        let mut status = self.shutdown_status.lock().unwrap();
+        if *status == ShutdownStatus::Running {
+            *status = ShutdownStatus::Draining;
+        }
+        chain_workers.clear();
+    }
+
+    /// Stops accepting new requests, and waits up to `deadline` for the chain workers to handle
+    /// the queued ones. The chain workers that are still running after that are aborted.
+    #[cfg(not(web))]
+    #[instrument(level = "trace", skip(self))]
+    pub async fn drain(&self, deadline: Duration) -> ShutdownStatus {
+        self.stop_accepting_requests();
+        let mut tasks = JoinSet::new();
+        let drained = timeout(deadline, async {
+            // Actors whose endpoints were handed out just before may still be spawned.
+            loop {
+                tasks = std::mem::take(&mut *self.chain_worker_tasks.lock().unwrap());
+                if tasks.is_empty() {
+                    break;
+                }
+                tasks.await_all_tasks().await;
+            }
+        })
+        .await
+        .is_ok();
+        let status = if drained {
+            ShutdownStatus::Drained
+        } else {
+            let aborted = tasks.len();
+            tasks.abort_all();
+            warn!(
+                "{} chain workers did not finish before the deadline",
+                aborted
+            );
+            ShutdownStatus::DeadlineExceeded { aborted }
+        };
+        *self.shutdown_status.lock().unwrap() = status;
+        status
+    }
+
+    /// Makes this worker the primary of its active/passive pair, fencing off the other
+    /// instance, and returns the new fencing term.
+    ///
+    /// The chains loaded so far are dropped: the other instance may have changed them since.
+    #[instrument(level = "trace", skip(self))]
+    pub async fn promote_to_primary(&self) -> Result<u64, WorkerError> {
+        let term = self.signing_fence()?.promote(&self.storage).await?;
+        self.chain_workers.lock().unwrap().clear();
+        Ok(term)
+    }
+
+    /// Makes this worker the standby of its active/passive pair: it stops signing votes and
+    /// writing chain states until it is promoted again.
+    #[instrument(level = "trace", skip(self))]
+    pub fn demote_to_standby(&self) -> Result<(), WorkerError> {
+        self.signing_fence()?.demote();
+        Ok(())
+    }
+
+    fn signing_fence(&self) -> Result<&SigningFence, WorkerError> {
+        self.chain_worker_config
+            .signing_fence
+            .as_ref()
+            .ok_or(WorkerError::NoSigningFence)
+    }
 }
 
 #[cfg(with_testing)]
@@ -5,9 +5,9 @@ use std::fmt::Debug;
 
 use alloy::rpc::types::eth::{
     request::{TransactionInput, TransactionRequest},
-    BlockId, BlockNumberOrTag, Filter, Log,
+    BlockId, BlockNumberOrTag, EIP1186AccountProofResponse, Filter, Header, Log,
 };
-use alloy_primitives::{Address, Bytes, U256, U64};
+use alloy_primitives::{Address, Bytes, B256, U256, U64};
 use async_trait::async_trait;
 use linera_base::ensure;
 use serde::{de::DeserializeOwned, Deserialize, Serialize};
@@ -121,6 +121,18 @@ pub trait EthereumQueries {
         from: &str,
         block: u64,
     ) -> Result<Bytes, Self::Error>;
+
+    /// Gets the header of the block with the specified number.
+    async fn get_block_header(&self, block_number: u64) -> Result<Header, Self::Error>;
+
+    /// Gets the Merkle proofs (as specified by EIP-1186) of the account at `address` and
+    /// of the given storage `slots` of that account, at the specified block number.
+    async fn get_proof(
+        &self,
+        address: &str,
+        slots: Vec<B256>,
+        block_number: u64,
+    ) -> Result<EIP1186AccountProofResponse, Self::Error>;
 }
 
 pub(crate) fn get_block_id(block_number: u64) -> BlockId {
@@ -195,4 +207,24 @@ where
         let tag = get_block_id(block);
         Ok(self.request::<_, Bytes>("eth_call", (tx, tag)).await?)
     }
+
+    async fn get_block_header(&self, block_number: u64) -> Result<Header, Self::Error> {
+        let number = BlockNumberOrTag::Number(block_number);
+        // Without the full transactions, a block is its header and the transaction hashes,
+        // and the latter are ignored.
+        Ok(self
+            .request::<_, Header>("eth_getBlockByNumber", (number, false))
+            .await?)
+    }
+
+    async fn get_proof(
+        &self,
+        address: &str,
+        slots: Vec<B256>,
+        block_number: u64,
+    ) -> Result<EIP1186AccountProofResponse, Self::Error> {
+        let address = address.parse::<Address>()?;
+        let tag = get_block_id(block_number);
+        Ok(self.request("eth_getProof", (address, slots, tag)).await?)
+    }
 }
@@ -10,6 +10,7 @@
 
 pub mod client;
 pub mod common;
+pub mod proofs;
 
 #[cfg(not(target_arch = "wasm32"))]
 pub mod provider;
@@ -0,0 +1,145 @@
+// Copyright (c) Zefchain Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! Verification of the consistency of Ethereum data returned by a node.
+//!
+//! This is not a light client: the block header and the proofs come from the same node, and
+//! nothing ties the header to the canonical Ethereum chain, e.g. through a sync committee or
+//! a trusted checkpoint. The checks only make sure that the returned storage value is the one
+//! committed to by the returned header, so the node itself must be trusted to serve canonical
+//! headers.
+
+use alloy::rpc::types::eth::{EIP1186AccountProofResponse, Header};
+use alloy_primitives::{keccak256, Address, B256, U256};
+use alloy_rlp::Encodable as _;
+use alloy_trie::{
+    proof::{verify_proof, ProofVerificationError},
+    Nibbles,
+};
+use linera_base::ensure;
+use thiserror::Error;
+
+/// An error found when verifying Ethereum data.
+#[derive(Debug, Error)]
+pub enum VerificationError {
+    /// The header is not the one of the requested block.
+    #[error("expected the header of block {expected}, got block {actual}")]
+    WrongBlockNumber { expected: u64, actual: u64 },
+
+    /// The hash announced with the header is not the hash of its contents.
+    #[error("the block header hashes to {computed}, not to {announced}")]
+    WrongHeaderHash { announced: B256, computed: B256 },
+
+    /// The proof is about a different account.
+    #[error("expected a proof for account {expected}, got one for {actual}")]
+    WrongAccount { expected: Address, actual: Address },
+
+    /// The proof does not contain exactly one storage proof.
+    #[error("expected a single storage proof, got {0}")]
+    WrongStorageProofCount(usize),
+
+    /// The account proof does not match the state root.
+    #[error("invalid account proof: {0}")]
+    InvalidAccountProof(ProofVerificationError),
+
+    /// The storage proof does not match the account's storage root.
+    #[error("invalid storage proof: {0}")]
+    InvalidStorageProof(ProofVerificationError),
+}
+
+/// Checks that `header` is the consistent header of the block with the given number, and
+/// returns the state root it commits to.
+///
+/// This does not check that the block is part of the canonical chain.
+pub fn verify_header(header: &Header, block_number: u64) -> Result<B256, VerificationError> {
+    ensure!(
+        header.inner.number == block_number,
+        VerificationError::WrongBlockNumber {
+            expected: block_number,
+            actual: header.inner.number,
+        }
+    );
+    let computed = header.inner.hash_slow();
+    ensure!(
+        computed == header.hash,
+        VerificationError::WrongHeaderHash {
+            announced: header.hash,
+            computed,
+        }
+    );
+    Ok(header.inner.state_root)
+}
+
+/// Verifies a proof of the value of the storage `slot` of the contract at `address`
+/// against a `state_root`, and returns that value.
+///
+/// The proof is expected to be the response to an `eth_getProof` query for that single slot.
+pub fn verify_storage_proof(
+    state_root: B256,
+    proof: &EIP1186AccountProofResponse,
+    address: Address,
+    slot: B256,
+) -> Result<U256, VerificationError> {
+    ensure!(
+        proof.address == address,
+        VerificationError::WrongAccount {
+            expected: address,
+            actual: proof.address,
+        }
+    );
+    let [storage_proof] = proof.storage_proof.as_slice() else {
+        return Err(VerificationError::WrongStorageProofCount(
+            proof.storage_proof.len(),
+        ));
+    };
+
+    let account = encode_account(proof);
+    verify_proof(
+        state_root,
+        Nibbles::unpack(keccak256(address)),
+        account,
+        &proof.account_proof,
+    )
+    .map_err(VerificationError::InvalidAccountProof)?;
+
+    // Zero values are not stored in the trie, so their proofs are proofs of absence.
+    let value = (!storage_proof.value.is_zero()).then(|| alloy_rlp::encode(storage_proof.value));
+    verify_proof(
+        proof.storage_hash,
+        Nibbles::unpack(keccak256(slot)),
+        value,
+        &storage_proof.proof,
+    )
+    .map_err(VerificationError::InvalidStorageProof)?;
+
+    Ok(storage_proof.value)
+}
+
+/// Returns the RLP encoding of the account as stored in the state trie, or `None` if the
+/// proof is about an account that does not exist.
+fn encode_account(proof: &EIP1186AccountProofResponse) -> Option<Vec<u8>> {
+    let empty_trie_root = keccak256([alloy_rlp::EMPTY_STRING_CODE]);
+    let empty_code_hash = keccak256(b"");
+    if proof.nonce == 0
+        && proof.balance.is_zero()
+        && proof.code_hash == empty_code_hash
+        && proof.storage_hash == empty_trie_root
+    {
+        return None;
+    }
+    let payload_length = proof.nonce.length()
+        + proof.balance.length()
+        + proof.storage_hash.length()
+        + proof.code_hash.length();
+    let mut account = Vec::new();
+    alloy_rlp::Header {
+        list: true,
+        payload_length,
+    }
+    .encode(&mut account);
+    proof.nonce.encode(&mut account);
+    proof.balance.encode(&mut account);
+    proof.storage_hash.encode(&mut account);
+    proof.code_hash.encode(&mut account);
+    Some(account)
+}
@@ -3,10 +3,11 @@
 
 #[cfg(feature = "ethereum")]
 use {
-    alloy_primitives::U256,
+    alloy_primitives::{Address, B256, U256},
     linera_ethereum::{
         client::EthereumQueries,
         common::{EthereumDataType, EthereumEvent},
+        proofs::{verify_header, verify_storage_proof},
         provider::EthereumClientSimplified,
         test_utils::{get_anvil, EventNumericsContractFunction, SimpleTokenContractFunction},
     },
@@ -154,3 +155,42 @@ async fn test_simple_token_queries() -> anyhow::Result<()> {
     assert_eq!(balance_contract, U256::from(0));
     Ok(())
 }
+
+#[cfg(feature = "ethereum")]
+#[tokio::test]
+async fn test_storage_proof_verification() -> anyhow::Result<()> {
+    let anvil_test = get_anvil().await?;
+    let ethereum_client_simp = EthereumClientSimplified::new(anvil_test.endpoint.clone());
+    let simple_token = SimpleTokenContractFunction::new(anvil_test).await?;
+    let contract_address = simple_token.contract_address.clone();
+    let address = contract_address.parse::<Address>()?;
+
+    let block_number = ethereum_client_simp.get_block_number().await?;
+    let mut header = ethereum_client_simp.get_block_header(block_number).await?;
+    let state_root = verify_header(&header, block_number)?;
+    assert!(verify_header(&header, block_number + 1).is_err());
+
+    // The contract's storage is not empty, so one of its first slots holds a value.
+    let mut found_value = false;
+    for index in 0..4u8 {
+        let slot = B256::with_last_byte(index);
+        let proof = ethereum_client_simp
+            .get_proof(&contract_address, vec![slot], block_number)
+            .await?;
+        let value = verify_storage_proof(state_root, &proof, address, slot)?;
+        assert_eq!(value, proof.storage_proof[0].value);
+        found_value |= !value.is_zero();
+
+        // A proof does not verify against another state root or for another slot.
+        assert!(verify_storage_proof(B256::ZERO, &proof, address, slot).is_err());
+        let other_slot = B256::with_last_byte(index + 100);
+        if !value.is_zero() {
+            assert!(verify_storage_proof(state_root, &proof, address, other_slot).is_err());
+        }
+    }
+    assert!(found_value);
+
+    header.inner.state_root = B256::ZERO;
+    assert!(verify_header(&header, block_number).is_err());
+    Ok(())
+}
@@ -12,6 +12,7 @@ fn main() {
         with_tokio_multi_thread: { not(target_arch = "wasm32") },
         with_wasmer: { feature = "wasmer" },
         with_revm: { feature = "revm" },
+        with_ethereum: { all(not(target_arch = "wasm32"), feature = "ethereum") },
         with_wasmtime: { all(not(target_arch = "wasm32"), feature = "wasmtime") },
 
         // If you change this, don't forget to update `WasmRuntime` and
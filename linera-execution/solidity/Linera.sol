@@ -35,6 +35,8 @@ library Linera {
         bytes32 address32;
         // choice=2 corresponds to Address20
         bytes20 address20;
+        // choice=3 corresponds to Chain
+        bytes32 chain;
     }
 
     function accountowner_from(LineraTypes.AccountOwner memory owner)
@@ -42,7 +44,8 @@ library Linera {
         pure
         returns (AccountOwner memory)
     {
-        return AccountOwner(owner.choice, owner.reserved, owner.address32.value, owner.address20);
+        bytes32 chain = owner.chain.value.value;
+        return AccountOwner(owner.choice, owner.reserved, owner.address32.value, owner.address20, chain);
     }
 
     function accountowner_to(Linera.AccountOwner memory owner)
@@ -51,7 +54,9 @@ library Linera {
         returns (LineraTypes.AccountOwner memory)
     {
         LineraTypes.CryptoHash memory hash = LineraTypes.CryptoHash(owner.address32);
-        return LineraTypes.AccountOwner(owner.choice, owner.reserved, hash, owner.address20);
+        LineraTypes.CryptoHash memory chain_hash = LineraTypes.CryptoHash(owner.chain);
+        LineraTypes.ChainId memory chain = LineraTypes.ChainId(chain_hash);
+        return LineraTypes.AccountOwner(owner.choice, owner.reserved, hash, owner.address20, chain);
     }
 
     struct AccountOwnerBalance {
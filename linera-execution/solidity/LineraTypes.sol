@@ -59,6 +59,8 @@ library LineraTypes {
         CryptoHash address32;
         // choice=2 corresponds to Address20
         bytes20 address20;
+        // choice=3 corresponds to Chain
+        ChainId chain;
     }
 
     function AccountOwner_case_reserved(uint8 reserved)
@@ -68,7 +70,8 @@ library LineraTypes {
     {
         CryptoHash memory address32;
         bytes20 address20;
-        return AccountOwner(uint8(0), reserved, address32, address20);
+        ChainId memory chain;
+        return AccountOwner(uint8(0), reserved, address32, address20, chain);
     }
 
     function AccountOwner_case_address32(CryptoHash memory address32)
@@ -78,7 +81,8 @@ library LineraTypes {
     {
         uint8 reserved;
         bytes20 address20;
-        return AccountOwner(uint8(1), reserved, address32, address20);
+        ChainId memory chain;
+        return AccountOwner(uint8(1), reserved, address32, address20, chain);
     }
 
     function AccountOwner_case_address20(bytes20 address20)
@@ -88,7 +92,19 @@ library LineraTypes {
     {
         uint8 reserved;
         CryptoHash memory address32;
-        return AccountOwner(uint8(2), reserved, address32, address20);
+        ChainId memory chain;
+        return AccountOwner(uint8(2), reserved, address32, address20, chain);
+    }
+
+    function AccountOwner_case_chain(ChainId memory chain)
+        internal
+        pure
+        returns (AccountOwner memory)
+    {
+        uint8 reserved;
+        CryptoHash memory address32;
+        bytes20 address20;
+        return AccountOwner(uint8(3), reserved, address32, address20, chain);
     }
 
     function bcs_serialize_AccountOwner(AccountOwner memory input)
@@ -105,6 +121,9 @@ library LineraTypes {
         if (input.choice == 2) {
             return abi.encodePacked(input.choice, bcs_serialize_bytes20(input.address20));
         }
+        if (input.choice == 3) {
+            return abi.encodePacked(input.choice, bcs_serialize_ChainId(input.chain));
+        }
         return abi.encodePacked(input.choice);
     }
 
@@ -128,8 +147,12 @@ library LineraTypes {
         if (choice == 2) {
             (new_pos, address20) = bcs_deserialize_offset_bytes20(new_pos, input);
         }
-        require(choice < 3);
-        return (new_pos, AccountOwner(choice, reserved, address32, address20));
+        ChainId memory chain;
+        if (choice == 3) {
+            (new_pos, chain) = bcs_deserialize_offset_ChainId(new_pos, input);
+        }
+        require(choice < 4);
+        return (new_pos, AccountOwner(choice, reserved, address32, address20, chain));
     }
 
     function bcs_deserialize_AccountOwner(bytes memory input)
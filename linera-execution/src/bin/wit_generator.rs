@@ -14,6 +14,7 @@ use std::{
 
 use anyhow::{Context, Result};
 use clap::Parser as _;
+use linera_base::vm::WasmApiVersion;
 use linera_execution::{
     BaseRuntimeApi, ContractEntrypoints, ContractRuntimeApi, ContractSyncRuntimeHandle,
     RuntimeApiData, ServiceEntrypoints, ServiceRuntimeApi, ServiceSyncRuntimeHandle,
@@ -63,10 +64,12 @@ fn run_operation(options: WitGeneratorOptions, mut operation: impl Operation) ->
     >();
 
     let contract_world = WitWorldWriter::new("linera:app", "contract")
+        .with_version(WasmApiVersion::CURRENT)
         .export::<ContractEntrypoints<StubInstance>>()
         .import::<ContractRuntimeApi<StubInstance<RuntimeApiData<ContractSyncRuntimeHandle>>>>()
         .import::<BaseRuntimeApi<StubInstance<RuntimeApiData<ContractSyncRuntimeHandle>>>>();
     let service_world = WitWorldWriter::new("linera:app", "service")
+        .with_version(WasmApiVersion::CURRENT)
         .export::<ServiceEntrypoints<StubInstance>>()
         .import::<ServiceRuntimeApi<StubInstance<RuntimeApiData<ServiceSyncRuntimeHandle>>>>()
         .import::<BaseRuntimeApi<StubInstance<RuntimeApiData<ContractSyncRuntimeHandle>>>>();
@@ -0,0 +1,42 @@
+// Copyright (c) Zefchain Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! Reads data from external blockchains on behalf of contracts. The data is checked against
+//! the block headers returned by the endpoint configured on the node before it is recorded as
+//! an oracle response. The endpoint is trusted to serve the canonical chain.
+
+use linera_base::bridge::EthereumStorageKey;
+#[cfg(with_ethereum)]
+use {
+    alloy_primitives::{Address, B256},
+    linera_ethereum::{client::EthereumQueries as _, proofs, provider::EthereumClientSimplified},
+};
+
+use crate::ExecutionError;
+
+/// Reads the value of an Ethereum storage slot from the node at `endpoint`, and verifies
+/// it against the header of the requested block.
+#[cfg(with_ethereum)]
+pub(crate) async fn read_ethereum_storage(
+    endpoint: String,
+    key: EthereumStorageKey,
+) -> Result<[u8; 32], ExecutionError> {
+    let client = EthereumClientSimplified::new(endpoint);
+    let address = Address::from(key.address);
+    let slot = B256::from(key.slot);
+    let header = client.get_block_header(key.block_number).await?;
+    let state_root = proofs::verify_header(&header, key.block_number)?;
+    let proof = client
+        .get_proof(&address.to_string(), vec![slot], key.block_number)
+        .await?;
+    let value = proofs::verify_storage_proof(state_root, &proof, address, slot)?;
+    Ok(value.to_be_bytes())
+}
+
+#[cfg(not(with_ethereum))]
+pub(crate) async fn read_ethereum_storage(
+    _endpoint: String,
+    _key: EthereumStorageKey,
+) -> Result<[u8; 32], ExecutionError> {
+    Err(ExecutionError::EthereumBridgeUnavailable)
+}
@@ -308,4 +308,17 @@ impl Committee {
     pub fn policy_mut(&mut self) -> &mut ResourceControlPolicy {
         &mut self.policy
     }
+
+    /// Returns a copy of this committee where the given validators have new voting rights.
+    ///
+    /// Returns `None` if one of the validators is not a member of the committee, or if the
+    /// total voting rights would be zero.
+    pub fn with_votes(&self, votes: &BTreeMap<ValidatorPublicKey, u64>) -> Option<Committee> {
+        let mut validators = self.validators.clone();
+        for (public_key, new_votes) in votes {
+            validators.get_mut(public_key)?.votes = *new_votes;
+        }
+        let committee = Committee::new(validators, self.policy.clone());
+        (committee.total_votes > 0).then_some(committee)
+    }
 }
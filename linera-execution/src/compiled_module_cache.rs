@@ -0,0 +1,265 @@
+// Copyright (c) Zefchain Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! A cache of compiled WebAssembly modules on disk, shared by all applications and kept
+//! across restarts.
+//!
+//! An entry is keyed by the hash of the bytecode, which includes the fuel metering added to
+//! contracts, and by the SHA-256 hash of an engine key, which covers everything else the
+//! compiled code depends on: the runtime and its version, the configuration of its engine,
+//! the version of this crate and the CPU features of the host. Storing an entry removes the
+//! entries of the same namespace compiled for other engine keys, as well as the least
+//! recently used entries beyond the maximum size of the cache.
+//!
+//! Compiled modules are loaded without being validated again, so the directory of the cache
+//! must only be writable by the validator. Each entry starts with the hash of its contents,
+//! so that truncated or corrupted entries are compiled again instead.
+
+#![cfg_attr(not(with_wasm_runtime), allow(dead_code))]
+
+#[cfg(test)]
+#[path = "unit_tests/compiled_module_cache_tests.rs"]
+mod unit_tests;
+
+use std::{
+    collections::HashMap,
+    fs,
+    hash::{Hash, Hasher},
+    io,
+    path::{Path, PathBuf},
+    sync::{Mutex, OnceLock},
+    time::SystemTime,
+};
+
+use linera_base::{
+    crypto::{BcsHashable, CryptoHash},
+    data_types::Bytecode,
+};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest as _, Sha256};
+use tracing::warn;
+
+/// The extension of the files of the cache entries.
+const ENTRY_EXTENSION: &str = "module";
+
+/// The size of the hash at the start of each entry.
+const HASH_SIZE: usize = 32;
+
+/// The cache used by the WebAssembly runtimes, if it is enabled.
+static COMPILED_MODULE_CACHE: OnceLock<CompiledModuleCache> = OnceLock::new();
+
+/// The configuration of the cache of compiled modules on disk.
+#[derive(Clone, Debug)]
+pub struct CompiledModuleCacheConfig {
+    /// The directory where the compiled modules are stored.
+    pub directory: PathBuf,
+    /// The maximum total size of the stored modules, in bytes.
+    pub max_size: u64,
+}
+
+/// Enables the cache of compiled modules on disk for the WebAssembly runtimes, creating its
+/// directory if needed. Only the first call has an effect.
+pub fn enable_compiled_module_cache(config: CompiledModuleCacheConfig) -> io::Result<()> {
+    let cache = CompiledModuleCache::new(config)?;
+    let _ = COMPILED_MODULE_CACHE.set(cache);
+    Ok(())
+}
+
+/// Returns the module compiled from `bytecode`, from the cache on disk if it is enabled and has
+/// it, or by calling `compile` otherwise.
+pub(crate) fn get_or_compile<Module>(
+    namespace: &str,
+    engine_key: impl Hash,
+    bytecode: &Bytecode,
+    compile: impl FnOnce() -> anyhow::Result<Module>,
+    serialize: impl FnOnce(&Module) -> anyhow::Result<Vec<u8>>,
+    deserialize: impl FnOnce(&[u8]) -> anyhow::Result<Module>,
+) -> anyhow::Result<Module> {
+    match COMPILED_MODULE_CACHE.get() {
+        Some(cache) => cache.get_or_compile(
+            namespace,
+            engine_key,
+            bytecode,
+            compile,
+            serialize,
+            deserialize,
+        ),
+        None => compile(),
+    }
+}
+
+/// The bytes hashed to identify a bytecode or to check the contents of an entry.
+#[derive(Serialize, Deserialize)]
+struct CachedBytes<'a>(#[serde(borrow, with = "serde_bytes")] &'a [u8]);
+
+impl<'a> BcsHashable<'a> for CachedBytes<'a> {}
+
+/// A [`Hasher`] computing the SHA-256 hash of the engine keys, which is stable across
+/// releases of Rust, unlike the [`std::collections::hash_map::DefaultHasher`].
+struct EngineKeyHasher(Sha256);
+
+impl Hasher for EngineKeyHasher {
+    fn write(&mut self, bytes: &[u8]) {
+        self.0.update(bytes);
+    }
+
+    fn finish(&self) -> u64 {
+        unreachable!("the engine keys are hashed with `EngineKeyHasher::digest`")
+    }
+}
+
+impl EngineKeyHasher {
+    /// Returns the hexadecimal SHA-256 hash of `engine_key`.
+    fn digest(engine_key: impl Hash) -> String {
+        let mut hasher = EngineKeyHasher(Sha256::new());
+        engine_key.hash(&mut hasher);
+        format!("{:x}", hasher.0.finalize())
+    }
+}
+
+/// The order in which this process used the entries.
+#[derive(Default)]
+struct Recency {
+    /// The number of uses so far.
+    clock: u64,
+    /// The value of the clock when each entry was last used.
+    last_used: HashMap<PathBuf, u64>,
+}
+
+/// A cache of compiled modules in a directory.
+pub(crate) struct CompiledModuleCache {
+    config: CompiledModuleCacheConfig,
+    /// Serializes the writes and evictions made by this process, and records the order in
+    /// which it used the entries. The entries that it didn't use are older, and ordered by
+    /// their modification times, which are updated when they are used.
+    recency: Mutex<Recency>,
+}
+
+impl CompiledModuleCache {
+    /// Creates a cache in the configured directory, creating it if needed.
+    pub(crate) fn new(config: CompiledModuleCacheConfig) -> io::Result<Self> {
+        fs::create_dir_all(&config.directory)?;
+        Ok(CompiledModuleCache {
+            config,
+            recency: Mutex::default(),
+        })
+    }
+
+    /// Returns the module compiled from `bytecode`, loading it from the cache if possible, or
+    /// compiling and storing it otherwise.
+    pub(crate) fn get_or_compile<Module>(
+        &self,
+        namespace: &str,
+        engine_key: impl Hash,
+        bytecode: &Bytecode,
+        compile: impl FnOnce() -> anyhow::Result<Module>,
+        serialize: impl FnOnce(&Module) -> anyhow::Result<Vec<u8>>,
+        deserialize: impl FnOnce(&[u8]) -> anyhow::Result<Module>,
+    ) -> anyhow::Result<Module> {
+        let engine_key = EngineKeyHasher::digest(engine_key);
+        let prefix = format!("{namespace}-{engine_key}-");
+        let bytecode_hash = CryptoHash::new(&CachedBytes(bytecode.as_ref()));
+        let path = self
+            .config
+            .directory
+            .join(format!("{prefix}{bytecode_hash}.{ENTRY_EXTENSION}"));
+
+        if let Some(bytes) = self.read_entry(&path) {
+            match deserialize(&bytes) {
+                Ok(module) => return Ok(module),
+                Err(error) => {
+                    warn!(%error, path = %path.display(), "Failed to load a cached module");
+                    let _ = fs::remove_file(&path);
+                }
+            }
+        }
+        let module = compile()?;
+        let result = serialize(&module).and_then(|bytes| {
+            let mut recency = self.recency.lock().unwrap();
+            Self::write_entry(&path, &bytes)?;
+            recency.touch(&path);
+            self.evict(&mut recency, namespace, &prefix)?;
+            Ok(())
+        });
+        if let Err(error) = result {
+            warn!(%error, path = %path.display(), "Failed to cache a compiled module");
+        }
+        Ok(module)
+    }
+
+    /// Reads the compiled module in the entry at `path`, if it exists and is intact, and marks
+    /// it as recently used.
+    fn read_entry(&self, path: &Path) -> Option<Vec<u8>> {
+        let mut bytes = fs::read(path).ok()?;
+        if bytes.len() < HASH_SIZE
+            || CryptoHash::new(&CachedBytes(&bytes[HASH_SIZE..])).as_bytes()[..]
+                != bytes[..HASH_SIZE]
+        {
+            warn!(path = %path.display(), "Removing a corrupted cached module");
+            let _ = fs::remove_file(path);
+            return None;
+        }
+        self.recency.lock().unwrap().touch(path);
+        if let Ok(file) = fs::File::options().write(true).open(path) {
+            let _ = file.set_modified(SystemTime::now());
+        }
+        bytes.drain(..HASH_SIZE);
+        Some(bytes)
+    }
+
+    /// Writes a compiled module to the entry at `path`, atomically.
+    fn write_entry(path: &Path, bytes: &[u8]) -> io::Result<()> {
+        let hash = CryptoHash::new(&CachedBytes(bytes));
+        let mut contents = Vec::with_capacity(HASH_SIZE + bytes.len());
+        contents.extend_from_slice(&hash.as_bytes()[..]);
+        contents.extend_from_slice(bytes);
+        let temporary_path = path.with_extension(format!("{}.tmp", std::process::id()));
+        fs::write(&temporary_path, contents)?;
+        fs::rename(&temporary_path, path)
+    }
+
+    /// Removes the entries of `namespace` that don't start with `prefix`, as they were compiled
+    /// for another engine, then the least recently used entries beyond the maximum size.
+    fn evict(&self, recency: &mut Recency, namespace: &str, prefix: &str) -> io::Result<()> {
+        let mut entries = Vec::new();
+        for dir_entry in fs::read_dir(&self.config.directory)? {
+            let dir_entry = dir_entry?;
+            let path = dir_entry.path();
+            if path
+                .extension()
+                .is_none_or(|extension| extension != ENTRY_EXTENSION)
+            {
+                continue;
+            }
+            let name = dir_entry.file_name();
+            let name = name.to_string_lossy();
+            if name.starts_with(&format!("{namespace}-")) && !name.starts_with(prefix) {
+                fs::remove_file(&path)?;
+                recency.last_used.remove(&path);
+                continue;
+            }
+            let metadata = dir_entry.metadata()?;
+            let last_used = recency.last_used.get(&path).copied();
+            entries.push(((last_used, metadata.modified()?), metadata.len(), path));
+        }
+        let mut total_size = entries.iter().map(|(_, size, _)| size).sum::<u64>();
+        entries.sort_unstable();
+        for (_, size, path) in entries {
+            if total_size <= self.config.max_size {
+                break;
+            }
+            fs::remove_file(&path)?;
+            recency.last_used.remove(&path);
+            total_size -= size;
+        }
+        Ok(())
+    }
+}
+
+impl Recency {
+    /// Records that the entry at `path` was just used.
+    fn touch(&mut self, path: &Path) {
+        self.clock += 1;
+        self.last_used.insert(path.to_path_buf(), self.clock);
+    }
+}
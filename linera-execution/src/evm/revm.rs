@@ -54,6 +54,14 @@ const PROCESS_STREAMS_SELECTOR: &[u8] = &[254, 72, 102, 28];
 /// only when creating a new instance of a shared contract
 const INSTANTIATE_SELECTOR: &[u8] = &[156, 163, 60, 158];
 
+/// This is the selector of the `on_chain_close` that should be called
+/// only when the chain is closed
+const ON_CHAIN_CLOSE_SELECTOR: &[u8] = &[78, 43, 146, 4];
+
+/// This is the selector of the `on_upgrade` that should be called
+/// only when the application is upgraded
+const ON_UPGRADE_SELECTOR: &[u8] = &[25, 189, 123, 60];
+
 fn forbid_execute_operation_origin(vec: &[u8]) -> Result<(), EvmExecutionError> {
     if vec == EXECUTE_MESSAGE_SELECTOR {
         return Err(EvmExecutionError::IllegalOperationCall(
@@ -70,6 +78,16 @@ fn forbid_execute_operation_origin(vec: &[u8]) -> Result<(), EvmExecutionError>
             "function instantiate".to_string(),
         ));
     }
+    if vec == ON_CHAIN_CLOSE_SELECTOR {
+        return Err(EvmExecutionError::IllegalOperationCall(
+            "function on_chain_close".to_string(),
+        ));
+    }
+    if vec == ON_UPGRADE_SELECTOR {
+        return Err(EvmExecutionError::IllegalOperationCall(
+            "function on_upgrade".to_string(),
+        ));
+    }
     Ok(())
 }
 
@@ -101,7 +119,8 @@ mod tests {
     use revm_primitives::keccak256;
 
     use crate::evm::revm::{
-        EXECUTE_MESSAGE_SELECTOR, INSTANTIATE_SELECTOR, PROCESS_STREAMS_SELECTOR,
+        EXECUTE_MESSAGE_SELECTOR, INSTANTIATE_SELECTOR, ON_CHAIN_CLOSE_SELECTOR,
+        ON_UPGRADE_SELECTOR, PROCESS_STREAMS_SELECTOR,
     };
 
     // The function keccak256 is not const so we cannot build the execute_message
@@ -159,6 +178,18 @@ mod tests {
         let selector = &keccak256("instantiate(bytes)".as_bytes())[..4];
         assert_eq!(selector, INSTANTIATE_SELECTOR);
     }
+
+    #[test]
+    fn check_on_chain_close_selector() {
+        let selector = &keccak256("on_chain_close()".as_bytes())[..4];
+        assert_eq!(selector, ON_CHAIN_CLOSE_SELECTOR);
+    }
+
+    #[test]
+    fn check_on_upgrade_selector() {
+        let selector = &keccak256("on_upgrade()".as_bytes())[..4];
+        assert_eq!(selector, ON_UPGRADE_SELECTOR);
+    }
 }
 
 fn has_selector(module: &[u8], selector: &[u8]) -> bool {
@@ -1211,6 +1242,40 @@ where
         self.execute_no_return_operation(operation, "process_streams", caller)
     }
 
+    fn on_chain_close(&mut self) -> Result<(), ExecutionError> {
+        // Unlike `process_streams`, this function is optional.
+        if !has_selector(&self.module, ON_CHAIN_CLOSE_SELECTOR) {
+            return Ok(());
+        }
+        self.db.set_contract_address()?;
+        let operation = ON_CHAIN_CLOSE_SELECTOR.to_vec();
+        // The closure of the chain is not authenticated by any signer or caller.
+        let caller = Address::ZERO;
+        self.execute_no_return_operation(operation, "on_chain_close", caller)
+    }
+
+    fn on_upgrade(&mut self) -> Result<(), ExecutionError> {
+        // Like `on_chain_close`, this function is optional.
+        if !has_selector(&self.module, ON_UPGRADE_SELECTOR) {
+            return Ok(());
+        }
+        self.db.set_contract_address()?;
+        let operation = ON_UPGRADE_SELECTOR.to_vec();
+        // The upgrade is performed by the system, not by any signer or caller.
+        let caller = Address::ZERO;
+        self.execute_no_return_operation(operation, "on_upgrade", caller)
+    }
+
+    fn authorize_proposal(&mut self, _payload: Vec<u8>) -> Result<bool, ExecutionError> {
+        // EVM contracts cannot act as authorization applications yet.
+        Ok(false)
+    }
+
+    fn execute_view(&mut self, _argument: Vec<u8>) -> Result<Vec<u8>, ExecutionError> {
+        // EVM contracts don't expose view functions to other applications yet.
+        Err(ExecutionError::ViewCallsNotSupported)
+    }
+
     fn finalize(&mut self) -> Result<(), ExecutionError> {
         Ok(())
     }
@@ -8,22 +8,27 @@ use std::{
 
 use futures::{FutureExt, StreamExt};
 use linera_base::{
+    crypto::CryptoHash,
     data_types::{Amount, BlockHeight, StreamUpdate},
+    ensure,
     identifiers::{Account, AccountOwner, StreamId},
+    merkle::{
+        self, MerkleHash, MerkleProof, MerkleStateHash, MerkleTreeBuilder, StateInclusionProof,
+    },
 };
 use linera_views::{
     context::Context,
     key_value_store_view::KeyValueStoreView,
     map_view::MapView,
     reentrant_collection_view::HashedReentrantCollectionView,
-    views::{ClonableView, View},
+    views::{ClonableView, CryptoHashView as _, HashableView as _, View},
+    ViewError,
 };
 use linera_views_derive::CryptoHashView;
+use tracing::warn;
 #[cfg(with_testing)]
 use {
-    crate::{
-        ResourceControlPolicy, ResourceTracker, TestExecutionRuntimeContext, UserContractCode,
-    },
+    crate::{ResourceControlPolicy, ResourceTracker, TestExecutionRuntimeContext},
     linera_base::data_types::Blob,
     linera_views::context::MemoryContext,
     std::sync::Arc,
@@ -35,7 +40,7 @@ use crate::{
     ApplicationId, ContractSyncRuntime, ExecutionError, ExecutionRuntimeConfig,
     ExecutionRuntimeContext, Message, MessageContext, MessageKind, Operation, OperationContext,
     OutgoingMessage, ProcessStreamsContext, Query, QueryContext, QueryOutcome, ServiceSyncRuntime,
-    SystemMessage, Timestamp, TransactionTracker,
+    SystemMessage, SystemOperation, Timestamp, TransactionTracker, UserContractCode,
 };
 
 /// A view accessing the execution state of a chain.
@@ -138,6 +143,9 @@ pub enum UserAction {
     Operation(OperationContext, Vec<u8>),
     Message(MessageContext, Vec<u8>),
     ProcessStreams(ProcessStreamsContext, Vec<StreamUpdate>),
+    CloseChain(ProcessStreamsContext),
+    Upgrade(ProcessStreamsContext),
+    AuthorizeProposal(OperationContext, Vec<u8>),
 }
 
 impl UserAction {
@@ -146,7 +154,10 @@ impl UserAction {
             UserAction::Instantiate(context, _) => context.authenticated_signer,
             UserAction::Operation(context, _) => context.authenticated_signer,
             UserAction::ProcessStreams(_, _) => None,
+            UserAction::CloseChain(_) => None,
+            UserAction::Upgrade(_) => None,
             UserAction::Message(context, _) => context.authenticated_signer,
+            UserAction::AuthorizeProposal(context, _) => context.authenticated_signer,
         }
     }
 
@@ -155,7 +166,10 @@ impl UserAction {
             UserAction::Instantiate(context, _) => context.height,
             UserAction::Operation(context, _) => context.height,
             UserAction::ProcessStreams(context, _) => context.height,
+            UserAction::CloseChain(context) => context.height,
+            UserAction::Upgrade(context) => context.height,
             UserAction::Message(context, _) => context.height,
+            UserAction::AuthorizeProposal(context, _) => context.height,
         }
     }
 
@@ -164,7 +178,10 @@ impl UserAction {
             UserAction::Instantiate(context, _) => context.round,
             UserAction::Operation(context, _) => context.round,
             UserAction::ProcessStreams(context, _) => context.round,
+            UserAction::CloseChain(context) => context.round,
+            UserAction::Upgrade(context) => context.round,
             UserAction::Message(context, _) => context.round,
+            UserAction::AuthorizeProposal(context, _) => context.round,
         }
     }
 
@@ -173,7 +190,10 @@ impl UserAction {
             UserAction::Instantiate(context, _) => context.timestamp,
             UserAction::Operation(context, _) => context.timestamp,
             UserAction::ProcessStreams(context, _) => context.timestamp,
+            UserAction::CloseChain(context) => context.timestamp,
+            UserAction::Upgrade(context) => context.timestamp,
             UserAction::Message(context, _) => context.timestamp,
+            UserAction::AuthorizeProposal(context, _) => context.timestamp,
         }
     }
 }
@@ -183,6 +203,129 @@ where
     C: Context + Clone + Send + Sync + 'static,
     C::Extra: ExecutionRuntimeContext,
 {
+    /// Returns whether the state hash is a [`MerkleStateHash`], according to the policy of
+    /// the current committee.
+    pub fn uses_merkle_state_hash(&self) -> bool {
+        self.system
+            .current_committee()
+            .is_some_and(|(_, committee)| committee.policy().merkle_state_hash)
+    }
+
+    /// Returns the hash of the execution state that blocks contain: the [`MerkleStateHash`]
+    /// if the policy of the current committee enables it, or the hash of the view otherwise.
+    pub async fn state_hash(&self) -> Result<CryptoHash, ViewError> {
+        if !self.uses_merkle_state_hash() {
+            return self.crypto_hash().await;
+        }
+        let storage_roots = self.storage_roots().await?;
+        self.merkle_state_hash(&storage_roots).await
+    }
+
+    /// Returns the same hash as [`Self::state_hash`]. The Merkle trees of the applications'
+    /// storage that have to be rebuilt from all their entries are kept in the view, so that
+    /// they are saved with it and only updated incrementally from then on.
+    pub async fn state_hash_mut(&mut self) -> Result<CryptoHash, ViewError> {
+        if !self.uses_merkle_state_hash() {
+            return self.crypto_hash_mut().await;
+        }
+        let mut storage_roots = Vec::new();
+        for application_id in self.users.indices().await? {
+            let storage = self
+                .users
+                .try_load_entry(&application_id)
+                .await?
+                .ok_or(ViewError::MissingEntries)?;
+            let root = if storage.stores_merkle_tree() {
+                storage.merkle_root().await?
+            } else {
+                drop(storage);
+                let storage = self.users.try_load_entry_mut(&application_id).await?;
+                storage.merkle_root().await?
+            };
+            storage_roots.push((application_id, root));
+        }
+        self.merkle_state_hash(&storage_roots).await
+    }
+
+    /// Returns the value that `application_id` stores under `key`, with a proof that it is
+    /// part of this execution state, or `None` if there is no such value.
+    ///
+    /// The proof can be checked against the [`MerkleStateHash`], e.g. a block's state hash,
+    /// with [`linera_base::merkle::verify_state_inclusion`]. It is only meaningful if the
+    /// state hash is a Merkle state hash; see [`Self::uses_merkle_state_hash`].
+    pub async fn inclusion_proof(
+        &self,
+        application_id: ApplicationId,
+        key: &[u8],
+    ) -> Result<Option<(Vec<u8>, StateInclusionProof)>, ViewError> {
+        let Some(storage) = self.users.try_load_entry(&application_id).await? else {
+            return Ok(None);
+        };
+        let Some((value, storage_proof)) = storage.inclusion_proof(key).await? else {
+            return Ok(None);
+        };
+        drop(storage);
+        let storage_roots = self.storage_roots().await?;
+        let (_, application_proof) = Self::applications_tree(&storage_roots, Some(application_id));
+        let application_proof = application_proof.ok_or(ViewError::MissingEntries)?;
+        let proof = StateInclusionProof {
+            system_hash: self.system.hash().await?.into(),
+            stream_event_counts_hash: self.stream_event_counts.hash().await?.into(),
+            application_proof,
+            storage_proof,
+        };
+        Ok(Some((value, proof)))
+    }
+
+    /// Returns the roots of the Merkle trees of the applications' storage. These are stored
+    /// with the storage, and only the trees of the storage changed since it was last saved
+    /// are updated.
+    async fn storage_roots(&self) -> Result<Vec<(ApplicationId, MerkleHash)>, ViewError> {
+        let mut storage_roots = Vec::new();
+        for application_id in self.users.indices().await? {
+            let storage = self
+                .users
+                .try_load_entry(&application_id)
+                .await?
+                .ok_or(ViewError::MissingEntries)?;
+            storage_roots.push((application_id, storage.merkle_root().await?));
+        }
+        Ok(storage_roots)
+    }
+
+    /// Returns the [`MerkleStateHash`] of the execution state, given the roots of the
+    /// applications' storage.
+    async fn merkle_state_hash(
+        &self,
+        storage_roots: &[(ApplicationId, MerkleHash)],
+    ) -> Result<CryptoHash, ViewError> {
+        let (applications_root, _) = Self::applications_tree(storage_roots, None);
+        Ok(MerkleStateHash {
+            system_hash: self.system.hash().await?.into(),
+            applications_root,
+            stream_event_counts_hash: self.stream_event_counts.hash().await?.into(),
+        }
+        .state_hash())
+    }
+
+    /// Returns the root of the Merkle tree of the applications' storage, and the proof of the
+    /// storage of `proven`, if any.
+    fn applications_tree(
+        storage_roots: &[(ApplicationId, MerkleHash)],
+        proven: Option<ApplicationId>,
+    ) -> (MerkleHash, Option<MerkleProof>) {
+        let mut builder = MerkleTreeBuilder::default();
+        for (application_id, storage_root) in storage_roots {
+            let leaf = merkle::application_leaf_hash(application_id, storage_root);
+            if proven == Some(*application_id) {
+                builder.push_proven(leaf);
+            } else {
+                builder.push(leaf);
+            }
+        }
+        builder.finish()
+    }
+
     async fn run_user_action(
         &mut self,
         application_id: ApplicationId,
@@ -192,27 +335,67 @@ where
         txn_tracker: &mut TransactionTracker,
         resource_controller: &mut ResourceController<Option<AccountOwner>>,
     ) -> Result<(), ExecutionError> {
-        let ExecutionRuntimeConfig {} = self.context().extra().execution_runtime_config();
+        let contract = self.load_contract(application_id, txn_tracker).await?;
+        self.run_loaded_user_action(
+            application_id,
+            contract,
+            action,
+            refund_grant_to,
+            grant,
+            txn_tracker,
+            resource_controller,
+        )
+        .await
+    }
+
+    /// Runs an action of an application whose contract was already loaded.
+    #[expect(clippy::too_many_arguments)]
+    async fn run_loaded_user_action(
+        &mut self,
+        application_id: ApplicationId,
+        contract: (UserContractCode, ApplicationDescription),
+        action: UserAction,
+        refund_grant_to: Option<Account>,
+        grant: Option<&mut Amount>,
+        txn_tracker: &mut TransactionTracker,
+        resource_controller: &mut ResourceController<Option<AccountOwner>>,
+    ) -> Result<(), ExecutionError> {
+        let ExecutionRuntimeConfig {
+            disable_instance_pooling,
+            ethereum_endpoint: _,
+        } = self.context().extra().execution_runtime_config();
         self.run_user_action_with_runtime(
             application_id,
+            contract,
             action,
             refund_grant_to,
             grant,
             txn_tracker,
             resource_controller,
+            !disable_instance_pooling,
         )
         .await
     }
 
+    #[expect(clippy::too_many_arguments)]
     async fn run_user_action_with_runtime(
         &mut self,
         application_id: ApplicationId,
+        (code, description): (UserContractCode, ApplicationDescription),
         action: UserAction,
         refund_grant_to: Option<Account>,
         grant: Option<&mut Amount>,
         txn_tracker: &mut TransactionTracker,
         resource_controller: &mut ResourceController<Option<AccountOwner>>,
+        pool_instances: bool,
     ) -> Result<(), ExecutionError> {
+        let application_filter = txn_tracker.application_filter().cloned();
+        if let Some(applications) = &application_filter {
+            ensure!(
+                applications.contains(&application_id),
+                ExecutionError::UntrackedState(format!("running application {application_id}"))
+            );
+        }
         let chain_id = self.context().extra().chain_id();
         let mut cloned_grant = grant.as_ref().map(|x| **x);
         let initial_balance = resource_controller
@@ -226,7 +409,9 @@ where
         );
         let (execution_state_sender, mut execution_state_receiver) =
             futures::channel::mpsc::unbounded();
-        let (code, description) = self.load_contract(application_id, txn_tracker).await?;
+        // Only operations have a result: the actions that follow in the same transaction,
+        // e.g. lifecycle hooks or stream processing, must not overwrite it.
+        let is_operation = matches!(action, UserAction::Operation(..));
         let txn_tracker_moved = mem::take(txn_tracker);
         let contract_runtime_task = linera_base::task::Blocking::spawn(move |mut codes| {
             let runtime = ContractSyncRuntime::new(
@@ -236,6 +421,7 @@ where
                 controller,
                 &action,
                 txn_tracker_moved,
+                pool_instances,
             );
 
             async move {
@@ -249,13 +435,21 @@ where
         contract_runtime_task.send(code)?;
 
         while let Some(request) = execution_state_receiver.next().await {
+            if let Some(applications) = &application_filter {
+                ensure!(
+                    request.is_within(applications),
+                    ExecutionError::UntrackedState(format!("{request:?}"))
+                );
+            }
             self.handle_request(request, resource_controller).await?;
         }
 
         let (result, controller, txn_tracker_moved) = contract_runtime_task.join().await?;
 
         *txn_tracker = txn_tracker_moved;
-        txn_tracker.add_operation_result(result);
+        if is_operation {
+            txn_tracker.add_operation_result(result);
+        }
 
         resource_controller
             .with_state_and_grant(&mut self.system, grant)
@@ -274,17 +468,24 @@ where
         resource_controller: &mut ResourceController<Option<AccountOwner>>,
     ) -> Result<(), ExecutionError> {
         assert_eq!(context.chain_id, self.context().extra().chain_id());
+        let was_closed = *self.system.closed.get();
         match operation {
-            Operation::System(op) => {
-                let new_application = self
-                    .system
-                    .execute_operation(context, *op, txn_tracker, resource_controller)
-                    .await?;
-                if let Some((application_id, argument)) = new_application {
-                    let user_action = UserAction::Instantiate(context, argument);
+            Operation::System(op) => match *op {
+                SystemOperation::AuthorizeProposal { payload } => {
+                    self.authorize_proposal(context, payload, txn_tracker, resource_controller)
+                        .await?;
+                }
+                SystemOperation::UpgradeApplication {
+                    application_id,
+                    module_id,
+                } => {
+                    self.system
+                        .upgrade_application(context, application_id, module_id, txn_tracker)
+                        .await?;
+                    // The hook runs on the new code, and the upgrade fails if it fails.
                     self.run_user_action(
                         application_id,
-                        user_action,
+                        UserAction::Upgrade(context.into()),
                         context.refund_grant_to(),
                         None,
                         txn_tracker,
@@ -292,13 +493,42 @@ where
                     )
                     .await?;
                 }
-            }
+                op => {
+                    let new_application = self
+                        .system
+                        .execute_operation(context, op, txn_tracker, resource_controller)
+                        .await?;
+                    if let Some((application_id, argument)) = new_application {
+                        let user_action = UserAction::Instantiate(context, argument);
+                        self.run_user_action(
+                            application_id,
+                            user_action,
+                            context.refund_grant_to(),
+                            None,
+                            txn_tracker,
+                            resource_controller,
+                        )
+                        .await?;
+                    }
+                }
+            },
             Operation::User {
                 application_id,
                 bytes,
             } => {
-                self.run_user_action(
+                let contract = self.load_contract(application_id, txn_tracker).await?;
+                if let Some(fees) = contract.1.operation_fees() {
+                    self.system
+                        .charge_operation_fee(
+                            context.authenticated_signer,
+                            application_id,
+                            fees.fee(&bytes),
+                        )
+                        .await?;
+                }
+                self.run_loaded_user_action(
                     application_id,
+                    contract,
                     UserAction::Operation(context, bytes),
                     context.refund_grant_to(),
                     None,
@@ -308,6 +538,10 @@ where
                 .await?;
             }
         }
+        if !was_closed {
+            self.process_chain_closure(txn_tracker, resource_controller, context.into())
+                .await?;
+        }
         self.process_subscriptions(txn_tracker, resource_controller, context.into())
             .await?;
         Ok(())
@@ -322,9 +556,13 @@ where
         resource_controller: &mut ResourceController<Option<AccountOwner>>,
     ) -> Result<(), ExecutionError> {
         assert_eq!(context.chain_id, self.context().extra().chain_id());
+        let was_closed = *self.system.closed.get();
         match message {
             Message::System(message) => {
-                let outcome = self.system.execute_message(context, message).await?;
+                let outcome = self
+                    .system
+                    .execute_message(context, message, resource_controller.policy())
+                    .await?;
                 txn_tracker.add_outgoing_messages(outcome)?;
             }
             Message::User {
@@ -342,6 +580,10 @@ where
                 .await?;
             }
         }
+        if !was_closed {
+            self.process_chain_closure(txn_tracker, resource_controller, context.into())
+                .await?;
+        }
         self.process_subscriptions(txn_tracker, resource_controller, context.into())
             .await?;
         Ok(())
@@ -408,7 +650,11 @@ where
                 application_id,
                 bytes,
             } => {
-                let ExecutionRuntimeConfig {} = self.context().extra().execution_runtime_config();
+                // Service instances are not pooled.
+                let ExecutionRuntimeConfig {
+                    disable_instance_pooling: _,
+                    ethereum_endpoint: _,
+                } = self.context().extra().execution_runtime_config();
                 let outcome = match endpoint {
                     Some(endpoint) => {
                         self.query_user_application_with_long_lived_service(
@@ -421,7 +667,7 @@ where
                         .await?
                     }
                     None => {
-                        self.query_user_application(application_id, context, bytes)
+                        self.query_user_application(application_id, context, bytes, None)
                             .await?
                     }
                 };
@@ -430,11 +676,31 @@ where
         }
     }
 
+    /// Queries a user application, which may only use the state of the given `applications`,
+    /// e.g. on a chain where only these applications are executed.
+    pub async fn query_tracked_application(
+        &mut self,
+        context: QueryContext,
+        application_id: ApplicationId,
+        query: Vec<u8>,
+        applications: &BTreeSet<ApplicationId>,
+    ) -> Result<QueryOutcome, ExecutionError> {
+        ensure!(
+            applications.contains(&application_id),
+            ExecutionError::UntrackedState(format!("querying application {application_id}"))
+        );
+        let outcome = self
+            .query_user_application(application_id, context, query, Some(applications))
+            .await?;
+        Ok(outcome.into())
+    }
+
     async fn query_user_application(
         &mut self,
         application_id: ApplicationId,
         context: QueryContext,
         query: Vec<u8>,
+        application_filter: Option<&BTreeSet<ApplicationId>>,
     ) -> Result<QueryOutcome<Vec<u8>>, ExecutionError> {
         let (execution_state_sender, mut execution_state_receiver) =
             futures::channel::mpsc::unbounded();
@@ -456,6 +722,12 @@ where
         service_runtime_task.send(code)?;
 
         while let Some(request) = execution_state_receiver.next().await {
+            if let Some(applications) = application_filter {
+                ensure!(
+                    request.is_within(applications),
+                    ExecutionError::UntrackedState(format!("{request:?}"))
+                );
+            }
             self.handle_request(request, &mut ResourceController::default())
                 .await?;
         }
@@ -563,4 +835,101 @@ where
             }
         }
     }
+
+    /// Runs the chain's authorization application to check that the authenticated signer may
+    /// propose the current block, and that this doesn't take more fuel than the policy allows.
+    async fn authorize_proposal(
+        &mut self,
+        context: OperationContext,
+        payload: Vec<u8>,
+        txn_tracker: &mut TransactionTracker,
+        resource_controller: &mut ResourceController<Option<AccountOwner>>,
+    ) -> Result<(), ExecutionError> {
+        let Some(application_id) = *self.system.authorization_application.get() else {
+            return Err(ExecutionError::NoAuthorizationApplication);
+        };
+        ensure!(
+            context.authenticated_signer.is_some(),
+            ExecutionError::ProposalNotAuthorized
+        );
+        let fuel_before = resource_controller.tracker.total_fuel();
+        self.run_user_action(
+            application_id,
+            UserAction::AuthorizeProposal(context, payload),
+            None,
+            None,
+            txn_tracker,
+            resource_controller,
+        )
+        .await?;
+        let fuel = resource_controller
+            .tracker
+            .total_fuel()
+            .saturating_sub(fuel_before);
+        ensure!(
+            fuel <= resource_controller.policy().maximum_authorization_fuel,
+            ExecutionError::MaximumAuthorizationFuelExceeded
+        );
+        Ok(())
+    }
+
+    /// Calls `on_chain_close` for all applications with a state on this chain, if the chain
+    /// has just been closed.
+    ///
+    /// An application whose hook fails cannot prevent the chain from closing: the changes made
+    /// by its hook are discarded, and the failure is logged. Only errors caused by data missing
+    /// locally are returned, since other validators may not encounter them.
+    ///
+    /// Hooks cannot call other applications, so only the system state, the state of the
+    /// notified application and the event counts need to be restored after a failure.
+    async fn process_chain_closure(
+        &mut self,
+        txn_tracker: &mut TransactionTracker,
+        resource_controller: &mut ResourceController<Option<AccountOwner>>,
+        context: ProcessStreamsContext,
+    ) -> Result<(), ExecutionError> {
+        if !*self.system.closed.get() {
+            return Ok(());
+        }
+        for app_id in self.users.indices().await? {
+            let system_snapshot = self.system.clone_unchecked()?;
+            let user_snapshot = self
+                .users
+                .try_load_entry_mut(&app_id)
+                .await?
+                .clone_unchecked()?;
+            let stream_event_counts_snapshot = self.stream_event_counts.clone_unchecked()?;
+            let tracker_snapshot = txn_tracker.snapshot();
+            let result = self
+                .run_user_action(
+                    app_id,
+                    UserAction::CloseChain(context),
+                    None,
+                    None,
+                    txn_tracker,
+                    resource_controller,
+                )
+                .await;
+            match result {
+                Ok(()) => {}
+                Err(error) if error.is_retriable() => return Err(error),
+                Err(error) => {
+                    warn!(
+                        %app_id,
+                        %error,
+                        "The chain closure hook failed; discarding its changes"
+                    );
+                    // The blobs read in the meantime remain used, as in a speculative call.
+                    let used_blobs = txn_tracker.restore(tracker_snapshot);
+                    self.system = system_snapshot;
+                    *self.users.try_load_entry_mut(&app_id).await? = user_snapshot;
+                    self.stream_event_counts = stream_event_counts_snapshot;
+                    for blob_id in &used_blobs {
+                        self.system.used_blobs.insert(blob_id)?;
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
 }
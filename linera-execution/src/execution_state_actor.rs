@@ -5,20 +5,28 @@
 
 #[cfg(not(web))]
 use std::time::Duration;
+use std::{any::Any, collections::BTreeSet};
 
 use custom_debug_derive::Debug;
 use futures::{channel::mpsc, StreamExt as _};
 #[cfg(with_metrics)]
 use linera_base::prometheus_util::MeasureLatency as _;
 use linera_base::{
+    bridge::EthereumStorageKey,
     data_types::{
-        Amount, ApplicationPermissions, ArithmeticError, BlobContent, BlockHeight, Timestamp,
+        Amount, ApplicationPermissions, ArithmeticError, BlobContent, BlockHeight, Epoch, Timestamp,
     },
     ensure, hex_debug, hex_vec_debug, http,
-    identifiers::{Account, AccountOwner, BlobId, BlobType, ChainId, EventId, StreamId},
+    identifiers::{
+        Account, AccountOwner, BlobId, BlobType, ChainId, EventId, GenericApplicationId, StreamId,
+    },
     ownership::ChainOwnership,
 };
-use linera_views::{batch::Batch, context::Context, views::View};
+use linera_views::{
+    batch::Batch,
+    context::Context,
+    views::{ClonableView as _, View},
+};
 use oneshot::Sender;
 use reqwest::{header::HeaderMap, Client, Url};
 
@@ -77,10 +85,14 @@ where
             Some(blob) => bcs::from_bytes(blob.bytes())?,
             None => self.system.describe_application(id, txn_tracker).await?,
         };
+        let code_description = self
+            .system
+            .code_description(&id, description.clone())
+            .await?;
         let code = self
             .context()
             .extra()
-            .get_user_contract(&description, txn_tracker.created_blobs())
+            .get_user_contract(&code_description, txn_tracker.created_blobs())
             .await?;
         Ok((code, description))
     }
@@ -97,10 +109,14 @@ where
             Some(blob) => bcs::from_bytes(blob.bytes())?,
             None => self.system.describe_application(id, txn_tracker).await?,
         };
+        let code_description = self
+            .system
+            .code_description(&id, description.clone())
+            .await?;
         let code = self
             .context()
             .extra()
-            .get_user_service(&description, txn_tracker.created_blobs())
+            .get_user_service(&code_description, txn_tracker.created_blobs())
             .await?;
         Ok((code, description))
     }
@@ -201,6 +217,16 @@ where
                 callback.respond(ownership);
             }
 
+            CurrentEpoch { callback } => {
+                let epoch = *self.system.epoch.get();
+                callback.respond(epoch);
+            }
+
+            ApplicationConfig { id, callback } => {
+                let config = self.system.application_configs.get(&id).await?;
+                callback.respond(config);
+            }
+
             ContainsKey { id, key, callback } => {
                 let view = self.users.try_load_entry(&id).await?;
                 let result = match view {
@@ -320,6 +346,7 @@ where
                     self.system
                         .application_permissions
                         .set(application_permissions);
+                    self.system.staged_application_permissions.set(None);
                     callback.respond(Ok(()));
                 }
             }
@@ -401,6 +428,17 @@ where
                 );
             }
 
+            ReadEthereumStorage { key, callback } => {
+                let endpoint = self
+                    .context()
+                    .extra()
+                    .execution_runtime_config()
+                    .ethereum_endpoint
+                    .ok_or(ExecutionError::EthereumBridgeNotConfigured)?;
+                let value = crate::bridge::read_ethereum_storage(endpoint, key).await?;
+                callback.respond(value);
+            }
+
             ReadBlobContent { blob_id, callback } => {
                 let blob = self.system.read_blob_content(blob_id).await?;
                 if blob_id.blob_type == BlobType::Data {
@@ -451,6 +489,34 @@ where
                 callback.respond(bytes);
             }
 
+            ReadApplicationValue {
+                chain_id,
+                application_id,
+                key,
+                callback,
+            } => {
+                let value = self
+                    .context()
+                    .extra()
+                    .read_application_value(chain_id, application_id, key)
+                    .await?;
+                callback.respond(value);
+            }
+
+            QueryApplicationAt {
+                application_id,
+                height,
+                query,
+                callback,
+            } => {
+                let response = self
+                    .context()
+                    .extra()
+                    .query_application_at(application_id, height, query)
+                    .await?;
+                callback.respond(response);
+            }
+
             SubscribeToEvents {
                 chain_id,
                 stream_id,
@@ -493,6 +559,25 @@ where
                 let app_permissions = self.system.application_permissions.get();
                 callback.respond(app_permissions.clone());
             }
+
+            SnapshotState { callback } => {
+                callback.respond(StateSnapshot(Box::new(self.clone_unchecked()?)));
+            }
+
+            RestoreState {
+                snapshot,
+                used_blobs,
+                callback,
+            } => {
+                *self = *snapshot
+                    .0
+                    .downcast()
+                    .expect("Snapshots should only be restored into the state they were taken of");
+                for blob_id in &used_blobs {
+                    self.system.used_blobs.insert(blob_id)?;
+                }
+                callback.respond(());
+            }
         }
 
         Ok(())
@@ -638,6 +723,17 @@ pub enum ExecutionRequest {
         callback: Sender<ChainOwnership>,
     },
 
+    CurrentEpoch {
+        #[debug(skip)]
+        callback: Sender<Epoch>,
+    },
+
+    ApplicationConfig {
+        id: ApplicationId,
+        #[debug(skip)]
+        callback: Sender<Option<Vec<u8>>>,
+    },
+
     ReadValueBytes {
         id: ApplicationId,
         #[debug(with = hex_debug)]
@@ -737,6 +833,12 @@ pub enum ExecutionRequest {
         callback: Sender<http::Response>,
     },
 
+    ReadEthereumStorage {
+        key: EthereumStorageKey,
+        #[debug(skip)]
+        callback: Sender<[u8; 32]>,
+    },
+
     ReadBlobContent {
         blob_id: BlobId,
         #[debug(skip)]
@@ -760,6 +862,24 @@ pub enum ExecutionRequest {
         callback: oneshot::Sender<Vec<u8>>,
     },
 
+    ReadApplicationValue {
+        chain_id: ChainId,
+        application_id: ApplicationId,
+        #[debug(with = hex_debug)]
+        key: Vec<u8>,
+        #[debug(skip)]
+        callback: Sender<Option<Vec<u8>>>,
+    },
+
+    QueryApplicationAt {
+        application_id: ApplicationId,
+        height: BlockHeight,
+        #[debug(with = hex_debug)]
+        query: Vec<u8>,
+        #[debug(skip)]
+        callback: Sender<Option<Vec<u8>>>,
+    },
+
     SubscribeToEvents {
         chain_id: ChainId,
         stream_id: StreamId,
@@ -780,4 +900,74 @@ pub enum ExecutionRequest {
         #[debug(skip)]
         callback: Sender<ApplicationPermissions>,
     },
+
+    SnapshotState {
+        #[debug(skip)]
+        callback: Sender<StateSnapshot>,
+    },
+
+    RestoreState {
+        #[debug(skip)]
+        snapshot: StateSnapshot,
+        /// The blobs that were used in the meantime and recorded as oracle responses. They
+        /// remain used after the state is restored.
+        used_blobs: Vec<BlobId>,
+        #[debug(skip)]
+        callback: Sender<()>,
+    },
 }
+
+impl ExecutionRequest {
+    /// Returns whether the request only uses the state of the given applications, or data that
+    /// doesn't depend on the rest of the chain's state, such as blobs, events or the block's
+    /// timestamp.
+    pub fn is_within(&self, applications: &BTreeSet<ApplicationId>) -> bool {
+        use ExecutionRequest::*;
+        let is_tracked = |stream_id: &StreamId| match stream_id.application_id {
+            GenericApplicationId::System => false,
+            GenericApplicationId::User(id) => applications.contains(&id),
+        };
+        match self {
+            #[cfg(not(web))]
+            LoadContract { id, .. } | LoadService { id, .. } => applications.contains(id),
+            ReadValueBytes { id, .. }
+            | ContainsKey { id, .. }
+            | ContainsKeys { id, .. }
+            | ReadMultiValuesBytes { id, .. }
+            | FindKeysByPrefix { id, .. }
+            | FindKeyValuesByPrefix { id, .. }
+            | WriteBatch { id, .. } => applications.contains(id),
+            NextEventIndex { stream_id, .. } => is_tracked(stream_id),
+            ReadEvent { event_id, .. } => is_tracked(&event_id.stream_id),
+            SystemTimestamp { .. }
+            | ReadEthereumStorage { .. }
+            | ReadBlobContent { .. }
+            | AssertBlobExists { .. }
+            | SnapshotState { .. }
+            | RestoreState { .. } => true,
+            ChainBalance { .. }
+            | OwnerBalance { .. }
+            | OwnerBalances { .. }
+            | BalanceOwners { .. }
+            | Transfer { .. }
+            | Claim { .. }
+            | ChainOwnership { .. }
+            | CurrentEpoch { .. }
+            | ApplicationConfig { .. }
+            | OpenChain { .. }
+            | CloseChain { .. }
+            | ChangeApplicationPermissions { .. }
+            | CreateApplication { .. }
+            | PerformHttpRequest { .. }
+            | ReadApplicationValue { .. }
+            | QueryApplicationAt { .. }
+            | SubscribeToEvents { .. }
+            | UnsubscribeFromEvents { .. }
+            | GetApplicationPermissions { .. } => false,
+        }
+    }
+}
+
+/// A copy of the execution state, taken before a speculative call so that the changes made
+/// by the call can be undone.
+pub struct StateSnapshot(Box<dyn Any + Send>);
@@ -6,7 +6,10 @@
 
 #![deny(clippy::large_futures)]
 
+mod bridge;
 pub mod committee;
+#[cfg(not(web))]
+mod compiled_module_cache;
 pub mod evm;
 mod execution;
 mod execution_state_actor;
@@ -33,26 +36,29 @@ use derive_more::Display;
 use js_sys::wasm_bindgen::JsValue;
 use linera_base::{
     abi::Abi,
+    bridge::EthereumStorageKey,
     crypto::{BcsHashable, CryptoHash},
     data_types::{
         Amount, ApplicationDescription, ApplicationPermissions, ArithmeticError, Blob, BlockHeight,
-        Bytecode, DecompressionError, Epoch, NetworkDescription, SendMessageRequest, StreamUpdate,
-        Timestamp,
+        Bytecode, DecompressionError, Epoch, NetworkDescription, OperationMetadata,
+        ReentrancyPolicy, SendMessageRequest, StreamUpdate, Timestamp,
     },
     doc_scalar, hex_debug, http,
     identifiers::{
-        Account, AccountOwner, ApplicationId, BlobId, BlobType, ChainId, EventId,
+        Account, AccountOwner, ApplicationId, BlobId, BlobType, ChainId, ChannelName, EventId,
         GenericApplicationId, MessageId, ModuleId, StreamName,
     },
     ownership::ChainOwnership,
     task,
-    vm::VmRuntime,
+    vm::{VmRuntime, WasmApiVersion},
 };
 use linera_views::{batch::Batch, ViewError};
 use serde::{Deserialize, Serialize};
 use system::AdminOperation;
 use thiserror::Error;
 
+#[cfg(not(web))]
+pub use crate::compiled_module_cache::{enable_compiled_module_cache, CompiledModuleCacheConfig};
 #[cfg(with_revm)]
 use crate::evm::EvmExecutionError;
 use crate::runtime::ContractSyncRuntime;
@@ -70,7 +76,7 @@ pub use crate::{
     execution::{ExecutionStateView, ServiceRuntimeEndpoint},
     execution_state_actor::ExecutionRequest,
     policy::ResourceControlPolicy,
-    resources::{BalanceHolder, ResourceController, ResourceTracker},
+    resources::{BalanceHolder, BlockResourceUsage, ResourceController, ResourceTracker},
     runtime::{
         ContractSyncRuntimeHandle, ServiceRuntimeRequest, ServiceSyncRuntime,
         ServiceSyncRuntimeHandle,
@@ -89,6 +95,9 @@ pub const LINERA_TYPES_SOL: &str = include_str!("../solidity/LineraTypes.sol");
 /// The maximum length of a stream name.
 const MAX_STREAM_NAME_LEN: usize = 64;
 
+/// The maximum length of a channel name.
+const MAX_CHANNEL_NAME_LEN: usize = 64;
+
 /// An implementation of [`UserContractModule`].
 #[derive(Clone)]
 pub struct UserContractCode(Box<dyn UserContractModule>);
@@ -109,6 +118,17 @@ pub trait UserContractModule: dyn_clone::DynClone + Any + task::Post + Send + Sy
         &self,
         runtime: ContractSyncRuntimeHandle,
     ) -> Result<UserContractInstance, ExecutionError>;
+
+    /// Returns an instance of the contract of `application_id` on `chain_id`, possibly reusing
+    /// one that a previous transaction was done with.
+    fn instantiate_pooled(
+        &self,
+        runtime: ContractSyncRuntimeHandle,
+        _application_id: ApplicationId,
+        _chain_id: ChainId,
+    ) -> Result<UserContractInstance, ExecutionError> {
+        self.instantiate(runtime)
+    }
 }
 
 impl<T: UserContractModule + Send + Sync + 'static> From<T> for UserContractCode {
@@ -151,6 +171,15 @@ impl UserContractCode {
     ) -> Result<UserContractInstance, ExecutionError> {
         self.0.instantiate(runtime)
     }
+
+    fn instantiate_pooled(
+        &self,
+        runtime: ContractSyncRuntimeHandle,
+        application_id: ApplicationId,
+        chain_id: ChainId,
+    ) -> Result<UserContractInstance, ExecutionError> {
+        self.0.instantiate_pooled(runtime, application_id, chain_id)
+    }
 }
 
 #[cfg(web)]
@@ -221,6 +250,14 @@ pub enum ExecutionError {
         caller_id: Box<ApplicationId>,
         callee_id: Box<ApplicationId>,
     },
+    #[error(
+        "Application {caller_id} attempted to perform a cross-application call to {callee_id} \
+        from `on_chain_close`"
+    )]
+    CrossApplicationCallInClosureHook {
+        caller_id: Box<ApplicationId>,
+        callee_id: Box<ApplicationId>,
+    },
     #[error("Attempt to write to storage from a contract")]
     ServiceWriteAttempt,
     #[error("Failed to load bytecode from storage {0:?}")]
@@ -228,6 +265,14 @@ pub enum ExecutionError {
     // TODO(#2927): support dynamic loading of modules on the Web
     #[error("Unsupported dynamic application load: {0:?}")]
     UnsupportedDynamicApplicationLoad(Box<ApplicationId>),
+    #[error(
+        "Application was built against version {declared} of the Wasm API, which is not \
+        compatible with version {supported} supported by this node"
+    )]
+    IncompatibleApiVersion {
+        declared: WasmApiVersion,
+        supported: WasmApiVersion,
+    },
 
     #[error("Excessive number of bytes read from storage")]
     ExcessiveRead,
@@ -239,6 +284,10 @@ pub enum ExecutionError {
     MaximumServiceOracleExecutionTimeExceeded,
     #[error("Service running as an oracle produced a response that's too large")]
     ServiceOracleResponseTooLarge,
+    #[error("Block reads more values from other chains than allowed")]
+    MaximumCrossChainReadsExceeded,
+    #[error("Value read from another chain is larger than the maximum oracle response size")]
+    CrossChainReadResponseTooLarge,
     #[error("Serialized size of the block exceeds limit")]
     BlockTooLarge,
     #[error("HTTP response exceeds the size limit of {limit} bytes, having at least {size} bytes")]
@@ -269,10 +318,16 @@ pub enum ExecutionError {
 
     #[error("Stream names can be at most {MAX_STREAM_NAME_LEN} bytes.")]
     StreamNameTooLong,
+    #[error("Channel names can be at most {MAX_CHANNEL_NAME_LEN} bytes.")]
+    ChannelNameTooLong,
     #[error("Blob exceeds size limit")]
     BlobTooLarge,
     #[error("Bytecode exceeds size limit")]
     BytecodeTooLarge,
+    #[error("Contract bytecode is not a valid Wasm module")]
+    InvalidWasmBytecode,
+    #[error("Contract bytecode uses floating-point values, which the current policy forbids")]
+    WasmFloatsNotAllowed,
     #[error("Attempt to perform an HTTP request to an unauthorized host: {0:?}")]
     UnauthorizedHttpRequest(reqwest::Url),
     #[error("Attempt to perform an HTTP request to an invalid URL")]
@@ -287,6 +342,18 @@ pub enum ExecutionError {
     BlobsNotFound(Vec<BlobId>),
     #[error("Events not found: {0:?}")]
     EventsNotFound(Vec<EventId>),
+    #[error("No Ethereum endpoint is configured on this node")]
+    EthereumBridgeNotConfigured,
+    #[error("This node was built without support for reading Ethereum data")]
+    EthereumBridgeUnavailable,
+    #[error("Ethereum storage slots must be 32 bytes long, got {0} bytes")]
+    InvalidEthereumStorageSlot(usize),
+    #[cfg(with_ethereum)]
+    #[error("Failed to query the Ethereum node: {0}")]
+    EthereumServiceError(#[from] linera_ethereum::common::EthereumServiceError),
+    #[cfg(with_ethereum)]
+    #[error("Failed to verify Ethereum data: {0}")]
+    EthereumVerificationError(#[from] linera_ethereum::proofs::VerificationError),
 
     #[error("Invalid HTTP header name used for HTTP request")]
     InvalidHeaderName(#[from] reqwest::header::InvalidHeaderName),
@@ -310,16 +377,36 @@ pub enum ExecutionError {
     },
     #[error("Required execution fees exceeded the total funding available. Fees {fees}, available balance: {balance}")]
     FeesExceedFunding { fees: Amount, balance: Amount },
+    #[error(
+        "The fee {fee} of application {application_id} exceeds the balance of the signer: {balance}"
+    )]
+    InsufficientBalanceForApplicationFee {
+        application_id: ApplicationId,
+        fee: Amount,
+        balance: Amount,
+    },
+    #[error("Application {0} charges a fee for this operation but the operation is not signed")]
+    UnauthenticatedApplicationFee(ApplicationId),
     #[error("Claim must have positive amount")]
     IncorrectClaimAmount,
     #[error("Claim must be authenticated by the right signer")]
     UnauthenticatedClaimOwner,
+    #[error("Only owners of the chain may change the configuration of its applications")]
+    UnauthenticatedApplicationConfigOwner,
+    #[error("Only owners of the chain may upgrade its applications")]
+    UnauthenticatedApplicationUpgrade,
+    #[error("Application {0} cannot be upgraded to a module for a different virtual machine")]
+    InvalidApplicationUpgrade(Box<ApplicationId>),
     #[error("Admin operations are only allowed on the admin chain.")]
     AdminOperationOnNonAdminChain,
     #[error("Failed to create new committee: expected {expected}, but got {provided}")]
     InvalidCommitteeEpoch { expected: Epoch, provided: Epoch },
     #[error("Failed to remove committee")]
     InvalidCommitteeRemoval,
+    #[error("Invalid validator weight update")]
+    InvalidValidatorWeightUpdate,
+    #[error("Invalid validator address announcement")]
+    InvalidValidatorAddress,
     #[error("Amount overflow")]
     AmountOverflow,
     #[error("Amount underflow")]
@@ -338,6 +425,166 @@ pub enum ExecutionError {
     InternalError(&'static str),
     #[error("UpdateStreams is outdated")]
     OutdatedUpdateStreams,
+    #[error("Fee sponsor {0} must be declared by the first operation of the block")]
+    MisplacedFeeSponsor(AccountOwner),
+    #[error("Chain has no authorization application to authorize block proposals")]
+    NoAuthorizationApplication,
+    #[error("The authorization application rejected the block proposal")]
+    ProposalNotAuthorized,
+    #[error("Authorizing the block proposal required more fuel than allowed")]
+    MaximumAuthorizationFuelExceeded,
+    #[error("Transaction drew more than the maximum of {0} bytes of entropy")]
+    MaximumEntropyExceeded(u64),
+    #[error("The call stack exceeded the maximum depth of {0} applications")]
+    MaximumCallDepthExceeded(u64),
+    #[error("Transaction made more than the maximum of {0} speculative calls")]
+    MaximumSpeculativeCallsExceeded(u64),
+    #[error("The channel already has the maximum of {0} subscribers")]
+    MaximumChannelSubscribersExceeded(u64),
+    #[error("Application {0:?} attempted to modify the chain state during a view call")]
+    WriteInViewCall(Box<ApplicationId>),
+    #[error("The application does not support view calls")]
+    ViewCallsNotSupported,
+    #[error("Execution needs state outside of the tracked applications: {0}")]
+    UntrackedState(String),
+}
+
+impl ExecutionError {
+    /// Returns a stable code identifying the kind of error, for programmatic clients.
+    ///
+    /// Codes start out as the names of the variants, and must be kept if a variant is renamed.
+    pub fn code(&self) -> &'static str {
+        match self {
+            ExecutionError::ViewError(..) => "ViewError",
+            ExecutionError::ArithmeticError(..) => "ArithmeticError",
+            ExecutionError::UserError(..) => "UserError",
+            #[cfg(with_wasm_runtime)]
+            ExecutionError::WasmError(..) => "WasmError",
+            #[cfg(with_revm)]
+            ExecutionError::EvmError(..) => "EvmError",
+            ExecutionError::DecompressionError(..) => "DecompressionError",
+            ExecutionError::InvalidPromise => "InvalidPromise",
+            ExecutionError::ReentrantCall(..) => "ReentrantCall",
+            ExecutionError::CrossApplicationCallInFinalize { .. } => {
+                "CrossApplicationCallInFinalize"
+            }
+            ExecutionError::CrossApplicationCallInClosureHook { .. } => {
+                "CrossApplicationCallInClosureHook"
+            }
+            ExecutionError::ServiceWriteAttempt => "ServiceWriteAttempt",
+            ExecutionError::ApplicationBytecodeNotFound(..) => "ApplicationBytecodeNotFound",
+            ExecutionError::UnsupportedDynamicApplicationLoad(..) => {
+                "UnsupportedDynamicApplicationLoad"
+            }
+            ExecutionError::IncompatibleApiVersion { .. } => "IncompatibleApiVersion",
+            ExecutionError::ExcessiveRead => "ExcessiveRead",
+            ExecutionError::ExcessiveWrite => "ExcessiveWrite",
+            ExecutionError::MaximumFuelExceeded(..) => "MaximumFuelExceeded",
+            ExecutionError::MaximumServiceOracleExecutionTimeExceeded => {
+                "MaximumServiceOracleExecutionTimeExceeded"
+            }
+            ExecutionError::ServiceOracleResponseTooLarge => "ServiceOracleResponseTooLarge",
+            ExecutionError::MaximumCrossChainReadsExceeded => "MaximumCrossChainReadsExceeded",
+            ExecutionError::CrossChainReadResponseTooLarge => "CrossChainReadResponseTooLarge",
+            ExecutionError::BlockTooLarge => "BlockTooLarge",
+            ExecutionError::HttpResponseSizeLimitExceeded { .. } => "HttpResponseSizeLimitExceeded",
+            ExecutionError::MissingRuntimeResponse => "MissingRuntimeResponse",
+            ExecutionError::InvalidModuleId(..) => "InvalidModuleId",
+            ExecutionError::UnauthorizedApplication(..) => "UnauthorizedApplication",
+            ExecutionError::ReqwestError(..) => "ReqwestError",
+            ExecutionError::IoError(..) => "IoError",
+            ExecutionError::UnexpectedOracleResponse => "UnexpectedOracleResponse",
+            ExecutionError::JsonError(..) => "JsonError",
+            ExecutionError::BcsError(..) => "BcsError",
+            ExecutionError::OracleResponseMismatch => "OracleResponseMismatch",
+            ExecutionError::AssertBefore { .. } => "AssertBefore",
+            ExecutionError::StreamNameTooLong => "StreamNameTooLong",
+            ExecutionError::ChannelNameTooLong => "ChannelNameTooLong",
+            ExecutionError::BlobTooLarge => "BlobTooLarge",
+            ExecutionError::BytecodeTooLarge => "BytecodeTooLarge",
+            ExecutionError::InvalidWasmBytecode => "InvalidWasmBytecode",
+            ExecutionError::WasmFloatsNotAllowed => "WasmFloatsNotAllowed",
+            ExecutionError::UnauthorizedHttpRequest(..) => "UnauthorizedHttpRequest",
+            ExecutionError::InvalidUrlForHttpRequest(..) => "InvalidUrlForHttpRequest",
+            ExecutionError::ContractModuleSend(..) => "ContractModuleSend",
+            ExecutionError::ServiceModuleSend(..) => "ServiceModuleSend",
+            ExecutionError::InactiveChain(..) => "InactiveChain",
+            ExecutionError::BlobsNotFound(..) => "BlobsNotFound",
+            ExecutionError::EventsNotFound(..) => "EventsNotFound",
+            ExecutionError::EthereumBridgeNotConfigured => "EthereumBridgeNotConfigured",
+            ExecutionError::EthereumBridgeUnavailable => "EthereumBridgeUnavailable",
+            ExecutionError::InvalidEthereumStorageSlot(..) => "InvalidEthereumStorageSlot",
+            #[cfg(with_ethereum)]
+            ExecutionError::EthereumServiceError(..) => "EthereumServiceError",
+            #[cfg(with_ethereum)]
+            ExecutionError::EthereumVerificationError(..) => "EthereumVerificationError",
+            ExecutionError::InvalidHeaderName(..) => "InvalidHeaderName",
+            ExecutionError::InvalidHeaderValue(..) => "InvalidHeaderValue",
+            ExecutionError::NoNetworkDescriptionFound => "NoNetworkDescriptionFound",
+            ExecutionError::InvalidCommittees => "InvalidCommittees",
+            ExecutionError::InvalidEpoch { .. } => "InvalidEpoch",
+            ExecutionError::IncorrectTransferAmount => "IncorrectTransferAmount",
+            ExecutionError::UnauthenticatedTransferOwner => "UnauthenticatedTransferOwner",
+            ExecutionError::InsufficientBalance { .. } => "InsufficientBalance",
+            ExecutionError::FeesExceedFunding { .. } => "FeesExceedFunding",
+            ExecutionError::InsufficientBalanceForApplicationFee { .. } => {
+                "InsufficientBalanceForApplicationFee"
+            }
+            ExecutionError::UnauthenticatedApplicationFee(..) => "UnauthenticatedApplicationFee",
+            ExecutionError::IncorrectClaimAmount => "IncorrectClaimAmount",
+            ExecutionError::UnauthenticatedClaimOwner => "UnauthenticatedClaimOwner",
+            ExecutionError::UnauthenticatedApplicationConfigOwner => {
+                "UnauthenticatedApplicationConfigOwner"
+            }
+            ExecutionError::UnauthenticatedApplicationUpgrade => {
+                "UnauthenticatedApplicationUpgrade"
+            }
+            ExecutionError::InvalidApplicationUpgrade(_) => "InvalidApplicationUpgrade",
+            ExecutionError::AdminOperationOnNonAdminChain => "AdminOperationOnNonAdminChain",
+            ExecutionError::InvalidCommitteeEpoch { .. } => "InvalidCommitteeEpoch",
+            ExecutionError::InvalidCommitteeRemoval => "InvalidCommitteeRemoval",
+            ExecutionError::InvalidValidatorWeightUpdate => "InvalidValidatorWeightUpdate",
+            ExecutionError::InvalidValidatorAddress => "InvalidValidatorAddress",
+            ExecutionError::AmountOverflow => "AmountOverflow",
+            ExecutionError::AmountUnderflow => "AmountUnderflow",
+            ExecutionError::BalanceOverflow => "BalanceOverflow",
+            ExecutionError::BalanceUnderflow => "BalanceUnderflow",
+            ExecutionError::UnknownApplicationId(..) => "UnknownApplicationId",
+            ExecutionError::MissingOracleResponse => "MissingOracleResponse",
+            ExecutionError::UnprocessedStreams => "UnprocessedStreams",
+            ExecutionError::InternalError(..) => "InternalError",
+            ExecutionError::OutdatedUpdateStreams => "OutdatedUpdateStreams",
+            ExecutionError::MisplacedFeeSponsor(..) => "MisplacedFeeSponsor",
+            ExecutionError::NoAuthorizationApplication => "NoAuthorizationApplication",
+            ExecutionError::ProposalNotAuthorized => "ProposalNotAuthorized",
+            ExecutionError::MaximumAuthorizationFuelExceeded => "MaximumAuthorizationFuelExceeded",
+            ExecutionError::MaximumEntropyExceeded(..) => "MaximumEntropyExceeded",
+            ExecutionError::MaximumCallDepthExceeded(..) => "MaximumCallDepthExceeded",
+            ExecutionError::MaximumSpeculativeCallsExceeded(..) => {
+                "MaximumSpeculativeCallsExceeded"
+            }
+            ExecutionError::MaximumChannelSubscribersExceeded(..) => {
+                "MaximumChannelSubscribersExceeded"
+            }
+            ExecutionError::WriteInViewCall(..) => "WriteInViewCall",
+            ExecutionError::ViewCallsNotSupported => "ViewCallsNotSupported",
+            ExecutionError::UntrackedState(..) => "UntrackedState",
+        }
+    }
+
+    /// Returns whether executing the same block or query again may succeed later, e.g. once
+    /// missing data has been synchronized or an external service is reachable again.
+    pub fn is_retriable(&self) -> bool {
+        matches!(
+            self,
+            ExecutionError::ViewError(_)
+                | ExecutionError::ReqwestError(_)
+                | ExecutionError::InactiveChain(_)
+                | ExecutionError::BlobsNotFound(_)
+                | ExecutionError::EventsNotFound(_)
+                | ExecutionError::EthereumServiceError(_)
+        )
+    }
 }
 
 /// The public entry points provided by the contract part of an application.
@@ -354,6 +601,21 @@ pub trait UserContract {
     /// Reacts to new events on streams this application subscribes to.
     fn process_streams(&mut self, updates: Vec<StreamUpdate>) -> Result<(), ExecutionError>;
 
+    /// Reacts to the closure of the chain, e.g. to release the assets held on this chain.
+    fn on_chain_close(&mut self) -> Result<(), ExecutionError>;
+
+    /// Reacts to the upgrade of the application's code on this chain, e.g. to migrate its
+    /// state. This is called on an instance of the new code.
+    fn on_upgrade(&mut self) -> Result<(), ExecutionError>;
+
+    /// Decides whether the authenticated signer may propose the current block on this chain,
+    /// based on the `payload` of its [`SystemOperation::AuthorizeProposal`].
+    fn authorize_proposal(&mut self, payload: Vec<u8>) -> Result<bool, ExecutionError>;
+
+    /// Runs a read-only view function, called by another application with
+    /// [`ContractRuntime::try_view_call`].
+    fn execute_view(&mut self, argument: Vec<u8>) -> Result<Vec<u8>, ExecutionError>;
+
     /// Finishes execution of the current transaction.
     fn finalize(&mut self) -> Result<(), ExecutionError>;
 }
@@ -365,8 +627,16 @@ pub trait UserService {
 }
 
 /// Configuration options for the execution runtime available to applications.
-#[derive(Clone, Copy, Default)]
-pub struct ExecutionRuntimeConfig {}
+#[derive(Clone, Default)]
+pub struct ExecutionRuntimeConfig {
+    /// Whether to create a new Wasm instance for every transaction, instead of reusing pooled
+    /// ones. Only useful to rule out the pool when checking that execution is deterministic.
+    pub disable_instance_pooling: bool,
+    /// The JSON-RPC endpoint of the Ethereum node serving the Ethereum data that contracts
+    /// request. The node is trusted to serve the canonical chain. If unset, contracts cannot
+    /// read Ethereum data on this node.
+    pub ethereum_endpoint: Option<String>,
+}
 
 /// Requirements for the `extra` field in our state views (and notably the
 /// [`ExecutionStateView`]).
@@ -408,6 +678,24 @@ pub trait ExecutionRuntimeContext {
 
     async fn contains_event(&self, event_id: EventId) -> Result<bool, ViewError>;
 
+    /// Reads the value under `key` in the state of an application on another chain, if the
+    /// chain is known locally.
+    async fn read_application_value(
+        &self,
+        chain_id: ChainId,
+        application_id: ApplicationId,
+        key: Vec<u8>,
+    ) -> Result<Option<Vec<u8>>, ViewError>;
+
+    /// Queries an application of this chain as it was right after the block at `height`.
+    /// Returns `None` if that state is not retained anymore.
+    async fn query_application_at(
+        &self,
+        application_id: ApplicationId,
+        height: BlockHeight,
+        query: Vec<u8>,
+    ) -> Result<Option<Vec<u8>>, ExecutionError>;
+
     #[cfg(with_testing)]
     async fn add_blobs(
         &self,
@@ -562,6 +850,12 @@ pub trait BaseRuntime {
     /// Reads the current ownership configuration for this chain.
     fn chain_ownership(&mut self) -> Result<ChainOwnership, ExecutionError>;
 
+    /// Reads the current epoch of this chain.
+    fn read_epoch(&mut self) -> Result<Epoch, ExecutionError>;
+
+    /// Reads the configuration of the current application on this chain, if any.
+    fn read_application_config(&mut self) -> Result<Option<Vec<u8>>, ExecutionError>;
+
     /// Tests whether a key exists in the key-value store
     #[cfg(feature = "test")]
     fn contains_key(&mut self, key: Vec<u8>) -> Result<bool, ExecutionError> {
@@ -689,6 +983,17 @@ pub trait BaseRuntime {
 
     /// Asserts the existence of a data blob with the given hash.
     fn assert_data_blob_exists(&mut self, hash: &CryptoHash) -> Result<(), ExecutionError>;
+
+    /// Reads the value under `key` in the state of the current application on another chain,
+    /// as stored by the executing validator. The value is recorded as an oracle response.
+    ///
+    /// Cannot be used in fast blocks: A block using this call should be proposed by a regular
+    /// owner, not a super owner.
+    fn read_value_bytes_on_chain(
+        &mut self,
+        chain_id: ChainId,
+        key: Vec<u8>,
+    ) -> Result<Option<Vec<u8>>, ExecutionError>;
 }
 
 pub trait ServiceRuntime: BaseRuntime {
@@ -699,9 +1004,26 @@ pub trait ServiceRuntime: BaseRuntime {
         argument: Vec<u8>,
     ) -> Result<Vec<u8>, ExecutionError>;
 
+    /// Queries an application of this chain as it was right after the block at `height`.
+    /// Returns `None` if the executing node doesn't retain that state anymore.
+    fn query_application_at(
+        &mut self,
+        queried_id: ApplicationId,
+        height: BlockHeight,
+        argument: Vec<u8>,
+    ) -> Result<Option<Vec<u8>>, ExecutionError>;
+
     /// Schedules an operation to be included in the block proposed after execution.
     fn schedule_operation(&mut self, operation: Vec<u8>) -> Result<(), ExecutionError>;
 
+    /// Schedules an operation to be included in the block proposed after execution, with a
+    /// description to show to the user.
+    fn schedule_operation_with_metadata(
+        &mut self,
+        operation: Vec<u8>,
+        metadata: OperationMetadata,
+    ) -> Result<(), ExecutionError>;
+
     /// Checks if the service has exceeded its execution time limit.
     fn check_execution_time(&mut self) -> Result<(), ExecutionError>;
 }
@@ -758,6 +1080,31 @@ pub trait ContractRuntime: BaseRuntime {
         argument: Vec<u8>,
     ) -> Result<Vec<u8>, ExecutionError>;
 
+    /// Calls a read-only view function of another application.
+    ///
+    /// The callee can't modify the state of the chain: the runtime rejects its writes, and it
+    /// may only make view calls itself. Its instance is not finalized for the view call, so
+    /// its state is not stored again at the end of the transaction.
+    fn try_view_call(
+        &mut self,
+        callee_id: ApplicationId,
+        argument: Vec<u8>,
+    ) -> Result<Vec<u8>, ExecutionError>;
+
+    /// Calls another application speculatively, to find out whether the call would succeed.
+    /// Returns the response, or `None` if the call failed.
+    ///
+    /// The changes the call makes to the chain state, the messages it sends and the events
+    /// it emits are undone afterwards, but the resources it consumes are paid for. The callee
+    /// and the applications it calls run in new instances that are not finalized, so they see
+    /// the state that each application last stored.
+    fn try_call(
+        &mut self,
+        authenticated: bool,
+        callee_id: ApplicationId,
+        argument: Vec<u8>,
+    ) -> Result<Option<Vec<u8>>, ExecutionError>;
+
     /// Adds a new item to an event stream. Returns the new event's index in the stream.
     fn emit(&mut self, name: StreamName, value: Vec<u8>) -> Result<u32, ExecutionError>;
 
@@ -787,6 +1134,28 @@ pub trait ContractRuntime: BaseRuntime {
         stream_name: StreamName,
     ) -> Result<(), ExecutionError>;
 
+    /// Sends a message to this application on every chain subscribed to one of its channels
+    /// at the end of the transaction.
+    fn publish_message(
+        &mut self,
+        channel: ChannelName,
+        message: Vec<u8>,
+    ) -> Result<(), ExecutionError>;
+
+    /// Subscribes the current chain to a channel of this application on another chain.
+    fn subscribe_to_channel(
+        &mut self,
+        chain_id: ChainId,
+        channel: ChannelName,
+    ) -> Result<(), ExecutionError>;
+
+    /// Unsubscribes the current chain from a channel of this application on another chain.
+    fn unsubscribe_from_channel(
+        &mut self,
+        chain_id: ChainId,
+        channel: ChannelName,
+    ) -> Result<(), ExecutionError>;
+
     /// Queries a service.
     fn query_service(
         &mut self,
@@ -834,6 +1203,18 @@ pub trait ContractRuntime: BaseRuntime {
     /// Returns the round in which this block was validated.
     fn validation_round(&mut self) -> Result<Option<u32>, ExecutionError>;
 
+    /// Returns the next `num_bytes` bytes of the transaction's deterministic entropy.
+    fn draw_entropy(&mut self, num_bytes: u32) -> Result<Vec<u8>, ExecutionError>;
+
+    /// Declares whether the current application accepts reentrant view calls for the rest of
+    /// the transaction.
+    fn declare_reentrancy_policy(&mut self, policy: ReentrancyPolicy)
+        -> Result<(), ExecutionError>;
+
+    /// Returns the value of an Ethereum storage slot, as verified by the validators against
+    /// the header of the given Ethereum block.
+    fn ethereum_storage(&mut self, key: EthereumStorageKey) -> Result<[u8; 32], ExecutionError>;
+
     /// Writes a batch of changes.
     fn write_batch(&mut self, batch: Batch) -> Result<(), ExecutionError>;
 }
@@ -887,6 +1268,8 @@ pub enum Query {
 pub struct QueryOutcome<Response = QueryResponse> {
     pub response: Response,
     pub operations: Vec<Operation>,
+    /// The metadata provided by the service for each of the `operations`, if any.
+    pub operation_metadata: Vec<Option<OperationMetadata>>,
 }
 
 impl From<QueryOutcome<SystemResponse>> for QueryOutcome {
@@ -894,11 +1277,13 @@ impl From<QueryOutcome<SystemResponse>> for QueryOutcome {
         let QueryOutcome {
             response,
             operations,
+            operation_metadata,
         } = system_outcome;
 
         QueryOutcome {
             response: QueryResponse::System(response),
             operations,
+            operation_metadata,
         }
     }
 }
@@ -908,11 +1293,13 @@ impl From<QueryOutcome<Vec<u8>>> for QueryOutcome {
         let QueryOutcome {
             response,
             operations,
+            operation_metadata,
         } = user_service_outcome;
 
         QueryOutcome {
             response: QueryResponse::User(response),
             operations,
+            operation_metadata,
         }
     }
 }
@@ -1004,6 +1391,34 @@ impl OutgoingMessage {
     }
 }
 
+/// A message published to a channel of an application, before it is sent to the
+/// subscribers of that channel.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct PublishedMessage {
+    /// The application whose channel the message was published to.
+    pub application_id: ApplicationId,
+    /// The channel.
+    pub channel: ChannelName,
+    /// The user authentication carried by the message, if any.
+    pub authenticated_signer: Option<AccountOwner>,
+    /// The message for the application on the subscriber chains.
+    pub bytes: Vec<u8>,
+}
+
+impl PublishedMessage {
+    /// Returns the message sent to the given subscriber chain.
+    pub fn to_subscriber(&self, subscriber: ChainId) -> OutgoingMessage {
+        OutgoingMessage::new(
+            subscriber,
+            Message::User {
+                application_id: self.application_id,
+                bytes: self.bytes.clone(),
+            },
+        )
+        .with_authenticated_signer(self.authenticated_signer)
+    }
+}
+
 impl OperationContext {
     /// Returns an account for the refund.
     /// Returns `None` if there is no authenticated signer of the [`OperationContext`].
@@ -1049,7 +1464,7 @@ impl ExecutionRuntimeContext for TestExecutionRuntimeContext {
     }
 
     fn execution_runtime_config(&self) -> ExecutionRuntimeConfig {
-        self.execution_runtime_config
+        self.execution_runtime_config.clone()
     }
 
     fn user_contracts(&self) -> &Arc<DashMap<ApplicationId, UserContractCode>> {
@@ -1143,6 +1558,26 @@ impl ExecutionRuntimeContext for TestExecutionRuntimeContext {
         Ok(self.events.contains_key(&event_id))
     }
 
+    async fn read_application_value(
+        &self,
+        _chain_id: ChainId,
+        _application_id: ApplicationId,
+        _key: Vec<u8>,
+    ) -> Result<Option<Vec<u8>>, ViewError> {
+        // There are no other chains in this context.
+        Ok(None)
+    }
+
+    async fn query_application_at(
+        &self,
+        _application_id: ApplicationId,
+        _height: BlockHeight,
+        _query: Vec<u8>,
+    ) -> Result<Option<Vec<u8>>, ExecutionError> {
+        // No history is retained in this context.
+        Ok(None)
+    }
+
     #[cfg(with_testing)]
     async fn add_blobs(
         &self,
@@ -1241,6 +1676,9 @@ impl Operation {
             SystemOperation::ProcessNewEpoch(_)
                 | SystemOperation::ProcessRemovedEpoch(_)
                 | SystemOperation::UpdateStreams(_)
+                | SystemOperation::ProcessValidatorWeights(_)
+                | SystemOperation::SponsorFees { .. }
+                | SystemOperation::AuthorizeProposal { .. }
         )
     }
 }
@@ -1344,6 +1782,15 @@ pub struct BlobState {
     pub epoch: Option<Epoch>,
 }
 
+/// What a node knows about a blob: where it was last used, and whether it has the content.
+#[derive(Eq, PartialEq, Debug, Clone, Serialize, Deserialize)]
+pub struct BlobProvenance {
+    /// The state of the blob, if any certificate that uses it was seen.
+    pub state: Option<BlobState>,
+    /// Whether the content of the blob is stored.
+    pub has_content: bool,
+}
+
 /// The runtime to use for running the application.
 #[derive(Clone, Copy, Display)]
 #[cfg_attr(with_wasm_runtime, derive(Debug, Default))]
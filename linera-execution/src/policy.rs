@@ -9,16 +9,27 @@
 //! It also sets overarching limits such as the maximum fuel allowed per block,
 //! the maximum block size, and limits on concurrent operations.
 
+#[cfg(test)]
+#[path = "unit_tests/policy_tests.rs"]
+mod tests;
+
 use std::{collections::BTreeSet, fmt};
 
 use async_graphql::InputObject;
 use linera_base::{
-    data_types::{Amount, ArithmeticError, BlobContent, CompressedBytecode, Resources},
+    crypto::CryptoHash,
+    data_types::{
+        Amount, ArithmeticError, BlobContent, CompressedBytecode, Resources, TimeDelta, Timestamp,
+    },
     ensure,
     identifiers::BlobType,
     vm::VmRuntime,
 };
 use serde::{Deserialize, Serialize};
+use wasm_instrument::parity_wasm::{
+    self,
+    elements::{Instruction, Module, Type, ValueType},
+};
 
 use crate::ExecutionError;
 
@@ -62,6 +73,10 @@ pub struct ResourceControlPolicy {
     pub service_as_oracle_query: Amount,
     /// The price for a performing an HTTP request.
     pub http_request: Amount,
+    /// The fixed price of a deterministic system read, such as the chain balance, the
+    /// ownership configuration, the timestamp or the current epoch. It replaces the
+    /// per-byte runtime price for these reads.
+    pub system_read: Amount,
 
     // TODO(#1538): Cap the number of transactions per block and the total size of their
     // arguments.
@@ -94,6 +109,45 @@ pub struct ResourceControlPolicy {
     pub http_request_timeout_ms: u64,
     /// The list of hosts that contracts and services can send HTTP requests to.
     pub http_request_allow_list: BTreeSet<String>,
+    /// Whether Wasm contract bytecode may use floating-point values. NaNs are always
+    /// canonicalized during contract execution; forbidding floats altogether additionally
+    /// rules out any other source of divergence between Wasm runtimes.
+    pub allow_wasm_floats: bool,
+    /// The hashes of the contract bytecode blobs that may use floating-point values even if
+    /// `allow_wasm_floats` is not set, i.e. the applications the committee has opted in.
+    pub wasm_float_contracts: BTreeSet<CryptoHash>,
+    /// The maximum number of values a block can read from the state of its applications on
+    /// other chains. Such reads are disabled if this is zero.
+    pub maximum_cross_chain_reads_per_block: u64,
+    /// The maximum age in milliseconds of an incoming bundle that a block can accept, relative
+    /// to the block's timestamp. Older bundles can only be rejected, unless they contain
+    /// protected messages.
+    pub maximum_incoming_bundle_age_ms: u64,
+    /// The maximum amount of fuel that a chain's authorization application can consume to
+    /// accept a block proposal from a non-owner.
+    pub maximum_authorization_fuel: u64,
+    /// The maximum number of bytes of deterministic entropy that the applications can draw
+    /// in a single transaction, e.g. to shuffle or sample values.
+    pub maximum_entropy_per_transaction: u64,
+    /// The maximum number of applications in the call stack of a transaction, including the
+    /// one that executes the operation or message.
+    pub maximum_call_depth: u64,
+    /// The maximum number of speculative calls that the applications can make in a single
+    /// transaction. Their effects are undone, but their execution is paid for.
+    pub maximum_speculative_calls_per_transaction: u64,
+    /// Whether each block publishes a summary of the resources it consumed as an event in the
+    /// [`RESOURCE_USAGE_STREAM_NAME`](crate::system::RESOURCE_USAGE_STREAM_NAME) stream.
+    pub publish_resource_usage: bool,
+    /// Whether the state hash of each block is a [`MerkleStateHash`], which commits to the
+    /// storage of the applications as Merkle trees, so that single values can be proven to be
+    /// part of the state with a [`StateInclusionProof`].
+    ///
+    /// [`MerkleStateHash`]: linera_base::merkle::MerkleStateHash
+    /// [`StateInclusionProof`]: linera_base::merkle::StateInclusionProof
+    pub merkle_state_hash: bool,
+    /// The maximum number of chains that can subscribe to a single channel of an application.
+    /// This bounds the number of messages a block pays for when it publishes to a channel.
+    pub maximum_channel_subscribers: u64,
 }
 
 impl fmt::Display for ResourceControlPolicy {
@@ -117,6 +171,7 @@ impl fmt::Display for ResourceControlPolicy {
             message_byte,
             service_as_oracle_query,
             http_request,
+            system_read,
             maximum_wasm_fuel_per_block,
             maximum_evm_fuel_per_block,
             maximum_service_oracle_execution_ms,
@@ -131,6 +186,17 @@ impl fmt::Display for ResourceControlPolicy {
             maximum_http_response_bytes,
             http_request_allow_list,
             http_request_timeout_ms,
+            allow_wasm_floats,
+            wasm_float_contracts,
+            maximum_cross_chain_reads_per_block,
+            maximum_incoming_bundle_age_ms,
+            maximum_authorization_fuel,
+            maximum_entropy_per_transaction,
+            maximum_call_depth,
+            maximum_speculative_calls_per_transaction,
+            publish_resource_usage,
+            merkle_state_hash,
+            maximum_channel_subscribers,
         } = self;
         write!(
             f,
@@ -153,6 +219,7 @@ impl fmt::Display for ResourceControlPolicy {
             {message:.2} per outgoing messages\n\
             {message_byte:.2} per byte in the argument of an outgoing messages\n\
             {http_request:.2} per HTTP request performed\n\
+            {system_read:.2} per system read (balance, ownership, timestamp, epoch)\n\
             {maximum_wasm_fuel_per_block} maximum Wasm fuel per block\n\
             {maximum_evm_fuel_per_block} maximum EVM fuel per block\n\
             {maximum_service_oracle_execution_ms} ms maximum service-as-oracle execution time per \
@@ -167,7 +234,22 @@ impl fmt::Display for ResourceControlPolicy {
             {maximum_oracle_response_bytes} maximum number of bytes of an oracle response\n\
             {maximum_http_response_bytes} maximum number of bytes of an HTTP response\n\
             {http_request_timeout_ms} ms timeout for HTTP requests\n\
-            HTTP hosts allowed for contracts and services: {http_request_allow_list:#?}\n",
+            HTTP hosts allowed for contracts and services: {http_request_allow_list:#?}\n\
+            Floating-point values allowed in Wasm contracts: {allow_wasm_floats}\n\
+            Contracts allowed to use floating-point values: {wasm_float_contracts:#?}\n\
+            {maximum_cross_chain_reads_per_block} maximum number of reads from other chains per \
+                block\n\
+            {maximum_incoming_bundle_age_ms} ms maximum age of the incoming bundles a block can \
+                accept\n\
+            {maximum_authorization_fuel} maximum fuel to authorize a block proposal\n\
+            {maximum_entropy_per_transaction} maximum number of bytes of entropy per \
+                transaction\n\
+            {maximum_call_depth} maximum depth of the call stack of a transaction\n\
+            {maximum_speculative_calls_per_transaction} maximum number of speculative calls per \
+                transaction\n\
+            Resource usage published by each block: {publish_resource_usage}\n\
+            Merkle state hashes: {merkle_state_hash}\n\
+            {maximum_channel_subscribers} maximum number of subscribers per channel\n",
         )?;
         Ok(())
     }
@@ -203,6 +285,7 @@ impl ResourceControlPolicy {
             message_byte: Amount::ZERO,
             service_as_oracle_query: Amount::ZERO,
             http_request: Amount::ZERO,
+            system_read: Amount::ZERO,
             maximum_wasm_fuel_per_block: u64::MAX,
             maximum_evm_fuel_per_block: u64::MAX,
             maximum_service_oracle_execution_ms: u64::MAX,
@@ -217,9 +300,31 @@ impl ResourceControlPolicy {
             maximum_http_response_bytes: u64::MAX,
             http_request_timeout_ms: u64::MAX,
             http_request_allow_list: BTreeSet::new(),
+            allow_wasm_floats: true,
+            wasm_float_contracts: BTreeSet::new(),
+            maximum_cross_chain_reads_per_block: 0,
+            maximum_incoming_bundle_age_ms: u64::MAX,
+            maximum_authorization_fuel: u64::MAX,
+            maximum_entropy_per_transaction: u64::MAX,
+            maximum_call_depth: u64::MAX,
+            maximum_speculative_calls_per_transaction: u64::MAX,
+            publish_resource_usage: false,
+            merkle_state_hash: false,
+            maximum_channel_subscribers: u64::MAX,
         }
     }
 
+    /// Returns whether a bundle with the given timestamp is too old to be accepted in a block
+    /// with the given timestamp.
+    pub fn is_incoming_bundle_too_old(
+        &self,
+        bundle_timestamp: Timestamp,
+        block_timestamp: Timestamp,
+    ) -> bool {
+        block_timestamp.delta_since(bundle_timestamp)
+            > TimeDelta::from_millis(self.maximum_incoming_bundle_age_ms)
+    }
+
     /// The maximum fuel per block according to the `VmRuntime`.
     pub fn maximum_fuel_per_block(&self, vm_runtime: VmRuntime) -> u64 {
         match vm_runtime {
@@ -257,6 +362,7 @@ impl ResourceControlPolicy {
             message: Amount::from_attos(10),
             message_byte: Amount::from_attos(1),
             http_request: Amount::from_micros(1),
+            system_read: Amount::from_attos(10),
             ..Self::no_fees()
         }
     }
@@ -282,6 +388,7 @@ impl ResourceControlPolicy {
             message: Amount::from_micros(10),
             service_as_oracle_query: Amount::from_millis(10),
             http_request: Amount::from_micros(50),
+            system_read: Amount::from_nanos(100),
             maximum_wasm_fuel_per_block: 100_000_000,
             maximum_evm_fuel_per_block: 100_000_000,
             maximum_service_oracle_execution_ms: 10_000,
@@ -296,6 +403,17 @@ impl ResourceControlPolicy {
             maximum_http_response_bytes: 10_000,
             http_request_timeout_ms: 20_000,
             http_request_allow_list: BTreeSet::new(),
+            allow_wasm_floats: true,
+            wasm_float_contracts: BTreeSet::new(),
+            maximum_cross_chain_reads_per_block: 100,
+            maximum_incoming_bundle_age_ms: u64::MAX,
+            maximum_authorization_fuel: 1_000_000,
+            maximum_entropy_per_transaction: 4096,
+            maximum_call_depth: 64,
+            maximum_speculative_calls_per_transaction: 16,
+            publish_resource_usage: true,
+            merkle_state_hash: false,
+            maximum_channel_subscribers: 1000,
         }
     }
 
@@ -389,6 +507,11 @@ impl ResourceControlPolicy {
         self.http_request.try_mul(count as u128)
     }
 
+    /// Returns how much it would cost to perform `count` system reads.
+    pub(crate) fn system_reads_price(&self, count: u32) -> Result<Amount, ArithmeticError> {
+        self.system_read.try_mul(count as u128)
+    }
+
     fn fuel_unit_price(&self, vm_runtime: VmRuntime) -> Amount {
         match vm_runtime {
             VmRuntime::Wasm => self.wasm_fuel_unit,
@@ -426,6 +549,14 @@ impl ResourceControlPolicy {
                     )?,
                     ExecutionError::BytecodeTooLarge
                 );
+                if content.blob_type() == BlobType::ContractBytecode
+                    && !self.allow_wasm_floats
+                    && !self
+                        .wasm_float_contracts
+                        .contains(&CryptoHash::new(content))
+                {
+                    check_no_wasm_floats(content.bytes())?;
+                }
             }
             BlobType::Data
             | BlobType::ApplicationDescription
@@ -435,3 +566,125 @@ impl ResourceControlPolicy {
         Ok(())
     }
 }
+
+/// Returns an error if the compressed Wasm bytecode uses floating-point values.
+fn check_no_wasm_floats(compressed_bytes: &[u8]) -> Result<(), ExecutionError> {
+    let compressed_bytecode = CompressedBytecode {
+        compressed_bytes: compressed_bytes.to_vec(),
+    };
+    let bytecode = compressed_bytecode.decompress()?;
+    let module = parity_wasm::deserialize_buffer::<Module>(&bytecode.bytes)
+        .map_err(|_| ExecutionError::InvalidWasmBytecode)?;
+    ensure!(
+        !wasm_module_uses_floats(&module),
+        ExecutionError::WasmFloatsNotAllowed
+    );
+    Ok(())
+}
+
+/// Returns whether any type, global, local or instruction of the module involves a
+/// floating-point value.
+fn wasm_module_uses_floats(module: &Module) -> bool {
+    let is_float = |value_type: &ValueType| matches!(value_type, ValueType::F32 | ValueType::F64);
+    let types_use_floats = module.type_section().is_some_and(|section| {
+        section.types().iter().any(|Type::Function(function_type)| {
+            function_type
+                .params()
+                .iter()
+                .chain(function_type.results())
+                .any(is_float)
+        })
+    });
+    let globals_use_floats = module.global_section().is_some_and(|section| {
+        section
+            .entries()
+            .iter()
+            .any(|global| is_float(&global.global_type().content_type()))
+    });
+    let code_uses_floats = module.code_section().is_some_and(|section| {
+        section.bodies().iter().any(|body| {
+            body.locals()
+                .iter()
+                .any(|local| is_float(&local.value_type()))
+                || body.code().elements().iter().any(is_float_instruction)
+        })
+    });
+    types_use_floats || globals_use_floats || code_uses_floats
+}
+
+/// Returns whether the instruction consumes or produces a floating-point value.
+fn is_float_instruction(instruction: &Instruction) -> bool {
+    use Instruction::*;
+
+    matches!(
+        instruction,
+        F32Load(..)
+            | F64Load(..)
+            | F32Store(..)
+            | F64Store(..)
+            | F32Const(_)
+            | F64Const(_)
+            | F32Eq
+            | F32Ne
+            | F32Lt
+            | F32Gt
+            | F32Le
+            | F32Ge
+            | F64Eq
+            | F64Ne
+            | F64Lt
+            | F64Gt
+            | F64Le
+            | F64Ge
+            | F32Abs
+            | F32Neg
+            | F32Ceil
+            | F32Floor
+            | F32Trunc
+            | F32Nearest
+            | F32Sqrt
+            | F32Add
+            | F32Sub
+            | F32Mul
+            | F32Div
+            | F32Min
+            | F32Max
+            | F32Copysign
+            | F64Abs
+            | F64Neg
+            | F64Ceil
+            | F64Floor
+            | F64Trunc
+            | F64Nearest
+            | F64Sqrt
+            | F64Add
+            | F64Sub
+            | F64Mul
+            | F64Div
+            | F64Min
+            | F64Max
+            | F64Copysign
+            | I32TruncSF32
+            | I32TruncUF32
+            | I32TruncSF64
+            | I32TruncUF64
+            | I64TruncSF32
+            | I64TruncUF32
+            | I64TruncSF64
+            | I64TruncUF64
+            | F32ConvertSI32
+            | F32ConvertUI32
+            | F32ConvertSI64
+            | F32ConvertUI64
+            | F32DemoteF64
+            | F64ConvertSI32
+            | F64ConvertUI32
+            | F64ConvertSI64
+            | F64ConvertUI64
+            | F64PromoteF32
+            | I32ReinterpretF32
+            | I64ReinterpretF64
+            | F32ReinterpretI32
+            | F64ReinterpretI64
+    )
+}
@@ -7,14 +7,13 @@ use std::{sync::Arc, time::Duration};
 
 use custom_debug_derive::Debug;
 use linera_base::{
-    data_types::{Amount, ArithmeticError, Blob},
+    data_types::{Amount, ArithmeticError, Blob, FeeBreakdown},
     ensure,
     identifiers::AccountOwner,
-    ownership::ChainOwnership,
     vm::VmRuntime,
 };
 use linera_views::{context::Context, ViewError};
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 
 use crate::{ExecutionError, Message, Operation, ResourceControlPolicy, SystemExecutionStateView};
 
@@ -67,12 +66,6 @@ pub const RUNTIME_TIMESTAMP_SIZE: u32 = 8;
 /// The runtime size of the weight of an owner.
 pub const RUNTIME_OWNER_WEIGHT_SIZE: u32 = 8;
 
-/// The runtime constant part size of the `ChainOwnership`.
-/// It consists of one `u32` and four `TimeDelta` which are the constant part of
-/// the `ChainOwnership`. The way we do it is not optimal:
-/// TODO(#4164): Implement a procedure for computing naive sizes.
-pub const RUNTIME_CONSTANT_CHAIN_OWNERSHIP_SIZE: u32 = 4 + 4 * 8;
-
 #[cfg(test)]
 mod tests {
     use std::mem::size_of;
@@ -144,12 +137,18 @@ pub struct ResourceTracker {
     pub message_bytes: u64,
     /// The number of HTTP requests performed.
     pub http_requests: u32,
+    /// The number of deterministic system reads performed, charged at a fixed price.
+    pub system_reads: u32,
     /// The number of calls to services as oracles.
     pub service_oracle_queries: u32,
     /// The time spent executing services as oracles.
     pub service_oracle_execution: Duration,
+    /// The number of values read from the state of applications on other chains.
+    pub cross_chain_reads: u32,
     /// The amount allocated to message grants.
     pub grants: Amount,
+    /// The fees charged so far, by kind of resource. This doesn't include the grants.
+    pub fees: FeeBreakdown,
 }
 
 impl ResourceTracker {
@@ -159,6 +158,70 @@ impl ResourceTracker {
             VmRuntime::Evm => self.evm_fuel,
         }
     }
+
+    /// Returns the fuel used so far by all virtual machines.
+    pub(crate) fn total_fuel(&self) -> u64 {
+        self.wasm_fuel.saturating_add(self.evm_fuel)
+    }
+}
+
+/// A summary of the resources consumed by a block, published as an event in the
+/// [`RESOURCE_USAGE_STREAM_NAME`](crate::system::RESOURCE_USAGE_STREAM_NAME) stream if the
+/// policy requires it. The index of the event is the height of the block.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BlockResourceUsage {
+    /// The Wasm fuel consumed.
+    pub wasm_fuel: u64,
+    /// The EVM fuel consumed.
+    pub evm_fuel: u64,
+    /// The number of read operations.
+    pub read_operations: u32,
+    /// The number of write operations.
+    pub write_operations: u32,
+    /// The number of bytes read.
+    pub bytes_read: u64,
+    /// The number of bytes written.
+    pub bytes_written: u64,
+    /// The change in the number of bytes stored by user applications.
+    pub bytes_stored: i32,
+    /// The number of blobs published.
+    pub blobs_published: u32,
+    /// The number of blob bytes published.
+    pub blob_bytes_published: u64,
+    /// The number of operations executed.
+    pub operations: u32,
+    /// The number of outgoing messages.
+    pub messages: u32,
+    /// The number of HTTP requests performed.
+    pub http_requests: u32,
+    /// The number of calls to services as oracles.
+    pub service_oracle_queries: u32,
+    /// The size of the block, not counting this summary.
+    pub block_size: u64,
+    /// The fees charged for the block, by kind of resource.
+    pub fees: FeeBreakdown,
+}
+
+impl From<&ResourceTracker> for BlockResourceUsage {
+    fn from(tracker: &ResourceTracker) -> Self {
+        BlockResourceUsage {
+            wasm_fuel: tracker.wasm_fuel,
+            evm_fuel: tracker.evm_fuel,
+            read_operations: tracker.read_operations,
+            write_operations: tracker.write_operations,
+            bytes_read: tracker.bytes_read,
+            bytes_written: tracker.bytes_written,
+            bytes_stored: tracker.bytes_stored,
+            blobs_published: tracker.blobs_published,
+            blob_bytes_published: tracker.blob_bytes_published,
+            operations: tracker.operations,
+            messages: tracker.messages,
+            http_requests: tracker.http_requests,
+            service_oracle_queries: tracker.service_oracle_queries,
+            block_size: tracker.block_size,
+            fees: tracker.fees,
+        }
+    }
 }
 
 /// How to access the balance of an account.
@@ -211,6 +274,18 @@ where
         Ok(())
     }
 
+    /// Charges fees to the account, and records them in the entry of the fee breakdown
+    /// returned by `kind`.
+    fn charge_fees(
+        &mut self,
+        fees: Amount,
+        kind: fn(&mut FeeBreakdown) -> &mut Amount,
+    ) -> Result<(), ExecutionError> {
+        self.update_balance(fees)?;
+        kind(&mut self.tracker.as_mut().fees).try_add_assign(fees)?;
+        Ok(())
+    }
+
     /// Obtains the amount of fuel that could be spent by consuming the entire balance.
     pub(crate) fn remaining_fuel(&self, vm_runtime: VmRuntime) -> u64 {
         let balance = self.balance().unwrap_or(Amount::MAX);
@@ -235,7 +310,7 @@ where
             .operations
             .checked_add(1)
             .ok_or(ArithmeticError::Overflow)?;
-        self.update_balance(self.policy.operation)?;
+        self.charge_fees(self.policy.operation, |fees| &mut fees.operations)?;
         match operation {
             Operation::System(_) => Ok(()),
             Operation::User { bytes, .. } => {
@@ -246,7 +321,8 @@ where
                     .operation_bytes
                     .checked_add(size as u64)
                     .ok_or(ArithmeticError::Overflow)?;
-                self.update_balance(self.policy.operation_bytes_price(size as u64)?)?;
+                let price = self.policy.operation_bytes_price(size as u64)?;
+                self.charge_fees(price, |fees| &mut fees.operations)?;
                 Ok(())
             }
         }
@@ -260,7 +336,7 @@ where
             .messages
             .checked_add(1)
             .ok_or(ArithmeticError::Overflow)?;
-        self.update_balance(self.policy.message)?;
+        self.charge_fees(self.policy.message, |fees| &mut fees.messages)?;
         match message {
             Message::System(_) => Ok(()),
             Message::User { bytes, .. } => {
@@ -271,7 +347,8 @@ where
                     .message_bytes
                     .checked_add(size as u64)
                     .ok_or(ArithmeticError::Overflow)?;
-                self.update_balance(self.policy.message_bytes_price(size as u64)?)?;
+                let price = self.policy.message_bytes_price(size as u64)?;
+                self.charge_fees(price, |fees| &mut fees.messages)?;
                 Ok(())
             }
         }
@@ -285,7 +362,7 @@ where
             .http_requests
             .checked_add(1)
             .ok_or(ArithmeticError::Overflow)?;
-        self.update_balance(self.policy.http_request)
+        self.charge_fees(self.policy.http_request, |fees| &mut fees.other)
     }
 
     /// Tracks a number of fuel units used.
@@ -320,7 +397,8 @@ where
                 );
             }
         }
-        self.update_balance(self.policy.fuel_price(fuel, vm_runtime)?)
+        let price = self.policy.fuel_price(fuel, vm_runtime)?;
+        self.charge_fees(price, |fees| &mut fees.fuel)
     }
 
     /// Tracks runtime reading of `ChainId`
@@ -347,14 +425,16 @@ where
         self.track_size_runtime_operations(parameters_len)
     }
 
-    /// Tracks runtime reading of `Timestamp`
-    pub(crate) fn track_runtime_timestamp(&mut self) -> Result<(), ExecutionError> {
-        self.track_size_runtime_operations(RUNTIME_TIMESTAMP_SIZE)
-    }
-
-    /// Tracks runtime reading of balance
-    pub(crate) fn track_runtime_balance(&mut self) -> Result<(), ExecutionError> {
-        self.track_size_runtime_operations(RUNTIME_AMOUNT_SIZE)
+    /// Tracks a deterministic system read, such as the timestamp, a balance, the chain
+    /// ownership or the epoch. These reads have a fixed price regardless of their size.
+    pub(crate) fn track_system_read(&mut self) -> Result<(), ExecutionError> {
+        self.tracker.as_mut().system_reads = self
+            .tracker
+            .as_ref()
+            .system_reads
+            .checked_add(1)
+            .ok_or(ArithmeticError::Overflow)?;
+        self.charge_fees(self.policy.system_read, |fees| &mut fees.other)
     }
 
     /// Tracks runtime reading of owner balances
@@ -381,22 +461,6 @@ where
         self.track_size_runtime_operations(size)
     }
 
-    /// Tracks runtime reading of owners
-    pub(crate) fn track_runtime_chain_ownership(
-        &mut self,
-        chain_ownership: &ChainOwnership,
-    ) -> Result<(), ExecutionError> {
-        let mut size = 0;
-        for account_owner in &chain_ownership.super_owners {
-            size += account_owner.size();
-        }
-        for account_owner in chain_ownership.owners.keys() {
-            size += account_owner.size() + RUNTIME_OWNER_WEIGHT_SIZE;
-        }
-        size += RUNTIME_CONSTANT_CHAIN_OWNERSHIP_SIZE;
-        self.track_size_runtime_operations(size)
-    }
-
     /// Tracks runtime operations.
     fn track_size_runtime_operations(&mut self, size: u32) -> Result<(), ExecutionError> {
         self.tracker.as_mut().bytes_runtime = self
@@ -405,7 +469,8 @@ where
             .bytes_runtime
             .checked_add(size)
             .ok_or(ArithmeticError::Overflow)?;
-        self.update_balance(self.policy.bytes_runtime_price(size)?)
+        let price = self.policy.bytes_runtime_price(size)?;
+        self.charge_fees(price, |fees| &mut fees.other)
     }
 
     /// Tracks a read operation.
@@ -416,7 +481,8 @@ where
             .read_operations
             .checked_add(1)
             .ok_or(ArithmeticError::Overflow)?;
-        self.update_balance(self.policy.read_operations_price(1)?)
+        let price = self.policy.read_operations_price(1)?;
+        self.charge_fees(price, |fees| &mut fees.storage_reads)
     }
 
     /// Tracks a write operation.
@@ -427,7 +493,8 @@ where
             .write_operations
             .checked_add(count)
             .ok_or(ArithmeticError::Overflow)?;
-        self.update_balance(self.policy.write_operations_price(count)?)
+        let price = self.policy.write_operations_price(count)?;
+        self.charge_fees(price, |fees| &mut fees.storage_writes)
     }
 
     /// Tracks a number of bytes read.
@@ -441,7 +508,8 @@ where
         if self.tracker.as_mut().bytes_read >= self.policy.maximum_bytes_read_per_block {
             return Err(ExecutionError::ExcessiveRead);
         }
-        self.update_balance(self.policy.bytes_read_price(count)?)?;
+        let price = self.policy.bytes_read_price(count)?;
+        self.charge_fees(price, |fees| &mut fees.storage_reads)?;
         Ok(())
     }
 
@@ -456,7 +524,8 @@ where
         if self.tracker.as_mut().bytes_written >= self.policy.maximum_bytes_written_per_block {
             return Err(ExecutionError::ExcessiveWrite);
         }
-        self.update_balance(self.policy.bytes_written_price(count)?)?;
+        let price = self.policy.bytes_written_price(count)?;
+        self.charge_fees(price, |fees| &mut fees.storage_writes)?;
         Ok(())
     }
 
@@ -473,7 +542,8 @@ where
                 .checked_add(1)
                 .ok_or(ArithmeticError::Overflow)?;
         }
-        self.update_balance(self.policy.blob_read_price(count)?)?;
+        let price = self.policy.blob_read_price(count)?;
+        self.charge_fees(price, |fees| &mut fees.storage_reads)?;
         Ok(())
     }
 
@@ -495,7 +565,8 @@ where
                 .checked_add(1)
                 .ok_or(ArithmeticError::Overflow)?;
         }
-        self.update_balance(self.policy.blob_published_price(size)?)?;
+        let price = self.policy.blob_published_price(size)?;
+        self.charge_fees(price, |fees| &mut fees.blob_bytes)?;
         Ok(())
     }
 
@@ -533,7 +604,7 @@ where
             .service_oracle_queries
             .checked_add(1)
             .ok_or(ArithmeticError::Overflow)?;
-        self.update_balance(self.policy.service_as_oracle_query)
+        self.charge_fees(self.policy.service_as_oracle_query, |fees| &mut fees.other)
     }
 
     /// Tracks the time spent executing the service as an oracle.
@@ -555,6 +626,27 @@ where
         Ok(())
     }
 
+    /// Tracks a value read from the state of an application on another chain.
+    pub(crate) fn track_cross_chain_read(
+        &mut self,
+        value_bytes: usize,
+    ) -> Result<(), ExecutionError> {
+        let tracker = self.tracker.as_mut();
+        tracker.cross_chain_reads = tracker
+            .cross_chain_reads
+            .checked_add(1)
+            .ok_or(ArithmeticError::Overflow)?;
+        ensure!(
+            u64::from(tracker.cross_chain_reads) <= self.policy.maximum_cross_chain_reads_per_block,
+            ExecutionError::MaximumCrossChainReadsExceeded
+        );
+        ensure!(
+            value_bytes as u64 <= self.policy.maximum_oracle_response_bytes,
+            ExecutionError::CrossChainReadResponseTooLarge
+        );
+        self.track_bytes_read(value_bytes as u64)
+    }
+
     /// Tracks the size of a response produced by an oracle.
     pub(crate) fn track_service_oracle_response(
         &mut self,
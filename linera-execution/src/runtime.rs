@@ -11,38 +11,46 @@ use std::{
 
 use custom_debug_derive::Debug;
 use linera_base::{
-    crypto::CryptoHash,
+    bridge::EthereumStorageKey,
+    crypto::{BcsHashable, CryptoHash},
     data_types::{
-        Amount, ApplicationPermissions, ArithmeticError, Blob, BlockHeight, Bytecode,
-        OracleResponse, SendMessageRequest, Timestamp,
+        Amount, ApplicationPermissions, ArithmeticError, Blob, BlockHeight, Bytecode, Epoch,
+        OperationMetadata, OracleResponse, ReentrancyPolicy, SendMessageRequest, Timestamp,
     },
     ensure, http,
     identifiers::{
-        Account, AccountOwner, BlobId, BlobType, ChainId, EventId, GenericApplicationId, MessageId,
-        StreamId, StreamName,
+        Account, AccountOwner, BlobId, BlobType, ChainId, ChannelName, EventId,
+        GenericApplicationId, MessageId, StreamId, StreamName,
     },
     ownership::ChainOwnership,
     vm::VmRuntime,
 };
 use linera_views::batch::Batch;
 use oneshot::Receiver;
+use serde::{Deserialize, Serialize};
 
 use crate::{
     execution::UserAction,
-    execution_state_actor::{ExecutionRequest, ExecutionStateSender},
+    execution_state_actor::{ExecutionRequest, ExecutionStateSender, StateSnapshot},
     resources::ResourceController,
     system::CreateApplicationResult,
+    transaction_tracker::TransactionTrackerSnapshot,
     util::{ReceiverExt, UnboundedSenderExt},
     ApplicationDescription, ApplicationId, BaseRuntime, ContractRuntime, ExecutionError,
     FinalizeContext, Message, MessageContext, MessageKind, ModuleId, Operation, OutgoingMessage,
-    QueryContext, QueryOutcome, ServiceRuntime, TransactionTracker, UserContractCode,
-    UserContractInstance, UserServiceCode, UserServiceInstance, MAX_STREAM_NAME_LEN,
+    PublishedMessage, QueryContext, QueryOutcome, ServiceRuntime, SystemMessage,
+    TransactionTracker, UserContractCode, UserContractInstance, UserServiceCode,
+    UserServiceInstance, MAX_CHANNEL_NAME_LEN, MAX_STREAM_NAME_LEN,
 };
 
 #[cfg(test)]
 #[path = "unit_tests/runtime_tests.rs"]
 mod tests;
 
+/// The fuel consumed by view functions is divided by this: they can't modify the chain state,
+/// so their execution costs validators less.
+const VIEW_CALL_FUEL_DIVISOR: u64 = 2;
+
 pub trait WithContext {
     type UserContext;
 }
@@ -102,6 +110,11 @@ pub struct SyncRuntimeInternal<UserInstance: WithContext> {
     ///
     /// If [`true`], disables cross-application calls.
     is_finalizing: bool,
+    /// If an application is being notified of the closure of the chain.
+    ///
+    /// If [`true`], disables cross-application calls, so that the changes of a failing hook
+    /// can be discarded by restoring the state of that application only.
+    is_closing_chain: bool,
     /// Applications that need to be finalized.
     applications_to_finalize: Vec<ApplicationId>,
 
@@ -111,10 +124,18 @@ pub struct SyncRuntimeInternal<UserInstance: WithContext> {
     call_stack: Vec<ApplicationStatus>,
     /// The set of the IDs of the applications that are in the `call_stack`.
     active_applications: HashSet<ApplicationId>,
+    /// The reentrancy policies declared by the applications loaded in this transaction.
+    reentrancy_policies: HashMap<ApplicationId, ReentrancyPolicy>,
+    /// The number of speculative calls made in this transaction.
+    speculative_calls: u64,
+    /// Whether contract instances are taken from and returned to the instance pool.
+    pool_instances: bool,
     /// The tracking information for this transaction.
     transaction_tracker: TransactionTracker,
     /// The operations scheduled during this query.
     scheduled_operations: Vec<Operation>,
+    /// The metadata of each of the `scheduled_operations`, if provided.
+    scheduled_operation_metadata: Vec<Option<OperationMetadata>>,
 
     /// Track application states based on views.
     view_user_states: BTreeMap<ApplicationId, ViewUserState>,
@@ -144,6 +165,8 @@ struct ApplicationStatus {
     description: ApplicationDescription,
     /// The authenticated signer for the execution thread, if any.
     signer: Option<AccountOwner>,
+    /// Whether the application runs a view function, and must not modify the chain state.
+    read_only: bool,
 }
 
 /// A loaded application instance.
@@ -174,6 +197,67 @@ impl<Instance> Clone for LoadedApplication<Instance> {
     }
 }
 
+/// The state of the runtime before a speculative call, restored after it.
+#[derive(Debug)]
+struct Speculation {
+    /// The length of the call stack before the call.
+    call_stack_depth: usize,
+    /// The applications loaded before the call. The call loads its own instances.
+    loaded_applications: HashMap<ApplicationId, LoadedApplication<UserContractInstance>>,
+    applications_to_finalize: Vec<ApplicationId>,
+    reentrancy_policies: HashMap<ApplicationId, ReentrancyPolicy>,
+    #[debug(skip)]
+    transaction_tracker: TransactionTrackerSnapshot,
+    #[debug(skip)]
+    execution_state: StateSnapshot,
+}
+
+/// Returns whether an error of a speculative call must also fail the caller, instead of being
+/// reported to it: the call may have failed only because data is missing locally, and
+/// reporting that would make validators disagree, or the transaction has run out of resources.
+fn fails_speculative_caller(error: &ExecutionError) -> bool {
+    error.is_retriable()
+        || matches!(
+            error,
+            ExecutionError::MaximumFuelExceeded(_)
+                | ExecutionError::FeesExceedFunding { .. }
+                | ExecutionError::MaximumSpeculativeCallsExceeded(_)
+        )
+}
+
+/// A block of 32 bytes of the deterministic entropy of a transaction.
+///
+/// The entropy only depends on the position of the transaction in the chain, so that all
+/// validators agree on it. It is not secret: the block proposer can predict it.
+#[derive(Serialize, Deserialize)]
+struct EntropyBlock {
+    chain_id: ChainId,
+    height: BlockHeight,
+    transaction_index: u32,
+    /// The index of the block in the transaction's entropy.
+    index: u64,
+}
+
+impl BcsHashable<'_> for EntropyBlock {}
+
+impl EntropyBlock {
+    /// Returns `num_bytes` bytes of the transaction's entropy, starting at `offset`.
+    fn entropy(mut self, offset: u64, num_bytes: u32) -> Vec<u8> {
+        let end = offset + u64::from(num_bytes);
+        let mut bytes = Vec::with_capacity(num_bytes as usize);
+        let mut position = offset;
+        while position < end {
+            self.index = position / 32;
+            let hash = CryptoHash::new(&self);
+            let start = (position % 32) as usize;
+            let stop = (end - self.index * 32).min(32) as usize;
+            bytes.extend_from_slice(&hash.as_bytes().as_slice()[start..stop]);
+            position = (self.index + 1) * 32;
+        }
+        bytes
+    }
+}
+
 #[derive(Debug)]
 enum Promise<T> {
     Ready(T),
@@ -329,16 +413,21 @@ impl<UserInstance: WithContext> SyncRuntimeInternal<UserInstance> {
             executing_message,
             execution_state_sender,
             is_finalizing: false,
+            is_closing_chain: false,
             applications_to_finalize: Vec::new(),
             loaded_applications: HashMap::new(),
             call_stack: Vec::new(),
             active_applications: HashSet::new(),
+            reentrancy_policies: HashMap::new(),
+            speculative_calls: 0,
+            pool_instances: false,
             view_user_states: BTreeMap::new(),
             deadline,
             refund_grant_to,
             resource_controller,
             transaction_tracker,
             scheduled_operations: Vec::new(),
+            scheduled_operation_metadata: Vec::new(),
             user_context,
         }
     }
@@ -380,27 +469,62 @@ impl<UserInstance: WithContext> SyncRuntimeInternal<UserInstance> {
         status
     }
 
-    /// Ensures that a call to `application_id` is not-reentrant.
+    /// Checks whether a call to `application_id` may be made, and returns whether it is
+    /// reentrant, i.e. whether there already is an entry for `application_id` in the call
+    /// stack.
     ///
-    /// Returns an error if there already is an entry for `application_id` in the call stack.
+    /// Reentrant calls must be view calls to an application whose [`ReentrancyPolicy`]
+    /// accepts them.
     fn check_for_reentrancy(
         &mut self,
         application_id: ApplicationId,
-    ) -> Result<(), ExecutionError> {
+        read_only: bool,
+    ) -> Result<bool, ExecutionError> {
+        if !self.active_applications.contains(&application_id) {
+            return Ok(false);
+        }
+        let policy = self
+            .reentrancy_policies
+            .get(&application_id)
+            .copied()
+            .unwrap_or_default();
+        let is_allowed = match policy {
+            ReentrancyPolicy::Deny => false,
+            ReentrancyPolicy::Allow => true,
+            ReentrancyPolicy::AllowSameChainOnly => self
+                .executing_message
+                .as_ref()
+                .is_none_or(|message| message.id.chain_id == self.chain_id),
+        };
         ensure!(
-            !self.active_applications.contains(&application_id),
+            is_allowed && read_only,
             ExecutionError::ReentrantCall(application_id)
         );
+        Ok(true)
+    }
+
+    /// Returns an error if one more application can't be pushed to the call stack.
+    fn check_call_depth(&self) -> Result<(), ExecutionError> {
+        let maximum = self.resource_controller.policy().maximum_call_depth;
+        ensure!(
+            (self.call_stack.len() as u64) < maximum,
+            ExecutionError::MaximumCallDepthExceeded(maximum)
+        );
         Ok(())
     }
 }
 
 impl SyncRuntimeInternal<UserContractInstance> {
     /// Loads a contract instance, initializing it with this runtime if needed.
+    ///
+    /// The instance is only finalized at the end of the transaction if `finalize` is true for
+    /// one of the times it is loaded, so that instances only used for view calls don't store
+    /// their state again.
     fn load_contract_instance(
         &mut self,
         this: SyncRuntimeHandle<UserContractInstance>,
         id: ApplicationId,
+        finalize: bool,
     ) -> Result<LoadedApplication<UserContractInstance>, ExecutionError> {
         match self.loaded_applications.entry(id) {
             // TODO(#2927): support dynamic loading of modules on the Web
@@ -424,36 +548,112 @@ impl SyncRuntimeInternal<UserContractInstance> {
                     .recv_response()?;
                 self.transaction_tracker = txn_tracker_moved;
 
-                let instance = code.instantiate(this)?;
+                let instance = if self.pool_instances {
+                    code.instantiate_pooled(this, id, self.chain_id)?
+                } else {
+                    code.instantiate(this)?
+                };
 
-                self.applications_to_finalize.push(id);
+                if finalize {
+                    self.applications_to_finalize.push(id);
+                }
                 Ok(entry
                     .insert(LoadedApplication::new(instance, description))
                     .clone())
             }
-            hash_map::Entry::Occupied(entry) => Ok(entry.get().clone()),
+            hash_map::Entry::Occupied(entry) => {
+                let application = entry.get().clone();
+                if finalize && !self.applications_to_finalize.contains(&id) {
+                    self.applications_to_finalize.push(id);
+                }
+                Ok(application)
+            }
         }
     }
 
+    /// Creates a new instance of a contract that is already running, to execute a reentrant
+    /// view call. The instance is neither kept nor finalized after the call.
+    #[cfg(not(web))]
+    fn load_reentrant_contract_instance(
+        &mut self,
+        this: SyncRuntimeHandle<UserContractInstance>,
+        id: ApplicationId,
+    ) -> Result<LoadedApplication<UserContractInstance>, ExecutionError> {
+        let txn_tracker_moved = mem::take(&mut self.transaction_tracker);
+        let (code, description, txn_tracker_moved) = self
+            .execution_state_sender
+            .send_request(move |callback| ExecutionRequest::LoadContract {
+                id,
+                callback,
+                txn_tracker: txn_tracker_moved,
+            })?
+            .recv_response()?;
+        self.transaction_tracker = txn_tracker_moved;
+        let instance = code.instantiate(this)?;
+        Ok(LoadedApplication::new(instance, description))
+    }
+
+    // TODO(#2927): support dynamic loading of modules on the Web
+    #[cfg(web)]
+    fn load_reentrant_contract_instance(
+        &mut self,
+        this: SyncRuntimeHandle<UserContractInstance>,
+        id: ApplicationId,
+    ) -> Result<LoadedApplication<UserContractInstance>, ExecutionError> {
+        drop(this);
+        Err(ExecutionError::UnsupportedDynamicApplicationLoad(Box::new(
+            id,
+        )))
+    }
+
+    /// Returns an error if the current application runs a view function.
+    fn ensure_writable(&self) -> Result<(), ExecutionError> {
+        let application = self.current_application();
+        ensure!(
+            !application.read_only,
+            ExecutionError::WriteInViewCall(Box::new(application.id))
+        );
+        Ok(())
+    }
+
     /// Configures the runtime for executing a call to a different contract.
+    ///
+    /// View functions may be called in more contexts, e.g. while finalizing, but may only
+    /// call other view functions themselves.
     fn prepare_for_call(
         &mut self,
         this: ContractSyncRuntimeHandle,
         authenticated: bool,
         callee_id: ApplicationId,
+        read_only: bool,
     ) -> Result<Arc<Mutex<UserContractInstance>>, ExecutionError> {
-        self.check_for_reentrancy(callee_id)?;
+        let is_reentrant = self.check_for_reentrancy(callee_id, read_only)?;
+        self.check_call_depth()?;
 
-        ensure!(
-            !self.is_finalizing,
-            ExecutionError::CrossApplicationCallInFinalize {
-                caller_id: Box::new(self.current_application().id),
-                callee_id: Box::new(callee_id),
-            }
-        );
+        if !read_only {
+            ensure!(
+                !self.is_finalizing,
+                ExecutionError::CrossApplicationCallInFinalize {
+                    caller_id: Box::new(self.current_application().id),
+                    callee_id: Box::new(callee_id),
+                }
+            );
+            ensure!(
+                !self.is_closing_chain,
+                ExecutionError::CrossApplicationCallInClosureHook {
+                    caller_id: Box::new(self.current_application().id),
+                    callee_id: Box::new(callee_id),
+                }
+            );
+            self.ensure_writable()?;
+        }
 
         // Load the application.
-        let application = self.load_contract_instance(this, callee_id)?;
+        let application = if is_reentrant {
+            self.load_reentrant_contract_instance(this, callee_id)?
+        } else {
+            self.load_contract_instance(this, callee_id, !read_only)?
+        };
 
         let caller = self.current_application();
         let caller_id = caller.id;
@@ -470,6 +670,7 @@ impl SyncRuntimeInternal<UserContractInstance> {
             description: application.description,
             // Allow further nested calls to be authenticated if this one is.
             signer: authenticated_signer,
+            read_only,
         });
         Ok(application.instance)
     }
@@ -480,6 +681,72 @@ impl SyncRuntimeInternal<UserContractInstance> {
         Ok(())
     }
 
+    /// Records the state of the runtime and of the chain before a speculative call to
+    /// `callee_id`, and sets aside the loaded applications so that the call uses new instances.
+    fn begin_speculation(
+        &mut self,
+        callee_id: ApplicationId,
+    ) -> Result<Speculation, ExecutionError> {
+        ensure!(
+            !self.is_finalizing,
+            ExecutionError::CrossApplicationCallInFinalize {
+                caller_id: Box::new(self.current_application().id),
+                callee_id: Box::new(callee_id),
+            }
+        );
+        self.ensure_writable()?;
+        let maximum = self
+            .resource_controller
+            .policy()
+            .maximum_speculative_calls_per_transaction;
+        ensure!(
+            self.speculative_calls < maximum,
+            ExecutionError::MaximumSpeculativeCallsExceeded(maximum)
+        );
+        self.speculative_calls += 1;
+        let execution_state = self
+            .execution_state_sender
+            .send_request(|callback| ExecutionRequest::SnapshotState { callback })?
+            .recv_response()?;
+        Ok(Speculation {
+            call_stack_depth: self.call_stack.len(),
+            loaded_applications: mem::take(&mut self.loaded_applications),
+            applications_to_finalize: mem::take(&mut self.applications_to_finalize),
+            reentrancy_policies: self.reentrancy_policies.clone(),
+            transaction_tracker: self.transaction_tracker.snapshot(),
+            execution_state,
+        })
+    }
+
+    /// Undoes the effects of a speculative call, whether it succeeded or not, and discards
+    /// the instances it loaded. The resources it consumed remain accounted for.
+    fn end_speculation(&mut self, speculation: Speculation) -> Result<(), ExecutionError> {
+        let Speculation {
+            call_stack_depth,
+            loaded_applications,
+            applications_to_finalize,
+            reentrancy_policies,
+            transaction_tracker,
+            execution_state,
+        } = speculation;
+        // A failed call leaves its callees on the call stack.
+        while self.call_stack.len() > call_stack_depth {
+            self.pop_application();
+        }
+        self.loaded_applications = loaded_applications;
+        self.applications_to_finalize = applications_to_finalize;
+        self.reentrancy_policies = reentrancy_policies;
+        let used_blobs = self.transaction_tracker.restore(transaction_tracker);
+        self.execution_state_sender
+            .send_request(|callback| ExecutionRequest::RestoreState {
+                snapshot: execution_state,
+                used_blobs,
+                callback,
+            })?
+            .recv_response()?;
+        Ok(())
+    }
+
     /// Runs the service in a separate thread as an oracle.
     fn run_service_oracle_query(
         &mut self,
@@ -515,12 +782,14 @@ impl SyncRuntimeInternal<UserContractInstance> {
         let QueryOutcome {
             response,
             operations,
+            operation_metadata,
         } = result?;
 
         self.resource_controller
             .track_service_oracle_response(response.len())?;
 
         self.scheduled_operations.extend(operations);
+        self.scheduled_operation_metadata.extend(operation_metadata);
         Ok(response)
     }
 }
@@ -646,7 +915,7 @@ where
             .execution_state_sender
             .send_request(|callback| ExecutionRequest::SystemTimestamp { callback })?
             .recv_response()?;
-        this.resource_controller.track_runtime_timestamp()?;
+        this.resource_controller.track_system_read()?;
         Ok(timestamp)
     }
 
@@ -656,7 +925,7 @@ where
             .execution_state_sender
             .send_request(|callback| ExecutionRequest::ChainBalance { callback })?
             .recv_response()?;
-        this.resource_controller.track_runtime_balance()?;
+        this.resource_controller.track_system_read()?;
         Ok(balance)
     }
 
@@ -666,7 +935,7 @@ where
             .execution_state_sender
             .send_request(|callback| ExecutionRequest::OwnerBalance { owner, callback })?
             .recv_response()?;
-        this.resource_controller.track_runtime_balance()?;
+        this.resource_controller.track_system_read()?;
         Ok(balance)
     }
 
@@ -697,11 +966,35 @@ where
             .execution_state_sender
             .send_request(|callback| ExecutionRequest::ChainOwnership { callback })?
             .recv_response()?;
-        this.resource_controller
-            .track_runtime_chain_ownership(&chain_ownership)?;
+        this.resource_controller.track_system_read()?;
         Ok(chain_ownership)
     }
 
+    fn read_epoch(&mut self) -> Result<Epoch, ExecutionError> {
+        let mut this = self.inner();
+        let epoch = this
+            .execution_state_sender
+            .send_request(|callback| ExecutionRequest::CurrentEpoch { callback })?
+            .recv_response()?;
+        this.resource_controller.track_system_read()?;
+        Ok(epoch)
+    }
+
+    fn read_application_config(&mut self) -> Result<Option<Vec<u8>>, ExecutionError> {
+        let mut this = self.inner();
+        let id = this.current_application().id;
+        this.resource_controller.track_read_operation()?;
+        let config = this
+            .execution_state_sender
+            .send_request(|callback| ExecutionRequest::ApplicationConfig { id, callback })?
+            .recv_response()?;
+        if let Some(config) = &config {
+            this.resource_controller
+                .track_bytes_read(config.len() as u64)?;
+        }
+        Ok(config)
+    }
+
     fn contains_key_new(&mut self, key: Vec<u8>) -> Result<Self::ContainsKey, ExecutionError> {
         let mut this = self.inner();
         let id = this.current_application().id;
@@ -965,6 +1258,48 @@ where
         }
         Ok(())
     }
+
+    fn read_value_bytes_on_chain(
+        &mut self,
+        chain_id: ChainId,
+        key: Vec<u8>,
+    ) -> Result<Option<Vec<u8>>, ExecutionError> {
+        let mut this = self.inner();
+        let application_id = this.current_application().id;
+        let value = match this.transaction_tracker.next_replayed_oracle_response()? {
+            None => this
+                .execution_state_sender
+                .send_request(|callback| ExecutionRequest::ReadApplicationValue {
+                    chain_id,
+                    application_id,
+                    key: key.clone(),
+                    callback,
+                })?
+                .recv_response()?,
+            Some(OracleResponse::ApplicationValue(
+                recorded_chain_id,
+                recorded_application_id,
+                recorded_key,
+                value,
+            )) if recorded_chain_id == chain_id
+                && recorded_application_id == application_id
+                && recorded_key == key =>
+            {
+                value
+            }
+            Some(_) => return Err(ExecutionError::OracleResponseMismatch),
+        };
+        this.resource_controller
+            .track_cross_chain_read(value.as_ref().map_or(0, Vec::len))?;
+        this.transaction_tracker
+            .add_oracle_response(OracleResponse::ApplicationValue(
+                chain_id,
+                application_id,
+                key,
+                value.clone(),
+            ));
+        Ok(value)
+    }
 }
 
 /// An extension trait to determine in compile time the different behaviors between contract and
@@ -1000,26 +1335,28 @@ impl ContractSyncRuntime {
         resource_controller: ResourceController,
         action: &UserAction,
         txn_tracker: TransactionTracker,
+        pool_instances: bool,
     ) -> Self {
-        SyncRuntime(Some(ContractSyncRuntimeHandle::from(
-            SyncRuntimeInternal::new(
-                chain_id,
-                action.height(),
-                action.round(),
-                action.signer(),
-                if let UserAction::Message(context, _) = action {
-                    Some(context.into())
-                } else {
-                    None
-                },
-                execution_state_sender,
-                None,
-                refund_grant_to,
-                resource_controller,
-                txn_tracker,
-                action.timestamp(),
-            ),
-        )))
+        let mut runtime = SyncRuntimeInternal::new(
+            chain_id,
+            action.height(),
+            action.round(),
+            action.signer(),
+            if let UserAction::Message(context, _) = action {
+                Some(context.into())
+            } else {
+                None
+            },
+            execution_state_sender,
+            None,
+            refund_grant_to,
+            resource_controller,
+            txn_tracker,
+            action.timestamp(),
+        );
+        runtime.pool_instances = pool_instances;
+        runtime.is_closing_chain = matches!(action, UserAction::CloseChain(_));
+        SyncRuntime(Some(ContractSyncRuntimeHandle::from(runtime)))
     }
 
     pub(crate) fn preload_contract(
@@ -1034,12 +1371,15 @@ impl ContractSyncRuntime {
             .expect("contracts shouldn't be preloaded while the runtime is being dropped");
         let runtime_handle = this.clone();
         let mut this_guard = this.inner();
+        let (pool_instances, chain_id) = (this_guard.pool_instances, this_guard.chain_id);
 
         if let hash_map::Entry::Vacant(entry) = this_guard.loaded_applications.entry(id) {
-            entry.insert(LoadedApplication::new(
-                code.instantiate(runtime_handle)?,
-                description,
-            ));
+            let instance = if pool_instances {
+                code.instantiate_pooled(runtime_handle, id, chain_id)?
+            } else {
+                code.instantiate(runtime_handle)?
+            };
+            entry.insert(LoadedApplication::new(instance, description));
             this_guard.applications_to_finalize.push(id);
         }
 
@@ -1100,6 +1440,13 @@ impl ContractSyncRuntimeHandle {
             UserAction::ProcessStreams(_context, updates) => {
                 code.process_streams(updates).map(|()| None)
             }
+            UserAction::CloseChain(_context) => code.on_chain_close().map(|()| None),
+            UserAction::Upgrade(_context) => code.on_upgrade().map(|()| None),
+            UserAction::AuthorizeProposal(_context, payload) => {
+                let authorized = code.authorize_proposal(payload)?;
+                ensure!(authorized, ExecutionError::ProposalNotAuthorized);
+                Ok(None)
+            }
         };
 
         let result = self.execute(application_id, signer, closure)?;
@@ -1134,13 +1481,17 @@ impl ContractSyncRuntimeHandle {
     ) -> Result<Option<Vec<u8>>, ExecutionError> {
         let contract = {
             let mut runtime = self.inner();
-            let application = runtime.load_contract_instance(self.clone(), application_id)?;
+            // Applications executed while finalizing are already being finalized.
+            let finalize = !runtime.is_finalizing;
+            let application =
+                runtime.load_contract_instance(self.clone(), application_id, finalize)?;
 
             let status = ApplicationStatus {
                 caller_id: None,
                 id: application_id,
                 description: application.description.clone(),
                 signer,
+                read_only: false,
             };
 
             runtime.push_application(status);
@@ -1214,11 +1565,21 @@ impl ContractRuntime for ContractSyncRuntimeHandle {
 
     fn consume_fuel(&mut self, fuel: u64, vm_runtime: VmRuntime) -> Result<(), ExecutionError> {
         let mut this = self.inner();
+        let fuel = if this
+            .call_stack
+            .last()
+            .is_some_and(|application| application.read_only)
+        {
+            fuel.div_ceil(VIEW_CALL_FUEL_DIVISOR)
+        } else {
+            fuel
+        };
         this.resource_controller.track_fuel(fuel, vm_runtime)
     }
 
     fn send_message(&mut self, message: SendMessageRequest<Vec<u8>>) -> Result<(), ExecutionError> {
         let mut this = self.inner();
+        this.ensure_writable()?;
         let application = this.current_application();
         let application_id = application.id;
         let authenticated_signer = application.signer;
@@ -1262,6 +1623,7 @@ impl ContractRuntime for ContractSyncRuntimeHandle {
         amount: Amount,
     ) -> Result<(), ExecutionError> {
         let mut this = self.inner();
+        this.ensure_writable()?;
         let current_application = this.current_application();
         let application_id = current_application.id;
         let signer = current_application.signer;
@@ -1290,6 +1652,7 @@ impl ContractRuntime for ContractSyncRuntimeHandle {
         amount: Amount,
     ) -> Result<(), ExecutionError> {
         let mut this = self.inner();
+        this.ensure_writable()?;
         let current_application = this.current_application();
         let application_id = current_application.id;
         let signer = current_application.signer;
@@ -1314,23 +1677,59 @@ impl ContractRuntime for ContractSyncRuntimeHandle {
         authenticated: bool,
         callee_id: ApplicationId,
         argument: Vec<u8>,
+    ) -> Result<Vec<u8>, ExecutionError> {
+        let contract =
+            self.inner()
+                .prepare_for_call(self.clone(), authenticated, callee_id, false)?;
+
+        let value = contract
+            .try_lock()
+            .expect("Applications should not have reentrant calls")
+            .execute_operation(argument)?;
+
+        self.inner().finish_call()?;
+
+        Ok(value)
+    }
+
+    fn try_view_call(
+        &mut self,
+        callee_id: ApplicationId,
+        argument: Vec<u8>,
     ) -> Result<Vec<u8>, ExecutionError> {
         let contract = self
             .inner()
-            .prepare_for_call(self.clone(), authenticated, callee_id)?;
+            .prepare_for_call(self.clone(), false, callee_id, true)?;
 
         let value = contract
             .try_lock()
             .expect("Applications should not have reentrant calls")
-            .execute_operation(argument)?;
+            .execute_view(argument)?;
 
         self.inner().finish_call()?;
 
         Ok(value)
     }
 
+    fn try_call(
+        &mut self,
+        authenticated: bool,
+        callee_id: ApplicationId,
+        argument: Vec<u8>,
+    ) -> Result<Option<Vec<u8>>, ExecutionError> {
+        let speculation = self.inner().begin_speculation(callee_id)?;
+        let result = self.try_call_application(authenticated, callee_id, argument);
+        self.inner().end_speculation(speculation)?;
+        match result {
+            Ok(response) => Ok(Some(response)),
+            Err(error) if fails_speculative_caller(&error) => Err(error),
+            Err(_) => Ok(None),
+        }
+    }
+
     fn emit(&mut self, stream_name: StreamName, value: Vec<u8>) -> Result<u32, ExecutionError> {
         let mut this = self.inner();
+        this.ensure_writable()?;
         ensure!(
             stream_name.0.len() <= MAX_STREAM_NAME_LEN,
             ExecutionError::StreamNameTooLong
@@ -1405,6 +1804,7 @@ impl ContractRuntime for ContractSyncRuntimeHandle {
         stream_name: StreamName,
     ) -> Result<(), ExecutionError> {
         let mut this = self.inner();
+        this.ensure_writable()?;
         ensure!(
             stream_name.0.len() <= MAX_STREAM_NAME_LEN,
             ExecutionError::StreamNameTooLong
@@ -1440,6 +1840,7 @@ impl ContractRuntime for ContractSyncRuntimeHandle {
         stream_name: StreamName,
     ) -> Result<(), ExecutionError> {
         let mut this = self.inner();
+        this.ensure_writable()?;
         ensure!(
             stream_name.0.len() <= MAX_STREAM_NAME_LEN,
             ExecutionError::StreamNameTooLong
@@ -1462,6 +1863,70 @@ impl ContractRuntime for ContractSyncRuntimeHandle {
         Ok(())
     }
 
+    fn publish_message(
+        &mut self,
+        channel: ChannelName,
+        message: Vec<u8>,
+    ) -> Result<(), ExecutionError> {
+        let mut this = self.inner();
+        this.ensure_writable()?;
+        ensure!(
+            channel.0.len() <= MAX_CHANNEL_NAME_LEN,
+            ExecutionError::ChannelNameTooLong
+        );
+        let application = this.current_application();
+        let message = PublishedMessage {
+            application_id: application.id,
+            channel,
+            authenticated_signer: application.signer,
+            bytes: message,
+        };
+        this.transaction_tracker.add_published_message(message);
+        Ok(())
+    }
+
+    fn subscribe_to_channel(
+        &mut self,
+        chain_id: ChainId,
+        channel: ChannelName,
+    ) -> Result<(), ExecutionError> {
+        let mut this = self.inner();
+        this.ensure_writable()?;
+        ensure!(
+            channel.0.len() <= MAX_CHANNEL_NAME_LEN,
+            ExecutionError::ChannelNameTooLong
+        );
+        let application_id = this.current_application().id;
+        let message = SystemMessage::SubscribeToChannel {
+            application_id,
+            channel,
+        };
+        this.transaction_tracker
+            .add_outgoing_message(OutgoingMessage::new(chain_id, message))?;
+        Ok(())
+    }
+
+    fn unsubscribe_from_channel(
+        &mut self,
+        chain_id: ChainId,
+        channel: ChannelName,
+    ) -> Result<(), ExecutionError> {
+        let mut this = self.inner();
+        this.ensure_writable()?;
+        ensure!(
+            channel.0.len() <= MAX_CHANNEL_NAME_LEN,
+            ExecutionError::ChannelNameTooLong
+        );
+        let application_id = this.current_application().id;
+        let message = SystemMessage::UnsubscribeFromChannel {
+            application_id,
+            channel,
+        };
+        this.transaction_tracker
+            .add_outgoing_message(OutgoingMessage::new(chain_id, message))?;
+        Ok(())
+    }
+
     fn query_service(
         &mut self,
         application_id: ApplicationId,
@@ -1503,6 +1968,7 @@ impl ContractRuntime for ContractSyncRuntimeHandle {
         application_permissions: ApplicationPermissions,
         balance: Amount,
     ) -> Result<ChainId, ExecutionError> {
+        self.inner().ensure_writable()?;
         let parent_id = self.inner().chain_id;
         let block_height = self.block_height()?;
 
@@ -1531,6 +1997,7 @@ impl ContractRuntime for ContractSyncRuntimeHandle {
 
     fn close_chain(&mut self) -> Result<(), ExecutionError> {
         let this = self.inner();
+        this.ensure_writable()?;
         let application_id = this.current_application().id;
         this.execution_state_sender
             .send_request(|callback| ExecutionRequest::CloseChain {
@@ -1545,6 +2012,7 @@ impl ContractRuntime for ContractSyncRuntimeHandle {
         application_permissions: ApplicationPermissions,
     ) -> Result<(), ExecutionError> {
         let this = self.inner();
+        this.ensure_writable()?;
         let application_id = this.current_application().id;
         this.execution_state_sender
             .send_request(|callback| ExecutionRequest::ChangeApplicationPermissions {
@@ -1562,6 +2030,7 @@ impl ContractRuntime for ContractSyncRuntimeHandle {
         argument: Vec<u8>,
         required_application_ids: Vec<ApplicationId>,
     ) -> Result<ApplicationId, ExecutionError> {
+        self.inner().ensure_writable()?;
         let chain_id = self.inner().chain_id;
         let block_height = self.block_height()?;
 
@@ -1586,7 +2055,9 @@ impl ContractRuntime for ContractSyncRuntimeHandle {
 
         self.inner().transaction_tracker = txn_tracker_moved;
 
-        let contract = self.inner().prepare_for_call(self.clone(), true, app_id)?;
+        let contract = self
+            .inner()
+            .prepare_for_call(self.clone(), true, app_id, false)?;
 
         contract
             .try_lock()
@@ -1599,6 +2070,7 @@ impl ContractRuntime for ContractSyncRuntimeHandle {
     }
 
     fn create_data_blob(&mut self, bytes: Vec<u8>) -> Result<BlobId, ExecutionError> {
+        self.inner().ensure_writable()?;
         let blob = Blob::new_data(bytes);
         let blob_id = blob.id();
         self.inner().transaction_tracker.add_created_blob(blob);
@@ -1611,6 +2083,7 @@ impl ContractRuntime for ContractSyncRuntimeHandle {
         service: Bytecode,
         vm_runtime: VmRuntime,
     ) -> Result<ModuleId, ExecutionError> {
+        self.inner().ensure_writable()?;
         let (blobs, module_id) =
             crate::runtime::create_bytecode_blobs_sync(contract, service, vm_runtime);
         for blob in blobs {
@@ -1635,8 +2108,61 @@ impl ContractRuntime for ContractSyncRuntimeHandle {
         Ok(round)
     }
 
+    fn draw_entropy(&mut self, num_bytes: u32) -> Result<Vec<u8>, ExecutionError> {
+        let mut this = self.inner();
+        let maximum = this
+            .resource_controller
+            .policy()
+            .maximum_entropy_per_transaction;
+        let offset = this
+            .transaction_tracker
+            .draw_entropy(num_bytes.into(), maximum)?;
+        let block = EntropyBlock {
+            chain_id: this.chain_id,
+            height: this.height,
+            transaction_index: this.transaction_tracker.transaction_index(),
+            index: 0,
+        };
+        Ok(block.entropy(offset, num_bytes))
+    }
+
+    fn declare_reentrancy_policy(
+        &mut self,
+        policy: ReentrancyPolicy,
+    ) -> Result<(), ExecutionError> {
+        let mut this = self.inner();
+        let application_id = this.current_application().id;
+        this.reentrancy_policies.insert(application_id, policy);
+        Ok(())
+    }
+
+    fn ethereum_storage(&mut self, key: EthereumStorageKey) -> Result<[u8; 32], ExecutionError> {
+        let mut this = self.inner();
+        // Reading Ethereum data requires requests to an Ethereum node, priced like HTTP requests.
+        this.resource_controller.track_http_request()?;
+
+        let value = if let Some(response) =
+            this.transaction_tracker.next_replayed_oracle_response()?
+        {
+            match response {
+                OracleResponse::EthereumStorage(recorded_key, value) if recorded_key == key => {
+                    value
+                }
+                _ => return Err(ExecutionError::OracleResponseMismatch),
+            }
+        } else {
+            this.execution_state_sender
+                .send_request(|callback| ExecutionRequest::ReadEthereumStorage { key, callback })?
+                .recv_response()?
+        };
+        this.transaction_tracker
+            .add_oracle_response(OracleResponse::EthereumStorage(key, value));
+        Ok(value)
+    }
+
     fn write_batch(&mut self, batch: Batch) -> Result<(), ExecutionError> {
         let mut this = self.inner();
+        this.ensure_writable()?;
         let id = this.current_application().id;
         let state = this.view_user_states.entry(id).or_default();
         state.force_all_pending_queries()?;
@@ -1765,11 +2291,14 @@ impl ServiceSyncRuntime {
     ) -> Result<QueryOutcome<Vec<u8>>, ExecutionError> {
         let this = self.handle_mut();
         let response = this.try_query_application(application_id, query)?;
-        let operations = mem::take(&mut this.inner().scheduled_operations);
+        let mut inner = this.inner();
+        let operations = mem::take(&mut inner.scheduled_operations);
+        let operation_metadata = mem::take(&mut inner.scheduled_operation_metadata);
 
         Ok(QueryOutcome {
             response,
             operations,
+            operation_metadata,
         })
     }
 
@@ -1799,6 +2328,7 @@ impl ServiceRuntime for ServiceSyncRuntimeHandle {
                 id: queried_id,
                 description: application.description,
                 signer: None,
+                read_only: true,
             });
             application.instance
         };
@@ -1810,6 +2340,24 @@ impl ServiceRuntime for ServiceSyncRuntimeHandle {
         Ok(response)
     }
 
+    fn query_application_at(
+        &mut self,
+        queried_id: ApplicationId,
+        height: BlockHeight,
+        argument: Vec<u8>,
+    ) -> Result<Option<Vec<u8>>, ExecutionError> {
+        // The historical state is queried by another runtime, outside of this one.
+        self.inner()
+            .execution_state_sender
+            .send_request(|callback| ExecutionRequest::QueryApplicationAt {
+                application_id: queried_id,
+                height,
+                query: argument,
+                callback,
+            })?
+            .recv_response()
+    }
+
     fn schedule_operation(&mut self, operation: Vec<u8>) -> Result<(), ExecutionError> {
         let mut this = self.inner();
         let application_id = this.current_application().id;
@@ -1818,6 +2366,24 @@ impl ServiceRuntime for ServiceSyncRuntimeHandle {
             application_id,
             bytes: operation,
         });
+        this.scheduled_operation_metadata.push(None);
+
+        Ok(())
+    }
+
+    fn schedule_operation_with_metadata(
+        &mut self,
+        operation: Vec<u8>,
+        metadata: OperationMetadata,
+    ) -> Result<(), ExecutionError> {
+        let mut this = self.inner();
+        let application_id = this.current_application().id;
+
+        this.scheduled_operations.push(Operation::User {
+            application_id,
+            bytes: operation,
+        });
+        this.scheduled_operation_metadata.push(Some(metadata));
 
         Ok(())
     }
@@ -14,20 +14,28 @@ use std::{
 
 use custom_debug_derive::Debug;
 use linera_base::{
-    crypto::CryptoHash,
+    crypto::{
+        BcsSignable, CryptoError, CryptoHash, ValidatorPublicKey, ValidatorSecretKey,
+        ValidatorSignature,
+    },
     data_types::{
         Amount, ApplicationPermissions, ArithmeticError, Blob, BlobContent, BlockHeight,
-        ChainDescription, ChainOrigin, Epoch, InitialChainConfig, OracleResponse, Timestamp,
+        ChainDescription, ChainOrigin, Epoch, InitialChainConfig, OracleResponse, TimeDelta,
+        Timestamp,
     },
     ensure, hex_debug,
-    identifiers::{Account, AccountOwner, BlobId, BlobType, ChainId, EventId, ModuleId, StreamId},
+    identifiers::{
+        Account, AccountOwner, BlobId, BlobType, ChainId, ChannelName, EventId,
+        GenericApplicationId, ModuleId, StreamId,
+    },
     ownership::{ChainOwnership, TimeoutConfig},
 };
 use linera_views::{
+    collection_view::CollectionView,
     context::Context,
     map_view::{HashedMapView, MapView},
     register_view::HashedRegisterView,
-    set_view::HashedSetView,
+    set_view::{HashedSetView, SetView},
     views::{ClonableView, HashableView, View},
 };
 use serde::{Deserialize, Serialize};
@@ -37,13 +45,21 @@ use crate::test_utils::SystemExecutionState;
 use crate::{
     committee::Committee, ApplicationDescription, ApplicationId, ExecutionError,
     ExecutionRuntimeContext, MessageContext, MessageKind, OperationContext, OutgoingMessage,
-    QueryContext, QueryOutcome, ResourceController, TransactionTracker,
+    QueryContext, QueryOutcome, ResourceControlPolicy, ResourceController, TransactionTracker,
 };
 
 /// The event stream name for new epochs and committees.
 pub static EPOCH_STREAM_NAME: &[u8] = &[0];
 /// The event stream name for removed epochs.
 pub static REMOVED_EPOCH_STREAM_NAME: &[u8] = &[1];
+/// The event stream name for validator weight updates within an epoch.
+pub static VALIDATOR_WEIGHTS_STREAM_NAME: &[u8] = &[2];
+/// The event stream name for staged changes of a chain's application permissions.
+pub static APPLICATION_PERMISSIONS_STREAM_NAME: &[u8] = &[3];
+/// The event stream name for the resources consumed by each block, if the policy requires it.
+pub static RESOURCE_USAGE_STREAM_NAME: &[u8] = &[4];
+/// The event stream name for the network addresses announced by validators.
+pub static VALIDATOR_ADDRESSES_STREAM_NAME: &[u8] = &[5];
 
 /// The number of times the [`SystemOperation::OpenChain`] was executed.
 #[cfg(with_metrics)]
@@ -92,6 +108,166 @@ pub struct SystemExecutionStateView<C> {
     pub used_blobs: HashedSetView<C, BlobId>,
     /// The event stream subscriptions of applications on this chain.
     pub event_subscriptions: MapView<C, (ChainId, StreamId), EventSubscriptions>,
+    /// The chains subscribed to the channels of applications on this chain, with one set per
+    /// channel so that (un)subscribing does not rewrite the other subscribers.
+    pub channel_subscribers: CollectionView<C, (ApplicationId, ChannelName), SetView<C, ChainId>>,
+    /// The number of validator weight updates from the admin chain processed by this chain.
+    pub validator_weight_updates: HashedRegisterView<C, u32>,
+    /// The committees replaced by a validator weight update, with the time until which
+    /// validators still accept certificates signed according to the previous weights.
+    pub superseded_committees: HashedRegisterView<C, BTreeMap<Epoch, (Committee, Timestamp)>>,
+    /// The latest network address announced by each validator. Only used on the admin chain.
+    pub validator_addresses: HashedMapView<C, ValidatorPublicKey, SignedValidatorAddress>,
+    /// The number of validator address announcements on this chain, i.e. the next index in
+    /// the [`VALIDATOR_ADDRESSES_STREAM_NAME`] stream.
+    pub validator_address_count: HashedRegisterView<C, u32>,
+    /// The configuration of applications on this chain, set by the chain owners with
+    /// [`SystemOperation::SetApplicationConfig`].
+    pub application_configs: HashedMapView<C, ApplicationId, Vec<u8>>,
+    /// Limits on the messages from each other chain waiting in the inboxes of this chain.
+    pub inbox_quotas: HashedRegisterView<C, InboxQuotas>,
+    /// The application that decides whether non-owners may propose blocks on this chain, if
+    /// any. See [`SystemOperation::AuthorizeProposal`].
+    pub authorization_application: HashedRegisterView<C, Option<ApplicationId>>,
+    /// Which owners may execute the operations of which applications on this chain, if
+    /// restricted.
+    pub operation_permissions: HashedRegisterView<C, Option<OperationPermissions>>,
+    /// A change of the application permissions that takes effect in a later block, if any.
+    pub staged_application_permissions: HashedRegisterView<C, Option<StagedApplicationPermissions>>,
+    /// The number of changes of the application permissions that were staged on this chain,
+    /// i.e. the next index in the [`APPLICATION_PERMISSIONS_STREAM_NAME`] stream.
+    pub staged_application_permissions_count: HashedRegisterView<C, u32>,
+    /// The modules that replace the code of applications on this chain, set with
+    /// [`SystemOperation::UpgradeApplication`].
+    pub upgraded_applications: HashedMapView<C, ApplicationId, ModuleId>,
+}
+
+/// A change of the voting rights of some validators within an epoch, published by the
+/// admin chain.
+#[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize)]
+pub struct ValidatorWeightUpdate {
+    /// The epoch of the committee to update.
+    pub epoch: Epoch,
+    /// The new voting rights of the validators that change.
+    pub votes: BTreeMap<ValidatorPublicKey, u64>,
+    /// Validators accept certificates signed according to the previous weights until their
+    /// clocks reach this time. This doesn't depend on the timestamps of the certified blocks,
+    /// which are chosen by their proposers.
+    pub grace_deadline: Timestamp,
+}
+
+/// The network address of a validator, announced on the admin chain. Clients connect to
+/// this address instead of the one in the committee.
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Serialize, Deserialize)]
+pub struct ValidatorAddress {
+    /// The validator whose address this is.
+    pub public_key: ValidatorPublicKey,
+    /// The network address, in a format understood by the networking layer.
+    pub network_address: String,
+    /// Must increase with each announcement by the same validator, so that an older
+    /// announcement cannot be replayed.
+    pub version: u64,
+}
+
+impl BcsSignable<'_> for ValidatorAddress {}
+
+/// A [`ValidatorAddress`] signed by the validator's key.
+#[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize)]
+pub struct SignedValidatorAddress {
+    pub address: ValidatorAddress,
+    pub signature: ValidatorSignature,
+}
+
+impl SignedValidatorAddress {
+    /// Signs the address with the secret key of the validator.
+    pub fn new(address: ValidatorAddress, secret_key: &ValidatorSecretKey) -> Self {
+        let signature = ValidatorSignature::new(&address, secret_key);
+        SignedValidatorAddress { address, signature }
+    }
+
+    /// Checks that the address was signed by the validator it belongs to.
+    pub fn check(&self) -> Result<(), CryptoError> {
+        self.signature.check(&self.address, self.address.public_key)
+    }
+}
+
+impl std::hash::Hash for SignedValidatorAddress {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.address.hash(state);
+        self.signature.as_bytes().hash(state);
+    }
+}
+
+/// Limits on the bundles from any single origin chain that can wait in an inbox. Once an
+/// inbox reaches them, further bundles from the same origin are refused until the chain
+/// processes its inbox, and the sender keeps them in its outbox to retry later.
+///
+/// Bundles from the admin chain are not limited.
+#[derive(Debug, Default, PartialEq, Eq, Hash, Clone, Copy, Serialize, Deserialize)]
+pub struct InboxQuotas {
+    /// The maximal number of pending bundles from the same origin.
+    pub max_pending_bundles: Option<u32>,
+    /// The maximal total size in bytes of the pending bundles from the same origin.
+    pub max_pending_bytes: Option<u64>,
+}
+
+/// An access control list of the operations on a chain: which owners may execute the
+/// operations of which applications. This applies on top of the chain's
+/// [`ApplicationPermissions`], which restrict the applications for everyone.
+#[derive(Debug, Default, PartialEq, Eq, Hash, Clone, Serialize, Deserialize)]
+pub struct OperationPermissions {
+    /// The applications whose operations each owner may execute, including
+    /// [`GenericApplicationId::System`] for system operations. Blocks with operations from
+    /// other owners, or without an authenticated signer, are rejected.
+    pub allowed_applications: BTreeMap<AccountOwner, BTreeSet<GenericApplicationId>>,
+}
+
+impl OperationPermissions {
+    /// Returns whether `owner` may execute the operations of the given application.
+    pub fn can_execute_operation(
+        &self,
+        owner: Option<&AccountOwner>,
+        application_id: &GenericApplicationId,
+    ) -> bool {
+        owner
+            .and_then(|owner| self.allowed_applications.get(owner))
+            .is_some_and(|application_ids| application_ids.contains(application_id))
+    }
+}
+
+/// A change of a chain's [`ApplicationPermissions`] that only takes effect in a later block,
+/// so that the messages already sent to the chain's applications under the current
+/// permissions can still be processed, and the applications can adapt in the meantime.
+///
+/// Staging a change publishes it in the [`APPLICATION_PERMISSIONS_STREAM_NAME`] stream.
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Serialize, Deserialize)]
+pub struct StagedApplicationPermissions {
+    /// The new permissions.
+    pub application_permissions: ApplicationPermissions,
+    /// The first block in which the new permissions apply.
+    pub activation: PermissionsActivation,
+}
+
+/// The first block in which staged application permissions apply.
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy, Serialize, Deserialize)]
+pub enum PermissionsActivation {
+    /// The first block at or above this height.
+    Height(BlockHeight),
+    /// The first block with this timestamp or a later one.
+    Timestamp(Timestamp),
+}
+
+impl PermissionsActivation {
+    /// Returns whether staged permissions apply in a block with the given height and
+    /// timestamp.
+    pub fn is_reached(&self, height: BlockHeight, timestamp: Timestamp) -> bool {
+        match self {
+            PermissionsActivation::Height(activation_height) => height >= *activation_height,
+            PermissionsActivation::Timestamp(activation_timestamp) => {
+                timestamp >= *activation_timestamp
+            }
+        }
+    }
 }
 
 /// The applications subscribing to a particular stream, and the next event index.
@@ -148,6 +324,9 @@ pub enum SystemOperation {
     /// Claims `amount` units of value from the given owner's account in the remote
     /// `target` chain. Depending on its configuration, the `target` chain may refuse to
     /// process the message.
+    ///
+    /// The owners of this chain can claim from the account that the chain itself owns on the
+    /// `target` chain, i.e. `AccountOwner::Chain(chain_id)`.
     Claim {
         owner: AccountOwner,
         target_id: ChainId,
@@ -204,6 +383,50 @@ pub enum SystemOperation {
     ProcessRemovedEpoch(Epoch),
     /// Updates the event stream trackers.
     UpdateStreams(Vec<(ChainId, StreamId, u32)>),
+    /// Processes the validator weight update event from the admin chain with the given index.
+    ProcessValidatorWeights(u32),
+    /// Makes `sponsor` pay the fees of the block instead of the authenticated signer. This
+    /// must be the first operation of the block, and the block proposal must carry the
+    /// sponsor's approval.
+    SponsorFees { sponsor: AccountOwner },
+    /// Changes the limits on the pending bundles from each origin in the inboxes of this
+    /// chain.
+    ChangeInboxQuotas(InboxQuotas),
+    /// Sets or removes the application that can authorize block proposals from accounts
+    /// that are not owners of this chain.
+    ChangeAuthorizationApplication {
+        application_id: Option<ApplicationId>,
+    },
+    /// Asks the chain's authorization application to accept the authenticated signer as the
+    /// proposer of this block, based on the given `payload`, e.g. a multisig or a recovery
+    /// proof. This must be the first operation of a block proposed by a non-owner, in a
+    /// multi-leader round.
+    AuthorizeProposal {
+        #[serde(with = "serde_bytes")]
+        #[debug(with = "hex_debug")]
+        payload: Vec<u8>,
+    },
+    /// Restricts which owners may execute the operations of which applications on this
+    /// chain, or lifts the restriction.
+    ChangeOperationPermissions(Option<OperationPermissions>),
+    /// Changes the application permissions of this chain from a later block on, replacing
+    /// any change staged before, or cancels the staged change if `None`.
+    StageApplicationPermissions(Option<StagedApplicationPermissions>),
+    /// Sets the configuration of an application on this chain, or removes it if `None`.
+    /// The application can read it during execution. Only owners of the chain may change it.
+    SetApplicationConfig {
+        application_id: ApplicationId,
+        #[debug(skip_if = Option::is_none)]
+        config: Option<Vec<u8>>,
+    },
+    /// Replaces the code of an application on this chain with the given module, and calls
+    /// the application's `on_upgrade` entrypoint with the new code so that it can migrate its
+    /// state. Other chains keep running the application's original code. Only owners of the
+    /// chain may upgrade applications.
+    UpgradeApplication {
+        application_id: ApplicationId,
+        module_id: ModuleId,
+    },
 }
 
 /// Operations that are only allowed on the admin chain.
@@ -219,6 +442,20 @@ pub enum AdminOperation {
     /// so that blocks from the retired epoch will not be accepted until they are followed (hence
     /// re-certified) by a block certified by a recent committee.
     RemoveCommittee { epoch: Epoch },
+    /// Changes the voting rights of some validators of the current committee without creating
+    /// a new epoch. Other chains apply the change by executing
+    /// [`SystemOperation::ProcessValidatorWeights`]. Validators keep accepting certificates
+    /// signed according to the previous weights for `grace_period` after this block's
+    /// timestamp, as measured by their own clocks.
+    UpdateValidatorWeights {
+        epoch: Epoch,
+        votes: BTreeMap<ValidatorPublicKey, u64>,
+        grace_period: TimeDelta,
+    },
+    /// Publishes a new network address of a validator of the current committee. The
+    /// announcement must be signed by the validator and have a higher version than the
+    /// previous one.
+    AnnounceValidatorAddress(SignedValidatorAddress),
 }
 
 /// A system message meant to be executed on a remote chain.
@@ -241,6 +478,16 @@ pub enum SystemMessage {
     },
     /// Notifies that a new application was created.
     ApplicationCreated,
+    /// Subscribes the sender chain to a channel of an application on the receiver chain.
+    SubscribeToChannel {
+        application_id: ApplicationId,
+        channel: ChannelName,
+    },
+    /// Unsubscribes the sender chain from a channel of an application on the receiver chain.
+    UnsubscribeFromChannel {
+        application_id: ApplicationId,
+        channel: ChannelName,
+    },
 }
 
 /// A query to the system state.
@@ -398,6 +645,54 @@ where
             }
             ChangeApplicationPermissions(application_permissions) => {
                 self.application_permissions.set(application_permissions);
+                self.staged_application_permissions.set(None);
+            }
+            StageApplicationPermissions(staged) => {
+                if let Some(staged) = &staged {
+                    let count = self.staged_application_permissions_count.get_mut();
+                    let index = *count;
+                    *count = count.checked_add(1).ok_or(ArithmeticError::Overflow)?;
+                    txn_tracker.add_event(
+                        StreamId::system(APPLICATION_PERMISSIONS_STREAM_NAME),
+                        index,
+                        bcs::to_bytes(staged)?,
+                    );
+                }
+                self.staged_application_permissions.set(staged);
+            }
+            ChangeInboxQuotas(inbox_quotas) => {
+                self.inbox_quotas.set(inbox_quotas);
+            }
+            ChangeAuthorizationApplication { application_id } => {
+                self.authorization_application.set(application_id);
+            }
+            ChangeOperationPermissions(operation_permissions) => {
+                self.operation_permissions.set(operation_permissions);
+            }
+            SetApplicationConfig {
+                application_id,
+                config,
+            } => {
+                ensure!(
+                    context
+                        .authenticated_signer
+                        .is_some_and(|signer| self.ownership.get().verify_owner(&signer)),
+                    ExecutionError::UnauthenticatedApplicationConfigOwner
+                );
+                match config {
+                    Some(config) => self.application_configs.insert(&application_id, config)?,
+                    None => self.application_configs.remove(&application_id)?,
+                }
+            }
+            AuthorizeProposal { .. } => {
+                return Err(ExecutionError::InternalError(
+                    "proposal authorization must be executed by the execution state view",
+                ));
+            }
+            UpgradeApplication { .. } => {
+                return Err(ExecutionError::InternalError(
+                    "application upgrades must be executed by the execution state view",
+                ));
             }
             CloseChain => self.close_chain().await?,
             Transfer {
@@ -459,12 +754,49 @@ where
                             self.committees.get_mut().remove(&epoch).is_some(),
                             ExecutionError::InvalidCommitteeRemoval
                         );
+                        self.superseded_committees.get_mut().remove(&epoch);
                         txn_tracker.add_event(
                             StreamId::system(REMOVED_EPOCH_STREAM_NAME),
                             epoch.0,
                             vec![],
                         );
                     }
+                    AdminOperation::UpdateValidatorWeights {
+                        epoch,
+                        votes,
+                        grace_period,
+                    } => {
+                        ensure!(
+                            *self.epoch.get() == epoch,
+                            ExecutionError::InvalidValidatorWeightUpdate
+                        );
+                        let index = *self.validator_weight_updates.get();
+                        let update = ValidatorWeightUpdate {
+                            epoch,
+                            votes,
+                            grace_deadline: context.timestamp.saturating_add(grace_period),
+                        };
+                        let bytes = bcs::to_bytes(&update)?;
+                        self.apply_validator_weight_update(update, context.timestamp)?;
+                        txn_tracker.add_event(
+                            StreamId::system(VALIDATOR_WEIGHTS_STREAM_NAME),
+                            index,
+                            bytes,
+                        );
+                    }
+                    AdminOperation::AnnounceValidatorAddress(announcement) => {
+                        self.check_validator_address(&announcement).await?;
+                        let count = self.validator_address_count.get_mut();
+                        let index = *count;
+                        *count = count.checked_add(1).ok_or(ArithmeticError::Overflow)?;
+                        txn_tracker.add_event(
+                            StreamId::system(VALIDATOR_ADDRESSES_STREAM_NAME),
+                            index,
+                            bcs::to_bytes(&announcement)?,
+                        );
+                        self.validator_addresses
+                            .insert(&announcement.address.public_key, announcement)?;
+                    }
                 }
             }
             PublishModule { module_id } => {
@@ -538,6 +870,7 @@ where
                     self.committees.get_mut().remove(&epoch).is_some(),
                     ExecutionError::InvalidCommitteeRemoval
                 );
+                self.superseded_committees.get_mut().remove(&epoch);
                 let admin_id = self
                     .admin_id
                     .get()
@@ -558,6 +891,33 @@ where
                 };
                 txn_tracker.add_oracle_response(OracleResponse::Event(event_id, bytes));
             }
+            ProcessValidatorWeights(index) => {
+                ensure!(
+                    *self.validator_weight_updates.get() == index,
+                    ExecutionError::InvalidValidatorWeightUpdate
+                );
+                let admin_id = self
+                    .admin_id
+                    .get()
+                    .ok_or_else(|| ExecutionError::InactiveChain(context.chain_id))?;
+                let event_id = EventId {
+                    chain_id: admin_id,
+                    stream_id: StreamId::system(VALIDATOR_WEIGHTS_STREAM_NAME),
+                    index,
+                };
+                let bytes = match txn_tracker.next_replayed_oracle_response()? {
+                    None => self.get_event(event_id.clone()).await?,
+                    Some(OracleResponse::Event(recorded_event_id, bytes))
+                        if recorded_event_id == event_id =>
+                    {
+                        bytes
+                    }
+                    Some(_) => return Err(ExecutionError::OracleResponseMismatch),
+                };
+                let update = bcs::from_bytes(&bytes)?;
+                txn_tracker.add_oracle_response(OracleResponse::Event(event_id, bytes));
+                self.apply_validator_weight_update(update, context.timestamp)?;
+            }
             UpdateStreams(streams) => {
                 let mut missing_events = Vec::new();
                 for (chain_id, stream_id, next_index) in streams {
@@ -610,6 +970,13 @@ where
                     ExecutionError::EventsNotFound(missing_events)
                 );
             }
+            SponsorFees { sponsor } => {
+                // The fees are already charged to the sponsor if this is the first operation.
+                ensure!(
+                    resource_controller.account == Some(sponsor),
+                    ExecutionError::MisplacedFeeSponsor(sponsor)
+                );
+            }
         }
 
         Ok(new_application)
@@ -626,6 +993,91 @@ where
         Ok(())
     }
 
+    /// Applies a validator weight update to the committee of its epoch, if this chain still
+    /// trusts it, and keeps the previous committee until the end of the grace window.
+    fn apply_validator_weight_update(
+        &mut self,
+        update: ValidatorWeightUpdate,
+        timestamp: Timestamp,
+    ) -> Result<(), ExecutionError> {
+        let ValidatorWeightUpdate {
+            epoch,
+            votes,
+            grace_deadline,
+        } = update;
+        if let Some(committee) = self.committees.get_mut().get_mut(&epoch) {
+            let updated = committee
+                .with_votes(&votes)
+                .ok_or(ExecutionError::InvalidValidatorWeightUpdate)?;
+            let previous = mem::replace(committee, updated);
+            let superseded = self.superseded_committees.get_mut();
+            superseded.retain(|_, (_, deadline)| *deadline >= timestamp);
+            if grace_deadline >= timestamp {
+                superseded.insert(epoch, (previous, grace_deadline));
+            }
+        }
+        let count = self.validator_weight_updates.get_mut();
+        *count = count.checked_add(1).ok_or(ArithmeticError::Overflow)?;
+        Ok(())
+    }
+
+    /// Applies the staged change of the application permissions, if it takes effect in a
+    /// block with the given height and timestamp.
+    pub fn activate_staged_application_permissions(
+        &mut self,
+        height: BlockHeight,
+        timestamp: Timestamp,
+    ) {
+        if !self
+            .staged_application_permissions
+            .get()
+            .as_ref()
+            .is_some_and(|staged| staged.activation.is_reached(height, timestamp))
+        {
+            return;
+        }
+        if let Some(staged) = self.staged_application_permissions.get_mut().take() {
+            self.application_permissions
+                .set(staged.application_permissions);
+        }
+    }
+
+    /// Checks that a validator address announcement is signed by a validator of the current
+    /// committee, and that it is newer than the validator's previous announcement.
+    async fn check_validator_address(
+        &self,
+        announcement: &SignedValidatorAddress,
+    ) -> Result<(), ExecutionError> {
+        let public_key = announcement.address.public_key;
+        let is_validator = self
+            .committees
+            .get()
+            .get(self.epoch.get())
+            .is_some_and(|committee| committee.validators().contains_key(&public_key));
+        ensure!(is_validator, ExecutionError::InvalidValidatorAddress);
+        announcement
+            .check()
+            .map_err(|_| ExecutionError::InvalidValidatorAddress)?;
+        if let Some(previous) = self.validator_addresses.get(&public_key).await? {
+            ensure!(
+                previous.address.version < announcement.address.version,
+                ExecutionError::InvalidValidatorAddress
+            );
+        }
+        Ok(())
+    }
+
+    /// Returns the committee of `epoch` from before its last validator weight update, if a
+    /// validator whose clock shows `local_time` still accepts certificates signed according
+    /// to it.
+    ///
+    /// This must not be called with the timestamp of the certified block: proposers choose it,
+    /// so a backdated block would keep the previous weights valid forever.
+    pub fn superseded_committee(&self, epoch: Epoch, local_time: Timestamp) -> Option<&Committee> {
+        let (committee, deadline) = self.superseded_committees.get().get(&epoch)?;
+        (local_time <= *deadline).then_some(committee)
+    }
+
     pub async fn transfer(
         &mut self,
         authenticated_signer: Option<AccountOwner>,
@@ -679,11 +1131,19 @@ where
         recipient: Recipient,
         amount: Amount,
     ) -> Result<OutgoingMessage, ExecutionError> {
-        ensure!(
-            authenticated_signer == Some(source)
-                || authenticated_application_id.map(AccountOwner::from) == Some(source),
-            ExecutionError::UnauthenticatedClaimOwner
-        );
+        match source {
+            AccountOwner::Chain(owner_chain_id) => ensure!(
+                owner_chain_id == self.context().extra().chain_id()
+                    && authenticated_signer
+                        .is_some_and(|signer| self.ownership.get().verify_owner(&signer)),
+                ExecutionError::UnauthenticatedClaimOwner
+            ),
+            _ => ensure!(
+                authenticated_signer == Some(source)
+                    || authenticated_application_id.map(AccountOwner::from) == Some(source),
+                ExecutionError::UnauthenticatedClaimOwner
+            ),
+        }
         ensure!(amount > Amount::ZERO, ExecutionError::IncorrectClaimAmount);
 
         let message = SystemMessage::Withdraw {
@@ -697,6 +1157,40 @@ where
         )
     }
 
+    /// Transfers the fee of an application's operation from the authenticated signer's
+    /// account to the application's account on this chain.
+    pub async fn charge_operation_fee(
+        &mut self,
+        authenticated_signer: Option<AccountOwner>,
+        application_id: ApplicationId,
+        fee: Amount,
+    ) -> Result<(), ExecutionError> {
+        if fee == Amount::ZERO {
+            return Ok(());
+        }
+        let signer = authenticated_signer.ok_or(ExecutionError::UnauthenticatedApplicationFee(
+            application_id,
+        ))?;
+        self.debit(&signer, fee)
+            .await
+            .map_err(|error| match error {
+                ExecutionError::InsufficientBalance { balance, .. } => {
+                    ExecutionError::InsufficientBalanceForApplicationFee {
+                        application_id,
+                        fee,
+                        balance,
+                    }
+                }
+                error => error,
+            })?;
+        let balance = self
+            .balances
+            .get_mut_or_default(&AccountOwner::from(application_id))
+            .await?;
+        *balance = balance.saturating_add(fee);
+        Ok(())
+    }
+
     /// Debits an [`Amount`] of tokens from an account's balance.
     async fn debit(
         &mut self,
@@ -733,6 +1227,7 @@ where
         &mut self,
         context: MessageContext,
         message: SystemMessage,
+        policy: &ResourceControlPolicy,
     ) -> Result<Vec<OutgoingMessage>, ExecutionError> {
         let mut outcome = Vec::new();
         use SystemMessage::*;
@@ -774,6 +1269,36 @@ where
             }
             // This message is only a placeholder: Its ID is part of the application ID.
             ApplicationCreated => {}
+            SubscribeToChannel {
+                application_id,
+                channel,
+            } => {
+                let subscriber = context.message_id.chain_id;
+                let subscribers = self
+                    .channel_subscribers
+                    .load_entry_mut(&(application_id, channel))
+                    .await?;
+                if !subscribers.contains(&subscriber).await? {
+                    let maximum = policy.maximum_channel_subscribers;
+                    ensure!(
+                        (subscribers.count().await? as u64) < maximum,
+                        ExecutionError::MaximumChannelSubscribersExceeded(maximum)
+                    );
+                    subscribers.insert(&subscriber)?;
+                }
+            }
+            UnsubscribeFromChannel {
+                application_id,
+                channel,
+            } => {
+                let subscriber = context.message_id.chain_id;
+                let key = (application_id, channel);
+                let subscribers = self.channel_subscribers.load_entry_mut(&key).await?;
+                subscribers.remove(&subscriber)?;
+                if subscribers.count().await? == 0 {
+                    self.channel_subscribers.remove_entry(&key)?;
+                }
+            }
         }
         Ok(outcome)
     }
@@ -832,6 +1357,7 @@ where
         Ok(QueryOutcome {
             response,
             operations: vec![],
+            operation_metadata: vec![],
         })
     }
 
@@ -918,6 +1444,59 @@ where
         })
     }
 
+    /// Makes the application use the code of `module_id` on this chain from now on.
+    pub async fn upgrade_application(
+        &mut self,
+        context: OperationContext,
+        application_id: ApplicationId,
+        module_id: ModuleId,
+        txn_tracker: &mut TransactionTracker,
+    ) -> Result<(), ExecutionError> {
+        ensure!(
+            context
+                .authenticated_signer
+                .is_some_and(|signer| self.ownership.get().verify_owner(&signer)),
+            ExecutionError::UnauthenticatedApplicationUpgrade
+        );
+        let description = self
+            .describe_application(application_id, txn_tracker)
+            .await?;
+        ensure!(
+            module_id.vm_runtime == description.module_id.vm_runtime,
+            ExecutionError::InvalidApplicationUpgrade(Box::new(application_id))
+        );
+        let blob_ids = self.check_bytecode_blobs(&module_id, txn_tracker).await?;
+        for blob_id in blob_ids {
+            self.blob_used(txn_tracker, blob_id).await?;
+        }
+        if module_id == description.module_id {
+            self.upgraded_applications.remove(&application_id)?;
+        } else {
+            self.upgraded_applications
+                .insert(&application_id, module_id)?;
+        }
+        Ok(())
+    }
+
+    /// Returns the description from which the code of an application is loaded on this
+    /// chain: its own `description`, with the module replaced if the application was
+    /// upgraded here.
+    pub async fn code_description(
+        &self,
+        application_id: &ApplicationId,
+        description: ApplicationDescription,
+    ) -> Result<ApplicationDescription, ExecutionError> {
+        Ok(
+            match self.upgraded_applications.get(application_id).await? {
+                Some(module_id) => ApplicationDescription {
+                    module_id,
+                    ..description
+                },
+                None => description,
+            },
+        )
+    }
+
     async fn check_required_applications(
         &mut self,
         application_description: &ApplicationDescription,
@@ -138,6 +138,20 @@ type ProcessStreamHandler = Box<
         + Send
         + Sync,
 >;
+type OnChainCloseHandler =
+    Box<dyn FnOnce(&mut ContractSyncRuntimeHandle) -> Result<(), ExecutionError> + Send + Sync>;
+type OnUpgradeHandler =
+    Box<dyn FnOnce(&mut ContractSyncRuntimeHandle) -> Result<(), ExecutionError> + Send + Sync>;
+type AuthorizeProposalHandler = Box<
+    dyn FnOnce(&mut ContractSyncRuntimeHandle, Vec<u8>) -> Result<bool, ExecutionError>
+        + Send
+        + Sync,
+>;
+type ExecuteViewHandler = Box<
+    dyn FnOnce(&mut ContractSyncRuntimeHandle, Vec<u8>) -> Result<Vec<u8>, ExecutionError>
+        + Send
+        + Sync,
+>;
 type FinalizeHandler =
     Box<dyn FnOnce(&mut ContractSyncRuntimeHandle) -> Result<(), ExecutionError> + Send + Sync>;
 type HandleQueryHandler = Box<
@@ -157,6 +171,14 @@ pub enum ExpectedCall {
     ExecuteMessage(#[debug(skip)] ExecuteMessageHandler),
     /// An expected call to [`UserContract::process_streams`].
     ProcessStreams(#[debug(skip)] ProcessStreamHandler),
+    /// An expected call to [`UserContract::on_chain_close`].
+    OnChainClose(#[debug(skip)] OnChainCloseHandler),
+    /// An expected call to [`UserContract::on_upgrade`].
+    OnUpgrade(#[debug(skip)] OnUpgradeHandler),
+    /// An expected call to [`UserContract::authorize_proposal`].
+    AuthorizeProposal(#[debug(skip)] AuthorizeProposalHandler),
+    /// An expected call to [`UserContract::execute_view`].
+    ExecuteView(#[debug(skip)] ExecuteViewHandler),
     /// An expected call to [`UserContract::finalize`].
     Finalize(#[debug(skip)] FinalizeHandler),
     /// An expected call to [`UserService::handle_query`].
@@ -170,6 +192,10 @@ impl Display for ExpectedCall {
             ExpectedCall::ExecuteOperation(_) => "execute_operation",
             ExpectedCall::ExecuteMessage(_) => "execute_message",
             ExpectedCall::ProcessStreams(_) => "process_streams",
+            ExpectedCall::OnChainClose(_) => "on_chain_close",
+            ExpectedCall::OnUpgrade(_) => "on_upgrade",
+            ExpectedCall::AuthorizeProposal(_) => "authorize_proposal",
+            ExpectedCall::ExecuteView(_) => "execute_view",
             ExpectedCall::Finalize(_) => "finalize",
             ExpectedCall::HandleQuery(_) => "handle_query",
         };
@@ -226,6 +252,52 @@ impl ExpectedCall {
         ExpectedCall::ProcessStreams(Box::new(handler))
     }
 
+    /// Creates an [`ExpectedCall`] to the [`MockApplicationInstance`]'s
+    /// [`UserContract::on_chain_close`] implementation, which is handled by the provided
+    /// `handler`.
+    pub fn on_chain_close(
+        handler: impl FnOnce(&mut ContractSyncRuntimeHandle) -> Result<(), ExecutionError>
+            + Send
+            + Sync
+            + 'static,
+    ) -> Self {
+        ExpectedCall::OnChainClose(Box::new(handler))
+    }
+
+    /// Creates an [`ExpectedCall`] to the [`MockApplicationInstance`]'s
+    /// [`UserContract::on_upgrade`] implementation, which is handled by the provided `handler`.
+    pub fn on_upgrade(
+        handler: impl FnOnce(&mut ContractSyncRuntimeHandle) -> Result<(), ExecutionError>
+            + Send
+            + Sync
+            + 'static,
+    ) -> Self {
+        ExpectedCall::OnUpgrade(Box::new(handler))
+    }
+
+    /// Creates an [`ExpectedCall`] to the [`MockApplicationInstance`]'s
+    /// [`UserContract::authorize_proposal`] implementation, which is handled by the provided
+    /// `handler`.
+    pub fn authorize_proposal(
+        handler: impl FnOnce(&mut ContractSyncRuntimeHandle, Vec<u8>) -> Result<bool, ExecutionError>
+            + Send
+            + Sync
+            + 'static,
+    ) -> Self {
+        ExpectedCall::AuthorizeProposal(Box::new(handler))
+    }
+
+    /// Creates an [`ExpectedCall`] to the [`MockApplicationInstance`]'s
+    /// [`UserContract::execute_view`] implementation, which is handled by the provided `handler`.
+    pub fn execute_view(
+        handler: impl FnOnce(&mut ContractSyncRuntimeHandle, Vec<u8>) -> Result<Vec<u8>, ExecutionError>
+            + Send
+            + Sync
+            + 'static,
+    ) -> Self {
+        ExpectedCall::ExecuteView(Box::new(handler))
+    }
+
     /// Creates an [`ExpectedCall`] to the [`MockApplicationInstance`]'s [`UserContract::finalize`]
     /// implementation, which is handled by the provided `handler`.
     pub fn finalize(
@@ -324,6 +396,47 @@ impl UserContract for MockApplicationInstance<ContractSyncRuntimeHandle> {
         }
     }
 
+    fn on_chain_close(&mut self) -> Result<(), ExecutionError> {
+        match self.next_expected_call() {
+            Some(ExpectedCall::OnChainClose(handler)) => handler(&mut self.runtime),
+            Some(unexpected_call) => panic!(
+                "Expected a call to `on_chain_close`, got a call to `{unexpected_call}` instead."
+            ),
+            None => panic!("Unexpected call to `on_chain_close`"),
+        }
+    }
+
+    fn on_upgrade(&mut self) -> Result<(), ExecutionError> {
+        match self.next_expected_call() {
+            Some(ExpectedCall::OnUpgrade(handler)) => handler(&mut self.runtime),
+            Some(unexpected_call) => panic!(
+                "Expected a call to `on_upgrade`, got a call to `{unexpected_call}` instead."
+            ),
+            None => panic!("Unexpected call to `on_upgrade`"),
+        }
+    }
+
+    fn authorize_proposal(&mut self, payload: Vec<u8>) -> Result<bool, ExecutionError> {
+        match self.next_expected_call() {
+            Some(ExpectedCall::AuthorizeProposal(handler)) => handler(&mut self.runtime, payload),
+            Some(unexpected_call) => panic!(
+                "Expected a call to `authorize_proposal`, got a call to `{unexpected_call}` \
+                instead."
+            ),
+            None => panic!("Unexpected call to `authorize_proposal`"),
+        }
+    }
+
+    fn execute_view(&mut self, argument: Vec<u8>) -> Result<Vec<u8>, ExecutionError> {
+        match self.next_expected_call() {
+            Some(ExpectedCall::ExecuteView(handler)) => handler(&mut self.runtime, argument),
+            Some(unexpected_call) => panic!(
+                "Expected a call to `execute_view`, got a call to `{unexpected_call}` instead."
+            ),
+            None => panic!("Unexpected call to `execute_view`"),
+        }
+    }
+
     fn finalize(&mut self) -> Result<(), ExecutionError> {
         match self.next_expected_call() {
             Some(ExpectedCall::Finalize(handler)) => handler(&mut self.runtime),
@@ -13,7 +13,7 @@ use linera_base::{
     identifiers::{ApplicationId, BlobId, ChainId, StreamId},
 };
 
-use crate::{ExecutionError, OutgoingMessage};
+use crate::{ExecutionError, OutgoingMessage, PublishedMessage};
 
 type AppStreamUpdates = BTreeMap<(ChainId, StreamId), (u32, u32)>;
 
@@ -27,6 +27,10 @@ pub struct TransactionTracker {
     oracle_responses: Vec<OracleResponse>,
     #[debug(skip_if = Vec::is_empty)]
     outgoing_messages: Vec<OutgoingMessage>,
+    /// Messages published to channels, which the chain sends to the subscribers once the
+    /// transaction is complete.
+    #[debug(skip_if = Vec::is_empty)]
+    published_messages: Vec<PublishedMessage>,
     /// The current local time.
     local_time: Timestamp,
     /// The index of the current transaction in the block.
@@ -47,6 +51,30 @@ pub struct TransactionTracker {
     streams_to_process: BTreeMap<ApplicationId, AppStreamUpdates>,
     /// Published blobs this transaction refers to by [`BlobId`].
     blobs_published: BTreeSet<BlobId>,
+    /// The number of bytes of deterministic entropy drawn by the applications.
+    entropy_drawn: u64,
+    /// If set, the transaction may only use the state of these applications.
+    #[debug(skip_if = Option::is_none)]
+    application_filter: Option<BTreeSet<ApplicationId>>,
+}
+
+/// The parts of a [`TransactionTracker`] that a speculative call may change, recorded before
+/// the call so that its effects can be undone.
+///
+/// Oracle responses are not part of it: the responses obtained during the call stay recorded,
+/// so that validators replaying the block make the same speculative call.
+#[derive(Debug)]
+pub(crate) struct TransactionTrackerSnapshot {
+    oracle_response_count: usize,
+    outgoing_message_count: usize,
+    published_message_count: usize,
+    event_count: usize,
+    next_application_index: u32,
+    next_chain_index: u32,
+    blobs: BTreeMap<BlobId, Blob>,
+    streams_to_process: BTreeMap<ApplicationId, AppStreamUpdates>,
+    blobs_published: BTreeSet<BlobId>,
+    entropy_drawn: u64,
 }
 
 /// The [`TransactionTracker`] contents after a transaction has finished.
@@ -56,6 +84,9 @@ pub struct TransactionOutcome {
     pub oracle_responses: Vec<OracleResponse>,
     #[debug(skip_if = Vec::is_empty)]
     pub outgoing_messages: Vec<OutgoingMessage>,
+    /// Messages published to channels, not yet sent to the subscribers.
+    #[debug(skip_if = Vec::is_empty)]
+    pub published_messages: Vec<PublishedMessage>,
     pub next_application_index: u32,
     pub next_chain_index: u32,
     /// Events recorded by contracts' `emit` calls.
@@ -91,6 +122,18 @@ impl TransactionTracker {
         self
     }
 
+    /// Restricts the transaction to the state of the given applications: it fails if it runs
+    /// another application, or uses the state of another application or of the system.
+    pub fn with_application_filter(mut self, applications: BTreeSet<ApplicationId>) -> Self {
+        self.application_filter = Some(applications);
+        self
+    }
+
+    /// Returns the applications the transaction is restricted to, if any.
+    pub fn application_filter(&self) -> Option<&BTreeSet<ApplicationId>> {
+        self.application_filter.as_ref()
+    }
+
     pub fn local_time(&self) -> Timestamp {
         self.local_time
     }
@@ -115,6 +158,21 @@ impl TransactionTracker {
         index
     }
 
+    /// Reserves the next `num_bytes` bytes of the transaction's entropy and returns the
+    /// offset of the first one. Fails if more than `maximum` bytes would have been drawn.
+    pub fn draw_entropy(&mut self, num_bytes: u64, maximum: u64) -> Result<u64, ExecutionError> {
+        let offset = self.entropy_drawn;
+        let end = offset
+            .checked_add(num_bytes)
+            .ok_or(ArithmeticError::Overflow)?;
+        ensure!(
+            end <= maximum,
+            ExecutionError::MaximumEntropyExceeded(maximum)
+        );
+        self.entropy_drawn = end;
+        Ok(offset)
+    }
+
     pub fn add_outgoing_message(
         &mut self,
         message: OutgoingMessage,
@@ -133,6 +191,12 @@ impl TransactionTracker {
         Ok(())
     }
 
+    /// Records a message published to a channel. It is sent to the subscribers of the
+    /// channel when the chain adds the outcome of the transaction to the block.
+    pub fn add_published_message(&mut self, message: PublishedMessage) {
+        self.published_messages.push(message);
+    }
+
     pub fn add_event(&mut self, stream_id: StreamId, index: u32, value: Vec<u8>) {
         self.events.push(Event {
             stream_id,
@@ -255,11 +319,61 @@ impl TransactionTracker {
         Ok(Some(response))
     }
 
+    /// Records the parts of the tracker that a speculative call may change.
+    pub(crate) fn snapshot(&self) -> TransactionTrackerSnapshot {
+        TransactionTrackerSnapshot {
+            oracle_response_count: self.oracle_responses.len(),
+            outgoing_message_count: self.outgoing_messages.len(),
+            published_message_count: self.published_messages.len(),
+            event_count: self.events.len(),
+            next_application_index: self.next_application_index,
+            next_chain_index: self.next_chain_index,
+            blobs: self.blobs.clone(),
+            streams_to_process: self.streams_to_process.clone(),
+            blobs_published: self.blobs_published.clone(),
+            entropy_drawn: self.entropy_drawn,
+        }
+    }
+
+    /// Undoes the changes made since `snapshot` was taken, except for the oracle responses.
+    /// Returns the blobs that were recorded as used in the meantime.
+    pub(crate) fn restore(&mut self, snapshot: TransactionTrackerSnapshot) -> Vec<BlobId> {
+        let TransactionTrackerSnapshot {
+            oracle_response_count,
+            outgoing_message_count,
+            published_message_count,
+            event_count,
+            next_application_index,
+            next_chain_index,
+            blobs,
+            streams_to_process,
+            blobs_published,
+            entropy_drawn,
+        } = snapshot;
+        self.outgoing_messages.truncate(outgoing_message_count);
+        self.published_messages.truncate(published_message_count);
+        self.events.truncate(event_count);
+        self.next_application_index = next_application_index;
+        self.next_chain_index = next_chain_index;
+        self.blobs = blobs;
+        self.streams_to_process = streams_to_process;
+        self.blobs_published = blobs_published;
+        self.entropy_drawn = entropy_drawn;
+        self.oracle_responses[oracle_response_count..]
+            .iter()
+            .filter_map(|response| match response {
+                OracleResponse::Blob(blob_id) => Some(*blob_id),
+                _ => None,
+            })
+            .collect()
+    }
+
     pub fn into_outcome(self) -> Result<TransactionOutcome, ExecutionError> {
         let TransactionTracker {
             replaying_oracle_responses,
             oracle_responses,
             outgoing_messages,
+            published_messages,
             local_time: _,
             transaction_index: _,
             next_application_index,
@@ -269,6 +383,8 @@ impl TransactionTracker {
             operation_result,
             streams_to_process,
             blobs_published,
+            entropy_drawn: _,
+            application_filter: _,
         } = self;
         ensure!(
             streams_to_process.is_empty(),
@@ -282,6 +398,7 @@ impl TransactionTracker {
         }
         Ok(TransactionOutcome {
             outgoing_messages,
+            published_messages,
             oracle_responses,
             next_application_index,
             next_chain_index,
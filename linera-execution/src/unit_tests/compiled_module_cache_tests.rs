@@ -0,0 +1,140 @@
+// Copyright (c) Zefchain Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+use std::cell::Cell;
+
+use linera_base::data_types::Bytecode;
+
+use super::*;
+
+/// Loads the "module" compiled from `bytecode` through `cache`, counting the compilations.
+fn load(
+    cache: &CompiledModuleCache,
+    engine_key: u32,
+    bytecode: &Bytecode,
+    compilations: &Cell<usize>,
+) -> Vec<u8> {
+    cache
+        .get_or_compile(
+            "test",
+            engine_key,
+            bytecode,
+            || {
+                compilations.set(compilations.get() + 1);
+                Ok(bytecode.as_ref().iter().rev().copied().collect())
+            },
+            |module| Ok(module.clone()),
+            |bytes| Ok(bytes.to_vec()),
+        )
+        .unwrap()
+}
+
+fn entries(directory: &Path) -> Vec<PathBuf> {
+    let mut entries = fs::read_dir(directory)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .collect::<Vec<_>>();
+    entries.sort();
+    entries
+}
+
+fn new_cache(directory: &Path, max_size: u64) -> CompiledModuleCache {
+    CompiledModuleCache::new(CompiledModuleCacheConfig {
+        directory: directory.to_path_buf(),
+        max_size,
+    })
+    .unwrap()
+}
+
+#[test]
+fn test_compiled_modules_are_reused_after_a_restart() {
+    let directory = tempfile::tempdir().unwrap();
+    let bytecode = Bytecode::new(vec![1, 2, 3]);
+    let compilations = Cell::new(0);
+
+    let cache = new_cache(directory.path(), u64::MAX);
+    assert_eq!(load(&cache, 0, &bytecode, &compilations), vec![3, 2, 1]);
+    assert_eq!(compilations.get(), 1);
+
+    let cache = new_cache(directory.path(), u64::MAX);
+    assert_eq!(load(&cache, 0, &bytecode, &compilations), vec![3, 2, 1]);
+    assert_eq!(compilations.get(), 1);
+    assert_eq!(entries(directory.path()).len(), 1);
+}
+
+#[test]
+fn test_corrupted_entries_are_compiled_again() {
+    let directory = tempfile::tempdir().unwrap();
+    let bytecode = Bytecode::new(vec![1, 2, 3]);
+    let compilations = Cell::new(0);
+
+    let cache = new_cache(directory.path(), u64::MAX);
+    load(&cache, 0, &bytecode, &compilations);
+    let [path] = &entries(directory.path())[..] else {
+        panic!("Expected a single cache entry");
+    };
+    let mut contents = fs::read(path).unwrap();
+    *contents.last_mut().unwrap() ^= 1;
+    fs::write(path, contents).unwrap();
+
+    assert_eq!(load(&cache, 0, &bytecode, &compilations), vec![3, 2, 1]);
+    assert_eq!(compilations.get(), 2);
+    assert_eq!(load(&cache, 0, &bytecode, &compilations), vec![3, 2, 1]);
+    assert_eq!(compilations.get(), 2);
+}
+
+#[test]
+fn test_entries_of_other_engines_are_removed() {
+    let directory = tempfile::tempdir().unwrap();
+    let bytecode = Bytecode::new(vec![1, 2, 3]);
+    let compilations = Cell::new(0);
+
+    let cache = new_cache(directory.path(), u64::MAX);
+    load(&cache, 0, &bytecode, &compilations);
+    let old_entries = entries(directory.path());
+    load(&cache, 1, &bytecode, &compilations);
+    assert_eq!(compilations.get(), 2);
+    let new_entries = entries(directory.path());
+    assert_eq!(new_entries.len(), 1);
+    assert_ne!(new_entries, old_entries);
+}
+
+#[test]
+fn test_least_recently_used_entries_are_evicted() {
+    let directory = tempfile::tempdir().unwrap();
+    let compilations = Cell::new(0);
+    let bytecodes = (0..3)
+        .map(|index| Bytecode::new(vec![index; 100]))
+        .collect::<Vec<_>>();
+
+    // Each entry takes 132 bytes, so only two of them fit.
+    let cache = new_cache(directory.path(), 300);
+    load(&cache, 0, &bytecodes[0], &compilations);
+    load(&cache, 0, &bytecodes[1], &compilations);
+    load(&cache, 0, &bytecodes[0], &compilations);
+    load(&cache, 0, &bytecodes[2], &compilations);
+    assert_eq!(compilations.get(), 3);
+    assert_eq!(entries(directory.path()).len(), 2);
+
+    load(&cache, 0, &bytecodes[0], &compilations);
+    assert_eq!(compilations.get(), 3);
+    load(&cache, 0, &bytecodes[1], &compilations);
+    assert_eq!(compilations.get(), 4);
+}
+
+#[test]
+fn test_entries_are_named_after_the_sha256_of_the_engine_key() {
+    let directory = tempfile::tempdir().unwrap();
+    let bytecode = Bytecode::new(vec![1, 2, 3]);
+    let compilations = Cell::new(0);
+
+    let cache = new_cache(directory.path(), u64::MAX);
+    load(&cache, 0, &bytecode, &compilations);
+    let [path] = &entries(directory.path())[..] else {
+        panic!("Expected a single cache entry");
+    };
+    // The SHA-256 hash of the four bytes hashed for `0u32`.
+    let engine_key = "df3f619804a92fdb4057192dc43dd748ea778adc52bc498ce80524c014b81119";
+    let name = path.file_name().unwrap().to_string_lossy();
+    assert!(name.starts_with(&format!("test-{engine_key}-")), "{name}");
+}
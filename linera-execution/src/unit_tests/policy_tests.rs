@@ -0,0 +1,68 @@
+// Copyright (c) Zefchain Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+use assert_matches::assert_matches;
+use linera_base::data_types::{BlobContent, Bytecode};
+
+use super::*;
+
+/// The Wasm module header, followed by a type section with a single function type
+/// returning a value of the given type.
+fn module_returning(value_type: u8) -> Vec<u8> {
+    vec![
+        0x00, 0x61, 0x73, 0x6d, 0x01, 0x00, 0x00, 0x00, // header
+        0x01, 0x05, 0x01, 0x60, 0x00, 0x01, value_type, // type section
+    ]
+}
+
+fn contract_bytecode_blob(module: Vec<u8>) -> BlobContent {
+    BlobContent::new_contract_bytecode(Bytecode::new(module).compress())
+}
+
+#[test]
+fn test_wasm_floats_are_allowed_by_default() {
+    let policy = ResourceControlPolicy::default();
+    let content = contract_bytecode_blob(module_returning(0x7d));
+    assert!(policy.check_blob_size(&content).is_ok());
+}
+
+#[test]
+fn test_wasm_floats_can_be_forbidden() {
+    let policy = ResourceControlPolicy {
+        allow_wasm_floats: false,
+        ..ResourceControlPolicy::default()
+    };
+    let f32_content = contract_bytecode_blob(module_returning(0x7d));
+    assert_matches!(
+        policy.check_blob_size(&f32_content),
+        Err(ExecutionError::WasmFloatsNotAllowed)
+    );
+    let f64_content = contract_bytecode_blob(module_returning(0x7c));
+    assert_matches!(
+        policy.check_blob_size(&f64_content),
+        Err(ExecutionError::WasmFloatsNotAllowed)
+    );
+    let i32_content = contract_bytecode_blob(module_returning(0x7f));
+    assert!(policy.check_blob_size(&i32_content).is_ok());
+    let invalid_content = contract_bytecode_blob(vec![0x00, 0x61]);
+    assert_matches!(
+        policy.check_blob_size(&invalid_content),
+        Err(ExecutionError::InvalidWasmBytecode)
+    );
+}
+
+#[test]
+fn test_wasm_floats_can_be_allowed_per_contract() {
+    let f32_content = contract_bytecode_blob(module_returning(0x7d));
+    let f64_content = contract_bytecode_blob(module_returning(0x7c));
+    let policy = ResourceControlPolicy {
+        allow_wasm_floats: false,
+        wasm_float_contracts: BTreeSet::from([CryptoHash::new(&f32_content)]),
+        ..ResourceControlPolicy::default()
+    };
+    assert!(policy.check_blob_size(&f32_content).is_ok());
+    assert_matches!(
+        policy.check_blob_size(&f64_content),
+        Err(ExecutionError::WasmFloatsNotAllowed)
+    );
+}
@@ -19,7 +19,8 @@ use crate::{
     execution_state_actor::ExecutionRequest,
     runtime::{LoadedApplication, ResourceController, SyncRuntime},
     test_utils::{create_dummy_user_application_description, dummy_chain_description},
-    ContractRuntime, TransactionTracker, UserContractInstance,
+    ContractRuntime, ExecutionError, ResourceControlPolicy, TransactionTracker,
+    UserContractInstance,
 };
 
 /// Test if dropping [`SyncRuntime`] does not leak memory.
@@ -146,6 +147,36 @@ async fn test_write_batch() {
     );
 }
 
+/// Test drawing deterministic entropy.
+///
+/// Ensure that the bytes drawn in several calls are the same as those drawn at once, and that
+/// the policy limits how many bytes a transaction can draw.
+#[test_log::test(tokio::test)]
+async fn test_draw_entropy() {
+    let (runtime, _execution_state_receiver) = create_contract_runtime();
+    let mut runtime = SyncRuntimeHandle::from(runtime);
+    let all_bytes = runtime.draw_entropy(70).expect("Failed to draw entropy");
+    assert_eq!(all_bytes.len(), 70);
+
+    let (mut runtime, _execution_state_receiver) = create_contract_runtime();
+    let policy = ResourceControlPolicy {
+        maximum_entropy_per_transaction: 70,
+        ..ResourceControlPolicy::default()
+    };
+    runtime.resource_controller =
+        ResourceController::new(Arc::new(policy), Default::default(), Default::default());
+    let mut runtime = SyncRuntimeHandle::from(runtime);
+    let mut bytes = runtime.draw_entropy(5).expect("Failed to draw entropy");
+    bytes.extend(runtime.draw_entropy(40).expect("Failed to draw entropy"));
+    bytes.extend(runtime.draw_entropy(25).expect("Failed to draw entropy"));
+    assert_eq!(bytes, all_bytes);
+
+    assert!(matches!(
+        runtime.draw_entropy(1),
+        Err(ExecutionError::MaximumEntropyExceeded(70))
+    ));
+}
+
 /// Creates a [`SyncRuntimeInternal`] instance for contracts, and returns it and the receiver
 /// endpoint for the requests the runtime sends to the [`ExecutionStateView`] actor.
 fn create_contract_runtime() -> (
@@ -201,6 +232,7 @@ fn create_dummy_application() -> ApplicationStatus {
         id,
         description,
         signer: None,
+        read_only: false,
     }
 }
 
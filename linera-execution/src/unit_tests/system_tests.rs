@@ -1,9 +1,13 @@
 // Copyright (c) Zefchain Labs, Inc.
 // SPDX-License-Identifier: Apache-2.0
 
-use linera_base::data_types::{Blob, BlockHeight, Bytecode};
+use assert_matches::assert_matches;
 #[cfg(with_testing)]
 use linera_base::vm::VmRuntime;
+use linera_base::{
+    crypto::{AccountPublicKey, ValidatorKeypair},
+    data_types::{Blob, BlockHeight, Bytecode},
+};
 use linera_views::context::MemoryContext;
 
 use super::*;
@@ -138,3 +142,300 @@ async fn empty_accounts_are_removed() -> anyhow::Result<()> {
 
     Ok(())
 }
+
+/// Tests that channel subscriptions are tracked per sender chain and removed when empty.
+#[tokio::test]
+async fn channel_subscriptions_are_tracked() -> anyhow::Result<()> {
+    let mut view = SystemExecutionState {
+        description: Some(dummy_chain_description(0)),
+        ..SystemExecutionState::default()
+    }
+    .into_view()
+    .await;
+    let subscriber = dummy_chain_description(1).id();
+    let context = crate::test_utils::create_dummy_message_context(subscriber, None);
+    let application_id = ApplicationId::new(CryptoHash::test_hash("application"));
+    let channel = ChannelName::from(b"news".to_vec());
+    let key = (application_id, channel.clone());
+
+    let policy = ResourceControlPolicy::default();
+
+    let message = SystemMessage::SubscribeToChannel {
+        application_id,
+        channel: channel.clone(),
+    };
+    let outcome = view
+        .system
+        .execute_message(context, message, &policy)
+        .await?;
+    assert!(outcome.is_empty());
+    let subscribers = view
+        .system
+        .channel_subscribers
+        .try_load_entry(&key)
+        .await?
+        .expect("the channel should have subscribers");
+    assert_eq!(subscribers.indices().await?, [subscriber]);
+    drop(subscribers);
+
+    let message = SystemMessage::UnsubscribeFromChannel {
+        application_id,
+        channel,
+    };
+    view.system
+        .execute_message(context, message, &policy)
+        .await?;
+    assert!(view.system.channel_subscribers.indices().await?.is_empty());
+
+    Ok(())
+}
+
+/// Tests that a channel does not accept more subscribers than the policy allows, but that
+/// existing subscribers can subscribe again.
+#[tokio::test]
+async fn channel_subscribers_are_limited() -> anyhow::Result<()> {
+    let mut view = SystemExecutionState {
+        description: Some(dummy_chain_description(0)),
+        ..SystemExecutionState::default()
+    }
+    .into_view()
+    .await;
+    let application_id = ApplicationId::new(CryptoHash::test_hash("application"));
+    let channel = ChannelName::from(b"news".to_vec());
+    let policy = ResourceControlPolicy {
+        maximum_channel_subscribers: 1,
+        ..ResourceControlPolicy::default()
+    };
+    let subscribe = || SystemMessage::SubscribeToChannel {
+        application_id,
+        channel: channel.clone(),
+    };
+
+    let subscriber = dummy_chain_description(1).id();
+    let context = crate::test_utils::create_dummy_message_context(subscriber, None);
+    view.system
+        .execute_message(context, subscribe(), &policy)
+        .await?;
+    view.system
+        .execute_message(context, subscribe(), &policy)
+        .await?;
+
+    let other_subscriber = dummy_chain_description(2).id();
+    let context = crate::test_utils::create_dummy_message_context(other_subscriber, None);
+    let result = view
+        .system
+        .execute_message(context, subscribe(), &policy)
+        .await;
+    assert_matches!(
+        result,
+        Err(ExecutionError::MaximumChannelSubscribersExceeded(1))
+    );
+    let subscribers = view
+        .system
+        .channel_subscribers
+        .try_load_entry(&(application_id, channel))
+        .await?
+        .expect("the channel should have subscribers");
+    assert_eq!(subscribers.indices().await?, [subscriber]);
+
+    Ok(())
+}
+
+/// Tests that a validator weight update changes the current committee and keeps the previous
+/// one during the grace window.
+#[tokio::test]
+async fn validator_weights_are_updated() -> anyhow::Result<()> {
+    let description = dummy_chain_description(0);
+    let chain_id = description.id();
+    let validator = ValidatorPublicKey::test_key(0);
+    let committee = crate::test_utils::dummy_committee();
+    let mut view = SystemExecutionState {
+        description: Some(description),
+        epoch: Epoch::ZERO,
+        admin_id: Some(chain_id),
+        committees: BTreeMap::from([(Epoch::ZERO, committee.clone())]),
+        ..SystemExecutionState::default()
+    }
+    .into_view()
+    .await;
+    let context = OperationContext {
+        chain_id,
+        authenticated_signer: None,
+        authenticated_caller_id: None,
+        height: BlockHeight::from(1),
+        round: Some(0),
+        timestamp: Timestamp::from(1_000),
+    };
+    let operation = SystemOperation::Admin(AdminOperation::UpdateValidatorWeights {
+        epoch: Epoch::ZERO,
+        votes: BTreeMap::from([(validator, 7)]),
+        grace_period: TimeDelta::from_micros(500),
+    });
+    let mut txn_tracker = TransactionTracker::default();
+    view.system
+        .execute_operation(
+            context,
+            operation,
+            &mut txn_tracker,
+            &mut ResourceController::default(),
+        )
+        .await?;
+
+    let updated = &view.system.committees.get()[&Epoch::ZERO];
+    assert_eq!(updated.weight(&validator), 7);
+    assert_eq!(*view.system.validator_weight_updates.get(), 1);
+    assert_eq!(
+        view.system
+            .superseded_committee(Epoch::ZERO, Timestamp::from(1_500)),
+        Some(&committee)
+    );
+    assert_eq!(
+        view.system
+            .superseded_committee(Epoch::ZERO, Timestamp::from(1_501)),
+        None
+    );
+    let events = txn_tracker.into_outcome()?.events;
+    assert_eq!(events.len(), 1);
+    assert_eq!(
+        events[0].stream_id,
+        StreamId::system(VALIDATOR_WEIGHTS_STREAM_NAME)
+    );
+
+    Ok(())
+}
+
+/// Tests that validators of the current committee can announce new network addresses, and
+/// that announcements that are forged or replayed are rejected.
+#[tokio::test]
+async fn validator_addresses_are_announced() -> anyhow::Result<()> {
+    let description = dummy_chain_description(0);
+    let chain_id = description.id();
+    let validator = ValidatorKeypair::generate();
+    let outsider = ValidatorKeypair::generate();
+    let committee =
+        Committee::make_simple(vec![(validator.public_key, AccountPublicKey::test_key(0))]);
+    let mut view = SystemExecutionState {
+        description: Some(description),
+        epoch: Epoch::ZERO,
+        admin_id: Some(chain_id),
+        committees: BTreeMap::from([(Epoch::ZERO, committee)]),
+        ..SystemExecutionState::default()
+    }
+    .into_view()
+    .await;
+    let context = OperationContext {
+        chain_id,
+        authenticated_signer: None,
+        authenticated_caller_id: None,
+        height: BlockHeight::from(1),
+        round: Some(0),
+        timestamp: Timestamp::from(1_000),
+    };
+    let address = |public_key, version| ValidatorAddress {
+        public_key,
+        network_address: format!("grpc:validator-{version}.example.com:443"),
+        version,
+    };
+
+    let announcement =
+        SignedValidatorAddress::new(address(validator.public_key, 1), &validator.secret_key);
+    let operation = SystemOperation::Admin(AdminOperation::AnnounceValidatorAddress(
+        announcement.clone(),
+    ));
+    let mut txn_tracker = TransactionTracker::default();
+    view.system
+        .execute_operation(
+            context,
+            operation.clone(),
+            &mut txn_tracker,
+            &mut ResourceController::default(),
+        )
+        .await?;
+    assert_eq!(
+        view.system
+            .validator_addresses
+            .get(&validator.public_key)
+            .await?,
+        Some(announcement)
+    );
+    let events = txn_tracker.into_outcome()?.events;
+    assert_eq!(events.len(), 1);
+    assert_eq!(
+        events[0].stream_id,
+        StreamId::system(VALIDATOR_ADDRESSES_STREAM_NAME)
+    );
+
+    let forged =
+        SignedValidatorAddress::new(address(validator.public_key, 2), &outsider.secret_key);
+    let not_a_validator =
+        SignedValidatorAddress::new(address(outsider.public_key, 2), &outsider.secret_key);
+    for operation in [
+        operation,
+        SystemOperation::Admin(AdminOperation::AnnounceValidatorAddress(forged)),
+        SystemOperation::Admin(AdminOperation::AnnounceValidatorAddress(not_a_validator)),
+    ] {
+        let result = view
+            .system
+            .execute_operation(
+                context,
+                operation,
+                &mut TransactionTracker::default(),
+                &mut ResourceController::default(),
+            )
+            .await;
+        assert_matches!(result, Err(ExecutionError::InvalidValidatorAddress));
+    }
+    assert_eq!(*view.system.validator_address_count.get(), 1);
+
+    Ok(())
+}
+
+/// Tests that chain owners can set and remove the configuration of an application, and that
+/// other signers can't.
+#[tokio::test]
+async fn application_config_is_set_by_owners() -> anyhow::Result<()> {
+    let owner = AccountOwner::from(CryptoHash::test_hash("owner"));
+    let outsider = AccountOwner::from(CryptoHash::test_hash("outsider"));
+    let (mut view, mut context) = new_view_and_context().await;
+    view.system.ownership.set(ChainOwnership::single(owner));
+    let application_id = ApplicationId::new(CryptoHash::test_hash("application"));
+    let set_config = |config: Option<&[u8]>| SystemOperation::SetApplicationConfig {
+        application_id,
+        config: config.map(<[u8]>::to_vec),
+    };
+
+    for signer in [None, Some(outsider)] {
+        context.authenticated_signer = signer;
+        let result = view
+            .system
+            .execute_operation(
+                context,
+                set_config(Some(b"{}")),
+                &mut TransactionTracker::default(),
+                &mut ResourceController::default(),
+            )
+            .await;
+        assert_matches!(
+            result,
+            Err(ExecutionError::UnauthenticatedApplicationConfigOwner)
+        );
+    }
+
+    context.authenticated_signer = Some(owner);
+    for config in [Some(&b"{\"fee\":1}"[..]), None] {
+        view.system
+            .execute_operation(
+                context,
+                set_config(config),
+                &mut TransactionTracker::default(),
+                &mut ResourceController::default(),
+            )
+            .await?;
+        assert_eq!(
+            view.system.application_configs.get(&application_id).await?,
+            config.map(<[u8]>::to_vec)
+        );
+    }
+
+    Ok(())
+}
@@ -0,0 +1,172 @@
+// Copyright (c) Zefchain Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! Detection of the version of the Wasm API that an application was built against.
+//!
+//! The SDK declares the version in a custom section of the Wasm module. Modules without that
+//! section were built before the API was versioned, and are assumed to use
+//! [`WasmApiVersion::UNVERSIONED`].
+
+use linera_base::{data_types::Bytecode, vm::WasmApiVersion};
+
+use super::WasmExecutionError;
+use crate::ExecutionError;
+
+/// The oldest version of the API still supported, through shims.
+const OLDEST_SUPPORTED: WasmApiVersion = WasmApiVersion::UNVERSIONED;
+
+/// The version that introduced the `on-chain-close` contract entrypoint.
+pub(crate) const ON_CHAIN_CLOSE_SINCE: WasmApiVersion = WasmApiVersion::new(1, 1);
+
+/// The version that introduced the `authorize-proposal` contract entrypoint.
+pub(crate) const AUTHORIZE_PROPOSAL_SINCE: WasmApiVersion = WasmApiVersion::new(1, 2);
+
+/// The version that introduced the `execute-view` contract entrypoint and the `try-view-call`
+/// runtime function.
+pub(crate) const VIEW_CALL_SINCE: WasmApiVersion = WasmApiVersion::new(1, 3);
+
+/// The version that introduced the `read-epoch` runtime function.
+pub(crate) const READ_EPOCH_SINCE: WasmApiVersion = WasmApiVersion::new(1, 4);
+
+/// The version that introduced the `draw-entropy` runtime function.
+pub(crate) const DRAW_ENTROPY_SINCE: WasmApiVersion = WasmApiVersion::new(1, 5);
+
+/// The version that introduced the `declare-reentrancy-policy` runtime function.
+pub(crate) const REENTRANCY_POLICY_SINCE: WasmApiVersion = WasmApiVersion::new(1, 6);
+
+/// The version that introduced the `try-call` runtime function.
+pub(crate) const TRY_CALL_SINCE: WasmApiVersion = WasmApiVersion::new(1, 7);
+
+/// The version that introduced the `read-application-config` runtime function.
+pub(crate) const READ_APPLICATION_CONFIG_SINCE: WasmApiVersion = WasmApiVersion::new(1, 8);
+
+/// The version that introduced the `on-upgrade` contract entrypoint.
+pub(crate) const ON_UPGRADE_SINCE: WasmApiVersion = WasmApiVersion::new(1, 9);
+
+/// The version that introduced the `query-application-at` runtime function.
+pub(crate) const QUERY_APPLICATION_AT_SINCE: WasmApiVersion = WasmApiVersion::new(1, 10);
+
+/// Returns the version of the API declared by the module in `bytecode`, checking that it is
+/// supported by this runtime.
+pub(crate) fn check_api_version(bytecode: &Bytecode) -> Result<WasmApiVersion, ExecutionError> {
+    let sections = custom_sections(&bytecode.bytes, WasmApiVersion::CUSTOM_SECTION)?;
+    let api_version = parse_api_version(sections)?;
+    if api_version.major != WasmApiVersion::CURRENT.major
+        || api_version > WasmApiVersion::CURRENT
+        || api_version < OLDEST_SUPPORTED
+    {
+        return Err(ExecutionError::IncompatibleApiVersion {
+            declared: api_version,
+            supported: WasmApiVersion::CURRENT,
+        });
+    }
+    Ok(api_version)
+}
+
+/// Parses the version of the API declared in the custom sections of a module.
+pub(crate) fn parse_api_version(
+    sections: impl IntoIterator<Item = impl AsRef<[u8]>>,
+) -> Result<WasmApiVersion, WasmExecutionError> {
+    let mut sections = sections.into_iter();
+    let Some(section) = sections.next() else {
+        return Ok(WasmApiVersion::UNVERSIONED);
+    };
+    if sections.next().is_some() {
+        return Err(WasmExecutionError::InvalidApiVersion);
+    }
+    WasmApiVersion::from_custom_section(section.as_ref())
+        .ok_or(WasmExecutionError::InvalidApiVersion)
+}
+
+/// Returns the contents of the custom sections with the given `name` in a Wasm module.
+///
+/// Only the section headers are read, so that this works for any module the runtimes accept.
+fn custom_sections<'a>(
+    mut bytes: &'a [u8],
+    name: &str,
+) -> Result<Vec<&'a [u8]>, WasmExecutionError> {
+    const HEADER: [u8; 8] = [0x00, 0x61, 0x73, 0x6d, 0x01, 0x00, 0x00, 0x00];
+    const CUSTOM_SECTION_ID: u8 = 0;
+
+    bytes = bytes
+        .strip_prefix(&HEADER)
+        .ok_or(WasmExecutionError::InvalidApiVersion)?;
+    let mut sections = Vec::new();
+    while let Some((&id, rest)) = bytes.split_first() {
+        let (size, rest) = read_leb128(rest)?;
+        if rest.len() < size {
+            return Err(WasmExecutionError::InvalidApiVersion);
+        }
+        let (contents, rest) = rest.split_at(size);
+        bytes = rest;
+        if id != CUSTOM_SECTION_ID {
+            continue;
+        }
+        let (name_length, contents) = read_leb128(contents)?;
+        if contents.len() < name_length {
+            return Err(WasmExecutionError::InvalidApiVersion);
+        }
+        let (section_name, payload) = contents.split_at(name_length);
+        if section_name == name.as_bytes() {
+            sections.push(payload);
+        }
+    }
+    Ok(sections)
+}
+
+/// Reads an unsigned 32-bit integer encoded in LEB128.
+fn read_leb128(bytes: &[u8]) -> Result<(usize, &[u8]), WasmExecutionError> {
+    let mut value = 0_u32;
+    for (index, &byte) in bytes.iter().enumerate().take(5) {
+        value |= u32::from(byte & 0x7f) << (7 * index);
+        if byte & 0x80 == 0 {
+            return Ok((value as usize, &bytes[index + 1..]));
+        }
+    }
+    Err(WasmExecutionError::InvalidApiVersion)
+}
+
+#[cfg(test)]
+mod tests {
+    use linera_base::{data_types::Bytecode, vm::WasmApiVersion};
+
+    use super::check_api_version;
+    use crate::ExecutionError;
+
+    /// Returns an empty module, with a custom section declaring the given version.
+    fn module_declaring(api_version: Option<WasmApiVersion>) -> Bytecode {
+        let mut bytes = vec![0x00, 0x61, 0x73, 0x6d, 0x01, 0x00, 0x00, 0x00];
+        if let Some(api_version) = api_version {
+            let name = WasmApiVersion::CUSTOM_SECTION.as_bytes();
+            bytes.push(0);
+            bytes.push((1 + name.len() + 4) as u8);
+            bytes.push(name.len() as u8);
+            bytes.extend_from_slice(name);
+            bytes.extend_from_slice(&api_version.to_custom_section());
+        }
+        Bytecode::new(bytes)
+    }
+
+    #[test]
+    fn test_api_version_detection() {
+        let current = WasmApiVersion::CURRENT;
+        assert_eq!(
+            check_api_version(&module_declaring(Some(current))).unwrap(),
+            current
+        );
+        assert_eq!(
+            check_api_version(&module_declaring(None)).unwrap(),
+            WasmApiVersion::UNVERSIONED
+        );
+
+        let newer_minor = WasmApiVersion::new(current.major, current.minor + 1);
+        let newer_major = WasmApiVersion::new(current.major + 1, 0);
+        for declared in [newer_minor, newer_major] {
+            assert!(matches!(
+                check_api_version(&module_declaring(Some(declared))),
+                Err(ExecutionError::IncompatibleApiVersion { declared: version, .. })
+                    if version == declared
+            ));
+        }
+    }
+}
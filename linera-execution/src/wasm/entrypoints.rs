@@ -13,6 +13,10 @@ pub trait ContractEntrypoints {
     fn execute_operation(operation: Vec<u8>) -> Vec<u8>;
     fn execute_message(message: Vec<u8>);
     fn process_streams(streams: Vec<StreamUpdate>);
+    fn on_chain_close();
+    fn on_upgrade();
+    fn authorize_proposal(payload: Vec<u8>) -> bool;
+    fn execute_view(argument: Vec<u8>) -> Vec<u8>;
     fn finalize();
 }
 
@@ -0,0 +1,196 @@
+// Copyright (c) Zefchain Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! A pool of contract instances that are reused by later transactions.
+//!
+//! Creating an instance of a contract links the host functions, allocates the linear memory and
+//! initializes it, which can cost more than a short operation itself. Instead, when a transaction
+//! is done with an instance, the instance is reset to the state it had right after it was
+//! created, and kept for the next transaction of the same application on the same chain.
+//!
+//! An instance is only reset if this restores its whole state. Otherwise, e.g. if its memory
+//! grew, it is discarded. The state of a core Wasm instance consists of its memories, mutable
+//! globals and tables, which contract modules are instrumented to export so that they can be
+//! saved and restored, and of which data and element segments were dropped. Segments can only
+//! be dropped with bulk memory instructions, which the instrumentation rejects. The host data
+//! of the instance is replaced when it is attached to a new runtime.
+//!
+//! Instances cannot be sent to other threads, so each thread keeps its own pool.
+
+use std::{
+    cell::RefCell,
+    num::NonZeroUsize,
+    sync::atomic::{AtomicU64, Ordering},
+};
+
+use linera_base::{
+    data_types::StreamUpdate,
+    identifiers::{ApplicationId, ChainId},
+    vm::WasmApiVersion,
+};
+use lru::LruCache;
+use wasmtime::Module;
+
+#[cfg(with_metrics)]
+use super::metrics;
+use super::{
+    wasmtime::{InstanceSnapshot, WasmtimeContractInstance},
+    WasmExecutionError,
+};
+use crate::{ContractSyncRuntimeHandle, ExecutionError, UserContract, UserContractInstance};
+
+/// The maximum number of applications and chains with idle instances in the pool.
+const MAX_POOLED_APPLICATIONS: usize = 256;
+
+/// The maximum number of idle instances kept for an application on a chain.
+const MAX_IDLE_INSTANCES: usize = 2;
+
+/// Identifies a compiled contract module, so that an application upgraded to a different
+/// module doesn't reuse the instances of its previous code.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct ModulePoolId(u64);
+
+impl ModulePoolId {
+    /// Returns a new identifier, different from all the previous ones.
+    pub(crate) fn unique() -> Self {
+        static NEXT_ID: AtomicU64 = AtomicU64::new(0);
+        ModulePoolId(NEXT_ID.fetch_add(1, Ordering::Relaxed))
+    }
+}
+
+/// The module, the application and the chain that an instance is used for.
+type PoolKey = (ModulePoolId, ApplicationId, ChainId);
+
+thread_local! {
+    /// The idle instances of this thread, by module, application and chain.
+    static CONTRACT_INSTANCE_POOL: RefCell<LruCache<PoolKey, Vec<IdleContractInstance>>> = {
+        let capacity = NonZeroUsize::try_from(MAX_POOLED_APPLICATIONS)
+            .expect("Pool capacity should be larger than zero");
+        RefCell::new(LruCache::new(capacity))
+    };
+}
+
+/// An instance that is not attached to any runtime, and the state to reset it to.
+struct IdleContractInstance {
+    contract: WasmtimeContractInstance<ContractSyncRuntimeHandle>,
+    snapshot: InstanceSnapshot,
+}
+
+/// A contract instance that returns to the pool when it is dropped.
+pub(crate) struct PooledContractInstance {
+    key: PoolKey,
+    instance: Option<IdleContractInstance>,
+}
+
+impl PooledContractInstance {
+    /// Returns an idle instance of the contract of `application_id` on `chain_id` attached to
+    /// `runtime`, or creates a new one from `module` if there is none.
+    ///
+    /// A new instance that can't be reset is returned as is, and isn't pooled.
+    pub(crate) fn acquire(
+        module: &Module,
+        api_version: WasmApiVersion,
+        pool_id: ModulePoolId,
+        runtime: ContractSyncRuntimeHandle,
+        application_id: ApplicationId,
+        chain_id: ChainId,
+    ) -> Result<UserContractInstance, WasmExecutionError> {
+        let key = (pool_id, application_id, chain_id);
+        let idle_instance =
+            CONTRACT_INSTANCE_POOL.with_borrow_mut(|pool| pool.get_mut(&key).and_then(Vec::pop));
+        if let Some(mut instance) = idle_instance {
+            #[cfg(with_metrics)]
+            metrics::CONTRACT_INSTANCE_POOL_HITS
+                .with_label_values(&[])
+                .inc();
+            instance.contract.attach(runtime);
+            return Ok(Box::new(PooledContractInstance {
+                key,
+                instance: Some(instance),
+            }));
+        }
+
+        #[cfg(with_metrics)]
+        metrics::CONTRACT_INSTANCE_POOL_MISSES
+            .with_label_values(&[])
+            .inc();
+        let (contract, snapshot) =
+            WasmtimeContractInstance::prepare_with_snapshot(module, api_version, runtime)?;
+        Ok(match snapshot {
+            Some(snapshot) => Box::new(PooledContractInstance {
+                key,
+                instance: Some(IdleContractInstance { contract, snapshot }),
+            }),
+            None => Box::new(contract),
+        })
+    }
+
+    fn contract(&mut self) -> &mut WasmtimeContractInstance<ContractSyncRuntimeHandle> {
+        &mut self
+            .instance
+            .as_mut()
+            .expect("Pooled instances are only taken when they are dropped")
+            .contract
+    }
+}
+
+impl Drop for PooledContractInstance {
+    fn drop(&mut self) {
+        let Some(mut instance) = self.instance.take() else {
+            return;
+        };
+        if !instance.contract.reset(&instance.snapshot) {
+            #[cfg(with_metrics)]
+            metrics::CONTRACT_INSTANCE_POOL_DISCARDS
+                .with_label_values(&[])
+                .inc();
+            return;
+        }
+        // The pool is gone if the thread is exiting, and then the instance is simply dropped.
+        let _ = CONTRACT_INSTANCE_POOL.try_with(|pool| {
+            let mut pool = pool.borrow_mut();
+            let instances = pool.get_or_insert_mut(self.key, Vec::new);
+            if instances.len() < MAX_IDLE_INSTANCES {
+                instances.push(instance);
+            }
+        });
+    }
+}
+
+impl UserContract for PooledContractInstance {
+    fn instantiate(&mut self, argument: Vec<u8>) -> Result<(), ExecutionError> {
+        self.contract().instantiate(argument)
+    }
+
+    fn execute_operation(&mut self, operation: Vec<u8>) -> Result<Vec<u8>, ExecutionError> {
+        self.contract().execute_operation(operation)
+    }
+
+    fn execute_message(&mut self, message: Vec<u8>) -> Result<(), ExecutionError> {
+        self.contract().execute_message(message)
+    }
+
+    fn process_streams(&mut self, updates: Vec<StreamUpdate>) -> Result<(), ExecutionError> {
+        self.contract().process_streams(updates)
+    }
+
+    fn on_chain_close(&mut self) -> Result<(), ExecutionError> {
+        self.contract().on_chain_close()
+    }
+
+    fn on_upgrade(&mut self) -> Result<(), ExecutionError> {
+        self.contract().on_upgrade()
+    }
+
+    fn authorize_proposal(&mut self, payload: Vec<u8>) -> Result<bool, ExecutionError> {
+        self.contract().authorize_proposal(payload)
+    }
+
+    fn execute_view(&mut self, argument: Vec<u8>) -> Result<Vec<u8>, ExecutionError> {
+        self.contract().execute_view(argument)
+    }
+
+    fn finalize(&mut self) -> Result<(), ExecutionError> {
+        self.contract().finalize()
+    }
+}
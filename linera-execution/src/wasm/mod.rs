@@ -7,11 +7,19 @@
 //!
 //! - `wasmer` enables the [Wasmer](https://wasmer.io/) runtime
 //! - `wasmtime` enables the [Wasmtime](https://wasmtime.dev/) runtime
+//!
+//! Application modules built with the SDK may also be published wrapped into WebAssembly
+//! components, in which case the wrapped module is extracted and executed. Other components,
+//! which would need the runtime API to be linked through the component model, are rejected.
 
 #![cfg(with_wasm_runtime)]
 
+mod api_version;
 mod entrypoints;
+#[cfg(with_wasmtime)]
+mod instance_pool;
 mod module_cache;
+mod wrapped_module;
 #[macro_use]
 mod runtime_api;
 #[cfg(with_wasmer)]
@@ -19,9 +27,13 @@ mod wasmer;
 #[cfg(with_wasmtime)]
 mod wasmtime;
 
-use linera_base::data_types::Bytecode;
 #[cfg(with_metrics)]
 use linera_base::prometheus_util::MeasureLatency as _;
+use linera_base::{
+    data_types::Bytecode,
+    identifiers::{ApplicationId, ChainId},
+    vm::WasmApiVersion,
+};
 use thiserror::Error;
 use wasm_instrument::{gas_metering, parity_wasm};
 #[cfg(with_wasmer)]
@@ -29,6 +41,7 @@ use wasmer::{WasmerContractInstance, WasmerServiceInstance};
 #[cfg(with_wasmtime)]
 use wasmtime::{WasmtimeContractInstance, WasmtimeServiceInstance};
 
+use self::wrapped_module::ApplicationKind;
 pub use self::{
     entrypoints::{ContractEntrypoints, ServiceEntrypoints},
     runtime_api::{BaseRuntimeApi, ContractRuntimeApi, RuntimeApiData, ServiceRuntimeApi},
@@ -42,8 +55,10 @@ use crate::{
 mod metrics {
     use std::sync::LazyLock;
 
-    use linera_base::prometheus_util::{exponential_bucket_latencies, register_histogram_vec};
-    use prometheus::HistogramVec;
+    use linera_base::prometheus_util::{
+        exponential_bucket_latencies, register_histogram_vec, register_int_counter_vec,
+    };
+    use prometheus::{HistogramVec, IntCounterVec};
 
     pub static CONTRACT_INSTANTIATION_LATENCY: LazyLock<HistogramVec> = LazyLock::new(|| {
         register_histogram_vec(
@@ -62,6 +77,33 @@ mod metrics {
             exponential_bucket_latencies(1.0),
         )
     });
+
+    #[cfg(with_wasmtime)]
+    pub static CONTRACT_INSTANCE_POOL_HITS: LazyLock<IntCounterVec> = LazyLock::new(|| {
+        register_int_counter_vec(
+            "wasm_contract_instance_pool_hits",
+            "The number of Wasm contract instances reused from the instance pool",
+            &[],
+        )
+    });
+
+    #[cfg(with_wasmtime)]
+    pub static CONTRACT_INSTANCE_POOL_MISSES: LazyLock<IntCounterVec> = LazyLock::new(|| {
+        register_int_counter_vec(
+            "wasm_contract_instance_pool_misses",
+            "The number of Wasm contract instances created because the pool had none",
+            &[],
+        )
+    });
+
+    #[cfg(with_wasmtime)]
+    pub static CONTRACT_INSTANCE_POOL_DISCARDS: LazyLock<IntCounterVec> = LazyLock::new(|| {
+        register_int_counter_vec(
+            "wasm_contract_instance_pool_discards",
+            "The number of Wasm contract instances discarded because they could not be reset",
+            &[],
+        )
+    });
 }
 
 /// A user contract in a compiled WebAssembly module.
@@ -71,24 +113,38 @@ pub enum WasmContractModule {
     Wasmer {
         engine: ::wasmer::Engine,
         module: ::wasmer::Module,
+        api_version: WasmApiVersion,
     },
     #[cfg(with_wasmtime)]
-    Wasmtime { module: ::wasmtime::Module },
+    Wasmtime {
+        module: ::wasmtime::Module,
+        api_version: WasmApiVersion,
+        /// Identifies the module in the instance pool, so that pooled instances are only
+        /// reused for the same code.
+        pool_id: instance_pool::ModulePoolId,
+    },
 }
 
 impl WasmContractModule {
-    /// Creates a new [`WasmContractModule`] using the WebAssembly module with the provided bytecode.
+    /// Creates a new [`WasmContractModule`] using the WebAssembly module, possibly wrapped into
+    /// a component, with the provided bytecode.
+    ///
+    /// Fails if the module was built against a version of the API this runtime doesn't support.
     pub async fn new(
         contract_bytecode: Bytecode,
         runtime: WasmRuntime,
-    ) -> Result<Self, WasmExecutionError> {
-        let contract_bytecode = add_metering(contract_bytecode)?;
-        match runtime {
+    ) -> Result<Self, ExecutionError> {
+        let contract_bytecode =
+            wrapped_module::unwrap_module(contract_bytecode, ApplicationKind::Contract)?;
+        let api_version = api_version::check_api_version(&contract_bytecode)?;
+        let contract_bytecode = export_mutable_state(add_metering(contract_bytecode)?)?;
+        let module = match runtime {
             #[cfg(with_wasmer)]
-            WasmRuntime::Wasmer => Self::from_wasmer(contract_bytecode).await,
+            WasmRuntime::Wasmer => Self::from_wasmer(contract_bytecode, api_version).await?,
             #[cfg(with_wasmtime)]
-            WasmRuntime::Wasmtime => Self::from_wasmtime(contract_bytecode).await,
-        }
+            WasmRuntime::Wasmtime => Self::from_wasmtime(contract_bytecode, api_version).await?,
+        };
+        Ok(module)
     }
 
     /// Creates a new [`WasmContractModule`] using the WebAssembly module in `contract_bytecode_file`.
@@ -96,7 +152,7 @@ impl WasmContractModule {
     pub async fn from_file(
         contract_bytecode_file: impl AsRef<std::path::Path>,
         runtime: WasmRuntime,
-    ) -> Result<Self, WasmExecutionError> {
+    ) -> Result<Self, ExecutionError> {
         Self::new(
             Bytecode::load_from_file(contract_bytecode_file)
                 .await
@@ -118,40 +174,99 @@ impl UserContractModule for WasmContractModule {
 
         let instance: UserContractInstance = match self {
             #[cfg(with_wasmtime)]
-            WasmContractModule::Wasmtime { module } => {
-                Box::new(WasmtimeContractInstance::prepare(module, runtime)?)
-            }
+            WasmContractModule::Wasmtime {
+                module,
+                api_version,
+                pool_id: _,
+            } => Box::new(WasmtimeContractInstance::prepare(
+                module,
+                *api_version,
+                runtime,
+            )?),
             #[cfg(with_wasmer)]
-            WasmContractModule::Wasmer { engine, module } => Box::new(
-                WasmerContractInstance::prepare(engine.clone(), module, runtime)?,
-            ),
+            WasmContractModule::Wasmer {
+                engine,
+                module,
+                api_version,
+            } => Box::new(WasmerContractInstance::prepare(
+                engine.clone(),
+                module,
+                *api_version,
+                runtime,
+            )?),
         };
 
         Ok(instance)
     }
+
+    #[cfg_attr(not(with_wasmtime), allow(unused_variables))]
+    fn instantiate_pooled(
+        &self,
+        runtime: ContractSyncRuntimeHandle,
+        application_id: ApplicationId,
+        chain_id: ChainId,
+    ) -> Result<UserContractInstance, ExecutionError> {
+        match self {
+            #[cfg(with_wasmtime)]
+            WasmContractModule::Wasmtime {
+                module,
+                api_version,
+                pool_id,
+            } => {
+                #[cfg(with_metrics)]
+                let _instantiation_latency =
+                    metrics::CONTRACT_INSTANTIATION_LATENCY.measure_latency();
+
+                Ok(instance_pool::PooledContractInstance::acquire(
+                    module,
+                    *api_version,
+                    *pool_id,
+                    runtime,
+                    application_id,
+                    chain_id,
+                )?)
+            }
+            // Wasmer instances are not pooled.
+            #[cfg(with_wasmer)]
+            WasmContractModule::Wasmer { .. } => self.instantiate(runtime),
+        }
+    }
 }
 
 /// A user service in a compiled WebAssembly module.
 #[derive(Clone)]
 pub enum WasmServiceModule {
     #[cfg(with_wasmer)]
-    Wasmer { module: ::wasmer::Module },
+    Wasmer {
+        module: ::wasmer::Module,
+        api_version: WasmApiVersion,
+    },
     #[cfg(with_wasmtime)]
-    Wasmtime { module: ::wasmtime::Module },
+    Wasmtime {
+        module: ::wasmtime::Module,
+        api_version: WasmApiVersion,
+    },
 }
 
 impl WasmServiceModule {
-    /// Creates a new [`WasmServiceModule`] using the WebAssembly module with the provided bytecode.
+    /// Creates a new [`WasmServiceModule`] using the WebAssembly module, possibly wrapped into
+    /// a component, with the provided bytecode.
+    ///
+    /// Fails if the module was built against a version of the API this runtime doesn't support.
     pub async fn new(
         service_bytecode: Bytecode,
         runtime: WasmRuntime,
-    ) -> Result<Self, WasmExecutionError> {
-        match runtime {
+    ) -> Result<Self, ExecutionError> {
+        let service_bytecode =
+            wrapped_module::unwrap_module(service_bytecode, ApplicationKind::Service)?;
+        let api_version = api_version::check_api_version(&service_bytecode)?;
+        let module = match runtime {
             #[cfg(with_wasmer)]
-            WasmRuntime::Wasmer => Self::from_wasmer(service_bytecode).await,
+            WasmRuntime::Wasmer => Self::from_wasmer(service_bytecode, api_version).await?,
             #[cfg(with_wasmtime)]
-            WasmRuntime::Wasmtime => Self::from_wasmtime(service_bytecode).await,
-        }
+            WasmRuntime::Wasmtime => Self::from_wasmtime(service_bytecode, api_version).await?,
+        };
+        Ok(module)
     }
 
     /// Creates a new [`WasmServiceModule`] using the WebAssembly module in `service_bytecode_file`.
@@ -159,7 +274,7 @@ impl WasmServiceModule {
     pub async fn from_file(
         service_bytecode_file: impl AsRef<std::path::Path>,
         runtime: WasmRuntime,
-    ) -> Result<Self, WasmExecutionError> {
+    ) -> Result<Self, ExecutionError> {
         Self::new(
             Bytecode::load_from_file(service_bytecode_file)
                 .await
@@ -181,13 +296,23 @@ impl UserServiceModule for WasmServiceModule {
 
         let instance: UserServiceInstance = match self {
             #[cfg(with_wasmtime)]
-            WasmServiceModule::Wasmtime { module } => {
-                Box::new(WasmtimeServiceInstance::prepare(module, runtime)?)
-            }
+            WasmServiceModule::Wasmtime {
+                module,
+                api_version,
+            } => Box::new(WasmtimeServiceInstance::prepare(
+                module,
+                *api_version,
+                runtime,
+            )?),
             #[cfg(with_wasmer)]
-            WasmServiceModule::Wasmer { module } => {
-                Box::new(WasmerServiceInstance::prepare(module, runtime)?)
-            }
+            WasmServiceModule::Wasmer {
+                module,
+                api_version,
+            } => Box::new(WasmerServiceInstance::prepare(
+                module,
+                *api_version,
+                runtime,
+            )?),
         };
 
         Ok(instance)
@@ -237,6 +362,48 @@ pub fn add_metering(bytecode: Bytecode) -> Result<Bytecode, WasmExecutionError>
     Ok(Bytecode::new(instrumented_module.into_bytes()?))
 }
 
+/// Exports the mutable globals, the tables and the memories of a contract that it does not
+/// export already, so that the whole state of its instances can be saved and restored by the
+/// instance pool.
+fn export_mutable_state(bytecode: Bytecode) -> Result<Bytecode, WasmExecutionError> {
+    use parity_wasm::elements::{ExportEntry, ImportCountType, Internal, Module};
+
+    let mut module: Module = parity_wasm::deserialize_buffer(&bytecode.bytes)?;
+    let first_global = module.import_count(ImportCountType::Global) as u32;
+    let first_table = module.import_count(ImportCountType::Table) as u32;
+    let first_memory = module.import_count(ImportCountType::Memory) as u32;
+    let mut state = Vec::new();
+    if let Some(section) = module.global_section() {
+        for (index, global) in (first_global..).zip(section.entries()) {
+            if global.global_type().is_mutable() {
+                state.push((format!("global{index}"), Internal::Global(index)));
+            }
+        }
+    }
+    if let Some(section) = module.table_section() {
+        for index in (first_table..).take(section.entries().len()) {
+            state.push((format!("table{index}"), Internal::Table(index)));
+        }
+    }
+    if let Some(section) = module.memory_section() {
+        for index in (first_memory..).take(section.entries().len()) {
+            state.push((format!("memory{index}"), Internal::Memory(index)));
+        }
+    }
+
+    let exports = module
+        .export_section_mut()
+        .ok_or(WasmExecutionError::InstrumentModule)?
+        .entries_mut();
+    for (name, internal) in state {
+        if !exports.iter().any(|export| *export.internal() == internal) {
+            exports.push(ExportEntry::new(format!("linera:state/{name}"), internal));
+        }
+    }
+
+    Ok(Bytecode::new(module.into_bytes()?))
+}
+
 #[cfg(web)]
 const _: () = {
     use js_sys::wasm_bindgen::JsValue;
@@ -249,8 +416,14 @@ const _: () = {
 
             cfg_if::cfg_if! {
                 if #[cfg(with_wasmer)] {
+                    let module: ::wasmer::Module = value.try_into()?;
+                    let api_version = api_version::parse_api_version(
+                        module.custom_sections(WasmApiVersion::CUSTOM_SECTION),
+                    )
+                    .map_err(|error| JsValue::from_str(&error.to_string()))?;
                     Ok(Self::Wasmer {
-                        module: value.try_into()?,
+                        module,
+                        api_version,
                     })
                 } else {
                     Err(value)
@@ -263,7 +436,7 @@ const _: () = {
         fn from(module: WasmServiceModule) -> JsValue {
             match module {
                 #[cfg(with_wasmer)]
-                WasmServiceModule::Wasmer { module } => ::wasmer::Module::clone(&module).into(),
+                WasmServiceModule::Wasmer { module, .. } => ::wasmer::Module::clone(&module).into(),
             }
         }
     }
@@ -275,9 +448,15 @@ const _: () = {
             // TODO(#2775): be generic over possible implementations
             cfg_if::cfg_if! {
                 if #[cfg(with_wasmer)] {
+                    let module: ::wasmer::Module = value.try_into()?;
+                    let api_version = api_version::parse_api_version(
+                        module.custom_sections(WasmApiVersion::CUSTOM_SECTION),
+                    )
+                    .map_err(|error| JsValue::from_str(&error.to_string()))?;
                     Ok(Self::Wasmer {
-                        module: value.try_into()?,
+                        module,
                         engine: Default::default(),
+                        api_version,
                     })
                 } else {
                     Err(value)
@@ -290,7 +469,7 @@ const _: () = {
         fn from(module: WasmContractModule) -> JsValue {
             match module {
                 #[cfg(with_wasmer)]
-                WasmContractModule::Wasmer { module, engine: _ } => {
+                WasmContractModule::Wasmer { module, .. } => {
                     ::wasmer::Module::clone(&module).into()
                 }
             }
@@ -329,6 +508,17 @@ pub enum WasmExecutionError {
     UnknownPromise,
     #[error("Attempt to call incorrect `wait` function for a promise")]
     IncorrectPromise,
+    #[error("Invalid declaration of the Wasm API version in the module")]
+    InvalidApiVersion,
+    #[error("Function `{name}` is not part of version {api_version} of the Wasm API")]
+    UnsupportedImport {
+        name: &'static str,
+        api_version: WasmApiVersion,
+    },
+    #[error("Invalid Wasm component: {0}")]
+    InvalidComponent(#[from] wasmparser::BinaryReaderError),
+    #[error("Unsupported Wasm component, only wrapped SDK modules can be executed: {0}")]
+    UnsupportedComponent(String),
 }
 
 #[cfg(with_wasmer)]
@@ -4,42 +4,91 @@
 use std::{any::Any, collections::HashMap, marker::PhantomData};
 
 use linera_base::{
+    bridge::EthereumStorageKey,
     crypto::CryptoHash,
     data_types::{
-        Amount, ApplicationPermissions, BlockHeight, Bytecode, SendMessageRequest, Timestamp,
+        Amount, ApplicationPermissions, BlockHeight, Bytecode, Epoch, OperationMetadata,
+        ReentrancyPolicy, SendMessageRequest, Timestamp,
     },
     http,
-    identifiers::{Account, AccountOwner, ApplicationId, BlobId, ChainId, MessageId, StreamName},
+    identifiers::{
+        Account, AccountOwner, ApplicationId, BlobId, ChainId, ChannelName, MessageId, StreamName,
+    },
     ownership::{ChainOwnership, ChangeApplicationPermissionsError, CloseChainError},
-    vm::VmRuntime,
+    vm::{VmRuntime, WasmApiVersion},
 };
 use linera_views::batch::{Batch, WriteOperation};
 use linera_witty::{wit_export, Instance, RuntimeError};
 use tracing::log;
 
-use super::WasmExecutionError;
+use super::{
+    api_version::{
+        DRAW_ENTROPY_SINCE, QUERY_APPLICATION_AT_SINCE, READ_APPLICATION_CONFIG_SINCE,
+        READ_EPOCH_SINCE, REENTRANCY_POLICY_SINCE, TRY_CALL_SINCE, VIEW_CALL_SINCE,
+    },
+    WasmExecutionError,
+};
 use crate::{BaseRuntime, ContractRuntime, ExecutionError, ModuleId, ServiceRuntime};
 
 /// Common host data used as the `UserData` of the system API implementations.
 pub struct RuntimeApiData<Runtime> {
-    runtime: Runtime,
+    /// The runtime, which is only missing while the instance waits in the instance pool.
+    runtime: Option<Runtime>,
     active_promises: HashMap<u32, Box<dyn Any + Send + Sync>>,
     promise_counter: u32,
+    /// The version of the API the module was built against.
+    api_version: WasmApiVersion,
 }
 
 impl<Runtime> RuntimeApiData<Runtime> {
-    /// Creates a new [`RuntimeApiData`] using the provided `runtime` to execute the system APIs.
-    pub fn new(runtime: Runtime) -> Self {
+    /// Creates a new [`RuntimeApiData`] using the provided `runtime` to execute the system APIs,
+    /// for a module built against `api_version`.
+    pub fn new(runtime: Runtime, api_version: WasmApiVersion) -> Self {
         RuntimeApiData {
-            runtime,
+            runtime: Some(runtime),
             active_promises: HashMap::new(),
             promise_counter: 0,
+            api_version,
         }
     }
 
     /// Returns a mutable reference the system API `Runtime`.
     pub fn runtime_mut(&mut self) -> &mut Runtime {
-        &mut self.runtime
+        self.runtime
+            .as_mut()
+            .expect("Instances should not run while they are detached from their runtime")
+    }
+
+    /// Replaces the data with a fresh one using `runtime`, as if the instance had just been
+    /// created with it.
+    pub(crate) fn attach(&mut self, runtime: Runtime) {
+        *self = RuntimeApiData::new(runtime, self.api_version);
+    }
+
+    /// Drops the runtime and the pending promises, so that the instance keeps no reference to
+    /// the transaction it was used in.
+    pub(crate) fn detach(&mut self) {
+        self.runtime = None;
+        self.active_promises.clear();
+    }
+
+    /// Fails if the module was built against a version of the API older than `since`, in which
+    /// the imported function `name` didn't exist.
+    fn require_api_version(
+        &self,
+        name: &'static str,
+        since: WasmApiVersion,
+    ) -> Result<(), RuntimeError> {
+        if self.api_version < since {
+            return Err(RuntimeError::Custom(
+                WasmExecutionError::UnsupportedImport {
+                    name,
+                    api_version: self.api_version,
+                }
+                .into(),
+            ));
+        }
+        Ok(())
     }
 
     /// Registers a `promise` internally, returning an ID that is unique for the lifetime of this
@@ -88,7 +137,7 @@ where
     fn get_chain_id(caller: &mut Caller) -> Result<ChainId, RuntimeError> {
         caller
             .user_data_mut()
-            .runtime
+            .runtime_mut()
             .chain_id()
             .map_err(|error| RuntimeError::Custom(error.into()))
     }
@@ -97,7 +146,7 @@ where
     fn get_block_height(caller: &mut Caller) -> Result<BlockHeight, RuntimeError> {
         caller
             .user_data_mut()
-            .runtime
+            .runtime_mut()
             .block_height()
             .map_err(|error| RuntimeError::Custom(error.into()))
     }
@@ -106,7 +155,7 @@ where
     fn get_application_id(caller: &mut Caller) -> Result<ApplicationId, RuntimeError> {
         caller
             .user_data_mut()
-            .runtime
+            .runtime_mut()
             .application_id()
             .map_err(|error| RuntimeError::Custom(error.into()))
     }
@@ -115,7 +164,7 @@ where
     fn get_application_creator_chain_id(caller: &mut Caller) -> Result<ChainId, RuntimeError> {
         caller
             .user_data_mut()
-            .runtime
+            .runtime_mut()
             .application_creator_chain_id()
             .map_err(|error| RuntimeError::Custom(error.into()))
     }
@@ -124,7 +173,7 @@ where
     fn application_parameters(caller: &mut Caller) -> Result<Vec<u8>, RuntimeError> {
         caller
             .user_data_mut()
-            .runtime
+            .runtime_mut()
             .application_parameters()
             .map_err(|error| RuntimeError::Custom(error.into()))
     }
@@ -133,7 +182,7 @@ where
     fn get_chain_ownership(caller: &mut Caller) -> Result<ChainOwnership, RuntimeError> {
         caller
             .user_data_mut()
-            .runtime
+            .runtime_mut()
             .chain_ownership()
             .map_err(|error| RuntimeError::Custom(error.into()))
     }
@@ -142,16 +191,40 @@ where
     fn read_system_timestamp(caller: &mut Caller) -> Result<Timestamp, RuntimeError> {
         caller
             .user_data_mut()
-            .runtime
+            .runtime_mut()
             .read_system_timestamp()
             .map_err(|error| RuntimeError::Custom(error.into()))
     }
 
+    /// Returns the current epoch of the chain.
+    fn read_epoch(caller: &mut Caller) -> Result<Epoch, RuntimeError> {
+        caller
+            .user_data_mut()
+            .require_api_version("read-epoch", READ_EPOCH_SINCE)?;
+        caller
+            .user_data_mut()
+            .runtime_mut()
+            .read_epoch()
+            .map_err(|error| RuntimeError::Custom(error.into()))
+    }
+
+    /// Returns the configuration of the current application on this chain, if any.
+    fn read_application_config(caller: &mut Caller) -> Result<Option<Vec<u8>>, RuntimeError> {
+        caller
+            .user_data_mut()
+            .require_api_version("read-application-config", READ_APPLICATION_CONFIG_SINCE)?;
+        caller
+            .user_data_mut()
+            .runtime_mut()
+            .read_application_config()
+            .map_err(|error| RuntimeError::Custom(error.into()))
+    }
+
     /// Returns the current chain balance.
     fn read_chain_balance(caller: &mut Caller) -> Result<Amount, RuntimeError> {
         caller
             .user_data_mut()
-            .runtime
+            .runtime_mut()
             .read_chain_balance()
             .map_err(|error| RuntimeError::Custom(error.into()))
     }
@@ -163,7 +236,7 @@ where
     ) -> Result<Amount, RuntimeError> {
         caller
             .user_data_mut()
-            .runtime
+            .runtime_mut()
             .read_owner_balance(owner)
             .map_err(|error| RuntimeError::Custom(error.into()))
     }
@@ -174,7 +247,7 @@ where
     ) -> Result<Vec<(AccountOwner, Amount)>, RuntimeError> {
         caller
             .user_data_mut()
-            .runtime
+            .runtime_mut()
             .read_owner_balances()
             .map_err(|error| RuntimeError::Custom(error.into()))
     }
@@ -183,7 +256,7 @@ where
     fn read_balance_owners(caller: &mut Caller) -> Result<Vec<AccountOwner>, RuntimeError> {
         caller
             .user_data_mut()
-            .runtime
+            .runtime_mut()
             .read_balance_owners()
             .map_err(|error| RuntimeError::Custom(error.into()))
     }
@@ -195,7 +268,7 @@ where
     ) -> Result<http::Response, RuntimeError> {
         caller
             .user_data_mut()
-            .runtime
+            .runtime_mut()
             .perform_http_request(request)
             .map_err(|error| RuntimeError::Custom(error.into()))
     }
@@ -206,7 +279,7 @@ where
     fn assert_before(caller: &mut Caller, timestamp: Timestamp) -> Result<(), RuntimeError> {
         caller
             .user_data_mut()
-            .runtime
+            .runtime_mut()
             .assert_before(timestamp)
             .map_err(|error| RuntimeError::Custom(error.into()))
     }
@@ -215,7 +288,7 @@ where
     fn read_data_blob(caller: &mut Caller, hash: CryptoHash) -> Result<Vec<u8>, RuntimeError> {
         caller
             .user_data_mut()
-            .runtime
+            .runtime_mut()
             .read_data_blob(&hash)
             .map_err(|error| RuntimeError::Custom(error.into()))
     }
@@ -224,11 +297,24 @@ where
     fn assert_data_blob_exists(caller: &mut Caller, hash: CryptoHash) -> Result<(), RuntimeError> {
         caller
             .user_data_mut()
-            .runtime
+            .runtime_mut()
             .assert_data_blob_exists(&hash)
             .map_err(|error| RuntimeError::Custom(error.into()))
     }
 
+    /// Reads the value under `key` in the state of the current application on another chain.
+    fn read_value_bytes_on_chain(
+        caller: &mut Caller,
+        chain_id: ChainId,
+        key: Vec<u8>,
+    ) -> Result<Option<Vec<u8>>, RuntimeError> {
+        caller
+            .user_data_mut()
+            .runtime_mut()
+            .read_value_bytes_on_chain(chain_id, key)
+            .map_err(|error| RuntimeError::Custom(error.into()))
+    }
+
     /// Logs a `message` with the provided information `level`.
     fn log(_caller: &mut Caller, message: String, level: log::Level) -> Result<(), RuntimeError> {
         match level {
@@ -245,7 +331,7 @@ where
     fn contains_key_new(caller: &mut Caller, key: Vec<u8>) -> Result<u32, RuntimeError> {
         let mut data = caller.user_data_mut();
         let promise = data
-            .runtime
+            .runtime_mut()
             .contains_key_new(key)
             .map_err(|error| RuntimeError::Custom(error.into()))?;
 
@@ -257,7 +343,7 @@ where
         let mut data = caller.user_data_mut();
         let promise = data.take_promise(promise_id)?;
 
-        data.runtime
+        data.runtime_mut()
             .contains_key_wait(&promise)
             .map_err(|error| RuntimeError::Custom(error.into()))
     }
@@ -266,7 +352,7 @@ where
     fn contains_keys_new(caller: &mut Caller, keys: Vec<Vec<u8>>) -> Result<u32, RuntimeError> {
         let mut data = caller.user_data_mut();
         let promise = data
-            .runtime
+            .runtime_mut()
             .contains_keys_new(keys)
             .map_err(|error| RuntimeError::Custom(error.into()))?;
 
@@ -278,7 +364,7 @@ where
         let mut data = caller.user_data_mut();
         let promise = data.take_promise(promise_id)?;
 
-        data.runtime
+        data.runtime_mut()
             .contains_keys_wait(&promise)
             .map_err(|error| RuntimeError::Custom(error.into()))
     }
@@ -290,7 +376,7 @@ where
     ) -> Result<u32, RuntimeError> {
         let mut data = caller.user_data_mut();
         let promise = data
-            .runtime
+            .runtime_mut()
             .read_multi_values_bytes_new(keys)
             .map_err(|error| RuntimeError::Custom(error.into()))?;
 
@@ -305,7 +391,7 @@ where
         let mut data = caller.user_data_mut();
         let promise = data.take_promise(promise_id)?;
 
-        data.runtime
+        data.runtime_mut()
             .read_multi_values_bytes_wait(&promise)
             .map_err(|error| RuntimeError::Custom(error.into()))
     }
@@ -314,7 +400,7 @@ where
     fn read_value_bytes_new(caller: &mut Caller, key: Vec<u8>) -> Result<u32, RuntimeError> {
         let mut data = caller.user_data_mut();
         let promise = data
-            .runtime
+            .runtime_mut()
             .read_value_bytes_new(key)
             .map_err(|error| RuntimeError::Custom(error.into()))?;
 
@@ -329,7 +415,7 @@ where
         let mut data = caller.user_data_mut();
         let promise = data.take_promise(promise_id)?;
 
-        data.runtime
+        data.runtime_mut()
             .read_value_bytes_wait(&promise)
             .map_err(|error| RuntimeError::Custom(error.into()))
     }
@@ -338,7 +424,7 @@ where
     fn find_keys_new(caller: &mut Caller, key_prefix: Vec<u8>) -> Result<u32, RuntimeError> {
         let mut data = caller.user_data_mut();
         let promise = data
-            .runtime
+            .runtime_mut()
             .find_keys_by_prefix_new(key_prefix)
             .map_err(|error| RuntimeError::Custom(error.into()))?;
 
@@ -350,7 +436,7 @@ where
         let mut data = caller.user_data_mut();
         let promise = data.take_promise(promise_id)?;
 
-        data.runtime
+        data.runtime_mut()
             .find_keys_by_prefix_wait(&promise)
             .map_err(|error| RuntimeError::Custom(error.into()))
     }
@@ -359,7 +445,7 @@ where
     fn find_key_values_new(caller: &mut Caller, key_prefix: Vec<u8>) -> Result<u32, RuntimeError> {
         let mut data = caller.user_data_mut();
         let promise = data
-            .runtime
+            .runtime_mut()
             .find_key_values_by_prefix_new(key_prefix)
             .map_err(|error| RuntimeError::Custom(error.into()))?;
 
@@ -375,7 +461,7 @@ where
         let mut data = caller.user_data_mut();
         let promise = data.take_promise(promise_id)?;
 
-        data.runtime
+        data.runtime_mut()
             .find_key_values_by_prefix_wait(&promise)
             .map_err(|error| RuntimeError::Custom(error.into()))
     }
@@ -395,7 +481,7 @@ where
     fn authenticated_signer(caller: &mut Caller) -> Result<Option<AccountOwner>, RuntimeError> {
         caller
             .user_data_mut()
-            .runtime
+            .runtime_mut()
             .authenticated_signer()
             .map_err(|error| RuntimeError::Custom(error.into()))
     }
@@ -405,7 +491,7 @@ where
     fn get_message_id(caller: &mut Caller) -> Result<Option<MessageId>, RuntimeError> {
         caller
             .user_data_mut()
-            .runtime
+            .runtime_mut()
             .message_id()
             .map_err(|error| RuntimeError::Custom(error.into()))
     }
@@ -416,7 +502,7 @@ where
     fn message_is_bouncing(caller: &mut Caller) -> Result<Option<bool>, RuntimeError> {
         caller
             .user_data_mut()
-            .runtime
+            .runtime_mut()
             .message_is_bouncing()
             .map_err(|error| RuntimeError::Custom(error.into()))
     }
@@ -425,7 +511,7 @@ where
     fn authenticated_caller_id(caller: &mut Caller) -> Result<Option<ApplicationId>, RuntimeError> {
         caller
             .user_data_mut()
-            .runtime
+            .runtime_mut()
             .authenticated_caller_id()
             .map_err(|error| RuntimeError::Custom(error.into()))
     }
@@ -437,7 +523,7 @@ where
     ) -> Result<(), RuntimeError> {
         caller
             .user_data_mut()
-            .runtime
+            .runtime_mut()
             .send_message(message)
             .map_err(|error| RuntimeError::Custom(error.into()))
     }
@@ -452,7 +538,7 @@ where
     ) -> Result<(), RuntimeError> {
         caller
             .user_data_mut()
-            .runtime
+            .runtime_mut()
             .transfer(source, destination, amount)
             .map_err(|error| RuntimeError::Custom(error.into()))
     }
@@ -466,7 +552,7 @@ where
     ) -> Result<(), RuntimeError> {
         caller
             .user_data_mut()
-            .runtime
+            .runtime_mut()
             .claim(source, destination, amount)
             .map_err(|error| RuntimeError::Custom(error.into()))
     }
@@ -481,7 +567,7 @@ where
     ) -> Result<ChainId, RuntimeError> {
         caller
             .user_data_mut()
-            .runtime
+            .runtime_mut()
             .open_chain(chain_ownership, application_permissions, balance)
             .map_err(|error| RuntimeError::Custom(error.into()))
     }
@@ -489,7 +575,7 @@ where
     /// Closes the current chain. Returns an error if the application doesn't have
     /// permission to do so.
     fn close_chain(caller: &mut Caller) -> Result<Result<(), CloseChainError>, RuntimeError> {
-        match caller.user_data_mut().runtime.close_chain() {
+        match caller.user_data_mut().runtime_mut().close_chain() {
             Ok(()) => Ok(Ok(())),
             Err(ExecutionError::UnauthorizedApplication(_)) => {
                 Ok(Err(CloseChainError::NotPermitted))
@@ -506,7 +592,7 @@ where
     ) -> Result<Result<(), ChangeApplicationPermissionsError>, RuntimeError> {
         match caller
             .user_data_mut()
-            .runtime
+            .runtime_mut()
             .change_application_permissions(application_permissions)
         {
             Ok(()) => Ok(Ok(())),
@@ -528,7 +614,7 @@ where
     ) -> Result<ApplicationId, RuntimeError> {
         caller
             .user_data_mut()
-            .runtime
+            .runtime_mut()
             .create_application(module_id, parameters, argument, required_application_ids)
             .map_err(|error| RuntimeError::Custom(error.into()))
     }
@@ -537,7 +623,7 @@ where
     fn create_data_blob(caller: &mut Caller, bytes: Vec<u8>) -> Result<BlobId, RuntimeError> {
         caller
             .user_data_mut()
-            .runtime
+            .runtime_mut()
             .create_data_blob(bytes)
             .map_err(|error| RuntimeError::Custom(error.into()))
     }
@@ -551,7 +637,7 @@ where
     ) -> Result<ModuleId, RuntimeError> {
         caller
             .user_data_mut()
-            .runtime
+            .runtime_mut()
             .publish_module(contract, service, vm_runtime)
             .map_err(|error| RuntimeError::Custom(error.into()))
     }
@@ -565,16 +651,50 @@ where
     ) -> Result<Vec<u8>, RuntimeError> {
         caller
             .user_data_mut()
-            .runtime
+            .runtime_mut()
             .try_call_application(authenticated, callee_id, argument)
             .map_err(|error| RuntimeError::Custom(error.into()))
     }
 
+    /// Calls another application speculatively, undoing its effects afterwards. Returns
+    /// `None` if the call failed.
+    fn try_call(
+        caller: &mut Caller,
+        authenticated: bool,
+        callee_id: ApplicationId,
+        argument: Vec<u8>,
+    ) -> Result<Option<Vec<u8>>, RuntimeError> {
+        caller
+            .user_data_mut()
+            .require_api_version("try-call", TRY_CALL_SINCE)?;
+        caller
+            .user_data_mut()
+            .runtime_mut()
+            .try_call(authenticated, callee_id, argument)
+            .map_err(|error| RuntimeError::Custom(error.into()))
+    }
+
+    /// Calls a read-only view function of another application.
+    fn try_view_call(
+        caller: &mut Caller,
+        callee_id: ApplicationId,
+        argument: Vec<u8>,
+    ) -> Result<Vec<u8>, RuntimeError> {
+        caller
+            .user_data_mut()
+            .require_api_version("try-view-call", VIEW_CALL_SINCE)?;
+        caller
+            .user_data_mut()
+            .runtime_mut()
+            .try_view_call(callee_id, argument)
+            .map_err(|error| RuntimeError::Custom(error.into()))
+    }
+
     /// Adds a new item to an event stream. Returns the new event's index in the stream.
     fn emit(caller: &mut Caller, name: StreamName, value: Vec<u8>) -> Result<u32, RuntimeError> {
         caller
             .user_data_mut()
-            .runtime
+            .runtime_mut()
             .emit(name, value)
             .map_err(|error| RuntimeError::Custom(error.into()))
     }
@@ -590,7 +710,7 @@ where
     ) -> Result<Vec<u8>, RuntimeError> {
         caller
             .user_data_mut()
-            .runtime
+            .runtime_mut()
             .read_event(chain_id, name, index)
             .map_err(|error| RuntimeError::Custom(error.into()))
     }
@@ -604,7 +724,7 @@ where
     ) -> Result<(), RuntimeError> {
         caller
             .user_data_mut()
-            .runtime
+            .runtime_mut()
             .subscribe_to_events(chain_id, application_id, name)
             .map_err(|error| RuntimeError::Custom(error.into()))
     }
@@ -618,11 +738,50 @@ where
     ) -> Result<(), RuntimeError> {
         caller
             .user_data_mut()
-            .runtime
+            .runtime_mut()
             .unsubscribe_from_events(chain_id, application_id, name)
             .map_err(|error| RuntimeError::Custom(error.into()))
     }
 
+    /// Sends a message to this application on every chain subscribed to one of its channels.
+    fn publish_message(
+        caller: &mut Caller,
+        channel: ChannelName,
+        message: Vec<u8>,
+    ) -> Result<(), RuntimeError> {
+        caller
+            .user_data_mut()
+            .runtime_mut()
+            .publish_message(channel, message)
+            .map_err(|error| RuntimeError::Custom(error.into()))
+    }
+
+    /// Subscribes the current chain to a channel of this application on another chain.
+    fn subscribe_to_channel(
+        caller: &mut Caller,
+        chain_id: ChainId,
+        channel: ChannelName,
+    ) -> Result<(), RuntimeError> {
+        caller
+            .user_data_mut()
+            .runtime_mut()
+            .subscribe_to_channel(chain_id, channel)
+            .map_err(|error| RuntimeError::Custom(error.into()))
+    }
+
+    /// Unsubscribes the current chain from a channel of this application on another chain.
+    fn unsubscribe_from_channel(
+        caller: &mut Caller,
+        chain_id: ChainId,
+        channel: ChannelName,
+    ) -> Result<(), RuntimeError> {
+        caller
+            .user_data_mut()
+            .runtime_mut()
+            .unsubscribe_from_channel(chain_id, channel)
+            .map_err(|error| RuntimeError::Custom(error.into()))
+    }
+
     /// Queries a service and returns the response.
     fn query_service(
         caller: &mut Caller,
@@ -631,7 +790,7 @@ where
     ) -> Result<Vec<u8>, RuntimeError> {
         caller
             .user_data_mut()
-            .runtime
+            .runtime_mut()
             .query_service(application_id, query)
             .map_err(|error| RuntimeError::Custom(error.into()))
     }
@@ -657,6 +816,57 @@ where
             .map_err(|error| RuntimeError::Custom(error.into()))
     }
 
+    /// Returns the next `num_bytes` bytes of the transaction's deterministic entropy.
+    fn draw_entropy(caller: &mut Caller, num_bytes: u32) -> Result<Vec<u8>, RuntimeError> {
+        caller
+            .user_data_mut()
+            .require_api_version("draw-entropy", DRAW_ENTROPY_SINCE)?;
+        caller
+            .user_data_mut()
+            .runtime_mut()
+            .draw_entropy(num_bytes)
+            .map_err(|error| RuntimeError::Custom(error.into()))
+    }
+
+    /// Declares whether the current application accepts reentrant view calls.
+    fn declare_reentrancy_policy(
+        caller: &mut Caller,
+        policy: ReentrancyPolicy,
+    ) -> Result<(), RuntimeError> {
+        caller
+            .user_data_mut()
+            .require_api_version("declare-reentrancy-policy", REENTRANCY_POLICY_SINCE)?;
+        caller
+            .user_data_mut()
+            .runtime_mut()
+            .declare_reentrancy_policy(policy)
+            .map_err(|error| RuntimeError::Custom(error.into()))
+    }
+
+    /// Returns the value of the storage `slot` of the Ethereum contract at `address`, as of
+    /// the Ethereum block `block_number`, verified by the validators.
+    fn ethereum_storage_proof(
+        caller: &mut Caller,
+        block_number: u64,
+        address: [u8; 20],
+        slot: Vec<u8>,
+    ) -> Result<Vec<u8>, RuntimeError> {
+        let slot = <[u8; 32]>::try_from(slot.as_slice()).map_err(|_| {
+            RuntimeError::Custom(ExecutionError::InvalidEthereumStorageSlot(slot.len()).into())
+        })?;
+        let key = EthereumStorageKey {
+            block_number,
+            address,
+            slot,
+        };
+        caller
+            .user_data_mut()
+            .runtime_mut()
+            .ethereum_storage(key)
+            .map(Vec::from)
+            .map_err(|error| RuntimeError::Custom(error.into()))
+    }
+
     /// Writes a batch of `operations` to storage.
     fn write_batch(
         caller: &mut Caller,
@@ -684,11 +894,25 @@ where
     fn schedule_operation(caller: &mut Caller, operation: Vec<u8>) -> Result<(), RuntimeError> {
         caller
             .user_data_mut()
-            .runtime
+            .runtime_mut()
             .schedule_operation(operation)
             .map_err(|error| RuntimeError::Custom(error.into()))
     }
 
+    /// Schedules an operation to be included in the block being built by this query, with
+    /// a description to show to the user.
+    fn schedule_operation_with_metadata(
+        caller: &mut Caller,
+        operation: Vec<u8>,
+        metadata: OperationMetadata,
+    ) -> Result<(), RuntimeError> {
+        caller
+            .user_data_mut()
+            .runtime_mut()
+            .schedule_operation_with_metadata(operation, metadata)
+            .map_err(|error| RuntimeError::Custom(error.into()))
+    }
+
     /// Queries another application.
     fn try_query_application(
         caller: &mut Caller,
@@ -697,11 +921,29 @@ where
     ) -> Result<Vec<u8>, RuntimeError> {
         caller
             .user_data_mut()
-            .runtime
+            .runtime_mut()
             .try_query_application(application, argument)
             .map_err(|error| RuntimeError::Custom(error.into()))
     }
 
+    /// Queries an application of this chain as it was right after the block at `height`.
+    /// Returns `None` if that state is not retained anymore.
+    fn query_application_at(
+        caller: &mut Caller,
+        application: ApplicationId,
+        height: BlockHeight,
+        argument: Vec<u8>,
+    ) -> Result<Option<Vec<u8>>, RuntimeError> {
+        caller
+            .user_data_mut()
+            .require_api_version("query-application-at", QUERY_APPLICATION_AT_SINCE)?;
+        caller
+            .user_data_mut()
+            .runtime_mut()
+            .query_application_at(application, height, argument)
+            .map_err(|error| RuntimeError::Custom(error.into()))
+    }
+
     /// Checks if the service has exceeded its execution time limit.
     ///
     /// This is called by the metering instrumentation, but the fuel consumed argument is
@@ -714,3 +956,38 @@ where
             .map_err(|error| RuntimeError::Custom(error.into()))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use linera_base::vm::WasmApiVersion;
+
+    use super::{
+        RuntimeApiData, DRAW_ENTROPY_SINCE, QUERY_APPLICATION_AT_SINCE,
+        READ_APPLICATION_CONFIG_SINCE, READ_EPOCH_SINCE, REENTRANCY_POLICY_SINCE, TRY_CALL_SINCE,
+        VIEW_CALL_SINCE,
+    };
+
+    #[test]
+    fn test_imports_require_their_api_version() {
+        let imports = [
+            ("read-epoch", READ_EPOCH_SINCE),
+            ("draw-entropy", DRAW_ENTROPY_SINCE),
+            ("declare-reentrancy-policy", REENTRANCY_POLICY_SINCE),
+            ("try-call", TRY_CALL_SINCE),
+            ("try-view-call", VIEW_CALL_SINCE),
+            ("read-application-config", READ_APPLICATION_CONFIG_SINCE),
+            ("query-application-at", QUERY_APPLICATION_AT_SINCE),
+        ];
+        for (name, since) in imports {
+            let mut older =
+                RuntimeApiData::new((), WasmApiVersion::new(since.major, since.minor - 1));
+            assert!(older.require_api_version(name, since).is_err());
+            // Reusing a pooled instance keeps the version of its module.
+            older.attach(());
+            assert!(older.require_api_version(name, since).is_err());
+
+            let current = RuntimeApiData::new((), WasmApiVersion::CURRENT);
+            assert!(current.require_api_version(name, since).is_ok());
+        }
+    }
+}
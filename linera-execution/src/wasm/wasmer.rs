@@ -5,7 +5,10 @@
 
 use std::{marker::Unpin, sync::LazyLock};
 
-use linera_base::data_types::{Bytecode, StreamUpdate};
+use linera_base::{
+    data_types::{Bytecode, StreamUpdate},
+    vm::WasmApiVersion,
+};
 use linera_witty::{
     wasmer::{EntrypointInstance, InstanceBuilder},
     ExportTo,
@@ -13,6 +16,9 @@ use linera_witty::{
 use tokio::sync::Mutex;
 
 use super::{
+    api_version::{
+        AUTHORIZE_PROPOSAL_SINCE, ON_CHAIN_CLOSE_SINCE, ON_UPGRADE_SINCE, VIEW_CALL_SINCE,
+    },
     module_cache::ModuleCache,
     runtime_api::{BaseRuntimeApi, ContractRuntimeApi, RuntimeApiData, ServiceRuntimeApi},
     ContractEntrypoints, ServiceEntrypoints, WasmExecutionError,
@@ -42,10 +48,57 @@ static CONTRACT_CACHE: LazyLock<Mutex<ModuleCache<CachedContractModule>>> =
 /// A cache of compiled service modules.
 static SERVICE_CACHE: LazyLock<Mutex<ModuleCache<wasmer::Module>>> = LazyLock::new(Mutex::default);
 
+/// The configuration of the engine compiling contracts, as part of the key of the compiled
+/// modules on disk. It must change whenever [`CachedContractModule::create_compilation_engine`]
+/// does.
+const CONTRACT_ENGINE_CONFIG: &str = "singlepass canonicalize-nans";
+
+/// The configuration of [`SERVICE_ENGINE`], as part of the key of the compiled modules on disk.
+const SERVICE_ENGINE_CONFIG: &str = "cranelift";
+
+/// Compiles `bytecode` with `engine`, whose configuration is described by `engine_config`,
+/// unless the compiled module is in the cache on disk.
+fn compile_or_load(
+    namespace: &str,
+    engine: &wasmer::Engine,
+    engine_config: &str,
+    bytecode: Bytecode,
+) -> Result<wasmer::Module, anyhow::Error> {
+    #[cfg(not(web))]
+    {
+        let engine_key = (
+            wasmer::VERSION,
+            engine_config,
+            env!("CARGO_PKG_VERSION"),
+            wasmer::CpuFeature::for_host(),
+        );
+        crate::compiled_module_cache::get_or_compile(
+            namespace,
+            engine_key,
+            &bytecode,
+            || Ok(wasmer::Module::new(engine, &bytecode)?),
+            |module| Ok(module.serialize()?.to_vec()),
+            // SAFETY: The cache only contains modules serialized by the same version of
+            // Wasmer and of this crate, with the same engine configuration, on a host with the
+            // same CPU features, and checks that they are intact.
+            |bytes| Ok(unsafe { wasmer::Module::deserialize(engine, bytes.to_vec()) }?),
+        )
+    }
+
+    #[cfg(web)]
+    {
+        let _ = (namespace, engine_config);
+        Ok(wasmer::Module::new(engine, bytecode)?)
+    }
+}
+
 /// Type representing a running [Wasmer](https://wasmer.io/) contract.
 pub(crate) struct WasmerContractInstance<Runtime> {
     /// The Wasmer instance.
     instance: EntrypointInstance<RuntimeApiData<Runtime>>,
+
+    /// The version of the API the contract was built against.
+    api_version: WasmApiVersion,
 }
 
 /// Type representing a running [Wasmer](https://wasmer.io/) service.
@@ -56,14 +109,21 @@ pub struct WasmerServiceInstance<Runtime> {
 
 impl WasmContractModule {
     /// Creates a new [`WasmContractModule`] using Wasmer with the provided bytecode files.
-    pub async fn from_wasmer(contract_bytecode: Bytecode) -> Result<Self, WasmExecutionError> {
+    pub async fn from_wasmer(
+        contract_bytecode: Bytecode,
+        api_version: WasmApiVersion,
+    ) -> Result<Self, WasmExecutionError> {
         let mut contract_cache = CONTRACT_CACHE.lock().await;
         let (engine, module) = contract_cache
             .get_or_insert_with(contract_bytecode, CachedContractModule::new)
             .map_err(WasmExecutionError::LoadContractModule)?
             .create_execution_instance()
             .map_err(WasmExecutionError::LoadContractModule)?;
-        Ok(WasmContractModule::Wasmer { engine, module })
+        Ok(WasmContractModule::Wasmer {
+            engine,
+            module,
+            api_version,
+        })
     }
 }
 
@@ -75,9 +135,10 @@ where
     pub fn prepare(
         contract_engine: wasmer::Engine,
         contract_module: &wasmer::Module,
+        api_version: WasmApiVersion,
         runtime: Runtime,
     ) -> Result<Self, WasmExecutionError> {
-        let system_api_data = RuntimeApiData::new(runtime);
+        let system_api_data = RuntimeApiData::new(runtime, api_version);
         let mut instance_builder = InstanceBuilder::new(contract_engine, system_api_data);
 
         BaseRuntimeApi::export_to(&mut instance_builder)?;
@@ -85,20 +146,34 @@ where
 
         let instance = instance_builder.instantiate(contract_module)?;
 
-        Ok(Self { instance })
+        Ok(Self {
+            instance,
+            api_version,
+        })
     }
 }
 
 impl WasmServiceModule {
     /// Creates a new [`WasmServiceModule`] using Wasmer with the provided bytecode files.
-    pub async fn from_wasmer(service_bytecode: Bytecode) -> Result<Self, WasmExecutionError> {
+    pub async fn from_wasmer(
+        service_bytecode: Bytecode,
+        api_version: WasmApiVersion,
+    ) -> Result<Self, WasmExecutionError> {
         let mut service_cache = SERVICE_CACHE.lock().await;
         let module = service_cache
             .get_or_insert_with(service_bytecode, |bytecode| {
-                wasmer::Module::new(&*SERVICE_ENGINE, bytecode).map_err(anyhow::Error::from)
+                compile_or_load(
+                    "wasmer-service",
+                    &SERVICE_ENGINE,
+                    SERVICE_ENGINE_CONFIG,
+                    bytecode,
+                )
             })
             .map_err(WasmExecutionError::LoadServiceModule)?;
-        Ok(WasmServiceModule::Wasmer { module })
+        Ok(WasmServiceModule::Wasmer {
+            module,
+            api_version,
+        })
     }
 }
 
@@ -109,9 +184,10 @@ where
     /// Prepares a runtime instance to call into the Wasm service.
     pub fn prepare(
         service_module: &wasmer::Module,
+        api_version: WasmApiVersion,
         runtime: Runtime,
     ) -> Result<Self, WasmExecutionError> {
-        let system_api_data = RuntimeApiData::new(runtime);
+        let system_api_data = RuntimeApiData::new(runtime, api_version);
         let mut instance_builder = InstanceBuilder::new(SERVICE_ENGINE.clone(), system_api_data);
 
         BaseRuntimeApi::export_to(&mut instance_builder)?;
@@ -154,6 +230,50 @@ where
         Ok(())
     }
 
+    fn on_chain_close(&mut self) -> Result<(), ExecutionError> {
+        // Contracts built against older versions of the API don't have this entrypoint.
+        if self.api_version < ON_CHAIN_CLOSE_SINCE {
+            return Ok(());
+        }
+        ContractEntrypoints::new(&mut self.instance)
+            .on_chain_close()
+            .map_err(WasmExecutionError::from)?;
+        Ok(())
+    }
+
+    fn on_upgrade(&mut self) -> Result<(), ExecutionError> {
+        // Contracts built against older versions of the API don't have this entrypoint.
+        if self.api_version < ON_UPGRADE_SINCE {
+            return Ok(());
+        }
+        ContractEntrypoints::new(&mut self.instance)
+            .on_upgrade()
+            .map_err(WasmExecutionError::from)?;
+        Ok(())
+    }
+
+    fn authorize_proposal(&mut self, payload: Vec<u8>) -> Result<bool, ExecutionError> {
+        // Contracts built against older versions of the API can't authorize proposals.
+        if self.api_version < AUTHORIZE_PROPOSAL_SINCE {
+            return Ok(false);
+        }
+        let authorized = ContractEntrypoints::new(&mut self.instance)
+            .authorize_proposal(payload)
+            .map_err(WasmExecutionError::from)?;
+        Ok(authorized)
+    }
+
+    fn execute_view(&mut self, argument: Vec<u8>) -> Result<Vec<u8>, ExecutionError> {
+        // Contracts built against older versions of the API have no view functions.
+        if self.api_version < VIEW_CALL_SINCE {
+            return Err(ExecutionError::ViewCallsNotSupported);
+        }
+        let response = ContractEntrypoints::new(&mut self.instance)
+            .execute_view(argument)
+            .map_err(WasmExecutionError::from)?;
+        Ok(response)
+    }
+
     fn finalize(&mut self) -> Result<(), ExecutionError> {
         ContractEntrypoints::new(&mut self.instance)
             .finalize()
@@ -194,7 +314,12 @@ pub struct CachedContractModule(wasmer::Module);
 impl CachedContractModule {
     /// Creates a new [`CachedContractModule`] by compiling a `contract_bytecode`.
     pub fn new(contract_bytecode: Bytecode) -> Result<Self, anyhow::Error> {
-        let module = wasmer::Module::new(&Self::create_compilation_engine(), contract_bytecode)?;
+        let module = compile_or_load(
+            "wasmer-contract",
+            &Self::create_compilation_engine(),
+            CONTRACT_ENGINE_CONFIG,
+            contract_bytecode,
+        )?;
         Ok(CachedContractModule(module))
     }
 
@@ -5,19 +5,30 @@
 
 use std::sync::LazyLock;
 
-use linera_base::data_types::{Bytecode, StreamUpdate};
+use linera_base::{
+    data_types::{Bytecode, StreamUpdate},
+    vm::WasmApiVersion,
+};
 use linera_witty::{wasmtime::EntrypointInstance, ExportTo};
 use tokio::sync::Mutex;
-use wasmtime::{Config, Engine, Linker, Module, Store};
+use wasmtime::{
+    AsContextMut, Config, Engine, Export, Extern, Func, Global, Instance, Linker, Memory, Module,
+    Mutability, Ref, Store, StoreContextMut, Table, Val,
+};
 
 use super::{
+    api_version::{
+        AUTHORIZE_PROPOSAL_SINCE, ON_CHAIN_CLOSE_SINCE, ON_UPGRADE_SINCE, VIEW_CALL_SINCE,
+    },
+    instance_pool::ModulePoolId,
     module_cache::ModuleCache,
     runtime_api::{BaseRuntimeApi, ContractRuntimeApi, RuntimeApiData, ServiceRuntimeApi},
     ContractEntrypoints, ServiceEntrypoints, WasmExecutionError,
 };
 use crate::{
+    compiled_module_cache,
     wasm::{WasmContractModule, WasmServiceModule},
-    ContractRuntime, ExecutionError, ServiceRuntime,
+    ContractRuntime, ContractSyncRuntimeHandle, ExecutionError, ServiceRuntime,
 };
 
 /// An [`Engine`] instance configured to run application contracts.
@@ -37,6 +48,24 @@ static CONTRACT_CACHE: LazyLock<Mutex<ModuleCache<Module>>> = LazyLock::new(Mute
 /// A cache of compiled service modules.
 static SERVICE_CACHE: LazyLock<Mutex<ModuleCache<Module>>> = LazyLock::new(Mutex::default);
 
+/// Compiles `bytecode` with `engine`, unless the compiled module is in the cache on disk.
+fn compile_or_load(
+    namespace: &str,
+    engine: &Engine,
+    bytecode: &Bytecode,
+) -> anyhow::Result<Module> {
+    compiled_module_cache::get_or_compile(
+        namespace,
+        engine.precompile_compatibility_hash(),
+        bytecode,
+        || Module::new(engine, bytecode),
+        Module::serialize,
+        // SAFETY: The cache only contains modules serialized by an engine with the same
+        // compatibility hash, and checks that their contents are intact.
+        |bytes| unsafe { Module::deserialize(engine, bytes) },
+    )
+}
+
 /// Type representing a running [Wasmtime](https://wasmtime.dev/) contract.
 ///
 /// The runtime has a lifetime so that it does not outlive the trait object used to export the
@@ -47,6 +76,9 @@ where
 {
     /// The Wasm module instance.
     instance: EntrypointInstance<RuntimeApiData<Runtime>>,
+
+    /// The version of the API the contract was built against.
+    api_version: WasmApiVersion,
 }
 
 /// Type representing a running [Wasmtime](https://wasmtime.dev/) service.
@@ -57,14 +89,21 @@ pub struct WasmtimeServiceInstance<Runtime> {
 
 impl WasmContractModule {
     /// Creates a new [`WasmContractModule`] using Wasmtime with the provided bytecode files.
-    pub async fn from_wasmtime(contract_bytecode: Bytecode) -> Result<Self, WasmExecutionError> {
+    pub async fn from_wasmtime(
+        contract_bytecode: Bytecode,
+        api_version: WasmApiVersion,
+    ) -> Result<Self, WasmExecutionError> {
         let mut contract_cache = CONTRACT_CACHE.lock().await;
         let module = contract_cache
             .get_or_insert_with(contract_bytecode, |bytecode| {
-                Module::new(&CONTRACT_ENGINE, bytecode)
+                compile_or_load("wasmtime-contract", &CONTRACT_ENGINE, &bytecode)
             })
             .map_err(WasmExecutionError::LoadContractModule)?;
-        Ok(WasmContractModule::Wasmtime { module })
+        Ok(WasmContractModule::Wasmtime {
+            module,
+            api_version,
+            pool_id: ModulePoolId::unique(),
+        })
     }
 }
 
@@ -73,34 +112,175 @@ where
     Runtime: ContractRuntime + 'static,
 {
     /// Prepares a runtime instance to call into the Wasm contract.
-    pub fn prepare(contract_module: &Module, runtime: Runtime) -> Result<Self, WasmExecutionError> {
+    pub fn prepare(
+        contract_module: &Module,
+        api_version: WasmApiVersion,
+        runtime: Runtime,
+    ) -> Result<Self, WasmExecutionError> {
+        let (instance, store) = Self::instantiate_module(contract_module, api_version, runtime)?;
+
+        Ok(Self {
+            instance: EntrypointInstance::new(instance, store),
+            api_version,
+        })
+    }
+
+    /// Creates an instance of `contract_module` in a new [`Store`].
+    fn instantiate_module(
+        contract_module: &Module,
+        api_version: WasmApiVersion,
+        runtime: Runtime,
+    ) -> Result<(Instance, Store<RuntimeApiData<Runtime>>), WasmExecutionError> {
         let mut linker = Linker::new(&CONTRACT_ENGINE);
 
         BaseRuntimeApi::export_to(&mut linker)?;
         ContractRuntimeApi::export_to(&mut linker)?;
 
-        let user_data = RuntimeApiData::new(runtime);
+        let user_data = RuntimeApiData::new(runtime, api_version);
         let mut store = Store::new(&CONTRACT_ENGINE, user_data);
         let instance = linker
             .instantiate(&mut store, contract_module)
             .map_err(WasmExecutionError::LoadContractModule)?;
 
-        Ok(Self {
+        Ok((instance, store))
+    }
+}
+
+impl WasmtimeContractInstance<ContractSyncRuntimeHandle> {
+    /// Prepares a runtime instance like [`Self::prepare`], together with a snapshot of its
+    /// initial state if the instance can be reset to it.
+    pub(crate) fn prepare_with_snapshot(
+        contract_module: &Module,
+        api_version: WasmApiVersion,
+        runtime: ContractSyncRuntimeHandle,
+    ) -> Result<(Self, Option<InstanceSnapshot>), WasmExecutionError> {
+        let (instance, mut store) =
+            Self::instantiate_module(contract_module, api_version, runtime)?;
+        let snapshot = InstanceSnapshot::save(instance, store.as_context_mut());
+
+        let contract = Self {
             instance: EntrypointInstance::new(instance, store),
-        })
+            api_version,
+        };
+        Ok((contract, snapshot))
+    }
+
+    /// Makes the instance use `runtime` for its next calls.
+    pub(crate) fn attach(&mut self, runtime: ContractSyncRuntimeHandle) {
+        self.instance.as_context_mut().data_mut().attach(runtime);
+    }
+
+    /// Detaches the instance from its runtime and resets it to the state in `snapshot`.
+    /// Returns `false` if the instance could not be reset and must be discarded.
+    pub(crate) fn reset(&mut self, snapshot: &InstanceSnapshot) -> bool {
+        let mut store = self.instance.as_context_mut();
+        store.data_mut().detach();
+        snapshot.restore(store)
+    }
+}
+
+/// The state of a contract instance right after it was created.
+///
+/// Instrumentation exports all the globals, tables and memories of contract modules, so that
+/// saving the exported ones saves the whole state of an instance.
+pub(crate) struct InstanceSnapshot {
+    memories: Vec<(Memory, Vec<u8>)>,
+    globals: Vec<(Global, Val)>,
+    tables: Vec<(Table, Vec<Option<Func>>)>,
+}
+
+impl InstanceSnapshot {
+    /// Saves the state of `instance`, or returns `None` if it holds values that cannot be
+    /// restored, such as external references.
+    fn save<T>(instance: Instance, mut store: StoreContextMut<'_, T>) -> Option<Self> {
+        let exports = instance
+            .exports(&mut store)
+            .map(Export::into_extern)
+            .collect::<Vec<_>>();
+        let mut snapshot = InstanceSnapshot {
+            memories: Vec::new(),
+            globals: Vec::new(),
+            tables: Vec::new(),
+        };
+        for export in exports {
+            match export {
+                Extern::Func(_) => {}
+                Extern::Memory(memory) => {
+                    let contents = memory.data(&store).to_vec();
+                    snapshot.memories.push((memory, contents));
+                }
+                Extern::Global(global) => {
+                    if global.ty(&store).mutability() == Mutability::Const {
+                        continue;
+                    }
+                    let value = global.get(&mut store);
+                    if !matches!(
+                        value,
+                        Val::I32(_) | Val::I64(_) | Val::F32(_) | Val::F64(_) | Val::V128(_)
+                    ) {
+                        return None;
+                    }
+                    snapshot.globals.push((global, value));
+                }
+                Extern::Table(table) => {
+                    let entries = (0..table.size(&store))
+                        .map(|index| match table.get(&mut store, index) {
+                            Some(Ref::Func(function)) => Some(function),
+                            _ => None,
+                        })
+                        .collect::<Option<Vec<_>>>()?;
+                    snapshot.tables.push((table, entries));
+                }
+                _ => return None,
+            }
+        }
+        Some(snapshot)
+    }
+
+    /// Restores the saved state in `store`. Returns `false` if it is not possible, because a
+    /// memory or a table grew, and they cannot shrink.
+    fn restore<T>(&self, mut store: StoreContextMut<'_, T>) -> bool {
+        for (memory, contents) in &self.memories {
+            if memory.data_size(&store) != contents.len() {
+                return false;
+            }
+            memory.data_mut(&mut store).copy_from_slice(contents);
+        }
+        for (global, value) in &self.globals {
+            if global.set(&mut store, value.clone()).is_err() {
+                return false;
+            }
+        }
+        for (table, entries) in &self.tables {
+            if table.size(&store) as usize != entries.len() {
+                return false;
+            }
+            for (index, function) in (0..).zip(entries) {
+                if table.set(&mut store, index, Ref::Func(*function)).is_err() {
+                    return false;
+                }
+            }
+        }
+        true
     }
 }
 
 impl WasmServiceModule {
     /// Creates a new [`WasmServiceModule`] using Wasmtime with the provided bytecode files.
-    pub async fn from_wasmtime(service_bytecode: Bytecode) -> Result<Self, WasmExecutionError> {
+    pub async fn from_wasmtime(
+        service_bytecode: Bytecode,
+        api_version: WasmApiVersion,
+    ) -> Result<Self, WasmExecutionError> {
         let mut service_cache = SERVICE_CACHE.lock().await;
         let module = service_cache
             .get_or_insert_with(service_bytecode, |bytecode| {
-                Module::new(&SERVICE_ENGINE, bytecode)
+                compile_or_load("wasmtime-service", &SERVICE_ENGINE, &bytecode)
             })
             .map_err(WasmExecutionError::LoadServiceModule)?;
-        Ok(WasmServiceModule::Wasmtime { module })
+        Ok(WasmServiceModule::Wasmtime {
+            module,
+            api_version,
+        })
     }
 }
 
@@ -109,13 +289,17 @@ where
     Runtime: ServiceRuntime + 'static,
 {
     /// Prepares a runtime instance to call into the Wasm service.
-    pub fn prepare(service_module: &Module, runtime: Runtime) -> Result<Self, WasmExecutionError> {
+    pub fn prepare(
+        service_module: &Module,
+        api_version: WasmApiVersion,
+        runtime: Runtime,
+    ) -> Result<Self, WasmExecutionError> {
         let mut linker = Linker::new(&SERVICE_ENGINE);
 
         BaseRuntimeApi::export_to(&mut linker)?;
         ServiceRuntimeApi::export_to(&mut linker)?;
 
-        let user_data = RuntimeApiData::new(runtime);
+        let user_data = RuntimeApiData::new(runtime, api_version);
         let mut store = Store::new(&SERVICE_ENGINE, user_data);
         let instance = linker
             .instantiate(&mut store, service_module)
@@ -159,6 +343,50 @@ where
         Ok(())
     }
 
+    fn on_chain_close(&mut self) -> Result<(), ExecutionError> {
+        // Contracts built against older versions of the API don't have this entrypoint.
+        if self.api_version < ON_CHAIN_CLOSE_SINCE {
+            return Ok(());
+        }
+        ContractEntrypoints::new(&mut self.instance)
+            .on_chain_close()
+            .map_err(WasmExecutionError::from)?;
+        Ok(())
+    }
+
+    fn on_upgrade(&mut self) -> Result<(), ExecutionError> {
+        // Contracts built against older versions of the API don't have this entrypoint.
+        if self.api_version < ON_UPGRADE_SINCE {
+            return Ok(());
+        }
+        ContractEntrypoints::new(&mut self.instance)
+            .on_upgrade()
+            .map_err(WasmExecutionError::from)?;
+        Ok(())
+    }
+
+    fn authorize_proposal(&mut self, payload: Vec<u8>) -> Result<bool, ExecutionError> {
+        // Contracts built against older versions of the API can't authorize proposals.
+        if self.api_version < AUTHORIZE_PROPOSAL_SINCE {
+            return Ok(false);
+        }
+        let authorized = ContractEntrypoints::new(&mut self.instance)
+            .authorize_proposal(payload)
+            .map_err(WasmExecutionError::from)?;
+        Ok(authorized)
+    }
+
+    fn execute_view(&mut self, argument: Vec<u8>) -> Result<Vec<u8>, ExecutionError> {
+        // Contracts built against older versions of the API have no view functions.
+        if self.api_version < VIEW_CALL_SINCE {
+            return Err(ExecutionError::ViewCallsNotSupported);
+        }
+        let response = ContractEntrypoints::new(&mut self.instance)
+            .execute_view(argument)
+            .map_err(WasmExecutionError::from)?;
+        Ok(response)
+    }
+
     fn finalize(&mut self) -> Result<(), ExecutionError> {
         ContractEntrypoints::new(&mut self.instance)
             .finalize()
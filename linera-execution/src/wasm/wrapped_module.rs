@@ -0,0 +1,242 @@
+// Copyright (c) Zefchain Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! Support for application modules wrapped into WebAssembly components.
+//!
+//! This is not support for the component model: the runtime API is not linked to components.
+//! Only components that `wit-component` makes of a core module built with the SDK are
+//! accepted, and only that core module is executed, like any other application module. The
+//! runtime API is already implemented with the canonical ABI for core modules, so the module
+//! needs none of the lifting and lowering that the component adds around it.
+//!
+//! The other core modules that `wit-component` adds to such a component only forward the calls
+//! of the main module to the imported interfaces, and are dropped. Components that need
+//! anything else, such as a WASI adapter or code of their own outside of the main module, are
+//! rejected.
+
+use linera_base::data_types::Bytecode;
+use wasmparser::{Operator, Parser, Payload, Validator, WasmFeatures};
+
+use super::WasmExecutionError;
+
+/// The package of the interfaces between applications and the runtime.
+const INTERFACE_PACKAGE: &str = "linera:app/";
+
+/// The kind of application implemented by a component.
+#[derive(Clone, Copy, Debug)]
+pub(crate) enum ApplicationKind {
+    Contract,
+    Service,
+}
+
+impl ApplicationKind {
+    /// Returns the name of the interface the component must export.
+    fn entrypoints(self) -> &'static str {
+        match self {
+            ApplicationKind::Contract => "linera:app/contract-entrypoints",
+            ApplicationKind::Service => "linera:app/service-entrypoints",
+        }
+    }
+}
+
+/// Returns the core module to execute for an application: the `bytecode` itself if it is a
+/// core module, or the wrapped module if it is a component made of an SDK module.
+pub(crate) fn unwrap_module(
+    bytecode: Bytecode,
+    kind: ApplicationKind,
+) -> Result<Bytecode, WasmExecutionError> {
+    let bytes = &bytecode.bytes;
+    if !Parser::is_component(bytes) {
+        return Ok(bytecode);
+    }
+    Validator::new_with_features(WasmFeatures::default()).validate_all(bytes)?;
+    let mut core_modules = Vec::new();
+    let mut imports = Vec::new();
+    let mut exports = Vec::new();
+    for payload in Parser::new(0).parse_all(bytes) {
+        match payload? {
+            Payload::ModuleSection { range, .. } => core_modules.push(&bytes[range]),
+            Payload::ComponentSection { .. } => {
+                return Err(WasmExecutionError::UnsupportedComponent(
+                    "it contains nested components".to_owned(),
+                ))
+            }
+            Payload::ComponentImportSection(reader) => {
+                for import in reader {
+                    imports.push(import?.name.0);
+                }
+            }
+            Payload::ComponentExportSection(reader) => {
+                for export in reader {
+                    exports.push(export?.name.0);
+                }
+            }
+            _ => {}
+        }
+    }
+    if let Some(import) = imports
+        .iter()
+        .find(|import| !import.starts_with(INTERFACE_PACKAGE))
+    {
+        return Err(WasmExecutionError::UnsupportedComponent(format!(
+            "it imports `{import}`"
+        )));
+    }
+    let entrypoints = kind.entrypoints();
+    if !exports
+        .iter()
+        .any(|export| without_version(export) == entrypoints)
+    {
+        return Err(WasmExecutionError::UnsupportedComponent(format!(
+            "it does not export `{entrypoints}`"
+        )));
+    }
+    // The main module is the only one with a memory: the modules generated to forward calls
+    // don't need one.
+    let mut main_module = None;
+    for module in core_modules {
+        if defines_memory(module)? {
+            if main_module.replace(module).is_some() {
+                return Err(WasmExecutionError::UnsupportedComponent(
+                    "several of its core modules have a memory".to_owned(),
+                ));
+            }
+        } else if !only_forwards_calls(module)? {
+            return Err(WasmExecutionError::UnsupportedComponent(
+                "its core modules other than the main one have code of their own".to_owned(),
+            ));
+        }
+    }
+    let main_module = main_module.ok_or_else(|| {
+        WasmExecutionError::UnsupportedComponent("none of its core modules has a memory".to_owned())
+    })?;
+    Ok(Bytecode::new(main_module.to_vec()))
+}
+
+/// Returns whether the functions of a core module only forward their arguments to a function
+/// of a table, as the modules generated by `wit-component` do, since they are not executed.
+fn only_forwards_calls(module: &[u8]) -> Result<bool, WasmExecutionError> {
+    for payload in Parser::new(0).parse_all(module) {
+        match payload? {
+            Payload::StartSection { .. } => return Ok(false),
+            Payload::CodeSectionEntry(body) => {
+                for operator in body.get_operators_reader()? {
+                    match operator? {
+                        Operator::LocalGet { .. }
+                        | Operator::I32Const { .. }
+                        | Operator::CallIndirect { .. }
+                        | Operator::End => {}
+                        _ => return Ok(false),
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    Ok(true)
+}
+
+/// Returns whether a core module defines a memory.
+fn defines_memory(module: &[u8]) -> Result<bool, WasmExecutionError> {
+    for payload in Parser::new(0).parse_all(module) {
+        if let Payload::MemorySection(reader) = payload? {
+            if reader.count() > 0 {
+                return Ok(true);
+            }
+        }
+    }
+    Ok(false)
+}
+
+/// Returns the name of an interface without its version, if any.
+fn without_version(name: &str) -> &str {
+    name.split_once('@').map_or(name, |(name, _)| name)
+}
+
+#[cfg(test)]
+mod tests {
+    use linera_base::data_types::Bytecode;
+
+    use super::{only_forwards_calls, unwrap_module, ApplicationKind};
+    use crate::wasm::{api_version, WasmExecutionError};
+
+    const COUNTER_CONTRACT: &[u8] = include_bytes!("../../tests/fixtures/counter_contract.wasm");
+    const COUNTER_SERVICE: &[u8] = include_bytes!("../../tests/fixtures/counter_service.wasm");
+
+    /// Returns the component that `wit-component` makes of a module built with the SDK.
+    fn component(module: &[u8]) -> Bytecode {
+        let component = wit_component::ComponentEncoder::default()
+            .module(module)
+            .unwrap()
+            .validate(true)
+            .encode()
+            .unwrap();
+        Bytecode::new(component)
+    }
+
+    #[test]
+    fn test_wrapped_module_of_component() {
+        let contract = component(COUNTER_CONTRACT);
+        let main_module = unwrap_module(contract.clone(), ApplicationKind::Contract).unwrap();
+        assert_ne!(main_module, contract);
+        api_version::check_api_version(&main_module).unwrap();
+        assert!(matches!(
+            unwrap_module(contract, ApplicationKind::Service),
+            Err(WasmExecutionError::UnsupportedComponent(_))
+        ));
+
+        let service = component(COUNTER_SERVICE);
+        let main_module = unwrap_module(service, ApplicationKind::Service).unwrap();
+        api_version::check_api_version(&main_module).unwrap();
+
+        let module = Bytecode::new(COUNTER_CONTRACT.to_vec());
+        assert_eq!(
+            unwrap_module(module.clone(), ApplicationKind::Contract).unwrap(),
+            module
+        );
+    }
+
+    #[test]
+    fn test_modules_with_code_of_their_own_are_detected() {
+        let shim = wasmer::wat2wasm(
+            br#"(module
+                (type (func (param i32)))
+                (table 1 1 funcref)
+                (func (export "0") (param i32) local.get 0 i32.const 0 call_indirect (type 0))
+            )"#,
+        )
+        .unwrap();
+        assert!(only_forwards_calls(&shim).unwrap());
+        let module =
+            wasmer::wat2wasm(br#"(module (func (export "0") (param i32) local.get 0 drop))"#)
+                .unwrap();
+        assert!(!only_forwards_calls(&module).unwrap());
+    }
+
+    #[test]
+    fn test_components_importing_other_interfaces_are_rejected() {
+        let mut bytes = vec![0x00, 0x61, 0x73, 0x6d, 0x0d, 0x00, 0x01, 0x00];
+        // A type section with an empty instance type.
+        bytes.extend([0x07, 0x03, 0x01, 0x42, 0x00]);
+        // An import section with an instance of that type.
+        let name = b"wasi:io/streams@0.2.0";
+        bytes.extend([0x0a, name.len() as u8 + 5, 0x01, 0x00, name.len() as u8]);
+        bytes.extend(name);
+        bytes.extend([0x05, 0x00]);
+        assert!(matches!(
+            unwrap_module(Bytecode::new(bytes), ApplicationKind::Contract),
+            Err(WasmExecutionError::UnsupportedComponent(message))
+                if message.contains("wasi:io/streams")
+        ));
+    }
+
+    #[test]
+    fn test_invalid_components_are_rejected() {
+        let mut bytes = component(COUNTER_CONTRACT).bytes;
+        bytes.truncate(bytes.len() / 2);
+        assert!(matches!(
+            unwrap_module(Bytecode::new(bytes), ApplicationKind::Contract),
+            Err(WasmExecutionError::InvalidComponent(_))
+        ));
+    }
+}
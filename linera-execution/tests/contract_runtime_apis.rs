@@ -5,6 +5,7 @@
 
 use std::{
     collections::{BTreeMap, BTreeSet},
+    sync::Arc,
     vec,
 };
 
@@ -27,8 +28,8 @@ use linera_execution::{
         RegisterMockApplication, SystemExecutionState,
     },
     BaseRuntime, ContractRuntime, ExecutionError, Message, MessageContext, Operation,
-    OperationContext, ResourceController, SystemExecutionStateView, TestExecutionRuntimeContext,
-    TransactionOutcome, TransactionTracker,
+    OperationContext, ResourceControlPolicy, ResourceController, ResourceTracker,
+    SystemExecutionStateView, TestExecutionRuntimeContext, TransactionOutcome, TransactionTracker,
 };
 use linera_views::context::MemoryContext;
 use test_case::{test_case, test_matrix};
@@ -945,6 +946,96 @@ async fn test_perform_http_request(authorized_apps: Option<Vec<()>>) -> Result<(
     Ok(())
 }
 
+/// Tests the contract system API to read values of the application's state on other chains.
+#[test_case(u64::MAX, false => matches Ok(_); "when allowed")]
+#[test_case(
+    0, false => matches Err(ExecutionError::MaximumCrossChainReadsExceeded);
+    "when disabled"
+)]
+#[test_case(
+    u64::MAX, true => matches Err(ExecutionError::OracleResponseMismatch);
+    "when recorded for another application"
+)]
+#[test_log::test(tokio::test)]
+async fn test_read_value_bytes_on_chain(
+    maximum_cross_chain_reads_per_block: u64,
+    recorded_for_other_application: bool,
+) -> Result<(), ExecutionError> {
+    let description = dummy_chain_description(0);
+    let chain_id = description.id();
+    let other_chain_id = dummy_chain_description(1).id();
+    let mut view = SystemExecutionState {
+        ownership: ChainOwnership::default(),
+        balance: Amount::ONE,
+        balances: BTreeMap::new(),
+        ..SystemExecutionState::new(description)
+    }
+    .into_view()
+    .await;
+
+    let contract_blob = TransferTestEndpoint::sender_application_contract_blob();
+    let service_blob = TransferTestEndpoint::sender_application_service_blob();
+    let contract_blob_id = contract_blob.id();
+    let service_blob_id = service_blob.id();
+
+    let application_description = TransferTestEndpoint::sender_application_description();
+    let application_description_blob = Blob::new_application_description(&application_description);
+    let app_desc_blob_id = application_description_blob.id();
+
+    let (application_id, application) = view
+        .register_mock_application_with(application_description, contract_blob, service_blob)
+        .await
+        .expect("should register mock application");
+
+    application.expect_call(ExpectedCall::execute_operation(
+        move |runtime, _operation| {
+            let value = runtime.read_value_bytes_on_chain(other_chain_id, b"key".to_vec())?;
+            assert_eq!(value, Some(b"value".to_vec()));
+            Ok(vec![])
+        },
+    ));
+    application.expect_call(ExpectedCall::default_finalize());
+
+    let context = create_dummy_operation_context(chain_id);
+    let policy = ResourceControlPolicy {
+        maximum_cross_chain_reads_per_block,
+        ..ResourceControlPolicy::default()
+    };
+    let mut controller =
+        ResourceController::new(Arc::new(policy), ResourceTracker::default(), None);
+    let operation = Operation::User {
+        application_id,
+        bytes: vec![],
+    };
+
+    let recorded_application_id = if recorded_for_other_application {
+        ApplicationId::new(CryptoHash::test_hash("other application"))
+    } else {
+        application_id
+    };
+
+    // The value is only known from the recorded oracle response, not from local storage.
+    view.execute_operation(
+        context,
+        operation,
+        &mut TransactionTracker::new_replaying(vec![
+            OracleResponse::Blob(app_desc_blob_id),
+            OracleResponse::Blob(contract_blob_id),
+            OracleResponse::Blob(service_blob_id),
+            OracleResponse::ApplicationValue(
+                other_chain_id,
+                recorded_application_id,
+                b"key".to_vec(),
+                Some(b"value".to_vec()),
+            ),
+        ]),
+        &mut controller,
+    )
+    .await?;
+
+    Ok(())
+}
+
 /// Tests creating multiple data blobs in a single transaction.
 #[test_log::test(tokio::test)]
 async fn test_create_multiple_data_blobs() -> anyhow::Result<()> {
@@ -183,6 +183,17 @@ use test_case::test_case;
     Some(Amount::from_tokens(1_000));
     "with all fee spend operations"
 )]
+#[test_case(
+    vec![
+        FeeSpend::SystemRead,
+        FeeSpend::Fuel(13),
+        FeeSpend::SystemRead,
+    ],
+    Amount::from_tokens(1_000),
+    None,
+    None;
+    "with system reads at a fixed price"
+)]
 // TODO(#1601): Add more test cases
 #[tokio::test]
 async fn test_fee_consumption(
@@ -223,6 +234,7 @@ async fn test_fee_consumption(
         message_byte: Amount::from_tokens(29),
         service_as_oracle_query: Amount::from_millis(31),
         http_request: Amount::from_tokens(37),
+        system_read: Amount::from_millis(41),
         maximum_wasm_fuel_per_block: 4_868_145_137,
         maximum_evm_fuel_per_block: 4_868_145_137,
         maximum_block_size: 41,
@@ -241,6 +253,17 @@ async fn test_fee_consumption(
         blob_byte_read: Amount::from_tokens(101),
         blob_byte_published: Amount::from_tokens(103),
         http_request_allow_list: BTreeSet::new(),
+        allow_wasm_floats: true,
+        wasm_float_contracts: BTreeSet::new(),
+        maximum_cross_chain_reads_per_block: u64::MAX,
+        maximum_incoming_bundle_age_ms: u64::MAX,
+        maximum_authorization_fuel: u64::MAX,
+        maximum_entropy_per_transaction: u64::MAX,
+        maximum_call_depth: u64::MAX,
+        maximum_speculative_calls_per_transaction: u64::MAX,
+        publish_resource_usage: false,
+        merkle_state_hash: false,
+        maximum_channel_subscribers: u64::MAX,
     };
 
     let consumed_fees = spends
@@ -372,13 +395,18 @@ pub enum FeeSpend {
     HttpRequest,
     /// Byte from runtime.
     Runtime(u32),
+    /// Reads the chain balance, at the fixed price of a system read.
+    SystemRead,
 }
 
 impl FeeSpend {
     /// Returns the [`OracleResponse`]s necessary for executing this runtime operation.
     pub fn expected_oracle_responses(&self) -> Vec<OracleResponse> {
         match self {
-            FeeSpend::Fuel(_) | FeeSpend::Read(_, _) | FeeSpend::Runtime(_) => vec![],
+            FeeSpend::Fuel(_)
+            | FeeSpend::Read(_, _)
+            | FeeSpend::Runtime(_)
+            | FeeSpend::SystemRead => vec![],
             FeeSpend::QueryServiceOracle => {
                 vec![OracleResponse::Service(vec![])]
             }
@@ -401,6 +429,7 @@ impl FeeSpend {
             FeeSpend::QueryServiceOracle => policy.service_as_oracle_query,
             FeeSpend::HttpRequest => policy.http_request,
             FeeSpend::Runtime(bytes) => policy.byte_runtime.saturating_mul(*bytes as u128),
+            FeeSpend::SystemRead => policy.system_read,
         }
     }
 
@@ -424,6 +453,10 @@ impl FeeSpend {
                 runtime.perform_http_request(http::Request::get("http://dummy.url"))?;
                 Ok(())
             }
+            FeeSpend::SystemRead => {
+                runtime.read_chain_balance()?;
+                Ok(())
+            }
         }
     }
 }
@@ -5,13 +5,16 @@
 
 use std::{collections::BTreeMap, vec};
 
-use linera_base::{data_types::Amount, identifiers::AccountOwner};
+use linera_base::{
+    data_types::{Amount, BlockHeight},
+    identifiers::AccountOwner,
+};
 use linera_execution::{
     test_utils::{
         create_dummy_query_context, dummy_chain_description, test_accounts_strategy, ExpectedCall,
         RegisterMockApplication, SystemExecutionState,
     },
-    BaseRuntime, Query,
+    BaseRuntime, Query, ServiceRuntime as _,
 };
 use test_strategy::proptest;
 
@@ -163,3 +166,28 @@ async fn test_read_balance_owners_system_api(
 
     view.query_application(context, query, None).await.unwrap();
 }
+
+/// Tests that a service's historical query reports when the state is not retained.
+#[tokio::test]
+async fn test_query_application_at_without_history() {
+    let mut view = SystemExecutionState::new(dummy_chain_description(0))
+        .into_view()
+        .await;
+
+    let (application_id, application, _) = view.register_mock_application(0).await.unwrap();
+
+    application.expect_call(ExpectedCall::handle_query(move |runtime, _query| {
+        let response = runtime.query_application_at(application_id, BlockHeight(0), vec![])?;
+        assert_eq!(response, None);
+        Ok(vec![])
+    }));
+    application.expect_call(ExpectedCall::default_finalize());
+
+    let context = create_dummy_query_context();
+    let query = Query::User {
+        application_id,
+        bytes: vec![],
+    };
+
+    view.query_application(context, query, None).await.unwrap();
+}
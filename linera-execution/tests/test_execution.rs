@@ -3,29 +3,31 @@
 
 #![allow(clippy::field_reassign_with_default)]
 
-use std::{collections::BTreeMap, vec};
+use std::{collections::BTreeMap, sync::Arc, vec};
 
 use assert_matches::assert_matches;
 use linera_base::{
     crypto::{AccountPublicKey, ValidatorPublicKey},
     data_types::{
-        Amount, ApplicationPermissions, Blob, BlockHeight, ChainDescription, ChainOrigin, Epoch,
-        InitialChainConfig, Resources, SendMessageRequest, Timestamp,
+        Amount, ApplicationDescription, ApplicationPermissions, Blob, BlockHeight,
+        ChainDescription, ChainOrigin, Epoch, InitialChainConfig, OperationFees, ReentrancyPolicy,
+        Resources, SendMessageRequest, Timestamp,
     },
-    identifiers::{Account, AccountOwner, BlobType},
+    identifiers::{Account, AccountOwner, ApplicationId, BlobType, StreamName},
     ownership::ChainOwnership,
+    vm::VmRuntime,
 };
 use linera_execution::{
     committee::Committee,
     test_utils::{
         blob_oracle_responses, create_dummy_message_context, create_dummy_operation_context,
-        create_dummy_user_application_registrations, dummy_chain_description,
-        dummy_chain_description_with_ownership_and_balance, ExpectedCall, RegisterMockApplication,
-        SystemExecutionState,
+        create_dummy_user_application_description, create_dummy_user_application_registrations,
+        dummy_chain_description, dummy_chain_description_with_ownership_and_balance, ExpectedCall,
+        MockApplication, RegisterMockApplication, SystemExecutionState,
     },
     BaseRuntime, ContractRuntime, ExecutionError, ExecutionRuntimeContext, Message, Operation,
     OperationContext, OutgoingMessage, Query, QueryContext, QueryOutcome, QueryResponse,
-    ResourceController, SystemOperation, TransactionTracker,
+    ResourceControlPolicy, ResourceController, SystemOperation, TransactionTracker,
 };
 use linera_views::{batch::Batch, context::Context, views::View};
 use test_case::test_case;
@@ -188,6 +190,7 @@ async fn test_simple_user_operation() -> anyhow::Result<()> {
         QueryOutcome {
             response: QueryResponse::User(dummy_operation.clone()),
             operations: vec![],
+            operation_metadata: vec![],
         }
     );
 
@@ -205,6 +208,7 @@ async fn test_simple_user_operation() -> anyhow::Result<()> {
         QueryOutcome {
             response: QueryResponse::User(dummy_operation),
             operations: vec![],
+            operation_metadata: vec![],
         }
     );
     Ok(())
@@ -809,47 +813,36 @@ async fn test_cross_application_error() -> anyhow::Result<()> {
     Ok(())
 }
 
-/// Tests if an application is scheduled to be registered together with any messages it sends to
-/// other chains.
+/// Tests if an application can call a view function of another application, which is not
+/// finalized unless it is also called to modify the state.
 #[tokio::test]
-async fn test_simple_message() -> anyhow::Result<()> {
+async fn test_view_call() -> anyhow::Result<()> {
     let (state, chain_id) = SystemExecutionState::dummy_chain_state(0);
     let mut view = state.into_view().await;
 
-    let (application_id, application, blobs) = view.register_mock_application(0).await?;
-
-    let destination = dummy_chain_description(1).id();
-    let dummy_message = SendMessageRequest {
-        destination,
-        authenticated: false,
-        is_tracked: false,
-        grant: Resources::default(),
-        message: b"msg".to_vec(),
-    };
-
-    let expected_dummy_message = OutgoingMessage::new(
-        destination,
-        Message::User {
-            application_id,
-            bytes: b"msg".to_vec(),
-        },
-    );
+    let (caller_id, caller_application, caller_blobs) = view.register_mock_application(0).await?;
+    let (target_id, target_application, target_blobs) = view.register_mock_application(1).await?;
 
-    application.expect_call(ExpectedCall::execute_operation(
-        move |runtime, _operation| {
-            runtime.send_message(dummy_message)?;
-            Ok(vec![])
-        },
+    caller_application.expect_call(ExpectedCall::execute_operation(
+        move |runtime, _operation| runtime.try_view_call(target_id, b"view".to_vec()),
     ));
-    application.expect_call(ExpectedCall::default_finalize());
+    target_application.expect_call(ExpectedCall::execute_view(|runtime, argument| {
+        assert_eq!(argument, b"view");
+        assert_eq!(runtime.authenticated_caller_id()?, None);
+        // View functions are charged half the fuel they consume.
+        runtime.consume_fuel(101, VmRuntime::Wasm)?;
+        Ok(b"response".to_vec())
+    }));
+    caller_application.expect_call(ExpectedCall::default_finalize());
 
     let context = create_dummy_operation_context(chain_id);
     let mut controller = ResourceController::default();
-    let mut txn_tracker = TransactionTracker::new_replaying_blobs(blobs);
+    let mut txn_tracker =
+        TransactionTracker::new_replaying_blobs(caller_blobs.iter().chain(&target_blobs));
     view.execute_operation(
         context,
         Operation::User {
-            application_id,
+            application_id: caller_id,
             bytes: vec![],
         },
         &mut txn_tracker,
@@ -858,20 +851,15 @@ async fn test_simple_message() -> anyhow::Result<()> {
     .await?;
 
     let txn_outcome = txn_tracker.into_outcome().unwrap();
-    let mut expected = TransactionTracker::default();
-    expected.add_outgoing_message(expected_dummy_message)?;
-    assert_eq!(
-        txn_outcome.outgoing_messages,
-        expected.into_outcome().unwrap().outgoing_messages
-    );
+    assert_eq!(txn_outcome.operation_result, b"response".to_vec());
+    assert_eq!(controller.tracker.wasm_fuel, 51);
 
     Ok(())
 }
 
-/// Tests if a message is scheduled to be sent while an application is handling a cross-application
-/// call.
+/// Tests if the runtime rejects writes to the storage during a view call.
 #[tokio::test]
-async fn test_message_from_cross_application_call() -> anyhow::Result<()> {
+async fn test_write_rejected_in_view_call() -> anyhow::Result<()> {
     let (state, chain_id) = SystemExecutionState::dummy_chain_state(0);
     let mut view = state.into_view().await;
 
@@ -879,34 +867,64 @@ async fn test_message_from_cross_application_call() -> anyhow::Result<()> {
     let (target_id, target_application, target_blobs) = view.register_mock_application(1).await?;
 
     caller_application.expect_call(ExpectedCall::execute_operation(
-        move |runtime, _operation| {
-            runtime.try_call_application(/* authenticated */ false, target_id, vec![])?;
-            Ok(vec![])
-        },
+        move |runtime, _operation| runtime.try_view_call(target_id, vec![]),
     ));
+    target_application.expect_call(ExpectedCall::execute_view(|runtime, _argument| {
+        runtime.write_batch(Batch::new())?;
+        Ok(vec![])
+    }));
 
-    let destination = dummy_chain_description(1).id();
-    let dummy_message = SendMessageRequest {
-        destination,
-        authenticated: false,
-        is_tracked: false,
-        grant: Resources::default(),
-        message: b"msg".to_vec(),
-    };
+    let context = create_dummy_operation_context(chain_id);
+    let mut controller = ResourceController::default();
+    let result = view
+        .execute_operation(
+            context,
+            Operation::User {
+                application_id: caller_id,
+                bytes: vec![],
+            },
+            &mut TransactionTracker::new_replaying_blobs(caller_blobs.iter().chain(&target_blobs)),
+            &mut controller,
+        )
+        .await;
 
-    let expected_dummy_message = OutgoingMessage::new(
-        destination,
-        Message::User {
-            application_id: target_id,
-            bytes: b"msg".to_vec(),
-        },
+    assert_matches!(
+        result,
+        Err(ExecutionError::WriteInViewCall(application_id)) if *application_id == target_id
     );
 
-    target_application.expect_call(ExpectedCall::execute_operation(|runtime, _argument| {
-        runtime.send_message(dummy_message)?;
-        Ok(vec![])
-    }));
+    Ok(())
+}
+
+/// Tests if an application that allows reentrancy can be called back with a view call, but
+/// not with a call that may modify its state.
+#[tokio::test]
+async fn test_reentrant_view_call() -> anyhow::Result<()> {
+    let (state, chain_id) = SystemExecutionState::dummy_chain_state(0);
+    let mut view = state.into_view().await;
+
+    let (caller_id, caller_application, caller_blobs) = view.register_mock_application(0).await?;
+    let (target_id, target_application, target_blobs) = view.register_mock_application(1).await?;
 
+    caller_application.expect_call(ExpectedCall::execute_operation(
+        move |runtime, _operation| {
+            runtime.declare_reentrancy_policy(ReentrancyPolicy::Allow)?;
+            runtime.try_call_application(/* authenticated */ false, target_id, vec![])
+        },
+    ));
+    target_application.expect_call(ExpectedCall::execute_operation(
+        move |runtime, _argument| {
+            assert_matches!(
+                runtime.try_call_application(/* authenticated */ false, caller_id, vec![]),
+                Err(ExecutionError::ReentrantCall(application_id)) if application_id == caller_id
+            );
+            runtime.try_view_call(caller_id, b"view".to_vec())
+        },
+    ));
+    caller_application.expect_call(ExpectedCall::execute_view(|_runtime, argument| {
+        assert_eq!(argument, b"view");
+        Ok(b"response".to_vec())
+    }));
     target_application.expect_call(ExpectedCall::default_finalize());
     caller_application.expect_call(ExpectedCall::default_finalize());
 
@@ -926,76 +944,163 @@ async fn test_message_from_cross_application_call() -> anyhow::Result<()> {
     .await?;
 
     let txn_outcome = txn_tracker.into_outcome().unwrap();
-    let mut expected = TransactionTracker::default();
-    expected.add_outgoing_message(expected_dummy_message)?;
-    assert_eq!(
-        txn_outcome.outgoing_messages,
-        expected.into_outcome().unwrap().outgoing_messages
+    assert_eq!(txn_outcome.operation_result, b"response".to_vec());
+
+    Ok(())
+}
+
+/// Tests if reentrant calls are rejected unless the application allows them.
+#[tokio::test]
+async fn test_reentrant_call_rejected_by_default() -> anyhow::Result<()> {
+    let (state, chain_id) = SystemExecutionState::dummy_chain_state(0);
+    let mut view = state.into_view().await;
+
+    let (caller_id, caller_application, caller_blobs) = view.register_mock_application(0).await?;
+    let (target_id, target_application, target_blobs) = view.register_mock_application(1).await?;
+
+    caller_application.expect_call(ExpectedCall::execute_operation(
+        move |runtime, _operation| {
+            runtime.try_call_application(/* authenticated */ false, target_id, vec![])
+        },
+    ));
+    target_application.expect_call(ExpectedCall::execute_operation(
+        move |runtime, _argument| runtime.try_view_call(caller_id, vec![]),
+    ));
+
+    let context = create_dummy_operation_context(chain_id);
+    let mut controller = ResourceController::default();
+    let result = view
+        .execute_operation(
+            context,
+            Operation::User {
+                application_id: caller_id,
+                bytes: vec![],
+            },
+            &mut TransactionTracker::new_replaying_blobs(caller_blobs.iter().chain(&target_blobs)),
+            &mut controller,
+        )
+        .await;
+
+    assert_matches!(
+        result,
+        Err(ExecutionError::ReentrantCall(application_id)) if application_id == caller_id
     );
 
     Ok(())
 }
 
-/// Tests if a message is scheduled to be sent by a deeper cross-application call.
+/// Tests if the call stack is limited to the maximum depth of the resource control policy.
 #[tokio::test]
-async fn test_message_from_deeper_call() -> anyhow::Result<()> {
+async fn test_maximum_call_depth() -> anyhow::Result<()> {
     let (state, chain_id) = SystemExecutionState::dummy_chain_state(0);
     let mut view = state.into_view().await;
 
     let (caller_id, caller_application, caller_blobs) = view.register_mock_application(0).await?;
     let (middle_id, middle_application, middle_blobs) = view.register_mock_application(1).await?;
-    let (target_id, target_application, target_blobs) = view.register_mock_application(2).await?;
+    let (target_id, _target_application, target_blobs) = view.register_mock_application(2).await?;
 
     caller_application.expect_call(ExpectedCall::execute_operation(
         move |runtime, _operation| {
-            runtime.try_call_application(/* authenticated */ false, middle_id, vec![])?;
-            Ok(vec![])
+            runtime.try_call_application(/* authenticated */ false, middle_id, vec![])
         },
     ));
-
     middle_application.expect_call(ExpectedCall::execute_operation(
         move |runtime, _argument| {
-            runtime.try_call_application(/* authenticated */ false, target_id, vec![])?;
-            Ok(vec![])
+            runtime.try_call_application(/* authenticated */ false, target_id, vec![])
         },
     ));
 
-    let destination = dummy_chain_description(1).id();
-    let dummy_message = SendMessageRequest {
-        destination,
-        authenticated: false,
-        is_tracked: false,
-        grant: Resources::default(),
-        message: b"msg".to_vec(),
+    let policy = ResourceControlPolicy {
+        maximum_call_depth: 2,
+        ..ResourceControlPolicy::default()
     };
+    let context = create_dummy_operation_context(chain_id);
+    let mut controller =
+        ResourceController::new(Arc::new(policy), Default::default(), Default::default());
+    let result = view
+        .execute_operation(
+            context,
+            Operation::User {
+                application_id: caller_id,
+                bytes: vec![],
+            },
+            &mut TransactionTracker::new_replaying_blobs(
+                caller_blobs
+                    .iter()
+                    .chain(&middle_blobs)
+                    .chain(&target_blobs),
+            ),
+            &mut controller,
+        )
+        .await;
 
-    let expected_dummy_message = OutgoingMessage::new(
-        destination,
-        Message::User {
-            application_id: target_id,
-            bytes: b"msg".to_vec(),
+    assert_matches!(result, Err(ExecutionError::MaximumCallDepthExceeded(2)));
+
+    Ok(())
+}
+
+/// Tests if speculative calls report whether the call succeeds, and if their effects are
+/// undone either way.
+#[tokio::test]
+async fn test_speculative_call() -> anyhow::Result<()> {
+    let (state, chain_id) = SystemExecutionState::dummy_chain_state(0);
+    let mut view = state.into_view().await;
+
+    let (caller_id, caller_application, caller_blobs) = view.register_mock_application(0).await?;
+    let (target_id, target_application, target_blobs) = view.register_mock_application(1).await?;
+
+    caller_application.expect_call(ExpectedCall::execute_operation(
+        move |runtime, _operation| {
+            assert_eq!(
+                runtime.try_call(
+                    /* authenticated */ false,
+                    target_id,
+                    b"succeed".to_vec()
+                )?,
+                Some(b"response".to_vec())
+            );
+            assert_eq!(
+                runtime.try_call(/* authenticated */ false, target_id, b"fail".to_vec())?,
+                None
+            );
+            runtime.try_call_application(
+                /* authenticated */ false,
+                target_id,
+                b"check".to_vec(),
+            )
         },
-    );
+    ));
 
+    let state_key = b"key".to_vec();
+    for expected_argument in [&b"succeed"[..], &b"fail"[..]] {
+        let state_key = state_key.clone();
+        target_application.expect_call(ExpectedCall::execute_operation(
+            move |runtime, argument| {
+                assert_eq!(argument, expected_argument);
+                let mut batch = Batch::new();
+                batch.put_key_value_bytes(state_key, vec![1]);
+                runtime.write_batch(batch)?;
+                runtime.emit(StreamName(b"stream".to_vec()), vec![])?;
+                if argument == b"fail" {
+                    return Err(ExecutionError::UserError("Failed".to_owned()));
+                }
+                Ok(b"response".to_vec())
+            },
+        ));
+    }
     target_application.expect_call(ExpectedCall::execute_operation(
         move |runtime, _argument| {
-            runtime.send_message(dummy_message)?;
-            Ok(vec![])
+            assert_eq!(runtime.read_value_bytes(state_key)?, None);
+            Ok(b"checked".to_vec())
         },
     ));
-
     target_application.expect_call(ExpectedCall::default_finalize());
-    middle_application.expect_call(ExpectedCall::default_finalize());
     caller_application.expect_call(ExpectedCall::default_finalize());
 
     let context = create_dummy_operation_context(chain_id);
     let mut controller = ResourceController::default();
-    let mut txn_tracker = TransactionTracker::new_replaying_blobs(
-        caller_blobs
-            .iter()
-            .chain(&middle_blobs)
-            .chain(&target_blobs),
-    );
+    let mut txn_tracker =
+        TransactionTracker::new_replaying_blobs(caller_blobs.iter().chain(&target_blobs));
     view.execute_operation(
         context,
         Operation::User {
@@ -1008,105 +1113,253 @@ async fn test_message_from_deeper_call() -> anyhow::Result<()> {
     .await?;
 
     let txn_outcome = txn_tracker.into_outcome().unwrap();
-    let mut expected = TransactionTracker::default();
-    expected.add_outgoing_message(expected_dummy_message)?;
-    assert_eq!(
-        txn_outcome.outgoing_messages,
-        expected.into_outcome().unwrap().outgoing_messages
-    );
+    assert_eq!(txn_outcome.operation_result, b"checked".to_vec());
+    assert!(txn_outcome.events.is_empty());
 
     Ok(())
 }
 
-/// Tests if multiple messages are scheduled to be sent by different applications to different
-/// chains.
-///
-/// Ensures that in a more complex scenario, chains receive application registration messages only
-/// for the applications that will receive messages on them.
+/// Tests if the number of speculative calls is limited by the resource control policy.
 #[tokio::test]
-async fn test_multiple_messages_from_different_applications() -> anyhow::Result<()> {
+async fn test_maximum_speculative_calls() -> anyhow::Result<()> {
     let (state, chain_id) = SystemExecutionState::dummy_chain_state(0);
     let mut view = state.into_view().await;
 
-    // The entrypoint application, which sends a message and calls other applications
     let (caller_id, caller_application, caller_blobs) = view.register_mock_application(0).await?;
-    // An application that does not send any messages
-    let (silent_target_id, silent_target_application, silent_blobs) =
-        view.register_mock_application(1).await?;
-    // An application that sends a message when handling a cross-application call
-    let (sending_target_id, sending_target_application, sending_blobs) =
-        view.register_mock_application(2).await?;
+    let (target_id, target_application, target_blobs) = view.register_mock_application(1).await?;
 
-    // The first destination chain receives messages from the caller and the sending applications
-    let first_destination = dummy_chain_description(1).id();
-    // The second destination chain only receives a message from the sending application
-    let second_destination = dummy_chain_description(2).id();
+    caller_application.expect_call(ExpectedCall::execute_operation(
+        move |runtime, _operation| {
+            runtime.try_call(/* authenticated */ false, target_id, vec![])?;
+            runtime.try_call(/* authenticated */ false, target_id, vec![])?;
+            Ok(vec![])
+        },
+    ));
+    target_application.expect_call(ExpectedCall::execute_operation(|_runtime, _argument| {
+        Ok(vec![])
+    }));
 
-    // The message sent to the first destination chain by the caller and the sending applications
-    let first_message = SendMessageRequest {
-        destination: first_destination,
-        authenticated: false,
-        is_tracked: false,
-        grant: Resources::default(),
-        message: b"first".to_vec(),
+    let policy = ResourceControlPolicy {
+        maximum_speculative_calls_per_transaction: 1,
+        ..ResourceControlPolicy::default()
     };
-
-    // The entrypoint sends a message to the first chain and calls the silent and the sending
-    // applications
-    caller_application.expect_call(ExpectedCall::execute_operation({
-        let first_message = first_message.clone();
+    let context = create_dummy_operation_context(chain_id);
+    let mut controller =
+        ResourceController::new(Arc::new(policy), Default::default(), Default::default());
+    let result = view
+        .execute_operation(
+            context,
+            Operation::User {
+                application_id: caller_id,
+                bytes: vec![],
+            },
+            &mut TransactionTracker::new_replaying_blobs(caller_blobs.iter().chain(&target_blobs)),
+            &mut controller,
+        )
+        .await;
+
+    assert_matches!(
+        result,
+        Err(ExecutionError::MaximumSpeculativeCallsExceeded(1))
+    );
+
+    Ok(())
+}
+
+/// Tests if an application is scheduled to be registered together with any messages it sends to
+/// other chains.
+#[tokio::test]
+async fn test_simple_message() -> anyhow::Result<()> {
+    let (state, chain_id) = SystemExecutionState::dummy_chain_state(0);
+    let mut view = state.into_view().await;
+
+    let (application_id, application, blobs) = view.register_mock_application(0).await?;
+
+    let destination = dummy_chain_description(1).id();
+    let dummy_message = SendMessageRequest {
+        destination,
+        authenticated: false,
+        is_tracked: false,
+        grant: Resources::default(),
+        message: b"msg".to_vec(),
+    };
+
+    let expected_dummy_message = OutgoingMessage::new(
+        destination,
+        Message::User {
+            application_id,
+            bytes: b"msg".to_vec(),
+        },
+    );
+
+    application.expect_call(ExpectedCall::execute_operation(
         move |runtime, _operation| {
-            runtime.try_call_application(
-                /* authenticated */ false,
-                silent_target_id,
-                vec![],
-            )?;
-            runtime.send_message(first_message)?;
-            runtime.try_call_application(
-                /* authenticated */ false,
-                sending_target_id,
-                vec![],
-            )?;
+            runtime.send_message(dummy_message)?;
             Ok(vec![])
-        }
-    }));
+        },
+    ));
+    application.expect_call(ExpectedCall::default_finalize());
 
-    // The silent application does nothing
-    silent_target_application.expect_call(ExpectedCall::execute_operation(
-        |_runtime, _argument| Ok(vec![]),
+    let context = create_dummy_operation_context(chain_id);
+    let mut controller = ResourceController::default();
+    let mut txn_tracker = TransactionTracker::new_replaying_blobs(blobs);
+    view.execute_operation(
+        context,
+        Operation::User {
+            application_id,
+            bytes: vec![],
+        },
+        &mut txn_tracker,
+        &mut controller,
+    )
+    .await?;
+
+    let txn_outcome = txn_tracker.into_outcome().unwrap();
+    let mut expected = TransactionTracker::default();
+    expected.add_outgoing_message(expected_dummy_message)?;
+    assert_eq!(
+        txn_outcome.outgoing_messages,
+        expected.into_outcome().unwrap().outgoing_messages
+    );
+
+    Ok(())
+}
+
+/// Tests that the fees declared in an application's parameters are transferred from the
+/// signer to the application before its operations are executed.
+#[tokio::test]
+async fn test_application_operation_fees() -> anyhow::Result<()> {
+    let owner = AccountOwner::from(AccountPublicKey::test_key(0));
+    let (mut state, chain_id) = SystemExecutionState::dummy_chain_state(0);
+    state.balances.insert(owner, Amount::ONE);
+    let mut view = state.into_view().await;
+
+    let (mut description, contract, service) = create_dummy_user_application_description(0);
+    let fees = OperationFees {
+        default: Amount::ONE,
+        by_variant: BTreeMap::from([(1, Amount::ZERO)]),
+    };
+    description.parameters = serde_json::to_vec(&serde_json::json!({ "operation_fees": fees }))?;
+    let blobs = [
+        Blob::new_application_description(&description).id(),
+        contract.id(),
+        service.id(),
+    ];
+    let (application_id, application) = view
+        .register_mock_application_with(description, contract, service)
+        .await?;
+    let application_account = AccountOwner::from(application_id);
+
+    let signed_context = OperationContext {
+        authenticated_signer: Some(owner),
+        ..create_dummy_operation_context(chain_id)
+    };
+    let mut controller = ResourceController::default();
+
+    // The first operation costs the default fee, the second one is free.
+    for variant in [0, 1] {
+        application.expect_call(ExpectedCall::execute_operation(|_runtime, _operation| {
+            Ok(vec![])
+        }));
+        application.expect_call(ExpectedCall::default_finalize());
+        view.execute_operation(
+            signed_context,
+            Operation::User {
+                application_id,
+                bytes: vec![variant],
+            },
+            &mut TransactionTracker::new_replaying_blobs(blobs),
+            &mut controller,
+        )
+        .await?;
+    }
+    assert_eq!(view.system.balances.get(&owner).await?, None);
+    assert_eq!(
+        view.system.balances.get(&application_account).await?,
+        Some(Amount::ONE)
+    );
+
+    let result = view
+        .execute_operation(
+            signed_context,
+            Operation::User {
+                application_id,
+                bytes: vec![0],
+            },
+            &mut TransactionTracker::new_replaying_blobs(blobs),
+            &mut controller,
+        )
+        .await;
+    assert_matches!(
+        result,
+        Err(ExecutionError::InsufficientBalanceForApplicationFee { fee, balance, .. })
+            if fee == Amount::ONE && balance == Amount::ZERO
+    );
+
+    let result = view
+        .execute_operation(
+            create_dummy_operation_context(chain_id),
+            Operation::User {
+                application_id,
+                bytes: vec![0],
+            },
+            &mut TransactionTracker::new_replaying_blobs(blobs),
+            &mut controller,
+        )
+        .await;
+    assert_matches!(
+        result,
+        Err(ExecutionError::UnauthenticatedApplicationFee(id)) if id == application_id
+    );
+    Ok(())
+}
+
+/// Tests if a message is scheduled to be sent while an application is handling a cross-application
+/// call.
+#[tokio::test]
+async fn test_message_from_cross_application_call() -> anyhow::Result<()> {
+    let (state, chain_id) = SystemExecutionState::dummy_chain_state(0);
+    let mut view = state.into_view().await;
+
+    let (caller_id, caller_application, caller_blobs) = view.register_mock_application(0).await?;
+    let (target_id, target_application, target_blobs) = view.register_mock_application(1).await?;
+
+    caller_application.expect_call(ExpectedCall::execute_operation(
+        move |runtime, _operation| {
+            runtime.try_call_application(/* authenticated */ false, target_id, vec![])?;
+            Ok(vec![])
+        },
     ));
 
-    // The message sent to the second destination chain by the sending application
-    let second_message = SendMessageRequest {
-        destination: second_destination,
+    let destination = dummy_chain_description(1).id();
+    let dummy_message = SendMessageRequest {
+        destination,
         authenticated: false,
         is_tracked: false,
         grant: Resources::default(),
-        message: b"second".to_vec(),
+        message: b"msg".to_vec(),
     };
 
-    // The sending application sends two messages, one to each of the destination chains
-    sending_target_application.expect_call(ExpectedCall::execute_operation(
-        |runtime, _argument| {
-            runtime.send_message(first_message)?;
-            runtime.send_message(second_message)?;
-            Ok(vec![])
+    let expected_dummy_message = OutgoingMessage::new(
+        destination,
+        Message::User {
+            application_id: target_id,
+            bytes: b"msg".to_vec(),
         },
-    ));
+    );
 
-    sending_target_application.expect_call(ExpectedCall::default_finalize());
-    silent_target_application.expect_call(ExpectedCall::default_finalize());
+    target_application.expect_call(ExpectedCall::execute_operation(|runtime, _argument| {
+        runtime.send_message(dummy_message)?;
+        Ok(vec![])
+    }));
+
+    target_application.expect_call(ExpectedCall::default_finalize());
     caller_application.expect_call(ExpectedCall::default_finalize());
 
-    // Execute the operation, starting the test scenario
     let context = create_dummy_operation_context(chain_id);
     let mut controller = ResourceController::default();
-    let mut txn_tracker = TransactionTracker::new_replaying_blobs(
-        caller_blobs
-            .iter()
-            .chain(&silent_blobs)
-            .chain(&sending_blobs),
-    );
+    let mut txn_tracker =
+        TransactionTracker::new_replaying_blobs(caller_blobs.iter().chain(&target_blobs));
     view.execute_operation(
         context,
         Operation::User {
@@ -1118,32 +1371,9 @@ async fn test_multiple_messages_from_different_applications() -> anyhow::Result<
     )
     .await?;
 
-    // Return to checking the user application outcomes
     let txn_outcome = txn_tracker.into_outcome().unwrap();
     let mut expected = TransactionTracker::default();
-    expected.add_outgoing_messages(vec![
-        OutgoingMessage::new(
-            first_destination,
-            Message::User {
-                application_id: caller_id,
-                bytes: b"first".to_vec(),
-            },
-        ),
-        OutgoingMessage::new(
-            first_destination,
-            Message::User {
-                application_id: sending_target_id,
-                bytes: b"first".to_vec(),
-            },
-        ),
-        OutgoingMessage::new(
-            second_destination,
-            Message::User {
-                application_id: sending_target_id,
-                bytes: b"second".to_vec(),
-            },
-        ),
-    ])?;
+    expected.add_outgoing_message(expected_dummy_message)?;
     assert_eq!(
         txn_outcome.outgoing_messages,
         expected.into_outcome().unwrap().outgoing_messages
@@ -1152,155 +1382,692 @@ async fn test_multiple_messages_from_different_applications() -> anyhow::Result<
     Ok(())
 }
 
-/// Tests the system API calls `open_chain` and `chain_ownership`.
+/// Tests if a message is scheduled to be sent by a deeper cross-application call.
+#[tokio::test]
+async fn test_message_from_deeper_call() -> anyhow::Result<()> {
+    let (state, chain_id) = SystemExecutionState::dummy_chain_state(0);
+    let mut view = state.into_view().await;
+
+    let (caller_id, caller_application, caller_blobs) = view.register_mock_application(0).await?;
+    let (middle_id, middle_application, middle_blobs) = view.register_mock_application(1).await?;
+    let (target_id, target_application, target_blobs) = view.register_mock_application(2).await?;
+
+    caller_application.expect_call(ExpectedCall::execute_operation(
+        move |runtime, _operation| {
+            runtime.try_call_application(/* authenticated */ false, middle_id, vec![])?;
+            Ok(vec![])
+        },
+    ));
+
+    middle_application.expect_call(ExpectedCall::execute_operation(
+        move |runtime, _argument| {
+            runtime.try_call_application(/* authenticated */ false, target_id, vec![])?;
+            Ok(vec![])
+        },
+    ));
+
+    let destination = dummy_chain_description(1).id();
+    let dummy_message = SendMessageRequest {
+        destination,
+        authenticated: false,
+        is_tracked: false,
+        grant: Resources::default(),
+        message: b"msg".to_vec(),
+    };
+
+    let expected_dummy_message = OutgoingMessage::new(
+        destination,
+        Message::User {
+            application_id: target_id,
+            bytes: b"msg".to_vec(),
+        },
+    );
+
+    target_application.expect_call(ExpectedCall::execute_operation(
+        move |runtime, _argument| {
+            runtime.send_message(dummy_message)?;
+            Ok(vec![])
+        },
+    ));
+
+    target_application.expect_call(ExpectedCall::default_finalize());
+    middle_application.expect_call(ExpectedCall::default_finalize());
+    caller_application.expect_call(ExpectedCall::default_finalize());
+
+    let context = create_dummy_operation_context(chain_id);
+    let mut controller = ResourceController::default();
+    let mut txn_tracker = TransactionTracker::new_replaying_blobs(
+        caller_blobs
+            .iter()
+            .chain(&middle_blobs)
+            .chain(&target_blobs),
+    );
+    view.execute_operation(
+        context,
+        Operation::User {
+            application_id: caller_id,
+            bytes: vec![],
+        },
+        &mut txn_tracker,
+        &mut controller,
+    )
+    .await?;
+
+    let txn_outcome = txn_tracker.into_outcome().unwrap();
+    let mut expected = TransactionTracker::default();
+    expected.add_outgoing_message(expected_dummy_message)?;
+    assert_eq!(
+        txn_outcome.outgoing_messages,
+        expected.into_outcome().unwrap().outgoing_messages
+    );
+
+    Ok(())
+}
+
+/// Tests if multiple messages are scheduled to be sent by different applications to different
+/// chains.
+///
+/// Ensures that in a more complex scenario, chains receive application registration messages only
+/// for the applications that will receive messages on them.
+#[tokio::test]
+async fn test_multiple_messages_from_different_applications() -> anyhow::Result<()> {
+    let (state, chain_id) = SystemExecutionState::dummy_chain_state(0);
+    let mut view = state.into_view().await;
+
+    // The entrypoint application, which sends a message and calls other applications
+    let (caller_id, caller_application, caller_blobs) = view.register_mock_application(0).await?;
+    // An application that does not send any messages
+    let (silent_target_id, silent_target_application, silent_blobs) =
+        view.register_mock_application(1).await?;
+    // An application that sends a message when handling a cross-application call
+    let (sending_target_id, sending_target_application, sending_blobs) =
+        view.register_mock_application(2).await?;
+
+    // The first destination chain receives messages from the caller and the sending applications
+    let first_destination = dummy_chain_description(1).id();
+    // The second destination chain only receives a message from the sending application
+    let second_destination = dummy_chain_description(2).id();
+
+    // The message sent to the first destination chain by the caller and the sending applications
+    let first_message = SendMessageRequest {
+        destination: first_destination,
+        authenticated: false,
+        is_tracked: false,
+        grant: Resources::default(),
+        message: b"first".to_vec(),
+    };
+
+    // The entrypoint sends a message to the first chain and calls the silent and the sending
+    // applications
+    caller_application.expect_call(ExpectedCall::execute_operation({
+        let first_message = first_message.clone();
+        move |runtime, _operation| {
+            runtime.try_call_application(
+                /* authenticated */ false,
+                silent_target_id,
+                vec![],
+            )?;
+            runtime.send_message(first_message)?;
+            runtime.try_call_application(
+                /* authenticated */ false,
+                sending_target_id,
+                vec![],
+            )?;
+            Ok(vec![])
+        }
+    }));
+
+    // The silent application does nothing
+    silent_target_application.expect_call(ExpectedCall::execute_operation(
+        |_runtime, _argument| Ok(vec![]),
+    ));
+
+    // The message sent to the second destination chain by the sending application
+    let second_message = SendMessageRequest {
+        destination: second_destination,
+        authenticated: false,
+        is_tracked: false,
+        grant: Resources::default(),
+        message: b"second".to_vec(),
+    };
+
+    // The sending application sends two messages, one to each of the destination chains
+    sending_target_application.expect_call(ExpectedCall::execute_operation(
+        |runtime, _argument| {
+            runtime.send_message(first_message)?;
+            runtime.send_message(second_message)?;
+            Ok(vec![])
+        },
+    ));
+
+    sending_target_application.expect_call(ExpectedCall::default_finalize());
+    silent_target_application.expect_call(ExpectedCall::default_finalize());
+    caller_application.expect_call(ExpectedCall::default_finalize());
+
+    // Execute the operation, starting the test scenario
+    let context = create_dummy_operation_context(chain_id);
+    let mut controller = ResourceController::default();
+    let mut txn_tracker = TransactionTracker::new_replaying_blobs(
+        caller_blobs
+            .iter()
+            .chain(&silent_blobs)
+            .chain(&sending_blobs),
+    );
+    view.execute_operation(
+        context,
+        Operation::User {
+            application_id: caller_id,
+            bytes: vec![],
+        },
+        &mut txn_tracker,
+        &mut controller,
+    )
+    .await?;
+
+    // Return to checking the user application outcomes
+    let txn_outcome = txn_tracker.into_outcome().unwrap();
+    let mut expected = TransactionTracker::default();
+    expected.add_outgoing_messages(vec![
+        OutgoingMessage::new(
+            first_destination,
+            Message::User {
+                application_id: caller_id,
+                bytes: b"first".to_vec(),
+            },
+        ),
+        OutgoingMessage::new(
+            first_destination,
+            Message::User {
+                application_id: sending_target_id,
+                bytes: b"first".to_vec(),
+            },
+        ),
+        OutgoingMessage::new(
+            second_destination,
+            Message::User {
+                application_id: sending_target_id,
+                bytes: b"second".to_vec(),
+            },
+        ),
+    ])?;
+    assert_eq!(
+        txn_outcome.outgoing_messages,
+        expected.into_outcome().unwrap().outgoing_messages
+    );
+
+    Ok(())
+}
+
+/// Tests the system API calls `open_chain` and `chain_ownership`.
+#[tokio::test]
+async fn test_open_chain() -> anyhow::Result<()> {
+    let committee = Committee::make_simple(vec![(
+        ValidatorPublicKey::test_key(0),
+        AccountPublicKey::test_key(0),
+    )]);
+    let committee_blob = Blob::new_committee(
+        bcs::to_bytes(&committee).expect("serializing a committee should succeed"),
+    );
+    let chain_key = AccountPublicKey::test_key(1);
+    let ownership = ChainOwnership::single(chain_key.into());
+    let child_ownership = ChainOwnership::single(AccountPublicKey::test_key(2).into());
+    let balance = Amount::from_tokens(5);
+    let root_description =
+        dummy_chain_description_with_ownership_and_balance(0, ownership.clone(), balance);
+    let state = SystemExecutionState::new(root_description.clone());
+    let mut view = state.into_view().await;
+    view.context()
+        .extra()
+        .add_blobs([
+            committee_blob.clone(),
+            Blob::new_chain_description(&root_description),
+        ])
+        .await?;
+    let (application_id, application, blobs) = view.register_mock_application(0).await?;
+
+    let context = OperationContext {
+        height: BlockHeight(1),
+        authenticated_signer: Some(chain_key.into()),
+        ..create_dummy_operation_context(root_description.id())
+    };
+    let first_message_index = 5;
+
+    let child_origin = ChainOrigin::Child {
+        parent: root_description.id(),
+        block_height: BlockHeight(1),
+        chain_index: 0,
+    };
+    let child_application_permissions = ApplicationPermissions::new_single(application_id);
+    let child_config = InitialChainConfig {
+        balance: Amount::ONE,
+        ownership: child_ownership.clone(),
+        application_permissions: child_application_permissions.clone(),
+        ..root_description.config().clone()
+    };
+    let child_description = ChainDescription::new(child_origin, child_config, Timestamp::default());
+    let child_id = child_description.id();
+
+    application.expect_call(ExpectedCall::execute_operation({
+        let child_ownership = child_ownership.clone();
+        move |runtime, _operation| {
+            assert_eq!(runtime.chain_ownership()?, ownership);
+            let destination = Account::chain(dummy_chain_description(2).id());
+            runtime.transfer(AccountOwner::CHAIN, destination, Amount::ONE)?;
+            let application_permissions = child_application_permissions.clone();
+            let chain_id =
+                runtime.open_chain(child_ownership, application_permissions, Amount::ONE)?;
+            assert_eq!(chain_id, child_id);
+            Ok(vec![])
+        }
+    }));
+    application.expect_call(ExpectedCall::default_finalize());
+
+    let mut controller = ResourceController::default();
+    let operation = Operation::User {
+        application_id,
+        bytes: vec![],
+    };
+    let mut txn_tracker = TransactionTracker::new(
+        Timestamp::from(0),
+        1,
+        first_message_index,
+        0,
+        Some(blob_oracle_responses(blobs.iter())),
+    );
+    view.execute_operation(context, operation, &mut txn_tracker, &mut controller)
+        .await?;
+
+    assert_eq!(*view.system.balance.get(), Amount::from_tokens(3));
+    let txn_outcome = txn_tracker.into_outcome().unwrap();
+    let new_blob = &txn_outcome.blobs[0];
+    assert_eq!(new_blob.id().hash, child_id.0);
+    assert_eq!(new_blob.id().blob_type, BlobType::ChainDescription);
+    let created_description: ChainDescription = bcs::from_bytes(&new_blob.clone().into_bytes())
+        .expect("should deserialize a chain description");
+    assert_eq!(created_description.config().balance, Amount::ONE);
+    assert_eq!(created_description.config().ownership, child_ownership);
+
+    // Initialize the child chain using the new blob.
+    let mut child_view = SystemExecutionState::default()
+        .into_view_with(child_id, Default::default())
+        .await;
+    child_view
+        .context()
+        .extra()
+        .add_blobs([
+            committee_blob.clone(),
+            Blob::new_chain_description(&child_description),
+        ])
+        .await?;
+    child_view
+        .system
+        .initialize_chain(child_description.id())
+        .await
+        .expect("should initialize chain correctly");
+    assert_eq!(*child_view.system.balance.get(), Amount::ONE);
+    assert_eq!(*child_view.system.ownership.get(), child_ownership);
+    assert_eq!(
+        *child_view.system.committees.get(),
+        [(Epoch::ZERO, committee)]
+            .into_iter()
+            .collect::<BTreeMap<_, _>>()
+    );
+    assert_eq!(
+        *child_view.system.application_permissions.get(),
+        ApplicationPermissions::new_single(application_id)
+    );
+
+    Ok(())
+}
+
+/// Tests the system API call `close_chain`.
+#[tokio::test]
+async fn test_close_chain() -> anyhow::Result<()> {
+    let ownership = ChainOwnership::single(AccountPublicKey::test_key(1).into());
+    let description = dummy_chain_description_with_ownership_and_balance(
+        0,
+        ownership.clone(),
+        Amount::from_tokens(5),
+    );
+    let chain_id = description.id();
+    let state = SystemExecutionState::new(description);
+    let mut view = state.into_view().await;
+    let (application_id, application, blobs) = view.register_mock_application(0).await?;
+
+    // The application is not authorized to close the chain.
+    let context = create_dummy_operation_context(chain_id);
+    application.expect_call(ExpectedCall::execute_operation(
+        move |runtime, _operation| {
+            assert_matches!(
+                runtime.close_chain(),
+                Err(ExecutionError::UnauthorizedApplication(_))
+            );
+            Ok(vec![])
+        },
+    ));
+    application.expect_call(ExpectedCall::default_finalize());
+
+    let mut controller = ResourceController::default();
+    let operation = Operation::User {
+        application_id,
+        bytes: vec![],
+    };
+    view.execute_operation(
+        context,
+        operation,
+        &mut TransactionTracker::new_replaying_blobs(blobs),
+        &mut controller,
+    )
+    .await?;
+    assert!(!view.system.closed.get());
+
+    // Now we authorize the application and it can close the chain.
+    let permissions = ApplicationPermissions::new_single(application_id);
+    let operation = SystemOperation::ChangeApplicationPermissions(permissions);
+    view.execute_operation(
+        context,
+        operation.into(),
+        &mut TransactionTracker::new_replaying(Vec::new()),
+        &mut controller,
+    )
+    .await?;
+
+    application.expect_call(ExpectedCall::execute_operation(
+        move |runtime, _operation| {
+            runtime.close_chain()?;
+            Ok(vec![])
+        },
+    ));
+    application.expect_call(ExpectedCall::default_finalize());
+
+    let operation = Operation::User {
+        application_id,
+        bytes: vec![],
+    };
+    view.execute_operation(
+        context,
+        operation,
+        &mut TransactionTracker::new_replaying(Vec::new()),
+        &mut controller,
+    )
+    .await?;
+    assert!(view.system.closed.get());
+
+    Ok(())
+}
+
+/// Tests that the applications with a state on the chain are notified when it is closed.
+#[tokio::test]
+async fn test_close_chain_notifies_applications() -> anyhow::Result<()> {
+    let (state, chain_id) = SystemExecutionState::dummy_chain_state(0);
+    let mut view = state.into_view().await;
+    let (application_id, application, blobs) = view.register_mock_application(0).await?;
+    let state_key = vec![];
+
+    // The application stores a state on the chain.
+    application.expect_call(ExpectedCall::execute_operation({
+        let state_key = state_key.clone();
+        move |runtime, _operation| {
+            let mut batch = Batch::new();
+            batch.put_key_value_bytes(state_key, b"escrow".to_vec());
+            runtime.write_batch(batch)?;
+            Ok(vec![])
+        }
+    }));
+    application.expect_call(ExpectedCall::default_finalize());
+
+    let context = create_dummy_operation_context(chain_id);
+    let mut controller = ResourceController::default();
+    let operation = Operation::User {
+        application_id,
+        bytes: vec![],
+    };
+    view.execute_operation(
+        context,
+        operation,
+        &mut TransactionTracker::new_replaying_blobs(blobs),
+        &mut controller,
+    )
+    .await?;
+
+    // Closing the chain lets the application release what it holds.
+    application.expect_call(ExpectedCall::on_chain_close({
+        let state_key = state_key.clone();
+        move |runtime| {
+            assert!(runtime.read_value_bytes(state_key.clone())?.is_some());
+            let mut batch = Batch::new();
+            batch.delete_key(state_key);
+            runtime.write_batch(batch)?;
+            Ok(())
+        }
+    }));
+    application.expect_call(ExpectedCall::default_finalize());
+
+    view.execute_operation(
+        context,
+        SystemOperation::CloseChain.into(),
+        &mut TransactionTracker::new_replaying(Vec::new()),
+        &mut controller,
+    )
+    .await?;
+    assert!(view.system.closed.get());
+
+    // The application is only notified once.
+    view.execute_operation(
+        context,
+        SystemOperation::CloseChain.into(),
+        &mut TransactionTracker::new_replaying(Vec::new()),
+        &mut controller,
+    )
+    .await?;
+
+    Ok(())
+}
+
+/// Tests that an application whose `on_chain_close` hook fails cannot prevent the chain from
+/// closing, and that the changes made by its hook are discarded.
+#[tokio::test]
+async fn test_failing_close_chain_hook() -> anyhow::Result<()> {
+    let (state, chain_id) = SystemExecutionState::dummy_chain_state(0);
+    let mut view = state.into_view().await;
+    let (application_id, application, blobs) = view.register_mock_application(0).await?;
+    let state_key = vec![];
+
+    // The application stores a state on the chain.
+    application.expect_call(ExpectedCall::execute_operation({
+        let state_key = state_key.clone();
+        move |runtime, _operation| {
+            let mut batch = Batch::new();
+            batch.put_key_value_bytes(state_key, b"escrow".to_vec());
+            runtime.write_batch(batch)?;
+            Ok(vec![])
+        }
+    }));
+    application.expect_call(ExpectedCall::default_finalize());
+
+    let context = create_dummy_operation_context(chain_id);
+    let mut controller = ResourceController::default();
+    let operation = Operation::User {
+        application_id,
+        bytes: vec![],
+    };
+    view.execute_operation(
+        context,
+        operation,
+        &mut TransactionTracker::new_replaying_blobs(blobs),
+        &mut controller,
+    )
+    .await?;
+
+    // The hook deletes the state, but then fails.
+    application.expect_call(ExpectedCall::on_chain_close({
+        let state_key = state_key.clone();
+        move |runtime| {
+            let mut batch = Batch::new();
+            batch.delete_key(state_key);
+            runtime.write_batch(batch)?;
+            Err(ExecutionError::UserError(
+                "cannot release the escrow".to_owned(),
+            ))
+        }
+    }));
+
+    view.execute_operation(
+        context,
+        SystemOperation::CloseChain.into(),
+        &mut TransactionTracker::new_replaying(Vec::new()),
+        &mut controller,
+    )
+    .await?;
+    assert!(view.system.closed.get());
+    let application_state = view
+        .users
+        .try_load_entry(&application_id)
+        .await?
+        .expect("the application should still have a state");
+    assert_eq!(
+        application_state.get(&state_key).await?,
+        Some(b"escrow".to_vec())
+    );
+
+    Ok(())
+}
+
+/// Tests that an operation that closes the chain keeps its result, although the closure hooks
+/// run after it in the same transaction.
+#[tokio::test]
+async fn test_operation_result_survives_closure_hooks() -> anyhow::Result<()> {
+    let (state, chain_id) = SystemExecutionState::dummy_chain_state(0);
+    let mut view = state.into_view().await;
+    let (application_id, application, blobs) = view.register_mock_application(0).await?;
+
+    let context = create_dummy_operation_context(chain_id);
+    let mut controller = ResourceController::default();
+    let permissions = ApplicationPermissions::new_single(application_id);
+    view.execute_operation(
+        context,
+        SystemOperation::ChangeApplicationPermissions(permissions).into(),
+        &mut TransactionTracker::new_replaying(Vec::new()),
+        &mut controller,
+    )
+    .await?;
+
+    // The application stores a state, then closes the chain.
+    application.expect_call(ExpectedCall::execute_operation(
+        move |runtime, _operation| {
+            let mut batch = Batch::new();
+            batch.put_key_value_bytes(vec![], b"escrow".to_vec());
+            runtime.write_batch(batch)?;
+            runtime.close_chain()?;
+            Ok(b"closed".to_vec())
+        },
+    ));
+    application.expect_call(ExpectedCall::default_finalize());
+    application.expect_call(ExpectedCall::on_chain_close(|_runtime| Ok(())));
+    application.expect_call(ExpectedCall::default_finalize());
+
+    let mut txn_tracker = TransactionTracker::new_replaying_blobs(blobs);
+    view.execute_operation(
+        context,
+        Operation::User {
+            application_id,
+            bytes: vec![],
+        },
+        &mut txn_tracker,
+        &mut controller,
+    )
+    .await?;
+    assert!(view.system.closed.get());
+
+    let txn_outcome = txn_tracker.into_outcome().unwrap();
+    assert_eq!(txn_outcome.operation_result, b"closed".to_vec());
+
+    Ok(())
+}
+
+/// Tests that the `on_chain_close` hook cannot call other applications, since only the state
+/// of the notified application is restored if the hook fails.
 #[tokio::test]
-async fn test_open_chain() -> anyhow::Result<()> {
-    let committee = Committee::make_simple(vec![(
-        ValidatorPublicKey::test_key(0),
-        AccountPublicKey::test_key(0),
-    )]);
-    let committee_blob = Blob::new_committee(
-        bcs::to_bytes(&committee).expect("serializing a committee should succeed"),
-    );
-    let chain_key = AccountPublicKey::test_key(1);
-    let ownership = ChainOwnership::single(chain_key.into());
-    let child_ownership = ChainOwnership::single(AccountPublicKey::test_key(2).into());
-    let balance = Amount::from_tokens(5);
-    let root_description =
-        dummy_chain_description_with_ownership_and_balance(0, ownership.clone(), balance);
-    let state = SystemExecutionState::new(root_description.clone());
+async fn test_close_chain_hook_cannot_call_other_applications() -> anyhow::Result<()> {
+    let (state, chain_id) = SystemExecutionState::dummy_chain_state(0);
     let mut view = state.into_view().await;
-    view.context()
-        .extra()
-        .add_blobs([
-            committee_blob.clone(),
-            Blob::new_chain_description(&root_description),
-        ])
-        .await?;
     let (application_id, application, blobs) = view.register_mock_application(0).await?;
+    let (other_id, _other_application, _) = view.register_mock_application(1).await?;
 
-    let context = OperationContext {
-        height: BlockHeight(1),
-        authenticated_signer: Some(chain_key.into()),
-        ..create_dummy_operation_context(root_description.id())
-    };
-    let first_message_index = 5;
-
-    let child_origin = ChainOrigin::Child {
-        parent: root_description.id(),
-        block_height: BlockHeight(1),
-        chain_index: 0,
-    };
-    let child_application_permissions = ApplicationPermissions::new_single(application_id);
-    let child_config = InitialChainConfig {
-        balance: Amount::ONE,
-        ownership: child_ownership.clone(),
-        application_permissions: child_application_permissions.clone(),
-        ..root_description.config().clone()
-    };
-    let child_description = ChainDescription::new(child_origin, child_config, Timestamp::default());
-    let child_id = child_description.id();
-
-    application.expect_call(ExpectedCall::execute_operation({
-        let child_ownership = child_ownership.clone();
+    // The application stores a state on the chain.
+    application.expect_call(ExpectedCall::execute_operation(
         move |runtime, _operation| {
-            assert_eq!(runtime.chain_ownership()?, ownership);
-            let destination = Account::chain(dummy_chain_description(2).id());
-            runtime.transfer(AccountOwner::CHAIN, destination, Amount::ONE)?;
-            let application_permissions = child_application_permissions.clone();
-            let chain_id =
-                runtime.open_chain(child_ownership, application_permissions, Amount::ONE)?;
-            assert_eq!(chain_id, child_id);
+            let mut batch = Batch::new();
+            batch.put_key_value_bytes(vec![], b"escrow".to_vec());
+            runtime.write_batch(batch)?;
             Ok(vec![])
-        }
-    }));
+        },
+    ));
     application.expect_call(ExpectedCall::default_finalize());
 
+    let context = create_dummy_operation_context(chain_id);
     let mut controller = ResourceController::default();
-    let operation = Operation::User {
-        application_id,
-        bytes: vec![],
-    };
-    let mut txn_tracker = TransactionTracker::new(
-        Timestamp::from(0),
-        1,
-        first_message_index,
-        0,
-        Some(blob_oracle_responses(blobs.iter())),
-    );
-    view.execute_operation(context, operation, &mut txn_tracker, &mut controller)
-        .await?;
+    view.execute_operation(
+        context,
+        Operation::User {
+            application_id,
+            bytes: vec![],
+        },
+        &mut TransactionTracker::new_replaying_blobs(blobs),
+        &mut controller,
+    )
+    .await?;
 
-    assert_eq!(*view.system.balance.get(), Amount::from_tokens(3));
-    let txn_outcome = txn_tracker.into_outcome().unwrap();
-    let new_blob = &txn_outcome.blobs[0];
-    assert_eq!(new_blob.id().hash, child_id.0);
-    assert_eq!(new_blob.id().blob_type, BlobType::ChainDescription);
-    let created_description: ChainDescription = bcs::from_bytes(&new_blob.clone().into_bytes())
-        .expect("should deserialize a chain description");
-    assert_eq!(created_description.config().balance, Amount::ONE);
-    assert_eq!(created_description.config().ownership, child_ownership);
+    application.expect_call(ExpectedCall::on_chain_close(move |runtime| {
+        assert_matches!(
+            runtime.try_call_application(false, other_id, vec![]),
+            Err(ExecutionError::CrossApplicationCallInClosureHook { .. })
+        );
+        Ok(())
+    }));
+    application.expect_call(ExpectedCall::default_finalize());
 
-    // Initialize the child chain using the new blob.
-    let mut child_view = SystemExecutionState::default()
-        .into_view_with(child_id, Default::default())
-        .await;
-    child_view
-        .context()
-        .extra()
-        .add_blobs([
-            committee_blob.clone(),
-            Blob::new_chain_description(&child_description),
-        ])
-        .await?;
-    child_view
-        .system
-        .initialize_chain(child_description.id())
-        .await
-        .expect("should initialize chain correctly");
-    assert_eq!(*child_view.system.balance.get(), Amount::ONE);
-    assert_eq!(*child_view.system.ownership.get(), child_ownership);
-    assert_eq!(
-        *child_view.system.committees.get(),
-        [(Epoch::ZERO, committee)]
-            .into_iter()
-            .collect::<BTreeMap<_, _>>()
-    );
-    assert_eq!(
-        *child_view.system.application_permissions.get(),
-        ApplicationPermissions::new_single(application_id)
-    );
+    view.execute_operation(
+        context,
+        SystemOperation::CloseChain.into(),
+        &mut TransactionTracker::new_replaying(Vec::new()),
+        &mut controller,
+    )
+    .await?;
+    assert!(view.system.closed.get());
 
     Ok(())
 }
 
-/// Tests the system API call `close_chain`.
+/// Tests that chain owners can upgrade the code of an application, that the new code is
+/// notified with `on_upgrade` and can read the state written by the previous code, and that it
+/// runs the later operations.
 #[tokio::test]
-async fn test_close_chain() -> anyhow::Result<()> {
-    let ownership = ChainOwnership::single(AccountPublicKey::test_key(1).into());
+async fn test_upgrade_application() -> anyhow::Result<()> {
+    let owner = AccountOwner::from(AccountPublicKey::test_key(1));
     let description = dummy_chain_description_with_ownership_and_balance(
         0,
-        ownership.clone(),
+        ChainOwnership::single(owner),
         Amount::from_tokens(5),
     );
     let chain_id = description.id();
-    let state = SystemExecutionState::new(description);
-    let mut view = state.into_view().await;
+    let mut view = SystemExecutionState::new(description).into_view().await;
     let (application_id, application, blobs) = view.register_mock_application(0).await?;
+    let state_key = vec![];
 
-    // The application is not authorized to close the chain.
-    let context = create_dummy_operation_context(chain_id);
-    application.expect_call(ExpectedCall::execute_operation(
+    // The previous code stores a state on the chain.
+    application.expect_call(ExpectedCall::execute_operation({
+        let state_key = state_key.clone();
         move |runtime, _operation| {
-            assert_matches!(
-                runtime.close_chain(),
-                Err(ExecutionError::UnauthorizedApplication(_))
-            );
+            let mut batch = Batch::new();
+            batch.put_key_value_bytes(state_key, b"v1".to_vec());
+            runtime.write_batch(batch)?;
             Ok(vec![])
-        },
-    ));
+        }
+    }));
     application.expect_call(ExpectedCall::default_finalize());
 
+    let mut context = create_dummy_operation_context(chain_id);
     let mut controller = ResourceController::default();
     let operation = Operation::User {
         application_id,
@@ -1308,44 +2075,170 @@ async fn test_close_chain() -> anyhow::Result<()> {
     };
     view.execute_operation(
         context,
-        operation,
+        operation.clone(),
         &mut TransactionTracker::new_replaying_blobs(blobs),
         &mut controller,
     )
     .await?;
-    assert!(!view.system.closed.get());
 
-    // Now we authorize the application and it can close the chain.
-    let permissions = ApplicationPermissions::new_single(application_id);
-    let operation = SystemOperation::ChangeApplicationPermissions(permissions);
+    // Publish the new code. It is loaded with the original description, but the new module.
+    let (original_description, _, _) = create_dummy_user_application_description(0);
+    let (other_description, new_contract, new_service) =
+        create_dummy_user_application_description(1);
+    let module_id = other_description.module_id;
+    let code_description = ApplicationDescription {
+        module_id,
+        ..original_description
+    };
+    let upgraded_application = MockApplication::default();
+    view.context().extra().user_contracts().insert(
+        ApplicationId::from(&code_description),
+        upgraded_application.clone().into(),
+    );
+    let new_blob_ids = [new_contract.id(), new_service.id()];
+    view.context()
+        .extra()
+        .add_blobs([new_contract, new_service])
+        .await?;
+    let upgrade = SystemOperation::UpgradeApplication {
+        application_id,
+        module_id,
+    };
+
+    // Only owners can upgrade the application.
+    let result = view
+        .execute_operation(
+            context,
+            upgrade.clone().into(),
+            &mut TransactionTracker::new_replaying_blobs(new_blob_ids),
+            &mut controller,
+        )
+        .await;
+    assert_matches!(
+        result,
+        Err(ExecutionError::UnauthenticatedApplicationUpgrade)
+    );
+
+    // The new code migrates the state.
+    upgraded_application.expect_call(ExpectedCall::on_upgrade({
+        let state_key = state_key.clone();
+        move |runtime| {
+            assert_eq!(
+                runtime.read_value_bytes(state_key.clone())?,
+                Some(b"v1".to_vec())
+            );
+            let mut batch = Batch::new();
+            batch.put_key_value_bytes(state_key, b"v2".to_vec());
+            runtime.write_batch(batch)?;
+            Ok(())
+        }
+    }));
+    upgraded_application.expect_call(ExpectedCall::default_finalize());
+
+    context.authenticated_signer = Some(owner);
     view.execute_operation(
         context,
-        operation.into(),
-        &mut TransactionTracker::new_replaying(Vec::new()),
+        upgrade.into(),
+        &mut TransactionTracker::new_replaying_blobs(new_blob_ids),
         &mut controller,
     )
     .await?;
+    assert_eq!(
+        view.system
+            .upgraded_applications
+            .get(&application_id)
+            .await?,
+        Some(module_id)
+    );
 
-    application.expect_call(ExpectedCall::execute_operation(
+    // Later operations run the new code, and the application keeps its ID.
+    upgraded_application.expect_call(ExpectedCall::execute_operation(
         move |runtime, _operation| {
-            runtime.close_chain()?;
-            Ok(vec![])
+            assert_eq!(runtime.application_id()?, application_id);
+            Ok(runtime.read_value_bytes(state_key)?.unwrap())
         },
     ));
-    application.expect_call(ExpectedCall::default_finalize());
+    upgraded_application.expect_call(ExpectedCall::default_finalize());
 
-    let operation = Operation::User {
-        application_id,
-        bytes: vec![],
+    let mut txn_tracker = TransactionTracker::new_replaying(Vec::new());
+    view.execute_operation(context, operation, &mut txn_tracker, &mut controller)
+        .await?;
+    let txn_outcome = txn_tracker.into_outcome().unwrap();
+    assert_eq!(txn_outcome.operation_result, b"v2".to_vec());
+
+    Ok(())
+}
+
+/// Tests that a chain's authorization application decides whether a proposal is authorized.
+#[tokio::test]
+async fn test_authorize_proposal() -> anyhow::Result<()> {
+    let (state, chain_id) = SystemExecutionState::dummy_chain_state(0);
+    let mut view = state.into_view().await;
+    let (application_id, application, blobs) = view.register_mock_application(0).await?;
+    let signer = AccountPublicKey::test_key(1).into();
+    let context = OperationContext {
+        authenticated_signer: Some(signer),
+        ..create_dummy_operation_context(chain_id)
+    };
+    let mut controller = ResourceController::default();
+    let authorize = |payload: &[u8]| -> Operation {
+        SystemOperation::AuthorizeProposal {
+            payload: payload.to_vec(),
+        }
+        .into()
     };
+
+    // Without an authorization application, no proposal can be authorized.
+    let result = view
+        .execute_operation(
+            context,
+            authorize(b"approved"),
+            &mut TransactionTracker::new_replaying(Vec::new()),
+            &mut controller,
+        )
+        .await;
+    assert_matches!(result, Err(ExecutionError::NoAuthorizationApplication));
+
     view.execute_operation(
         context,
-        operation,
+        SystemOperation::ChangeAuthorizationApplication {
+            application_id: Some(application_id),
+        }
+        .into(),
         &mut TransactionTracker::new_replaying(Vec::new()),
         &mut controller,
     )
     .await?;
-    assert!(view.system.closed.get());
+    assert_eq!(
+        *view.system.authorization_application.get(),
+        Some(application_id)
+    );
+
+    application.expect_call(ExpectedCall::authorize_proposal(move |runtime, payload| {
+        assert_eq!(runtime.authenticated_signer()?, Some(signer));
+        Ok(payload == b"approved")
+    }));
+    application.expect_call(ExpectedCall::default_finalize());
+    view.execute_operation(
+        context,
+        authorize(b"approved"),
+        &mut TransactionTracker::new_replaying_blobs(blobs),
+        &mut controller,
+    )
+    .await?;
+
+    application.expect_call(ExpectedCall::authorize_proposal(|_runtime, payload| {
+        Ok(payload == b"approved")
+    }));
+    let result = view
+        .execute_operation(
+            context,
+            authorize(b"forged"),
+            &mut TransactionTracker::new_replaying(Vec::new()),
+            &mut controller,
+        )
+        .await;
+    assert_matches!(result, Err(ExecutionError::ProposalNotAuthorized));
 
     Ok(())
 }
@@ -4,9 +4,10 @@
 #![allow(clippy::field_reassign_with_default)]
 
 use linera_base::{
-    crypto::AccountSecretKey,
+    crypto::{AccountSecretKey, CryptoHash},
     data_types::{Amount, BlockHeight, Timestamp},
-    identifiers::{AccountOwner, MessageId},
+    identifiers::{AccountOwner, ApplicationId, MessageId},
+    merkle::verify_state_inclusion,
     ownership::ChainOwnership,
 };
 use linera_execution::{
@@ -19,6 +20,7 @@ use linera_execution::{
     QueryResponse, ResourceController, SystemMessage, SystemOperation, SystemQuery, SystemResponse,
     TransactionTracker,
 };
+use linera_views::views::CryptoHashView as _;
 
 #[tokio::test]
 async fn test_simple_system_operation() -> anyhow::Result<()> {
@@ -127,6 +129,7 @@ async fn test_simple_system_query() -> anyhow::Result<()> {
     let QueryOutcome {
         response,
         operations,
+        ..
     } = view
         .query_application(context, Query::System(SystemQuery), None)
         .await
@@ -141,3 +144,55 @@ async fn test_simple_system_query() -> anyhow::Result<()> {
     assert!(operations.is_empty());
     Ok(())
 }
+
+#[tokio::test]
+async fn test_state_inclusion_proof() -> anyhow::Result<()> {
+    let mut state = SystemExecutionState::new(dummy_chain_description(0));
+    let mut view = state.clone().into_view().await;
+    let app_a = ApplicationId::new(CryptoHash::test_hash("application A"));
+    let app_b = ApplicationId::new(CryptoHash::test_hash("application B"));
+    for (application_id, count) in [(app_a, 3u8), (app_b, 5)] {
+        let mut storage = view.users.try_load_entry_mut(&application_id).await?;
+        for i in 0..count {
+            storage.insert(vec![i], vec![i; 2]).await?;
+        }
+    }
+    // Unless the policy enables it, the state hash is the hash of the view.
+    assert!(!view.uses_merkle_state_hash());
+    assert_eq!(view.state_hash().await?, view.crypto_hash_mut().await?);
+
+    for committee in state.committees.values_mut() {
+        committee.policy_mut().merkle_state_hash = true;
+    }
+    view.system.committees.set(state.committees);
+    assert!(view.uses_merkle_state_hash());
+    let state_hash = view.state_hash().await?;
+    assert_ne!(state_hash, view.crypto_hash_mut().await?);
+    assert_eq!(view.state_hash_mut().await?, state_hash);
+
+    let (value, proof) = view.inclusion_proof(app_b, &[4]).await?.unwrap();
+    assert_eq!(value, vec![4, 4]);
+    assert!(verify_state_inclusion(
+        state_hash,
+        app_b,
+        &[4],
+        &value,
+        &proof
+    ));
+    assert!(!verify_state_inclusion(
+        state_hash,
+        app_b,
+        &[4],
+        &[0],
+        &proof
+    ));
+    assert!(!verify_state_inclusion(
+        state_hash,
+        app_a,
+        &[4],
+        &value,
+        &proof
+    ));
+    assert!(view.inclusion_proof(app_a, &[4]).await?.is_none());
+    Ok(())
+}
@@ -22,11 +22,19 @@ use test_case::test_case;
 /// called correctly and consume the expected amount of fuel.
 ///
 /// To update the bytecode files, run `linera-execution/update_wasm_fixtures.sh`.
-#[cfg_attr(with_wasmer, test_case(WasmRuntime::Wasmer, 71_229; "wasmer"))]
-#[cfg_attr(with_wasmtime, test_case(WasmRuntime::Wasmtime, 71_229; "wasmtime"))]
+///
+/// Successive operations reuse pooled Wasmtime instances, and must behave exactly as with a new
+/// instance each time.
+#[cfg_attr(with_wasmer, test_case(WasmRuntime::Wasmer, false, 71_229; "wasmer"))]
+#[cfg_attr(with_wasmtime, test_case(WasmRuntime::Wasmtime, false, 71_229; "wasmtime"))]
+#[cfg_attr(
+    with_wasmtime,
+    test_case(WasmRuntime::Wasmtime, true, 71_229; "wasmtime without instance pooling")
+)]
 #[test_log::test(tokio::test(flavor = "multi_thread"))]
 async fn test_fuel_for_counter_wasm_application(
     wasm_runtime: WasmRuntime,
+    disable_instance_pooling: bool,
     expected_fuel: u64,
 ) -> anyhow::Result<()> {
     let chain_description = dummy_chain_description(0);
@@ -35,9 +43,11 @@ async fn test_fuel_for_counter_wasm_application(
         description: Some(chain_description),
         ..Default::default()
     };
-    let mut view = state
-        .into_view_with(chain_id, ExecutionRuntimeConfig::default())
-        .await;
+    let config = ExecutionRuntimeConfig {
+        disable_instance_pooling,
+        ..ExecutionRuntimeConfig::default()
+    };
+    let mut view = state.into_view_with(chain_id, config).await;
     let (app_desc, contract_blob, service_blob) = create_dummy_user_application_description(1);
     let app_id = From::from(&app_desc);
     let app_desc_blob_id = Blob::new_application_description(&app_desc).id();
@@ -134,6 +144,7 @@ async fn test_fuel_for_counter_wasm_application(
     let QueryOutcome {
         response: QueryResponse::User(serialized_value),
         operations,
+        ..
     } = outcome
     else {
         panic!("unexpected response")
@@ -145,3 +156,69 @@ async fn test_fuel_for_counter_wasm_application(
     assert!(operations.is_empty());
     Ok(())
 }
+
+/// Tests that a pooled Wasmtime instance starts each transaction in the state it had right
+/// after it was created: the counter application caches its state in memory, so reusing an
+/// instance without resetting it would carry the value from one chain state over to another.
+#[cfg(with_wasmtime)]
+#[test_log::test(tokio::test(flavor = "current_thread"))]
+async fn test_pooled_instances_do_not_leak_state() -> anyhow::Result<()> {
+    let chain_description = dummy_chain_description(0);
+    let chain_id = chain_description.id();
+    let (app_desc, contract_blob, service_blob) = create_dummy_user_application_description(1);
+    let app_id = From::from(&app_desc);
+    let blob_ids = [
+        Blob::new_application_description(&app_desc).id(),
+        contract_blob.id(),
+        service_blob.id(),
+    ];
+    let contract = WasmContractModule::from_file(
+        "tests/fixtures/counter_contract.wasm",
+        WasmRuntime::Wasmtime,
+    )
+    .await?;
+    let context = OperationContext {
+        chain_id,
+        height: BlockHeight(0),
+        round: Some(0),
+        authenticated_signer: None,
+        authenticated_caller_id: None,
+        timestamp: Default::default(),
+    };
+
+    // Each execution state is new, but uses the same module, application and chain, so the
+    // instance of the previous iteration is reused from the pool of the blocking thread.
+    for _ in 0..3 {
+        let state = SystemExecutionState {
+            description: Some(chain_description.clone()),
+            ..Default::default()
+        };
+        let mut view = state
+            .into_view_with(chain_id, ExecutionRuntimeConfig::default())
+            .await;
+        view.context()
+            .extra()
+            .user_contracts()
+            .insert(app_id, contract.clone().into());
+        view.context()
+            .extra()
+            .add_blobs([
+                contract_blob.clone(),
+                service_blob.clone(),
+                Blob::new_application_description(&app_desc),
+            ])
+            .await?;
+
+        let mut txn_tracker = TransactionTracker::new_replaying_blobs(blob_ids);
+        view.execute_operation(
+            context,
+            Operation::user_without_abi(app_id, &5_u64).unwrap(),
+            &mut txn_tracker,
+            &mut ResourceController::default(),
+        )
+        .await?;
+        let txn_outcome = txn_tracker.into_outcome().unwrap();
+        assert_eq!(bcs::from_bytes::<u64>(&txn_outcome.operation_result)?, 5);
+    }
+    Ok(())
+}
@@ -53,6 +53,7 @@ test('Block mounting', () => {
             oracleResponses: [],
             blobs: [[]],
             operationResults: [],
+            fees: { fuel: "0.", storageReads: "0.", storageWrites: "0.", messages: "0.", blobBytes: "0.", operations: "0.", other: "0." },
             incomingBundles: [{
               origin: {
                 medium: "Direct",
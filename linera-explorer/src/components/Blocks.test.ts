@@ -55,6 +55,7 @@ test('Blocks mounting', () => {
                 oracleResponses: [],
                 blobs: [[]],
                 operationResults: [],
+                fees: { fuel: "0.", storageReads: "0.", storageWrites: "0.", messages: "0.", blobBytes: "0.", operations: "0.", other: "0." },
                 incomingBundles: [{
                   origin: {
                     medium: "Direct",
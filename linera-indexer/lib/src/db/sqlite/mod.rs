@@ -283,8 +283,21 @@ impl SqliteDatabase {
                     SystemOperation::ProcessNewEpoch(_) => "ProcessNewEpoch",
                     SystemOperation::ProcessRemovedEpoch(_) => "ProcessRemovedEpoch",
                     SystemOperation::UpdateStreams(_) => "UpdateStreams",
+                    SystemOperation::ProcessValidatorWeights(_) => "ProcessValidatorWeights",
                     SystemOperation::ChangeOwnership { .. } => "ChangeOwnership",
                     SystemOperation::VerifyBlob { .. } => "VerifyBlob",
+                    SystemOperation::SponsorFees { .. } => "SponsorFees",
+                    SystemOperation::ChangeInboxQuotas(_) => "ChangeInboxQuotas",
+                    SystemOperation::ChangeAuthorizationApplication { .. } => {
+                        "ChangeAuthorizationApplication"
+                    }
+                    SystemOperation::ChangeOperationPermissions(_) => "ChangeOperationPermissions",
+                    SystemOperation::AuthorizeProposal { .. } => "AuthorizeProposal",
+                    SystemOperation::StageApplicationPermissions(_) => {
+                        "StageApplicationPermissions"
+                    }
+                    SystemOperation::SetApplicationConfig { .. } => "SetApplicationConfig",
+                    SystemOperation::UpgradeApplication { .. } => "UpgradeApplication",
                 };
                 ("System", None, Some(sys_op_type))
             }
@@ -444,6 +457,25 @@ impl SqliteDatabase {
                     })?;
                     ("EventExists", None, Some(serialized))
                 }
+                OracleResponse::EthereumStorage(key, value) => {
+                    let serialized = bincode::serialize(&(key, value)).map_err(|e| {
+                        SqliteError::Serialization(format!(
+                            "Failed to serialize Ethereum storage: {}",
+                            e
+                        ))
+                    })?;
+                    ("EthereumStorage", None, Some(serialized))
+                }
+                OracleResponse::ApplicationValue(chain_id, application_id, key, value) => {
+                    let serialized = bincode::serialize(&(chain_id, application_id, key, value))
+                        .map_err(|e| {
+                            SqliteError::Serialization(format!(
+                                "Failed to serialize application value: {}",
+                                e
+                            ))
+                        })?;
+                    ("ApplicationValue", None, Some(serialized))
+                }
             };
 
         sqlx::query(
@@ -1054,6 +1086,12 @@ impl SqliteDatabase {
                     SystemMessage::ApplicationCreated => {
                         ("ApplicationCreated", None, None, None, None, None)
                     }
+                    SystemMessage::SubscribeToChannel { .. } => {
+                        ("SubscribeToChannel", None, None, None, None, None)
+                    }
+                    SystemMessage::UnsubscribeFromChannel { .. } => {
+                        ("UnsubscribeFromChannel", None, None, None, None, None)
+                    }
                 };
 
                 MessageClassification {
@@ -3,7 +3,7 @@
 
 use linera_base::{
     crypto::{CryptoHash, TestString},
-    data_types::{Amount, Blob, BlockHeight, Epoch, Timestamp},
+    data_types::{Amount, Blob, BlockHeight, Epoch, FeeBreakdown, Timestamp},
     hashed::Hashed,
     identifiers::{ApplicationId, ChainId},
 };
@@ -272,6 +272,7 @@ fn create_test_block(chain_id: ChainId, height: BlockHeight) -> Block {
             events_hash: CryptoHash::new(&TestString::new("events_hash")),
             blobs_hash: CryptoHash::new(&TestString::new("blobs_hash")),
             operation_results_hash: CryptoHash::new(&TestString::new("operation_results_hash")),
+            fees_hash: CryptoHash::new(&TestString::new("fees_hash")),
         },
         body: BlockBody {
             incoming_bundles: vec![],
@@ -283,6 +284,7 @@ fn create_test_block(chain_id: ChainId, height: BlockHeight) -> Block {
             events: vec![],
             blobs: vec![],
             operation_results: vec![],
+            fees: FeeBreakdown::default(),
         },
     }
 }
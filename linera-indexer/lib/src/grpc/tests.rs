@@ -37,7 +37,7 @@ fn valid_block_element_with_chain_id(chain_suffix: &str) -> Element {
 
     use linera_base::{
         crypto::CryptoHash,
-        data_types::{BlockHeight, Epoch, Round, Timestamp},
+        data_types::{BlockHeight, Epoch, FeeBreakdown, Round, Timestamp},
         identifiers::{ChainId, StreamId},
     };
     use linera_chain::{
@@ -69,6 +69,7 @@ fn valid_block_element_with_chain_id(chain_suffix: &str) -> Element {
         operation_results: vec![],
         previous_event_blocks: BTreeMap::<StreamId, (CryptoHash, BlockHeight)>::new(),
         previous_message_blocks: BTreeMap::<ChainId, (CryptoHash, BlockHeight)>::new(),
+        fees: FeeBreakdown::default(),
     };
 
     let block = Block::new(proposed_block, outcome);
@@ -7,7 +7,7 @@ use clap::Parser as _;
 use linera_views::{
     lru_caching::StorageCacheConfig,
     rocks_db::{
-        PathWithGuard, RocksDbDatabase, RocksDbSpawnMode, RocksDbStoreConfig,
+        DurabilityPolicy, PathWithGuard, RocksDbDatabase, RocksDbSpawnMode, RocksDbStoreConfig,
         RocksDbStoreInternalConfig,
     },
     store::KeyValueDatabase as _,
@@ -68,6 +68,8 @@ impl RocksDbRunner {
             spawn_mode,
             path_with_guard,
             max_stream_queries: config.client.max_stream_queries,
+            durability: DurabilityPolicy::default(),
+            range_compaction_threshold: None,
         };
         let store_config = RocksDbStoreConfig {
             inner_config,
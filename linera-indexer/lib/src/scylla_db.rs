@@ -3,7 +3,10 @@
 
 use linera_views::{
     lru_caching::StorageCacheConfig,
-    scylla_db::{ScyllaDbDatabase, ScyllaDbStoreConfig, ScyllaDbStoreInternalConfig},
+    scylla_db::{
+        ScyllaDbConsistencyConfig, ScyllaDbDatabase, ScyllaDbStoreConfig,
+        ScyllaDbStoreInternalConfig,
+    },
     store::KeyValueDatabase,
 };
 
@@ -45,6 +48,10 @@ pub struct ScyllaDbConfig {
     /// The replication factor for the keyspace
     #[arg(long, default_value = "1")]
     pub replication_factor: u32,
+
+    /// The consistency level of each class of requests, e.g. `blob_prefetch=one`
+    #[arg(long)]
+    pub consistency: Option<ScyllaDbConsistencyConfig>,
 }
 
 pub type ScyllaDbRunner = Runner<ScyllaDbDatabase, ScyllaDbConfig>;
@@ -62,6 +69,7 @@ impl ScyllaDbRunner {
             max_stream_queries: config.client.max_stream_queries,
             max_concurrent_queries: config.client.max_concurrent_queries,
             replication_factor: config.client.replication_factor,
+            consistency: config.client.consistency.unwrap_or_default(),
         };
         let store_config = ScyllaDbStoreConfig {
             inner_config,
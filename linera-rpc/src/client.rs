@@ -3,7 +3,7 @@
 
 use linera_base::{
     crypto::CryptoHash,
-    data_types::{BlobContent, NetworkDescription},
+    data_types::{BlobContent, BlockHeight, NetworkDescription},
     identifiers::{BlobId, ChainId},
 };
 use linera_chain::{
@@ -14,8 +14,11 @@ use linera_chain::{
 };
 use linera_core::{
     data_types::{ChainInfoQuery, ChainInfoResponse},
-    node::{CrossChainMessageDelivery, NodeError, NotificationStream, ValidatorNode},
+    node::{
+        CertificateStream, CrossChainMessageDelivery, NodeError, NotificationStream, ValidatorNode,
+    },
 };
+use linera_execution::BlobProvenance;
 
 use crate::grpc::GrpcClient;
 #[cfg(with_simple_network)]
@@ -254,6 +257,15 @@ impl ValidatorNode for Client {
         })
     }
 
+    async fn blob_provenance(&self, blob_id: BlobId) -> Result<BlobProvenance, NodeError> {
+        Ok(match self {
+            Client::Grpc(grpc_client) => grpc_client.blob_provenance(blob_id).await?,
+
+            #[cfg(with_simple_network)]
+            Client::Simple(simple_client) => simple_client.blob_provenance(blob_id).await?,
+        })
+    }
+
     async fn missing_blob_ids(&self, blob_ids: Vec<BlobId>) -> Result<Vec<BlobId>, NodeError> {
         Ok(match self {
             Client::Grpc(grpc_client) => grpc_client.missing_blob_ids(blob_ids).await?,
@@ -262,4 +274,26 @@ impl ValidatorNode for Client {
             Client::Simple(simple_client) => simple_client.missing_blob_ids(blob_ids).await?,
         })
     }
+
+    async fn stream_certificates(
+        &self,
+        chain_id: ChainId,
+        start: BlockHeight,
+        include_blobs: bool,
+    ) -> Result<CertificateStream, NodeError> {
+        Ok(match self {
+            Client::Grpc(grpc_client) => {
+                grpc_client
+                    .stream_certificates(chain_id, start, include_blobs)
+                    .await?
+            }
+
+            #[cfg(with_simple_network)]
+            Client::Simple(simple_client) => {
+                simple_client
+                    .stream_certificates(chain_id, start, include_blobs)
+                    .await?
+            }
+        })
+    }
 }
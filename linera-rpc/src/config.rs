@@ -64,6 +64,60 @@ pub struct NotificationConfig {
     pub(crate) notification_queue_size: usize,
 }
 
+/// Limits on the requests a shard handles concurrently. Requests beyond these limits are
+/// rejected with `RESOURCE_EXHAUSTED`, which clients retry after a delay. Cross-chain
+/// requests between shards are only bounded by the cross-chain queue.
+///
+/// Every request admitted for a chain waits in the queue of that chain's worker, so the
+/// per-chain limit is also the maximum length of these queues.
+#[derive(Clone, Debug, Default, Parser)]
+pub struct RequestLimitsConfig {
+    /// Maximum number of requests handled concurrently by a shard.
+    #[arg(long)]
+    pub max_in_flight_requests: Option<usize>,
+
+    /// Maximum number of requests handled concurrently for a single client connection,
+    /// as forwarded by the proxy.
+    #[arg(long)]
+    pub max_in_flight_requests_per_peer: Option<usize>,
+
+    /// Maximum number of requests handled concurrently for a single chain.
+    #[arg(long)]
+    pub max_in_flight_requests_per_chain: Option<usize>,
+}
+
+/// The algorithm used to compress gRPC messages.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum CompressionAlgorithm {
+    /// Messages are sent uncompressed.
+    #[default]
+    None,
+    Gzip,
+    Zstd,
+}
+
+/// The compression of the gRPC messages sent by a validator's proxies and shards.
+///
+/// Compressed messages are always accepted, whatever the algorithm. Responses to clients are
+/// only compressed if the client announces that it accepts the algorithm.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CompressionConfig {
+    /// The algorithm used to compress the messages sent.
+    pub algorithm: CompressionAlgorithm,
+    /// Messages whose uncompressed size is smaller than this, in bytes, are sent uncompressed.
+    pub min_message_size: usize,
+}
+
+impl Default for CompressionConfig {
+    fn default() -> Self {
+        CompressionConfig {
+            algorithm: CompressionAlgorithm::None,
+            min_message_size: 1024,
+        }
+    }
+}
+
 pub type ShardId = usize;
 
 /// The network configuration of a shard.
@@ -75,6 +129,8 @@ pub struct ShardConfig {
     pub port: u16,
     /// The port on which metrics are served.
     pub metrics_port: Option<u16>,
+    /// The port on which the admin endpoint of the shard is served, on localhost only.
+    pub admin_port: Option<u16>,
 }
 
 impl ShardConfig {
@@ -160,6 +216,24 @@ pub struct ValidatorInternalNetworkPreConfig<P> {
     pub block_exporters: Vec<ExporterServiceConfig>,
     /// The available proxies.
     pub proxies: Vec<ProxyConfig>,
+    /// The compression of the gRPC messages sent by the proxies and shards.
+    #[serde(default)]
+    pub compression: CompressionConfig,
+    /// Whether the shards sign the cross-chain requests they send to each other. Signed
+    /// requests are always checked by the receiving shard.
+    #[serde(default = "default_sign_cross_chain_requests")]
+    pub sign_cross_chain_requests: bool,
+    /// Whether the shards reject the unsigned cross-chain requests.
+    ///
+    /// To roll this out, first restart all shards with `sign_cross_chain_requests` set, which
+    /// is the default, and only then restart them with this flag set as well. Otherwise, the
+    /// requests of the shards that do not sign yet are dropped.
+    #[serde(default)]
+    pub require_signed_cross_chain_requests: bool,
+}
+
+fn default_sign_cross_chain_requests() -> bool {
+    true
 }
 
 impl<P> ValidatorInternalNetworkPreConfig<P> {
@@ -170,6 +244,9 @@ impl<P> ValidatorInternalNetworkPreConfig<P> {
             shards: self.shards.clone(),
             block_exporters: self.block_exporters.clone(),
             proxies: self.proxies.clone(),
+            compression: self.compression,
+            sign_cross_chain_requests: self.sign_cross_chain_requests,
+            require_signed_cross_chain_requests: self.require_signed_cross_chain_requests,
         }
     }
 }
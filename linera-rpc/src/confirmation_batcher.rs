@@ -0,0 +1,238 @@
+// Copyright (c) Zefchain Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! Batching of the confirmations that recipients received cross-chain messages.
+//!
+//! A chain sending messages to many recipients receives a confirmation from each of them.
+//! Instead of saving the sender's chain state once per confirmation, the confirmations that
+//! arrive while earlier ones of the same sender are being handled are grouped and handled
+//! together. Each sender is handled on its own, so that a slow one does not delay the others,
+//! and a confirmation is only acknowledged once it was saved.
+
+#![cfg(with_server)]
+
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    future::Future,
+    sync::Arc,
+};
+
+use futures::{
+    channel::{mpsc, oneshot},
+    stream::FuturesUnordered,
+    SinkExt as _, StreamExt as _,
+};
+use linera_base::{data_types::BlockHeight, identifiers::ChainId};
+use linera_core::{
+    join_set_ext::JoinSet,
+    worker::{WorkerError, WorkerState},
+    JoinSetExt as _,
+};
+use linera_storage::Storage;
+use thiserror::Error;
+use tracing::{error, trace};
+
+/// The maximum number of confirmations waiting to be handled. Beyond that, the requests
+/// wait before being queued.
+const CHANNEL_CAPACITY: usize = 1_000;
+
+/// An error handling a confirmation.
+#[derive(Debug, Error)]
+pub(crate) enum ConfirmationError {
+    #[error(transparent)]
+    Worker(#[from] Arc<WorkerError>),
+
+    #[error("the task handling the cross-chain confirmations has stopped")]
+    Stopped,
+}
+
+/// A confirmation that `recipient` received the messages of `sender` up to `latest_height`.
+struct Confirmation {
+    sender: ChainId,
+    recipient: ChainId,
+    latest_height: BlockHeight,
+    callback: oneshot::Sender<Result<(), ConfirmationError>>,
+}
+
+/// The confirmations of a sender chain that are handled together.
+#[derive(Default)]
+struct Batch {
+    heights: BTreeMap<ChainId, BlockHeight>,
+    callbacks: Vec<oneshot::Sender<Result<(), ConfirmationError>>>,
+}
+
+impl Batch {
+    fn add(&mut self, confirmation: Confirmation) {
+        let height = self.heights.entry(confirmation.recipient).or_default();
+        *height = (*height).max(confirmation.latest_height);
+        self.callbacks.push(confirmation.callback);
+    }
+}
+
+/// Queues confirmations to be handled in batches by a background task.
+#[derive(Clone)]
+pub(crate) struct ConfirmationBatcher {
+    sender: mpsc::Sender<Confirmation>,
+}
+
+impl ConfirmationBatcher {
+    /// Spawns the task handling the confirmations with the given worker.
+    pub(crate) fn spawn<S>(state: WorkerState<S>, join_set: &mut JoinSet) -> Self
+    where
+        S: Storage + Clone + Send + Sync + 'static,
+    {
+        let (sender, receiver) = mpsc::channel(CHANNEL_CAPACITY);
+        let handle = move |sender, confirmations| {
+            let state = state.clone();
+            async move {
+                state
+                    .confirm_updated_recipients(sender, confirmations)
+                    .await
+            }
+        };
+        join_set.spawn_task(handle_confirmations(receiver, handle));
+        ConfirmationBatcher { sender }
+    }
+
+    /// Confirms that `recipient` received the messages of `sender` up to `latest_height`,
+    /// and returns once the confirmation was saved.
+    pub(crate) async fn confirm(
+        &self,
+        sender: ChainId,
+        recipient: ChainId,
+        latest_height: BlockHeight,
+    ) -> Result<(), ConfirmationError> {
+        let (callback, result) = oneshot::channel();
+        let confirmation = Confirmation {
+            sender,
+            recipient,
+            latest_height,
+            callback,
+        };
+        self.sender
+            .clone()
+            .send(confirmation)
+            .await
+            .map_err(|_| ConfirmationError::Stopped)?;
+        result.await.map_err(|_| ConfirmationError::Stopped)?
+    }
+}
+
+/// Handles the received confirmations with `handle`, one batch per sender at a time.
+async fn handle_confirmations<F, Fut>(mut receiver: mpsc::Receiver<Confirmation>, handle: F)
+where
+    F: Fn(ChainId, BTreeMap<ChainId, BlockHeight>) -> Fut,
+    Fut: Future<Output = Result<(), WorkerError>>,
+{
+    let mut pending = BTreeMap::<ChainId, Batch>::new();
+    let mut busy = BTreeSet::new();
+    let mut in_progress = FuturesUnordered::new();
+    loop {
+        futures::select! {
+            confirmation = receiver.next() => {
+                let Some(confirmation) = confirmation else {
+                    break;
+                };
+                pending.entry(confirmation.sender).or_default().add(confirmation);
+            }
+            sender = in_progress.select_next_some() => {
+                busy.remove(&sender);
+            }
+        }
+        // Confirmations of a sender being handled wait for the next batch of that sender.
+        let ready = pending
+            .keys()
+            .filter(|sender| !busy.contains(*sender))
+            .copied()
+            .collect::<Vec<_>>();
+        for sender in ready {
+            let batch = pending
+                .remove(&sender)
+                .expect("sender has pending confirmations");
+            busy.insert(sender);
+            in_progress.push(handle_batch(&handle, sender, batch));
+        }
+    }
+    while in_progress.next().await.is_some() {}
+}
+
+/// Handles the confirmations of `sender`, and returns `sender` once they are saved.
+async fn handle_batch<F, Fut>(handle: &F, sender: ChainId, batch: Batch) -> ChainId
+where
+    F: Fn(ChainId, BTreeMap<ChainId, BlockHeight>) -> Fut,
+    Fut: Future<Output = Result<(), WorkerError>>,
+{
+    trace!(
+        ?sender,
+        num_confirmations = batch.heights.len(),
+        "Handling cross-chain confirmations"
+    );
+    let result = handle(sender, batch.heights).await.map_err(Arc::new);
+    if let Err(error) = &result {
+        error!(%error, "Failed to handle cross-chain confirmations");
+    }
+    for callback in batch.callbacks {
+        // The request may have been cancelled in the meantime.
+        let _ = callback.send(result.clone().map_err(ConfirmationError::Worker));
+    }
+    sender
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::RefCell, rc::Rc};
+
+    use futures::{executor::LocalPool, task::LocalSpawnExt as _, FutureExt as _};
+    use linera_base::crypto::CryptoHash;
+
+    use super::*;
+
+    #[test]
+    fn test_slow_sender_does_not_delay_others() {
+        let slow = ChainId(CryptoHash::test_hash("slow"));
+        let fast = ChainId(CryptoHash::test_hash("fast"));
+        let recipient = ChainId(CryptoHash::test_hash("recipient"));
+        let (release, gate) = oneshot::channel::<()>();
+        let gate = gate.shared();
+        let handle = move |sender: ChainId, _confirmations: BTreeMap<ChainId, BlockHeight>| {
+            let gate = gate.clone();
+            async move {
+                if sender == slow {
+                    let _ = gate.await;
+                }
+                Ok::<_, WorkerError>(())
+            }
+        };
+
+        let mut pool = LocalPool::new();
+        let spawner = pool.spawner();
+        let (sender, receiver) = mpsc::channel(CHANNEL_CAPACITY);
+        let batcher = ConfirmationBatcher { sender };
+        spawner
+            .spawn_local(handle_confirmations(receiver, handle))
+            .unwrap();
+        let results = Rc::new(RefCell::new(BTreeMap::new()));
+        for chain_id in [slow, fast] {
+            let batcher = batcher.clone();
+            let results = results.clone();
+            spawner
+                .spawn_local(async move {
+                    let result = batcher
+                        .confirm(chain_id, recipient, BlockHeight::from(1))
+                        .await;
+                    results.borrow_mut().insert(chain_id, result.is_ok());
+                })
+                .unwrap();
+        }
+
+        pool.run_until_stalled();
+        assert_eq!(*results.borrow(), BTreeMap::from([(fast, true)]));
+
+        release.send(()).unwrap();
+        pool.run_until_stalled();
+        assert_eq!(
+            *results.borrow(),
+            BTreeMap::from([(fast, true), (slow, true)])
+        );
+    }
+}
@@ -13,7 +13,7 @@ use std::{
 
 use futures::{channel::mpsc, StreamExt as _};
 use linera_base::identifiers::ChainId;
-use linera_core::data_types::CrossChainRequest;
+use linera_core::data_types::{CrossChainRequest, SignedCrossChainRequest};
 use rand::Rng as _;
 use tracing::{trace, warn};
 
@@ -37,10 +37,10 @@ pub(crate) async fn forward_cross_chain_queries<F, G>(
     cross_chain_sender_delay: Duration,
     cross_chain_sender_failure_rate: f32,
     this_shard: ShardId,
-    mut receiver: mpsc::Receiver<(CrossChainRequest, ShardId)>,
+    mut receiver: mpsc::Receiver<(SignedCrossChainRequest, ShardId)>,
     handle_request: F,
 ) where
-    F: Fn(ShardId, CrossChainRequest) -> G + Send + Clone + 'static,
+    F: Fn(ShardId, SignedCrossChainRequest) -> G + Send + Clone + 'static,
     G: Future<Output = anyhow::Result<()>>,
 {
     let mut steps = futures::stream::FuturesUnordered::new();
@@ -114,7 +114,7 @@ pub(crate) async fn forward_cross_chain_queries<F, G>(
                     continue;
                 }
 
-                let queue = QueueId::new(&request);
+                let queue = QueueId::new(&request.request);
 
                 let task = Task {
                     shard_id,
@@ -189,7 +189,7 @@ struct Task {
     /// The ID of the shard the request is sent to.
     pub shard_id: ShardId,
     /// The cross-chain request to be sent.
-    pub request: linera_core::data_types::CrossChainRequest,
+    pub request: SignedCrossChainRequest,
 }
 
 #[derive(Clone)]
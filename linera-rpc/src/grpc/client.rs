@@ -6,7 +6,7 @@ use std::{fmt, future::Future, iter};
 use futures::{future, stream, StreamExt};
 use linera_base::{
     crypto::CryptoHash,
-    data_types::{BlobContent, NetworkDescription},
+    data_types::{BlobContent, BlockHeight, NetworkDescription},
     ensure,
     identifiers::{BlobId, ChainId},
     time::Duration,
@@ -20,9 +20,13 @@ use linera_chain::{
 };
 use linera_core::{
     data_types::ChainInfoResponse,
-    node::{CrossChainMessageDelivery, NodeError, NotificationStream, ValidatorNode},
+    node::{
+        CertificateStream, CrossChainMessageDelivery, NodeError, NotificationStream,
+        StreamedCertificate, ValidatorNode,
+    },
     worker::Notification,
 };
+use linera_execution::BlobProvenance;
 use linera_version::VersionInfo;
 use tonic::{Code, IntoRequest, Request, Status};
 use tracing::{debug, error, info, instrument, warn};
@@ -51,9 +55,15 @@ impl GrpcClient {
         retry_delay: Duration,
         max_retries: u32,
     ) -> Self {
-        let client = ValidatorNodeClient::new(channel)
+        #[cfg_attr(web, allow(unused_mut))]
+        let mut client = ValidatorNodeClient::new(channel)
             .max_encoding_message_size(GRPC_MAX_MESSAGE_SIZE)
             .max_decoding_message_size(GRPC_MAX_MESSAGE_SIZE);
+        // Validators compress large responses if the client accepts it.
+        #[cfg(not(web))]
+        for encoding in super::compression::ACCEPTED_ENCODINGS {
+            client = client.accept_compressed(encoding);
+        }
         Self {
             address,
             client,
@@ -74,7 +84,11 @@ impl GrpcClient {
                 info!("gRPC request interrupted: {}; retrying", status);
                 true
             }
-            Code::Ok | Code::Cancelled | Code::ResourceExhausted => {
+            Code::ResourceExhausted => {
+                info!("gRPC server overloaded: {}; retrying", status);
+                true
+            }
+            Code::Ok | Code::Cancelled => {
                 error!("Unexpected gRPC status: {}; retrying", status);
                 true
             }
@@ -439,8 +453,27 @@ impl ValidatorNode for GrpcClient {
         Ok(client_delegate!(self, blob_last_used_by, blob_id)?.try_into()?)
     }
 
+    #[instrument(target = "grpc_client", skip(self), err, fields(address = self.address))]
+    async fn blob_provenance(&self, blob_id: BlobId) -> Result<BlobProvenance, NodeError> {
+        Ok(client_delegate!(self, blob_provenance, blob_id)?.try_into()?)
+    }
+
     #[instrument(target = "grpc_client", skip(self), err, fields(address = self.address))]
     async fn missing_blob_ids(&self, blob_ids: Vec<BlobId>) -> Result<Vec<BlobId>, NodeError> {
         Ok(client_delegate!(self, missing_blob_ids, blob_ids)?.try_into()?)
     }
+
+    #[instrument(target = "grpc_client", skip(self), err, fields(address = self.address))]
+    async fn stream_certificates(
+        &self,
+        chain_id: ChainId,
+        start: BlockHeight,
+        include_blobs: bool,
+    ) -> Result<CertificateStream, NodeError> {
+        let request = (chain_id, start, include_blobs);
+        let stream = client_delegate!(self, stream_certificates, request)?;
+        Ok(Box::pin(
+            stream.map(|result| Ok(StreamedCertificate::try_from(result?)?)),
+        ))
+    }
 }
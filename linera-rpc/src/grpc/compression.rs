@@ -0,0 +1,119 @@
+// Copyright (c) Zefchain Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! Compression of gRPC messages, negotiated with the `grpc-encoding` and
+//! `grpc-accept-encoding` headers.
+//!
+//! Certificates and message bundles compress well, but small messages aren't worth the
+//! cost: the [`CompressionConfig`] decides for each message whether it is compressed.
+
+use tonic::{codec::CompressionEncoding, Response};
+
+use crate::config::{CompressionAlgorithm, CompressionConfig};
+
+/// The encodings accepted by all clients and servers.
+pub const ACCEPTED_ENCODINGS: [CompressionEncoding; 2] =
+    [CompressionEncoding::Gzip, CompressionEncoding::Zstd];
+
+#[cfg(with_metrics)]
+mod metrics {
+    use std::sync::LazyLock;
+
+    use linera_base::prometheus_util::register_int_counter_vec;
+    use prometheus::IntCounterVec;
+
+    pub static COMPRESSION_MESSAGES: LazyLock<IntCounterVec> = LazyLock::new(|| {
+        register_int_counter_vec(
+            "grpc_compression_messages",
+            "Number of gRPC messages sent, by compression algorithm",
+            &["algorithm"],
+        )
+    });
+
+    pub static COMPRESSION_UNCOMPRESSED_BYTES: LazyLock<IntCounterVec> = LazyLock::new(|| {
+        register_int_counter_vec(
+            "grpc_compression_uncompressed_bytes",
+            "Size before compression of the gRPC messages sent, by compression algorithm",
+            &["algorithm"],
+        )
+    });
+}
+
+impl CompressionAlgorithm {
+    fn encoding(&self) -> Option<CompressionEncoding> {
+        match self {
+            CompressionAlgorithm::None => None,
+            CompressionAlgorithm::Gzip => Some(CompressionEncoding::Gzip),
+            CompressionAlgorithm::Zstd => Some(CompressionEncoding::Zstd),
+        }
+    }
+
+    #[cfg(with_metrics)]
+    fn label(&self) -> &'static str {
+        match self {
+            CompressionAlgorithm::None => "none",
+            CompressionAlgorithm::Gzip => "gzip",
+            CompressionAlgorithm::Zstd => "zstd",
+        }
+    }
+}
+
+impl CompressionConfig {
+    /// Returns the encoding to send messages with, if compression is enabled.
+    pub fn encoding(&self) -> Option<CompressionEncoding> {
+        self.algorithm.encoding()
+    }
+
+    /// Returns the encoding to send `message` with, or `None` if it should be sent
+    /// uncompressed.
+    pub fn encoding_for(&self, message: &impl prost::Message) -> Option<CompressionEncoding> {
+        let size = message.encoded_len();
+        let algorithm = if size < self.min_message_size {
+            CompressionAlgorithm::None
+        } else {
+            self.algorithm
+        };
+        #[cfg(with_metrics)]
+        {
+            let label = algorithm.label();
+            metrics::COMPRESSION_MESSAGES
+                .with_label_values(&[label])
+                .inc();
+            metrics::COMPRESSION_UNCOMPRESSED_BYTES
+                .with_label_values(&[label])
+                .inc_by(size as u64);
+        }
+        algorithm.encoding()
+    }
+
+    /// Returns a response with `message`, which is only compressed if it is large enough
+    /// and compression is enabled on the server.
+    pub fn response<T: prost::Message>(&self, message: T) -> Response<T> {
+        let compress = self.encoding_for(&message).is_some();
+        let mut response = Response::new(message);
+        if !compress {
+            response.disable_compression();
+        }
+        response
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_small_messages_are_not_compressed() {
+        let config = CompressionConfig {
+            algorithm: CompressionAlgorithm::Zstd,
+            min_message_size: 10,
+        };
+        assert_eq!(config.encoding_for(&vec![0u8; 5]), None);
+        assert_eq!(
+            config.encoding_for(&vec![0u8; 10]),
+            Some(CompressionEncoding::Zstd)
+        );
+        let disabled = CompressionConfig::default();
+        assert_eq!(disabled.encoding_for(&vec![0u8; 10_000]), None);
+    }
+}
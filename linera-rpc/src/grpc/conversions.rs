@@ -6,7 +6,7 @@ use linera_base::{
         AccountPublicKey, AccountSignature, CryptoError, CryptoHash, ValidatorPublicKey,
         ValidatorSignature,
     },
-    data_types::{BlobContent, BlockHeight, NetworkDescription},
+    data_types::{Blob, BlobContent, BlockHeight, NetworkDescription},
     ensure,
     identifiers::{AccountOwner, BlobId, ChainId},
 };
@@ -18,10 +18,11 @@ use linera_chain::{
     },
 };
 use linera_core::{
-    data_types::{ChainInfoQuery, ChainInfoResponse, CrossChainRequest},
-    node::NodeError,
+    data_types::{ChainInfoQuery, ChainInfoResponse, CrossChainRequest, SignedCrossChainRequest},
+    node::{NodeError, StreamedCertificate},
     worker::Notification,
 };
+use linera_execution::BlobProvenance;
 use thiserror::Error;
 use tonic::{Code, Status};
 
@@ -245,6 +246,11 @@ impl TryFrom<BlockProposal> for api::BlockProposal {
                 .original_proposal
                 .map(|cert| bincode::serialize(&cert))
                 .transpose()?,
+            idempotency_key: block_proposal.idempotency_key.map(Into::into),
+            sponsor_approval: block_proposal
+                .sponsor_approval
+                .map(|approval| bincode::serialize(&approval))
+                .transpose()?,
         })
     }
 }
@@ -265,17 +271,29 @@ impl TryFrom<api::BlockProposal> for BlockProposal {
                 .original_proposal
                 .map(|bytes| bincode::deserialize(&bytes))
                 .transpose()?,
+            idempotency_key: block_proposal
+                .idempotency_key
+                .map(CryptoHash::try_from)
+                .transpose()?,
+            sponsor_approval: block_proposal
+                .sponsor_approval
+                .map(|bytes| bincode::deserialize(&bytes))
+                .transpose()?,
         })
     }
 }
 
-impl TryFrom<api::CrossChainRequest> for CrossChainRequest {
+impl TryFrom<api::CrossChainRequest> for SignedCrossChainRequest {
     type Error = GrpcProtoConversionError;
 
     fn try_from(cross_chain_request: api::CrossChainRequest) -> Result<Self, Self::Error> {
         use api::cross_chain_request::Inner;
 
-        let ccr = match cross_chain_request
+        let signature = cross_chain_request
+            .signature
+            .map(TryInto::try_into)
+            .transpose()?;
+        let request = match cross_chain_request
             .inner
             .ok_or(GrpcProtoConversionError::MissingField)?
         {
@@ -300,17 +318,17 @@ impl TryFrom<api::CrossChainRequest> for CrossChainRequest {
                     .into(),
             },
         };
-        Ok(ccr)
+        Ok(Self { request, signature })
     }
 }
 
-impl TryFrom<CrossChainRequest> for api::CrossChainRequest {
+impl TryFrom<SignedCrossChainRequest> for api::CrossChainRequest {
     type Error = GrpcProtoConversionError;
 
-    fn try_from(cross_chain_request: CrossChainRequest) -> Result<Self, Self::Error> {
+    fn try_from(cross_chain_request: SignedCrossChainRequest) -> Result<Self, Self::Error> {
         use api::cross_chain_request::Inner;
 
-        let inner = match cross_chain_request {
+        let inner = match cross_chain_request.request {
             CrossChainRequest::UpdateRecipient {
                 sender,
                 recipient,
@@ -330,7 +348,10 @@ impl TryFrom<CrossChainRequest> for api::CrossChainRequest {
                 latest_height: Some(latest_height.into()),
             }),
         };
-        Ok(Self { inner: Some(inner) })
+        Ok(Self {
+            inner: Some(inner),
+            signature: cross_chain_request.signature.map(Into::into),
+        })
     }
 }
 
@@ -593,6 +614,18 @@ impl TryFrom<api::ChainInfoQuery> for ChainInfoQuery {
             .request_sent_certificate_hashes_in_range
             .map(|range| bincode::deserialize(&range))
             .transpose()?;
+        let request_received_log_in_range = chain_info_query
+            .request_received_log_in_range
+            .map(|range| bincode::deserialize(&range))
+            .transpose()?;
+        let request_fields = chain_info_query
+            .request_fields
+            .map(|fields| bincode::deserialize(&fields))
+            .transpose()?;
+        let request_state_inclusion_proof = chain_info_query
+            .request_state_inclusion_proof
+            .map(|request| bincode::deserialize(&request))
+            .transpose()?;
 
         Ok(Self {
             request_committees: chain_info_query.request_committees,
@@ -602,10 +635,16 @@ impl TryFrom<api::ChainInfoQuery> for ChainInfoQuery {
             request_sent_certificate_hashes_in_range,
             request_received_log_excluding_first_n: chain_info_query
                 .request_received_log_excluding_first_n,
+            request_received_log_in_range,
             test_next_block_height: chain_info_query.test_next_block_height.map(Into::into),
             request_manager_values: chain_info_query.request_manager_values,
             request_leader_timeout: chain_info_query.request_leader_timeout,
             request_fallback: chain_info_query.request_fallback,
+            request_vote_audit_log: chain_info_query.request_vote_audit_log,
+            request_event_streams: chain_info_query.request_event_streams,
+            request_fields,
+            max_response_bytes: chain_info_query.max_response_bytes,
+            request_state_inclusion_proof,
         })
     }
 }
@@ -618,6 +657,18 @@ impl TryFrom<ChainInfoQuery> for api::ChainInfoQuery {
             .request_sent_certificate_hashes_in_range
             .map(|range| bincode::serialize(&range))
             .transpose()?;
+        let request_received_log_in_range = chain_info_query
+            .request_received_log_in_range
+            .map(|range| bincode::serialize(&range))
+            .transpose()?;
+        let request_fields = chain_info_query
+            .request_fields
+            .map(|fields| bincode::serialize(&fields))
+            .transpose()?;
+        let request_state_inclusion_proof = chain_info_query
+            .request_state_inclusion_proof
+            .map(|request| bincode::serialize(&request))
+            .transpose()?;
         let request_owner_balance = Some(chain_info_query.request_owner_balance.try_into()?);
 
         Ok(Self {
@@ -629,9 +680,15 @@ impl TryFrom<ChainInfoQuery> for api::ChainInfoQuery {
             request_sent_certificate_hashes_in_range,
             request_received_log_excluding_first_n: chain_info_query
                 .request_received_log_excluding_first_n,
+            request_received_log_in_range,
             request_manager_values: chain_info_query.request_manager_values,
             request_leader_timeout: chain_info_query.request_leader_timeout,
             request_fallback: chain_info_query.request_fallback,
+            request_vote_audit_log: chain_info_query.request_vote_audit_log,
+            request_event_streams: chain_info_query.request_event_streams,
+            request_fields,
+            max_response_bytes: chain_info_query.max_response_bytes,
+            request_state_inclusion_proof,
         })
     }
 }
@@ -879,6 +936,34 @@ impl TryFrom<Vec<BlobId>> for api::BlobIds {
     }
 }
 
+impl TryFrom<api::BlobProvenance> for BlobProvenance {
+    type Error = GrpcProtoConversionError;
+
+    fn try_from(provenance: api::BlobProvenance) -> Result<Self, Self::Error> {
+        Ok(Self {
+            state: provenance
+                .state
+                .map(|state| bincode::deserialize(&state))
+                .transpose()?,
+            has_content: provenance.has_content,
+        })
+    }
+}
+
+impl TryFrom<BlobProvenance> for api::BlobProvenance {
+    type Error = GrpcProtoConversionError;
+
+    fn try_from(provenance: BlobProvenance) -> Result<Self, Self::Error> {
+        Ok(Self {
+            state: provenance
+                .state
+                .map(|state| bincode::serialize(&state))
+                .transpose()?,
+            has_content: provenance.has_content,
+        })
+    }
+}
+
 impl TryFrom<api::CryptoHash> for CryptoHash {
     type Error = GrpcProtoConversionError;
 
@@ -1001,20 +1086,77 @@ impl TryFrom<api::CertificatesBatchResponse> for Vec<Certificate> {
     }
 }
 
+impl From<(ChainId, BlockHeight, bool)> for api::StreamCertificatesRequest {
+    fn from((chain_id, start_height, include_blobs): (ChainId, BlockHeight, bool)) -> Self {
+        Self {
+            chain_id: Some(chain_id.into()),
+            start_height: Some(start_height.into()),
+            include_blobs,
+        }
+    }
+}
+
+impl TryFrom<api::StreamCertificatesRequest> for (ChainId, BlockHeight, bool) {
+    type Error = GrpcProtoConversionError;
+
+    fn try_from(request: api::StreamCertificatesRequest) -> Result<Self, Self::Error> {
+        let start_height = request
+            .start_height
+            .ok_or(GrpcProtoConversionError::MissingField)?
+            .into();
+        Ok((
+            try_proto_convert(request.chain_id)?,
+            start_height,
+            request.include_blobs,
+        ))
+    }
+}
+
+impl TryFrom<StreamedCertificate> for api::StreamedCertificate {
+    type Error = GrpcProtoConversionError;
+
+    fn try_from(streamed: StreamedCertificate) -> Result<Self, Self::Error> {
+        Ok(Self {
+            certificate: Some(streamed.certificate.try_into()?),
+            blobs: streamed
+                .blobs
+                .into_iter()
+                .map(|blob| blob.into_content().try_into())
+                .collect::<Result<_, _>>()?,
+        })
+    }
+}
+
+impl TryFrom<api::StreamedCertificate> for StreamedCertificate {
+    type Error = GrpcProtoConversionError;
+
+    fn try_from(streamed: api::StreamedCertificate) -> Result<Self, Self::Error> {
+        Ok(Self {
+            certificate: try_proto_convert(streamed.certificate)?,
+            blobs: streamed
+                .blobs
+                .into_iter()
+                .map(|blob| Ok(Blob::new(blob.try_into()?)))
+                .collect::<Result<_, Self::Error>>()?,
+        })
+    }
+}
+
 #[cfg(test)]
 pub mod tests {
     use std::{borrow::Cow, fmt::Debug};
 
     use linera_base::{
         crypto::{AccountSecretKey, BcsSignable, CryptoHash, Secp256k1SecretKey, ValidatorKeypair},
-        data_types::{Amount, Blob, Epoch, Round, Timestamp},
+        data_types::{Amount, Blob, BlockHeight, Epoch, Round, Timestamp},
+        identifiers::ApplicationId,
     };
     use linera_chain::{
-        data_types::{BlockExecutionOutcome, OriginalProposal, ProposedBlock},
+        data_types::{BlockExecutionOutcome, OriginalProposal, ProposedBlock, SponsorApproval},
         test::make_first_block,
         types::CertificateKind,
     };
-    use linera_core::data_types::ChainInfo;
+    use linera_core::data_types::{ChainInfo, ChainInfoField};
     use serde::{Deserialize, Serialize};
 
     use super::*;
@@ -1085,6 +1227,44 @@ pub mod tests {
         round_trip_check::<_, api::ChainId>(chain_id);
     }
 
+    #[test]
+    pub fn test_node_error_status_details() {
+        let errors = [
+            NodeError::WorkerErrorWithCode {
+                error: "Blob was not required by any pending block".to_string(),
+                code: "UnexpectedBlob".to_string(),
+                retriable: false,
+            },
+            NodeError::ChainErrorWithCode {
+                error: "Execution error".to_string(),
+                code: "ExecutionError".to_string(),
+                retriable: true,
+            },
+            NodeError::BlobsNotFound(vec![Blob::new_data(b"blob".to_vec()).id()]),
+            NodeError::HasIncompatibleConfirmedVote(BlockHeight(1), Round::Fast),
+        ];
+        for error in errors {
+            let status = Status::from(error.clone());
+            let expected_code = if error.is_retriable() {
+                Code::Unavailable
+            } else {
+                Code::FailedPrecondition
+            };
+            assert_eq!(status.code(), expected_code);
+            let round_trip = NodeError::from(status);
+            assert_eq!(round_trip.code(), error.code());
+            assert_eq!(round_trip.is_retriable(), error.is_retriable());
+            assert_eq!(round_trip, error);
+        }
+
+        // Statuses without details, e.g. from other servers, become gRPC errors.
+        let status = Status::unavailable("overloaded");
+        assert!(matches!(
+            NodeError::from(status),
+            NodeError::GrpcError { .. }
+        ));
+    }
+
     #[test]
     pub fn test_chain_info_response() {
         let chain_info = Box::new(ChainInfo {
@@ -1101,8 +1281,15 @@ pub mod tests {
             requested_owner_balance: None,
             requested_pending_message_bundles: vec![],
             requested_sent_certificate_hashes: vec![],
+            next_sent_certificate_hashes_cursor: None,
             count_received_log: 0,
             requested_received_log: vec![],
+            next_received_log_cursor: None,
+            requested_vote_audit_log: vec![],
+            requested_event_streams: vec![],
+            truncated_fields: Default::default(),
+            requested_state_inclusion_proof: None,
+            migrated_epoch: None,
         });
 
         let chain_info_response_none = ChainInfoResponse {
@@ -1141,9 +1328,25 @@ pub mod tests {
                 },
             ),
             request_received_log_excluding_first_n: None,
+            request_received_log_in_range: Some(linera_core::data_types::LogRange {
+                start: 7,
+                limit: None,
+            }),
             request_manager_values: false,
             request_leader_timeout: false,
             request_fallback: true,
+            request_vote_audit_log: Some(20),
+            request_event_streams: true,
+            request_fields: Some(
+                [ChainInfoField::Manager, ChainInfoField::ReceivedLog]
+                    .into_iter()
+                    .collect(),
+            ),
+            max_response_bytes: Some(4096),
+            request_state_inclusion_proof: Some((
+                ApplicationId::new(CryptoHash::test_hash("application")),
+                vec![1, 2, 3],
+            )),
         };
         round_trip_check::<_, api::ChainInfoQuery>(chain_info_query_some);
     }
@@ -1170,6 +1373,26 @@ pub mod tests {
         round_trip_check::<_, api::HandlePendingBlobRequest>(pending_blob_request);
     }
 
+    #[test]
+    pub fn test_blob_provenance() {
+        let provenance_none = BlobProvenance {
+            state: None,
+            has_content: false,
+        };
+        round_trip_check::<_, api::BlobProvenance>(provenance_none);
+
+        let provenance_some = BlobProvenance {
+            state: Some(linera_execution::BlobState {
+                last_used_by: Some(CryptoHash::new(&Foo("certificate".into()))),
+                chain_id: dummy_chain_id(1),
+                block_height: BlockHeight::from(4),
+                epoch: Some(Epoch::from(2)),
+            }),
+            has_content: true,
+        };
+        round_trip_check::<_, api::BlobProvenance>(provenance_some);
+    }
+
     #[test]
     pub fn test_lite_certificate() {
         let key_pair = ValidatorKeypair::generate();
@@ -1215,6 +1438,37 @@ pub mod tests {
         round_trip_check::<_, api::HandleValidatedCertificateRequest>(request);
     }
 
+    #[test]
+    pub fn test_stream_certificates_request() {
+        let request = (dummy_chain_id(0), BlockHeight(7), true);
+        round_trip_check::<_, api::StreamCertificatesRequest>(request);
+    }
+
+    #[test]
+    pub fn test_streamed_certificate() {
+        let key_pair = ValidatorKeypair::generate();
+        let certificate = ConfirmedBlockCertificate::new(
+            ConfirmedBlock::new(
+                BlockExecutionOutcome {
+                    state_hash: CryptoHash::new(&Foo("test".into())),
+                    ..BlockExecutionOutcome::default()
+                }
+                .with(get_block()),
+            ),
+            Round::Fast,
+            vec![(
+                key_pair.public_key,
+                ValidatorSignature::new(&Foo("test".into()), &key_pair.secret_key),
+            )],
+        );
+        let streamed = StreamedCertificate {
+            certificate,
+            blobs: vec![Blob::new(BlobContent::new_data(*b"foo"))],
+        };
+
+        round_trip_check::<_, api::StreamedCertificate>(streamed);
+    }
+
     #[test]
     pub fn test_cross_chain_request() {
         let cross_chain_request_update_recipient = CrossChainRequest::UpdateRecipient {
@@ -1222,7 +1476,10 @@ pub mod tests {
             recipient: dummy_chain_id(0),
             bundles: vec![],
         };
-        round_trip_check::<_, api::CrossChainRequest>(cross_chain_request_update_recipient);
+        round_trip_check::<_, api::CrossChainRequest>(SignedCrossChainRequest::new(
+            cross_chain_request_update_recipient,
+            None,
+        ));
 
         let cross_chain_request_confirm_updated_recipient =
             CrossChainRequest::ConfirmUpdatedRecipient {
@@ -1230,9 +1487,11 @@ pub mod tests {
                 recipient: dummy_chain_id(0),
                 latest_height: BlockHeight(1),
             };
-        round_trip_check::<_, api::CrossChainRequest>(
+        let key_pair = ValidatorKeypair::generate();
+        round_trip_check::<_, api::CrossChainRequest>(SignedCrossChainRequest::new(
             cross_chain_request_confirm_updated_recipient,
-        );
+            Some(&key_pair.secret_key),
+        ));
     }
 
     #[test]
@@ -1261,6 +1520,11 @@ pub mod tests {
             },
             signature: key_pair.sign(&Foo("test".into())),
             original_proposal: Some(OriginalProposal::Regular { certificate }),
+            idempotency_key: Some(CryptoHash::test_hash("key")),
+            sponsor_approval: Some(SponsorApproval {
+                max_fees: Amount::from_tokens(1),
+                signature: key_pair.sign(&Foo("sponsor".into())),
+            }),
         };
 
         round_trip_check::<_, api::BlockProposal>(block_proposal);
@@ -1,11 +1,21 @@
 // Copyright (c) Zefchain Labs, Inc.
 // SPDX-License-Identifier: Apache-2.0
 
+use std::net::SocketAddr;
+
+use tonic::Request;
+
 mod client;
+#[cfg(not(web))]
+pub mod compression;
 mod conversions;
 mod node_provider;
+#[cfg(with_server)]
+mod pacemaker;
 pub mod pool;
 #[cfg(with_server)]
+mod request_limiter;
+#[cfg(with_server)]
 mod server;
 pub mod transport;
 
@@ -13,6 +23,8 @@ pub use client::*;
 pub use conversions::*;
 pub use node_provider::*;
 #[cfg(with_server)]
+pub use pacemaker::GrpcPacemaker;
+#[cfg(with_server)]
 pub use server::*;
 
 pub mod api {
@@ -38,6 +50,36 @@ pub enum GrpcError {
 const MEBIBYTE: usize = 1024 * 1024;
 pub const GRPC_MAX_MESSAGE_SIZE: usize = 16 * MEBIBYTE;
 
+/// The metadata in which a proxy forwards the address of the client of a request to the
+/// shards, which would otherwise only see the address of the proxy. The shards trust it,
+/// since only the proxies can reach them.
+pub const CLIENT_ADDRESS_METADATA_KEY: &str = "x-linera-client-address";
+
+/// Wraps a message received from `client_address`, to be forwarded to a shard.
+pub fn forwarded_request<R>(message: R, client_address: Option<SocketAddr>) -> Request<R> {
+    let mut request = Request::new(message);
+    if let Some(client_address) = client_address {
+        let value = client_address
+            .to_string()
+            .parse()
+            .expect("socket addresses are valid metadata values");
+        request
+            .metadata_mut()
+            .insert(CLIENT_ADDRESS_METADATA_KEY, value);
+    }
+    request
+}
+
+/// Returns the address of the client of a request: the one forwarded by a proxy if any, or
+/// else the address of the connection.
+pub fn client_address<R>(request: &Request<R>) -> Option<SocketAddr> {
+    request
+        .metadata()
+        .get(CLIENT_ADDRESS_METADATA_KEY)
+        .and_then(|value| value.to_str().ok()?.parse().ok())
+        .or_else(|| request.remote_addr())
+}
+
 /// Limit of gRPC message size up to which we will try to populate with data when estimating.
 /// We leave 30% of buffer for the rest of the message and potential underestimation.
 pub const GRPC_CHUNKED_MESSAGE_FILL_LIMIT: usize = GRPC_MAX_MESSAGE_SIZE * 7 / 10;
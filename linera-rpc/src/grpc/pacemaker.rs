@@ -0,0 +1,86 @@
+// Copyright (c) Zefchain Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! A [`Pacemaker`] backed by an external gRPC service.
+
+use dashmap::DashMap;
+use futures::{future::BoxFuture, FutureExt as _};
+use linera_base::{
+    data_types::{BlockHeight, Round, Timestamp},
+    identifiers::{AccountOwner, ChainId},
+};
+use linera_chain::manager::{Pacemaker, PacemakerError, RoundSchedule};
+
+use super::{
+    api::{self, pacemaker_client::PacemakerClient},
+    transport, GrpcError, GrpcProtoConversionError,
+};
+
+/// Identifies a round of the block at some height on some chain.
+type RoundKey = (ChainId, BlockHeight, Round);
+
+/// A [`Pacemaker`] querying the round schedules from a gRPC service.
+///
+/// A round's schedule is fetched the first time it is asked for, and cached until the chain
+/// asks for a schedule at a later height. Failures are not cached, so that the next request
+/// asks the service again.
+pub struct GrpcPacemaker {
+    client: PacemakerClient<transport::Channel>,
+    /// The schedules received so far.
+    schedules: DashMap<RoundKey, RoundSchedule>,
+}
+
+impl GrpcPacemaker {
+    /// Creates a pacemaker querying the service at `address`.
+    pub fn new(address: String, options: &transport::Options) -> Result<Self, GrpcError> {
+        let channel = transport::create_channel(address, options)?;
+        Ok(GrpcPacemaker {
+            client: PacemakerClient::new(channel),
+            schedules: DashMap::new(),
+        })
+    }
+
+    async fn schedule(&self, key: RoundKey) -> Result<RoundSchedule, PacemakerError> {
+        if let Some(schedule) = self.schedules.get(&key) {
+            return Ok(*schedule);
+        }
+        let (chain_id, height, _) = key;
+        let schedule = fetch_schedule(self.client.clone(), key)
+            .await
+            .map_err(|error| PacemakerError(error.to_string()))?;
+        // The schedules of earlier blocks of the chain are not needed anymore.
+        self.schedules
+            .retain(|(other_chain_id, other_height, _), _| {
+                *other_chain_id != chain_id || *other_height >= height
+            });
+        self.schedules.insert(key, schedule);
+        Ok(schedule)
+    }
+}
+
+impl Pacemaker for GrpcPacemaker {
+    fn round_schedule(
+        &self,
+        chain_id: ChainId,
+        height: BlockHeight,
+        round: Round,
+    ) -> BoxFuture<'_, Result<RoundSchedule, PacemakerError>> {
+        self.schedule((chain_id, height, round)).boxed()
+    }
+}
+
+async fn fetch_schedule(
+    mut client: PacemakerClient<transport::Channel>,
+    (chain_id, height, round): RoundKey,
+) -> anyhow::Result<RoundSchedule> {
+    let request = api::RoundScheduleRequest {
+        chain_id: Some(chain_id.into()),
+        height: Some(height.into()),
+        round: bincode::serialize(&round).map_err(GrpcProtoConversionError::from)?,
+    };
+    let response = client.get_round_schedule(request).await?.into_inner();
+    Ok(RoundSchedule {
+        deadline: response.deadline.map(Timestamp::from),
+        leader: response.leader.map(AccountOwner::try_from).transpose()?,
+    })
+}
@@ -0,0 +1,227 @@
+// Copyright (c) Zefchain Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! Back-pressure for the gRPC server of a shard.
+//!
+//! The number of requests being handled is tracked globally, per client connection and per
+//! chain. Behind a proxy, the client connection is the one forwarded by the proxy.
+//! A request that would exceed one of the configured limits is rejected right away with
+//! `RESOURCE_EXHAUSTED` instead of waiting in the queues of the chain workers.
+
+use std::{
+    collections::HashMap,
+    net::SocketAddr,
+    sync::{Arc, Mutex},
+};
+
+use linera_base::identifiers::ChainId;
+use tonic::Status;
+
+use crate::config::RequestLimitsConfig;
+
+#[cfg(with_metrics)]
+mod metrics {
+    use std::sync::LazyLock;
+
+    use linera_base::prometheus_util::{register_int_counter_vec, register_int_gauge_vec};
+    use prometheus::{IntCounterVec, IntGaugeVec};
+
+    pub static SERVER_IN_FLIGHT_REQUESTS: LazyLock<IntGaugeVec> = LazyLock::new(|| {
+        register_int_gauge_vec(
+            "server_in_flight_requests",
+            "Number of requests being handled by the server",
+            &[],
+        )
+    });
+
+    pub static SERVER_REQUEST_REJECTED: LazyLock<IntCounterVec> = LazyLock::new(|| {
+        register_int_counter_vec(
+            "server_request_rejected",
+            "Number of requests rejected because of a limit on in-flight requests",
+            &["method_name", "limit"],
+        )
+    });
+}
+
+/// The requests currently being handled.
+#[derive(Default)]
+struct InFlightRequests {
+    total: usize,
+    per_peer: HashMap<SocketAddr, usize>,
+    per_chain: HashMap<ChainId, usize>,
+}
+
+impl InFlightRequests {
+    fn release(&mut self, peer: Option<SocketAddr>, chain_id: Option<ChainId>) {
+        self.total -= 1;
+        if let Some(peer) = peer {
+            decrement(&mut self.per_peer, peer);
+        }
+        if let Some(chain_id) = chain_id {
+            decrement(&mut self.per_chain, chain_id);
+        }
+        #[cfg(with_metrics)]
+        metrics::SERVER_IN_FLIGHT_REQUESTS
+            .with_label_values(&[])
+            .set(self.total as i64);
+    }
+}
+
+fn decrement<K: std::hash::Hash + Eq>(counts: &mut HashMap<K, usize>, key: K) {
+    if let Some(count) = counts.get_mut(&key) {
+        *count -= 1;
+        if *count == 0 {
+            counts.remove(&key);
+        }
+    }
+}
+
+fn is_full(limit: Option<usize>, count: usize) -> bool {
+    limit.is_some_and(|limit| count >= limit)
+}
+
+/// Admits requests as long as the configured limits are not reached.
+#[derive(Clone)]
+pub(crate) struct RequestLimiter {
+    config: RequestLimitsConfig,
+    in_flight: Arc<Mutex<InFlightRequests>>,
+}
+
+impl RequestLimiter {
+    pub(crate) fn new(config: RequestLimitsConfig) -> Self {
+        RequestLimiter {
+            config,
+            in_flight: Arc::default(),
+        }
+    }
+
+    /// Admits a request from the given peer for the given chain, or returns a
+    /// `RESOURCE_EXHAUSTED` status. The request counts as in flight until the returned permit
+    /// is dropped.
+    #[allow(clippy::result_large_err)]
+    pub(crate) fn try_acquire(
+        &self,
+        peer: Option<SocketAddr>,
+        chain_id: Option<ChainId>,
+        method_name: &str,
+    ) -> Result<RequestPermit, Status> {
+        let mut in_flight = self.in_flight.lock().unwrap();
+        let peer_count = peer.map_or(0, |peer| {
+            in_flight.per_peer.get(&peer).copied().unwrap_or_default()
+        });
+        let chain_count = chain_id.map_or(0, |chain_id| {
+            in_flight
+                .per_chain
+                .get(&chain_id)
+                .copied()
+                .unwrap_or_default()
+        });
+        let exceeded_limit = if is_full(self.config.max_in_flight_requests, in_flight.total) {
+            Some("global")
+        } else if is_full(self.config.max_in_flight_requests_per_peer, peer_count) {
+            Some("peer")
+        } else if is_full(self.config.max_in_flight_requests_per_chain, chain_count) {
+            Some("chain")
+        } else {
+            None
+        };
+        if let Some(limit) = exceeded_limit {
+            #[cfg(with_metrics)]
+            metrics::SERVER_REQUEST_REJECTED
+                .with_label_values(&[method_name, limit])
+                .inc();
+            return Err(Status::resource_exhausted(format!(
+                "too many in-flight requests ({limit} limit reached) for {method_name}"
+            )));
+        }
+
+        in_flight.total += 1;
+        if let Some(peer) = peer {
+            *in_flight.per_peer.entry(peer).or_default() += 1;
+        }
+        if let Some(chain_id) = chain_id {
+            *in_flight.per_chain.entry(chain_id).or_default() += 1;
+        }
+        #[cfg(with_metrics)]
+        metrics::SERVER_IN_FLIGHT_REQUESTS
+            .with_label_values(&[])
+            .set(in_flight.total as i64);
+        Ok(RequestPermit {
+            in_flight: self.in_flight.clone(),
+            peer,
+            chain_id,
+        })
+    }
+}
+
+/// A request admitted by a [`RequestLimiter`]. It stops counting as in flight when dropped.
+pub(crate) struct RequestPermit {
+    in_flight: Arc<Mutex<InFlightRequests>>,
+    peer: Option<SocketAddr>,
+    chain_id: Option<ChainId>,
+}
+
+impl Drop for RequestPermit {
+    fn drop(&mut self) {
+        self.in_flight
+            .lock()
+            .unwrap()
+            .release(self.peer, self.chain_id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use linera_base::crypto::CryptoHash;
+    use tonic::Code;
+
+    use super::*;
+
+    #[test]
+    fn test_request_limits() {
+        let limiter = RequestLimiter::new(RequestLimitsConfig {
+            max_in_flight_requests: Some(3),
+            max_in_flight_requests_per_peer: Some(2),
+            max_in_flight_requests_per_chain: Some(1),
+        });
+        let peer1 = SocketAddr::from(([127, 0, 0, 1], 1));
+        let peer2 = SocketAddr::from(([127, 0, 0, 1], 2));
+        let chain1 = ChainId(CryptoHash::test_hash("chain1"));
+        let chain2 = ChainId(CryptoHash::test_hash("chain2"));
+        let chain3 = ChainId(CryptoHash::test_hash("chain3"));
+
+        let permit = limiter
+            .try_acquire(Some(peer1), Some(chain1), "test")
+            .unwrap();
+        let error = limiter
+            .try_acquire(Some(peer2), Some(chain1), "test")
+            .err()
+            .unwrap();
+        assert_eq!(error.code(), Code::ResourceExhausted);
+
+        let _permit2 = limiter
+            .try_acquire(Some(peer1), Some(chain2), "test")
+            .unwrap();
+        assert!(limiter
+            .try_acquire(Some(peer1), Some(chain3), "test")
+            .is_err());
+        let _permit3 = limiter
+            .try_acquire(Some(peer2), Some(chain3), "test")
+            .unwrap();
+        assert!(limiter.try_acquire(Some(peer2), None, "test").is_err());
+
+        drop(permit);
+        let _permit4 = limiter
+            .try_acquire(Some(peer2), Some(chain1), "test")
+            .unwrap();
+    }
+
+    #[test]
+    fn test_forwarded_client_address() {
+        let client = SocketAddr::from(([192, 0, 2, 1], 1234));
+        let request = crate::grpc::forwarded_request((), Some(client));
+        assert_eq!(crate::grpc::client_address(&request), Some(client));
+        let request = crate::grpc::forwarded_request((), None);
+        assert_eq!(crate::grpc::client_address(&request), None);
+    }
+}
@@ -32,16 +32,23 @@ use super::{
         BlockProposal, ChainInfoQuery, ChainInfoResult, CrossChainRequest,
         HandlePendingBlobRequest, LiteCertificate, PendingBlobRequest, PendingBlobResult,
     },
+    client_address,
+    compression::ACCEPTED_ENCODINGS,
     pool::GrpcConnectionPool,
+    request_limiter::{RequestLimiter, RequestPermit},
     GrpcError, GRPC_MAX_MESSAGE_SIZE,
 };
 use crate::{
-    config::{CrossChainConfig, NotificationConfig, ShardId, ValidatorInternalNetworkConfig},
+    config::{
+        CrossChainConfig, NotificationConfig, RequestLimitsConfig, ShardId,
+        ValidatorInternalNetworkConfig,
+    },
+    confirmation_batcher::ConfirmationBatcher,
     cross_chain_message_queue, HandleConfirmedCertificateRequest, HandleLiteCertRequest,
     HandleTimeoutCertificateRequest, HandleValidatedCertificateRequest,
 };
 
-type CrossChainSender = mpsc::Sender<(linera_core::data_types::CrossChainRequest, ShardId)>;
+type CrossChainSender = mpsc::Sender<(linera_core::data_types::SignedCrossChainRequest, ShardId)>;
 type NotificationSender = tokio::sync::broadcast::Sender<Notification>;
 
 #[cfg(with_metrics)]
@@ -111,6 +118,8 @@ where
     network: ValidatorInternalNetworkConfig,
     cross_chain_sender: CrossChainSender,
     notification_sender: NotificationSender,
+    request_limiter: RequestLimiter,
+    confirmation_batcher: ConfirmationBatcher,
 }
 
 pub struct GrpcServerHandle {
@@ -184,6 +193,7 @@ where
         internal_network: ValidatorInternalNetworkConfig,
         cross_chain_config: CrossChainConfig,
         notification_config: NotificationConfig,
+        request_limits_config: RequestLimitsConfig,
         shutdown_signal: CancellationToken,
         join_set: &mut JoinSet,
     ) -> GrpcServerHandle {
@@ -233,17 +243,28 @@ where
 
         let (mut health_reporter, health_service) = tonic_health::server::health_reporter();
 
+        let confirmation_batcher = ConfirmationBatcher::spawn(state.clone(), join_set);
+
         let grpc_server = GrpcServer {
             state,
             shard_id,
             network: internal_network,
             cross_chain_sender,
             notification_sender,
+            request_limiter: RequestLimiter::new(request_limits_config),
+            confirmation_batcher,
         };
 
-        let worker_node = ValidatorWorkerServer::new(grpc_server)
+        let compression = grpc_server.network.compression;
+        let mut worker_node = ValidatorWorkerServer::new(grpc_server)
             .max_encoding_message_size(GRPC_MAX_MESSAGE_SIZE)
             .max_decoding_message_size(GRPC_MAX_MESSAGE_SIZE);
+        for encoding in ACCEPTED_ENCODINGS {
+            worker_node = worker_node.accept_compressed(encoding);
+        }
+        if let Some(encoding) = compression.encoding() {
+            worker_node = worker_node.send_compressed(encoding);
+        }
 
         let handle = join_set.spawn_task(async move {
             let server_address = SocketAddr::from((IpAddr::from_str(&host)?, port));
@@ -347,6 +368,11 @@ where
         let notification_sender = self.notification_sender.clone();
 
         for request in actions.cross_chain_requests {
+            let request = if self.network.sign_cross_chain_requests {
+                self.state.sign_cross_chain_request(request)
+            } else {
+                linera_core::data_types::SignedCrossChainRequest::new(request, None)
+            };
             let shard_id = self.network.get_shard_id(request.target_chain_id());
             trace!(
                 source_shard_id = self.shard_id,
@@ -384,18 +410,26 @@ where
         cross_chain_sender_delay: Duration,
         cross_chain_sender_failure_rate: f32,
         this_shard: ShardId,
-        receiver: mpsc::Receiver<(linera_core::data_types::CrossChainRequest, ShardId)>,
+        receiver: mpsc::Receiver<(linera_core::data_types::SignedCrossChainRequest, ShardId)>,
     ) {
         let pool = GrpcConnectionPool::default();
         let handle_request =
-            move |shard_id: ShardId, request: linera_core::data_types::CrossChainRequest| {
+            move |shard_id: ShardId, request: linera_core::data_types::SignedCrossChainRequest| {
                 let channel_result = pool.channel(network.shard(shard_id).http_address());
+                let compression = network.compression;
                 async move {
+                    let request: CrossChainRequest = request.try_into()?;
                     let mut client = ValidatorWorkerClient::new(channel_result?)
                         .max_encoding_message_size(GRPC_MAX_MESSAGE_SIZE)
                         .max_decoding_message_size(GRPC_MAX_MESSAGE_SIZE);
+                    for encoding in ACCEPTED_ENCODINGS {
+                        client = client.accept_compressed(encoding);
+                    }
+                    if let Some(encoding) = compression.encoding_for(&request) {
+                        client = client.send_compressed(encoding);
+                    }
                     client
-                        .handle_cross_chain_request(Request::new(request.try_into()?))
+                        .handle_cross_chain_request(Request::new(request))
                         .await?;
                     anyhow::Result::<_, anyhow::Error>::Ok(())
                 }
@@ -413,6 +447,20 @@ where
         .await;
     }
 
+    /// Admits the request if the limits on in-flight requests allow it.
+    #[allow(clippy::result_large_err)]
+    fn admit<R: GrpcProxyable>(
+        &self,
+        request: &Request<R>,
+        method_name: &str,
+    ) -> Result<RequestPermit, Status> {
+        self.request_limiter.try_acquire(
+            client_address(request),
+            request.get_ref().chain_id(),
+            method_name,
+        )
+    }
+
     fn log_request_outcome_and_latency(start: Instant, success: bool, method_name: &str) {
         #![allow(unused_variables)]
         #[cfg(with_metrics)]
@@ -451,10 +499,11 @@ where
         &self,
         request: Request<BlockProposal>,
     ) -> Result<Response<ChainInfoResult>, Status> {
+        let _permit = self.admit(&request, "handle_block_proposal")?;
         let start = Instant::now();
         let proposal = request.into_inner().try_into()?;
         trace!(?proposal, "Handling block proposal");
-        Ok(Response::new(
+        Ok(self.network.compression.response(
             match self.state.clone().handle_block_proposal(proposal).await {
                 Ok((info, actions)) => {
                     Self::log_request_outcome_and_latency(start, true, "handle_block_proposal");
@@ -484,6 +533,7 @@ where
         &self,
         request: Request<LiteCertificate>,
     ) -> Result<Response<ChainInfoResult>, Status> {
+        let _permit = self.admit(&request, "handle_lite_certificate")?;
         let start = Instant::now();
         let HandleLiteCertRequest {
             certificate,
@@ -506,7 +556,7 @@ where
                         error!("Failed to wait for message delivery: {e}");
                     }
                 }
-                Ok(Response::new(info.try_into()?))
+                Ok(self.network.compression.response(info.try_into()?))
             }
             Err(error) => {
                 Self::log_request_outcome_and_latency(start, false, "handle_lite_certificate");
@@ -516,7 +566,10 @@ where
                 } else {
                     error!(nickname, %error, "Failed to handle lite certificate");
                 }
-                Ok(Response::new(NodeError::from(error).try_into()?))
+                Ok(self
+                    .network
+                    .compression
+                    .response(NodeError::from(error).try_into()?))
             }
         }
     }
@@ -534,6 +587,7 @@ where
         &self,
         request: Request<api::HandleConfirmedCertificateRequest>,
     ) -> Result<Response<ChainInfoResult>, Status> {
+        let _permit = self.admit(&request, "handle_confirmed_certificate")?;
         let start = Instant::now();
         let HandleConfirmedCertificateRequest {
             certificate,
@@ -555,13 +609,16 @@ where
                         error!("Failed to wait for message delivery: {e}");
                     }
                 }
-                Ok(Response::new(info.try_into()?))
+                Ok(self.network.compression.response(info.try_into()?))
             }
             Err(error) => {
                 Self::log_request_outcome_and_latency(start, false, "handle_confirmed_certificate");
                 let nickname = self.state.nickname();
                 error!(nickname, %error, "Failed to handle confirmed certificate");
-                Ok(Response::new(NodeError::from(error).try_into()?))
+                Ok(self
+                    .network
+                    .compression
+                    .response(NodeError::from(error).try_into()?))
             }
         }
     }
@@ -579,6 +636,7 @@ where
         &self,
         request: Request<api::HandleValidatedCertificateRequest>,
     ) -> Result<Response<ChainInfoResult>, Status> {
+        let _permit = self.admit(&request, "handle_validated_certificate")?;
         let start = Instant::now();
         let HandleValidatedCertificateRequest { certificate } = request.into_inner().try_into()?;
         trace!(?certificate, "Handling certificate");
@@ -591,13 +649,16 @@ where
             Ok((info, actions)) => {
                 Self::log_request_outcome_and_latency(start, true, "handle_validated_certificate");
                 self.handle_network_actions(actions);
-                Ok(Response::new(info.try_into()?))
+                Ok(self.network.compression.response(info.try_into()?))
             }
             Err(error) => {
                 Self::log_request_outcome_and_latency(start, false, "handle_validated_certificate");
                 let nickname = self.state.nickname();
                 error!(nickname, %error, "Failed to handle validated certificate");
-                Ok(Response::new(NodeError::from(error).try_into()?))
+                Ok(self
+                    .network
+                    .compression
+                    .response(NodeError::from(error).try_into()?))
             }
         }
     }
@@ -615,6 +676,7 @@ where
         &self,
         request: Request<api::HandleTimeoutCertificateRequest>,
     ) -> Result<Response<ChainInfoResult>, Status> {
+        let _permit = self.admit(&request, "handle_timeout_certificate")?;
         let start = Instant::now();
         let HandleTimeoutCertificateRequest { certificate } = request.into_inner().try_into()?;
         trace!(?certificate, "Handling Timeout certificate");
@@ -626,13 +688,16 @@ where
         {
             Ok((info, _actions)) => {
                 Self::log_request_outcome_and_latency(start, true, "handle_timeout_certificate");
-                Ok(Response::new(info.try_into()?))
+                Ok(self.network.compression.response(info.try_into()?))
             }
             Err(error) => {
                 Self::log_request_outcome_and_latency(start, false, "handle_timeout_certificate");
                 let nickname = self.state.nickname();
                 error!(nickname, %error, "Failed to handle timeout certificate");
-                Ok(Response::new(NodeError::from(error).try_into()?))
+                Ok(self
+                    .network
+                    .compression
+                    .response(NodeError::from(error).try_into()?))
             }
         }
     }
@@ -650,6 +715,7 @@ where
         &self,
         request: Request<ChainInfoQuery>,
     ) -> Result<Response<ChainInfoResult>, Status> {
+        let _permit = self.admit(&request, "handle_chain_info_query")?;
         let start = Instant::now();
         let query = request.into_inner().try_into()?;
         trace!(?query, "Handling chain info query");
@@ -657,13 +723,16 @@ where
             Ok((info, actions)) => {
                 Self::log_request_outcome_and_latency(start, true, "handle_chain_info_query");
                 self.handle_network_actions(actions);
-                Ok(Response::new(info.try_into()?))
+                Ok(self.network.compression.response(info.try_into()?))
             }
             Err(error) => {
                 Self::log_request_outcome_and_latency(start, false, "handle_chain_info_query");
                 let nickname = self.state.nickname();
                 error!(nickname, %error, "Failed to handle chain info query");
-                Ok(Response::new(NodeError::from(error).try_into()?))
+                Ok(self
+                    .network
+                    .compression
+                    .response(NodeError::from(error).try_into()?))
             }
         }
     }
@@ -681,6 +750,7 @@ where
         &self,
         request: Request<PendingBlobRequest>,
     ) -> Result<Response<PendingBlobResult>, Status> {
+        let _permit = self.admit(&request, "download_pending_blob")?;
         let start = Instant::now();
         let (chain_id, blob_id) = request.into_inner().try_into()?;
         trace!(?chain_id, ?blob_id, "Download pending blob");
@@ -692,13 +762,19 @@ where
         {
             Ok(blob) => {
                 Self::log_request_outcome_and_latency(start, true, "download_pending_blob");
-                Ok(Response::new(blob.into_content().try_into()?))
+                Ok(self
+                    .network
+                    .compression
+                    .response(blob.into_content().try_into()?))
             }
             Err(error) => {
                 Self::log_request_outcome_and_latency(start, false, "download_pending_blob");
                 let nickname = self.state.nickname();
                 error!(nickname, %error, "Failed to download pending blob");
-                Ok(Response::new(NodeError::from(error).try_into()?))
+                Ok(self
+                    .network
+                    .compression
+                    .response(NodeError::from(error).try_into()?))
             }
         }
     }
@@ -716,6 +792,7 @@ where
         &self,
         request: Request<HandlePendingBlobRequest>,
     ) -> Result<Response<ChainInfoResult>, Status> {
+        let _permit = self.admit(&request, "handle_pending_blob")?;
         let start = Instant::now();
         let (chain_id, blob_content) = request.into_inner().try_into()?;
         let blob = Blob::new(blob_content);
@@ -724,13 +801,16 @@ where
         match self.state.clone().handle_pending_blob(chain_id, blob).await {
             Ok(info) => {
                 Self::log_request_outcome_and_latency(start, true, "handle_pending_blob");
-                Ok(Response::new(info.try_into()?))
+                Ok(self.network.compression.response(info.try_into()?))
             }
             Err(error) => {
                 Self::log_request_outcome_and_latency(start, false, "handle_pending_blob");
                 let nickname = self.state.nickname();
                 error!(nickname, %error, "Failed to handle pending blob");
-                Ok(Response::new(NodeError::from(error).try_into()?))
+                Ok(self
+                    .network
+                    .compression
+                    .response(NodeError::from(error).try_into()?))
             }
         }
     }
@@ -750,7 +830,39 @@ where
     ) -> Result<Response<()>, Status> {
         let start = Instant::now();
         let request = request.into_inner().try_into()?;
+        let require_signature = self.network.require_signed_cross_chain_requests;
+        let request = match self
+            .state
+            .authenticate_cross_chain_request(request, require_signature)
+        {
+            Ok(request) => request,
+            Err(error) => {
+                Self::log_request_outcome_and_latency(start, false, "handle_cross_chain_request");
+                return Err(Status::unauthenticated(error.to_string()));
+            }
+        };
         trace!(?request, "Handling cross-chain request");
+        if let linera_core::data_types::CrossChainRequest::ConfirmUpdatedRecipient {
+            sender,
+            recipient,
+            latest_height,
+        } = request
+        {
+            // Confirmations are saved in batches, and acknowledged once saved.
+            let result = self
+                .confirmation_batcher
+                .confirm(sender, recipient, latest_height)
+                .await;
+            Self::log_request_outcome_and_latency(
+                start,
+                result.is_ok(),
+                "handle_cross_chain_request",
+            );
+            return match result {
+                Ok(()) => Ok(Response::new(())),
+                Err(error) => Err(Status::internal(error.to_string())),
+            };
+        }
         match self.state.clone().handle_cross_chain_request(request).await {
             Ok(actions) => {
                 Self::log_request_outcome_and_latency(start, true, "handle_cross_chain_request");
@@ -820,6 +932,12 @@ impl GrpcProxyable for HandlePendingBlobRequest {
     }
 }
 
+impl GrpcProxyable for api::StreamCertificatesRequest {
+    fn chain_id(&self) -> Option<ChainId> {
+        self.chain_id.clone()?.try_into().ok()
+    }
+}
+
 impl GrpcProxyable for CrossChainRequest {
     fn chain_id(&self) -> Option<ChainId> {
         use super::api::cross_chain_request::Inner;
@@ -13,10 +13,13 @@ pub mod node_provider;
 
 pub mod client;
 
+mod confirmation_batcher;
 mod cross_chain_message_queue;
 mod message;
 #[cfg(with_simple_network)]
 pub mod simple;
+#[cfg(with_testing)]
+pub mod test_vectors;
 
 pub mod grpc;
 
@@ -12,9 +12,10 @@ use linera_chain::{
     types::{ConfirmedBlock, ConfirmedBlockCertificate},
 };
 use linera_core::{
-    data_types::{ChainInfoQuery, ChainInfoResponse, CrossChainRequest},
+    data_types::{ChainInfoQuery, ChainInfoResponse, CrossChainRequest, SignedCrossChainRequest},
     node::NodeError,
 };
+use linera_execution::BlobProvenance;
 use linera_version::VersionInfo;
 use serde::{Deserialize, Serialize};
 
@@ -60,6 +61,11 @@ pub enum RpcMessage {
 
     // Internal to a validator
     CrossChainRequest(Box<CrossChainRequest>),
+
+    // Added after the variants above, to keep their serialized indices.
+    BlobProvenance(Box<BlobId>),
+    BlobProvenanceResponse(Box<BlobProvenance>),
+    SignedCrossChainRequest(Box<SignedCrossChainRequest>),
 }
 
 impl RpcMessage {
@@ -77,6 +83,7 @@ impl RpcMessage {
             ConfirmedCertificate(request) => request.certificate.inner().chain_id(),
             ChainInfoQuery(query) => query.chain_id,
             CrossChainRequest(request) => request.target_chain_id(),
+            SignedCrossChainRequest(request) => request.target_chain_id(),
             DownloadPendingBlob(request) => request.0,
             HandlePendingBlob(request) => request.0,
             Vote(_)
@@ -96,6 +103,8 @@ impl RpcMessage {
             | DownloadCertificates(_)
             | BlobLastUsedBy(_)
             | BlobLastUsedByResponse(_)
+            | BlobProvenance(_)
+            | BlobProvenanceResponse(_)
             | MissingBlobIds(_)
             | MissingBlobIdsResponse(_)
             | DownloadCertificatesResponse(_) => {
@@ -118,6 +127,7 @@ impl RpcMessage {
             | DownloadBlob(_)
             | DownloadConfirmedBlock(_)
             | BlobLastUsedBy(_)
+            | BlobProvenance(_)
             | MissingBlobIds(_)
             | DownloadCertificates(_) => true,
             BlockProposal(_)
@@ -127,6 +137,7 @@ impl RpcMessage {
             | ConfirmedCertificate(_)
             | ChainInfoQuery(_)
             | CrossChainRequest(_)
+            | SignedCrossChainRequest(_)
             | Vote(_)
             | Error(_)
             | ChainInfoResponse(_)
@@ -139,6 +150,7 @@ impl RpcMessage {
             | DownloadBlobResponse(_)
             | DownloadConfirmedBlockResponse(_)
             | BlobLastUsedByResponse(_)
+            | BlobProvenanceResponse(_)
             | MissingBlobIdsResponse(_)
             | DownloadCertificatesResponse(_) => false,
         }
@@ -212,6 +224,17 @@ impl TryFrom<RpcMessage> for CryptoHash {
     }
 }
 
+impl TryFrom<RpcMessage> for BlobProvenance {
+    type Error = NodeError;
+    fn try_from(message: RpcMessage) -> Result<Self, Self::Error> {
+        match message {
+            RpcMessage::BlobProvenanceResponse(provenance) => Ok(*provenance),
+            RpcMessage::Error(error) => Err(*error),
+            _ => Err(NodeError::UnexpectedMessage),
+        }
+    }
+}
+
 impl TryFrom<RpcMessage> for NetworkDescription {
     type Error = NodeError;
     fn try_from(message: RpcMessage) -> Result<Self, Self::Error> {
@@ -7,7 +7,7 @@ use std::future::Future;
 use futures::{sink::SinkExt, stream::StreamExt};
 use linera_base::{
     crypto::CryptoHash,
-    data_types::{BlobContent, NetworkDescription},
+    data_types::{BlobContent, BlockHeight, NetworkDescription},
     identifiers::{BlobId, ChainId},
     time::{timer, Duration},
 };
@@ -19,8 +19,11 @@ use linera_chain::{
 };
 use linera_core::{
     data_types::{ChainInfoQuery, ChainInfoResponse},
-    node::{CrossChainMessageDelivery, NodeError, NotificationStream, ValidatorNode},
+    node::{
+        CertificateStream, CrossChainMessageDelivery, NodeError, NotificationStream, ValidatorNode,
+    },
 };
+use linera_execution::BlobProvenance;
 use linera_version::VersionInfo;
 
 use super::{codec, transport::TransportProtocol};
@@ -227,7 +230,22 @@ impl ValidatorNode for SimpleClient {
             .await
     }
 
+    async fn blob_provenance(&self, blob_id: BlobId) -> Result<BlobProvenance, NodeError> {
+        self.query(RpcMessage::BlobProvenance(Box::new(blob_id)))
+            .await
+    }
+
     async fn missing_blob_ids(&self, blob_ids: Vec<BlobId>) -> Result<Vec<BlobId>, NodeError> {
         self.query(RpcMessage::MissingBlobIds(blob_ids)).await
     }
+
+    async fn stream_certificates(
+        &self,
+        _chain_id: ChainId,
+        _start: BlockHeight,
+        _include_blobs: bool,
+    ) -> Result<CertificateStream, NodeError> {
+        let transport = self.network.protocol.to_string();
+        Err(NodeError::CertificateStreamingNotSupported { transport })
+    }
 }
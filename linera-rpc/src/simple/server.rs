@@ -7,7 +7,7 @@ use async_trait::async_trait;
 use futures::{channel::mpsc, lock::Mutex};
 use linera_base::{data_types::Blob, time::Duration};
 use linera_core::{
-    data_types::CrossChainRequest,
+    data_types::SignedCrossChainRequest,
     node::NodeError,
     worker::{NetworkActions, WorkerError, WorkerState},
     JoinSetExt as _,
@@ -85,7 +85,7 @@ where
         cross_chain_sender_delay: Duration,
         cross_chain_sender_failure_rate: f32,
         this_shard: ShardId,
-        receiver: mpsc::Receiver<(CrossChainRequest, ShardId)>,
+        receiver: mpsc::Receiver<(SignedCrossChainRequest, ShardId)>,
     ) {
         let pool = Arc::new(Mutex::new(
             network
@@ -98,7 +98,12 @@ where
             let pool = pool.clone();
             let shard = network.shard(shard_id);
             let remote_address = format!("{}:{}", shard.host, shard.port);
-            let message = RpcMessage::CrossChainRequest(Box::new(request));
+            // Unsigned requests are sent in the format older shards understand.
+            let message = if request.signature.is_some() {
+                RpcMessage::SignedCrossChainRequest(Box::new(request))
+            } else {
+                RpcMessage::CrossChainRequest(Box::new(request.request))
+            };
             async move {
                 pool.lock()
                     .await
@@ -161,7 +166,7 @@ where
     S: Storage,
 {
     server: Server<S>,
-    cross_chain_sender: mpsc::Sender<(CrossChainRequest, ShardId)>,
+    cross_chain_sender: mpsc::Sender<(SignedCrossChainRequest, ShardId)>,
 }
 
 #[async_trait]
@@ -315,15 +320,13 @@ where
                 }
             }
             RpcMessage::CrossChainRequest(request) => {
-                match self.server.state.handle_cross_chain_request(*request).await {
-                    Ok(actions) => {
-                        self.handle_network_actions(actions);
-                    }
-                    Err(error) => {
-                        let nickname = self.server.state.nickname();
-                        error!(nickname, %error, "Failed to handle cross-chain request");
-                    }
-                }
+                self.handle_cross_chain_request(SignedCrossChainRequest::new(*request, None))
+                    .await;
+                // No user to respond to.
+                Ok(None)
+            }
+            RpcMessage::SignedCrossChainRequest(request) => {
+                self.handle_cross_chain_request(*request).await;
                 // No user to respond to.
                 Ok(None)
             }
@@ -379,6 +382,8 @@ where
             | RpcMessage::DownloadConfirmedBlockResponse(_)
             | RpcMessage::BlobLastUsedBy(_)
             | RpcMessage::BlobLastUsedByResponse(_)
+            | RpcMessage::BlobProvenance(_)
+            | RpcMessage::BlobProvenanceResponse(_)
             | RpcMessage::MissingBlobIds(_)
             | RpcMessage::MissingBlobIdsResponse(_)
             | RpcMessage::DownloadCertificates(_)
@@ -415,12 +420,41 @@ where
     }
 }
 
+impl<S> RunningServerState<S>
+where
+    S: Storage + Clone + Send + Sync + 'static,
+{
+    /// Authenticates and handles a cross-chain request from another shard.
+    async fn handle_cross_chain_request(&mut self, request: SignedCrossChainRequest) {
+        let state = &self.server.state;
+        let require_signature = self.server.network.require_signed_cross_chain_requests;
+        let result = match state.authenticate_cross_chain_request(request, require_signature) {
+            Ok(request) => state.handle_cross_chain_request(request).await,
+            Err(error) => Err(error),
+        };
+        match result {
+            Ok(actions) => {
+                self.handle_network_actions(actions);
+            }
+            Err(error) => {
+                let nickname = self.server.state.nickname();
+                error!(nickname, %error, "Failed to handle cross-chain request");
+            }
+        }
+    }
+}
+
 impl<S> RunningServerState<S>
 where
     S: Storage + Send,
 {
     fn handle_network_actions(&mut self, actions: NetworkActions) {
         for request in actions.cross_chain_requests {
+            let request = if self.server.network.sign_cross_chain_requests {
+                self.server.state.sign_cross_chain_request(request)
+            } else {
+                SignedCrossChainRequest::new(request, None)
+            };
             let shard_id = self.server.network.get_shard_id(request.target_chain_id());
             debug!(
                 "[{}] Scheduling cross-chain query: {} -> {}",
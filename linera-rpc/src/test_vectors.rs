@@ -0,0 +1,323 @@
+// Copyright (c) Zefchain Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! Deterministic test vectors for the wire formats of the protocol.
+//!
+//! Implementations of the protocol in other languages, e.g. the TypeScript and Python SDKs,
+//! need reference encodings to check that they are compatible with this one. [`generate`]
+//! returns BCS-encoded samples of the messages exchanged between clients and validators: block
+//! proposals, votes, certificates, message bundles and cross-chain requests. The keys are
+//! derived from a seed and the signatures are deterministic, so the same seed always yields
+//! the same bytes.
+//!
+//! Other implementations should decode every vector and encode it back to the same bytes.
+//! [`verify`] and [`verify_all`] run the same checks on this side, e.g. on vectors encoded by
+//! another implementation. The vectors for the seed [`SEED`] are committed in
+//! `linera-rpc/tests/test_vectors.json`, and a test checks that they don't change by accident.
+
+use linera_base::{
+    bcs,
+    crypto::{AccountSecretKey, CryptoHash, CryptoRng, Ed25519SecretKey, ValidatorSecretKey},
+    data_types::{Amount, Blob, BlobContent, BlockHeight, Epoch, Round, Timestamp},
+    hex,
+    identifiers::{AccountOwner, BlobId, ChainId},
+};
+use linera_chain::{
+    data_types::{
+        BlockExecutionOutcome, BlockProposal, IncomingBundle, LiteValue, LiteVote, MessageAction,
+        MessageBundle, OperationResult, ProposalSigningRequest, ProposedBlock, Vote,
+    },
+    test::{make_first_block, BlockTestExt, MessageTestExt, VoteTestExt},
+    types::{
+        Block, Certificate, ConfirmedBlock, ConfirmedBlockCertificate, LiteCertificate, Timeout,
+        TimeoutCertificate, ValidatedBlock, ValidatedBlockCertificate,
+    },
+};
+use linera_core::data_types::{BlockHeightRange, ChainInfoQuery, CrossChainRequest};
+use linera_execution::{
+    system::{Recipient, SystemMessage, SystemOperation},
+    Message, MessageKind, Operation,
+};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+use crate::{HandleConfirmedCertificateRequest, HandleLiteCertRequest, RpcMessage};
+
+/// The seed of the test vectors committed in `linera-rpc/tests/test_vectors.json`.
+pub const SEED: u64 = 7;
+
+/// A sample value of a wire type, with its canonical encoding.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TestVector {
+    /// The name of the sample, unique among the vectors generated from the same seed.
+    pub name: String,
+    /// The name of the Rust type of the sample.
+    pub type_name: String,
+    /// The BCS encoding of the sample, in lowercase hexadecimal.
+    pub hex: String,
+}
+
+/// An error found while verifying test vectors.
+#[derive(Debug, thiserror::Error)]
+pub enum TestVectorError {
+    #[error("Test vector {name:?} has an unknown type {type_name:?}")]
+    UnknownType { name: String, type_name: String },
+    #[error("Test vector {name:?} is not valid hexadecimal: {error}")]
+    InvalidHex {
+        name: String,
+        error: hex::FromHexError,
+    },
+    #[error("Test vector {name:?} can't be decoded as {type_name}: {error}")]
+    Decode {
+        name: String,
+        type_name: String,
+        error: bcs::Error,
+    },
+    #[error("Test vector {0:?} is not encoded canonically")]
+    NotCanonical(String),
+    #[error("Test vector {0:?} is missing")]
+    Missing(String),
+    #[error("Test vector {0:?} differs from the one generated by this implementation")]
+    Mismatch(String),
+}
+
+impl TestVector {
+    fn new<T: Serialize>(name: &str, type_name: &str, value: &T) -> Self {
+        let bytes = bcs::to_bytes(value).expect("serialization should not fail");
+        TestVector {
+            name: name.to_owned(),
+            type_name: type_name.to_owned(),
+            hex: hex::encode(bytes),
+        }
+    }
+
+    /// Returns the encoded bytes of the sample.
+    pub fn bytes(&self) -> Result<Vec<u8>, TestVectorError> {
+        hex::decode(&self.hex).map_err(|error| TestVectorError::InvalidHex {
+            name: self.name.clone(),
+            error,
+        })
+    }
+
+    /// Decodes the sample as a `T` and checks that encoding it again yields the same bytes.
+    fn check_canonical<T: Serialize + DeserializeOwned>(&self) -> Result<(), TestVectorError> {
+        let bytes = self.bytes()?;
+        let value = bcs::from_bytes::<T>(&bytes).map_err(|error| TestVectorError::Decode {
+            name: self.name.clone(),
+            type_name: self.type_name.clone(),
+            error,
+        })?;
+        let encoded = bcs::to_bytes(&value).expect("serialization should not fail");
+        if encoded != bytes {
+            return Err(TestVectorError::NotCanonical(self.name.clone()));
+        }
+        Ok(())
+    }
+}
+
+/// Returns the test vectors for the given `seed`.
+pub fn generate(seed: u64) -> Vec<TestVector> {
+    let mut rng: Box<dyn CryptoRng> = Some(seed).into();
+    let owner_secret = AccountSecretKey::Ed25519(Ed25519SecretKey::generate_from(&mut rng));
+    let validator_secret = ValidatorSecretKey::generate_from(&mut rng);
+    let owner = AccountOwner::from(owner_secret.public());
+    let chain_id = ChainId(CryptoHash::test_hash(format!("test vectors {seed}: chain")));
+    let sender_id = ChainId(CryptoHash::test_hash(format!(
+        "test vectors {seed}: sender"
+    )));
+    let timestamp = Timestamp::from(1_700_000_000_000_000);
+
+    let credit = Message::System(SystemMessage::Credit {
+        target: owner,
+        amount: Amount::from_tokens(3),
+        source: AccountOwner::CHAIN,
+    });
+    let bundle = MessageBundle {
+        height: BlockHeight(4),
+        timestamp,
+        certificate_hash: CryptoHash::test_hash(format!("test vectors {seed}: sender block")),
+        transaction_index: 1,
+        messages: vec![credit.clone().to_posted(0, MessageKind::Tracked)],
+    };
+    let incoming_bundle = IncomingBundle {
+        origin: sender_id,
+        bundle: bundle.clone(),
+        action: MessageAction::Accept,
+    };
+
+    let transfer = Operation::from(SystemOperation::Transfer {
+        owner: AccountOwner::CHAIN,
+        recipient: Recipient::chain(sender_id),
+        amount: Amount::ONE,
+    });
+    let proposed_block = make_first_block(chain_id)
+        .with_timestamp(timestamp)
+        .with_authenticated_signer(Some(owner))
+        .with_incoming_bundle(incoming_bundle.clone())
+        .with_operation(transfer.clone());
+    let round = Round::MultiLeader(0);
+    let request = ProposalSigningRequest::new_initial(owner, round, proposed_block.clone());
+    let signature = owner_secret.sign(&request.content);
+    let proposal = request
+        .into_proposal(signature)
+        .expect("the proposal is signed by its owner");
+
+    let block = BlockExecutionOutcome {
+        state_hash: CryptoHash::test_hash(format!("test vectors {seed}: state")),
+        messages: vec![Vec::new(); 2],
+        oracle_responses: vec![Vec::new(); 2],
+        events: vec![Vec::new(); 2],
+        blobs: vec![Vec::new(); 2],
+        operation_results: vec![OperationResult::default()],
+        ..BlockExecutionOutcome::default()
+    }
+    .with(proposed_block.clone());
+    let validated = ValidatedBlock::new(block.clone());
+    let lite_vote = LiteVote::new(LiteValue::new(&validated), round, &validator_secret);
+    let validated_certificate: ValidatedBlockCertificate =
+        Vote::new(validated, round, &validator_secret).into_certificate();
+    let confirmed_certificate: ConfirmedBlockCertificate =
+        Vote::new(ConfirmedBlock::new(block.clone()), round, &validator_secret).into_certificate();
+    let lite_certificate = confirmed_certificate.lite_certificate().cloned();
+    let timeout = Timeout::new(chain_id, BlockHeight(1), Epoch::ZERO);
+    let timeout_certificate: TimeoutCertificate =
+        Vote::new(timeout, Round::SingleLeader(2), &validator_secret).into_certificate();
+
+    let query = ChainInfoQuery::new(chain_id)
+        .with_pending_message_bundles()
+        .with_sent_certificate_hashes_in_range(BlockHeightRange::multi(BlockHeight(0), 10));
+    let cross_chain_request = CrossChainRequest::UpdateRecipient {
+        sender: sender_id,
+        recipient: chain_id,
+        bundles: vec![(Epoch::ZERO, bundle.clone())],
+    };
+    let blob = Blob::new_data(format!("test vectors {seed}: blob").into_bytes());
+    let blob_id = blob.id();
+    let blob_content = blob.into_content();
+
+    let rpc_confirmed_certificate =
+        RpcMessage::ConfirmedCertificate(Box::new(HandleConfirmedCertificateRequest {
+            certificate: confirmed_certificate.clone(),
+            wait_for_outgoing_messages: true,
+        }));
+    let rpc_lite_certificate = RpcMessage::LiteCertificate(Box::new(HandleLiteCertRequest {
+        certificate: lite_certificate.clone(),
+        wait_for_outgoing_messages: false,
+    }));
+
+    vec![
+        TestVector::new("message_bundle", "MessageBundle", &bundle),
+        TestVector::new("incoming_bundle", "IncomingBundle", &incoming_bundle),
+        TestVector::new("message", "Message", &credit),
+        TestVector::new("operation", "Operation", &transfer),
+        TestVector::new("proposed_block", "ProposedBlock", &proposed_block),
+        TestVector::new("block_proposal", "BlockProposal", &proposal),
+        TestVector::new("block", "Block", &block),
+        TestVector::new("lite_vote", "LiteVote", &lite_vote),
+        TestVector::new(
+            "validated_block_certificate",
+            "ValidatedBlockCertificate",
+            &validated_certificate,
+        ),
+        TestVector::new(
+            "confirmed_block_certificate",
+            "ConfirmedBlockCertificate",
+            &confirmed_certificate,
+        ),
+        TestVector::new(
+            "timeout_certificate",
+            "TimeoutCertificate",
+            &timeout_certificate,
+        ),
+        TestVector::new("lite_certificate", "LiteCertificate", &lite_certificate),
+        TestVector::new(
+            "certificate",
+            "Certificate",
+            &Certificate::Confirmed(confirmed_certificate.clone()),
+        ),
+        TestVector::new("chain_info_query", "ChainInfoQuery", &query),
+        TestVector::new(
+            "cross_chain_request",
+            "CrossChainRequest",
+            &cross_chain_request,
+        ),
+        TestVector::new("blob_content", "BlobContent", &blob_content),
+        TestVector::new("blob_id", "BlobId", &blob_id),
+        TestVector::new(
+            "rpc_block_proposal",
+            "RpcMessage",
+            &RpcMessage::BlockProposal(Box::new(proposal)),
+        ),
+        TestVector::new(
+            "rpc_confirmed_certificate",
+            "RpcMessage",
+            &rpc_confirmed_certificate,
+        ),
+        TestVector::new("rpc_lite_certificate", "RpcMessage", &rpc_lite_certificate),
+        TestVector::new(
+            "rpc_chain_info_query",
+            "RpcMessage",
+            &RpcMessage::ChainInfoQuery(Box::new(query)),
+        ),
+        TestVector::new(
+            "rpc_upload_blob",
+            "RpcMessage",
+            &RpcMessage::UploadBlob(Box::new(blob_content)),
+        ),
+        TestVector::new(
+            "rpc_vote",
+            "RpcMessage",
+            &RpcMessage::Vote(Box::new(lite_vote)),
+        ),
+        TestVector::new(
+            "rpc_cross_chain_request",
+            "RpcMessage",
+            &RpcMessage::CrossChainRequest(Box::new(cross_chain_request)),
+        ),
+    ]
+}
+
+/// Checks that `vector` decodes as its type and is encoded canonically.
+pub fn verify(vector: &TestVector) -> Result<(), TestVectorError> {
+    match vector.type_name.as_str() {
+        "MessageBundle" => vector.check_canonical::<MessageBundle>(),
+        "IncomingBundle" => vector.check_canonical::<IncomingBundle>(),
+        "Message" => vector.check_canonical::<Message>(),
+        "Operation" => vector.check_canonical::<Operation>(),
+        "ProposedBlock" => vector.check_canonical::<ProposedBlock>(),
+        "BlockProposal" => vector.check_canonical::<BlockProposal>(),
+        "Block" => vector.check_canonical::<Block>(),
+        "LiteVote" => vector.check_canonical::<LiteVote>(),
+        "ValidatedBlockCertificate" => vector.check_canonical::<ValidatedBlockCertificate>(),
+        "ConfirmedBlockCertificate" => vector.check_canonical::<ConfirmedBlockCertificate>(),
+        "TimeoutCertificate" => vector.check_canonical::<TimeoutCertificate>(),
+        "LiteCertificate" => vector.check_canonical::<LiteCertificate<'static>>(),
+        "Certificate" => vector.check_canonical::<Certificate>(),
+        "ChainInfoQuery" => vector.check_canonical::<ChainInfoQuery>(),
+        "CrossChainRequest" => vector.check_canonical::<CrossChainRequest>(),
+        "BlobContent" => vector.check_canonical::<BlobContent>(),
+        "BlobId" => vector.check_canonical::<BlobId>(),
+        "RpcMessage" => vector.check_canonical::<RpcMessage>(),
+        _ => Err(TestVectorError::UnknownType {
+            name: vector.name.clone(),
+            type_name: vector.type_name.clone(),
+        }),
+    }
+}
+
+/// Checks that `vectors` contain exactly the same encodings as the ones generated by this
+/// implementation from `seed`, and that all of them are valid.
+pub fn verify_all(vectors: &[TestVector], seed: u64) -> Result<(), TestVectorError> {
+    for vector in vectors {
+        verify(vector)?;
+    }
+    for expected in generate(seed) {
+        let vector = vectors
+            .iter()
+            .find(|vector| vector.name == expected.name)
+            .ok_or_else(|| TestVectorError::Missing(expected.name.clone()))?;
+        if vector.type_name != expected.type_name || vector.bytes()? != expected.bytes()? {
+            return Err(TestVectorError::Mismatch(expected.name));
+        }
+    }
+    Ok(())
+}
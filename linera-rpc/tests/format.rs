@@ -11,12 +11,15 @@ use linera_base::{
 };
 use linera_chain::{
     data_types::{MessageAction, OriginalProposal},
-    manager::{ChainManagerInfo, LockingBlock},
+    manager::{ChainManagerInfo, LockingBlock, VoteKind, Voter},
     types::{Certificate, CertificateKind, ConfirmedBlock, Timeout, ValidatedBlock},
 };
-use linera_core::{data_types::CrossChainRequest, node::NodeError};
+use linera_core::{
+    data_types::{ChainInfoField, CrossChainRequest},
+    node::NodeError,
+};
 use linera_execution::{
-    system::{AdminOperation, Recipient, SystemMessage, SystemOperation},
+    system::{AdminOperation, PermissionsActivation, Recipient, SystemMessage, SystemOperation},
     Message, MessageKind, Operation,
 };
 use linera_rpc::RpcMessage;
@@ -60,6 +63,7 @@ fn get_registry() -> Result<Registry> {
     tracer.trace_type::<Recipient>(&samples)?;
     tracer.trace_type::<SystemOperation>(&samples)?;
     tracer.trace_type::<AdminOperation>(&samples)?;
+    tracer.trace_type::<PermissionsActivation>(&samples)?;
     tracer.trace_type::<SystemMessage>(&samples)?;
     tracer.trace_type::<Operation>(&samples)?;
     tracer.trace_type::<Message>(&samples)?;
@@ -78,7 +82,10 @@ fn get_registry() -> Result<Registry> {
     tracer.trace_type::<GenericApplicationId>(&samples)?;
     tracer.trace_type::<LockingBlock>(&samples)?;
     tracer.trace_type::<ChainManagerInfo>(&samples)?;
+    tracer.trace_type::<VoteKind>(&samples)?;
+    tracer.trace_type::<Voter>(&samples)?;
     tracer.trace_type::<CrossChainRequest>(&samples)?;
+    tracer.trace_type::<ChainInfoField>(&samples)?;
     tracer.trace_type::<NodeError>(&samples)?;
     tracer.trace_type::<RpcMessage>(&samples)?;
     tracer.trace_type::<BlobType>(&samples)?;
@@ -0,0 +1,65 @@
+// Copyright (c) Zefchain Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+use linera_rpc::test_vectors::{generate, verify, verify_all, TestVector, TestVectorError, SEED};
+
+/// The test vectors published for other implementations.
+const COMMITTED_VECTORS: &str = include_str!("test_vectors.json");
+
+#[test]
+fn test_vectors_are_deterministic() {
+    let vectors = generate(7);
+    assert_eq!(vectors, generate(7));
+    assert_ne!(vectors, generate(8));
+}
+
+#[test]
+fn test_vectors_are_valid() {
+    let vectors = generate(7);
+    for vector in &vectors {
+        verify(vector).unwrap();
+    }
+    verify_all(&vectors, 7).unwrap();
+    assert!(matches!(
+        verify_all(&vectors, 8),
+        Err(TestVectorError::Mismatch(_))
+    ));
+}
+
+#[test]
+fn test_vectors_match_the_committed_ones() {
+    let committed = serde_json::from_str::<Vec<TestVector>>(COMMITTED_VECTORS).unwrap();
+    let generated = generate(SEED);
+    if let Err(error) = verify_all(&committed, SEED) {
+        panic!(
+            "{error}. If the wire formats changed on purpose, replace \
+             linera-rpc/tests/test_vectors.json with:\n{}",
+            serde_json::to_string_pretty(&generated).unwrap()
+        );
+    }
+    assert_eq!(committed, generated);
+}
+
+#[test]
+fn test_invalid_vectors_are_rejected() {
+    let vectors = generate(7);
+
+    let mut trailing_bytes = vectors[0].clone();
+    trailing_bytes.hex.push_str("00");
+    assert!(matches!(
+        verify(&trailing_bytes),
+        Err(TestVectorError::Decode { .. })
+    ));
+
+    let mut unknown_type = vectors[0].clone();
+    unknown_type.type_name = "Unknown".to_owned();
+    assert!(matches!(
+        verify(&unknown_type),
+        Err(TestVectorError::UnknownType { .. })
+    ));
+
+    assert!(matches!(
+        verify_all(&vectors[1..], 7),
+        Err(TestVectorError::Missing(name)) if name == vectors[0].name
+    ));
+}
@@ -8,19 +8,19 @@ mod utils;
 use proc_macro::TokenStream;
 use proc_macro2::{Ident, Span};
 use syn::{
-    parse_macro_input, Fields, ItemEnum,
     __private::{quote::quote, TokenStream2},
+    parse_macro_input, Attribute, Fields, ItemEnum, Variant,
 };
 
 use crate::utils::{concat, snakify};
 
-#[proc_macro_derive(GraphQLMutationRoot)]
+#[proc_macro_derive(GraphQLMutationRoot, attributes(mutation))]
 pub fn derive_mutation_root(input: TokenStream) -> TokenStream {
     let input = parse_macro_input!(input as ItemEnum);
     generate_mutation_root_code(input, "linera_sdk").into()
 }
 
-#[proc_macro_derive(GraphQLMutationRootInCrate)]
+#[proc_macro_derive(GraphQLMutationRootInCrate, attributes(mutation))]
 pub fn derive_mutation_root_in_crate(input: TokenStream) -> TokenStream {
     let input = parse_macro_input!(input as ItemEnum);
     generate_mutation_root_code(input, "crate").into()
@@ -33,8 +33,19 @@ fn generate_mutation_root_code(input: ItemEnum, crate_root: &str) -> TokenStream
     let mut methods = vec![];
 
     for variant in input.variants {
+        match is_skipped(&variant) {
+            Ok(true) => continue,
+            Ok(false) => {}
+            Err(error) => return error.to_compile_error(),
+        }
         let variant_name = &variant.ident;
         let function_name = snakify(variant_name);
+        // Doc comments become the descriptions of the mutations.
+        let docs = variant
+            .attrs
+            .iter()
+            .filter(|attr| attr.path().is_ident("doc"))
+            .collect::<Vec<&Attribute>>();
         match variant.fields {
             Fields::Named(named) => {
                 let mut fields = vec![];
@@ -46,6 +57,7 @@ fn generate_mutation_root_code(input: ItemEnum, crate_root: &str) -> TokenStream
                     field_names.push(name);
                 }
                 methods.push(quote! {
+                    #(#docs)*
                     async fn #function_name(&self, #(#fields,)*) -> [u8; 0] {
                         let operation = #enum_name::#variant_name {
                             #(#field_names,)*
@@ -67,6 +79,7 @@ fn generate_mutation_root_code(input: ItemEnum, crate_root: &str) -> TokenStream
                     field_names.push(name);
                 }
                 methods.push(quote! {
+                    #(#docs)*
                     async fn #function_name(&self, #(#fields,)*) -> [u8; 0] {
                         let operation = #enum_name::#variant_name(
                             #(#field_names,)*
@@ -80,6 +93,7 @@ fn generate_mutation_root_code(input: ItemEnum, crate_root: &str) -> TokenStream
             }
             Fields::Unit => {
                 methods.push(quote! {
+                    #(#docs)*
                     async fn #function_name(&self) -> [u8; 0] {
                         let operation = #enum_name::#variant_name;
 
@@ -127,9 +141,28 @@ fn generate_mutation_root_code(input: ItemEnum, crate_root: &str) -> TokenStream
     }
 }
 
+/// Returns whether the variant is marked with `#[mutation(skip)]`, e.g. because its
+/// fields cannot be GraphQL input values.
+fn is_skipped(variant: &Variant) -> syn::Result<bool> {
+    let mut skip = false;
+    for attr in &variant.attrs {
+        if attr.path().is_ident("mutation") {
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("skip") {
+                    skip = true;
+                    Ok(())
+                } else {
+                    Err(meta.error("unsupported `mutation` attribute"))
+                }
+            })?;
+        }
+    }
+    Ok(skip)
+}
+
 #[cfg(test)]
 pub mod tests {
-    use syn::{parse_quote, ItemEnum, __private::quote::quote};
+    use syn::{__private::quote::quote, parse_quote, ItemEnum};
 
     use crate::generate_mutation_root_code;
 
@@ -147,12 +180,15 @@ pub mod tests {
     fn test_derive_mutation_root() {
         let operation: ItemEnum = parse_quote! {
             enum SomeOperation {
+                /// Does something with a string.
                 TupleVariant(String),
                 StructVariant {
                     a: u32,
                     b: u64
                 },
-                EmptyVariant
+                EmptyVariant,
+                #[mutation(skip)]
+                SkippedVariant(Vec<Box<dyn std::any::Any>>),
             }
         };
 
@@ -174,6 +210,7 @@ pub mod tests {
                 Application: linera_sdk::Service,
                 linera_sdk::ServiceRuntime<Application>: Send + Sync,
             {
+                #[doc = r" Does something with a string."]
                 async fn tuple_variant(&self, field0: String,) -> [u8; 0] {
                     let operation = SomeOperation::TupleVariant(field0,);
                     self.runtime.schedule_operation(&operation);
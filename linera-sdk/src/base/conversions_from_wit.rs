@@ -5,7 +5,7 @@
 
 use linera_base::{
     crypto::CryptoHash,
-    data_types::{Amount, BlockHeight, TimeDelta, Timestamp},
+    data_types::{Amount, BlockHeight, Epoch, TimeDelta, Timestamp},
     http,
     identifiers::{AccountOwner, ApplicationId, ChainId},
     ownership::{ChainOwnership, TimeoutConfig},
@@ -49,6 +49,9 @@ macro_rules! impl_from_wit {
                     $wit_base_api::AccountOwner::Address20(value) => {
                         AccountOwner::Address20(value.into())
                     }
+                    $wit_base_api::AccountOwner::Chain(chain_id) => {
+                        AccountOwner::Chain(chain_id.into())
+                    }
                 }
             }
         }
@@ -67,6 +70,12 @@ macro_rules! impl_from_wit {
             }
         }
 
+        impl From<$wit_base_api::Epoch> for Epoch {
+            fn from(epoch: $wit_base_api::Epoch) -> Self {
+                Epoch(epoch.inner0)
+            }
+        }
+
         impl From<$wit_base_api::ChainId> for ChainId {
             fn from(chain_id: $wit_base_api::ChainId) -> Self {
                 ChainId(chain_id.inner0.into())
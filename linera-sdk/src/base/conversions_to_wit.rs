@@ -50,6 +50,9 @@ macro_rules! impl_to_wit {
                     AccountOwner::Address20(value) => {
                         $wit_base_api::AccountOwner::Address20(value.into())
                     }
+                    AccountOwner::Chain(chain_id) => {
+                        $wit_base_api::AccountOwner::Chain(chain_id.into())
+                    }
                 }
             }
         }
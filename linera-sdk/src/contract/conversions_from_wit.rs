@@ -50,6 +50,7 @@ impl From<wit_contract_api::AccountOwner> for AccountOwner {
             wit_contract_api::AccountOwner::Address20(value) => {
                 AccountOwner::Address20(value.into())
             }
+            wit_contract_api::AccountOwner::Chain(chain_id) => AccountOwner::Chain(chain_id.into()),
         }
     }
 }
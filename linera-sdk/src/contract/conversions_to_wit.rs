@@ -6,10 +6,12 @@
 use linera_base::{
     crypto::CryptoHash,
     data_types::{
-        Amount, ApplicationPermissions, BlockHeight, Bytecode, Resources, SendMessageRequest,
-        TimeDelta,
+        Amount, ApplicationPermissions, BlockHeight, Bytecode, ReentrancyPolicy, Resources,
+        SendMessageRequest, TimeDelta,
+    },
+    identifiers::{
+        Account, AccountOwner, ApplicationId, ChainId, ChannelName, MessageId, ModuleId, StreamName,
     },
-    identifiers::{Account, AccountOwner, ApplicationId, ChainId, MessageId, ModuleId, StreamName},
     ownership::{ChainOwnership, TimeoutConfig},
     vm::VmRuntime,
 };
@@ -73,6 +75,7 @@ impl From<AccountOwner> for wit_contract_api::AccountOwner {
             AccountOwner::Address20(owner) => {
                 wit_contract_api::AccountOwner::Address20(owner.into())
             }
+            AccountOwner::Chain(chain_id) => wit_contract_api::AccountOwner::Chain(chain_id.into()),
         }
     }
 }
@@ -112,6 +115,18 @@ impl From<VmRuntime> for wit_contract_api::VmRuntime {
     }
 }
 
+impl From<ReentrancyPolicy> for wit_contract_api::ReentrancyPolicy {
+    fn from(policy: ReentrancyPolicy) -> Self {
+        match policy {
+            ReentrancyPolicy::Deny => wit_contract_api::ReentrancyPolicy::Deny,
+            ReentrancyPolicy::Allow => wit_contract_api::ReentrancyPolicy::Allow,
+            ReentrancyPolicy::AllowSameChainOnly => {
+                wit_contract_api::ReentrancyPolicy::AllowSameChainOnly
+            }
+        }
+    }
+}
+
 impl From<MessageId> for wit_contract_api::MessageId {
     fn from(message_id: MessageId) -> Self {
         wit_contract_api::MessageId {
@@ -173,6 +188,14 @@ impl From<StreamName> for wit_contract_api::StreamName {
     }
 }
 
+impl From<ChannelName> for wit_contract_api::ChannelName {
+    fn from(name: ChannelName) -> Self {
+        wit_contract_api::ChannelName {
+            inner0: name.into_bytes(),
+        }
+    }
+}
+
 impl From<TimeDelta> for wit_contract_api::TimeDelta {
     fn from(delta: TimeDelta) -> Self {
         Self {
@@ -0,0 +1,155 @@
+// Copyright (c) Zefchain Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! Shuffling and sampling on top of the deterministic entropy of a transaction.
+
+use std::collections::BTreeMap;
+
+/// Uniformly distributed integers, obtained from entropy drawn with a callback.
+struct Entropy<Draw> {
+    draw: Draw,
+    bytes: Vec<u8>,
+    position: usize,
+}
+
+impl<Draw> Entropy<Draw>
+where
+    Draw: FnMut(u32) -> Vec<u8>,
+{
+    /// Creates an [`Entropy`] with enough bytes for `count` integers drawn in a single call,
+    /// so that the usual case doesn't need more than one call to the runtime.
+    fn new(mut draw: Draw, count: usize) -> Self {
+        let num_bytes = u32::try_from(count.saturating_mul(8)).unwrap_or(u32::MAX);
+        let bytes = draw(num_bytes);
+        Entropy {
+            draw,
+            bytes,
+            position: 0,
+        }
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        if self.bytes.len() < self.position + 8 {
+            self.bytes = (self.draw)(8);
+            self.position = 0;
+        }
+        let bytes = &self.bytes[self.position..self.position + 8];
+        self.position += 8;
+        u64::from_le_bytes(bytes.try_into().expect("slice should have 8 bytes"))
+    }
+
+    /// Returns an integer uniformly distributed in `0..bound`.
+    fn below(&mut self, bound: u64) -> u64 {
+        // The first `2^64 mod bound` values would make the smallest results more likely.
+        let threshold = bound.wrapping_neg() % bound;
+        loop {
+            let value = self.next_u64();
+            if value >= threshold {
+                return value % bound;
+            }
+        }
+    }
+}
+
+/// Shuffles `slice` with the Fisher-Yates algorithm.
+pub(crate) fn shuffle<T>(slice: &mut [T], draw: impl FnMut(u32) -> Vec<u8>) {
+    if slice.len() < 2 {
+        return;
+    }
+    let mut entropy = Entropy::new(draw, slice.len() - 1);
+    for index in (1..slice.len()).rev() {
+        let other = entropy.below(index as u64 + 1) as usize;
+        slice.swap(index, other);
+    }
+}
+
+/// Returns `count` distinct integers of `0..population`, in random order.
+///
+/// This runs the first `count` steps of the Fisher-Yates algorithm on the virtual array
+/// `[0, 1, ..., population - 1]`, keeping only the swapped positions in memory.
+pub(crate) fn sample(
+    population: usize,
+    count: usize,
+    draw: impl FnMut(u32) -> Vec<u8>,
+) -> Vec<usize> {
+    assert!(
+        count <= population,
+        "Cannot sample {count} values out of {population}"
+    );
+    if count == 0 {
+        return Vec::new();
+    }
+    let mut entropy = Entropy::new(draw, count);
+    let mut swapped = BTreeMap::new();
+    (0..count)
+        .map(|index| {
+            let other = index + entropy.below((population - index) as u64) as usize;
+            let value = swapped.get(&other).copied().unwrap_or(other);
+            let replacement = swapped.get(&index).copied().unwrap_or(index);
+            swapped.insert(other, replacement);
+            value
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeSet;
+
+    use super::{sample, shuffle};
+
+    /// Returns a callback drawing bytes from a simple deterministic sequence.
+    fn draw() -> impl FnMut(u32) -> Vec<u8> {
+        let mut state = 0x9e37_79b9_7f4a_7c15_u64;
+        move |num_bytes| {
+            (0..num_bytes)
+                .map(|_| {
+                    state = state
+                        .wrapping_mul(6_364_136_223_846_793_005)
+                        .wrapping_add(1);
+                    (state >> 56) as u8
+                })
+                .collect()
+        }
+    }
+
+    #[test]
+    fn shuffle_returns_a_permutation() {
+        let mut values = (0..100).collect::<Vec<_>>();
+        shuffle(&mut values, draw());
+        assert_ne!(values, (0..100).collect::<Vec<_>>());
+        values.sort_unstable();
+        assert_eq!(values, (0..100).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn shuffle_draws_entropy_once() {
+        let mut calls = 0;
+        let mut inner = draw();
+        let mut values = [1, 2, 3, 4, 5];
+        shuffle(&mut values, |num_bytes| {
+            calls += 1;
+            assert_eq!(num_bytes, 32);
+            inner(num_bytes)
+        });
+        assert_eq!(calls, 1);
+    }
+
+    #[test]
+    fn sample_returns_distinct_values() {
+        let values = sample(1_000_000, 50, draw());
+        assert_eq!(values.len(), 50);
+        assert!(values.iter().all(|value| *value < 1_000_000));
+        assert_eq!(values.iter().collect::<BTreeSet<_>>().len(), 50);
+
+        let mut values = sample(10, 10, draw());
+        values.sort_unstable();
+        assert_eq!(values, (0..10).collect::<Vec<_>>());
+    }
+
+    #[test]
+    #[should_panic(expected = "Cannot sample 3 values out of 2")]
+    fn sample_rejects_too_many_values() {
+        sample(2, 3, draw());
+    }
+}
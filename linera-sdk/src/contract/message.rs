@@ -0,0 +1,298 @@
+// Copyright (c) Zefchain Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! Helpers to send messages to other chains and to handle the ones that bounce back.
+//!
+//! [`ContractRuntime::message`] returns an [`OutgoingMessage`] builder whose type records the
+//! options already set, so that setting an option twice is a compile-time error:
+//!
+//! ```ignore
+//! runtime
+//!     .message(destination)
+//!     .with_tracking()
+//!     .with_grant(grant)
+//!     .send(message);
+//! ```
+
+use std::marker::PhantomData;
+
+use linera_base::{data_types::Resources, identifiers::ChainId};
+
+use super::ContractRuntime;
+use crate::Contract;
+
+/// Marks an [`OutgoingMessage`] that is not tracked.
+pub struct Untracked;
+
+/// Marks an [`OutgoingMessage`] that is tracked: it bounces back to the sender if the
+/// receiver rejects it.
+pub struct Tracked;
+
+/// Marks an [`OutgoingMessage`] without a grant of resources.
+pub struct NoGrant;
+
+/// Marks an [`OutgoingMessage`] with a grant of resources.
+pub struct WithGrant;
+
+/// Whether an [`OutgoingMessage`] is tracked.
+pub trait TrackingOption {
+    /// Whether the message bounces back to the sender if the receiver rejects it.
+    const IS_TRACKED: bool;
+}
+
+impl TrackingOption for Untracked {
+    const IS_TRACKED: bool = false;
+}
+
+impl TrackingOption for Tracked {
+    const IS_TRACKED: bool = true;
+}
+
+/// A message to this application on another chain, being configured before it is sent.
+#[must_use]
+pub struct OutgoingMessage<'runtime, Application, Tracking = Untracked, Grant = NoGrant>
+where
+    Application: Contract,
+{
+    runtime: &'runtime mut ContractRuntime<Application>,
+    destination: ChainId,
+    authenticated: bool,
+    grant: Resources,
+    options: PhantomData<(Tracking, Grant)>,
+}
+
+impl<'runtime, Application, Tracking, Grant> OutgoingMessage<'runtime, Application, Tracking, Grant>
+where
+    Application: Contract,
+{
+    /// Returns the same message with other options.
+    fn with_options<NewTracking, NewGrant>(
+        self,
+    ) -> OutgoingMessage<'runtime, Application, NewTracking, NewGrant> {
+        OutgoingMessage {
+            runtime: self.runtime,
+            destination: self.destination,
+            authenticated: self.authenticated,
+            grant: self.grant,
+            options: PhantomData,
+        }
+    }
+
+    /// Forwards the authenticated signer with the message.
+    pub fn with_authentication(mut self) -> Self {
+        self.authenticated = true;
+        self
+    }
+}
+
+impl<'runtime, Application, Grant> OutgoingMessage<'runtime, Application, Untracked, Grant>
+where
+    Application: Contract,
+{
+    /// Marks the message to be tracked, so that the sender receives the message back if it is
+    /// rejected by the receiver.
+    pub fn with_tracking(self) -> OutgoingMessage<'runtime, Application, Tracked, Grant> {
+        self.with_options()
+    }
+}
+
+impl<'runtime, Application, Tracking> OutgoingMessage<'runtime, Application, Tracking, NoGrant>
+where
+    Application: Contract,
+{
+    /// Forwards a grant of resources so the receiver can use it to pay for receiving the message.
+    pub fn with_grant(
+        mut self,
+        grant: Resources,
+    ) -> OutgoingMessage<'runtime, Application, Tracking, WithGrant> {
+        self.grant = grant;
+        self.with_options()
+    }
+}
+
+impl<Application, Tracking, Grant> OutgoingMessage<'_, Application, Tracking, Grant>
+where
+    Application: Contract,
+    Tracking: TrackingOption,
+{
+    /// Schedules the `message` to be sent with the configured options.
+    pub fn send(self, message: Application::Message) {
+        let mut builder = self.runtime.prepare_message(message).with_grant(self.grant);
+        if Tracking::IS_TRACKED {
+            builder = builder.with_tracking();
+        }
+        if self.authenticated {
+            builder = builder.with_authentication();
+        }
+        builder.send_to(self.destination);
+    }
+}
+
+/// A message being executed, either on the chain it was sent to or back on the sender's
+/// chain.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum IncomingMessage<Message> {
+    /// The message was sent to this chain.
+    Received(Message),
+    /// The message was tracked, rejected by the receiver and is now back on the sender's chain.
+    Bounced(Message),
+}
+
+impl<Message> IncomingMessage<Message> {
+    /// Returns whether the message bounced back to the sender.
+    pub fn is_bounced(&self) -> bool {
+        matches!(self, IncomingMessage::Bounced(_))
+    }
+
+    /// Returns the message, regardless of whether it bounced.
+    pub fn into_inner(self) -> Message {
+        match self {
+            IncomingMessage::Received(message) | IncomingMessage::Bounced(message) => message,
+        }
+    }
+}
+
+impl<Application> ContractRuntime<Application>
+where
+    Application: Contract,
+{
+    /// Returns a builder to configure a message to this application on the `destination`
+    /// chain and send it.
+    pub fn message(&mut self, destination: ChainId) -> OutgoingMessage<'_, Application> {
+        OutgoingMessage {
+            runtime: self,
+            destination,
+            authenticated: false,
+            grant: Resources::default(),
+            options: PhantomData,
+        }
+    }
+
+    /// Tells whether the incoming `message` bounced back to this chain.
+    ///
+    /// # Panics
+    ///
+    /// If not called while executing a message, i.e. outside of `execute_message`.
+    pub fn incoming_message(
+        &mut self,
+        message: Application::Message,
+    ) -> IncomingMessage<Application::Message> {
+        let is_bouncing = self
+            .message_is_bouncing()
+            .expect("Message delivery status has to be available when executing a message");
+        if is_bouncing {
+            IncomingMessage::Bounced(message)
+        } else {
+            IncomingMessage::Received(message)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use linera_base::{
+        abi::{ContractAbi, WithContractAbi},
+        crypto::CryptoHash,
+        data_types::{Resources, SendMessageRequest},
+        identifiers::ChainId,
+    };
+
+    use super::IncomingMessage;
+    use crate::{Contract, ContractRuntime};
+
+    struct TestAbi;
+
+    impl ContractAbi for TestAbi {
+        type Operation = ();
+        type Response = ();
+    }
+
+    struct TestContract;
+
+    impl WithContractAbi for TestContract {
+        type Abi = TestAbi;
+    }
+
+    impl Contract for TestContract {
+        type Message = String;
+        type Parameters = ();
+        type InstantiationArgument = ();
+        type EventValue = ();
+
+        async fn load(_runtime: ContractRuntime<Self>) -> Self {
+            TestContract
+        }
+
+        async fn instantiate(&mut self, _argument: ()) {}
+
+        async fn execute_operation(&mut self, _operation: ()) {}
+
+        async fn execute_message(&mut self, _message: String) {}
+
+        async fn store(self) {}
+    }
+
+    /// Checks that the options set on the builder are the ones the message is sent with.
+    #[test]
+    fn messages_are_sent_with_their_options() {
+        let mut runtime = ContractRuntime::<TestContract>::new();
+        let destination = ChainId(CryptoHash::test_hash("destination"));
+        let grant = Resources {
+            wasm_fuel: 1_000,
+            ..Resources::default()
+        };
+
+        runtime.message(destination).send("plain".to_owned());
+        runtime
+            .message(destination)
+            .with_grant(grant)
+            .with_tracking()
+            .with_authentication()
+            .send("all options".to_owned());
+
+        assert_eq!(
+            *runtime.created_send_message_requests(),
+            vec![
+                SendMessageRequest {
+                    destination,
+                    authenticated: false,
+                    is_tracked: false,
+                    grant: Resources::default(),
+                    message: "plain".to_owned(),
+                },
+                SendMessageRequest {
+                    destination,
+                    authenticated: true,
+                    is_tracked: true,
+                    grant,
+                    message: "all options".to_owned(),
+                },
+            ]
+        );
+    }
+
+    /// Checks that incoming messages are told apart from the ones that bounced.
+    #[test]
+    fn bounced_messages_are_recognized() {
+        let mut runtime = ContractRuntime::<TestContract>::new();
+
+        runtime.set_message_is_bouncing(false);
+        let message = runtime.incoming_message("message".to_owned());
+        assert!(!message.is_bounced());
+        assert_eq!(message, IncomingMessage::Received("message".to_owned()));
+
+        runtime.set_message_is_bouncing(true);
+        let message = runtime.incoming_message("message".to_owned());
+        assert!(message.is_bounced());
+        assert_eq!(message.into_inner(), "message");
+    }
+
+    /// Checks that the bounce status is only available while executing a message.
+    #[test]
+    #[should_panic(expected = "Message delivery status has to be available")]
+    fn incoming_message_outside_of_execute_message() {
+        let mut runtime = ContractRuntime::<TestContract>::new();
+        runtime.set_message_is_bouncing(None);
+        runtime.incoming_message("message".to_owned());
+    }
+}
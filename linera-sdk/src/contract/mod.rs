@@ -5,6 +5,8 @@
 
 mod conversions_from_wit;
 mod conversions_to_wit;
+mod entropy;
+pub mod message;
 #[cfg(not(with_testing))]
 mod runtime;
 #[cfg(with_testing)]
@@ -12,6 +14,7 @@ mod test_runtime;
 #[doc(hidden)]
 pub mod wit;
 
+pub use self::message::{IncomingMessage, OutgoingMessage};
 #[cfg(not(with_testing))]
 pub use self::runtime::ContractRuntime;
 #[cfg(with_testing)]
@@ -29,9 +32,38 @@ pub type ContractRuntime<Application> = MockContractRuntime<Application>;
 ///
 /// Generates the necessary boilerplate for implementing the contract WIT interface, exporting the
 /// necessary resource types and functions so that the host can call the application contract.
+///
+/// Use `contract!(MyContract with_views)` to also export the view functions of a contract that
+/// implements [`ContractViews`][`crate::ContractViews`].
 #[macro_export]
 macro_rules! contract {
     ($contract:ident) => {
+        $crate::contract!(@export $contract, |_argument: Vec<u8>| -> Vec<u8> {
+            panic!("The contract `{}` has no view functions", stringify!($contract))
+        });
+    };
+
+    ($contract:ident with_views) => {
+        $crate::contract!(@export $contract, |argument: Vec<u8>| -> Vec<u8> {
+            use $crate::util::BlockingWait;
+            $crate::contract::run_async_entrypoint::<$contract, _, _>(
+                unsafe { &mut CONTRACT },
+                move |contract| {
+                    let call = $crate::bcs::from_bytes(&argument)
+                        .expect("Failed to deserialize `ViewCall` in execute_view");
+
+                    let response =
+                        <$contract as $crate::ContractViews>::execute_view(contract, call)
+                            .blocking_wait();
+
+                    $crate::bcs::to_bytes(&response)
+                        .expect("Failed to serialize `ViewResponse` in execute_view")
+                },
+            )
+        });
+    };
+
+    (@export $contract:ident, $execute_view:expr) => {
         #[doc(hidden)]
         static mut CONTRACT: Option<$contract> = None;
 
@@ -98,6 +130,34 @@ macro_rules! contract {
                 )
             }
 
+            fn on_chain_close() {
+                use $crate::util::BlockingWait;
+                $crate::contract::run_async_entrypoint::<$contract, _, _>(
+                    unsafe { &mut CONTRACT },
+                    move |contract| contract.on_chain_close().blocking_wait(),
+                )
+            }
+
+            fn on_upgrade() {
+                use $crate::util::BlockingWait;
+                $crate::contract::run_async_entrypoint::<$contract, _, _>(
+                    unsafe { &mut CONTRACT },
+                    move |contract| contract.on_upgrade().blocking_wait(),
+                )
+            }
+
+            fn authorize_proposal(payload: Vec<u8>) -> bool {
+                use $crate::util::BlockingWait;
+                $crate::contract::run_async_entrypoint::<$contract, _, _>(
+                    unsafe { &mut CONTRACT },
+                    move |contract| contract.authorize_proposal(payload).blocking_wait(),
+                )
+            }
+
+            fn execute_view(argument: Vec<u8>) -> Vec<u8> {
+                ($execute_view)(argument)
+            }
+
             fn finalize() {
                 use $crate::util::BlockingWait;
 
@@ -108,6 +168,12 @@ macro_rules! contract {
             }
         }
 
+        /// Declares the version of the Wasm API the contract is built against.
+        #[cfg(target_arch = "wasm32")]
+        #[link_section = "linera:api-version"]
+        #[used]
+        static LINERA_API_VERSION: [u8; 4] = $crate::API_VERSION_SECTION;
+
         /// Stub of a `main` entrypoint so that the binary doesn't fail to compile on targets other
         /// than WebAssembly.
         #[cfg(not(target_arch = "wasm32"))]
@@ -127,8 +193,14 @@ where
 {
     ContractLogger::install();
 
-    let contract =
-        contract.get_or_insert_with(|| Contract::load(ContractRuntime::new()).blocking_wait());
+    let contract = contract.get_or_insert_with(|| {
+        if Contract::REENTRANCY_POLICY != crate::ReentrancyPolicy::Deny {
+            wit::contract_runtime_api::declare_reentrancy_policy(
+                Contract::REENTRANCY_POLICY.into(),
+            );
+        }
+        Contract::load(ContractRuntime::new()).blocking_wait()
+    });
 
     entrypoint(contract).into()
 }
@@ -4,22 +4,31 @@
 //! Runtime types to interface with the host executing the contract.
 
 use linera_base::{
-    abi::{ContractAbi, ServiceAbi},
+    abi::{ContractAbi, ServiceAbi, ViewAbi},
     data_types::{
-        Amount, ApplicationPermissions, BlockHeight, Bytecode, Resources, SendMessageRequest,
-        Timestamp,
+        Amount, ApplicationPermissions, BlockHeight, Bytecode, Epoch, Resources,
+        SendMessageRequest, Timestamp,
     },
     ensure, http,
-    identifiers::{Account, AccountOwner, ApplicationId, ChainId, MessageId, ModuleId, StreamName},
+    identifiers::{
+        Account, AccountOwner, ApplicationId, ChainId, ChannelName, MessageId, ModuleId, StreamName,
+    },
     ownership::{
         AccountPermissionError, ChainOwnership, ChangeApplicationPermissionsError, CloseChainError,
     },
     vm::VmRuntime,
 };
-use serde::Serialize;
+use serde::{de::DeserializeOwned, Serialize};
 
-use super::wit::{base_runtime_api as base_wit, contract_runtime_api as contract_wit};
-use crate::{Contract, DataBlobHash, KeyValueStore, ViewStorageContext};
+use super::{
+    entropy,
+    wit::{base_runtime_api as base_wit, contract_runtime_api as contract_wit},
+};
+use crate::{
+    typed_blob::{BlobSchema, TypedBlob, TypedBlobError},
+    views::ViewTransaction,
+    Contract, DataBlobHash, KeyValueStore, ViewStorageContext,
+};
 
 /// The common runtime to interface with the host executing the contract.
 ///
@@ -70,6 +79,18 @@ where
     pub fn root_view_storage_context(&self) -> ViewStorageContext {
         ViewStorageContext::new_unsafe(self.key_value_store(), Vec::new(), ())
     }
+
+    /// Returns a storage context for one of several root views of the application, stored
+    /// under `base_key`. The base keys of the different root views must not be prefixes of
+    /// each other.
+    pub fn view_storage_context(&self, base_key: Vec<u8>) -> ViewStorageContext {
+        ViewStorageContext::new_unsafe(self.key_value_store(), base_key, ())
+    }
+
+    /// Returns an empty [`ViewTransaction`], to save several root views atomically.
+    pub fn view_transaction<'a>(&self) -> ViewTransaction<'a> {
+        ViewTransaction::new(self.key_value_store())
+    }
 }
 
 impl<Application> ContractRuntime<Application>
@@ -122,6 +143,20 @@ where
             .get_or_insert_with(|| base_wit::read_system_timestamp().into())
     }
 
+    /// Returns the current epoch of the chain.
+    pub fn epoch(&mut self) -> Epoch {
+        base_wit::read_epoch().into()
+    }
+
+    /// Returns the configuration of this application on the current chain, if the chain
+    /// owners set one.
+    pub fn application_config<Config: DeserializeOwned>(&mut self) -> Option<Config> {
+        base_wit::read_application_config().map(|bytes| {
+            serde_json::from_slice(&bytes)
+                .expect("Application configuration must be deserializable")
+        })
+    }
+
     /// Returns the current chain balance.
     pub fn chain_balance(&mut self) -> Amount {
         base_wit::read_chain_balance().into()
@@ -132,6 +167,14 @@ where
         base_wit::read_owner_balance(owner.into()).into()
     }
 
+    /// Returns the balance of the application's own account on this chain, which includes
+    /// the operation fees it collected, if its parameters declare
+    /// [`OperationFees`](linera_base::data_types::OperationFees).
+    pub fn collected_fees(&mut self) -> Amount {
+        let owner = AccountOwner::from(self.application_id().forget_abi());
+        self.owner_balance(owner)
+    }
+
     /// Retrieves the owner configuration for the current chain.
     pub fn chain_ownership(&mut self) -> ChainOwnership {
         base_wit::get_chain_ownership().into()
@@ -162,10 +205,31 @@ where
         base_wit::read_data_blob(hash.0.into())
     }
 
+    /// Reads a typed data blob from storage, checking that it holds a value of type `T`.
+    pub fn read_typed_data_blob<T: BlobSchema>(
+        &mut self,
+        blob: TypedBlob<T>,
+    ) -> Result<T, TypedBlobError> {
+        TypedBlob::decode(&self.read_data_blob(blob.hash()))
+    }
+
     /// Asserts that a data blob with the given hash exists in storage.
     pub fn assert_data_blob_exists(&mut self, hash: DataBlobHash) {
         base_wit::assert_data_blob_exists(hash.0.into())
     }
+
+    /// Reads the value under `key` in the state of this application on another chain, as
+    /// stored by the validators. Returns `None` if there is no such value, or if the
+    /// validator executing the block doesn't know the chain.
+    ///
+    /// Should only be used with chains that all validators are likely to have up to date,
+    /// otherwise most block proposals will fail.
+    ///
+    /// Cannot be used in fast blocks: A block using this call should be proposed by a regular
+    /// owner, not a super owner.
+    pub fn read_value_bytes_on_chain(&mut self, chain_id: ChainId, key: &[u8]) -> Option<Vec<u8>> {
+        base_wit::read_value_bytes_on_chain(chain_id.into(), key)
+    }
 }
 
 impl<Application> ContractRuntime<Application>
@@ -260,6 +324,44 @@ where
             .expect("Failed to deserialize `Response` in cross-application call")
     }
 
+    /// Calls another application speculatively, to find out whether the call would succeed.
+    /// Returns the response, or `None` if the call failed.
+    ///
+    /// Whatever the outcome, the changes the call makes are undone: the chain state, the
+    /// messages it sends and the events it emits. The resources it consumes are still paid
+    /// for, and the number of speculative calls per transaction is limited.
+    pub fn try_call<A: ContractAbi + Send>(
+        &mut self,
+        authenticated: bool,
+        application: ApplicationId<A>,
+        call: &A::Operation,
+    ) -> Option<A::Response> {
+        let call_bytes = A::serialize_operation(call)
+            .expect("Failed to serialize `Operation` in speculative call");
+
+        let response_bytes =
+            contract_wit::try_call(authenticated, application.forget_abi().into(), &call_bytes)?;
+
+        Some(
+            A::deserialize_response(response_bytes)
+                .expect("Failed to deserialize `Response` in speculative call"),
+        )
+    }
+
+    /// Calls a read-only view function of another application.
+    pub fn view_call<A: ViewAbi + Send>(
+        &self,
+        application: ApplicationId<A>,
+        call: &A::ViewCall,
+    ) -> A::ViewResponse {
+        let call_bytes = bcs::to_bytes(call).expect("Failed to serialize `ViewCall` in view call");
+
+        let response_bytes =
+            contract_wit::try_view_call(application.forget_abi().into(), &call_bytes);
+
+        bcs::from_bytes(&response_bytes).expect("Failed to deserialize `ViewResponse` in view call")
+    }
+
     /// Adds a new item to an event stream. Returns the new event's index in the stream.
     pub fn emit(&mut self, name: StreamName, value: &Application::EventValue) -> u32 {
         contract_wit::emit(
@@ -301,6 +403,28 @@ where
         contract_wit::unsubscribe_from_events(chain_id.into(), application_id.into(), &name.into())
     }
 
+    /// Sends a message to this application on every chain subscribed to the `channel` at the
+    /// end of the current transaction.
+    pub fn publish_message(&mut self, channel: ChannelName, message: Application::Message) {
+        let serialized_message =
+            bcs::to_bytes(&message).expect("Failed to serialize message to be published");
+        contract_wit::publish_message(&channel.into(), &serialized_message)
+    }
+
+    /// Subscribes the current chain to a channel of this application on the chain `chain_id`.
+    ///
+    /// Once the subscription message is processed there, messages published to the channel
+    /// are also sent to the current chain.
+    pub fn subscribe_to_channel(&mut self, chain_id: ChainId, channel: ChannelName) {
+        contract_wit::subscribe_to_channel(chain_id.into(), &channel.into())
+    }
+
+    /// Unsubscribes the current chain from a channel of this application on the chain
+    /// `chain_id`.
+    pub fn unsubscribe_from_channel(&mut self, chain_id: ChainId, channel: ChannelName) {
+        contract_wit::unsubscribe_from_channel(chain_id.into(), &channel.into())
+    }
+
     /// Queries an application service as an oracle and returns the response.
     ///
     /// Should only be used with queries where it is very likely that all validators will compute
@@ -386,6 +510,13 @@ where
         DataBlobHash(blob_id.hash.into())
     }
 
+    /// Creates a new data blob holding `value` with the header of its schema, and returns its
+    /// typed hash.
+    pub fn create_typed_data_blob<T: BlobSchema>(&mut self, value: &T) -> TypedBlob<T> {
+        let bytes = TypedBlob::encode(value).expect("Failed to serialize value of a typed blob");
+        TypedBlob::from_hash(self.create_data_blob(bytes))
+    }
+
     /// Publishes a module with contract and service bytecode and returns the module ID.
     pub fn publish_module(
         &mut self,
@@ -400,6 +531,55 @@ where
     pub fn validation_round(&mut self) -> Option<u32> {
         contract_wit::validation_round()
     }
+
+    /// Returns the next `num_bytes` bytes of the deterministic entropy of the current
+    /// transaction.
+    ///
+    /// The entropy only depends on the chain, the block height and the index of the
+    /// transaction, so it is not secret: the block proposer can predict it. The number of
+    /// bytes a transaction can draw is limited by the resource control policy.
+    pub fn draw_entropy(&mut self, num_bytes: u32) -> Vec<u8> {
+        contract_wit::draw_entropy(num_bytes)
+    }
+
+    /// Shuffles `slice` uniformly, using the entropy of the current transaction.
+    ///
+    /// This draws 8 bytes of entropy per element, in a single call to the runtime.
+    pub fn shuffle<T>(&mut self, slice: &mut [T]) {
+        entropy::shuffle(slice, |num_bytes| self.draw_entropy(num_bytes))
+    }
+
+    /// Returns `count` distinct indices sampled uniformly from `0..population`, in random
+    /// order, using the entropy of the current transaction.
+    ///
+    /// This draws 8 bytes of entropy per sampled index, in a single call to the runtime.
+    ///
+    /// # Panics
+    ///
+    /// If `count` is larger than `population`.
+    pub fn sample(&mut self, population: usize, count: usize) -> Vec<usize> {
+        entropy::sample(population, count, |num_bytes| self.draw_entropy(num_bytes))
+    }
+
+    /// Returns the value of the storage `slot` of the Ethereum contract at `address`, as of
+    /// the Ethereum block `block_number`.
+    ///
+    /// The validators read the value from an Ethereum node and verify it against the header
+    /// of that block. This is only available if the committee has configured an Ethereum
+    /// bridge.
+    ///
+    /// Cannot be used in fast blocks: A block using this call should be proposed by a regular
+    /// owner, not a super owner.
+    pub fn ethereum_storage_proof(
+        &mut self,
+        block_number: u64,
+        address: [u8; 20],
+        slot: [u8; 32],
+    ) -> [u8; 32] {
+        contract_wit::ethereum_storage_proof(block_number, address.into(), &slot)
+            .try_into()
+            .expect("Ethereum storage values should be 32 bytes long")
+    }
 }
 
 /// A helper type that uses the builder pattern to configure how a message is sent, and then
@@ -4,28 +4,35 @@
 //! Runtime types to simulate interfacing with the host executing the contract.
 
 use std::{
-    collections::{BTreeMap, HashMap, VecDeque},
+    collections::{BTreeMap, BTreeSet, HashMap, VecDeque},
     sync::{Arc, Mutex, MutexGuard},
 };
 
 use linera_base::{
-    abi::{ContractAbi, ServiceAbi},
+    abi::{ContractAbi, ServiceAbi, ViewAbi},
+    bridge::EthereumStorageKey,
     data_types::{
-        Amount, ApplicationPermissions, BlockHeight, Bytecode, Resources, SendMessageRequest,
-        Timestamp,
+        Amount, ApplicationPermissions, BlockHeight, Bytecode, Epoch, Resources,
+        SendMessageRequest, Timestamp,
     },
     ensure, http,
     identifiers::{
-        Account, AccountOwner, ApplicationId, BlobId, ChainId, MessageId, ModuleId, StreamName,
+        Account, AccountOwner, ApplicationId, BlobId, ChainId, ChannelName, MessageId, ModuleId,
+        StreamName,
     },
     ownership::{
         AccountPermissionError, ChainOwnership, ChangeApplicationPermissionsError, CloseChainError,
     },
     vm::VmRuntime,
 };
-use serde::Serialize;
+use serde::{de::DeserializeOwned, Serialize};
 
-use crate::{Contract, DataBlobHash, KeyValueStore, ViewStorageContext};
+use super::entropy;
+use crate::{
+    typed_blob::{BlobSchema, TypedBlob, TypedBlobError},
+    views::ViewTransaction,
+    Contract, DataBlobHash, KeyValueStore, ViewStorageContext,
+};
 
 struct ExpectedPublishModuleCall {
     contract: Bytecode,
@@ -63,19 +70,28 @@ where
     message_is_bouncing: Option<Option<bool>>,
     authenticated_caller_id: Option<Option<ApplicationId>>,
     timestamp: Option<Timestamp>,
+    epoch: Option<Epoch>,
+    application_config: Option<Vec<u8>>,
     chain_balance: Option<Amount>,
     owner_balances: Option<HashMap<AccountOwner, Amount>>,
     chain_ownership: Option<ChainOwnership>,
     can_close_chain: Option<bool>,
     can_change_application_permissions: Option<bool>,
     call_application_handler: Option<CallApplicationHandler>,
+    try_call_handler: Option<TryCallHandler>,
+    view_call_handler: Option<ViewCallHandler>,
     send_message_requests: Arc<Mutex<Vec<SendMessageRequest<Application::Message>>>>,
     outgoing_transfers: HashMap<Account, Amount>,
     created_events: BTreeMap<StreamName, Vec<Vec<u8>>>,
     events: BTreeMap<(ChainId, StreamName, u32), Vec<u8>>,
+    values_on_chains: BTreeMap<(ChainId, Vec<u8>), Vec<u8>>,
+    published_messages: Vec<(ChannelName, Application::Message)>,
+    channel_subscriptions: BTreeSet<(ChainId, ChannelName)>,
     claim_requests: Vec<ClaimRequest>,
     expected_service_queries: VecDeque<(ApplicationId, String, String)>,
     expected_http_requests: VecDeque<(http::Request, http::Response)>,
+    expected_ethereum_storage_proofs: VecDeque<(EthereumStorageKey, [u8; 32])>,
+    entropy: VecDeque<u8>,
     expected_read_data_blob_requests: VecDeque<(DataBlobHash, Vec<u8>)>,
     expected_assert_data_blob_exists_requests: VecDeque<(DataBlobHash, Option<()>)>,
     expected_open_chain_calls: VecDeque<(ChainOwnership, ApplicationPermissions, Amount, ChainId)>,
@@ -112,19 +128,28 @@ where
             message_is_bouncing: None,
             authenticated_caller_id: None,
             timestamp: None,
+            epoch: None,
+            application_config: None,
             chain_balance: None,
             owner_balances: None,
             chain_ownership: None,
             can_close_chain: None,
             can_change_application_permissions: None,
             call_application_handler: None,
+            try_call_handler: None,
+            view_call_handler: None,
             send_message_requests: Arc::default(),
             outgoing_transfers: HashMap::new(),
             created_events: BTreeMap::new(),
             events: BTreeMap::new(),
+            values_on_chains: BTreeMap::new(),
+            published_messages: Vec::new(),
+            channel_subscriptions: BTreeSet::new(),
             claim_requests: Vec::new(),
             expected_service_queries: VecDeque::new(),
             expected_http_requests: VecDeque::new(),
+            expected_ethereum_storage_proofs: VecDeque::new(),
+            entropy: VecDeque::new(),
             expected_read_data_blob_requests: VecDeque::new(),
             expected_assert_data_blob_exists_requests: VecDeque::new(),
             expected_open_chain_calls: VecDeque::new(),
@@ -145,6 +170,18 @@ where
         ViewStorageContext::new_unsafe(self.key_value_store(), Vec::new(), ())
     }
 
+    /// Returns a storage context for one of several root views of the application, stored
+    /// under `base_key`. The base keys of the different root views must not be prefixes of
+    /// each other.
+    pub fn view_storage_context(&self, base_key: Vec<u8>) -> ViewStorageContext {
+        ViewStorageContext::new_unsafe(self.key_value_store(), base_key, ())
+    }
+
+    /// Returns an empty [`ViewTransaction`], to save several root views atomically.
+    pub fn view_transaction<'a>(&self) -> ViewTransaction<'a> {
+        ViewTransaction::new(self.key_value_store())
+    }
+
     /// Configures the application parameters to return during the test.
     pub fn with_application_parameters(
         mut self,
@@ -400,6 +437,48 @@ where
         )
     }
 
+    /// Configures the epoch to return during the test.
+    pub fn with_epoch(mut self, epoch: Epoch) -> Self {
+        self.epoch = Some(epoch);
+        self
+    }
+
+    /// Configures the epoch to return during the test.
+    pub fn set_epoch(&mut self, epoch: Epoch) -> &mut Self {
+        self.epoch = Some(epoch);
+        self
+    }
+
+    /// Returns the current epoch of the chain.
+    pub fn epoch(&mut self) -> Epoch {
+        self.epoch.expect(
+            "Epoch has not been mocked, \
+            please call `MockContractRuntime::set_epoch` first",
+        )
+    }
+
+    /// Configures the application configuration to return during the test.
+    pub fn with_application_config(mut self, config: &impl Serialize) -> Self {
+        self.set_application_config(config);
+        self
+    }
+
+    /// Configures the application configuration to return during the test.
+    pub fn set_application_config(&mut self, config: &impl Serialize) -> &mut Self {
+        self.application_config = Some(
+            serde_json::to_vec(config).expect("Failed to serialize the application configuration"),
+        );
+        self
+    }
+
+    /// Returns the configuration of this application on the current chain, if the chain
+    /// owners set one.
+    pub fn application_config<Config: DeserializeOwned>(&mut self) -> Option<Config> {
+        self.application_config.as_ref().map(|bytes| {
+            serde_json::from_slice(bytes).expect("Application configuration must be deserializable")
+        })
+    }
+
     /// Configures the chain balance to return during the test.
     pub fn with_chain_balance(mut self, chain_balance: Amount) -> Self {
         self.chain_balance = Some(chain_balance);
@@ -462,6 +541,13 @@ where
         *self.owner_balance_mut(owner)
     }
 
+    /// Returns the balance of the application's own account on this chain, which includes
+    /// the operation fees it collected.
+    pub fn collected_fees(&mut self) -> Amount {
+        let owner = AccountOwner::from(self.application_id().forget_abi());
+        self.owner_balance(owner)
+    }
+
     /// Returns a mutable reference to the balance of one of the accounts on this chain.
     fn owner_balance_mut(&mut self, owner: AccountOwner) -> &mut Amount {
         self.owner_balances
@@ -818,6 +904,13 @@ where
         DataBlobHash(blob_id.hash)
     }
 
+    /// Creates a new data blob holding `value` with the header of its schema, and returns its
+    /// typed hash.
+    pub fn create_typed_data_blob<T: BlobSchema>(&mut self, value: &T) -> TypedBlob<T> {
+        let bytes = TypedBlob::encode(value).expect("Failed to serialize value of a typed blob");
+        TypedBlob::from_hash(self.create_data_blob(bytes))
+    }
+
     /// Configures the handler for cross-application calls made during the test.
     pub fn with_call_application_handler(
         mut self,
@@ -856,6 +949,84 @@ where
             .expect("Failed to deserialize `Response` in test runtime cross-application call")
     }
 
+    /// Configures the handler for speculative calls made during the test.
+    pub fn with_try_call_handler(
+        mut self,
+        handler: impl FnMut(bool, ApplicationId, Vec<u8>) -> Option<Vec<u8>> + 'static,
+    ) -> Self {
+        self.try_call_handler = Some(Box::new(handler));
+        self
+    }
+
+    /// Configures the handler for speculative calls made during the test.
+    pub fn set_try_call_handler(
+        &mut self,
+        handler: impl FnMut(bool, ApplicationId, Vec<u8>) -> Option<Vec<u8>> + 'static,
+    ) -> &mut Self {
+        self.try_call_handler = Some(Box::new(handler));
+        self
+    }
+
+    /// Calls another application speculatively. Returns `None` if the handler reports that
+    /// the call failed.
+    pub fn try_call<A: ContractAbi + Send>(
+        &mut self,
+        authenticated: bool,
+        application: ApplicationId<A>,
+        call: &A::Operation,
+    ) -> Option<A::Response> {
+        let call_bytes = A::serialize_operation(call)
+            .expect("Failed to serialize `Operation` in test runtime speculative call");
+
+        let handler = self.try_call_handler.as_mut().expect(
+            "Handler for `try_call` has not been mocked, \
+            please call `MockContractRuntime::set_try_call_handler` first",
+        );
+        let response_bytes = handler(authenticated, application.forget_abi(), call_bytes)?;
+
+        Some(
+            A::deserialize_response(response_bytes)
+                .expect("Failed to deserialize `Response` in test runtime speculative call"),
+        )
+    }
+
+    /// Configures the handler for view calls made during the test.
+    pub fn with_view_call_handler(
+        mut self,
+        handler: impl Fn(ApplicationId, Vec<u8>) -> Vec<u8> + 'static,
+    ) -> Self {
+        self.view_call_handler = Some(Box::new(handler));
+        self
+    }
+
+    /// Configures the handler for view calls made during the test.
+    pub fn set_view_call_handler(
+        &mut self,
+        handler: impl Fn(ApplicationId, Vec<u8>) -> Vec<u8> + 'static,
+    ) -> &mut Self {
+        self.view_call_handler = Some(Box::new(handler));
+        self
+    }
+
+    /// Calls a read-only view function of another application.
+    pub fn view_call<A: ViewAbi + Send>(
+        &self,
+        application: ApplicationId<A>,
+        call: &A::ViewCall,
+    ) -> A::ViewResponse {
+        let call_bytes =
+            bcs::to_bytes(call).expect("Failed to serialize `ViewCall` in test runtime view call");
+
+        let handler = self.view_call_handler.as_ref().expect(
+            "Handler for `view_call` has not been mocked, \
+            please call `MockContractRuntime::set_view_call_handler` first",
+        );
+        let response_bytes = handler(application.forget_abi(), call_bytes);
+
+        bcs::from_bytes(&response_bytes)
+            .expect("Failed to deserialize `ViewResponse` in test runtime view call")
+    }
+
     /// Adds a new item to an event stream. Returns the new event's index in the stream.
     pub fn emit(&mut self, name: StreamName, value: &Application::EventValue) -> u32 {
         let value = bcs::to_bytes(value).expect("Failed to serialize event value");
@@ -905,6 +1076,33 @@ where
         // This is a no-op in the mock runtime.
     }
 
+    /// Sends a message to this application on every chain currently subscribed to the
+    /// `channel`.
+    pub fn publish_message(&mut self, channel: ChannelName, message: Application::Message) {
+        self.published_messages.push((channel, message));
+    }
+
+    /// Returns the messages published to channels so far during the test.
+    pub fn published_messages(&self) -> &[(ChannelName, Application::Message)] {
+        &self.published_messages
+    }
+
+    /// Subscribes the current chain to a channel of this application on the chain `chain_id`.
+    pub fn subscribe_to_channel(&mut self, chain_id: ChainId, channel: ChannelName) {
+        self.channel_subscriptions.insert((chain_id, channel));
+    }
+
+    /// Unsubscribes the current chain from a channel of this application on the chain
+    /// `chain_id`.
+    pub fn unsubscribe_from_channel(&mut self, chain_id: ChainId, channel: ChannelName) {
+        self.channel_subscriptions.remove(&(chain_id, channel));
+    }
+
+    /// Returns the channels the current chain is subscribed to in the test.
+    pub fn channel_subscriptions(&self) -> &BTreeSet<(ChainId, ChannelName)> {
+        &self.channel_subscriptions
+    }
+
     /// Adds an expected `query_service` call`, and the response it should return in the test.
     pub fn add_expected_service_query<A: ServiceAbi + Send>(
         &mut self,
@@ -923,6 +1121,32 @@ where
         self.expected_http_requests.push_back((request, response));
     }
 
+    /// Adds an expected `ethereum_storage_proof` call, and the value it should return in the
+    /// test.
+    pub fn add_expected_ethereum_storage_proof(
+        &mut self,
+        block_number: u64,
+        address: [u8; 20],
+        slot: [u8; 32],
+        value: [u8; 32],
+    ) {
+        let key = EthereumStorageKey {
+            block_number,
+            address,
+            slot,
+        };
+        self.expected_ethereum_storage_proofs
+            .push_back((key, value));
+    }
+
+    /// Adds bytes of entropy to be drawn by the contract in the test.
+    ///
+    /// [`MockContractRuntime::shuffle`] and [`MockContractRuntime::sample`] draw 8 bytes
+    /// per element.
+    pub fn add_entropy(&mut self, bytes: impl IntoIterator<Item = u8>) {
+        self.entropy.extend(bytes);
+    }
+
     /// Adds an expected `read_data_blob` call, and the response it should return in the test.
     pub fn add_expected_read_data_blob_requests(&mut self, hash: DataBlobHash, response: Vec<u8>) {
         self.expected_read_data_blob_requests
@@ -991,6 +1215,14 @@ where
         response
     }
 
+    /// Reads a typed data blob from storage, checking that it holds a value of type `T`.
+    pub fn read_typed_data_blob<T: BlobSchema>(
+        &mut self,
+        blob: TypedBlob<T>,
+    ) -> Result<T, TypedBlobError> {
+        TypedBlob::decode(&self.read_data_blob(&blob.hash()))
+    }
+
     /// Asserts that a blob with the given hash exists in storage.
     pub fn assert_data_blob_exists(&mut self, hash: DataBlobHash) {
         let maybe_request = self.expected_assert_data_blob_exists_requests.pop_front();
@@ -1000,15 +1232,100 @@ where
         response.expect("Blob does not exist!");
     }
 
+    /// Sets a value of this application's state on another chain, so that it can be read
+    /// using `read_value_bytes_on_chain`.
+    pub fn set_value_on_chain(&mut self, chain_id: ChainId, key: &[u8], value: &[u8]) {
+        self.values_on_chains
+            .insert((chain_id, key.to_vec()), value.to_vec());
+    }
+
+    /// Reads the value under `key` in the state of this application on another chain.
+    pub fn read_value_bytes_on_chain(&mut self, chain_id: ChainId, key: &[u8]) -> Option<Vec<u8>> {
+        self.values_on_chains
+            .get(&(chain_id, key.to_vec()))
+            .cloned()
+    }
+
     /// Returns the round in which this block was validated.
     pub fn validation_round(&mut self) -> Option<u32> {
         self.round
     }
+
+    /// Returns the next `num_bytes` bytes of the deterministic entropy of the current
+    /// transaction.
+    ///
+    /// The entropy only depends on the chain, the block height and the index of the
+    /// transaction, so it is not secret: the block proposer can predict it. The number of
+    /// bytes a transaction can draw is limited by the resource control policy.
+    ///
+    /// In tests, the bytes are taken from the ones added with
+    /// [`MockContractRuntime::add_entropy`].
+    pub fn draw_entropy(&mut self, num_bytes: u32) -> Vec<u8> {
+        let num_bytes = num_bytes as usize;
+        assert!(
+            self.entropy.len() >= num_bytes,
+            "Not enough entropy has been mocked, \
+            please call `MockContractRuntime::add_entropy` first"
+        );
+        self.entropy.drain(..num_bytes).collect()
+    }
+
+    /// Shuffles `slice` uniformly, using the entropy of the current transaction.
+    ///
+    /// This draws 8 bytes of entropy per element, in a single call to the runtime.
+    pub fn shuffle<T>(&mut self, slice: &mut [T]) {
+        entropy::shuffle(slice, |num_bytes| self.draw_entropy(num_bytes))
+    }
+
+    /// Returns `count` distinct indices sampled uniformly from `0..population`, in random
+    /// order, using the entropy of the current transaction.
+    ///
+    /// This draws 8 bytes of entropy per sampled index, in a single call to the runtime.
+    ///
+    /// # Panics
+    ///
+    /// If `count` is larger than `population`.
+    pub fn sample(&mut self, population: usize, count: usize) -> Vec<usize> {
+        entropy::sample(population, count, |num_bytes| self.draw_entropy(num_bytes))
+    }
+
+    /// Returns the value of the storage `slot` of the Ethereum contract at `address`, as of
+    /// the Ethereum block `block_number`.
+    ///
+    /// The validators read the value from an Ethereum node and verify it against the header
+    /// of that block. This is only available if the committee has configured an Ethereum
+    /// bridge.
+    ///
+    /// Cannot be used in fast blocks: A block using this call should be proposed by a regular
+    /// owner, not a super owner.
+    pub fn ethereum_storage_proof(
+        &mut self,
+        block_number: u64,
+        address: [u8; 20],
+        slot: [u8; 32],
+    ) -> [u8; 32] {
+        let maybe_request = self.expected_ethereum_storage_proofs.pop_front();
+        let (expected_key, value) =
+            maybe_request.expect("Unexpected ethereum_storage_proof request");
+        let key = EthereumStorageKey {
+            block_number,
+            address,
+            slot,
+        };
+        assert_eq!(key, expected_key);
+        value
+    }
 }
 
 /// A type alias for the handler for cross-application calls.
 pub type CallApplicationHandler = Box<dyn FnMut(bool, ApplicationId, Vec<u8>) -> Vec<u8>>;
 
+/// A type alias for the handler for speculative calls.
+pub type TryCallHandler = Box<dyn FnMut(bool, ApplicationId, Vec<u8>) -> Option<Vec<u8>>>;
+
+/// A type alias for the handler for view calls.
+pub type ViewCallHandler = Box<dyn Fn(ApplicationId, Vec<u8>) -> Vec<u8>>;
+
 /// A helper type that uses the builder pattern to configure how a message is sent, and then
 /// sends the message once it is dropped.
 #[must_use]
@@ -6,6 +6,11 @@
 use std::sync::Arc;
 
 /// Re-exports the derive macro for [`GraphQLMutationRoot`].
+///
+/// Deriving it for an operation enum generates a mutation for each variant, with the
+/// variant's fields as arguments, that schedules the operation in the service runtime. The
+/// doc comments of the variants become the descriptions of the mutations, and variants
+/// marked with `#[mutation(skip)]` are left out.
 pub use linera_sdk_derive::GraphQLMutationRoot;
 
 use crate::{Service, ServiceRuntime};
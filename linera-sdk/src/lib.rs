@@ -41,6 +41,7 @@ mod log;
 pub mod service;
 #[cfg(with_testing)]
 pub mod test;
+pub mod typed_blob;
 pub mod views;
 
 use std::fmt::Debug;
@@ -48,11 +49,11 @@ use std::fmt::Debug;
 pub use bcs;
 pub use linera_base::{
     abi,
-    data_types::{Resources, SendMessageRequest},
+    data_types::{ReentrancyPolicy, Resources, SendMessageRequest},
     ensure, http,
 };
 use linera_base::{
-    abi::{ContractAbi, ServiceAbi, WithContractAbi, WithServiceAbi},
+    abi::{ContractAbi, ServiceAbi, ViewAbi, WithContractAbi, WithServiceAbi},
     crypto::CryptoHash,
     data_types::StreamUpdate,
     doc_scalar,
@@ -67,9 +68,16 @@ pub use self::{
     extensions::{FromBcsBytes, ToBcsBytes},
     log::{ContractLogger, ServiceLogger},
     service::ServiceRuntime,
+    typed_blob::{BlobEncoding, BlobSchema, TypedBlob, TypedBlobError},
     views::{KeyValueStore, ViewStorageContext},
 };
 
+/// The contents of the custom section through which the [`contract!`] and [`service!`] macros
+/// declare the version of the Wasm API the application is built against.
+#[doc(hidden)]
+pub const API_VERSION_SECTION: [u8; 4] =
+    linera_base::vm::WasmApiVersion::CURRENT.to_custom_section();
+
 /// Hash of a data blob.
 #[derive(Eq, Hash, PartialEq, Debug, Serialize, Deserialize, Clone, Copy)]
 pub struct DataBlobHash(pub CryptoHash);
@@ -93,6 +101,11 @@ pub trait Contract: WithContractAbi + ContractAbi + Sized {
     type Message: Serialize + DeserializeOwned + Debug;
 
     /// Immutable parameters specific to this application (e.g. the name of a token).
+    ///
+    /// If the parameters contain an `operation_fees` field of type
+    /// [`OperationFees`](linera_base::data_types::OperationFees), the fee of each operation
+    /// is transferred from the signer's account to the application's account before the
+    /// operation is executed, and the operation is rejected if the signer cannot pay.
     type Parameters: Serialize + DeserializeOwned + Clone + Debug;
 
     /// Instantiation argument passed to a new application on the chain that created it
@@ -105,6 +118,14 @@ pub trait Contract: WithContractAbi + ContractAbi + Sized {
     /// Event values for streams created by this application.
     type EventValue: Serialize + DeserializeOwned + Debug;
 
+    /// Whether the application accepts view calls while one of its own calls is still
+    /// running, e.g. when an application it called calls it back.
+    ///
+    /// Reentrant calls are rejected by default. If they are allowed, they must be view calls
+    /// (see [`ContractViews`]), and they see the state that the application last stored
+    /// rather than the one of the calls that are still running.
+    const REENTRANCY_POLICY: ReentrancyPolicy = ReentrancyPolicy::Deny;
+
     /// Creates an in-memory instance of the contract handler.
     async fn load(runtime: ContractRuntime<Self>) -> Self;
 
@@ -139,6 +160,32 @@ pub trait Contract: WithContractAbi + ContractAbi + Sized {
     /// subscribes to.
     async fn process_streams(&mut self, _updates: Vec<StreamUpdate>) {}
 
+    /// Reacts to the closure of the chain.
+    ///
+    /// This is called once, in the transaction that closes the chain, for every application
+    /// with a state on this chain. It allows the application to release the assets it holds
+    /// here, e.g. by refunding escrowed tokens to their owners.
+    async fn on_chain_close(&mut self) {}
+
+    /// Reacts to an upgrade of the application's code on the current chain.
+    ///
+    /// This is called on the new code, in the transaction of the
+    /// `SystemOperation::UpgradeApplication` operation, so that the application can migrate
+    /// the state written by its previous code. The upgrade fails if this panics.
+    async fn on_upgrade(&mut self) {}
+
+    /// Decides whether the authenticated signer may propose the current block, although it
+    /// is not an owner of the chain.
+    ///
+    /// This is only called on the chain's authorization application, set with
+    /// `SystemOperation::ChangeAuthorizationApplication`, when a block starts with
+    /// `SystemOperation::AuthorizeProposal`. The `payload` is the one carried by that
+    /// operation, e.g. signatures of a multisig or a social recovery proof. Returning `false`
+    /// rejects the block.
+    async fn authorize_proposal(&mut self, _payload: Vec<u8>) -> bool {
+        false
+    }
+
     /// Finishes the execution of the current transaction.
     ///
     /// This is called once at the end of the transaction, to allow all applications that
@@ -149,6 +196,19 @@ pub trait Contract: WithContractAbi + ContractAbi + Sized {
     async fn store(self);
 }
 
+/// The read-only view functions that a [`Contract`] exposes to other applications.
+///
+/// Other applications call them with [`ContractRuntime::view_call`]. The runtime rejects any
+/// attempt to modify the chain state while a view function runs, and a view function may only
+/// call other view functions. In return, view functions are charged only half the fuel they
+/// consume. The contract must be exported with
+/// `contract!(MyContract with_views)` for its view functions to be callable.
+#[allow(async_fn_in_trait)]
+pub trait ContractViews: Contract + ViewAbi {
+    /// Runs a view function on the current state of the application.
+    async fn execute_view(&self, call: Self::ViewCall) -> Self::ViewResponse;
+}
+
 /// The service interface of a Linera application.
 ///
 /// As opposed to the [`Contract`] interface of an application, service entry points
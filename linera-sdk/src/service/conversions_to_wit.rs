@@ -3,7 +3,11 @@
 
 //! Conversions from types declared in [`linera-sdk`] to types generated by [`wit-bindgen`].
 
-use linera_base::{crypto::CryptoHash, identifiers::ApplicationId};
+use linera_base::{
+    crypto::CryptoHash,
+    data_types::{BlockHeight, OperationMetadata},
+    identifiers::ApplicationId,
+};
 
 use super::wit::service_runtime_api as wit_service_api;
 
@@ -27,3 +31,21 @@ impl From<ApplicationId> for wit_service_api::ApplicationId {
         }
     }
 }
+
+impl From<BlockHeight> for wit_service_api::BlockHeight {
+    fn from(block_height: BlockHeight) -> Self {
+        wit_service_api::BlockHeight {
+            inner0: block_height.0,
+        }
+    }
+}
+
+impl From<OperationMetadata> for wit_service_api::OperationMetadata {
+    fn from(metadata: OperationMetadata) -> Self {
+        wit_service_api::OperationMetadata {
+            title: metadata.title,
+            description: metadata.description,
+            expected_effects: metadata.expected_effects,
+        }
+    }
+}
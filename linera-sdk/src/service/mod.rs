@@ -53,6 +53,12 @@ macro_rules! service {
             }
         }
 
+        /// Declares the version of the Wasm API the service is built against.
+        #[cfg(target_arch = "wasm32")]
+        #[link_section = "linera:api-version"]
+        #[used]
+        static LINERA_API_VERSION: [u8; 4] = $crate::API_VERSION_SECTION;
+
         /// Stub of a `main` entrypoint so that the binary doesn't fail to compile on targets other
         /// than WebAssembly.
         #[cfg(not(target_arch = "wasm32"))]
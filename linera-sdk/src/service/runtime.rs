@@ -7,14 +7,17 @@ use std::sync::Mutex;
 
 use linera_base::{
     abi::ServiceAbi,
-    data_types::{Amount, BlockHeight, Timestamp},
+    data_types::{Amount, BlockHeight, OperationMetadata, Timestamp},
     http,
     identifiers::{AccountOwner, ApplicationId, ChainId},
 };
-use serde::Serialize;
+use serde::{de::DeserializeOwned, Serialize};
 
 use super::wit::{base_runtime_api as base_wit, service_runtime_api as service_wit};
-use crate::{DataBlobHash, KeyValueStore, Service, ViewStorageContext};
+use crate::{
+    typed_blob::{BlobSchema, TypedBlob, TypedBlobError},
+    DataBlobHash, KeyValueStore, Service, ViewStorageContext,
+};
 
 /// The runtime available during execution of a query.
 pub struct ServiceRuntime<Application>
@@ -72,6 +75,15 @@ where
         })
     }
 
+    /// Returns the configuration of this application on the current chain, if the chain
+    /// owners set one.
+    pub fn application_config<Config: DeserializeOwned>(&self) -> Option<Config> {
+        base_wit::read_application_config().map(|bytes| {
+            serde_json::from_slice(&bytes)
+                .expect("Application configuration must be deserializable")
+        })
+    }
+
     /// Returns the ID of the current application.
     pub fn application_id(&self) -> ApplicationId<Application::Abi> {
         Self::fetch_value_through_cache(&self.application_id, || {
@@ -146,10 +158,25 @@ where
         base_wit::read_data_blob(hash.0.into())
     }
 
+    /// Reads a typed data blob from storage, checking that it holds a value of type `T`.
+    pub fn read_typed_data_blob<T: BlobSchema>(
+        &self,
+        blob: TypedBlob<T>,
+    ) -> Result<T, TypedBlobError> {
+        TypedBlob::decode(&self.read_data_blob(blob.hash()))
+    }
+
     /// Asserts that a data blob with the given hash exists in storage.
     pub fn assert_data_blob_exists(&self, hash: DataBlobHash) {
         base_wit::assert_data_blob_exists(hash.0.into())
     }
+
+    /// Reads the value under `key` in the state of this application on another chain, as
+    /// stored by the validator answering the query. Returns `None` if there is no such
+    /// value, or if the validator doesn't know the chain.
+    pub fn read_value_bytes_on_chain(&self, chain_id: ChainId, key: &[u8]) -> Option<Vec<u8>> {
+        base_wit::read_value_bytes_on_chain(chain_id.into(), key)
+    }
 }
 
 impl<Application> ServiceRuntime<Application>
@@ -172,6 +199,20 @@ where
         service_wit::schedule_operation(&bytes);
     }
 
+    /// Schedules an operation to be included in the block being built, with a description
+    /// that wallets can show to the user before proposing the block.
+    ///
+    /// The operation is serialized using BCS.
+    pub fn schedule_operation_with_metadata(
+        &self,
+        operation: &impl Serialize,
+        metadata: OperationMetadata,
+    ) {
+        let bytes = bcs::to_bytes(operation).expect("Failed to serialize application operation");
+
+        service_wit::schedule_operation_with_metadata(&bytes, &metadata.into());
+    }
+
     /// Queries another application.
     pub fn query_application<A: ServiceAbi>(
         &self,
@@ -187,6 +228,32 @@ where
         serde_json::from_slice(&response_bytes)
             .expect("Failed to deserialize query response from application")
     }
+
+    /// Queries an application, e.g. this one, as it was right after the block at `height` of
+    /// this chain.
+    ///
+    /// Returns `None` if the node answering the query doesn't retain the state at that height
+    /// anymore: nodes only keep the history of a configurable number of recent blocks.
+    pub fn query_at<A: ServiceAbi>(
+        &self,
+        application: ApplicationId<A>,
+        height: BlockHeight,
+        query: &A::Query,
+    ) -> Option<A::QueryResponse> {
+        let query_bytes =
+            serde_json::to_vec(&query).expect("Failed to serialize query to application");
+
+        let response_bytes = service_wit::query_application_at(
+            application.forget_abi().into(),
+            height.into(),
+            &query_bytes,
+        )?;
+
+        Some(
+            serde_json::from_slice(&response_bytes)
+                .expect("Failed to deserialize query response from application"),
+        )
+    }
 }
 
 impl<Application> ServiceRuntime<Application>
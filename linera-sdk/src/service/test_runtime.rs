@@ -11,13 +11,16 @@ use std::{
 
 use linera_base::{
     abi::ServiceAbi,
-    data_types::{Amount, BlockHeight, Timestamp},
+    data_types::{Amount, BlockHeight, OperationMetadata, Timestamp},
     hex, http,
     identifiers::{AccountOwner, ApplicationId, ChainId},
 };
 use serde::{de::DeserializeOwned, Serialize};
 
-use crate::{DataBlobHash, KeyValueStore, Service, ViewStorageContext};
+use crate::{
+    typed_blob::{BlobSchema, TypedBlob, TypedBlobError},
+    DataBlobHash, KeyValueStore, Service, ViewStorageContext,
+};
 
 /// The runtime available during execution of a query.
 pub struct MockServiceRuntime<Application>
@@ -26,15 +29,19 @@ where
 {
     application_parameters: Mutex<Option<Application::Parameters>>,
     application_id: Mutex<Option<ApplicationId<Application::Abi>>>,
+    application_config: Mutex<Option<Vec<u8>>>,
     chain_id: Mutex<Option<ChainId>>,
     next_block_height: Mutex<Option<BlockHeight>>,
     timestamp: Mutex<Option<Timestamp>>,
     chain_balance: Mutex<Option<Amount>>,
     owner_balances: Mutex<Option<HashMap<AccountOwner, Amount>>>,
     query_application_handler: Mutex<Option<QueryApplicationHandler>>,
+    query_at_handler: Mutex<Option<QueryAtHandler>>,
     expected_http_requests: Mutex<VecDeque<(http::Request, http::Response)>>,
     blobs: Mutex<Option<HashMap<DataBlobHash, Vec<u8>>>>,
+    values_on_chains: Mutex<HashMap<(ChainId, Vec<u8>), Vec<u8>>>,
     scheduled_operations: Mutex<Vec<Vec<u8>>>,
+    scheduled_operation_metadata: Mutex<Vec<Option<OperationMetadata>>>,
     key_value_store: KeyValueStore,
 }
 
@@ -56,15 +63,19 @@ where
         MockServiceRuntime {
             application_parameters: Mutex::new(None),
             application_id: Mutex::new(None),
+            application_config: Mutex::new(None),
             chain_id: Mutex::new(None),
             next_block_height: Mutex::new(None),
             timestamp: Mutex::new(None),
             chain_balance: Mutex::new(None),
             owner_balances: Mutex::new(None),
             query_application_handler: Mutex::new(None),
+            query_at_handler: Mutex::new(None),
             expected_http_requests: Mutex::new(VecDeque::new()),
             blobs: Mutex::new(None),
+            values_on_chains: Mutex::new(HashMap::new()),
             scheduled_operations: Mutex::new(vec![]),
+            scheduled_operation_metadata: Mutex::new(vec![]),
             key_value_store: KeyValueStore::mock(),
         }
     }
@@ -106,6 +117,33 @@ where
         )
     }
 
+    /// Configures the application configuration to return during the test.
+    pub fn with_application_config(self, config: &impl Serialize) -> Self {
+        self.set_application_config(config);
+        self
+    }
+
+    /// Configures the application configuration to return during the test.
+    pub fn set_application_config(&self, config: &impl Serialize) -> &Self {
+        *self.application_config.lock().unwrap() = Some(
+            serde_json::to_vec(config).expect("Failed to serialize the application configuration"),
+        );
+        self
+    }
+
+    /// Returns the configuration of this application on the current chain, if the chain
+    /// owners set one.
+    pub fn application_config<Config: DeserializeOwned>(&self) -> Option<Config> {
+        self.application_config
+            .lock()
+            .unwrap()
+            .as_ref()
+            .map(|bytes| {
+                serde_json::from_slice(bytes)
+                    .expect("Application configuration must be deserializable")
+            })
+    }
+
     /// Configures the application ID to return during the test.
     pub fn with_application_id(self, application_id: ApplicationId<Application::Abi>) -> Self {
         *self.application_id.lock().unwrap() = Some(application_id);
@@ -296,6 +334,7 @@ where
     /// The operation is specified as an opaque blob of bytes.
     pub fn schedule_raw_operation(&self, operation: Vec<u8>) {
         self.scheduled_operations.lock().unwrap().push(operation);
+        self.scheduled_operation_metadata.lock().unwrap().push(None);
     }
 
     /// Schedules an operation to be included in the block being built.
@@ -307,6 +346,33 @@ where
         self.schedule_raw_operation(bytes);
     }
 
+    /// Schedules an operation to be included in the block being built, with a description
+    /// to show to the user.
+    ///
+    /// The operation is serialized using BCS.
+    pub fn schedule_operation_with_metadata(
+        &self,
+        operation: &impl Serialize,
+        metadata: OperationMetadata,
+    ) {
+        let bytes = bcs::to_bytes(operation).expect("Failed to serialize application operation");
+
+        self.scheduled_operations.lock().unwrap().push(bytes);
+        self.scheduled_operation_metadata
+            .lock()
+            .unwrap()
+            .push(Some(metadata));
+    }
+
+    /// Returns the metadata of the operations scheduled since the most recent call to this
+    /// method or since the mock runtime was created, in the order the operations were
+    /// scheduled.
+    ///
+    /// Operations scheduled without metadata have a `None` entry.
+    pub fn scheduled_operation_metadata(&self) -> Vec<Option<OperationMetadata>> {
+        mem::take(&mut self.scheduled_operation_metadata.lock().unwrap())
+    }
+
     /// Returns the list of operations scheduled since the most recent of:
     ///
     /// - the last call to this method;
@@ -380,6 +446,49 @@ where
             .expect("Failed to deserialize query response from application")
     }
 
+    /// Configures the handler for historical application queries made during the test.
+    pub fn with_query_at_handler<Handler>(self, handler: Handler) -> Self
+    where
+        Handler: FnMut(ApplicationId, BlockHeight, Vec<u8>) -> Option<Vec<u8>> + Send + 'static,
+    {
+        *self.query_at_handler.lock().unwrap() = Some(Box::new(handler));
+        self
+    }
+
+    /// Configures the handler for historical application queries made during the test.
+    pub fn set_query_at_handler<Handler>(&self, handler: Handler) -> &Self
+    where
+        Handler: FnMut(ApplicationId, BlockHeight, Vec<u8>) -> Option<Vec<u8>> + Send + 'static,
+    {
+        *self.query_at_handler.lock().unwrap() = Some(Box::new(handler));
+        self
+    }
+
+    /// Queries an application as it was right after the block at `height` of this chain.
+    /// Returns `None` if that state is not retained anymore.
+    pub fn query_at<A: ServiceAbi>(
+        &self,
+        application: ApplicationId<A>,
+        height: BlockHeight,
+        query: &A::Query,
+    ) -> Option<A::QueryResponse> {
+        let query_bytes =
+            serde_json::to_vec(&query).expect("Failed to serialize query to application");
+
+        let mut handler_guard = self.query_at_handler.lock().unwrap();
+        let handler = handler_guard.as_mut().expect(
+            "Handler for `query_at` has not been mocked, \
+            please call `MockServiceRuntime::set_query_at_handler` first",
+        );
+
+        let response_bytes = handler(application.forget_abi(), height, query_bytes)?;
+
+        Some(
+            serde_json::from_slice(&response_bytes)
+                .expect("Failed to deserialize query response from application"),
+        )
+    }
+
     /// Adds an expected `http_request` call, and the response it should return in the test.
     pub fn add_expected_http_request(&mut self, request: http::Request, response: http::Response) {
         self.expected_http_requests
@@ -445,6 +554,14 @@ where
             })
     }
 
+    /// Fetches a typed blob, checking that it holds a value of type `T`.
+    pub fn read_typed_data_blob<T: BlobSchema>(
+        &self,
+        blob: TypedBlob<T>,
+    ) -> Result<T, TypedBlobError> {
+        TypedBlob::decode(&self.read_data_blob(blob.hash()))
+    }
+
     /// Asserts that a blob with the given hash exists in storage.
     pub fn assert_blob_exists(&self, hash: DataBlobHash) {
         self.blobs
@@ -460,6 +577,32 @@ where
             });
     }
 
+    /// Configures a value of this application's state on another chain, returned by
+    /// `read_value_bytes_on_chain` during the test.
+    pub fn with_value_on_chain(self, chain_id: ChainId, key: &[u8], value: &[u8]) -> Self {
+        self.set_value_on_chain(chain_id, key, value);
+        self
+    }
+
+    /// Configures a value of this application's state on another chain, returned by
+    /// `read_value_bytes_on_chain` during the test.
+    pub fn set_value_on_chain(&self, chain_id: ChainId, key: &[u8], value: &[u8]) -> &Self {
+        self.values_on_chains
+            .lock()
+            .unwrap()
+            .insert((chain_id, key.to_vec()), value.to_vec());
+        self
+    }
+
+    /// Reads the value under `key` in the state of this application on another chain.
+    pub fn read_value_bytes_on_chain(&self, chain_id: ChainId, key: &[u8]) -> Option<Vec<u8>> {
+        self.values_on_chains
+            .lock()
+            .unwrap()
+            .get(&(chain_id, key.to_vec()))
+            .cloned()
+    }
+
     /// Loads a mocked value from the `slot` cache or panics with a provided `message`.
     fn fetch_mocked_value<T>(slot: &Mutex<Option<T>>, message: &str) -> T
     where
@@ -471,3 +614,7 @@ where
 
 /// A type alias for the handler for application queries.
 pub type QueryApplicationHandler = Box<dyn FnMut(ApplicationId, Vec<u8>) -> Vec<u8> + Send>;
+
+/// A type alias for the handler for historical application queries.
+pub type QueryAtHandler =
+    Box<dyn FnMut(ApplicationId, BlockHeight, Vec<u8>) -> Option<Vec<u8>> + Send>;
@@ -17,8 +17,7 @@ use futures::future;
 use linera_base::{
     crypto::{AccountPublicKey, AccountSecretKey},
     data_types::{
-        Amount, ApplicationDescription, Blob, BlockHeight, Bytecode, ChainDescription,
-        CompressedBytecode, Epoch,
+        Amount, ApplicationDescription, Blob, BlockHeight, Bytecode, ChainDescription, Epoch,
     },
     identifiers::{AccountOwner, ApplicationId, ChainId, ModuleId},
     vm::VmRuntime,
@@ -382,11 +381,11 @@ impl ActiveChain {
         &self,
         repository_path: impl AsRef<Path>,
     ) -> ModuleId<Abi, Parameters, InstantiationArgument> {
-        let repository_path = fs::canonicalize(repository_path)
-            .await
-            .expect("Failed to obtain absolute application repository path");
-        Self::build_bytecode_files_in(&repository_path).await;
-        let (contract, service) = self.find_bytecode_files_in(&repository_path).await;
+        let (contract, service) = Self::build_bytecode_files_in(repository_path).await;
+        let (contract, service) =
+            tokio::task::spawn_blocking(move || (contract.compress(), service.compress()))
+                .await
+                .expect("Failed to compress bytecode files");
         let contract_blob = Blob::new_contract_bytecode(contract);
         let service_blob = Blob::new_service_bytecode(service);
         let contract_blob_hash = contract_blob.id().hash;
@@ -411,11 +410,19 @@ impl ActiveChain {
         module_id.with_abi()
     }
 
-    /// Compiles the crate in the `repository` path.
-    async fn build_bytecode_files_in(repository: &Path) {
+    /// Compiles the crate in the `repository_path` for WebAssembly.
+    ///
+    /// Returns a tuple with the contract and service [`Bytecode`]s, loaded from the binaries
+    /// whose names end with `contract` and `service`.
+    pub(super) async fn build_bytecode_files_in(
+        repository_path: impl AsRef<Path>,
+    ) -> (Bytecode, Bytecode) {
+        let repository = fs::canonicalize(repository_path)
+            .await
+            .expect("Failed to obtain absolute application repository path");
         let output = std::process::Command::new("cargo")
             .args(["build", "--release", "--target", "wasm32-unknown-unknown"])
-            .current_dir(repository)
+            .current_dir(&repository)
             .output()
             .expect("Failed to build Wasm binaries");
 
@@ -426,17 +433,15 @@ impl ActiveChain {
                 String::from_utf8_lossy(&output.stderr)
             );
         }
+
+        Self::find_bytecode_files_in(&repository).await
     }
 
     /// Searches the Cargo manifest of the crate calling this method for binaries to use as the
     /// contract and service bytecode files.
     ///
-    /// Returns a tuple with the loaded contract and service [`CompressedBytecode`]s,
-    /// ready to be published.
-    async fn find_bytecode_files_in(
-        &self,
-        repository: &Path,
-    ) -> (CompressedBytecode, CompressedBytecode) {
+    /// Returns a tuple with the loaded contract and service [`Bytecode`]s.
+    async fn find_bytecode_files_in(repository: &Path) -> (Bytecode, Bytecode) {
         let manifest_path = repository.join("Cargo.toml");
         let cargo_manifest =
             Manifest::from_path(manifest_path).expect("Failed to load Cargo.toml manifest");
@@ -461,8 +466,7 @@ impl ActiveChain {
             (&binaries[1], &binaries[0])
         };
 
-        let base_path = self
-            .find_output_directory_of(repository)
+        let base_path = Self::find_output_directory_of(repository)
             .await
             .expect("Failed to look for output binaries");
         let contract_path = base_path.join(format!("{}.wasm", contract_binary));
@@ -475,9 +479,7 @@ impl ActiveChain {
             .await
             .expect("Failed to load service bytecode from file");
 
-        tokio::task::spawn_blocking(move || (contract.compress(), service.compress()))
-            .await
-            .expect("Failed to compress bytecode files")
+        (contract, service)
     }
 
     /// Searches for the directory where the built WebAssembly binaries should be.
@@ -486,7 +488,7 @@ impl ActiveChain {
     /// `target/wasm32-unknown-unknown/release` sub-directory. However, since the crate with the
     /// binaries could be part of a workspace, that output sub-directory must be searched in parent
     /// directories as well.
-    async fn find_output_directory_of(&self, repository: &Path) -> Result<PathBuf, io::Error> {
+    async fn find_output_directory_of(repository: &Path) -> Result<PathBuf, io::Error> {
         let output_sub_directory = Path::new("target/wasm32-unknown-unknown/release");
         let mut current_directory = repository;
         let mut output_path = current_directory.join(output_sub_directory);
@@ -613,6 +615,7 @@ impl ActiveChain {
         let QueryOutcome {
             response,
             operations,
+            operation_metadata,
         } = self
             .validator
             .worker()
@@ -637,6 +640,7 @@ impl ActiveChain {
         Ok(QueryOutcome {
             response: deserialized_response,
             operations,
+            operation_metadata,
         })
     }
 
@@ -674,6 +678,7 @@ impl ActiveChain {
         let QueryOutcome {
             response,
             operations,
+            operation_metadata,
         } = self.try_query(application_id, query).await?;
 
         if !response.errors.is_empty() {
@@ -684,6 +689,7 @@ impl ActiveChain {
         Ok(QueryOutcome {
             response: json_response,
             operations,
+            operation_metadata,
         })
     }
 
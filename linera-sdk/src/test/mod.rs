@@ -16,14 +16,20 @@ mod block;
 mod chain;
 mod mock_stubs;
 #[cfg(with_integration_testing)]
+mod network;
+#[cfg(with_integration_testing)]
 mod validator;
 
+use linera_base::data_types::Blob;
 #[cfg(with_integration_testing)]
 pub use {
     linera_chain::{
         data_types::MessageAction, test::HttpServer, ChainError, ChainExecutionContext,
     },
-    linera_core::worker::WorkerError,
+    linera_core::{
+        test_utils::{ChainClient, FaultType, MemoryStorageBuilder, StorageBuilder},
+        worker::WorkerError,
+    },
     linera_execution::{system::Recipient, ExecutionError, QueryOutcome, WasmExecutionError},
 };
 
@@ -33,9 +39,13 @@ pub use self::mock_stubs::*;
 pub use self::{
     block::BlockBuilder,
     chain::{ActiveChain, TryGraphQLMutationError, TryGraphQLQueryError, TryQueryError},
+    network::TestNetwork,
     validator::TestValidator,
 };
-use crate::{Contract, ContractRuntime, Service, ServiceRuntime};
+use crate::{
+    typed_blob::{BlobSchema, TypedBlob},
+    Contract, ContractRuntime, DataBlobHash, Service, ServiceRuntime,
+};
 
 /// Creates a [`ContractRuntime`] to use in tests.
 pub fn test_contract_runtime<Application: Contract>() -> ContractRuntime<Application> {
@@ -46,3 +56,13 @@ pub fn test_contract_runtime<Application: Contract>() -> ContractRuntime<Applica
 pub fn test_service_runtime<Application: Service>() -> ServiceRuntime<Application> {
     ServiceRuntime::new()
 }
+
+/// Creates a data blob holding `value` with the header of its schema, and returns it with its
+/// typed hash.
+///
+/// The blob can be published on a test chain, or its bytes handed to a mock runtime.
+pub fn typed_data_blob<T: BlobSchema>(value: &T) -> (TypedBlob<T>, Blob) {
+    let bytes = TypedBlob::encode(value).expect("Failed to serialize value of a typed blob");
+    let blob = Blob::new_data(bytes);
+    (TypedBlob::from_hash(DataBlobHash(blob.id().hash)), blob)
+}
@@ -0,0 +1,183 @@
+// Copyright (c) Zefchain Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! A network of several validators suited for tests.
+//!
+//! Unlike the [`TestValidator`](super::TestValidator), the [`TestNetwork`] runs a committee of
+//! several in-process validators, each with its own storage. Microchains are driven by the same
+//! chain clients as in a real deployment, so blocks are only confirmed by a quorum of
+//! validators. Individual validators can be stopped or made faulty, to test how applications
+//! behave when only a quorum is available and when clients have to retry.
+
+use std::path::Path;
+
+use linera_base::{
+    crypto::InMemorySigner,
+    data_types::{Amount, BlockHeight},
+    identifiers::{ChainId, ModuleId},
+    vm::VmRuntime,
+};
+use linera_core::{
+    data_types::ChainInfoQuery,
+    node::ValidatorNode as _,
+    test_utils::{ChainClient, FaultType, MemoryStorageBuilder, StorageBuilder, TestBuilder},
+};
+use linera_execution::WasmRuntime;
+
+use super::ActiveChain;
+
+/// A network of several validators suited for tests.
+///
+/// ```rust
+/// # use linera_sdk::test::*;
+/// # use linera_base::{
+/// #     data_types::{Amount, BlockHeight},
+/// #     identifiers::{Account, AccountOwner},
+/// # };
+/// # tokio_test::block_on(async {
+/// let mut network = TestNetwork::new(4).await;
+/// let chain = network.new_chain().await;
+///
+/// // Three out of four validators are still a quorum.
+/// network.stop_validator(3).await;
+/// let recipient = Account::chain(network.admin_chain().chain_id());
+/// chain
+///     .transfer_to_account(AccountOwner::CHAIN, Amount::ONE, recipient)
+///     .await
+///     .unwrap()
+///     .unwrap();
+///
+/// network.restart_validator(3).await;
+/// assert_eq!(
+///     network
+///         .validator_next_block_height(3, chain.chain_id())
+///         .await,
+///     Some(BlockHeight::ZERO)
+/// );
+/// # });
+/// ```
+pub struct TestNetwork<B: StorageBuilder = MemoryStorageBuilder> {
+    builder: TestBuilder<B>,
+    admin_chain: ChainClient<B::Storage>,
+    next_root_chain_index: u32,
+}
+
+impl TestNetwork {
+    /// Creates a new [`TestNetwork`] with the given number of honest validators, each with its
+    /// own in-memory storage.
+    pub async fn new(validators: usize) -> Self {
+        let storage_builder = MemoryStorageBuilder::with_wasm_runtime(WasmRuntime::default());
+        TestNetwork::with_storage_builder(storage_builder, validators).await
+    }
+}
+
+impl<B: StorageBuilder> TestNetwork<B> {
+    /// Creates a new [`TestNetwork`] with the given number of honest validators, whose storages
+    /// are created by the `storage_builder`. The chain clients' storages are created by the
+    /// same builder.
+    pub async fn with_storage_builder(storage_builder: B, validators: usize) -> Self {
+        let mut builder =
+            TestBuilder::new(storage_builder, validators, 0, InMemorySigner::new(None))
+                .await
+                .expect("Failed to create the test validators");
+        let admin_chain = builder
+            .add_root_chain(0, Amount::from_tokens(1_000_000))
+            .await
+            .expect("Failed to create the admin chain");
+        TestNetwork {
+            builder,
+            admin_chain,
+            next_root_chain_index: 1,
+        }
+    }
+
+    /// Returns the number of validators in the committee.
+    pub fn validator_count(&self) -> usize {
+        self.builder.initial_committee.validators().len()
+    }
+
+    /// Returns a client for the admin chain.
+    pub fn admin_chain(&self) -> &ChainClient<B::Storage> {
+        &self.admin_chain
+    }
+
+    /// Creates a new microchain with 10 tokens, and returns a client for it.
+    pub async fn new_chain(&mut self) -> ChainClient<B::Storage> {
+        let index = self.next_root_chain_index;
+        self.next_root_chain_index += 1;
+        self.builder
+            .add_root_chain(index, Amount::from_tokens(10))
+            .await
+            .expect("Failed to create a new microchain")
+    }
+
+    /// Creates another client for an existing microchain, with its own storage.
+    ///
+    /// The new client only knows the genesis state and synchronizes from the validators.
+    pub async fn make_client(&mut self, chain_id: ChainId) -> ChainClient<B::Storage> {
+        self.builder
+            .make_client(chain_id, None, BlockHeight::ZERO)
+            .await
+            .expect("Failed to create a chain client")
+    }
+
+    /// Stops the validator with the given index: it doesn't answer any requests until it is
+    /// restarted, and it misses all the blocks confirmed in the meantime.
+    pub async fn stop_validator(&mut self, index: usize) {
+        self.set_fault_type(index, FaultType::Offline).await;
+    }
+
+    /// Restarts the validator with the given index, making it honest again.
+    ///
+    /// A validator that was stopped is lagging behind: it catches up when clients send it the
+    /// missing certificates, e.g. with the next block they propose.
+    pub async fn restart_validator(&mut self, index: usize) {
+        self.set_fault_type(index, FaultType::Honest).await;
+    }
+
+    /// Makes the validator with the given index behave according to the `fault_type`.
+    pub async fn set_fault_type(&mut self, index: usize, fault_type: FaultType) {
+        self.builder.set_fault_type([index], fault_type).await;
+    }
+
+    /// Returns the next block height of the chain according to the validator with the given
+    /// index, or `None` if the validator doesn't answer.
+    pub async fn validator_next_block_height(
+        &mut self,
+        index: usize,
+        chain_id: ChainId,
+    ) -> Option<BlockHeight> {
+        let response = self
+            .builder
+            .node(index)
+            .handle_chain_info_query(ChainInfoQuery::new(chain_id))
+            .await
+            .ok()?;
+        Some(response.info.next_block_height)
+    }
+
+    /// Publishes the module in the crate calling this method on the `publisher` chain.
+    ///
+    /// The contract and service binaries are built and found in the same way as in
+    /// [`ActiveChain::publish_current_module`].
+    pub async fn publish_current_module<Abi, Parameters, InstantiationArgument>(
+        publisher: &ChainClient<B::Storage>,
+    ) -> ModuleId<Abi, Parameters, InstantiationArgument> {
+        Self::publish_bytecode_files_in(publisher, ".").await
+    }
+
+    /// Publishes the bytecode files in the crate at `repository_path` on the `publisher`
+    /// chain.
+    pub async fn publish_bytecode_files_in<Abi, Parameters, InstantiationArgument>(
+        publisher: &ChainClient<B::Storage>,
+        repository_path: impl AsRef<Path>,
+    ) -> ModuleId<Abi, Parameters, InstantiationArgument> {
+        let (contract, service) = ActiveChain::build_bytecode_files_in(repository_path).await;
+        let (module_id, _certificate) = publisher
+            .publish_module(contract, service, VmRuntime::Wasm)
+            .await
+            .expect("Failed to publish the module")
+            .expect("Unexpected round timeout while publishing the module");
+        module_id.with_abi()
+    }
+}
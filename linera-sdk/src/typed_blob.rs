@@ -0,0 +1,269 @@
+// Copyright (c) Zefchain Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! Data blobs holding values of a known type.
+//!
+//! A data blob is only a sequence of bytes, so applications that store structured data in blobs
+//! must agree on how to decode them. A typed blob starts with a header naming the schema of its
+//! contents and the encoding used for them, followed by the encoded value. Reading a typed blob
+//! checks the header before decoding, so that a blob published for another type or by another
+//! application is rejected instead of being decoded into garbage.
+
+use std::{fmt, marker::PhantomData};
+
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::DataBlobHash;
+
+/// The bytes that every typed blob starts with.
+const TYPED_BLOB_MAGIC: [u8; 4] = *b"LTB\x01";
+
+/// A type whose values can be stored in typed data blobs.
+pub trait BlobSchema: Serialize + DeserializeOwned {
+    /// The identifier written in the header of the blobs, e.g. `"my-app/Metadata/v1"`.
+    ///
+    /// It should be changed whenever the serialized format of the type changes.
+    const SCHEMA_ID: &'static str;
+
+    /// How the values are encoded after the header.
+    const ENCODING: BlobEncoding = BlobEncoding::Bcs;
+}
+
+/// The encoding of the value in a typed data blob.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum BlobEncoding {
+    /// The value is serialized with [`bcs`].
+    Bcs,
+    /// The value is serialized with [`serde_json`].
+    Json,
+}
+
+/// The header at the start of a typed data blob.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TypedBlobHeader {
+    /// The schema of the value in the blob.
+    pub schema_id: String,
+    /// The encoding of the value in the blob.
+    pub encoding: BlobEncoding,
+}
+
+/// An error when decoding a typed data blob.
+#[derive(Debug, Error)]
+pub enum TypedBlobError {
+    /// The blob does not start with a typed blob header.
+    #[error("the data blob is not a typed blob")]
+    MissingHeader,
+    /// The blob holds a value of another schema.
+    #[error("expected a blob with schema {expected:?}, found {found:?}")]
+    SchemaMismatch {
+        /// The schema of the type being read.
+        expected: &'static str,
+        /// The schema in the header of the blob.
+        found: String,
+    },
+    /// The value in the blob is not encoded as the type expects.
+    #[error("expected a blob encoded with {expected:?}, found {found:?}")]
+    EncodingMismatch {
+        /// The encoding of the type being read.
+        expected: BlobEncoding,
+        /// The encoding in the header of the blob.
+        found: BlobEncoding,
+    },
+    /// The value failed to serialize or deserialize with BCS.
+    #[error(transparent)]
+    Bcs(#[from] bcs::Error),
+    /// The value failed to serialize or deserialize with JSON.
+    #[error(transparent)]
+    Json(#[from] serde_json::Error),
+}
+
+/// The hash of a data blob holding a value of type `T`.
+///
+/// This can be stored in the state of an application, or sent in operations and messages, in
+/// place of a [`DataBlobHash`].
+#[derive(Serialize, Deserialize)]
+#[serde(transparent, bound = "")]
+pub struct TypedBlob<T> {
+    hash: DataBlobHash,
+    #[serde(skip)]
+    _value: PhantomData<fn() -> T>,
+}
+
+impl<T> TypedBlob<T> {
+    /// Refers to the data blob with the given hash, assuming that it holds a value of type `T`.
+    ///
+    /// The assumption is checked when the blob is read.
+    pub fn from_hash(hash: DataBlobHash) -> Self {
+        TypedBlob {
+            hash,
+            _value: PhantomData,
+        }
+    }
+
+    /// Returns the hash of the data blob.
+    pub fn hash(&self) -> DataBlobHash {
+        self.hash
+    }
+}
+
+impl<T: BlobSchema> TypedBlob<T> {
+    /// Returns the contents of a typed data blob holding `value`.
+    pub fn encode(value: &T) -> Result<Vec<u8>, TypedBlobError> {
+        let header = TypedBlobHeader {
+            schema_id: T::SCHEMA_ID.to_owned(),
+            encoding: T::ENCODING,
+        };
+        let payload = match T::ENCODING {
+            BlobEncoding::Bcs => bcs::to_bytes(value)?,
+            BlobEncoding::Json => serde_json::to_vec(value)?,
+        };
+        let mut bytes = TYPED_BLOB_MAGIC.to_vec();
+        bcs::serialize_into(&mut bytes, &(header, payload))?;
+        Ok(bytes)
+    }
+
+    /// Decodes the value in the contents of a typed data blob, after checking that its header
+    /// matches the schema and encoding of `T`.
+    pub fn decode(bytes: &[u8]) -> Result<T, TypedBlobError> {
+        let (header, payload) = Self::split_header(bytes)?;
+        if header.schema_id != T::SCHEMA_ID {
+            return Err(TypedBlobError::SchemaMismatch {
+                expected: T::SCHEMA_ID,
+                found: header.schema_id,
+            });
+        }
+        if header.encoding != T::ENCODING {
+            return Err(TypedBlobError::EncodingMismatch {
+                expected: T::ENCODING,
+                found: header.encoding,
+            });
+        }
+        Ok(match T::ENCODING {
+            BlobEncoding::Bcs => bcs::from_bytes(&payload)?,
+            BlobEncoding::Json => serde_json::from_slice(&payload)?,
+        })
+    }
+
+    /// Splits the contents of a typed data blob into its header and the encoded value.
+    fn split_header(bytes: &[u8]) -> Result<(TypedBlobHeader, Vec<u8>), TypedBlobError> {
+        let contents = bytes
+            .strip_prefix(&TYPED_BLOB_MAGIC)
+            .ok_or(TypedBlobError::MissingHeader)?;
+        bcs::from_bytes(contents).map_err(|_| TypedBlobError::MissingHeader)
+    }
+}
+
+impl<T> Clone for TypedBlob<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for TypedBlob<T> {}
+
+impl<T> PartialEq for TypedBlob<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.hash == other.hash
+    }
+}
+
+impl<T> Eq for TypedBlob<T> {}
+
+impl<T> fmt::Debug for TypedBlob<T> {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        formatter
+            .debug_tuple("TypedBlob")
+            .field(&self.hash)
+            .finish()
+    }
+}
+
+impl<T> From<TypedBlob<T>> for DataBlobHash {
+    fn from(blob: TypedBlob<T>) -> Self {
+        blob.hash
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::{Deserialize, Serialize};
+
+    use super::{BlobEncoding, BlobSchema, TypedBlob, TypedBlobError};
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Metadata {
+        name: String,
+        size: u64,
+    }
+
+    impl BlobSchema for Metadata {
+        const SCHEMA_ID: &'static str = "test/Metadata/v1";
+    }
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct JsonMetadata {
+        name: String,
+        size: u64,
+    }
+
+    impl BlobSchema for JsonMetadata {
+        const SCHEMA_ID: &'static str = "test/Metadata/v1";
+        const ENCODING: BlobEncoding = BlobEncoding::Json;
+    }
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Other(u8);
+
+    impl BlobSchema for Other {
+        const SCHEMA_ID: &'static str = "test/Other/v1";
+    }
+
+    /// Checks that values round-trip through typed blobs with both encodings.
+    #[test]
+    fn typed_blobs_round_trip() {
+        let metadata = Metadata {
+            name: "picture".to_owned(),
+            size: 42,
+        };
+        let bytes = TypedBlob::encode(&metadata).unwrap();
+        assert_eq!(TypedBlob::<Metadata>::decode(&bytes).unwrap(), metadata);
+
+        let metadata = JsonMetadata {
+            name: "picture".to_owned(),
+            size: 42,
+        };
+        let bytes = TypedBlob::encode(&metadata).unwrap();
+        assert!(bytes.ends_with(br#"{"name":"picture","size":42}"#));
+        assert_eq!(TypedBlob::<JsonMetadata>::decode(&bytes).unwrap(), metadata);
+    }
+
+    /// Checks that blobs without a matching header are rejected.
+    #[test]
+    fn typed_blobs_check_their_header() {
+        let bytes = TypedBlob::encode(&Other(7)).unwrap();
+        assert!(matches!(
+            TypedBlob::<Metadata>::decode(&bytes),
+            Err(TypedBlobError::SchemaMismatch { found, .. }) if found == "test/Other/v1"
+        ));
+
+        let bytes = TypedBlob::encode(&JsonMetadata {
+            name: String::new(),
+            size: 0,
+        })
+        .unwrap();
+        assert!(matches!(
+            TypedBlob::<Metadata>::decode(&bytes),
+            Err(TypedBlobError::EncodingMismatch {
+                expected: BlobEncoding::Bcs,
+                found: BlobEncoding::Json,
+            })
+        ));
+
+        let untyped = bcs::to_bytes(&Other(7)).unwrap();
+        assert!(matches!(
+            TypedBlob::<Other>::decode(&untyped),
+            Err(TypedBlobError::MissingHeader)
+        ));
+    }
+}
@@ -7,6 +7,7 @@ mod aliases;
 #[cfg(with_testing)]
 mod mock_key_value_store;
 mod system_api;
+mod transaction;
 
 pub use linera_views::{
     self,
@@ -21,5 +22,6 @@ pub use self::{
         CustomMapView, CustomSetView, LogView, MapView, QueueView, ReadGuardedView, RegisterView,
         SetView,
     },
-    system_api::{KeyValueStore, ViewStorageContext},
+    system_api::{KeyValueStore, KeyValueStoreError, ViewStorageContext},
+    transaction::{TransactionalView, ViewTransaction, ViewTransactionError},
 };
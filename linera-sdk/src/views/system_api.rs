@@ -189,6 +189,17 @@ impl WritableKeyValueStore for KeyValueStore {
     const MAX_VALUE_SIZE: usize = usize::MAX;
 
     async fn write_batch(&self, batch: Batch) -> Result<(), KeyValueStoreError> {
+        for operation in &batch.operations {
+            let key = match operation {
+                linera_views::batch::WriteOperation::Delete { key }
+                | linera_views::batch::WriteOperation::Put { key, .. } => key,
+                linera_views::batch::WriteOperation::DeletePrefix { key_prefix } => key_prefix,
+            };
+            ensure!(
+                key.len() <= Self::MAX_KEY_SIZE,
+                KeyValueStoreError::KeyTooLong
+            );
+        }
         self.wit_api.write_batch(batch);
         Ok(())
     }
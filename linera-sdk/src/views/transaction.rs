@@ -0,0 +1,243 @@
+// Copyright (c) Zefchain Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! Saving several root views of a contract atomically.
+//!
+//! Each root view is normally saved on its own, so a contract whose state is split into
+//! several root views could end up saving only some of them if it stops half-way. A
+//! [`ViewTransaction`] instead collects the changes of all its views into a single batch,
+//! which is written only if every view could be flushed. Otherwise, or if the batch could
+//! not be written, all the views are reloaded from storage, discarding their changes.
+
+use futures::future::LocalBoxFuture;
+use linera_views::{batch::Batch, store::WritableKeyValueStore as _, views::View, ViewError};
+use thiserror::Error;
+
+use super::{KeyValueStore, KeyValueStoreError, ViewStorageContext};
+
+/// A root view that can be saved as part of a [`ViewTransaction`].
+///
+/// This is implemented by all the views using a [`ViewStorageContext`].
+pub trait TransactionalView {
+    /// Adds the pending changes of the view to the `batch`.
+    fn stage(&mut self, batch: &mut Batch) -> Result<(), ViewError>;
+
+    /// Discards the pending changes of the view by loading it again from storage.
+    fn reload(&mut self) -> LocalBoxFuture<'_, Result<(), ViewError>>;
+}
+
+impl<V> TransactionalView for V
+where
+    V: View<Context = ViewStorageContext>,
+{
+    fn stage(&mut self, batch: &mut Batch) -> Result<(), ViewError> {
+        self.flush(batch)?;
+        Ok(())
+    }
+
+    fn reload(&mut self) -> LocalBoxFuture<'_, Result<(), ViewError>> {
+        Box::pin(async move {
+            *self = V::load(self.context().clone()).await?;
+            Ok(())
+        })
+    }
+}
+
+/// A set of root views that are saved together: either the changes of all of them are
+/// written to storage, or none are.
+///
+/// The views must use distinct base keys, none of which is a prefix of another, e.g. the
+/// contexts returned by `ContractRuntime::view_storage_context` for different prefixes.
+pub struct ViewTransaction<'a> {
+    store: KeyValueStore,
+    views: Vec<&'a mut dyn TransactionalView>,
+}
+
+impl<'a> ViewTransaction<'a> {
+    /// Creates an empty transaction writing to the given `store`.
+    pub fn new(store: KeyValueStore) -> Self {
+        ViewTransaction {
+            store,
+            views: Vec::new(),
+        }
+    }
+
+    /// Adds a view to the transaction.
+    pub fn with(mut self, view: &'a mut dyn TransactionalView) -> Self {
+        self.views.push(view);
+        self
+    }
+
+    /// Saves the changes of all the views in a single batch.
+    ///
+    /// If one of the views fails to be flushed, nothing is written, and all the views are
+    /// reloaded from storage, i.e. they lose their changes. The views are also reloaded if
+    /// the batch fails to be written, since flushing them already marked their changes as
+    /// saved.
+    pub async fn commit(mut self) -> Result<(), ViewTransactionError> {
+        let mut batch = Batch::new();
+        let failure = self
+            .views
+            .iter_mut()
+            .enumerate()
+            .find_map(|(index, view)| view.stage(&mut batch).err().map(|error| (index, error)));
+        if let Some((index, error)) = failure {
+            self.reload()
+                .await
+                .map_err(|error| ViewTransactionError::Rollback { index, error })?;
+            return Err(ViewTransactionError::Save { index, error });
+        }
+        if !batch.is_empty() {
+            if let Err(error) = self.store.write_batch(batch).await {
+                self.reload()
+                    .await
+                    .map_err(|error| ViewTransactionError::WriteRollback { error })?;
+                return Err(ViewTransactionError::Write(error));
+            }
+        }
+        Ok(())
+    }
+
+    /// Discards the changes of all the views by loading them again from storage.
+    async fn reload(&mut self) -> Result<(), ViewError> {
+        for view in &mut self.views {
+            view.reload().await?;
+        }
+        Ok(())
+    }
+}
+
+/// An error while committing a [`ViewTransaction`].
+#[derive(Debug, Error)]
+pub enum ViewTransactionError {
+    /// A view could not be saved, so none was; their changes were discarded.
+    #[error("failed to save view {index} of the transaction, so no view was saved: {error}")]
+    Save {
+        /// The position of the failing view in the transaction.
+        index: usize,
+        #[source]
+        error: ViewError,
+    },
+
+    /// A view could not be saved, and the views could not be reloaded afterwards. Their
+    /// in-memory state should not be used anymore.
+    #[error("failed to reload the views after failing to save view {index}: {error}")]
+    Rollback {
+        /// The position of the view that failed to be saved.
+        index: usize,
+        #[source]
+        error: ViewError,
+    },
+
+    /// The batch with the changes of all views could not be written; their changes were
+    /// discarded.
+    #[error(transparent)]
+    Write(#[from] KeyValueStoreError),
+
+    /// The batch could not be written, and the views could not be reloaded afterwards. Their
+    /// in-memory state should not be used anymore.
+    #[error("failed to reload the views after failing to write their changes: {error}")]
+    WriteRollback {
+        #[source]
+        error: ViewError,
+    },
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use linera_views::register_view::RegisterView;
+
+    use super::*;
+
+    /// A view that cannot be flushed.
+    struct FailingView;
+
+    impl TransactionalView for FailingView {
+        fn stage(&mut self, _batch: &mut Batch) -> Result<(), ViewError> {
+            Err(ViewError::InconsistentEntries)
+        }
+
+        fn reload(&mut self) -> LocalBoxFuture<'_, Result<(), ViewError>> {
+            Box::pin(async { Ok(()) })
+        }
+    }
+
+    /// A view whose changes cannot be written, because its key is too long.
+    struct UnwritableView;
+
+    impl TransactionalView for UnwritableView {
+        fn stage(&mut self, batch: &mut Batch) -> Result<(), ViewError> {
+            batch.put_key_value_bytes(vec![0; 1000], vec![]);
+            Ok(())
+        }
+
+        fn reload(&mut self) -> LocalBoxFuture<'_, Result<(), ViewError>> {
+            Box::pin(async { Ok(()) })
+        }
+    }
+
+    async fn load(store: &KeyValueStore, base_key: u8) -> RegisterView<ViewStorageContext, u64> {
+        let context = ViewStorageContext::new_unsafe(store.clone(), vec![base_key], ());
+        RegisterView::load(context).await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_views_are_saved_together_or_not_at_all() -> anyhow::Result<()> {
+        let store = KeyValueStore::mock().to_mut();
+        let mut first = load(&store, 0).await;
+        let mut second = load(&store, 1).await;
+        first.set(1);
+        second.set(2);
+        ViewTransaction::new(store.clone())
+            .with(&mut first)
+            .with(&mut second)
+            .commit()
+            .await?;
+        assert_eq!(*load(&store, 0).await.get(), 1);
+        assert_eq!(*load(&store, 1).await.get(), 2);
+
+        first.set(3);
+        second.set(4);
+        let result = ViewTransaction::new(store.clone())
+            .with(&mut first)
+            .with(&mut FailingView)
+            .with(&mut second)
+            .commit()
+            .await;
+        assert!(matches!(
+            result,
+            Err(ViewTransactionError::Save { index: 1, .. })
+        ));
+        assert_eq!(*first.get(), 1);
+        assert_eq!(*second.get(), 2);
+        assert_eq!(*load(&store, 0).await.get(), 1);
+        assert_eq!(*load(&store, 1).await.get(), 2);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_views_are_reloaded_when_the_batch_cannot_be_written() -> anyhow::Result<()> {
+        let store = KeyValueStore::mock().to_mut();
+        let mut first = load(&store, 0).await;
+        first.set(1);
+        ViewTransaction::new(store.clone())
+            .with(&mut first)
+            .commit()
+            .await?;
+
+        first.set(2);
+        let result = ViewTransaction::new(store.clone())
+            .with(&mut first)
+            .with(&mut UnwritableView)
+            .commit()
+            .await;
+        assert!(matches!(
+            result,
+            Err(ViewTransactionError::Write(KeyValueStoreError::KeyTooLong))
+        ));
+        assert_eq!(*first.get(), 1);
+        assert!(!first.has_pending_changes().await);
+        assert_eq!(*load(&store, 0).await.get(), 1);
+        Ok(())
+    }
+}
@@ -38,7 +38,6 @@ mod types {
     }
 
     #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
-    #[expect(clippy::enum_variant_names)]
     pub enum Reason {
         NewBlock {
             height: BlockHeight,
@@ -52,6 +51,10 @@ mod types {
             height: BlockHeight,
             round: Round,
         },
+        ProposalDiscarded {
+            height: BlockHeight,
+            round: Round,
+        },
     }
 }
 
@@ -138,7 +141,10 @@ pub enum ConversionError {
 
 #[cfg(not(target_arch = "wasm32"))]
 mod from {
-    use linera_base::{data_types::Event, identifiers::StreamId};
+    use linera_base::{
+        data_types::{Event, FeeBreakdown},
+        identifiers::StreamId,
+    };
     use linera_chain::{
         block::{Block, BlockBody, BlockHeader},
         data_types::{IncomingBundle, MessageBundle, PostedMessage},
@@ -247,6 +253,7 @@ mod from {
                 events_hash,
                 blobs_hash,
                 operation_results_hash,
+                fees_hash,
             } = header;
             let block::BlockBlockBlockBody {
                 incoming_bundles,
@@ -258,6 +265,7 @@ mod from {
                 events,
                 blobs,
                 operation_results,
+                fees,
             } = body;
 
             let block_header = BlockHeader {
@@ -277,6 +285,7 @@ mod from {
                 events_hash,
                 blobs_hash,
                 operation_results_hash,
+                fees_hash,
             };
             let block_body = BlockBody {
                 incoming_bundles: incoming_bundles
@@ -300,6 +309,7 @@ mod from {
                     .map(|blobs| blobs.into_iter().collect())
                     .collect(),
                 operation_results,
+                fees: fees.into(),
             };
 
             Ok(Block {
@@ -328,6 +338,20 @@ mod from {
         }
     }
 
+    impl From<block::BlockBlockBlockBodyFees> for FeeBreakdown {
+        fn from(fees: block::BlockBlockBlockBodyFees) -> Self {
+            FeeBreakdown {
+                fuel: fees.fuel,
+                storage_reads: fees.storage_reads,
+                storage_writes: fees.storage_writes,
+                messages: fees.messages,
+                blob_bytes: fees.blob_bytes,
+                operations: fees.operations,
+                other: fees.other,
+            }
+        }
+    }
+
     impl TryFrom<block::BlockBlock> for ConfirmedBlock {
         type Error = ConversionError;
 
@@ -0,0 +1,130 @@
+// Copyright (c) Zefchain Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! An archive node: follows a Linera network without any keys, stores all its confirmed
+//! certificates and blobs, and serves them to the explorer and the indexer.
+
+#![deny(clippy::large_futures)]
+
+use std::{num::NonZeroU16, path::PathBuf, time::Duration};
+
+use anyhow::Result;
+use async_trait::async_trait;
+use futures::FutureExt as _;
+use linera_base::listen_for_shutdown_signals;
+use linera_client::config::GenesisConfig;
+use linera_execution::{WasmRuntime, WithWasmDefault as _};
+use linera_rpc::NodeOptions;
+use linera_service::{
+    archive_node::ArchiveNode,
+    storage::{CommonStorageOptions, Runnable, StorageConfig},
+    util,
+};
+use linera_storage::Storage;
+use tokio_util::sync::CancellationToken;
+
+/// Options for running the linera archive node.
+#[derive(clap::Parser, Debug, Clone)]
+#[command(
+    name = "Linera Archive Node",
+    version = linera_version::VersionInfo::default_clap_str(),
+    about = "Follows a Linera network read-only, and serves its history over GraphQL",
+)]
+struct ArchiveOptions {
+    /// Path to the genesis configuration of the network to follow. The storage must have been
+    /// initialized with it, e.g. with `linera storage initialize`.
+    #[arg(long = "genesis")]
+    genesis_config_path: PathBuf,
+
+    /// Storage configuration for the blockchain history, chain states and binary blobs.
+    #[arg(long = "storage")]
+    storage_config: StorageConfig,
+
+    /// Common storage options.
+    #[command(flatten)]
+    common_storage_options: CommonStorageOptions,
+
+    /// The port on which the GraphQL API is served.
+    #[arg(long, default_value = "8080")]
+    port: NonZeroU16,
+
+    /// The WebAssembly runtime used to execute the blocks.
+    #[arg(long)]
+    wasm_runtime: Option<WasmRuntime>,
+
+    /// Timeout in milliseconds for sending queries.
+    #[arg(long = "send-timeout-ms", default_value = "4000", value_parser = util::parse_millis)]
+    send_timeout: Duration,
+
+    /// Timeout in milliseconds for receiving responses.
+    #[arg(long = "recv-timeout-ms", default_value = "4000", value_parser = util::parse_millis)]
+    recv_timeout: Duration,
+
+    /// Delay increment for retrying to connect to a validator.
+    #[arg(
+        long = "retry-delay-ms",
+        default_value = "1000",
+        value_parser = util::parse_millis
+    )]
+    retry_delay: Duration,
+
+    /// Number of times to retry connecting to a validator.
+    #[arg(long, default_value = "10")]
+    max_retries: u32,
+}
+
+struct ArchiveContext {
+    genesis_config: GenesisConfig,
+    node_options: NodeOptions,
+    port: NonZeroU16,
+}
+
+#[async_trait]
+impl Runnable for ArchiveContext {
+    type Output = Result<()>;
+
+    async fn run<S>(self, storage: S) -> Self::Output
+    where
+        S: Storage + Clone + Send + Sync + 'static,
+    {
+        let shutdown_notifier = CancellationToken::new();
+        tokio::spawn(listen_for_shutdown_signals(shutdown_notifier.clone()));
+        let node = ArchiveNode::new(storage, self.genesis_config, self.node_options, self.port);
+        Box::pin(node.run(shutdown_notifier)).await
+    }
+}
+
+fn main() -> Result<()> {
+    linera_base::tracing::init("linera-archive");
+    let options = <ArchiveOptions as clap::Parser>::parse();
+    tokio::runtime::Builder::new_multi_thread()
+        .enable_all()
+        .build()?
+        .block_on(options.run())
+}
+
+impl ArchiveOptions {
+    async fn run(self) -> Result<()> {
+        linera_version::VERSION_INFO.log();
+        let genesis_config: GenesisConfig = util::read_json(&self.genesis_config_path)?;
+        let context = ArchiveContext {
+            genesis_config,
+            node_options: NodeOptions {
+                send_timeout: self.send_timeout,
+                recv_timeout: self.recv_timeout,
+                retry_delay: self.retry_delay,
+                max_retries: self.max_retries,
+            },
+            port: self.port,
+        };
+        let store_config = self
+            .storage_config
+            .add_common_storage_options(&self.common_storage_options)
+            .await?;
+        let options = self.common_storage_options.db_storage_options().await?;
+        store_config
+            .run_with_storage(self.wasm_runtime.with_wasm_default(), &options, context)
+            .boxed()
+            .await?
+    }
+}
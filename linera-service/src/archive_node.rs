@@ -0,0 +1,601 @@
+// Copyright (c) Zefchain Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! A read-only node that follows a Linera network to archive its history.
+//!
+//! The archive node holds no keys. Starting from the genesis chains, it follows the admin
+//! chain, and thereby the committees, as well as every chain created by a block it has seen.
+//! Confirmed certificates and the blobs they need are downloaded from the validators,
+//! verified and stored by the local node. The blocks are served with the same GraphQL queries
+//! as the node service, so that the explorer and the indexer can use an archive node instead.
+
+use std::{
+    collections::BTreeMap,
+    future::IntoFuture,
+    net::SocketAddr,
+    num::NonZeroU16,
+    sync::{Arc, RwLock},
+};
+
+use async_graphql::{futures_util::Stream, EmptyMutation, Error, Schema, Subscription};
+use async_graphql_axum::{GraphQLRequest, GraphQLResponse, GraphQLSubscription};
+use axum::{Extension, Router};
+use futures::{channel::mpsc, FutureExt as _, StreamExt as _};
+use linera_base::{
+    crypto::{AccountSignature, CryptoHash, Signer},
+    data_types::{ArithmeticError, BlockHeight},
+    identifiers::{AccountOwner, BlobType, ChainId},
+    time::Duration,
+};
+use linera_chain::types::ConfirmedBlock;
+use linera_client::config::GenesisConfig;
+use linera_core::{
+    client::{
+        BlanketMessagePolicy, ChainClient, ChainClientError, ChainClientOptions, Client,
+        MessagePolicy,
+    },
+    environment,
+    node::CrossChainMessageDelivery,
+    worker::{Notification, Reason},
+    DEFAULT_GRACE_PERIOD,
+};
+use linera_rpc::{NodeOptions, NodeProvider};
+use linera_storage::Storage as _;
+use thiserror::Error as ThisError;
+use tokio::task::JoinSet;
+use tokio_util::sync::CancellationToken;
+use tower_http::cors::CorsLayer;
+use tracing::{debug, info, instrument, warn, Instrument as _};
+
+use crate::{node_service::Chains, util};
+
+/// The name of the checkpoint recording the chains followed by the archive node, and up to
+/// which height their blocks were scanned for new chains.
+const FOLLOWED_CHAINS_CHECKPOINT: &str = "archive_followed_chains";
+
+/// The delay before following a chain again after it failed.
+const MIN_RETRY_DELAY: Duration = Duration::from_secs(1);
+
+/// The maximum delay before following a chain again after repeated failures.
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(60);
+
+/// A signer without any keys: the archive node never signs anything.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct NoSigner;
+
+/// The error returned by [`NoSigner`] when asked for a signature.
+#[derive(Debug, ThisError)]
+#[error("the archive node has no signing keys")]
+pub struct NoSignerError;
+
+impl Signer for NoSigner {
+    type Error = NoSignerError;
+
+    async fn sign(
+        &self,
+        _owner: &AccountOwner,
+        _value: &CryptoHash,
+    ) -> Result<AccountSignature, NoSignerError> {
+        Err(NoSignerError)
+    }
+
+    async fn contains_key(&self, _owner: &AccountOwner) -> Result<bool, NoSignerError> {
+        Ok(false)
+    }
+}
+
+/// The environment of the archive node's client: real validators, and no keys.
+pub type ArchiveEnvironment<S> = environment::Impl<S, NodeProvider, NoSigner>;
+
+/// The chains followed by the archive node, with the height up to which their blocks were
+/// scanned for new chains.
+type FollowedChains = Arc<RwLock<BTreeMap<ChainId, BlockHeight>>>;
+
+/// The blocks of a chain that were scanned for new chains.
+struct ScannedBlocks {
+    chain_id: ChainId,
+    next_height: BlockHeight,
+    created_chains: Vec<ChainId>,
+}
+
+/// The delays between the attempts to follow a chain: they double after each failure, up to
+/// [`MAX_RETRY_DELAY`], and are reset once an attempt made progress.
+struct Backoff {
+    delay: Duration,
+}
+
+impl Default for Backoff {
+    fn default() -> Self {
+        Backoff {
+            delay: MIN_RETRY_DELAY,
+        }
+    }
+}
+
+impl Backoff {
+    /// Returns the delay to wait before the next attempt.
+    fn next_delay(&mut self) -> Duration {
+        let delay = self.delay;
+        self.delay = (delay * 2).min(MAX_RETRY_DELAY);
+        delay
+    }
+
+    /// Resets the delay after a successful attempt.
+    fn reset(&mut self) {
+        self.delay = MIN_RETRY_DELAY;
+    }
+}
+
+/// A node following a whole network without taking part in it.
+pub struct ArchiveNode<S: environment::Storage> {
+    client: Arc<Client<ArchiveEnvironment<S>>>,
+    genesis_config: GenesisConfig,
+    port: NonZeroU16,
+    followed_chains: FollowedChains,
+}
+
+impl<S: environment::Storage> ArchiveNode<S> {
+    /// Creates an archive node for the network with the given genesis configuration. The
+    /// storage must have been initialized with the same configuration.
+    pub fn new(
+        storage: S,
+        genesis_config: GenesisConfig,
+        node_options: NodeOptions,
+        port: NonZeroU16,
+    ) -> Self {
+        let options = ChainClientOptions {
+            max_pending_message_bundles: 0,
+            message_policy: MessagePolicy::new(BlanketMessagePolicy::Ignore, None),
+            cross_chain_message_delivery: CrossChainMessageDelivery::NonBlocking,
+            grace_period: DEFAULT_GRACE_PERIOD,
+            blob_download_timeout: Duration::from_secs(1),
+            hedging: None,
+            execution_state_history: 0,
+            max_parallel_certificate_downloads: 4,
+            reproposal: None,
+        };
+        let client = Client::new(
+            environment::Impl {
+                storage,
+                network: NodeProvider::new(node_options),
+                signer: NoSigner,
+            },
+            genesis_config.admin_id(),
+            false,
+            [],
+            "Archive node",
+            Duration::from_secs(30),
+            options,
+        );
+        ArchiveNode {
+            client: Arc::new(client),
+            genesis_config,
+            port,
+            followed_chains: FollowedChains::default(),
+        }
+    }
+
+    /// Checks that the storage was initialized for this node's network.
+    pub async fn check_storage(&self) -> anyhow::Result<()> {
+        let Some(description) = self
+            .client
+            .storage_client()
+            .read_network_description()
+            .await?
+        else {
+            anyhow::bail!("The storage is not initialized: run `linera storage initialize`");
+        };
+        anyhow::ensure!(
+            description.genesis_config_hash == self.genesis_config.hash(),
+            "The storage was initialized for another network: {}",
+            description.name
+        );
+        Ok(())
+    }
+
+    /// Returns the GraphQL schema of the archive node.
+    pub fn schema(&self) -> ArchiveSchema<S> {
+        Schema::build(
+            QueryRoot {
+                client: self.client.clone(),
+                followed_chains: self.followed_chains.clone(),
+            },
+            EmptyMutation,
+            SubscriptionRoot {
+                client: self.client.clone(),
+            },
+        )
+        .finish()
+    }
+
+    /// Follows the network and serves the GraphQL API until cancelled.
+    #[instrument(name = "archive_node", level = "info", skip_all, fields(port = ?self.port))]
+    pub async fn run(self, cancellation_token: CancellationToken) -> anyhow::Result<()> {
+        self.check_storage().await?;
+        let port = self.port.get();
+        let index_handler = axum::routing::get(util::graphiql).post(Self::index_handler);
+        let app = Router::new()
+            .route("/", index_handler)
+            .route("/ready", axum::routing::get(|| async { "ready!" }))
+            .route_service("/ws", GraphQLSubscription::new(self.schema()))
+            .layer(Extension(self.schema()))
+            .layer(CorsLayer::permissive());
+
+        info!("GraphiQL IDE: http://localhost:{}", port);
+
+        let tcp_listener =
+            tokio::net::TcpListener::bind(SocketAddr::from(([0, 0, 0, 0], port))).await?;
+        let server = axum::serve(tcp_listener, app).into_future();
+        futures::select! {
+            result = Box::pin(self.follow()).fuse() => result?,
+            result = Box::pin(server).fuse() => result?,
+            () = Box::pin(cancellation_token.cancelled()).fuse() => info!("Archive node stopped"),
+        };
+        Ok(())
+    }
+
+    /// Follows the genesis chains and all the chains they create, recursively. This only
+    /// returns on storage errors.
+    async fn follow(&self) -> anyhow::Result<()> {
+        let storage = self.client.storage_client();
+        let checkpoint = storage
+            .read_task_checkpoint(FOLLOWED_CHAINS_CHECKPOINT)
+            .await?;
+        let genesis_chains = self.genesis_config.chains.iter().map(|chain| chain.id());
+        let mut chains = chains_to_resume(checkpoint, genesis_chains)?;
+        info!("Following {} chains", chains.len());
+
+        let (sender, mut receiver) = mpsc::unbounded();
+        let mut listeners = JoinSet::new();
+        for (chain_id, next_height) in &chains {
+            self.spawn_follower(&mut listeners, *chain_id, *next_height, &sender);
+        }
+        *self.followed_chains.write().unwrap() = chains.clone();
+
+        while let Some(scanned) = receiver.next().await {
+            for new_chain_id in record_scanned_blocks(&mut chains, scanned) {
+                self.spawn_follower(&mut listeners, new_chain_id, BlockHeight::ZERO, &sender);
+            }
+            storage
+                .write_task_checkpoint(FOLLOWED_CHAINS_CHECKPOINT, bcs::to_bytes(&chains)?)
+                .await?;
+            *self.followed_chains.write().unwrap() = chains.clone();
+        }
+        Ok(())
+    }
+
+    /// Spawns a task following the chain from `next_height` on. If following the chain fails,
+    /// it is retried with a [`Backoff`], from the height that was reached.
+    fn spawn_follower(
+        &self,
+        listeners: &mut JoinSet<()>,
+        chain_id: ChainId,
+        mut next_height: BlockHeight,
+        sender: &mpsc::UnboundedSender<ScannedBlocks>,
+    ) {
+        let chain_client =
+            self.client
+                .create_chain_client(chain_id, None, BlockHeight::ZERO, None, None, None);
+        let sender = sender.clone();
+        listeners.spawn(
+            async move {
+                let mut backoff = Backoff::default();
+                loop {
+                    let start_height = next_height;
+                    let Err(error) = follow_chain(&chain_client, &mut next_height, &sender).await
+                    else {
+                        return;
+                    };
+                    if next_height > start_height {
+                        backoff.reset();
+                    }
+                    let delay = backoff.next_delay();
+                    warn!(%chain_id, %error, ?delay, "Failed to follow chain; retrying");
+                    linera_base::time::timer::sleep(delay).await;
+                }
+            }
+            .in_current_span(),
+        );
+    }
+
+    async fn index_handler(
+        Extension(schema): Extension<ArchiveSchema<S>>,
+        request: GraphQLRequest,
+    ) -> GraphQLResponse {
+        schema.execute(request.into_inner()).await.into()
+    }
+}
+
+/// Returns the chains to follow with the heights up to which they were scanned: the ones
+/// recorded in the checkpoint, if any, and the genesis chains.
+fn chains_to_resume(
+    checkpoint: Option<Vec<u8>>,
+    genesis_chains: impl IntoIterator<Item = ChainId>,
+) -> Result<BTreeMap<ChainId, BlockHeight>, bcs::Error> {
+    let mut chains = match checkpoint {
+        Some(bytes) => bcs::from_bytes::<BTreeMap<ChainId, BlockHeight>>(&bytes)?,
+        None => BTreeMap::new(),
+    };
+    for chain_id in genesis_chains {
+        chains.entry(chain_id).or_default();
+    }
+    Ok(chains)
+}
+
+/// Records the blocks scanned by a follower, and returns the chains they created that are not
+/// followed yet.
+fn record_scanned_blocks(
+    chains: &mut BTreeMap<ChainId, BlockHeight>,
+    scanned: ScannedBlocks,
+) -> Vec<ChainId> {
+    let ScannedBlocks {
+        chain_id,
+        next_height,
+        created_chains,
+    } = scanned;
+    chains.insert(chain_id, next_height);
+    let mut new_chains = Vec::new();
+    for new_chain_id in created_chains {
+        if chains.contains_key(&new_chain_id) {
+            continue;
+        }
+        info!(%new_chain_id, origin = %chain_id, "Following a new chain");
+        chains.insert(new_chain_id, BlockHeight::ZERO);
+        new_chains.push(new_chain_id);
+    }
+    new_chains
+}
+
+/// Synchronizes the chain whenever a validator notifies a new block, and reports the chains
+/// created by its blocks from `next_height` on. `next_height` is updated as blocks are
+/// scanned, so that a failed attempt can be resumed.
+async fn follow_chain<S: environment::Storage>(
+    chain_client: &ChainClient<ArchiveEnvironment<S>>,
+    next_height: &mut BlockHeight,
+    sender: &mpsc::UnboundedSender<ScannedBlocks>,
+) -> Result<(), ChainClientError> {
+    let chain_id = chain_client.chain_id();
+    let (listener, _abort, mut notifications) = chain_client.listen().await?;
+    let _listener = linera_base::task::spawn(listener.in_current_span());
+    loop {
+        let created_chains = scan_new_blocks(chain_client, next_height).await?;
+        let scanned = ScannedBlocks {
+            chain_id,
+            next_height: *next_height,
+            created_chains,
+        };
+        if sender.unbounded_send(scanned).is_err() {
+            return Ok(());
+        }
+        // Wait until the listener has synchronized a new block.
+        loop {
+            match notifications.next().await {
+                Some(Notification {
+                    reason: Reason::NewBlock { .. },
+                    ..
+                }) => break,
+                Some(_) => continue,
+                None => return Ok(()),
+            }
+        }
+    }
+}
+
+/// Reads the blocks of the chain from `next_height` on, and returns the chains they created.
+async fn scan_new_blocks<S: environment::Storage>(
+    chain_client: &ChainClient<ArchiveEnvironment<S>>,
+    next_height: &mut BlockHeight,
+) -> Result<Vec<ChainId>, ChainClientError> {
+    let hashes = {
+        let chain = chain_client.chain_state_view().await?;
+        let end = chain.tip_state.get().next_block_height;
+        let start = usize::try_from(next_height.0).map_err(|_| ArithmeticError::Overflow)?;
+        let end = usize::try_from(end.0).map_err(|_| ArithmeticError::Overflow)?;
+        chain.confirmed_log.read(start..end).await?
+    };
+    let mut created_chains = Vec::new();
+    for hash in hashes {
+        let block = chain_client.read_confirmed_block(hash).await?;
+        created_chains.extend(
+            block
+                .block()
+                .created_blob_ids()
+                .into_iter()
+                .filter(|blob_id| blob_id.blob_type == BlobType::ChainDescription)
+                .map(|blob_id| ChainId(blob_id.hash)),
+        );
+        *next_height = block.height().try_add_one()?;
+    }
+    debug!(
+        chain_id = %chain_client.chain_id(),
+        %next_height,
+        "Scanned the blocks for new chains"
+    );
+    Ok(created_chains)
+}
+
+/// The GraphQL schema of the archive node.
+pub type ArchiveSchema<S> = Schema<QueryRoot<S>, EmptyMutation, SubscriptionRoot<S>>;
+
+/// The root GraphQL query type of the archive node.
+pub struct QueryRoot<S: environment::Storage> {
+    client: Arc<Client<ArchiveEnvironment<S>>>,
+    followed_chains: FollowedChains,
+}
+
+/// The root GraphQL subscription type of the archive node.
+pub struct SubscriptionRoot<S: environment::Storage> {
+    client: Arc<Client<ArchiveEnvironment<S>>>,
+}
+
+fn make_chain_client<S: environment::Storage>(
+    client: &Arc<Client<ArchiveEnvironment<S>>>,
+    chain_id: ChainId,
+) -> ChainClient<ArchiveEnvironment<S>> {
+    client.create_chain_client(chain_id, None, BlockHeight::ZERO, None, None, None)
+}
+
+#[async_graphql::Object(cache_control(no_cache))]
+impl<S: environment::Storage> QueryRoot<S> {
+    /// Returns the chains followed by the archive node.
+    async fn chains(&self) -> Chains {
+        let list = self
+            .followed_chains
+            .read()
+            .unwrap()
+            .keys()
+            .copied()
+            .collect();
+        Chains {
+            list,
+            default: None,
+        }
+    }
+
+    /// Returns the block with the given hash, or the latest block of the chain.
+    async fn block(
+        &self,
+        hash: Option<CryptoHash>,
+        chain_id: ChainId,
+    ) -> Result<Option<ConfirmedBlock>, Error> {
+        let client = make_chain_client(&self.client, chain_id);
+        let hash = match hash {
+            Some(hash) => Some(hash),
+            None => client.chain_state_view().await?.tip_state.get().block_hash,
+        };
+        let Some(hash) = hash else {
+            return Ok(None);
+        };
+        Ok(Some(client.read_confirmed_block(hash).await?))
+    }
+
+    /// Returns up to `limit` blocks of the chain, from the one with the given hash or the
+    /// latest one backwards.
+    async fn blocks(
+        &self,
+        from: Option<CryptoHash>,
+        chain_id: ChainId,
+        limit: Option<u32>,
+    ) -> Result<Vec<ConfirmedBlock>, Error> {
+        let client = make_chain_client(&self.client, chain_id);
+        let limit = limit.unwrap_or(10);
+        let from = match from {
+            Some(from) => Some(from),
+            None => client.chain_state_view().await?.tip_state.get().block_hash,
+        };
+        let mut hash = from;
+        let mut values = Vec::new();
+        for _ in 0..limit {
+            let Some(next_hash) = hash else {
+                break;
+            };
+            let value = client.read_confirmed_block(next_hash).await?;
+            hash = value.block().header.previous_block_hash;
+            values.push(value);
+        }
+        Ok(values)
+    }
+
+    /// Returns the version information on this archive node.
+    async fn version(&self) -> linera_version::VersionInfo {
+        linera_version::VersionInfo::default()
+    }
+}
+
+#[Subscription]
+impl<S: environment::Storage> SubscriptionRoot<S> {
+    /// Subscribes to notifications from the specified chain.
+    async fn notifications(
+        &self,
+        chain_id: ChainId,
+    ) -> Result<impl Stream<Item = Notification>, Error> {
+        Ok(make_chain_client(&self.client, chain_id)
+            .subscribe()
+            .await?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use linera_base::{crypto::CryptoHash, data_types::BlockHeight, identifiers::ChainId};
+
+    use super::{
+        chains_to_resume, record_scanned_blocks, Backoff, ScannedBlocks, MAX_RETRY_DELAY,
+        MIN_RETRY_DELAY,
+    };
+
+    fn chain(name: &str) -> ChainId {
+        ChainId(CryptoHash::test_hash(name))
+    }
+
+    #[test]
+    fn test_follow_created_chains() {
+        let mut chains = BTreeMap::from([(chain("admin"), BlockHeight::ZERO)]);
+        let scanned = ScannedBlocks {
+            chain_id: chain("admin"),
+            next_height: BlockHeight(3),
+            created_chains: vec![chain("new"), chain("admin")],
+        };
+        assert_eq!(record_scanned_blocks(&mut chains, scanned), [chain("new")]);
+        let scanned = ScannedBlocks {
+            chain_id: chain("new"),
+            next_height: BlockHeight(1),
+            created_chains: vec![chain("new")],
+        };
+        assert!(record_scanned_blocks(&mut chains, scanned).is_empty());
+        assert_eq!(
+            chains,
+            BTreeMap::from([
+                (chain("admin"), BlockHeight(3)),
+                (chain("new"), BlockHeight(1)),
+            ])
+        );
+    }
+
+    #[test]
+    fn test_resume_followed_chains() -> anyhow::Result<()> {
+        let genesis_chains = [chain("admin"), chain("user")];
+        let chains = chains_to_resume(None, genesis_chains)?;
+        assert_eq!(
+            chains,
+            BTreeMap::from([
+                (chain("admin"), BlockHeight::ZERO),
+                (chain("user"), BlockHeight::ZERO),
+            ])
+        );
+
+        let checkpointed = BTreeMap::from([
+            (chain("admin"), BlockHeight(5)),
+            (chain("created"), BlockHeight(2)),
+        ]);
+        let checkpoint = bcs::to_bytes(&checkpointed)?;
+        let chains = chains_to_resume(Some(checkpoint), genesis_chains)?;
+        assert_eq!(
+            chains,
+            BTreeMap::from([
+                (chain("admin"), BlockHeight(5)),
+                (chain("created"), BlockHeight(2)),
+                (chain("user"), BlockHeight::ZERO),
+            ])
+        );
+
+        assert!(chains_to_resume(Some(vec![1]), genesis_chains).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_retry_backoff_after_failures() {
+        let mut backoff = Backoff::default();
+        assert_eq!(backoff.next_delay(), MIN_RETRY_DELAY);
+        assert_eq!(backoff.next_delay(), MIN_RETRY_DELAY * 2);
+        assert_eq!(backoff.next_delay(), MIN_RETRY_DELAY * 4);
+        for _ in 0..10 {
+            assert!(backoff.next_delay() <= MAX_RETRY_DELAY);
+        }
+        assert_eq!(backoff.next_delay(), MAX_RETRY_DELAY);
+        backoff.reset();
+        assert_eq!(backoff.next_delay(), MIN_RETRY_DELAY);
+    }
+}
@@ -0,0 +1,179 @@
+// Copyright (c) Zefchain Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! An admin endpoint of a validator shard, to inspect and prune the consensus state of the
+//! chains it handles, to fail over between the instances of an active/passive pair, and to
+//! report the usage of its storage.
+
+use std::net::SocketAddr;
+
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    routing::{get, post},
+    Json, Router,
+};
+use linera_base::identifiers::ChainId;
+use linera_core::{
+    chain_diagnosis::{ChainDiagnosis, RoundEntries},
+    worker::{ShutdownStatus, WorkerError, WorkerState},
+};
+use linera_storage::Storage;
+use linera_views::store::NamespaceStats;
+use tokio_util::sync::CancellationToken;
+use tracing::info;
+
+/// Serves the admin endpoint of the shard running `worker` on `address` until `shutdown` is
+/// cancelled. The routes are not authenticated, so `address` must only be reachable locally:
+/// - `GET /chains/{chain_id}/manager` returns the proposals and votes of a chain's manager by
+///   round, as JSON;
+/// - `POST /chains/{chain_id}/manager/prune` prunes the stale ones;
+/// - `GET /chains/{chain_id}/diagnosis` reports what keeps a chain from advancing, as JSON;
+/// - `GET /signing` returns the fencing term of the shard if it is the primary;
+/// - `POST /signing/promote` makes it the primary, fencing off the other instance;
+/// - `POST /signing/demote` makes it the standby;
+/// - `GET /shutdown` returns how far the shard has gone in shutting down, as JSON, with
+///   the status `503 Service Unavailable` while it is draining;
+/// - `GET /storage/stats` returns the approximate number of keys and bytes in the namespace
+///   of `storage`, as JSON. Metered backends also record them in their metrics.
+pub async fn serve_chain_manager_admin<S>(
+    worker: WorkerState<S>,
+    storage: S,
+    address: SocketAddr,
+    shutdown: CancellationToken,
+) -> anyhow::Result<()>
+where
+    S: Storage + Clone + Send + Sync + 'static,
+{
+    let app = Router::new()
+        .route("/chains/{chain_id}/manager", get(entries_handler::<S>))
+        .route("/chains/{chain_id}/manager/prune", post(prune_handler::<S>))
+        .route("/chains/{chain_id}/diagnosis", get(diagnosis_handler::<S>))
+        .route("/signing", get(signing_handler::<S>))
+        .route("/signing/promote", post(promote_handler::<S>))
+        .route("/signing/demote", post(demote_handler::<S>))
+        .route("/shutdown", get(shutdown_handler::<S>))
+        .with_state(worker)
+        .merge(
+            Router::new()
+                .route("/storage/stats", get(storage_stats_handler::<S>))
+                .with_state(storage),
+        );
+    let listener = tokio::net::TcpListener::bind(address).await?;
+    info!("Chain manager admin endpoint listening on {address}");
+    axum::serve(listener, app)
+        .with_graceful_shutdown(shutdown.cancelled_owned())
+        .await?;
+    Ok(())
+}
+
+async fn entries_handler<S>(
+    State(worker): State<WorkerState<S>>,
+    Path(chain_id): Path<ChainId>,
+) -> Result<Json<Vec<RoundEntries>>, (StatusCode, String)>
+where
+    S: Storage + Clone + Send + Sync + 'static,
+{
+    let entries = worker
+        .chain_manager_entries(chain_id)
+        .await
+        .map_err(|error| (StatusCode::INTERNAL_SERVER_ERROR, error.to_string()))?;
+    Ok(Json(
+        entries
+            .into_iter()
+            .map(|(round, entries)| RoundEntries { round, entries })
+            .collect(),
+    ))
+}
+
+async fn prune_handler<S>(
+    State(worker): State<WorkerState<S>>,
+    Path(chain_id): Path<ChainId>,
+) -> (StatusCode, String)
+where
+    S: Storage + Clone + Send + Sync + 'static,
+{
+    match worker.prune_chain_manager(chain_id).await {
+        Ok(pruned) => (
+            StatusCode::OK,
+            format!("Pruned {pruned} entries from the manager of chain {chain_id}"),
+        ),
+        Err(error) => (StatusCode::INTERNAL_SERVER_ERROR, error.to_string()),
+    }
+}
+
+async fn diagnosis_handler<S>(
+    State(worker): State<WorkerState<S>>,
+    Path(chain_id): Path<ChainId>,
+) -> Result<Json<ChainDiagnosis>, (StatusCode, String)>
+where
+    S: Storage + Clone + Send + Sync + 'static,
+{
+    let diagnosis = worker
+        .diagnose_chain(chain_id)
+        .await
+        .map_err(|error| (StatusCode::INTERNAL_SERVER_ERROR, error.to_string()))?;
+    Ok(Json(diagnosis))
+}
+
+async fn signing_handler<S>(State(worker): State<WorkerState<S>>) -> (StatusCode, String)
+where
+    S: Storage + Clone + Send + Sync + 'static,
+{
+    match worker.signing_term() {
+        Ok(Some(term)) => (StatusCode::OK, format!("Primary in term {term}")),
+        Ok(None) => (StatusCode::OK, "Standby".to_string()),
+        Err(error) => (StatusCode::BAD_REQUEST, error.to_string()),
+    }
+}
+
+async fn promote_handler<S>(State(worker): State<WorkerState<S>>) -> (StatusCode, String)
+where
+    S: Storage + Clone + Send + Sync + 'static,
+{
+    match worker.promote_to_primary().await {
+        Ok(term) => (
+            StatusCode::OK,
+            format!("Promoted to primary in term {term}"),
+        ),
+        Err(error @ WorkerError::NoSigningFence) => (StatusCode::BAD_REQUEST, error.to_string()),
+        Err(error) => (StatusCode::INTERNAL_SERVER_ERROR, error.to_string()),
+    }
+}
+
+async fn demote_handler<S>(State(worker): State<WorkerState<S>>) -> (StatusCode, String)
+where
+    S: Storage + Clone + Send + Sync + 'static,
+{
+    match worker.demote_to_standby() {
+        Ok(()) => (StatusCode::OK, "Demoted to standby".to_string()),
+        Err(error) => (StatusCode::BAD_REQUEST, error.to_string()),
+    }
+}
+
+async fn shutdown_handler<S>(
+    State(worker): State<WorkerState<S>>,
+) -> (StatusCode, Json<ShutdownStatus>)
+where
+    S: Storage + Clone + Send + Sync + 'static,
+{
+    let status = worker.shutdown_status();
+    let code = match status {
+        ShutdownStatus::Draining => StatusCode::SERVICE_UNAVAILABLE,
+        _ => StatusCode::OK,
+    };
+    (code, Json(status))
+}
+
+async fn storage_stats_handler<S>(
+    State(storage): State<S>,
+) -> Result<Json<NamespaceStats>, (StatusCode, String)>
+where
+    S: Storage + Clone + Send + Sync + 'static,
+{
+    let stats = storage
+        .namespace_stats()
+        .await
+        .map_err(|error| (StatusCode::INTERNAL_SERVER_ERROR, error.to_string()))?;
+    Ok(Json(stats))
+}
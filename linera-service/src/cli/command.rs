@@ -1,13 +1,15 @@
 // Copyright (c) Zefchain Labs, Inc.
 // SPDX-License-Identifier: Apache-2.0
 
-use std::{borrow::Cow, num::NonZeroU16, path::PathBuf};
+use std::{borrow::Cow, collections::BTreeSet, num::NonZeroU16, path::PathBuf};
 
 use chrono::{DateTime, Utc};
 use linera_base::{
     crypto::{AccountPublicKey, CryptoHash, ValidatorPublicKey},
-    data_types::{Amount, Epoch},
-    identifiers::{Account, AccountOwner, ApplicationId, ChainId, ModuleId, StreamId},
+    data_types::{Amount, BlockHeight, Epoch},
+    identifiers::{
+        Account, AccountOwner, ApplicationId, ChainId, GenericApplicationId, ModuleId, StreamId,
+    },
     time::Duration,
     vm::VmRuntime,
 };
@@ -116,8 +118,10 @@ impl Default for BenchmarkCommand {
 
 #[cfg(feature = "kubernetes")]
 use crate::cli_wrappers::local_kubernetes_net::BuildMode;
-use crate::util::{
-    DEFAULT_PAUSE_AFTER_GQL_MUTATIONS_SECS, DEFAULT_PAUSE_AFTER_LINERA_SERVICE_SECS,
+use crate::{
+    query_cache::QueryCacheConfig,
+    service_auth::ServiceAuthConfig,
+    util::{DEFAULT_PAUSE_AFTER_GQL_MUTATIONS_SECS, DEFAULT_PAUSE_AFTER_LINERA_SERVICE_SECS},
 };
 
 #[derive(Clone, clap::Subcommand)]
@@ -136,6 +140,27 @@ pub enum ClientCommand {
         amount: Amount,
     },
 
+    /// Withdraw funds from the account that one of our chains owns on another chain
+    ///
+    /// The owner of that account is the owning chain, formatted as `chain-<CHAIN_ID>`. Anyone
+    /// can transfer funds to it, but only the owners of the owning chain can withdraw them.
+    WithdrawFromChainAccount {
+        /// The chain owning the account (must be one of our chains)
+        #[arg(long = "owner-chain")]
+        chain_id: ChainId,
+
+        /// The chain holding the account
+        #[arg(long = "from")]
+        target_id: ChainId,
+
+        /// Recipient account
+        #[arg(long = "to")]
+        recipient: Account,
+
+        /// Amount to withdraw
+        amount: Amount,
+    },
+
     /// Open (i.e. activate) a new chain deriving the UID from an existing one.
     OpenChain {
         /// Chain ID (must be one of our chains).
@@ -202,6 +227,116 @@ pub enum ClientCommand {
 
         #[clap(flatten)]
         application_permissions_config: ApplicationPermissionsConfig,
+
+        /// Stage the new permissions, so that they only apply from the block this many blocks
+        /// after the next one. Messages already sent to the chain can be processed meanwhile.
+        #[arg(long)]
+        after_blocks: Option<u64>,
+
+        /// Stage the new permissions, so that they only apply from the first block with this
+        /// timestamp or a later one, in microseconds since the Unix epoch.
+        #[arg(long, conflicts_with = "after_blocks")]
+        activation_timestamp: Option<u64>,
+    },
+
+    /// Changes the limits on the pending messages from each other chain in the inboxes of a
+    /// chain.
+    ///
+    /// Once the pending messages from a chain reach a limit, further messages from it are
+    /// refused until the inbox is processed. Messages from the admin chain are not limited.
+    ChangeInboxQuotas {
+        /// The ID of the chain whose inbox quotas are changed.
+        #[arg(long)]
+        chain_id: Option<ChainId>,
+
+        /// The maximal number of pending message bundles from the same chain. No limit if not
+        /// set.
+        #[arg(long)]
+        max_pending_bundles: Option<u32>,
+
+        /// The maximal total size in bytes of the pending message bundles from the same
+        /// chain. No limit if not set.
+        #[arg(long)]
+        max_pending_bytes: Option<u64>,
+    },
+
+    /// Sets the application that can authorize block proposals from accounts that are not
+    /// owners of a chain.
+    ///
+    /// Such proposals are only accepted in multi-leader rounds, and must start with an
+    /// operation carrying a payload that the application validates, e.g. multisig signatures.
+    ChangeAuthorizationApplication {
+        /// The ID of the chain whose authorization application is changed.
+        #[arg(long)]
+        chain_id: Option<ChainId>,
+
+        /// The authorization application. If not set, only the owners can propose blocks.
+        #[arg(long)]
+        application_id: Option<ApplicationId>,
+    },
+
+    /// Restricts which owners may execute the operations of which applications on a chain.
+    ///
+    /// Once restricted, blocks with operations that their signer is not allowed to execute
+    /// are rejected. This applies on top of the application permissions.
+    ChangeOperationPermissions {
+        /// The ID of the chain whose operation permissions are changed.
+        #[arg(long)]
+        chain_id: Option<ChainId>,
+
+        /// Allows an owner to execute the operations of the given applications, as
+        /// `OWNER=APPLICATION,...`, where each application is `System` for system operations or
+        /// `User:APPLICATION_ID`. Owners that are not listed cannot execute any operations.
+        #[arg(long = "allow", value_parser = parse_operation_permission)]
+        allowed_applications: Vec<(AccountOwner, BTreeSet<GenericApplicationId>)>,
+
+        /// Lift the restriction, so that all owners may execute all operations allowed by the
+        /// application permissions.
+        #[arg(long, conflicts_with = "allowed_applications")]
+        unrestricted: bool,
+    },
+
+    /// Sets the configuration of an application on a chain.
+    ///
+    /// The application can read its configuration on each chain during execution, so that
+    /// the same application can be tuned differently on different chains. Only the owners of
+    /// the chain can change it.
+    SetApplicationConfig {
+        /// The ID of the chain on which the configuration is set.
+        #[arg(long)]
+        chain_id: Option<ChainId>,
+
+        /// The application to configure.
+        application_id: ApplicationId,
+
+        /// The configuration as a JSON string.
+        #[arg(long)]
+        json_config: Option<String>,
+
+        /// Path to a JSON file containing the configuration.
+        #[arg(long)]
+        json_config_path: Option<PathBuf>,
+
+        /// Remove the configuration of the application on this chain instead.
+        #[arg(long, conflicts_with_all = ["json_config", "json_config_path"])]
+        remove: bool,
+    },
+
+    /// Upgrades the code of an application on a chain.
+    ///
+    /// The application runs the code of the given module on this chain from now on, and its
+    /// `on_upgrade` entrypoint is called so that it can migrate its state. Other chains keep
+    /// running its original code. Only the owners of the chain can upgrade it.
+    UpgradeApplication {
+        /// The ID of the chain on which the application is upgraded.
+        #[arg(long)]
+        chain_id: Option<ChainId>,
+
+        /// The application to upgrade.
+        application_id: ApplicationId,
+
+        /// The module with the new code, published with `publish-module`.
+        module_id: ModuleId,
     },
 
     /// Close an existing chain.
@@ -265,6 +400,33 @@ pub enum ClientCommand {
         chain_id: Option<ChainId>,
     },
 
+    /// Export the local state of a chain, its confirmed certificates and the recent logs to a
+    /// tar archive, to be attached to an issue report. The archive contains no private keys.
+    ExportDebugBundle {
+        /// The chain to export. If omitted, uses the default chain of the wallet.
+        chain_id: Option<ChainId>,
+
+        /// The height of the first certificate to include.
+        #[arg(long, default_value = "0")]
+        start_height: BlockHeight,
+
+        /// The maximum number of certificates to include. By default, all certificates from
+        /// the start height on are included.
+        #[arg(long)]
+        limit: Option<u64>,
+
+        /// The file to write the archive to, e.g. `bundle.tar`.
+        #[arg(long)]
+        output: PathBuf,
+    },
+
+    /// Re-drive the requests of a conversation recorded with `--record-conversations`
+    /// against the local node, and report where its responses differ from the validators'.
+    ReplayConversation {
+        /// The file containing the recorded conversation.
+        path: PathBuf,
+    },
+
     /// Show the version and genesis config hash of a new validator, and print a warning if it is
     /// incompatible. Also print some information about the given chain while we are at it.
     QueryValidator {
@@ -325,6 +487,13 @@ pub enum ClientCommand {
         public_key: ValidatorPublicKey,
     },
 
+    /// Publish the new network address of a validator, from an announcement signed with
+    /// `linera-server sign-address` (admin only)
+    AnnounceValidatorAddress {
+        /// Path to the file containing the signed announcement.
+        announcement: PathBuf,
+    },
+
     /// Deprecates all committees up to and including the specified one.
     RevokeEpochs { epoch: Epoch },
 
@@ -402,6 +571,10 @@ pub enum ClientCommand {
         #[arg(long)]
         http_request: Option<Amount>,
 
+        /// Set the fixed price of a system read, such as the chain balance or ownership.
+        #[arg(long)]
+        system_read: Option<Amount>,
+
         /// Set the maximum amount of Wasm fuel per block.
         #[arg(long)]
         maximum_wasm_fuel_per_block: Option<u64>,
@@ -458,6 +631,58 @@ pub enum ClientCommand {
         /// Set the list of hosts that contracts and services can send HTTP requests to.
         #[arg(long)]
         http_request_allow_list: Option<Vec<String>>,
+
+        /// Set whether Wasm contract bytecode may use floating-point values.
+        #[arg(long)]
+        allow_wasm_floats: Option<bool>,
+
+        /// Set the hashes of the contract bytecode blobs that may use floating-point values
+        /// even if they are not allowed in general.
+        #[arg(long)]
+        wasm_float_contracts: Option<Vec<CryptoHash>>,
+
+        /// Set the maximum number of values a block can read from the state of its
+        /// applications on other chains.
+        #[arg(long)]
+        maximum_cross_chain_reads_per_block: Option<u64>,
+
+        /// Set the maximum age in milliseconds of the incoming bundles a block can accept.
+        #[arg(long)]
+        maximum_incoming_bundle_age_ms: Option<u64>,
+
+        /// Set the maximum amount of fuel that a chain's authorization application can consume
+        /// to accept a block proposal from a non-owner.
+        #[arg(long)]
+        maximum_authorization_fuel: Option<u64>,
+
+        /// Set the maximum number of bytes of deterministic entropy that the applications can
+        /// draw in a single transaction.
+        #[arg(long)]
+        maximum_entropy_per_transaction: Option<u64>,
+
+        /// Set the maximum number of applications in the call stack of a transaction.
+        #[arg(long)]
+        maximum_call_depth: Option<u64>,
+
+        /// Set the maximum number of speculative calls that the applications can make in a
+        /// single transaction.
+        #[arg(long)]
+        maximum_speculative_calls_per_transaction: Option<u64>,
+
+        /// Set whether each block publishes a summary of the resources it consumed as a
+        /// system event.
+        #[arg(long)]
+        publish_resource_usage: Option<bool>,
+
+        /// Set whether the state hash of each block commits to the storage of the
+        /// applications as Merkle trees, so that single values can be proven.
+        #[arg(long)]
+        merkle_state_hash: Option<bool>,
+
+        /// Set the maximum number of chains that can subscribe to a single channel of an
+        /// application.
+        #[arg(long)]
+        maximum_channel_subscribers: Option<u64>,
     },
 
     /// Start a benchmark, maintaining a given TPS or just sending one transfer per chain in bulk mode.
@@ -606,6 +831,11 @@ pub enum ClientCommand {
         #[arg(long)]
         http_request_price: Option<Amount>,
 
+        /// Set the fixed price of a system read, such as the chain balance or ownership.
+        /// (This will overwrite value from `--policy-config`)
+        #[arg(long)]
+        system_read_price: Option<Amount>,
+
         /// Set the maximum amount of Wasm fuel per block.
         /// (This will overwrite value from `--policy-config`)
         #[arg(long)]
@@ -673,6 +903,58 @@ pub enum ClientCommand {
         #[arg(long)]
         http_request_allow_list: Option<Vec<String>>,
 
+        /// Set whether Wasm contract bytecode may use floating-point values.
+        #[arg(long)]
+        allow_wasm_floats: Option<bool>,
+
+        /// Set the hashes of the contract bytecode blobs that may use floating-point values
+        /// even if they are not allowed in general.
+        #[arg(long)]
+        wasm_float_contracts: Option<Vec<CryptoHash>>,
+
+        /// Set the maximum number of values a block can read from the state of its
+        /// applications on other chains.
+        #[arg(long)]
+        maximum_cross_chain_reads_per_block: Option<u64>,
+
+        /// Set the maximum age in milliseconds of the incoming bundles a block can accept.
+        #[arg(long)]
+        maximum_incoming_bundle_age_ms: Option<u64>,
+
+        /// Set the maximum amount of fuel that a chain's authorization application can consume
+        /// to accept a block proposal from a non-owner.
+        #[arg(long)]
+        maximum_authorization_fuel: Option<u64>,
+
+        /// Set the maximum number of bytes of deterministic entropy that the applications can
+        /// draw in a single transaction.
+        #[arg(long)]
+        maximum_entropy_per_transaction: Option<u64>,
+
+        /// Set the maximum number of applications in the call stack of a transaction.
+        #[arg(long)]
+        maximum_call_depth: Option<u64>,
+
+        /// Set the maximum number of speculative calls that the applications can make in a
+        /// single transaction.
+        #[arg(long)]
+        maximum_speculative_calls_per_transaction: Option<u64>,
+
+        /// Set whether each block publishes a summary of the resources it consumed as a
+        /// system event.
+        #[arg(long)]
+        publish_resource_usage: Option<bool>,
+
+        /// Set whether the state hash of each block commits to the storage of the
+        /// applications as Merkle trees, so that single values can be proven.
+        #[arg(long)]
+        merkle_state_hash: Option<bool>,
+
+        /// Set the maximum number of chains that can subscribe to a single channel of an
+        /// application.
+        #[arg(long)]
+        maximum_channel_subscribers: Option<u64>,
+
         /// Force this wallet to generate keys using a PRNG and a given seed. USE FOR
         /// TESTING ONLY.
         #[arg(long)]
@@ -698,6 +980,12 @@ pub enum ClientCommand {
         #[command(flatten)]
         config: ChainListenerConfig,
 
+        #[command(flatten)]
+        query_cache_config: QueryCacheConfig,
+
+        #[command(flatten)]
+        auth_config: ServiceAuthConfig,
+
         /// The port on which to run the server
         #[arg(long)]
         port: NonZeroU16,
@@ -874,6 +1162,48 @@ pub enum ClientCommand {
         chain_id: Option<ChainId>,
     },
 
+    /// Export the proposal of the next block to a file, to be signed with `linera sign-proposal`
+    /// on a machine holding the owner's key, e.g. an air-gapped one.
+    ///
+    /// The proposal is for a new block with the given operations if any, and otherwise for the
+    /// pending block or a validated block that must be retried. The hash to be signed is
+    /// printed, so that it can be compared with the one shown by `linera sign-proposal`.
+    ExportProposal {
+        /// The chain to propose a block for. If not specified, the wallet's default chain is
+        /// used.
+        chain_id: Option<ChainId>,
+
+        /// A JSON file with the operations of a new block to propose.
+        #[arg(long)]
+        operations_path: Option<PathBuf>,
+
+        /// The file to write the signing request to.
+        #[arg(long)]
+        output: PathBuf,
+    },
+
+    /// Sign a block proposal exported with `linera export-proposal`, using a key of the
+    /// keystore. This doesn't need access to the network or the storage.
+    SignProposal {
+        /// The file containing the signing request.
+        request: PathBuf,
+
+        /// The file to write the signature to.
+        #[arg(long)]
+        output: PathBuf,
+    },
+
+    /// Submit a block proposal exported with `linera export-proposal` to the validators, with
+    /// the signature created by `linera sign-proposal`.
+    SubmitProposal {
+        /// The file containing the signing request.
+        request: PathBuf,
+
+        /// The file containing the signature.
+        #[arg(long)]
+        signature: PathBuf,
+    },
+
     /// Show the contents of the wallet.
     #[command(subcommand)]
     Wallet(WalletCommand),
@@ -916,22 +1246,31 @@ impl ClientCommand {
     pub fn log_file_name(&self) -> Cow<'static, str> {
         match self {
             ClientCommand::Transfer { .. }
+            | ClientCommand::WithdrawFromChainAccount { .. }
             | ClientCommand::OpenChain { .. }
             | ClientCommand::OpenMultiOwnerChain { .. }
             | ClientCommand::ChangeOwnership { .. }
             | ClientCommand::SetPreferredOwner { .. }
             | ClientCommand::ChangeApplicationPermissions { .. }
+            | ClientCommand::ChangeInboxQuotas { .. }
+            | ClientCommand::ChangeAuthorizationApplication { .. }
+            | ClientCommand::ChangeOperationPermissions { .. }
+            | ClientCommand::SetApplicationConfig { .. }
+            | ClientCommand::UpgradeApplication { .. }
             | ClientCommand::CloseChain { .. }
             | ClientCommand::LocalBalance { .. }
             | ClientCommand::QueryBalance { .. }
             | ClientCommand::SyncBalance { .. }
             | ClientCommand::Sync { .. }
             | ClientCommand::ProcessInbox { .. }
+            | ClientCommand::ExportDebugBundle { .. }
+            | ClientCommand::ReplayConversation { .. }
             | ClientCommand::QueryValidator { .. }
             | ClientCommand::QueryValidators { .. }
             | ClientCommand::SyncValidator { .. }
             | ClientCommand::SetValidator { .. }
             | ClientCommand::RemoveValidator { .. }
+            | ClientCommand::AnnounceValidatorAddress { .. }
             | ClientCommand::ResourceControlPolicy { .. }
             | ClientCommand::RevokeEpochs { .. }
             | ClientCommand::CreateGenesisConfig { .. }
@@ -944,7 +1283,10 @@ impl ClientCommand {
             | ClientCommand::Keygen
             | ClientCommand::Assign { .. }
             | ClientCommand::Wallet { .. }
-            | ClientCommand::RetryPendingBlock { .. } => "client".into(),
+            | ClientCommand::RetryPendingBlock { .. }
+            | ClientCommand::ExportProposal { .. }
+            | ClientCommand::SignProposal { .. }
+            | ClientCommand::SubmitProposal { .. } => "client".into(),
             #[cfg(feature = "benchmark")]
             ClientCommand::Benchmark { .. } => "benchmark".into(),
             #[cfg(feature = "benchmark")]
@@ -987,6 +1329,13 @@ pub enum DatabaseToolCommand {
 
     /// List the chain IDs in the database
     ListChainIds,
+
+    /// Print the approximate number of keys and bytes stored in a namespace
+    NamespaceStats,
+
+    /// Remove the duplicated certificate data from the database, and index the blocks by
+    /// chain and height
+    DeduplicateCertificates,
 }
 
 #[allow(clippy::large_enum_variant)]
@@ -1060,6 +1409,13 @@ pub enum NetCommand {
         #[arg(long)]
         path: Option<String>,
 
+        /// Save the data of the storage service started for the network to this file when the
+        /// network is terminated, and restart the network from it if the file exists. This
+        /// requires `--path`, where the configuration of the network is kept, and cannot be
+        /// used with `--storage`.
+        #[arg(long)]
+        storage_snapshot: Option<PathBuf>,
+
         /// External protocol used, either `grpc` or `grpcs`.
         #[arg(long, default_value = "grpc")]
         external_protocol: String,
@@ -1167,6 +1523,20 @@ pub enum WalletCommand {
 
     /// Forgets the specified chain, including the associated key pair.
     ForgetChain { chain_id: ChainId },
+
+    /// Trust the admin chain checkpoint in the given JSON file instead of verifying the admin
+    /// chain from genesis.
+    SetCheckpoint {
+        /// The path to the trusted checkpoint.
+        path: PathBuf,
+    },
+
+    /// Follow the admin chain forward from the trusted checkpoint, and save and print the
+    /// latest checkpoint.
+    UpdateCheckpoint,
+
+    /// Check that a quorum of the checkpoint's validators confirms the trusted checkpoint.
+    AuditCheckpoint,
 }
 
 #[derive(Clone, clap::Parser)]
@@ -1208,6 +1578,11 @@ pub enum ProjectCommand {
         #[arg(long, default_value = "wasm")]
         vm_runtime: VmRuntime,
 
+        /// Publish the contract and the service wrapped into WebAssembly components. The
+        /// validators execute the wrapped core modules.
+        #[arg(long)]
+        wrap_in_component: bool,
+
         /// The shared parameters as JSON string.
         #[arg(long)]
         json_parameters: Option<String>,
@@ -1229,3 +1604,21 @@ pub enum ProjectCommand {
         required_application_ids: Option<Vec<ApplicationId>>,
     },
 }
+
+/// Parses an owner and the applications whose operations it may execute, as
+/// `OWNER=APPLICATION,...`.
+fn parse_operation_permission(
+    s: &str,
+) -> anyhow::Result<(AccountOwner, BTreeSet<GenericApplicationId>)> {
+    let (owner, application_ids) = s
+        .split_once('=')
+        .ok_or_else(|| anyhow::anyhow!("Expecting OWNER=APPLICATION,..."))?;
+    let application_ids = match application_ids.trim() {
+        "" => BTreeSet::new(),
+        application_ids => application_ids
+            .split(',')
+            .map(str::parse)
+            .collect::<Result<_, _>>()?,
+    };
+    Ok((owner.parse()?, application_ids))
+}
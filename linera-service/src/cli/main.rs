@@ -20,12 +20,13 @@ use chrono::Utc;
 use colored::Colorize;
 use futures::{lock::Mutex, FutureExt as _, StreamExt};
 use linera_base::{
-    crypto::{InMemorySigner, Signer},
-    data_types::{ApplicationPermissions, Timestamp},
+    crypto::{AccountSignature, InMemorySigner, Signer},
+    data_types::{ApplicationPermissions, BlockHeight, Timestamp},
     identifiers::AccountOwner,
     listen_for_shutdown_signals,
     ownership::ChainOwnership,
 };
+use linera_chain::data_types::ProposalSigningRequest;
 use linera_client::{
     chain_listener::ClientContext as _,
     client_context::ClientContext,
@@ -34,11 +35,20 @@ use linera_client::{
     wallet::{UserChain, Wallet},
 };
 use linera_core::{
-    data_types::ClientOutcome, node::ValidatorNodeProvider, worker::Reason, JoinSetExt as _,
+    client::checkpoint::TrustedCheckpoint,
+    data_types::{BlockHeightRange, ClientOutcome},
+    node::ValidatorNodeProvider,
+    recording::{replay_conversation, ConversationRecorder},
+    worker::Reason,
+    JoinSetExt as _,
 };
 use linera_execution::{
     committee::{Committee, ValidatorState},
-    WasmRuntime, WithWasmDefault as _,
+    system::{
+        InboxQuotas, OperationPermissions, PermissionsActivation, Recipient,
+        SignedValidatorAddress, StagedApplicationPermissions,
+    },
+    Operation, WasmRuntime, WithWasmDefault as _,
 };
 use linera_faucet_server::{FaucetConfig, FaucetService};
 use linera_persistent::{self as persistent, Persist, PersistExt as _};
@@ -138,6 +148,45 @@ impl Runnable for Job {
                 debug!("{:?}", certificate);
             }
 
+            WithdrawFromChainAccount {
+                chain_id,
+                target_id,
+                recipient,
+                amount,
+            } => {
+                let mut context = ClientContext::new(
+                    storage,
+                    options.context_options.clone(),
+                    wallet,
+                    signer.into_value(),
+                );
+                let chain_client = context.make_chain_client(chain_id);
+                info!(
+                    "Starting withdrawal of {} native tokens from the account of chain {} on \
+                    chain {} to {}",
+                    amount, chain_id, target_id, recipient
+                );
+                let time_start = Instant::now();
+                let certificate = context
+                    .apply_client_command(&chain_client, |chain_client| {
+                        let chain_client = chain_client.clone();
+                        async move {
+                            chain_client
+                                .withdraw_from_chain_account(
+                                    target_id,
+                                    Recipient::Account(recipient),
+                                    amount,
+                                )
+                                .await
+                        }
+                    })
+                    .await
+                    .context("Failed to make withdrawal")?;
+                let time_total = time_start.elapsed();
+                info!("Withdrawal confirmed after {} ms", time_total.as_millis());
+                debug!("{:?}", certificate);
+            }
+
             OpenChain {
                 chain_id,
                 owner,
@@ -261,6 +310,8 @@ impl Runnable for Job {
             ChangeApplicationPermissions {
                 chain_id,
                 application_permissions_config,
+                after_blocks,
+                activation_timestamp,
             } => {
                 let mut context = ClientContext::new(
                     storage,
@@ -274,14 +325,32 @@ impl Runnable for Job {
                 let time_start = Instant::now();
                 let application_permissions =
                     ApplicationPermissions::from(application_permissions_config);
+                let activation = if let Some(after_blocks) = after_blocks {
+                    let next_height = chain_client.chain_info().await?.next_block_height;
+                    let height = next_height.saturating_add(BlockHeight(after_blocks));
+                    Some(PermissionsActivation::Height(height))
+                } else {
+                    activation_timestamp
+                        .map(|micros| PermissionsActivation::Timestamp(Timestamp::from(micros)))
+                };
                 let certificate = context
                     .apply_client_command(&chain_client, |chain_client| {
                         let application_permissions = application_permissions.clone();
                         let chain_client = chain_client.clone();
                         async move {
-                            chain_client
-                                .change_application_permissions(application_permissions)
-                                .await
+                            if let Some(activation) = activation {
+                                let staged = StagedApplicationPermissions {
+                                    application_permissions,
+                                    activation,
+                                };
+                                chain_client
+                                    .stage_application_permissions(Some(staged))
+                                    .await
+                            } else {
+                                chain_client
+                                    .change_application_permissions(application_permissions)
+                                    .await
+                            }
                         }
                     })
                     .await
@@ -294,6 +363,193 @@ impl Runnable for Job {
                 debug!("{:?}", certificate);
             }
 
+            ChangeInboxQuotas {
+                chain_id,
+                max_pending_bundles,
+                max_pending_bytes,
+            } => {
+                let mut context = ClientContext::new(
+                    storage,
+                    options.context_options.clone(),
+                    wallet,
+                    signer.into_value(),
+                );
+                let chain_id = chain_id.unwrap_or_else(|| context.default_chain());
+                let chain_client = context.make_chain_client(chain_id);
+                info!("Changing inbox quotas for chain {}", chain_id);
+                let time_start = Instant::now();
+                let inbox_quotas = InboxQuotas {
+                    max_pending_bundles,
+                    max_pending_bytes,
+                };
+                let certificate = context
+                    .apply_client_command(&chain_client, |chain_client| {
+                        let chain_client = chain_client.clone();
+                        async move { chain_client.change_inbox_quotas(inbox_quotas).await }
+                    })
+                    .await
+                    .context("Failed to change inbox quotas")?;
+                let time_total = time_start.elapsed();
+                info!(
+                    "Changing inbox quotas confirmed after {} ms",
+                    time_total.as_millis()
+                );
+                debug!("{:?}", certificate);
+            }
+
+            ChangeAuthorizationApplication {
+                chain_id,
+                application_id,
+            } => {
+                let mut context = ClientContext::new(
+                    storage,
+                    options.context_options.clone(),
+                    wallet,
+                    signer.into_value(),
+                );
+                let chain_id = chain_id.unwrap_or_else(|| context.default_chain());
+                let chain_client = context.make_chain_client(chain_id);
+                info!("Changing authorization application for chain {}", chain_id);
+                let time_start = Instant::now();
+                let certificate = context
+                    .apply_client_command(&chain_client, |chain_client| {
+                        let chain_client = chain_client.clone();
+                        async move {
+                            chain_client
+                                .change_authorization_application(application_id)
+                                .await
+                        }
+                    })
+                    .await
+                    .context("Failed to change authorization application")?;
+                let time_total = time_start.elapsed();
+                info!(
+                    "Changing authorization application confirmed after {} ms",
+                    time_total.as_millis()
+                );
+                debug!("{:?}", certificate);
+            }
+
+            ChangeOperationPermissions {
+                chain_id,
+                allowed_applications,
+                unrestricted,
+            } => {
+                let mut context = ClientContext::new(
+                    storage,
+                    options.context_options.clone(),
+                    wallet,
+                    signer.into_value(),
+                );
+                let chain_id = chain_id.unwrap_or_else(|| context.default_chain());
+                let chain_client = context.make_chain_client(chain_id);
+                info!("Changing operation permissions for chain {}", chain_id);
+                let time_start = Instant::now();
+                let operation_permissions = (!unrestricted).then(|| OperationPermissions {
+                    allowed_applications: allowed_applications.into_iter().collect(),
+                });
+                let certificate = context
+                    .apply_client_command(&chain_client, |chain_client| {
+                        let chain_client = chain_client.clone();
+                        let operation_permissions = operation_permissions.clone();
+                        async move {
+                            chain_client
+                                .change_operation_permissions(operation_permissions)
+                                .await
+                        }
+                    })
+                    .await
+                    .context("Failed to change operation permissions")?;
+                let time_total = time_start.elapsed();
+                info!(
+                    "Changing operation permissions confirmed after {} ms",
+                    time_total.as_millis()
+                );
+                debug!("{:?}", certificate);
+            }
+
+            SetApplicationConfig {
+                chain_id,
+                application_id,
+                json_config,
+                json_config_path,
+                remove,
+            } => {
+                let mut context = ClientContext::new(
+                    storage,
+                    options.context_options.clone(),
+                    wallet,
+                    signer.into_value(),
+                );
+                let chain_id = chain_id.unwrap_or_else(|| context.default_chain());
+                let chain_client = context.make_chain_client(chain_id);
+                let config = if remove {
+                    None
+                } else {
+                    Some(read_json(json_config, json_config_path)?)
+                };
+                info!(
+                    "Setting the configuration of application {} on chain {}",
+                    application_id, chain_id
+                );
+                let time_start = Instant::now();
+                let certificate = context
+                    .apply_client_command(&chain_client, |chain_client| {
+                        let chain_client = chain_client.clone();
+                        let config = config.clone();
+                        async move {
+                            chain_client
+                                .set_application_config(application_id, config)
+                                .await
+                        }
+                    })
+                    .await
+                    .context("Failed to set application configuration")?;
+                let time_total = time_start.elapsed();
+                info!(
+                    "Setting application configuration confirmed after {} ms",
+                    time_total.as_millis()
+                );
+                debug!("{:?}", certificate);
+            }
+
+            UpgradeApplication {
+                chain_id,
+                application_id,
+                module_id,
+            } => {
+                let mut context = ClientContext::new(
+                    storage,
+                    options.context_options.clone(),
+                    wallet,
+                    signer.into_value(),
+                );
+                let chain_id = chain_id.unwrap_or_else(|| context.default_chain());
+                let chain_client = context.make_chain_client(chain_id);
+                info!(
+                    "Upgrading application {} on chain {} to module {}",
+                    application_id, chain_id, module_id
+                );
+                let time_start = Instant::now();
+                let certificate = context
+                    .apply_client_command(&chain_client, |chain_client| {
+                        let chain_client = chain_client.clone();
+                        async move {
+                            chain_client
+                                .upgrade_application(application_id, module_id)
+                                .await
+                        }
+                    })
+                    .await
+                    .context("Failed to upgrade application")?;
+                let time_total = time_start.elapsed();
+                info!(
+                    "Upgrading application confirmed after {} ms",
+                    time_total.as_millis()
+                );
+                debug!("{:?}", certificate);
+            }
+
             CloseChain { chain_id } => {
                 let mut context = ClientContext::new(
                     storage,
@@ -427,6 +683,81 @@ impl Runnable for Job {
                 );
             }
 
+            ExportDebugBundle {
+                chain_id,
+                start_height,
+                limit,
+                output,
+            } => {
+                let context = ClientContext::new(
+                    storage,
+                    options.context_options.clone(),
+                    wallet,
+                    signer.into_value(),
+                );
+                let chain_id = chain_id.unwrap_or_else(|| context.default_chain());
+                let chain_client = context.make_chain_client(chain_id);
+                let range = BlockHeightRange {
+                    start: start_height,
+                    limit,
+                };
+                let bundle = chain_client.debug_bundle(range).await?;
+                fs_err::write(&output, bundle.to_archive()?)?;
+                info!(
+                    "Exported chain {} with {} certificates to {}",
+                    chain_id,
+                    bundle.certificates.len(),
+                    output.display()
+                );
+            }
+
+            ReplayConversation { path } => {
+                let context = ClientContext::new(
+                    storage,
+                    options.context_options.clone(),
+                    wallet,
+                    signer.into_value(),
+                );
+                let exchanges = ConversationRecorder::read_file(&path)
+                    .with_context(|| format!("Unable to read {}", path.display()))?;
+                let outcomes = replay_conversation(exchanges, context.client.local_node()).await;
+                let mut divergences = 0;
+                for (index, outcome) in outcomes.iter().enumerate() {
+                    let exchange = &outcome.exchange;
+                    let validator = exchange
+                        .validator
+                        .map_or_else(|| exchange.address.clone(), |key| key.to_string());
+                    let latency = exchange.received_at.delta_since(exchange.sent_at);
+                    let recorded = match &exchange.response {
+                        Ok(_) => "ok".to_string(),
+                        Err(error) => format!("error: {error}"),
+                    };
+                    let replayed = match &outcome.replayed {
+                        None => "not replayed".to_string(),
+                        Some(Ok(_)) => "ok".to_string(),
+                        Some(Err(error)) => format!("error: {error}"),
+                    };
+                    let marker = if outcome.diverges() {
+                        divergences += 1;
+                        " DIVERGES"
+                    } else {
+                        ""
+                    };
+                    println!(
+                        "#{index} [{}] {validator} {} ({} us): recorded {recorded}, \
+                         replayed {replayed}{marker}",
+                        exchange.sent_at,
+                        exchange.request.kind(),
+                        latency.as_micros(),
+                    );
+                }
+                info!(
+                    "Replayed {} exchanges, {} of which diverged",
+                    outcomes.len(),
+                    divergences
+                );
+            }
+
             QueryValidator {
                 address,
                 chain_id,
@@ -649,6 +980,7 @@ impl Runnable for Job {
                                     message_byte,
                                     service_as_oracle_query,
                                     http_request,
+                                    system_read,
                                     maximum_wasm_fuel_per_block,
                                     maximum_evm_fuel_per_block,
                                     maximum_service_oracle_execution_ms,
@@ -663,6 +995,17 @@ impl Runnable for Job {
                                     maximum_http_response_bytes,
                                     http_request_timeout_ms,
                                     http_request_allow_list,
+                                    allow_wasm_floats,
+                                    wasm_float_contracts,
+                                    maximum_cross_chain_reads_per_block,
+                                    maximum_incoming_bundle_age_ms,
+                                    maximum_authorization_fuel,
+                                    maximum_entropy_per_transaction,
+                                    maximum_call_depth,
+                                    maximum_speculative_calls_per_transaction,
+                                    publish_resource_usage,
+                                    merkle_state_hash,
+                                    maximum_channel_subscribers,
                                 } => {
                                     let existing_policy = policy.clone();
                                     policy = linera_execution::ResourceControlPolicy {
@@ -698,6 +1041,8 @@ impl Runnable for Job {
                                             .unwrap_or(existing_policy.service_as_oracle_query),
                                         http_request: http_request
                                             .unwrap_or(existing_policy.http_request),
+                                        system_read: system_read
+                                            .unwrap_or(existing_policy.system_read),
                                         maximum_wasm_fuel_per_block: maximum_wasm_fuel_per_block
                                             .unwrap_or(existing_policy.maximum_wasm_fuel_per_block),
                                         maximum_evm_fuel_per_block: maximum_evm_fuel_per_block
@@ -735,6 +1080,38 @@ impl Runnable for Job {
                                         http_request_allow_list: http_request_allow_list
                                             .map(BTreeSet::from_iter)
                                             .unwrap_or(existing_policy.http_request_allow_list),
+                                        allow_wasm_floats: allow_wasm_floats
+                                            .unwrap_or(existing_policy.allow_wasm_floats),
+                                        wasm_float_contracts: wasm_float_contracts
+                                            .map(BTreeSet::from_iter)
+                                            .unwrap_or(existing_policy.wasm_float_contracts),
+                                        maximum_cross_chain_reads_per_block:
+                                            maximum_cross_chain_reads_per_block.unwrap_or(
+                                                existing_policy.maximum_cross_chain_reads_per_block,
+                                            ),
+                                        maximum_incoming_bundle_age_ms:
+                                            maximum_incoming_bundle_age_ms.unwrap_or(
+                                                existing_policy.maximum_incoming_bundle_age_ms,
+                                            ),
+                                        maximum_authorization_fuel: maximum_authorization_fuel
+                                            .unwrap_or(existing_policy.maximum_authorization_fuel),
+                                        maximum_entropy_per_transaction:
+                                            maximum_entropy_per_transaction.unwrap_or(
+                                                existing_policy.maximum_entropy_per_transaction,
+                                            ),
+                                        maximum_call_depth: maximum_call_depth
+                                            .unwrap_or(existing_policy.maximum_call_depth),
+                                        maximum_speculative_calls_per_transaction:
+                                            maximum_speculative_calls_per_transaction.unwrap_or(
+                                                existing_policy
+                                                    .maximum_speculative_calls_per_transaction,
+                                            ),
+                                        publish_resource_usage: publish_resource_usage
+                                            .unwrap_or(existing_policy.publish_resource_usage),
+                                        merkle_state_hash: merkle_state_hash
+                                            .unwrap_or(existing_policy.merkle_state_hash),
+                                        maximum_channel_subscribers: maximum_channel_subscribers
+                                            .unwrap_or(existing_policy.maximum_channel_subscribers),
                                     };
                                     info!("{policy}");
                                     if committee.policy() == &policy {
@@ -761,6 +1138,41 @@ impl Runnable for Job {
                 info!("Operations confirmed after {} ms", time_total.as_millis());
             }
 
+            AnnounceValidatorAddress { announcement } => {
+                let announcement: SignedValidatorAddress = util::read_json(&announcement)
+                    .context("Failed to read the validator address announcement")?;
+                announcement
+                    .check()
+                    .context("The announcement is not signed by the validator")?;
+                info!(
+                    "Announcing address {} of validator {}",
+                    announcement.address.network_address, announcement.address.public_key
+                );
+                let time_start = Instant::now();
+                let mut context = ClientContext::new(
+                    storage,
+                    options.context_options.clone(),
+                    wallet,
+                    signer.into_value(),
+                );
+                let chain_client = context.make_chain_client(context.wallet.genesis_admin_chain());
+                context
+                    .apply_client_command(&chain_client, |chain_client| {
+                        let chain_client = chain_client.clone();
+                        let announcement = announcement.clone();
+                        async move { chain_client.announce_validator_address(announcement).await }
+                    })
+                    .await
+                    .context("Failed to announce the validator address")?;
+                context.save_wallet().await?;
+
+                let time_total = time_start.elapsed();
+                info!(
+                    "Validator address announced after {} ms",
+                    time_total.as_millis()
+                );
+            }
+
             RevokeEpochs { epoch } => {
                 info!("Starting operations to remove old committees");
                 let time_start = Instant::now();
@@ -927,7 +1339,12 @@ impl Runnable for Job {
                 info!("Notification stream ended.");
             }
 
-            Service { config, port } => {
+            Service {
+                config,
+                query_cache_config,
+                auth_config,
+                port,
+            } => {
                 let context = ClientContext::new(
                     storage,
                     options.context_options.clone(),
@@ -936,7 +1353,10 @@ impl Runnable for Job {
                 );
 
                 let default_chain = context.wallet().default_chain();
-                let service = NodeService::new(config, port, default_chain, context).await;
+                let service = NodeService::new(config, port, default_chain, context)
+                    .await
+                    .with_query_cache(&query_cache_config)
+                    .with_auth(&auth_config)?;
                 let cancellation_token = CancellationToken::new();
                 let child_token = cancellation_token.child_token();
                 tokio::spawn(listen_for_shutdown_signals(cancellation_token));
@@ -1207,6 +1627,7 @@ impl Runnable for Job {
                     path,
                     name,
                     vm_runtime,
+                    wrap_in_component,
                     publisher,
                     json_parameters,
                     json_parameters_path,
@@ -1230,7 +1651,7 @@ impl Runnable for Job {
                     let project_path = path.unwrap_or_else(|| env::current_dir().unwrap());
 
                     let project = project::Project::from_existing_project(project_path)?;
-                    let (contract_path, service_path) = project.build(name)?;
+                    let (contract_path, service_path) = project.build(name, wrap_in_component)?;
 
                     let module_id = context
                         .publish_module(&chain_client, contract_path, service_path, vm_runtime)
@@ -1293,6 +1714,80 @@ impl Runnable for Job {
                 );
             }
 
+            ExportProposal {
+                chain_id,
+                operations_path,
+                output,
+            } => {
+                let mut context = ClientContext::new(
+                    storage,
+                    options.context_options.clone(),
+                    wallet,
+                    signer.into_value(),
+                );
+                let chain_id = chain_id.unwrap_or_else(|| context.default_chain());
+                let chain_client = context.make_chain_client(chain_id);
+                let operations: Vec<Operation> = match &operations_path {
+                    Some(path) => util::read_json(path)
+                        .with_context(|| format!("Unable to read {}", path.display()))?,
+                    None => Vec::new(),
+                };
+                let outcome = chain_client.export_proposal(operations, Vec::new()).await?;
+                context.update_wallet_from_client(&chain_client).await?;
+                match outcome {
+                    ClientOutcome::Committed(Some(request)) => {
+                        let file = std::fs::File::create(&output)
+                            .with_context(|| format!("Unable to create {}", output.display()))?;
+                        serde_json::to_writer_pretty(file, &request)?;
+                        info!(
+                            "Exported the proposal of block {} of chain {} in round {} to {}",
+                            request.content.block.height,
+                            chain_id,
+                            request.content.round,
+                            output.display()
+                        );
+                        println!("{}", request.signing_hash());
+                    }
+                    ClientOutcome::Committed(None) => info!("No block needs to be proposed."),
+                    ClientOutcome::WaitForTimeout(timeout) => {
+                        info!("Please try again at {}", timeout.timestamp)
+                    }
+                }
+            }
+
+            SubmitProposal { request, signature } => {
+                let mut context = ClientContext::new(
+                    storage,
+                    options.context_options.clone(),
+                    wallet,
+                    signer.into_value(),
+                );
+                let request: ProposalSigningRequest = util::read_json(&request)
+                    .with_context(|| format!("Unable to read {}", request.display()))?;
+                let signature: AccountSignature = util::read_json(&signature)
+                    .with_context(|| format!("Unable to read {}", signature.display()))?;
+                let chain_id = request.content.block.chain_id;
+                info!(
+                    "Submitting the proposal with hash {} signed by {}",
+                    request.signing_hash(),
+                    signature.owner()
+                );
+                let chain_client = context.make_chain_client(chain_id);
+                match chain_client
+                    .submit_signed_proposal(request, signature)
+                    .await?
+                {
+                    ClientOutcome::Committed(certificate) => {
+                        info!("Block committed successfully.");
+                        println!("{}", certificate.hash());
+                    }
+                    ClientOutcome::WaitForTimeout(timeout) => {
+                        info!("Please try again at {}", timeout.timestamp)
+                    }
+                }
+                context.update_wallet_from_client(&chain_client).await?;
+            }
+
             Wallet(WalletCommand::RequestChain {
                 faucet: faucet_url,
                 set_default,
@@ -1351,12 +1846,38 @@ impl Runnable for Job {
                 );
             }
 
+            Wallet(WalletCommand::UpdateCheckpoint) => {
+                let mut context = ClientContext::new(
+                    storage,
+                    options.context_options.clone(),
+                    wallet,
+                    signer.into_value(),
+                );
+                let checkpoint = context.client.update_trusted_checkpoint().await?;
+                println!("{}", serde_json::to_string_pretty(&checkpoint)?);
+                context
+                    .mutate_wallet(|w| w.trusted_checkpoint = Some(checkpoint))
+                    .await?;
+            }
+
+            Wallet(WalletCommand::AuditCheckpoint) => {
+                let context = ClientContext::new(
+                    storage,
+                    options.context_options.clone(),
+                    wallet,
+                    signer.into_value(),
+                );
+                context.client.audit_trusted_checkpoint().await?;
+                info!("The trusted checkpoint is confirmed by a quorum of validators");
+            }
+
             #[cfg(feature = "benchmark")]
             MultiBenchmark { .. } => {
                 unreachable!()
             }
             CreateGenesisConfig { .. }
             | Keygen
+            | SignProposal { .. }
             | Net(_)
             | Storage { .. }
             | Wallet(_)
@@ -1392,6 +1913,11 @@ struct ClientOptions {
     #[arg(long)]
     wasm_runtime: Option<WasmRuntime>,
 
+    /// The JSON-RPC endpoint of the Ethereum node serving the Ethereum data requested by
+    /// contracts, when executing blocks. The node is trusted to serve the canonical chain.
+    #[arg(long)]
+    ethereum_endpoint: Option<String>,
+
     /// The number of Tokio worker threads to use.
     #[arg(long, env = "LINERA_CLIENT_TOKIO_THREADS")]
     tokio_threads: Option<usize>,
@@ -1416,9 +1942,14 @@ impl ClientOptions {
         let store_config = storage_config
             .add_common_storage_options(&self.common_storage_options)
             .await?;
-        let output =
-            Box::pin(store_config.run_with_storage(self.wasm_runtime.with_wasm_default(), job))
-                .await?;
+        let mut options = self.common_storage_options.db_storage_options().await?;
+        options.execution_runtime_config.ethereum_endpoint = self.ethereum_endpoint.clone();
+        let output = Box::pin(store_config.run_with_storage(
+            self.wasm_runtime.with_wasm_default(),
+            &options,
+            job,
+        ))
+        .await?;
         Ok(output)
     }
 
@@ -1641,6 +2172,27 @@ impl RunnableWithStore for DatabaseToolJob<'_> {
                     println!("{}", id);
                 }
             }
+            DatabaseToolCommand::NamespaceStats => {
+                let stats = D::connect(&config, &namespace)
+                    .await?
+                    .namespace_stats()
+                    .await?;
+                info!(
+                    "Usage of namespace {namespace} computed in {} ms",
+                    start_time.elapsed().as_millis()
+                );
+                println!("{}", serde_json::to_string_pretty(&stats)?);
+            }
+            DatabaseToolCommand::DeduplicateCertificates => {
+                let report =
+                    DbStorage::<D, _>::deduplicate_certificates(&config, &namespace).await?;
+                info!(
+                    "Removed {} duplicated block headers and indexed {} blocks in {} ms",
+                    report.removed_headers,
+                    report.indexed_blocks,
+                    start_time.elapsed().as_millis()
+                );
+            }
         }
         Ok(0)
     }
@@ -1750,6 +2302,7 @@ async fn run(options: &ClientOptions) -> Result<i32, Error> {
             message_byte_price,
             service_as_oracle_query_price,
             http_request_price,
+            system_read_price,
             maximum_wasm_fuel_per_block,
             maximum_evm_fuel_per_block,
             maximum_service_oracle_execution_ms,
@@ -1764,6 +2317,17 @@ async fn run(options: &ClientOptions) -> Result<i32, Error> {
             maximum_http_response_bytes,
             http_request_timeout_ms,
             http_request_allow_list,
+            allow_wasm_floats,
+            wasm_float_contracts,
+            maximum_cross_chain_reads_per_block,
+            maximum_incoming_bundle_age_ms,
+            maximum_authorization_fuel,
+            maximum_entropy_per_transaction,
+            maximum_call_depth,
+            maximum_speculative_calls_per_transaction,
+            publish_resource_usage,
+            merkle_state_hash,
+            maximum_channel_subscribers,
             testing_prng_seed,
             network_name,
         } => {
@@ -1792,6 +2356,7 @@ async fn run(options: &ClientOptions) -> Result<i32, Error> {
                 service_as_oracle_query: service_as_oracle_query_price
                     .unwrap_or(existing_policy.service_as_oracle_query),
                 http_request: http_request_price.unwrap_or(existing_policy.http_request),
+                system_read: system_read_price.unwrap_or(existing_policy.system_read),
                 maximum_wasm_fuel_per_block: maximum_wasm_fuel_per_block
                     .unwrap_or(existing_policy.maximum_wasm_fuel_per_block),
                 maximum_evm_fuel_per_block: maximum_evm_fuel_per_block
@@ -1821,6 +2386,29 @@ async fn run(options: &ClientOptions) -> Result<i32, Error> {
                     .as_ref()
                     .map(|list| list.iter().cloned().collect())
                     .unwrap_or(existing_policy.http_request_allow_list),
+                allow_wasm_floats: allow_wasm_floats.unwrap_or(existing_policy.allow_wasm_floats),
+                wasm_float_contracts: wasm_float_contracts
+                    .as_ref()
+                    .map(|hashes| hashes.iter().cloned().collect())
+                    .unwrap_or(existing_policy.wasm_float_contracts),
+                maximum_cross_chain_reads_per_block: maximum_cross_chain_reads_per_block
+                    .unwrap_or(existing_policy.maximum_cross_chain_reads_per_block),
+                maximum_incoming_bundle_age_ms: maximum_incoming_bundle_age_ms
+                    .unwrap_or(existing_policy.maximum_incoming_bundle_age_ms),
+                maximum_authorization_fuel: maximum_authorization_fuel
+                    .unwrap_or(existing_policy.maximum_authorization_fuel),
+                maximum_entropy_per_transaction: maximum_entropy_per_transaction
+                    .unwrap_or(existing_policy.maximum_entropy_per_transaction),
+                maximum_call_depth: maximum_call_depth
+                    .unwrap_or(existing_policy.maximum_call_depth),
+                maximum_speculative_calls_per_transaction:
+                    maximum_speculative_calls_per_transaction
+                        .unwrap_or(existing_policy.maximum_speculative_calls_per_transaction),
+                publish_resource_usage: publish_resource_usage
+                    .unwrap_or(existing_policy.publish_resource_usage),
+                merkle_state_hash: merkle_state_hash.unwrap_or(existing_policy.merkle_state_hash),
+                maximum_channel_subscribers: maximum_channel_subscribers
+                    .unwrap_or(existing_policy.maximum_channel_subscribers),
             };
             let timestamp = start_timestamp
                 .map(|st| {
@@ -1916,6 +2504,30 @@ async fn run(options: &ClientOptions) -> Result<i32, Error> {
             Ok(0)
         }
 
+        ClientCommand::SignProposal { request, output } => {
+            let signer = options.signer().await?;
+            let request: ProposalSigningRequest = util::read_json(request)
+                .with_context(|| format!("Unable to read {}", request.display()))?;
+            request.check_version()?;
+            let block = &request.content.block;
+            info!(
+                "Signing the proposal of block {} of chain {} in round {} as {}, \
+                 with {} incoming bundles and {} operations",
+                block.height,
+                block.chain_id,
+                request.content.round,
+                request.owner,
+                block.incoming_bundles.len(),
+                block.operations.len(),
+            );
+            println!("{}", request.signing_hash());
+            let signature = request.sign(&*signer).await?;
+            let file = std::fs::File::create(output)
+                .with_context(|| format!("Unable to create {}", output.display()))?;
+            serde_json::to_writer_pretty(file, &signature)?;
+            Ok(0)
+        }
+
         ClientCommand::Net(net_command) => match net_command {
             #[cfg(feature = "kubernetes")]
             NetCommand::Up {
@@ -1968,6 +2580,7 @@ async fn run(options: &ClientOptions) -> Result<i32, Error> {
                 policy_config,
                 cross_chain_config,
                 path,
+                storage_snapshot,
                 external_protocol,
                 with_faucet,
                 faucet_chain,
@@ -1992,6 +2605,7 @@ async fn run(options: &ClientOptions) -> Result<i32, Error> {
                     path,
                     // Not using the default value for storage
                     &options.storage_config,
+                    storage_snapshot,
                     external_protocol.clone(),
                     *with_faucet,
                     *faucet_chain,
@@ -2111,7 +2725,20 @@ async fn run(options: &ClientOptions) -> Result<i32, Error> {
                 Ok(0)
             }
 
-            WalletCommand::RequestChain { .. } => {
+            WalletCommand::SetCheckpoint { path } => {
+                let checkpoint: TrustedCheckpoint = util::read_json(path)?;
+                options
+                    .wallet()
+                    .await?
+                    .mutate(|w| w.trusted_checkpoint = Some(checkpoint))
+                    .await?;
+                info!("Trusted checkpoint set");
+                Ok(0)
+            }
+
+            WalletCommand::RequestChain { .. }
+            | WalletCommand::UpdateCheckpoint
+            | WalletCommand::AuditCheckpoint => {
                 options.run_with_storage(Job(options.clone())).await??;
                 Ok(0)
             }
@@ -1,7 +1,7 @@
 // Copyright (c) Zefchain Labs, Inc.
 // SPDX-License-Identifier: Apache-2.0
 
-use std::{num::NonZeroU16, str::FromStr};
+use std::{num::NonZeroU16, path::PathBuf, str::FromStr};
 
 use colored::Colorize as _;
 use linera_base::{data_types::Amount, listen_for_shutdown_signals, time::Duration};
@@ -14,12 +14,9 @@ use linera_storage_service::{
 };
 use tokio_util::sync::CancellationToken;
 use tracing::info;
-#[cfg(feature = "kubernetes")]
-use {
-    crate::cli_wrappers::local_kubernetes_net::{BuildMode, LocalKubernetesNetConfig},
-    std::path::PathBuf,
-};
 
+#[cfg(feature = "kubernetes")]
+use crate::cli_wrappers::local_kubernetes_net::{BuildMode, LocalKubernetesNetConfig};
 use crate::{
     cli_wrappers::{
         local_net::{
@@ -33,20 +30,31 @@ use crate::{
 struct StorageConfigProvider {
     /// The storage config.
     config: StorageConfig,
+    /// The storage service started for the network, if any.
     #[cfg(feature = "storage-service")]
-    _service_guard: Option<StorageServiceGuard>,
+    service_guard: Option<StorageServiceGuard>,
 }
 
 impl StorageConfigProvider {
-    pub async fn new(storage: &Option<String>) -> anyhow::Result<StorageConfigProvider> {
+    /// Returns the given storage, or starts a storage service, which saves its data to
+    /// `snapshot_path` when terminated, if any.
+    pub async fn new(
+        storage: &Option<String>,
+        snapshot_path: Option<PathBuf>,
+    ) -> anyhow::Result<StorageConfigProvider> {
+        if storage.is_some() && snapshot_path.is_some() {
+            anyhow::bail!("A storage snapshot can only be used without `--storage`");
+        }
         match storage {
             #[cfg(feature = "storage-service")]
             None => {
                 let service_endpoint = linera_base::port::get_free_endpoint().await?;
                 let binary = get_service_storage_binary().await?.display().to_string();
-                let service = StorageService::new(&service_endpoint, binary);
-                let _service_guard = service.run().await?;
-                let _service_guard = Some(_service_guard);
+                let mut service = StorageService::new(&service_endpoint, binary);
+                if let Some(snapshot_path) = snapshot_path {
+                    service = service.with_snapshot_path(snapshot_path);
+                }
+                let service_guard = Some(service.run().await?);
                 let inner_storage_config = InnerStorageConfig::Service {
                     endpoint: service_endpoint,
                 };
@@ -57,7 +65,7 @@ impl StorageConfigProvider {
                 };
                 Ok(StorageConfigProvider {
                     config,
-                    _service_guard,
+                    service_guard,
                 })
             }
             #[cfg(not(feature = "storage-service"))]
@@ -69,7 +77,7 @@ impl StorageConfigProvider {
                 let config = StorageConfig::from_str(storage)?;
                 Ok(StorageConfigProvider {
                     config,
-                    _service_guard: None,
+                    service_guard: None,
                 })
             }
             #[cfg(not(feature = "storage-service"))]
@@ -80,6 +88,16 @@ impl StorageConfigProvider {
         }
     }
 
+    /// Terminates the storage service started for the network, if any, letting it save its
+    /// snapshot.
+    pub async fn terminate(self) -> anyhow::Result<()> {
+        #[cfg(feature = "storage-service")]
+        if let Some(service_guard) = self.service_guard {
+            service_guard.terminate().await?;
+        }
+        Ok(())
+    }
+
     pub fn inner_storage_config(&self) -> &InnerStorageConfig {
         &self.config.inner_storage_config
     }
@@ -166,7 +184,7 @@ pub async fn handle_net_up_kubernetes(
         num_other_initial_chains,
     )
     .await?;
-    wait_for_shutdown(shutdown_notifier, &mut net, faucet_service).await
+    wait_for_shutdown(shutdown_notifier, &mut net, faucet_service, None).await
 }
 
 #[expect(clippy::too_many_arguments)]
@@ -183,6 +201,7 @@ pub async fn handle_net_up_service(
     block_exporter_port: NonZeroU16,
     path: &Option<String>,
     storage: &Option<String>,
+    storage_snapshot: &Option<PathBuf>,
     external_protocol: String,
     with_faucet: bool,
     faucet_chain: Option<u32>,
@@ -199,7 +218,14 @@ pub async fn handle_net_up_service(
     let shutdown_notifier = CancellationToken::new();
     tokio::spawn(listen_for_shutdown_signals(shutdown_notifier.clone()));
 
-    let storage = StorageConfigProvider::new(storage).await?;
+    if storage_snapshot.is_some() && path.is_none() {
+        anyhow::bail!("A storage snapshot can only be used with `--path`");
+    }
+    // The network is restarted if the storage and the configuration files were preserved.
+    let restart = storage_snapshot
+        .as_ref()
+        .is_some_and(|snapshot_path| snapshot_path.exists());
+    let storage = StorageConfigProvider::new(storage, storage_snapshot.clone()).await?;
     let storage_config = storage.inner_storage_config().clone();
     let namespace = storage.namespace().to_string();
     let database = storage.database()?;
@@ -238,7 +264,12 @@ pub async fn handle_net_up_service(
         path_provider,
         block_exporters,
     };
-    let (mut net, client) = config.instantiate().await?;
+    let (mut net, client) = if restart {
+        info!("Restarting the local test network from the storage snapshot");
+        config.restart().await?
+    } else {
+        config.instantiate().await?
+    };
     let faucet_service = print_messages_and_create_faucet(
         client,
         with_faucet,
@@ -249,13 +280,14 @@ pub async fn handle_net_up_service(
     )
     .await?;
 
-    wait_for_shutdown(shutdown_notifier, &mut net, faucet_service).await
+    wait_for_shutdown(shutdown_notifier, &mut net, faucet_service, Some(storage)).await
 }
 
 async fn wait_for_shutdown(
     shutdown_notifier: CancellationToken,
     net: &mut impl LineraNet,
     faucet_service: Option<FaucetService>,
+    storage: Option<StorageConfigProvider>,
 ) -> anyhow::Result<()> {
     shutdown_notifier.cancelled().await;
     eprintln!();
@@ -265,6 +297,9 @@ async fn wait_for_shutdown(
     }
     eprintln!("Terminating the local test network");
     net.terminate().await?;
+    if let Some(storage) = storage {
+        storage.terminate().await?;
+    }
     eprintln!("Done.");
 
     Ok(())
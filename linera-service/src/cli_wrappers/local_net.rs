@@ -354,6 +354,36 @@ impl LineraNetConfig for LocalNetConfig {
     }
 }
 
+impl LocalNetConfig {
+    /// Restarts a network that was started before with the same configuration and the same
+    /// external path, and whose storage was preserved: the validator configurations, the
+    /// genesis configuration and the wallet found in the path are reused, and the storage is
+    /// not initialized again.
+    pub async fn restart(self) -> Result<(LocalNet, ClientWrapper)> {
+        let storage_config = self.storage_config_builder.build(self.database).await?;
+        let mut net = LocalNet::new(
+            self.network,
+            self.testing_prng_seed,
+            self.namespace,
+            self.num_initial_validators,
+            self.num_shards,
+            self.num_proxies,
+            storage_config,
+            self.cross_chain_config,
+            self.path_provider,
+            self.block_exporters,
+        )?;
+        let client = net.make_client().await;
+        for validator in 0..self.num_initial_validators {
+            let storage = net.validator_storage(validator);
+            net.initialized_validator_storages
+                .insert(validator, storage);
+            net.restart_validator(validator).await?;
+        }
+        Ok((net, client))
+    }
+}
+
 #[async_trait]
 impl LineraNet for LocalNet {
     async fn ensure_is_running(&mut self) -> Result<()> {
@@ -820,13 +850,18 @@ impl LocalNet {
         bail!("Failed to start {nickname}");
     }
 
-    async fn initialize_storage(&mut self, validator: usize) -> Result<()> {
+    /// Returns the storage configuration of the given validator.
+    fn validator_storage(&self, validator: usize) -> StorageConfig {
         let namespace = format!("{}_server_{}_db", self.common_namespace, validator);
         let inner_storage_config = self.common_storage_config.clone();
-        let storage = StorageConfig {
+        StorageConfig {
             inner_storage_config,
             namespace,
-        };
+        }
+    }
+
+    async fn initialize_storage(&mut self, validator: usize) -> Result<()> {
+        let storage = self.validator_storage(validator);
         let mut command = self.command_for_binary("linera").await?;
         if let Ok(var) = env::var(SERVER_ENV) {
             command.args(var.split_whitespace());
@@ -177,7 +177,15 @@ impl ExporterOptions {
                 .add_common_storage_options(&self.common_storage_options)
                 .await
                 .unwrap();
-            store_config.run_with_storage(None, context).boxed().await
+            let options = self
+                .common_storage_options
+                .db_storage_options()
+                .await
+                .unwrap();
+            store_config
+                .run_with_storage(None, &options, context)
+                .boxed()
+                .await
         };
 
         runtime.block_on(future)?.map_err(|e| e.into())
@@ -179,6 +179,8 @@ impl DummyValidator {
 impl ValidatorNode for DummyValidator {
     type SubscribeStream =
         UnboundedReceiverStream<Result<linera_rpc::grpc::api::Notification, Status>>;
+    type StreamCertificatesStream =
+        UnboundedReceiverStream<Result<linera_rpc::grpc::api::StreamedCertificate, Status>>;
 
     async fn handle_confirmed_certificate(
         &self,
@@ -228,8 +230,15 @@ impl ValidatorNode for DummyValidator {
             requested_committees: None,
             requested_pending_message_bundles: vec![],
             requested_sent_certificate_hashes: vec![],
+            next_sent_certificate_hashes_cursor: None,
             count_received_log: 0,
             requested_received_log: vec![],
+            next_received_log_cursor: None,
+            requested_vote_audit_log: vec![],
+            requested_event_streams: vec![],
+            truncated_fields: Default::default(),
+            requested_state_inclusion_proof: None,
+            migrated_epoch: None,
         };
 
         let response = if missing_blobs.is_empty() {
@@ -368,12 +377,26 @@ impl ValidatorNode for DummyValidator {
         unimplemented!()
     }
 
+    async fn blob_provenance(
+        &self,
+        _request: Request<linera_rpc::grpc::api::BlobId>,
+    ) -> Result<Response<linera_rpc::grpc::api::BlobProvenance>, Status> {
+        unimplemented!()
+    }
+
     async fn missing_blob_ids(
         &self,
         _request: Request<BlobIds>,
     ) -> Result<Response<BlobIds>, Status> {
         unimplemented!()
     }
+
+    async fn stream_certificates(
+        &self,
+        _request: Request<linera_rpc::grpc::api::StreamCertificatesRequest>,
+    ) -> Result<Response<Self::StreamCertificatesStream>, Status> {
+        unimplemented!()
+    }
 }
 
 #[async_trait]
@@ -6,11 +6,17 @@
 
 #![deny(clippy::large_futures)]
 
+pub mod archive_node;
+pub mod chain_manager_admin;
 pub mod cli;
 pub mod cli_wrappers;
 pub mod config;
 pub mod node_service;
 pub mod project;
+pub mod query_cache;
+pub mod service_auth;
+pub mod shutdown;
 pub mod storage;
+pub mod task_manager;
 pub mod util;
 pub mod wallet;
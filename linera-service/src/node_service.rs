@@ -9,13 +9,18 @@ use async_graphql::{
 };
 use async_graphql_axum::{GraphQLRequest, GraphQLResponse, GraphQLSubscription};
 use axum::{extract::Path, http::StatusCode, response, response::IntoResponse, Extension, Router};
+use base64::{engine::general_purpose::STANDARD as BASE64_STANDARD, Engine as _};
 use futures::{lock::Mutex, Future, FutureExt as _};
 use linera_base::{
     crypto::{CryptoError, CryptoHash},
     data_types::{
-        Amount, ApplicationDescription, ApplicationPermissions, Bytecode, Epoch, TimeDelta,
+        Amount, ApplicationDescription, ApplicationPermissions, BlockHeight, Bytecode, Epoch,
+        OperationMetadata, TimeDelta,
+    },
+    identifiers::{
+        AccountOwner, ApplicationId, ChainId, EventStreamInfo, GenericApplicationId, IndexAndEvent,
+        ModuleId, StreamId,
     },
-    identifiers::{AccountOwner, ApplicationId, ChainId, IndexAndEvent, ModuleId, StreamId},
     ownership::{ChainOwnership, TimeoutConfig},
     vm::VmRuntime,
     BcsHexParseError,
@@ -26,9 +31,13 @@ use linera_chain::{
 };
 use linera_client::chain_listener::{ChainListener, ChainListenerConfig, ClientContext};
 use linera_core::{
-    client::{ChainClient, ChainClientError},
-    data_types::ClientOutcome,
-    worker::Notification,
+    client::{
+        monitoring::{NetworkHealth, NetworkMonitor},
+        ChainClient, ChainClientError,
+    },
+    data_types::{BlockHeightRange, ClientOutcome},
+    local_node::LocalNodeError,
+    worker::{Notification, WorkerError},
 };
 use linera_execution::{
     committee::Committee,
@@ -44,7 +53,11 @@ use tokio_util::sync::CancellationToken;
 use tower_http::cors::CorsLayer;
 use tracing::{debug, error, info, instrument, trace};
 
-use crate::util;
+use crate::{
+    query_cache::{QueryCache, QueryCacheConfig, QueryKey},
+    service_auth::{self, Caller, QueryComplexityQuota, ServiceAuth, ServiceAuthConfig},
+    util,
+};
 
 #[derive(SimpleObject, Serialize, Deserialize, Clone)]
 pub struct Chains {
@@ -57,6 +70,7 @@ pub struct QueryRoot<C> {
     context: Arc<Mutex<C>>,
     port: NonZeroU16,
     default_chain: Option<ChainId>,
+    network_monitor: NetworkMonitor,
 }
 
 /// Our root GraphQL subscription type.
@@ -79,6 +93,8 @@ enum NodeServiceError {
     JsonError(#[from] serde_json::Error),
     #[error("malformed chain ID: {0}")]
     InvalidChainId(CryptoError),
+    #[error("queries at past block heights cannot schedule operations")]
+    OperationsAtPastHeight,
 }
 
 impl IntoResponse for NodeServiceError {
@@ -86,7 +102,22 @@ impl IntoResponse for NodeServiceError {
         let tuple = match self {
             NodeServiceError::BcsHexError(e) => (StatusCode::BAD_REQUEST, vec![e.to_string()]),
             NodeServiceError::ChainClientError(e) => {
-                (StatusCode::INTERNAL_SERVER_ERROR, vec![e.to_string()])
+                let body = json!({
+                    "error": [e.to_string()],
+                    "code": e.code(),
+                    "retriable": e.is_retriable(),
+                });
+                let status = if matches!(
+                    e,
+                    ChainClientError::LocalNodeError(LocalNodeError::WorkerError(
+                        WorkerError::HistoryUnavailable { .. }
+                    ))
+                ) {
+                    StatusCode::NOT_FOUND
+                } else {
+                    StatusCode::INTERNAL_SERVER_ERROR
+                };
+                return (status, body.to_string()).into_response();
             }
             NodeServiceError::JsonError(e) => {
                 (StatusCode::INTERNAL_SERVER_ERROR, vec![e.to_string()])
@@ -95,12 +126,44 @@ impl IntoResponse for NodeServiceError {
                 StatusCode::BAD_REQUEST,
                 vec!["invalid chain ID".to_string()],
             ),
+            NodeServiceError::OperationsAtPastHeight => {
+                (StatusCode::BAD_REQUEST, vec![self.to_string()])
+            }
         };
         let tuple = (tuple.0, json!({"error": tuple.1}).to_string());
         tuple.into_response()
     }
 }
 
+/// Returns the response of an application to a mutation, along with the operations it
+/// scheduled and their metadata, so that wallets can ask for confirmation before any block
+/// is proposed.
+fn prepared_response(
+    response: &[u8],
+    operations: &[Operation],
+    operation_metadata: &[Option<OperationMetadata>],
+) -> Result<Vec<u8>, serde_json::Error> {
+    let response: serde_json::Value = serde_json::from_slice(response)?;
+    serde_json::to_vec(&json!({
+        "response": response,
+        "operations": operations,
+        "operationMetadata": operation_metadata,
+    }))
+}
+
+/// Adds the code of the client error behind a GraphQL error to its extensions, along with
+/// whether the request may succeed if retried.
+fn add_error_code(error: &mut async_graphql::ServerError) {
+    let Some(client_error) = error.source::<ChainClientError>() else {
+        return;
+    };
+    let code = client_error.code().to_owned();
+    let retriable = client_error.is_retriable();
+    let extensions = error.extensions.get_or_insert_with(Default::default);
+    extensions.set("code", code);
+    extensions.set("retriable", retriable);
+}
+
 #[Subscription]
 impl<C> SubscriptionRoot<C>
 where
@@ -639,6 +702,22 @@ where
             .await?)
     }
 
+    /// Lists the streams that events were published on in the chain, with the index of
+    /// their latest event, optionally only the ones of the given application.
+    async fn event_streams(
+        &self,
+        chain_id: ChainId,
+        application_id: Option<GenericApplicationId>,
+    ) -> Result<Vec<EventStreamInfo>, Error> {
+        Ok(self
+            .context
+            .lock()
+            .await
+            .make_chain_client(chain_id)
+            .event_streams(application_id)
+            .await?)
+    }
+
     async fn blocks(
         &self,
         from: Option<CryptoHash>,
@@ -670,6 +749,34 @@ where
         Ok(values)
     }
 
+    /// Exports a snapshot of the chain state and of the confirmed certificates from
+    /// `start_height` on, up to `limit` of them, as a tar archive to attach to issue reports.
+    async fn export_chain_debug_bundle(
+        &self,
+        chain_id: ChainId,
+        start_height: BlockHeight,
+        limit: Option<u64>,
+    ) -> Result<Base64Bytes, Error> {
+        let client = self.context.lock().await.make_chain_client(chain_id);
+        let range = BlockHeightRange {
+            start: start_height,
+            limit,
+        };
+        let bundle = client.debug_bundle(range).await?;
+        Ok(Base64Bytes(bundle.to_archive()?))
+    }
+
+    /// Returns the latest snapshot of the committee's validators: their reachability and
+    /// their view of the chains tracked by this node service. The validators are probed
+    /// right away if no snapshot was taken yet.
+    async fn network_health(&self) -> Result<NetworkHealth, Error> {
+        if let Some(health) = self.network_monitor.latest() {
+            return Ok(health);
+        }
+        let client = self.context.lock().await.client().clone();
+        Ok(client.probe_network_health().await?)
+    }
+
     /// Returns the version information on this node service.
     async fn version(&self) -> linera_version::VersionInfo {
         linera_version::VersionInfo::default()
@@ -746,6 +853,23 @@ where
     }
 }
 
+/// Binary data, encoded in base64 in GraphQL.
+pub struct Base64Bytes(pub Vec<u8>);
+
+#[async_graphql::Scalar]
+impl ScalarType for Base64Bytes {
+    fn parse(value: async_graphql::Value) -> async_graphql::InputValueResult<Self> {
+        let async_graphql::Value::String(string) = &value else {
+            return Err(async_graphql::InputValueError::expected_type(value));
+        };
+        Ok(Base64Bytes(BASE64_STANDARD.decode(string)?))
+    }
+
+    fn to_value(&self) -> async_graphql::Value {
+        async_graphql::Value::String(BASE64_STANDARD.encode(&self.0))
+    }
+}
+
 #[derive(SimpleObject)]
 pub struct ApplicationOverview {
     id: ApplicationId,
@@ -783,6 +907,11 @@ where
     port: NonZeroU16,
     default_chain: Option<ChainId>,
     context: Arc<Mutex<C>>,
+    network_monitor: NetworkMonitor,
+    query_cache: Option<Arc<QueryCache>>,
+    auth: Arc<ServiceAuth>,
+    max_query_complexity: Option<usize>,
+    max_query_depth: Option<usize>,
 }
 
 impl<C> Clone for NodeService<C>
@@ -795,6 +924,11 @@ where
             port: self.port,
             default_chain: self.default_chain,
             context: Arc::clone(&self.context),
+            network_monitor: self.network_monitor.clone(),
+            query_cache: self.query_cache.clone(),
+            auth: self.auth.clone(),
+            max_query_complexity: self.max_query_complexity,
+            max_query_depth: self.max_query_depth,
         }
     }
 }
@@ -815,15 +949,37 @@ where
             port,
             default_chain,
             context: Arc::new(Mutex::new(context)),
+            network_monitor: NetworkMonitor::default(),
+            query_cache: None,
+            auth: Arc::default(),
+            max_query_complexity: None,
+            max_query_depth: None,
         }
     }
 
+    /// Authenticates the requests from other hosts, and limits the requests and the
+    /// GraphQL queries of each client. Fails if the files with the credentials can't be read.
+    pub fn with_auth(mut self, config: &ServiceAuthConfig) -> std::io::Result<Self> {
+        self.auth = Arc::new(ServiceAuth::new(config)?);
+        self.max_query_complexity = config.max_query_complexity;
+        self.max_query_depth = config.max_query_depth;
+        Ok(self)
+    }
+
+    /// Caches the responses of application services that don't schedule operations, until
+    /// the chain has a new block.
+    pub fn with_query_cache(mut self, config: &QueryCacheConfig) -> Self {
+        self.query_cache = QueryCache::new(config).map(Arc::new);
+        self
+    }
+
     pub fn schema(&self) -> Schema<QueryRoot<C>, MutationRoot<C>, SubscriptionRoot<C>> {
-        Schema::build(
+        let mut builder = Schema::build(
             QueryRoot {
                 context: Arc::clone(&self.context),
                 port: self.port,
                 default_chain: self.default_chain,
+                network_monitor: self.network_monitor.clone(),
             },
             MutationRoot {
                 context: Arc::clone(&self.context),
@@ -832,7 +988,14 @@ where
                 context: Arc::clone(&self.context),
             },
         )
-        .finish()
+        .extension(QueryComplexityQuota(self.auth.clone()));
+        if let Some(max_complexity) = self.max_query_complexity {
+            builder = builder.limit_complexity(max_complexity);
+        }
+        if let Some(max_depth) = self.max_query_depth {
+            builder = builder.limit_depth(max_depth);
+        }
+        builder.finish()
     }
 
     /// Runs the node service.
@@ -842,6 +1005,9 @@ where
         let index_handler = axum::routing::get(util::graphiql).post(Self::index_handler);
         let application_handler =
             axum::routing::get(util::graphiql).post(Self::application_handler);
+        let historical_application_handler =
+            axum::routing::get(util::graphiql).post(Self::historical_application_handler);
+        let prepare_handler = axum::routing::post(Self::prepare_handler);
 
         let app = Router::new()
             .route("/", index_handler)
@@ -849,8 +1015,20 @@ where
                 "/chains/{chain_id}/applications/{application_id}",
                 application_handler,
             )
-            .route("/ready", axum::routing::get(|| async { "ready!" }))
+            .route(
+                "/chains/{chain_id}/applications/{application_id}/prepare",
+                prepare_handler,
+            )
+            .route(
+                "/chains/{chain_id}/applications/{application_id}/heights/{height}",
+                historical_application_handler,
+            )
             .route_service("/ws", GraphQLSubscription::new(self.schema()))
+            .layer(axum::middleware::from_fn_with_state(
+                self.auth.clone(),
+                service_auth::authenticate,
+            ))
+            .route("/ready", axum::routing::get(|| async { "ready!" }))
             .layer(Extension(self.clone()))
             // TODO(#551): Provide application authentication.
             .layer(CorsLayer::permissive());
@@ -858,16 +1036,25 @@ where
         info!("GraphiQL IDE: http://localhost:{}", port);
 
         let storage = self.context.lock().await.storage().clone();
+        let client = self.context.lock().await.client().clone();
+        let network_monitor = self
+            .network_monitor
+            .run(client, NetworkMonitor::DEFAULT_INTERVAL);
 
         let chain_listener =
             ChainListener::new(self.config, self.context, storage, cancellation_token).run();
         let mut chain_listener = Box::pin(chain_listener).fuse();
         let tcp_listener =
             tokio::net::TcpListener::bind(SocketAddr::from(([0, 0, 0, 0], port))).await?;
-        let server = axum::serve(tcp_listener, app).into_future();
+        let server = axum::serve(
+            tcp_listener,
+            app.into_make_service_with_connect_info::<SocketAddr>(),
+        )
+        .into_future();
         futures::select! {
             result = chain_listener => result?,
             result = Box::pin(server).fuse() => result?,
+            () = Box::pin(network_monitor).fuse() => unreachable!("the network monitor never stops"),
         };
 
         Ok(())
@@ -880,13 +1067,30 @@ where
         request: Vec<u8>,
         chain_id: ChainId,
     ) -> Result<Vec<u8>, NodeServiceError> {
+        let cache_key = match &self.query_cache {
+            Some(cache) => {
+                let key = self
+                    .query_cache_key(chain_id, application_id, &request)
+                    .await?;
+                if let Some(response) = cache.get(&key) {
+                    trace!("Using the cached response to a query on chain {chain_id}");
+                    return Ok(response);
+                }
+                Some(key)
+            }
+            None => None,
+        };
         let QueryOutcome {
             response,
             operations,
+            operation_metadata,
         } = self
             .query_user_application(application_id, request, chain_id)
             .await?;
         if operations.is_empty() {
+            if let Some(key) = cache_key {
+                self.cache_response(chain_id, key, response.clone()).await?;
+            }
             return Ok(response);
         }
 
@@ -905,10 +1109,106 @@ where
             })?;
             util::wait_for_next_round(&mut stream, timeout).await;
         };
-        let response = async_graphql::Response::new(hash.to_value());
+        let mut response = async_graphql::Response::new(hash.to_value());
+        if operation_metadata.iter().any(Option::is_some) {
+            let metadata =
+                async_graphql::Value::from_json(serde_json::to_value(&operation_metadata)?)?;
+            response = response.extension("operationMetadata", metadata);
+        }
         Ok(serde_json::to_vec(&response)?)
     }
 
+    /// Handles service queries for user applications without proposing a block: returns the
+    /// operations they schedule together with their metadata, for the user to confirm before
+    /// sending the same request to the application's endpoint.
+    async fn handle_prepare_request(
+        &self,
+        application_id: ApplicationId,
+        request: Vec<u8>,
+        chain_id: ChainId,
+    ) -> Result<Vec<u8>, NodeServiceError> {
+        let QueryOutcome {
+            response,
+            operations,
+            operation_metadata,
+        } = self
+            .query_user_application(application_id, request, chain_id)
+            .await?;
+        for metadata in operation_metadata.iter().flatten() {
+            debug!(
+                "Prepared operation {:?}: {}",
+                metadata.title, metadata.description
+            );
+        }
+        Ok(prepared_response(
+            &response,
+            &operations,
+            &operation_metadata,
+        )?)
+    }
+
+    /// Returns the key of a query in the cache, for the current tip of the chain.
+    async fn query_cache_key(
+        &self,
+        chain_id: ChainId,
+        application_id: ApplicationId,
+        request: &[u8],
+    ) -> Result<QueryKey, NodeServiceError> {
+        let client = self.context.lock().await.make_chain_client(chain_id);
+        let info = client.chain_info().await.map_err(ChainClientError::from)?;
+        Ok(QueryKey::new(
+            chain_id,
+            info.block_hash,
+            application_id,
+            request,
+        ))
+    }
+
+    /// Caches the response to a query, making sure it is invalidated by the next block.
+    async fn cache_response(
+        &self,
+        chain_id: ChainId,
+        key: QueryKey,
+        response: Vec<u8>,
+    ) -> Result<(), NodeServiceError> {
+        let Some(cache) = &self.query_cache else {
+            return Ok(());
+        };
+        if !cache.is_watching(chain_id) {
+            let client = self.context.lock().await.make_chain_client(chain_id);
+            let notifications = client.subscribe().await.map_err(ChainClientError::from)?;
+            cache.watch(chain_id, notifications);
+        }
+        cache.insert(key, response);
+        Ok(())
+    }
+
+    /// Handles service queries for user applications, against their state right after the
+    /// block at `height`. These are never cached, and must not schedule any operations.
+    async fn handle_historical_service_request(
+        &self,
+        application_id: ApplicationId,
+        bytes: Vec<u8>,
+        chain_id: ChainId,
+        height: BlockHeight,
+    ) -> Result<Vec<u8>, NodeServiceError> {
+        let query = Query::User {
+            application_id,
+            bytes,
+        };
+        let client = self.context.lock().await.make_chain_client(chain_id);
+        let outcome = client.query_application_at(query, height).await?;
+        if !outcome.operations.is_empty() {
+            return Err(NodeServiceError::OperationsAtPastHeight);
+        }
+        match outcome.response {
+            QueryResponse::System(_) => {
+                unreachable!("cannot get a system response for a user query")
+            }
+            QueryResponse::User(response) => Ok(response),
+        }
+    }
+
     /// Queries a user application, returning the raw [`QueryOutcome`].
     async fn query_user_application(
         &self,
@@ -924,6 +1224,7 @@ where
         let QueryOutcome {
             response,
             operations,
+            operation_metadata,
         } = client.query_application(query).await?;
         match response {
             QueryResponse::System(_) => {
@@ -932,18 +1233,23 @@ where
             QueryResponse::User(user_response_bytes) => Ok(QueryOutcome {
                 response: user_response_bytes,
                 operations,
+                operation_metadata,
             }),
         }
     }
 
     /// Executes a GraphQL query and generates a response for our `Schema`.
-    async fn index_handler(service: Extension<Self>, request: GraphQLRequest) -> GraphQLResponse {
-        service
-            .0
-            .schema()
-            .execute(request.into_inner())
-            .await
-            .into()
+    async fn index_handler(
+        service: Extension<Self>,
+        caller: Extension<Caller>,
+        request: GraphQLRequest,
+    ) -> GraphQLResponse {
+        let request = request.into_inner().data(caller.0);
+        let mut response = service.0.schema().execute(request).await;
+        for error in &mut response.errors {
+            add_error_code(error);
+        }
+        response.into()
     }
 
     /// Executes a GraphQL query against an application.
@@ -968,4 +1274,133 @@ where
 
         Ok(response)
     }
+
+    /// Executes a GraphQL mutation against an application without proposing a block, and
+    /// returns the operations it would schedule with their metadata.
+    async fn prepare_handler(
+        Path((chain_id, application_id)): Path<(String, String)>,
+        service: Extension<Self>,
+        request: String,
+    ) -> Result<Vec<u8>, NodeServiceError> {
+        let chain_id: ChainId = chain_id.parse().map_err(NodeServiceError::InvalidChainId)?;
+        let application_id: ApplicationId = application_id.parse()?;
+
+        debug!(
+            "Preparing operations for application {application_id} on chain {chain_id}:\n{:?}",
+            &request
+        );
+        service
+            .0
+            .handle_prepare_request(application_id, request.into_bytes(), chain_id)
+            .await
+    }
+
+    /// Executes a GraphQL query against an application's state right after the block at the
+    /// given height, if the node still keeps that state's history.
+    async fn historical_application_handler(
+        Path((chain_id, application_id, height)): Path<(String, String, u64)>,
+        service: Extension<Self>,
+        request: String,
+    ) -> Result<Vec<u8>, NodeServiceError> {
+        let chain_id: ChainId = chain_id.parse().map_err(NodeServiceError::InvalidChainId)?;
+        let application_id: ApplicationId = application_id.parse()?;
+        let height = BlockHeight(height);
+
+        debug!(
+            "Processing request for application {application_id} on chain {chain_id} at \
+             height {height}:\n{:?}",
+            &request
+        );
+        service
+            .0
+            .handle_historical_service_request(
+                application_id,
+                request.into_bytes(),
+                chain_id,
+                height,
+            )
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use async_graphql::{Pos, Value};
+    use linera_core::node::NodeError;
+
+    use super::*;
+
+    #[test]
+    fn test_error_code_extensions() {
+        let client_error = ChainClientError::RemoteNodeError(NodeError::WorkerErrorWithCode {
+            error: "Blob was not required by any pending block".to_string(),
+            code: "UnexpectedBlob".to_string(),
+            retriable: false,
+        });
+        let mut error = async_graphql::Error::from(client_error).into_server_error(Pos::default());
+        add_error_code(&mut error);
+        let extensions = error.extensions.expect("the extensions should be set");
+        assert_eq!(extensions.get("code"), Some(&Value::from("UnexpectedBlob")));
+        assert_eq!(extensions.get("retriable"), Some(&Value::from(false)));
+
+        let mut error = async_graphql::Error::from(ChainClientError::CommitteeSynchronizationError)
+            .into_server_error(Pos::default());
+        add_error_code(&mut error);
+        let extensions = error.extensions.expect("the extensions should be set");
+        assert_eq!(
+            extensions.get("code"),
+            Some(&Value::from("CommitteeSynchronizationError"))
+        );
+        assert_eq!(extensions.get("retriable"), Some(&Value::from(true)));
+
+        // Errors that don't come from the client are left alone.
+        let mut error =
+            async_graphql::Error::new("invalid input").into_server_error(Pos::default());
+        add_error_code(&mut error);
+        assert!(error.extensions.is_none());
+    }
+
+    #[test]
+    fn test_prepared_response() {
+        let application_id = ApplicationId::new(CryptoHash::test_hash("application"));
+        let operations = vec![
+            Operation::User {
+                application_id,
+                bytes: vec![1, 2, 3],
+            },
+            Operation::User {
+                application_id,
+                bytes: vec![4],
+            },
+        ];
+        let metadata = OperationMetadata {
+            title: "Transfer".to_string(),
+            description: "Transfer 10 tokens to Bob".to_string(),
+            expected_effects: vec!["10 tokens leave your account".to_string()],
+        };
+        let operation_metadata = vec![Some(metadata), None];
+        let response = br#"{"data":{"transfer":[]}}"#;
+
+        let prepared = prepared_response(response, &operations, &operation_metadata).unwrap();
+        let prepared: serde_json::Value = serde_json::from_slice(&prepared).unwrap();
+        assert_eq!(prepared["response"], json!({"data": {"transfer": []}}));
+        assert_eq!(
+            prepared["operations"],
+            serde_json::to_value(&operations).unwrap()
+        );
+        assert_eq!(
+            prepared["operationMetadata"],
+            json!([
+                {
+                    "title": "Transfer",
+                    "description": "Transfer 10 tokens to Bob",
+                    "expected_effects": ["10 tokens leave your account"],
+                },
+                null,
+            ])
+        );
+
+        // Responses that are not JSON are rejected rather than forwarded.
+        assert!(prepared_response(b"not json", &operations, &operation_metadata).is_err());
+    }
 }
@@ -266,7 +266,14 @@ impl Project {
         (linera_sdk_dep, linera_sdk_dev_dep)
     }
 
-    pub fn build(&self, name: Option<String>) -> Result<(PathBuf, PathBuf), anyhow::Error> {
+    /// Builds the contract and the service of the project, and returns the paths of their
+    /// bytecode files. With `wrap_in_component`, the modules are wrapped into WebAssembly
+    /// components.
+    pub fn build(
+        &self,
+        name: Option<String>,
+        wrap_in_component: bool,
+    ) -> Result<(PathBuf, PathBuf), anyhow::Error> {
         let name = match name {
             Some(name) => name,
             None => self.project_package_name()?.replace('-', "_"),
@@ -284,12 +291,33 @@ impl Project {
         let build_path = self
             .workspace_root()?
             .join("target/wasm32-unknown-unknown/release");
+        let contract_path = build_path.join(contract_name).with_extension("wasm");
+        let service_path = build_path.join(service_name).with_extension("wasm");
+        if !wrap_in_component {
+            return Ok((contract_path, service_path));
+        }
         Ok((
-            build_path.join(contract_name).with_extension("wasm"),
-            build_path.join(service_name).with_extension("wasm"),
+            Self::wrap_in_component(&contract_path)?,
+            Self::wrap_in_component(&service_path)?,
         ))
     }
 
+    /// Wraps the module at `path` into a component implementing the WIT world that the SDK
+    /// embedded in it, and returns the path of the component.
+    fn wrap_in_component(path: &Path) -> Result<PathBuf> {
+        let module = fs_err::read(path)?;
+        let component = wit_component::ComponentEncoder::default()
+            .module(&module)
+            .with_context(|| format!("{} was not built with the Linera SDK", path.display()))?
+            .validate(true)
+            .encode()
+            .with_context(|| format!("Failed to make a component of {}", path.display()))?;
+        let component_path = path.with_extension("component.wasm");
+        debug!("Writing component to {}", component_path.display());
+        fs_err::write(&component_path, component)?;
+        Ok(component_path)
+    }
+
     fn project_package_name(&self) -> Result<String> {
         let manifest = Manifest::from_path(self.cargo_toml_path())?;
         let name = manifest
@@ -303,3 +331,117 @@ impl Project {
         self.root.join("Cargo.toml")
     }
 }
+
+#[cfg(all(test, any(feature = "wasmer", feature = "wasmtime")))]
+mod tests {
+    use std::path::Path;
+
+    use linera_base::data_types::{Blob, BlockHeight, Timestamp};
+    use linera_execution::{
+        test_utils::{
+            create_dummy_user_application_description, dummy_chain_description,
+            SystemExecutionState,
+        },
+        ExecutionRuntimeConfig, ExecutionRuntimeContext as _, Operation, OperationContext, Query,
+        QueryContext, QueryOutcome, QueryResponse, ResourceController, TransactionTracker,
+        WasmContractModule, WasmRuntime, WasmServiceModule, WithWasmDefault as _,
+    };
+    use linera_views::context::Context as _;
+    use serde_json::json;
+
+    use super::Project;
+
+    /// Tests that the components `linera project publish --wrap-in-component` makes of the "counter"
+    /// example run like the modules they were made of.
+    #[test_log::test(tokio::test(flavor = "multi_thread"))]
+    async fn test_components_run_like_their_modules() -> anyhow::Result<()> {
+        let fixtures =
+            Path::new(env!("CARGO_MANIFEST_DIR")).join("../linera-execution/tests/fixtures");
+        let directory = tempfile::tempdir()?;
+        let contract_path = directory.path().join("counter_contract.wasm");
+        let service_path = directory.path().join("counter_service.wasm");
+        fs_err::copy(fixtures.join("counter_contract.wasm"), &contract_path)?;
+        fs_err::copy(fixtures.join("counter_service.wasm"), &service_path)?;
+        let contract_path = Project::wrap_in_component(&contract_path)?;
+        let service_path = Project::wrap_in_component(&service_path)?;
+
+        let wasm_runtime = None::<WasmRuntime>.with_wasm_default().unwrap();
+        let chain_description = dummy_chain_description(0);
+        let chain_id = chain_description.id();
+        let state = SystemExecutionState {
+            description: Some(chain_description),
+            ..Default::default()
+        };
+        let mut view = state
+            .into_view_with(chain_id, ExecutionRuntimeConfig::default())
+            .await;
+        let (app_desc, contract_blob, service_blob) = create_dummy_user_application_description(1);
+        let app_id = From::from(&app_desc);
+        let blob_ids = vec![
+            Blob::new_application_description(&app_desc).id(),
+            contract_blob.id(),
+            service_blob.id(),
+        ];
+        let contract = WasmContractModule::from_file(&contract_path, wasm_runtime).await?;
+        let service = WasmServiceModule::from_file(&service_path, wasm_runtime).await?;
+        view.context()
+            .extra()
+            .user_contracts()
+            .insert(app_id, contract.into());
+        view.context()
+            .extra()
+            .user_services()
+            .insert(app_id, service.into());
+        view.context()
+            .extra()
+            .add_blobs([
+                contract_blob,
+                service_blob,
+                Blob::new_application_description(&app_desc),
+            ])
+            .await?;
+
+        let context = OperationContext {
+            chain_id,
+            height: BlockHeight(0),
+            round: Some(0),
+            authenticated_signer: None,
+            authenticated_caller_id: None,
+            timestamp: Default::default(),
+        };
+        let mut controller = ResourceController::default();
+        let mut txn_tracker = TransactionTracker::new_replaying_blobs(blob_ids);
+        view.execute_operation(
+            context,
+            Operation::user_without_abi(app_id, &42_u64)?,
+            &mut txn_tracker,
+            &mut controller,
+        )
+        .await?;
+
+        let context = QueryContext {
+            chain_id,
+            next_block_height: BlockHeight(0),
+            local_time: Timestamp::from(0),
+        };
+        let mut service_runtime_endpoint = context.spawn_service_runtime_actor();
+        let request = async_graphql::Request::new("query { value }");
+        let outcome = view
+            .query_application(
+                context,
+                Query::user_without_abi(app_id, &request)?,
+                Some(&mut service_runtime_endpoint),
+            )
+            .await?;
+        let QueryOutcome {
+            response: QueryResponse::User(response),
+            ..
+        } = outcome
+        else {
+            panic!("unexpected response")
+        };
+        let response = serde_json::from_slice::<async_graphql::Response>(&response)?;
+        assert_eq!(response.data.into_json()?, json!({"value": 42}));
+        Ok(())
+    }
+}
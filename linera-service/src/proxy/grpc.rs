@@ -16,23 +16,34 @@ use std::{
 use anyhow::Result;
 use async_trait::async_trait;
 use futures::{future::BoxFuture, FutureExt as _};
-use linera_base::identifiers::ChainId;
-use linera_core::{notifier::ChannelNotifier, JoinSetExt as _};
+use linera_base::{data_types::BlockHeight, identifiers::ChainId};
+use linera_chain::types::ConfirmedBlockCertificate;
+use linera_core::{
+    data_types::BlockHeightRange,
+    node::{NodeError, StreamedCertificate},
+    notifier::ChannelNotifier,
+    JoinSetExt as _,
+};
 #[cfg(with_metrics)]
 use linera_metrics::prometheus_server;
 use linera_rpc::{
-    config::{ProxyConfig, ShardConfig, TlsConfig, ValidatorInternalNetworkConfig},
+    config::{
+        CompressionConfig, ProxyConfig, ShardConfig, TlsConfig, ValidatorInternalNetworkConfig,
+    },
     grpc::{
         api::{
             self,
             notifier_service_server::{NotifierService, NotifierServiceServer},
             validator_node_server::{ValidatorNode, ValidatorNodeServer},
             validator_worker_client::ValidatorWorkerClient,
-            BlobContent, BlobId, BlobIds, BlockProposal, Certificate, CertificatesBatchRequest,
-            CertificatesBatchResponse, ChainInfoQuery, ChainInfoResult, CryptoHash,
-            HandlePendingBlobRequest, LiteCertificate, NetworkDescription, Notification,
-            PendingBlobRequest, PendingBlobResult, SubscriptionRequest, VersionInfo,
+            BlobContent, BlobId, BlobIds, BlobProvenance, BlockProposal, Certificate,
+            CertificatesBatchRequest, CertificatesBatchResponse, ChainInfoQuery, ChainInfoResult,
+            CryptoHash, HandlePendingBlobRequest, LiteCertificate, NetworkDescription,
+            Notification, PendingBlobRequest, PendingBlobResult, StreamCertificatesRequest,
+            SubscriptionRequest, VersionInfo,
         },
+        compression::ACCEPTED_ENCODINGS,
+        forwarded_request,
         pool::GrpcConnectionPool,
         GrpcProtoConversionError, GrpcProxyable, GRPC_CHUNKED_MESSAGE_FILL_LIMIT,
         GRPC_MAX_MESSAGE_SIZE,
@@ -41,8 +52,8 @@ use linera_rpc::{
 use linera_sdk::{linera_base_types::Blob, views::ViewError};
 use linera_storage::{ResultReadCertificates, Storage};
 use prost::Message;
-use tokio::{select, task::JoinSet};
-use tokio_stream::wrappers::UnboundedReceiverStream;
+use tokio::{select, sync::mpsc, task::JoinSet};
+use tokio_stream::wrappers::{ReceiverStream, UnboundedReceiverStream};
 use tokio_util::sync::CancellationToken;
 use tonic::{
     transport::{Channel, Identity, Server, ServerTlsConfig},
@@ -51,6 +62,13 @@ use tonic::{
 use tower::{builder::ServiceBuilder, Layer, Service};
 use tracing::{debug, info, instrument, Instrument as _, Level};
 
+/// The number of certificates requested from a shard at a time when streaming a chain.
+const CERTIFICATE_STREAM_BATCH_SIZE: u64 = 100;
+
+/// The number of streamed certificates the proxy buffers before waiting for the client to
+/// receive them.
+const CERTIFICATE_STREAM_BUFFER_SIZE: usize = 16;
+
 #[cfg(with_metrics)]
 mod metrics {
     use std::sync::LazyLock;
@@ -174,9 +192,20 @@ where
     }
 
     fn as_validator_node(&self) -> ValidatorNodeServer<Self> {
-        ValidatorNodeServer::new(self.clone())
+        let mut server = ValidatorNodeServer::new(self.clone())
             .max_encoding_message_size(GRPC_MAX_MESSAGE_SIZE)
-            .max_decoding_message_size(GRPC_MAX_MESSAGE_SIZE)
+            .max_decoding_message_size(GRPC_MAX_MESSAGE_SIZE);
+        for encoding in ACCEPTED_ENCODINGS {
+            server = server.accept_compressed(encoding);
+        }
+        if let Some(encoding) = self.compression().encoding() {
+            server = server.send_compressed(encoding);
+        }
+        server
+    }
+
+    fn compression(&self) -> &CompressionConfig {
+        &self.0.internal_config.compression
     }
 
     fn config(&self) -> &ProxyConfig {
@@ -218,10 +247,12 @@ where
     ) -> Result<ValidatorWorkerClient<Channel>> {
         let address = shard.http_address();
         let channel = self.0.worker_connection_pool.channel(address)?;
-        let client = ValidatorWorkerClient::new(channel)
+        let mut client = ValidatorWorkerClient::new(channel)
             .max_encoding_message_size(GRPC_MAX_MESSAGE_SIZE)
             .max_decoding_message_size(GRPC_MAX_MESSAGE_SIZE);
-
+        for encoding in ACCEPTED_ENCODINGS {
+            client = client.accept_compressed(encoding);
+        }
         Ok(client)
     }
 
@@ -299,23 +330,28 @@ where
     async fn worker_client<R>(
         &self,
         request: Request<R>,
-    ) -> Result<(ValidatorWorkerClient<Channel>, R), Status>
+    ) -> Result<(ValidatorWorkerClient<Channel>, Request<R>), Status>
     where
-        R: Debug + GrpcProxyable,
+        R: Debug + GrpcProxyable + Message,
     {
-        debug!("proxying request from {:?}", request.remote_addr());
+        let client_address = request.remote_addr();
+        debug!("proxying request from {:?}", client_address);
         let inner = request.into_inner();
         let shard = self
             .shard_for(&inner)
             .ok_or_else(|| Status::not_found("could not find shard for message"))?;
-        let client = self
+        let mut client = self
             .worker_client_for_shard(&shard)
             .map_err(|_| Status::internal("could not connect to shard"))?;
-        Ok((client, inner))
+        if let Some(encoding) = self.compression().encoding_for(&inner) {
+            client = client.send_compressed(encoding);
+        }
+        Ok((client, forwarded_request(inner, client_address)))
     }
 
     #[allow(clippy::result_large_err)]
     fn log_and_return_proxy_request_outcome(
+        &self,
         result: Result<Response<ChainInfoResult>, Status>,
         method_name: &str,
     ) -> Result<Response<ChainInfoResult>, Status> {
@@ -326,7 +362,7 @@ where
                 metrics::PROXY_REQUEST_SUCCESS
                     .with_label_values(&[method_name])
                     .inc();
-                Ok(chain_info_result)
+                Ok(self.compression().response(chain_info_result.into_inner()))
             }
             Err(status) => {
                 #[cfg(with_metrics)]
@@ -338,6 +374,114 @@ where
         }
     }
 
+    /// Sends the certificates of `chain_id` from height `next_height` on, until the
+    /// shard has no more or the client stops listening.
+    async fn send_certificates(
+        &self,
+        mut client: ValidatorWorkerClient<Channel>,
+        chain_id: ChainId,
+        mut next_height: BlockHeight,
+        include_blobs: bool,
+        sender: &mpsc::Sender<Result<api::StreamedCertificate, Status>>,
+    ) -> Result<(), Status> {
+        loop {
+            let hashes = Self::sent_certificate_hashes(&mut client, chain_id, next_height).await?;
+            if hashes.is_empty() {
+                return Ok(());
+            }
+            let certificates = self
+                .0
+                .storage
+                .read_certificates(hashes.clone())
+                .await
+                .map_err(Self::view_error_to_status)?;
+            let certificates = match ResultReadCertificates::new(certificates, hashes) {
+                ResultReadCertificates::Certificates(certificates) => certificates,
+                ResultReadCertificates::InvalidHashes(hashes) => {
+                    return Err(Status::not_found(format!("{:?}", hashes)))
+                }
+            };
+            for certificate in certificates {
+                next_height = certificate
+                    .block()
+                    .header
+                    .height
+                    .try_add_one()
+                    .map_err(|error| Status::out_of_range(error.to_string()))?;
+                let blobs = if include_blobs {
+                    self.read_streamed_blobs(&certificate).await?
+                } else {
+                    Vec::new()
+                };
+                let streamed = StreamedCertificate { certificate, blobs };
+                if sender.send(Ok(streamed.try_into()?)).await.is_err() {
+                    debug!("client stopped streaming the certificates of chain {chain_id}");
+                    return Ok(());
+                }
+            }
+        }
+    }
+
+    /// Asks the shard for the hashes of the next certificates of the chain, starting at
+    /// `start`.
+    async fn sent_certificate_hashes(
+        client: &mut ValidatorWorkerClient<Channel>,
+        chain_id: ChainId,
+        start: BlockHeight,
+    ) -> Result<Vec<linera_base::crypto::CryptoHash>, Status> {
+        let range = BlockHeightRange {
+            start,
+            limit: Some(CERTIFICATE_STREAM_BATCH_SIZE),
+        };
+        let query = linera_core::data_types::ChainInfoQuery::new(chain_id)
+            .with_sent_certificate_hashes_in_range(range);
+        let result = client
+            .handle_chain_info_query(ChainInfoQuery::try_from(query)?)
+            .await?
+            .into_inner();
+        match result.inner {
+            Some(api::chain_info_result::Inner::ChainInfoResponse(response)) => {
+                let response = linera_core::data_types::ChainInfoResponse::try_from(response)?;
+                Ok(response.info.requested_sent_certificate_hashes)
+            }
+            Some(api::chain_info_result::Inner::Error(error)) => {
+                let error: NodeError = bincode::deserialize(&error)
+                    .map_err(|error| Status::internal(error.to_string()))?;
+                Err(error.into())
+            }
+            None => Err(Status::internal("missing body from shard response")),
+        }
+    }
+
+    /// Reads the blobs required by the certificate's block. Blobs that are missing or that
+    /// would not fit in a single message are left out; the client can download them
+    /// separately.
+    async fn read_streamed_blobs(
+        &self,
+        certificate: &ConfirmedBlockCertificate,
+    ) -> Result<Vec<Blob>, Status> {
+        let blob_ids = certificate
+            .block()
+            .required_blob_ids()
+            .into_iter()
+            .collect::<Vec<_>>();
+        let blobs = self
+            .0
+            .storage
+            .read_blobs(&blob_ids)
+            .await
+            .map_err(Self::view_error_to_status)?;
+        let mut grpc_message_limiter: GrpcMessageLimiter<linera_base::data_types::BlobContent> =
+            GrpcMessageLimiter::new(GRPC_CHUNKED_MESSAGE_FILL_LIMIT);
+        let mut streamed_blobs = Vec::new();
+        for blob in blobs.into_iter().flatten() {
+            if grpc_message_limiter.fits::<BlobContent>(blob.content().clone())? {
+                streamed_blobs.push(blob);
+            }
+        }
+        Ok(streamed_blobs)
+    }
+
     /// Returns the appropriate gRPC status for the given [`ViewError`].
     fn view_error_to_status(err: ViewError) -> Status {
         let mut status = match &err {
@@ -354,6 +498,7 @@ where
             ViewError::NotFound(_)
             | ViewError::CannotAcquireCollectionEntry
             | ViewError::MissingEntries => Status::not_found(err.to_string()),
+            ViewError::ScanBudgetExceeded(_) => Status::resource_exhausted(err.to_string()),
         };
         status.set_source(Arc::new(err));
         status
@@ -366,15 +511,16 @@ where
     S: Storage + Clone + Send + Sync + 'static,
 {
     type SubscribeStream = UnboundedReceiverStream<Result<Notification, Status>>;
+    type StreamCertificatesStream = ReceiverStream<Result<api::StreamedCertificate, Status>>;
 
     #[instrument(skip_all, err(Display))]
     async fn handle_block_proposal(
         &self,
         request: Request<BlockProposal>,
     ) -> Result<Response<ChainInfoResult>, Status> {
-        let (mut client, inner) = self.worker_client(request).await?;
-        Self::log_and_return_proxy_request_outcome(
-            client.handle_block_proposal(inner).await,
+        let (mut client, request) = self.worker_client(request).await?;
+        self.log_and_return_proxy_request_outcome(
+            client.handle_block_proposal(request).await,
             "handle_block_proposal",
         )
     }
@@ -384,9 +530,9 @@ where
         &self,
         request: Request<LiteCertificate>,
     ) -> Result<Response<ChainInfoResult>, Status> {
-        let (mut client, inner) = self.worker_client(request).await?;
-        Self::log_and_return_proxy_request_outcome(
-            client.handle_lite_certificate(inner).await,
+        let (mut client, request) = self.worker_client(request).await?;
+        self.log_and_return_proxy_request_outcome(
+            client.handle_lite_certificate(request).await,
             "handle_lite_certificate",
         )
     }
@@ -396,9 +542,9 @@ where
         &self,
         request: Request<api::HandleConfirmedCertificateRequest>,
     ) -> Result<Response<ChainInfoResult>, Status> {
-        let (mut client, inner) = self.worker_client(request).await?;
-        Self::log_and_return_proxy_request_outcome(
-            client.handle_confirmed_certificate(inner).await,
+        let (mut client, request) = self.worker_client(request).await?;
+        self.log_and_return_proxy_request_outcome(
+            client.handle_confirmed_certificate(request).await,
             "handle_confirmed_certificate",
         )
     }
@@ -408,9 +554,9 @@ where
         &self,
         request: Request<api::HandleValidatedCertificateRequest>,
     ) -> Result<Response<ChainInfoResult>, Status> {
-        let (mut client, inner) = self.worker_client(request).await?;
-        Self::log_and_return_proxy_request_outcome(
-            client.handle_validated_certificate(inner).await,
+        let (mut client, request) = self.worker_client(request).await?;
+        self.log_and_return_proxy_request_outcome(
+            client.handle_validated_certificate(request).await,
             "handle_validated_certificate",
         )
     }
@@ -420,9 +566,9 @@ where
         &self,
         request: Request<api::HandleTimeoutCertificateRequest>,
     ) -> Result<Response<ChainInfoResult>, Status> {
-        let (mut client, inner) = self.worker_client(request).await?;
-        Self::log_and_return_proxy_request_outcome(
-            client.handle_timeout_certificate(inner).await,
+        let (mut client, request) = self.worker_client(request).await?;
+        self.log_and_return_proxy_request_outcome(
+            client.handle_timeout_certificate(request).await,
             "handle_timeout_certificate",
         )
     }
@@ -432,9 +578,9 @@ where
         &self,
         request: Request<ChainInfoQuery>,
     ) -> Result<Response<ChainInfoResult>, Status> {
-        let (mut client, inner) = self.worker_client(request).await?;
-        Self::log_and_return_proxy_request_outcome(
-            client.handle_chain_info_query(inner).await,
+        let (mut client, request) = self.worker_client(request).await?;
+        self.log_and_return_proxy_request_outcome(
+            client.handle_chain_info_query(request).await,
             "handle_chain_info_query",
         )
     }
@@ -509,7 +655,7 @@ where
             .await
             .map_err(Self::view_error_to_status)?;
         let blob = blob.ok_or_else(|| Status::not_found(format!("Blob not found {}", blob_id)))?;
-        Ok(Response::new(blob.into_content().try_into()?))
+        Ok(self.compression().response(blob.into_content().try_into()?))
     }
 
     #[instrument(skip_all, err(Display))]
@@ -517,9 +663,9 @@ where
         &self,
         request: Request<PendingBlobRequest>,
     ) -> Result<Response<PendingBlobResult>, Status> {
-        let (mut client, inner) = self.worker_client(request).await?;
+        let (mut client, request) = self.worker_client(request).await?;
         #[cfg_attr(not(with_metrics), expect(clippy::needless_match))]
-        match client.download_pending_blob(inner).await {
+        match client.download_pending_blob(request).await {
             Ok(blob_result) => {
                 #[cfg(with_metrics)]
                 metrics::PROXY_REQUEST_SUCCESS
@@ -542,9 +688,9 @@ where
         &self,
         request: Request<HandlePendingBlobRequest>,
     ) -> Result<Response<ChainInfoResult>, Status> {
-        let (mut client, inner) = self.worker_client(request).await?;
+        let (mut client, request) = self.worker_client(request).await?;
         #[cfg_attr(not(with_metrics), expect(clippy::needless_match))]
-        match client.handle_pending_blob(inner).await {
+        match client.handle_pending_blob(request).await {
             Ok(blob_result) => {
                 #[cfg(with_metrics)]
                 metrics::PROXY_REQUEST_SUCCESS
@@ -576,7 +722,7 @@ where
             .map_err(Self::view_error_to_status)?
             .ok_or(Status::not_found(hash.to_string()))?
             .into();
-        Ok(Response::new(certificate.try_into()?))
+        Ok(self.compression().response(certificate.try_into()?))
     }
 
     #[instrument(skip_all, err(Display))]
@@ -620,9 +766,9 @@ where
             }
         }
 
-        Ok(Response::new(CertificatesBatchResponse::try_from(
-            returned_certificates,
-        )?))
+        Ok(self
+            .compression()
+            .response(CertificatesBatchResponse::try_from(returned_certificates)?))
     }
 
     #[instrument(skip_all, err(level = Level::WARN))]
@@ -645,6 +791,21 @@ where
         Ok(Response::new(last_used_by.into()))
     }
 
+    #[instrument(skip_all, err(level = Level::WARN))]
+    async fn blob_provenance(
+        &self,
+        request: Request<BlobId>,
+    ) -> Result<Response<BlobProvenance>, Status> {
+        let blob_id = request.into_inner().try_into()?;
+        let provenance = self
+            .0
+            .storage
+            .blob_provenance(blob_id)
+            .await
+            .map_err(Self::view_error_to_status)?;
+        Ok(Response::new(provenance.try_into()?))
+    }
+
     #[instrument(skip_all, err(level = Level::WARN))]
     async fn missing_blob_ids(
         &self,
@@ -659,6 +820,31 @@ where
             .map_err(Self::view_error_to_status)?;
         Ok(Response::new(missing_blob_ids.try_into()?))
     }
+
+    #[instrument(skip_all, err(Display))]
+    async fn stream_certificates(
+        &self,
+        request: Request<StreamCertificatesRequest>,
+    ) -> Result<Response<Self::StreamCertificatesStream>, Status> {
+        let (client, request) = self.worker_client(request).await?;
+        let (chain_id, start, include_blobs) = request.into_inner().try_into()?;
+        // The bounded channel makes the task wait whenever the client falls behind.
+        let (sender, receiver) = mpsc::channel(CERTIFICATE_STREAM_BUFFER_SIZE);
+        let proxy = self.clone();
+        tokio::spawn(
+            async move {
+                if let Err(status) = proxy
+                    .send_certificates(client, chain_id, start, include_blobs, &sender)
+                    .await
+                {
+                    // This fails only if the client is gone, and then there is no one to tell.
+                    let _ = sender.send(Err(status)).await;
+                }
+            }
+            .in_current_span(),
+        );
+        Ok(Response::new(ReceiverStream::new(receiver)))
+    }
 }
 
 #[async_trait]
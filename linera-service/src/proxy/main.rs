@@ -362,6 +362,9 @@ where
                     last_used_by,
                 ))))
             }
+            BlobProvenance(blob_id) => Ok(Some(RpcMessage::BlobProvenanceResponse(Box::new(
+                self.storage.blob_provenance(*blob_id).await?,
+            )))),
             MissingBlobIds(blob_ids) => Ok(Some(RpcMessage::MissingBlobIdsResponse(
                 self.storage.missing_blobs(&blob_ids).await?,
             ))),
@@ -372,6 +375,7 @@ where
             | ValidatedCertificate(_)
             | ChainInfoQuery(_)
             | CrossChainRequest(_)
+            | SignedCrossChainRequest(_)
             | Vote(_)
             | Error(_)
             | ChainInfoResponse(_)
@@ -382,6 +386,7 @@ where
             | DownloadPendingBlobResponse(_)
             | HandlePendingBlob(_)
             | BlobLastUsedByResponse(_)
+            | BlobProvenanceResponse(_)
             | MissingBlobIdsResponse(_)
             | DownloadConfirmedBlockResponse(_)
             | DownloadCertificatesResponse(_)
@@ -423,8 +428,9 @@ impl ProxyOptions {
             .storage_config
             .add_common_storage_options(&self.common_storage_options)
             .await?;
+        let options = self.common_storage_options.db_storage_options().await?;
         store_config
-            .run_with_storage(None, ProxyContext::from_options(self)?)
+            .run_with_storage(None, &options, ProxyContext::from_options(self)?)
             .boxed()
             .await?
     }
@@ -0,0 +1,219 @@
+// Copyright (c) Zefchain Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! A cache of the responses of application services, so that the node service doesn't
+//! execute the same query again until the chain has a new block.
+
+use std::{
+    collections::HashSet,
+    mem::size_of,
+    num::NonZeroUsize,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use futures::StreamExt as _;
+use linera_base::{
+    crypto::{BcsHashable, CryptoHash},
+    identifiers::{ApplicationId, ChainId},
+};
+use linera_core::{node::NotificationStream, worker::Reason};
+use quick_cache::{sync::Cache as FifoCache, Weighter};
+use serde::{Deserialize, Serialize};
+
+/// The configuration of the [`QueryCache`].
+#[derive(Clone, Debug, clap::Args, Serialize)]
+pub struct QueryCacheConfig {
+    /// The maximal number of application query responses cached by the node service.
+    /// Responses are not cached if this is not set.
+    #[arg(long = "query-cache-max-entries")]
+    pub max_entries: Option<NonZeroUsize>,
+
+    /// The maximal total size of the cached query responses, in bytes.
+    #[arg(long = "query-cache-max-size", default_value = "10000000")]
+    pub max_size: u64,
+
+    /// The time after which a cached query response is dropped even if the chain has no new
+    /// block, in milliseconds.
+    #[arg(long = "query-cache-ttl-ms")]
+    pub ttl_ms: Option<u64>,
+}
+
+/// Identifies the response to a query: the same query against the same state of the same
+/// application.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct QueryKey {
+    chain_id: ChainId,
+    /// The hash of the latest block of the chain, if any.
+    tip: Option<CryptoHash>,
+    application_id: ApplicationId,
+    query_hash: CryptoHash,
+}
+
+/// The bytes of a service query, to be hashed.
+#[derive(Serialize, Deserialize)]
+struct ServiceQuery<'a>(&'a [u8]);
+
+impl<'de> BcsHashable<'de> for ServiceQuery<'de> {}
+
+impl QueryKey {
+    /// Creates the key of the query `bytes` to an application, on a chain whose latest block
+    /// is `tip`.
+    pub fn new(
+        chain_id: ChainId,
+        tip: Option<CryptoHash>,
+        application_id: ApplicationId,
+        bytes: &[u8],
+    ) -> Self {
+        QueryKey {
+            chain_id,
+            tip,
+            application_id,
+            query_hash: CryptoHash::new(&ServiceQuery(bytes)),
+        }
+    }
+}
+
+#[derive(Clone)]
+struct CachedResponse {
+    response: Arc<Vec<u8>>,
+    inserted_at: Instant,
+}
+
+#[derive(Clone)]
+struct ResponseWeighter;
+
+impl Weighter<QueryKey, CachedResponse> for ResponseWeighter {
+    fn weight(&self, _key: &QueryKey, value: &CachedResponse) -> u64 {
+        (size_of::<QueryKey>() + size_of::<CachedResponse>() + value.response.len()) as u64
+    }
+}
+
+/// The responses of application services, by [`QueryKey`].
+pub struct QueryCache {
+    responses: FifoCache<QueryKey, CachedResponse, ResponseWeighter>,
+    ttl: Option<Duration>,
+    /// The chains whose notifications are used to invalidate the cache.
+    watched_chains: Mutex<HashSet<ChainId>>,
+}
+
+impl QueryCache {
+    /// Creates a cache with the given configuration, or returns `None` if caching is
+    /// disabled.
+    pub fn new(config: &QueryCacheConfig) -> Option<Self> {
+        let max_entries = config.max_entries?;
+        Some(QueryCache {
+            responses: FifoCache::with_weighter(
+                max_entries.get(),
+                config.max_size,
+                ResponseWeighter,
+            ),
+            ttl: config.ttl_ms.map(Duration::from_millis),
+            watched_chains: Mutex::default(),
+        })
+    }
+
+    /// Returns the cached response to a query, unless it has expired.
+    pub fn get(&self, key: &QueryKey) -> Option<Vec<u8>> {
+        let cached = self.responses.get(key)?;
+        if self
+            .ttl
+            .is_some_and(|ttl| cached.inserted_at.elapsed() >= ttl)
+        {
+            self.responses.remove(key);
+            return None;
+        }
+        Some(cached.response.as_ref().clone())
+    }
+
+    /// Caches the response to a query.
+    pub fn insert(&self, key: QueryKey, response: Vec<u8>) {
+        let cached = CachedResponse {
+            response: Arc::new(response),
+            inserted_at: Instant::now(),
+        };
+        self.responses.insert(key, cached);
+    }
+
+    /// Drops the cached responses of the queries on the given chain.
+    pub fn invalidate_chain(&self, chain_id: ChainId) {
+        self.responses.retain(|key, _| key.chain_id != chain_id);
+    }
+
+    /// Returns whether the cache is already invalidated by the notifications of the chain.
+    pub fn is_watching(&self, chain_id: ChainId) -> bool {
+        self.watched_chains.lock().unwrap().contains(&chain_id)
+    }
+
+    /// Invalidates the cached responses of a chain whenever `notifications` report a new
+    /// block on it.
+    pub fn watch(self: &Arc<Self>, chain_id: ChainId, mut notifications: NotificationStream) {
+        if !self.watched_chains.lock().unwrap().insert(chain_id) {
+            return;
+        }
+        let cache = Arc::clone(self);
+        tokio::spawn(async move {
+            while let Some(notification) = notifications.next().await {
+                if let Reason::NewBlock { .. } = notification.reason {
+                    cache.invalidate_chain(chain_id);
+                }
+            }
+            // Without notifications, the responses can't be kept any longer.
+            cache.watched_chains.lock().unwrap().remove(&chain_id);
+            cache.invalidate_chain(chain_id);
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use linera_base::{
+        crypto::CryptoHash,
+        identifiers::{ApplicationId, ChainId},
+    };
+
+    use super::{QueryCache, QueryCacheConfig, QueryKey};
+
+    fn new_cache(ttl_ms: Option<u64>) -> QueryCache {
+        let config = QueryCacheConfig {
+            max_entries: Some(10.try_into().unwrap()),
+            max_size: 10_000,
+            ttl_ms,
+        };
+        QueryCache::new(&config).unwrap()
+    }
+
+    #[test]
+    fn test_query_cache() {
+        let chain_id = ChainId(CryptoHash::from([1; 32]));
+        let other_chain_id = ChainId(CryptoHash::from([2; 32]));
+        let application_id = ApplicationId::new(CryptoHash::from([3; 32]));
+        let tip = Some(CryptoHash::from([4; 32]));
+        let key = QueryKey::new(chain_id, tip, application_id, b"query");
+        let other_key = QueryKey::new(other_chain_id, tip, application_id, b"query");
+
+        let cache = new_cache(None);
+        cache.insert(key.clone(), b"response".to_vec());
+        cache.insert(other_key.clone(), b"other response".to_vec());
+        assert_eq!(cache.get(&key).unwrap(), b"response");
+        assert!(cache
+            .get(&QueryKey::new(
+                chain_id,
+                tip,
+                application_id,
+                b"other query"
+            ))
+            .is_none());
+        assert!(cache
+            .get(&QueryKey::new(chain_id, None, application_id, b"query"))
+            .is_none());
+
+        cache.invalidate_chain(chain_id);
+        assert!(cache.get(&key).is_none());
+        assert_eq!(cache.get(&other_key).unwrap(), b"other response");
+
+        let cache = new_cache(Some(0));
+        cache.insert(key.clone(), b"response".to_vec());
+        assert!(cache.get(&key).is_none());
+    }
+}
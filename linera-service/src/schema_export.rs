@@ -5,7 +5,7 @@ use std::sync::Arc;
 
 use linera_base::{
     crypto::CryptoHash,
-    data_types::{BlobContent, NetworkDescription, Timestamp},
+    data_types::{BlobContent, BlockHeight, NetworkDescription, Timestamp},
     identifiers::{AccountOwner, BlobId, ChainId},
 };
 use linera_chain::{
@@ -24,11 +24,11 @@ use linera_core::{
     client::ChainClient,
     data_types::{ChainInfoQuery, ChainInfoResponse},
     node::{
-        CrossChainMessageDelivery, NodeError, NotificationStream, ValidatorNode,
+        CertificateStream, CrossChainMessageDelivery, NodeError, NotificationStream, ValidatorNode,
         ValidatorNodeProvider,
     },
 };
-use linera_execution::committee::Committee;
+use linera_execution::{committee::Committee, BlobProvenance};
 use linera_sdk::linera_base_types::ValidatorPublicKey;
 use linera_service::node_service::NodeService;
 use linera_storage::DbStorage;
@@ -135,9 +135,22 @@ impl ValidatorNode for DummyValidatorNode {
         Err(NodeError::UnexpectedMessage)
     }
 
+    async fn blob_provenance(&self, _: BlobId) -> Result<BlobProvenance, NodeError> {
+        Err(NodeError::UnexpectedMessage)
+    }
+
     async fn missing_blob_ids(&self, _: Vec<BlobId>) -> Result<Vec<BlobId>, NodeError> {
         Err(NodeError::UnexpectedMessage)
     }
+
+    async fn stream_certificates(
+        &self,
+        _: ChainId,
+        _: BlockHeight,
+        _: bool,
+    ) -> Result<CertificateStream, NodeError> {
+        Err(NodeError::UnexpectedMessage)
+    }
 }
 
 struct DummyValidatorNodeProvider;
@@ -6,8 +6,10 @@
 
 use std::{
     borrow::Cow,
-    num::NonZeroU16,
+    net::{Ipv4Addr, SocketAddr},
+    num::{NonZeroU16, NonZeroU32},
     path::{Path, PathBuf},
+    sync::Arc,
     time::Duration,
 };
 
@@ -18,23 +20,36 @@ use linera_base::{
     crypto::{CryptoRng, Ed25519SecretKey},
     listen_for_shutdown_signals,
 };
+use linera_chain::manager::Pacemaker;
 use linera_client::config::{CommitteeConfig, ValidatorConfig, ValidatorServerConfig};
-use linera_core::{worker::WorkerState, JoinSetExt as _};
-use linera_execution::{WasmRuntime, WithWasmDefault};
+use linera_core::{
+    audit_log::{AuditLog, FileAuditSink},
+    service_runtime_pool::{ServiceRuntimePool, ServiceRuntimePoolConfig},
+    signing_fence::SigningFence,
+    worker::WorkerState,
+    JoinSetExt as _,
+};
+use linera_execution::{
+    system::{SignedValidatorAddress, ValidatorAddress},
+    CompiledModuleCacheConfig, WasmRuntime, WithWasmDefault,
+};
 #[cfg(with_metrics)]
 use linera_metrics::prometheus_server;
 use linera_persistent::{self as persistent, Persist};
 use linera_rpc::{
     config::{
-        CrossChainConfig, ExporterServiceConfig, NetworkProtocol, NotificationConfig, ProxyConfig,
-        ShardConfig, ShardId, TlsConfig, ValidatorInternalNetworkConfig,
-        ValidatorPublicNetworkConfig,
+        CompressionConfig, CrossChainConfig, ExporterServiceConfig, NetworkProtocol,
+        NotificationConfig, ProxyConfig, RequestLimitsConfig, ShardConfig, ShardId, TlsConfig,
+        ValidatorInternalNetworkConfig, ValidatorPublicNetworkConfig,
     },
     grpc, simple,
 };
 use linera_sdk::linera_base_types::{AccountSecretKey, ValidatorKeypair};
 use linera_service::{
+    chain_manager_admin,
+    shutdown::ShutdownCoordinator,
     storage::{CommonStorageOptions, Runnable, StorageConfig},
+    task_manager::{BlobGcTask, CronSchedule, NamespaceStatsTask, TaskManager},
     util,
 };
 use linera_storage::Storage;
@@ -47,9 +62,28 @@ struct ServerContext {
     server_config: ValidatorServerConfig,
     cross_chain_config: CrossChainConfig,
     notification_config: NotificationConfig,
+    request_limits_config: RequestLimitsConfig,
     shard: Option<usize>,
     grace_period: Duration,
     chain_worker_ttl: Duration,
+    proposal_outcome_ttl: Duration,
+    validation_only: bool,
+    blob_gc_schedule: Option<CronSchedule>,
+    namespace_stats_schedule: Option<CronSchedule>,
+    maintenance_admin_port: Option<u16>,
+    prune_chain_manager_on_save: bool,
+    max_proposal_age: Option<Duration>,
+    proposal_expiry_rounds: Option<NonZeroU32>,
+    max_execution_state_deltas: usize,
+    max_buffered_events: usize,
+    pacemaker: Option<Arc<dyn Pacemaker>>,
+    active_passive: bool,
+    standby: bool,
+    signing_handover_delay: Duration,
+    shutdown_drain_timeout: Duration,
+    epoch_migration_interval: Duration,
+    service_runtime_pool: Option<Arc<ServiceRuntimePool>>,
+    audit_log: Option<Arc<AuditLog>>,
 }
 
 impl ServerContext {
@@ -76,7 +110,21 @@ impl ServerContext {
         .with_allow_inactive_chains(false)
         .with_allow_messages_from_deprecated_epochs(false)
         .with_grace_period(self.grace_period)
-        .with_chain_worker_ttl(self.chain_worker_ttl);
+        .with_chain_worker_ttl(self.chain_worker_ttl)
+        .with_proposal_outcome_ttl(self.proposal_outcome_ttl)
+        .with_validation_only(self.validation_only)
+        .with_prune_manager_on_save(self.prune_chain_manager_on_save)
+        .with_max_proposal_age(self.max_proposal_age)
+        .with_proposal_expiry_rounds(self.proposal_expiry_rounds)
+        .with_max_execution_state_deltas(self.max_execution_state_deltas)
+        .with_max_buffered_events(self.max_buffered_events)
+        .with_pacemaker(self.pacemaker.clone())
+        .with_signing_fence(
+            self.active_passive
+                .then(|| SigningFence::standby(shard_id as u32, self.signing_handover_delay)),
+        )
+        .with_service_runtime_pool(self.service_runtime_pool.clone())
+        .with_audit_log(self.audit_log.clone());
         (state, shard_id, shard.clone())
     }
 
@@ -84,6 +132,7 @@ impl ServerContext {
         &self,
         listen_address: &str,
         states: Vec<(WorkerState<S>, ShardId, ShardConfig)>,
+        storage: &S,
         protocol: simple::TransportProtocol,
         shutdown_signal: CancellationToken,
     ) -> JoinSet<()>
@@ -108,6 +157,16 @@ impl ServerContext {
                 Self::start_metrics(&listen_address, port, shutdown_signal.clone());
             }
 
+            if let Some(port) = shard.admin_port {
+                Self::spawn_chain_manager_admin(
+                    &state,
+                    &storage,
+                    port,
+                    shutdown_signal.clone(),
+                    &mut join_set,
+                );
+            }
+
             let server_handle = simple::Server::new(
                 internal_network,
                 listen_address,
@@ -137,6 +196,7 @@ impl ServerContext {
         &self,
         listen_address: &str,
         states: Vec<(WorkerState<S>, ShardId, ShardConfig)>,
+        storage: &S,
         shutdown_signal: CancellationToken,
     ) -> JoinSet<()>
     where
@@ -151,6 +211,16 @@ impl ServerContext {
                 Self::start_metrics(listen_address, port, shutdown_signal.clone());
             }
 
+            if let Some(port) = shard.admin_port {
+                Self::spawn_chain_manager_admin(
+                    &state,
+                    &storage,
+                    port,
+                    shutdown_signal.clone(),
+                    &mut join_set,
+                );
+            }
+
             let server_handle = grpc::GrpcServer::spawn(
                 listen_address.to_string(),
                 shard.port,
@@ -159,6 +229,7 @@ impl ServerContext {
                 self.server_config.internal_network.clone(),
                 self.cross_chain_config.clone(),
                 self.notification_config.clone(),
+                self.request_limits_config.clone(),
                 shutdown_signal.clone(),
                 &mut join_set,
             );
@@ -183,6 +254,76 @@ impl ServerContext {
         prometheus_server::start_metrics((host.to_owned(), port), shutdown_signal);
     }
 
+    fn spawn_chain_manager_admin<S>(
+        state: &WorkerState<S>,
+        storage: &S,
+        port: u16,
+        shutdown_signal: CancellationToken,
+        join_set: &mut JoinSet<()>,
+    ) where
+        S: Storage + Clone + Send + Sync + 'static,
+    {
+        // The admin routes are not authenticated, so they are only reachable locally.
+        let address = SocketAddr::from((Ipv4Addr::LOCALHOST, port));
+        join_set.spawn_task(
+            chain_manager_admin::serve_chain_manager_admin(
+                state.clone(),
+                storage.clone(),
+                address,
+                shutdown_signal,
+            )
+            .unwrap_or_else(|error| {
+                error!("Error running the chain manager admin endpoint: {error:#}")
+            }),
+        );
+    }
+
+    fn spawn_maintenance_tasks<S>(
+        &self,
+        storage: S,
+        shutdown_signal: CancellationToken,
+        join_set: &mut JoinSet<()>,
+    ) where
+        S: Storage + Clone + Send + Sync + 'static,
+    {
+        // Maintenance tasks run once per validator, in the process of the first shard.
+        if !matches!(self.shard, None | Some(0)) {
+            return;
+        }
+        let mut manager = TaskManager::new(storage.clone());
+        if let Some(schedule) = &self.blob_gc_schedule {
+            manager.register(
+                "blob-gc",
+                schedule.clone(),
+                BlobGcTask::new(storage.clone()),
+            );
+        }
+        if let Some(schedule) = &self.namespace_stats_schedule {
+            manager.register(
+                "namespace-stats",
+                schedule.clone(),
+                NamespaceStatsTask::new(storage),
+            );
+        }
+        if manager.is_empty() {
+            return;
+        }
+        let manager = Arc::new(manager);
+        if let Some(port) = self.maintenance_admin_port {
+            // The admin routes are not authenticated, so they are only reachable locally.
+            let address = SocketAddr::from((Ipv4Addr::LOCALHOST, port));
+            join_set.spawn_task(
+                manager
+                    .clone()
+                    .serve_admin(address, shutdown_signal.clone())
+                    .unwrap_or_else(|error| {
+                        error!("Error running the maintenance admin endpoint: {error:#}")
+                    }),
+            );
+        }
+        join_set.spawn_task(manager.run(shutdown_signal));
+    }
+
     fn get_listen_address(&self) -> String {
         // Allow local IP address to be different from the public one.
         "0.0.0.0".to_string()
@@ -197,16 +338,18 @@ impl Runnable for ServerContext {
     where
         S: Storage + Clone + Send + Sync + 'static,
     {
-        let shutdown_notifier = CancellationToken::new();
+        let coordinator = ShutdownCoordinator::new(self.shutdown_drain_timeout);
+        let shutdown_notifier = coordinator.terminate_signal();
+        let drain_signal = coordinator.drain_signal();
         let listen_address = self.get_listen_address();
 
-        tokio::spawn(listen_for_shutdown_signals(shutdown_notifier.clone()));
+        tokio::spawn(listen_for_shutdown_signals(drain_signal.clone()));
 
         // Run the server
         let states = match self.shard {
             Some(shard) => {
                 info!("Running shard number {}", shard);
-                vec![self.make_shard_state(&listen_address, shard, storage)]
+                vec![self.make_shard_state(&listen_address, shard, storage.clone())]
             }
             None => {
                 info!("Running all shards");
@@ -216,16 +359,40 @@ impl Runnable for ServerContext {
                     .collect()
             }
         };
+        let workers: Vec<_> = states.iter().map(|(state, _, _)| state.clone()).collect();
+        let shard_ids: Vec<_> = states.iter().map(|(_, shard_id, _)| *shard_id).collect();
+        if self.active_passive && !self.standby {
+            // Claiming the term fences off any other instance still running on this storage.
+            futures::future::try_join_all(workers.iter().map(WorkerState::promote_to_primary))
+                .await
+                .context("failed to promote the shards to primary")?;
+        }
 
         let mut join_set = match self.server_config.internal_network.protocol {
-            NetworkProtocol::Simple(protocol) => {
-                self.spawn_simple(&listen_address, states, protocol, shutdown_notifier)
-            }
+            NetworkProtocol::Simple(protocol) => self.spawn_simple(
+                &listen_address,
+                states,
+                &storage,
+                protocol,
+                shutdown_notifier.clone(),
+            ),
             NetworkProtocol::Grpc(tls_config) => match tls_config {
-                TlsConfig::ClearText => self.spawn_grpc(&listen_address, states, shutdown_notifier),
+                TlsConfig::ClearText => {
+                    self.spawn_grpc(&listen_address, states, &storage, shutdown_notifier.clone())
+                }
                 TlsConfig::Tls => bail!("TLS not supported between proxy and shards."),
             },
         };
+        self.spawn_maintenance_tasks(storage, drain_signal, &mut join_set);
+        if !self.epoch_migration_interval.is_zero() {
+            for (worker, shard_id) in workers.iter().zip(&shard_ids) {
+                join_set.spawn_task(worker.clone().run_epoch_migration(
+                    self.epoch_migration_interval,
+                    format!("epoch_migration_shard_{shard_id}"),
+                ));
+            }
+        }
+        join_set.spawn_task(coordinator.run(workers).map(drop));
 
         join_set.await_all_tasks().await;
 
@@ -279,6 +446,23 @@ struct ValidatorOptions {
 
     /// The name and the port of the proxies
     proxies: Vec<ProxyConfig>,
+
+    /// The compression of the gRPC messages sent by the proxies and shards.
+    #[serde(default)]
+    compression: CompressionConfig,
+
+    /// Whether the shards sign the cross-chain requests they send to each other.
+    #[serde(default = "default_sign_cross_chain_requests")]
+    sign_cross_chain_requests: bool,
+
+    /// Whether the shards reject the unsigned cross-chain requests. Only enable this once all
+    /// shards sign their requests.
+    #[serde(default)]
+    require_signed_cross_chain_requests: bool,
+}
+
+fn default_sign_cross_chain_requests() -> bool {
+    true
 }
 
 fn make_server_config<R: CryptoRng>(
@@ -300,6 +484,9 @@ fn make_server_config<R: CryptoRng>(
         shards: options.shards,
         block_exporters: options.block_exporters,
         proxies: options.proxies,
+        compression: options.compression,
+        sign_cross_chain_requests: options.sign_cross_chain_requests,
+        require_signed_cross_chain_requests: options.require_signed_cross_chain_requests,
     };
     let validator = ValidatorConfig {
         network,
@@ -341,6 +528,10 @@ enum ServerCommand {
         #[command(flatten)]
         notification_config: NotificationConfig,
 
+        /// Limits on the requests handled concurrently by each shard
+        #[command(flatten)]
+        request_limits_config: RequestLimitsConfig,
+
         /// Runs a specific shard (from 0 to shards-1)
         #[arg(long)]
         shard: Option<usize>,
@@ -354,6 +545,12 @@ enum ServerCommand {
         #[arg(long)]
         wasm_runtime: Option<WasmRuntime>,
 
+        /// The JSON-RPC endpoint of the Ethereum node serving the Ethereum data requested by
+        /// contracts. The node is trusted to serve the canonical chain. If unset, blocks
+        /// reading Ethereum data are rejected.
+        #[arg(long)]
+        ethereum_endpoint: Option<String>,
+
         /// The duration in milliseconds after which an idle chain worker will free its memory.
         #[arg(
             long = "chain-worker-ttl-ms",
@@ -361,6 +558,152 @@ enum ServerCommand {
             value_parser = util::parse_millis
         )]
         chain_worker_ttl: Duration,
+
+        /// The duration in milliseconds during which the outcome of a block proposal
+        /// submitted with an idempotency key is returned again to retries.
+        #[arg(
+            long = "proposal-outcome-ttl-ms",
+            default_value = "60000",
+            value_parser = util::parse_millis
+        )]
+        proposal_outcome_ttl: Duration,
+
+        /// Only validate proposals and certificates, without ever voting. This is meant for
+        /// public RPC gateways that are not part of the committee.
+        #[arg(long)]
+        validation_only: bool,
+
+        /// When to collect the unreferenced blobs of the object store, as a cron schedule
+        /// (`minute hour day-of-month month day-of-week`, in UTC). This requires
+        /// `--storage-blob-offload-min-size`.
+        #[arg(long)]
+        blob_gc_schedule: Option<CronSchedule>,
+
+        /// When to refresh the metrics of the approximate number of keys and bytes in the
+        /// storage namespace, as a cron schedule. They are otherwise only refreshed when the
+        /// `/storage/stats` route of a shard's admin endpoint is called.
+        #[arg(long)]
+        namespace_stats_schedule: Option<CronSchedule>,
+
+        /// The port of the admin endpoint reporting the state of the maintenance tasks
+        /// and allowing to cancel them.
+        #[arg(long)]
+        maintenance_admin_port: Option<u16>,
+
+        /// Prune the proposals and votes of past rounds from the chain managers whenever
+        /// a chain is saved.
+        #[arg(long)]
+        prune_chain_manager_on_save: bool,
+
+        /// The age in milliseconds after which a block proposal is pruned from the chain
+        /// managers, even in the current round.
+        #[arg(long = "max-proposal-age-ms", value_parser = util::parse_millis)]
+        max_proposal_age: Option<Duration>,
+
+        /// Discard a block proposal from the chain managers once the current round is that
+        /// many rounds after the proposal's round, and notify the clients about it.
+        #[arg(long)]
+        proposal_expiry_rounds: Option<NonZeroU32>,
+
+        /// The maximal number of execution state changes of validated blocks kept in storage,
+        /// so that confirming the blocks doesn't require executing them again, even after a
+        /// restart. Zero disables this.
+        #[arg(long, default_value = "0")]
+        max_execution_state_deltas: usize,
+
+        /// The maximal number of events of consecutive confirmed blocks that are buffered and
+        /// written to storage together, e.g. while chains catch up. Zero writes the events of
+        /// each block right away.
+        #[arg(long, default_value = "0")]
+        max_buffered_events: usize,
+
+        /// The URL of a gRPC pacemaker service supplying the deadlines and leaders of the
+        /// consensus rounds, instead of the chains' own round schedules.
+        #[arg(long)]
+        pacemaker_address: Option<String>,
+
+        /// Run as one instance of an active/passive pair sharing the same storage. Only the
+        /// primary instance signs votes and writes chain states, and promoting an instance,
+        /// at startup or through the `POST /signing/promote` route of its shards' admin
+        /// endpoints, fences off the other one.
+        #[arg(long)]
+        active_passive: bool,
+
+        /// Start as the standby of an active/passive pair, which neither signs nor writes
+        /// chain states until it is promoted, instead of promoting itself at startup.
+        #[arg(long, requires = "active_passive")]
+        standby: bool,
+
+        /// How long in milliseconds a promoted instance waits before signing. It must be at
+        /// least the longest time the previous primary may take to save a chain, so that its
+        /// last saves land before the promoted instance loads the chains.
+        #[arg(
+            long = "signing-handover-delay-ms",
+            default_value = "5000",
+            value_parser = util::parse_millis
+        )]
+        signing_handover_delay: Duration,
+
+        /// How long in milliseconds the server waits on shutdown for the chain workers to
+        /// handle the requests they already accepted and save their chains, before exiting
+        /// anyway.
+        #[arg(
+            long = "shutdown-drain-timeout-ms",
+            default_value = "10000",
+            value_parser = util::parse_millis
+        )]
+        shutdown_drain_timeout: Duration,
+
+        /// How often in milliseconds the shards check the admin chain for a new epoch, and
+        /// move the loaded chains to it without waiting for their next block. Zero disables
+        /// this.
+        #[arg(
+            long = "epoch-migration-interval-ms",
+            default_value = "5000",
+            value_parser = util::parse_millis
+        )]
+        epoch_migration_interval: Duration,
+
+        /// A directory where the compiled Wasm modules are kept, so that they are not compiled
+        /// again after a restart. This directory must only be writable by the validator.
+        #[arg(long)]
+        compiled_module_cache_dir: Option<PathBuf>,
+
+        /// The maximum total size in megabytes of the compiled Wasm modules kept on disk.
+        #[arg(long, default_value = "1024")]
+        compiled_module_cache_max_size_mb: u64,
+
+        /// The maximum number of application queries running at the same time, across all
+        /// shards of this process. If unset, queries are not limited.
+        #[arg(long)]
+        max_concurrent_queries: Option<usize>,
+
+        /// The maximum number of queries to the same application running at the same time.
+        #[arg(long, default_value = "16")]
+        max_concurrent_queries_per_application: usize,
+
+        /// How long in milliseconds a query may wait for a service runtime before it is
+        /// rejected.
+        #[arg(
+            long = "max-query-queue-delay-ms",
+            default_value = "2000",
+            value_parser = util::parse_millis
+        )]
+        max_query_queue_delay: Duration,
+
+        /// The memory in megabytes that all service runtimes together may reserve.
+        #[arg(long, default_value = "4096")]
+        service_runtime_max_memory_mb: u32,
+
+        /// The memory in megabytes reserved by each service runtime.
+        #[arg(long, default_value = "64")]
+        service_runtime_memory_mb: u32,
+
+        /// Append the state transitions of the chain workers to a hash-chained audit log in
+        /// this file, signed with the validator key. With `--shard`, the shard number is
+        /// appended to the file name, so that the shards can share this option.
+        #[arg(long)]
+        audit_log: Option<PathBuf>,
     },
 
     /// Act as a trusted third-party and generate all server configurations
@@ -408,9 +751,27 @@ enum ServerCommand {
         #[arg(long)]
         metrics_port: Option<String>,
     },
+
+    /// Signs a new network address of this validator, to be announced on the admin chain
+    /// with `linera announce-validator-address`. Prints the announcement as JSON.
+    #[command(name = "sign-address")]
+    SignAddress {
+        /// Path to the file containing the server configuration of this Linera validator.
+        #[arg(long = "server")]
+        server_config_path: PathBuf,
+
+        /// The new network address, e.g. `grpcs:validator.example.com:443`.
+        #[arg(long)]
+        address: String,
+
+        /// The version of the announcement. It must be higher than the version of this
+        /// validator's previous announcement.
+        #[arg(long)]
+        version: u64,
+    },
 }
 
-fn main() {
+fn main() -> anyhow::Result<()> {
     let options = <ServerOptions as clap::Parser>::parse();
 
     linera_base::tracing::init(&log_file_name_for(&options.command));
@@ -457,11 +818,24 @@ fn log_file_name_for(command: &ServerCommand) -> Cow<'static, str> {
             }
             .into()
         }
-        ServerCommand::Generate { .. } | ServerCommand::EditShards { .. } => "server".into(),
+        ServerCommand::Generate { .. }
+        | ServerCommand::EditShards { .. }
+        | ServerCommand::SignAddress { .. } => "server".into(),
     }
 }
 
-async fn run(options: ServerOptions) {
+/// Returns the file of the audit log of the given shard, or of all the shards of the
+/// process if `shard` is `None`.
+fn audit_log_path(path: PathBuf, shard: Option<usize>) -> PathBuf {
+    let Some(shard) = shard else {
+        return path;
+    };
+    let mut file_name = path.file_name().unwrap_or_default().to_os_string();
+    file_name.push(format!(".shard-{shard}"));
+    path.with_file_name(file_name)
+}
+
+async fn run(options: ServerOptions) -> anyhow::Result<()> {
     match options.command {
         ServerCommand::Run {
             server_config_path,
@@ -469,31 +843,118 @@ async fn run(options: ServerOptions) {
             common_storage_options,
             cross_chain_config,
             notification_config,
+            request_limits_config,
             shard,
             grace_period,
             wasm_runtime,
+            ethereum_endpoint,
             chain_worker_ttl,
+            proposal_outcome_ttl,
+            validation_only,
+            blob_gc_schedule,
+            namespace_stats_schedule,
+            maintenance_admin_port,
+            prune_chain_manager_on_save,
+            max_proposal_age,
+            proposal_expiry_rounds,
+            max_execution_state_deltas,
+            max_buffered_events,
+            pacemaker_address,
+            active_passive,
+            standby,
+            signing_handover_delay,
+            shutdown_drain_timeout,
+            epoch_migration_interval,
+            compiled_module_cache_dir,
+            compiled_module_cache_max_size_mb,
+            max_concurrent_queries,
+            max_concurrent_queries_per_application,
+            max_query_queue_delay,
+            service_runtime_max_memory_mb,
+            service_runtime_memory_mb,
+            audit_log,
         } => {
             linera_version::VERSION_INFO.log();
 
+            assert!(
+                blob_gc_schedule.is_none()
+                    || common_storage_options
+                        .storage_blob_offload_min_size
+                        .is_some(),
+                "The blob garbage collection requires blob offloading: \
+                 use --storage-blob-offload-min-size"
+            );
+
+            if let Some(directory) = compiled_module_cache_dir {
+                linera_execution::enable_compiled_module_cache(CompiledModuleCacheConfig {
+                    directory,
+                    max_size: compiled_module_cache_max_size_mb.saturating_mul(1 << 20),
+                })
+                .expect("Failed to create the directory of the compiled module cache");
+            }
+
             let server_config: ValidatorServerConfig =
                 util::read_json(&server_config_path).expect("Failed to read server config");
+            let pacemaker = pacemaker_address
+                .map(|address| {
+                    let options = grpc::transport::Options::default();
+                    let pacemaker = grpc::GrpcPacemaker::new(address, &options)
+                        .context("Failed to connect to the pacemaker service")?;
+                    anyhow::Ok(Arc::new(pacemaker) as Arc<dyn Pacemaker>)
+                })
+                .transpose()?;
+            let service_runtime_pool = max_concurrent_queries.map(|max_concurrent_queries| {
+                Arc::new(ServiceRuntimePool::new(ServiceRuntimePoolConfig {
+                    max_concurrent_queries,
+                    max_concurrent_queries_per_application,
+                    max_queue_delay: max_query_queue_delay,
+                    max_memory_mb: service_runtime_max_memory_mb,
+                    memory_per_runtime_mb: service_runtime_memory_mb,
+                }))
+            });
+            let audit_log = audit_log.map(|path| {
+                let path = audit_log_path(path, shard);
+                let sink = FileAuditSink::new(path).expect("Failed to open the audit log");
+                let signer = Some(server_config.validator_secret.copy());
+                Arc::new(AuditLog::new(sink, signer).expect("Failed to open the audit log"))
+            });
 
             let job = ServerContext {
                 server_config,
                 cross_chain_config,
                 notification_config,
+                request_limits_config,
                 shard,
                 grace_period,
                 chain_worker_ttl,
+                proposal_outcome_ttl,
+                validation_only,
+                blob_gc_schedule,
+                namespace_stats_schedule,
+                maintenance_admin_port,
+                prune_chain_manager_on_save,
+                max_proposal_age,
+                proposal_expiry_rounds,
+                max_execution_state_deltas,
+                max_buffered_events,
+                pacemaker,
+                active_passive,
+                standby,
+                signing_handover_delay,
+                shutdown_drain_timeout,
+                epoch_migration_interval,
+                service_runtime_pool,
+                audit_log,
             };
             let wasm_runtime = wasm_runtime.with_wasm_default();
+            let mut options = common_storage_options.db_storage_options().await.unwrap();
+            options.execution_runtime_config.ethereum_endpoint = ethereum_endpoint;
             let store_config = storage_config
                 .add_common_storage_options(&common_storage_options)
                 .await
                 .unwrap();
             store_config
-                .run_with_storage(wasm_runtime, job)
+                .run_with_storage(wasm_runtime, &options, job)
                 .boxed()
                 .await
                 .unwrap()
@@ -560,7 +1021,29 @@ async fn run(options: ServerOptions) {
                 .await
                 .expect("Failed to write updated server config");
         }
+
+        ServerCommand::SignAddress {
+            server_config_path,
+            address,
+            version,
+        } => {
+            let server_config: ValidatorServerConfig =
+                util::read_json(&server_config_path).expect("Failed to read server config");
+            let address = ValidatorAddress {
+                public_key: server_config.validator_secret.public(),
+                network_address: address,
+                version,
+            };
+            let announcement =
+                SignedValidatorAddress::new(address, &server_config.validator_secret);
+            println!(
+                "{}",
+                serde_json::to_string_pretty(&announcement)
+                    .expect("Failed to serialize the announcement")
+            );
+        }
     }
+    Ok(())
 }
 
 fn generate_shard_configs(
@@ -595,6 +1078,7 @@ fn generate_shard_configs(
             host,
             port,
             metrics_port,
+            admin_port: None,
         };
         shards.push(shard);
     }
@@ -603,7 +1087,7 @@ fn generate_shard_configs(
 
 #[cfg(test)]
 mod test {
-    use linera_rpc::simple::TransportProtocol;
+    use linera_rpc::{config::CompressionAlgorithm, simple::TransportProtocol};
 
     use super::*;
 
@@ -616,6 +1100,9 @@ mod test {
             external_protocol = { Simple = "Tcp" }
             internal_protocol = { Simple = "Udp" }
 
+            [compression]
+            algorithm = "Zstd"
+
             [[proxies]]
             host = "proxy"
             public_port = 20100
@@ -661,13 +1148,21 @@ mod test {
                         host: "host1".into(),
                         port: 9001,
                         metrics_port: Some(5001),
+                        admin_port: None,
                     },
                     ShardConfig {
                         host: "host2".into(),
                         port: 9002,
                         metrics_port: Some(5002),
+                        admin_port: None,
                     },
                 ],
+                compression: CompressionConfig {
+                    algorithm: CompressionAlgorithm::Zstd,
+                    min_message_size: 1024,
+                },
+                sign_cross_chain_requests: true,
+                require_signed_cross_chain_requests: false,
             }
         );
     }
@@ -687,11 +1182,13 @@ mod test {
                     host: "host01".into(),
                     port: 1001,
                     metrics_port: Some(1101),
+                    admin_port: None,
                 },
                 ShardConfig {
                     host: "host02".into(),
                     port: 1002,
                     metrics_port: Some(1102),
+                    admin_port: None,
                 },
             ],
         );
@@ -704,4 +1201,16 @@ mod test {
         )
         .is_err());
     }
+
+    #[test]
+    fn test_audit_log_path() {
+        assert_eq!(
+            audit_log_path("logs/audit.jsonl".into(), None),
+            PathBuf::from("logs/audit.jsonl")
+        );
+        assert_eq!(
+            audit_log_path("logs/audit.jsonl".into(), Some(3)),
+            PathBuf::from("logs/audit.jsonl.shard-3")
+        );
+    }
 }
@@ -0,0 +1,491 @@
+// Copyright (c) Zefchain Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! Authentication and per-client quotas for the requests to the node service.
+
+use std::{
+    collections::HashMap,
+    fs, io,
+    net::{IpAddr, SocketAddr},
+    num::{NonZeroU32, NonZeroUsize},
+    path::PathBuf,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use async_graphql::{
+    extensions::{Extension, ExtensionContext, ExtensionFactory, NextValidation},
+    ServerError, ValidationResult,
+};
+use axum::{
+    extract::{ConnectInfo, Request, State},
+    http::{header::AUTHORIZATION, HeaderMap, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use jsonwebtoken::{Algorithm, DecodingKey, Validation};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use subtle::ConstantTimeEq as _;
+use thiserror::Error;
+
+/// The period over which the requests and the query complexity of a client are counted.
+const QUOTA_WINDOW: Duration = Duration::from_secs(60);
+
+/// The configuration of the [`ServiceAuth`].
+#[derive(Clone, Debug, Default, clap::Args, Serialize)]
+pub struct ServiceAuthConfig {
+    /// A file with the API keys accepted by the node service in the
+    /// `Authorization: Bearer <KEY>` header, one per line. Requests are not authenticated if
+    /// neither API keys nor a JWT secret are set.
+    #[arg(long, env = "LINERA_SERVICE_API_KEYS_FILE")]
+    pub api_keys_file: Option<PathBuf>,
+
+    /// A file with the secret verifying the JSON Web Tokens, signed with HS256, accepted in the
+    /// `Authorization: Bearer <TOKEN>` header. The `sub` claim identifies the client.
+    #[arg(long, env = "LINERA_SERVICE_JWT_SECRET_FILE")]
+    pub jwt_secret_file: Option<PathBuf>,
+
+    /// Require authentication for requests from localhost too, e.g. when the node service is
+    /// behind a reverse proxy.
+    #[arg(long)]
+    pub authenticate_localhost: bool,
+
+    /// The maximal number of requests per minute for each client.
+    #[arg(long)]
+    pub max_requests_per_minute: Option<NonZeroU32>,
+
+    /// The maximal total complexity of the GraphQL queries per minute for each client.
+    #[arg(long)]
+    pub max_query_complexity_per_minute: Option<NonZeroUsize>,
+
+    /// The maximal complexity of a single GraphQL query.
+    #[arg(long)]
+    pub max_query_complexity: Option<usize>,
+
+    /// The maximal depth of a single GraphQL query.
+    #[arg(long)]
+    pub max_query_depth: Option<usize>,
+}
+
+/// The client that sent a request, as far as the quotas are concerned.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Caller {
+    /// A client with the API key at this index in the configuration.
+    ApiKey(usize),
+    /// A client with a JSON Web Token for this subject.
+    Token(String),
+    /// A client that didn't have to authenticate, from this address.
+    Anonymous(IpAddr),
+}
+
+/// The claims of the JSON Web Tokens that are checked.
+#[derive(Deserialize)]
+struct Claims {
+    sub: String,
+}
+
+#[derive(Debug, Error)]
+pub enum AuthError {
+    #[error("missing credentials: set the `Authorization: Bearer` header")]
+    MissingCredentials,
+    #[error("invalid API key or token")]
+    InvalidCredentials,
+    #[error("too many requests: at most {0} are allowed per minute")]
+    TooManyRequests(NonZeroU32),
+    #[error("query complexity quota exceeded: at most {0} is allowed per minute")]
+    ComplexityQuotaExceeded(NonZeroUsize),
+}
+
+impl IntoResponse for AuthError {
+    fn into_response(self) -> Response {
+        let status = match self {
+            AuthError::MissingCredentials | AuthError::InvalidCredentials => {
+                StatusCode::UNAUTHORIZED
+            }
+            AuthError::TooManyRequests(_) | AuthError::ComplexityQuotaExceeded(_) => {
+                StatusCode::TOO_MANY_REQUESTS
+            }
+        };
+        (status, json!({"error": [self.to_string()]}).to_string()).into_response()
+    }
+}
+
+/// What a client used up in the current quota window.
+struct Usage {
+    window_start: Instant,
+    requests: u32,
+    complexity: usize,
+}
+
+impl Usage {
+    fn new(now: Instant) -> Self {
+        Usage {
+            window_start: now,
+            requests: 0,
+            complexity: 0,
+        }
+    }
+}
+
+/// What the clients used up in their current quota windows.
+struct Usages {
+    by_caller: HashMap<Caller, Usage>,
+    /// When the clients whose quota window is over were last removed.
+    last_sweep: Instant,
+}
+
+/// Authenticates the requests to the node service, and enforces the quotas of each client.
+pub struct ServiceAuth {
+    api_keys: Vec<String>,
+    jwt_key: Option<DecodingKey>,
+    authenticate_localhost: bool,
+    max_requests_per_minute: Option<NonZeroU32>,
+    max_query_complexity_per_minute: Option<NonZeroUsize>,
+    usages: Mutex<Usages>,
+}
+
+impl Default for ServiceAuth {
+    fn default() -> Self {
+        ServiceAuth::with_credentials(&ServiceAuthConfig::default(), Vec::new(), None)
+    }
+}
+
+impl ServiceAuth {
+    /// Creates the authentication of the node service, reading the API keys and the JWT secret
+    /// from the files in the configuration.
+    pub fn new(config: &ServiceAuthConfig) -> io::Result<Self> {
+        let api_keys = match &config.api_keys_file {
+            Some(path) => fs::read_to_string(path)?
+                .lines()
+                .map(str::trim)
+                .filter(|key| !key.is_empty())
+                .map(String::from)
+                .collect(),
+            None => Vec::new(),
+        };
+        let jwt_secret = match &config.jwt_secret_file {
+            Some(path) => Some(fs::read_to_string(path)?.trim().to_string()),
+            None => None,
+        };
+        Ok(ServiceAuth::with_credentials(config, api_keys, jwt_secret))
+    }
+
+    fn with_credentials(
+        config: &ServiceAuthConfig,
+        api_keys: Vec<String>,
+        jwt_secret: Option<String>,
+    ) -> Self {
+        ServiceAuth {
+            api_keys,
+            jwt_key: jwt_secret.map(|secret| DecodingKey::from_secret(secret.as_bytes())),
+            authenticate_localhost: config.authenticate_localhost,
+            max_requests_per_minute: config.max_requests_per_minute,
+            max_query_complexity_per_minute: config.max_query_complexity_per_minute,
+            usages: Mutex::new(Usages {
+                by_caller: HashMap::new(),
+                last_sweep: Instant::now(),
+            }),
+        }
+    }
+
+    /// Returns whether clients have to present an API key or a token.
+    fn is_enabled(&self) -> bool {
+        !self.api_keys.is_empty() || self.jwt_key.is_some()
+    }
+
+    /// Identifies the client that sent a request from `address` with the given headers.
+    pub fn authenticate(
+        &self,
+        address: SocketAddr,
+        headers: &HeaderMap,
+    ) -> Result<Caller, AuthError> {
+        let anonymous = Caller::Anonymous(address.ip());
+        if !self.is_enabled() || (address.ip().is_loopback() && !self.authenticate_localhost) {
+            return Ok(anonymous);
+        }
+        let credentials = headers
+            .get(AUTHORIZATION)
+            .ok_or(AuthError::MissingCredentials)?
+            .to_str()
+            .map_err(|_| AuthError::InvalidCredentials)?;
+        let credentials = credentials
+            .strip_prefix("Bearer ")
+            .ok_or(AuthError::InvalidCredentials)?;
+        // Compare in constant time, so that the response time doesn't tell how much of a key
+        // was guessed right.
+        if let Some(index) = self
+            .api_keys
+            .iter()
+            .position(|key| key.as_bytes().ct_eq(credentials.as_bytes()).into())
+        {
+            return Ok(Caller::ApiKey(index));
+        }
+        let jwt_key = self.jwt_key.as_ref().ok_or(AuthError::InvalidCredentials)?;
+        let token = jsonwebtoken::decode::<Claims>(
+            credentials,
+            jwt_key,
+            &Validation::new(Algorithm::HS256),
+        )
+        .map_err(|_| AuthError::InvalidCredentials)?;
+        Ok(Caller::Token(token.claims.sub))
+    }
+
+    /// Counts a request of the client against its quota.
+    pub fn charge_request(&self, caller: &Caller) -> Result<(), AuthError> {
+        let Some(max_requests) = self.max_requests_per_minute else {
+            return Ok(());
+        };
+        self.charge(caller, |usage| {
+            if usage.requests >= max_requests.get() {
+                return Err(AuthError::TooManyRequests(max_requests));
+            }
+            usage.requests += 1;
+            Ok(())
+        })
+    }
+
+    /// Counts the complexity of a GraphQL query of the client against its quota.
+    pub fn charge_query_complexity(
+        &self,
+        caller: &Caller,
+        complexity: usize,
+    ) -> Result<(), AuthError> {
+        let Some(max_complexity) = self.max_query_complexity_per_minute else {
+            return Ok(());
+        };
+        self.charge(caller, |usage| {
+            let total = usage.complexity.saturating_add(complexity);
+            if total > max_complexity.get() {
+                return Err(AuthError::ComplexityQuotaExceeded(max_complexity));
+            }
+            usage.complexity = total;
+            Ok(())
+        })
+    }
+
+    fn charge(
+        &self,
+        caller: &Caller,
+        update: impl FnOnce(&mut Usage) -> Result<(), AuthError>,
+    ) -> Result<(), AuthError> {
+        self.charge_at(Instant::now(), caller, update)
+    }
+
+    fn charge_at(
+        &self,
+        now: Instant,
+        caller: &Caller,
+        update: impl FnOnce(&mut Usage) -> Result<(), AuthError>,
+    ) -> Result<(), AuthError> {
+        let mut usages = self.usages.lock().unwrap();
+        // Forget the clients whose quota window is over, so that the map doesn't grow with
+        // every address that ever sent a request. Doing this once per window is enough, and
+        // keeps the requests from scanning the whole map.
+        if now.duration_since(usages.last_sweep) >= QUOTA_WINDOW {
+            usages
+                .by_caller
+                .retain(|_, usage| now.duration_since(usage.window_start) < QUOTA_WINDOW);
+            usages.last_sweep = now;
+        }
+        let usage = usages
+            .by_caller
+            .entry(caller.clone())
+            .or_insert_with(|| Usage::new(now));
+        if now.duration_since(usage.window_start) >= QUOTA_WINDOW {
+            *usage = Usage::new(now);
+        }
+        update(usage)
+    }
+}
+
+/// Rejects the requests without valid credentials or over their client's request quota.
+/// The [`Caller`] is added to the extensions of the accepted requests.
+pub async fn authenticate(
+    State(auth): State<Arc<ServiceAuth>>,
+    ConnectInfo(address): ConnectInfo<SocketAddr>,
+    mut request: Request,
+    next: Next,
+) -> Result<Response, AuthError> {
+    let caller = auth.authenticate(address, request.headers())?;
+    auth.charge_request(&caller)?;
+    request.extensions_mut().insert(caller);
+    Ok(next.run(request).await)
+}
+
+/// A GraphQL extension counting the complexity of the queries against the quota of the
+/// [`Caller`] in the request data.
+pub struct QueryComplexityQuota(pub Arc<ServiceAuth>);
+
+impl ExtensionFactory for QueryComplexityQuota {
+    fn create(&self) -> Arc<dyn Extension> {
+        Arc::new(QueryComplexityQuotaExtension(self.0.clone()))
+    }
+}
+
+struct QueryComplexityQuotaExtension(Arc<ServiceAuth>);
+
+#[async_trait::async_trait]
+impl Extension for QueryComplexityQuotaExtension {
+    async fn validation(
+        &self,
+        ctx: &ExtensionContext<'_>,
+        next: NextValidation<'_>,
+    ) -> Result<ValidationResult, Vec<ServerError>> {
+        let result = next.run(ctx).await?;
+        if let Some(caller) = ctx.data_opt::<Caller>() {
+            self.0
+                .charge_query_complexity(caller, result.complexity)
+                .map_err(|error| vec![ServerError::new(error.to_string(), None)])?;
+        }
+        Ok(result)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs, net::SocketAddr, time::Instant};
+
+    use axum::http::{header::AUTHORIZATION, HeaderMap, HeaderValue};
+    use jsonwebtoken::{EncodingKey, Header};
+    use serde_json::json;
+
+    use super::{AuthError, Caller, ServiceAuth, ServiceAuthConfig, Usage, QUOTA_WINDOW};
+
+    fn bearer(credentials: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        let value = HeaderValue::from_str(&format!("Bearer {credentials}")).unwrap();
+        headers.insert(AUTHORIZATION, value);
+        headers
+    }
+
+    #[test]
+    fn test_authentication() {
+        let localhost: SocketAddr = "127.0.0.1:8080".parse().unwrap();
+        let remote: SocketAddr = "192.0.2.1:8080".parse().unwrap();
+
+        // Without credentials configured, nobody has to authenticate.
+        let auth = ServiceAuth::new(&ServiceAuthConfig::default()).unwrap();
+        assert_eq!(
+            auth.authenticate(remote, &HeaderMap::new()).unwrap(),
+            Caller::Anonymous(remote.ip())
+        );
+
+        let dir = tempfile::tempdir().unwrap();
+        let api_keys_file = dir.path().join("api_keys");
+        fs::write(&api_keys_file, "first\n\nsecond\n").unwrap();
+        let jwt_secret_file = dir.path().join("jwt_secret");
+        fs::write(&jwt_secret_file, "secret\n").unwrap();
+        let config = ServiceAuthConfig {
+            api_keys_file: Some(api_keys_file),
+            jwt_secret_file: Some(jwt_secret_file),
+            ..ServiceAuthConfig::default()
+        };
+        let auth = ServiceAuth::new(&config).unwrap();
+        assert_eq!(
+            auth.authenticate(localhost, &HeaderMap::new()).unwrap(),
+            Caller::Anonymous(localhost.ip())
+        );
+        assert!(matches!(
+            auth.authenticate(remote, &HeaderMap::new()),
+            Err(AuthError::MissingCredentials)
+        ));
+        assert!(matches!(
+            auth.authenticate(remote, &bearer("third")),
+            Err(AuthError::InvalidCredentials)
+        ));
+        assert_eq!(
+            auth.authenticate(remote, &bearer("second")).unwrap(),
+            Caller::ApiKey(1)
+        );
+
+        let claims = json!({"sub": "alice", "exp": u32::MAX});
+        let key = EncodingKey::from_secret(b"secret");
+        let token = jsonwebtoken::encode(&Header::default(), &claims, &key).unwrap();
+        assert_eq!(
+            auth.authenticate(remote, &bearer(&token)).unwrap(),
+            Caller::Token("alice".to_string())
+        );
+        let key = EncodingKey::from_secret(b"other secret");
+        let token = jsonwebtoken::encode(&Header::default(), &claims, &key).unwrap();
+        assert!(matches!(
+            auth.authenticate(remote, &bearer(&token)),
+            Err(AuthError::InvalidCredentials)
+        ));
+
+        let auth = ServiceAuth::new(&ServiceAuthConfig {
+            authenticate_localhost: true,
+            ..config
+        })
+        .unwrap();
+        assert!(matches!(
+            auth.authenticate(localhost, &HeaderMap::new()),
+            Err(AuthError::MissingCredentials)
+        ));
+    }
+
+    #[test]
+    fn test_quotas() {
+        let auth = ServiceAuth::new(&ServiceAuthConfig {
+            max_requests_per_minute: Some(2.try_into().unwrap()),
+            max_query_complexity_per_minute: Some(10.try_into().unwrap()),
+            ..ServiceAuthConfig::default()
+        })
+        .unwrap();
+        let caller = Caller::ApiKey(0);
+        let other_caller = Caller::ApiKey(1);
+
+        auth.charge_request(&caller).unwrap();
+        auth.charge_request(&caller).unwrap();
+        assert!(matches!(
+            auth.charge_request(&caller),
+            Err(AuthError::TooManyRequests(_))
+        ));
+        auth.charge_request(&other_caller).unwrap();
+
+        auth.charge_query_complexity(&caller, 6).unwrap();
+        assert!(matches!(
+            auth.charge_query_complexity(&caller, 5),
+            Err(AuthError::ComplexityQuotaExceeded(_))
+        ));
+        auth.charge_query_complexity(&caller, 4).unwrap();
+        auth.charge_query_complexity(&other_caller, 10).unwrap();
+    }
+
+    #[test]
+    fn test_quota_windows() {
+        let auth = ServiceAuth::new(&ServiceAuthConfig {
+            max_requests_per_minute: Some(1.try_into().unwrap()),
+            ..ServiceAuthConfig::default()
+        })
+        .unwrap();
+        let caller = Caller::ApiKey(0);
+        let other_caller = Caller::ApiKey(1);
+        let count_request = |usage: &mut Usage| -> Result<(), AuthError> {
+            usage.requests += 1;
+            Ok(())
+        };
+
+        let start = Instant::now();
+        auth.charge_at(start, &caller, count_request).unwrap();
+        let later = start + QUOTA_WINDOW / 2;
+        auth.charge_at(later, &other_caller, count_request).unwrap();
+        assert_eq!(auth.usages.lock().unwrap().by_caller.len(), 2);
+
+        // Once a window has passed since the last sweep, the clients whose window is over are
+        // removed.
+        auth.charge_at(start + QUOTA_WINDOW, &other_caller, count_request)
+            .unwrap();
+        {
+            let usages = auth.usages.lock().unwrap();
+            assert!(!usages.by_caller.contains_key(&caller));
+            assert_eq!(usages.by_caller[&other_caller].requests, 2);
+        }
+
+        // Between the sweeps, the usage of a client is reset once its window is over.
+        auth.charge_at(later + QUOTA_WINDOW, &other_caller, count_request)
+            .unwrap();
+        let usages = auth.usages.lock().unwrap();
+        assert_eq!(usages.by_caller[&other_caller].requests, 1);
+    }
+}
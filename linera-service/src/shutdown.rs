@@ -0,0 +1,84 @@
+// Copyright (c) Zefchain Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! Graceful shutdown of a validator's shards.
+//!
+//! Terminating a shard while a chain worker is saving its chain can lose a vote that was
+//! already sent, and makes the clients retry against a half-updated validator. On a shutdown
+//! signal, the [`ShutdownCoordinator`] first makes the workers reject new requests, then waits
+//! for the chain workers to handle the requests they already queued and to save their chains,
+//! up to a deadline. Only then does it stop the servers, so that the process can exit.
+
+use std::time::Duration;
+
+use futures::future;
+use linera_core::worker::{ShutdownStatus, WorkerState};
+use linera_storage::Storage;
+use tokio_util::sync::CancellationToken;
+use tracing::{info, warn};
+
+/// Coordinates the shutdown of the workers of a validator process.
+pub struct ShutdownCoordinator {
+    /// Cancelled when the shutdown starts: the workers stop accepting requests.
+    drain_signal: CancellationToken,
+    /// Cancelled once the workers are drained: the servers stop.
+    terminate_signal: CancellationToken,
+    /// How long to wait for the chain workers to finish their queued requests.
+    drain_timeout: Duration,
+}
+
+impl ShutdownCoordinator {
+    /// Creates a coordinator that waits at most `drain_timeout` for the workers to drain.
+    pub fn new(drain_timeout: Duration) -> Self {
+        Self {
+            drain_signal: CancellationToken::new(),
+            terminate_signal: CancellationToken::new(),
+            drain_timeout,
+        }
+    }
+
+    /// Returns the token to cancel to start the shutdown, e.g. on `SIGTERM`.
+    pub fn drain_signal(&self) -> CancellationToken {
+        self.drain_signal.clone()
+    }
+
+    /// Returns the token that is cancelled once the process is ready to terminate. Servers
+    /// should keep running until then, to answer the requests that are being handled.
+    pub fn terminate_signal(&self) -> CancellationToken {
+        self.terminate_signal.clone()
+    }
+
+    /// Waits for the shutdown to start, drains `workers`, and then cancels the terminate
+    /// signal. Returns the status of each worker at that point.
+    pub async fn run<S>(self, workers: Vec<WorkerState<S>>) -> Vec<ShutdownStatus>
+    where
+        S: Storage + Clone + Send + Sync + 'static,
+    {
+        let _terminate_guard = self.terminate_signal.drop_guard();
+        self.drain_signal.cancelled().await;
+        info!(
+            "Shutting down: draining {} workers for at most {:?}",
+            workers.len(),
+            self.drain_timeout
+        );
+        let statuses = future::join_all(
+            workers
+                .iter()
+                .map(|worker| worker.drain(self.drain_timeout)),
+        )
+        .await;
+        let aborted = statuses
+            .iter()
+            .map(|status| match status {
+                ShutdownStatus::DeadlineExceeded { aborted } => *aborted,
+                _ => 0,
+            })
+            .sum::<usize>();
+        if aborted == 0 {
+            info!("All workers are drained; ready to terminate");
+        } else {
+            warn!("Ready to terminate after aborting {aborted} chain workers past the deadline");
+        }
+        statuses
+    }
+}
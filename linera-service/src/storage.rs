@@ -1,26 +1,34 @@
 // Copyright (c) Zefchain Labs, Inc.
 // SPDX-License-Identifier: Apache-2.0
 
-use std::{fmt, path::PathBuf, str::FromStr};
+use std::{fmt, path::PathBuf, str::FromStr, sync::Arc};
 
-use anyhow::{anyhow, bail};
+use anyhow::{anyhow, bail, ensure, Context as _};
 use async_trait::async_trait;
 use linera_client::config::GenesisConfig;
-use linera_execution::WasmRuntime;
-use linera_storage::{DbStorage, Storage, DEFAULT_NAMESPACE};
+use linera_execution::{ExecutionRuntimeConfig, WasmRuntime};
+use linera_storage::{
+    object_store::{S3BlobObjectStore, SigV4Signer},
+    BlobCacheConfig, BlobObjectStore, BlobOffloadConfig, DbStorage, KeyValueBlobObjectStore,
+    ReadReplicaConfig, Storage, DEFAULT_NAMESPACE,
+};
 #[cfg(feature = "storage-service")]
 use linera_storage_service::{
     client::StorageServiceDatabase,
     common::{StorageServiceStoreConfig, StorageServiceStoreInternalConfig},
 };
 #[cfg(feature = "dynamodb")]
-use linera_views::dynamo_db::{DynamoDbDatabase, DynamoDbStoreConfig, DynamoDbStoreInternalConfig};
+use linera_views::dynamo_db::{
+    DynamoDbConsistencyConfig, DynamoDbDatabase, DynamoDbStoreConfig, DynamoDbStoreInternalConfig,
+};
 #[cfg(feature = "rocksdb")]
 use linera_views::rocks_db::{
-    PathWithGuard, RocksDbDatabase, RocksDbSpawnMode, RocksDbStoreConfig,
+    DurabilityPolicy, PathWithGuard, RocksDbDatabase, RocksDbSpawnMode, RocksDbStoreConfig,
     RocksDbStoreInternalConfig,
 };
 use linera_views::{
+    change_log::{ChangeLog, WriterSink},
+    context::ScanBudget,
     lru_caching::StorageCacheConfig,
     memory::{MemoryDatabase, MemoryStoreConfig},
     store::{KeyValueDatabase, KeyValueStore},
@@ -35,7 +43,10 @@ use {
 };
 #[cfg(feature = "scylladb")]
 use {
-    linera_views::scylla_db::{ScyllaDbDatabase, ScyllaDbStoreConfig, ScyllaDbStoreInternalConfig},
+    linera_views::scylla_db::{
+        ScyllaDbConsistencyConfig, ScyllaDbDatabase, ScyllaDbStoreConfig,
+        ScyllaDbStoreInternalConfig,
+    },
     std::num::NonZeroU16,
     tracing::debug,
 };
@@ -65,6 +76,94 @@ pub struct CommonStorageOptions {
     /// The replication factor for the keyspace
     #[arg(long, default_value = "1", global = true)]
     pub storage_replication_factor: u32,
+
+    /// When RocksDB writes are synced to disk: `always`, `never` (the default), or every
+    /// given number of milliseconds with `interval:<milliseconds>`. Only supported with
+    /// RocksDB storage
+    #[cfg(feature = "rocksdb")]
+    #[arg(long, global = true)]
+    pub storage_durability: Option<DurabilityPolicy>,
+
+    /// Compact the range of keys removed by a RocksDB prefix deletion when it contained at
+    /// least this many keys
+    #[cfg(feature = "rocksdb")]
+    #[arg(long, global = true)]
+    pub storage_range_compaction_threshold: Option<usize>,
+
+    /// The ScyllaDB consistency level of each class of requests, as a comma-separated list of
+    /// `<class>=<level>` entries such as `blob_prefetch=one`. The classes are
+    /// `certificate_reads`, `blob_prefetch` and `writes`; the unlisted ones and all the other
+    /// requests use `local_quorum`. Writes must reach a quorum
+    #[cfg(feature = "scylladb")]
+    #[arg(long, global = true)]
+    pub storage_scylladb_consistency: Option<ScyllaDbConsistencyConfig>,
+
+    /// The DynamoDB read consistency of each class of requests, as a comma-separated list of
+    /// `<class>=<consistency>` entries such as `certificate_reads=strong`. The classes are
+    /// `certificate_reads` and `blob_prefetch`; the unlisted ones and all the other reads use
+    /// `eventual`
+    #[cfg(feature = "dynamodb")]
+    #[arg(long, global = true)]
+    pub storage_dynamodb_consistency: Option<DynamoDbConsistencyConfig>,
+
+    /// The maximal number of keys found by a prefix scan of a chain state that is only read,
+    /// e.g. to answer queries. Larger scans fail instead of loading all the keys in memory
+    #[arg(long, global = true)]
+    pub storage_scan_max_keys: Option<usize>,
+
+    /// The maximal total size in bytes of the keys and values found by a prefix scan of a
+    /// chain state that is only read
+    #[arg(long, global = true)]
+    pub storage_scan_max_bytes: Option<usize>,
+
+    /// Store the contents of the blobs of at least this many bytes in a separate namespace,
+    /// named after the storage namespace with the suffix `_blobs`, or in the bucket given by
+    /// `--storage-blob-offload-bucket`, instead of with the rest of the data
+    #[arg(long, global = true)]
+    pub storage_blob_offload_min_size: Option<usize>,
+
+    /// Store the contents of the offloaded blobs in a bucket of a cloud storage service:
+    /// `s3://<bucket>/<prefix>` for Amazon S3 or `gs://<bucket>/<prefix>` for Google Cloud
+    /// Storage. The credentials are read from the environment: `AWS_ACCESS_KEY_ID`,
+    /// `AWS_SECRET_ACCESS_KEY`, `AWS_REGION` and optionally `AWS_ENDPOINT_URL` for S3, and
+    /// the HMAC key `GCS_HMAC_ACCESS_ID` and `GCS_HMAC_SECRET` for Google Cloud Storage
+    #[arg(long, global = true, requires = "storage_blob_offload_min_size")]
+    pub storage_blob_offload_bucket: Option<BlobBucketConfig>,
+
+    /// The name under which the storage references the offloaded blobs, so that they are
+    /// only garbage-collected once no storage sharing the bucket references them. Storages
+    /// sharing a bucket must use different names. Defaults to the storage namespace
+    #[arg(long, global = true, requires = "storage_blob_offload_min_size")]
+    pub storage_blob_offload_holder: Option<String>,
+
+    /// A replica of the storage to read from, e.g. in another region, in the same format as
+    /// the storage. It must use the same kind of database, and is never written to
+    #[arg(long, global = true)]
+    pub storage_read_replica: Option<StorageConfig>,
+
+    /// Only read blobs, certificates, confirmed blocks and events from the read replica, and
+    /// never chain states
+    #[arg(long, global = true)]
+    pub storage_read_replica_immutable_only: bool,
+
+    /// Keep blobs of at most this many bytes in total in memory, in a cache shared by all the
+    /// chains of the process, together with the application modules compiled from them
+    #[arg(long, global = true)]
+    pub storage_blob_cache_size: Option<u64>,
+
+    /// Blobs larger than this many bytes are not kept in the blob cache
+    #[arg(long, global = true, requires = "storage_blob_cache_size")]
+    pub storage_blob_cache_max_blob_size: Option<usize>,
+
+    /// The maximal number of compiled contract modules, and of compiled service modules, kept
+    /// in the blob cache
+    #[arg(long, global = true, requires = "storage_blob_cache_size")]
+    pub storage_blob_cache_max_modules: Option<usize>,
+
+    /// Append the changes of the chain states saved by this process to this file, as JSON
+    /// lines
+    #[arg(long, global = true)]
+    pub storage_change_log_file: Option<PathBuf>,
 }
 
 impl CommonStorageOptions {
@@ -75,6 +174,203 @@ impl CommonStorageOptions {
             max_cache_entries: self.storage_max_cache_entries,
         }
     }
+
+    /// Returns the options of the storage built on top of the key-value store.
+    pub async fn db_storage_options(&self) -> Result<DbStorageOptions, anyhow::Error> {
+        let scan_budget = ScanBudget {
+            max_keys: self.storage_scan_max_keys,
+            max_bytes: self.storage_scan_max_bytes,
+        };
+        let blob_offload =
+            self.storage_blob_offload_min_size
+                .map(|min_blob_size| BlobOffloadTarget {
+                    config: BlobOffloadConfig {
+                        min_blob_size,
+                        ..BlobOffloadConfig::default()
+                    },
+                    bucket: self.storage_blob_offload_bucket.clone(),
+                    holder: self.storage_blob_offload_holder.clone(),
+                });
+        let read_replica = match &self.storage_read_replica {
+            Some(storage_config) => {
+                let store_config = storage_config.add_common_storage_options(self).await?;
+                let config = ReadReplicaConfig {
+                    read_immutable_data: true,
+                    read_chain_states: !self.storage_read_replica_immutable_only,
+                };
+                Some((store_config, config))
+            }
+            None => None,
+        };
+        let blob_cache = self.storage_blob_cache_size.map(|max_total_size| {
+            let default = BlobCacheConfig::default();
+            BlobCacheConfig {
+                max_total_size,
+                max_blob_size: self
+                    .storage_blob_cache_max_blob_size
+                    .unwrap_or(default.max_blob_size),
+                max_modules: self
+                    .storage_blob_cache_max_modules
+                    .unwrap_or(default.max_modules),
+            }
+        });
+        Ok(DbStorageOptions {
+            scan_budget: (scan_budget != ScanBudget::default()).then_some(scan_budget),
+            blob_offload,
+            read_replica,
+            blob_cache,
+            change_log_file: self.storage_change_log_file.clone(),
+            execution_runtime_config: ExecutionRuntimeConfig::default(),
+        })
+    }
+}
+
+/// The options of a [`DbStorage`] on top of its key-value store.
+#[derive(Clone, Debug, Default)]
+pub struct DbStorageOptions {
+    /// The budget of the prefix scans of the chain states that are only read.
+    pub scan_budget: Option<ScanBudget>,
+    /// Where and how the contents of large blobs are stored, if not with the rest of the
+    /// data.
+    pub blob_offload: Option<BlobOffloadTarget>,
+    /// The replica of the storage to read from, if any, and the reads it serves.
+    pub read_replica: Option<(StoreConfig, ReadReplicaConfig)>,
+    /// The limits of the cache of blobs and compiled modules shared by all the chains, if any.
+    pub blob_cache: Option<BlobCacheConfig>,
+    /// The file to append the changes of the saved chain states to, if any.
+    pub change_log_file: Option<PathBuf>,
+    /// The configuration of the runtime executing the applications.
+    pub execution_runtime_config: ExecutionRuntimeConfig,
+}
+
+/// The suffix of the namespace holding the contents of the offloaded blobs.
+const BLOB_OFFLOAD_NAMESPACE_SUFFIX: &str = "_blobs";
+
+/// The offloading of the contents of large blobs.
+#[derive(Clone, Debug)]
+pub struct BlobOffloadTarget {
+    /// The configuration of the blob offload layer.
+    pub config: BlobOffloadConfig,
+    /// The bucket holding the contents of the blobs, or `None` for a separate namespace.
+    pub bucket: Option<BlobBucketConfig>,
+    /// The name under which the storage references the blobs, or `None` for its namespace.
+    pub holder: Option<String>,
+}
+
+/// A bucket of a cloud storage service holding the contents of the offloaded blobs.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum BlobBucketConfig {
+    /// A bucket of Amazon S3, or of another service compatible with its API.
+    S3 { bucket: String, prefix: String },
+    /// A bucket of Google Cloud Storage.
+    Gcs { bucket: String, prefix: String },
+}
+
+impl FromStr for BlobBucketConfig {
+    type Err = anyhow::Error;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        let (scheme, path) = input
+            .split_once("://")
+            .ok_or_else(|| anyhow!("Invalid bucket URL: {input}"))?;
+        let (bucket, prefix) = path.split_once('/').unwrap_or((path, ""));
+        ensure!(!bucket.is_empty(), "Missing bucket name in {input}");
+        let (bucket, prefix) = (bucket.to_owned(), prefix.to_owned());
+        match scheme {
+            "s3" => Ok(BlobBucketConfig::S3 { bucket, prefix }),
+            "gs" => Ok(BlobBucketConfig::Gcs { bucket, prefix }),
+            _ => bail!("Unsupported bucket URL scheme {scheme}: use s3 or gs"),
+        }
+    }
+}
+
+impl BlobBucketConfig {
+    /// Connects to the bucket with the credentials found in the environment.
+    fn connect(&self) -> Result<S3BlobObjectStore, anyhow::Error> {
+        let var = |name: &str| {
+            std::env::var(name).with_context(|| format!("{name} must be set to use {self:?}"))
+        };
+        let (object_store, prefix) = match self {
+            BlobBucketConfig::S3 { bucket, prefix } => {
+                let region = var("AWS_REGION")?;
+                let endpoint = std::env::var("AWS_ENDPOINT_URL")
+                    .unwrap_or_else(|_| format!("https://s3.{region}.amazonaws.com"));
+                let signer = SigV4Signer::new(
+                    region,
+                    var("AWS_ACCESS_KEY_ID")?,
+                    var("AWS_SECRET_ACCESS_KEY")?,
+                );
+                let object_store = S3BlobObjectStore::new(&endpoint, bucket.clone(), signer)?;
+                (object_store, prefix)
+            }
+            BlobBucketConfig::Gcs { bucket, prefix } => {
+                let object_store = S3BlobObjectStore::gcs(
+                    bucket.clone(),
+                    var("GCS_HMAC_ACCESS_ID")?,
+                    var("GCS_HMAC_SECRET")?,
+                )?;
+                (object_store, prefix)
+            }
+        };
+        Ok(object_store.with_prefix(prefix)?)
+    }
+}
+
+impl DbStorageOptions {
+    /// Applies the options to the storage using the given database configuration and
+    /// namespace. The configuration of the read replica, if any, is extracted by `replica`,
+    /// which returns `None` if it is for another kind of database.
+    async fn apply<D, C>(
+        &self,
+        mut storage: DbStorage<D, C>,
+        config: &D::Config,
+        namespace: &str,
+        replica: impl FnOnce(StoreConfig) -> Option<(D::Config, String)>,
+    ) -> Result<DbStorage<D, C>, anyhow::Error>
+    where
+        D: KeyValueDatabase,
+        D::Store: KeyValueStore + Send + Sync + 'static,
+        D::Error: Send + Sync,
+    {
+        storage = storage.with_execution_runtime_config(self.execution_runtime_config.clone());
+        if let Some(scan_budget) = self.scan_budget {
+            storage = storage.with_scan_budget(scan_budget);
+        }
+        if let Some(blob_offload) = &self.blob_offload {
+            let object_store: Arc<dyn BlobObjectStore> = match &blob_offload.bucket {
+                Some(bucket) => Arc::new(bucket.connect()?),
+                None => {
+                    let namespace = format!("{namespace}{BLOB_OFFLOAD_NAMESPACE_SUFFIX}");
+                    let database = D::maybe_create_and_connect(config, &namespace).await?;
+                    Arc::new(KeyValueBlobObjectStore::new(database.open_shared(&[])?))
+                }
+            };
+            let holder = blob_offload.holder.as_deref().unwrap_or(namespace);
+            storage = storage.with_blob_offload(object_store, holder, blob_offload.config);
+        }
+        if let Some((store_config, replica_config)) = &self.read_replica {
+            let Some((config, namespace)) = replica(store_config.clone()) else {
+                bail!("The read replica must use the same kind of database as the storage");
+            };
+            let database = D::connect(&config, &namespace).await?;
+            storage = storage.with_read_replica(database, *replica_config);
+        }
+        if let Some(blob_cache) = self.blob_cache {
+            storage = storage.with_blob_cache(blob_cache);
+        }
+        if let Some(path) = &self.change_log_file {
+            let file = std::fs::OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(path)
+                .with_context(|| format!("Failed to open the change log file {path:?}"))?;
+            let change_log = ChangeLog::default()
+                .with_namespace(namespace)
+                .with_sink(Arc::new(WriterSink::new(file)));
+            storage = storage.with_change_log(change_log);
+        }
+        Ok(storage)
+    }
 }
 
 /// The configuration of the key value store in use.
@@ -164,6 +460,18 @@ pub enum InnerStorageConfig {
     },
 }
 
+#[cfg(feature = "rocksdb")]
+impl InnerStorageConfig {
+    /// Returns whether the storage writes to a RocksDB database.
+    fn uses_rocks_db(&self) -> bool {
+        let uses_rocks_db = matches!(self, InnerStorageConfig::RocksDb { .. });
+        #[cfg(feature = "scylladb")]
+        let uses_rocks_db =
+            uses_rocks_db || matches!(self, InnerStorageConfig::DualRocksDbScyllaDb { .. });
+        uses_rocks_db
+    }
+}
+
 /// The description of a storage implementation.
 #[derive(Clone, Debug)]
 #[cfg_attr(any(test), derive(Eq, PartialEq))]
@@ -427,6 +735,14 @@ impl StorageConfig {
         &self,
         options: &CommonStorageOptions,
     ) -> Result<StoreConfig, anyhow::Error> {
+        #[cfg(feature = "rocksdb")]
+        if options.storage_durability.is_some() && !self.inner_storage_config.uses_rocks_db() {
+            bail!(
+                "`--storage-durability` only applies to RocksDB storage, not to {self}. The \
+                durability of a storage service is set with the `--durability` option of its \
+                server"
+            );
+        }
         let namespace = self.namespace.clone();
         match &self.inner_storage_config {
             InnerStorageConfig::Memory { genesis_path } => {
@@ -457,10 +773,14 @@ impl StorageConfig {
             #[cfg(feature = "rocksdb")]
             InnerStorageConfig::RocksDb { path, spawn_mode } => {
                 let path_with_guard = PathWithGuard::new(path.to_path_buf());
+                let durability = options.storage_durability.unwrap_or_default();
+                tracing::info!("Using the {durability} durability policy for RocksDB");
                 let inner_config = RocksDbStoreInternalConfig {
                     spawn_mode: *spawn_mode,
                     path_with_guard,
                     max_stream_queries: options.storage_max_stream_queries,
+                    durability,
+                    range_compaction_threshold: options.storage_range_compaction_threshold,
                 };
                 let config = RocksDbStoreConfig {
                     inner_config,
@@ -474,6 +794,7 @@ impl StorageConfig {
                     use_dynamodb_local: *use_dynamodb_local,
                     max_concurrent_queries: options.storage_max_concurrent_queries,
                     max_stream_queries: options.storage_max_stream_queries,
+                    consistency: options.storage_dynamodb_consistency.unwrap_or_default(),
                 };
                 let config = DynamoDbStoreConfig {
                     inner_config,
@@ -488,6 +809,7 @@ impl StorageConfig {
                     max_stream_queries: options.storage_max_stream_queries,
                     max_concurrent_queries: options.storage_max_concurrent_queries,
                     replication_factor: options.storage_replication_factor,
+                    consistency: options.storage_scylladb_consistency.unwrap_or_default(),
                 };
                 let config = ScyllaDbStoreConfig {
                     inner_config,
@@ -505,6 +827,8 @@ impl StorageConfig {
                     spawn_mode: *spawn_mode,
                     path_with_guard: path_with_guard.clone(),
                     max_stream_queries: options.storage_max_stream_queries,
+                    durability: options.storage_durability.unwrap_or_default(),
+                    range_compaction_threshold: options.storage_range_compaction_threshold,
                 };
                 let first_config = RocksDbStoreConfig {
                     inner_config,
@@ -516,6 +840,7 @@ impl StorageConfig {
                     max_stream_queries: options.storage_max_stream_queries,
                     max_concurrent_queries: options.storage_max_concurrent_queries,
                     replication_factor: options.storage_replication_factor,
+                    consistency: options.storage_scylladb_consistency.unwrap_or_default(),
                 };
                 let second_config = ScyllaDbStoreConfig {
                     inner_config,
@@ -604,6 +929,7 @@ impl StoreConfig {
     pub async fn run_with_storage<Job>(
         self,
         wasm_runtime: Option<WasmRuntime>,
+        options: &DbStorageOptions,
         job: Job,
     ) -> Result<Job::Output, anyhow::Error>
     where
@@ -615,12 +941,20 @@ impl StoreConfig {
                 namespace,
                 genesis_path,
             } => {
-                let mut storage = DbStorage::<MemoryDatabase, _>::maybe_create_and_connect(
+                let storage = DbStorage::<MemoryDatabase, _>::maybe_create_and_connect(
                     &config,
                     &namespace,
                     wasm_runtime,
                 )
                 .await?;
+                let replica = |store| match store {
+                    StoreConfig::Memory {
+                        config, namespace, ..
+                    } => Some((config, namespace)),
+                    #[allow(unreachable_patterns)]
+                    _ => None,
+                };
+                let mut storage = options.apply(storage, &config, &namespace, replica).await?;
                 let genesis_config = crate::util::read_json::<GenesisConfig>(genesis_path)?;
                 // Memory storage must be initialized every time.
                 genesis_config.initialize_storage(&mut storage).await?;
@@ -634,6 +968,11 @@ impl StoreConfig {
                     wasm_runtime,
                 )
                 .await?;
+                let replica = |store| match store {
+                    StoreConfig::StorageService { config, namespace } => Some((config, namespace)),
+                    _ => None,
+                };
+                let storage = options.apply(storage, &config, &namespace, replica).await?;
                 Ok(job.run(storage).await)
             }
             #[cfg(feature = "rocksdb")]
@@ -641,6 +980,11 @@ impl StoreConfig {
                 let storage =
                     DbStorage::<RocksDbDatabase, _>::connect(&config, &namespace, wasm_runtime)
                         .await?;
+                let replica = |store| match store {
+                    StoreConfig::RocksDb { config, namespace } => Some((config, namespace)),
+                    _ => None,
+                };
+                let storage = options.apply(storage, &config, &namespace, replica).await?;
                 Ok(job.run(storage).await)
             }
             #[cfg(feature = "dynamodb")]
@@ -648,6 +992,11 @@ impl StoreConfig {
                 let storage =
                     DbStorage::<DynamoDbDatabase, _>::connect(&config, &namespace, wasm_runtime)
                         .await?;
+                let replica = |store| match store {
+                    StoreConfig::DynamoDb { config, namespace } => Some((config, namespace)),
+                    _ => None,
+                };
+                let storage = options.apply(storage, &config, &namespace, replica).await?;
                 Ok(job.run(storage).await)
             }
             #[cfg(feature = "scylladb")]
@@ -655,6 +1004,11 @@ impl StoreConfig {
                 let storage =
                     DbStorage::<ScyllaDbDatabase, _>::connect(&config, &namespace, wasm_runtime)
                         .await?;
+                let replica = |store| match store {
+                    StoreConfig::ScyllaDb { config, namespace } => Some((config, namespace)),
+                    _ => None,
+                };
+                let storage = options.apply(storage, &config, &namespace, replica).await?;
                 Ok(job.run(storage).await)
             }
             #[cfg(all(feature = "rocksdb", feature = "scylladb"))]
@@ -664,6 +1018,13 @@ impl StoreConfig {
                     _,
                 >::connect(&config, &namespace, wasm_runtime)
                 .await?;
+                let replica = |store| match store {
+                    StoreConfig::DualRocksDbScyllaDb { config, namespace } => {
+                        Some((config, namespace))
+                    }
+                    _ => None,
+                };
+                let storage = options.apply(storage, &config, &namespace, replica).await?;
                 Ok(job.run(storage).await)
             }
         }
@@ -806,6 +1167,32 @@ fn test_rocks_db_storage_config_from_str() {
     );
 }
 
+#[cfg(feature = "rocksdb")]
+#[tokio::test]
+async fn test_storage_durability_only_applies_to_rocks_db() -> anyhow::Result<()> {
+    use std::time::Duration;
+
+    use clap::Parser as _;
+
+    let options =
+        CommonStorageOptions::parse_from(["linera", "--storage-durability", "interval:100"]);
+    let config = StorageConfig::from_str("rocksdb:foo.db")?;
+    let StoreConfig::RocksDb { config, .. } = config.add_common_storage_options(&options).await?
+    else {
+        panic!("Expected a RocksDB configuration");
+    };
+    assert_eq!(
+        config.inner_config.durability,
+        DurabilityPolicy::Interval(Duration::from_millis(100))
+    );
+
+    let config = StorageConfig::from_str("memory:path/to/genesis.json")?;
+    assert!(config.add_common_storage_options(&options).await.is_err());
+    let options = CommonStorageOptions::parse_from(["linera"]);
+    assert!(config.add_common_storage_options(&options).await.is_ok());
+    Ok(())
+}
+
 #[cfg(feature = "dynamodb")]
 #[test]
 fn test_aws_storage_config_from_str() {
@@ -879,3 +1266,406 @@ fn test_scylla_db_storage_config_from_str() {
     assert!(StorageConfig::from_str("scylladb:tcp:address1:tcp:/address2").is_err());
     assert!(StorageConfig::from_str("scylladb:wrong").is_err());
 }
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        collections::{BTreeMap, BTreeSet},
+        sync::Mutex,
+    };
+
+    use axum::{
+        body::Bytes,
+        extract::State,
+        http::{Method, StatusCode, Uri},
+    };
+    use clap::Parser as _;
+    use linera_base::{
+        crypto::CryptoHash,
+        data_types::{Blob, Timestamp},
+        identifiers::ChainId,
+    };
+    use linera_views::{change_log::ChangeLogEntry, views::RootView as _};
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_blob_offload_round_trip() -> anyhow::Result<()> {
+        let config = MemoryStoreConfig {
+            max_stream_queries: 10,
+            kill_on_drop: false,
+        };
+        let namespace = "test_blob_offload_round_trip";
+        let storage =
+            DbStorage::<MemoryDatabase, _>::maybe_create_and_connect(&config, namespace, None)
+                .await?;
+        let options = DbStorageOptions {
+            blob_offload: Some(BlobOffloadTarget {
+                config: BlobOffloadConfig {
+                    min_blob_size: 16,
+                    verify_on_read: true,
+                },
+                bucket: None,
+                holder: None,
+            }),
+            ..DbStorageOptions::default()
+        };
+        let storage = options.apply(storage, &config, namespace, |_| None).await?;
+
+        let large_blob = Blob::new_data(vec![7; 100]);
+        let small_blob = Blob::new_data(vec![1]);
+        storage
+            .write_blobs(&[large_blob.clone(), small_blob.clone()])
+            .await?;
+        assert_eq!(
+            storage.read_blob(large_blob.id()).await?,
+            Some(large_blob.clone())
+        );
+        assert_eq!(storage.read_blob(small_blob.id()).await?, Some(small_blob));
+
+        // Only the large blob was written to the separate namespace.
+        let namespace = format!("{namespace}{BLOB_OFFLOAD_NAMESPACE_SUFFIX}");
+        let database = MemoryDatabase::connect(&config, &namespace).await?;
+        let object_store = KeyValueBlobObjectStore::new(database.open_shared(&[])?);
+        assert_eq!(object_store.list_objects().await?, [large_blob.id()]);
+        Ok(())
+    }
+
+    type Objects = Arc<Mutex<BTreeMap<String, Vec<u8>>>>;
+
+    /// Answers the requests of an [`S3BlobObjectStore`] like S3 would, storing the objects
+    /// by path.
+    async fn handle_s3_request(
+        State(objects): State<Objects>,
+        method: Method,
+        uri: Uri,
+        body: Bytes,
+    ) -> (StatusCode, Vec<u8>) {
+        let mut objects = objects.lock().unwrap();
+        let path = uri.path().to_owned();
+        match method {
+            Method::PUT => {
+                objects.insert(path, body.to_vec());
+                (StatusCode::OK, Vec::new())
+            }
+            Method::DELETE => {
+                objects.remove(&path);
+                (StatusCode::NO_CONTENT, Vec::new())
+            }
+            Method::GET if uri.query().is_some() => {
+                let url = reqwest::Url::parse(&format!("http://localhost{uri}")).unwrap();
+                let (_, prefix) = url
+                    .query_pairs()
+                    .find(|(name, _)| name == "prefix")
+                    .unwrap();
+                let contents = objects
+                    .keys()
+                    .filter_map(|object_path| object_path.strip_prefix(&path))
+                    .filter(|key| key.starts_with(&*prefix))
+                    .map(|key| format!("<Contents><Key>{key}</Key></Contents>"))
+                    .collect::<String>();
+                let body = format!("<ListBucketResult>{contents}</ListBucketResult>");
+                (StatusCode::OK, body.into_bytes())
+            }
+            Method::GET => match objects.get(&path) {
+                Some(bytes) => (StatusCode::OK, bytes.clone()),
+                None => (StatusCode::NOT_FOUND, Vec::new()),
+            },
+            _ => (StatusCode::METHOD_NOT_ALLOWED, Vec::new()),
+        }
+    }
+
+    // Storages sharing a bucket only collect the objects none of them references.
+    #[tokio::test]
+    async fn test_s3_blob_offload_is_shared() -> anyhow::Result<()> {
+        let objects = Objects::default();
+        let app = axum::Router::new()
+            .fallback(handle_s3_request)
+            .with_state(objects.clone());
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await?;
+        let endpoint = format!("http://{}", listener.local_addr()?);
+        tokio::spawn(async move { axum::serve(listener, app).await });
+        let signer = SigV4Signer::new(
+            "us-east-1".to_owned(),
+            "access".to_owned(),
+            "secret".to_owned(),
+        );
+        let object_store = Arc::new(
+            S3BlobObjectStore::new(&endpoint, "bucket".to_owned(), signer)?
+                .with_prefix("validator/")?,
+        );
+        let config = BlobOffloadConfig {
+            min_blob_size: 16,
+            verify_on_read: true,
+        };
+        let make_storage = async |holder: &str| {
+            DbStorage::<MemoryDatabase, _>::make_test_storage(None)
+                .await
+                .with_blob_offload(object_store.clone(), holder, config)
+        };
+        let storage_a = make_storage("a").await;
+        let storage_b = make_storage("b").await;
+
+        let blob = Blob::new_data(vec![7; 100]);
+        storage_a.write_blob(&blob).await?;
+        assert_eq!(storage_a.read_blob(blob.id()).await?, Some(blob.clone()));
+        assert_eq!(object_store.list_objects().await?, [blob.id()]);
+        assert_eq!(object_store.reference_holders(blob.id()).await?, ["a"]);
+
+        // An object that no storage references is deleted by the second pass of `b`, but
+        // the one referenced by `a` is kept.
+        let orphan = Blob::new_data(vec![8; 100]);
+        object_store.put_object(orphan.id(), orphan.bytes()).await?;
+        let report = storage_b.collect_offloaded_blobs(&BTreeSet::new()).await?;
+        assert_eq!(report.candidates, BTreeSet::from([orphan.id()]));
+        let report = storage_b
+            .collect_offloaded_blobs(&report.candidates)
+            .await?;
+        assert_eq!(report.deleted, [orphan.id()]);
+        assert_eq!(object_store.list_objects().await?, [blob.id()]);
+        assert_eq!(storage_a.read_blob(blob.id()).await?, Some(blob));
+        assert!(objects
+            .lock()
+            .unwrap()
+            .keys()
+            .all(|path| path.starts_with("/bucket/validator/")));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_blob_offload_option() -> anyhow::Result<()> {
+        let options = CommonStorageOptions::try_parse_from([
+            "test",
+            "--storage-blob-offload-min-size",
+            "1024",
+        ])?;
+        let blob_offload = options.db_storage_options().await?.blob_offload.unwrap();
+        assert_eq!(blob_offload.config.min_blob_size, 1024);
+        assert!(blob_offload.config.verify_on_read);
+        assert_eq!(blob_offload.bucket, None);
+        let options = CommonStorageOptions::try_parse_from(["test"])?;
+        assert!(options.db_storage_options().await?.blob_offload.is_none());
+
+        let options = CommonStorageOptions::try_parse_from([
+            "test",
+            "--storage-blob-offload-min-size",
+            "1024",
+            "--storage-blob-offload-bucket",
+            "gs://linera-blobs/validator-1/",
+            "--storage-blob-offload-holder",
+            "shard-0",
+        ])?;
+        let blob_offload = options.db_storage_options().await?.blob_offload.unwrap();
+        assert_eq!(
+            blob_offload.bucket,
+            Some(BlobBucketConfig::Gcs {
+                bucket: "linera-blobs".to_owned(),
+                prefix: "validator-1/".to_owned(),
+            })
+        );
+        assert_eq!(blob_offload.holder.as_deref(), Some("shard-0"));
+        assert!(CommonStorageOptions::try_parse_from([
+            "test",
+            "--storage-blob-offload-bucket",
+            "s3://linera-blobs",
+        ])
+        .is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_blob_bucket_config_from_str() -> anyhow::Result<()> {
+        assert_eq!(
+            "s3://linera-blobs".parse::<BlobBucketConfig>()?,
+            BlobBucketConfig::S3 {
+                bucket: "linera-blobs".to_owned(),
+                prefix: String::new(),
+            }
+        );
+        assert_eq!(
+            "s3://linera-blobs/a/b/".parse::<BlobBucketConfig>()?,
+            BlobBucketConfig::S3 {
+                bucket: "linera-blobs".to_owned(),
+                prefix: "a/b/".to_owned(),
+            }
+        );
+        assert!("s3:///prefix".parse::<BlobBucketConfig>().is_err());
+        assert!("ftp://linera-blobs".parse::<BlobBucketConfig>().is_err());
+        assert!("linera-blobs".parse::<BlobBucketConfig>().is_err());
+        Ok(())
+    }
+
+    // Saving a chain state through a storage configured with a change log file appends its
+    // changes to that file.
+    #[tokio::test]
+    async fn test_change_log_file_option() -> anyhow::Result<()> {
+        let directory = tempfile::tempdir()?;
+        let path = directory.path().join("changes.jsonl");
+        let options = CommonStorageOptions::try_parse_from([
+            "test",
+            "--storage-change-log-file",
+            path.to_str().unwrap(),
+        ])?;
+        let options = options.db_storage_options().await?;
+        assert_eq!(options.change_log_file.as_ref(), Some(&path));
+
+        let config = MemoryStoreConfig {
+            max_stream_queries: 10,
+            kill_on_drop: false,
+        };
+        let namespace = "test_change_log_file_option";
+        let storage =
+            DbStorage::<MemoryDatabase, _>::maybe_create_and_connect(&config, namespace, None)
+                .await?;
+        let storage = options.apply(storage, &config, namespace, |_| None).await?;
+        let chain_id = ChainId(CryptoHash::test_hash("chain"));
+        let mut chain = storage.load_chain(chain_id).await?;
+        chain
+            .execution_state
+            .system
+            .timestamp
+            .set(Timestamp::from(1));
+        chain.save().await?;
+
+        // The file is written by another thread.
+        let mut contents = String::new();
+        for _ in 0..100 {
+            contents = std::fs::read_to_string(&path)?;
+            if contents.ends_with('\n') {
+                break;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+        }
+        let entries = contents
+            .lines()
+            .map(serde_json::from_str::<ChangeLogEntry>)
+            .collect::<Result<Vec<_>, _>>()?;
+        let [entry] = entries.as_slice() else {
+            panic!("unexpected change logs {entries:?}");
+        };
+        assert_eq!(entry.namespace, namespace);
+        assert!(entry.root_key.ends_with(&bcs::to_bytes(&chain_id)?));
+        assert!(entry.view_name.ends_with("ChainStateView"));
+        assert!(!entry.changes.is_empty());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_blob_cache_option() -> anyhow::Result<()> {
+        let options = CommonStorageOptions::try_parse_from([
+            "test",
+            "--storage-blob-cache-size",
+            "1048576",
+            "--storage-blob-cache-max-modules",
+            "8",
+        ])?;
+        let blob_cache = options.db_storage_options().await?.blob_cache.unwrap();
+        assert_eq!(blob_cache.max_total_size, 1048576);
+        assert_eq!(
+            blob_cache.max_blob_size,
+            BlobCacheConfig::default().max_blob_size
+        );
+        assert_eq!(blob_cache.max_modules, 8);
+        // The limits of the cache are only accepted with the cache.
+        assert!(CommonStorageOptions::try_parse_from([
+            "test",
+            "--storage-blob-cache-max-modules",
+            "8"
+        ])
+        .is_err());
+
+        let config = MemoryStoreConfig {
+            max_stream_queries: 10,
+            kill_on_drop: false,
+        };
+        let namespace = "test_blob_cache_option";
+        let storage =
+            DbStorage::<MemoryDatabase, _>::maybe_create_and_connect(&config, namespace, None)
+                .await?;
+        let options = options.db_storage_options().await?;
+        let storage = options.apply(storage, &config, namespace, |_| None).await?;
+        assert!(storage.blob_cache().is_some());
+
+        let options = CommonStorageOptions::try_parse_from(["test"])?;
+        let options = options.db_storage_options().await?;
+        assert!(options.blob_cache.is_none());
+        let storage =
+            DbStorage::<MemoryDatabase, _>::maybe_create_and_connect(&config, namespace, None)
+                .await?;
+        let storage = options.apply(storage, &config, namespace, |_| None).await?;
+        assert!(storage.blob_cache().is_none());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_read_replica_option() -> anyhow::Result<()> {
+        let options = CommonStorageOptions::try_parse_from([
+            "test",
+            "--storage-read-replica",
+            "memory:genesis.json:test_read_replica_option",
+            "--storage-read-replica-immutable-only",
+        ])?;
+        let (store_config, replica_config) =
+            options.db_storage_options().await?.read_replica.unwrap();
+        assert!(matches!(
+            store_config,
+            StoreConfig::Memory { namespace, .. } if namespace == "test_read_replica_option"
+        ));
+        assert!(replica_config.read_immutable_data);
+        assert!(!replica_config.read_chain_states);
+        let options = CommonStorageOptions::try_parse_from(["test"])?;
+        assert!(options.db_storage_options().await?.read_replica.is_none());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_read_replica_round_trip() -> anyhow::Result<()> {
+        let config = MemoryStoreConfig {
+            max_stream_queries: 10,
+            kill_on_drop: false,
+        };
+        let namespace = "test_read_replica_round_trip";
+        let replica_namespace = "test_read_replica_round_trip_replica";
+        let replica_store_config = StoreConfig::Memory {
+            config: config.clone(),
+            namespace: replica_namespace.to_string(),
+            genesis_path: PathBuf::from("genesis.json"),
+        };
+        let options = DbStorageOptions {
+            read_replica: Some((replica_store_config, ReadReplicaConfig::default())),
+            ..DbStorageOptions::default()
+        };
+        let replica = |store| match store {
+            StoreConfig::Memory {
+                config, namespace, ..
+            } => Some((config, namespace)),
+            #[allow(unreachable_patterns)]
+            _ => None,
+        };
+
+        // The replica holds a blob that the primary does not have, so it must be read from it.
+        let replica_storage = DbStorage::<MemoryDatabase, _>::maybe_create_and_connect(
+            &config,
+            replica_namespace,
+            None,
+        )
+        .await?;
+        let blob = Blob::new_data(b"replicated".to_vec());
+        replica_storage.write_blob(&blob).await?;
+        let storage =
+            DbStorage::<MemoryDatabase, _>::maybe_create_and_connect(&config, namespace, None)
+                .await?;
+        let storage = options.apply(storage, &config, namespace, replica).await?;
+        assert_eq!(storage.read_blob(blob.id()).await?, Some(blob));
+
+        // A replica for another kind of database is rejected.
+        let storage =
+            DbStorage::<MemoryDatabase, _>::maybe_create_and_connect(&config, namespace, None)
+                .await?;
+        assert!(options
+            .apply(storage, &config, namespace, |_| None)
+            .await
+            .is_err());
+        Ok(())
+    }
+}
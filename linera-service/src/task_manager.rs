@@ -0,0 +1,693 @@
+// Copyright (c) Zefchain Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! Scheduling of background maintenance tasks (garbage collection, pruning, ...) on a
+//! validator.
+//!
+//! Each task runs on a cron-like [`CronSchedule`]. Its state and progress can be
+//! inspected through a small admin endpoint, and a running task can be cancelled. The
+//! checkpoint returned by the last run of a task is persisted in storage and handed to
+//! the next run, so that work can resume across restarts.
+
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt,
+    net::SocketAddr,
+    str::FromStr,
+    sync::{Arc, Mutex},
+};
+
+use anyhow::{bail, ensure, Context as _};
+use async_trait::async_trait;
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    routing::{get, post},
+    Json, Router,
+};
+use chrono::{DateTime, Datelike as _, Duration, NaiveDate, Timelike as _, Utc};
+use linera_base::{data_types::Timestamp, identifiers::BlobId};
+use linera_storage::Storage;
+use serde::Serialize;
+use tokio_util::sync::CancellationToken;
+use tracing::{error, info, warn};
+
+/// A schedule in the usual cron syntax: `minute hour day-of-month month day-of-week`,
+/// interpreted in UTC.
+///
+/// Each field is `*`, a value, a range `a-b`, or a comma-separated list of those, each
+/// optionally followed by a step `/n`. Days of the week go from 0 (Sunday) to 6.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CronSchedule {
+    source: String,
+    minutes: u64,
+    hours: u64,
+    days_of_month: u64,
+    months: u64,
+    days_of_week: u64,
+    /// Whether both day fields are restricted, in which case a day matches if either of
+    /// them does.
+    either_day: bool,
+}
+
+impl CronSchedule {
+    /// Returns the first time strictly after `after` (at the start of a minute) matching
+    /// this schedule, or `None` if there is none within the next five years.
+    pub fn next_after(&self, after: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        let mut time = after
+            .with_second(0)?
+            .with_nanosecond(0)?
+            .checked_add_signed(Duration::minutes(1))?;
+        let limit = after.checked_add_signed(Duration::days(5 * 366))?;
+        while time <= limit {
+            if !contains(self.months, time.month()) {
+                let (year, month) = match time.month() {
+                    12 => (time.year() + 1, 1),
+                    month => (time.year(), month + 1),
+                };
+                time = NaiveDate::from_ymd_opt(year, month, 1)?
+                    .and_hms_opt(0, 0, 0)?
+                    .and_utc();
+            } else if !self.matches_day(time) {
+                time = time
+                    .date_naive()
+                    .succ_opt()?
+                    .and_hms_opt(0, 0, 0)?
+                    .and_utc();
+            } else if !contains(self.hours, time.hour()) {
+                time = time.with_minute(0)? + Duration::hours(1);
+            } else if !contains(self.minutes, time.minute()) {
+                time += Duration::minutes(1);
+            } else {
+                return Some(time);
+            }
+        }
+        None
+    }
+
+    fn matches_day(&self, time: DateTime<Utc>) -> bool {
+        let day_of_month = contains(self.days_of_month, time.day());
+        let day_of_week = contains(self.days_of_week, time.weekday().num_days_from_sunday());
+        if self.either_day {
+            day_of_month || day_of_week
+        } else {
+            day_of_month && day_of_week
+        }
+    }
+}
+
+fn contains(set: u64, value: u32) -> bool {
+    set & (1 << value) != 0
+}
+
+/// Parses one field of a cron expression into a bit set of the values in `min..=max`.
+fn parse_cron_field(field: &str, min: u32, max: u32) -> anyhow::Result<u64> {
+    let mut set = 0;
+    for item in field.split(',') {
+        let (range, step) = match item.split_once('/') {
+            Some((range, step)) => (range, step.parse::<u32>()?),
+            None => (item, 1),
+        };
+        ensure!(step > 0, "invalid step in {item:?}");
+        let (start, end) = if range == "*" {
+            (min, max)
+        } else if let Some((start, end)) = range.split_once('-') {
+            (start.parse()?, end.parse()?)
+        } else {
+            let value = range.parse()?;
+            (value, if item.contains('/') { max } else { value })
+        };
+        ensure!(
+            min <= start && start <= end && end <= max,
+            "{item:?} is not within {min}-{max}"
+        );
+        for value in (start..=end).step_by(step as usize) {
+            set |= 1 << value;
+        }
+    }
+    Ok(set)
+}
+
+impl FromStr for CronSchedule {
+    type Err = anyhow::Error;
+
+    fn from_str(source: &str) -> Result<Self, Self::Err> {
+        let fields = source.split_whitespace().collect::<Vec<_>>();
+        let [minutes, hours, days_of_month, months, days_of_week] = fields[..] else {
+            bail!("expected 5 fields in cron schedule {source:?}");
+        };
+        Ok(CronSchedule {
+            source: source.to_string(),
+            minutes: parse_cron_field(minutes, 0, 59).context("invalid minutes")?,
+            hours: parse_cron_field(hours, 0, 23).context("invalid hours")?,
+            days_of_month: parse_cron_field(days_of_month, 1, 31)
+                .context("invalid days of month")?,
+            months: parse_cron_field(months, 1, 12).context("invalid months")?,
+            days_of_week: parse_cron_field(days_of_week, 0, 6).context("invalid days of week")?,
+            either_day: days_of_month != "*" && days_of_week != "*",
+        })
+    }
+}
+
+impl fmt::Display for CronSchedule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.source)
+    }
+}
+
+/// The outcome of the current or last run of a task.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case", tag = "status")]
+pub enum TaskStatus {
+    /// The task has not run yet.
+    Idle,
+    /// The task is currently running.
+    Running,
+    /// The last run completed successfully.
+    Succeeded,
+    /// The last run failed.
+    Failed { error: String },
+    /// The last run was cancelled.
+    Cancelled,
+}
+
+/// The progress of a running task, as reported by the task itself.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize)]
+pub struct Progress {
+    /// The number of items processed so far.
+    pub done: u64,
+    /// The total number of items to process, if known.
+    pub total: Option<u64>,
+}
+
+/// The observable state of a task.
+#[derive(Clone, Debug, Serialize)]
+pub struct TaskState {
+    pub name: String,
+    pub schedule: String,
+    #[serde(flatten)]
+    pub status: TaskStatus,
+    pub progress: Progress,
+    pub last_started: Option<Timestamp>,
+    pub last_finished: Option<Timestamp>,
+    pub next_run: Option<Timestamp>,
+    /// The number of runs started since the task manager was created.
+    pub runs: u64,
+}
+
+/// A handle for a task to report its progress.
+pub struct TaskProgress<'a>(&'a Mutex<TaskState>);
+
+impl TaskProgress<'_> {
+    /// Records that `done` items out of `total` have been processed.
+    pub fn set(&self, done: u64, total: Option<u64>) {
+        self.0.lock().unwrap().progress = Progress { done, total };
+    }
+}
+
+/// A maintenance task that can be run by the [`TaskManager`].
+#[async_trait]
+pub trait MaintenanceTask: Send + Sync + 'static {
+    /// Runs the task once, starting from the checkpoint saved by the previous run, if any.
+    ///
+    /// Long-running tasks should check `cancellation` regularly and return early once it
+    /// is cancelled. The returned checkpoint, if any, replaces the saved one, including
+    /// when the run was cancelled.
+    async fn run(
+        &self,
+        checkpoint: Option<Vec<u8>>,
+        progress: TaskProgress<'_>,
+        cancellation: &CancellationToken,
+    ) -> anyhow::Result<Option<Vec<u8>>>;
+}
+
+struct ManagedTask {
+    task: Box<dyn MaintenanceTask>,
+    schedule: CronSchedule,
+    state: Mutex<TaskState>,
+    /// The cancellation token of the current run, if the task is running.
+    cancellation: Mutex<Option<CancellationToken>>,
+}
+
+/// Runs maintenance tasks according to their schedules and keeps track of their state.
+pub struct TaskManager<S> {
+    storage: S,
+    tasks: BTreeMap<String, Arc<ManagedTask>>,
+}
+
+impl<S> TaskManager<S>
+where
+    S: Storage + Clone + Send + Sync + 'static,
+{
+    /// Creates a task manager without any tasks, using `storage` for the checkpoints.
+    pub fn new(storage: S) -> Self {
+        Self {
+            storage,
+            tasks: BTreeMap::new(),
+        }
+    }
+
+    /// Returns whether no tasks were registered.
+    pub fn is_empty(&self) -> bool {
+        self.tasks.is_empty()
+    }
+
+    /// Registers a task under the given name, replacing any task with the same name.
+    pub fn register(
+        &mut self,
+        name: impl Into<String>,
+        schedule: CronSchedule,
+        task: impl MaintenanceTask,
+    ) {
+        let name = name.into();
+        let state = TaskState {
+            name: name.clone(),
+            schedule: schedule.to_string(),
+            status: TaskStatus::Idle,
+            progress: Progress::default(),
+            last_started: None,
+            last_finished: None,
+            next_run: None,
+            runs: 0,
+        };
+        let task = ManagedTask {
+            task: Box::new(task),
+            schedule,
+            state: Mutex::new(state),
+            cancellation: Mutex::new(None),
+        };
+        self.tasks.insert(name, Arc::new(task));
+    }
+
+    /// Returns the state of all the tasks, sorted by name.
+    pub fn states(&self) -> Vec<TaskState> {
+        self.tasks
+            .values()
+            .map(|task| task.state.lock().unwrap().clone())
+            .collect()
+    }
+
+    /// Cancels the current run of the given task. Returns whether it was running.
+    pub fn cancel(&self, name: &str) -> anyhow::Result<bool> {
+        let task = self
+            .tasks
+            .get(name)
+            .with_context(|| format!("unknown task {name:?}"))?;
+        let cancellation = task.cancellation.lock().unwrap();
+        match &*cancellation {
+            Some(cancellation) => {
+                cancellation.cancel();
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+
+    /// Runs the given task once, right away.
+    pub async fn run_now(&self, name: &str, shutdown: &CancellationToken) -> anyhow::Result<()> {
+        let task = self
+            .tasks
+            .get(name)
+            .with_context(|| format!("unknown task {name:?}"))?;
+        self.run_task(name, task, shutdown).await;
+        Ok(())
+    }
+
+    /// Runs all the tasks according to their schedules until `shutdown` is cancelled.
+    pub async fn run(self: Arc<Self>, shutdown: CancellationToken) {
+        let schedules = self
+            .tasks
+            .iter()
+            .map(|(name, task)| self.run_schedule(name, task, &shutdown));
+        futures::future::join_all(schedules).await;
+    }
+
+    async fn run_schedule(&self, name: &str, task: &ManagedTask, shutdown: &CancellationToken) {
+        loop {
+            let Some(next_run) = task.schedule.next_after(Utc::now()) else {
+                warn!(
+                    "Task {name} has no upcoming run for schedule {}",
+                    task.schedule
+                );
+                return;
+            };
+            task.state.lock().unwrap().next_run =
+                Some(Timestamp::from(next_run.timestamp_micros() as u64));
+            let delay = (next_run - Utc::now()).to_std().unwrap_or_default();
+            tokio::select! {
+                () = shutdown.cancelled() => return,
+                () = tokio::time::sleep(delay) => {}
+            }
+            self.run_task(name, task, shutdown).await;
+        }
+    }
+
+    async fn run_task(&self, name: &str, task: &ManagedTask, shutdown: &CancellationToken) {
+        let cancellation = shutdown.child_token();
+        *task.cancellation.lock().unwrap() = Some(cancellation.clone());
+        {
+            let mut state = task.state.lock().unwrap();
+            state.status = TaskStatus::Running;
+            state.progress = Progress::default();
+            state.last_started = Some(Timestamp::now());
+            state.runs += 1;
+        }
+        info!("Starting task {name}");
+        let result = async {
+            let checkpoint = self.storage.read_task_checkpoint(name).await?;
+            let progress = TaskProgress(&task.state);
+            let checkpoint = task.task.run(checkpoint, progress, &cancellation).await?;
+            if let Some(checkpoint) = checkpoint {
+                self.storage.write_task_checkpoint(name, checkpoint).await?;
+            }
+            anyhow::Ok(())
+        }
+        .await;
+        *task.cancellation.lock().unwrap() = None;
+        let status = match result {
+            Err(error) => {
+                error!("Task {name} failed: {error:#}");
+                TaskStatus::Failed {
+                    error: format!("{error:#}"),
+                }
+            }
+            Ok(()) if cancellation.is_cancelled() => {
+                info!("Task {name} was cancelled");
+                TaskStatus::Cancelled
+            }
+            Ok(()) => {
+                info!("Task {name} succeeded");
+                TaskStatus::Succeeded
+            }
+        };
+        let mut state = task.state.lock().unwrap();
+        state.status = status;
+        state.last_finished = Some(Timestamp::now());
+    }
+
+    /// Serves the admin endpoint on `address` until `shutdown` is cancelled:
+    /// - `GET /tasks` returns the state of all the tasks as JSON;
+    /// - `POST /tasks/{name}/cancel` cancels the current run of a task.
+    pub async fn serve_admin(
+        self: Arc<Self>,
+        address: SocketAddr,
+        shutdown: CancellationToken,
+    ) -> anyhow::Result<()> {
+        let app = Router::new()
+            .route("/tasks", get(Self::list_handler))
+            .route("/tasks/{name}/cancel", post(Self::cancel_handler))
+            .with_state(self);
+        let listener = tokio::net::TcpListener::bind(address).await?;
+        info!("Maintenance task admin endpoint listening on {address}");
+        axum::serve(listener, app)
+            .with_graceful_shutdown(shutdown.cancelled_owned())
+            .await?;
+        Ok(())
+    }
+
+    async fn list_handler(State(manager): State<Arc<Self>>) -> Json<Vec<TaskState>> {
+        Json(manager.states())
+    }
+
+    async fn cancel_handler(
+        State(manager): State<Arc<Self>>,
+        Path(name): Path<String>,
+    ) -> (StatusCode, String) {
+        match manager.cancel(&name) {
+            Ok(true) => (StatusCode::OK, format!("Cancelling task {name}")),
+            Ok(false) => (StatusCode::CONFLICT, format!("Task {name} is not running")),
+            Err(error) => (StatusCode::NOT_FOUND, error.to_string()),
+        }
+    }
+}
+
+/// The number of offloaded blob objects deleted between two checks of the cancellation.
+const BLOB_GC_BATCH_SIZE: usize = 100;
+
+/// Deletes the offloaded blob objects that are no longer referenced.
+///
+/// Objects are only deleted if they were already unreferenced during the previous run,
+/// whose candidates are kept in the checkpoint. If the run is cancelled, the objects it did
+/// not get to are kept as candidates, to be deleted by the next run.
+pub struct BlobGcTask<S> {
+    storage: S,
+}
+
+impl<S> BlobGcTask<S> {
+    /// Creates a blob garbage collection task for the given storage.
+    pub fn new(storage: S) -> Self {
+        Self { storage }
+    }
+}
+
+#[async_trait]
+impl<S> MaintenanceTask for BlobGcTask<S>
+where
+    S: Storage + Send + Sync + 'static,
+{
+    async fn run(
+        &self,
+        checkpoint: Option<Vec<u8>>,
+        progress: TaskProgress<'_>,
+        cancellation: &CancellationToken,
+    ) -> anyhow::Result<Option<Vec<u8>>> {
+        let previous_candidates = match checkpoint {
+            Some(bytes) => bcs::from_bytes::<BTreeSet<BlobId>>(&bytes)?,
+            None => BTreeSet::new(),
+        };
+        let mut unreferenced = Vec::new();
+        let mut candidates = BTreeSet::new();
+        for blob_id in self.storage.find_unreferenced_offloaded_blobs().await? {
+            if previous_candidates.contains(&blob_id) {
+                unreferenced.push(blob_id);
+            } else {
+                candidates.insert(blob_id);
+            }
+        }
+        let total = unreferenced.len() as u64;
+        let mut deleted = 0;
+        progress.set(deleted, Some(total));
+        for (index, batch) in unreferenced.chunks(BLOB_GC_BATCH_SIZE).enumerate() {
+            if cancellation.is_cancelled() {
+                candidates.extend(&unreferenced[index * BLOB_GC_BATCH_SIZE..]);
+                break;
+            }
+            let deleted_blobs = self
+                .storage
+                .delete_unreferenced_offloaded_blobs(batch)
+                .await?;
+            deleted += deleted_blobs.len() as u64;
+            progress.set(deleted, Some(total));
+        }
+        info!(
+            "Deleted {} offloaded blobs; {} more are unreferenced",
+            deleted,
+            candidates.len()
+        );
+        Ok(Some(bcs::to_bytes(&candidates)?))
+    }
+}
+
+/// Computes the approximate number of keys and bytes in the namespace of the storage, so
+/// that metered backends update the corresponding metrics without the admin endpoint of a
+/// shard being called.
+pub struct NamespaceStatsTask<S> {
+    storage: S,
+}
+
+impl<S> NamespaceStatsTask<S> {
+    /// Creates a task refreshing the namespace statistics of the given storage.
+    pub fn new(storage: S) -> Self {
+        Self { storage }
+    }
+}
+
+#[async_trait]
+impl<S> MaintenanceTask for NamespaceStatsTask<S>
+where
+    S: Storage + Send + Sync + 'static,
+{
+    async fn run(
+        &self,
+        _checkpoint: Option<Vec<u8>>,
+        progress: TaskProgress<'_>,
+        _cancellation: &CancellationToken,
+    ) -> anyhow::Result<Option<Vec<u8>>> {
+        let stats = self.storage.namespace_stats().await?;
+        progress.set(1, Some(1));
+        info!("Refreshed the namespace statistics: {stats:?}");
+        Ok(None)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone as _;
+    use linera_base::data_types::Blob;
+    use linera_storage::{BlobObjectStore, BlobOffloadConfig, DbStorage, KeyValueBlobObjectStore};
+    use linera_views::{memory::MemoryDatabase, store::TestKeyValueDatabase as _};
+
+    use super::*;
+
+    fn at(year: i32, month: u32, day: u32, hour: u32, minute: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(year, month, day, hour, minute, 0)
+            .unwrap()
+    }
+
+    #[test]
+    fn test_cron_schedule() -> anyhow::Result<()> {
+        let schedule = "*/15 * * * *".parse::<CronSchedule>()?;
+        assert_eq!(
+            schedule.next_after(at(2025, 1, 1, 10, 7)),
+            Some(at(2025, 1, 1, 10, 15))
+        );
+        assert_eq!(
+            schedule.next_after(at(2025, 1, 1, 10, 45)),
+            Some(at(2025, 1, 1, 11, 0))
+        );
+
+        // 2025-01-01 was a Wednesday.
+        let schedule = "30 3 * * 0".parse::<CronSchedule>()?;
+        assert_eq!(
+            schedule.next_after(at(2025, 1, 1, 10, 0)),
+            Some(at(2025, 1, 5, 3, 30))
+        );
+
+        let schedule = "0 0 29 2 *".parse::<CronSchedule>()?;
+        assert_eq!(
+            schedule.next_after(at(2025, 1, 1, 0, 0)),
+            Some(at(2028, 2, 29, 0, 0))
+        );
+
+        let schedule = "0 12 1,15 1-6/2 *".parse::<CronSchedule>()?;
+        assert_eq!(
+            schedule.next_after(at(2025, 1, 15, 12, 0)),
+            Some(at(2025, 3, 1, 12, 0))
+        );
+
+        assert!("* * * *".parse::<CronSchedule>().is_err());
+        assert!("60 * * * *".parse::<CronSchedule>().is_err());
+        assert!("* * 0 * *".parse::<CronSchedule>().is_err());
+        assert!("*/0 * * * *".parse::<CronSchedule>().is_err());
+        Ok(())
+    }
+
+    struct CountingTask;
+
+    #[async_trait]
+    impl MaintenanceTask for CountingTask {
+        async fn run(
+            &self,
+            checkpoint: Option<Vec<u8>>,
+            progress: TaskProgress<'_>,
+            _cancellation: &CancellationToken,
+        ) -> anyhow::Result<Option<Vec<u8>>> {
+            let count = checkpoint.map_or(0, |bytes| bytes[0]);
+            progress.set(1, Some(1));
+            Ok(Some(vec![count + 1]))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_task_checkpoints_are_persisted() -> anyhow::Result<()> {
+        let storage = DbStorage::<MemoryDatabase, _>::make_test_storage(None).await;
+        let shutdown = CancellationToken::new();
+        let mut manager = TaskManager::new(storage.clone());
+        manager.register("count", "0 0 * * *".parse()?, CountingTask);
+
+        manager.run_now("count", &shutdown).await?;
+        manager.run_now("count", &shutdown).await?;
+        assert_eq!(storage.read_task_checkpoint("count").await?, Some(vec![2]));
+
+        let [state] = &manager.states()[..] else {
+            panic!("expected a single task");
+        };
+        assert_eq!(state.status, TaskStatus::Succeeded);
+        assert_eq!(
+            state.progress,
+            Progress {
+                done: 1,
+                total: Some(1)
+            }
+        );
+        assert_eq!(state.runs, 2);
+        assert!(!manager.cancel("count")?);
+        assert!(manager.cancel("missing").is_err());
+        Ok(())
+    }
+
+    async fn list_objects(object_store: &impl BlobObjectStore) -> anyhow::Result<BTreeSet<BlobId>> {
+        Ok(object_store.list_objects().await?.into_iter().collect())
+    }
+
+    #[tokio::test]
+    async fn test_blob_gc_task() -> anyhow::Result<()> {
+        let object_store = Arc::new(KeyValueBlobObjectStore::new(
+            MemoryDatabase::new_test_store().await?,
+        ));
+        let config = BlobOffloadConfig {
+            min_blob_size: 16,
+            verify_on_read: true,
+        };
+        let storage = DbStorage::<MemoryDatabase, _>::make_test_storage(None)
+            .await
+            .with_blob_offload(object_store.clone(), "test", config);
+        let referenced = Blob::new_data(vec![1; 100]);
+        storage.write_blob(&referenced).await?;
+        let unreferenced = Blob::new_data(vec![2; 100]);
+        object_store
+            .put_object(unreferenced.id(), unreferenced.bytes())
+            .await?;
+        let mut manager = TaskManager::new(storage.clone());
+        manager.register(
+            "blob-gc",
+            "0 0 * * *".parse()?,
+            BlobGcTask::new(storage.clone()),
+        );
+        let object_ids = BTreeSet::from([referenced.id(), unreferenced.id()]);
+
+        // The first run only finds the unreferenced object.
+        let shutdown = CancellationToken::new();
+        manager.run_now("blob-gc", &shutdown).await?;
+        assert_eq!(list_objects(&*object_store).await?, object_ids);
+
+        // A cancelled run keeps it as a candidate.
+        let cancelled = CancellationToken::new();
+        cancelled.cancel();
+        manager.run_now("blob-gc", &cancelled).await?;
+        assert_eq!(list_objects(&*object_store).await?, object_ids);
+        assert_eq!(manager.states()[0].status, TaskStatus::Cancelled);
+        let checkpoint = storage.read_task_checkpoint("blob-gc").await?.unwrap();
+        let candidates = bcs::from_bytes::<BTreeSet<BlobId>>(&checkpoint)?;
+        assert_eq!(candidates, BTreeSet::from([unreferenced.id()]));
+
+        // The next run deletes it.
+        manager.run_now("blob-gc", &shutdown).await?;
+        assert_eq!(
+            list_objects(&*object_store).await?,
+            BTreeSet::from([referenced.id()])
+        );
+        assert_eq!(manager.states()[0].status, TaskStatus::Succeeded);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_namespace_stats_task() -> anyhow::Result<()> {
+        let storage = DbStorage::<MemoryDatabase, _>::make_test_storage(None).await;
+        storage.write_blob(&Blob::new_data(vec![1; 100])).await?;
+        let mut manager = TaskManager::new(storage.clone());
+        manager.register(
+            "namespace-stats",
+            "*/5 * * * *".parse()?,
+            NamespaceStatsTask::new(storage.clone()),
+        );
+        manager
+            .run_now("namespace-stats", &CancellationToken::new())
+            .await?;
+        let state = &manager.states()[0];
+        assert_eq!(state.status, TaskStatus::Succeeded);
+        assert_eq!(state.runs, 1);
+        assert!(storage.namespace_stats().await?.total_bytes > 100);
+        Ok(())
+    }
+}
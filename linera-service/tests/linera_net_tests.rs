@@ -4620,7 +4620,7 @@ async fn test_end_to_end_repeated_transfers(config: impl LineraNetConfig) -> Res
                     }
                     break hash;
                 }
-                reason @ Reason::NewRound { .. } => {
+                reason @ (Reason::NewRound { .. } | Reason::ProposalDiscarded { .. }) => {
                     panic!("Unexpected notification about transfer #{i} {reason:?}")
                 }
             }
@@ -1,7 +1,9 @@
 // Copyright (c) Zefchain Labs, Inc.
 // SPDX-License-Identifier: Apache-2.0
 
-use anyhow::{bail, Result};
+use std::path::PathBuf;
+
+use anyhow::{bail, Context as _, Result};
 use linera_base::{command::CommandExt, time::Duration};
 use tokio::process::{Child, Command};
 
@@ -11,6 +13,7 @@ use crate::client::{storage_service_check_absence, storage_service_check_validit
 pub struct StorageService {
     endpoint: String,
     binary: String,
+    snapshot_path: Option<PathBuf>,
 }
 
 /// A storage service running as a child process.
@@ -18,7 +21,7 @@ pub struct StorageService {
 /// The guard preserves the child from destruction and destroys it when
 /// it drops out of scope.
 pub struct StorageServiceGuard {
-    _child: Child,
+    child: Child,
 }
 
 impl StorageService {
@@ -27,12 +30,23 @@ impl StorageService {
         Self {
             endpoint: endpoint.to_string(),
             binary,
+            snapshot_path: None,
         }
     }
 
+    /// Makes the service load its data from the given file on startup, and save it there
+    /// when terminated with [`StorageServiceGuard::terminate`].
+    pub fn with_snapshot_path(mut self, snapshot_path: PathBuf) -> Self {
+        self.snapshot_path = Some(snapshot_path);
+        self
+    }
+
     async fn command(&self) -> Command {
         let mut command = Command::new(&self.binary);
         command.args(["memory", "--endpoint", &self.endpoint]);
+        if let Some(snapshot_path) = &self.snapshot_path {
+            command.arg("--snapshot-path").arg(snapshot_path);
+        }
         command.kill_on_drop(true);
         command
     }
@@ -52,8 +66,8 @@ impl StorageService {
     pub async fn run(&self) -> Result<StorageServiceGuard> {
         self.wait_for_absence().await?;
         let mut command = self.command().await;
-        let _child = command.spawn_into()?;
-        let guard = StorageServiceGuard { _child };
+        let child = command.spawn_into()?;
+        let guard = StorageServiceGuard { child };
         // We iterate until the child is spawned and can be accessed.
         // We add an additional waiting period to avoid problems.
         for i in 1..10 {
@@ -66,3 +80,26 @@ impl StorageService {
         bail!("Failed to start child server");
     }
 }
+
+impl StorageServiceGuard {
+    /// Asks the service to shut down gracefully, giving it a chance to save its snapshot,
+    /// and waits for it to exit.
+    pub async fn terminate(mut self) -> Result<()> {
+        let Some(pid) = self.child.id() else {
+            // The child has already exited.
+            return Ok(());
+        };
+        let status = Command::new("kill")
+            .arg("-TERM")
+            .arg(pid.to_string())
+            .status()
+            .await?;
+        if !status.success() {
+            bail!("Failed to send SIGTERM to the storage service (pid {pid})");
+        }
+        tokio::time::timeout(Duration::from_secs(10), self.child.wait())
+            .await
+            .context("Timed out waiting for the storage service to exit")??;
+        Ok(())
+    }
+}
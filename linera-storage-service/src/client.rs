@@ -19,7 +19,10 @@ use linera_views::store::TestKeyValueDatabase;
 use linera_views::{
     batch::{Batch, WriteOperation},
     lru_caching::LruCachingDatabase,
-    store::{KeyValueDatabase, ReadableKeyValueStore, WithError, WritableKeyValueStore},
+    store::{
+        sample_namespace_stats, KeyValueDatabase, NamespaceStats, ReadableKeyValueStore, WithError,
+        WritableKeyValueStore,
+    },
     FutureSyncExt,
 };
 use serde::de::DeserializeOwned;
@@ -588,6 +591,17 @@ impl KeyValueDatabase for StorageServiceDatabaseInternal {
         let _response = client.process_delete_namespace(request).make_sync().await?;
         Ok(())
     }
+
+    async fn namespace_stats(&self) -> Result<NamespaceStats, StorageServiceStoreError> {
+        let query = RequestListRootKeys {
+            namespace: self.namespace.clone(),
+        };
+        let request = tonic::Request::new(query);
+        let mut client = StorageServiceClient::new(self.channel.clone());
+        let response = client.process_list_root_keys(request).make_sync().await?;
+        let ReplyListRootKeys { root_keys } = response.into_inner();
+        sample_namespace_stats(self, &root_keys).await
+    }
 }
 
 #[cfg(with_testing)]
@@ -1,26 +1,27 @@
 // Copyright (c) Zefchain Labs, Inc.
 // SPDX-License-Identifier: Apache-2.0
 
-use std::{collections::BTreeMap, sync::Arc};
+use std::{collections::BTreeMap, path::PathBuf, sync::Arc};
 
 use async_lock::RwLock;
 use linera_storage_service::common::{KeyPrefix, MAX_PAYLOAD_SIZE};
 use linera_views::{
     batch::Batch,
-    memory::{MemoryDatabase, MemoryStoreConfig},
+    memory::{load_memory_snapshot, save_memory_snapshot, MemoryDatabase, MemoryStoreConfig},
     store::{KeyValueDatabase, ReadableKeyValueStore, WritableKeyValueStore},
 };
 #[cfg(with_rocksdb)]
 use linera_views::{
     lru_caching::StorageCacheConfig,
     rocks_db::{
-        PathWithGuard, RocksDbDatabase, RocksDbSpawnMode, RocksDbStoreConfig,
+        DurabilityPolicy, PathWithGuard, RocksDbDatabase, RocksDbSpawnMode, RocksDbStoreConfig,
         RocksDbStoreInternalConfig,
     },
 };
 use serde::Serialize;
+use tokio_util::sync::CancellationToken;
 use tonic::{transport::Server, Request, Response, Status};
-use tracing::{info, instrument};
+use tracing::{error, info, instrument, warn};
 use tracing_subscriber::fmt::format::FmtSpan;
 
 use crate::key_value_store::{
@@ -262,6 +263,14 @@ enum StorageServerOptions {
         /// Preferred buffer size for async streams.
         #[arg(long, default_value = "10")]
         max_stream_queries: usize,
+        /// A file to load the stored data from on startup, if it exists, and to save it to
+        /// on shutdown.
+        #[arg(long)]
+        snapshot_path: Option<PathBuf>,
+        /// Start from an empty store instead of failing if the snapshot file cannot be
+        /// loaded, e.g. because it is truncated or corrupt.
+        #[arg(long)]
+        discard_invalid_snapshot: bool,
     },
 
     #[cfg(with_rocksdb)]
@@ -288,6 +297,14 @@ enum StorageServerOptions {
         /// The maximum number of entries in the cache.
         #[arg(long, default_value = "1000")]
         max_cache_entries: usize,
+        /// When writes are synced to disk: `always`, `never`, or every given number of
+        /// milliseconds with `interval:<milliseconds>`.
+        #[arg(long, default_value = "never")]
+        durability: DurabilityPolicy,
+        /// Compact the range of keys removed by a prefix deletion when it contained at least
+        /// this many keys.
+        #[arg(long)]
+        range_compaction_threshold: Option<usize>,
     },
 }
 
@@ -585,7 +602,7 @@ impl StorageService for StorageServer {
 }
 
 #[tokio::main]
-async fn main() {
+async fn main() -> anyhow::Result<()> {
     let env_filter = tracing_subscriber::EnvFilter::builder()
         .with_default_directive(tracing_subscriber::filter::LevelFilter::INFO.into())
         .from_env_lossy();
@@ -622,12 +639,24 @@ async fn main() {
         .init();
 
     let options = <StorageServerOptions as clap::Parser>::parse();
-    let (store, endpoint) = match options {
+    let (store, endpoint, snapshot_path) = match options {
         StorageServerOptions::Memory {
             namespace,
             endpoint,
             max_stream_queries,
+            snapshot_path,
+            discard_invalid_snapshot,
         } => {
+            if let Some(path) = &snapshot_path {
+                match load_memory_snapshot(path) {
+                    Ok(true) => info!("Loaded the memory snapshot from {}", path.display()),
+                    Ok(false) => {}
+                    Err(error) if discard_invalid_snapshot => {
+                        warn!("{error}; starting from an empty store");
+                    }
+                    Err(error) => return Err(error.into()),
+                }
+            }
             let config = MemoryStoreConfig {
                 max_stream_queries,
                 kill_on_drop: false,
@@ -637,7 +666,7 @@ async fn main() {
                 .unwrap();
             let store = database.open_shared(&[]).unwrap();
             let store = LocalStore::Memory(store);
-            (store, endpoint)
+            (store, endpoint, snapshot_path)
         }
 
         #[cfg(with_rocksdb)]
@@ -649,7 +678,10 @@ async fn main() {
             max_cache_size,
             max_entry_size,
             max_cache_entries,
+            durability,
+            range_compaction_threshold,
         } => {
+            info!("Using the {durability} durability policy");
             let path_buf = path.into();
             let path_with_guard = PathWithGuard::new(path_buf);
             let spawn_mode = RocksDbSpawnMode::get_spawn_mode_from_runtime();
@@ -657,6 +689,8 @@ async fn main() {
                 spawn_mode,
                 path_with_guard,
                 max_stream_queries,
+                durability,
+                range_compaction_threshold,
             };
             let storage_cache_config = StorageCacheConfig {
                 max_cache_size,
@@ -672,7 +706,7 @@ async fn main() {
                 .expect("store");
             let store = database.open_shared(&[]).expect("Failed to open store");
             let store = LocalStore::RocksDb(store);
-            (store, endpoint)
+            (store, endpoint, None)
         }
     };
     let pending_big_puts = Arc::new(RwLock::new(BTreeMap::default()));
@@ -684,9 +718,20 @@ async fn main() {
     };
     let endpoint = endpoint.parse().unwrap();
     info!("Starting linera_storage_service on endpoint={}", endpoint);
+    let shutdown_signal = CancellationToken::new();
+    tokio::spawn(linera_base::listen_for_shutdown_signals(
+        shutdown_signal.clone(),
+    ));
     Server::builder()
         .add_service(StorageServiceServer::new(store))
-        .serve(endpoint)
+        .serve_with_shutdown(endpoint, shutdown_signal.cancelled_owned())
         .await
         .expect("a successful running of the server");
+    if let Some(path) = snapshot_path {
+        match save_memory_snapshot(&path) {
+            Ok(()) => info!("Saved the memory snapshot to {}", path.display()),
+            Err(error) => error!("Failed to save the memory snapshot: {error}"),
+        }
+    }
+    Ok(())
 }
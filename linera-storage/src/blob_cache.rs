@@ -0,0 +1,203 @@
+// Copyright (c) Zefchain Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! A process-wide cache of blobs, and of the application modules compiled from them.
+//!
+//! Blobs never change once written, so they can be kept in memory without any invalidation.
+//! A single cache is shared by all the clones of a storage, i.e. by all the chain workers and
+//! the execution runtimes of a process: popular bytecode is then read, decompressed and
+//! compiled once instead of once per chain.
+
+use std::sync::Arc;
+
+use linera_base::{data_types::Blob, identifiers::BlobId};
+use linera_execution::{UserContractCode, UserServiceCode};
+use quick_cache::{sync::Cache, Weighter};
+use serde::{Deserialize, Serialize};
+
+#[cfg(with_metrics)]
+mod metrics {
+    use std::sync::LazyLock;
+
+    use linera_base::prometheus_util::{register_int_counter_vec, register_int_gauge_vec};
+    use prometheus::{IntCounterVec, IntGaugeVec};
+
+    /// The number of reads served by the cache, by kind of cached value.
+    pub static BLOB_CACHE_HIT_COUNT: LazyLock<IntCounterVec> = LazyLock::new(|| {
+        register_int_counter_vec(
+            "blob_cache_hit",
+            "The number of reads served by the blob cache",
+            &["kind"],
+        )
+    });
+
+    /// The number of reads that missed the cache, by kind of cached value.
+    pub static BLOB_CACHE_MISS_COUNT: LazyLock<IntCounterVec> = LazyLock::new(|| {
+        register_int_counter_vec(
+            "blob_cache_miss",
+            "The number of reads that missed the blob cache",
+            &["kind"],
+        )
+    });
+
+    /// The number of cached values, by kind.
+    pub static BLOB_CACHE_ENTRIES: LazyLock<IntGaugeVec> = LazyLock::new(|| {
+        register_int_gauge_vec(
+            "blob_cache_entries",
+            "The number of values in the blob cache",
+            &["kind"],
+        )
+    });
+
+    /// The total weight of the cached values, by kind: the size in bytes of the blobs, and
+    /// the number of compiled modules.
+    pub static BLOB_CACHE_WEIGHT: LazyLock<IntGaugeVec> = LazyLock::new(|| {
+        register_int_gauge_vec(
+            "blob_cache_weight",
+            "The total weight of the values in the blob cache",
+            &["kind"],
+        )
+    });
+}
+
+/// The limits of the blob cache.
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub struct BlobCacheConfig {
+    /// The maximal total size in bytes of the cached blobs.
+    pub max_total_size: u64,
+    /// Blobs larger than this many bytes are not cached.
+    pub max_blob_size: usize,
+    /// The maximal number of compiled contract modules, and of compiled service modules.
+    pub max_modules: usize,
+}
+
+impl Default for BlobCacheConfig {
+    fn default() -> Self {
+        Self {
+            max_total_size: 256 * 1024 * 1024,
+            max_blob_size: 16 * 1024 * 1024,
+            max_modules: 256,
+        }
+    }
+}
+
+/// Weighs the cached blobs by the size of their content.
+#[derive(Clone)]
+struct BlobWeighter;
+
+impl Weighter<BlobId, Arc<Blob>> for BlobWeighter {
+    fn weight(&self, _blob_id: &BlobId, blob: &Arc<Blob>) -> u64 {
+        (size_of::<BlobId>() + size_of::<Blob>() + blob.bytes().len()) as u64
+    }
+}
+
+/// The cached blobs, and the modules compiled from bytecode blobs.
+pub struct BlobCache {
+    config: BlobCacheConfig,
+    blobs: Cache<BlobId, Arc<Blob>, BlobWeighter>,
+    contracts: Cache<BlobId, UserContractCode>,
+    services: Cache<BlobId, UserServiceCode>,
+}
+
+impl BlobCache {
+    /// Creates an empty cache with the given limits.
+    pub fn new(config: BlobCacheConfig) -> Self {
+        // The number of items is only used to size the cache initially.
+        let estimated_blobs = (config.max_total_size / (64 * 1024)).max(1) as usize;
+        Self {
+            config,
+            blobs: Cache::with_weighter(estimated_blobs, config.max_total_size, BlobWeighter),
+            contracts: Cache::new(config.max_modules),
+            services: Cache::new(config.max_modules),
+        }
+    }
+
+    /// Returns the cached blob with the given ID, if any.
+    pub(crate) fn blob(&self, blob_id: &BlobId) -> Option<Blob> {
+        let blob = self.blobs.get(blob_id).map(|blob| Blob::clone(&blob));
+        Self::record_access("blob", blob.is_some());
+        blob
+    }
+
+    /// Caches the given blob, unless it is too large.
+    pub(crate) fn insert_blob(&self, blob: &Blob) {
+        if blob.bytes().len() <= self.config.max_blob_size {
+            self.blobs.insert(blob.id(), Arc::new(blob.clone()));
+            Self::record_size("blob", self.blobs.len(), self.blobs.weight());
+        }
+    }
+
+    /// Returns the contract module compiled from the bytecode blob with the given ID, if any.
+    pub(crate) fn contract(&self, bytecode_id: &BlobId) -> Option<UserContractCode> {
+        let contract = self.contracts.get(bytecode_id);
+        Self::record_access("contract", contract.is_some());
+        contract
+    }
+
+    /// Caches the contract module compiled from the bytecode blob with the given ID.
+    pub(crate) fn insert_contract(&self, bytecode_id: BlobId, contract: UserContractCode) {
+        self.contracts.insert(bytecode_id, contract);
+        Self::record_size("contract", self.contracts.len(), self.contracts.weight());
+    }
+
+    /// Returns the service module compiled from the bytecode blob with the given ID, if any.
+    pub(crate) fn service(&self, bytecode_id: &BlobId) -> Option<UserServiceCode> {
+        let service = self.services.get(bytecode_id);
+        Self::record_access("service", service.is_some());
+        service
+    }
+
+    /// Caches the service module compiled from the bytecode blob with the given ID.
+    pub(crate) fn insert_service(&self, bytecode_id: BlobId, service: UserServiceCode) {
+        self.services.insert(bytecode_id, service);
+        Self::record_size("service", self.services.len(), self.services.weight());
+    }
+
+    #[cfg_attr(not(with_metrics), allow(unused_variables))]
+    fn record_size(kind: &str, entries: usize, weight: u64) {
+        #[cfg(with_metrics)]
+        {
+            metrics::BLOB_CACHE_ENTRIES
+                .with_label_values(&[kind])
+                .set(entries as i64);
+            metrics::BLOB_CACHE_WEIGHT
+                .with_label_values(&[kind])
+                .set(weight as i64);
+        }
+    }
+
+    #[cfg_attr(not(with_metrics), allow(unused_variables))]
+    fn record_access(kind: &str, hit: bool) {
+        #[cfg(with_metrics)]
+        if hit {
+            metrics::BLOB_CACHE_HIT_COUNT
+                .with_label_values(&[kind])
+                .inc();
+        } else {
+            metrics::BLOB_CACHE_MISS_COUNT
+                .with_label_values(&[kind])
+                .inc();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use linera_base::data_types::Blob;
+
+    use super::{BlobCache, BlobCacheConfig};
+
+    #[test]
+    fn test_large_blobs_are_not_cached() {
+        let cache = BlobCache::new(BlobCacheConfig {
+            max_blob_size: 4,
+            ..BlobCacheConfig::default()
+        });
+        let small = Blob::new_data(b"abc".to_vec());
+        let large = Blob::new_data(b"abcdef".to_vec());
+        cache.insert_blob(&small);
+        cache.insert_blob(&large);
+        assert_eq!(cache.blob(&small.id()), Some(small));
+        assert_eq!(cache.blob(&large.id()), None);
+    }
+}
@@ -0,0 +1,218 @@
+// Copyright (c) Zefchain Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! Offloading of large blob contents to an external object store.
+//!
+//! When enabled, the contents of blobs above a configurable size are written to a
+//! [`BlobObjectStore`] (e.g. S3 or GCS) keyed by their [`BlobId`], while the key-value
+//! store only keeps a small record of the offloaded blob along with the usual blob states.
+//!
+//! An object store may be shared by several storages, e.g. the shards of a validator. Each
+//! of them, identified by a holder name, records a reference to the objects of the blobs it
+//! stores, and an object is only garbage-collected once no storage references it anymore.
+
+use std::{collections::BTreeSet, sync::Arc};
+
+use async_trait::async_trait;
+use linera_base::{
+    data_types::{Blob, BlobContent},
+    identifiers::BlobId,
+};
+use linera_views::{
+    batch::Batch,
+    store::{KeyValueStore, ReadableKeyValueStore as _, WritableKeyValueStore as _},
+    ViewError,
+};
+use serde::{Deserialize, Serialize};
+
+/// An object store holding the contents of offloaded blobs.
+///
+/// Implementations for cloud object stores only need to map these operations to their
+/// respective `PUT`, `GET`, `DELETE` and `LIST` requests. References are kept per holder,
+/// rather than as counters, so that recording one twice, e.g. when a write is retried, does
+/// not change the reference count.
+#[cfg_attr(not(web), async_trait)]
+#[cfg_attr(web, async_trait(?Send))]
+pub trait BlobObjectStore: Send + Sync {
+    /// Stores the bytes of the blob with the given ID. Overwriting an object with the same
+    /// content must be supported.
+    async fn put_object(&self, blob_id: BlobId, bytes: &[u8]) -> Result<(), ViewError>;
+
+    /// Reads the bytes of the blob with the given ID, if present.
+    async fn get_object(&self, blob_id: BlobId) -> Result<Option<Vec<u8>>, ViewError>;
+
+    /// Deletes the object of the blob with the given ID. Deleting a missing object is not an
+    /// error.
+    async fn delete_object(&self, blob_id: BlobId) -> Result<(), ViewError>;
+
+    /// Lists the IDs of all the objects in the store.
+    async fn list_objects(&self) -> Result<Vec<BlobId>, ViewError>;
+
+    /// Records that the storage called `holder` references the object of the given blob.
+    async fn add_reference(&self, blob_id: BlobId, holder: &str) -> Result<(), ViewError>;
+
+    /// Removes the reference of the storage called `holder` to the object of the given blob,
+    /// if any.
+    async fn remove_reference(&self, blob_id: BlobId, holder: &str) -> Result<(), ViewError>;
+
+    /// Returns the storages referencing the object of the given blob. Their number is the
+    /// reference count of the object.
+    async fn reference_holders(&self, blob_id: BlobId) -> Result<Vec<String>, ViewError>;
+}
+
+/// The prefix of the keys of the objects in a [`KeyValueBlobObjectStore`].
+const OBJECT_TAG: u8 = 0;
+/// The prefix of the keys of the references in a [`KeyValueBlobObjectStore`].
+const REFERENCE_TAG: u8 = 1;
+
+/// A [`BlobObjectStore`] backed by a key-value store, e.g. a separate namespace of one of
+/// the `linera-views` backends.
+#[derive(Clone)]
+pub struct KeyValueBlobObjectStore<Store> {
+    store: Store,
+}
+
+impl<Store> KeyValueBlobObjectStore<Store> {
+    /// Creates a new object store using the given key-value store.
+    pub fn new(store: Store) -> Self {
+        Self { store }
+    }
+}
+
+#[cfg_attr(not(web), async_trait)]
+#[cfg_attr(web, async_trait(?Send))]
+impl<Store> BlobObjectStore for KeyValueBlobObjectStore<Store>
+where
+    Store: KeyValueStore + Send + Sync,
+{
+    async fn put_object(&self, blob_id: BlobId, bytes: &[u8]) -> Result<(), ViewError> {
+        let mut batch = Batch::new();
+        batch.put_key_value_bytes(object_key(blob_id)?, bytes.to_vec());
+        self.store.write_batch(batch).await?;
+        Ok(())
+    }
+
+    async fn get_object(&self, blob_id: BlobId) -> Result<Option<Vec<u8>>, ViewError> {
+        Ok(self.store.read_value_bytes(&object_key(blob_id)?).await?)
+    }
+
+    async fn delete_object(&self, blob_id: BlobId) -> Result<(), ViewError> {
+        let mut batch = Batch::new();
+        batch.delete_key(object_key(blob_id)?);
+        self.store.write_batch(batch).await?;
+        Ok(())
+    }
+
+    async fn list_objects(&self) -> Result<Vec<BlobId>, ViewError> {
+        let keys = self.store.find_keys_by_prefix(&[OBJECT_TAG]).await?;
+        keys.iter().map(|key| Ok(bcs::from_bytes(key)?)).collect()
+    }
+
+    async fn add_reference(&self, blob_id: BlobId, holder: &str) -> Result<(), ViewError> {
+        let mut key = references_prefix(blob_id)?;
+        key.extend(holder.as_bytes());
+        let mut batch = Batch::new();
+        batch.put_key_value_bytes(key, Vec::new());
+        self.store.write_batch(batch).await?;
+        Ok(())
+    }
+
+    async fn remove_reference(&self, blob_id: BlobId, holder: &str) -> Result<(), ViewError> {
+        let mut key = references_prefix(blob_id)?;
+        key.extend(holder.as_bytes());
+        let mut batch = Batch::new();
+        batch.delete_key(key);
+        self.store.write_batch(batch).await?;
+        Ok(())
+    }
+
+    async fn reference_holders(&self, blob_id: BlobId) -> Result<Vec<String>, ViewError> {
+        let keys = self
+            .store
+            .find_keys_by_prefix(&references_prefix(blob_id)?)
+            .await?;
+        keys.into_iter()
+            .map(|key| String::from_utf8(key).map_err(|_| ViewError::InconsistentEntries))
+            .collect()
+    }
+}
+
+fn object_key(blob_id: BlobId) -> Result<Vec<u8>, ViewError> {
+    let mut key = vec![OBJECT_TAG];
+    bcs::serialize_into(&mut key, &blob_id)?;
+    Ok(key)
+}
+
+fn references_prefix(blob_id: BlobId) -> Result<Vec<u8>, ViewError> {
+    let mut key = vec![REFERENCE_TAG];
+    bcs::serialize_into(&mut key, &blob_id)?;
+    Ok(key)
+}
+
+/// The configuration of the blob offload layer.
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub struct BlobOffloadConfig {
+    /// Blobs with at least this many bytes are stored in the object store.
+    pub min_blob_size: usize,
+    /// Whether to check that the content fetched from the object store matches the blob ID.
+    pub verify_on_read: bool,
+}
+
+impl Default for BlobOffloadConfig {
+    fn default() -> Self {
+        Self {
+            min_blob_size: 256 * 1024,
+            verify_on_read: true,
+        }
+    }
+}
+
+/// The record kept in the key-value store for a blob whose content was offloaded.
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub(crate) struct OffloadedBlob {
+    /// The size of the blob content in bytes.
+    pub size: u64,
+}
+
+/// The outcome of a garbage collection pass over the object store.
+#[derive(Clone, Debug, Default)]
+pub struct BlobOffloadGcReport {
+    /// The objects that were deleted during this pass.
+    pub deleted: Vec<BlobId>,
+    /// The unreferenced objects found during this pass. They are deleted by the next pass
+    /// if they are still unreferenced then, so that concurrent writes are never affected.
+    pub candidates: BTreeSet<BlobId>,
+}
+
+/// An object store together with its configuration.
+#[derive(Clone)]
+pub(crate) struct BlobOffload {
+    pub store: Arc<dyn BlobObjectStore>,
+    /// The name under which this storage references the objects it uses.
+    pub holder: String,
+    pub config: BlobOffloadConfig,
+}
+
+impl BlobOffload {
+    /// Returns whether the content of the given blob should be offloaded.
+    pub fn should_offload(&self, blob: &Blob) -> bool {
+        blob.bytes().len() >= self.config.min_blob_size
+    }
+
+    /// Fetches the content of an offloaded blob and verifies it if configured to.
+    pub async fn fetch(&self, blob_id: BlobId) -> Result<Blob, ViewError> {
+        let bytes = self
+            .store
+            .get_object(blob_id)
+            .await?
+            .ok_or_else(|| ViewError::NotFound(format!("offloaded blob {}", blob_id)))?;
+        if !self.config.verify_on_read {
+            return Ok(Blob::new_with_id_unchecked(blob_id, bytes));
+        }
+        let blob = Blob::new(BlobContent::new(blob_id.blob_type, bytes));
+        if blob.id() != blob_id {
+            return Err(ViewError::InconsistentEntries);
+        }
+        Ok(blob)
+    }
+}
@@ -1,7 +1,7 @@
 // Copyright (c) Zefchain Labs, Inc.
 // SPDX-License-Identifier: Apache-2.0
 
-use std::{fmt::Debug, sync::Arc};
+use std::{collections::BTreeMap, fmt::Debug, sync::Arc};
 
 use async_trait::async_trait;
 use dashmap::DashMap;
@@ -9,10 +9,11 @@ use dashmap::DashMap;
 use linera_base::prometheus_util::MeasureLatency as _;
 use linera_base::{
     crypto::CryptoHash,
-    data_types::{Blob, NetworkDescription, TimeDelta, Timestamp},
+    data_types::{Blob, BlockHeight, Epoch, NetworkDescription, TimeDelta, Timestamp},
     identifiers::{ApplicationId, BlobId, ChainId, EventId, IndexAndEvent, StreamId},
 };
 use linera_chain::{
+    block::BlockHeader,
     types::{CertificateValue, ConfirmedBlock, ConfirmedBlockCertificate, LiteCertificate},
     ChainStateView,
 };
@@ -21,9 +22,11 @@ use linera_execution::{
 };
 use linera_views::{
     backends::dual::{DualStoreRootKeyAssignment, StoreInUse},
-    context::ViewContext,
+    change_log::ChangeLog,
+    context::{ScanBudget, ViewContext},
     store::{
-        KeyValueDatabase, KeyValueStore, ReadableKeyValueStore as _, WritableKeyValueStore as _,
+        KeyValueDatabase, KeyValueStore, NamespaceStats, ReadableKeyValueStore as _, RequestClass,
+        WritableKeyValueStore as _,
     },
     views::View,
     ViewError,
@@ -33,10 +36,15 @@ use serde::{Deserialize, Serialize};
 use {
     futures::channel::oneshot::{self, Receiver},
     linera_views::{random::generate_test_namespace, store::TestKeyValueDatabase},
-    std::{cmp::Reverse, collections::BTreeMap},
+    std::cmp::Reverse,
 };
 
-use crate::{ChainRuntimeContext, Clock, Storage};
+use crate::{
+    blob_cache::{BlobCache, BlobCacheConfig},
+    blob_offload::{BlobObjectStore, BlobOffload, BlobOffloadConfig, OffloadedBlob},
+    read_replica::{self, ReadReplica, ReadReplicaConfig},
+    ChainRuntimeContext, Clock, EpochCommittee, ExecutionStateDelta, Storage,
+};
 
 #[cfg(with_metrics)]
 pub mod metrics {
@@ -174,6 +182,16 @@ pub mod metrics {
         )
     });
 
+    /// The metric counting how often a chain state read from the replica is behind the fence.
+    #[doc(hidden)]
+    pub static READ_REPLICA_FENCED_COUNTER: LazyLock<IntCounterVec> = LazyLock::new(|| {
+        register_int_counter_vec(
+            "read_replica_fenced",
+            "The metric counting how often a chain state read from the replica is behind the fence",
+            &[],
+        )
+    });
+
     /// The metric counting how often an event is read from storage.
     #[doc(hidden)]
     pub static READ_EVENT_COUNTER: LazyLock<IntCounterVec> = LazyLock::new(|| {
@@ -252,6 +270,17 @@ impl Batch {
         Ok(())
     }
 
+    fn add_offloaded_blob(&mut self, blob: &Blob) -> Result<(), ViewError> {
+        #[cfg(with_metrics)]
+        metrics::WRITE_BLOB_COUNTER.with_label_values(&[]).inc();
+        let offloaded_blob_key = bcs::to_bytes(&BaseKey::OffloadedBlob(blob.id()))?;
+        let offloaded_blob = OffloadedBlob {
+            size: blob.bytes().len() as u64,
+        };
+        self.put_key_value(offloaded_blob_key, &offloaded_blob)?;
+        Ok(())
+    }
+
     fn add_blob_state(&mut self, blob_id: BlobId, blob_state: &BlobState) -> Result<(), ViewError> {
         let blob_state_key = bcs::to_bytes(&BaseKey::BlobState(blob_id))?;
         self.put_key_value(blob_state_key.to_vec(), blob_state)?;
@@ -271,7 +300,26 @@ impl Batch {
         let block_key = bcs::to_bytes(&BaseKey::ConfirmedBlock(hash))?;
         self.put_key_value(cert_key.to_vec(), &certificate.lite_certificate())?;
         self.put_key_value(block_key.to_vec(), certificate.value())?;
-        Ok(())
+        self.add_block_hash(&certificate.block().header, hash)
+    }
+
+    fn add_certificate_header(
+        &mut self,
+        certificate: &ConfirmedBlockCertificate,
+    ) -> Result<(), ViewError> {
+        let hash = certificate.hash();
+        let cert_key = bcs::to_bytes(&BaseKey::Certificate(hash))?;
+        let header_key = bcs::to_bytes(&BaseKey::BlockHeader(hash))?;
+        self.put_key_value(cert_key.to_vec(), &certificate.lite_certificate())?;
+        self.put_key_value(header_key.to_vec(), &certificate.block().header)?;
+        self.add_block_hash(&certificate.block().header, hash)
+    }
+
+    /// Indexes the hash of a block by its chain and height. The certificate itself is only
+    /// stored once, under its hash.
+    fn add_block_hash(&mut self, header: &BlockHeader, hash: CryptoHash) -> Result<(), ViewError> {
+        let index_key = bcs::to_bytes(&BaseKey::BlockHash(header.chain_id, header.height))?;
+        self.put_key_value(index_key, &hash)
     }
 
     fn add_event(&mut self, event_id: EventId, value: Vec<u8>) -> Result<(), ViewError> {
@@ -296,6 +344,15 @@ impl Batch {
     }
 }
 
+/// The outcome of [`DbStorage::deduplicate_certificates`].
+#[derive(Debug, Default)]
+pub struct CertificateDeduplicationReport {
+    /// The number of block headers deleted because the full block is stored as well.
+    pub removed_headers: usize,
+    /// The number of blocks that were added to the index by chain and height.
+    pub indexed_blocks: usize,
+}
+
 /// Main implementation of the [`Storage`] trait.
 #[derive(Clone)]
 pub struct DbStorage<Database, Clock = WallClock> {
@@ -305,6 +362,11 @@ pub struct DbStorage<Database, Clock = WallClock> {
     user_contracts: Arc<DashMap<ApplicationId, UserContractCode>>,
     user_services: Arc<DashMap<ApplicationId, UserServiceCode>>,
     execution_runtime_config: ExecutionRuntimeConfig,
+    blob_offload: Option<BlobOffload>,
+    read_replica: Option<ReadReplica<Database>>,
+    scan_budget: Option<ScanBudget>,
+    blob_cache: Option<Arc<BlobCache>>,
+    change_log: Option<ChangeLog>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -317,26 +379,76 @@ enum BaseKey {
     Event(EventId),
     BlockExporterState(u32),
     NetworkDescription,
+    OffloadedBlob(BlobId),
+    TaskCheckpoint(String),
+    ExecutionStateDelta(CryptoHash),
+    BlockHeader(CryptoHash),
+    BlockHash(ChainId, BlockHeight),
+    SigningTerm(u32),
+    ExecutionStateUndo(ChainId, BlockHeight),
+    ExecutionStateDeltaUse([u8; 8], CryptoHash),
+    ReadReplicaFence(ChainId),
+    EpochCommittee(Epoch),
 }
 
 const INDEX_CHAIN_ID: u8 = 0;
+const INDEX_CERTIFICATE: u8 = 1;
 const INDEX_BLOB_ID: u8 = 3;
 const INDEX_EVENT_ID: u8 = 5;
+const INDEX_OFFLOADED_BLOB_ID: u8 = 8;
+const INDEX_EXECUTION_STATE_DELTA: u8 = 10;
+const INDEX_BLOCK_HEADER: u8 = 11;
+const INDEX_EXECUTION_STATE_DELTA_USE: u8 = 15;
 const CHAIN_ID_LENGTH: usize = std::mem::size_of::<ChainId>();
 const BLOB_ID_LENGTH: usize = std::mem::size_of::<BlobId>();
 
+/// The maximum number of changes that [`DbStorage::deduplicate_certificates`] writes at once,
+/// so that the changes to a large storage are neither all kept in memory nor written in a
+/// single batch.
+const DEDUPLICATION_BATCH_OPERATIONS: usize = 1000;
+
 #[cfg(test)]
 mod tests {
+    use std::{
+        collections::{BTreeMap, BTreeSet},
+        sync::Arc,
+    };
+
     use linera_base::{
         crypto::CryptoHash,
+        data_types::{ApplicationDescription, Blob, BlockHeight, TimeDelta},
         identifiers::{
             ApplicationId, BlobId, BlobType, ChainId, EventId, GenericApplicationId, StreamId,
             StreamName,
         },
     };
+    use linera_execution::{
+        test_utils::{
+            create_dummy_user_application_description, dummy_chain_description, MockApplication,
+        },
+        ExecutionRuntimeContext as _,
+    };
+    use linera_views::{
+        batch::Batch,
+        context::ScanBudget,
+        memory::MemoryDatabase,
+        random::generate_test_namespace,
+        store::{
+            KeyValueDatabase as _, ReadableKeyValueStore as _, TestKeyValueDatabase as _,
+            WritableKeyValueStore as _,
+        },
+        views::RootView,
+        ViewError,
+    };
 
-    use crate::db_storage::{
-        BaseKey, BLOB_ID_LENGTH, CHAIN_ID_LENGTH, INDEX_BLOB_ID, INDEX_CHAIN_ID, INDEX_EVENT_ID,
+    use crate::{
+        db_storage::{
+            BaseKey, DbStorage, BLOB_ID_LENGTH, CHAIN_ID_LENGTH, INDEX_BLOB_ID, INDEX_BLOCK_HEADER,
+            INDEX_CERTIFICATE, INDEX_CHAIN_ID, INDEX_EVENT_ID, INDEX_EXECUTION_STATE_DELTA,
+            INDEX_EXECUTION_STATE_DELTA_USE, INDEX_OFFLOADED_BLOB_ID,
+        },
+        BlobCacheConfig, BlobObjectStore as _, BlobOffloadConfig, ExecutionStateDelta,
+        KeyValueBlobObjectStore, ReadReplicaConfig, Storage as _, TestClock, WallClock,
     };
 
     // Several functionalities of the storage rely on the way that the serialization
@@ -356,6 +468,126 @@ mod tests {
         assert_eq!(key.len(), 1 + BLOB_ID_LENGTH);
     }
 
+    // The listing of the offloaded blobs in `list_blob_ids` and the garbage collection of
+    // the object store depend on the serialization of `BaseKey::OffloadedBlob`.
+    #[test]
+    fn test_basekey_offloaded_blob_serialization() {
+        let hash = CryptoHash::test_hash("offloaded");
+        let blob_id = BlobId::new(hash, BlobType::Data);
+        let base_key = BaseKey::OffloadedBlob(blob_id);
+        let key = bcs::to_bytes(&base_key).expect("a key");
+        assert_eq!(key[0], INDEX_OFFLOADED_BLOB_ID);
+        assert_eq!(key.len(), 1 + BLOB_ID_LENGTH);
+    }
+
+    // The eviction of execution state deltas in `write_execution_state_delta` depends on
+    // the serialization of `BaseKey::ExecutionStateDelta`.
+    #[test]
+    fn test_basekey_execution_state_delta_serialization() {
+        let hash = CryptoHash::test_hash("state");
+        let base_key = BaseKey::ExecutionStateDelta(hash);
+        let key = bcs::to_bytes(&base_key).expect("a key");
+        assert_eq!(key[0], INDEX_EXECUTION_STATE_DELTA);
+        assert_eq!(key[1..], bcs::to_bytes(&hash).expect("a hash"));
+    }
+
+    // The least recently used execution state deltas are found first in
+    // `read_execution_state_delta_uses` because of the serialization of
+    // `BaseKey::ExecutionStateDeltaUse`.
+    #[test]
+    fn test_basekey_execution_state_delta_use_serialization() {
+        let hash = CryptoHash::test_hash("state");
+        let earlier = BaseKey::ExecutionStateDeltaUse(255u64.to_be_bytes(), hash);
+        let earlier = bcs::to_bytes(&earlier).expect("a key");
+        let later = BaseKey::ExecutionStateDeltaUse(256u64.to_be_bytes(), CryptoHash::default());
+        let later = bcs::to_bytes(&later).expect("a key");
+        assert_eq!(earlier[0], INDEX_EXECUTION_STATE_DELTA_USE);
+        assert_eq!(earlier[1..9], 255u64.to_be_bytes());
+        assert_eq!(earlier[9..], bcs::to_bytes(&hash).expect("a hash"));
+        assert!(earlier < later);
+    }
+
+    // Writing an execution state delta beyond the limit evicts the least recently used one.
+    #[tokio::test]
+    async fn test_execution_state_deltas_are_evicted_in_lru_order() -> anyhow::Result<()> {
+        let storage = DbStorage::<MemoryDatabase, _>::make_test_storage(None).await;
+        let deltas = ["first", "second", "third"].map(|name| {
+            let state_hash = CryptoHash::test_hash(name);
+            ExecutionStateDelta::new(CryptoHash::default(), state_hash, Batch::new()).unwrap()
+        });
+        for delta in &deltas[..2] {
+            storage.write_execution_state_delta(delta, 2).await?;
+            storage.clock().add(TimeDelta::from_micros(1));
+        }
+        // Reading the first delta makes the second one the least recently used.
+        assert!(storage
+            .read_execution_state_delta(deltas[0].state_hash)
+            .await?
+            .is_some());
+        storage.clock().add(TimeDelta::from_micros(1));
+        storage.write_execution_state_delta(&deltas[2], 2).await?;
+
+        let [first, second, third] = deltas.map(|delta| delta.state_hash);
+        assert!(storage.read_execution_state_delta(first).await?.is_some());
+        assert!(storage.read_execution_state_delta(second).await?.is_none());
+        assert!(storage.read_execution_state_delta(third).await?.is_some());
+
+        storage.remove_execution_state_delta(first).await?;
+        assert!(storage.read_execution_state_delta(first).await?.is_none());
+        let store = storage.database.open_shared(&[])?;
+        let uses =
+            DbStorage::<MemoryDatabase, TestClock>::read_execution_state_delta_uses(&store).await?;
+        assert_eq!(
+            uses.into_iter().map(|(_, hash)| hash).collect::<Vec<_>>(),
+            [third]
+        );
+        Ok(())
+    }
+
+    // The scans in `deduplicate_certificates` depend on the serialization of
+    // `BaseKey::Certificate` and `BaseKey::BlockHeader`.
+    #[test]
+    fn test_basekey_certificate_serialization() {
+        let hash = CryptoHash::test_hash("certificate");
+        let hash_bytes = bcs::to_bytes(&hash).expect("a hash");
+        let key = bcs::to_bytes(&BaseKey::Certificate(hash)).expect("a key");
+        assert_eq!(key[0], INDEX_CERTIFICATE);
+        assert_eq!(key[1..], hash_bytes);
+        let key = bcs::to_bytes(&BaseKey::BlockHeader(hash)).expect("a key");
+        assert_eq!(key[0], INDEX_BLOCK_HEADER);
+        assert_eq!(key[1..], hash_bytes);
+    }
+
+    // Deduplicating certificates writes its changes in several batches if needed.
+    #[tokio::test]
+    async fn test_deduplicate_certificates_in_batches() -> anyhow::Result<()> {
+        let config = MemoryDatabase::new_test_config().await?;
+        let database = MemoryDatabase::recreate_and_connect(&config, "dedup").await?;
+        let store = database.open_shared(&[])?;
+        let hashes = (0..5)
+            .map(|i| CryptoHash::test_hash(format!("block {i}")))
+            .collect::<Vec<_>>();
+        let mut batch = Batch::new();
+        for (i, hash) in hashes.iter().enumerate() {
+            batch.put_key_value_bytes(bcs::to_bytes(&BaseKey::BlockHeader(*hash))?, vec![0]);
+            if i % 2 == 0 {
+                batch.put_key_value_bytes(bcs::to_bytes(&BaseKey::ConfirmedBlock(*hash))?, vec![0]);
+            }
+        }
+        store.write_batch(batch).await?;
+
+        let report =
+            DbStorage::<MemoryDatabase, WallClock>::deduplicate_certificates_in_store(&store, 2)
+                .await?;
+        assert_eq!(report.removed_headers, 3);
+        assert_eq!(report.indexed_blocks, 0);
+        for (i, hash) in hashes.iter().enumerate() {
+            let header_key = bcs::to_bytes(&BaseKey::BlockHeader(*hash))?;
+            assert_eq!(store.contains_key(&header_key).await?, i % 2 == 1);
+        }
+        Ok(())
+    }
+
     // The listing of the chains in `list_chain_ids` depends on the serialization
     // of `BaseKey::ChainState`.
     #[test]
@@ -396,6 +628,234 @@ mod tests {
         let key = bcs::to_bytes(&base_key).unwrap();
         assert!(key.starts_with(&prefix));
     }
+
+    // The scan budget limits the chain states that are only read, but never the ones used
+    // to execute blocks.
+    #[tokio::test]
+    async fn test_scan_budget_only_limits_reads() -> anyhow::Result<()> {
+        let budget = ScanBudget {
+            max_keys: Some(1),
+            max_bytes: None,
+        };
+        let storage = DbStorage::<MemoryDatabase, _>::make_test_storage(None)
+            .await
+            .with_scan_budget(budget);
+        let chain_id = ChainId(CryptoHash::test_hash("chain"));
+        let mut chain = storage.load_chain(chain_id).await?;
+        for height in 0..2 {
+            let hash = CryptoHash::test_hash(format!("block {height}"));
+            chain
+                .preprocessed_blocks
+                .insert(&BlockHeight(height), hash)?;
+        }
+        chain.save().await?;
+
+        let chain = storage.load_chain(chain_id).await?;
+        assert_eq!(chain.preprocessed_blocks.indices().await?.len(), 2);
+        let chain = storage.load_chain_for_read(chain_id).await?;
+        assert!(matches!(
+            chain.preprocessed_blocks.indices().await,
+            Err(ViewError::ScanBudgetExceeded(exceeded)) if exceeded == budget
+        ));
+        Ok(())
+    }
+
+    // A chain state is only read from the replica if the fence of its chain in the primary
+    // database exists and the state is not behind it, for every storage instance.
+    #[tokio::test]
+    async fn test_read_replica_fence() -> anyhow::Result<()> {
+        let config = MemoryDatabase::new_test_config().await?;
+        let namespace = generate_test_namespace();
+        let replica_namespace = generate_test_namespace();
+        let chain_id = ChainId(CryptoHash::test_hash("chain"));
+        let primary = MemoryDatabase::recreate_and_connect(&config, &namespace).await?;
+        let replica = MemoryDatabase::recreate_and_connect(&config, &replica_namespace).await?;
+        let storage = DbStorage::new(primary, None, TestClock::new())
+            .with_read_replica(replica, ReadReplicaConfig::default());
+        // Writes to the replica's own database stand in for the replication.
+        let replication = DbStorage::<MemoryDatabase, _>::new(
+            MemoryDatabase::connect(&config, &replica_namespace).await?,
+            None,
+            TestClock::new(),
+        );
+        let set_height = async |storage: &DbStorage<MemoryDatabase, TestClock>, height| {
+            let mut chain = storage.load_chain(chain_id).await?;
+            chain.tip_state.get_mut().next_block_height = BlockHeight(height);
+            chain.save().await?;
+            anyhow::Ok(())
+        };
+        set_height(&storage, 2).await?;
+        set_height(&replication, 1).await?;
+        let read_height = async |storage: &DbStorage<MemoryDatabase, TestClock>| {
+            let chain = storage.load_chain_for_read(chain_id).await?;
+            anyhow::Ok(chain.tip_state.get().next_block_height)
+        };
+
+        // Without a fence, nothing is known about the replica, and the primary is read.
+        assert_eq!(read_height(&storage).await?, BlockHeight(2));
+        // Writing the execution data of a block sets the fence: the replica is behind it.
+        storage
+            .write_execution_state_undo(chain_id, BlockHeight(1), &Batch::new(), 1)
+            .await?;
+        assert_eq!(read_height(&storage).await?, BlockHeight(2));
+        // Once the replica has caught up, or is ahead, it is read.
+        set_height(&replication, 3).await?;
+        assert_eq!(read_height(&storage).await?, BlockHeight(3));
+        // Writing an older block doesn't lower the fence.
+        storage
+            .write_execution_state_undo(chain_id, BlockHeight(0), &Batch::new(), 1)
+            .await?;
+        assert_eq!(
+            storage.read_replica_fence(chain_id).await?,
+            Some(BlockHeight(2))
+        );
+
+        // The fence is stored in the primary database, so other storage instances use it
+        // without having loaded or written the chain.
+        set_height(&replication, 1).await?;
+        let other_storage = DbStorage::new(
+            MemoryDatabase::connect(&config, &namespace).await?,
+            None,
+            TestClock::new(),
+        )
+        .with_read_replica(
+            MemoryDatabase::connect(&config, &replica_namespace).await?,
+            ReadReplicaConfig::default(),
+        );
+        assert_eq!(read_height(&other_storage).await?, BlockHeight(2));
+        set_height(&replication, 3).await?;
+        assert_eq!(read_height(&other_storage).await?, BlockHeight(3));
+        Ok(())
+    }
+
+    // An object shared by several storages is only collected once none of them references
+    // it, and unreferenced objects only after two passes.
+    #[tokio::test]
+    async fn test_offloaded_blobs_are_collected_by_reference_count() -> anyhow::Result<()> {
+        let object_store = Arc::new(KeyValueBlobObjectStore::new(
+            MemoryDatabase::new_test_store().await?,
+        ));
+        let config = BlobOffloadConfig {
+            min_blob_size: 16,
+            verify_on_read: true,
+        };
+        let make_storage = async |holder: &str| {
+            DbStorage::<MemoryDatabase, _>::make_test_storage(None)
+                .await
+                .with_blob_offload(object_store.clone(), holder, config)
+        };
+        let storage_a = make_storage("a").await;
+        let storage_b = make_storage("b").await;
+        let storage_c = make_storage("c").await;
+        let shared = Blob::new_data(vec![1; 100]);
+        storage_a.write_blob(&shared).await?;
+        storage_b.write_blob(&shared).await?;
+        let orphan = Blob::new_data(vec![2; 100]);
+        object_store.put_object(orphan.id(), orphan.bytes()).await?;
+        // An upload of `c` that was interrupted before its blob was stored.
+        let interrupted = Blob::new_data(vec![3; 100]);
+        object_store.add_reference(interrupted.id(), "c").await?;
+        object_store
+            .put_object(interrupted.id(), interrupted.bytes())
+            .await?;
+        let mut holders = object_store.reference_holders(shared.id()).await?;
+        holders.sort();
+        assert_eq!(holders, ["a", "b"]);
+
+        let report = storage_c.collect_offloaded_blobs(&BTreeSet::new()).await?;
+        assert!(report.deleted.is_empty());
+        assert_eq!(
+            report.candidates,
+            BTreeSet::from([orphan.id(), interrupted.id()])
+        );
+        let report = storage_c
+            .collect_offloaded_blobs(&report.candidates)
+            .await?;
+        assert_eq!(
+            report.deleted.into_iter().collect::<BTreeSet<_>>(),
+            BTreeSet::from([orphan.id(), interrupted.id()])
+        );
+        assert_eq!(object_store.list_objects().await?, [shared.id()]);
+        assert_eq!(
+            storage_a.read_blob(shared.id()).await?,
+            Some(shared.clone())
+        );
+        assert_eq!(storage_b.read_blob(shared.id()).await?, Some(shared));
+        Ok(())
+    }
+
+    // All the undo batches that expired are removed, even if heights were skipped or the
+    // retention was larger before.
+    #[tokio::test]
+    async fn test_execution_state_undo_retention() -> anyhow::Result<()> {
+        let storage = DbStorage::<MemoryDatabase, _>::make_test_storage(None).await;
+        let chain_id = ChainId(CryptoHash::test_hash("chain"));
+        let other_chain_id = ChainId(CryptoHash::test_hash("other chain"));
+        let undo = Batch::new();
+        for height in [0, 1, 2, 3] {
+            storage
+                .write_execution_state_undo(chain_id, BlockHeight(height), &undo, 10)
+                .await?;
+        }
+        storage
+            .write_execution_state_undo(other_chain_id, BlockHeight(0), &undo, 10)
+            .await?;
+        storage
+            .write_execution_state_undo(chain_id, BlockHeight(5), &undo, 3)
+            .await?;
+        for (height, is_retained) in [(0, false), (1, false), (2, false), (3, true), (5, true)] {
+            let retained = storage
+                .read_execution_state_undo(chain_id, BlockHeight(height))
+                .await?;
+            assert_eq!(retained.is_some(), is_retained, "height {height}");
+        }
+        assert!(storage
+            .read_execution_state_undo(other_chain_id, BlockHeight(0))
+            .await?
+            .is_some());
+        Ok(())
+    }
+
+    // Applications created from the same module share the modules compiled for the first of
+    // them, on all chains.
+    #[tokio::test]
+    async fn test_blob_cache_shares_compiled_modules() -> anyhow::Result<()> {
+        let storage = DbStorage::<MemoryDatabase, _>::make_test_storage(None)
+            .await
+            .with_blob_cache(BlobCacheConfig::default());
+        let (description, _, _) = create_dummy_user_application_description(0);
+        let other_description = ApplicationDescription {
+            application_index: 1,
+            ..description.clone()
+        };
+        // The bytecode blobs are not in storage: the modules can only come from the cache.
+        let cache = storage.blob_cache().unwrap();
+        cache.insert_contract(
+            description.contract_bytecode_blob_id(),
+            MockApplication::default().into(),
+        );
+        cache.insert_service(
+            description.service_bytecode_blob_id(),
+            MockApplication::default().into(),
+        );
+        for (index, description) in [(0, &description), (1, &other_description)] {
+            let context = storage.runtime_context(dummy_chain_description(index).id());
+            context
+                .get_user_contract(description, &BTreeMap::new())
+                .await?;
+            context
+                .get_user_service(description, &BTreeMap::new())
+                .await?;
+        }
+
+        let storage = DbStorage::<MemoryDatabase, _>::make_test_storage(None).await;
+        let context = storage.runtime_context(dummy_chain_description(0).id());
+        assert!(context
+            .get_user_contract(&description, &BTreeMap::new())
+            .await
+            .is_err());
+        Ok(())
+    }
 }
 
 /// An implementation of [`DualStoreRootKeyAssignment`] that stores the
@@ -549,32 +1009,74 @@ where
         &self,
         chain_id: ChainId,
     ) -> Result<ChainStateView<Self::Context>, ViewError> {
-        #[cfg(with_metrics)]
-        let _metric = metrics::LOAD_CHAIN_LATENCY.measure_latency();
-        let runtime_context = ChainRuntimeContext {
-            storage: self.clone(),
-            chain_id,
-            execution_runtime_config: self.execution_runtime_config,
-            user_contracts: self.user_contracts.clone(),
-            user_services: self.user_services.clone(),
+        // The chain states used to execute blocks are never limited by the scan budget:
+        // validators must all reach the same outcome.
+        self.load_chain_within(chain_id, None).await
+    }
+
+    async fn load_chain_for_read(
+        &self,
+        chain_id: ChainId,
+    ) -> Result<ChainStateView<Self::Context>, ViewError> {
+        let Some(replica) = self
+            .read_replica
+            .as_ref()
+            .filter(|replica| replica.config.read_chain_states)
+        else {
+            return self.load_chain_within(chain_id, self.scan_budget).await;
         };
+        // The fence is read first: if the replica then replicates further, it is only ahead.
+        let fence = self.read_replica_fence(chain_id).await?;
+        if fence.is_some() {
+            let root_key = bcs::to_bytes(&BaseKey::ChainState(chain_id))?;
+            let store = replica.database.open_shared(&root_key)?;
+            // The journal is only ever resolved on the primary: the replica is not written to.
+            let mut context =
+                ViewContext::new_unsafe(store, Vec::new(), self.runtime_context(chain_id));
+            if let Some(scan_budget) = self.scan_budget {
+                context = context.with_scan_budget(scan_budget);
+            }
+            let chain = ChainStateView::load(context).await?;
+            if read_replica::is_up_to_date(fence, chain.tip_state.get().next_block_height) {
+                return Ok(chain);
+            }
+        }
+        #[cfg(with_metrics)]
+        metrics::READ_REPLICA_FENCED_COUNTER
+            .with_label_values(&[])
+            .inc();
+        self.load_chain_within(chain_id, self.scan_budget).await
+    }
+
+    async fn load_chain_shared(
+        &self,
+        chain_id: ChainId,
+    ) -> Result<ChainStateView<Self::Context>, ViewError> {
         let root_key = bcs::to_bytes(&BaseKey::ChainState(chain_id))?;
-        let store = self.database.open_exclusive(&root_key)?;
-        let context = ViewContext::create_root_context(store, runtime_context).await?;
+        let store = self.database.open_shared(&root_key)?;
+        let context = ViewContext::new_unsafe(store, Vec::new(), self.runtime_context(chain_id));
         ChainStateView::load(context).await
     }
 
     async fn contains_blob(&self, blob_id: BlobId) -> Result<bool, ViewError> {
-        let store = self.database.open_shared(&[])?;
+        let store = self
+            .database
+            .open_shared_for(&[], RequestClass::BlobPrefetch)?;
         let blob_key = bcs::to_bytes(&BaseKey::Blob(blob_id))?;
-        let test = store.contains_key(&blob_key).await?;
+        let mut test = store.contains_key(&blob_key).await?;
+        if !test && self.blob_offload.is_some() {
+            let offloaded_blob_key = bcs::to_bytes(&BaseKey::OffloadedBlob(blob_id))?;
+            test = store.contains_key(&offloaded_blob_key).await?;
+        }
         #[cfg(with_metrics)]
         metrics::CONTAINS_BLOB_COUNTER.with_label_values(&[]).inc();
         Ok(test)
     }
 
     async fn missing_blobs(&self, blob_ids: &[BlobId]) -> Result<Vec<BlobId>, ViewError> {
-        let store = self.database.open_shared(&[])?;
+        let store = self
+            .database
+            .open_shared_for(&[], RequestClass::BlobPrefetch)?;
         let mut keys = Vec::new();
         for blob_id in blob_ids {
             let key = bcs::to_bytes(&BaseKey::Blob(*blob_id))?;
@@ -587,6 +1089,18 @@ where
                 missing_blobs.push(*blob_id);
             }
         }
+        if self.blob_offload.is_some() && !missing_blobs.is_empty() {
+            let keys = missing_blobs
+                .iter()
+                .map(|blob_id| bcs::to_bytes(&BaseKey::OffloadedBlob(*blob_id)))
+                .collect::<Result<Vec<_>, _>>()?;
+            let results = store.contains_keys(keys).await?;
+            missing_blobs = missing_blobs
+                .into_iter()
+                .zip(results)
+                .filter_map(|(blob_id, result)| (!result).then_some(blob_id))
+                .collect();
+        }
         #[cfg(with_metrics)]
         metrics::CONTAINS_BLOBS_COUNTER.with_label_values(&[]).inc();
         Ok(missing_blobs)
@@ -607,8 +1121,15 @@ where
         &self,
         hash: CryptoHash,
     ) -> Result<Option<ConfirmedBlock>, ViewError> {
-        let store = self.database.open_shared(&[])?;
         let block_key = bcs::to_bytes(&BaseKey::ConfirmedBlock(hash))?;
+        if let Some(store) = self.replica_store()? {
+            if let Some(value) = store.read_value(&block_key).await? {
+                return Ok(Some(value));
+            }
+        }
+        let store = self
+            .database
+            .open_shared_for(&[], RequestClass::CertificateReads)?;
         let value = store.read_value(&block_key).await?;
         #[cfg(with_metrics)]
         metrics::READ_CONFIRMED_BLOCK_COUNTER
@@ -618,36 +1139,44 @@ where
     }
 
     async fn read_blob(&self, blob_id: BlobId) -> Result<Option<Blob>, ViewError> {
-        let store = self.database.open_shared(&[])?;
-        let blob_key = bcs::to_bytes(&BaseKey::Blob(blob_id))?;
-        let maybe_blob_bytes = store.read_value_bytes(&blob_key).await?;
-        #[cfg(with_metrics)]
-        metrics::READ_BLOB_COUNTER.with_label_values(&[]).inc();
-        Ok(maybe_blob_bytes.map(|blob_bytes| Blob::new_with_id_unchecked(blob_id, blob_bytes)))
+        let Some(cache) = &self.blob_cache else {
+            return self.read_blob_from_database(blob_id).await;
+        };
+        if let Some(blob) = cache.blob(&blob_id) {
+            return Ok(Some(blob));
+        }
+        let maybe_blob = self.read_blob_from_database(blob_id).await?;
+        if let Some(blob) = &maybe_blob {
+            cache.insert_blob(blob);
+        }
+        Ok(maybe_blob)
     }
 
     async fn read_blobs(&self, blob_ids: &[BlobId]) -> Result<Vec<Option<Blob>>, ViewError> {
-        if blob_ids.is_empty() {
-            return Ok(Vec::new());
-        }
-        let blob_keys = blob_ids
+        let Some(cache) = &self.blob_cache else {
+            return self.read_blobs_from_database(blob_ids).await;
+        };
+        let mut blobs = blob_ids
             .iter()
-            .map(|blob_id| bcs::to_bytes(&BaseKey::Blob(*blob_id)))
-            .collect::<Result<Vec<_>, _>>()?;
-        let store = self.database.open_shared(&[])?;
-        let maybe_blob_bytes = store.read_multi_values_bytes(blob_keys).await?;
-        #[cfg(with_metrics)]
-        metrics::READ_BLOB_COUNTER
-            .with_label_values(&[])
-            .inc_by(blob_ids.len() as u64);
-
-        Ok(blob_ids
+            .map(|blob_id| cache.blob(blob_id))
+            .collect::<Vec<_>>();
+        let missing_indices = blobs
             .iter()
-            .zip(maybe_blob_bytes)
-            .map(|(blob_id, maybe_blob_bytes)| {
-                maybe_blob_bytes.map(|blob_bytes| Blob::new_with_id_unchecked(*blob_id, blob_bytes))
-            })
-            .collect())
+            .enumerate()
+            .filter_map(|(index, blob)| blob.is_none().then_some(index))
+            .collect::<Vec<_>>();
+        let missing_blob_ids = missing_indices
+            .iter()
+            .map(|index| blob_ids[*index])
+            .collect::<Vec<_>>();
+        let missing_blobs = self.read_blobs_from_database(&missing_blob_ids).await?;
+        for (index, maybe_blob) in missing_indices.into_iter().zip(missing_blobs) {
+            if let Some(blob) = &maybe_blob {
+                cache.insert_blob(blob);
+            }
+            blobs[index] = maybe_blob;
+        }
+        Ok(blobs)
     }
 
     async fn read_blob_state(&self, blob_id: BlobId) -> Result<Option<BlobState>, ViewError> {
@@ -685,8 +1214,9 @@ where
 
     async fn write_blob(&self, blob: &Blob) -> Result<(), ViewError> {
         let mut batch = Batch::new();
-        batch.add_blob(blob)?;
+        self.add_blobs_to_batch(&mut batch, [blob]).await?;
         self.write_batch(batch).await?;
+        self.cache_blobs([blob]);
         Ok(())
     }
 
@@ -737,12 +1267,14 @@ where
         let store = self.database.open_shared(&[])?;
         let blob_states = store.contains_keys(blob_state_keys).await?;
         let mut batch = Batch::new();
-        for (blob, has_state) in blobs.iter().zip(&blob_states) {
-            if *has_state {
-                batch.add_blob(blob)?;
-            }
-        }
+        let blobs_with_state = blobs
+            .iter()
+            .zip(&blob_states)
+            .filter_map(|(blob, has_state)| has_state.then_some(blob));
+        self.add_blobs_to_batch(&mut batch, blobs_with_state.clone())
+            .await?;
         self.write_batch(batch).await?;
+        self.cache_blobs(blobs_with_state);
         Ok(blob_states)
     }
 
@@ -751,10 +1283,10 @@ where
             return Ok(());
         }
         let mut batch = Batch::new();
-        for blob in blobs {
-            batch.add_blob(blob)?;
-        }
-        self.write_batch(batch).await
+        self.add_blobs_to_batch(&mut batch, blobs).await?;
+        self.write_batch(batch).await?;
+        self.cache_blobs(blobs);
+        Ok(())
     }
 
     async fn write_blobs_and_certificate(
@@ -763,11 +1295,49 @@ where
         certificate: &ConfirmedBlockCertificate,
     ) -> Result<(), ViewError> {
         let mut batch = Batch::new();
-        for blob in blobs {
-            batch.add_blob(blob)?;
-        }
+        self.add_blobs_to_batch(&mut batch, blobs).await?;
         batch.add_certificate(certificate)?;
-        self.write_batch(batch).await
+        self.write_batch(batch).await?;
+        self.cache_blobs(blobs);
+        let header = &certificate.block().header;
+        self.advance_read_replica_fence(header.chain_id, header.height)
+            .await
+    }
+
+    async fn write_certificate_header(
+        &self,
+        certificate: &ConfirmedBlockCertificate,
+    ) -> Result<(), ViewError> {
+        // The header is part of the full block, if that is stored already.
+        if self.contains_certificate(certificate.hash()).await? {
+            return Ok(());
+        }
+        let mut batch = Batch::new();
+        batch.add_certificate_header(certificate)?;
+        self.write_batch(batch).await?;
+        let header = &certificate.block().header;
+        self.advance_read_replica_fence(header.chain_id, header.height)
+            .await
+    }
+
+    async fn read_block_header(&self, hash: CryptoHash) -> Result<Option<BlockHeader>, ViewError> {
+        let store = self.database.open_shared(&[])?;
+        let header_key = bcs::to_bytes(&BaseKey::BlockHeader(hash))?;
+        if let Some(header) = store.read_value(&header_key).await? {
+            return Ok(Some(header));
+        }
+        let block = self.read_confirmed_block(hash).await?;
+        Ok(block.map(|block| block.into_block().header))
+    }
+
+    async fn read_block_hash(
+        &self,
+        chain_id: ChainId,
+        height: BlockHeight,
+    ) -> Result<Option<CryptoHash>, ViewError> {
+        let store = self.database.open_shared(&[])?;
+        let index_key = bcs::to_bytes(&BaseKey::BlockHash(chain_id, height))?;
+        Ok(store.read_value(&index_key).await?)
     }
 
     async fn contains_certificate(&self, hash: CryptoHash) -> Result<bool, ViewError> {
@@ -785,8 +1355,16 @@ where
         &self,
         hash: CryptoHash,
     ) -> Result<Option<ConfirmedBlockCertificate>, ViewError> {
-        let store = self.database.open_shared(&[])?;
         let keys = Self::get_keys_for_certificates(&[hash])?;
+        if let Some(store) = self.replica_store()? {
+            let values = store.read_multi_values_bytes(keys.clone()).await?;
+            if let Some(certificate) = Self::deserialize_certificate(&values, hash)? {
+                return Ok(Some(certificate));
+            }
+        }
+        let store = self
+            .database
+            .open_shared_for(&[], RequestClass::CertificateReads)?;
         let values = store.read_multi_values_bytes(keys).await;
         if values.is_ok() {
             #[cfg(with_metrics)]
@@ -807,7 +1385,19 @@ where
             return Ok(Vec::new());
         }
         let keys = Self::get_keys_for_certificates(&hashes)?;
-        let store = self.database.open_shared(&[])?;
+        if let Some(store) = self.replica_store()? {
+            let values = store.read_multi_values_bytes(keys.clone()).await?;
+            if values.iter().all(Option::is_some) {
+                return values
+                    .chunks_exact(2)
+                    .zip(hashes)
+                    .map(|(pair, hash)| Self::deserialize_certificate(pair, hash))
+                    .collect();
+            }
+        }
+        let store = self
+            .database
+            .open_shared_for(&[], RequestClass::CertificateReads)?;
         let values = store.read_multi_values_bytes(keys).await;
         if values.is_ok() {
             #[cfg(with_metrics)]
@@ -825,8 +1415,13 @@ where
     }
 
     async fn read_event(&self, event_id: EventId) -> Result<Option<Vec<u8>>, ViewError> {
-        let store = self.database.open_shared(&[])?;
         let event_key = bcs::to_bytes(&BaseKey::Event(event_id.clone()))?;
+        if let Some(store) = self.replica_store()? {
+            if let Some(event) = store.read_value_bytes(&event_key).await? {
+                return Ok(Some(event));
+            }
+        }
+        let store = self.database.open_shared(&[])?;
         let event = store.read_value_bytes(&event_key).await?;
         #[cfg(with_metrics)]
         metrics::READ_EVENT_COUNTER.with_label_values(&[]).inc();
@@ -904,10 +1499,231 @@ where
         Ok(())
     }
 
+    async fn read_signing_term(&self, shard_id: u32) -> Result<u64, ViewError> {
+        // Always read the primary database: a replica could be behind a promotion.
+        let store = self.database.open_shared(&[])?;
+        let key = bcs::to_bytes(&BaseKey::SigningTerm(shard_id))?;
+        Ok(store.read_value(&key).await?.unwrap_or_default())
+    }
+
+    async fn write_signing_term(&self, shard_id: u32, term: u64) -> Result<(), ViewError> {
+        let mut batch = Batch::new();
+        batch.put_key_value(bcs::to_bytes(&BaseKey::SigningTerm(shard_id))?, &term)?;
+        self.write_batch(batch).await?;
+        Ok(())
+    }
+
+    async fn read_epoch_committees(
+        &self,
+        epochs: &[Epoch],
+    ) -> Result<BTreeMap<Epoch, EpochCommittee>, ViewError> {
+        let store = self.database.open_shared(&[])?;
+        let keys = epochs
+            .iter()
+            .map(|epoch| bcs::to_bytes(&BaseKey::EpochCommittee(*epoch)))
+            .collect::<Result<Vec<_>, _>>()?;
+        let committees = store.read_multi_values::<EpochCommittee>(keys).await?;
+        Ok(epochs
+            .iter()
+            .zip(committees)
+            .filter_map(|(epoch, committee)| Some((*epoch, committee?)))
+            .collect())
+    }
+
+    async fn write_epoch_committee(
+        &self,
+        epoch: Epoch,
+        committee: &EpochCommittee,
+    ) -> Result<(), ViewError> {
+        let mut batch = Batch::new();
+        batch.put_key_value(bcs::to_bytes(&BaseKey::EpochCommittee(epoch))?, committee)?;
+        self.write_batch(batch).await?;
+        Ok(())
+    }
+
+    async fn read_task_checkpoint(&self, task_name: &str) -> Result<Option<Vec<u8>>, ViewError> {
+        let store = self.database.open_shared(&[])?;
+        let key = bcs::to_bytes(&BaseKey::TaskCheckpoint(task_name.to_owned()))?;
+        Ok(store.read_value_bytes(&key).await?)
+    }
+
+    async fn write_task_checkpoint(
+        &self,
+        task_name: &str,
+        checkpoint: Vec<u8>,
+    ) -> Result<(), ViewError> {
+        let mut batch = Batch::new();
+        let key = bcs::to_bytes(&BaseKey::TaskCheckpoint(task_name.to_owned()))?;
+        batch.put_key_value_bytes(key, checkpoint);
+        self.write_batch(batch).await?;
+        Ok(())
+    }
+
+    async fn read_execution_state_delta(
+        &self,
+        state_hash: CryptoHash,
+    ) -> Result<Option<ExecutionStateDelta>, ViewError> {
+        let store = self.database.open_shared(&[])?;
+        let key = bcs::to_bytes(&BaseKey::ExecutionStateDelta(state_hash))?;
+        let Some(delta) = store.read_value(&key).await? else {
+            return Ok(None);
+        };
+        // Mark the delta as the most recently used one.
+        let mut batch = linera_views::batch::Batch::new();
+        for (use_key, hash) in Self::read_execution_state_delta_uses(&store).await? {
+            if hash == state_hash {
+                batch.delete_key(use_key);
+            }
+        }
+        batch.put_key_value_bytes(self.execution_state_delta_use_key(state_hash)?, Vec::new());
+        store.write_batch(batch).await?;
+        Ok(Some(delta))
+    }
+
+    async fn write_execution_state_delta(
+        &self,
+        delta: &ExecutionStateDelta,
+        max_entries: usize,
+    ) -> Result<(), ViewError> {
+        let store = self.database.open_shared(&[])?;
+        let key = bcs::to_bytes(&BaseKey::ExecutionStateDelta(delta.state_hash))?;
+        let mut batch = linera_views::batch::Batch::new();
+        let mut others = Vec::new();
+        for (use_key, hash) in Self::read_execution_state_delta_uses(&store).await? {
+            if hash == delta.state_hash {
+                batch.delete_key(use_key);
+            } else {
+                others.push((use_key, hash));
+            }
+        }
+        // Evict the least recently used deltas.
+        let excess = (others.len() + 1).saturating_sub(max_entries);
+        for (use_key, hash) in others.into_iter().take(excess) {
+            batch.delete_key(use_key);
+            batch.delete_key(bcs::to_bytes(&BaseKey::ExecutionStateDelta(hash))?);
+        }
+        batch.put_key_value(key, delta)?;
+        batch.put_key_value_bytes(
+            self.execution_state_delta_use_key(delta.state_hash)?,
+            Vec::new(),
+        );
+        store.write_batch(batch).await?;
+        Ok(())
+    }
+
+    async fn remove_execution_state_delta(&self, state_hash: CryptoHash) -> Result<(), ViewError> {
+        let store = self.database.open_shared(&[])?;
+        let mut batch = linera_views::batch::Batch::new();
+        batch.delete_key(bcs::to_bytes(&BaseKey::ExecutionStateDelta(state_hash))?);
+        for (use_key, hash) in Self::read_execution_state_delta_uses(&store).await? {
+            if hash == state_hash {
+                batch.delete_key(use_key);
+            }
+        }
+        store.write_batch(batch).await?;
+        Ok(())
+    }
+
+    async fn read_execution_state_undo(
+        &self,
+        chain_id: ChainId,
+        height: BlockHeight,
+    ) -> Result<Option<linera_views::batch::Batch>, ViewError> {
+        let store = self.database.open_shared(&[])?;
+        let key = bcs::to_bytes(&BaseKey::ExecutionStateUndo(chain_id, height))?;
+        Ok(store.read_value(&key).await?)
+    }
+
+    async fn write_execution_state_undo(
+        &self,
+        chain_id: ChainId,
+        height: BlockHeight,
+        undo: &linera_views::batch::Batch,
+        retention: u64,
+    ) -> Result<(), ViewError> {
+        let store = self.database.open_shared(&[])?;
+        let mut batch = linera_views::batch::Batch::new();
+        // Heights may be skipped, and the retention may have been larger before, so all the
+        // expired batches are removed, not just the one that expires now.
+        if let Some(expired_height) = height.0.checked_sub(retention) {
+            let key = bcs::to_bytes(&BaseKey::ExecutionStateUndo(chain_id, BlockHeight::ZERO))?;
+            let prefix = &key[..key.len() - std::mem::size_of::<u64>()];
+            for suffix in store.find_keys_by_prefix(prefix).await? {
+                let retained_height: u64 = bcs::from_bytes(&suffix)?;
+                if retained_height <= expired_height {
+                    batch.delete_key([prefix, &suffix].concat());
+                }
+            }
+        }
+        let key = bcs::to_bytes(&BaseKey::ExecutionStateUndo(chain_id, height))?;
+        batch.put_key_value(key, undo)?;
+        store.write_batch(batch).await?;
+        self.advance_read_replica_fence(chain_id, height).await
+    }
+
+    async fn namespace_stats(&self) -> Result<NamespaceStats, ViewError> {
+        Ok(self.database.namespace_stats().await?)
+    }
+
+    async fn find_unreferenced_offloaded_blobs(&self) -> Result<Vec<BlobId>, ViewError> {
+        let Some(blob_offload) = &self.blob_offload else {
+            return Ok(Vec::new());
+        };
+        let object_ids = blob_offload.store.list_objects().await?;
+        let mut unreferenced = Vec::new();
+        for (blob_id, is_stored) in self.offloaded_blobs_are_stored(&object_ids).await? {
+            let holders = blob_offload.store.reference_holders(blob_id).await?;
+            let is_held = holders.contains(&blob_offload.holder);
+            if is_stored {
+                // Objects stored before references were recorded are referenced now.
+                if !is_held {
+                    let holder = &blob_offload.holder;
+                    blob_offload.store.add_reference(blob_id, holder).await?;
+                }
+            } else if holders.len() == usize::from(is_held) {
+                unreferenced.push(blob_id);
+            }
+        }
+        Ok(unreferenced)
+    }
+
+    async fn delete_unreferenced_offloaded_blobs(
+        &self,
+        blob_ids: &[BlobId],
+    ) -> Result<Vec<BlobId>, ViewError> {
+        let Some(blob_offload) = &self.blob_offload else {
+            return Ok(Vec::new());
+        };
+        let mut deleted = Vec::new();
+        for (blob_id, is_stored) in self.offloaded_blobs_are_stored(blob_ids).await? {
+            if is_stored {
+                continue;
+            }
+            let holder = &blob_offload.holder;
+            blob_offload.store.remove_reference(blob_id, holder).await?;
+            // The object is kept as long as another storage sharing the object store
+            // references it.
+            if blob_offload
+                .store
+                .reference_holders(blob_id)
+                .await?
+                .is_empty()
+            {
+                blob_offload.store.delete_object(blob_id).await?;
+                deleted.push(blob_id);
+            }
+        }
+        Ok(deleted)
+    }
+
     fn wasm_runtime(&self) -> Option<WasmRuntime> {
         self.wasm_runtime
     }
 
+    fn blob_cache(&self) -> Option<&BlobCache> {
+        self.blob_cache.as_deref()
+    }
+
     async fn block_exporter_context(
         &self,
         block_exporter_id: u32,
@@ -922,9 +1738,239 @@ impl<Database, C> DbStorage<Database, C>
 where
     Database: KeyValueDatabase + Clone + Send + Sync + 'static,
     Database::Store: KeyValueStore + Clone + Send + Sync + 'static,
-    C: Clock,
+    C: Clock + Clone,
     Database::Error: Send + Sync,
 {
+    /// Returns the given offloaded blobs, each with whether this storage holds it.
+    async fn offloaded_blobs_are_stored(
+        &self,
+        blob_ids: &[BlobId],
+    ) -> Result<Vec<(BlobId, bool)>, ViewError> {
+        let keys = blob_ids
+            .iter()
+            .map(|blob_id| bcs::to_bytes(&BaseKey::OffloadedBlob(*blob_id)))
+            .collect::<Result<Vec<_>, _>>()?;
+        let store = self.database.open_shared(&[])?;
+        let is_stored = store.contains_keys(keys).await?;
+        Ok(blob_ids.iter().copied().zip(is_stored).collect())
+    }
+
+    /// Returns the keys recording the last use of each persisted execution state delta, with
+    /// the hash of its state, from the least to the most recently used.
+    async fn read_execution_state_delta_uses(
+        store: &Database::Store,
+    ) -> Result<Vec<(Vec<u8>, CryptoHash)>, ViewError> {
+        let suffixes = store
+            .find_keys_by_prefix(&[INDEX_EXECUTION_STATE_DELTA_USE])
+            .await?;
+        let mut uses = Vec::with_capacity(suffixes.len());
+        for suffix in suffixes {
+            let hash = bcs::from_bytes(&suffix[8..])?;
+            let mut key = vec![INDEX_EXECUTION_STATE_DELTA_USE];
+            key.extend(suffix);
+            uses.push((key, hash));
+        }
+        Ok(uses)
+    }
+
+    /// Returns the key recording that the delta leading to `state_hash` is used now.
+    fn execution_state_delta_use_key(&self, state_hash: CryptoHash) -> Result<Vec<u8>, ViewError> {
+        let micros = self.clock.current_time().micros();
+        let base_key = BaseKey::ExecutionStateDeltaUse(micros.to_be_bytes(), state_hash);
+        Ok(bcs::to_bytes(&base_key)?)
+    }
+
+    fn runtime_context(&self, chain_id: ChainId) -> ChainRuntimeContext<Self> {
+        ChainRuntimeContext {
+            storage: self.clone(),
+            chain_id,
+            execution_runtime_config: self.execution_runtime_config.clone(),
+            user_contracts: self.user_contracts.clone(),
+            user_services: self.user_services.clone(),
+        }
+    }
+
+    /// Loads the view of a chain state from the primary storage, limiting its prefix scans to
+    /// the given budget, if any.
+    async fn load_chain_within(
+        &self,
+        chain_id: ChainId,
+        scan_budget: Option<ScanBudget>,
+    ) -> Result<ChainStateView<ViewContext<ChainRuntimeContext<Self>, Database::Store>>, ViewError>
+    where
+        C: Send + Sync + 'static,
+    {
+        #[cfg(with_metrics)]
+        let _metric = metrics::LOAD_CHAIN_LATENCY.measure_latency();
+        let root_key = bcs::to_bytes(&BaseKey::ChainState(chain_id))?;
+        let store = self.database.open_exclusive(&root_key)?;
+        let mut context =
+            ViewContext::create_root_context(store, self.runtime_context(chain_id)).await?;
+        if let Some(scan_budget) = scan_budget {
+            context = context.with_scan_budget(scan_budget);
+        }
+        if let Some(change_log) = &self.change_log {
+            context = context.with_change_log(change_log.clone().with_root_key(root_key));
+        }
+        ChainStateView::load(context).await
+    }
+
+    /// Records in the primary database that it has the block at the given height of a chain,
+    /// so that no storage instance reads an older chain state from a replica.
+    ///
+    /// The fence is written after the block, and is never lowered. This happens whether or
+    /// not this instance reads from a replica, since the ones that do may run elsewhere.
+    async fn advance_read_replica_fence(
+        &self,
+        chain_id: ChainId,
+        height: BlockHeight,
+    ) -> Result<(), ViewError> {
+        let Ok(next_block_height) = height.try_add_one() else {
+            return Ok(());
+        };
+        if self
+            .read_replica_fence(chain_id)
+            .await?
+            .is_some_and(|fence| fence >= next_block_height)
+        {
+            return Ok(());
+        }
+        let store = self.database.open_shared(&[])?;
+        let mut batch = linera_views::batch::Batch::new();
+        let key = bcs::to_bytes(&BaseKey::ReadReplicaFence(chain_id))?;
+        batch.put_key_value(key, &next_block_height)?;
+        store.write_batch(batch).await?;
+        Ok(())
+    }
+
+    /// Returns the next block height of a chain according to the fence in the primary
+    /// database, or `None` if no block of that chain was written.
+    async fn read_replica_fence(
+        &self,
+        chain_id: ChainId,
+    ) -> Result<Option<BlockHeight>, ViewError> {
+        let store = self.database.open_shared(&[])?;
+        let key = bcs::to_bytes(&BaseKey::ReadReplicaFence(chain_id))?;
+        Ok(store.read_value(&key).await?)
+    }
+
+    /// Returns the store of the replica, if immutable data is to be read from it.
+    fn replica_store(&self) -> Result<Option<Database::Store>, ViewError> {
+        let Some(replica) = &self.read_replica else {
+            return Ok(None);
+        };
+        if !replica.config.read_immutable_data {
+            return Ok(None);
+        }
+        Ok(Some(replica.database.open_shared(&[])?))
+    }
+
+    /// Adds blobs that were just written to the blob cache, if any, since they are likely to
+    /// be read soon, e.g. the bytecode of a newly published module.
+    fn cache_blobs<'a>(&self, blobs: impl IntoIterator<Item = &'a Blob>) {
+        if let Some(cache) = &self.blob_cache {
+            for blob in blobs {
+                cache.insert_blob(blob);
+            }
+        }
+    }
+
+    /// Reads a blob from the database, bypassing the blob cache.
+    async fn read_blob_from_database(&self, blob_id: BlobId) -> Result<Option<Blob>, ViewError> {
+        let blob_key = bcs::to_bytes(&BaseKey::Blob(blob_id))?;
+        if let Some(store) = self.replica_store()? {
+            if let Some(blob_bytes) = store.read_value_bytes(&blob_key).await? {
+                return Ok(Some(Blob::new_with_id_unchecked(blob_id, blob_bytes)));
+            }
+        }
+        let store = self.database.open_shared(&[])?;
+        let maybe_blob_bytes = store.read_value_bytes(&blob_key).await?;
+        #[cfg(with_metrics)]
+        metrics::READ_BLOB_COUNTER.with_label_values(&[]).inc();
+        if let Some(blob_bytes) = maybe_blob_bytes {
+            return Ok(Some(Blob::new_with_id_unchecked(blob_id, blob_bytes)));
+        }
+        let Some(blob_offload) = &self.blob_offload else {
+            return Ok(None);
+        };
+        let offloaded_blob_key = bcs::to_bytes(&BaseKey::OffloadedBlob(blob_id))?;
+        if !store.contains_key(&offloaded_blob_key).await? {
+            return Ok(None);
+        }
+        Ok(Some(blob_offload.fetch(blob_id).await?))
+    }
+
+    /// Reads several blobs from the database, bypassing the blob cache.
+    async fn read_blobs_from_database(
+        &self,
+        blob_ids: &[BlobId],
+    ) -> Result<Vec<Option<Blob>>, ViewError> {
+        if blob_ids.is_empty() {
+            return Ok(Vec::new());
+        }
+        let blob_keys = blob_ids
+            .iter()
+            .map(|blob_id| bcs::to_bytes(&BaseKey::Blob(*blob_id)))
+            .collect::<Result<Vec<_>, _>>()?;
+        if let Some(store) = self.replica_store()? {
+            let maybe_blob_bytes = store.read_multi_values_bytes(blob_keys.clone()).await?;
+            if maybe_blob_bytes.iter().all(Option::is_some) {
+                return Ok(blob_ids
+                    .iter()
+                    .zip(maybe_blob_bytes.into_iter().flatten())
+                    .map(|(blob_id, blob_bytes)| {
+                        Some(Blob::new_with_id_unchecked(*blob_id, blob_bytes))
+                    })
+                    .collect());
+            }
+        }
+        let store = self.database.open_shared(&[])?;
+        let maybe_blob_bytes = store.read_multi_values_bytes(blob_keys).await?;
+        #[cfg(with_metrics)]
+        metrics::READ_BLOB_COUNTER
+            .with_label_values(&[])
+            .inc_by(blob_ids.len() as u64);
+
+        let mut blobs = blob_ids
+            .iter()
+            .zip(maybe_blob_bytes)
+            .map(|(blob_id, maybe_blob_bytes)| {
+                maybe_blob_bytes.map(|blob_bytes| Blob::new_with_id_unchecked(*blob_id, blob_bytes))
+            })
+            .collect::<Vec<_>>();
+        let Some(blob_offload) = &self.blob_offload else {
+            return Ok(blobs);
+        };
+        let missing_indices = blobs
+            .iter()
+            .enumerate()
+            .filter_map(|(index, blob)| blob.is_none().then_some(index))
+            .collect::<Vec<_>>();
+        if missing_indices.is_empty() {
+            return Ok(blobs);
+        }
+        let offloaded_blob_keys = missing_indices
+            .iter()
+            .map(|index| bcs::to_bytes(&BaseKey::OffloadedBlob(blob_ids[*index])))
+            .collect::<Result<Vec<_>, _>>()?;
+        let is_offloaded = store.contains_keys(offloaded_blob_keys).await?;
+        let offloaded_indices = missing_indices
+            .into_iter()
+            .zip(is_offloaded)
+            .filter_map(|(index, is_offloaded)| is_offloaded.then_some(index))
+            .collect::<Vec<_>>();
+        let offloaded_blobs = futures::future::try_join_all(
+            offloaded_indices
+                .iter()
+                .map(|index| blob_offload.fetch(blob_ids[*index])),
+        )
+        .await?;
+        for (index, blob) in offloaded_indices.into_iter().zip(offloaded_blobs) {
+            blobs[index] = Some(blob);
+        }
+        Ok(blobs)
+    }
+
     fn get_keys_for_certificates(hashes: &[CryptoHash]) -> Result<Vec<Vec<u8>>, ViewError> {
         Ok(hashes
             .iter()
@@ -955,6 +2001,39 @@ where
         Ok(Some(certificate))
     }
 
+    /// Adds the given blobs to the batch. If blob offloading is enabled, the contents of large
+    /// blobs are written to the object store first, so that a committed batch never refers to
+    /// a missing object. The reference of this storage is recorded before the content, so
+    /// that the garbage collection of another storage sharing the object store keeps it.
+    async fn add_blobs_to_batch(
+        &self,
+        batch: &mut Batch,
+        blobs: impl IntoIterator<Item = &Blob>,
+    ) -> Result<(), ViewError> {
+        let Some(blob_offload) = &self.blob_offload else {
+            for blob in blobs {
+                batch.add_blob(blob)?;
+            }
+            return Ok(());
+        };
+        let mut offloaded_blobs = Vec::new();
+        for blob in blobs {
+            if blob_offload.should_offload(blob) {
+                batch.add_offloaded_blob(blob)?;
+                offloaded_blobs.push(blob);
+            } else {
+                batch.add_blob(blob)?;
+            }
+        }
+        futures::future::try_join_all(offloaded_blobs.into_iter().map(|blob| async move {
+            let store = &blob_offload.store;
+            store.add_reference(blob.id(), &blob_offload.holder).await?;
+            store.put_object(blob.id(), blob.bytes()).await
+        }))
+        .await?;
+        Ok(())
+    }
+
     async fn write_entry(
         store: &Database::Store,
         key: Vec<u8>,
@@ -989,8 +2068,69 @@ impl<Database, C> DbStorage<Database, C> {
             user_contracts: Arc::new(DashMap::new()),
             user_services: Arc::new(DashMap::new()),
             execution_runtime_config: ExecutionRuntimeConfig::default(),
+            blob_offload: None,
+            read_replica: None,
+            scan_budget: None,
+            blob_cache: None,
+            change_log: None,
         }
     }
+
+    /// Stores the contents of blobs of at least `config.min_blob_size` bytes in the given
+    /// object store instead of the key-value store. The `holder` name identifies this storage
+    /// among the ones sharing the object store, e.g. it is the namespace of the storage.
+    pub fn with_blob_offload(
+        mut self,
+        object_store: Arc<dyn BlobObjectStore>,
+        holder: impl Into<String>,
+        config: BlobOffloadConfig,
+    ) -> Self {
+        self.blob_offload = Some(BlobOffload {
+            store: object_store,
+            holder: holder.into(),
+            config,
+        });
+        self
+    }
+
+    /// Serves reads from the given replica of the database, as allowed by `config`. Writes
+    /// still go to the primary database only.
+    pub fn with_read_replica(mut self, replica: Database, config: ReadReplicaConfig) -> Self {
+        self.read_replica = Some(ReadReplica::new(replica, config));
+        self
+    }
+
+    /// Rejects the prefix scans of the chain states loaded for reading only that exceed the
+    /// given budget, instead of loading an unbounded number of entries in memory. The chain
+    /// states loaded to execute blocks are never limited.
+    pub fn with_scan_budget(mut self, scan_budget: ScanBudget) -> Self {
+        self.scan_budget = Some(scan_budget);
+        self
+    }
+
+    /// Keeps the blobs that are read or written, and the application modules compiled from
+    /// them, in a cache shared by all the clones of this storage.
+    pub fn with_blob_cache(mut self, config: BlobCacheConfig) -> Self {
+        self.blob_cache = Some(Arc::new(BlobCache::new(config)));
+        self
+    }
+
+    /// Emits the changes of the chain states saved through this storage to the sinks of
+    /// the given change log. The root key of each entry is the one of the chain state, i.e.
+    /// the BCS serialization of the chain ID, preceded by the tag of chain states.
+    pub fn with_change_log(mut self, change_log: ChangeLog) -> Self {
+        self.change_log = Some(change_log);
+        self
+    }
+
+    /// Executes the applications of all the chains with the given runtime configuration.
+    pub fn with_execution_runtime_config(
+        mut self,
+        execution_runtime_config: ExecutionRuntimeConfig,
+    ) -> Self {
+        self.execution_runtime_config = execution_runtime_config;
+        self
+    }
 }
 
 impl<Database> DbStorage<Database, WallClock>
@@ -1024,16 +2164,76 @@ where
     ) -> Result<Vec<BlobId>, ViewError> {
         let database = Database::maybe_create_and_connect(config, namespace).await?;
         let store = database.open_shared(&[])?;
-        let prefix = &[INDEX_BLOB_ID];
-        let keys = store.find_keys_by_prefix(prefix).await?;
         let mut blob_ids = Vec::new();
-        for key in keys {
-            let key_red = &key[..BLOB_ID_LENGTH];
-            let blob_id = bcs::from_bytes(key_red)?;
-            blob_ids.push(blob_id);
+        for prefix in [INDEX_BLOB_ID, INDEX_OFFLOADED_BLOB_ID] {
+            let keys = store.find_keys_by_prefix(&[prefix]).await?;
+            for key in keys {
+                let key_red = &key[..BLOB_ID_LENGTH];
+                let blob_id = bcs::from_bytes(key_red)?;
+                blob_ids.push(blob_id);
+            }
         }
         Ok(blob_ids)
     }
+
+    /// Reclaims the space of duplicated certificate data, and indexes the blocks written
+    /// before they were indexed by chain and height.
+    ///
+    /// Certificates are stored once under their hash. Only their block header is duplicated,
+    /// if a chain was first synchronized partially and later fully.
+    pub async fn deduplicate_certificates(
+        config: &Database::Config,
+        namespace: &str,
+    ) -> Result<CertificateDeduplicationReport, ViewError> {
+        let database = Database::connect(config, namespace).await?;
+        let store = database.open_shared(&[])?;
+        Self::deduplicate_certificates_in_store(&store, DEDUPLICATION_BATCH_OPERATIONS).await
+    }
+
+    /// Deduplicates the certificates of the given store, writing the changes in batches of at
+    /// most `max_batch_operations` operations.
+    async fn deduplicate_certificates_in_store(
+        store: &Database::Store,
+        max_batch_operations: usize,
+    ) -> Result<CertificateDeduplicationReport, ViewError> {
+        let mut report = CertificateDeduplicationReport::default();
+        let mut batch = linera_views::batch::Batch::new();
+        for key in store.find_keys_by_prefix(&[INDEX_BLOCK_HEADER]).await? {
+            let hash = bcs::from_bytes::<CryptoHash>(&key)?;
+            let block_key = bcs::to_bytes(&BaseKey::ConfirmedBlock(hash))?;
+            if store.contains_key(&block_key).await? {
+                batch.delete_key(bcs::to_bytes(&BaseKey::BlockHeader(hash))?);
+                report.removed_headers += 1;
+                if batch.num_operations() >= max_batch_operations {
+                    store.write_batch(std::mem::take(&mut batch)).await?;
+                }
+            }
+        }
+        for key in store.find_keys_by_prefix(&[INDEX_CERTIFICATE]).await? {
+            let hash = bcs::from_bytes::<CryptoHash>(&key)?;
+            let block_key = bcs::to_bytes(&BaseKey::ConfirmedBlock(hash))?;
+            let header_key = bcs::to_bytes(&BaseKey::BlockHeader(hash))?;
+            let header = match store.read_value::<ConfirmedBlock>(&block_key).await? {
+                Some(block) => block.into_block().header,
+                None => match store.read_value::<BlockHeader>(&header_key).await? {
+                    Some(header) => header,
+                    None => continue,
+                },
+            };
+            let index_key = bcs::to_bytes(&BaseKey::BlockHash(header.chain_id, header.height))?;
+            if !store.contains_key(&index_key).await? {
+                batch.put_key_value(index_key, &hash)?;
+                report.indexed_blocks += 1;
+                if batch.num_operations() >= max_batch_operations {
+                    store.write_batch(std::mem::take(&mut batch)).await?;
+                }
+            }
+        }
+        if !batch.is_empty() {
+            store.write_batch(batch).await?;
+        }
+        Ok(report)
+    }
 }
 
 impl<Database> DbStorage<Database, WallClock>
@@ -0,0 +1,99 @@
+// Copyright (c) Zefchain Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! The changes made by a block to the execution state of a chain, persisted when the block
+//! is validated so that it doesn't need to be executed again once confirmed, even by a
+//! restarted worker.
+
+use linera_base::crypto::{BcsHashable, CryptoHash};
+use linera_views::{batch::Batch, ViewError};
+use serde::{Deserialize, Serialize};
+
+/// The writes turning an execution state into the one resulting from a block.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ExecutionStateDelta {
+    /// The hash of the execution state that the writes apply to.
+    pub base_state_hash: CryptoHash,
+    /// The hash of the execution state after the writes.
+    pub state_hash: CryptoHash,
+    /// The writes, relative to the context of the execution state.
+    pub batch: Batch,
+    /// A checksum of the other fields, to detect corrupted entries.
+    checksum: CryptoHash,
+}
+
+/// The contents of an [`ExecutionStateDelta`] covered by its checksum.
+#[derive(Serialize, Deserialize)]
+struct DeltaContents<'a> {
+    base_state_hash: CryptoHash,
+    state_hash: CryptoHash,
+    batch: &'a [u8],
+}
+
+impl<'de> BcsHashable<'de> for DeltaContents<'de> {}
+
+impl ExecutionStateDelta {
+    /// Creates the delta from `base_state_hash` to `state_hash` made of the writes in `batch`.
+    pub fn new(
+        base_state_hash: CryptoHash,
+        state_hash: CryptoHash,
+        batch: Batch,
+    ) -> Result<Self, ViewError> {
+        let checksum = Self::checksum(base_state_hash, state_hash, &batch)?;
+        Ok(ExecutionStateDelta {
+            base_state_hash,
+            state_hash,
+            batch,
+            checksum,
+        })
+    }
+
+    /// Returns whether the checksum of the delta matches its contents.
+    pub fn is_intact(&self) -> bool {
+        Self::checksum(self.base_state_hash, self.state_hash, &self.batch)
+            .is_ok_and(|checksum| checksum == self.checksum)
+    }
+
+    fn checksum(
+        base_state_hash: CryptoHash,
+        state_hash: CryptoHash,
+        batch: &Batch,
+    ) -> Result<CryptoHash, ViewError> {
+        let batch = bcs::to_bytes(batch)?;
+        Ok(CryptoHash::new(&DeltaContents {
+            base_state_hash,
+            state_hash,
+            batch: &batch,
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use linera_base::crypto::CryptoHash;
+    use linera_views::batch::Batch;
+
+    use super::ExecutionStateDelta;
+
+    #[test]
+    fn test_execution_state_delta_checksum() {
+        let mut batch = Batch::new();
+        batch.put_key_value_bytes(vec![1, 2], vec![3]);
+        batch.delete_key_prefix(vec![4]);
+        let base_state_hash = CryptoHash::test_hash("base");
+        let state_hash = CryptoHash::test_hash("state");
+        let delta = ExecutionStateDelta::new(base_state_hash, state_hash, batch).unwrap();
+        assert!(delta.is_intact());
+
+        let bytes = bcs::to_bytes(&delta).unwrap();
+        let deserialized = bcs::from_bytes::<ExecutionStateDelta>(&bytes).unwrap();
+        assert!(deserialized.is_intact());
+
+        let mut corrupted = delta.clone();
+        corrupted.batch.put_key_value_bytes(vec![5], vec![6]);
+        assert!(!corrupted.is_intact());
+        let mut corrupted = delta;
+        corrupted.base_state_hash = state_hash;
+        assert!(!corrupted.is_intact());
+    }
+}
@@ -5,9 +5,19 @@
 
 #![deny(clippy::large_futures)]
 
+mod blob_cache;
+mod blob_offload;
 mod db_storage;
-
-use std::{collections::BTreeMap, ops::RangeInclusive, sync::Arc};
+mod execution_state_delta;
+#[cfg(with_object_store)]
+pub mod object_store;
+mod read_replica;
+
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    ops::RangeInclusive,
+    sync::Arc,
+};
 
 use async_trait::async_trait;
 use dashmap::DashMap;
@@ -15,20 +25,21 @@ use itertools::Itertools;
 use linera_base::{
     crypto::CryptoHash,
     data_types::{
-        ApplicationDescription, Blob, ChainDescription, CompressedBytecode, Epoch,
+        ApplicationDescription, Blob, BlockHeight, ChainDescription, CompressedBytecode, Epoch,
         NetworkDescription, TimeDelta, Timestamp,
     },
     identifiers::{ApplicationId, BlobId, BlobType, ChainId, EventId, IndexAndEvent, StreamId},
     vm::VmRuntime,
 };
 use linera_chain::{
+    block::BlockHeader,
     types::{ConfirmedBlock, ConfirmedBlockCertificate},
     ChainError, ChainStateView,
 };
 use linera_execution::{
-    committee::Committee, system::EPOCH_STREAM_NAME, BlobState, ExecutionError,
-    ExecutionRuntimeConfig, ExecutionRuntimeContext, UserContractCode, UserServiceCode,
-    WasmRuntime,
+    committee::Committee, system::EPOCH_STREAM_NAME, BlobProvenance, BlobState, ExecutionError,
+    ExecutionRuntimeConfig, ExecutionRuntimeContext, ExecutionStateView, Query, QueryContext,
+    QueryResponse, UserContractCode, UserServiceCode, WasmRuntime,
 };
 #[cfg(with_revm)]
 use linera_execution::{
@@ -37,13 +48,31 @@ use linera_execution::{
 };
 #[cfg(with_wasm_runtime)]
 use linera_execution::{WasmContractModule, WasmServiceModule};
-use linera_views::{context::Context, views::RootView, ViewError};
+use linera_views::{
+    batch::Batch,
+    context::{Context, ViewContext},
+    fork::ForkStore,
+    store::{NamespaceStats, WritableKeyValueStore as _},
+    views::{RootView, View as _},
+    ViewError,
+};
+use serde::{Deserialize, Serialize};
 
 #[cfg(with_metrics)]
 pub use crate::db_storage::metrics;
 #[cfg(with_testing)]
 pub use crate::db_storage::TestClock;
-pub use crate::db_storage::{ChainStatesFirstAssignment, DbStorage, WallClock};
+pub use crate::{
+    blob_cache::{BlobCache, BlobCacheConfig},
+    blob_offload::{
+        BlobObjectStore, BlobOffloadConfig, BlobOffloadGcReport, KeyValueBlobObjectStore,
+    },
+    db_storage::{
+        CertificateDeduplicationReport, ChainStatesFirstAssignment, DbStorage, WallClock,
+    },
+    execution_state_delta::ExecutionStateDelta,
+    read_replica::ReadReplicaConfig,
+};
 
 /// The default namespace to be used when none is specified
 pub const DEFAULT_NAMESPACE: &str = "table_linera";
@@ -53,7 +82,11 @@ pub const DEFAULT_NAMESPACE: &str = "table_linera";
 #[cfg_attr(web, async_trait(?Send))]
 pub trait Storage: Sized {
     /// The low-level storage implementation in use by the core protocol (chain workers etc).
-    type Context: Context<Extra = ChainRuntimeContext<Self>> + Clone + Send + Sync + 'static;
+    type Context: Context<Extra = ChainRuntimeContext<Self>, Store: Send + Sync + 'static>
+        + Clone
+        + Send
+        + Sync
+        + 'static;
 
     /// The clock type being used.
     type Clock: Clock;
@@ -73,6 +106,28 @@ pub trait Storage: Sized {
     /// storage. This can lead to invalid states and data corruption.
     async fn load_chain(&self, id: ChainId) -> Result<ChainStateView<Self::Context>, ViewError>;
 
+    /// Loads the view of a chain state that is only going to be read, possibly from a replica
+    /// of the storage.
+    ///
+    /// The returned view is at least as recent as the last certificate written for this chain
+    /// through this instance, but it must never be saved.
+    async fn load_chain_for_read(
+        &self,
+        id: ChainId,
+    ) -> Result<ChainStateView<Self::Context>, ViewError> {
+        self.load_chain(id).await
+    }
+
+    /// Loads a read-only view of a chain state through a shared store, e.g. to read the
+    /// state of another chain during execution.
+    ///
+    /// Unlike [`Storage::load_chain`], this never writes to the chain's store: a pending
+    /// journal is not resolved. The returned view must never be saved.
+    async fn load_chain_shared(
+        &self,
+        id: ChainId,
+    ) -> Result<ChainStateView<Self::Context>, ViewError>;
+
     /// Tests the existence of a blob with the given blob ID.
     async fn contains_blob(&self, blob_id: BlobId) -> Result<bool, ViewError>;
 
@@ -103,6 +158,13 @@ pub trait Storage: Sized {
         blob_ids: &[BlobId],
     ) -> Result<Vec<Option<BlobState>>, ViewError>;
 
+    /// Returns the state of the blob with the given ID and whether its content is stored.
+    async fn blob_provenance(&self, blob_id: BlobId) -> Result<BlobProvenance, ViewError> {
+        let state = self.read_blob_state(blob_id).await?;
+        let has_content = self.contains_blob(blob_id).await?;
+        Ok(BlobProvenance { state, has_content })
+    }
+
     /// Writes the given blob.
     async fn write_blob(&self, blob: &Blob) -> Result<(), ViewError>;
 
@@ -127,6 +189,24 @@ pub trait Storage: Sized {
     /// Writes several blobs.
     async fn write_blobs(&self, blobs: &[Blob]) -> Result<(), ViewError>;
 
+    /// Writes the signatures and the header of a certificate, but not the block body, for
+    /// chains that are only synchronized partially.
+    async fn write_certificate_header(
+        &self,
+        certificate: &ConfirmedBlockCertificate,
+    ) -> Result<(), ViewError>;
+
+    /// Reads the header of the block with the given hash, even if only the header was stored.
+    async fn read_block_header(&self, hash: CryptoHash) -> Result<Option<BlockHeader>, ViewError>;
+
+    /// Reads the hash of the confirmed block of the given chain at the given height, from the
+    /// index written together with its certificate.
+    async fn read_block_hash(
+        &self,
+        chain_id: ChainId,
+        height: BlockHeight,
+    ) -> Result<Option<CryptoHash>, ViewError>;
+
     /// Tests existence of the certificate with the given hash.
     async fn contains_certificate(&self, hash: CryptoHash) -> Result<bool, ViewError>;
 
@@ -171,10 +251,199 @@ pub trait Storage: Sized {
         information: &NetworkDescription,
     ) -> Result<(), ViewError>;
 
-    /// Returns a map of the committees for the given epochs.
+    /// Reads the checkpoint saved by the last run of a background maintenance task.
+    async fn read_task_checkpoint(&self, task_name: &str) -> Result<Option<Vec<u8>>, ViewError>;
+
+    /// Saves the checkpoint of a background maintenance task.
+    async fn write_task_checkpoint(
+        &self,
+        task_name: &str,
+        checkpoint: Vec<u8>,
+    ) -> Result<(), ViewError>;
+
+    /// Reads the fencing term of the validator instance allowed to sign for the given
+    /// shard, or zero if no instance was ever promoted.
+    async fn read_signing_term(&self, shard_id: u32) -> Result<u64, ViewError>;
+
+    /// Records the fencing term of the validator instance allowed to sign for the given
+    /// shard.
+    async fn write_signing_term(&self, shard_id: u32, term: u64) -> Result<(), ViewError>;
+
+    /// Reads the persisted delta leading to the execution state with the given hash, if any.
+    async fn read_execution_state_delta(
+        &self,
+        state_hash: CryptoHash,
+    ) -> Result<Option<ExecutionStateDelta>, ViewError>;
+
+    /// Persists a delta between two execution states. If there are already `max_entries`
+    /// deltas, the least recently written or read ones are removed to make room for it.
+    async fn write_execution_state_delta(
+        &self,
+        delta: &ExecutionStateDelta,
+        max_entries: usize,
+    ) -> Result<(), ViewError>;
+
+    /// Removes the persisted delta leading to the execution state with the given hash.
+    async fn remove_execution_state_delta(&self, state_hash: CryptoHash) -> Result<(), ViewError>;
+
+    /// Reads the batch reverting the changes that the block at `height` made to the
+    /// execution state of the given chain, if it is still retained.
+    async fn read_execution_state_undo(
+        &self,
+        chain_id: ChainId,
+        height: BlockHeight,
+    ) -> Result<Option<Batch>, ViewError>;
+
+    /// Persists the batch reverting the changes that the block at `height` made to the
+    /// execution state of the given chain, and removes the ones of the blocks at least
+    /// `retention` heights earlier.
+    async fn write_execution_state_undo(
+        &self,
+        chain_id: ChainId,
+        height: BlockHeight,
+        undo: &Batch,
+        retention: u64,
+    ) -> Result<(), ViewError>;
+
+    /// Loads the execution state of `chain` as it was right after the block at `height`.
+    ///
+    /// The later blocks are reverted on a fork of the store, with the batches persisted by
+    /// [`Storage::write_execution_state_undo`], so nothing is written. Returns `None` if these
+    /// are not all retained, or if the result doesn't match the block's state hash, e.g.
+    /// because the batches are left over from an earlier history of the chain, or because
+    /// the chain changed in the meantime.
+    async fn load_execution_state_at(
+        &self,
+        chain: &ChainStateView<Self::Context>,
+        height: BlockHeight,
+    ) -> Result<Option<HistoricalExecutionStateView<Self>>, ViewError> {
+        let chain_id = chain.chain_id();
+        let next_block_height = chain.tip_state.get().next_block_height;
+        if height >= next_block_height {
+            return Ok(None);
+        }
+        let context = chain.execution_state.context().fork();
+        for reverted_height in (height.0 + 1..next_block_height.0).rev() {
+            let Some(undo) = self
+                .read_execution_state_undo(chain_id, BlockHeight(reverted_height))
+                .await?
+            else {
+                return Ok(None);
+            };
+            context.store().write_batch(undo).await?;
+        }
+        let Ok(index) = usize::try_from(height.0) else {
+            return Ok(None);
+        };
+        let Some(block_hash) = chain.confirmed_log.get(index).await? else {
+            return Ok(None);
+        };
+        let Some(block) = self.read_confirmed_block(block_hash).await? else {
+            return Ok(None);
+        };
+        let execution_state = ExecutionStateView::load(context).await?;
+        if execution_state.state_hash().await? != block.block().header.state_hash {
+            return Ok(None);
+        }
+        Ok(Some(execution_state))
+    }
+
+    /// Returns the objects of the blob object store that are not referenced by this storage,
+    /// nor by any other storage sharing the object store, if blob offloading is enabled.
+    async fn find_unreferenced_offloaded_blobs(&self) -> Result<Vec<BlobId>, ViewError>;
+
+    /// Drops the references of this storage to the given objects of the blob object store,
+    /// except the ones it stores a blob for by now, and deletes the objects that are left
+    /// with no references. Returns the deleted ones.
+    async fn delete_unreferenced_offloaded_blobs(
+        &self,
+        blob_ids: &[BlobId],
+    ) -> Result<Vec<BlobId>, ViewError>;
+
+    /// Deletes the objects of the blob object store that are not referenced by any storage,
+    /// if blob offloading is enabled.
+    ///
+    /// Unreferenced objects are only deleted if they were already reported as candidates by
+    /// the previous pass, passed in as `previous_candidates`. This leaves time for a
+    /// concurrent write to commit the reference to an object it has just uploaded.
+    async fn collect_offloaded_blobs(
+        &self,
+        previous_candidates: &BTreeSet<BlobId>,
+    ) -> Result<BlobOffloadGcReport, ViewError> {
+        let mut report = BlobOffloadGcReport::default();
+        let mut unreferenced = Vec::new();
+        for blob_id in self.find_unreferenced_offloaded_blobs().await? {
+            if previous_candidates.contains(&blob_id) {
+                unreferenced.push(blob_id);
+            } else {
+                report.candidates.insert(blob_id);
+            }
+        }
+        report.deleted = self
+            .delete_unreferenced_offloaded_blobs(&unreferenced)
+            .await?;
+        Ok(report)
+    }
+
+    /// Returns the approximate number of keys and bytes in the namespace of the database.
+    /// Metered databases also record them in their metrics.
+    async fn namespace_stats(&self) -> Result<NamespaceStats, ViewError>;
+
+    /// Returns a map of the committees for the given epochs, with the voting rights after the
+    /// validator weight updates processed by the admin chain so far.
     async fn committees_for(
         &self,
         epoch_range: RangeInclusive<Epoch>,
+    ) -> Result<BTreeMap<Epoch, Committee>, ViewError> {
+        Ok(self
+            .epoch_committees_for(epoch_range)
+            .await?
+            .into_iter()
+            .map(|(epoch, epoch_committee)| (epoch, epoch_committee.committee))
+            .collect())
+    }
+
+    /// Returns a map of the committees for the given epochs, with the voting rights after the
+    /// validator weight updates processed by the admin chain so far, and the committees these
+    /// updates replaced during their grace window.
+    async fn epoch_committees_for(
+        &self,
+        epoch_range: RangeInclusive<Epoch>,
+    ) -> Result<BTreeMap<Epoch, EpochCommittee>, ViewError> {
+        let mut committees = self
+            .initial_committees_for(epoch_range)
+            .await?
+            .into_iter()
+            .map(|(epoch, committee)| (epoch, EpochCommittee::from(committee)))
+            .collect::<BTreeMap<_, _>>();
+        if committees.is_empty() {
+            return Ok(committees);
+        }
+        let epochs = committees.keys().copied().collect::<Vec<_>>();
+        committees.extend(self.read_epoch_committees(&epochs).await?);
+        Ok(committees)
+    }
+
+    /// Reads the committees recorded with [`Storage::write_epoch_committee`] for the given
+    /// epochs. Epochs without validator weight updates are omitted.
+    async fn read_epoch_committees(
+        &self,
+        epochs: &[Epoch],
+    ) -> Result<BTreeMap<Epoch, EpochCommittee>, ViewError>;
+
+    /// Records the committee of `epoch` after a validator weight update processed by the
+    /// admin chain.
+    async fn write_epoch_committee(
+        &self,
+        epoch: Epoch,
+        committee: &EpochCommittee,
+    ) -> Result<(), ViewError>;
+
+    /// Returns a map of the committees for the given epochs as they were created, i.e.
+    /// without the validator weight updates within these epochs.
+    async fn initial_committees_for(
+        &self,
+        epoch_range: RangeInclusive<Epoch>,
     ) -> Result<BTreeMap<Epoch, Committee>, ViewError> {
         // Short-circuit for an empty input range.
         if epoch_range.is_empty() {
@@ -257,6 +526,9 @@ pub trait Storage: Sized {
     /// Selects the WebAssembly runtime to use for applications (if any).
     fn wasm_runtime(&self) -> Option<WasmRuntime>;
 
+    /// Returns the cache of blobs and compiled modules shared by this storage, if any.
+    fn blob_cache(&self) -> Option<&BlobCache>;
+
     /// Creates a [`UserContractCode`] instance using the bytecode in storage referenced
     /// by the `application_description`.
     async fn load_contract(
@@ -388,6 +660,35 @@ pub trait Storage: Sized {
     ) -> Result<Self::BlockExporterContext, ViewError>;
 }
 
+/// The committee of an epoch after the validator weight updates processed by the admin
+/// chain so far.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EpochCommittee {
+    /// The committee with the latest voting rights.
+    pub committee: Committee,
+    /// The committee from before the last weight update, and the time until which validators
+    /// still accept certificates signed according to it.
+    pub superseded: Option<(Committee, Timestamp)>,
+}
+
+impl EpochCommittee {
+    /// Returns the committee from before the last weight update, if a validator whose clock
+    /// shows `local_time` still accepts certificates signed according to it.
+    pub fn superseded_committee(&self, local_time: Timestamp) -> Option<&Committee> {
+        let (committee, deadline) = self.superseded.as_ref()?;
+        (local_time <= *deadline).then_some(committee)
+    }
+}
+
+impl From<Committee> for EpochCommittee {
+    fn from(committee: Committee) -> Self {
+        EpochCommittee {
+            committee,
+            superseded: None,
+        }
+    }
+}
+
 /// The result of processing the obtained read certificates.
 pub enum ResultReadCertificates {
     Certificates(Vec<ConfirmedBlockCertificate>),
@@ -415,6 +716,12 @@ impl ResultReadCertificates {
     }
 }
 
+/// The execution state of a chain as it was after one of its blocks, loaded by
+/// [`Storage::load_execution_state_at`] on a fork of the store.
+pub type HistoricalExecutionStateView<S> = ExecutionStateView<
+    ViewContext<ChainRuntimeContext<S>, ForkStore<<<S as Storage>::Context as Context>::Store>>,
+>;
+
 /// An implementation of `ExecutionRuntimeContext` suitable for the core protocol.
 #[derive(Clone)]
 pub struct ChainRuntimeContext<S> {
@@ -436,7 +743,7 @@ where
     }
 
     fn execution_runtime_config(&self) -> linera_execution::ExecutionRuntimeConfig {
-        self.execution_runtime_config
+        self.execution_runtime_config.clone()
     }
 
     fn user_contracts(&self) -> &Arc<DashMap<ApplicationId, UserContractCode>> {
@@ -456,10 +763,22 @@ where
         if let Some(contract) = self.user_contracts.get(&application_id) {
             return Ok(contract.clone());
         }
-        let contract = self
-            .storage
-            .load_contract(description, created_blobs)
-            .await?;
+        // Applications created from the same module share the compiled code.
+        let bytecode_id = description.contract_bytecode_blob_id();
+        let cache = self.storage.blob_cache();
+        let contract = match cache.and_then(|cache| cache.contract(&bytecode_id)) {
+            Some(contract) => contract,
+            None => {
+                let contract = self
+                    .storage
+                    .load_contract(description, created_blobs)
+                    .await?;
+                if let Some(cache) = cache {
+                    cache.insert_contract(bytecode_id, contract.clone());
+                }
+                contract
+            }
+        };
         self.user_contracts.insert(application_id, contract.clone());
         Ok(contract)
     }
@@ -473,10 +792,21 @@ where
         if let Some(service) = self.user_services.get(&application_id) {
             return Ok(service.clone());
         }
-        let service = self
-            .storage
-            .load_service(description, created_blobs)
-            .await?;
+        let bytecode_id = description.service_bytecode_blob_id();
+        let cache = self.storage.blob_cache();
+        let service = match cache.and_then(|cache| cache.service(&bytecode_id)) {
+            Some(service) => service,
+            None => {
+                let service = self
+                    .storage
+                    .load_service(description, created_blobs)
+                    .await?;
+                if let Some(cache) = cache {
+                    cache.insert_service(bytecode_id, service.clone());
+                }
+                service
+            }
+        };
         self.user_services.insert(application_id, service.clone());
         Ok(service)
     }
@@ -497,7 +827,10 @@ where
         &self,
         epoch_range: RangeInclusive<Epoch>,
     ) -> Result<BTreeMap<Epoch, Committee>, ViewError> {
-        self.storage.committees_for(epoch_range).await
+        // New chains start with the committees as they were created, and then process the
+        // validator weight updates, so that their state doesn't depend on which updates this
+        // storage has already seen.
+        self.storage.initial_committees_for(epoch_range).await
     }
 
     async fn contains_blob(&self, blob_id: BlobId) -> Result<bool, ViewError> {
@@ -508,6 +841,52 @@ where
         self.storage.contains_event(event_id).await
     }
 
+    async fn read_application_value(
+        &self,
+        chain_id: ChainId,
+        application_id: ApplicationId,
+        key: Vec<u8>,
+    ) -> Result<Option<Vec<u8>>, ViewError> {
+        let chain = self.storage.load_chain_shared(chain_id).await?;
+        let Some(state) = chain
+            .execution_state
+            .users
+            .try_load_entry(&application_id)
+            .await?
+        else {
+            return Ok(None);
+        };
+        state.get(&key).await
+    }
+
+    async fn query_application_at(
+        &self,
+        application_id: ApplicationId,
+        height: BlockHeight,
+        query: Vec<u8>,
+    ) -> Result<Option<Vec<u8>>, ExecutionError> {
+        let chain = self.storage.load_chain_shared(self.chain_id).await?;
+        let Some(mut execution_state) =
+            self.storage.load_execution_state_at(&chain, height).await?
+        else {
+            return Ok(None);
+        };
+        let context = QueryContext {
+            chain_id: self.chain_id,
+            next_block_height: height.try_add_one()?,
+            local_time: self.storage.clock().current_time(),
+        };
+        let query = Query::User {
+            application_id,
+            bytes: query,
+        };
+        let outcome = Box::pin(execution_state.query_application(context, query, None)).await?;
+        match outcome.response {
+            QueryResponse::User(response) => Ok(Some(response)),
+            QueryResponse::System(_) => unreachable!("user queries get user responses"),
+        }
+    }
+
     #[cfg(with_testing)]
     async fn add_blobs(
         &self,
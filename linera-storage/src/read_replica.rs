@@ -0,0 +1,69 @@
+// Copyright (c) Zefchain Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! Reading from a replica of the primary database, e.g. in another region.
+//!
+//! All writes go to the primary database, which is expected to replicate them
+//! asynchronously. Content-addressed data (blobs, certificates, confirmed blocks and
+//! events) never changes once written, so it can be read from the replica as long as
+//! missing entries are looked up again on the primary.
+//!
+//! Chain states do change. Whenever a block or its execution data is written, the primary
+//! also records the next block height of its chain. This version fence is stored in the
+//! primary database, so that it is shared by all the storage instances, e.g. in other
+//! regions. A chain state read from the replica is only used if the fence exists and the
+//! state is not behind it, and is otherwise discarded in favor of the primary.
+
+use std::sync::Arc;
+
+use linera_base::data_types::BlockHeight;
+use serde::{Deserialize, Serialize};
+
+/// Which reads are served by the replica.
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub struct ReadReplicaConfig {
+    /// Whether to read blobs, certificates, confirmed blocks and events from the replica.
+    pub read_immutable_data: bool,
+    /// Whether to read chain states from the replica, if they are not behind the fence.
+    pub read_chain_states: bool,
+}
+
+impl Default for ReadReplicaConfig {
+    fn default() -> Self {
+        Self {
+            read_immutable_data: true,
+            read_chain_states: true,
+        }
+    }
+}
+
+/// A replica database together with its configuration.
+pub(crate) struct ReadReplica<Database> {
+    pub database: Arc<Database>,
+    pub config: ReadReplicaConfig,
+}
+
+impl<Database> Clone for ReadReplica<Database> {
+    fn clone(&self) -> Self {
+        Self {
+            database: self.database.clone(),
+            config: self.config,
+        }
+    }
+}
+
+impl<Database> ReadReplica<Database> {
+    pub fn new(database: Database, config: ReadReplicaConfig) -> Self {
+        Self {
+            database: Arc::new(database),
+            config,
+        }
+    }
+}
+
+/// Returns whether a chain state with the given next block height read from the replica is
+/// recent enough to be used, given the fence of its chain in the primary database. Without a
+/// fence, nothing is known about the chain, so the state is not used.
+pub(crate) fn is_up_to_date(fence: Option<BlockHeight>, next_block_height: BlockHeight) -> bool {
+    fence.is_some_and(|fence| next_block_height >= fence)
+}
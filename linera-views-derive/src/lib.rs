@@ -206,7 +206,7 @@ fn generate_root_view_code(input: ItemStruct) -> TokenStream2 {
             linera_views::metrics::increment_counter(
                 &linera_views::metrics::SAVE_VIEW_COUNTER,
                 stringify!(#struct_name),
-                &self.context().base_key().bytes,
+                &linera_views::context::Context::base_key(self.context()).bytes,
             );
         }
     } else {
@@ -220,12 +220,12 @@ fn generate_root_view_code(input: ItemStruct) -> TokenStream2 {
             Self: linera_views::views::View,
         {
             async fn save(&mut self) -> Result<(), linera_views::ViewError> {
-                use linera_views::{context::Context, batch::Batch, store::WritableKeyValueStore as _, views::View};
+                use linera_views::{batch::Batch, views::View};
                 #increment_counter
                 let mut batch = Batch::new();
                 self.flush(&mut batch)?;
                 if !batch.is_empty() {
-                    self.context().store().write_batch(batch).await?;
+                    linera_views::change_log::write_batch(self, batch).await?;
                 }
                 Ok(())
             }
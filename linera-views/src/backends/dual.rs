@@ -10,9 +10,10 @@ use thiserror::Error;
 use crate::store::TestKeyValueDatabase;
 use crate::{
     batch::Batch,
+    context::ScanBudget,
     store::{
-        KeyValueDatabase, KeyValueStoreError, ReadableKeyValueStore, WithError,
-        WritableKeyValueStore,
+        KeyValueDatabase, KeyValueStoreError, NamespaceStats, ReadableKeyValueStore, RequestClass,
+        WithError, WritableKeyValueStore,
     },
 };
 
@@ -185,6 +186,61 @@ where
         };
         Ok(result)
     }
+
+    fn start_recording_reads(&self) {
+        match self {
+            Self::First(store) => store.start_recording_reads(),
+            Self::Second(store) => store.start_recording_reads(),
+        }
+    }
+
+    fn take_recorded_reads(&self) -> Vec<Vec<u8>> {
+        match self {
+            Self::First(store) => store.take_recorded_reads(),
+            Self::Second(store) => store.take_recorded_reads(),
+        }
+    }
+
+    async fn prefetch(&self, keys: Vec<Vec<u8>>) -> Result<(), Self::Error> {
+        match self {
+            Self::First(store) => store.prefetch(keys).await.map_err(DualStoreError::First),
+            Self::Second(store) => store.prefetch(keys).await.map_err(DualStoreError::Second),
+        }
+    }
+
+    async fn find_keys_by_prefix_within(
+        &self,
+        key_prefix: &[u8],
+        budget: ScanBudget,
+    ) -> Result<Option<Vec<Vec<u8>>>, Self::Error> {
+        match self {
+            Self::First(store) => store
+                .find_keys_by_prefix_within(key_prefix, budget)
+                .await
+                .map_err(DualStoreError::First),
+            Self::Second(store) => store
+                .find_keys_by_prefix_within(key_prefix, budget)
+                .await
+                .map_err(DualStoreError::Second),
+        }
+    }
+
+    async fn find_key_values_by_prefix_within(
+        &self,
+        key_prefix: &[u8],
+        budget: ScanBudget,
+    ) -> Result<Option<Vec<(Vec<u8>, Vec<u8>)>>, Self::Error> {
+        match self {
+            Self::First(store) => store
+                .find_key_values_by_prefix_within(key_prefix, budget)
+                .await
+                .map_err(DualStoreError::First),
+            Self::Second(store) => store
+                .find_key_values_by_prefix_within(key_prefix, budget)
+                .await
+                .map_err(DualStoreError::Second),
+        }
+    }
 }
 
 impl<S1, S2> WritableKeyValueStore for DualStore<S1, S2>
@@ -249,18 +305,26 @@ where
     }
 
     fn open_shared(&self, root_key: &[u8]) -> Result<Self::Store, Self::Error> {
+        self.open_shared_for(root_key, RequestClass::Default)
+    }
+
+    fn open_shared_for(
+        &self,
+        root_key: &[u8],
+        class: RequestClass,
+    ) -> Result<Self::Store, Self::Error> {
         match A::assigned_store(root_key)? {
             StoreInUse::First => {
                 let store = self
                     .first_database
-                    .open_shared(root_key)
+                    .open_shared_for(root_key, class)
                     .map_err(DualStoreError::First)?;
                 Ok(DualStore::First(store))
             }
             StoreInUse::Second => {
                 let store = self
                     .second_database
-                    .open_shared(root_key)
+                    .open_shared_for(root_key, class)
                     .map_err(DualStoreError::Second)?;
                 Ok(DualStore::Second(store))
             }
@@ -360,6 +424,24 @@ where
             .map_err(DualStoreError::Second)?;
         Ok(())
     }
+
+    async fn namespace_stats(&self) -> Result<NamespaceStats, Self::Error> {
+        let first = self
+            .first_database
+            .namespace_stats()
+            .await
+            .map_err(DualStoreError::First)?;
+        let second = self
+            .second_database
+            .namespace_stats()
+            .await
+            .map_err(DualStoreError::Second)?;
+        Ok(NamespaceStats {
+            key_count: first.key_count.zip(second.key_count).map(|(a, b)| a + b),
+            total_bytes: first.total_bytes + second.total_bytes,
+            estimated: first.estimated || second.estimated,
+        })
+    }
 }
 
 #[cfg(with_testing)]
@@ -6,6 +6,8 @@
 use std::{
     collections::HashMap,
     env,
+    fmt::{self, Display},
+    str::FromStr,
     sync::{
         atomic::{AtomicBool, Ordering},
         Arc,
@@ -18,6 +20,7 @@ use aws_sdk_dynamodb::{
     operation::{
         create_table::CreateTableError,
         delete_table::DeleteTableError,
+        describe_table::DescribeTableError,
         get_item::GetItemError,
         list_tables::ListTablesError,
         query::{QueryError, QueryOutput},
@@ -42,12 +45,13 @@ use crate::metering::MeteredDatabase;
 use crate::store::TestKeyValueDatabase;
 use crate::{
     batch::SimpleUnorderedBatch,
-    common::get_uleb128_size,
+    common::{get_uleb128_size, NamespaceError, NamespaceRules},
+    context::{ScanBudget, ScanCounter},
     journaling::{JournalConsistencyError, JournalingKeyValueDatabase},
     lru_caching::{LruCachingConfig, LruCachingDatabase},
     store::{
-        DirectWritableKeyValueStore, KeyValueDatabase, KeyValueStoreError, ReadableKeyValueStore,
-        WithError,
+        DirectWritableKeyValueStore, KeyValueDatabase, KeyValueStoreError, NamespaceStats,
+        ReadableKeyValueStore, RequestClass, WithError,
     },
     value_splitting::{ValueSplittingDatabase, ValueSplittingError},
     FutureSyncExt as _,
@@ -253,6 +257,21 @@ fn extract_key_value(
     Ok((key, value))
 }
 
+/// Extracts the size of the key, and of the value if it was read, from an item found by a
+/// scan.
+fn extract_scanned_size(
+    prefix_len: usize,
+    attributes: &HashMap<String, AttributeValue>,
+) -> Result<usize, DynamoDbStoreInternalError> {
+    let key = extract_key(prefix_len, attributes)?;
+    let value = if attributes.contains_key(VALUE_ATTRIBUTE) {
+        extract_value(attributes)?
+    } else {
+        &[]
+    };
+    Ok(key.len() + value.len())
+}
+
 struct TransactionBuilder {
     start_key: Vec<u8>,
     transactions: Vec<TransactWriteItem>,
@@ -288,6 +307,111 @@ impl TransactionBuilder {
     }
 }
 
+/// Whether a DynamoDB read returns the latest written data, or may return stale data at a
+/// lower cost and latency.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+pub enum DynamoDbReadConsistency {
+    /// The read may not reflect the most recent writes.
+    #[default]
+    Eventual,
+    /// The read reflects all the writes that succeeded before it.
+    Strong,
+}
+
+impl DynamoDbReadConsistency {
+    /// Returns the name of the consistency.
+    pub fn name(&self) -> &'static str {
+        match self {
+            DynamoDbReadConsistency::Eventual => "eventual",
+            DynamoDbReadConsistency::Strong => "strong",
+        }
+    }
+
+    fn is_strong(&self) -> bool {
+        matches!(self, DynamoDbReadConsistency::Strong)
+    }
+}
+
+impl Display for DynamoDbReadConsistency {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+impl FromStr for DynamoDbReadConsistency {
+    type Err = InvalidDynamoDbConsistency;
+
+    fn from_str(string: &str) -> Result<Self, Self::Err> {
+        match string {
+            "eventual" => Ok(DynamoDbReadConsistency::Eventual),
+            "strong" => Ok(DynamoDbReadConsistency::Strong),
+            _ => Err(InvalidDynamoDbConsistency(string.to_owned())),
+        }
+    }
+}
+
+/// The DynamoDB read consistency of each class of requests. The requests of no other class
+/// use eventually consistent reads, and writes are always strongly consistent.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+pub struct DynamoDbConsistencyConfig {
+    /// The consistency of reading certificates and the blocks they contain.
+    pub certificate_reads: DynamoDbReadConsistency,
+    /// The consistency of checking which blobs are stored before fetching the missing ones.
+    pub blob_prefetch: DynamoDbReadConsistency,
+}
+
+impl DynamoDbConsistencyConfig {
+    /// Returns whether the reads of the given class are strongly consistent.
+    fn is_strong(&self, class: RequestClass) -> bool {
+        match class {
+            RequestClass::Default => false,
+            RequestClass::CertificateReads => self.certificate_reads.is_strong(),
+            RequestClass::BlobPrefetch => self.blob_prefetch.is_strong(),
+        }
+    }
+}
+
+impl Display for DynamoDbConsistencyConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "certificate_reads={},blob_prefetch={}",
+            self.certificate_reads, self.blob_prefetch
+        )
+    }
+}
+
+impl FromStr for DynamoDbConsistencyConfig {
+    type Err = InvalidDynamoDbConsistency;
+
+    /// Parses a comma-separated list of `<class>=<consistency>` entries, where the class is
+    /// one of `certificate_reads` and `blob_prefetch`. The classes that are not listed use
+    /// `eventual`.
+    fn from_str(string: &str) -> Result<Self, Self::Err> {
+        let mut config = Self::default();
+        for entry in string.split(',') {
+            let (class, consistency) = entry
+                .split_once('=')
+                .ok_or_else(|| InvalidDynamoDbConsistency(entry.to_owned()))?;
+            let consistency = consistency.trim().parse()?;
+            match class.trim() {
+                "certificate_reads" => config.certificate_reads = consistency,
+                "blob_prefetch" => config.blob_prefetch = consistency,
+                _ => return Err(InvalidDynamoDbConsistency(entry.to_owned())),
+            }
+        }
+        Ok(config)
+    }
+}
+
+/// Error caused by an invalid [`DynamoDbReadConsistency`] or [`DynamoDbConsistencyConfig`].
+#[derive(Clone, Debug, Error)]
+#[error(
+    "{0:?} is not a valid DynamoDB consistency; expected `eventual` or `strong`, assigned to \
+     `certificate_reads` or `blob_prefetch`"
+)]
+pub struct InvalidDynamoDbConsistency(String);
+
 /// A DynamoDB client.
 #[derive(Clone, Debug)]
 pub struct DynamoDbStoreInternal {
@@ -295,6 +419,8 @@ pub struct DynamoDbStoreInternal {
     namespace: String,
     semaphore: Option<Arc<Semaphore>>,
     max_stream_queries: usize,
+    /// Whether the reads are strongly consistent.
+    consistent_read: bool,
     start_key: Vec<u8>,
     root_key_written: Arc<AtomicBool>,
 }
@@ -306,6 +432,7 @@ pub struct DynamoDbDatabaseInternal {
     namespace: String,
     semaphore: Option<Arc<Semaphore>>,
     max_stream_queries: usize,
+    consistency: DynamoDbConsistencyConfig,
 }
 
 impl WithError for DynamoDbDatabaseInternal {
@@ -321,6 +448,9 @@ pub struct DynamoDbStoreInternalConfig {
     pub max_concurrent_queries: Option<usize>,
     /// Preferred buffer size for async streams.
     pub max_stream_queries: usize,
+    /// The read consistency of each class of requests.
+    #[serde(default)]
+    pub consistency: DynamoDbConsistencyConfig,
 }
 
 impl DynamoDbStoreInternalConfig {
@@ -346,26 +476,34 @@ impl KeyValueDatabase for DynamoDbDatabaseInternal {
         config: &Self::Config,
         namespace: &str,
     ) -> Result<Self, DynamoDbStoreInternalError> {
-        Self::check_namespace(namespace)?;
+        let namespace = NamespaceRules::DYNAMO_DB.escape(namespace)?;
         let client = config.client().await?;
         let semaphore = config
             .max_concurrent_queries
             .map(|n| Arc::new(Semaphore::new(n)));
         let max_stream_queries = config.max_stream_queries;
-        let namespace = namespace.to_string();
         let store = Self {
             client,
             namespace,
             semaphore,
             max_stream_queries,
+            consistency: config.consistency,
         };
         Ok(store)
     }
 
     fn open_shared(&self, root_key: &[u8]) -> Result<Self::Store, DynamoDbStoreInternalError> {
+        self.open_shared_for(root_key, RequestClass::Default)
+    }
+
+    fn open_shared_for(
+        &self,
+        root_key: &[u8],
+        class: RequestClass,
+    ) -> Result<Self::Store, DynamoDbStoreInternalError> {
         let mut start_key = EMPTY_ROOT_KEY.to_vec();
         start_key.extend(root_key);
-        self.open_internal(start_key)
+        self.open_internal(start_key, class)
     }
 
     fn open_exclusive(&self, root_key: &[u8]) -> Result<Self::Store, DynamoDbStoreInternalError> {
@@ -373,26 +511,11 @@ impl KeyValueDatabase for DynamoDbDatabaseInternal {
     }
 
     async fn list_all(config: &Self::Config) -> Result<Vec<String>, DynamoDbStoreInternalError> {
-        let client = config.client().await?;
-        let mut namespaces = Vec::new();
-        let mut start_table = None;
-        loop {
-            let response = client
-                .list_tables()
-                .set_exclusive_start_table_name(start_table)
-                .send()
-                .boxed_sync()
-                .await?;
-            if let Some(namespaces_blk) = response.table_names {
-                namespaces.extend(namespaces_blk);
-            }
-            if response.last_evaluated_table_name.is_none() {
-                break;
-            } else {
-                start_table = response.last_evaluated_table_name;
-            }
-        }
-        Ok(namespaces)
+        Ok(Self::list_tables(config)
+            .await?
+            .iter()
+            .filter_map(|table| NamespaceRules::DYNAMO_DB.unescape(table).ok())
+            .collect())
     }
 
     async fn list_root_keys(
@@ -400,13 +523,14 @@ impl KeyValueDatabase for DynamoDbDatabaseInternal {
         namespace: &str,
     ) -> Result<Vec<Vec<u8>>, DynamoDbStoreInternalError> {
         let database = Self::connect(config, namespace).await?;
-        let store = database.open_internal(PARTITION_KEY_ROOT_KEY.to_vec())?;
+        let store =
+            database.open_internal(PARTITION_KEY_ROOT_KEY.to_vec(), RequestClass::Default)?;
         store.find_keys_by_prefix(EMPTY_ROOT_KEY).await
     }
 
     async fn delete_all(config: &Self::Config) -> Result<(), DynamoDbStoreInternalError> {
         let client = config.client().await?;
-        let tables = Self::list_all(config).await?;
+        let tables = Self::list_tables(config).await?;
         for table in tables {
             client
                 .delete_table()
@@ -422,12 +546,12 @@ impl KeyValueDatabase for DynamoDbDatabaseInternal {
         config: &Self::Config,
         namespace: &str,
     ) -> Result<bool, DynamoDbStoreInternalError> {
-        Self::check_namespace(namespace)?;
+        let namespace = NamespaceRules::DYNAMO_DB.escape(namespace)?;
         let client = config.client().await?;
         let key_db = build_key(EMPTY_ROOT_KEY, DB_KEY.to_vec());
         let response = client
             .get_item()
-            .table_name(namespace)
+            .table_name(&namespace)
             .set_key(Some(key_db))
             .send()
             .boxed_sync()
@@ -456,11 +580,11 @@ impl KeyValueDatabase for DynamoDbDatabaseInternal {
         config: &Self::Config,
         namespace: &str,
     ) -> Result<(), DynamoDbStoreInternalError> {
-        Self::check_namespace(namespace)?;
+        let namespace = NamespaceRules::DYNAMO_DB.escape(namespace)?;
         let client = config.client().await?;
         client
             .create_table()
-            .table_name(namespace)
+            .table_name(&namespace)
             .attribute_definitions(
                 AttributeDefinition::builder()
                     .attribute_name(PARTITION_ATTRIBUTE)
@@ -501,53 +625,79 @@ impl KeyValueDatabase for DynamoDbDatabaseInternal {
         config: &Self::Config,
         namespace: &str,
     ) -> Result<(), DynamoDbStoreInternalError> {
-        Self::check_namespace(namespace)?;
+        let namespace = NamespaceRules::DYNAMO_DB.escape(namespace)?;
         let client = config.client().await?;
         client
             .delete_table()
-            .table_name(namespace)
+            .table_name(&namespace)
             .send()
             .boxed_sync()
             .await?;
         Ok(())
     }
+
+    async fn namespace_stats(&self) -> Result<NamespaceStats, DynamoDbStoreInternalError> {
+        let response = self
+            .client
+            .describe_table()
+            .table_name(&self.namespace)
+            .send()
+            .boxed_sync()
+            .await?;
+        // DynamoDB only refreshes these numbers every six hours or so.
+        let table = response.table();
+        let count = |value: Option<i64>| value.map_or(0, |value| value.max(0) as u64);
+        Ok(NamespaceStats {
+            key_count: Some(count(table.and_then(|table| table.item_count()))),
+            total_bytes: count(table.and_then(|table| table.table_size_bytes())),
+            estimated: true,
+        })
+    }
 }
 
 impl DynamoDbDatabaseInternal {
-    /// Namespaces are named table names in DynamoDB [naming
-    /// rules](https://docs.aws.amazon.com/amazondynamodb/latest/developerguide/HowItWorks.NamingRulesDataTypes.html#HowItWorks.NamingRules),
-    /// so we need to check correctness of the namespace
-    fn check_namespace(namespace: &str) -> Result<(), InvalidNamespace> {
-        if namespace.len() < 3 {
-            return Err(InvalidNamespace::TooShort);
-        }
-        if namespace.len() > 255 {
-            return Err(InvalidNamespace::TooLong);
-        }
-        if !namespace.chars().all(|character| {
-            character.is_ascii_alphanumeric()
-                || character == '.'
-                || character == '-'
-                || character == '_'
-        }) {
-            return Err(InvalidNamespace::InvalidCharacter);
+    /// Lists the names of all the tables, including the ones that are not namespaces.
+    async fn list_tables(
+        config: &DynamoDbStoreInternalConfig,
+    ) -> Result<Vec<String>, DynamoDbStoreInternalError> {
+        let client = config.client().await?;
+        let mut tables = Vec::new();
+        let mut start_table = None;
+        loop {
+            let response = client
+                .list_tables()
+                .set_exclusive_start_table_name(start_table)
+                .send()
+                .boxed_sync()
+                .await?;
+            if let Some(tables_blk) = response.table_names {
+                tables.extend(tables_blk);
+            }
+            if response.last_evaluated_table_name.is_none() {
+                break;
+            } else {
+                start_table = response.last_evaluated_table_name;
+            }
         }
-        Ok(())
+        Ok(tables)
     }
 
     fn open_internal(
         &self,
         start_key: Vec<u8>,
+        class: RequestClass,
     ) -> Result<DynamoDbStoreInternal, DynamoDbStoreInternalError> {
         let client = self.client.clone();
         let namespace = self.namespace.clone();
         let semaphore = self.semaphore.clone();
         let max_stream_queries = self.max_stream_queries;
+        let consistent_read = self.consistency.is_strong(class);
         Ok(DynamoDbStoreInternal {
             client,
             namespace,
             semaphore,
             max_stream_queries,
+            consistent_read,
             start_key,
             root_key_written: Arc::new(AtomicBool::new(false)),
         })
@@ -607,6 +757,7 @@ impl DynamoDbStoreInternal {
             .client
             .query()
             .table_name(&self.namespace)
+            .consistent_read(self.consistent_read)
             .projection_expression(attribute_str)
             .key_condition_expression(format!(
                 "{PARTITION_ATTRIBUTE} = :partition and begins_with({KEY_ATTRIBUTE}, :prefix)"
@@ -630,6 +781,7 @@ impl DynamoDbStoreInternal {
             .get_item()
             .table_name(&self.namespace)
             .set_key(Some(key_db))
+            .consistent_read(self.consistent_read)
             .send()
             .boxed_sync()
             .await?;
@@ -653,6 +805,7 @@ impl DynamoDbStoreInternal {
             .get_item()
             .table_name(&self.namespace)
             .set_key(Some(key_db))
+            .consistent_read(self.consistent_read)
             .projection_expression(PARTITION_ATTRIBUTE)
             .send()
             .boxed_sync()
@@ -661,19 +814,28 @@ impl DynamoDbStoreInternal {
         Ok(response.item.is_some())
     }
 
+    /// Reads the pages of a prefix scan, or returns `None` as soon as the items read exceed
+    /// the budget, without reading the next pages.
     async fn get_list_responses(
         &self,
         attribute: &str,
         start_key: &[u8],
         key_prefix: &[u8],
-    ) -> Result<QueryResponses, DynamoDbStoreInternalError> {
+        budget: ScanBudget,
+    ) -> Result<Option<QueryResponses>, DynamoDbStoreInternalError> {
         check_key_size(key_prefix)?;
+        let mut counter = ScanCounter::new(budget);
         let mut responses = Vec::new();
         let mut start_key_map = None;
         loop {
             let response = self
                 .get_query_output(attribute, start_key, key_prefix, start_key_map)
                 .await?;
+            for item in response.items.iter().flatten() {
+                if !counter.record(extract_scanned_size(key_prefix.len(), item)?) {
+                    return Ok(None);
+                }
+            }
             let last_evaluated = response.last_evaluated_key.clone();
             responses.push(response);
             match last_evaluated {
@@ -685,10 +847,10 @@ impl DynamoDbStoreInternal {
                 }
             }
         }
-        Ok(QueryResponses {
+        Ok(Some(QueryResponses {
             prefix_len: key_prefix.len(),
             responses,
-        })
+        }))
     }
 }
 
@@ -779,26 +941,56 @@ impl ReadableKeyValueStore for DynamoDbStoreInternal {
         &self,
         key_prefix: &[u8],
     ) -> Result<Vec<Vec<u8>>, DynamoDbStoreInternalError> {
-        let result_queries = self
-            .get_list_responses(KEY_ATTRIBUTE, &self.start_key, key_prefix)
+        let keys = self
+            .find_keys_by_prefix_within(key_prefix, ScanBudget::default())
             .await?;
-        result_queries
-            .keys()
-            .map(|key| key.map(|k| k.to_vec()))
-            .collect()
+        Ok(keys.expect("scans without limits are never stopped"))
     }
 
     async fn find_key_values_by_prefix(
         &self,
         key_prefix: &[u8],
     ) -> Result<Vec<(Vec<u8>, Vec<u8>)>, DynamoDbStoreInternalError> {
-        let result_queries = self
-            .get_list_responses(KEY_VALUE_ATTRIBUTE, &self.start_key, key_prefix)
+        let key_values = self
+            .find_key_values_by_prefix_within(key_prefix, ScanBudget::default())
             .await?;
+        Ok(key_values.expect("scans without limits are never stopped"))
+    }
+
+    async fn find_keys_by_prefix_within(
+        &self,
+        key_prefix: &[u8],
+        budget: ScanBudget,
+    ) -> Result<Option<Vec<Vec<u8>>>, DynamoDbStoreInternalError> {
+        let Some(result_queries) = self
+            .get_list_responses(KEY_ATTRIBUTE, &self.start_key, key_prefix, budget)
+            .await?
+        else {
+            return Ok(None);
+        };
+        result_queries
+            .keys()
+            .map(|key| key.map(|k| k.to_vec()))
+            .collect::<Result<_, _>>()
+            .map(Some)
+    }
+
+    async fn find_key_values_by_prefix_within(
+        &self,
+        key_prefix: &[u8],
+        budget: ScanBudget,
+    ) -> Result<Option<Vec<(Vec<u8>, Vec<u8>)>>, DynamoDbStoreInternalError> {
+        let Some(result_queries) = self
+            .get_list_responses(KEY_VALUE_ATTRIBUTE, &self.start_key, key_prefix, budget)
+            .await?
+        else {
+            return Ok(None);
+        };
         result_queries
             .key_values()
             .map(|entry| entry.map(|(key, value)| (key.to_vec(), value.to_vec())))
-            .collect()
+            .collect::<Result<_, _>>()
+            .map(Some)
     }
 }
 
@@ -841,22 +1033,6 @@ impl DirectWritableKeyValueStore for DynamoDbStoreInternal {
     }
 }
 
-/// Error when validating a namespace
-#[derive(Debug, Error)]
-pub enum InvalidNamespace {
-    /// The namespace should be at least 3 characters.
-    #[error("Namespace must have at least 3 characters")]
-    TooShort,
-
-    /// The namespace should be at most 63 characters.
-    #[error("Namespace must be at most 63 characters")]
-    TooLong,
-
-    /// allowed characters are lowercase letters, numbers, periods and hyphens
-    #[error("Namespace must only contain lowercase letters, numbers, periods and hyphens")]
-    InvalidCharacter,
-}
-
 /// Errors that occur when using [`DynamoDbStoreInternal`].
 #[derive(Debug, Error)]
 pub enum DynamoDbStoreInternalError {
@@ -876,6 +1052,10 @@ pub enum DynamoDbStoreInternalError {
     #[error(transparent)]
     DeleteTable(#[from] Box<SdkError<DeleteTableError>>),
 
+    /// An error occurred while describing a table
+    #[error(transparent)]
+    DescribeTable(#[from] Box<SdkError<DescribeTableError>>),
+
     /// An error occurred while listing tables
     #[error(transparent)]
     ListTables(#[from] Box<SdkError<ListTablesError>>),
@@ -930,7 +1110,7 @@ pub enum DynamoDbStoreInternalError {
 
     /// A wrong namespace error occurred
     #[error(transparent)]
-    InvalidNamespace(#[from] InvalidNamespace),
+    InvalidNamespace(#[from] NamespaceError),
 
     /// An error occurred while creating the table.
     #[error(transparent)]
@@ -1004,6 +1184,7 @@ impl TestKeyValueDatabase for JournalingKeyValueDatabase<DynamoDbDatabaseInterna
             use_dynamodb_local: true,
             max_concurrent_queries: Some(TEST_DYNAMO_DB_MAX_CONCURRENT_QUERIES),
             max_stream_queries: TEST_DYNAMO_DB_MAX_STREAM_QUERIES,
+            consistency: DynamoDbConsistencyConfig::default(),
         })
     }
 }
@@ -1035,7 +1216,8 @@ pub type DynamoDbDatabase = LruCachingDatabase<
 mod tests {
     use bcs::serialized_size;
 
-    use crate::common::get_uleb128_size;
+    use super::{DynamoDbConsistencyConfig, DynamoDbReadConsistency};
+    use crate::{common::get_uleb128_size, store::RequestClass};
 
     #[test]
     fn test_serialization_len() {
@@ -1046,4 +1228,34 @@ mod tests {
             assert_eq!(est_size, serial_size);
         }
     }
+
+    #[test]
+    fn test_consistency_config_parsing() {
+        let config = "certificate_reads=strong, blob_prefetch=eventual"
+            .parse::<DynamoDbConsistencyConfig>()
+            .unwrap();
+        assert_eq!(
+            config,
+            DynamoDbConsistencyConfig {
+                certificate_reads: DynamoDbReadConsistency::Strong,
+                blob_prefetch: DynamoDbReadConsistency::Eventual,
+            }
+        );
+        assert!(config.is_strong(RequestClass::CertificateReads));
+        assert!(!config.is_strong(RequestClass::BlobPrefetch));
+        assert!(!config.is_strong(RequestClass::Default));
+        assert_eq!(
+            config
+                .to_string()
+                .parse::<DynamoDbConsistencyConfig>()
+                .unwrap(),
+            config
+        );
+        assert!("writes=strong"
+            .parse::<DynamoDbConsistencyConfig>()
+            .is_err());
+        assert!("certificate_reads=quorum"
+            .parse::<DynamoDbConsistencyConfig>()
+            .is_err());
+    }
 }
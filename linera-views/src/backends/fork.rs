@@ -0,0 +1,330 @@
+// Copyright (c) Zefchain Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! A copy-on-write fork of a given store.
+//!
+//! Reads fall through to the underlying store unless the key was written in the fork,
+//! while writes are kept in an in-memory overlay and never reach the underlying store.
+//! This allows views to be loaded, modified and saved speculatively, e.g. to simulate
+//! the execution of a block, without affecting the persisted state.
+
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    sync::{Arc, Mutex},
+};
+
+use crate::{
+    batch::{Batch, WriteOperation},
+    common::{contains_prefix_of, get_interval, insert_key_prefix},
+    context::ScanBudget,
+    store::{ReadableKeyValueStore, WithError, WritableKeyValueStore},
+};
+
+/// The changes written to a fork.
+#[derive(Default)]
+struct ForkOverlay {
+    /// The keys written in the fork, with `None` for deleted keys.
+    entries: BTreeMap<Vec<u8>, Option<Vec<u8>>>,
+    /// The prefixes deleted in the fork. Keys of the underlying store matching them are
+    /// hidden, unless they were written again in `entries`.
+    deleted_prefixes: BTreeSet<Vec<u8>>,
+}
+
+impl ForkOverlay {
+    /// Returns the value of `key` in the fork, or `None` if it must be read from the
+    /// underlying store.
+    fn get(&self, key: &[u8]) -> Option<Option<Vec<u8>>> {
+        if let Some(value) = self.entries.get(key) {
+            return Some(value.clone());
+        }
+        if contains_prefix_of(&self.deleted_prefixes, key) {
+            return Some(None);
+        }
+        None
+    }
+
+    /// Returns whether the keys of the underlying store matching `key_prefix` are all hidden.
+    fn hides_prefix(&self, key_prefix: &[u8]) -> bool {
+        contains_prefix_of(&self.deleted_prefixes, key_prefix)
+    }
+
+    /// Returns whether the key of the underlying store is overridden by the fork.
+    fn overrides(&self, key: &[u8]) -> bool {
+        self.entries.contains_key(key) || contains_prefix_of(&self.deleted_prefixes, key)
+    }
+
+    /// Merges the key-value pairs found under `key_prefix` in the underlying store with
+    /// those of the fork. The prefix is not included in the keys.
+    fn merge_key_values(
+        &self,
+        key_prefix: &[u8],
+        base_key_values: Vec<(Vec<u8>, Vec<u8>)>,
+    ) -> Vec<(Vec<u8>, Vec<u8>)> {
+        let mut key_values = BTreeMap::new();
+        for (suffix, value) in base_key_values {
+            if !self.overrides(&[key_prefix, &suffix].concat()) {
+                key_values.insert(suffix, value);
+            }
+        }
+        for (key, value) in self.entries.range(get_interval(key_prefix.to_vec())) {
+            if let Some(value) = value {
+                key_values.insert(key[key_prefix.len()..].to_vec(), value.clone());
+            }
+        }
+        key_values.into_iter().collect()
+    }
+
+    /// Merges the keys found under `key_prefix` in the underlying store with those of the
+    /// fork. The prefix is not included in the keys.
+    fn merge_keys(&self, key_prefix: &[u8], base_keys: Vec<Vec<u8>>) -> Vec<Vec<u8>> {
+        let mut keys = base_keys
+            .into_iter()
+            .filter(|suffix| !self.overrides(&[key_prefix, suffix].concat()))
+            .collect::<BTreeSet<_>>();
+        for (key, value) in self.entries.range(get_interval(key_prefix.to_vec())) {
+            if value.is_some() {
+                keys.insert(key[key_prefix.len()..].to_vec());
+            }
+        }
+        keys.into_iter().collect()
+    }
+
+    fn apply(&mut self, batch: Batch) {
+        for operation in batch.operations {
+            match operation {
+                WriteOperation::Put { key, value } => {
+                    self.entries.insert(key, Some(value));
+                }
+                WriteOperation::Delete { key } => {
+                    self.entries.insert(key, None);
+                }
+                WriteOperation::DeletePrefix { key_prefix } => {
+                    let keys = self
+                        .entries
+                        .range(get_interval(key_prefix.clone()))
+                        .map(|(key, _)| key.clone())
+                        .collect::<Vec<_>>();
+                    for key in keys {
+                        self.entries.remove(&key);
+                    }
+                    insert_key_prefix(&mut self.deleted_prefixes, key_prefix);
+                }
+            }
+        }
+    }
+}
+
+/// A key-value store whose writes are kept in memory on top of a read-only underlying
+/// store. Clones share the same overlay.
+#[derive(Clone)]
+pub struct ForkStore<S> {
+    /// The underlying store.
+    store: S,
+    /// The changes written to the fork.
+    overlay: Arc<Mutex<ForkOverlay>>,
+}
+
+impl<S> ForkStore<S> {
+    /// Creates a fork of the given store, initially without any changes.
+    pub fn new(store: S) -> Self {
+        Self {
+            store,
+            overlay: Arc::default(),
+        }
+    }
+
+    /// Returns whether anything was written to the fork.
+    pub fn has_changes(&self) -> bool {
+        let overlay = self.overlay.lock().unwrap();
+        !overlay.entries.is_empty() || !overlay.deleted_prefixes.is_empty()
+    }
+}
+
+impl<S> WithError for ForkStore<S>
+where
+    S: WithError,
+{
+    type Error = S::Error;
+}
+
+impl<S> ReadableKeyValueStore for ForkStore<S>
+where
+    S: ReadableKeyValueStore,
+{
+    const MAX_KEY_SIZE: usize = S::MAX_KEY_SIZE;
+
+    fn max_stream_queries(&self) -> usize {
+        self.store.max_stream_queries()
+    }
+
+    async fn read_value_bytes(&self, key: &[u8]) -> Result<Option<Vec<u8>>, Self::Error> {
+        let value = self.overlay.lock().unwrap().get(key);
+        if let Some(value) = value {
+            return Ok(value);
+        }
+        self.store.read_value_bytes(key).await
+    }
+
+    async fn contains_key(&self, key: &[u8]) -> Result<bool, Self::Error> {
+        let value = self.overlay.lock().unwrap().get(key);
+        if let Some(value) = value {
+            return Ok(value.is_some());
+        }
+        self.store.contains_key(key).await
+    }
+
+    async fn contains_keys(&self, keys: Vec<Vec<u8>>) -> Result<Vec<bool>, Self::Error> {
+        let mut results = Vec::with_capacity(keys.len());
+        let mut missing_indices = Vec::new();
+        let mut missing_keys = Vec::new();
+        {
+            let overlay = self.overlay.lock().unwrap();
+            for (index, key) in keys.into_iter().enumerate() {
+                match overlay.get(&key) {
+                    Some(value) => results.push(value.is_some()),
+                    None => {
+                        results.push(false);
+                        missing_indices.push(index);
+                        missing_keys.push(key);
+                    }
+                }
+            }
+        }
+        if !missing_keys.is_empty() {
+            let missing_results = self.store.contains_keys(missing_keys).await?;
+            for (index, result) in missing_indices.into_iter().zip(missing_results) {
+                results[index] = result;
+            }
+        }
+        Ok(results)
+    }
+
+    async fn read_multi_values_bytes(
+        &self,
+        keys: Vec<Vec<u8>>,
+    ) -> Result<Vec<Option<Vec<u8>>>, Self::Error> {
+        let mut values = Vec::with_capacity(keys.len());
+        let mut missing_indices = Vec::new();
+        let mut missing_keys = Vec::new();
+        {
+            let overlay = self.overlay.lock().unwrap();
+            for (index, key) in keys.into_iter().enumerate() {
+                match overlay.get(&key) {
+                    Some(value) => values.push(value),
+                    None => {
+                        values.push(None);
+                        missing_indices.push(index);
+                        missing_keys.push(key);
+                    }
+                }
+            }
+        }
+        if !missing_keys.is_empty() {
+            let missing_values = self.store.read_multi_values_bytes(missing_keys).await?;
+            for (index, value) in missing_indices.into_iter().zip(missing_values) {
+                values[index] = value;
+            }
+        }
+        Ok(values)
+    }
+
+    async fn find_keys_by_prefix(&self, key_prefix: &[u8]) -> Result<Vec<Vec<u8>>, Self::Error> {
+        let hidden = self.overlay.lock().unwrap().hides_prefix(key_prefix);
+        let base_keys = if hidden {
+            Vec::new()
+        } else {
+            self.store.find_keys_by_prefix(key_prefix).await?
+        };
+        let overlay = self.overlay.lock().unwrap();
+        Ok(overlay.merge_keys(key_prefix, base_keys))
+    }
+
+    async fn find_key_values_by_prefix(
+        &self,
+        key_prefix: &[u8],
+    ) -> Result<Vec<(Vec<u8>, Vec<u8>)>, Self::Error> {
+        let hidden = self.overlay.lock().unwrap().hides_prefix(key_prefix);
+        let base_key_values = if hidden {
+            Vec::new()
+        } else {
+            self.store.find_key_values_by_prefix(key_prefix).await?
+        };
+        let overlay = self.overlay.lock().unwrap();
+        Ok(overlay.merge_key_values(key_prefix, base_key_values))
+    }
+
+    // The budget applies to the scan of the underlying store and to the merged result, so
+    // that keys hidden by the fork still count.
+
+    async fn find_keys_by_prefix_within(
+        &self,
+        key_prefix: &[u8],
+        budget: ScanBudget,
+    ) -> Result<Option<Vec<Vec<u8>>>, Self::Error> {
+        let hidden = self.overlay.lock().unwrap().hides_prefix(key_prefix);
+        let base_keys = if hidden {
+            Vec::new()
+        } else {
+            let keys = self
+                .store
+                .find_keys_by_prefix_within(key_prefix, budget)
+                .await?;
+            let Some(keys) = keys else {
+                return Ok(None);
+            };
+            keys
+        };
+        let keys = self
+            .overlay
+            .lock()
+            .unwrap()
+            .merge_keys(key_prefix, base_keys);
+        Ok(budget.admits(&keys, Vec::len).then_some(keys))
+    }
+
+    async fn find_key_values_by_prefix_within(
+        &self,
+        key_prefix: &[u8],
+        budget: ScanBudget,
+    ) -> Result<Option<Vec<(Vec<u8>, Vec<u8>)>>, Self::Error> {
+        let hidden = self.overlay.lock().unwrap().hides_prefix(key_prefix);
+        let base_key_values = if hidden {
+            Vec::new()
+        } else {
+            let key_values = self
+                .store
+                .find_key_values_by_prefix_within(key_prefix, budget)
+                .await?;
+            let Some(key_values) = key_values else {
+                return Ok(None);
+            };
+            key_values
+        };
+        let key_values = self
+            .overlay
+            .lock()
+            .unwrap()
+            .merge_key_values(key_prefix, base_key_values);
+        let admitted = budget.admits(&key_values, |(key, value)| key.len() + value.len());
+        Ok(admitted.then_some(key_values))
+    }
+}
+
+impl<S> WritableKeyValueStore for ForkStore<S>
+where
+    S: WritableKeyValueStore,
+{
+    // Values are never written to the underlying store, but keeping its limit lets views
+    // behave exactly as they would on it.
+    const MAX_VALUE_SIZE: usize = S::MAX_VALUE_SIZE;
+
+    async fn write_batch(&self, batch: Batch) -> Result<(), Self::Error> {
+        self.overlay.lock().unwrap().apply(batch);
+        Ok(())
+    }
+
+    async fn clear_journal(&self) -> Result<(), Self::Error> {
+        // Nothing is ever written to the underlying store, so there is no journal.
+        Ok(())
+    }
+}
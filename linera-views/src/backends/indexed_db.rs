@@ -12,10 +12,10 @@ use thiserror::Error;
 
 use crate::{
     batch::{Batch, WriteOperation},
-    common::get_upper_bound_option,
+    common::{get_upper_bound_option, NamespaceError, NamespaceRules},
     store::{
-        KeyValueDatabase, KeyValueStoreError, ReadableKeyValueStore, WithError,
-        WritableKeyValueStore,
+        sample_namespace_stats, KeyValueDatabase, KeyValueStoreError, NamespaceStats,
+        ReadableKeyValueStore, WithError, WritableKeyValueStore,
     },
 };
 
@@ -261,7 +261,7 @@ impl KeyValueDatabase for IndexedDbDatabase {
     }
 
     async fn connect(config: &Self::Config, namespace: &str) -> Result<Self, IndexedDbStoreError> {
-        let namespace = namespace.to_string();
+        let namespace = NamespaceRules::INDEXED_DB.escape(namespace)?;
         let object_store_name = namespace.clone();
         let mut database = IdbDatabase::open(DATABASE_NAME)?.await?;
 
@@ -298,6 +298,7 @@ impl KeyValueDatabase for IndexedDbDatabase {
             .await?
             .database
             .object_store_names()
+            .filter_map(|name| NamespaceRules::INDEXED_DB.unescape(&name).ok())
             .collect())
     }
 
@@ -312,6 +313,7 @@ impl KeyValueDatabase for IndexedDbDatabase {
     }
 
     async fn exists(config: &Self::Config, namespace: &str) -> Result<bool, IndexedDbStoreError> {
+        let namespace = NamespaceRules::INDEXED_DB.escape(namespace)?;
         Ok(Self::connect(config, "")
             .await?
             .database
@@ -320,18 +322,26 @@ impl KeyValueDatabase for IndexedDbDatabase {
     }
 
     async fn create(config: &Self::Config, namespace: &str) -> Result<(), IndexedDbStoreError> {
+        let namespace = NamespaceRules::INDEXED_DB.escape(namespace)?;
         Self::connect(config, "")
             .await?
             .database
-            .create_object_store(namespace)?;
+            .create_object_store(&namespace)?;
         Ok(())
     }
 
     async fn delete(config: &Self::Config, namespace: &str) -> Result<(), IndexedDbStoreError> {
+        let namespace = NamespaceRules::INDEXED_DB.escape(namespace)?;
         Ok(Self::connect(config, "")
             .await?
             .database
-            .delete_object_store(namespace)?)
+            .delete_object_store(&namespace)?)
+    }
+
+    async fn namespace_stats(&self) -> Result<NamespaceStats, IndexedDbStoreError> {
+        let store = self.open_internal(STORED_ROOT_KEYS_PREFIX.to_vec())?;
+        let root_keys = store.find_keys_by_prefix(&[]).await?;
+        sample_namespace_stats(self, &root_keys).await
     }
 }
 
@@ -376,6 +386,10 @@ pub enum IndexedDbStoreError {
     /// JavaScript threw an exception whilst handling IndexedDB operations
     #[error("JavaScript exception: {0:?}")]
     Js(gloo_utils::errors::JsError),
+
+    /// The namespace cannot be mapped to an object store name
+    #[error(transparent)]
+    InvalidNamespace(#[from] NamespaceError),
 }
 
 impl From<web_sys::DomException> for IndexedDbStoreError {
@@ -25,9 +25,10 @@ use thiserror::Error;
 
 use crate::{
     batch::{Batch, BatchValueWriter, DeletePrefixExpander, SimplifiedBatch},
+    context::ScanBudget,
     store::{
-        DirectKeyValueStore, KeyValueDatabase, ReadableKeyValueStore, WithError,
-        WritableKeyValueStore,
+        DirectKeyValueStore, KeyValueDatabase, NamespaceStats, ReadableKeyValueStore, RequestClass,
+        WithError, WritableKeyValueStore,
     },
     views::MIN_VIEW_TAG,
 };
@@ -68,8 +69,10 @@ sa::const_assert!(JOURNAL_TAG < MIN_VIEW_TAG);
 enum KeyTag {
     /// Prefix for the storing of the header of the journal.
     Journal = 1,
-    /// Prefix for the block entry.
+    /// Prefix for the block entry, in the legacy serialization format of the batches.
     Entry,
+    /// Prefix for the block entry, in the current serialization format of the batches.
+    VersionedEntry,
 }
 
 fn get_journaling_key(tag: u8, pos: u32) -> Result<Vec<u8>, bcs::Error> {
@@ -152,6 +155,26 @@ where
     ) -> Result<Vec<(Vec<u8>, Vec<u8>)>, Self::Error> {
         self.store.find_key_values_by_prefix(key_prefix).await
     }
+
+    async fn find_keys_by_prefix_within(
+        &self,
+        key_prefix: &[u8],
+        budget: ScanBudget,
+    ) -> Result<Option<Vec<Vec<u8>>>, Self::Error> {
+        self.store
+            .find_keys_by_prefix_within(key_prefix, budget)
+            .await
+    }
+
+    async fn find_key_values_by_prefix_within(
+        &self,
+        key_prefix: &[u8],
+        budget: ScanBudget,
+    ) -> Result<Option<Vec<(Vec<u8>, Vec<u8>)>>, Self::Error> {
+        self.store
+            .find_key_values_by_prefix_within(key_prefix, budget)
+            .await
+    }
 }
 
 impl<D> KeyValueDatabase for JournalingKeyValueDatabase<D>
@@ -171,7 +194,15 @@ where
     }
 
     fn open_shared(&self, root_key: &[u8]) -> Result<Self::Store, Self::Error> {
-        let store = self.database.open_shared(root_key)?;
+        self.open_shared_for(root_key, RequestClass::Default)
+    }
+
+    fn open_shared_for(
+        &self,
+        root_key: &[u8],
+        class: RequestClass,
+    ) -> Result<Self::Store, Self::Error> {
+        let store = self.database.open_shared_for(root_key, class)?;
         Ok(JournalingKeyValueStore {
             store,
             has_exclusive_access: false,
@@ -212,6 +243,10 @@ where
     async fn delete(config: &Self::Config, namespace: &str) -> Result<(), Self::Error> {
         D::delete(config, namespace).await
     }
+
+    async fn namespace_stats(&self) -> Result<NamespaceStats, Self::Error> {
+        self.database.namespace_stats().await
+    }
 }
 
 impl<S> WritableKeyValueStore for JournalingKeyValueStore<S>
@@ -273,13 +308,8 @@ where
     async fn coherently_resolve_journal(&self, mut header: JournalHeader) -> Result<(), S::Error> {
         let header_key = get_journaling_key(KeyTag::Journal as u8, 0)?;
         while header.block_count > 0 {
-            let block_key = get_journaling_key(KeyTag::Entry as u8, header.block_count - 1)?;
-            // Read the batch of updates (aka. "block") previously saved in the journal.
-            let mut batch = self
-                .store
-                .read_value::<S::Batch>(&block_key)
-                .await?
-                .ok_or(JournalConsistencyError::FailureToRetrieveJournalBlock)?;
+            let pos = header.block_count - 1;
+            let (block_key, mut batch) = self.read_journal_block(pos).await?;
             // Execute the block and delete it from the journal atomically.
             batch.add_delete(block_key);
             header.block_count -= 1;
@@ -294,6 +324,26 @@ where
         Ok(())
     }
 
+    /// Reads the batch of updates (aka. "block") previously saved in the journal at the
+    /// given position, and returns it together with its key.
+    ///
+    /// A journal written before an upgrade stores its blocks under `KeyTag::Entry`, in the
+    /// legacy serialization format of `S::Batch`.
+    async fn read_journal_block(&self, pos: u32) -> Result<(Vec<u8>, S::Batch), S::Error> {
+        let block_key = get_journaling_key(KeyTag::VersionedEntry as u8, pos)?;
+        if let Some(batch) = self.store.read_value::<S::Batch>(&block_key).await? {
+            return Ok((block_key, batch));
+        }
+        let block_key = get_journaling_key(KeyTag::Entry as u8, pos)?;
+        let bytes = self
+            .store
+            .read_value_bytes(&block_key)
+            .await?
+            .ok_or(JournalConsistencyError::FailureToRetrieveJournalBlock)?;
+        let batch = S::Batch::from_legacy_bytes(&bytes)?;
+        Ok((block_key, batch))
+    }
+
     /// Writes the content of `batch` to the journal as a succession of blocks that can be
     /// interpreted later by `coherently_resolve_journal`.
     ///
@@ -373,7 +423,7 @@ where
                 let value = bcs::to_bytes(&block_batch)?;
                 block_batch = S::Batch::default();
                 assert_eq!(value.len(), block_size);
-                let key = get_journaling_key(KeyTag::Entry as u8, block_count)?;
+                let key = get_journaling_key(KeyTag::VersionedEntry as u8, block_count)?;
                 transaction_batch.add_insert(key, value);
                 block_count += 1;
                 transaction_size += block_size + key_len;
@@ -4,7 +4,7 @@
 //! Add LRU (least recently used) caching to a given store.
 
 use std::{
-    collections::{btree_map, hash_map::RandomState, BTreeMap},
+    collections::{btree_map, hash_map::RandomState, BTreeMap, BTreeSet},
     sync::{Arc, Mutex},
 };
 
@@ -18,7 +18,11 @@ use crate::store::TestKeyValueDatabase;
 use crate::{
     batch::{Batch, WriteOperation},
     common::get_interval,
-    store::{KeyValueDatabase, ReadableKeyValueStore, WithError, WritableKeyValueStore},
+    context::ScanBudget,
+    store::{
+        KeyValueDatabase, NamespaceStats, ReadableKeyValueStore, RequestClass, WithError,
+        WritableKeyValueStore,
+    },
 };
 
 #[cfg(with_metrics)]
@@ -111,6 +115,8 @@ struct LruPrefixCache {
     total_size: usize,
     /// Whether we have exclusive R/W access to the keys under the root key of the store.
     has_exclusive_access: bool,
+    /// The keys read since reads started being recorded, if they are.
+    recorded_reads: Option<BTreeSet<Vec<u8>>>,
 }
 
 impl LruPrefixCache {
@@ -122,9 +128,28 @@ impl LruPrefixCache {
             config,
             total_size: 0,
             has_exclusive_access,
+            recorded_reads: None,
         }
     }
 
+    /// Records a read of the given key, if reads are being recorded. Since prefetching
+    /// more keys than the cache can hold is pointless, so is recording them.
+    fn record_read(&mut self, key: &[u8]) {
+        if let Some(keys) = &mut self.recorded_reads {
+            if keys.len() < self.config.max_cache_entries {
+                keys.insert(key.to_vec());
+            }
+        }
+    }
+
+    /// Returns whether reading the value of the given key would be a cache hit.
+    fn has_value(&self, key: &[u8]) -> bool {
+        matches!(
+            self.map.get(key),
+            Some(CacheEntry::Value(_) | CacheEntry::DoesNotExist)
+        )
+    }
+
     /// Trim the cache so that it fits within the constraints.
     fn trim_cache(&mut self) {
         while self.total_size > self.config.max_cache_size
@@ -298,6 +323,7 @@ where
         // First inquiring in the read_value_bytes LRU
         {
             let mut cache = cache.lock().unwrap();
+            cache.record_read(key);
             if let Some(value) = cache.query_read_value(key) {
                 #[cfg(with_metrics)]
                 metrics::READ_VALUE_CACHE_HIT_COUNT
@@ -322,6 +348,7 @@ where
         };
         {
             let mut cache = cache.lock().unwrap();
+            cache.record_read(key);
             if let Some(value) = cache.query_contains_key(key) {
                 #[cfg(with_metrics)]
                 metrics::CONTAINS_KEY_CACHE_HIT_COUNT
@@ -351,6 +378,7 @@ where
         {
             let mut cache = cache.lock().unwrap();
             for i in 0..size {
+                cache.record_read(&keys[i]);
                 if let Some(value) = cache.query_contains_key(&keys[i]) {
                     #[cfg(with_metrics)]
                     metrics::CONTAINS_KEY_CACHE_HIT_COUNT
@@ -392,6 +420,7 @@ where
         {
             let mut cache = cache.lock().unwrap();
             for (i, key) in keys.into_iter().enumerate() {
+                cache.record_read(&key);
                 if let Some(value) = cache.query_read_value(&key) {
                     #[cfg(with_metrics)]
                     metrics::READ_VALUE_CACHE_HIT_COUNT
@@ -436,6 +465,67 @@ where
     ) -> Result<Vec<(Vec<u8>, Vec<u8>)>, Self::Error> {
         self.store.find_key_values_by_prefix(key_prefix).await
     }
+
+    async fn find_keys_by_prefix_within(
+        &self,
+        key_prefix: &[u8],
+        budget: ScanBudget,
+    ) -> Result<Option<Vec<Vec<u8>>>, Self::Error> {
+        self.store
+            .find_keys_by_prefix_within(key_prefix, budget)
+            .await
+    }
+
+    async fn find_key_values_by_prefix_within(
+        &self,
+        key_prefix: &[u8],
+        budget: ScanBudget,
+    ) -> Result<Option<Vec<(Vec<u8>, Vec<u8>)>>, Self::Error> {
+        self.store
+            .find_key_values_by_prefix_within(key_prefix, budget)
+            .await
+    }
+
+    fn start_recording_reads(&self) {
+        if let Some(cache) = &self.cache {
+            cache.lock().unwrap().recorded_reads = Some(BTreeSet::new());
+        }
+    }
+
+    fn take_recorded_reads(&self) -> Vec<Vec<u8>> {
+        let Some(cache) = &self.cache else {
+            return Vec::new();
+        };
+        let keys = cache.lock().unwrap().recorded_reads.take();
+        keys.unwrap_or_default().into_iter().collect()
+    }
+
+    async fn prefetch(&self, keys: Vec<Vec<u8>>) -> Result<(), Self::Error> {
+        let Some(cache) = &self.cache else {
+            return Ok(());
+        };
+        let missing_keys = {
+            let cache = cache.lock().unwrap();
+            keys.into_iter()
+                .filter(|key| !cache.has_value(key))
+                .collect::<Vec<_>>()
+        };
+        if missing_keys.is_empty() {
+            return Ok(());
+        }
+        let values = self
+            .store
+            .read_multi_values_bytes(missing_keys.clone())
+            .await?;
+        let mut cache = cache.lock().unwrap();
+        for (key, value) in missing_keys.into_iter().zip(values) {
+            // Don't overwrite what was written in the meantime.
+            if !cache.has_value(&key) {
+                cache.insert_read_value(key, &value);
+            }
+        }
+        Ok(())
+    }
 }
 
 impl<K> WritableKeyValueStore for LruCachingStore<K>
@@ -506,7 +596,15 @@ where
     }
 
     fn open_shared(&self, root_key: &[u8]) -> Result<Self::Store, Self::Error> {
-        let store = self.database.open_shared(root_key)?;
+        self.open_shared_for(root_key, RequestClass::Default)
+    }
+
+    fn open_shared_for(
+        &self,
+        root_key: &[u8],
+        class: RequestClass,
+    ) -> Result<Self::Store, Self::Error> {
+        let store = self.database.open_shared_for(root_key, class)?;
         let store = LruCachingStore::new(
             store,
             self.config.clone(),
@@ -551,6 +649,10 @@ where
     async fn delete(config: &Self::Config, namespace: &str) -> Result<(), Self::Error> {
         D::delete(&config.inner_config, namespace).await
     }
+
+    async fn namespace_stats(&self) -> Result<NamespaceStats, Self::Error> {
+        self.database.namespace_stats().await
+    }
 }
 
 impl<S> LruCachingStore<S> {
@@ -2,9 +2,14 @@
 // SPDX-License-Identifier: Apache-2.0
 
 //! Implements [`crate::store::KeyValueDatabase`] in memory.
+//!
+//! The contents of all namespaces can be saved to a single file with
+//! [`save_memory_snapshot`] and restored, e.g. by the next run of the same process, with
+//! [`load_memory_snapshot`].
 
 use std::{
     collections::BTreeMap,
+    path::{Path, PathBuf},
     sync::{Arc, LazyLock, Mutex, RwLock},
 };
 
@@ -16,8 +21,9 @@ use crate::store::TestKeyValueDatabase;
 use crate::{
     batch::{Batch, WriteOperation},
     common::get_interval,
+    context::{ScanBudget, ScanCounter},
     store::{
-        KeyValueDatabase, KeyValueStoreError, ReadableKeyValueStore, WithError,
+        KeyValueDatabase, KeyValueStoreError, NamespaceStats, ReadableKeyValueStore, WithError,
         WritableKeyValueStore,
     },
 };
@@ -88,6 +94,29 @@ impl MemoryDatabases {
         }
     }
 
+    fn sync_namespace_stats(&self, namespace: &str) -> NamespaceStats {
+        let mut key_count = 0;
+        let mut total_bytes = 0;
+        for map in self
+            .databases
+            .get(namespace)
+            .into_iter()
+            .flat_map(BTreeMap::values)
+        {
+            let map = map.read().expect("MemoryStore lock should not be poisoned");
+            key_count += map.len() as u64;
+            total_bytes += map
+                .iter()
+                .map(|(key, value)| (key.len() + value.len()) as u64)
+                .sum::<u64>();
+        }
+        NamespaceStats {
+            key_count: Some(key_count),
+            total_bytes,
+            estimated: false,
+        }
+    }
+
     fn sync_exists(&self, namespace: &str) -> bool {
         self.databases.contains_key(namespace)
     }
@@ -100,6 +129,44 @@ impl MemoryDatabases {
     fn sync_delete(&mut self, namespace: &str) {
         self.databases.remove(namespace);
     }
+
+    fn snapshot(&self) -> MemorySnapshot {
+        let namespaces = self
+            .databases
+            .iter()
+            .map(|(namespace, stores)| {
+                let stores = stores
+                    .iter()
+                    .map(|(root_key, map)| {
+                        let map = map
+                            .read()
+                            .expect("MemoryStore lock should not be poisoned")
+                            .clone();
+                        (root_key.clone(), map)
+                    })
+                    .collect();
+                (namespace.clone(), stores)
+            })
+            .collect();
+        MemorySnapshot { namespaces }
+    }
+
+    fn restore(&mut self, snapshot: MemorySnapshot) {
+        for (namespace, stores) in snapshot.namespaces {
+            let stores = stores
+                .into_iter()
+                .map(|(root_key, map)| (root_key, Arc::new(RwLock::new(map))))
+                .collect();
+            self.databases.insert(namespace, stores);
+        }
+    }
+}
+
+/// The contents of the in-memory databases, as saved to a snapshot file.
+#[derive(Serialize, Deserialize)]
+struct MemorySnapshot {
+    /// The key-values of each namespace, by root key.
+    namespaces: BTreeMap<String, BTreeMap<Vec<u8>, MemoryStoreMap>>,
 }
 
 /// The global table of namespaces.
@@ -204,6 +271,48 @@ impl ReadableKeyValueStore for MemoryStore {
         }
         Ok(key_values)
     }
+
+    async fn find_keys_by_prefix_within(
+        &self,
+        key_prefix: &[u8],
+        budget: ScanBudget,
+    ) -> Result<Option<Vec<Vec<u8>>>, MemoryStoreError> {
+        let map = self
+            .map
+            .read()
+            .expect("MemoryStore lock should not be poisoned");
+        let mut counter = ScanCounter::new(budget);
+        let mut keys = Vec::new();
+        let len = key_prefix.len();
+        for (key, _value) in map.range(get_interval(key_prefix.to_vec())) {
+            if !counter.record(key.len() - len) {
+                return Ok(None);
+            }
+            keys.push(key[len..].to_vec())
+        }
+        Ok(Some(keys))
+    }
+
+    async fn find_key_values_by_prefix_within(
+        &self,
+        key_prefix: &[u8],
+        budget: ScanBudget,
+    ) -> Result<Option<Vec<(Vec<u8>, Vec<u8>)>>, MemoryStoreError> {
+        let map = self
+            .map
+            .read()
+            .expect("MemoryStore lock should not be poisoned");
+        let mut counter = ScanCounter::new(budget);
+        let mut key_values = Vec::new();
+        let len = key_prefix.len();
+        for (key, value) in map.range(get_interval(key_prefix.to_vec())) {
+            if !counter.record(key.len() - len + value.len()) {
+                return Ok(None);
+            }
+            key_values.push((key[len..].to_vec(), value.to_vec()));
+        }
+        Ok(Some(key_values))
+    }
 }
 
 impl WritableKeyValueStore for MemoryStore {
@@ -339,6 +448,56 @@ impl KeyValueDatabase for MemoryDatabase {
         databases.sync_delete(namespace);
         Ok(())
     }
+
+    async fn namespace_stats(&self) -> Result<NamespaceStats, MemoryStoreError> {
+        let databases = MEMORY_DATABASES
+            .lock()
+            .expect("MEMORY_DATABASES lock should not be poisoned");
+        Ok(databases.sync_namespace_stats(&self.namespace))
+    }
+}
+
+/// Saves all the namespaces of the in-memory databases to the file at `path`.
+///
+/// The snapshot is written to a temporary file first, so that an existing snapshot is
+/// only replaced once the new one is complete.
+pub fn save_memory_snapshot(path: &Path) -> Result<(), MemoryStoreError> {
+    let snapshot = MEMORY_DATABASES
+        .lock()
+        .expect("MEMORY_DATABASES lock should not be poisoned")
+        .snapshot();
+    let bytes = bcs::to_bytes(&snapshot)?;
+    let temporary_path = path.with_extension("tmp");
+    std::fs::write(&temporary_path, bytes)?;
+    std::fs::rename(temporary_path, path)?;
+    Ok(())
+}
+
+/// Restores the namespaces saved by [`save_memory_snapshot`] to the file at `path`,
+/// replacing any existing namespaces with the same names. Returns `false` if there is no
+/// such file.
+pub fn load_memory_snapshot(path: &Path) -> Result<bool, MemoryStoreError> {
+    let bytes = match std::fs::read(path) {
+        Ok(bytes) => bytes,
+        Err(error) if error.kind() == std::io::ErrorKind::NotFound => return Ok(false),
+        Err(error) => {
+            return Err(MemoryStoreError::UnreadableSnapshot {
+                path: path.to_path_buf(),
+                error,
+            })
+        }
+    };
+    let snapshot = bcs::from_bytes::<MemorySnapshot>(&bytes).map_err(|error| {
+        MemoryStoreError::InvalidSnapshot {
+            path: path.to_path_buf(),
+            error,
+        }
+    })?;
+    MEMORY_DATABASES
+        .lock()
+        .expect("MEMORY_DATABASES lock should not be poisoned")
+        .restore(snapshot);
+    Ok(true)
 }
 
 #[cfg(with_testing)]
@@ -365,6 +524,30 @@ pub enum MemoryStoreError {
     /// The namespace does not exist
     #[error("The namespace does not exist")]
     NamespaceNotFound,
+
+    /// An I/O error while saving a snapshot.
+    #[error(transparent)]
+    IoError(#[from] std::io::Error),
+
+    /// The snapshot file could not be read.
+    #[error("Failed to read the memory snapshot {}: {error}", path.display())]
+    UnreadableSnapshot {
+        /// The path of the snapshot file.
+        path: PathBuf,
+        /// The I/O error.
+        #[source]
+        error: std::io::Error,
+    },
+
+    /// The snapshot file is truncated or corrupt.
+    #[error("Invalid memory snapshot {}: {error}", path.display())]
+    InvalidSnapshot {
+        /// The path of the snapshot file.
+        path: PathBuf,
+        /// The deserialization error.
+        #[source]
+        error: bcs::Error,
+    },
 }
 
 impl KeyValueStoreError for MemoryStoreError {
@@ -10,15 +10,19 @@ use std::{
 
 use convert_case::{Case, Casing};
 use linera_base::prometheus_util::{
-    register_histogram_vec, register_int_counter_vec, MeasureLatency as _,
+    register_histogram_vec, register_int_counter_vec, register_int_gauge_vec, MeasureLatency as _,
 };
-use prometheus::{HistogramVec, IntCounterVec};
+use prometheus::{HistogramVec, IntCounterVec, IntGaugeVec};
 
 #[cfg(with_testing)]
 use crate::store::TestKeyValueDatabase;
 use crate::{
     batch::Batch,
-    store::{KeyValueDatabase, ReadableKeyValueStore, WithError, WritableKeyValueStore},
+    context::ScanBudget,
+    store::{
+        KeyValueDatabase, NamespaceStats, ReadableKeyValueStore, RequestClass, WithError,
+        WritableKeyValueStore,
+    },
 };
 
 #[derive(Clone)]
@@ -41,6 +45,7 @@ pub struct KeyValueStoreMetrics {
     exists_latency: HistogramVec,
     create_latency: HistogramVec,
     delete_latency: HistogramVec,
+    namespace_stats_latency: HistogramVec,
     read_value_none_cases: IntCounterVec,
     read_value_key_size: HistogramVec,
     read_value_value_size: HistogramVec,
@@ -58,6 +63,8 @@ pub struct KeyValueStoreMetrics {
     write_batch_size: HistogramVec,
     list_all_sizes: HistogramVec,
     exists_true_cases: IntCounterVec,
+    namespace_key_count: IntGaugeVec,
+    namespace_total_bytes: IntGaugeVec,
 }
 
 #[derive(Default)]
@@ -160,6 +167,10 @@ impl KeyValueStoreMetrics {
         let entry2 = format!("{} delete latency", title_name);
         let delete_latency = register_histogram_vec(&entry1, &entry2, &[], None);
 
+        let entry1 = format!("{}_namespace_stats_latency", var_name);
+        let entry2 = format!("{} namespace stats latency", title_name);
+        let namespace_stats_latency = register_histogram_vec(&entry1, &entry2, &[], None);
+
         let entry1 = format!("{}_read_value_none_cases", var_name);
         let entry2 = format!("{} read value none cases", title_name);
         let read_value_none_cases = register_int_counter_vec(&entry1, &entry2, &[]);
@@ -231,6 +242,14 @@ impl KeyValueStoreMetrics {
         let entry2 = format!("{} exists true cases", title_name);
         let exists_true_cases = register_int_counter_vec(&entry1, &entry2, &[]);
 
+        let entry1 = format!("{}_namespace_key_count", var_name);
+        let entry2 = format!("{} namespace key count", title_name);
+        let namespace_key_count = register_int_gauge_vec(&entry1, &entry2, &["namespace"]);
+
+        let entry1 = format!("{}_namespace_total_bytes", var_name);
+        let entry2 = format!("{} namespace total bytes", title_name);
+        let namespace_total_bytes = register_int_gauge_vec(&entry1, &entry2, &["namespace"]);
+
         KeyValueStoreMetrics {
             read_value_bytes_latency,
             contains_key_latency,
@@ -249,6 +268,7 @@ impl KeyValueStoreMetrics {
             exists_latency,
             create_latency,
             delete_latency,
+            namespace_stats_latency,
             read_value_none_cases,
             read_value_key_size,
             read_value_value_size,
@@ -266,6 +286,8 @@ impl KeyValueStoreMetrics {
             write_batch_size,
             list_all_sizes,
             exists_true_cases,
+            namespace_key_count,
+            namespace_total_bytes,
         }
     }
 }
@@ -277,6 +299,8 @@ pub struct MeteredDatabase<D> {
     counter: Arc<KeyValueStoreMetrics>,
     /// The underlying database.
     database: D,
+    /// The namespace, to label the metrics of its size.
+    namespace: String,
 }
 
 /// A metered store that keeps track of every operation.
@@ -426,6 +450,43 @@ where
             .observe(key_values_size as f64);
         Ok(result)
     }
+
+    fn start_recording_reads(&self) {
+        self.store.start_recording_reads()
+    }
+
+    fn take_recorded_reads(&self) -> Vec<Vec<u8>> {
+        self.store.take_recorded_reads()
+    }
+
+    async fn prefetch(&self, keys: Vec<Vec<u8>>) -> Result<(), Self::Error> {
+        self.store.prefetch(keys).await
+    }
+
+    async fn find_keys_by_prefix_within(
+        &self,
+        key_prefix: &[u8],
+        budget: ScanBudget,
+    ) -> Result<Option<Vec<Vec<u8>>>, Self::Error> {
+        let _latency = self.counter.find_keys_by_prefix_latency.measure_latency();
+        self.store
+            .find_keys_by_prefix_within(key_prefix, budget)
+            .await
+    }
+
+    async fn find_key_values_by_prefix_within(
+        &self,
+        key_prefix: &[u8],
+        budget: ScanBudget,
+    ) -> Result<Option<Vec<(Vec<u8>, Vec<u8>)>>, Self::Error> {
+        let _latency = self
+            .counter
+            .find_key_values_by_prefix_latency
+            .measure_latency();
+        self.store
+            .find_key_values_by_prefix_within(key_prefix, budget)
+            .await
+    }
 }
 
 impl<S> WritableKeyValueStore for MeteredStore<S>
@@ -466,12 +527,25 @@ where
         let _latency = counter.connect_latency.measure_latency();
         let database = D::connect(config, namespace).await?;
         let counter = get_counter(&name);
-        Ok(Self { counter, database })
+        let namespace = namespace.to_string();
+        Ok(Self {
+            counter,
+            database,
+            namespace,
+        })
     }
 
     fn open_shared(&self, root_key: &[u8]) -> Result<Self::Store, Self::Error> {
+        self.open_shared_for(root_key, RequestClass::Default)
+    }
+
+    fn open_shared_for(
+        &self,
+        root_key: &[u8],
+        class: RequestClass,
+    ) -> Result<Self::Store, Self::Error> {
         let _latency = self.counter.open_shared_latency.measure_latency();
-        let store = self.database.open_shared(root_key)?;
+        let store = self.database.open_shared_for(root_key, class)?;
         let counter = self.counter.clone();
         Ok(MeteredStore { counter, store })
     }
@@ -538,6 +612,23 @@ where
         let _latency = counter.delete_latency.measure_latency();
         D::delete(config, namespace).await
     }
+
+    async fn namespace_stats(&self) -> Result<NamespaceStats, Self::Error> {
+        let _latency = self.counter.namespace_stats_latency.measure_latency();
+        let stats = self.database.namespace_stats().await?;
+        let namespace = self.namespace.as_str();
+        if let Some(key_count) = stats.key_count {
+            self.counter
+                .namespace_key_count
+                .with_label_values(&[namespace])
+                .set(i64::try_from(key_count).unwrap_or(i64::MAX));
+        }
+        self.counter
+            .namespace_total_bytes
+            .with_label_values(&[namespace])
+            .set(i64::try_from(stats.total_bytes).unwrap_or(i64::MAX));
+        Ok(stats)
+    }
 }
 
 #[cfg(with_testing)]
@@ -12,6 +12,8 @@ pub mod memory;
 
 pub mod lru_caching;
 
+pub mod fork;
+
 pub mod dual;
 
 #[cfg(with_scylladb)]
@@ -25,3 +27,6 @@ pub mod dynamo_db;
 
 #[cfg(with_indexeddb)]
 pub mod indexed_db;
+
+#[cfg(with_opfs)]
+pub mod opfs;
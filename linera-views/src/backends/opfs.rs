@@ -0,0 +1,579 @@
+// Copyright (c) Zefchain Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! Implements [`crate::store::KeyValueStore`] for the Origin Private File System (OPFS)
+//! of Web browsers.
+//!
+//! Every namespace is a file of the [`DIRECTORY_NAME`] directory of the origin private
+//! file system, accessed through a synchronous access handle. Browsers only provide such
+//! handles to dedicated workers, so this store must be used from a worker.
+//!
+//! The file is a log of batches: each batch is appended to it when written and the
+//! whole log is replayed in memory when the namespace is opened. The log is compacted
+//! into a single batch once the replaced entries make up most of the file. The compacted log
+//! is first written to a second file, with the suffix [`COMPACTION_SUFFIX`], so that a
+//! compaction interrupted while the log is rewritten is completed when the namespace is
+//! opened again.
+
+use std::{
+    cell::RefCell,
+    collections::{BTreeMap, HashMap},
+    rc::{Rc, Weak},
+};
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use wasm_bindgen::JsCast as _;
+use wasm_bindgen_futures::JsFuture;
+use web_sys::{js_sys, wasm_bindgen};
+
+use crate::{
+    batch::{Batch, WriteOperation},
+    common::{get_interval, NamespaceError, NamespaceRules},
+    store::{
+        sample_namespace_stats, KeyValueDatabase, KeyValueStoreError, NamespaceStats,
+        ReadableKeyValueStore, WithError, WritableKeyValueStore,
+    },
+};
+
+/// The initial configuration of the system
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OpfsStoreConfig {
+    /// Preferred buffer size for async streams.
+    pub max_stream_queries: usize,
+}
+
+/// The prefixes being used in the system
+static ROOT_KEY_DOMAIN: [u8; 1] = [0];
+static STORED_ROOT_KEYS_PREFIX: [u8; 1] = [1];
+
+/// The number of streams for the test
+pub const TEST_OPFS_MAX_STREAM_QUERIES: usize = 10;
+
+/// The directory of the origin private file system containing the namespaces.
+pub const DIRECTORY_NAME: &str = "linera";
+
+/// The size of a log below which it is never compacted.
+const MIN_COMPACTION_SIZE: u64 = 1 << 20;
+
+/// The suffix of the file holding the compacted log of a namespace while it replaces the
+/// log. Escaped namespaces never contain a `.`, so this cannot be the file of a namespace.
+pub const COMPACTION_SUFFIX: &str = ".compacting";
+
+thread_local! {
+    /// The files that are currently open, by name. A file only accepts one synchronous
+    /// access handle at a time, so connections to the same namespace share it.
+    static OPEN_FILES: RefCell<HashMap<String, Weak<RefCell<OpfsFile>>>> =
+        RefCell::default();
+}
+
+struct OpfsFile {
+    /// The synchronous access handle of the file.
+    handle: web_sys::FileSystemSyncAccessHandle,
+    /// The synchronous access handle of the file holding the compacted log. It is empty
+    /// unless a compaction is in progress.
+    compaction_handle: web_sys::FileSystemSyncAccessHandle,
+    /// The key-value pairs of the namespace.
+    map: BTreeMap<Vec<u8>, Vec<u8>>,
+    /// The current size of the log.
+    size: u64,
+    /// The size of the log if it was compacted, as estimated from the key-value pairs.
+    live_size: u64,
+}
+
+/// A browser implementation of a key-value store using the [Origin Private File
+/// System](https://developer.mozilla.org/en-US/docs/Web/API/File_System_API/Origin_private_file_system).
+pub struct OpfsDatabase {
+    /// The file of the namespace.
+    file: Rc<RefCell<OpfsFile>>,
+    /// The maximum number of queries used for the stream.
+    pub max_stream_queries: usize,
+}
+
+/// A logical partition of [`OpfsDatabase`]
+pub struct OpfsStore {
+    /// The file of the namespace.
+    file: Rc<RefCell<OpfsFile>>,
+    /// The maximum number of queries used for the stream.
+    pub max_stream_queries: usize,
+    /// The key being used at the start of the writing
+    start_key: Vec<u8>,
+}
+
+/// Returns the whole content of a file.
+fn read_file(handle: &web_sys::FileSystemSyncAccessHandle) -> Result<Vec<u8>, OpfsStoreError> {
+    let mut bytes = vec![0; handle.get_size()? as usize];
+    let options = web_sys::FileSystemReadWriteOptions::new();
+    options.set_at(0.0);
+    handle.read_with_u8_array_and_options(&mut bytes, &options)?;
+    Ok(bytes)
+}
+
+/// Replaces the content of a file and flushes it.
+fn write_file(
+    handle: &web_sys::FileSystemSyncAccessHandle,
+    bytes: &[u8],
+) -> Result<(), OpfsStoreError> {
+    handle.truncate_with_f64(0.0)?;
+    let options = web_sys::FileSystemReadWriteOptions::new();
+    options.set_at(0.0);
+    handle.write_with_u8_array_and_options(bytes, &options)?;
+    handle.flush()?;
+    Ok(())
+}
+
+/// Returns the record of a batch of operations in the log: its length, followed by the
+/// operations.
+fn log_record(operations: &[WriteOperation]) -> Result<Vec<u8>, OpfsStoreError> {
+    let bytes = bcs::to_bytes(operations)?;
+    let length = u32::try_from(bytes.len()).map_err(|_| OpfsStoreError::BatchTooLarge)?;
+    let mut record = length.to_le_bytes().to_vec();
+    record.extend(bytes);
+    Ok(record)
+}
+
+/// The content of a namespace, together with the file it is persisted to.
+impl OpfsFile {
+    /// Reads the log from the file and replays it, after completing an interrupted
+    /// compaction if there is one. A trailing batch that was only partially written is
+    /// discarded.
+    fn load(
+        handle: web_sys::FileSystemSyncAccessHandle,
+        compaction_handle: web_sys::FileSystemSyncAccessHandle,
+    ) -> Result<Self, OpfsStoreError> {
+        let compacted_log = read_file(&compaction_handle)?;
+        if !compacted_log.is_empty() {
+            // The compacted log is only used if it was completely written, in which case
+            // the log may have been partially rewritten. Otherwise the log was not touched.
+            let is_complete = compacted_log.get(..4).is_some_and(|length| {
+                let length = u32::from_le_bytes(length.try_into().unwrap()) as usize;
+                compacted_log.len() == 4 + length
+            });
+            if is_complete {
+                write_file(&handle, &compacted_log)?;
+            }
+            write_file(&compaction_handle, &[])?;
+        }
+        let log = read_file(&handle)?;
+        let size = log.len();
+        let mut file = OpfsFile {
+            handle,
+            compaction_handle,
+            map: BTreeMap::new(),
+            size: 0,
+            live_size: 0,
+        };
+        let mut position = 0;
+        while let Some(length) = log.get(position..position + 4) {
+            let length = u32::from_le_bytes(length.try_into().unwrap()) as usize;
+            let Some(bytes) = log.get(position + 4..position + 4 + length) else {
+                break;
+            };
+            let operations = bcs::from_bytes::<Vec<WriteOperation>>(bytes)?;
+            file.apply(operations);
+            position += 4 + length;
+        }
+        file.size = position as u64;
+        if position < size {
+            file.handle.truncate_with_f64(position as f64)?;
+            file.handle.flush()?;
+        }
+        Ok(file)
+    }
+
+    /// Applies the operations to the key-value pairs.
+    fn apply(&mut self, operations: Vec<WriteOperation>) {
+        for operation in operations {
+            match operation {
+                WriteOperation::Put { key, value } => {
+                    self.live_size += (key.len() + value.len()) as u64;
+                    if let Some(old_value) = self.map.insert(key.clone(), value) {
+                        self.live_size -= (key.len() + old_value.len()) as u64;
+                    }
+                }
+                WriteOperation::Delete { key } => {
+                    if let Some(old_value) = self.map.remove(&key) {
+                        self.live_size -= (key.len() + old_value.len()) as u64;
+                    }
+                }
+                WriteOperation::DeletePrefix { key_prefix } => {
+                    let keys = self
+                        .map
+                        .range(get_interval(key_prefix))
+                        .map(|(key, _)| key.clone())
+                        .collect::<Vec<_>>();
+                    for key in keys {
+                        let old_value = self.map.remove(&key).unwrap();
+                        self.live_size -= (key.len() + old_value.len()) as u64;
+                    }
+                }
+            }
+        }
+    }
+
+    /// Appends a batch of operations to the log and flushes it.
+    fn append(&mut self, operations: &[WriteOperation]) -> Result<(), OpfsStoreError> {
+        let record = log_record(operations)?;
+        let options = web_sys::FileSystemReadWriteOptions::new();
+        options.set_at(self.size as f64);
+        self.handle
+            .write_with_u8_array_and_options(&record, &options)?;
+        self.handle.flush()?;
+        self.size += record.len() as u64;
+        Ok(())
+    }
+
+    /// Rewrites the log as a single batch if most of it consists of replaced entries. The
+    /// batch is written to the compaction file before the log is replaced, and the compaction
+    /// file is only emptied once the log is, so that the log can always be recovered.
+    fn maybe_compact(&mut self) -> Result<(), OpfsStoreError> {
+        if self.size < MIN_COMPACTION_SIZE || self.size < 2 * self.live_size {
+            return Ok(());
+        }
+        let operations = self
+            .map
+            .iter()
+            .map(|(key, value)| WriteOperation::Put {
+                key: key.clone(),
+                value: value.clone(),
+            })
+            .collect::<Vec<_>>();
+        let record = log_record(&operations)?;
+        write_file(&self.compaction_handle, &record)?;
+        write_file(&self.handle, &record)?;
+        self.size = record.len() as u64;
+        write_file(&self.compaction_handle, &[])
+    }
+}
+
+impl Drop for OpfsFile {
+    fn drop(&mut self) {
+        self.handle.close();
+        self.compaction_handle.close();
+    }
+}
+
+impl OpfsStore {
+    fn full_key(&self, key: &[u8]) -> Vec<u8> {
+        let mut full_key = self.start_key.clone();
+        full_key.extend(key);
+        full_key
+    }
+}
+
+impl OpfsDatabase {
+    fn open_internal(&self, start_key: Vec<u8>) -> OpfsStore {
+        OpfsStore {
+            file: self.file.clone(),
+            max_stream_queries: self.max_stream_queries,
+            start_key,
+        }
+    }
+}
+
+/// Returns the directory of the origin private file system containing the namespaces.
+async fn get_directory() -> Result<web_sys::FileSystemDirectoryHandle, OpfsStoreError> {
+    let scope = js_sys::global().unchecked_into::<web_sys::WorkerGlobalScope>();
+    let root = JsFuture::from(scope.navigator().storage().get_directory())
+        .await?
+        .unchecked_into::<web_sys::FileSystemDirectoryHandle>();
+    let options = web_sys::FileSystemGetDirectoryOptions::new();
+    options.set_create(true);
+    Ok(
+        JsFuture::from(root.get_directory_handle_with_options(DIRECTORY_NAME, &options))
+            .await?
+            .unchecked_into(),
+    )
+}
+
+/// Returns the synchronous access handle of the file with the given name, creating it if
+/// necessary.
+async fn get_sync_access_handle(
+    name: &str,
+) -> Result<web_sys::FileSystemSyncAccessHandle, OpfsStoreError> {
+    Ok(
+        JsFuture::from(get_file(name).await?.create_sync_access_handle())
+            .await?
+            .unchecked_into(),
+    )
+}
+
+/// Returns the handle of the file with the given name, creating it if necessary.
+async fn get_file(name: &str) -> Result<web_sys::FileSystemFileHandle, OpfsStoreError> {
+    let options = web_sys::FileSystemGetFileOptions::new();
+    options.set_create(true);
+    Ok(JsFuture::from(
+        get_directory()
+            .await?
+            .get_file_handle_with_options(name, &options),
+    )
+    .await?
+    .unchecked_into())
+}
+
+/// Returns the names of the files of the directory.
+async fn list_files() -> Result<Vec<String>, OpfsStoreError> {
+    let iterator = get_directory().await?.keys();
+    let mut names = Vec::new();
+    loop {
+        let next = JsFuture::from(iterator.next()?)
+            .await?
+            .unchecked_into::<js_sys::IteratorNext>();
+        if next.done() {
+            return Ok(names);
+        }
+        names.extend(next.value().as_string());
+    }
+}
+
+impl WithError for OpfsStore {
+    type Error = OpfsStoreError;
+}
+
+impl WithError for OpfsDatabase {
+    type Error = OpfsStoreError;
+}
+
+impl ReadableKeyValueStore for OpfsStore {
+    const MAX_KEY_SIZE: usize = usize::MAX;
+
+    fn max_stream_queries(&self) -> usize {
+        self.max_stream_queries
+    }
+
+    async fn read_value_bytes(&self, key: &[u8]) -> Result<Option<Vec<u8>>, OpfsStoreError> {
+        let key = self.full_key(key);
+        Ok(self.file.borrow().map.get(&key).cloned())
+    }
+
+    async fn contains_key(&self, key: &[u8]) -> Result<bool, OpfsStoreError> {
+        let key = self.full_key(key);
+        Ok(self.file.borrow().map.contains_key(&key))
+    }
+
+    async fn contains_keys(&self, keys: Vec<Vec<u8>>) -> Result<Vec<bool>, OpfsStoreError> {
+        let file = self.file.borrow();
+        Ok(keys
+            .into_iter()
+            .map(|key| file.map.contains_key(&self.full_key(&key)))
+            .collect())
+    }
+
+    async fn read_multi_values_bytes(
+        &self,
+        keys: Vec<Vec<u8>>,
+    ) -> Result<Vec<Option<Vec<u8>>>, OpfsStoreError> {
+        let file = self.file.borrow();
+        Ok(keys
+            .into_iter()
+            .map(|key| file.map.get(&self.full_key(&key)).cloned())
+            .collect())
+    }
+
+    async fn find_keys_by_prefix(&self, key_prefix: &[u8]) -> Result<Vec<Vec<u8>>, OpfsStoreError> {
+        let key_prefix = self.full_key(key_prefix);
+        let len = key_prefix.len();
+        Ok(self
+            .file
+            .borrow()
+            .map
+            .range(get_interval(key_prefix))
+            .map(|(key, _)| key[len..].to_vec())
+            .collect())
+    }
+
+    async fn find_key_values_by_prefix(
+        &self,
+        key_prefix: &[u8],
+    ) -> Result<Vec<(Vec<u8>, Vec<u8>)>, OpfsStoreError> {
+        let key_prefix = self.full_key(key_prefix);
+        let len = key_prefix.len();
+        Ok(self
+            .file
+            .borrow()
+            .map
+            .range(get_interval(key_prefix))
+            .map(|(key, value)| (key[len..].to_vec(), value.clone()))
+            .collect())
+    }
+}
+
+impl WritableKeyValueStore for OpfsStore {
+    const MAX_VALUE_SIZE: usize = usize::MAX;
+
+    async fn write_batch(&self, batch: Batch) -> Result<(), OpfsStoreError> {
+        let mut operations = batch
+            .operations
+            .into_iter()
+            .map(|operation| match operation {
+                WriteOperation::Put { key, value } => WriteOperation::Put {
+                    key: self.full_key(&key),
+                    value,
+                },
+                WriteOperation::Delete { key } => WriteOperation::Delete {
+                    key: self.full_key(&key),
+                },
+                WriteOperation::DeletePrefix { key_prefix } => WriteOperation::DeletePrefix {
+                    key_prefix: self.full_key(&key_prefix),
+                },
+            })
+            .collect::<Vec<_>>();
+        let mut key = self.start_key.clone();
+        key[0] = STORED_ROOT_KEYS_PREFIX[0];
+        operations.push(WriteOperation::Put {
+            key,
+            value: Vec::new(),
+        });
+        let mut file = self.file.borrow_mut();
+        file.append(&operations)?;
+        file.apply(operations);
+        file.maybe_compact()
+    }
+
+    async fn clear_journal(&self) -> Result<(), OpfsStoreError> {
+        Ok(())
+    }
+}
+
+impl KeyValueDatabase for OpfsDatabase {
+    type Config = OpfsStoreConfig;
+
+    type Store = OpfsStore;
+
+    fn get_name() -> String {
+        "opfs".to_string()
+    }
+
+    async fn connect(config: &Self::Config, namespace: &str) -> Result<Self, OpfsStoreError> {
+        let name = NamespaceRules::OPFS.escape(namespace)?;
+        let open_file = OPEN_FILES.with(|files| files.borrow().get(&name)?.upgrade());
+        let file = match open_file {
+            Some(file) => file,
+            None => {
+                let handle = get_sync_access_handle(&name).await?;
+                let compaction_handle =
+                    get_sync_access_handle(&format!("{name}{COMPACTION_SUFFIX}")).await?;
+                let file = Rc::new(RefCell::new(OpfsFile::load(handle, compaction_handle)?));
+                OPEN_FILES.with(|files| {
+                    let mut files = files.borrow_mut();
+                    files.retain(|_, file| file.strong_count() > 0);
+                    files.insert(name, Rc::downgrade(&file));
+                });
+                file
+            }
+        };
+        Ok(Self {
+            file,
+            max_stream_queries: config.max_stream_queries,
+        })
+    }
+
+    fn open_shared(&self, root_key: &[u8]) -> Result<Self::Store, OpfsStoreError> {
+        let mut start_key = ROOT_KEY_DOMAIN.to_vec();
+        start_key.extend(root_key);
+        Ok(self.open_internal(start_key))
+    }
+
+    fn open_exclusive(&self, root_key: &[u8]) -> Result<Self::Store, OpfsStoreError> {
+        self.open_shared(root_key)
+    }
+
+    async fn list_all(_config: &Self::Config) -> Result<Vec<String>, OpfsStoreError> {
+        Ok(list_files()
+            .await?
+            .into_iter()
+            .filter(|name| !name.ends_with(COMPACTION_SUFFIX))
+            .filter_map(|name| NamespaceRules::OPFS.unescape(&name).ok())
+            .collect())
+    }
+
+    async fn list_root_keys(
+        config: &Self::Config,
+        namespace: &str,
+    ) -> Result<Vec<Vec<u8>>, OpfsStoreError> {
+        let database = Self::connect(config, namespace).await?;
+        let start_key = STORED_ROOT_KEYS_PREFIX.to_vec();
+        let store = database.open_internal(start_key);
+        store.find_keys_by_prefix(&[]).await
+    }
+
+    async fn exists(_config: &Self::Config, namespace: &str) -> Result<bool, OpfsStoreError> {
+        let name = NamespaceRules::OPFS.escape(namespace)?;
+        Ok(list_files().await?.contains(&name))
+    }
+
+    async fn create(_config: &Self::Config, namespace: &str) -> Result<(), OpfsStoreError> {
+        let name = NamespaceRules::OPFS.escape(namespace)?;
+        get_file(&name).await?;
+        Ok(())
+    }
+
+    async fn delete(_config: &Self::Config, namespace: &str) -> Result<(), OpfsStoreError> {
+        let name = NamespaceRules::OPFS.escape(namespace)?;
+        let compaction_name = format!("{name}{COMPACTION_SUFFIX}");
+        let directory = get_directory().await?;
+        if list_files().await?.contains(&compaction_name) {
+            JsFuture::from(directory.remove_entry(&compaction_name)).await?;
+        }
+        JsFuture::from(directory.remove_entry(&name)).await?;
+        Ok(())
+    }
+
+    async fn namespace_stats(&self) -> Result<NamespaceStats, OpfsStoreError> {
+        let store = self.open_internal(STORED_ROOT_KEYS_PREFIX.to_vec());
+        let root_keys = store.find_keys_by_prefix(&[]).await?;
+        sample_namespace_stats(self, &root_keys).await
+    }
+}
+
+#[cfg(with_testing)]
+mod testing {
+    use super::*;
+    use crate::random::generate_test_namespace;
+
+    /// Creates a test OPFS store with the given number of stream queries.
+    pub async fn create_opfs_store_stream_queries(max_stream_queries: usize) -> OpfsStore {
+        let config = OpfsStoreConfig { max_stream_queries };
+        let namespace = generate_test_namespace();
+        let database = OpfsDatabase::connect(&config, &namespace).await.unwrap();
+        database.open_shared(&[]).unwrap()
+    }
+
+    /// Creates a test OPFS store for working.
+    pub async fn create_opfs_test_store() -> OpfsStore {
+        create_opfs_store_stream_queries(TEST_OPFS_MAX_STREAM_QUERIES).await
+    }
+}
+
+#[cfg(with_testing)]
+pub use testing::*;
+
+/// The error type for [`OpfsStore`].
+#[derive(Error, Debug)]
+pub enum OpfsStoreError {
+    /// Serialization error with BCS.
+    #[error(transparent)]
+    BcsError(#[from] bcs::Error),
+
+    /// JavaScript threw an exception whilst handling OPFS operations
+    #[error("JavaScript exception: {0:?}")]
+    Js(gloo_utils::errors::JsError),
+
+    /// A batch is too large to be stored in the log.
+    #[error("The batch is too large to be stored in the log")]
+    BatchTooLarge,
+
+    /// The namespace cannot be mapped to a file name
+    #[error(transparent)]
+    InvalidNamespace(#[from] NamespaceError),
+}
+
+impl From<wasm_bindgen::JsValue> for OpfsStoreError {
+    fn from(js_value: wasm_bindgen::JsValue) -> Self {
+        Self::Js(js_value.try_into().unwrap())
+    }
+}
+
+impl KeyValueStoreError for OpfsStoreError {
+    const BACKEND: &'static str = "opfs";
+}
@@ -7,10 +7,12 @@ use std::{
     ffi::OsString,
     fmt::Display,
     path::PathBuf,
+    str::FromStr,
     sync::{
         atomic::{AtomicBool, Ordering},
         Arc,
     },
+    time::Duration,
 };
 
 use linera_base::ensure;
@@ -26,15 +28,49 @@ use crate::metering::MeteredDatabase;
 use crate::store::TestKeyValueDatabase;
 use crate::{
     batch::{Batch, WriteOperation},
-    common::get_upper_bound_option,
+    common::{get_upper_bound_option, NamespaceError, NamespaceRules},
+    context::{ScanBudget, ScanCounter},
     lru_caching::{LruCachingConfig, LruCachingDatabase},
     store::{
-        KeyValueDatabase, KeyValueStoreError, ReadableKeyValueStore, WithError,
+        KeyValueDatabase, KeyValueStoreError, NamespaceStats, ReadableKeyValueStore, WithError,
         WritableKeyValueStore,
     },
     value_splitting::{ValueSplittingDatabase, ValueSplittingError},
 };
 
+#[cfg(with_metrics)]
+mod metrics {
+    use std::sync::LazyLock;
+
+    use linera_base::prometheus_util::{register_int_counter, register_int_gauge_vec};
+    use prometheus::{IntCounter, IntGaugeVec};
+
+    /// The durability policy of the RocksDB databases, as a label set to 1.
+    pub static DURABILITY_POLICY: LazyLock<IntGaugeVec> = LazyLock::new(|| {
+        register_int_gauge_vec(
+            "rocks_db_durability_policy",
+            "The durability policy of the RocksDB databases",
+            &["policy"],
+        )
+    });
+
+    /// The number of periodic syncs of the RocksDB write-ahead logs.
+    pub static WAL_SYNC_COUNTER: LazyLock<IntCounter> = LazyLock::new(|| {
+        register_int_counter(
+            "rocks_db_wal_syncs",
+            "Number of periodic syncs of the RocksDB write-ahead logs",
+        )
+    });
+
+    /// The number of compactions of ranges of keys removed by prefix deletions.
+    pub static RANGE_COMPACTION_COUNTER: LazyLock<IntCounter> = LazyLock::new(|| {
+        register_int_counter(
+            "rocks_db_range_compactions",
+            "Number of compactions of ranges of keys removed by prefix deletions",
+        )
+    });
+}
+
 /// The prefixes being used in the system
 static ROOT_KEY_DOMAIN: [u8; 1] = [0];
 static STORED_ROOT_KEYS_PREFIX: u8 = 1;
@@ -108,6 +144,100 @@ impl Display for RocksDbSpawnMode {
     }
 }
 
+/// When the writes to RocksDB are synced to disk.
+///
+/// Without syncing, completed writes survive a crash of the process but not of the machine.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+pub enum DurabilityPolicy {
+    /// Every write is synced to disk before it completes.
+    Always,
+    /// The write-ahead log is synced to disk periodically, for all the writes since the
+    /// previous sync at once.
+    Interval(Duration),
+    /// Syncing is left to the operating system.
+    #[default]
+    Never,
+}
+
+impl DurabilityPolicy {
+    /// Returns the name of the policy, without its parameters.
+    pub fn name(&self) -> &'static str {
+        match self {
+            DurabilityPolicy::Always => "always",
+            DurabilityPolicy::Interval(_) => "interval",
+            DurabilityPolicy::Never => "never",
+        }
+    }
+}
+
+impl Display for DurabilityPolicy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DurabilityPolicy::Interval(interval) => write!(f, "interval:{}", interval.as_millis()),
+            policy => write!(f, "{}", policy.name()),
+        }
+    }
+}
+
+impl FromStr for DurabilityPolicy {
+    type Err = InvalidDurabilityPolicy;
+
+    /// Parses `always`, `never`, or `interval:<milliseconds>`.
+    fn from_str(string: &str) -> Result<Self, Self::Err> {
+        match string {
+            "always" => Ok(DurabilityPolicy::Always),
+            "never" => Ok(DurabilityPolicy::Never),
+            _ => {
+                let millis = string
+                    .strip_prefix("interval:")
+                    .and_then(|millis| millis.parse::<u64>().ok())
+                    .filter(|millis| *millis > 0)
+                    .ok_or_else(|| InvalidDurabilityPolicy(string.to_owned()))?;
+                Ok(DurabilityPolicy::Interval(Duration::from_millis(millis)))
+            }
+        }
+    }
+}
+
+/// Error caused by an invalid [`DurabilityPolicy`].
+#[derive(Clone, Debug, Error)]
+#[error(
+    "{0:?} is not a valid durability policy; expected `always`, `never` or \
+     `interval:<milliseconds>`"
+)]
+pub struct InvalidDurabilityPolicy(String);
+
+/// Syncs the write-ahead log of `db` every `interval`, until it is closed.
+fn spawn_wal_syncer(db: &Arc<DB>, interval: Duration) {
+    let db = Arc::downgrade(db);
+    std::thread::spawn(move || loop {
+        std::thread::sleep(interval);
+        let Some(db) = db.upgrade() else {
+            return;
+        };
+        match db.flush_wal(true) {
+            Ok(()) => {
+                #[cfg(with_metrics)]
+                metrics::WAL_SYNC_COUNTER.inc();
+            }
+            Err(error) => tracing::warn!("Failed to sync the RocksDB write-ahead log: {error}"),
+        }
+    });
+}
+
+/// Compacts the given ranges of keys of `db` in the background, so that the deleted keys and
+/// the range tombstones covering them are dropped from disk.
+fn spawn_range_compaction(db: &Arc<DB>, ranges: Vec<(Vec<u8>, Vec<u8>)>) {
+    let db = db.clone();
+    std::thread::spawn(move || {
+        for (start, end) in ranges {
+            db.compact_range(Some(start), Some(end));
+            #[cfg(with_metrics)]
+            metrics::RANGE_COMPACTION_COUNTER.inc();
+        }
+    });
+}
+
 fn check_key_size(key: &[u8]) -> Result<(), RocksDbStoreInternalError> {
     ensure!(
         key.len() <= MAX_KEY_SIZE,
@@ -120,6 +250,8 @@ fn check_key_size(key: &[u8]) -> Result<(), RocksDbStoreInternalError> {
 struct RocksDbStoreExecutor {
     db: Arc<DB>,
     start_key: Vec<u8>,
+    sync_writes: bool,
+    range_compaction_threshold: Option<usize>,
 }
 
 impl RocksDbStoreExecutor {
@@ -166,15 +298,17 @@ impl RocksDbStoreExecutor {
         Ok(entries.into_iter().collect::<Result<_, _>>()?)
     }
 
+    /// Finds the keys matching the prefix, or `None` as soon as they exceed the budget.
     fn find_keys_by_prefix_internal(
         &self,
-        key_prefix: Vec<u8>,
-    ) -> Result<Vec<Vec<u8>>, RocksDbStoreInternalError> {
+        (key_prefix, budget): (Vec<u8>, ScanBudget),
+    ) -> Result<Option<Vec<Vec<u8>>>, RocksDbStoreInternalError> {
         check_key_size(&key_prefix)?;
         let mut prefix = self.start_key.clone();
         prefix.extend(key_prefix);
         let len = prefix.len();
         let mut iter = self.db.raw_iterator();
+        let mut counter = ScanCounter::new(budget);
         let mut keys = Vec::new();
         iter.seek(&prefix);
         let mut next_key = iter.key();
@@ -182,23 +316,29 @@ impl RocksDbStoreExecutor {
             if !key.starts_with(&prefix) {
                 break;
             }
+            if !counter.record(key.len() - len) {
+                return Ok(None);
+            }
             keys.push(key[len..].to_vec());
             iter.next();
             next_key = iter.key();
         }
-        Ok(keys)
+        Ok(Some(keys))
     }
 
+    /// Finds the `(key,value)` pairs matching the prefix, or `None` as soon as they exceed
+    /// the budget.
     #[expect(clippy::type_complexity)]
     fn find_key_values_by_prefix_internal(
         &self,
-        key_prefix: Vec<u8>,
-    ) -> Result<Vec<(Vec<u8>, Vec<u8>)>, RocksDbStoreInternalError> {
+        (key_prefix, budget): (Vec<u8>, ScanBudget),
+    ) -> Result<Option<Vec<(Vec<u8>, Vec<u8>)>>, RocksDbStoreInternalError> {
         check_key_size(&key_prefix)?;
         let mut prefix = self.start_key.clone();
         prefix.extend(key_prefix);
         let len = prefix.len();
         let mut iter = self.db.raw_iterator();
+        let mut counter = ScanCounter::new(budget);
         let mut key_values = Vec::new();
         iter.seek(&prefix);
         let mut next_key = iter.key();
@@ -207,13 +347,29 @@ impl RocksDbStoreExecutor {
                 break;
             }
             if let Some(value) = iter.value() {
+                if !counter.record(key.len() - len + value.len()) {
+                    return Ok(None);
+                }
                 let key_value = (key[len..].to_vec(), value.to_vec());
                 key_values.push(key_value);
             }
             iter.next();
             next_key = iter.key();
         }
-        Ok(key_values)
+        Ok(Some(key_values))
+    }
+
+    /// Tests whether there are at least `count` keys from `start` included to `end` excluded.
+    fn has_keys_in_range(&self, start: &[u8], end: &[u8], count: usize) -> bool {
+        let mut iter = self.db.raw_iterator();
+        iter.seek(start);
+        for _ in 0..count {
+            match iter.key() {
+                Some(key) if key < end => iter.next(),
+                _ => return false,
+            }
+        }
+        true
     }
 
     fn write_batch_internal(
@@ -222,6 +378,7 @@ impl RocksDbStoreExecutor {
         write_root_key: bool,
     ) -> Result<(), RocksDbStoreInternalError> {
         let mut inner_batch = rocksdb::WriteBatchWithTransaction::default();
+        let mut oversized_ranges = Vec::new();
         for operation in batch.operations {
             match operation {
                 WriteOperation::Delete { key } => {
@@ -243,6 +400,11 @@ impl RocksDbStoreExecutor {
                     let full_key2 =
                         get_upper_bound_option(&full_key1).expect("the first entry cannot be 255");
                     inner_batch.delete_range(&full_key1, &full_key2);
+                    if let Some(threshold) = self.range_compaction_threshold {
+                        if self.has_keys_in_range(&full_key1, &full_key2, threshold) {
+                            oversized_ranges.push((full_key1, full_key2));
+                        }
+                    }
                 }
             }
         }
@@ -251,7 +413,12 @@ impl RocksDbStoreExecutor {
             full_key[0] = STORED_ROOT_KEYS_PREFIX;
             inner_batch.put(&full_key, vec![]);
         }
-        self.db.write(inner_batch)?;
+        let mut write_options = rocksdb::WriteOptions::default();
+        write_options.set_sync(self.sync_writes);
+        self.db.write_opt(inner_batch, &write_options)?;
+        if !oversized_ranges.is_empty() {
+            spawn_range_compaction(&self.db, oversized_ranges);
+        }
         Ok(())
     }
 }
@@ -288,17 +455,47 @@ pub struct RocksDbStoreInternalConfig {
     pub spawn_mode: RocksDbSpawnMode,
     /// Preferred buffer size for async streams.
     pub max_stream_queries: usize,
+    /// When the writes are synced to disk.
+    #[serde(default)]
+    pub durability: DurabilityPolicy,
+    /// If set, the range of keys removed by a prefix deletion is compacted once the write
+    /// completes, if it contained at least this many keys. Otherwise, compacting the range
+    /// is left to RocksDB.
+    #[serde(default)]
+    pub range_compaction_threshold: Option<usize>,
 }
 
 impl RocksDbDatabaseInternal {
-    fn check_namespace(namespace: &str) -> Result<(), RocksDbStoreInternalError> {
-        if !namespace
-            .chars()
-            .all(|character| character.is_ascii_alphanumeric() || character == '_')
-        {
-            return Err(RocksDbStoreInternalError::InvalidNamespace);
+    /// Returns the directory of the given namespace.
+    fn namespace_path(
+        config: &RocksDbStoreInternalConfig,
+        namespace: &str,
+    ) -> Result<PathBuf, RocksDbStoreInternalError> {
+        let mut path_buf = config.path_with_guard.path_buf.clone();
+        path_buf.push(NamespaceRules::ROCKS_DB.escape(namespace)?);
+        Ok(path_buf)
+    }
+
+    /// Lists the names of all the directories, including the ones that are not namespaces.
+    fn list_directories(
+        config: &RocksDbStoreInternalConfig,
+    ) -> Result<Vec<String>, RocksDbStoreInternalError> {
+        let entries = std::fs::read_dir(config.path_with_guard.path_buf.clone())?;
+        let mut directories = Vec::new();
+        for entry in entries {
+            let entry = entry?;
+            if !entry.file_type()?.is_dir() {
+                return Err(RocksDbStoreInternalError::NonDirectoryNamespace);
+            }
+            let directory = match entry.file_name().into_string() {
+                Err(error) => {
+                    return Err(RocksDbStoreInternalError::IntoStringError(error));
+                }
+                Ok(directory) => directory,
+            };
+            directories.push(directory);
         }
-        Ok(())
+        Ok(directories)
     }
 
     fn build(
@@ -323,10 +520,8 @@ impl RocksDbStoreInternal {
         namespace: &str,
         start_key: Vec<u8>,
     ) -> Result<RocksDbStoreInternal, RocksDbStoreInternalError> {
-        RocksDbDatabaseInternal::check_namespace(namespace)?;
-        let mut path_buf = config.path_with_guard.path_buf.clone();
+        let path_buf = RocksDbDatabaseInternal::namespace_path(config, namespace)?;
         let mut path_with_guard = config.path_with_guard.clone();
-        path_buf.push(namespace);
         path_with_guard.path_buf = path_buf.clone();
         let max_stream_queries = config.max_stream_queries;
         let spawn_mode = config.spawn_mode;
@@ -375,10 +570,19 @@ impl RocksDbStoreInternal {
         ));
         options.set_block_based_table_factory(&block_options);
 
-        let db = DB::open(&options, path_buf)?;
+        let db = Arc::new(DB::open(&options, path_buf)?);
+        if let DurabilityPolicy::Interval(interval) = config.durability {
+            spawn_wal_syncer(&db, interval);
+        }
+        #[cfg(with_metrics)]
+        metrics::DURABILITY_POLICY
+            .with_label_values(&[config.durability.name()])
+            .set(1);
         let executor = RocksDbStoreExecutor {
-            db: Arc::new(db),
+            db,
             start_key,
+            sync_writes: config.durability == DurabilityPolicy::Always,
+            range_compaction_threshold: config.range_compaction_threshold,
         };
         Ok(RocksDbStoreInternal {
             executor,
@@ -456,26 +660,48 @@ impl ReadableKeyValueStore for RocksDbStoreInternal {
         &self,
         key_prefix: &[u8],
     ) -> Result<Vec<Vec<u8>>, RocksDbStoreInternalError> {
+        let keys = self
+            .find_keys_by_prefix_within(key_prefix, ScanBudget::default())
+            .await?;
+        Ok(keys.expect("scans without limits are never stopped"))
+    }
+
+    async fn find_key_values_by_prefix(
+        &self,
+        key_prefix: &[u8],
+    ) -> Result<Vec<(Vec<u8>, Vec<u8>)>, RocksDbStoreInternalError> {
+        let key_values = self
+            .find_key_values_by_prefix_within(key_prefix, ScanBudget::default())
+            .await?;
+        Ok(key_values.expect("scans without limits are never stopped"))
+    }
+
+    async fn find_keys_by_prefix_within(
+        &self,
+        key_prefix: &[u8],
+        budget: ScanBudget,
+    ) -> Result<Option<Vec<Vec<u8>>>, RocksDbStoreInternalError> {
         let executor = self.executor.clone();
         let key_prefix = key_prefix.to_vec();
         self.spawn_mode
             .spawn(
                 move |x| executor.find_keys_by_prefix_internal(x),
-                key_prefix,
+                (key_prefix, budget),
             )
             .await
     }
 
-    async fn find_key_values_by_prefix(
+    async fn find_key_values_by_prefix_within(
         &self,
         key_prefix: &[u8],
-    ) -> Result<Vec<(Vec<u8>, Vec<u8>)>, RocksDbStoreInternalError> {
+        budget: ScanBudget,
+    ) -> Result<Option<Vec<(Vec<u8>, Vec<u8>)>>, RocksDbStoreInternalError> {
         let executor = self.executor.clone();
         let key_prefix = key_prefix.to_vec();
         self.spawn_mode
             .spawn(
                 move |x| executor.find_key_values_by_prefix_internal(x),
-                key_prefix,
+                (key_prefix, budget),
             )
             .await
     }
@@ -534,22 +760,10 @@ impl KeyValueDatabase for RocksDbDatabaseInternal {
     }
 
     async fn list_all(config: &Self::Config) -> Result<Vec<String>, RocksDbStoreInternalError> {
-        let entries = std::fs::read_dir(config.path_with_guard.path_buf.clone())?;
-        let mut namespaces = Vec::new();
-        for entry in entries {
-            let entry = entry?;
-            if !entry.file_type()?.is_dir() {
-                return Err(RocksDbStoreInternalError::NonDirectoryNamespace);
-            }
-            let namespace = match entry.file_name().into_string() {
-                Err(error) => {
-                    return Err(RocksDbStoreInternalError::IntoStringError(error));
-                }
-                Ok(namespace) => namespace,
-            };
-            namespaces.push(namespace);
-        }
-        Ok(namespaces)
+        Ok(Self::list_directories(config)?
+            .iter()
+            .filter_map(|directory| NamespaceRules::ROCKS_DB.unescape(directory).ok())
+            .collect())
     }
 
     async fn list_root_keys(
@@ -562,10 +776,10 @@ impl KeyValueDatabase for RocksDbDatabaseInternal {
     }
 
     async fn delete_all(config: &Self::Config) -> Result<(), RocksDbStoreInternalError> {
-        let namespaces = Self::list_all(config).await?;
-        for namespace in namespaces {
+        let directories = Self::list_directories(config)?;
+        for directory in directories {
             let mut path_buf = config.path_with_guard.path_buf.clone();
-            path_buf.push(&namespace);
+            path_buf.push(&directory);
             std::fs::remove_dir_all(path_buf.as_path())?;
         }
         Ok(())
@@ -575,9 +789,7 @@ impl KeyValueDatabase for RocksDbDatabaseInternal {
         config: &Self::Config,
         namespace: &str,
     ) -> Result<bool, RocksDbStoreInternalError> {
-        Self::check_namespace(namespace)?;
-        let mut path_buf = config.path_with_guard.path_buf.clone();
-        path_buf.push(namespace);
+        let path_buf = Self::namespace_path(config, namespace)?;
         let test = std::path::Path::exists(&path_buf);
         Ok(test)
     }
@@ -586,9 +798,7 @@ impl KeyValueDatabase for RocksDbDatabaseInternal {
         config: &Self::Config,
         namespace: &str,
     ) -> Result<(), RocksDbStoreInternalError> {
-        Self::check_namespace(namespace)?;
-        let mut path_buf = config.path_with_guard.path_buf.clone();
-        path_buf.push(namespace);
+        let path_buf = Self::namespace_path(config, namespace)?;
         if std::path::Path::exists(&path_buf) {
             return Err(RocksDbStoreInternalError::StoreAlreadyExists);
         }
@@ -600,13 +810,25 @@ impl KeyValueDatabase for RocksDbDatabaseInternal {
         config: &Self::Config,
         namespace: &str,
     ) -> Result<(), RocksDbStoreInternalError> {
-        Self::check_namespace(namespace)?;
-        let mut path_buf = config.path_with_guard.path_buf.clone();
-        path_buf.push(namespace);
+        let path_buf = Self::namespace_path(config, namespace)?;
         let path = path_buf.as_path();
         std::fs::remove_dir_all(path)?;
         Ok(())
     }
+
+    async fn namespace_stats(&self) -> Result<NamespaceStats, RocksDbStoreInternalError> {
+        // Each namespace is a database of its own, whose statistics RocksDB keeps up to date.
+        let db = &self.executor.db;
+        let property = |name: &str| -> Result<u64, RocksDbStoreInternalError> {
+            Ok(db.property_int_value(name)?.unwrap_or(0))
+        };
+        Ok(NamespaceStats {
+            key_count: Some(property("rocksdb.estimate-num-keys")?),
+            total_bytes: property("rocksdb.total-sst-files-size")?
+                + property("rocksdb.cur-size-all-mem-tables")?,
+            estimated: true,
+        })
+    }
 }
 
 #[cfg(with_testing)]
@@ -619,6 +841,8 @@ impl TestKeyValueDatabase for RocksDbDatabaseInternal {
             path_with_guard,
             spawn_mode,
             max_stream_queries,
+            durability: DurabilityPolicy::default(),
+            range_compaction_threshold: None,
         })
     }
 }
@@ -650,9 +874,9 @@ pub enum RocksDbStoreInternalError {
     #[error("The key must have at most 8 MiB")]
     KeyTooLong,
 
-    /// Namespace contains forbidden characters
-    #[error("Namespace contains forbidden characters")]
-    InvalidNamespace,
+    /// The namespace cannot be mapped to a directory name
+    #[error(transparent)]
+    InvalidNamespace(#[from] NamespaceError),
 
     /// Filesystem error
     #[error("Filesystem error: {0}")]
@@ -8,8 +8,11 @@
 //! `max_concurrent_queries`.
 
 use std::{
+    borrow::Cow,
     collections::{BTreeSet, HashMap},
+    fmt::{self, Display},
     ops::Deref,
+    str::FromStr,
     sync::Arc,
 };
 
@@ -44,12 +47,13 @@ use crate::metering::MeteredDatabase;
 use crate::store::TestKeyValueDatabase;
 use crate::{
     batch::UnorderedBatch,
-    common::{get_uleb128_size, get_upper_bound_option},
+    common::{get_uleb128_size, get_upper_bound_option, NamespaceError, NamespaceRules},
+    context::{ScanBudget, ScanCounter},
     journaling::{JournalConsistencyError, JournalingKeyValueDatabase},
     lru_caching::{LruCachingConfig, LruCachingDatabase},
     store::{
-        DirectWritableKeyValueStore, KeyValueDatabase, KeyValueStoreError, ReadableKeyValueStore,
-        WithError,
+        DirectWritableKeyValueStore, KeyValueDatabase, KeyValueStoreError, NamespaceStats,
+        ReadableKeyValueStore, RequestClass, WithError,
     },
     value_splitting::{ValueSplittingDatabase, ValueSplittingError},
     FutureSyncExt as _,
@@ -75,18 +79,18 @@ const MAX_BATCH_TOTAL_SIZE: usize = RAW_MAX_VALUE_SIZE + MAX_KEY_SIZE;
 /// Therefore the actual `MAX_VALUE_SIZE` is lower.
 /// At the maximum the key size is 1024 bytes (see below) and we pack just one entry.
 /// So if the key has 1024 bytes this gets us the inequality
-/// `1 + 1 + 1 + serialized_size(MAX_KEY_SIZE)? + serialized_size(x)? <= RAW_MAX_VALUE_SIZE`.
-/// and so this simplifies to `1 + 1 + 1 + (2 + 10240) + (4 + x) <= RAW_MAX_VALUE_SIZE`
+/// `1 + 1 + 1 + 1 + serialized_size(MAX_KEY_SIZE)? + serialized_size(x)? <= RAW_MAX_VALUE_SIZE`.
+/// and so this simplifies to `1 + 1 + 1 + 1 + (2 + 10240) + (4 + x) <= RAW_MAX_VALUE_SIZE`
 /// Note on the above formula:
 /// * We write 4 because `get_uleb128_size(RAW_MAX_VALUE_SIZE) = 4)`
-/// * We write `1 + 1 + 1`  because the `UnorderedBatch` has three entries.
+/// * We write `1 + 1 + 1 + 1`  because the `UnorderedBatch` has four entries.
 ///
-/// This gets us to a maximal value of 16752727.
+/// This gets us to a maximal value of 16752726.
 const VISIBLE_MAX_VALUE_SIZE: usize = RAW_MAX_VALUE_SIZE
     - MAX_KEY_SIZE
     - get_uleb128_size(RAW_MAX_VALUE_SIZE)
     - get_uleb128_size(MAX_KEY_SIZE)
-    - 3;
+    - 4;
 
 /// The constant 14000 is an empirical constant that was found to be necessary
 /// to make the ScyllaDB system work. We have not been able to find this or
@@ -98,6 +102,189 @@ const MAX_BATCH_SIZE: usize = 5000;
 /// The keyspace to use for the ScyllaDB database.
 const KEYSPACE: &str = "kv";
 
+/// A ScyllaDB consistency level, i.e. how many replicas must answer a query.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+pub enum ScyllaDbConsistency {
+    /// One replica.
+    One,
+    /// Two replicas.
+    Two,
+    /// Three replicas.
+    Three,
+    /// A majority of the replicas.
+    Quorum,
+    /// All the replicas.
+    All,
+    /// A majority of the replicas in the local datacenter.
+    #[default]
+    LocalQuorum,
+    /// A majority of the replicas in each datacenter.
+    EachQuorum,
+    /// One replica in the local datacenter.
+    LocalOne,
+}
+
+impl ScyllaDbConsistency {
+    const ALL: [ScyllaDbConsistency; 8] = [
+        ScyllaDbConsistency::One,
+        ScyllaDbConsistency::Two,
+        ScyllaDbConsistency::Three,
+        ScyllaDbConsistency::Quorum,
+        ScyllaDbConsistency::All,
+        ScyllaDbConsistency::LocalQuorum,
+        ScyllaDbConsistency::EachQuorum,
+        ScyllaDbConsistency::LocalOne,
+    ];
+
+    /// Returns the name of the consistency level.
+    pub fn name(&self) -> &'static str {
+        match self {
+            ScyllaDbConsistency::One => "one",
+            ScyllaDbConsistency::Two => "two",
+            ScyllaDbConsistency::Three => "three",
+            ScyllaDbConsistency::Quorum => "quorum",
+            ScyllaDbConsistency::All => "all",
+            ScyllaDbConsistency::LocalQuorum => "local_quorum",
+            ScyllaDbConsistency::EachQuorum => "each_quorum",
+            ScyllaDbConsistency::LocalOne => "local_one",
+        }
+    }
+
+    /// Returns whether the level requires a majority of the replicas, so that reads at
+    /// `local_quorum` observe the writes at this level.
+    pub fn is_quorum(&self) -> bool {
+        matches!(
+            self,
+            ScyllaDbConsistency::Quorum
+                | ScyllaDbConsistency::All
+                | ScyllaDbConsistency::LocalQuorum
+                | ScyllaDbConsistency::EachQuorum
+        )
+    }
+}
+
+impl From<ScyllaDbConsistency> for Consistency {
+    fn from(consistency: ScyllaDbConsistency) -> Self {
+        match consistency {
+            ScyllaDbConsistency::One => Consistency::One,
+            ScyllaDbConsistency::Two => Consistency::Two,
+            ScyllaDbConsistency::Three => Consistency::Three,
+            ScyllaDbConsistency::Quorum => Consistency::Quorum,
+            ScyllaDbConsistency::All => Consistency::All,
+            ScyllaDbConsistency::LocalQuorum => Consistency::LocalQuorum,
+            ScyllaDbConsistency::EachQuorum => Consistency::EachQuorum,
+            ScyllaDbConsistency::LocalOne => Consistency::LocalOne,
+        }
+    }
+}
+
+impl Display for ScyllaDbConsistency {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+impl FromStr for ScyllaDbConsistency {
+    type Err = InvalidScyllaDbConsistency;
+
+    /// Parses the name of a consistency level, ignoring the case.
+    fn from_str(string: &str) -> Result<Self, Self::Err> {
+        let name = string.to_lowercase();
+        Self::ALL
+            .into_iter()
+            .find(|consistency| consistency.name() == name)
+            .ok_or_else(|| InvalidScyllaDbConsistency::Unknown(string.to_owned()))
+    }
+}
+
+/// The ScyllaDB consistency level of each class of requests.
+///
+/// Weakening a class trades consistency for latency: for instance, the existence checks
+/// done before prefetching blobs can usually use `one`, at the cost of fetching a blob that
+/// was already stored. The requests of no other class use `local_quorum`. Writes must reach
+/// a quorum, so that these requests observe them.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+pub struct ScyllaDbConsistencyConfig {
+    /// The consistency of reading certificates and the blocks they contain.
+    pub certificate_reads: ScyllaDbConsistency,
+    /// The consistency of checking which blobs are stored before fetching the missing ones.
+    pub blob_prefetch: ScyllaDbConsistency,
+    /// The consistency of writing batches.
+    pub writes: ScyllaDbConsistency,
+}
+
+impl ScyllaDbConsistencyConfig {
+    /// Checks that the writes reach a quorum.
+    pub fn check(&self) -> Result<(), InvalidScyllaDbConsistency> {
+        ensure!(
+            self.writes.is_quorum(),
+            InvalidScyllaDbConsistency::UnsafeWrites(self.writes)
+        );
+        Ok(())
+    }
+
+    /// Returns the consistency of the reads of the given class, if it is not the default one.
+    fn reads(&self, class: RequestClass) -> Option<Consistency> {
+        match class {
+            RequestClass::Default => None,
+            RequestClass::CertificateReads => Some(self.certificate_reads.into()),
+            RequestClass::BlobPrefetch => Some(self.blob_prefetch.into()),
+        }
+    }
+}
+
+impl Display for ScyllaDbConsistencyConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "certificate_reads={},blob_prefetch={},writes={}",
+            self.certificate_reads, self.blob_prefetch, self.writes
+        )
+    }
+}
+
+impl FromStr for ScyllaDbConsistencyConfig {
+    type Err = InvalidScyllaDbConsistency;
+
+    /// Parses a comma-separated list of `<class>=<level>` entries, where the class is one of
+    /// `certificate_reads`, `blob_prefetch` and `writes`. The classes that are not listed use
+    /// `local_quorum`.
+    fn from_str(string: &str) -> Result<Self, Self::Err> {
+        let mut config = Self::default();
+        for entry in string.split(',') {
+            let (class, level) = entry
+                .split_once('=')
+                .ok_or_else(|| InvalidScyllaDbConsistency::Unknown(entry.to_owned()))?;
+            let level = level.trim().parse()?;
+            match class.trim() {
+                "certificate_reads" => config.certificate_reads = level,
+                "blob_prefetch" => config.blob_prefetch = level,
+                "writes" => config.writes = level,
+                _ => return Err(InvalidScyllaDbConsistency::Unknown(entry.to_owned())),
+            }
+        }
+        config.check()?;
+        Ok(config)
+    }
+}
+
+/// Error caused by an invalid [`ScyllaDbConsistency`] or [`ScyllaDbConsistencyConfig`].
+#[derive(Clone, Debug, Error)]
+pub enum InvalidScyllaDbConsistency {
+    /// The level or the class is unknown.
+    #[error(
+        "{0:?} is not a valid ScyllaDB consistency; expected a level such as `one` or \
+         `local_quorum`, assigned to `certificate_reads`, `blob_prefetch` or `writes`"
+    )]
+    Unknown(String),
+    /// The writes would not be observed by the reads at `local_quorum`.
+    #[error(
+        "writes at `{0}` may be missed by quorum reads; expected `quorum`, `local_quorum`, \
+         `each_quorum` or `all`"
+    )]
+    UnsafeWrites(ScyllaDbConsistency),
+}
+
 /// The client for ScyllaDB:
 /// * The session allows to pass queries
 /// * The namespace that is being assigned to the database
@@ -117,10 +304,15 @@ struct ScyllaDbClient {
     find_key_values_by_prefix_bounded: PreparedStatement,
     multi_key_values: DashMap<usize, PreparedStatement>,
     multi_keys: DashMap<usize, PreparedStatement>,
+    consistency: ScyllaDbConsistencyConfig,
 }
 
 impl ScyllaDbClient {
-    async fn new(session: Session, namespace: &str) -> Result<Self, ScyllaDbStoreInternalError> {
+    async fn new(
+        session: Session,
+        namespace: &str,
+        consistency: ScyllaDbConsistencyConfig,
+    ) -> Result<Self, ScyllaDbStoreInternalError> {
         let namespace = namespace.to_string();
         let read_value = session
             .prepare(format!(
@@ -207,6 +399,7 @@ impl ScyllaDbClient {
             find_key_values_by_prefix_bounded,
             multi_key_values: DashMap::new(),
             multi_keys: DashMap::new(),
+            consistency,
         })
     }
 
@@ -283,6 +476,22 @@ impl ScyllaDbClient {
         Ok(prepared_statement)
     }
 
+    /// Returns the statement to run with the given consistency, if any, instead of the
+    /// default one.
+    fn with_consistency(
+        statement: &PreparedStatement,
+        consistency: Option<Consistency>,
+    ) -> Cow<'_, PreparedStatement> {
+        match consistency {
+            None => Cow::Borrowed(statement),
+            Some(consistency) => {
+                let mut statement = statement.clone();
+                statement.set_consistency(consistency);
+                Cow::Owned(statement)
+            }
+        }
+    }
+
     fn check_key_size(key: &[u8]) -> Result<(), ScyllaDbStoreInternalError> {
         ensure!(
             key.len() <= MAX_KEY_SIZE,
@@ -291,6 +500,17 @@ impl ScyllaDbClient {
         Ok(())
     }
 
+    /// The bounds of a range deletion are only compared with the stored keys. They may be
+    /// one byte longer than a key, since the range following an inserted key starts at that
+    /// key followed by a zero byte.
+    fn check_range_bound_size(bound: &[u8]) -> Result<(), ScyllaDbStoreInternalError> {
+        ensure!(
+            bound.len() <= MAX_KEY_SIZE + 1,
+            ScyllaDbStoreInternalError::KeyTooLong
+        );
+        Ok(())
+    }
+
     fn check_value_size(value: &[u8]) -> Result<(), ScyllaDbStoreInternalError> {
         ensure!(
             value.len() <= RAW_MAX_VALUE_SIZE,
@@ -311,14 +531,16 @@ impl ScyllaDbClient {
         &self,
         root_key: &[u8],
         key: Vec<u8>,
+        consistency: Option<Consistency>,
     ) -> Result<Option<Vec<u8>>, ScyllaDbStoreInternalError> {
         Self::check_key_size(&key)?;
         let session = &self.session;
         // Read the value of a key
         let values = (root_key.to_vec(), key);
+        let statement = Self::with_consistency(&self.read_value, consistency);
 
         let (result, _) = session
-            .execute_single_page(&self.read_value, &values, PagingState::start())
+            .execute_single_page(&statement, &values, PagingState::start())
             .await?;
         let rows = result.into_rows_result()?;
         let mut rows = rows.rows::<(Vec<u8>,)>()?;
@@ -343,10 +565,14 @@ impl ScyllaDbClient {
         &self,
         root_key: &[u8],
         keys: Vec<Vec<u8>>,
+        consistency: Option<Consistency>,
     ) -> Result<Vec<Option<Vec<u8>>>, ScyllaDbStoreInternalError> {
         let mut values = vec![None; keys.len()];
         let map = Self::get_occurrences_map(keys)?;
-        let statement = self.get_multi_key_values_statement(map.len()).await?;
+        let mut statement = self.get_multi_key_values_statement(map.len()).await?;
+        if let Some(consistency) = consistency {
+            statement.set_consistency(consistency);
+        }
         let mut inputs = vec![root_key.to_vec()];
         inputs.extend(map.keys().cloned());
         let mut rows = self
@@ -368,10 +594,14 @@ impl ScyllaDbClient {
         &self,
         root_key: &[u8],
         keys: Vec<Vec<u8>>,
+        consistency: Option<Consistency>,
     ) -> Result<Vec<bool>, ScyllaDbStoreInternalError> {
         let mut values = vec![false; keys.len()];
         let map = Self::get_occurrences_map(keys)?;
-        let statement = self.get_multi_keys_statement(map.len()).await?;
+        let mut statement = self.get_multi_keys_statement(map.len()).await?;
+        if let Some(consistency) = consistency {
+            statement.set_consistency(consistency);
+        }
         let mut inputs = vec![root_key.to_vec()];
         inputs.extend(map.keys().cloned());
         let mut rows = self
@@ -394,14 +624,16 @@ impl ScyllaDbClient {
         &self,
         root_key: &[u8],
         key: Vec<u8>,
+        consistency: Option<Consistency>,
     ) -> Result<bool, ScyllaDbStoreInternalError> {
         Self::check_key_size(&key)?;
         let session = &self.session;
         // Read the value of a key
         let values = (root_key.to_vec(), key);
+        let statement = Self::with_consistency(&self.contains_key, consistency);
 
         let (result, _) = session
-            .execute_single_page(&self.contains_key, &values, PagingState::start())
+            .execute_single_page(&statement, &values, PagingState::start())
             .await?;
         let rows = result.into_rows_result()?;
         let mut rows = rows.rows::<(Vec<u8>,)>()?;
@@ -415,6 +647,7 @@ impl ScyllaDbClient {
     ) -> Result<(), ScyllaDbStoreInternalError> {
         let session = &self.session;
         let mut batch_query = scylla::statement::batch::Batch::new(BatchType::Unlogged);
+        batch_query.set_consistency(self.consistency.writes.into());
         let mut batch_values = Vec::new();
         let query1 = &self.write_batch_delete_prefix_unbounded;
         let query2 = &self.write_batch_delete_prefix_bounded;
@@ -434,6 +667,23 @@ impl ScyllaDbClient {
                 }
             }
         }
+        // Like the key-prefix deletions, each range deletion is a single range tombstone.
+        for (lower_bound, upper_bound) in batch.key_range_deletions {
+            Self::check_range_bound_size(&lower_bound)?;
+            match upper_bound {
+                None => {
+                    let values = vec![root_key.to_vec(), lower_bound];
+                    batch_values.push(values);
+                    batch_query.append_statement(query1.clone());
+                }
+                Some(upper_bound) => {
+                    Self::check_range_bound_size(&upper_bound)?;
+                    let values = vec![root_key.to_vec(), lower_bound, upper_bound];
+                    batch_values.push(values);
+                    batch_query.append_statement(query2.clone());
+                }
+            }
+        }
         let query3 = &self.write_batch_deletion;
         for key in batch.simple_unordered_batch.deletions {
             Self::check_key_size(&key)?;
@@ -453,11 +703,15 @@ impl ScyllaDbClient {
         Ok(())
     }
 
+    /// Finds the keys matching the prefix, or `None` as soon as they exceed the budget. The
+    /// rows are streamed page by page, so that the scan stops without loading the next pages.
     async fn find_keys_by_prefix_internal(
         &self,
         root_key: &[u8],
         key_prefix: Vec<u8>,
-    ) -> Result<Vec<Vec<u8>>, ScyllaDbStoreInternalError> {
+        budget: ScanBudget,
+        consistency: Option<Consistency>,
+    ) -> Result<Option<Vec<Vec<u8>>>, ScyllaDbStoreInternalError> {
         Self::check_key_size(&key_prefix)?;
         let session = &self.session;
         // Read the value of a key
@@ -467,30 +721,38 @@ impl ScyllaDbClient {
         let rows = match get_upper_bound_option(&key_prefix) {
             None => {
                 let values = (root_key.to_vec(), key_prefix.clone());
-                session
-                    .execute_iter(query_unbounded.clone(), values)
-                    .await?
+                let query = Self::with_consistency(query_unbounded, consistency);
+                session.execute_iter(query.into_owned(), values).await?
             }
             Some(upper_bound) => {
                 let values = (root_key.to_vec(), key_prefix.clone(), upper_bound);
-                session.execute_iter(query_bounded.clone(), values).await?
+                let query = Self::with_consistency(query_bounded, consistency);
+                session.execute_iter(query.into_owned(), values).await?
             }
         };
         let mut rows = rows.rows_stream::<(Vec<u8>,)>()?;
+        let mut counter = ScanCounter::new(budget);
         let mut keys = Vec::new();
         while let Some(row) = rows.next().await {
             let (key,) = row?;
+            if !counter.record(key.len() - len) {
+                return Ok(None);
+            }
             let short_key = key[len..].to_vec();
             keys.push(short_key);
         }
-        Ok(keys)
+        Ok(Some(keys))
     }
 
+    /// Finds the `(key,value)` pairs matching the prefix, or `None` as soon as they exceed
+    /// the budget.
     async fn find_key_values_by_prefix_internal(
         &self,
         root_key: &[u8],
         key_prefix: Vec<u8>,
-    ) -> Result<Vec<(Vec<u8>, Vec<u8>)>, ScyllaDbStoreInternalError> {
+        budget: ScanBudget,
+        consistency: Option<Consistency>,
+    ) -> Result<Option<Vec<(Vec<u8>, Vec<u8>)>>, ScyllaDbStoreInternalError> {
         Self::check_key_size(&key_prefix)?;
         let session = &self.session;
         // Read the value of a key
@@ -500,23 +762,27 @@ impl ScyllaDbClient {
         let rows = match get_upper_bound_option(&key_prefix) {
             None => {
                 let values = (root_key.to_vec(), key_prefix.clone());
-                session
-                    .execute_iter(query_unbounded.clone(), values)
-                    .await?
+                let query = Self::with_consistency(query_unbounded, consistency);
+                session.execute_iter(query.into_owned(), values).await?
             }
             Some(upper_bound) => {
                 let values = (root_key.to_vec(), key_prefix.clone(), upper_bound);
-                session.execute_iter(query_bounded.clone(), values).await?
+                let query = Self::with_consistency(query_bounded, consistency);
+                session.execute_iter(query.into_owned(), values).await?
             }
         };
         let mut rows = rows.rows_stream::<(Vec<u8>, Vec<u8>)>()?;
+        let mut counter = ScanCounter::new(budget);
         let mut key_values = Vec::new();
         while let Some(row) = rows.next().await {
             let (key, value) = row?;
+            if !counter.record(key.len() - len + value.len()) {
+                return Ok(None);
+            }
             let short_key = key[len..].to_vec();
             key_values.push((short_key, value));
         }
-        Ok(key_values)
+        Ok(Some(key_values))
     }
 }
 
@@ -527,6 +793,8 @@ pub struct ScyllaDbStoreInternal {
     semaphore: Option<Arc<Semaphore>>,
     max_stream_queries: usize,
     root_key: Vec<u8>,
+    /// The consistency of the reads, if not the default one.
+    read_consistency: Option<Consistency>,
 }
 
 /// Database-level connection to ScyllaDB for managing namespaces and partitions.
@@ -580,14 +848,18 @@ pub enum ScyllaDbStoreInternalError {
     #[error(transparent)]
     ScyllaDbNewSessionError(#[from] NewSessionError),
 
-    /// Namespace contains forbidden characters
-    #[error("Namespace contains forbidden characters")]
-    InvalidNamespace,
+    /// The namespace cannot be mapped to a table name
+    #[error(transparent)]
+    InvalidNamespace(#[from] NamespaceError),
 
     /// The journal is not coherent
     #[error(transparent)]
     JournalConsistencyError(#[from] JournalConsistencyError),
 
+    /// The consistency configuration is invalid
+    #[error(transparent)]
+    InvalidConsistency(#[from] InvalidScyllaDbConsistency),
+
     /// The batch is too long to be written
     #[error("The batch is too long to be written")]
     BatchTooLong,
@@ -627,7 +899,7 @@ impl ReadableKeyValueStore for ScyllaDbStoreInternal {
         let store = self.store.deref();
         let _guard = self.acquire().await;
         store
-            .read_value_internal(&self.root_key, key.to_vec())
+            .read_value_internal(&self.root_key, key.to_vec(), self.read_consistency)
             .await
     }
 
@@ -635,7 +907,7 @@ impl ReadableKeyValueStore for ScyllaDbStoreInternal {
         let store = self.store.deref();
         let _guard = self.acquire().await;
         store
-            .contains_key_internal(&self.root_key, key.to_vec())
+            .contains_key_internal(&self.root_key, key.to_vec(), self.read_consistency)
             .await
     }
 
@@ -648,9 +920,9 @@ impl ReadableKeyValueStore for ScyllaDbStoreInternal {
         }
         let store = self.store.deref();
         let _guard = self.acquire().await;
-        let handles = keys
-            .chunks(MAX_MULTI_KEYS)
-            .map(|keys| store.contains_keys_internal(&self.root_key, keys.to_vec()));
+        let handles = keys.chunks(MAX_MULTI_KEYS).map(|keys| {
+            store.contains_keys_internal(&self.root_key, keys.to_vec(), self.read_consistency)
+        });
         let results: Vec<_> = join_all(handles)
             .await
             .into_iter()
@@ -667,9 +939,9 @@ impl ReadableKeyValueStore for ScyllaDbStoreInternal {
         }
         let store = self.store.deref();
         let _guard = self.acquire().await;
-        let handles = keys
-            .chunks(MAX_MULTI_KEYS)
-            .map(|keys| store.read_multi_values_internal(&self.root_key, keys.to_vec()));
+        let handles = keys.chunks(MAX_MULTI_KEYS).map(|keys| {
+            store.read_multi_values_internal(&self.root_key, keys.to_vec(), self.read_consistency)
+        });
         let results: Vec<_> = join_all(handles)
             .await
             .into_iter()
@@ -681,21 +953,53 @@ impl ReadableKeyValueStore for ScyllaDbStoreInternal {
         &self,
         key_prefix: &[u8],
     ) -> Result<Vec<Vec<u8>>, ScyllaDbStoreInternalError> {
+        let keys = self
+            .find_keys_by_prefix_within(key_prefix, ScanBudget::default())
+            .await?;
+        Ok(keys.expect("scans without limits are never stopped"))
+    }
+
+    async fn find_key_values_by_prefix(
+        &self,
+        key_prefix: &[u8],
+    ) -> Result<Vec<(Vec<u8>, Vec<u8>)>, ScyllaDbStoreInternalError> {
+        let key_values = self
+            .find_key_values_by_prefix_within(key_prefix, ScanBudget::default())
+            .await?;
+        Ok(key_values.expect("scans without limits are never stopped"))
+    }
+
+    async fn find_keys_by_prefix_within(
+        &self,
+        key_prefix: &[u8],
+        budget: ScanBudget,
+    ) -> Result<Option<Vec<Vec<u8>>>, ScyllaDbStoreInternalError> {
         let store = self.store.deref();
         let _guard = self.acquire().await;
         store
-            .find_keys_by_prefix_internal(&self.root_key, key_prefix.to_vec())
+            .find_keys_by_prefix_internal(
+                &self.root_key,
+                key_prefix.to_vec(),
+                budget,
+                self.read_consistency,
+            )
             .await
     }
 
-    async fn find_key_values_by_prefix(
+    async fn find_key_values_by_prefix_within(
         &self,
         key_prefix: &[u8],
-    ) -> Result<Vec<(Vec<u8>, Vec<u8>)>, ScyllaDbStoreInternalError> {
+        budget: ScanBudget,
+    ) -> Result<Option<Vec<(Vec<u8>, Vec<u8>)>>, ScyllaDbStoreInternalError> {
         let store = self.store.deref();
         let _guard = self.acquire().await;
         store
-            .find_key_values_by_prefix_internal(&self.root_key, key_prefix.to_vec())
+            .find_key_values_by_prefix_internal(
+                &self.root_key,
+                key_prefix.to_vec(),
+                budget,
+                self.read_consistency,
+            )
             .await
     }
 }
@@ -737,6 +1041,9 @@ pub struct ScyllaDbStoreInternalConfig {
     pub max_stream_queries: usize,
     /// The replication factor.
     pub replication_factor: u32,
+    /// The consistency level of each class of operations.
+    #[serde(default)]
+    pub consistency: ScyllaDbConsistencyConfig,
 }
 
 impl KeyValueDatabase for ScyllaDbDatabaseInternal {
@@ -751,9 +1058,10 @@ impl KeyValueDatabase for ScyllaDbDatabaseInternal {
         config: &Self::Config,
         namespace: &str,
     ) -> Result<Self, ScyllaDbStoreInternalError> {
-        Self::check_namespace(namespace)?;
+        config.consistency.check()?;
+        let namespace = &NamespaceRules::SCYLLA_DB.escape(namespace)?;
         let session = ScyllaDbClient::build_default_session(&config.uri).await?;
-        let store = ScyllaDbClient::new(session, namespace).await?;
+        let store = ScyllaDbClient::new(session, namespace, config.consistency).await?;
         let store = Arc::new(store);
         let semaphore = config
             .max_concurrent_queries
@@ -767,15 +1075,25 @@ impl KeyValueDatabase for ScyllaDbDatabaseInternal {
     }
 
     fn open_shared(&self, root_key: &[u8]) -> Result<Self::Store, ScyllaDbStoreInternalError> {
+        self.open_shared_for(root_key, RequestClass::Default)
+    }
+
+    fn open_shared_for(
+        &self,
+        root_key: &[u8],
+        class: RequestClass,
+    ) -> Result<Self::Store, ScyllaDbStoreInternalError> {
         let store = self.store.clone();
         let semaphore = self.semaphore.clone();
         let max_stream_queries = self.max_stream_queries;
         let root_key = get_big_root_key(root_key);
+        let read_consistency = store.consistency.reads(class);
         Ok(ScyllaDbStoreInternal {
             store,
             semaphore,
             max_stream_queries,
             root_key,
+            read_consistency,
         })
     }
 
@@ -810,8 +1128,11 @@ impl KeyValueDatabase for ScyllaDbDatabaseInternal {
         let mut rows_stream = result.rows_stream::<(String, String, String, String)>()?;
         while let Some(row) = rows_stream.next().await {
             let (_, object_kind, name, _) = row?;
-            if object_kind == "table" {
-                namespaces.push(name);
+            if object_kind != "table" {
+                continue;
+            }
+            if let Ok(namespace) = NamespaceRules::SCYLLA_DB.unescape(&name) {
+                namespaces.push(namespace);
             }
         }
         Ok(namespaces)
@@ -821,7 +1142,7 @@ impl KeyValueDatabase for ScyllaDbDatabaseInternal {
         config: &Self::Config,
         namespace: &str,
     ) -> Result<Vec<Vec<u8>>, ScyllaDbStoreInternalError> {
-        Self::check_namespace(namespace)?;
+        let namespace = &NamespaceRules::SCYLLA_DB.escape(namespace)?;
         let session = ScyllaDbClient::build_default_session(&config.uri).await?;
         let statement = session
             .prepare(format!(
@@ -858,7 +1179,7 @@ impl KeyValueDatabase for ScyllaDbDatabaseInternal {
         config: &Self::Config,
         namespace: &str,
     ) -> Result<bool, ScyllaDbStoreInternalError> {
-        Self::check_namespace(namespace)?;
+        let namespace = &NamespaceRules::SCYLLA_DB.escape(namespace)?;
         let session = ScyllaDbClient::build_default_session(&config.uri).await?;
 
         // We check the way the test can fail. It can fail in different ways.
@@ -903,7 +1224,7 @@ impl KeyValueDatabase for ScyllaDbDatabaseInternal {
         config: &Self::Config,
         namespace: &str,
     ) -> Result<(), ScyllaDbStoreInternalError> {
-        Self::check_namespace(namespace)?;
+        let namespace = &NamespaceRules::SCYLLA_DB.escape(namespace)?;
         let session = ScyllaDbClient::build_default_session(&config.uri).await?;
 
         // Create a keyspace if it doesn't exist
@@ -958,7 +1279,7 @@ impl KeyValueDatabase for ScyllaDbDatabaseInternal {
         config: &Self::Config,
         namespace: &str,
     ) -> Result<(), ScyllaDbStoreInternalError> {
-        Self::check_namespace(namespace)?;
+        let namespace = &NamespaceRules::SCYLLA_DB.escape(namespace)?;
         let session = ScyllaDbClient::build_default_session(&config.uri).await?;
         let statement = session
             .prepare(format!("DROP TABLE IF EXISTS {}.{};", KEYSPACE, namespace))
@@ -968,6 +1289,35 @@ impl KeyValueDatabase for ScyllaDbDatabaseInternal {
             .await?;
         Ok(())
     }
+
+    async fn namespace_stats(&self) -> Result<NamespaceStats, ScyllaDbStoreInternalError> {
+        // Listing the partitions would scan the whole table, so this relies on the estimates
+        // that ScyllaDB keeps for the token ranges of the node serving the query. They are
+        // per partition, so the number of keys is unknown.
+        let session = &self.store.session;
+        let statement = session
+            .prepare(
+                "SELECT partitions_count, mean_partition_size FROM system.size_estimates \
+                WHERE keyspace_name = ? AND table_name = ?",
+            )
+            .await?;
+        let rows = session
+            .execute_iter(statement, (KEYSPACE, self.store.namespace.as_str()))
+            .await?;
+        let mut rows = rows.rows_stream::<(i64, i64)>()?;
+        let mut total_bytes = 0u64;
+        while let Some(row) = rows.next().await {
+            let (partitions_count, mean_partition_size) = row?;
+            let bytes =
+                (partitions_count.max(0) as u64).saturating_mul(mean_partition_size.max(0) as u64);
+            total_bytes = total_bytes.saturating_add(bytes);
+        }
+        Ok(NamespaceStats {
+            key_count: None,
+            total_bytes,
+            estimated: true,
+        })
+    }
 }
 
 impl ScyllaDbStoreInternal {
@@ -980,20 +1330,6 @@ impl ScyllaDbStoreInternal {
     }
 }
 
-impl ScyllaDbDatabaseInternal {
-    fn check_namespace(namespace: &str) -> Result<(), ScyllaDbStoreInternalError> {
-        if !namespace.is_empty()
-            && namespace.len() <= 48
-            && namespace
-                .chars()
-                .all(|c| c.is_ascii_alphanumeric() || c == '_')
-        {
-            return Ok(());
-        }
-        Err(ScyllaDbStoreInternalError::InvalidNamespace)
-    }
-}
-
 #[cfg(with_testing)]
 impl TestKeyValueDatabase for JournalingKeyValueDatabase<ScyllaDbDatabaseInternal> {
     async fn new_test_config() -> Result<ScyllaDbStoreInternalConfig, ScyllaDbStoreInternalError> {
@@ -1004,6 +1340,7 @@ impl TestKeyValueDatabase for JournalingKeyValueDatabase<ScyllaDbDatabaseInterna
             max_concurrent_queries: Some(10),
             max_stream_queries: 10,
             replication_factor: 1,
+            consistency: ScyllaDbConsistencyConfig::default(),
         })
     }
 }
@@ -1031,3 +1368,70 @@ pub type ScyllaDbStoreConfig = LruCachingConfig<ScyllaDbStoreInternalConfig>;
 
 /// The combined error type for the `ScyllaDbDatabase`.
 pub type ScyllaDbStoreError = ValueSplittingError<ScyllaDbStoreInternalError>;
+
+#[cfg(test)]
+mod tests {
+    use scylla::statement::Consistency;
+
+    use super::{InvalidScyllaDbConsistency, ScyllaDbConsistency, ScyllaDbConsistencyConfig};
+    use crate::store::RequestClass;
+
+    #[test]
+    fn test_consistency_config_parsing() {
+        let config = "blob_prefetch=one, certificate_reads=LOCAL_QUORUM, writes=quorum"
+            .parse::<ScyllaDbConsistencyConfig>()
+            .unwrap();
+        assert_eq!(
+            config,
+            ScyllaDbConsistencyConfig {
+                certificate_reads: ScyllaDbConsistency::LocalQuorum,
+                blob_prefetch: ScyllaDbConsistency::One,
+                writes: ScyllaDbConsistency::Quorum,
+            }
+        );
+        assert_eq!(
+            config
+                .to_string()
+                .parse::<ScyllaDbConsistencyConfig>()
+                .unwrap(),
+            config
+        );
+        assert_eq!(
+            config.reads(RequestClass::BlobPrefetch),
+            Some(Consistency::One)
+        );
+        assert_eq!(
+            config.reads(RequestClass::CertificateReads),
+            Some(Consistency::LocalQuorum)
+        );
+        assert_eq!(config.reads(RequestClass::Default), None);
+        assert!(matches!(
+            "value_reads=one".parse::<ScyllaDbConsistencyConfig>(),
+            Err(InvalidScyllaDbConsistency::Unknown(_))
+        ));
+        assert!(matches!(
+            "blob_prefetch=none".parse::<ScyllaDbConsistencyConfig>(),
+            Err(InvalidScyllaDbConsistency::Unknown(_))
+        ));
+    }
+
+    #[test]
+    fn test_writes_must_reach_a_quorum() {
+        for level in ["one", "two", "three", "local_one"] {
+            assert!(matches!(
+                format!("writes={level}").parse::<ScyllaDbConsistencyConfig>(),
+                Err(InvalidScyllaDbConsistency::UnsafeWrites(_))
+            ));
+        }
+        for level in ["quorum", "all", "local_quorum", "each_quorum"] {
+            assert!(format!("writes={level}")
+                .parse::<ScyllaDbConsistencyConfig>()
+                .is_ok());
+        }
+        let config = ScyllaDbConsistencyConfig {
+            writes: ScyllaDbConsistency::One,
+            ..ScyllaDbConsistencyConfig::default()
+        };
+        assert!(config.check().is_err());
+    }
+}
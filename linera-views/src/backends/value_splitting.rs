@@ -8,9 +8,10 @@ use thiserror::Error;
 
 use crate::{
     batch::{Batch, WriteOperation},
+    context::ScanBudget,
     store::{
-        KeyValueDatabase, KeyValueStoreError, ReadableKeyValueStore, WithError,
-        WritableKeyValueStore,
+        KeyValueDatabase, KeyValueStoreError, NamespaceStats, ReadableKeyValueStore, RequestClass,
+        WithError, WritableKeyValueStore,
     },
 };
 #[cfg(with_testing)]
@@ -199,15 +200,8 @@ where
     }
 
     async fn find_keys_by_prefix(&self, key_prefix: &[u8]) -> Result<Vec<Vec<u8>>, Self::Error> {
-        let mut keys = Vec::new();
-        for big_key in self.store.find_keys_by_prefix(key_prefix).await? {
-            let len = big_key.len();
-            if Self::read_index_from_key(&big_key)? == 0 {
-                let key = big_key[0..len - 4].to_vec();
-                keys.push(key);
-            }
-        }
-        Ok(keys)
+        let big_keys = self.store.find_keys_by_prefix(key_prefix).await?;
+        Self::keys_of_segments(big_keys)
     }
 
     async fn find_key_values_by_prefix(
@@ -215,31 +209,34 @@ where
         key_prefix: &[u8],
     ) -> Result<Vec<(Vec<u8>, Vec<u8>)>, Self::Error> {
         let small_key_values = self.store.find_key_values_by_prefix(key_prefix).await?;
-        let mut small_kv_iterator = small_key_values.into_iter();
-        let mut key_values = Vec::new();
-        while let Some((mut big_key, value)) = small_kv_iterator.next() {
-            if Self::read_index_from_key(&big_key)? != 0 {
-                continue; // Leftover segment from an earlier value.
-            }
-            big_key.truncate(big_key.len() - 4);
-            let key = big_key;
-            let count = Self::read_count_from_value(&value)?;
-            let mut big_value = value[4..].to_vec();
-            for idx in 1..count {
-                let (big_key, value) = small_kv_iterator
-                    .next()
-                    .ok_or(ValueSplittingError::MissingSegment)?;
-                ensure!(
-                    Self::read_index_from_key(&big_key)? == idx
-                        && big_key.starts_with(&key)
-                        && big_key.len() == key.len() + 4,
-                    ValueSplittingError::MissingSegment
-                );
-                big_value.extend(value);
-            }
-            key_values.push((key, big_value));
-        }
-        Ok(key_values)
+        Self::key_values_of_segments(small_key_values)
+    }
+
+    // The budget applies to the segments found by the scan of the underlying store, which
+    // are at least as many and as large as the values they make up.
+
+    async fn find_keys_by_prefix_within(
+        &self,
+        key_prefix: &[u8],
+        budget: ScanBudget,
+    ) -> Result<Option<Vec<Vec<u8>>>, Self::Error> {
+        self.store
+            .find_keys_by_prefix_within(key_prefix, budget)
+            .await?
+            .map(Self::keys_of_segments)
+            .transpose()
+    }
+
+    async fn find_key_values_by_prefix_within(
+        &self,
+        key_prefix: &[u8],
+        budget: ScanBudget,
+    ) -> Result<Option<Vec<(Vec<u8>, Vec<u8>)>>, Self::Error> {
+        self.store
+            .find_key_values_by_prefix_within(key_prefix, budget)
+            .await?
+            .map(Self::key_values_of_segments)
+            .transpose()
     }
 }
 
@@ -307,7 +304,15 @@ where
     }
 
     fn open_shared(&self, root_key: &[u8]) -> Result<Self::Store, Self::Error> {
-        let store = self.database.open_shared(root_key)?;
+        self.open_shared_for(root_key, RequestClass::Default)
+    }
+
+    fn open_shared_for(
+        &self,
+        root_key: &[u8],
+        class: RequestClass,
+    ) -> Result<Self::Store, Self::Error> {
+        let store = self.database.open_shared_for(root_key, class)?;
         Ok(ValueSplittingStore { store })
     }
 
@@ -342,6 +347,10 @@ where
     async fn delete(config: &Self::Config, namespace: &str) -> Result<(), Self::Error> {
         Ok(D::delete(config, namespace).await?)
     }
+
+    async fn namespace_stats(&self) -> Result<NamespaceStats, Self::Error> {
+        Ok(self.database.namespace_stats().await?)
+    }
 }
 
 #[cfg(with_testing)]
@@ -402,6 +411,53 @@ where
         bytes.reverse();
         Ok(bcs::from_bytes::<u32>(&bytes)?)
     }
+
+    /// Returns the keys of the values made up by the given segment keys.
+    fn keys_of_segments(
+        big_keys: Vec<Vec<u8>>,
+    ) -> Result<Vec<Vec<u8>>, ValueSplittingError<D::Error>> {
+        let mut keys = Vec::new();
+        for big_key in big_keys {
+            let len = big_key.len();
+            if Self::read_index_from_key(&big_key)? == 0 {
+                let key = big_key[0..len - 4].to_vec();
+                keys.push(key);
+            }
+        }
+        Ok(keys)
+    }
+
+    /// Reassembles the values made up by the given segments.
+    #[expect(clippy::type_complexity)]
+    fn key_values_of_segments(
+        small_key_values: Vec<(Vec<u8>, Vec<u8>)>,
+    ) -> Result<Vec<(Vec<u8>, Vec<u8>)>, ValueSplittingError<D::Error>> {
+        let mut small_kv_iterator = small_key_values.into_iter();
+        let mut key_values = Vec::new();
+        while let Some((mut big_key, value)) = small_kv_iterator.next() {
+            if Self::read_index_from_key(&big_key)? != 0 {
+                continue; // Leftover segment from an earlier value.
+            }
+            big_key.truncate(big_key.len() - 4);
+            let key = big_key;
+            let count = Self::read_count_from_value(&value)?;
+            let mut big_value = value[4..].to_vec();
+            for idx in 1..count {
+                let (big_key, value) = small_kv_iterator
+                    .next()
+                    .ok_or(ValueSplittingError::MissingSegment)?;
+                ensure!(
+                    Self::read_index_from_key(&big_key)? == idx
+                        && big_key.starts_with(&key)
+                        && big_key.len() == key.len() + 4,
+                    ValueSplittingError::MissingSegment
+                );
+                big_value.extend(value);
+            }
+            key_values.push((key, big_value));
+        }
+        Ok(key_values)
+    }
 }
 
 /// A memory store for which the values are limited to 100 bytes and can be used for tests.
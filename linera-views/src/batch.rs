@@ -28,7 +28,8 @@ use linera_witty::{WitLoad, WitStore, WitType};
 use serde::{Deserialize, Serialize};
 
 use crate::{
-    common::{get_interval, get_uleb128_size},
+    common::{get_interval, get_uleb128_size, get_upper_bound_option},
+    store::ReadableKeyValueStore,
     ViewError,
 };
 
@@ -37,7 +38,7 @@ use crate::{
 /// * Deletion of a specific key.
 /// * Deletion of all keys matching a specific prefix.
 /// * Insertion or replacement of a key with a value.
-#[derive(Clone, Debug, Eq, PartialEq, WitType, WitLoad, WitStore)]
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize, WitType, WitLoad, WitStore)]
 pub enum WriteOperation {
     /// Delete the given key.
     Delete {
@@ -59,7 +60,7 @@ pub enum WriteOperation {
 }
 
 /// A batch of write operations.
-#[derive(Clone, Debug, Default, Eq, PartialEq)]
+#[derive(Clone, Debug, Default, Eq, PartialEq, Serialize, Deserialize)]
 pub struct Batch {
     /// The write operations.
     pub operations: Vec<WriteOperation>,
@@ -75,16 +76,28 @@ pub struct SimpleUnorderedBatch {
     pub insertions: Vec<(Vec<u8>, Vec<u8>)>,
 }
 
-/// An unordered batch of deletions and insertions, together with a set of key-prefixes to
-/// delete. Key-prefix deletions must happen before the insertions and the deletions.
+/// An unordered batch of deletions and insertions, together with a set of key-prefixes and
+/// key ranges to delete. Key-prefix and key-range deletions must happen before the insertions
+/// and the deletions.
 #[derive(Default, Serialize, Deserialize)]
 pub struct UnorderedBatch {
     /// The key-prefix deletions.
     pub key_prefix_deletions: Vec<Vec<u8>>,
+    /// The deletions of ranges of keys, given by an inclusive lower bound and an exclusive
+    /// upper bound, if any.
+    pub key_range_deletions: Vec<(Vec<u8>, Option<Vec<u8>>)>,
     /// The batch of deletions and insertions.
     pub simple_unordered_batch: SimpleUnorderedBatch,
 }
 
+/// The serialization format of an [`UnorderedBatch`] before the key-range deletions were
+/// introduced. Journals written in this format can still be replayed.
+#[derive(Serialize, Deserialize)]
+struct LegacyUnorderedBatch {
+    key_prefix_deletions: Vec<Vec<u8>>,
+    simple_unordered_batch: SimpleUnorderedBatch,
+}
+
 impl UnorderedBatch {
     /// From an `UnorderedBatch`, creates a [`SimpleUnorderedBatch`] that does not contain the
     /// `key_prefix_deletions`. This requires accessing the database to eliminate them.
@@ -151,14 +164,56 @@ impl UnorderedBatch {
         Ok(())
     }
 
+    /// Modifies an [`UnorderedBatch`] so that the key-prefix deletions do not conflict
+    /// with subsequent insertions, without accessing the database: a colliding key-prefix
+    /// deletion is replaced by the deletions of the ranges of keys around the inserted keys.
+    pub fn split_colliding_prefix_deletions(&mut self) {
+        if self.key_prefix_deletions.is_empty() {
+            return;
+        }
+        let inserted_keys = self
+            .simple_unordered_batch
+            .insertions
+            .iter()
+            .map(|x| x.0.clone())
+            .collect::<BTreeSet<_>>();
+        let mut key_prefix_deletions = Vec::new();
+        for key_prefix in std::mem::take(&mut self.key_prefix_deletions) {
+            let mut colliding_keys = inserted_keys
+                .range(get_interval(key_prefix.clone()))
+                .peekable();
+            if colliding_keys.peek().is_none() {
+                key_prefix_deletions.push(key_prefix);
+                continue;
+            }
+            let mut lower_bound = key_prefix.clone();
+            for key in colliding_keys {
+                if &lower_bound < key {
+                    let upper_bound = Some(key.clone());
+                    self.key_range_deletions.push((lower_bound, upper_bound));
+                }
+                // The smallest key after `key` is `key` followed by a zero.
+                lower_bound = key.clone();
+                lower_bound.push(0);
+            }
+            let upper_bound = get_upper_bound_option(&key_prefix);
+            self.key_range_deletions.push((lower_bound, upper_bound));
+        }
+        self.key_prefix_deletions = key_prefix_deletions;
+    }
+
     /// The total number of entries of the batch.
     pub fn len(&self) -> usize {
-        self.key_prefix_deletions.len() + self.simple_unordered_batch.len()
+        self.key_prefix_deletions.len()
+            + self.key_range_deletions.len()
+            + self.simple_unordered_batch.len()
     }
 
     /// Tests whether the batch is empty or not
     pub fn is_empty(&self) -> bool {
-        self.key_prefix_deletions.is_empty() && self.simple_unordered_batch.is_empty()
+        self.key_prefix_deletions.is_empty()
+            && self.key_range_deletions.is_empty()
+            && self.simple_unordered_batch.is_empty()
     }
 }
 
@@ -219,6 +274,67 @@ impl Batch {
         Ok(batch)
     }
 
+    /// Returns the operations of this batch that change keys starting with `key_prefix`. A
+    /// deleted prefix that covers all of these keys becomes a deletion of `key_prefix`.
+    pub fn restrict_to_prefix(&self, key_prefix: &[u8]) -> Batch {
+        let operations = self
+            .operations
+            .iter()
+            .filter_map(|operation| match operation {
+                WriteOperation::Put { key, .. } | WriteOperation::Delete { key }
+                    if key.starts_with(key_prefix) =>
+                {
+                    Some(operation.clone())
+                }
+                WriteOperation::DeletePrefix {
+                    key_prefix: deleted,
+                } if deleted.starts_with(key_prefix) => Some(operation.clone()),
+                WriteOperation::DeletePrefix {
+                    key_prefix: deleted,
+                } if key_prefix.starts_with(deleted) => Some(WriteOperation::DeletePrefix {
+                    key_prefix: key_prefix.to_vec(),
+                }),
+                _ => None,
+            })
+            .collect();
+        Batch { operations }
+    }
+
+    /// Returns the batch that restores the current contents of `store` once this batch has
+    /// been written to it.
+    ///
+    /// The prefixes deleted by this batch are deleted again, then the values that were
+    /// overwritten or deleted are put back and the keys that were created are deleted.
+    pub async fn undo<S: ReadableKeyValueStore>(&self, store: &S) -> Result<Batch, S::Error> {
+        let mut undo = Batch::new();
+        let mut keys = Vec::new();
+        let mut old_values = Vec::new();
+        for operation in &self.operations {
+            match operation {
+                WriteOperation::Put { key, .. } | WriteOperation::Delete { key } => {
+                    keys.push(key.clone());
+                }
+                WriteOperation::DeletePrefix { key_prefix } => {
+                    undo.delete_key_prefix(key_prefix.clone());
+                    for (suffix, value) in store.find_key_values_by_prefix(key_prefix).await? {
+                        old_values.push(([key_prefix.as_slice(), &suffix].concat(), value));
+                    }
+                }
+            }
+        }
+        for (key, value) in old_values {
+            undo.put_key_value_bytes(key, value);
+        }
+        let values = store.read_multi_values_bytes(keys.clone()).await?;
+        for (key, value) in keys.into_iter().zip(values) {
+            match value {
+                Some(value) => undo.put_key_value_bytes(key, value),
+                None => undo.delete_key(key),
+            }
+        }
+        Ok(undo)
+    }
+
     /// Simplifies the batch by removing operations that are overwritten by others.
     ///
     /// A key may appear multiple times in the batch, as an insert, a delete
@@ -294,6 +410,7 @@ impl Batch {
         };
         UnorderedBatch {
             key_prefix_deletions,
+            key_range_deletions: Vec::new(),
             simple_unordered_batch,
         }
     }
@@ -403,6 +520,10 @@ pub trait SimplifiedBatch: Sized + Send + Sync {
     /// Adds the insertion of a key-value pair to the batch.
     fn add_insert(&mut self, key: Vec<u8>, value: Vec<u8>);
 
+    /// Deserializes a batch stored in the serialization format that preceded the current one,
+    /// e.g. by a journal written before an upgrade.
+    fn from_legacy_bytes(bytes: &[u8]) -> Result<Self, bcs::Error>;
+
     /// Returns true if the batch is empty.
     fn is_empty(&self) -> bool {
         self.len() == 0
@@ -479,6 +600,11 @@ impl SimplifiedBatch for SimpleUnorderedBatch {
         self.insertions.push((key, value))
     }
 
+    fn from_legacy_bytes(bytes: &[u8]) -> Result<Self, bcs::Error> {
+        // The serialization format has not changed.
+        bcs::from_bytes(bytes)
+    }
+
     async fn from_batch<S: DeletePrefixExpander>(store: S, batch: Batch) -> Result<Self, S::Error> {
         let unordered_batch = batch.simplify();
         unordered_batch.expand_delete_prefixes(&store).await
@@ -538,6 +664,7 @@ impl BatchValueWriter<SimpleUnorderedBatch> for SimpleUnorderedBatchIter {
 /// The iterator that corresponds to a `SimpleUnorderedBatch`
 pub struct UnorderedBatchIter {
     delete_prefix_iter: Peekable<IntoIter<Vec<u8>>>,
+    delete_range_iter: Peekable<IntoIter<(Vec<u8>, Option<Vec<u8>>)>>,
     insert_deletion_iter: SimpleUnorderedBatchIter,
 }
 
@@ -546,15 +673,19 @@ impl SimplifiedBatch for UnorderedBatch {
 
     fn into_iter(self) -> Self::Iter {
         let delete_prefix_iter = self.key_prefix_deletions.into_iter().peekable();
+        let delete_range_iter = self.key_range_deletions.into_iter().peekable();
         let insert_deletion_iter = self.simple_unordered_batch.into_iter();
         Self::Iter {
             delete_prefix_iter,
+            delete_range_iter,
             insert_deletion_iter,
         }
     }
 
     fn len(&self) -> usize {
-        self.key_prefix_deletions.len() + self.simple_unordered_batch.len()
+        self.key_prefix_deletions.len()
+            + self.key_range_deletions.len()
+            + self.simple_unordered_batch.len()
     }
 
     fn num_bytes(&self) -> usize {
@@ -562,11 +693,15 @@ impl SimplifiedBatch for UnorderedBatch {
         for prefix_deletion in &self.key_prefix_deletions {
             total_size += prefix_deletion.len();
         }
+        for (lower_bound, upper_bound) in &self.key_range_deletions {
+            total_size += lower_bound.len() + upper_bound.as_ref().map_or(0, Vec::len);
+        }
         total_size
     }
 
     fn overhead_size(&self) -> usize {
         get_uleb128_size(self.key_prefix_deletions.len())
+            + get_uleb128_size(self.key_range_deletions.len())
             + self.simple_unordered_batch.overhead_size()
     }
 
@@ -578,18 +713,35 @@ impl SimplifiedBatch for UnorderedBatch {
         self.simple_unordered_batch.add_insert(key, value)
     }
 
-    async fn from_batch<S: DeletePrefixExpander>(store: S, batch: Batch) -> Result<Self, S::Error> {
+    fn from_legacy_bytes(bytes: &[u8]) -> Result<Self, bcs::Error> {
+        let LegacyUnorderedBatch {
+            key_prefix_deletions,
+            simple_unordered_batch,
+        } = bcs::from_bytes(bytes)?;
+        Ok(UnorderedBatch {
+            key_prefix_deletions,
+            key_range_deletions: Vec::new(),
+            simple_unordered_batch,
+        })
+    }
+
+    async fn from_batch<S: DeletePrefixExpander>(
+        _store: S,
+        batch: Batch,
+    ) -> Result<Self, S::Error> {
+        // Deleting ranges around the inserted keys avoids reading the deleted keys, and
+        // writing one tombstone for each of them.
         let mut unordered_batch = batch.simplify();
-        unordered_batch
-            .expand_colliding_prefix_deletions(&store)
-            .await?;
+        unordered_batch.split_colliding_prefix_deletions();
         Ok(unordered_batch)
     }
 }
 
 impl BatchValueWriter<UnorderedBatch> for UnorderedBatchIter {
     fn is_empty(&self) -> bool {
-        self.delete_prefix_iter.len() == 0 && self.insert_deletion_iter.is_empty()
+        self.delete_prefix_iter.len() == 0
+            && self.delete_range_iter.len() == 0
+            && self.insert_deletion_iter.is_empty()
     }
 
     fn write_next_value(
@@ -601,6 +753,10 @@ impl BatchValueWriter<UnorderedBatch> for UnorderedBatchIter {
             *batch_size += serialized_size(&delete_prefix)?;
             batch.key_prefix_deletions.push(delete_prefix);
             Ok(true)
+        } else if let Some(delete_range) = self.delete_range_iter.next() {
+            *batch_size += serialized_size(&delete_range)?;
+            batch.key_range_deletions.push(delete_range);
+            Ok(true)
         } else {
             self.insert_deletion_iter
                 .write_next_value(&mut batch.simple_unordered_batch, batch_size)
@@ -618,10 +774,22 @@ impl BatchValueWriter<UnorderedBatch> for UnorderedBatchIter {
                 batch_size
                     + next_size
                     + get_uleb128_size(batch.key_prefix_deletions.len() + 1)
+                    + get_uleb128_size(batch.key_range_deletions.len())
+                    + batch.simple_unordered_batch.overhead_size(),
+            ))
+        } else if let Some(delete_range) = self.delete_range_iter.peek() {
+            let next_size = serialized_size(&delete_range)?;
+            Ok(Some(
+                batch_size
+                    + next_size
+                    + get_uleb128_size(batch.key_prefix_deletions.len())
+                    + get_uleb128_size(batch.key_range_deletions.len() + 1)
                     + batch.simple_unordered_batch.overhead_size(),
             ))
         } else {
-            let batch_size = batch_size + get_uleb128_size(batch.key_prefix_deletions.len());
+            let batch_size = batch_size
+                + get_uleb128_size(batch.key_prefix_deletions.len())
+                + get_uleb128_size(batch.key_range_deletions.len());
             self.insert_deletion_iter
                 .next_batch_size(&batch.simple_unordered_batch, batch_size)
         }
@@ -631,9 +799,9 @@ impl BatchValueWriter<UnorderedBatch> for UnorderedBatchIter {
 #[cfg(test)]
 mod tests {
     use linera_views::{
-        batch::{Batch, SimpleUnorderedBatch, UnorderedBatch},
+        batch::{Batch, SimpleUnorderedBatch, SimplifiedBatch as _, UnorderedBatch},
         context::{Context, MemoryContext},
-        store::WritableKeyValueStore as _,
+        store::{ReadableKeyValueStore as _, WritableKeyValueStore as _},
     };
 
     #[test]
@@ -723,6 +891,7 @@ mod tests {
         let mut unordered_batch = UnorderedBatch {
             simple_unordered_batch,
             key_prefix_deletions,
+            key_range_deletions: vec![],
         };
         unordered_batch
             .expand_colliding_prefix_deletions(&context)
@@ -735,4 +904,89 @@ mod tests {
         );
         assert!(unordered_batch.key_prefix_deletions.is_empty());
     }
+
+    #[test]
+    fn test_split_colliding_prefix_deletions() {
+        let mut batch = Batch::new();
+        batch.delete_key_prefix(vec![1, 2]);
+        batch.delete_key_prefix(vec![3]);
+        batch.put_key_value_bytes(vec![1, 2, 3], vec![]);
+        batch.put_key_value_bytes(vec![1, 2, 5], vec![]);
+        let mut unordered_batch = batch.simplify();
+        unordered_batch.split_colliding_prefix_deletions();
+        assert_eq!(unordered_batch.key_prefix_deletions, vec![vec![3]]);
+        assert_eq!(
+            unordered_batch.key_range_deletions,
+            vec![
+                (vec![1, 2], Some(vec![1, 2, 3])),
+                (vec![1, 2, 3, 0], Some(vec![1, 2, 5])),
+                (vec![1, 2, 5, 0], Some(vec![1, 3])),
+            ]
+        );
+        assert_eq!(unordered_batch.simple_unordered_batch.insertions.len(), 2);
+    }
+
+    #[test]
+    fn test_unordered_batch_from_legacy_bytes() {
+        let legacy_batch = super::LegacyUnorderedBatch {
+            key_prefix_deletions: vec![vec![1, 2]],
+            simple_unordered_batch: SimpleUnorderedBatch {
+                deletions: vec![vec![3]],
+                insertions: vec![(vec![4], vec![5])],
+            },
+        };
+        let bytes = bcs::to_bytes(&legacy_batch).unwrap();
+        let unordered_batch = UnorderedBatch::from_legacy_bytes(&bytes).unwrap();
+        assert_eq!(unordered_batch.key_prefix_deletions, vec![vec![1, 2]]);
+        assert!(unordered_batch.key_range_deletions.is_empty());
+        assert_eq!(
+            unordered_batch.simple_unordered_batch.deletions,
+            vec![vec![3]]
+        );
+        assert_eq!(
+            unordered_batch.simple_unordered_batch.insertions,
+            vec![(vec![4], vec![5])]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_undo_batch() {
+        let context = MemoryContext::new_for_testing(());
+        let store = context.store();
+        let mut batch = Batch::new();
+        batch.put_key_value_bytes(vec![1, 2, 3], vec![1]);
+        batch.put_key_value_bytes(vec![1, 2, 4], vec![2]);
+        batch.put_key_value_bytes(vec![2, 1], vec![3]);
+        store.write_batch(batch).await.unwrap();
+        let before = store.find_key_values_by_prefix(&[]).await.unwrap();
+
+        let mut batch = Batch::new();
+        batch.delete_key_prefix(vec![1, 2]);
+        batch.put_key_value_bytes(vec![1, 2, 3], vec![4]);
+        batch.put_key_value_bytes(vec![2, 1], vec![5]);
+        batch.put_key_value_bytes(vec![2, 2], vec![6]);
+        let undo = batch.undo(store).await.unwrap();
+        store.write_batch(batch).await.unwrap();
+        assert_ne!(store.find_key_values_by_prefix(&[]).await.unwrap(), before);
+        store.write_batch(undo).await.unwrap();
+        assert_eq!(store.find_key_values_by_prefix(&[]).await.unwrap(), before);
+    }
+
+    #[test]
+    fn test_restrict_batch_to_prefix() {
+        let mut batch = Batch::new();
+        batch.put_key_value_bytes(vec![1, 2, 3], vec![1]);
+        batch.delete_key(vec![1, 3]);
+        batch.delete_key(vec![1, 2]);
+        batch.delete_key_prefix(vec![1]);
+        batch.delete_key_prefix(vec![1, 2, 4]);
+        batch.delete_key_prefix(vec![1, 3]);
+        let restricted = batch.restrict_to_prefix(&[1, 2]);
+        let mut expected = Batch::new();
+        expected.put_key_value_bytes(vec![1, 2, 3], vec![1]);
+        expected.delete_key(vec![1, 2]);
+        expected.delete_key_prefix(vec![1, 2]);
+        expected.delete_key_prefix(vec![1, 2, 4]);
+        assert_eq!(restricted, expected);
+    }
 }
@@ -0,0 +1,272 @@
+// Copyright (c) Zefchain Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! A change-data-capture feed of the commits of root views.
+//!
+//! The sinks of the [`ChangeLog`] attached to a context, with
+//! [`ViewContext::with_change_log`](crate::context::ViewContext::with_change_log), receive a
+//! [`ChangeLogEntry`] every time a root view using that context is saved, after its batch was
+//! successfully written to the store. Without a change log, saving a root view works exactly
+//! as before. Otherwise, the previous values of the written keys are read first, so that each
+//! change can report what it replaced.
+
+use std::{
+    fmt,
+    io::Write,
+    sync::{mpsc, Arc},
+};
+
+use linera_base::crypto::CryptoHash;
+use serde::{Deserialize, Serialize};
+use sha3::{Digest as _, Keccak256};
+use thiserror::Error;
+use tracing::warn;
+
+use crate::{
+    batch::{Batch, WriteOperation},
+    context::Context,
+    store::{ReadableKeyValueStore as _, WritableKeyValueStore as _},
+    views::View,
+    ViewError,
+};
+
+/// An error emitting a change log.
+#[derive(Debug, Error)]
+pub enum ChangeLogError {
+    /// The change log could not be serialized.
+    #[error(transparent)]
+    Serialization(#[from] serde_json::Error),
+    /// The receiver of the change logs is gone.
+    #[error("the change log receiver was dropped")]
+    Disconnected,
+}
+
+/// The destination of the change logs, e.g. a channel, a file, or a writer to a message
+/// broker.
+pub trait ChangeLogSink: Send + Sync {
+    /// Emits the changes of a root view commit.
+    ///
+    /// This is called after the commit, while the view is being saved: implementations
+    /// must not block, e.g. on IO, and should hand the entry over and return quickly.
+    fn emit(&self, entry: &ChangeLogEntry) -> Result<(), ChangeLogError>;
+}
+
+/// The changes written by saving a root view.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct ChangeLogEntry {
+    /// The namespace of the store the view was saved to, if the change log was given one.
+    #[serde(default)]
+    pub namespace: String,
+    /// The root key of the store the view was saved to, if the change log was given one,
+    /// e.g. to tell the chain states of a storage apart.
+    #[serde(default)]
+    pub root_key: Vec<u8>,
+    /// The name of the root view's type.
+    pub view_name: String,
+    /// The base key of the root view, which prefixes all the keys of its changes.
+    pub base_key: Vec<u8>,
+    /// The changes, in the order in which they were written.
+    pub changes: Vec<Change>,
+}
+
+/// A single change of a [`ChangeLogEntry`].
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub enum Change {
+    /// A key was set.
+    Put {
+        /// The key.
+        key: Vec<u8>,
+        /// The previous value, if there was one.
+        old_value: Option<ValueMetadata>,
+        /// The new value.
+        new_value: ValueMetadata,
+    },
+    /// A key was deleted.
+    Delete {
+        /// The key.
+        key: Vec<u8>,
+        /// The previous value, if there was one.
+        old_value: Option<ValueMetadata>,
+    },
+    /// All the keys with the given prefix were deleted.
+    DeletePrefix {
+        /// The prefix.
+        key_prefix: Vec<u8>,
+    },
+}
+
+/// A description of a value, to tell values apart without copying them.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct ValueMetadata {
+    /// The size of the value in bytes.
+    pub size: usize,
+    /// The Keccak-256 hash of the value.
+    pub hash: CryptoHash,
+}
+
+impl ValueMetadata {
+    /// Describes the given value.
+    pub fn new(value: &[u8]) -> Self {
+        let hash: [u8; 32] = Keccak256::digest(value).into();
+        ValueMetadata {
+            size: value.len(),
+            hash: hash.into(),
+        }
+    }
+}
+
+/// A sink sending the change logs to a channel.
+pub struct ChannelSink(tokio::sync::mpsc::UnboundedSender<ChangeLogEntry>);
+
+impl ChannelSink {
+    /// Creates a sink together with the receiver of its change logs.
+    pub fn new() -> (Self, tokio::sync::mpsc::UnboundedReceiver<ChangeLogEntry>) {
+        let (sender, receiver) = tokio::sync::mpsc::unbounded_channel();
+        (ChannelSink(sender), receiver)
+    }
+}
+
+impl ChangeLogSink for ChannelSink {
+    fn emit(&self, entry: &ChangeLogEntry) -> Result<(), ChangeLogError> {
+        self.0
+            .send(entry.clone())
+            .map_err(|_| ChangeLogError::Disconnected)
+    }
+}
+
+/// A sink writing the change logs as JSON lines, e.g. to a file or to the input of a
+/// producer for a Kafka-compatible message broker.
+///
+/// The lines are written in order by a dedicated thread, so that saving a view never waits
+/// for the writer. Failures to write are logged by that thread.
+pub struct WriterSink(mpsc::Sender<Vec<u8>>);
+
+impl WriterSink {
+    /// Creates a sink writing to the given writer.
+    pub fn new<W: Write + Send + 'static>(mut writer: W) -> Self {
+        let (sender, receiver) = mpsc::channel::<Vec<u8>>();
+        std::thread::spawn(move || {
+            for line in receiver {
+                if let Err(error) = writer.write_all(&line).and_then(|()| writer.flush()) {
+                    warn!("Failed to write a change log: {error}");
+                }
+            }
+        });
+        WriterSink(sender)
+    }
+}
+
+impl ChangeLogSink for WriterSink {
+    fn emit(&self, entry: &ChangeLogEntry) -> Result<(), ChangeLogError> {
+        let mut line = serde_json::to_vec(entry)?;
+        line.push(b'\n');
+        self.0.send(line).map_err(|_| ChangeLogError::Disconnected)
+    }
+}
+
+/// The sinks receiving the change logs of the root views saved through a context.
+#[derive(Clone, Default)]
+pub struct ChangeLog {
+    sinks: Vec<Arc<dyn ChangeLogSink>>,
+    namespace: String,
+    root_key: Vec<u8>,
+}
+
+impl ChangeLog {
+    /// Sets the namespace reported by the change logs.
+    pub fn with_namespace(mut self, namespace: impl Into<String>) -> Self {
+        self.namespace = namespace.into();
+        self
+    }
+
+    /// Sets the root key reported by the change logs. Storages set it for each of the root
+    /// keys they open, on a clone of their change log.
+    pub fn with_root_key(mut self, root_key: Vec<u8>) -> Self {
+        self.root_key = root_key;
+        self
+    }
+
+    /// Adds a sink to receive the change logs.
+    pub fn with_sink(mut self, sink: Arc<dyn ChangeLogSink>) -> Self {
+        self.sinks.push(sink);
+        self
+    }
+
+    /// Returns whether the change logs are sent anywhere.
+    pub fn is_empty(&self) -> bool {
+        self.sinks.is_empty()
+    }
+}
+
+impl fmt::Debug for ChangeLog {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ChangeLog")
+            .field("sinks", &self.sinks.len())
+            .field("namespace", &self.namespace)
+            .field("root_key", &self.root_key)
+            .finish()
+    }
+}
+
+/// Writes the batch of a root view to its store, and emits the changes to the sinks of the
+/// change log of its context. This is used by the `save` method of the derived `RootView`
+/// implementations.
+#[doc(hidden)]
+pub async fn write_batch<V: View>(view: &V, batch: Batch) -> Result<(), ViewError> {
+    let store = view.context().store();
+    let Some(change_log) = view.context().change_log().filter(|log| !log.is_empty()) else {
+        store.write_batch(batch).await?;
+        return Ok(());
+    };
+
+    let keys = batch
+        .operations
+        .iter()
+        .filter_map(|operation| match operation {
+            WriteOperation::Put { key, .. } | WriteOperation::Delete { key } => Some(key.clone()),
+            WriteOperation::DeletePrefix { .. } => None,
+        })
+        .collect::<Vec<_>>();
+    let mut old_values = store
+        .read_multi_values_bytes(keys)
+        .await?
+        .into_iter()
+        .map(|value| value.as_deref().map(ValueMetadata::new));
+    let changes = batch
+        .operations
+        .iter()
+        .map(|operation| match operation {
+            WriteOperation::Put { key, value } => Change::Put {
+                key: key.clone(),
+                old_value: old_values.next().flatten(),
+                new_value: ValueMetadata::new(value),
+            },
+            WriteOperation::Delete { key } => Change::Delete {
+                key: key.clone(),
+                old_value: old_values.next().flatten(),
+            },
+            WriteOperation::DeletePrefix { key_prefix } => Change::DeletePrefix {
+                key_prefix: key_prefix.clone(),
+            },
+        })
+        .collect();
+    store.write_batch(batch).await?;
+
+    let view_name = std::any::type_name::<V>();
+    let entry = ChangeLogEntry {
+        namespace: change_log.namespace.clone(),
+        root_key: change_log.root_key.clone(),
+        view_name: view_name.split('<').next().unwrap_or(view_name).to_owned(),
+        base_key: view.context().base_key().bytes.clone(),
+        changes,
+    };
+    for sink in &change_log.sinks {
+        if let Err(error) = sink.emit(&entry) {
+            warn!(
+                "Failed to emit the change log of {}: {error}",
+                entry.view_name
+            );
+        }
+    }
+    Ok(())
+}
@@ -12,6 +12,7 @@ use std::{
 };
 
 use serde::de::DeserializeOwned;
+use thiserror::Error;
 
 use crate::ViewError;
 
@@ -311,11 +312,217 @@ pub(crate) const fn get_uleb128_size(len: usize) -> usize {
     expo
 }
 
+/// The prefix of the names of the namespaces that had to be escaped.
+pub const ESCAPED_NAMESPACE_PREFIX: &str = "esc__";
+
+/// The alphabet of the base32 encoding of the escaped namespaces. It only has lowercase
+/// letters and digits, which all backends accept in names, even case-insensitive ones.
+const BASE32_ALPHABET: &[u8; 32] = b"abcdefghijklmnopqrstuvwxyz234567";
+
+/// The constraints of a storage backend on the names of its namespaces, e.g. DynamoDB
+/// tables or ScyllaDB tables.
+///
+/// Any namespace is mapped to a distinct name satisfying the constraints. The namespaces
+/// that the backend accepted before escaping was introduced are used as they are, so that
+/// existing namespaces keep their data, and the others are escaped. Escaped names are
+/// [`ESCAPED_NAMESPACE_PREFIX`] followed by the unpadded lowercase base32 encoding of the
+/// namespace.
+///
+/// The names starting with [`ESCAPED_NAMESPACE_PREFIX`] are reserved: a namespace starting
+/// with it is escaped as well, so it cannot take the name of another one.
+#[derive(Clone, Copy, Debug)]
+pub struct NamespaceRules {
+    /// The minimum length of a namespace used as it is.
+    pub min_len: usize,
+    /// The maximum length of a name, if there is one.
+    pub max_len: Option<usize>,
+    /// The characters allowed besides ASCII letters, digits and `_`, or `None` if any
+    /// character is allowed.
+    pub extra_characters: Option<&'static str>,
+    /// Whether names differing only by case refer to the same namespace. Namespaces with
+    /// uppercase letters are then escaped, so that they don't share the data of their
+    /// lowercase version.
+    pub case_insensitive: bool,
+}
+
+/// An error mapping a namespace to the name used by a storage backend.
+#[derive(Clone, Debug, Error, Eq, PartialEq)]
+pub enum NamespaceError {
+    /// The namespace is too long, once escaped.
+    #[error("Namespace {namespace:?} is longer than {max_len} characters once escaped")]
+    TooLong {
+        /// The namespace.
+        namespace: String,
+        /// The maximum length of a name.
+        max_len: usize,
+    },
+
+    /// The name is neither a namespace used as it is nor the escaped name of a namespace,
+    /// so it could collide with the name of another namespace.
+    #[error("Name {0:?} is not the name of a namespace")]
+    NotEscaped(String),
+}
+
+impl NamespaceRules {
+    /// The rules of the directories of RocksDB. The empty namespace was always accepted, and
+    /// is the directory of the database itself.
+    pub const ROCKS_DB: Self = NamespaceRules {
+        min_len: 0,
+        max_len: Some(255),
+        extra_characters: Some(""),
+        case_insensitive: false,
+    };
+
+    /// The rules of the [table names of DynamoDB](https://docs.aws.amazon.com/amazondynamodb/latest/developerguide/HowItWorks.NamingRulesDataTypes.html#HowItWorks.NamingRules).
+    pub const DYNAMO_DB: Self = NamespaceRules {
+        min_len: 3,
+        max_len: Some(255),
+        extra_characters: Some(".-"),
+        case_insensitive: false,
+    };
+
+    /// The rules of the table names of ScyllaDB, which are not quoted and therefore
+    /// lowercased.
+    pub const SCYLLA_DB: Self = NamespaceRules {
+        min_len: 1,
+        max_len: Some(48),
+        extra_characters: Some(""),
+        case_insensitive: true,
+    };
+
+    /// The rules of the object stores of IndexedDB.
+    pub const INDEXED_DB: Self = NamespaceRules {
+        min_len: 1,
+        max_len: None,
+        extra_characters: None,
+        case_insensitive: false,
+    };
+
+    /// The rules of the file names of the Origin Private File System.
+    pub const OPFS: Self = NamespaceRules {
+        min_len: 1,
+        max_len: Some(255),
+        extra_characters: Some("-"),
+        case_insensitive: false,
+    };
+
+    /// Returns the name of the given namespace in the storage backend.
+    pub fn escape(&self, namespace: &str) -> Result<String, NamespaceError> {
+        let name = if self.is_plain(namespace) {
+            namespace.to_owned()
+        } else {
+            format!(
+                "{ESCAPED_NAMESPACE_PREFIX}{}",
+                encode_base32(namespace.as_bytes())
+            )
+        };
+        if let Some(max_len) = self.max_len {
+            if name.len() > max_len {
+                return Err(NamespaceError::TooLong {
+                    namespace: namespace.to_owned(),
+                    max_len,
+                });
+            }
+        }
+        Ok(name)
+    }
+
+    /// Returns the namespace with the given name in the storage backend. This is the
+    /// inverse of [`NamespaceRules::escape`], and fails for the names that it doesn't
+    /// produce.
+    pub fn unescape(&self, name: &str) -> Result<String, NamespaceError> {
+        let namespace = match name.strip_prefix(ESCAPED_NAMESPACE_PREFIX) {
+            Some(encoded) => decode_base32(encoded)
+                .and_then(|bytes| String::from_utf8(bytes).ok())
+                .ok_or_else(|| NamespaceError::NotEscaped(name.to_owned()))?,
+            None => name.to_owned(),
+        };
+        // Only accept the name that escaping produces: any other one, e.g. with trailing
+        // bits in its encoding, would be a second name for the same namespace.
+        if self.escape(&namespace).ok().as_deref() != Some(name) {
+            return Err(NamespaceError::NotEscaped(name.to_owned()));
+        }
+        Ok(namespace)
+    }
+
+    /// Returns whether the namespace was accepted by the backend before escaping was
+    /// introduced, without sharing its name with another namespace, and is therefore used
+    /// as a name without escaping it.
+    fn is_plain(&self, namespace: &str) -> bool {
+        let reserved = namespace
+            .get(..ESCAPED_NAMESPACE_PREFIX.len())
+            .is_some_and(|start| {
+                if self.case_insensitive {
+                    start.eq_ignore_ascii_case(ESCAPED_NAMESPACE_PREFIX)
+                } else {
+                    start == ESCAPED_NAMESPACE_PREFIX
+                }
+            });
+        namespace.len() >= self.min_len
+            && !reserved
+            && namespace.bytes().all(|byte| self.is_plain_byte(byte))
+    }
+
+    /// Returns whether the byte may appear in a namespace used as it is.
+    fn is_plain_byte(&self, byte: u8) -> bool {
+        let Some(extra_characters) = self.extra_characters else {
+            return true;
+        };
+        byte.is_ascii_lowercase()
+            || byte.is_ascii_digit()
+            || byte == b'_'
+            || (byte.is_ascii_uppercase() && !self.case_insensitive)
+            || extra_characters.as_bytes().contains(&byte)
+    }
+}
+
+/// Encodes the bytes in base32 with [`BASE32_ALPHABET`], without padding.
+fn encode_base32(bytes: &[u8]) -> String {
+    let mut encoded = String::with_capacity((bytes.len() * 8).div_ceil(5));
+    let mut buffer = 0u32;
+    let mut bits = 0;
+    for byte in bytes {
+        buffer = (buffer << 8) | u32::from(*byte);
+        bits += 8;
+        while bits >= 5 {
+            bits -= 5;
+            encoded.push(char::from(
+                BASE32_ALPHABET[((buffer >> bits) & 31) as usize],
+            ));
+        }
+    }
+    if bits > 0 {
+        encoded.push(char::from(
+            BASE32_ALPHABET[((buffer << (5 - bits)) & 31) as usize],
+        ));
+    }
+    encoded
+}
+
+/// Decodes unpadded base32 with [`BASE32_ALPHABET`]. The trailing bits are ignored.
+fn decode_base32(encoded: &str) -> Option<Vec<u8>> {
+    let mut bytes = Vec::with_capacity(encoded.len() * 5 / 8);
+    let mut buffer = 0u32;
+    let mut bits = 0;
+    for character in encoded.bytes() {
+        let value = BASE32_ALPHABET
+            .iter()
+            .position(|letter| *letter == character)?;
+        buffer = (buffer << 5) | value as u32;
+        bits += 5;
+        if bits >= 8 {
+            bits -= 8;
+            bytes.push((buffer >> bits) as u8);
+        }
+    }
+    Some(bytes)
+}
+
 #[cfg(test)]
 mod tests {
     use std::collections::BTreeSet;
 
-    use linera_views::common::CustomSerialize;
+    use linera_views::common::{CustomSerialize, NamespaceError, NamespaceRules};
     use rand::Rng;
 
     #[test]
@@ -344,13 +551,131 @@ mod tests {
             assert_eq!(val2, val_ret2);
         }
     }
-}
 
-#[test]
-fn test_upper_bound() {
-    assert_eq!(get_upper_bound(&[255]), Unbounded);
-    assert_eq!(get_upper_bound(&[255, 255, 255, 255]), Unbounded);
-    assert_eq!(get_upper_bound(&[0, 2]), Excluded(vec![0, 3]));
-    assert_eq!(get_upper_bound(&[0, 255]), Excluded(vec![1]));
-    assert_eq!(get_upper_bound(&[255, 0]), Excluded(vec![255, 1]));
+    fn check_namespace_rules(rules: NamespaceRules, namespaces: &[&str]) {
+        let mut names = BTreeSet::new();
+        for namespace in namespaces {
+            let name = rules.escape(namespace).unwrap();
+            assert_eq!(rules.unescape(&name).unwrap(), *namespace);
+            let name = if rules.case_insensitive {
+                name.to_lowercase()
+            } else {
+                name
+            };
+            assert!(names.insert(name), "collision for {namespace:?}");
+        }
+    }
+
+    #[test]
+    fn test_rocks_db_namespace_rules() {
+        let rules = NamespaceRules::ROCKS_DB;
+        assert_eq!(rules.escape("table_test").unwrap(), "table_test");
+        assert_eq!(rules.escape("a/b").unwrap(), "esc__mexwe");
+        assert_eq!(rules.escape("..").unwrap(), "esc__fyxa");
+        check_namespace_rules(
+            rules,
+            &[
+                "",
+                "a",
+                "a_b",
+                "a/b",
+                "esc__a",
+                "esc__mexwe",
+                "Ab",
+                "ab",
+                "été",
+            ],
+        );
+    }
+
+    #[test]
+    fn test_dynamo_db_namespace_rules() {
+        let rules = NamespaceRules::DYNAMO_DB;
+        assert_eq!(rules.escape("table.test-1").unwrap(), "table.test-1");
+        assert_eq!(rules.escape("ab").unwrap(), "esc__mfra");
+        assert_eq!(rules.escape("a b").unwrap(), "esc__meqge");
+        check_namespace_rules(
+            rules,
+            &["", "a", "ab", "abc", "esc__a.b", "esc__mfra", "a.b", "a b"],
+        );
+        assert!(matches!(
+            rules.escape(&"/".repeat(160)),
+            Err(NamespaceError::TooLong { max_len: 255, .. })
+        ));
+    }
+
+    #[test]
+    fn test_scylla_db_namespace_rules() {
+        let rules = NamespaceRules::SCYLLA_DB;
+        assert_eq!(rules.escape("table_test").unwrap(), "table_test");
+        assert_eq!(rules.escape("t.a").unwrap(), "esc__oqxgc");
+        assert_eq!(rules.escape("T.a").unwrap(), "esc__kqxgc");
+        assert_eq!(rules.escape("Table").unwrap(), "esc__krqwe3df");
+        check_namespace_rules(
+            rules,
+            &[
+                "table", "Table", "TABLE", "t.a", "T.a", "t-a", "t_a", "été", "ESC__a", "esc__a",
+            ],
+        );
+        assert!(rules.escape(&"a".repeat(48)).is_ok());
+        assert!(rules.escape(&"a".repeat(49)).is_err());
+    }
+
+    #[test]
+    fn test_indexed_db_namespace_rules() {
+        let rules = NamespaceRules::INDEXED_DB;
+        assert_eq!(rules.escape("linera: été").unwrap(), "linera: été");
+        check_namespace_rules(rules, &["", "a", "a b", "esc__a", "esc__", "esc__mfra"]);
+    }
+
+    #[test]
+    fn test_opfs_namespace_rules() {
+        let rules = NamespaceRules::OPFS;
+        assert_eq!(rules.escape("linera-test").unwrap(), "linera-test");
+        assert_eq!(rules.escape("..").unwrap(), "esc__fyxa");
+        assert_eq!(rules.escape("a/b").unwrap(), "esc__mexwe");
+        check_namespace_rules(rules, &["", "a", "a-b", "a/b", "..", "esc__a", "Ab", "ab"]);
+    }
+
+    // Namespaces that the backends accepted before escaping was introduced must keep their
+    // names, or their data would no longer be found.
+    #[test]
+    fn test_existing_namespaces_are_kept() {
+        let cases = [
+            (NamespaceRules::ROCKS_DB, &["", "a", "A_b", "Table_1"][..]),
+            (
+                NamespaceRules::DYNAMO_DB,
+                &["abc", "A.b-C_d", "linera.Test"],
+            ),
+            (NamespaceRules::SCYLLA_DB, &["a", "table", "t_a_1"]),
+            (NamespaceRules::INDEXED_DB, &["a", "a b", "linera: été"]),
+        ];
+        for (rules, namespaces) in cases {
+            for namespace in namespaces {
+                assert_eq!(rules.escape(namespace).unwrap(), *namespace);
+                assert_eq!(rules.unescape(namespace).unwrap(), *namespace);
+            }
+        }
+    }
+
+    // Names that escaping doesn't produce are rejected, since they could collide with the
+    // name of a namespace.
+    #[test]
+    fn test_unescape_rejects_other_names() {
+        let rules = NamespaceRules::SCYLLA_DB;
+        for name in [
+            "esc__a",
+            "esc__mexwf",
+            "esc__1",
+            "esc__mfra",
+            "Table",
+            "a.b",
+            "",
+        ] {
+            assert!(
+                matches!(rules.unescape(name), Err(NamespaceError::NotEscaped(_))),
+                "{name:?} was accepted"
+            );
+        }
+    }
 }
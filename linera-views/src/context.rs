@@ -1,15 +1,24 @@
 // Copyright (c) Zefchain Labs, Inc.
 // SPDX-License-Identifier: Apache-2.0
 
+use std::future::Future;
+
 use serde::{de::DeserializeOwned, Serialize};
 
 use crate::{
     batch::DeletePrefixExpander,
+    change_log::ChangeLog,
+    fork::ForkStore,
     memory::MemoryStore,
     store::{KeyValueStoreError, ReadableKeyValueStore, WithError, WritableKeyValueStore},
     views::MIN_VIEW_TAG,
+    ViewError,
 };
 
+/// The share of a scan budget above which a scan counts as a near miss, in percent.
+#[cfg(with_metrics)]
+const SCAN_BUDGET_NEAR_MISS_PERCENT: usize = 80;
+
 /// A wrapper over `Vec<u8>` with functions for using it as a key prefix.
 #[derive(Default, Debug, Clone, derive_more::From)]
 pub struct BaseKey {
@@ -76,6 +85,85 @@ impl BaseKey {
     }
 }
 
+/// Limits on the results of a single prefix scan of the store, to protect a node from running
+/// out of memory when a view with a huge number of entries is iterated.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ScanBudget {
+    /// The maximum number of keys returned by a scan.
+    pub max_keys: Option<usize>,
+    /// The maximum total size of the keys and values returned by a scan, in bytes.
+    pub max_bytes: Option<usize>,
+}
+
+impl ScanBudget {
+    /// Returns whether a scan that found `keys` keys and `bytes` bytes exceeds the budget.
+    pub fn is_exceeded_by(&self, keys: usize, bytes: usize) -> bool {
+        self.max_keys.is_some_and(|max_keys| keys > max_keys)
+            || self.max_bytes.is_some_and(|max_bytes| bytes > max_bytes)
+    }
+
+    /// Returns whether the given entries, of the given sizes, fit in the budget.
+    pub fn admits<T>(&self, entries: &[T], size: impl Fn(&T) -> usize) -> bool {
+        let mut counter = ScanCounter::new(*self);
+        entries.iter().all(|entry| counter.record(size(entry)))
+    }
+
+    /// Returns the error of a scan that was stopped for exceeding the budget.
+    fn exceeded(&self) -> ViewError {
+        #[cfg(with_metrics)]
+        crate::metrics::SCAN_BUDGET_EXCEEDED_COUNTER
+            .with_label_values(&[])
+            .inc();
+        ViewError::ScanBudgetExceeded(*self)
+    }
+
+    /// Records the scans within the budget that came close to exceeding it.
+    #[cfg_attr(not(with_metrics), allow(unused_variables))]
+    fn record_near_miss(&self, keys: usize, bytes: usize) {
+        #[cfg(with_metrics)]
+        for (limit, max, used) in [
+            ("keys", self.max_keys, keys),
+            ("bytes", self.max_bytes, bytes),
+        ] {
+            if max.is_some_and(|max| {
+                used.saturating_mul(100) > max.saturating_mul(SCAN_BUDGET_NEAR_MISS_PERCENT)
+            }) {
+                crate::metrics::SCAN_BUDGET_NEAR_MISS_COUNTER
+                    .with_label_values(&[limit])
+                    .inc();
+            }
+        }
+    }
+}
+
+/// Counts the entries found by a scan, so that stores can stop scanning as soon as the
+/// entries exceed a [`ScanBudget`], instead of loading all of them first.
+#[derive(Clone, Debug)]
+pub struct ScanCounter {
+    budget: ScanBudget,
+    keys: usize,
+    bytes: usize,
+}
+
+impl ScanCounter {
+    /// Creates a counter for a scan with the given budget.
+    pub fn new(budget: ScanBudget) -> Self {
+        Self {
+            budget,
+            keys: 0,
+            bytes: 0,
+        }
+    }
+
+    /// Records an entry of `size` bytes, and returns whether the scan is still within the
+    /// budget.
+    pub fn record(&mut self, size: usize) -> bool {
+        self.keys += 1;
+        self.bytes = self.bytes.saturating_add(size);
+        !self.budget.is_exceeded_by(self.keys, self.bytes)
+    }
+}
+
 /// The context in which a view is operated. Typically, this includes the client to
 /// connect to the database and the address of the current entry.
 #[cfg_attr(not(web), trait_variant::make(Send + Sync))]
@@ -84,7 +172,10 @@ where
     crate::ViewError: From<Self::Error>,
 {
     /// The type of the key-value store used by this context.
-    type Store: ReadableKeyValueStore + WritableKeyValueStore + WithError<Error = Self::Error>;
+    type Store: ReadableKeyValueStore
+        + WritableKeyValueStore
+        + WithError<Error = Self::Error>
+        + Clone;
 
     /// User-provided data to be carried along.
     type Extra: Clone + Send + Sync;
@@ -105,12 +196,83 @@ where
     /// Mutable getter for the address of the base key.
     fn base_key_mut(&mut self) -> &mut BaseKey;
 
+    /// Returns the budget of the prefix scans made through this context, if any.
+    fn scan_budget(&self) -> Option<&ScanBudget> {
+        None
+    }
+
+    /// Returns the change log receiving the commits of the root views using this context,
+    /// if any.
+    fn change_log(&self) -> Option<&ChangeLog> {
+        None
+    }
+
+    // We can't use `async fn` here due to
+    // https://github.com/rust-lang/impl-trait-utils/issues/17.
+
+    /// Finds the keys matching the prefix, within the scan budget. The scan stops as soon as
+    /// the keys exceed the budget. The prefix is not included in the returned keys.
+    fn find_keys_by_prefix(
+        &self,
+        key_prefix: &[u8],
+    ) -> impl Future<Output = Result<Vec<Vec<u8>>, ViewError>> {
+        async move {
+            let Some(budget) = self.scan_budget() else {
+                return Ok(self.store().find_keys_by_prefix(key_prefix).await?);
+            };
+            let keys = self
+                .store()
+                .find_keys_by_prefix_within(key_prefix, *budget)
+                .await?
+                .ok_or_else(|| budget.exceeded())?;
+            budget.record_near_miss(keys.len(), keys.iter().map(Vec::len).sum());
+            Ok(keys)
+        }
+    }
+
+    /// Finds the `(key,value)` pairs matching the prefix, within the scan budget. The scan
+    /// stops as soon as the pairs exceed the budget. The prefix is not included in the
+    /// returned keys.
+    fn find_key_values_by_prefix(
+        &self,
+        key_prefix: &[u8],
+    ) -> impl Future<Output = Result<Vec<(Vec<u8>, Vec<u8>)>, ViewError>> {
+        async move {
+            let Some(budget) = self.scan_budget() else {
+                return Ok(self.store().find_key_values_by_prefix(key_prefix).await?);
+            };
+            let key_values = self
+                .store()
+                .find_key_values_by_prefix_within(key_prefix, *budget)
+                .await?
+                .ok_or_else(|| budget.exceeded())?;
+            let bytes = key_values
+                .iter()
+                .map(|(key, value)| key.len() + value.len())
+                .sum();
+            budget.record_near_miss(key_values.len(), bytes);
+            Ok(key_values)
+        }
+    }
+
     /// Obtains a similar [`Context`] implementation with a different base key.
     fn clone_with_base_key(&self, base_key: Vec<u8>) -> Self {
         let mut context = self.clone();
         context.base_key_mut().bytes = base_key;
         context
     }
+
+    /// Obtains a context over a copy-on-write fork of the store. Views loaded from it read
+    /// the current state, but whatever they save is only kept in memory.
+    fn fork(&self) -> ViewContext<Self::Extra, ForkStore<Self::Store>> {
+        let mut context = ViewContext::new_unsafe(
+            ForkStore::new(self.store().clone()),
+            self.base_key().bytes.clone(),
+            self.extra().clone(),
+        );
+        context.scan_budget = self.scan_budget().copied();
+        context
+    }
 }
 
 /// Implementation of the [`Context`] trait on top of a DB client implementing
@@ -123,6 +285,10 @@ pub struct ViewContext<E, S> {
     base_key: BaseKey,
     /// User-defined data attached to the view.
     extra: E,
+    /// The budget of the prefix scans, if any.
+    scan_budget: Option<ScanBudget>,
+    /// The change log of the root views, if any.
+    change_log: Option<ChangeLog>,
 }
 
 impl<E, S> ViewContext<E, S>
@@ -147,8 +313,22 @@ impl<E, S> ViewContext<E, S> {
             store,
             base_key: BaseKey { bytes: base_key },
             extra,
+            scan_budget: None,
+            change_log: None,
         }
     }
+
+    /// Limits the prefix scans made through this context and the contexts derived from it.
+    pub fn with_scan_budget(mut self, scan_budget: ScanBudget) -> Self {
+        self.scan_budget = Some(scan_budget);
+        self
+    }
+
+    /// Emits the commits of the root views using this context to the given change log.
+    pub fn with_change_log(mut self, change_log: ChangeLog) -> Self {
+        self.change_log = Some(change_log);
+        self
+    }
 }
 
 impl<E, S> Context for ViewContext<E, S>
@@ -177,6 +357,14 @@ where
     fn base_key_mut(&mut self) -> &mut BaseKey {
         &mut self.base_key
     }
+
+    fn scan_budget(&self) -> Option<&ScanBudget> {
+        self.scan_budget.as_ref()
+    }
+
+    fn change_log(&self) -> Option<&ChangeLog> {
+        self.change_log.as_ref()
+    }
 }
 
 /// An implementation of [`crate::context::Context`] that stores all values in memory.
@@ -190,6 +378,8 @@ impl<E> MemoryContext<E> {
             store: MemoryStore::new_for_testing(),
             base_key: BaseKey::default(),
             extra,
+            scan_budget: None,
+            change_log: None,
         }
     }
 }
@@ -60,4 +60,8 @@ pub enum ViewError {
     /// The values are incoherent.
     #[error("post load values error")]
     PostLoadValuesError,
+
+    /// A prefix scan found more entries or bytes than the budget of the context allows.
+    #[error("scan exceeded the budget of the context: {0:?}")]
+    ScanBudgetExceeded(crate::context::ScanBudget),
 }
@@ -85,6 +85,9 @@ pub use future_sync_ext::FutureSyncExt;
 /// Elementary data-structures implementing the [`views::View`] trait.
 pub mod views;
 
+/// Change-data-capture hooks for the commits of root views.
+pub mod change_log;
+
 /// Backend implementing the [`crate::store::KeyValueStore`] trait.
 pub mod backends;
 
@@ -110,11 +113,13 @@ pub use backends::dynamo_db;
 pub use backends::indexed_db;
 #[cfg(with_metrics)]
 pub use backends::metering;
+#[cfg(with_opfs)]
+pub use backends::opfs;
 #[cfg(with_rocksdb)]
 pub use backends::rocks_db;
 #[cfg(with_scylladb)]
 pub use backends::scylla_db;
-pub use backends::{journaling, lru_caching, memory, value_splitting};
+pub use backends::{fork, journaling, lru_caching, memory, value_splitting};
 pub use views::{
     bucket_queue_view, collection_view, hashable_wrapper, key_value_store_view, log_view, map_view,
     queue_view, reentrant_collection_view, register_view, set_view,
@@ -44,3 +44,23 @@ pub static SAVE_VIEW_COUNTER: LazyLock<IntCounterVec> = LazyLock::new(|| {
         &["type", "base_key"],
     )
 });
+
+/// The metric counting the prefix scans rejected for exceeding the scan budget.
+#[doc(hidden)]
+pub static SCAN_BUDGET_EXCEEDED_COUNTER: LazyLock<IntCounterVec> = LazyLock::new(|| {
+    prometheus_util::register_int_counter_vec(
+        "scan_budget_exceeded",
+        "The metric counting the prefix scans rejected for exceeding the scan budget",
+        &[],
+    )
+});
+
+/// The metric counting the prefix scans that came close to exceeding the scan budget.
+#[doc(hidden)]
+pub static SCAN_BUDGET_NEAR_MISS_COUNTER: LazyLock<IntCounterVec> = LazyLock::new(|| {
+    prometheus_util::register_int_counter_vec(
+        "scan_budget_near_miss",
+        "The metric counting the prefix scans that came close to exceeding the scan budget",
+        &["limit"],
+    )
+});
@@ -5,13 +5,14 @@
 
 use std::{fmt::Debug, future::Future};
 
-use serde::{de::DeserializeOwned, Serialize};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
 
 #[cfg(with_testing)]
 use crate::random::generate_test_namespace;
 use crate::{
     batch::{Batch, SimplifiedBatch},
     common::from_bytes_option,
+    context::ScanBudget,
     ViewError,
 };
 
@@ -75,6 +76,37 @@ pub trait ReadableKeyValueStore: WithError {
     // https://github.com/rust-lang/impl-trait-utils/issues/17, but once that bug is fixed
     // we can revert them to `async fn` syntax, which is neater.
 
+    /// Finds the keys matching the prefix, like [`Self::find_keys_by_prefix`], unless they
+    /// exceed the budget, in which case `None` is returned. Stores that can stop scanning as
+    /// soon as the budget is exceeded should override the default implementation, which
+    /// loads all the keys first.
+    fn find_keys_by_prefix_within(
+        &self,
+        key_prefix: &[u8],
+        budget: ScanBudget,
+    ) -> impl Future<Output = Result<Option<Vec<Vec<u8>>>, Self::Error>> {
+        async move {
+            let keys = self.find_keys_by_prefix(key_prefix).await?;
+            Ok(budget.admits(&keys, Vec::len).then_some(keys))
+        }
+    }
+
+    /// Finds the `(key,value)` pairs matching the prefix, like
+    /// [`Self::find_key_values_by_prefix`], unless they exceed the budget, in which case
+    /// `None` is returned. Stores that can stop scanning as soon as the budget is exceeded
+    /// should override the default implementation, which loads all the pairs first.
+    fn find_key_values_by_prefix_within(
+        &self,
+        key_prefix: &[u8],
+        budget: ScanBudget,
+    ) -> impl Future<Output = Result<Option<Vec<(Vec<u8>, Vec<u8>)>>, Self::Error>> {
+        async move {
+            let key_values = self.find_key_values_by_prefix(key_prefix).await?;
+            let admitted = budget.admits(&key_values, |(key, value)| key.len() + value.len());
+            Ok(admitted.then_some(key_values))
+        }
+    }
+
     /// Reads a single `key` and deserializes the result if present.
     fn read_value<V: DeserializeOwned>(
         &self,
@@ -96,6 +128,26 @@ pub trait ReadableKeyValueStore: WithError {
             Ok(values)
         }
     }
+
+    /// Starts recording the keys read through this store and its clones. Stores without a
+    /// cache don't record anything.
+    fn start_recording_reads(&self) {}
+
+    /// Stops recording and returns the keys read since [`Self::start_recording_reads`]
+    /// was called.
+    fn take_recorded_reads(&self) -> Vec<Vec<u8>> {
+        Vec::new()
+    }
+
+    /// Loads the values of the given keys into the cache of this store, so that reading
+    /// them later doesn't wait for the underlying storage. Stores without a cache ignore
+    /// this.
+    fn prefetch(&self, keys: Vec<Vec<u8>>) -> impl Future<Output = Result<(), Self::Error>> {
+        async move {
+            drop(keys);
+            Ok(())
+        }
+    }
 }
 
 /// Asynchronous write key-value operations.
@@ -134,6 +186,100 @@ pub trait DirectWritableKeyValueStore: WithError {
     async fn write_batch(&self, batch: Self::Batch) -> Result<(), Self::Error>;
 }
 
+/// The storage used by a namespace, as reported by [`KeyValueDatabase::namespace_stats`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct NamespaceStats {
+    /// The number of keys, including the ones used internally by the backend, or `None` if
+    /// the backend only keeps statistics about partitions.
+    pub key_count: Option<u64>,
+    /// The total size in bytes of the keys and values. Backends with statistics of their own
+    /// may report the size on disk instead, after compression.
+    pub total_bytes: u64,
+    /// Whether the numbers are estimates, e.g. extrapolated from a sample of the partitions.
+    pub estimated: bool,
+}
+
+/// The maximal number of partitions read by [`sample_namespace_stats`].
+const NAMESPACE_STATS_SAMPLE_SIZE: usize = 64;
+
+/// The maximal number of values read in each partition by [`sample_namespace_stats`].
+const NAMESPACE_STATS_VALUE_SAMPLE_SIZE: usize = 16;
+
+/// Estimates the storage used by the partitions with the given root keys, for backends
+/// without statistics of their own.
+///
+/// Only the keys of evenly spaced partitions are read, together with a few of their values
+/// to estimate the average size of a value, and the result is extrapolated to all the
+/// partitions. The shared partition, with the empty root key, is left out: it is much larger
+/// than the others and would skew the estimate.
+pub async fn sample_namespace_stats<D>(
+    database: &D,
+    root_keys: &[Vec<u8>],
+) -> Result<NamespaceStats, D::Error>
+where
+    D: KeyValueDatabase,
+    D::Store: ReadableKeyValueStore + WithError<Error = D::Error>,
+{
+    let root_keys = root_keys
+        .iter()
+        .filter(|root_key| !root_key.is_empty())
+        .collect::<Vec<_>>();
+    if root_keys.is_empty() {
+        return Ok(NamespaceStats::default());
+    }
+    let step = root_keys.len().div_ceil(NAMESPACE_STATS_SAMPLE_SIZE);
+    let mut sample_count = 0;
+    let mut key_count = 0;
+    let mut total_bytes = 0;
+    let mut estimated = false;
+    for root_key in root_keys.iter().step_by(step) {
+        let store = database.open_shared(root_key)?;
+        let keys = store.find_keys_by_prefix(&[]).await?;
+        key_count += keys.len() as u64;
+        total_bytes += keys.iter().map(|key| key.len() as u64).sum::<u64>();
+        let value_step = keys
+            .len()
+            .div_ceil(NAMESPACE_STATS_VALUE_SAMPLE_SIZE)
+            .max(1);
+        let sampled_keys = keys.iter().step_by(value_step).cloned().collect::<Vec<_>>();
+        let sampled_key_count = sampled_keys.len() as u64;
+        let value_bytes = store
+            .read_multi_values_bytes(sampled_keys)
+            .await?
+            .iter()
+            .map(|value| value.as_ref().map_or(0, |value| value.len() as u64))
+            .sum::<u64>();
+        if sampled_key_count > 0 {
+            total_bytes += value_bytes * keys.len() as u64 / sampled_key_count;
+        }
+        estimated |= value_step > 1;
+        sample_count += 1;
+    }
+    let extrapolate = |count: u64| {
+        let count = u128::from(count) * root_keys.len() as u128 / sample_count as u128;
+        u64::try_from(count).unwrap_or(u64::MAX)
+    };
+    Ok(NamespaceStats {
+        key_count: Some(extrapolate(key_count)),
+        total_bytes: extrapolate(total_bytes),
+        estimated: estimated || sample_count < root_keys.len(),
+    })
+}
+
+/// The class of the requests made through a store. Backends with tunable consistency may
+/// serve the classes where stale data is harmless with a weaker but faster consistency.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum RequestClass {
+    /// The requests of no other class, served with the default consistency of the backend.
+    #[default]
+    Default,
+    /// Reads of certificates and of the blocks they contain, which never change once
+    /// written.
+    CertificateReads,
+    /// Checks of which blobs are already stored, before fetching the missing ones.
+    BlobPrefetch,
+}
+
 /// The definition of a key-value database.
 #[cfg_attr(not(web), trait_variant::make(Send + Sync))]
 pub trait KeyValueDatabase: WithError + Sized {
@@ -160,6 +306,16 @@ pub trait KeyValueDatabase: WithError + Sized {
     /// implementations may choose to return an error if another client is detected.
     fn open_exclusive(&self, root_key: &[u8]) -> Result<Self::Store, Self::Error>;
 
+    /// Opens a shared partition starting at `root_key` for requests of the given class.
+    /// Backends without tunable consistency serve all the classes alike.
+    fn open_shared_for(
+        &self,
+        root_key: &[u8],
+        _class: RequestClass,
+    ) -> Result<Self::Store, Self::Error> {
+        self.open_shared(root_key)
+    }
+
     /// Obtains the list of existing namespaces.
     async fn list_all(config: &Self::Config) -> Result<Vec<String>, Self::Error>;
 
@@ -190,6 +346,11 @@ pub trait KeyValueDatabase: WithError + Sized {
     /// Deletes the given namespace.
     async fn delete(config: &Self::Config, namespace: &str) -> Result<(), Self::Error>;
 
+    /// Returns the approximate number of keys and bytes stored in the namespace of this
+    /// connection. Backends without statistics of their own estimate them with
+    /// [`sample_namespace_stats`].
+    async fn namespace_stats(&self) -> Result<NamespaceStats, Self::Error>;
+
     /// Initializes a storage if missing and provides it.
     fn maybe_create_and_connect(
         config: &Self::Config,
@@ -0,0 +1,310 @@
+// Copyright (c) Zefchain Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! Property-based equivalence testing of key-value stores.
+//!
+//! [`run_equivalence_test`] generates random sequences of batches and reads with `proptest`,
+//! applies them both to a fresh store of a [`TestKeyValueDatabase`] and to an in-memory
+//! model, and checks after every operation that both agree. Failing sequences are shrunk to
+//! a minimal one before the test panics.
+//!
+//! Keys are drawn from a small alphabet that includes `0x00` and `0xff`, so that batches
+//! often overwrite or delete keys that were just written, and prefix scans have to handle
+//! the upper bounds of ranges correctly. Some values are large enough to exercise the
+//! splitting of values by the backends that need it.
+//!
+//! Implementors of new backends can run the same harness against their
+//! [`TestKeyValueDatabase`].
+
+use std::{collections::BTreeMap, fmt::Debug};
+
+use proptest::{
+    collection::vec,
+    prelude::{any, prop_oneof},
+    strategy::{BoxedStrategy, Strategy, ValueTree},
+    test_runner::{Config, RngAlgorithm, TestRng, TestRunner},
+};
+use rand::{Rng as _, SeedableRng as _};
+
+use crate::{
+    batch::Batch,
+    store::{ReadableKeyValueStore, TestKeyValueDatabase, WritableKeyValueStore},
+};
+
+/// The bytes that keys are made of.
+const KEY_BYTES: [u8; 4] = [0, 1, 0xfe, 0xff];
+
+/// The parameters of [`run_equivalence_test`].
+#[derive(Clone, Debug)]
+pub struct EquivalenceTestConfig {
+    /// The number of random sequences of operations to run.
+    pub cases: u32,
+    /// The maximal number of operations in a sequence.
+    pub max_operations: usize,
+    /// The maximal number of writes in a batch.
+    pub max_batch_size: usize,
+    /// The maximal length of keys, excluding the one-byte prefix common to all keys.
+    pub max_key_len: usize,
+    /// The size of the largest values. It is capped by the `MAX_VALUE_SIZE` of the store.
+    pub max_big_value_size: usize,
+    /// The seed of the random number generator, so that failures can be reproduced.
+    pub seed: [u8; 32],
+}
+
+impl Default for EquivalenceTestConfig {
+    fn default() -> Self {
+        Self {
+            cases: 32,
+            max_operations: 12,
+            max_batch_size: 16,
+            max_key_len: 4,
+            max_big_value_size: 1_000_000,
+            seed: [0; 32],
+        }
+    }
+}
+
+/// A write in a batch of an [`Operation::Write`].
+#[derive(Clone, Debug)]
+pub enum WriteOperation {
+    /// Sets the value of a key.
+    Put { key: Vec<u8>, value: Vec<u8> },
+    /// Deletes a key.
+    Delete { key: Vec<u8> },
+    /// Deletes all the keys starting with a prefix.
+    DeletePrefix { key_prefix: Vec<u8> },
+}
+
+/// An operation applied to both the store and the model.
+#[derive(Clone, Debug)]
+pub enum Operation {
+    /// Writes a batch.
+    Write(Vec<WriteOperation>),
+    /// Reads the value of a key, and whether it exists.
+    ReadValue(Vec<u8>),
+    /// Reads several values at once, and whether they exist.
+    ReadMultiValues(Vec<Vec<u8>>),
+    /// Lists the keys, and the key-values, starting with a prefix.
+    FindByPrefix(Vec<u8>),
+}
+
+/// The expected content of a store: a sorted map from keys to values.
+#[derive(Debug, Default)]
+pub struct StoreModel {
+    key_values: BTreeMap<Vec<u8>, Vec<u8>>,
+}
+
+impl StoreModel {
+    /// Applies the writes of a batch, in order.
+    pub fn write(&mut self, writes: &[WriteOperation]) {
+        for write in writes {
+            match write {
+                WriteOperation::Put { key, value } => {
+                    self.key_values.insert(key.clone(), value.clone());
+                }
+                WriteOperation::Delete { key } => {
+                    self.key_values.remove(key);
+                }
+                WriteOperation::DeletePrefix { key_prefix } => {
+                    self.key_values
+                        .retain(|key, _| !key.starts_with(key_prefix));
+                }
+            }
+        }
+    }
+
+    /// Returns the value of `key`, if any.
+    pub fn read_value(&self, key: &[u8]) -> Option<Vec<u8>> {
+        self.key_values.get(key).cloned()
+    }
+
+    /// Returns the key-values starting with `key_prefix`, in order, without the prefix.
+    pub fn find_key_values_by_prefix(&self, key_prefix: &[u8]) -> Vec<(Vec<u8>, Vec<u8>)> {
+        self.key_values
+            .range(key_prefix.to_vec()..)
+            .take_while(|(key, _)| key.starts_with(key_prefix))
+            .map(|(key, value)| (key[key_prefix.len()..].to_vec(), value.clone()))
+            .collect()
+    }
+}
+
+fn key_strategy(max_key_len: usize) -> impl Strategy<Value = Vec<u8>> {
+    // All keys share a non-empty prefix, as some backends can't scan the empty prefix.
+    vec(proptest::sample::select(&KEY_BYTES[..]), 0..=max_key_len).prop_map(|suffix| {
+        let mut key = vec![KEY_BYTES[1]];
+        key.extend(suffix);
+        key
+    })
+}
+
+fn value_strategy(max_big_value_size: usize) -> impl Strategy<Value = Vec<u8>> {
+    // Large values are filled from a seed rather than byte by byte, which would be slow.
+    let big_value = (any::<u64>(), 0..=max_big_value_size).prop_map(|(seed, len)| {
+        let mut rng = rand::rngs::SmallRng::seed_from_u64(seed);
+        (0..len).map(|_| rng.gen()).collect::<Vec<u8>>()
+    });
+    prop_oneof![
+        8 => vec(any::<u8>(), 0..8),
+        1 => big_value,
+    ]
+}
+
+/// Returns the strategy generating the sequences of operations of [`run_equivalence_test`].
+pub fn operations_strategy(
+    config: &EquivalenceTestConfig,
+    max_value_size: usize,
+) -> BoxedStrategy<Vec<Operation>> {
+    let key = key_strategy(config.max_key_len);
+    let value = value_strategy(config.max_big_value_size.min(max_value_size));
+    let write = prop_oneof![
+        4 => (key.clone(), value).prop_map(|(key, value)| WriteOperation::Put { key, value }),
+        2 => key.clone().prop_map(|key| WriteOperation::Delete { key }),
+        1 => key
+            .clone()
+            .prop_map(|key_prefix| WriteOperation::DeletePrefix { key_prefix }),
+    ];
+    let operation = prop_oneof![
+        3 => vec(write, 1..=config.max_batch_size).prop_map(Operation::Write),
+        1 => key.clone().prop_map(Operation::ReadValue),
+        1 => vec(key.clone(), 0..8).prop_map(Operation::ReadMultiValues),
+        2 => key.prop_map(Operation::FindByPrefix),
+    ];
+    vec(operation, 1..=config.max_operations).boxed()
+}
+
+fn check_eq<T: PartialEq + Debug>(
+    index: usize,
+    what: &str,
+    actual: T,
+    expected: T,
+) -> Result<(), String> {
+    if actual == expected {
+        Ok(())
+    } else {
+        Err(format!(
+            "operation {index}: {what} returned {actual:?}, expected {expected:?}"
+        ))
+    }
+}
+
+/// Applies `operations` to `store`, which must be empty, and to a model, and returns an
+/// error describing the first difference between them.
+pub async fn check_operations<S>(store: &S, operations: &[Operation]) -> Result<(), String>
+where
+    S: ReadableKeyValueStore + WritableKeyValueStore,
+{
+    let mut model = StoreModel::default();
+    for (index, operation) in operations.iter().enumerate() {
+        match operation {
+            Operation::Write(writes) => {
+                let mut batch = Batch::new();
+                for write in writes {
+                    match write {
+                        WriteOperation::Put { key, value } => {
+                            batch.put_key_value_bytes(key.clone(), value.clone())
+                        }
+                        WriteOperation::Delete { key } => batch.delete_key(key.clone()),
+                        WriteOperation::DeletePrefix { key_prefix } => {
+                            batch.delete_key_prefix(key_prefix.clone())
+                        }
+                    }
+                }
+                store
+                    .write_batch(batch)
+                    .await
+                    .map_err(|error| format!("operation {index}: write_batch failed: {error}"))?;
+                model.write(writes);
+            }
+            Operation::ReadValue(key) => {
+                let value = store
+                    .read_value_bytes(key)
+                    .await
+                    .map_err(|error| format!("operation {index}: read failed: {error}"))?;
+                let contains = store
+                    .contains_key(key)
+                    .await
+                    .map_err(|error| format!("operation {index}: read failed: {error}"))?;
+                let expected = model.read_value(key);
+                check_eq(index, "contains_key", contains, expected.is_some())?;
+                check_eq(index, "read_value_bytes", value, expected)?;
+            }
+            Operation::ReadMultiValues(keys) => {
+                let values = store
+                    .read_multi_values_bytes(keys.clone())
+                    .await
+                    .map_err(|error| format!("operation {index}: read failed: {error}"))?;
+                let contains = store
+                    .contains_keys(keys.clone())
+                    .await
+                    .map_err(|error| format!("operation {index}: read failed: {error}"))?;
+                let expected = keys
+                    .iter()
+                    .map(|key| model.read_value(key))
+                    .collect::<Vec<_>>();
+                let expected_contains = expected.iter().map(Option::is_some).collect();
+                check_eq(index, "contains_keys", contains, expected_contains)?;
+                check_eq(index, "read_multi_values_bytes", values, expected)?;
+            }
+            Operation::FindByPrefix(key_prefix) => {
+                let keys = store
+                    .find_keys_by_prefix(key_prefix)
+                    .await
+                    .map_err(|error| format!("operation {index}: scan failed: {error}"))?;
+                let key_values = store
+                    .find_key_values_by_prefix(key_prefix)
+                    .await
+                    .map_err(|error| format!("operation {index}: scan failed: {error}"))?;
+                let expected = model.find_key_values_by_prefix(key_prefix);
+                let expected_keys = expected.iter().map(|(key, _)| key.clone()).collect();
+                check_eq(index, "find_keys_by_prefix", keys, expected_keys)?;
+                check_eq(index, "find_key_values_by_prefix", key_values, expected)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Runs random sequences of operations against fresh stores of the database `D` and
+/// against a model, and panics with a minimal failing sequence if they ever disagree.
+pub async fn run_equivalence_test<D: TestKeyValueDatabase>(config: EquivalenceTestConfig) {
+    let max_value_size = <D::Store as WritableKeyValueStore>::MAX_VALUE_SIZE;
+    let strategy = operations_strategy(&config, max_value_size);
+    let rng = TestRng::from_seed(RngAlgorithm::ChaCha, &config.seed);
+    let mut runner = TestRunner::new_with_rng(Config::default(), rng);
+    for _ in 0..config.cases {
+        let mut tree = strategy
+            .new_tree(&mut runner)
+            .expect("the strategy has no filters");
+        let Err(mut error) = check_with_fresh_store::<D>(&tree.current()).await else {
+            continue;
+        };
+        // Shrink the failing sequence, keeping the simplest one that still fails.
+        let mut minimal = tree.current();
+        while tree.simplify() {
+            loop {
+                match check_with_fresh_store::<D>(&tree.current()).await {
+                    Err(new_error) => {
+                        error = new_error;
+                        minimal = tree.current();
+                        break;
+                    }
+                    Ok(()) => {
+                        if !tree.complicate() {
+                            break;
+                        }
+                    }
+                }
+            }
+        }
+        panic!("The store differs from the model: {error}\nOperations: {minimal:#?}");
+    }
+}
+
+async fn check_with_fresh_store<D: TestKeyValueDatabase>(
+    operations: &[Operation],
+) -> Result<(), String> {
+    let store = D::new_test_store()
+        .await
+        .map_err(|error| format!("failed to create a test store: {error}"))?;
+    check_operations(&store, operations).await
+}
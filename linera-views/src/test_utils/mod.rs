@@ -3,6 +3,9 @@
 
 pub mod test_views;
 
+/// Property-based testing of stores against an in-memory model.
+pub mod equivalence;
+
 /// Functions for computing the performance of stores.
 #[cfg(not(target_arch = "wasm32"))]
 pub mod performance;
@@ -765,6 +768,39 @@ pub async fn namespace_admin_test<D: TestKeyValueDatabase>() {
     }
 }
 
+/// Checks that namespaces that are not valid names in the storage backend, e.g. because of
+/// their characters or their case, can still be created, listed and deleted.
+pub async fn namespace_escaping_admin_test<D: TestKeyValueDatabase>() {
+    let config = D::new_test_config().await.expect("config");
+    // Escaped names are longer, and ScyllaDB limits them to 48 characters.
+    let mut prefix = generate_test_namespace();
+    prefix.truncate(16);
+    let namespaces = ["_x", "_X", "/A", ".b", "-B", " é", "esc__"]
+        .into_iter()
+        .map(|suffix| format!("{prefix}{suffix}"))
+        .collect::<BTreeSet<_>>();
+    for namespace in &namespaces {
+        assert!(!D::exists(&config, namespace).await.expect("test"));
+        D::create(&config, namespace)
+            .await
+            .expect("creation of a namespace");
+        assert!(D::exists(&config, namespace).await.expect("test"));
+    }
+    assert_eq!(
+        namespaces_with_prefix::<D>(&config, &prefix).await,
+        namespaces
+    );
+    for namespace in &namespaces {
+        D::delete(&config, namespace)
+            .await
+            .expect("A successful deletion");
+        assert!(!D::exists(&config, namespace).await.expect("test"));
+    }
+    assert!(namespaces_with_prefix::<D>(&config, &prefix)
+        .await
+        .is_empty());
+}
+
 /// Tests listing the root keys.
 pub async fn root_key_admin_test<D>()
 where
@@ -488,7 +488,7 @@ impl<W: View> ByteCollectionView<W::Context, W> {
         let mut update = updates.next();
         if !self.delete_storage_first {
             let base = self.get_index_key(&[]);
-            for index in self.context.store().find_keys_by_prefix(&base).await? {
+            for index in self.context.find_keys_by_prefix(&base).await? {
                 loop {
                     match update {
                         Some((key, value)) if key <= &index => {
@@ -13,11 +13,21 @@
 //!
 //! Key tags to create the sub-keys of a `KeyValueStoreView` on top of the base key.
 
-use std::{collections::BTreeMap, fmt::Debug, mem, ops::Bound::Included, sync::Mutex};
+use std::{
+    collections::BTreeMap,
+    fmt::Debug,
+    mem,
+    ops::Bound::Included,
+    sync::{Arc, Mutex},
+};
 
 #[cfg(with_metrics)]
 use linera_base::prometheus_util::MeasureLatency as _;
-use linera_base::{data_types::ArithmeticError, ensure};
+use linera_base::{
+    data_types::ArithmeticError,
+    ensure,
+    merkle::{self, MerkleHash, MerkleProof, MerkleSibling},
+};
 use serde::{Deserialize, Serialize};
 
 use crate::{
@@ -130,7 +140,6 @@ mod metrics {
 use {
     crate::store::{KeyValueStoreError, WithError, WritableKeyValueStore},
     async_lock::RwLock,
-    std::sync::Arc,
     thiserror::Error,
 };
 
@@ -144,6 +153,10 @@ enum KeyTag {
     Sizes,
     /// Prefix for the hash.
     Hash,
+    /// The root of the Merkle tree of the stored entries, if the stored tree is up to date.
+    MerkleRoot,
+    /// Prefix for the nodes of the Merkle tree of the stored entries.
+    MerkleNode,
 }
 
 /// A pair containing the key and value size.
@@ -181,6 +194,243 @@ impl SizeData {
     }
 }
 
+/// A node of the Merkle tree of the entries of a [`KeyValueStoreView`].
+#[derive(Clone, Debug, Serialize, Deserialize)]
+enum MerkleNode {
+    /// The leaf of the only entry of the subtree, whose key has the given path.
+    Leaf { path: MerkleHash, hash: MerkleHash },
+    /// The root of a subtree with several entries.
+    Inner { left: MerkleHash, right: MerkleHash },
+}
+
+impl MerkleNode {
+    fn hash(&self) -> MerkleHash {
+        match self {
+            MerkleNode::Leaf { hash, .. } => *hash,
+            MerkleNode::Inner { left, right } => merkle::node_hash(left, right),
+        }
+    }
+
+    /// Returns the inner node at `depth` on `path`, with the given children on the path and
+    /// off it.
+    fn inner(path: &MerkleHash, depth: usize, on_path: MerkleHash, off_path: MerkleHash) -> Self {
+        if merkle::goes_right(path, depth) {
+            MerkleNode::Inner {
+                left: off_path,
+                right: on_path,
+            }
+        } else {
+            MerkleNode::Inner {
+                left: on_path,
+                right: off_path,
+            }
+        }
+    }
+}
+
+/// Returns the hash of the subtree with the given root, if any.
+fn subtree_hash(node: &Option<MerkleNode>) -> MerkleHash {
+    node.as_ref()
+        .map_or(merkle::EMPTY_SUBTREE, MerkleNode::hash)
+}
+
+/// Returns the position of the node at `depth` on `path`: the depth, followed by the bits of
+/// the path above the node.
+fn node_position(path: &MerkleHash, depth: usize) -> Vec<u8> {
+    let mut position = (depth as u16).to_be_bytes().to_vec();
+    let length = depth.div_ceil(8);
+    position.extend_from_slice(&path[..length]);
+    if depth % 8 != 0 {
+        position[1 + length] &= 0xff_u8 << (8 - depth % 8);
+    }
+    position
+}
+
+/// The changes to the stored Merkle tree of a [`KeyValueStoreView`] that bring it up to date
+/// with the entries of the view.
+#[derive(Debug, Default)]
+struct MerkleTreeUpdate {
+    /// The root of the updated tree.
+    root: MerkleHash,
+    /// Whether the stored nodes are discarded, the tree being rebuilt from all the entries.
+    rebuilt: bool,
+    /// The nodes to write, or to delete if `None`, by position.
+    nodes: BTreeMap<Vec<u8>, Option<MerkleNode>>,
+}
+
+impl MerkleTreeUpdate {
+    async fn node<C: Context>(
+        &self,
+        context: &C,
+        position: &[u8],
+    ) -> Result<Option<MerkleNode>, ViewError> {
+        if let Some(node) = self.nodes.get(position) {
+            return Ok(node.clone());
+        }
+        if self.rebuilt {
+            return Ok(None);
+        }
+        let key = context
+            .base_key()
+            .base_tag_index(KeyTag::MerkleNode as u8, position);
+        Ok(context.store().read_value(&key).await?)
+    }
+
+    /// Sets the leaf of the entry whose key has the given path, or removes the entry if
+    /// `leaf` is `None`. Only the nodes on the path, and the sibling of a removed leaf, are
+    /// read.
+    async fn set_leaf<C: Context>(
+        &mut self,
+        context: &C,
+        path: MerkleHash,
+        leaf: Option<MerkleHash>,
+    ) -> Result<(), ViewError> {
+        let mut ancestors = Vec::new();
+        let mut depth = 0;
+        let end = loop {
+            match self.node(context, &node_position(&path, depth)).await? {
+                Some(MerkleNode::Inner { left, right }) => {
+                    ancestors.push((left, right));
+                    depth += 1;
+                }
+                end => break end,
+            }
+        };
+        let mut node = match (end, leaf) {
+            (
+                Some(MerkleNode::Leaf {
+                    path: other,
+                    hash: other_hash,
+                }),
+                Some(hash),
+            ) if other != path => {
+                // Both leaves go down to the node where their paths part.
+                let split = (depth..)
+                    .find(|d| merkle::goes_right(&path, *d) != merkle::goes_right(&other, *d))
+                    .expect("different paths part at some depth");
+                self.nodes.insert(
+                    node_position(&path, split + 1),
+                    Some(MerkleNode::Leaf { path, hash }),
+                );
+                self.nodes.insert(
+                    node_position(&other, split + 1),
+                    Some(MerkleNode::Leaf {
+                        path: other,
+                        hash: other_hash,
+                    }),
+                );
+                let mut node = MerkleNode::inner(&path, split, hash, other_hash);
+                for depth in (depth..split).rev() {
+                    self.nodes
+                        .insert(node_position(&path, depth + 1), Some(node.clone()));
+                    node = MerkleNode::inner(&path, depth, node.hash(), merkle::EMPTY_SUBTREE);
+                }
+                Some(node)
+            }
+            (_, Some(hash)) => Some(MerkleNode::Leaf { path, hash }),
+            (Some(MerkleNode::Leaf { path: other, .. }), None) if other == path => None,
+            // There is no such entry to remove.
+            (_, None) => return Ok(()),
+        };
+        self.nodes.insert(node_position(&path, depth), node.clone());
+        while let Some((left, right)) = ancestors.pop() {
+            depth -= 1;
+            let sibling = if merkle::goes_right(&path, depth) {
+                left
+            } else {
+                right
+            };
+            node = match node {
+                // A leaf left alone in a subtree moves up to its root.
+                Some(leaf @ MerkleNode::Leaf { .. }) if sibling == merkle::EMPTY_SUBTREE => {
+                    self.nodes.insert(node_position(&path, depth + 1), None);
+                    Some(leaf)
+                }
+                None => {
+                    let mut sibling_path = path;
+                    sibling_path[depth / 8] ^= 0x80 >> (depth % 8);
+                    let sibling_position = node_position(&sibling_path, depth + 1);
+                    match self.node(context, &sibling_position).await? {
+                        Some(leaf @ MerkleNode::Leaf { .. }) => {
+                            self.nodes.insert(sibling_position, None);
+                            Some(leaf)
+                        }
+                        Some(MerkleNode::Inner { .. }) => Some(MerkleNode::inner(
+                            &path,
+                            depth,
+                            merkle::EMPTY_SUBTREE,
+                            sibling,
+                        )),
+                        None => None,
+                    }
+                }
+                node => Some(MerkleNode::inner(
+                    &path,
+                    depth,
+                    subtree_hash(&node),
+                    sibling,
+                )),
+            };
+            self.nodes.insert(node_position(&path, depth), node.clone());
+        }
+        Ok(())
+    }
+
+    /// Returns the root of the tree.
+    async fn compute_root<C: Context>(&self, context: &C) -> Result<MerkleHash, ViewError> {
+        let root = self
+            .node(context, &node_position(&merkle::EMPTY_SUBTREE, 0))
+            .await?;
+        Ok(subtree_hash(&root))
+    }
+
+    /// Returns the path from the leaf of the entry whose key has the given path to the root,
+    /// or `None` if there is no such entry.
+    async fn proof<C: Context>(
+        &self,
+        context: &C,
+        path: &MerkleHash,
+    ) -> Result<Option<MerkleProof>, ViewError> {
+        let mut siblings = Vec::new();
+        let mut depth = 0;
+        loop {
+            match self.node(context, &node_position(path, depth)).await? {
+                Some(MerkleNode::Inner { left, right }) => {
+                    siblings.push(if merkle::goes_right(path, depth) {
+                        MerkleSibling::Left(left)
+                    } else {
+                        MerkleSibling::Right(right)
+                    });
+                    depth += 1;
+                }
+                Some(MerkleNode::Leaf {
+                    path: leaf_path, ..
+                }) if leaf_path == *path => {
+                    siblings.reverse();
+                    return Ok(Some(MerkleProof { siblings }));
+                }
+                _ => return Ok(None),
+            }
+        }
+    }
+
+    fn flush<C: Context>(&self, context: &C, batch: &mut Batch) -> Result<(), ViewError> {
+        if self.rebuilt {
+            batch.delete_key_prefix(context.base_key().base_tag(KeyTag::MerkleNode as u8));
+        }
+        for (position, node) in &self.nodes {
+            let key = context
+                .base_key()
+                .base_tag_index(KeyTag::MerkleNode as u8, position);
+            match node {
+                Some(node) => batch.put_key_value(key, node)?,
+                None => batch.delete_key(key),
+            }
+        }
+        Ok(())
+    }
+}
+
 /// A view that represents the functions of `KeyValueStore`.
 ///
 /// Comment on the data set:
@@ -208,10 +458,12 @@ pub struct KeyValueStoreView<C> {
     sizes: ByteMapView<C, u32>,
     stored_hash: Option<HasherOutput>,
     hash: Mutex<Option<HasherOutput>>,
+    stored_merkle_root: Option<MerkleHash>,
+    merkle_tree: Mutex<Option<Arc<MerkleTreeUpdate>>>,
 }
 
 impl<C: Context> View for KeyValueStoreView<C> {
-    const NUM_INIT_KEYS: usize = 2 + ByteMapView::<C, u32>::NUM_INIT_KEYS;
+    const NUM_INIT_KEYS: usize = 3 + ByteMapView::<C, u32>::NUM_INIT_KEYS;
 
     type Context = C;
 
@@ -222,7 +474,8 @@ impl<C: Context> View for KeyValueStoreView<C> {
     fn pre_load(context: &C) -> Result<Vec<Vec<u8>>, ViewError> {
         let key_hash = context.base_key().base_tag(KeyTag::Hash as u8);
         let key_total_size = context.base_key().base_tag(KeyTag::TotalSize as u8);
-        let mut v = vec![key_hash, key_total_size];
+        let key_merkle_root = context.base_key().base_tag(KeyTag::MerkleRoot as u8);
+        let mut v = vec![key_hash, key_total_size, key_merkle_root];
         let base_key = context.base_key().base_tag(KeyTag::Sizes as u8);
         let context_sizes = context.clone_with_base_key(base_key);
         v.extend(ByteMapView::<C, u32>::pre_load(&context_sizes)?);
@@ -233,11 +486,12 @@ impl<C: Context> View for KeyValueStoreView<C> {
         let hash = from_bytes_option(values.first().ok_or(ViewError::PostLoadValuesError)?)?;
         let total_size =
             from_bytes_option_or_default(values.get(1).ok_or(ViewError::PostLoadValuesError)?)?;
+        let merkle_root = from_bytes_option(values.get(2).ok_or(ViewError::PostLoadValuesError)?)?;
         let base_key = context.base_key().base_tag(KeyTag::Sizes as u8);
         let context_sizes = context.clone_with_base_key(base_key);
         let sizes = ByteMapView::post_load(
             context_sizes,
-            values.get(2..).ok_or(ViewError::PostLoadValuesError)?,
+            values.get(3..).ok_or(ViewError::PostLoadValuesError)?,
         )?;
         Ok(Self {
             context,
@@ -248,6 +502,8 @@ impl<C: Context> View for KeyValueStoreView<C> {
             sizes,
             stored_hash: hash,
             hash: Mutex::new(hash),
+            stored_merkle_root: merkle_root,
+            merkle_tree: Mutex::new(None),
         })
     }
 
@@ -263,6 +519,7 @@ impl<C: Context> View for KeyValueStoreView<C> {
         self.total_size = self.stored_total_size;
         self.sizes.rollback();
         *self.hash.get_mut().unwrap() = self.stored_hash;
+        *self.merkle_tree.get_mut().unwrap() = None;
     }
 
     async fn has_pending_changes(&self) -> bool {
@@ -278,12 +535,19 @@ impl<C: Context> View for KeyValueStoreView<C> {
         if self.sizes.has_pending_changes().await {
             return true;
         }
+        let merkle_tree = self.merkle_tree.lock().unwrap().clone();
+        if merkle_tree.is_some_and(|tree| {
+            tree.rebuilt || !tree.nodes.is_empty() || self.stored_merkle_root != Some(tree.root)
+        }) {
+            return true;
+        }
         let hash = self.hash.lock().unwrap();
         self.stored_hash != *hash
     }
 
     fn flush(&mut self, batch: &mut Batch) -> Result<bool, ViewError> {
         let mut delete_view = false;
+        let entries_changed = self.deletion_set.has_pending_changes() || !self.updates.is_empty();
         if self.deletion_set.delete_storage_first {
             delete_view = true;
             self.stored_total_size = SizeData::default();
@@ -298,7 +562,8 @@ impl<C: Context> View for KeyValueStoreView<C> {
                     delete_view = false;
                 }
             }
-            self.stored_hash = None
+            self.stored_hash = None;
+            self.stored_merkle_root = None;
         } else {
             for index in mem::take(&mut self.deletion_set.deleted_prefixes) {
                 let key = self
@@ -333,6 +598,23 @@ impl<C: Context> View for KeyValueStoreView<C> {
             batch.put_key_value(key, &self.total_size)?;
             self.stored_total_size = self.total_size;
         }
+        let merkle_root_key = self.context.base_key().base_tag(KeyTag::MerkleRoot as u8);
+        match self.merkle_tree.get_mut().unwrap().take() {
+            Some(tree) if !delete_view => {
+                tree.flush(&self.context, batch)?;
+                if self.stored_merkle_root != Some(tree.root) {
+                    batch.put_key_value(merkle_root_key, &tree.root)?;
+                    self.stored_merkle_root = Some(tree.root);
+                }
+            }
+            _ if entries_changed => {
+                // The stored tree is out of date until it is rebuilt.
+                if self.stored_merkle_root.take().is_some() {
+                    batch.delete_key(merkle_root_key);
+                }
+            }
+            _ => {}
+        }
         self.deletion_set.delete_storage_first = false;
         Ok(delete_view)
     }
@@ -343,6 +625,7 @@ impl<C: Context> View for KeyValueStoreView<C> {
         self.total_size = SizeData::default();
         self.sizes.clear();
         *self.hash.get_mut().unwrap() = None;
+        *self.merkle_tree.get_mut().unwrap() = None;
     }
 }
 
@@ -357,6 +640,8 @@ impl<C: Context> ClonableView for KeyValueStoreView<C> {
             sizes: self.sizes.clone_unchecked()?,
             stored_hash: self.stored_hash,
             hash: Mutex::new(*self.hash.get_mut().unwrap()),
+            stored_merkle_root: self.stored_merkle_root,
+            merkle_tree: Mutex::new(self.merkle_tree.get_mut().unwrap().clone()),
         })
     }
 }
@@ -415,12 +700,7 @@ impl<C: Context> KeyValueStoreView<C> {
         if !self.deletion_set.delete_storage_first {
             let mut suffix_closed_set =
                 SuffixClosedSetIterator::new(0, self.deletion_set.deleted_prefixes.iter());
-            for index in self
-                .context
-                .store()
-                .find_keys_by_prefix(&key_prefix)
-                .await?
-            {
+            for index in self.context.find_keys_by_prefix(&key_prefix).await? {
                 loop {
                     match update {
                         Some((key, value)) if key <= &index => {
@@ -518,12 +798,7 @@ impl<C: Context> KeyValueStoreView<C> {
         if !self.deletion_set.delete_storage_first {
             let mut suffix_closed_set =
                 SuffixClosedSetIterator::new(0, self.deletion_set.deleted_prefixes.iter());
-            for entry in self
-                .context
-                .store()
-                .find_key_values_by_prefix(&key_prefix)
-                .await?
-            {
+            for entry in self.context.find_key_values_by_prefix(&key_prefix).await? {
                 let (index, index_val) = entry;
                 loop {
                     match update {
@@ -854,6 +1129,7 @@ impl<C: Context> KeyValueStoreView<C> {
         #[cfg(with_metrics)]
         let _latency = metrics::KEY_VALUE_STORE_VIEW_WRITE_BATCH_LATENCY.measure_latency();
         *self.hash.get_mut().unwrap() = None;
+        *self.merkle_tree.get_mut().unwrap() = None;
         let max_key_size = self.max_key_size();
         for operation in batch.operations {
             match operation {
@@ -1009,12 +1285,7 @@ impl<C: Context> KeyValueStoreView<C> {
         if !self.deletion_set.delete_storage_first {
             let mut suffix_closed_set =
                 SuffixClosedSetIterator::new(0, self.deletion_set.deleted_prefixes.iter());
-            for key in self
-                .context
-                .store()
-                .find_keys_by_prefix(&key_prefix_full)
-                .await?
-            {
+            for key in self.context.find_keys_by_prefix(&key_prefix_full).await? {
                 loop {
                     match update {
                         Some((update_key, update_value))
@@ -1092,7 +1363,6 @@ impl<C: Context> KeyValueStoreView<C> {
                 SuffixClosedSetIterator::new(0, self.deletion_set.deleted_prefixes.iter());
             for entry in self
                 .context
-                .store()
                 .find_key_values_by_prefix(&key_prefix_full)
                 .await?
             {
@@ -1146,6 +1416,103 @@ impl<C: Context> KeyValueStoreView<C> {
         hasher.update_with_bcs_bytes(&count)?;
         Ok(hasher.finalize())
     }
+
+    /// Returns whether the Merkle tree of the stored entries is stored as well. If not,
+    /// [`KeyValueStoreView::merkle_root`] rebuilds it from all the entries, and the rebuilt
+    /// tree is only kept if the view is saved.
+    pub fn stores_merkle_tree(&self) -> bool {
+        self.stored_merkle_root.is_some()
+    }
+
+    /// Returns the changes to the stored Merkle tree that bring it up to date with the
+    /// entries of the view. They are cached until the view is changed or saved.
+    async fn merkle_tree_update(&self) -> Result<Arc<MerkleTreeUpdate>, ViewError> {
+        let cached = self.merkle_tree.lock().unwrap().clone();
+        if let Some(tree) = cached {
+            return Ok(tree);
+        }
+        let mut tree = MerkleTreeUpdate::default();
+        match self.stored_merkle_root {
+            Some(root) if !self.deletion_set.has_pending_changes() && self.updates.is_empty() => {
+                tree.root = root;
+                return Ok(Arc::new(tree));
+            }
+            Some(_) if !self.deletion_set.delete_storage_first => {
+                for prefix in &self.deletion_set.deleted_prefixes {
+                    let key_prefix = self
+                        .context
+                        .base_key()
+                        .base_tag_index(KeyTag::Index as u8, prefix);
+                    for suffix in self.context.find_keys_by_prefix(&key_prefix).await? {
+                        let index = [prefix.as_slice(), &suffix].concat();
+                        tree.set_leaf(&self.context, merkle::key_path(&index), None)
+                            .await?;
+                    }
+                }
+                for (index, update) in &self.updates {
+                    let leaf = match update {
+                        Update::Set(value) => Some(merkle::leaf_hash(index, value)),
+                        Update::Removed => None,
+                    };
+                    tree.set_leaf(&self.context, merkle::key_path(index), leaf)
+                        .await?;
+                }
+            }
+            _ => {
+                tree.rebuilt = true;
+                for (index, value) in self.index_values().await? {
+                    let leaf = merkle::leaf_hash(&index, &value);
+                    tree.set_leaf(&self.context, merkle::key_path(&index), Some(leaf))
+                        .await?;
+                }
+            }
+        }
+        tree.root = tree.compute_root(&self.context).await?;
+        let tree = Arc::new(tree);
+        *self.merkle_tree.lock().unwrap() = Some(tree.clone());
+        Ok(tree)
+    }
+
+    /// Returns the root of the sparse Merkle tree whose leaves are the entries of the view,
+    /// as described in [`linera_base::merkle`].
+    ///
+    /// The tree is stored with the view, so only the paths of the entries changed since the
+    /// view was last saved are read and updated. If the stored tree is out of date, because
+    /// the view was saved without computing its root, the tree is rebuilt from all the
+    /// entries instead.
+    pub async fn merkle_root(&self) -> Result<MerkleHash, ViewError> {
+        Ok(self.merkle_tree_update().await?.root)
+    }
+
+    /// Returns the value under `key`, with a proof that the entry is part of the Merkle tree
+    /// whose root is [`KeyValueStoreView::merkle_root`], or `None` if there is no such key.
+    /// ```rust
+    /// # tokio_test::block_on(async {
+    /// # use linera_views::context::MemoryContext;
+    /// # use linera_views::key_value_store_view::KeyValueStoreView;
+    /// # use linera_views::views::View;
+    /// # use linera_base::merkle::leaf_hash;
+    /// # let context = MemoryContext::new_for_testing(());
+    /// let mut view = KeyValueStoreView::load(context).await.unwrap();
+    /// view.insert(vec![0, 1], vec![42]).await.unwrap();
+    /// view.insert(vec![0, 2], vec![43]).await.unwrap();
+    /// let (value, proof) = view.inclusion_proof(&[0, 2]).await.unwrap().unwrap();
+    /// assert_eq!(value, vec![43]);
+    /// let root = proof.root(leaf_hash(&[0, 2], &value));
+    /// assert_eq!(root, view.merkle_root().await.unwrap());
+    /// # })
+    /// ```
+    pub async fn inclusion_proof(
+        &self,
+        key: &[u8],
+    ) -> Result<Option<(Vec<u8>, MerkleProof)>, ViewError> {
+        let Some(value) = self.get(key).await? else {
+            return Ok(None);
+        };
+        let tree = self.merkle_tree_update().await?;
+        let proof = tree.proof(&self.context, &merkle::key_path(key)).await?;
+        Ok(proof.map(|proof| (value, proof)))
+    }
 }
 
 impl<C: Context> HashableView for KeyValueStoreView<C> {
@@ -5,7 +5,7 @@ use std::ops::{Bound, Range, RangeBounds};
 
 #[cfg(with_metrics)]
 use linera_base::prometheus_util::MeasureLatency as _;
-use serde::{de::DeserializeOwned, Serialize};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
 
 use crate::{
     batch::Batch,
@@ -41,15 +41,31 @@ enum KeyTag {
     Count = MIN_VIEW_TAG,
     /// Prefix for the indices of the log.
     Index,
+    /// Prefix for the storing of the checkpoint of the compacted entries.
+    Checkpoint,
+}
+
+/// The entries at the start of a log that were folded into a single value.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+struct Checkpoint {
+    /// The number of compacted entries. Their indices don't have values anymore.
+    count: usize,
+    /// The serialized result of folding the compacted entries.
+    value: Vec<u8>,
 }
 
 /// A view that supports logging values of type `T`.
+///
+/// The entries at the start of the log can be compacted into a checkpoint, if only an aggregate
+/// of them is needed.
 #[derive(Debug)]
 pub struct LogView<C, T> {
     context: C,
     delete_storage_first: bool,
     stored_count: usize,
     new_values: Vec<T>,
+    stored_checkpoint: Option<Checkpoint>,
+    checkpoint: Option<Checkpoint>,
 }
 
 impl<C, T> View for LogView<C, T>
@@ -57,7 +73,7 @@ where
     C: Context,
     T: Send + Sync + Serialize,
 {
-    const NUM_INIT_KEYS: usize = 1;
+    const NUM_INIT_KEYS: usize = 2;
 
     type Context = C;
 
@@ -66,17 +82,24 @@ where
     }
 
     fn pre_load(context: &C) -> Result<Vec<Vec<u8>>, ViewError> {
-        Ok(vec![context.base_key().base_tag(KeyTag::Count as u8)])
+        Ok(vec![
+            context.base_key().base_tag(KeyTag::Count as u8),
+            context.base_key().base_tag(KeyTag::Checkpoint as u8),
+        ])
     }
 
     fn post_load(context: C, values: &[Option<Vec<u8>>]) -> Result<Self, ViewError> {
         let stored_count =
             from_bytes_option_or_default(values.first().ok_or(ViewError::PostLoadValuesError)?)?;
+        let stored_checkpoint: Option<Checkpoint> =
+            from_bytes_option_or_default(values.get(1).ok_or(ViewError::PostLoadValuesError)?)?;
         Ok(Self {
             context,
             delete_storage_first: false,
             stored_count,
             new_values: Vec::new(),
+            checkpoint: stored_checkpoint.clone(),
+            stored_checkpoint,
         })
     }
 
@@ -89,13 +112,14 @@ where
     fn rollback(&mut self) {
         self.delete_storage_first = false;
         self.new_values.clear();
+        self.checkpoint.clone_from(&self.stored_checkpoint);
     }
 
     async fn has_pending_changes(&self) -> bool {
         if self.delete_storage_first {
             return true;
         }
-        !self.new_values.is_empty()
+        !self.new_values.is_empty() || self.checkpoint != self.stored_checkpoint
     }
 
     fn flush(&mut self, batch: &mut Batch) -> Result<bool, ViewError> {
@@ -103,16 +127,37 @@ where
         if self.delete_storage_first {
             batch.delete_key_prefix(self.context.base_key().bytes.clone());
             self.stored_count = 0;
+            self.stored_checkpoint = None;
             delete_view = true;
         }
-        if !self.new_values.is_empty() {
+        let compacted_count = self.compacted_count();
+        if self.checkpoint != self.stored_checkpoint {
             delete_view = false;
-            for value in &self.new_values {
+            let stored_compacted_count = self
+                .stored_checkpoint
+                .as_ref()
+                .map_or(0, |checkpoint| checkpoint.count);
+            for index in stored_compacted_count..compacted_count.min(self.stored_count) {
                 let key = self
                     .context
                     .base_key()
-                    .derive_tag_key(KeyTag::Index as u8, &self.stored_count)?;
-                batch.put_key_value(key, value)?;
+                    .derive_tag_key(KeyTag::Index as u8, &index)?;
+                batch.delete_key(key);
+            }
+            let key = self.context.base_key().base_tag(KeyTag::Checkpoint as u8);
+            batch.put_key_value(key, &self.checkpoint)?;
+            self.stored_checkpoint.clone_from(&self.checkpoint);
+        }
+        if !self.new_values.is_empty() {
+            delete_view = false;
+            for value in &self.new_values {
+                if self.stored_count >= compacted_count {
+                    let key = self
+                        .context
+                        .base_key()
+                        .derive_tag_key(KeyTag::Index as u8, &self.stored_count)?;
+                    batch.put_key_value(key, value)?;
+                }
                 self.stored_count += 1;
             }
             let key = self.context.base_key().base_tag(KeyTag::Count as u8);
@@ -126,6 +171,7 @@ where
     fn clear(&mut self) {
         self.delete_storage_first = true;
         self.new_values.clear();
+        self.checkpoint = None;
     }
 }
 
@@ -140,6 +186,8 @@ where
             delete_storage_first: self.delete_storage_first,
             stored_count: self.stored_count,
             new_values: self.new_values.clone(),
+            stored_checkpoint: self.stored_checkpoint.clone(),
+            checkpoint: self.checkpoint.clone(),
         })
     }
 }
@@ -184,6 +232,25 @@ where
         }
     }
 
+    /// Returns the number of entries at the start of the log that were compacted. Their
+    /// indices don't have values anymore, but they are still included in [`Self::count`].
+    pub fn compacted_count(&self) -> usize {
+        self.checkpoint
+            .as_ref()
+            .map_or(0, |checkpoint| checkpoint.count)
+    }
+
+    /// Reads the value the compacted entries were folded into, if any.
+    pub fn checkpoint<S>(&self) -> Result<Option<S>, ViewError>
+    where
+        S: DeserializeOwned,
+    {
+        let Some(checkpoint) = &self.checkpoint else {
+            return Ok(None);
+        };
+        Ok(Some(bcs::from_bytes(&checkpoint.value)?))
+    }
+
     /// Obtains the extra data.
     pub fn extra(&self) -> &C::Extra {
         self.context.extra()
@@ -195,7 +262,8 @@ where
     C: Context,
     T: Clone + DeserializeOwned + Serialize + Send + Sync,
 {
-    /// Reads the logged value with the given index (including staged ones).
+    /// Reads the logged value with the given index (including staged ones). Compacted entries
+    /// are `None`.
     /// ```rust
     /// # tokio_test::block_on(async {
     /// # use linera_views::context::MemoryContext;
@@ -208,7 +276,9 @@ where
     /// # })
     /// ```
     pub async fn get(&self, index: usize) -> Result<Option<T>, ViewError> {
-        let value = if self.delete_storage_first {
+        let value = if index < self.compacted_count() {
+            None
+        } else if self.delete_storage_first {
             self.new_values.get(index).cloned()
         } else if index < self.stored_count {
             let key = self
@@ -222,7 +292,7 @@ where
         Ok(value)
     }
 
-    /// Reads several logged keys (including staged ones)
+    /// Reads several logged keys (including staged ones). Compacted entries are `None`.
     /// ```rust
     /// # tokio_test::block_on(async {
     /// # use linera_views::context::MemoryContext;
@@ -239,16 +309,23 @@ where
     /// # })
     /// ```
     pub async fn multi_get(&self, indices: Vec<usize>) -> Result<Vec<Option<T>>, ViewError> {
+        let compacted_count = self.compacted_count();
         let mut result = Vec::new();
         if self.delete_storage_first {
             for index in indices {
-                result.push(self.new_values.get(index).cloned());
+                if index < compacted_count {
+                    result.push(None);
+                } else {
+                    result.push(self.new_values.get(index).cloned());
+                }
             }
         } else {
             let mut keys = Vec::new();
             let mut positions = Vec::new();
             for (pos, index) in indices.into_iter().enumerate() {
-                if index < self.stored_count {
+                if index < compacted_count {
+                    result.push(None);
+                } else if index < self.stored_count {
                     let key = self
                         .context
                         .base_key()
@@ -290,7 +367,8 @@ where
         Ok(values)
     }
 
-    /// Reads the logged values in the given range (including staged ones).
+    /// Reads the logged values in the given range (including staged ones), skipping the
+    /// compacted entries.
     /// ```rust
     /// # tokio_test::block_on(async {
     /// # use linera_views::context::MemoryContext;
@@ -323,7 +401,8 @@ where
             Bound::Included(start) => *start,
             Bound::Excluded(start) => *start + 1,
             Bound::Unbounded => 0,
-        };
+        }
+        .max(self.compacted_count());
         if start >= end {
             return Ok(Vec::new());
         }
@@ -346,6 +425,50 @@ where
             )
         }
     }
+
+    /// Folds the entries with indices below `count` into the checkpoint, and prunes them.
+    ///
+    /// The `fold` function receives the previous checkpoint, if any, and the entries that were
+    /// not compacted yet, in order. The log keeps its count, so the indices of the remaining
+    /// entries don't change.
+    /// ```rust
+    /// # tokio_test::block_on(async {
+    /// # use linera_views::context::MemoryContext;
+    /// # use linera_views::log_view::LogView;
+    /// # use linera_views::views::View;
+    /// # let context = MemoryContext::new_for_testing(());
+    /// let mut log = LogView::load(context).await.unwrap();
+    /// log.push(34);
+    /// log.push(42);
+    /// log.push(56);
+    /// log.compact(2, |sum: Option<u64>, values| {
+    ///     sum.unwrap_or_default() + values.into_iter().sum::<u64>()
+    /// })
+    /// .await
+    /// .unwrap();
+    /// assert_eq!(log.checkpoint::<u64>().unwrap(), Some(76));
+    /// assert_eq!(log.count(), 3);
+    /// assert_eq!(log.get(0).await.unwrap(), None);
+    /// assert_eq!(log.read(..).await.unwrap(), vec![56]);
+    /// # })
+    /// ```
+    pub async fn compact<S, F>(&mut self, count: usize, fold: F) -> Result<(), ViewError>
+    where
+        S: Serialize + DeserializeOwned,
+        F: FnOnce(Option<S>, Vec<T>) -> S,
+    {
+        let count = count.min(self.count());
+        if count <= self.compacted_count() {
+            return Ok(());
+        }
+        let values = self.read(..count).await?;
+        let value = fold(self.checkpoint()?, values);
+        self.checkpoint = Some(Checkpoint {
+            count,
+            value: bcs::to_bytes(&value)?,
+        });
+        Ok(())
+    }
 }
 
 impl<C, T> HashableView for LogView<C, T>
@@ -364,6 +487,10 @@ where
         let _hash_latency = metrics::LOG_VIEW_HASH_RUNTIME.measure_latency();
         let elements = self.read(..).await?;
         let mut hasher = sha3::Sha3_256::default();
+        // Logs that were never compacted keep the hash they had before compaction existed.
+        if let Some(checkpoint) = &self.checkpoint {
+            hasher.update_with_bcs_bytes(checkpoint)?;
+        }
         hasher.update_with_bcs_bytes(&elements)?;
         Ok(hasher.finalize())
     }
@@ -440,7 +440,7 @@ where
                 .range(get_interval(prefix.clone()));
             let mut suffix_closed_set = SuffixClosedSetIterator::new(prefix_len, iter);
             let base = self.context.base_key().base_index(&prefix);
-            for index in self.context.store().find_keys_by_prefix(&base).await? {
+            for index in self.context.find_keys_by_prefix(&base).await? {
                 loop {
                     match update {
                         Some((key, value)) if &key[prefix_len..] <= index.as_slice() => {
@@ -631,12 +631,7 @@ where
                 .range(get_interval(prefix.clone()));
             let mut suffix_closed_set = SuffixClosedSetIterator::new(prefix_len, iter);
             let base = self.context.base_key().base_index(&prefix);
-            for (index, bytes) in self
-                .context
-                .store()
-                .find_key_values_by_prefix(&base)
-                .await?
-            {
+            for (index, bytes) in self.context.find_key_values_by_prefix(&base).await? {
                 loop {
                     match update {
                         Some((key, value)) if key[prefix_len..] <= *index => {
@@ -266,7 +266,7 @@ impl<C: Context> ByteSetView<C> {
         let mut update = updates.next();
         if !self.delete_storage_first {
             let base = &self.context.base_key().bytes;
-            for index in self.context.store().find_keys_by_prefix(base).await? {
+            for index in self.context.find_keys_by_prefix(base).await? {
                 loop {
                     match update {
                         Some((key, value)) if key <= &index => {
@@ -10,9 +10,14 @@ use linera_views::rocks_db::RocksDbDatabase;
 #[cfg(with_scylladb)]
 use linera_views::scylla_db::ScyllaDbDatabase;
 use linera_views::{
+    batch::Batch,
     memory::MemoryDatabase,
-    store::{KeyValueStore, TestKeyValueDatabase},
-    test_utils::{namespace_admin_test, root_key_admin_test},
+    random::generate_test_namespace,
+    store::{
+        sample_namespace_stats, KeyValueDatabase, KeyValueStore, NamespaceStats,
+        TestKeyValueDatabase, WritableKeyValueStore as _,
+    },
+    test_utils::{namespace_admin_test, namespace_escaping_admin_test, root_key_admin_test},
 };
 use test_case::test_case;
 
@@ -28,6 +33,18 @@ where
     namespace_admin_test::<K>().await;
 }
 
+#[test_case(PhantomData::<MemoryDatabase>; "MemoryDatabase")]
+#[cfg_attr(with_rocksdb, test_case(PhantomData::<RocksDbDatabase>; "RocksDbDatabase"))]
+#[cfg_attr(with_dynamodb, test_case(PhantomData::<DynamoDbDatabase>; "DynamoDbDatabase"))]
+#[cfg_attr(with_scylladb, test_case(PhantomData::<ScyllaDbDatabase>; "ScyllaDbDatabase"))]
+#[tokio::test]
+async fn namespace_escaping_admin_test_cases<K: TestKeyValueDatabase>(_view_type: PhantomData<K>)
+where
+    K::Store: KeyValueStore,
+{
+    namespace_escaping_admin_test::<K>().await;
+}
+
 #[test_case(PhantomData::<MemoryDatabase>; "MemoryDatabase")]
 #[cfg_attr(with_rocksdb, test_case(PhantomData::<RocksDbDatabase>; "RocksDbDatabase"))]
 #[cfg_attr(with_dynamodb, test_case(PhantomData::<DynamoDbDatabase>; "DynamoDbDatabase"))]
@@ -39,3 +56,87 @@ where
 {
     root_key_admin_test::<K>().await;
 }
+
+/// Writes `1 + 2 + 3 + 4` entries of 12 bytes in four partitions, and 100 larger entries in
+/// the shared partition.
+async fn write_namespace_stats_entries<D>(database: &D)
+where
+    D: KeyValueDatabase,
+    D::Store: KeyValueStore,
+{
+    for root_key in 0..4u8 {
+        let store = database.open_exclusive(&[root_key]).unwrap();
+        let mut batch = Batch::new();
+        for index in 0..=root_key {
+            batch.put_key_value_bytes(vec![index, 1], vec![0; 10]);
+        }
+        store.write_batch(batch).await.unwrap();
+    }
+    let store = database.open_shared(&[]).unwrap();
+    let mut batch = Batch::new();
+    for index in 0..100u8 {
+        batch.put_key_value_bytes(vec![index], vec![0; 100]);
+    }
+    store.write_batch(batch).await.unwrap();
+}
+
+#[test_case(PhantomData::<MemoryDatabase>; "MemoryDatabase")]
+#[cfg_attr(with_rocksdb, test_case(PhantomData::<RocksDbDatabase>; "RocksDbDatabase"))]
+#[cfg_attr(with_dynamodb, test_case(PhantomData::<DynamoDbDatabase>; "DynamoDbDatabase"))]
+#[cfg_attr(with_scylladb, test_case(PhantomData::<ScyllaDbDatabase>; "ScyllaDbDatabase"))]
+#[tokio::test]
+async fn namespace_stats_test_cases<K: TestKeyValueDatabase>(_view_type: PhantomData<K>)
+where
+    K::Store: KeyValueStore,
+{
+    let config = K::new_test_config().await.unwrap();
+    let namespace = generate_test_namespace();
+    let database = K::recreate_and_connect(&config, &namespace).await.unwrap();
+    write_namespace_stats_entries(&database).await;
+    let stats = database.namespace_stats().await.unwrap();
+    // The statistics of some backends are only refreshed from time to time, so only exact
+    // ones can be checked.
+    if !stats.estimated {
+        let expected = NamespaceStats {
+            key_count: Some(110),
+            total_bytes: 120 + 100 * 101,
+            estimated: false,
+        };
+        assert_eq!(stats, expected);
+    }
+}
+
+#[tokio::test]
+async fn sample_namespace_stats_test() {
+    let config = MemoryDatabase::new_test_config().await.unwrap();
+    let namespace = generate_test_namespace();
+    let database = MemoryDatabase::recreate_and_connect(&config, &namespace)
+        .await
+        .unwrap();
+    write_namespace_stats_entries(&database).await;
+
+    // The shared partition is left out of the sample.
+    let root_keys = [vec![], vec![0], vec![1], vec![2], vec![3]];
+    let stats = sample_namespace_stats(&database, &root_keys).await.unwrap();
+    let expected = NamespaceStats {
+        key_count: Some(10),
+        total_bytes: 120,
+        estimated: false,
+    };
+    assert_eq!(stats, expected);
+
+    // Values are only read in a few evenly spaced keys of a large partition.
+    let store = database.open_exclusive(&[4]).unwrap();
+    let mut batch = Batch::new();
+    for index in 0..100u8 {
+        batch.put_key_value_bytes(vec![index, 1], vec![0; 10]);
+    }
+    store.write_batch(batch).await.unwrap();
+    let stats = sample_namespace_stats(&database, &[vec![4]]).await.unwrap();
+    let expected = NamespaceStats {
+        key_count: Some(100),
+        total_bytes: 1200,
+        estimated: true,
+    };
+    assert_eq!(stats, expected);
+}
@@ -0,0 +1,112 @@
+// Copyright (c) Zefchain Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+use std::sync::Arc;
+
+use anyhow::Result;
+use linera_views::{
+    change_log::{Change, ChangeLog, ChangeLogEntry, ChannelSink, ValueMetadata, WriterSink},
+    context::MemoryContext,
+    map_view::MapView,
+    register_view::RegisterView,
+    views::{RootView, View},
+};
+use linera_views_derive::RootView;
+
+#[derive(RootView)]
+struct TestView<C> {
+    pub register: RegisterView<C, u32>,
+    pub map: MapView<C, u8, String>,
+}
+
+#[tokio::test]
+async fn test_change_log_of_root_view_commits() -> Result<()> {
+    let (sink, mut receiver) = ChannelSink::new();
+    let change_log = ChangeLog::default().with_sink(Arc::new(sink));
+    let context = MemoryContext::new_for_testing(()).with_change_log(change_log.clone());
+
+    let mut view = TestView::load(context.clone()).await?;
+    view.register.set(1);
+    view.map.insert(&7, "seven".to_owned())?;
+    view.save().await?;
+
+    let entry = receiver.try_recv()?;
+    assert!(entry.view_name.ends_with("TestView"));
+    assert_eq!(entry.changes.len(), 2);
+    for change in &entry.changes {
+        let Change::Put { old_value, .. } = change else {
+            panic!("unexpected change {change:?}");
+        };
+        assert_eq!(*old_value, None);
+    }
+
+    let mut view = TestView::load(context).await?;
+    view.register.set(2);
+    view.map.remove(&7)?;
+    view.save().await?;
+
+    let entry = receiver.try_recv()?;
+    let old_register = ValueMetadata::new(&bcs::to_bytes(&1u32)?);
+    let new_register = ValueMetadata::new(&bcs::to_bytes(&2u32)?);
+    let old_map_value = ValueMetadata::new(&bcs::to_bytes("seven")?);
+    assert!(entry.changes.iter().any(|change| matches!(
+        change,
+        Change::Put { old_value: Some(old), new_value, .. }
+            if *old == old_register && *new_value == new_register
+    )));
+    assert!(entry.changes.iter().any(|change| matches!(
+        change,
+        Change::Delete { old_value: Some(old), .. } if *old == old_map_value
+    )));
+
+    // Saving without changes emits nothing.
+    let context = MemoryContext::new_for_testing(()).with_change_log(change_log);
+    let mut view = TestView::load(context).await?;
+    view.save().await?;
+    assert!(receiver.try_recv().is_err());
+
+    // Saving through a context without a change log emits nothing.
+    let mut view = TestView::load(MemoryContext::new_for_testing(())).await?;
+    view.register.set(3);
+    view.save().await?;
+    assert!(receiver.try_recv().is_err());
+    Ok(())
+}
+
+/// A writer sending what it writes to a channel.
+struct ChannelWriter(std::sync::mpsc::Sender<Vec<u8>>);
+
+impl std::io::Write for ChannelWriter {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.send(buf.to_vec()).unwrap();
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+#[tokio::test]
+async fn test_change_log_writer_sink() -> Result<()> {
+    let (sender, receiver) = std::sync::mpsc::channel();
+    let sink = WriterSink::new(ChannelWriter(sender));
+    let change_log = ChangeLog::default()
+        .with_sink(Arc::new(sink))
+        .with_namespace("test")
+        .with_root_key(vec![1, 2]);
+    let context = MemoryContext::new_for_testing(()).with_change_log(change_log);
+
+    let mut view = TestView::load(context).await?;
+    view.register.set(1);
+    view.save().await?;
+
+    let line = receiver.recv_timeout(std::time::Duration::from_secs(10))?;
+    let entry = serde_json::from_slice::<ChangeLogEntry>(&line)?;
+    assert!(entry.view_name.ends_with("TestView"));
+    assert_eq!(entry.namespace, "test");
+    assert_eq!(entry.root_key, vec![1, 2]);
+    assert_eq!(entry.changes.len(), 1);
+    assert_eq!(line.last(), Some(&b'\n'));
+    Ok(())
+}
@@ -0,0 +1,124 @@
+// Copyright (c) Zefchain Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! The synchronous access handles used by the OPFS store are only available in dedicated
+//! workers, so these tests don't run in the main thread like the other Web tests.
+
+#![cfg(with_opfs)]
+
+use linera_views::{
+    opfs::{
+        create_opfs_test_store, OpfsDatabase, OpfsStoreConfig, COMPACTION_SUFFIX,
+        TEST_OPFS_MAX_STREAM_QUERIES,
+    },
+    random::generate_test_namespace,
+    store::{KeyValueDatabase as _, ReadableKeyValueStore as _, WritableKeyValueStore as _},
+    test_utils::{
+        get_random_test_scenarios, run_big_write_read, run_reads, run_writes_from_blank,
+        run_writes_from_state,
+    },
+};
+use wasm_bindgen_test::wasm_bindgen_test;
+
+wasm_bindgen_test::wasm_bindgen_test_configure!(run_in_dedicated_worker);
+
+#[wasm_bindgen_test]
+async fn test_reads_opfs() {
+    for scenario in get_random_test_scenarios() {
+        let key_value_store = create_opfs_test_store().await;
+        run_reads(key_value_store, scenario).await;
+    }
+}
+
+#[wasm_bindgen_test]
+async fn test_opfs_writes_from_blank() {
+    let key_value_store = create_opfs_test_store().await;
+    run_writes_from_blank(&key_value_store).await;
+}
+
+#[wasm_bindgen_test]
+async fn test_opfs_big_write_read() {
+    let key_value_store = create_opfs_test_store().await;
+    let value_sizes = vec![100, 1000, 200000, 5000000];
+    let target_size = 20000000;
+    run_big_write_read(key_value_store, target_size, value_sizes).await;
+}
+
+#[wasm_bindgen_test]
+async fn test_opfs_writes_from_state() {
+    let key_value_store = create_opfs_test_store().await;
+    run_writes_from_state(&key_value_store).await;
+}
+
+#[wasm_bindgen_test]
+async fn test_opfs_reload() {
+    let config = OpfsStoreConfig {
+        max_stream_queries: TEST_OPFS_MAX_STREAM_QUERIES,
+    };
+    let namespace = generate_test_namespace();
+    {
+        let database = OpfsDatabase::connect(&config, &namespace).await.unwrap();
+        let store = database.open_exclusive(&[1]).unwrap();
+        let mut batch = linera_views::batch::Batch::new();
+        batch.put_key_value_bytes(vec![1, 2], vec![3]);
+        batch.put_key_value_bytes(vec![1, 3], vec![4]);
+        store.write_batch(batch).await.unwrap();
+        let mut batch = linera_views::batch::Batch::new();
+        batch.delete_key(vec![1, 2]);
+        store.write_batch(batch).await.unwrap();
+    }
+    // The file was closed when the database was dropped, so the log is replayed here.
+    let database = OpfsDatabase::connect(&config, &namespace).await.unwrap();
+    let store = database.open_exclusive(&[1]).unwrap();
+    assert_eq!(store.read_value_bytes(&[1, 2]).await.unwrap(), None);
+    assert_eq!(
+        store.read_value_bytes(&[1, 3]).await.unwrap(),
+        Some(vec![4])
+    );
+    assert_eq!(
+        OpfsDatabase::list_root_keys(&config, &namespace)
+            .await
+            .unwrap(),
+        vec![vec![1]]
+    );
+    drop(store);
+    drop(database);
+    OpfsDatabase::delete(&config, &namespace).await.unwrap();
+    assert!(!OpfsDatabase::exists(&config, &namespace).await.unwrap());
+}
+
+#[wasm_bindgen_test]
+async fn test_opfs_compaction() {
+    let config = OpfsStoreConfig {
+        max_stream_queries: TEST_OPFS_MAX_STREAM_QUERIES,
+    };
+    let namespace = generate_test_namespace();
+    {
+        let database = OpfsDatabase::connect(&config, &namespace).await.unwrap();
+        let store = database.open_exclusive(&[1]).unwrap();
+        // Overwriting the same value makes the log several times larger than the compaction
+        // threshold, so it is compacted a few times.
+        for index in 0..64u8 {
+            let mut batch = linera_views::batch::Batch::new();
+            batch.put_key_value_bytes(vec![1], vec![index; 100_000]);
+            store.write_batch(batch).await.unwrap();
+        }
+    }
+    let database = OpfsDatabase::connect(&config, &namespace).await.unwrap();
+    let store = database.open_exclusive(&[1]).unwrap();
+    assert_eq!(
+        store.read_value_bytes(&[1]).await.unwrap(),
+        Some(vec![63; 100_000])
+    );
+    // The file holding the compacted log is not a namespace.
+    let namespaces = OpfsDatabase::list_all(&config).await.unwrap();
+    assert!(namespaces.contains(&namespace));
+    assert!(namespaces
+        .iter()
+        .all(|namespace| !namespace.ends_with(COMPACTION_SUFFIX)));
+    drop(store);
+    drop(database);
+    OpfsDatabase::delete(&config, &namespace).await.unwrap();
+    let namespaces = OpfsDatabase::list_all(&config).await.unwrap();
+    assert!(!namespaces.contains(&namespace));
+}
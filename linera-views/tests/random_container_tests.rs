@@ -4,11 +4,13 @@
 use std::collections::{BTreeMap, BTreeSet};
 
 use anyhow::Result;
+use linera_base::merkle::{self, MerkleHash};
 use linera_views::{
     bucket_queue_view::HashedBucketQueueView,
     collection_view::HashedCollectionView,
     context::{Context, MemoryContext},
     key_value_store_view::{KeyValueStoreView, SizeData},
+    log_view::HashedLogView,
     map_view::HashedByteMapView,
     queue_view::HashedQueueView,
     random::make_deterministic_rng,
@@ -248,6 +250,68 @@ async fn key_value_store_view_mutability() -> Result<()> {
     Ok(())
 }
 
+/// Returns the root of the Merkle tree of the given entries, built from scratch.
+async fn merkle_root_of(entries: &BTreeMap<Vec<u8>, Vec<u8>>) -> Result<MerkleHash> {
+    let mut view = KeyValueStoreView::load(MemoryContext::new_for_testing(())).await?;
+    for (key, value) in entries {
+        view.insert(key.clone(), value.clone()).await?;
+    }
+    Ok(view.merkle_root().await?)
+}
+
+#[tokio::test]
+async fn key_value_store_view_merkle_tree() -> Result<()> {
+    let context = MemoryContext::new_for_testing(());
+    let mut rng = make_deterministic_rng();
+    let mut state_map = BTreeMap::new();
+    for _ in 0..40 {
+        let mut view = KeyValueStateView::load(context.clone()).await?;
+        if view.store.stores_merkle_tree() {
+            assert_eq!(
+                view.store.merkle_root().await?,
+                merkle_root_of(&state_map).await?
+            );
+        }
+        for _ in 0..rng.gen_range(0..10) {
+            let len = rng.gen_range(1..4);
+            let key = (&mut rng)
+                .sample_iter(Uniform::from(0..4))
+                .take(len)
+                .collect::<Vec<u8>>();
+            match rng.gen_range(0..4) {
+                0 => {
+                    view.store.remove(key.clone()).await?;
+                    state_map.remove(&key);
+                }
+                1 => {
+                    view.store.remove_by_prefix(key[..1].to_vec()).await?;
+                    remove_by_prefix(&mut state_map, key[..1].to_vec());
+                }
+                _ => {
+                    let value = vec![rng.gen::<u8>()];
+                    view.store.insert(key.clone(), value.clone()).await?;
+                    state_map.insert(key, value);
+                }
+            }
+        }
+        // Saving the view without computing the root leaves the stored tree out of date, and
+        // it is then rebuilt from all the entries.
+        if rng.gen::<bool>() {
+            let root = view.store.merkle_root().await?;
+            assert_eq!(root, merkle_root_of(&state_map).await?);
+            for (key, value) in &state_map {
+                let (proven, proof) = view.store.inclusion_proof(key).await?.unwrap();
+                assert_eq!(&proven, value);
+                assert_eq!(proof.root(merkle::leaf_hash(key, value)), root);
+            }
+            assert!(view.store.inclusion_proof(&[4]).await?.is_none());
+        }
+        view.save().await?;
+        assert!(!view.has_pending_changes().await);
+    }
+    Ok(())
+}
+
 #[derive(CryptoHashRootView)]
 pub struct ByteMapStateView<C> {
     pub map: HashedByteMapView<C, u8>,
@@ -764,3 +828,57 @@ async fn reentrant_collection_view_check() -> Result<()> {
     }
     Ok(())
 }
+
+#[derive(CryptoHashRootView)]
+struct LogStateView<C> {
+    pub log: HashedLogView<C, u32>,
+}
+
+fn sum_entries(sum: Option<u64>, entries: Vec<u32>) -> u64 {
+    sum.unwrap_or_default() + entries.into_iter().map(u64::from).sum::<u64>()
+}
+
+#[tokio::test]
+async fn log_view_compaction_check() -> Result<()> {
+    let context = MemoryContext::new_for_testing(());
+    let mut rng = make_deterministic_rng();
+    let mut values = Vec::new();
+    let mut compacted_count = 0;
+    for _ in 0..20 {
+        let mut view = LogStateView::load(context.clone()).await?;
+        assert_eq!(view.log.count(), values.len());
+        assert_eq!(view.log.compacted_count(), compacted_count);
+        let sum = sum_entries(None, values[..compacted_count].to_vec());
+        assert_eq!(view.log.checkpoint::<u64>()?.unwrap_or_default(), sum);
+        assert_eq!(view.log.read(..).await?, values[compacted_count..]);
+        if compacted_count > 0 {
+            assert_eq!(view.log.get(compacted_count - 1).await?, None);
+        }
+        let n_push = rng.gen_range(0..10);
+        for _ in 0..n_push {
+            let value = rng.gen::<u32>();
+            view.log.push(value);
+            values.push(value);
+        }
+        if rng.gen::<bool>() {
+            let count = rng.gen_range(0..=values.len());
+            view.log.compact(count, sum_entries).await?;
+            compacted_count = compacted_count.max(count);
+        }
+        view.save().await?;
+    }
+
+    // The hash only depends on the checkpoint and the remaining entries, not on the order of
+    // the operations.
+    let view = LogStateView::load(context).await?;
+    let other_context = MemoryContext::new_for_testing(());
+    let mut other_view = LogStateView::load(other_context).await?;
+    for value in &values {
+        other_view.log.push(*value);
+    }
+    other_view.log.compact(compacted_count, sum_entries).await?;
+    assert_eq!(other_view.crypto_hash().await?, view.crypto_hash().await?);
+    other_view.save().await?;
+    assert_eq!(other_view.crypto_hash().await?, view.crypto_hash().await?);
+    Ok(())
+}
@@ -4,13 +4,20 @@
 use linera_views::{
     batch::Batch,
     context::{Context as _, MemoryContext},
+    fork::ForkStore,
     key_value_store_view::ViewContainer,
+    lru_caching::LruCachingMemoryDatabase,
     memory::MemoryDatabase,
     random::make_deterministic_rng,
-    store::{ReadableKeyValueStore as _, TestKeyValueDatabase as _, WritableKeyValueStore as _},
+    store::{
+        KeyValueDatabase as _, ReadableKeyValueStore as _, TestKeyValueDatabase as _,
+        WritableKeyValueStore as _,
+    },
     test_utils::{
-        big_read_multi_values, get_random_test_scenarios, run_big_write_read, run_reads,
-        run_writes_from_blank, run_writes_from_state,
+        big_read_multi_values,
+        equivalence::{run_equivalence_test, EquivalenceTestConfig},
+        get_random_test_scenarios, run_big_write_read, run_reads, run_writes_from_blank,
+        run_writes_from_state,
     },
     value_splitting::create_value_splitting_memory_store,
 };
@@ -172,6 +179,62 @@ async fn test_rocks_db_writes_from_blank() {
     run_writes_from_blank(&store).await;
 }
 
+#[cfg(with_rocksdb)]
+#[tokio::test]
+async fn test_rocks_db_writes_with_durability_policies() {
+    use std::time::Duration;
+
+    use linera_views::{
+        random::generate_test_namespace,
+        rocks_db::{DurabilityPolicy, RocksDbDatabase},
+    };
+
+    for durability in [
+        DurabilityPolicy::Always,
+        DurabilityPolicy::Interval(Duration::from_millis(10)),
+        DurabilityPolicy::Never,
+    ] {
+        let mut config = RocksDbDatabase::new_test_config().await.unwrap();
+        config.inner_config.durability = durability;
+        let namespace = generate_test_namespace();
+        let database = RocksDbDatabase::recreate_and_connect(&config, &namespace)
+            .await
+            .unwrap();
+        let store = database.open_shared(&[]).unwrap();
+        run_writes_from_blank(&store).await;
+    }
+}
+
+#[cfg(with_rocksdb)]
+#[test]
+fn test_rocks_db_durability_policy_from_str() {
+    use std::time::Duration;
+
+    use linera_views::rocks_db::DurabilityPolicy;
+
+    for (string, policy) in [
+        ("always", DurabilityPolicy::Always),
+        ("never", DurabilityPolicy::Never),
+        (
+            "interval:250",
+            DurabilityPolicy::Interval(Duration::from_millis(250)),
+        ),
+    ] {
+        assert_eq!(string.parse::<DurabilityPolicy>().unwrap(), policy);
+        assert_eq!(policy.to_string(), string);
+    }
+    for string in [
+        "",
+        "sometimes",
+        "interval",
+        "interval:",
+        "interval:0",
+        "interval:-1",
+    ] {
+        assert!(string.parse::<DurabilityPolicy>().is_err());
+    }
+}
+
 #[cfg(with_dynamodb)]
 #[tokio::test]
 async fn test_dynamo_db_writes_from_blank() {
@@ -305,6 +368,171 @@ async fn test_memory_writes_from_state() {
     run_writes_from_state(&store).await;
 }
 
+#[tokio::test]
+async fn test_reads_fork_memory() {
+    for scenario in get_random_test_scenarios() {
+        let store = MemoryDatabase::new_test_store().await.unwrap();
+        run_reads(ForkStore::new(store), scenario).await;
+    }
+}
+
+#[tokio::test]
+async fn test_fork_memory_writes_from_blank() {
+    let store = MemoryDatabase::new_test_store().await.unwrap();
+    run_writes_from_blank(&ForkStore::new(store)).await;
+}
+
+#[tokio::test]
+async fn test_fork_memory_writes_from_state() {
+    let store = MemoryDatabase::new_test_store().await.unwrap();
+    run_writes_from_state(&ForkStore::new(store)).await;
+}
+
+#[tokio::test]
+async fn test_fork_does_not_modify_store() {
+    let store = MemoryDatabase::new_test_store().await.unwrap();
+    let mut batch = Batch::new();
+    batch.put_key_value_bytes(vec![0, 1], vec![1]);
+    batch.put_key_value_bytes(vec![0, 2], vec![2]);
+    batch.put_key_value_bytes(vec![1, 1], vec![3]);
+    store.write_batch(batch).await.unwrap();
+
+    let fork = ForkStore::new(store.clone());
+    assert!(!fork.has_changes());
+    let mut batch = Batch::new();
+    batch.delete_key_prefix(vec![0]);
+    batch.put_key_value_bytes(vec![0, 3], vec![4]);
+    batch.put_key_value_bytes(vec![1, 1], vec![5]);
+    batch.put_key_value_bytes(vec![2], vec![6]);
+    fork.write_batch(batch).await.unwrap();
+    assert!(fork.has_changes());
+
+    assert_eq!(fork.read_value_bytes(&[0, 1]).await.unwrap(), None);
+    assert_eq!(fork.read_value_bytes(&[1, 1]).await.unwrap(), Some(vec![5]));
+    assert_eq!(
+        fork.contains_keys(vec![vec![0, 2], vec![0, 3], vec![2]])
+            .await
+            .unwrap(),
+        vec![false, true, true]
+    );
+    assert_eq!(
+        fork.find_key_values_by_prefix(&[]).await.unwrap(),
+        vec![
+            (vec![0, 3], vec![4]),
+            (vec![1, 1], vec![5]),
+            (vec![2], vec![6]),
+        ]
+    );
+
+    assert_eq!(
+        store.read_value_bytes(&[1, 1]).await.unwrap(),
+        Some(vec![3])
+    );
+    assert_eq!(
+        store.find_keys_by_prefix(&[]).await.unwrap(),
+        vec![vec![0, 1], vec![0, 2], vec![1, 1]]
+    );
+}
+
+#[tokio::test]
+async fn test_lru_caching_prefetch() {
+    let database = LruCachingMemoryDatabase::connect_test_namespace()
+        .await
+        .unwrap();
+    let store = database.open_exclusive(&[]).unwrap();
+    let mut batch = Batch::new();
+    batch.put_key_value_bytes(vec![1], vec![1]);
+    batch.put_key_value_bytes(vec![2], vec![2]);
+    store.write_batch(batch).await.unwrap();
+
+    // Reads through the clones of a store are recorded too.
+    let store = database.open_exclusive(&[]).unwrap();
+    store.start_recording_reads();
+    store.read_value_bytes(&[1]).await.unwrap();
+    store.clone().contains_key(&[3]).await.unwrap();
+    assert_eq!(store.take_recorded_reads(), vec![vec![1], vec![3]]);
+    assert!(store.take_recorded_reads().is_empty());
+
+    // Once prefetched, values are read from the cache, even if the storage changed since.
+    let store = database.open_exclusive(&[]).unwrap();
+    store.prefetch(vec![vec![1], vec![2]]).await.unwrap();
+    let other_store = database.open_exclusive(&[]).unwrap();
+    let mut batch = Batch::new();
+    batch.put_key_value_bytes(vec![1], vec![3]);
+    other_store.write_batch(batch).await.unwrap();
+    assert_eq!(store.read_value_bytes(&[1]).await.unwrap(), Some(vec![1]));
+    assert_eq!(
+        other_store.read_value_bytes(&[1]).await.unwrap(),
+        Some(vec![3])
+    );
+}
+
+#[cfg(not(web))]
+#[tokio::test]
+async fn test_memory_snapshot_round_trip() {
+    use linera_views::{
+        memory::{load_memory_snapshot, save_memory_snapshot},
+        random::generate_test_namespace,
+        store::KeyValueDatabase as _,
+    };
+
+    let config = MemoryDatabase::new_test_config().await.unwrap();
+    let namespace = generate_test_namespace();
+    let database = MemoryDatabase::recreate_and_connect(&config, &namespace)
+        .await
+        .unwrap();
+    let store = database.open_shared(&[1]).unwrap();
+    let mut batch = Batch::new();
+    batch.put_key_value_bytes(vec![0, 1], vec![1]);
+    batch.put_key_value_bytes(vec![0, 2], vec![2]);
+    store.write_batch(batch).await.unwrap();
+
+    let directory = tempfile::tempdir().unwrap();
+    let path = directory.path().join("memory.snapshot");
+    assert!(!load_memory_snapshot(&path).unwrap());
+    save_memory_snapshot(&path).unwrap();
+
+    MemoryDatabase::delete(&config, &namespace).await.unwrap();
+    assert!(!MemoryDatabase::exists(&config, &namespace).await.unwrap());
+
+    assert!(load_memory_snapshot(&path).unwrap());
+    assert!(MemoryDatabase::exists(&config, &namespace).await.unwrap());
+    let database = MemoryDatabase::connect(&config, &namespace).await.unwrap();
+    let store = database.open_shared(&[1]).unwrap();
+    assert_eq!(
+        store.find_key_values_by_prefix(&[0]).await.unwrap(),
+        vec![(vec![1], vec![1]), (vec![2], vec![2])]
+    );
+}
+
+#[cfg(not(web))]
+#[test]
+fn test_memory_snapshot_corrupt() {
+    use linera_views::memory::{load_memory_snapshot, save_memory_snapshot, MemoryStoreError};
+
+    let directory = tempfile::tempdir().unwrap();
+    let path = directory.path().join("memory.snapshot");
+    save_memory_snapshot(&path).unwrap();
+    let mut bytes = std::fs::read(&path).unwrap();
+    bytes.pop();
+    std::fs::write(&path, &bytes).unwrap();
+    let error = load_memory_snapshot(&path).unwrap_err();
+    assert!(error.to_string().contains(&path.display().to_string()));
+    let MemoryStoreError::InvalidSnapshot {
+        path: error_path, ..
+    } = &error
+    else {
+        panic!("unexpected error: {error}");
+    };
+    assert_eq!(error_path, &path);
+
+    std::fs::write(&path, [0xff; 8]).unwrap();
+    assert!(matches!(
+        load_memory_snapshot(&path),
+        Err(MemoryStoreError::InvalidSnapshot { .. })
+    ));
+}
+
 #[cfg(with_rocksdb)]
 #[tokio::test]
 async fn test_rocks_db_writes_from_state() {
@@ -350,3 +578,40 @@ async fn test_scylladb_access() {
 async fn test_dynamodb_access() {
     access_admin_test::<linera_views::dynamo_db::DynamoDbDatabase>().await
 }
+
+#[tokio::test]
+async fn test_memory_equivalence() {
+    run_equivalence_test::<MemoryDatabase>(EquivalenceTestConfig::default()).await;
+}
+
+#[tokio::test]
+async fn test_lru_caching_memory_equivalence() {
+    run_equivalence_test::<LruCachingMemoryDatabase>(EquivalenceTestConfig::default()).await;
+}
+
+#[cfg(with_rocksdb)]
+#[tokio::test]
+async fn test_rocks_db_equivalence() {
+    run_equivalence_test::<linera_views::rocks_db::RocksDbDatabase>(
+        EquivalenceTestConfig::default(),
+    )
+    .await;
+}
+
+#[cfg(with_dynamodb)]
+#[tokio::test]
+async fn test_dynamo_db_equivalence() {
+    run_equivalence_test::<linera_views::dynamo_db::DynamoDbDatabase>(
+        EquivalenceTestConfig::default(),
+    )
+    .await;
+}
+
+#[cfg(with_scylladb)]
+#[tokio::test]
+async fn test_scylla_db_equivalence() {
+    run_equivalence_test::<linera_views::scylla_db::ScyllaDbDatabase>(
+        EquivalenceTestConfig::default(),
+    )
+    .await;
+}
@@ -16,7 +16,7 @@ use linera_views::{
         WriteOperation::{Delete, DeletePrefix, Put},
     },
     collection_view::HashedCollectionView,
-    context::{Context, MemoryContext, ViewContext},
+    context::{Context, MemoryContext, ScanBudget, ViewContext},
     key_value_store_view::{KeyValueStoreView, ViewContainer},
     log_view::HashedLogView,
     lru_caching::LruCachingMemoryDatabase,
@@ -27,7 +27,10 @@ use linera_views::{
     reentrant_collection_view::HashedReentrantCollectionView,
     register_view::HashedRegisterView,
     set_view::HashedSetView,
-    store::{KeyValueDatabase, TestKeyValueDatabase as _, WritableKeyValueStore as _},
+    store::{
+        KeyValueDatabase, ReadableKeyValueStore as _, TestKeyValueDatabase as _,
+        WritableKeyValueStore as _,
+    },
     test_utils::{
         get_random_byte_vector, get_random_key_value_operations, get_random_key_values,
         span_random_reordering_put_delete,
@@ -838,6 +841,69 @@ async fn test_collection_removal() -> Result<()> {
     Ok(())
 }
 
+#[tokio::test]
+async fn test_scan_budget() -> Result<()> {
+    let context = MemoryContext::new_for_testing(());
+
+    let mut map = ByteMapView::<_, u8>::load(context.clone()).await?;
+    for i in 0..3u8 {
+        map.insert(vec![i], i);
+    }
+    let mut batch = Batch::new();
+    map.flush(&mut batch)?;
+    map.context().store().write_batch(batch).await?;
+
+    // Scans within the budget succeed.
+    let budget = ScanBudget {
+        max_keys: Some(3),
+        max_bytes: None,
+    };
+    let map = ByteMapView::<_, u8>::load(context.clone().with_scan_budget(budget)).await?;
+    assert_eq!(map.keys().await?.len(), 3);
+
+    // Scans returning too many keys or bytes are rejected.
+    for budget in [
+        ScanBudget {
+            max_keys: Some(2),
+            max_bytes: None,
+        },
+        ScanBudget {
+            max_keys: None,
+            max_bytes: Some(5),
+        },
+    ] {
+        let map = ByteMapView::<_, u8>::load(context.clone().with_scan_budget(budget)).await?;
+        assert!(matches!(
+            map.key_values().await,
+            Err(ViewError::ScanBudgetExceeded(exceeded)) if exceeded == budget
+        ));
+    }
+
+    // The store stops the scan as soon as it exceeds the budget.
+    let store = context.store();
+    let budget = ScanBudget {
+        max_keys: Some(2),
+        max_bytes: None,
+    };
+    let prefix = &context.base_key().bytes;
+    assert_eq!(
+        store.find_keys_by_prefix_within(prefix, budget).await?,
+        None
+    );
+    assert_eq!(
+        store
+            .find_key_values_by_prefix_within(prefix, budget)
+            .await?,
+        None
+    );
+    let keys = store
+        .find_keys_by_prefix_within(prefix, ScanBudget::default())
+        .await?;
+    assert_eq!(keys.map(|keys| keys.len()), Some(3));
+
+    Ok(())
+}
+
 async fn test_removal_api_first_second_condition(
     first_condition: bool,
     second_condition: bool,
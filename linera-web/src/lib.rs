@@ -34,21 +34,42 @@ use web_sys::{js_sys, wasm_bindgen};
 use crate::signer::JsSigner;
 
 // TODO(#12): convert to IndexedDbStore once we refactor Context
-type WebStorage =
-    linera_storage::DbStorage<linera_views::memory::MemoryDatabase, linera_storage::WallClock>;
+#[cfg(not(feature = "opfs"))]
+type WebDatabase = linera_views::memory::MemoryDatabase;
 
-type WebEnvironment =
-    linera_core::environment::Impl<WebStorage, linera_rpc::node_provider::NodeProvider, JsSigner>;
+/// With the `opfs` feature, the client persists its storage in the Origin Private File
+/// System. It must then be initialized from a dedicated worker.
+#[cfg(feature = "opfs")]
+type WebDatabase = linera_views::opfs::OpfsDatabase;
+
+type WebStorage = linera_storage::DbStorage<WebDatabase, linera_storage::WallClock>;
+
+type WebEnvironment = linera_core::environment::Impl<
+    WebStorage,
+    linera_core::recording::RecordingNodeProvider<linera_rpc::node_provider::NodeProvider>,
+    JsSigner,
+>;
 
 type JsResult<T> = Result<T, JsError>;
 
-async fn get_storage(
-) -> Result<WebStorage, <linera_views::memory::MemoryDatabase as WithError>::Error> {
+#[cfg(not(feature = "opfs"))]
+fn store_config() -> linera_views::memory::MemoryStoreConfig {
+    linera_views::memory::MemoryStoreConfig {
+        max_stream_queries: 1,
+        kill_on_drop: false,
+    }
+}
+
+#[cfg(feature = "opfs")]
+fn store_config() -> linera_views::opfs::OpfsStoreConfig {
+    linera_views::opfs::OpfsStoreConfig {
+        max_stream_queries: 1,
+    }
+}
+
+async fn get_storage() -> Result<WebStorage, <WebDatabase as WithError>::Error> {
     linera_storage::DbStorage::maybe_create_and_connect(
-        &linera_views::memory::MemoryStoreConfig {
-            max_stream_queries: 1,
-            kill_on_drop: false,
-        },
+        &store_config(),
         "linera",
         Some(linera_execution::WasmRuntime::Wasmer),
     )
@@ -77,6 +98,15 @@ pub const OPTIONS: ClientContextOptions = ClientContextOptions {
     blob_download_timeout: std::time::Duration::from_millis(1000),
     chain_worker_ttl: Duration::from_secs(30),
     grace_period: linera_core::DEFAULT_GRACE_PERIOD,
+    hedging_delay: None,
+    hedging_extra_validators: 0,
+    hedging_latency_factor: 2.0,
+    auto_recover: false,
+    execution_state_history: 0,
+    max_parallel_certificate_downloads: 4,
+    max_reproposals: None,
+    reproposal_delay: std::time::Duration::from_millis(100),
+    reproposal_max_delay: std::time::Duration::from_millis(5000),
 
     // TODO(linera-protocol#2944): separate these out from the
     // `ClientOptions` struct, since they apply only to the CLI/native
@@ -447,6 +477,7 @@ impl Application {
         let linera_execution::QueryOutcome {
             response: linera_execution::QueryResponse::User(response),
             operations,
+            ..
         } = chain_client
             .query_application(linera_execution::Query::User {
                 application_id: self.id,
@@ -87,6 +87,7 @@ impl FileContentGenerator for WitInterfaceWriter {
 pub struct WitWorldWriter {
     package: Option<&'static str>,
     name: String,
+    version: Option<String>,
     imports: Vec<&'static str>,
     exports: Vec<&'static str>,
 }
@@ -97,11 +98,18 @@ impl WitWorldWriter {
         WitWorldWriter {
             package: package.into(),
             name: name.into(),
+            version: None,
             imports: Vec::new(),
             exports: Vec::new(),
         }
     }
 
+    /// Documents the `version` of the interface described by this world.
+    pub fn with_version(mut self, version: impl ToString) -> Self {
+        self.version = Some(version.to_string());
+        self
+    }
+
     /// Registers a [`WitInterface`] to be imported into this world.
     pub fn import<Interface>(mut self) -> Self
     where
@@ -127,6 +135,14 @@ impl FileContentGenerator for WitWorldWriter {
             writeln!(writer, "package {};\n", package)?;
         }
 
+        if let Some(version) = &self.version {
+            writeln!(
+                writer,
+                "/// Version {version} of the `{}` world.",
+                &self.name
+            )?;
+        }
+
         writeln!(writer, "world {} {{", &self.name)?;
 
         for import in &self.imports {